@@ -7,14 +7,132 @@
 use crate::AppArgs;
 use cosmarium_atmosphere::color::{AtmospherePalette, Harmony, RybWheel};
 use cosmarium_atmosphere::AtmospherePlugin;
+use cosmarium_binder::{
+    BinderPlugin, DOCUMENT_ORDER_KEY, DOCUMENT_ORDER_REQUEST, EXPORT_DOCUMENT_REQUEST,
+};
+use cosmarium_changes::ChangesPlugin;
+use cosmarium_core::check::{
+    check_project, relink_document, repair, CheckReport, Issue, Repair, Subject,
+};
+use cosmarium_core::config::SecondCopySchedule;
+use cosmarium_core::document::snapshots::{delete_snapshot, keep_snapshot, load_snapshots};
+use cosmarium_core::document::{decode_text, LineEnding, TextEncoding};
+use cosmarium_core::export::compile::{
+    compile_manuscript, export_manuscript, CompileTarget, EpigraphPlacement,
+};
+use cosmarium_core::export::filter::{Alias, DashStyle, SpellingConversion};
+use cosmarium_core::export::preset::ExportPreset;
+use cosmarium_core::export::{Anonymization, DocumentExportFormat};
+use cosmarium_core::git::{merge, GitIntegration, MergeOutcome};
+use cosmarium_core::goals::{
+    GoalKind, GoalProgress, WritingGoals, PROJECT_DEADLINE_KEY, PROJECT_WORD_TARGET_KEY,
+};
+use cosmarium_core::import::folder::{
+    import_folder, sync_folder, FolderWatch, WatchedFolder, WATCHED_FOLDERS_KEY,
+};
+use cosmarium_core::import::ImportFormat;
+use cosmarium_core::keymap::{self, Keymap};
+use cosmarium_core::layout::{Activity, WindowSettings, PANEL_ZOOM_RANGE};
+use cosmarium_core::logging::{self, filter_directives};
+use cosmarium_core::navigation::{Location, NavigationHistory};
+use cosmarium_core::profile::{self, AuthorIdentity, AuthorProfile};
+use cosmarium_core::project::duplicate::duplicate;
+use cosmarium_core::project::location::{check_location, LocationIssue};
+use cosmarium_core::project::migration::MigrationReport;
+use cosmarium_core::project::mirror::{self, MirrorReport};
+use cosmarium_core::project::store::LoadDiagnostic;
+use cosmarium_core::project::template::{ProjectTemplate, TemplateNode, UserTemplate};
+use cosmarium_core::project::Project;
+use cosmarium_core::proof::{ProgressProof, ProofKey};
+use cosmarium_core::search::global::GlobalIndex;
+use cosmarium_core::search::quick::QuickIndex;
+use cosmarium_core::search::replace::{self, ReplacePreview};
+use cosmarium_core::search::{SearchQuery, SearchResults, SearchService};
+use cosmarium_core::snapshot::take_snapshot;
+use cosmarium_core::structure::{NodeKind, StructureNode};
+use cosmarium_core::theme::{
+    parse_hex_color, Appearance, EditorColorOverrides, ThemeScheduleConfig, ThemeScheduler,
+    ThemeSource, AUTO_THEME,
+};
 use cosmarium_core::Session;
 use cosmarium_core::{Application, Config, Layout, LayoutManager, PluginManager, Result};
+use cosmarium_drafts::DraftsPlugin;
+#[cfg(feature = "export-docx")]
+use cosmarium_export_docx::DocxExportPlugin;
+use cosmarium_export_pandoc::Pandoc;
+#[cfg(feature = "export-pdf")]
+use cosmarium_export_pdf::PdfExportPlugin;
+use cosmarium_git_history::GitHistoryPlugin;
+use cosmarium_grammar::GrammarPlugin;
+use cosmarium_history::HistoryPlugin;
+use cosmarium_inspector::InspectorPlugin;
+use cosmarium_kanban::KanbanPlugin;
+use cosmarium_markdown_editor::autocorrect::{AutocorrectSettings, Correction, AUTOCORRECT_KEY};
+use cosmarium_markdown_editor::commands::{self, EditorCommand};
+use cosmarium_markdown_editor::completion::project_documents;
+use cosmarium_markdown_editor::dictionary::{
+    ProjectDictionary, ADD_TO_DICTIONARY_REQUEST, PROJECT_DICTIONARY_KEY,
+};
+use cosmarium_markdown_editor::documents::{
+    self as editor_documents, DocumentBuffer, ACTIVE_DOCUMENT_KEY, CLOSE_DOCUMENTS_REQUEST,
+    CURSOR_LOCATION_KEY, DOCUMENT_UPDATES, OPEN_DOCUMENTS_REQUEST, REPLACE_DOCUMENTS_REQUEST,
+    SAVE_DOCUMENTS_REQUEST, WORD_COUNT_KEY,
+};
+use cosmarium_markdown_editor::editor::{
+    UndoGrouping, UndoSettings, UndoState, UNDO_SETTINGS_KEY, UNDO_STATE_KEY,
+};
+use cosmarium_markdown_editor::focus::{self, FocusSettings, FOCUS_MODE_KEY, FOCUS_SETTINGS_KEY};
+use cosmarium_markdown_editor::glossary::{check_terms, TermIssue};
+use cosmarium_markdown_editor::paste::{
+    PasteRequest, QuoteStyle, CLIPBOARD_HTML_KEY, PASTE_CLEANUP_KEY, PASTE_REQUEST, QUOTE_STYLE_KEY,
+};
+use cosmarium_markdown_editor::spellcheck::{SpellCheckSettings, SPELL_CHECK_KEY};
+use cosmarium_markdown_editor::stats::{WordCountRules, WritingStats, WORD_COUNT_RULES_KEY};
+use cosmarium_markdown_editor::whitespace::SHOW_WHITESPACE_KEY;
+use cosmarium_markdown_editor::wrap::{self, WRAP_COLUMN_KEY};
 use cosmarium_markdown_editor::MarkdownEditorPlugin;
 use cosmarium_outline::OutlinePlugin;
-use cosmarium_plugin_api::{Event, EventType, PanelPlugin, Plugin, PluginContext};
+use cosmarium_plugin_api::accessibility::{self, Finding};
+use cosmarium_plugin_api::branch::{
+    BranchRequest, Branches, MergeState, BRANCHES_KEY, BRANCH_REQUEST,
+};
+use cosmarium_plugin_api::document_snapshot::{
+    DocumentSnapshot, DocumentSnapshotRequest, DocumentSnapshots, DOCUMENT_SNAPSHOTS_KEY,
+    DOCUMENT_SNAPSHOT_REQUEST,
+};
+use cosmarium_plugin_api::export::{ManuscriptSection, SectionKind};
+use cosmarium_plugin_api::git_history::{
+    short_id, CommitFile, DocumentCommits, GitHistoryRequest, COMMIT_FILE_KEY,
+    DOCUMENT_COMMITS_KEY, GIT_HISTORY_REQUEST, PROJECT_CHANGES_KEY,
+};
+use cosmarium_plugin_api::history::{WritingHistory, WRITING_HISTORY_KEY};
+use cosmarium_plugin_api::locale::{DateStyle, Locale, LOCALE_KEY};
+use cosmarium_plugin_api::metadata::{NodeMetadata, ACTIVE_METADATA_KEY, METADATA_UPDATE_REQUEST};
+use cosmarium_plugin_api::scene::{SceneHeadingFormat, SCENE_HEADING_FORMAT_KEY};
+use cosmarium_plugin_api::search::{
+    ReplaceRequest, ReplaceStatus, SearchResponse, REPLACE_REQUEST, REPLACE_STATUS_KEY,
+    SEARCH_REQUEST, SEARCH_RESULTS_KEY,
+};
+use cosmarium_plugin_api::snapshot::{PROJECT_SNAPSHOT_KEY, PROJECT_SNAPSHOT_REQUEST};
+use cosmarium_plugin_api::sprint::{
+    Sprint, SprintRecord, SPRINT_KEY, SPRINT_RECORD_REQUEST, SPRINT_STOP_REQUEST,
+};
+use cosmarium_plugin_api::transaction::EditTransaction;
+use cosmarium_plugin_api::verse::VerseStats;
+use cosmarium_plugin_api::{
+    Event, EventType, ExportPlugin, PanelPlugin, PanelPosition, Plugin, PluginContext, Shortcut,
+    TaskHandle, FOCUS_PANEL_REQUEST, SESSION_STATE_KEY,
+};
+use cosmarium_prose::ProsePlugin;
+use cosmarium_quote_card::QuoteCardPlugin;
+use cosmarium_search::{SearchPlugin, SEARCH_COMMAND};
+use cosmarium_snapshots::SnapshotsPlugin;
+use cosmarium_sprint::SprintPlugin;
+use cosmarium_tasks::TasksPlugin;
+use cosmarium_wiki::WikiPlugin;
 use eframe::egui;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Instant;
 use uuid::Uuid;
@@ -29,6 +147,10 @@ pub struct Cosmarium {
     current_sentiment: f32,
     /// Current smoothed intensity value (for transition strength)
     current_intensity: f32,
+    /// Current smoothed light/dark blend (0 = dark, 1 = light)
+    current_daylight: f32,
+    /// Scheduler driving the automatic theme
+    theme_scheduler: ThemeScheduler,
     /// Currently loaded plugins
     plugins: HashMap<String, Box<dyn Plugin>>,
     /// Panel plugins for UI rendering
@@ -45,6 +167,12 @@ pub struct Cosmarium {
     show_about: bool,
     /// Whether to show plugin manager
     show_plugin_manager: bool,
+    /// Whether to show the diagnostics window
+    show_diagnostics: bool,
+    /// Per-target log levels, as edited in the diagnostics window
+    log_targets_text: String,
+    /// Why the edited log filter was refused
+    log_filter_error: Option<String>,
     /// Whether to show settings dialog
     show_settings: bool,
     /// Current project path
@@ -55,20 +183,439 @@ pub struct Cosmarium {
     recent_projects: Vec<std::path::PathBuf>,
     /// Current Git branch
     current_branch: Option<String>,
+    /// Pending lookup of the current Git branch
+    branch_task: Option<TaskHandle<Option<String>>>,
+    /// Project being read in the background, with the first file of its
+    /// content folder
+    open_task: Option<TaskHandle<(Project, Option<std::path::PathBuf>)>>,
+    /// Commit of the active project's changes running in the background
+    commit_task: Option<TaskHandle<Option<String>>>,
+    /// Document exports in progress
+    export_tasks: Vec<TaskHandle<std::path::PathBuf>>,
+    /// Output formats provided by plugins for compiled manuscripts
+    export_plugins: Vec<Arc<dyn ExportPlugin>>,
+    /// Word count rules of the active project
+    word_count_rules: WordCountRules,
+    /// Line ending of the active project's new documents and exports
+    line_ending: LineEnding,
+    /// Whether saving the active project commits its changes
+    auto_commit: bool,
+    /// Author profile and pen name the active project is published under
+    published_as: (String, String),
+    /// Quotes typed and pasted in the active project
+    quote_style: QuoteStyle,
+    /// Auto-correction of the active project
+    autocorrect: AutocorrectSettings,
+    /// Keymap being edited in the settings dialog
+    keymap: Keymap,
+    /// Command whose new shortcut the settings dialog waits for
+    recording_shortcut: Option<String>,
+    /// Typo and correction being added in the settings dialog
+    new_correction: (String, String),
+    /// Dictionary of the active project's invented words
+    project_dictionary: ProjectDictionary,
+    /// Suffix rules being edited in the settings dialog
+    dictionary_suffixes: String,
+    /// Word being added in the settings dialog
+    new_dictionary_word: String,
+    /// Scene heading convention of the active project, as edited
+    scene_heading_format: String,
+    /// Word target of the active project, as edited, 0 for none
+    project_word_target: usize,
+    /// Date the word target is due, as edited, empty for none
+    project_deadline: String,
+    /// Word targets and the words written this session
+    writing_goals: WritingGoals,
+    /// Targets reached, to congratulate the author for
+    reached_goals: Vec<GoalProgress>,
+    /// Formats of numbers and dates in the application language
+    locale: Locale,
+    /// Running check of the glossary terms' spelling across the manuscript
+    term_check_task: Option<TaskHandle<Vec<(std::path::PathBuf, TermIssue)>>>,
+    /// Inconsistent glossary terms found by the last check, until dismissed
+    term_issues: Option<Vec<(std::path::PathBuf, TermIssue)>>,
+    /// Running accessibility checklist of the compiled manuscript
+    accessibility_task: Option<TaskHandle<Vec<(Finding, usize)>>>,
+    /// Accessibility problems found by the last checklist, with the line of
+    /// their source file, until dismissed
+    accessibility_report: Option<Vec<(Finding, usize)>>,
+    /// Running update of the second copy of the project left
+    second_copy_task: Option<TaskHandle<MirrorReport>>,
+    /// Last progress proof exported and where, until dismissed
+    progress_proof: Option<(ProgressProof, std::path::PathBuf)>,
+    /// Lines of the active document the compile filters change, before and
+    /// after, until the filters are edited
+    filter_preview: Option<Vec<(String, String)>>,
+    /// Problems met while loading the project state, until dismissed
+    load_diagnostics: Vec<LoadDiagnostic>,
+    /// Upgrade of the project's format made while opening it, until dismissed
+    migration_report: Option<MigrationReport>,
+    /// Problems found by the last integrity check, until dismissed
+    integrity_report: Option<CheckReport>,
+    /// Index of the active project for the search panel
+    search_service: Option<SearchService>,
+    /// Index of the other recent projects, with the folders it was built
+    /// for, for searches across projects
+    global_index: Option<(Vec<std::path::PathBuf>, Arc<GlobalIndex>)>,
+    /// Titles and headings of the project's documents, for quick open
+    quick_index: Option<QuickIndex>,
+    /// Folders the active project keeps importing from
+    folder_watches: Vec<FolderWatch>,
+    /// Running project search and its query
+    search_task: Option<(SearchQuery, TaskHandle<SearchResults>)>,
+    /// Visited and recently edited locations, for Back/Forward
+    navigation: NavigationHistory,
     /// UI state
     ui_state: UiState,
     /// Whether to show the new project dialog
     show_new_project_dialog: bool,
     /// New project dialog state
-    new_project_name: String,
-    new_project_path: String,
-    new_project_template: String,
+    new_project: NewProjectWizard,
+    /// Duplicate Project dialog, while it is open
+    duplicate_project: Option<DuplicateProjectForm>,
+    /// Save as Template dialog, while it is open
+    save_template: Option<SaveTemplateForm>,
+    /// Import Folder dialog, while it is open
+    import_folder: Option<ImportFolderForm>,
+    /// Command palette, while it is open
+    command_palette: Option<CommandPalette>,
+    /// Quick open switcher, while it is open
+    quick_open: Option<CommandPalette>,
     /// Whether to show the close confirmation dialog
     show_close_confirmation: bool,
     /// Whether to force close the application (ignoring unsaved changes)
     force_close: bool,
 }
 
+/// Sides of the workspace holding tabbed panels
+const DOCK_SIDES: [PanelPosition; 3] = [
+    PanelPosition::Left,
+    PanelPosition::Right,
+    PanelPosition::Bottom,
+];
+
+/// Languages offered in the settings, by tag, for numbers and dates
+const LANGUAGES: [(&str, &str); 14] = [
+    ("en", "English (US)"),
+    ("en-GB", "English (UK)"),
+    ("fr", "French"),
+    ("fr-CA", "French (Canada)"),
+    ("de", "German"),
+    ("de-CH", "German (Switzerland)"),
+    ("es", "Spanish"),
+    ("it", "Italian"),
+    ("pt-BR", "Portuguese (Brazil)"),
+    ("nl", "Dutch"),
+    ("pl", "Polish"),
+    ("ru", "Russian"),
+    ("ja", "Japanese"),
+    ("zh", "Chinese"),
+];
+
+/// Lines shown in the preview of the compile filters
+const FILTER_PREVIEW_LINES: usize = 10;
+
+/// Payload dragged when moving a panel's tab to another side
+#[derive(Debug, Clone)]
+struct PanelTab(String);
+
+/// Change made from a side's tab bar, applied once the sides are drawn
+#[derive(Debug, Clone)]
+enum TabAction {
+    /// Select the panel's tab
+    Select(String),
+    /// Move the panel's tab to the end of another side
+    Move(String, PanelPosition),
+    /// Hide the panel
+    Close(String),
+    /// Pop the panel out into its own window, where the tab was dropped
+    Float(String, Option<egui::Pos2>),
+    /// Keep the panel's window above other applications, or stop doing so
+    Pin(String, bool),
+    /// Put a floating panel back on its default side
+    Dock(String),
+    /// Scale the panel's text, 1.0 to follow the rest of the interface
+    Zoom(String, f32),
+}
+
+/// Step of the Larger Text and Smaller Text entries of a panel's menu.
+const PANEL_ZOOM_STEP: f32 = 0.1;
+
+/// Pages of the New Project wizard, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NewProjectStep {
+    Template,
+    Location,
+    Remote,
+    Goal,
+}
+
+impl NewProjectStep {
+    const ALL: [NewProjectStep; 4] = [
+        NewProjectStep::Template,
+        NewProjectStep::Location,
+        NewProjectStep::Remote,
+        NewProjectStep::Goal,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            NewProjectStep::Template => "Template",
+            NewProjectStep::Location => "Name and Location",
+            NewProjectStep::Remote => "Git Remote",
+            NewProjectStep::Goal => "Goal",
+        }
+    }
+}
+
+/// Choices made in the New Project wizard
+#[derive(Debug, Clone)]
+struct NewProjectWizard {
+    /// Page shown
+    step: NewProjectStep,
+    template: ProjectTemplate,
+    /// Templates saved from projects, read when the wizard opens
+    user_templates: Vec<UserTemplate>,
+    /// Index of the user template chosen over `template`
+    user_template: Option<usize>,
+    name: String,
+    /// Folder the project folder is created in
+    location: String,
+    /// URL of the `origin` remote, empty for none
+    remote_url: String,
+    /// Words to write, 0 for none
+    word_target: usize,
+    /// Date the words are due, empty for none
+    deadline: String,
+    location_check: LocationCheck,
+}
+
+impl Default for NewProjectWizard {
+    fn default() -> Self {
+        Self {
+            step: NewProjectStep::Template,
+            template: ProjectTemplate::Novel,
+            user_templates: Vec::new(),
+            user_template: None,
+            name: String::new(),
+            location: dirs::document_dir()
+                .unwrap_or_else(|| std::path::PathBuf::from("."))
+                .join("Cosmarium Projects")
+                .to_string_lossy()
+                .to_string(),
+            remote_url: String::new(),
+            word_target: 0,
+            deadline: String::new(),
+            location_check: LocationCheck::default(),
+        }
+    }
+}
+
+impl NewProjectWizard {
+    /// Issues of the location, blocking ones first.
+    fn location_issues(&mut self) -> &[LocationIssue] {
+        self.location_check.issues(&self.location, &self.name)
+    }
+
+    /// Date the words are due, `Err` if it cannot be read.
+    fn deadline(&self) -> std::result::Result<Option<chrono::NaiveDate>, chrono::ParseError> {
+        parse_deadline(&self.deadline)
+    }
+
+    /// Whether the project can be created with these choices.
+    fn is_valid(&mut self) -> bool {
+        !self
+            .location_issues()
+            .iter()
+            .any(LocationIssue::is_blocking)
+            && self.deadline().is_ok()
+    }
+
+    /// User template chosen, if any.
+    fn chosen_user_template(&self) -> Option<&UserTemplate> {
+        self.user_template
+            .and_then(|index| self.user_templates.get(index))
+    }
+
+    /// Start over, keeping the location for the next project.
+    fn reset(&mut self) {
+        *self = Self {
+            location: std::mem::take(&mut self.location),
+            ..Self::default()
+        };
+    }
+}
+
+/// Issues of where a project would be created, checked again only when
+/// the folder or the name changes: the check writes a file in the folder.
+#[derive(Debug, Clone, Default)]
+struct LocationCheck {
+    /// Folder and name last checked
+    checked: Option<(String, String)>,
+    issues: Vec<LocationIssue>,
+}
+
+impl LocationCheck {
+    /// Issues of creating a project named `name` in the folder `location`,
+    /// blocking ones first.
+    fn issues(&mut self, location: &str, name: &str) -> &[LocationIssue] {
+        let key = (location.trim().to_string(), name.to_string());
+        if self.checked.as_ref() != Some(&key) {
+            self.issues = check_location(std::path::Path::new(&key.0), name);
+            self.checked = Some(key);
+        }
+        &self.issues
+    }
+}
+
+/// Choices of the Duplicate Project dialog
+#[derive(Debug, Clone)]
+struct DuplicateProjectForm {
+    name: String,
+    /// Folder the copy is created in
+    location: String,
+    /// Whether the copy starts a new Git history
+    reset_history: bool,
+    location_check: LocationCheck,
+}
+
+/// Choices of the Save as Template dialog
+#[derive(Debug, Clone, Default)]
+struct SaveTemplateForm {
+    name: String,
+    description: String,
+}
+
+/// Choices of the Import Folder dialog
+#[derive(Debug, Clone)]
+struct ImportFolderForm {
+    source: std::path::PathBuf,
+    /// Whether new and changed files keep being imported
+    watch: bool,
+}
+
+/// Documents and headings listed by quick open at most.
+const QUICK_OPEN_RESULTS: usize = 50;
+
+/// Shared state key (`Option<String>`) of the application command to run,
+/// by identifier, set by the handlers of the [`AppCommand`]s.
+const APP_COMMAND_REQUEST: &str = "app_command_request";
+
+/// Commits listed at most in the Git history of a document.
+const DOCUMENT_COMMITS_LIMIT: usize = 200;
+
+/// Commands of the application itself, registered with the plugin context
+/// next to those of the plugins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AppCommand {
+    NewProject,
+    OpenProject,
+    SaveProject,
+    Quit,
+    Back,
+    Forward,
+    Activity(Activity),
+    QuickOpen,
+    CommandPalette,
+}
+
+impl AppCommand {
+    /// All commands, in palette order.
+    const ALL: [AppCommand; 11] = [
+        AppCommand::NewProject,
+        AppCommand::OpenProject,
+        AppCommand::SaveProject,
+        AppCommand::Quit,
+        AppCommand::Back,
+        AppCommand::Forward,
+        AppCommand::Activity(Activity::Drafting),
+        AppCommand::Activity(Activity::Revising),
+        AppCommand::Activity(Activity::Planning),
+        AppCommand::QuickOpen,
+        AppCommand::CommandPalette,
+    ];
+
+    /// Identifier of the command registered with the plugin context.
+    fn id(self) -> &'static str {
+        match self {
+            AppCommand::NewProject => "app.new_project",
+            AppCommand::OpenProject => "app.open_project",
+            AppCommand::SaveProject => "app.save_project",
+            AppCommand::Quit => "app.quit",
+            AppCommand::Back => "app.back",
+            AppCommand::Forward => "app.forward",
+            AppCommand::Activity(Activity::Drafting) => "app.activity.drafting",
+            AppCommand::Activity(Activity::Revising) => "app.activity.revising",
+            AppCommand::Activity(Activity::Planning) => "app.activity.planning",
+            AppCommand::QuickOpen => "app.quick_open",
+            AppCommand::CommandPalette => "app.command_palette",
+        }
+    }
+
+    /// Command registered as `id`, if it is one of the application's.
+    fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|command| command.id() == id)
+    }
+
+    /// Title shown in the command palette.
+    fn title(self) -> &'static str {
+        match self {
+            AppCommand::NewProject => "New Project",
+            AppCommand::OpenProject => "Open Project",
+            AppCommand::SaveProject => "Save Project",
+            AppCommand::Quit => "Exit",
+            AppCommand::Back => "Go Back",
+            AppCommand::Forward => "Go Forward",
+            AppCommand::Activity(Activity::Drafting) => "Switch to Drafting",
+            AppCommand::Activity(Activity::Revising) => "Switch to Revising",
+            AppCommand::Activity(Activity::Planning) => "Switch to Planning",
+            AppCommand::QuickOpen => "Go to Document or Heading",
+            AppCommand::CommandPalette => "Command Palette",
+        }
+    }
+
+    /// Shortcut the command is bound to by default.
+    fn default_shortcut(self) -> Shortcut {
+        match self {
+            AppCommand::NewProject => Shortcut::ctrl("N"),
+            AppCommand::OpenProject => Shortcut::ctrl("O"),
+            AppCommand::SaveProject => Shortcut::ctrl("S"),
+            AppCommand::Quit => Shortcut::ctrl("Q"),
+            AppCommand::Back => Shortcut::new("Left").with_alt(),
+            AppCommand::Forward => Shortcut::new("Right").with_alt(),
+            AppCommand::Activity(Activity::Drafting) => Shortcut::ctrl("1"),
+            AppCommand::Activity(Activity::Revising) => Shortcut::ctrl("2"),
+            AppCommand::Activity(Activity::Planning) => Shortcut::ctrl("3"),
+            AppCommand::QuickOpen => Shortcut::ctrl("P"),
+            AppCommand::CommandPalette => Shortcut::ctrl("P").with_shift(),
+        }
+    }
+}
+
+/// Register the [`AppCommand`]s with `ctx`, each asking the application to
+/// run it through [`APP_COMMAND_REQUEST`].
+fn register_app_commands(ctx: &mut PluginContext) {
+    for command in AppCommand::ALL {
+        ctx.register_command(
+            command.id(),
+            command.title(),
+            Some(command.default_shortcut()),
+            move |ctx: &mut PluginContext| {
+                ctx.set_shared_state(APP_COMMAND_REQUEST, Some(command.id().to_string()))
+            },
+        );
+    }
+}
+
+/// State of the command palette and of the quick open switcher
+#[derive(Debug, Clone, Default)]
+struct CommandPalette {
+    /// Text the commands or documents are filtered by
+    query: String,
+    /// Index of the highlighted entry among those shown
+    selected: usize,
+}
+
 /// Identifiers for the top-level menus
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum MenuId {
@@ -103,12 +650,14 @@ struct UiState {
     active_menu: Option<MenuId>,
     /// Current theme name
     current_theme: String,
-    /// Currently active panel in the left sidebar
-    active_left_panel: Option<String>,
     /// Whether to show the atmosphere color picker
     show_atmosphere_picker: bool,
     /// The current color in the picker
     atmosphere_picker_color: egui::Color32,
+    /// Floating panels to move where their tab was dropped
+    window_moves: HashMap<String, egui::Pos2>,
+    /// Pinned panels whose window is already open
+    pinned_windows: HashSet<String>,
 }
 
 impl Default for UiState {
@@ -116,7 +665,6 @@ impl Default for UiState {
         Self {
             open_panels: HashMap::new(),
             left_panel_width: 250.0,
-            active_left_panel: None,
             right_panel_width: 300.0,
             bottom_panel_height: 200.0,
             show_menu_bar: true,
@@ -127,6 +675,8 @@ impl Default for UiState {
             current_theme: "Dark".to_string(),
             show_atmosphere_picker: false,
             atmosphere_picker_color: egui::Color32::from_gray(128),
+            window_moves: HashMap::new(),
+            pinned_windows: HashSet::new(),
         }
     }
 }
@@ -140,13 +690,16 @@ impl Cosmarium {
     /// * `args` - Command line arguments
     pub fn new(cc: &eframe::CreationContext<'_>, args: AppArgs) -> Self {
         // Enable IME for dead keys support
-        cc.egui_ctx.send_viewport_cmd(egui::ViewportCommand::IMEAllowed(true));
+        cc.egui_ctx
+            .send_viewport_cmd(egui::ViewportCommand::IMEAllowed(true));
 
         let mut app = Self {
             core_app: Application::new(),
             plugin_context: PluginContext::new(),
             current_sentiment: 0.0,
             current_intensity: 0.0,
+            current_daylight: 0.0,
+            theme_scheduler: ThemeScheduler::new(ThemeScheduleConfig::default()),
             plugins: HashMap::new(),
             panel_plugins: HashMap::new(),
             config: Config::default(),
@@ -155,20 +708,61 @@ impl Cosmarium {
             startup_time: Instant::now(),
             show_about: false,
             show_plugin_manager: false,
+            show_diagnostics: false,
+            log_targets_text: String::new(),
+            log_filter_error: None,
             show_settings: false,
             current_project: None,
             active_document_id: None,
             recent_projects: Vec::new(), // Will be populated from session
             current_branch: None,
+            branch_task: None,
+            open_task: None,
+            commit_task: None,
+            export_tasks: Vec::new(),
+            export_plugins: Vec::new(),
+            word_count_rules: WordCountRules::default(),
+            line_ending: LineEnding::default(),
+            auto_commit: true,
+            published_as: (String::new(), String::new()),
+            quote_style: QuoteStyle::default(),
+            autocorrect: AutocorrectSettings::default(),
+            keymap: Keymap::default(),
+            recording_shortcut: None,
+            new_correction: (String::new(), String::new()),
+            project_dictionary: ProjectDictionary::default(),
+            dictionary_suffixes: String::new(),
+            new_dictionary_word: String::new(),
+            scene_heading_format: String::new(),
+            project_word_target: 0,
+            project_deadline: String::new(),
+            writing_goals: WritingGoals::default(),
+            reached_goals: Vec::new(),
+            locale: Locale::default(),
+            term_check_task: None,
+            term_issues: None,
+            accessibility_task: None,
+            second_copy_task: None,
+            accessibility_report: None,
+            progress_proof: None,
+            filter_preview: None,
+            load_diagnostics: Vec::new(),
+            migration_report: None,
+            integrity_report: None,
+            search_service: None,
+            global_index: None,
+            quick_index: None,
+            folder_watches: Vec::new(),
+            search_task: None,
+            navigation: NavigationHistory::new(),
             ui_state: UiState::default(),
             show_new_project_dialog: false,
-            new_project_name: String::new(),
-            new_project_path: dirs::document_dir()
-                .unwrap_or_else(|| std::path::PathBuf::from("."))
-                .join("Cosmarium Projects")
-                .to_string_lossy()
-                .to_string(),
-            new_project_template: "novel".to_string(),
+            new_project: NewProjectWizard::default(),
+            duplicate_project: None,
+            save_template: None,
+            import_folder: None,
+            command_palette: None,
+            quick_open: None,
             show_close_confirmation: false,
             force_close: false,
         };
@@ -204,14 +798,21 @@ impl Cosmarium {
     fn initialize(&mut self) -> Result<()> {
         tracing::info!("Initializing Cosmarium application");
 
-        // Initialize core application (this is async, so we need a runtime)
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| anyhow::anyhow!("Failed to create Tokio runtime: {}", e))?;
-        rt.block_on(async { self.core_app.initialize().await })?;
+        // Initialize core application on the shared executor
+        let executor = self.core_app.executor();
+        executor.block_on(async { self.core_app.initialize().await })?;
+
+        // Let plugins publish and subscribe through the core event bus, and
+        // run tracked tasks on the shared executor
+        let event_bus = self.core_app.event_bus();
+        let link = executor.block_on(async { event_bus.read().await.link() });
+        self.plugin_context.connect_event_bus(link);
+        self.plugin_context
+            .connect_task_spawner(self.core_app.task_manager().spawner());
 
         // Load recent projects
         let project_manager = Arc::clone(&self.core_app.project_manager());
-        let recent = rt.block_on(async {
+        let recent = executor.block_on(async {
             let pm = project_manager.read().await;
             pm.recent_projects().to_vec()
         });
@@ -219,9 +820,34 @@ impl Cosmarium {
 
         // Load configuration
         self.config = Config::load_or_default()?;
+        self.apply_theme_config();
+        self.apply_editor_config();
+        self.apply_locale();
+        self.writing_goals.set_config(self.config.goals);
+        self.publish_writing_history();
+
+        // Give plugins back the state they kept from the last session
+        for (plugin_name, state) in &self.session.plugin_state {
+            self.plugin_context
+                .set_plugin_data(plugin_name, SESSION_STATE_KEY, state.clone());
+        }
+
+        // Hand plugins their settings
+        for (plugin_name, settings) in &self.config.plugins.plugin_settings {
+            self.plugin_context.set_config(plugin_name, settings);
+        }
 
-        // Initialize core plugins
+        // Initialize core plugins, after the commands of the application
+        register_app_commands(&mut self.plugin_context);
         self.load_core_plugins()?;
+        self.keymap = keymap::load().unwrap_or_else(|e| {
+            tracing::warn!(
+                "Failed to load the keymap, using the default shortcuts: {}",
+                e
+            );
+            Keymap::default()
+        });
+        self.plugin_context.set_keymap(self.keymap.clone());
 
         // Emit application startup event
         let event = Event::new(
@@ -250,6 +876,15 @@ impl Cosmarium {
         // Mark the editor panel as open by default
         self.ui_state.open_panels.insert(plugin_name, true);
 
+        // Load binder plugin, open by default as the project's navigation
+        let mut binder_plugin = BinderPlugin::new();
+        binder_plugin.initialize(&mut self.plugin_context)?;
+
+        let binder_plugin_name = binder_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(binder_plugin_name.clone(), Box::new(binder_plugin));
+        self.ui_state.open_panels.insert(binder_plugin_name, true);
+
         // Load outline plugin
         let mut outline_plugin = OutlinePlugin::new();
         outline_plugin.initialize(&mut self.plugin_context)?;
@@ -259,6 +894,156 @@ impl Cosmarium {
             .insert(outline_plugin_name.clone(), Box::new(outline_plugin));
         // Outline panel is not open by default, so no need to add to open_panels
 
+        // Load inline tasks plugin
+        let mut tasks_plugin = TasksPlugin::new();
+        tasks_plugin.initialize(&mut self.plugin_context)?;
+
+        let tasks_plugin_name = tasks_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(tasks_plugin_name, Box::new(tasks_plugin));
+
+        // Load worldbuilding wiki plugin
+        let mut wiki_plugin = WikiPlugin::new();
+        wiki_plugin.initialize(&mut self.plugin_context)?;
+
+        let wiki_plugin_name = wiki_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(wiki_plugin_name, Box::new(wiki_plugin));
+
+        // Load scene metadata inspector plugin
+        let mut inspector_plugin = InspectorPlugin::new();
+        inspector_plugin.initialize(&mut self.plugin_context)?;
+
+        let inspector_plugin_name = inspector_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(inspector_plugin_name, Box::new(inspector_plugin));
+
+        // Load writing history dashboard plugin
+        let mut history_plugin = HistoryPlugin::new();
+        history_plugin.initialize(&mut self.plugin_context)?;
+
+        let history_plugin_name = history_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(history_plugin_name, Box::new(history_plugin));
+
+        // Load writing sprint timer plugin
+        let mut sprint_plugin = SprintPlugin::new();
+        sprint_plugin.initialize(&mut self.plugin_context)?;
+
+        let sprint_plugin_name = sprint_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(sprint_plugin_name, Box::new(sprint_plugin));
+
+        // Load statistics changes plugin
+        let mut changes_plugin = ChangesPlugin::new();
+        changes_plugin.initialize(&mut self.plugin_context)?;
+
+        let changes_plugin_name = changes_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(changes_plugin_name, Box::new(changes_plugin));
+
+        // Load document snapshots plugin
+        let mut snapshots_plugin = SnapshotsPlugin::new();
+        snapshots_plugin.initialize(&mut self.plugin_context)?;
+
+        let snapshots_plugin_name = snapshots_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(snapshots_plugin_name, Box::new(snapshots_plugin));
+
+        // Load Git history plugin
+        let mut git_history_plugin = GitHistoryPlugin::new();
+        git_history_plugin.initialize(&mut self.plugin_context)?;
+
+        let git_history_plugin_name = git_history_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(git_history_plugin_name, Box::new(git_history_plugin));
+
+        // Load alternate drafts plugin
+        let mut drafts_plugin = DraftsPlugin::new();
+        drafts_plugin.initialize(&mut self.plugin_context)?;
+
+        let drafts_plugin_name = drafts_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(drafts_plugin_name, Box::new(drafts_plugin));
+
+        // Load project search plugin
+        let mut search_plugin = SearchPlugin::new();
+        search_plugin.initialize(&mut self.plugin_context)?;
+
+        let search_plugin_name = search_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(search_plugin_name, Box::new(search_plugin));
+
+        // Load grammar checking plugin
+        let mut grammar_plugin = GrammarPlugin::new();
+        grammar_plugin.initialize(&mut self.plugin_context)?;
+
+        let grammar_plugin_name = grammar_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(grammar_plugin_name, Box::new(grammar_plugin));
+
+        // Load prose analysis plugin
+        let mut prose_plugin = ProsePlugin::new();
+        prose_plugin.initialize(&mut self.plugin_context)?;
+
+        let prose_plugin_name = prose_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(prose_plugin_name, Box::new(prose_plugin));
+
+        // Load kanban board plugin (opened from the View menu)
+        let mut kanban_plugin = KanbanPlugin::new();
+        kanban_plugin.initialize(&mut self.plugin_context)?;
+
+        let kanban_plugin_name = kanban_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(kanban_plugin_name, Box::new(kanban_plugin));
+
+        // Load quote card plugin (opened from the View menu)
+        let mut quote_card_plugin = QuoteCardPlugin::new();
+        quote_card_plugin.initialize(&mut self.plugin_context)?;
+
+        let quote_card_plugin_name = quote_card_plugin.info().name.clone();
+        self.panel_plugins
+            .insert(quote_card_plugin_name, Box::new(quote_card_plugin));
+
+        // Load PDF export plugin (offered in the Compile Manuscript menu)
+        #[cfg(feature = "export-pdf")]
+        {
+            let mut pdf_plugin = PdfExportPlugin::new();
+            pdf_plugin.initialize(&mut self.plugin_context)?;
+            self.export_plugins.push(Arc::new(pdf_plugin));
+        }
+
+        // Load Word export plugin
+        #[cfg(feature = "export-docx")]
+        {
+            let mut docx_plugin = DocxExportPlugin::new();
+            docx_plugin.initialize(&mut self.plugin_context)?;
+            self.export_plugins.push(Arc::new(docx_plugin));
+        }
+
+        // Load the pandoc formats, falling back on the native exporters
+        if self.config.export.pandoc.enabled {
+            match Pandoc::detect(&self.config.export.pandoc.executable) {
+                Some(pandoc) => {
+                    for plugin in pandoc.plugins() {
+                        let native = self
+                            .export_plugins
+                            .iter()
+                            .find(|native| native.format_id() == plugin.format_id())
+                            .cloned();
+                        let mut plugin = match native {
+                            Some(native) => plugin.with_fallback(native),
+                            None => plugin,
+                        };
+                        plugin.initialize(&mut self.plugin_context)?;
+                        self.export_plugins.push(Arc::new(plugin));
+                    }
+                }
+                None => tracing::info!("pandoc not found, its export formats are not offered"),
+            }
+        }
+
         // Load atmosphere plugin
         let mut atmosphere_plugin = AtmospherePlugin::new();
         atmosphere_plugin.initialize(&mut self.plugin_context)?;
@@ -267,1327 +1052,7105 @@ impl Cosmarium {
         self.plugins
             .insert(atmosphere_name, Box::new(atmosphere_plugin));
 
+        self.dock_panels();
+        self.restore_open_panels();
+
         tracing::info!("Core plugins loaded. Total plugins: {}", self.plugins.len());
         Ok(())
     }
 
-    /// Get the current Git branch name if a project is open.
-    fn get_current_branch(&self) -> Option<String> {
-        let project_manager = Arc::clone(&self.core_app.project_manager());
-
-        let rt = tokio::runtime::Runtime::new().ok()?;
-        rt.block_on(async {
-            let pm = project_manager.read().await;
-            if let Some(project) = pm.active_project() {
-                if let Some(git) = project.git() {
-                    return git.current_branch().ok();
-                }
+    /// Give every side panel a tab on its default side, unless the saved
+    /// layout already placed it.
+    fn dock_panels(&mut self) {
+        let placements: Vec<(String, PanelPosition)> = self
+            .panel_plugins
+            .iter()
+            .filter(|(_, plugin)| DOCK_SIDES.contains(&plugin.default_position()))
+            .map(|(name, plugin)| (name.clone(), plugin.default_position()))
+            .collect();
+        self.with_layout(|layout_manager| {
+            let layout = layout_manager.current_layout_mut();
+            for (name, position) in &placements {
+                layout.dock_panel(name, *position);
             }
-            None
-        })
+        });
     }
 
-    /// Load a project from the specified path.
-    fn load_project(&mut self, path: &std::path::Path) -> Result<()> {
-        tracing::info!("Loading project from {:?}", path);
-
-        // TODO: Implement actual project loading
-        self.current_project = Some(path.to_path_buf());
-
-        let event = Event::new(
-            EventType::ProjectOpened,
-            format!("Opened project: {}", path.display()),
-        );
-        self.plugin_context.emit_event(event);
-
-        Ok(())
+    /// Switch to the open panels and arrangement of another activity.
+    fn switch_activity(&mut self, activity: Activity) {
+        self.store_open_panels();
+        let layout_manager = self.core_app.layout_manager();
+        let switched = self
+            .core_app
+            .executor()
+            .block_on(async move { layout_manager.write().await.switch_activity(activity).await });
+        if let Err(e) = switched {
+            tracing::error!("Failed to switch to {}: {}", activity.name(), e);
+            return;
+        }
+        self.dock_panels();
+        self.restore_open_panels();
     }
 
-    /// Synchronize content from the editor plugin to the document manager
-    fn sync_editor_content(&mut self) {
-        let document_manager = self.core_app.document_manager();
+    /// Keep the open panels in the current layout.
+    fn store_open_panels(&mut self) {
+        let open: Vec<String> = self
+            .ui_state
+            .open_panels
+            .iter()
+            .filter(|(_, open)| **open)
+            .map(|(name, _)| name.clone())
+            .collect();
+        self.with_layout(|layout_manager| {
+            layout_manager.current_layout_mut().set_open_panels(open);
+        });
+    }
 
-        // Check if there's new content from the editor
-        if let Some(content) = self
-            .plugin_context
-            .get_shared_state::<String>("markdown_editor_content")
-        {
-            if let Some(doc_id) = self.active_document_id {
-                // Use a blocking runtime to acquire the write lock deterministically
-                let rt = match tokio::runtime::Runtime::new() {
-                    Ok(r) => r,
-                    Err(e) => {
-                        tracing::error!("Failed to create Tokio runtime for sync: {}", e);
-                        return;
-                    }
-                };
-                rt.block_on(async {
-                    let mut manager = document_manager.write().await;
-                    if let Some(doc) = manager.get_document_mut(doc_id) {
-                        if doc.content() != content {
-                            doc.set_content(&content);
-                        }
-                    }
-                });
+    /// Open the panels kept in the current layout and close the others.
+    fn restore_open_panels(&mut self) {
+        let open = self.with_layout(|layout_manager| {
+            layout_manager
+                .current_layout()
+                .open_panels()
+                .map(<[String]>::to_vec)
+        });
+        let Some(open) = open else {
+            return;
+        };
+        for (name, plugin) in &self.panel_plugins {
+            if plugin.is_closable() {
+                self.ui_state
+                    .open_panels
+                    .insert(name.clone(), open.contains(name));
             }
         }
     }
 
-    /// Check if there are any unsaved changes in the project
-    fn check_unsaved_changes(&self) -> bool {
-        // Use a blocking runtime to acquire read locks deterministically. If runtime
-        // creation fails, be conservative and report unsaved changes.
-        let rt = match tokio::runtime::Runtime::new() {
-            Ok(r) => r,
-            Err(e) => {
-                tracing::error!(
-                    "Failed to create Tokio runtime for check_unsaved_changes: {}",
-                    e
-                );
-                return true; // Conservative: assume there are unsaved changes
-            }
-        };
+    /// Render the segment switching between activities.
+    fn render_activity_switcher(&mut self, ui: &mut egui::Ui) {
+        let current = self.with_layout(|layout_manager| layout_manager.current_layout().activity());
+        let mut selected = current;
+        for (index, activity) in Activity::ALL.iter().enumerate().rev() {
+            let response = ui
+                .selectable_value(&mut selected, *activity, activity.name())
+                .on_hover_text(format!("Ctrl+{}", index + 1));
+            // Let screen readers tell which activity is current
+            response.widget_info(|| {
+                egui::WidgetInfo::selected(
+                    egui::WidgetType::SelectableLabel,
+                    true,
+                    *activity == current,
+                    activity.name(),
+                )
+            });
+        }
+        if selected != current {
+            self.switch_activity(selected);
+        }
+    }
 
-        let project_manager = self.core_app.project_manager();
-        if rt.block_on(async {
-            let manager = project_manager.read().await;
-            if let Some(project) = manager.active_project() {
-                return project.has_unsaved_changes();
+    /// Run `f` on the layout manager keeping the panel arrangement.
+    fn with_layout<R>(&self, f: impl FnOnce(&mut LayoutManager) -> R) -> R {
+        let layout_manager = self.core_app.layout_manager();
+        self.core_app.executor().block_on(async move {
+            let mut layout_manager = layout_manager.write().await;
+            f(&mut layout_manager)
+        })
+    }
+
+    /// Copy the settings plugins keep in their configuration into the
+    /// application configuration, to be saved with it.
+    fn store_plugin_settings(&mut self) {
+        let plugin_names = self.plugins.keys().chain(self.panel_plugins.keys());
+        for plugin_name in plugin_names {
+            if let Some(settings) = self
+                .plugin_context
+                .get_config::<serde_json::Value>(plugin_name)
+            {
+                self.config
+                    .plugins
+                    .plugin_settings
+                    .insert(plugin_name.clone(), settings);
             }
-            false
-        }) {
-            return true;
         }
+    }
+
+    /// Publish the editor settings the editor plugin and the document
+    /// manager follow.
+    fn apply_editor_config(&mut self) {
+        let editor = &self.config.editor;
+        self.plugin_context
+            .set_shared_state(WRAP_COLUMN_KEY, editor.word_wrap_column);
+        self.plugin_context
+            .set_shared_state(SHOW_WHITESPACE_KEY, editor.show_whitespace);
+        self.plugin_context
+            .set_shared_state(PASTE_CLEANUP_KEY, editor.paste_cleanup);
+        self.plugin_context.set_shared_state(
+            FOCUS_SETTINGS_KEY,
+            FocusSettings {
+                width: editor.focus_width,
+                dim_paragraphs: editor.focus_dim_paragraphs,
+            },
+        );
+        self.plugin_context.set_shared_state(
+            UNDO_SETTINGS_KEY,
+            UndoSettings {
+                depth: editor.undo_depth,
+                grouping: if editor.undo_by_sentence {
+                    UndoGrouping::Sentence
+                } else {
+                    UndoGrouping::Word
+                },
+            },
+        );
+        let dictionary_dirs = dirs::data_dir()
+            .map(|dir| vec![dir.join("cosmarium").join("dictionaries")])
+            .unwrap_or_default();
+        self.plugin_context.set_shared_state(
+            SPELL_CHECK_KEY,
+            SpellCheckSettings {
+                enabled: editor.spell_check_enabled,
+                language: editor.spell_check_language.clone(),
+                dictionary_dirs,
+            },
+        );
 
+        let trim = editor.trim_trailing_whitespace;
         let document_manager = self.core_app.document_manager();
-        let any_unsaved = rt.block_on(async {
-            let manager = document_manager.read().await;
-            for doc_id in manager.list_documents() {
-                if let Some(doc) = manager.get_document(doc_id) {
-                    if doc.has_unsaved_changes() {
-                        return true;
-                    }
-                }
-            }
-            false
+        self.core_app.executor().block_on(async {
+            document_manager
+                .write()
+                .await
+                .set_trim_trailing_whitespace(trim);
         });
+    }
 
-        any_unsaved
+    /// Format numbers and dates in the configured language, and hand the
+    /// locale to plugins.
+    fn apply_locale(&mut self) {
+        self.locale = Locale::new(&self.config.app.language);
+        self.plugin_context
+            .set_shared_state(LOCALE_KEY, self.locale.clone());
     }
 
-    /// Save the current project.
-    fn save_current_project(&mut self) -> Result<()> {
-        // Sync editor content first
-        self.sync_editor_content();
+    /// Sync theme UI state and scheduler with the loaded configuration.
+    fn apply_theme_config(&mut self) {
+        self.ui_state.current_theme = match self.config.ui.theme.as_str() {
+            "light" => "Light",
+            AUTO_THEME => "Auto",
+            _ => "Dark",
+        }
+        .to_string();
+        self.theme_scheduler = ThemeScheduler::new(self.config.ui.theme_schedule.clone());
+        self.current_daylight = self.target_daylight(None);
+    }
 
-        // Capture editor content (if any) before entering async block
-        let editor_content = self
-            .plugin_context
-            .get_shared_state::<String>("markdown_editor_content");
+    /// Target light/dark blend for the configured theme (0 = dark, 1 = light).
+    fn target_daylight(&self, system: Option<Appearance>) -> f32 {
+        match self.config.ui.theme.as_str() {
+            "light" => 1.0,
+            AUTO_THEME => self.theme_scheduler.current_daylight(system),
+            _ => 0.0,
+        }
+    }
 
+    /// Look up the current Git branch name in the background.
+    ///
+    /// The result is picked up by [`Self::poll_background_tasks`].
+    fn refresh_current_branch(&mut self) {
         let project_manager = self.core_app.project_manager();
-        let document_manager = self.core_app.document_manager();
+        let executor = self.core_app.executor();
+        let project_path = executor.block_on(async {
+            let pm = project_manager.read().await;
+            pm.active_project()
+                .filter(|project| project.git().is_some())
+                .map(|project| project.path().to_path_buf())
+        });
 
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| anyhow::anyhow!("Failed to create Tokio runtime: {}", e))?;
+        self.branch_task = project_path.map(|path| {
+            executor.spawn_blocking(move || {
+                GitIntegration::open(&path)
+                    .and_then(|git| git.current_branch())
+                    .ok()
+            })
+        });
+    }
 
-        // Save active document (if any) first, then save project metadata
-        rt.block_on(async {
-            // Determine project path if available
-            let project_path_opt = {
-                let pm_read = project_manager.read().await;
-                pm_read.active_project().map(|p| p.path().to_path_buf()).or_else(|| self.current_project.clone())
-            };
+    /// Whether the results of background tasks, such as the project being
+    /// opened, are still awaited.
+    pub fn is_busy(&self) -> bool {
+        self.branch_task.is_some()
+            || self.open_task.is_some()
+            || self.commit_task.is_some()
+            || !self.core_app.task_manager().running().is_empty()
+    }
 
-            tracing::debug!("save_current_project: project_path={:?}, active_document_id={:?}, editor_content_present={}",
-                project_path_opt,
-                self.active_document_id,
-                editor_content.is_some());
+    /// Collect the results of background tasks that have finished.
+    fn poll_background_tasks(&mut self, ctx: &egui::Context) {
+        if let Some(result) = self.open_task.as_mut().and_then(TaskHandle::try_take) {
+            self.open_task = None;
+            let opened = result.and_then(|(project, document)| {
+                Ok(self.finish_opening_project(project, document)?)
+            });
+            if let Err(e) = opened {
+                tracing::error!("Failed to open project: {}", e);
+            }
+        }
 
-            if let Some(doc_id) = self.active_document_id {
-                // Acquire document manager write lock to modify document and set file path if missing
-                let mut dm = document_manager.write().await;
-                if let Some(doc) = dm.get_document_mut(doc_id) {
-                    tracing::debug!("Found active document {} title='{}' file_path={:?}", doc_id, doc.title(), doc.file_path());
+        if let Some(result) = self.commit_task.as_mut().and_then(TaskHandle::try_take) {
+            self.commit_task = None;
+            self.finish_commit(result);
+        }
 
-                    if doc.file_path().is_none() {
-                        if let Some(proj_path) = &project_path_opt {
-                            // Ensure content dir exists
-                            let content_dir = proj_path.join("content");
-                            if let Err(e) = tokio::fs::create_dir_all(&content_dir).await {
-                                tracing::error!("Failed to create content dir: {}", e);
-                            } else {
-                                // Create filename from title or uuid fallback
-                                let safe_name = if !doc.title().is_empty() { doc.title().to_lowercase().replace(" ", "_") } else { format!("doc_{}", uuid::Uuid::new_v4()) };
-                                let filename = format!("{}.{}", safe_name, doc.format().extension());
-                                let file_path = content_dir.join(filename);
-                                tracing::debug!("Setting file path for doc {} -> {:?}", doc_id, file_path);
-                                doc.set_file_path(&file_path);
-                            }
-                        } else {
-                            tracing::warn!("No active project path; cannot set file path for document {}", doc_id);
-                        }
-                    }
+        if let Some(result) = self.branch_task.as_mut().and_then(TaskHandle::try_take) {
+            self.branch_task = None;
+            match result {
+                Ok(branch) => self.current_branch = branch,
+                Err(e) => tracing::warn!("Failed to read the current branch: {}", e),
+            }
+        }
 
-                    let file_path_opt = doc.file_path().map(|p| p.to_path_buf());
+        if let Some(result) = self.term_check_task.as_mut().and_then(TaskHandle::try_take) {
+            self.term_check_task = None;
+            match result {
+                Ok(issues) => self.term_issues = Some(issues),
+                Err(e) => tracing::error!("Failed to check glossary terms: {}", e),
+            }
+        }
 
-                    if let Err(e) = dm.save_document(doc_id).await {
-                        tracing::error!("Failed to save active document {}: {}", doc_id, e);
-                    } else {
-                        tracing::info!("Saved active document {} to {:?}", doc_id, file_path_opt);
-                        // Check file metadata
-                        if let Some(ref path) = file_path_opt {
-                            match tokio::fs::metadata(path).await {
-                                Ok(meta) => tracing::debug!("Saved file metadata: exists={}, size={}", true, meta.len()),
-                                Err(e) => tracing::warn!("Saved file metadata not found for {:?}: {}", path, e),
-                            }
-                        }
+        if let Some(result) = self
+            .accessibility_task
+            .as_mut()
+            .and_then(TaskHandle::try_take)
+        {
+            self.accessibility_task = None;
+            match result {
+                Ok(findings) => self.accessibility_report = Some(findings),
+                Err(e) => tracing::error!("Failed to check accessibility: {}", e),
+            }
+        }
 
-                        // Ensure project references this document id before saving project
-                        let mut pm = project_manager.write().await;
-                        if let Some(project) = pm.active_project_mut() {
-                            project.add_document(doc_id);
-                        }
-                        // pm.save_project() will be called after this async block
-                    }
-                } else {
-                    tracing::warn!("Active document id {} not found in DocumentManager", doc_id);
+        let search = self
+            .search_task
+            .as_mut()
+            .and_then(|(query, task)| Some((query.clone(), task.try_take()?)));
+        if let Some((query, result)) = search {
+            self.search_task = None;
+            let (hits, error) = match result {
+                Ok(results) => (
+                    results
+                        .groups()
+                        .flat_map(|(_, hits)| hits.iter().cloned())
+                        .collect(),
+                    None,
+                ),
+                Err(e) => {
+                    tracing::error!("Failed to search the project: {}", e);
+                    (Vec::new(), Some(e.to_string()))
                 }
-            } else if let Some(content) = editor_content {
-                tracing::debug!("No active document; creating new document from editor content (len={})", content.len());
-                // No active document: create one from editor content and save it
-                let mut dm = document_manager.write().await;
-                // Choose a title based on project or fallback
-                let title = if let Some(proj_path) = &project_path_opt {
-                    proj_path.file_name().and_then(|n| n.to_str()).unwrap_or("untitled").to_string()
-                } else {
-                    "untitled".to_string()
-                };
-
-                // Create document (Markdown assumed)
-                match dm.create_document(&title, &content, cosmarium_core::document::DocumentFormat::Markdown).await {
-                    Ok(new_id) => {
-                        tracing::debug!("Created new document {} with title='{}'", new_id, title);
-                        // Set file path if project exists
-                        if let Some(proj_path) = &project_path_opt {
-                            let content_dir = proj_path.join("content");
-                            if let Err(e) = tokio::fs::create_dir_all(&content_dir).await {
-                                tracing::error!("Failed to create content dir: {}", e);
-                            } else {
-                                let filename = format!("{}.{}", format!("doc_{}", new_id), cosmarium_core::document::DocumentFormat::Markdown.extension());
-                                let file_path = content_dir.join(filename);
-                                tracing::debug!("Setting file path for new doc {} -> {:?}", new_id, file_path);
-                                if let Some(doc_mut) = dm.get_document_mut(new_id) {
-                                    doc_mut.set_file_path(&file_path);
-                                }
-                            }
-                        } else {
-                            tracing::warn!("No active project path; new document {} will have no file path", new_id);
-                        }
+            };
+            self.publish_search_response(query, hits, error);
+        }
 
-                        if let Err(e) = dm.save_document(new_id).await {
-                            tracing::error!("Failed to save new document {}: {}", new_id, e);
-                        } else {
-                            tracing::info!("Saved new document {}", new_id);
-                            // Update active document id so UI reflects saved doc
-                            self.active_document_id = Some(new_id);
+        if let Some(result) = self
+            .second_copy_task
+            .as_mut()
+            .and_then(TaskHandle::try_take)
+        {
+            self.second_copy_task = None;
+            match result {
+                Ok(report) => tracing::info!(
+                    "Second copy in {:?} up to date ({} files copied)",
+                    report.dest,
+                    report.copied
+                ),
+                Err(e) => tracing::error!("Failed to update the second copy: {}", e),
+            }
+        }
 
-                            // Ensure project references this document id before saving project
-                            let mut pm = project_manager.write().await;
-                            if let Some(project) = pm.active_project_mut() {
-                                project.add_document(new_id);
-                            }
-                        }
-                    }
-                    Err(e) => tracing::error!("Failed to create document from editor content: {}", e),
-                }
-            } else {
-                tracing::debug!("No active document and no editor content to save");
+        self.export_tasks.retain_mut(|task| match task.try_take() {
+            Some(Ok(path)) => {
+                tracing::info!("Document exported to {:?}", path);
+                false
+            }
+            Some(Err(e)) => {
+                tracing::error!("Failed to export document: {}", e);
+                false
             }
+            None => true,
+        });
 
-            // Finally, save project metadata
-            let mut pm = project_manager.write().await;
-            pm.save_project().await
-        })
+        // Results were handled above or by the plugins that own them
+        self.core_app.task_manager().clear_finished();
+
+        // Keep polling while results are pending, even without user input
+        if self.is_busy() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
     }
 
-    /// Open a project asynchronously (called from file dialog).
-    fn open_project_async(&mut self, path: std::path::PathBuf) -> Result<()> {
-        tracing::info!("Opening project from {:?}", path);
+    /// Read a custom setting of the active project.
+    fn project_setting<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let project_manager = self.core_app.project_manager();
+        self.core_app
+            .executor()
+            .block_on(async {
+                let pm = project_manager.read().await;
+                pm.active_project()
+                    .and_then(|p| p.settings().custom.get(key).cloned())
+            })
+            .and_then(|value| serde_json::from_value(value).ok())
+    }
 
-        // Clone the Arc to avoid lifetime issues
-        let project_manager = Arc::clone(&self.core_app.project_manager());
-        let path_clone = path.clone();
+    /// Store a custom setting in the active project's settings.
+    fn set_project_setting<T: serde::Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        let value = serde_json::to_value(value)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize project setting '{}': {}", key, e))?;
+        let project_manager = self.core_app.project_manager();
 
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| anyhow::anyhow!("Failed to create Tokio runtime: {}", e))?;
-        rt.block_on(async move {
+        self.core_app.executor().block_on(async {
             let mut pm = project_manager.write().await;
-            pm.open_project(&path_clone).await
-        })?;
-
-        self.current_project = Some(path.clone());
-        self.plugin_context.set_project_path(Some(path.clone()));
-
-        // Load first document into editor (if any)
-        let project_manager = Arc::clone(&self.core_app.project_manager());
-        let document_manager = Arc::clone(&self.core_app.document_manager());
-
-        let rt2 = tokio::runtime::Runtime::new()
-            .map_err(|e| anyhow::anyhow!("Failed to create Tokio runtime: {}", e))?;
-        let pm_clone = Arc::clone(&project_manager);
-        let (doc_id_opt, doc_content) = rt2.block_on(async move {
-            // Try to find a content file in the project's content directory and open it.
-            let pm_read = pm_clone.read().await;
-            if let Some(project) = pm_read.active_project() {
-                let content_dir = project.path().join("content");
-                drop(pm_read); // release read lock before async file ops / write locks
-
-                // Look for a file in content_dir with a supported extension
-                let mut found: Option<std::path::PathBuf> = None;
-                if let Ok(mut rd) = tokio::fs::read_dir(&content_dir).await {
-                    while let Ok(Some(entry)) = rd.next_entry().await {
-                        if let Ok(ft) = entry.file_type().await {
-                            if ft.is_file() {
-                                let path = entry.path();
-                                if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-                                    match ext {
-                                        "md" | "markdown" | "txt" | "rtf" | "html" | "htm" => {
-                                            found = Some(path);
-                                            break;
-                                        }
-                                        _ => {}
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                if let Some(path) = found {
-                    // Open document in DocumentManager so it becomes available in-memory
-                    let mut dm_write = document_manager.write().await;
-                    match dm_write.open_document(&path).await {
-                        Ok(new_id) => {
-                            if let Some(doc) = dm_write.get_document(new_id) {
-                                return (Some(new_id), Some(doc.content().to_string()));
-                            }
-                        }
-                        Err(e) => {
-                            tracing::error!("Failed to open document from path {:?}: {}", path, e)
-                        }
-                    }
+            if let Some(project) = pm.active_project_mut() {
+                if project.settings().custom.get(key) != Some(&value) {
+                    project.settings_mut().custom.insert(key.to_string(), value);
                 }
             }
-
-            (None, None)
         });
+        Ok(())
+    }
 
-        // Set active document and content in editor if we got any
-        self.active_document_id = doc_id_opt;
-        if let Some(content) = doc_content {
-            self.plugin_context
-                .set_shared_state("markdown_editor_content", content.clone());
-            // Also write plugin-specific data as a fallback synchronization channel
-            self.plugin_context.set_plugin_data(
-                "markdown-editor",
-                "loaded_content",
-                content.clone(),
-            );
-        }
+    /// Read the active project's word count rules and publish them to plugins.
+    fn load_word_count_rules(&mut self) {
+        let rules = self
+            .project_setting(WORD_COUNT_RULES_KEY)
+            .unwrap_or_default();
+        self.word_count_rules = rules;
+        self.plugin_context.set_config(WORD_COUNT_RULES_KEY, rules);
+    }
 
-        // Update recent projects list
-        let rt3 = tokio::runtime::Runtime::new()
-            .map_err(|e| anyhow::anyhow!("Failed to create Tokio runtime: {}", e))?;
-        let recent = rt3.block_on(async {
-            let pm = project_manager.read().await;
-            pm.recent_projects().to_vec()
-        });
-        self.recent_projects = recent;
+    /// Store the edited word count rules in the active project's settings.
+    fn save_word_count_rules(&mut self) -> Result<()> {
+        self.set_project_setting(WORD_COUNT_RULES_KEY, &self.word_count_rules)?;
+        // Words the new rules count or leave out were not written
+        self.writing_goals.forget_counts();
+        self.plugin_context
+            .set_config(WORD_COUNT_RULES_KEY, self.word_count_rules);
+        Ok(())
+    }
 
-        // Get current Git branch
-        self.current_branch = self.get_current_branch();
+    /// Read the active project's scene heading convention and publish it to
+    /// plugins.
+    fn load_scene_heading_format(&mut self) {
+        self.scene_heading_format = self
+            .project_setting(SCENE_HEADING_FORMAT_KEY)
+            .unwrap_or_default();
+        self.plugin_context
+            .set_config(SCENE_HEADING_FORMAT_KEY, &self.scene_heading_format);
+    }
 
-        // Update session
-        self.session
-            .add_recent_project(path.clone(), self.config.app.max_recent_projects);
-        self.session.last_opened_project = Some(path);
-        if let Err(e) = self.session.save() {
-            tracing::warn!("Failed to save session: {}", e);
+    /// Store the edited scene heading convention in the active project's
+    /// settings. An empty convention turns scene headings off.
+    fn save_scene_heading_format(&mut self) -> Result<()> {
+        let pattern = self.scene_heading_format.trim().to_string();
+        if !pattern.is_empty() {
+            SceneHeadingFormat::parse(&pattern)
+                .map_err(|e| cosmarium_core::Error::config(e.to_string()))?;
         }
+        self.set_project_setting(SCENE_HEADING_FORMAT_KEY, &pattern)?;
+        self.plugin_context
+            .set_config(SCENE_HEADING_FORMAT_KEY, &pattern);
+        Ok(())
+    }
 
-        // Update UI list
-        self.recent_projects = self.session.recent_projects.clone();
+    /// Read the active project's word target and count the words written
+    /// towards it.
+    fn load_project_word_target(&mut self) {
+        self.project_word_target = self.project_setting(PROJECT_WORD_TARGET_KEY).unwrap_or(0);
+        self.project_deadline = self
+            .project_setting::<chrono::NaiveDate>(PROJECT_DEADLINE_KEY)
+            .map(|date| date.to_string())
+            .unwrap_or_default();
+        self.writing_goals
+            .set_project(self.current_project.clone(), self.project_word_target);
+        self.publish_writing_history();
+    }
 
-        // Request focus for the editor
+    /// Publish the words written, with the open project, for plugins.
+    fn publish_writing_history(&mut self) {
+        let history = WritingHistory {
+            project: self.current_project.clone(),
+            log: self.session.writing_log.clone(),
+            session_words: self.writing_goals.session_words(),
+        };
         self.plugin_context
-            .set_shared_state("markdown_editor_focus_requested", true);
+            .set_shared_state(WRITING_HISTORY_KEY, history);
+    }
 
+    /// Store the edited word target and its deadline in the active
+    /// project's settings.
+    fn save_project_word_target(&mut self) -> Result<()> {
+        let deadline = parse_deadline(&self.project_deadline)
+            .map_err(|e| anyhow::anyhow!("Invalid deadline '{}': {}", self.project_deadline, e))?;
+        self.set_project_setting(PROJECT_WORD_TARGET_KEY, &self.project_word_target)?;
+        self.set_project_setting(PROJECT_DEADLINE_KEY, &deadline)?;
+        self.writing_goals
+            .set_project(self.current_project.clone(), self.project_word_target);
         Ok(())
     }
 
-    /// Create a new project with the given parameters.
-    fn create_new_project(&mut self, name: String, path: String, template: String) -> Result<()> {
-        let project_path = std::path::PathBuf::from(&path).join(&name);
+    /// Log the words written in the active tab towards the writing goals,
+    /// and announce the targets this reaches.
+    fn track_writing_goals(&mut self) {
+        let Some((document, words)) = self
+            .plugin_context
+            .get_shared_state::<(uuid::Uuid, usize)>(WORD_COUNT_KEY)
+        else {
+            return;
+        };
+        let path = if self.active_document_id == Some(document) {
+            self.plugin_context
+                .get_shared_state::<Option<std::path::PathBuf>>("active_document_path")
+                .flatten()
+        } else {
+            None
+        };
+        let today = self.today();
+        let written = self.writing_goals.session_words();
+        let reached = self.writing_goals.record(
+            &mut self.session.writing_log,
+            document,
+            path.as_deref(),
+            words,
+            today,
+        );
+        if self.writing_goals.session_words() != written {
+            self.publish_writing_history();
+        }
+        if reached.is_empty() {
+            return;
+        }
 
-        tracing::info!("Creating new project '{}' at {:?}", name, project_path);
+        let progress = self
+            .writing_goals
+            .progress(&self.session.writing_log, today);
+        for progress in progress.into_iter().filter(|p| reached.contains(&p.kind)) {
+            let event = Event::new(EventType::Custom, "Word goal reached").with_payload(
+                serde_json::json!({ "goal": progress.kind, "target": progress.target }),
+            );
+            self.plugin_context.emit_event(event);
+            if self.config.goals.celebrate {
+                self.reached_goals.push(progress);
+            }
+        }
+    }
 
-        let project_manager = Arc::clone(&self.core_app.project_manager());
-        let project_path_clone = project_path.clone();
-        let path_buf = project_path.clone();
+    /// Keep the writing sprints that ended in the writing history.
+    fn handle_sprint_record_request(&mut self) {
+        let Some(record) = self
+            .plugin_context
+            .get_shared_state::<Option<SprintRecord>>(SPRINT_RECORD_REQUEST)
+            .flatten()
+        else {
+            return;
+        };
+        self.plugin_context
+            .set_shared_state(SPRINT_RECORD_REQUEST, None::<SprintRecord>);
+        self.log_sprint(record);
+    }
 
-        let rt = tokio::runtime::Runtime::new()
-            .map_err(|e| anyhow::anyhow!("Failed to create Tokio runtime: {}", e))?;
-        rt.block_on(async move {
-            let mut pm = project_manager.write().await;
-            pm.create_project(&name, &path_buf, &template).await
-        })?;
+    /// Log `record` as written in the open project.
+    fn log_sprint(&mut self, mut record: SprintRecord) {
+        record.project = self.current_project.clone();
+        let event = Event::new(EventType::Custom, "Writing sprint finished")
+            .with_payload(serde_json::json!({ "minutes": record.minutes, "words": record.words }));
+        self.plugin_context.emit_event(event);
+        self.session.writing_log.record_sprint(record);
+        self.publish_writing_history();
+    }
 
-        self.current_project = Some(project_path.clone());
-        self.plugin_context.set_project_path(Some(project_path.clone()));
+    /// The writing sprint under way, if any.
+    fn sprint(&self) -> Option<Sprint> {
+        self.plugin_context
+            .get_shared_state::<Option<Sprint>>(SPRINT_KEY)
+            .flatten()
+    }
 
-        // Update recent projects list
-        let rt2 = tokio::runtime::Runtime::new()
-            .map_err(|e| anyhow::anyhow!("Failed to create Tokio runtime: {}", e))?;
-        let project_manager = Arc::clone(&self.core_app.project_manager());
-        let recent = rt2.block_on(async {
-            let pm = project_manager.read().await;
-            pm.recent_projects().to_vec()
-        });
-        self.recent_projects = recent;
+    /// Whether the editor is in focus mode, with only its text in sight.
+    fn focus_mode(&self) -> bool {
+        self.plugin_context
+            .get_shared_state::<bool>(FOCUS_MODE_KEY)
+            .unwrap_or(false)
+    }
 
-        // Get current Git branch
-        self.current_branch = self.get_current_branch();
+    /// Ask the editor to enter or leave focus mode.
+    fn toggle_focus_mode(&mut self) {
+        commands::send(&mut self.plugin_context, EditorCommand::ToggleFocusMode);
+    }
 
-        // Update session
-        self.session
-            .add_recent_project(project_path.clone(), self.config.app.max_recent_projects);
-        self.session.last_opened_project = Some(project_path);
-        if let Err(e) = self.session.save() {
-            tracing::warn!("Failed to save session: {}", e);
+    /// Shortcut of every command in the settings dialog, with buttons to
+    /// record a new one, leave the command unbound or bind its default
+    /// again.
+    fn render_keymap_settings(&mut self, ui: &mut egui::Ui) {
+        // The next key pressed with its modifiers becomes the shortcut
+        if let Some(id) = self.recording_shortcut.clone() {
+            let pressed = ui.input(|input| {
+                input.events.iter().find_map(|event| match event {
+                    egui::Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => Some((*key, *modifiers)),
+                    _ => None,
+                })
+            });
+            match pressed {
+                Some((egui::Key::Escape, modifiers)) if modifiers.is_none() => {
+                    self.recording_shortcut = None;
+                }
+                Some((key, modifiers)) => {
+                    let shortcut = Shortcut {
+                        ctrl: modifiers.command || modifiers.ctrl,
+                        shift: modifiers.shift,
+                        alt: modifiers.alt,
+                        key: key.name().to_string(),
+                    };
+                    if let Some(command) = self.plugin_context.command(&id) {
+                        self.keymap.bind(command, Some(shortcut));
+                    }
+                    self.recording_shortcut = None;
+                }
+                None => {}
+            }
         }
-        self.recent_projects = self.session.recent_projects.clone();
 
-        // Request focus for the editor
-        self.plugin_context
-            .set_shared_state("markdown_editor_focus_requested", true);
+        let conflicts = self.keymap.conflicts(self.plugin_context.commands());
+        let commands = self.plugin_context.commands().to_vec();
+        egui::ScrollArea::vertical()
+            .id_salt("keymap_commands")
+            .max_height(240.0)
+            .show(ui, |ui| {
+                egui::Grid::new("keymap")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for command in &commands {
+                            let shortcut = self.keymap.shortcut(command);
+                            let clashes = shortcut
+                                .as_ref()
+                                .is_some_and(|s| conflicts.iter().any(|(c, _)| c == s));
+                            if clashes {
+                                ui.colored_label(ui.visuals().warn_fg_color, &command.title);
+                            } else {
+                                ui.label(&command.title);
+                            }
 
-        Ok(())
+                            let recording =
+                                self.recording_shortcut.as_deref() == Some(command.id.as_str());
+                            let text = if recording {
+                                "Press a shortcut…".to_string()
+                            } else {
+                                shortcut
+                                    .as_ref()
+                                    .map(ToString::to_string)
+                                    .unwrap_or_else(|| "None".to_string())
+                            };
+                            if ui
+                                .selectable_label(recording, text)
+                                .on_hover_text(
+                                    "Click, then press the new shortcut (Escape cancels)",
+                                )
+                                .clicked()
+                            {
+                                self.recording_shortcut = (!recording).then(|| command.id.clone());
+                            }
+                            if ui
+                                .add_enabled(shortcut.is_some(), egui::Button::new("Clear"))
+                                .clicked()
+                            {
+                                self.keymap.bind(command, None);
+                            }
+                            if ui
+                                .add_enabled(
+                                    self.keymap.is_rebound(&command.id),
+                                    egui::Button::new("Reset"),
+                                )
+                                .on_hover_text(match &command.default_shortcut {
+                                    Some(shortcut) => format!("Default: {}", shortcut),
+                                    None => "Unbound by default".to_string(),
+                                })
+                                .clicked()
+                            {
+                                self.keymap.reset(&command.id);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        for (shortcut, ids) in &conflicts {
+            let titles: Vec<&str> = ids
+                .iter()
+                .filter_map(|id| self.plugin_context.command(id))
+                .map(|command| command.title.as_str())
+                .collect();
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!(
+                    "{} is bound to {}: only the first runs",
+                    shortcut,
+                    titles.join(", ")
+                ),
+            );
+        }
     }
 
-    /// Render the main menu bar.
-    /// Render the menu bar (Zed-style: compact or expanded).
-    fn render_menu_bar(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if self.ui_state.menu_expanded {
-            self.render_expanded_menu_bar(ctx);
-        } else {
-            self.render_compact_menu_bar(ctx);
-        }
+    /// Shortcut of the command `id`, written next to its menu item.
+    fn shortcut_text(&self, id: &str) -> egui::RichText {
+        let shortcut = self
+            .plugin_context
+            .shortcut(id)
+            .map(|shortcut| shortcut.to_string())
+            .unwrap_or_default();
+        egui::RichText::new(shortcut).size(12.0).weak()
     }
 
-    /// Render the compact menu bar (burger + project + branch).
-    fn render_compact_menu_bar(&mut self, ctx: &egui::Context) {
-        egui::TopBottomPanel::top("compact_menu_bar").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                // Configure visuals for "frameless feel but visible hover"
-                let mut visuals = ui.visuals().clone();
-                visuals.widgets.inactive.bg_fill = egui::Color32::TRANSPARENT;
-                visuals.widgets.inactive.weak_bg_fill = egui::Color32::TRANSPARENT;
-                visuals.widgets.inactive.bg_stroke = egui::Stroke::NONE;
-
-                visuals.widgets.hovered.bg_stroke = egui::Stroke::NONE;
-                visuals.widgets.active.bg_stroke = egui::Stroke::NONE;
-                visuals.widgets.open.bg_stroke = egui::Stroke::NONE;
-
-                ui.ctx().set_visuals(visuals);
+    /// Run the command whose shortcut was pressed during this frame, if any.
+    fn run_pressed_command(&mut self, ctx: &egui::Context) {
+        // Keys pressed to rebind a command do not run it
+        if self.recording_shortcut.is_some() {
+            return;
+        }
+        let keymap = self.plugin_context.keymap();
+        let pressed = ctx.input(|input| {
+            self.plugin_context
+                .commands()
+                .iter()
+                .find(|command| {
+                    keymap
+                        .shortcut(command)
+                        .is_some_and(|shortcut| shortcut.pressed(input))
+                })
+                .map(|command| command.id.clone())
+        });
+        if let Some(id) = pressed {
+            self.plugin_context.run_command(&id);
+        }
+    }
 
-                // Burger menu button
-                if ui.button("☰").clicked() {
-                    self.ui_state.menu_expanded = true;
-                    self.ui_state.active_menu = Some(MenuId::Cosmarium);
+    /// Run the application command asked for by its handler.
+    fn handle_app_command_request(&mut self, ctx: &egui::Context) {
+        let Some(id) = self
+            .plugin_context
+            .get_shared_state::<Option<String>>(APP_COMMAND_REQUEST)
+            .flatten()
+        else {
+            return;
+        };
+        self.plugin_context
+            .set_shared_state::<Option<String>>(APP_COMMAND_REQUEST, None);
+        let Some(command) = AppCommand::from_id(&id) else {
+            tracing::warn!("Unknown application command {}", id);
+            return;
+        };
+        match command {
+            AppCommand::NewProject => self.open_new_project_wizard(),
+            AppCommand::OpenProject => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_title("Open Project")
+                    .pick_folder()
+                {
+                    if let Err(e) = self.open_project_async(path) {
+                        tracing::error!("Failed to open project: {}", e);
+                    }
+                }
+            }
+            AppCommand::SaveProject => {
+                if let Err(e) = self.save_current_project() {
+                    tracing::error!("Failed to save project: {}", e);
                 }
+            }
+            AppCommand::Quit => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+            AppCommand::Back => self.navigate_back(),
+            AppCommand::Forward => self.navigate_forward(),
+            AppCommand::Activity(activity) => self.switch_activity(activity),
+            AppCommand::QuickOpen => self.open_quick_open(),
+            AppCommand::CommandPalette => self.command_palette = Some(CommandPalette::default()),
+        }
+    }
 
-                // Project name button (now a submenu)
-                let project_name = self
-                    .current_project
-                    .as_ref()
-                    .and_then(|p| p.file_name())
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("No Project")
-                    .to_string();
+    /// Whether a sprint keeps `panel` out of sight: all panels but the
+    /// sprint's own hide during a sprint in focus.
+    fn hidden_by_sprint(&self, panel: &str) -> bool {
+        panel != cosmarium_sprint::PLUGIN_NAME && self.sprint().is_some_and(|sprint| sprint.focus)
+    }
 
-                ui.menu_button(project_name, |ui| {
-                    ui.set_min_width(200.0);
-                    ui.with_layout(egui::Layout::top_down_justified(egui::Align::Min), |ui| {
-                        if self.recent_projects.is_empty() {
-                            ui.label("No recent projects");
-                        } else {
-                            let mut path_to_open = None;
-                            for path in &self.recent_projects {
-                                let label = path
-                                    .file_name()
-                                    .and_then(|n| n.to_str())
-                                    .unwrap_or("Unknown Project");
+    /// Today's date in the local time zone, by the context's clock.
+    fn today(&self) -> chrono::NaiveDate {
+        self.plugin_context
+            .clock()
+            .now()
+            .with_timezone(&chrono::Local)
+            .date_naive()
+    }
 
-                                if ui.button(label).clicked() {
-                                    path_to_open = Some(path.clone());
-                                    ui.close_menu();
-                                }
-                            }
+    /// Show the diagnostics window, with the log settings of the
+    /// configuration.
+    fn open_diagnostics(&mut self) {
+        self.log_targets_text = self
+            .config
+            .advanced
+            .log_targets
+            .iter()
+            .map(|(target, level)| format!("{} = {}\n", target, level))
+            .collect();
+        self.log_filter_error = None;
+        self.show_diagnostics = true;
+    }
 
-                            if let Some(path) = path_to_open {
-                                if let Err(e) = self.open_project_async(path) {
-                                    tracing::error!("Failed to open recent project: {}", e);
-                                }
-                            }
-                        }
+    /// Compile filters, with their effect on the active document.
+    fn render_text_filters(&mut self, ui: &mut egui::Ui) {
+        let filters = &mut self.config.export.compile.filters;
+        let before = filters.clone();
 
-                        ui.separator();
+        ui.label("Text filters (applied when compiling):");
+        ui.indent("compile_text_filters", |ui| {
+            let mut removed = None;
+            for (index, alias) in filters.aliases.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut alias.from)
+                            .hint_text("Working name")
+                            .desired_width(120.0),
+                    );
+                    ui.label("→");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut alias.to)
+                            .hint_text("Final name")
+                            .desired_width(120.0),
+                    );
+                    if ui.small_button("✖").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = removed {
+                filters.aliases.remove(index);
+            }
+            if ui.button("Add name").clicked() {
+                filters.aliases.push(Alias::default());
+            }
 
-                        if ui.button("New").clicked() {
-                            ui.close_menu();
-                            self.show_new_project_dialog = true;
-                        }
+            let mut removed = None;
+            for (index, text) in filters.redactions.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(text)
+                            .hint_text("Word or phrase to redact")
+                            .desired_width(260.0),
+                    );
+                    if ui.small_button("✖").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = removed {
+                filters.redactions.remove(index);
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Add redaction").clicked() {
+                    filters.redactions.push(String::new());
+                }
+                ui.label("Replaced by:");
+                ui.add(egui::TextEdit::singleline(&mut filters.redaction_mark).desired_width(80.0));
+            });
 
-                        if ui.button("Open").clicked() {
-                            ui.close_menu();
-                            // Open project selector
-                            if let Some(path) = rfd::FileDialog::new()
-                                .set_title("Open Project")
-                                .pick_folder()
-                            {
-                                if let Err(e) = self.open_project_async(path) {
-                                    tracing::error!("Failed to open project: {}", e);
-                                }
-                            }
+            ui.horizontal(|ui| {
+                ui.label("Dashes:");
+                egui::ComboBox::from_id_salt("compile_dash_style")
+                    .selected_text(filters.dashes.display_name())
+                    .show_ui(ui, |ui| {
+                        for style in DashStyle::ALL {
+                            ui.selectable_value(&mut filters.dashes, style, style.display_name());
+                        }
+                    });
+            });
+            ui.horizontal(|ui| {
+                ui.label("Spelling:");
+                egui::ComboBox::from_id_salt("compile_spelling")
+                    .selected_text(filters.spelling.display_name())
+                    .show_ui(ui, |ui| {
+                        for conversion in SpellingConversion::ALL {
+                            ui.selectable_value(
+                                &mut filters.spelling,
+                                conversion,
+                                conversion.display_name(),
+                            );
                         }
                     });
-                });
             });
         });
+        if *filters != before {
+            self.filter_preview = None;
+        }
+
+        if ui
+            .add_enabled(
+                self.active_document_id.is_some(),
+                egui::Button::new("Preview on the active document"),
+            )
+            .clicked()
+        {
+            self.filter_preview = self.active_document_content().map(|content| {
+                self.config
+                    .export
+                    .compile
+                    .filters
+                    .preview(&content, FILTER_PREVIEW_LINES)
+            });
+        }
+        let Some(preview) = &self.filter_preview else {
+            return;
+        };
+        if preview.is_empty() {
+            ui.weak("The filters change nothing in the active document.");
+            return;
+        }
+        egui::ScrollArea::vertical()
+            .id_salt("compile_filter_preview")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for (line, filtered) in preview {
+                    ui.weak(line);
+                    ui.label(format!("→ {}", filtered));
+                    ui.add_space(4.0);
+                }
+            });
     }
 
-    /// Render the expanded menu bar (custom implementation).
-    fn render_expanded_menu_bar(&mut self, ctx: &egui::Context) {
-        egui::TopBottomPanel::top("expanded_menu_bar").show(ctx, |ui| {
-            // Horizontal menu bar
-            ui.horizontal(|ui| {
-                // Configure visuals for "frameless feel but visible hover"
-                let mut visuals = ui.visuals().clone();
-                visuals.widgets.inactive.bg_fill = egui::Color32::TRANSPARENT;
-                visuals.widgets.inactive.weak_bg_fill = egui::Color32::TRANSPARENT;
-                visuals.widgets.inactive.bg_stroke = egui::Stroke::NONE;
+    /// Text of the active document, as edited.
+    fn active_document_content(&self) -> Option<String> {
+        let doc_id = self.active_document_id?;
+        let document_manager = self.core_app.document_manager();
+        self.core_app.executor().block_on(async {
+            let dm = document_manager.read().await;
+            dm.get_document(doc_id).map(|doc| doc.content().to_string())
+        })
+    }
 
-                visuals.widgets.hovered.bg_stroke = egui::Stroke::NONE;
-                visuals.widgets.active.bg_stroke = egui::Stroke::NONE;
-                visuals.widgets.open.bg_stroke = egui::Stroke::NONE;
+    /// Log settings: the level of each target applies at once, the output
+    /// from the next start.
+    fn render_diagnostics(&mut self, ui: &mut egui::Ui) {
+        let Some(control) = logging::control() else {
+            ui.label("Logging is set up outside of Cosmarium.");
+            return;
+        };
+        let advanced = &mut self.config.advanced;
+
+        ui.label("Log Level");
+        ui.horizontal(|ui| {
+            for level in ["error", "warn", "info", "debug", "trace"] {
+                if ui
+                    .selectable_label(advanced.log_level == level, level)
+                    .clicked()
+                {
+                    advanced.log_level = level.to_string();
+                }
+            }
+        });
+        ui.label("Levels of particular targets, one per line:");
+        ui.add(
+            egui::TextEdit::multiline(&mut self.log_targets_text)
+                .desired_rows(4)
+                .hint_text("cosmarium_markdown_editor = debug"),
+        );
 
-                visuals.widgets.noninteractive.bg_stroke.width = 0.5;
-                visuals.window_stroke.width = 0.5;
+        ui.separator();
+        ui.checkbox(&mut advanced.log_to_file, "Write log files")
+            .on_hover_text("From the next start");
+        ui.checkbox(&mut advanced.log_json, "Write records as JSON lines")
+            .on_hover_text("From the next start");
+        match control.directory() {
+            Some(dir) => ui.weak(format!("Log files: {}", dir.display())),
+            None => ui.weak("No log files are written"),
+        };
+        ui.weak(format!("Current filter: {}", control.directives()));
 
-                ui.ctx().set_visuals(visuals);
+        if let Some(error) = &self.log_filter_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        if ui.button("Apply").clicked() {
+            let applied = parse_log_targets(&self.log_targets_text).and_then(|targets| {
+                let directives = filter_directives(&advanced.log_level, &targets);
+                control.set_directives(&directives)?;
+                advanced.log_targets = targets;
+                Ok(())
+            });
+            self.log_filter_error = applied.err().map(|e| e.to_string());
+            if self.log_filter_error.is_none() {
+                if let Err(e) = self.config.save() {
+                    tracing::error!("Failed to save settings: {}", e);
+                }
+            }
+        }
+    }
 
-                // Helper closure to render a menu item
-                let mut render_menu_item =
-                    |ui: &mut egui::Ui,
-                     id: MenuId,
-                     label: &str,
-                     content: Box<dyn FnOnce(&mut Self, &mut egui::Ui)>| {
-                        let button_response = ui.button(label);
+    /// Read the active project's quote style and publish it to plugins.
+    fn load_quote_style(&mut self) {
+        self.quote_style = self.project_setting(QUOTE_STYLE_KEY).unwrap_or_default();
+        self.plugin_context
+            .set_config(QUOTE_STYLE_KEY, self.quote_style);
+    }
 
-                        // Click logic: toggle menu
-                        if button_response.clicked() {
-                            if self.ui_state.active_menu == Some(id) {
-                                self.ui_state.active_menu = None;
-                                self.ui_state.menu_expanded = false;
-                            } else {
-                                self.ui_state.active_menu = Some(id);
-                            }
-                        }
+    /// Store the edited quote style in the active project's settings.
+    fn save_quote_style(&mut self) -> Result<()> {
+        self.set_project_setting(QUOTE_STYLE_KEY, &self.quote_style)?;
+        self.plugin_context
+            .set_config(QUOTE_STYLE_KEY, self.quote_style);
+        Ok(())
+    }
 
-                        // Hover logic: switch if another menu is open
-                        if self.ui_state.active_menu.is_some() && button_response.hovered() {
-                            self.ui_state.active_menu = Some(id);
-                        }
+    /// Read the active project's auto-correction and publish it to plugins.
+    fn load_autocorrect(&mut self) {
+        self.autocorrect = self.project_setting(AUTOCORRECT_KEY).unwrap_or_default();
+        self.plugin_context
+            .set_config(AUTOCORRECT_KEY, &self.autocorrect);
+    }
 
-                        // Popup rendering
-                        if self.ui_state.active_menu == Some(id) {
-                            let popup_id = ui.make_persistent_id(format!("{}_popup", label));
+    /// Store the edited auto-correction in the active project's settings.
+    fn save_autocorrect(&mut self) -> Result<()> {
+        let autocorrect = &mut self.autocorrect;
+        autocorrect
+            .corrections
+            .retain(|c| !c.typo.trim().is_empty() && !c.fix.trim().is_empty());
+        self.set_project_setting(AUTOCORRECT_KEY, &self.autocorrect)?;
+        self.plugin_context
+            .set_config(AUTOCORRECT_KEY, &self.autocorrect);
+        Ok(())
+    }
 
-                            // Sync with egui's internal state to ensure popup_below_widget renders
-                            if !ui.memory(|m| m.is_popup_open(popup_id)) {
-                                ui.memory_mut(|m| m.open_popup(popup_id));
-                            }
+    /// Read the active project's line ending, used by new documents and
+    /// text exports.
+    fn load_line_ending(&mut self) {
+        let project_manager = self.core_app.project_manager();
+        let document_manager = self.core_app.document_manager();
+        self.line_ending = self.core_app.executor().block_on(async {
+            let line_ending = project_manager
+                .read()
+                .await
+                .active_project()
+                .map(|p| p.settings().line_ending)
+                .unwrap_or_default();
+            document_manager
+                .write()
+                .await
+                .set_default_line_ending(line_ending);
+            line_ending
+        });
+    }
 
-                            egui::popup_below_widget(
-                                ui,
-                                popup_id,
-                                &button_response,
-                                egui::PopupCloseBehavior::CloseOnClickOutside,
-                                |ui| {
-                                    ui.set_min_width(150.0);
-                                    ui.with_layout(
-                                        egui::Layout::top_down_justified(egui::Align::Min),
-                                        |ui| {
-                                            content(self, ui);
-                                        },
-                                    );
-                                },
-                            );
-                        }
-                    };
+    /// Store the edited line ending in the active project's settings.
+    fn save_line_ending(&mut self) {
+        let line_ending = self.line_ending;
+        let project_manager = self.core_app.project_manager();
+        let document_manager = self.core_app.document_manager();
+        self.core_app.executor().block_on(async {
+            if let Some(project) = project_manager.write().await.active_project_mut() {
+                project.settings_mut().line_ending = line_ending;
+            }
+            document_manager
+                .write()
+                .await
+                .set_default_line_ending(line_ending);
+        });
+    }
 
-                // Cosmarium Menu
-                render_menu_item(
-                    ui,
-                    MenuId::Cosmarium,
-                    "Cosmarium",
-                    Box::new(|app, ui| {
-                        if ui.button("About").clicked() {
-                            app.show_about = true;
-                            app.ui_state.menu_expanded = false;
-                            app.ui_state.active_menu = None;
-                        }
-                        if ui.button("Settings").clicked() {
-                            app.show_settings = true;
-                            app.ui_state.menu_expanded = false;
-                            app.ui_state.active_menu = None;
-                        }
-                        ui.separator();
-                        if ui
-                            .add(
-                                egui::Button::new("Exit")
-                                    .shortcut_text(egui::RichText::new("Ctrl+Q").size(12.0).weak()),
-                            )
-                            .clicked()
-                        {
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                        }
-                    }),
-                );
+    /// Load whether saving the active project commits its changes.
+    fn load_auto_commit(&mut self) {
+        let project_manager = self.core_app.project_manager();
+        self.auto_commit = self.core_app.executor().block_on(async {
+            project_manager
+                .read()
+                .await
+                .active_project()
+                .is_none_or(|p| p.settings().auto_commit)
+        });
+    }
 
-                // File Menu
+    /// Store whether saving the active project commits its changes in its
+    /// settings.
+    fn save_auto_commit(&mut self) {
+        let auto_commit = self.auto_commit;
+        let project_manager = self.core_app.project_manager();
+        self.core_app.executor().block_on(async {
+            if let Some(project) = project_manager.write().await.active_project_mut() {
+                project.settings_mut().auto_commit = auto_commit;
+            }
+        });
+    }
+
+    /// Load the author profile and pen name of the active project.
+    fn load_published_as(&mut self) {
+        let project_manager = self.core_app.project_manager();
+        self.published_as = self.core_app.executor().block_on(async {
+            project_manager
+                .read()
+                .await
+                .active_project()
+                .map(|p| (p.metadata().profile.clone(), p.metadata().pen_name.clone()))
+                .unwrap_or_default()
+        });
+    }
+
+    /// Store the edited author profile and pen name in the active project's
+    /// metadata.
+    fn save_published_as(&mut self) {
+        let (profile, pen_name) = self.published_as.clone();
+        let project_manager = self.core_app.project_manager();
+        self.core_app.executor().block_on(async {
+            if let Some(project) = project_manager.write().await.active_project_mut() {
+                let metadata = project.metadata_mut();
+                metadata.profile = profile;
+                metadata.pen_name = pen_name;
+            }
+        });
+    }
+
+    /// Line ending and encoding of the active document.
+    fn active_document_format(&self) -> Option<(LineEnding, TextEncoding)> {
+        let doc_id = self.active_document_id?;
+        let document_manager = self.core_app.document_manager();
+        self.core_app.executor().block_on(async {
+            let dm = document_manager.read().await;
+            dm.get_document(doc_id)
+                .map(|doc| (doc.line_ending(), doc.encoding()))
+        })
+    }
+
+    /// Convert the active document to `line_ending` and `encoding` the next
+    /// time it is saved.
+    fn convert_active_document(&mut self, line_ending: LineEnding, encoding: TextEncoding) {
+        let Some(doc_id) = self.active_document_id else {
+            return;
+        };
+        let document_manager = self.core_app.document_manager();
+        self.core_app.executor().block_on(async {
+            let mut dm = document_manager.write().await;
+            if let Some(doc) = dm.get_document_mut(doc_id) {
+                doc.set_line_ending(line_ending);
+                doc.set_encoding(encoding);
+            }
+        });
+    }
+
+    /// Publish the active project's document order to plugins.
+    fn load_document_order(&mut self) {
+        let project_manager = self.core_app.project_manager();
+        let order = self.core_app.executor().block_on(async {
+            let mut pm = project_manager.write().await;
+            pm.active_project_mut()
+                .map(|p| {
+                    p.sync_structure();
+                    p.document_order()
+                })
+                .unwrap_or_default()
+        });
+        self.plugin_context.set_config(DOCUMENT_ORDER_KEY, &order);
+    }
+
+    /// Serve document reordering requests posted by the binder.
+    fn handle_document_order_request(&mut self) {
+        let request = self
+            .plugin_context
+            .get_shared_state::<Option<Vec<String>>>(DOCUMENT_ORDER_REQUEST)
+            .flatten();
+
+        if let Some(order) = request {
+            self.plugin_context
+                .set_shared_state::<Option<Vec<String>>>(DOCUMENT_ORDER_REQUEST, None);
+            let project_manager = self.core_app.project_manager();
+            self.core_app.executor().block_on(async {
+                let mut pm = project_manager.write().await;
+                if let Some(project) = pm.active_project_mut() {
+                    project.sync_structure();
+                    project.set_document_order(order);
+                }
+            });
+            self.load_document_order();
+        }
+    }
+
+    /// Read the active project's dictionary and publish it to plugins.
+    fn load_project_dictionary(&mut self) {
+        self.project_dictionary = self
+            .project_setting(PROJECT_DICTIONARY_KEY)
+            .unwrap_or_default();
+        self.dictionary_suffixes = self.project_dictionary.morphology.suffixes.join(" ");
+        self.plugin_context
+            .set_config(PROJECT_DICTIONARY_KEY, &self.project_dictionary);
+    }
+
+    /// Store the project dictionary in the active project's settings.
+    fn save_project_dictionary(&mut self) -> Result<()> {
+        self.set_project_setting(PROJECT_DICTIONARY_KEY, &self.project_dictionary)?;
+        self.plugin_context
+            .set_config(PROJECT_DICTIONARY_KEY, &self.project_dictionary);
+        Ok(())
+    }
+
+    /// Serve "add to project dictionary" requests posted by plugins.
+    fn handle_add_to_dictionary_request(&mut self) {
+        let request = self
+            .plugin_context
+            .get_shared_state::<Option<String>>(ADD_TO_DICTIONARY_REQUEST)
+            .flatten();
+
+        if let Some(word) = request {
+            self.plugin_context
+                .set_shared_state::<Option<String>>(ADD_TO_DICTIONARY_REQUEST, None);
+            if self.current_project.is_none() {
+                tracing::warn!("Open a project before adding words to its dictionary");
+                return;
+            }
+            if self.project_dictionary.add(&word) {
+                tracing::info!("Added '{}' to the project dictionary", word);
+                if let Err(e) = self.save_project_dictionary() {
+                    tracing::error!("Failed to save the project dictionary: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Load a project from the specified path.
+    fn load_project(&mut self, path: &std::path::Path) -> Result<()> {
+        tracing::info!("Loading project from {:?}", path);
+
+        // TODO: Implement actual project loading
+        self.current_project = Some(path.to_path_buf());
+
+        let event = Event::new(
+            EventType::ProjectOpened,
+            format!("Opened project: {}", path.display()),
+        );
+        self.plugin_context.emit_event(event);
+
+        Ok(())
+    }
+
+    /// Synchronize edits from the editor tabs to the document manager and
+    /// follow the editor's active tab.
+    fn sync_editor_content(&mut self) {
+        let updates: Vec<DocumentBuffer> =
+            editor_documents::drain(&mut self.plugin_context, DOCUMENT_UPDATES);
+        if !updates.is_empty() {
+            let document_manager = self.core_app.document_manager();
+            // Block on the write lock so the sync is deterministic
+            let edited: Vec<Option<std::path::PathBuf>> =
+                self.core_app.executor().block_on(async {
+                    let mut manager = document_manager.write().await;
+                    let mut edited = Vec::new();
+                    for update in updates {
+                        match manager.get_document_mut(update.id) {
+                            Some(doc) if doc.content() != update.content => {
+                                doc.set_content(&update.content);
+                                edited.push(update.path);
+                            }
+                            Some(_) => {}
+                            None => tracing::warn!("Edited document {} is not open", update.id),
+                        }
+                    }
+                    edited
+                });
+
+            // Edits are made at the caret of the active tab
+            if let Some((path, line)) = self.cursor_location() {
+                if edited.iter().any(|p| p.as_deref() == Some(path.as_path())) {
+                    self.navigation.record_edit(Location::new(path, line));
+                }
+            }
+        }
+
+        let active = self
+            .plugin_context
+            .get_shared_state::<Option<uuid::Uuid>>(ACTIVE_DOCUMENT_KEY)
+            .flatten();
+        if active.is_some() && active != self.active_document_id {
+            self.active_document_id = active;
+            self.publish_active_document_path();
+        }
+    }
+
+    /// Serve the save and close requests of the editor tabs.
+    fn handle_editor_document_requests(&mut self) {
+        let saves: Vec<uuid::Uuid> =
+            editor_documents::drain(&mut self.plugin_context, SAVE_DOCUMENTS_REQUEST);
+        let closes: Vec<uuid::Uuid> =
+            editor_documents::drain(&mut self.plugin_context, CLOSE_DOCUMENTS_REQUEST);
+        if saves.is_empty() && closes.is_empty() {
+            return;
+        }
+
+        // Apply the last edits first
+        self.sync_editor_content();
+        self.hard_wrap_documents(Some(&saves));
+
+        let document_manager = self.core_app.document_manager();
+        self.core_app.executor().block_on(async {
+            let mut dm = document_manager.write().await;
+            for id in saves {
+                if dm.get_document(id).is_some_and(|d| d.file_path().is_none()) {
+                    tracing::warn!("Document {} has no file yet; save the project instead", id);
+                } else if let Err(e) = dm.save_document(id).await {
+                    tracing::error!("Failed to save document {}: {}", id, e);
+                }
+            }
+            for id in &closes {
+                let saveable = dm
+                    .get_document(*id)
+                    .is_some_and(|d| d.file_path().is_some());
+                if let Err(e) = dm.close_document(*id, saveable).await {
+                    tracing::error!("Failed to close document {}: {}", id, e);
+                }
+            }
+        });
+
+        if self
+            .active_document_id
+            .is_some_and(|id| closes.contains(&id))
+        {
+            self.active_document_id = None;
+            self.publish_active_document_path();
+            self.sync_editor_content();
+        }
+    }
+
+    /// Break the long lines of the unsaved Markdown documents about to be
+    /// saved (all of them if `only` is `None`), when hard wrapping is on, and
+    /// show the result in their tabs.
+    fn hard_wrap_documents(&mut self, only: Option<&[uuid::Uuid]>) {
+        let column = self.config.editor.word_wrap_column;
+        if !self.config.editor.hard_wrap_on_save || column == 0 {
+            return;
+        }
+        let document_manager = self.core_app.document_manager();
+        let wrapped: Vec<DocumentBuffer> = self.core_app.executor().block_on(async {
+            let mut manager = document_manager.write().await;
+            let mut wrapped = Vec::new();
+            for id in manager.list_documents() {
+                if only.is_some_and(|only| !only.contains(&id)) {
+                    continue;
+                }
+                let Some(doc) = manager.get_document_mut(id) else {
+                    continue;
+                };
+                if !doc.has_unsaved_changes()
+                    || doc.format() != cosmarium_core::document::DocumentFormat::Markdown
+                {
+                    continue;
+                }
+                let content = wrap::hard_wrap(doc.content(), column);
+                if content != doc.content() {
+                    doc.set_content(&content);
+                    wrapped.push(DocumentBuffer {
+                        id,
+                        title: doc.title().to_string(),
+                        path: doc.file_path().map(|p| p.to_path_buf()),
+                        content,
+                    });
+                }
+            }
+            wrapped
+        });
+        for buffer in wrapped {
+            editor_documents::push(&mut self.plugin_context, REPLACE_DOCUMENTS_REQUEST, buffer);
+        }
+    }
+
+    /// Show a document of the document manager in an editor tab.
+    fn show_document_in_editor(&mut self, doc_id: uuid::Uuid) {
+        let document_manager = self.core_app.document_manager();
+        let buffer = self.core_app.executor().block_on(async {
+            let dm = document_manager.read().await;
+            dm.get_document(doc_id).map(|doc| DocumentBuffer {
+                id: doc_id,
+                title: doc.title().to_string(),
+                path: doc.file_path().map(|p| p.to_path_buf()),
+                content: doc.content().to_string(),
+            })
+        });
+
+        match buffer {
+            Some(buffer) => {
+                self.active_document_id = Some(doc_id);
+                editor_documents::push(&mut self.plugin_context, OPEN_DOCUMENTS_REQUEST, buffer);
+                self.publish_active_document_path();
+            }
+            None => tracing::warn!("Document {} is not open", doc_id),
+        }
+    }
+
+    /// Check if there are any unsaved changes in the project
+    fn check_unsaved_changes(&self) -> bool {
+        // Block on the read locks so the answer is deterministic
+        let executor = self.core_app.executor();
+
+        let project_manager = self.core_app.project_manager();
+        if executor.block_on(async {
+            let manager = project_manager.read().await;
+            if let Some(project) = manager.active_project() {
+                return project.has_unsaved_changes();
+            }
+            false
+        }) {
+            return true;
+        }
+
+        let document_manager = self.core_app.document_manager();
+        let any_unsaved = executor.block_on(async {
+            let manager = document_manager.read().await;
+            for doc_id in manager.list_documents() {
+                if let Some(doc) = manager.get_document(doc_id) {
+                    if doc.has_unsaved_changes() {
+                        return true;
+                    }
+                }
+            }
+            false
+        });
+
+        any_unsaved
+    }
+
+    /// Save the current project.
+    fn save_current_project(&mut self) -> Result<()> {
+        self.save_and_commit_current_project(None)
+    }
+
+    /// Save the current project, then commit its changes with `message`,
+    /// or with a summary of them if it commits when saved.
+    ///
+    /// The commit runs in the background, picked up by
+    /// [`Self::poll_background_tasks`].
+    fn save_and_commit_current_project(&mut self, message: Option<&str>) -> Result<()> {
+        // Sync editor content first
+        self.sync_editor_content();
+        self.hard_wrap_documents(None);
+
+        // Capture editor content (if any) before entering async block
+        let editor_content = self
+            .plugin_context
+            .get_shared_state::<String>("markdown_editor_content");
+
+        let project_manager = self.core_app.project_manager();
+        let document_manager = self.core_app.document_manager();
+        let had_active_document = self.active_document_id.is_some();
+
+        // Save active document (if any) first, then save project metadata
+        let result = self.core_app.executor().block_on(async {
+            // Determine project path if available
+            let project_path_opt = {
+                let pm_read = project_manager.read().await;
+                pm_read.active_project().map(|p| p.path().to_path_buf()).or_else(|| self.current_project.clone())
+            };
+
+            tracing::debug!("save_current_project: project_path={:?}, active_document_id={:?}, editor_content_present={}",
+                project_path_opt,
+                self.active_document_id,
+                editor_content.is_some());
+
+            if let Some(doc_id) = self.active_document_id {
+                // Acquire document manager write lock to modify document and set file path if missing
+                let mut dm = document_manager.write().await;
+                if let Some(doc) = dm.get_document_mut(doc_id) {
+                    tracing::debug!("Found active document {} title='{}' file_path={:?}", doc_id, doc.title(), doc.file_path());
+
+                    if doc.file_path().is_none() {
+                        if let Some(proj_path) = &project_path_opt {
+                            // Ensure content dir exists
+                            let content_dir = proj_path.join("content");
+                            if let Err(e) = tokio::fs::create_dir_all(&content_dir).await {
+                                tracing::error!("Failed to create content dir: {}", e);
+                            } else {
+                                // Create filename from title or uuid fallback
+                                let safe_name = if !doc.title().is_empty() { doc.title().to_lowercase().replace(" ", "_") } else { format!("doc_{}", uuid::Uuid::new_v4()) };
+                                let filename = format!("{}.{}", safe_name, doc.format().extension());
+                                let file_path = content_dir.join(filename);
+                                tracing::debug!("Setting file path for doc {} -> {:?}", doc_id, file_path);
+                                doc.set_file_path(&file_path);
+                            }
+                        } else {
+                            tracing::warn!("No active project path; cannot set file path for document {}", doc_id);
+                        }
+                    }
+
+                    let file_path_opt = doc.file_path().map(|p| p.to_path_buf());
+
+                    if let Err(e) = dm.save_document(doc_id).await {
+                        tracing::error!("Failed to save active document {}: {}", doc_id, e);
+                    } else {
+                        tracing::info!("Saved active document {} to {:?}", doc_id, file_path_opt);
+                        // Check file metadata
+                        if let Some(ref path) = file_path_opt {
+                            match tokio::fs::metadata(path).await {
+                                Ok(meta) => tracing::debug!("Saved file metadata: exists={}, size={}", true, meta.len()),
+                                Err(e) => tracing::warn!("Saved file metadata not found for {:?}: {}", path, e),
+                            }
+                        }
+
+                        // Ensure project references this document id before saving project
+                        let mut pm = project_manager.write().await;
+                        if let Some(project) = pm.active_project_mut() {
+                            project.add_document(doc_id);
+                        }
+                        // pm.save_project() will be called after this async block
+                    }
+                } else {
+                    tracing::warn!("Active document id {} not found in DocumentManager", doc_id);
+                }
+            } else if let Some(content) = editor_content {
+                tracing::debug!("No active document; creating new document from editor content (len={})", content.len());
+                // No active document: create one from editor content and save it
+                let mut dm = document_manager.write().await;
+                // Choose a title based on project or fallback
+                let title = if let Some(proj_path) = &project_path_opt {
+                    proj_path.file_name().and_then(|n| n.to_str()).unwrap_or("untitled").to_string()
+                } else {
+                    "untitled".to_string()
+                };
+
+                // Create document (Markdown assumed)
+                match dm.create_document(&title, &content, cosmarium_core::document::DocumentFormat::Markdown).await {
+                    Ok(new_id) => {
+                        tracing::debug!("Created new document {} with title='{}'", new_id, title);
+                        // Set file path if project exists
+                        if let Some(proj_path) = &project_path_opt {
+                            let content_dir = proj_path.join("content");
+                            if let Err(e) = tokio::fs::create_dir_all(&content_dir).await {
+                                tracing::error!("Failed to create content dir: {}", e);
+                            } else {
+                                let filename = format!("{}.{}", format!("doc_{}", new_id), cosmarium_core::document::DocumentFormat::Markdown.extension());
+                                let file_path = content_dir.join(filename);
+                                tracing::debug!("Setting file path for new doc {} -> {:?}", new_id, file_path);
+                                if let Some(doc_mut) = dm.get_document_mut(new_id) {
+                                    doc_mut.set_file_path(&file_path);
+                                }
+                            }
+                        } else {
+                            tracing::warn!("No active project path; new document {} will have no file path", new_id);
+                        }
+
+                        if let Err(e) = dm.save_document(new_id).await {
+                            tracing::error!("Failed to save new document {}: {}", new_id, e);
+                        } else {
+                            tracing::info!("Saved new document {}", new_id);
+                            // Update active document id so UI reflects saved doc
+                            self.active_document_id = Some(new_id);
+                            self.plugin_context.set_shared_state(
+                                "active_document_path",
+                                dm.get_document(new_id).and_then(|d| d.file_path().map(|p| p.to_path_buf())),
+                            );
+
+                            // Ensure project references this document id before saving project
+                            let mut pm = project_manager.write().await;
+                            if let Some(project) = pm.active_project_mut() {
+                                project.add_document(new_id);
+                            }
+                        }
+                    }
+                    Err(e) => tracing::error!("Failed to create document from editor content: {}", e),
+                }
+            } else {
+                tracing::debug!("No active document and no editor content to save");
+            }
+
+            // Save the other documents edited in tabs
+            let mut dm = document_manager.write().await;
+            let modified: Vec<uuid::Uuid> = dm
+                .list_documents()
+                .into_iter()
+                .filter(|id| {
+                    dm.get_document(*id)
+                        .is_some_and(|d| d.has_unsaved_changes() && d.file_path().is_some())
+                })
+                .collect();
+            for doc_id in modified {
+                if let Err(e) = dm.save_document(doc_id).await {
+                    tracing::error!("Failed to save document {}: {}", doc_id, e);
+                }
+            }
+            drop(dm);
+
+            // Finally, save project metadata
+            let mut pm = project_manager.write().await;
+            pm.save_project_for_commit(message).await
+        });
+
+        // A document created from the editor content gets its own tab
+        if !had_active_document {
+            if let Some(doc_id) = self.active_document_id {
+                self.show_document_in_editor(doc_id);
+            }
+        }
+        self.publish_project_changes();
+        self.publish_branches();
+        let Some(commit) = result? else {
+            return Ok(());
+        };
+
+        // One commit at a time
+        self.wait_for_commit();
+        let name = format!(
+            "Commit {}",
+            self.current_project
+                .as_deref()
+                .and_then(std::path::Path::file_name)
+                .unwrap_or_default()
+                .to_string_lossy()
+        );
+        let task = self
+            .core_app
+            .task_manager()
+            .spawn_task(name, move |progress| {
+                progress.set_message("Committing the changes");
+                Ok(commit.run()?)
+            });
+        self.commit_task = Some(task);
+        Ok(())
+    }
+
+    /// Report the outcome of the commit made in the background, and
+    /// publish the history and changes of the project it moved on.
+    fn finish_commit(&mut self, result: cosmarium_plugin_api::Result<Option<String>>) {
+        match result {
+            Ok(Some(id)) => {
+                tracing::info!("Committed {}", short_id(&id));
+                self.publish_document_commits();
+            }
+            Ok(None) => tracing::info!("Nothing to commit"),
+            Err(e) => tracing::error!("Failed to commit the project: {}", e),
+        }
+        self.publish_project_changes();
+        self.publish_branches();
+    }
+
+    /// Wait for the commit running in the background, if any, before Git
+    /// or the files of the project are read or changed under it.
+    fn wait_for_commit(&mut self) {
+        if let Some(task) = self.commit_task.take() {
+            self.finish_commit(task.wait());
+        }
+    }
+
+    /// Open the project at `path`, read in the background.
+    ///
+    /// It replaces the active project once read, when
+    /// [`Self::poll_background_tasks`] picks it up.
+    fn open_project_async(&mut self, path: std::path::PathBuf) -> Result<()> {
+        tracing::info!("Opening project from {:?}", path);
+        if !path.exists() {
+            return Err(cosmarium_core::Error::project(format!(
+                "Project path does not exist: {:?}",
+                path
+            )));
+        }
+
+        let name = format!(
+            "Open {}",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        let handle = self.core_app.executor().handle();
+        let task = self
+            .core_app
+            .task_manager()
+            .spawn_task(name, move |progress| {
+                progress.set_message("Reading the project");
+                let project = handle.block_on(Project::load(&path))?;
+                let document = first_content_file(&path.join("content"));
+                Ok((project, document))
+            });
+        self.open_task = Some(task);
+        Ok(())
+    }
+
+    /// Make `project`, read in the background, the active project, and
+    /// show `document`, the first file of its content folder, unless one
+    /// of its documents is already shown.
+    fn finish_opening_project(
+        &mut self,
+        project: Project,
+        document: Option<std::path::PathBuf>,
+    ) -> Result<()> {
+        let path = project.path().to_path_buf();
+        let project_manager = self.core_app.project_manager();
+        let document_manager = self.core_app.document_manager();
+        let executor = self.core_app.executor();
+
+        // The project left is saved here, committing in the background,
+        // rather than when it is replaced
+        let unsaved = executor.block_on(async {
+            let pm = project_manager.read().await;
+            pm.active_project()
+                .is_some_and(|project| project.has_unsaved_changes())
+        });
+        if unsaved {
+            self.save_current_project()?;
+        }
+        self.update_second_copy(true, false);
+        // The project left may have changed since it was indexed
+        self.global_index = None;
+        self.quick_index = None;
+
+        self.load_diagnostics = project.load_diagnostics().to_vec();
+        self.migration_report = project.migration_report().cloned();
+        executor.block_on(async {
+            let mut pm = project_manager.write().await;
+            pm.activate_project(project).await
+        })?;
+
+        self.current_project = Some(path.clone());
+        self.plugin_context.set_project_path(Some(path.clone()));
+
+        // A document of the project may already be shown, opened from a
+        // search result while the project was read
+        let shown = self
+            .plugin_context
+            .get_shared_state::<Option<std::path::PathBuf>>("active_document_path")
+            .flatten()
+            .is_some_and(|shown| shown.starts_with(&path));
+        if !shown {
+            let doc_id_opt = document.and_then(|document| {
+                executor.block_on(async {
+                    let mut dm = document_manager.write().await;
+                    dm.open_document(&document)
+                        .await
+                        .map_err(|e| {
+                            tracing::error!(
+                                "Failed to open document from path {:?}: {}",
+                                document,
+                                e
+                            )
+                        })
+                        .ok()
+                })
+            });
+
+            // Show the document in an editor tab if we got any
+            match doc_id_opt {
+                Some(doc_id) => self.show_document_in_editor(doc_id),
+                None => {
+                    self.active_document_id = None;
+                    self.publish_active_document_path();
+                }
+            }
+        }
+
+        // Update recent projects list
+        let recent = executor.block_on(async {
+            let pm = project_manager.read().await;
+            pm.recent_projects().to_vec()
+        });
+        self.recent_projects = recent;
+
+        // Get current Git branch
+        self.refresh_current_branch();
+        self.publish_project_changes();
+        self.publish_branches();
+        self.load_word_count_rules();
+        self.load_project_dictionary();
+        self.load_scene_heading_format();
+        self.load_project_word_target();
+        self.load_line_ending();
+        self.load_auto_commit();
+        self.load_published_as();
+        self.load_quote_style();
+        self.load_autocorrect();
+        self.load_folder_watches();
+        self.load_document_order();
+        self.update_second_copy(false, false);
+
+        // Update session
+        self.session
+            .add_recent_project(path.clone(), self.config.app.max_recent_projects);
+        self.session.last_opened_project = Some(path);
+        if let Err(e) = self.session.save() {
+            tracing::warn!("Failed to save session: {}", e);
+        }
+
+        // Update UI list
+        self.recent_projects = self.session.recent_projects.clone();
+
+        // Request focus for the editor
+        self.plugin_context
+            .set_shared_state("markdown_editor_focus_requested", true);
+
+        Ok(())
+    }
+
+    /// Publish the file path of the active document for plugins.
+    fn publish_active_document_path(&mut self) {
+        let document_manager = self.core_app.document_manager();
+        let executor = self.core_app.executor();
+        let path = self.active_document_id.and_then(|doc_id| {
+            executor.block_on(async {
+                let dm = document_manager.read().await;
+                dm.get_document(doc_id)
+                    .and_then(|doc| doc.file_path().map(|p| p.to_path_buf()))
+            })
+        });
+        self.plugin_context
+            .set_shared_state("active_document_path", path);
+        self.publish_active_metadata();
+        self.publish_document_snapshots();
+        self.publish_document_commits();
+    }
+
+    /// The active document's project and file, if it is a file of the
+    /// active project.
+    fn active_project_file(&self) -> Option<(std::path::PathBuf, std::path::PathBuf)> {
+        let doc_id = self.active_document_id?;
+        let document_manager = self.core_app.document_manager();
+        let project_manager = self.core_app.project_manager();
+        self.core_app.executor().block_on(async {
+            let dm = document_manager.read().await;
+            let pm = project_manager.read().await;
+            let project = pm.active_project()?.path().to_path_buf();
+            let file = dm.get_document(doc_id)?.file_path()?.to_path_buf();
+            file.starts_with(&project).then_some((project, file))
+        })
+    }
+
+    /// Publish the snapshots of the active document for plugins.
+    fn publish_document_snapshots(&mut self) {
+        let snapshots = self.active_project_file().map(|(project, file)| {
+            let snapshots = load_snapshots(&project, &file).unwrap_or_else(|e| {
+                tracing::warn!("Cannot read the snapshots of {:?}: {}", file, e);
+                Vec::new()
+            });
+            Arc::new(DocumentSnapshots {
+                path: file,
+                snapshots,
+            })
+        });
+        self.plugin_context
+            .set_shared_state(DOCUMENT_SNAPSHOTS_KEY, snapshots);
+    }
+
+    /// Publish the metadata of the active document's structure node for
+    /// plugins, keeping the document's copy in step; its front matter
+    /// overrides the values it sets.
+    fn publish_active_metadata(&mut self) {
+        let Some(doc_id) = self.active_document_id else {
+            self.plugin_context
+                .set_shared_state(ACTIVE_METADATA_KEY, None::<NodeMetadata>);
+            return;
+        };
+        let document_manager = self.core_app.document_manager();
+        let project_manager = self.core_app.project_manager();
+        let metadata = self.core_app.executor().block_on(async {
+            let mut dm = document_manager.write().await;
+            let pm = project_manager.read().await;
+            let doc = dm.get_document_mut(doc_id)?;
+            let node = pm.active_project()?.node_of_file(doc.file_path()?)?;
+            doc.set_scene_metadata(node.scene_metadata());
+            Some(NodeMetadata {
+                node: node.id,
+                title: node.title.clone(),
+                metadata: doc.metadata().scene.clone(),
+            })
+        });
+        self.plugin_context
+            .set_shared_state(ACTIVE_METADATA_KEY, metadata);
+    }
+
+    /// Open a project document in an editor tab, optionally jumping to a
+    /// line.
+    ///
+    /// A document already open keeps its tab, with its unsaved edits.
+    fn open_document(&mut self, path: &std::path::Path, line: Option<usize>) -> Result<()> {
+        self.sync_editor_content();
+
+        let document_manager = self.core_app.document_manager();
+        let doc_id = self.core_app.executor().block_on(async {
+            let mut dm = document_manager.write().await;
+
+            // Reuse the document if it is already open
+            let existing = dm
+                .list_documents()
+                .into_iter()
+                .find(|id| dm.get_document(*id).and_then(|d| d.file_path()) == Some(path));
+            match existing {
+                Some(id) => Ok(id),
+                None => dm.open_document(path).await,
+            }
+        })?;
+
+        self.show_document_in_editor(doc_id);
+
+        if let Some(line) = line {
+            self.plugin_context
+                .set_shared_state("markdown_editor_goto_line", line);
+        }
+        self.plugin_context
+            .set_shared_state("markdown_editor_focus_requested", true);
+
+        Ok(())
+    }
+
+    /// Convert a Word or OpenDocument file to a new Markdown document in the
+    /// project's content directory and show it in an editor tab.
+    fn import_document(&mut self, path: &std::path::Path) -> Result<()> {
+        let Some(project_path) = self.current_project.clone() else {
+            return Err(cosmarium_core::Error::project(
+                "Open a project to import documents into",
+            ));
+        };
+        self.sync_editor_content();
+
+        let content_dir = project_path.join("content");
+        let stem = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let document_manager = self.core_app.document_manager();
+        let project_manager = self.core_app.project_manager();
+        let doc_id = self.core_app.executor().block_on(async {
+            let mut dm = document_manager.write().await;
+            let doc_id = cosmarium_core::import::import_document(&mut dm, path).await?;
+
+            // Never overwrite a document of the project
+            tokio::fs::create_dir_all(&content_dir).await?;
+            let mut file_path = content_dir.join(format!("{}.md", stem));
+            let mut copy = 2;
+            while file_path.exists() {
+                file_path = content_dir.join(format!("{}_{}.md", stem, copy));
+                copy += 1;
+            }
+            if let Some(doc) = dm.get_document_mut(doc_id) {
+                doc.set_file_path(&file_path);
+            }
+            dm.save_document(doc_id).await?;
+            if let Some(project) = project_manager.write().await.active_project_mut() {
+                project.add_document(doc_id);
+            }
+            Ok::<_, cosmarium_core::Error>(doc_id)
+        })?;
+
+        self.show_document_in_editor(doc_id);
+        self.plugin_context
+            .set_shared_state("markdown_editor_focus_requested", true);
+        Ok(())
+    }
+
+    /// Import the documents of the folder `source` into the project, and
+    /// keep importing its new and changed files with `watch`.
+    fn import_document_folder(&mut self, source: &std::path::Path, watch: bool) -> Result<()> {
+        let project_manager = self.core_app.project_manager();
+        let imported = self.core_app.executor().block_on(async {
+            let mut pm = project_manager.write().await;
+            let project = pm
+                .active_project_mut()
+                .ok_or_else(|| cosmarium_core::Error::project("Open a project to import into"))?;
+            import_folder(project, source)
+        })?;
+        self.load_document_order();
+
+        if watch {
+            let mut watched: Vec<WatchedFolder> = self
+                .project_setting(WATCHED_FOLDERS_KEY)
+                .unwrap_or_default();
+            watched.push(WatchedFolder {
+                source: source.to_path_buf(),
+                target: imported.target.clone(),
+            });
+            self.set_project_setting(WATCHED_FOLDERS_KEY, &watched)?;
+            self.folder_watches
+                .push(FolderWatch::new(source, &imported.target)?);
+        }
+        if let Some(first) = imported.documents.first() {
+            let path = self
+                .current_project
+                .as_ref()
+                .map(|project| project.join(first));
+            if let Some(path) = path {
+                self.open_document(&path, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Watch the folders the active project imports from, importing what
+    /// changed in them while the project was closed.
+    fn load_folder_watches(&mut self) {
+        self.folder_watches.clear();
+        let watched: Vec<WatchedFolder> = self
+            .project_setting(WATCHED_FOLDERS_KEY)
+            .unwrap_or_default();
+        for folder in watched {
+            match FolderWatch::new(&folder.source, &folder.target) {
+                Ok(watch) => self.folder_watches.push(watch),
+                Err(e) => tracing::warn!("Cannot watch {:?}: {}", folder.source, e),
+            }
+        }
+        self.import_folder_changes(|_| true);
+    }
+
+    /// Import the files that changed in the watched folders.
+    fn sync_watched_folders(&mut self, ctx: &egui::Context) {
+        if self.folder_watches.is_empty() {
+            return;
+        }
+        self.import_folder_changes(FolderWatch::take_changes);
+        // Changes arrive without user input
+        ctx.request_repaint_after(std::time::Duration::from_secs(1));
+    }
+
+    /// Import the new and changed files of the watched folders picked by
+    /// `changed`.
+    fn import_folder_changes(&mut self, mut changed: impl FnMut(&FolderWatch) -> bool) {
+        let watches: Vec<_> = self
+            .folder_watches
+            .iter()
+            .filter(|watch| changed(watch))
+            .map(|watch| (watch.source().to_path_buf(), watch.target().to_string()))
+            .collect();
+        if watches.is_empty() {
+            return;
+        }
+        let project_manager = self.core_app.project_manager();
+        let written = self.core_app.executor().block_on(async {
+            let mut pm = project_manager.write().await;
+            let Some(project) = pm.active_project_mut() else {
+                return 0;
+            };
+            let mut written = 0;
+            for (source, target) in &watches {
+                match sync_folder(project, source, target) {
+                    Ok(documents) => written += documents.len(),
+                    Err(e) => tracing::error!("Failed to import from {:?}: {}", source, e),
+                }
+            }
+            written
+        });
+        if written > 0 {
+            tracing::info!(
+                "Imported {} changed documents from watched folders",
+                written
+            );
+            self.load_document_order();
+        }
+    }
+
+    /// Stop importing from the watched folder `source`.
+    fn stop_watching_folder(&mut self, source: &std::path::Path) -> Result<()> {
+        self.folder_watches.retain(|watch| watch.source() != source);
+        let mut watched: Vec<WatchedFolder> = self
+            .project_setting(WATCHED_FOLDERS_KEY)
+            .unwrap_or_default();
+        watched.retain(|folder| folder.source != source);
+        self.set_project_setting(WATCHED_FOLDERS_KEY, &watched)
+    }
+
+    /// Bring the second copy of the open project up to date if it is due,
+    /// on `closing` the project or opening it, in the background unless
+    /// the application is about to `exit`.
+    fn update_second_copy(&mut self, closing: bool, exit: bool) {
+        let Some(project) = self.current_project.clone() else {
+            return;
+        };
+        let config = &self.config.project.second_copy;
+        if self.second_copy_task.is_some()
+            || !mirror::is_due(config, &project, closing, std::time::SystemTime::now())
+        {
+            return;
+        }
+        let location = config.location.clone();
+        // The copy is of the history with the last commit
+        self.wait_for_commit();
+        if exit {
+            if let Err(e) = mirror::mirror_project(&project, &location) {
+                tracing::error!("Failed to update the second copy: {}", e);
+            }
+            return;
+        }
+        let name = format!(
+            "Second copy of {}",
+            project.file_name().unwrap_or_default().to_string_lossy()
+        );
+        let task = self
+            .core_app
+            .task_manager()
+            .spawn_task(name, move |progress| {
+                progress.set_message("Copying changed files");
+                let report = mirror::mirror_project(&project, &location)?;
+                Ok(report)
+            });
+        self.second_copy_task = Some(task);
+    }
+
+    /// Bring the quick open index up to date with the project's structure
+    /// and open documents, and show the switcher.
+    fn open_quick_open(&mut self) {
+        let Some(project_path) = self.current_project.clone() else {
+            tracing::warn!("Open a project to go to its documents");
+            return;
+        };
+        self.sync_editor_content();
+        let index = match &mut self.quick_index {
+            Some(index) if index.root() == project_path => index,
+            index => index.insert(QuickIndex::new(&project_path)),
+        };
+        let document_manager = self.core_app.document_manager();
+        let project_manager = self.core_app.project_manager();
+        self.core_app.executor().block_on(async {
+            let dm = document_manager.read().await;
+            let pm = project_manager.read().await;
+            let Some(project) = pm.active_project() else {
+                return;
+            };
+            let open: Vec<(String, String)> = dm
+                .list_documents()
+                .into_iter()
+                .filter_map(|id| dm.get_document(id))
+                .filter_map(|doc| {
+                    let rel = doc.file_path()?.strip_prefix(&project_path).ok()?;
+                    let key = rel
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    Some((key, doc.content().to_string()))
+                })
+                .collect();
+            let open: Vec<(&str, &str)> = open
+                .iter()
+                .map(|(key, text)| (key.as_str(), text.as_str()))
+                .collect();
+            index.refresh(project.structure(), &open);
+        });
+        self.quick_open = Some(CommandPalette::default());
+    }
+
+    /// Document and line of the editor's caret.
+    fn cursor_location(&self) -> Option<(std::path::PathBuf, usize)> {
+        self.plugin_context
+            .get_shared_state::<Option<(std::path::PathBuf, usize)>>(CURSOR_LOCATION_KEY)
+            .flatten()
+    }
+
+    /// Follow the editor's caret in the navigation history.
+    fn track_navigation(&mut self) {
+        // The caret is not at its destination until the editor serves the jump
+        let goto_pending = self
+            .plugin_context
+            .get_shared_state::<usize>("markdown_editor_goto_line")
+            .is_some_and(|line| line > 0);
+        if goto_pending {
+            return;
+        }
+        if let Some((path, line)) = self.cursor_location() {
+            self.navigation.visit(Location::new(path, line));
+        }
+    }
+
+    /// Publish the HTML flavour of the clipboard when text is pasted in this
+    /// frame, for the editor to keep its formatting as Markdown.
+    fn publish_clipboard_html(&mut self, ctx: &egui::Context) {
+        let pasting = ctx.input(|input| {
+            !input.modifiers.shift
+                && input
+                    .events
+                    .iter()
+                    .any(|event| matches!(event, egui::Event::Paste(_)))
+        });
+        if !pasting {
+            return;
+        }
+        let html = arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get().html())
+            .ok();
+        self.plugin_context
+            .set_shared_state::<Option<String>>(CLIPBOARD_HTML_KEY, html);
+    }
+
+    /// Paste the clipboard in the active editor tab, as plain text or
+    /// cleaned up.
+    fn paste_from_clipboard(&mut self, plain: bool) {
+        let mut clipboard = match arboard::Clipboard::new() {
+            Ok(clipboard) => clipboard,
+            Err(e) => {
+                tracing::error!("Failed to access the clipboard: {}", e);
+                return;
+            }
+        };
+        let text = match clipboard.get_text() {
+            Ok(text) => text,
+            Err(e) => {
+                tracing::warn!("No text to paste: {}", e);
+                return;
+            }
+        };
+        let html = if plain {
+            None
+        } else {
+            clipboard.get().html().ok()
+        };
+        self.plugin_context
+            .set_shared_state(PASTE_REQUEST, Some(PasteRequest { text, html, plain }));
+    }
+
+    /// Go back to the previous location of the navigation history.
+    fn navigate_back(&mut self) {
+        if let Some(location) = self.navigation.back() {
+            self.go_to_location(location);
+        }
+    }
+
+    /// Go forward to the location left by going back.
+    fn navigate_forward(&mut self) {
+        if let Some(location) = self.navigation.forward() {
+            self.go_to_location(location);
+        }
+    }
+
+    fn go_to_location(&mut self, location: Location) {
+        if let Err(e) = self.open_document(&location.path, Some(location.line)) {
+            tracing::error!("Failed to open {:?}: {}", location.path, e);
+            self.navigation.forget(&location.path);
+        }
+    }
+
+    /// Deliver the events published since the last frame to their subscribers.
+    fn process_plugin_events(&self) {
+        let event_bus = self.core_app.event_bus();
+        let result = self
+            .core_app
+            .executor()
+            .block_on(async { event_bus.read().await.process_events().await });
+        if let Err(e) = result {
+            tracing::error!("Event processing error: {}", e);
+        }
+    }
+
+    /// Serve document open requests posted by plugins.
+    fn handle_open_document_request(&mut self) {
+        let request = self
+            .plugin_context
+            .get_shared_state::<Option<(std::path::PathBuf, usize)>>("open_document_request")
+            .flatten();
+
+        if let Some((path, line)) = request {
+            self.plugin_context
+                .set_shared_state::<Option<(std::path::PathBuf, usize)>>(
+                    "open_document_request",
+                    None,
+                );
+            // A match found in another project opens that project first
+            let elsewhere = self
+                .current_project
+                .as_ref()
+                .is_none_or(|root| !path.starts_with(root));
+            let owner = self
+                .recent_projects
+                .iter()
+                .find(|project| path.starts_with(project))
+                .cloned();
+            if let Some(project) = owner.filter(|_| elsewhere) {
+                if let Err(e) = self.open_project_async(project) {
+                    tracing::error!("Failed to open project of {:?}: {}", path, e);
+                    return;
+                }
+            }
+            if let Err(e) = self.open_document(&path, Some(line)) {
+                tracing::error!("Failed to open document {:?}: {}", path, e);
+            }
+        }
+    }
+
+    /// Serve requests of plugins to show their panel.
+    fn handle_focus_panel_request(&mut self) {
+        let request = self
+            .plugin_context
+            .get_shared_state::<Option<String>>(FOCUS_PANEL_REQUEST)
+            .flatten();
+
+        if let Some(name) = request {
+            self.plugin_context
+                .set_shared_state::<Option<String>>(FOCUS_PANEL_REQUEST, None);
+            let Some(plugin) = self.panel_plugins.get(&name) else {
+                tracing::warn!("No panel named {} to show", name);
+                return;
+            };
+            if plugin.default_position() != PanelPosition::Center {
+                self.with_layout(|layout_manager| layout_manager.select_panel(&name));
+            }
+            self.ui_state.open_panels.insert(name, true);
+        }
+    }
+
+    /// Store the scene metadata edited by plugins in the project structure.
+    fn handle_metadata_update_request(&mut self) {
+        let Some(update) = self
+            .plugin_context
+            .get_shared_state::<Option<NodeMetadata>>(METADATA_UPDATE_REQUEST)
+            .flatten()
+        else {
+            return;
+        };
+        self.plugin_context
+            .set_shared_state(METADATA_UPDATE_REQUEST, None::<NodeMetadata>);
+
+        let project_manager = self.core_app.project_manager();
+        let stored = self.core_app.executor().block_on(async {
+            let mut pm = project_manager.write().await;
+            match pm.active_project_mut() {
+                Some(project) => project.set_scene_metadata(update.node, &update.metadata),
+                None => Err(cosmarium_core::Error::project("No active project")),
+            }
+        });
+        if let Err(e) = stored {
+            tracing::error!("Failed to store the metadata of {}: {}", update.title, e);
+        }
+        self.publish_active_metadata();
+    }
+
+    /// Serve requests of plugins for a snapshot of the active project,
+    /// unsaved edits included.
+    fn handle_project_snapshot_request(&mut self) {
+        let requested = self
+            .plugin_context
+            .get_shared_state::<bool>(PROJECT_SNAPSHOT_REQUEST)
+            .unwrap_or(false);
+        if !requested {
+            return;
+        }
+        self.plugin_context
+            .set_shared_state(PROJECT_SNAPSHOT_REQUEST, false);
+        self.sync_editor_content();
+
+        let document_manager = self.core_app.document_manager();
+        let project_manager = self.core_app.project_manager();
+        let snapshot = self.core_app.executor().block_on(async {
+            let dm = document_manager.read().await;
+            let mut pm = project_manager.write().await;
+            pm.active_project_mut().map(|project| {
+                project.sync_structure();
+                take_snapshot(project, &dm)
+            })
+        });
+        match snapshot {
+            Some(snapshot) => self
+                .plugin_context
+                .set_shared_state(PROJECT_SNAPSHOT_KEY, Arc::new(snapshot)),
+            None => tracing::warn!("Open a project before taking its snapshot"),
+        }
+    }
+
+    /// Publish the commits that changed the active document for plugins.
+    fn publish_document_commits(&mut self) {
+        let commits = self.active_project_file().and_then(|(project, file)| {
+            let relative = file.strip_prefix(&project).ok()?.to_path_buf();
+            let project_manager = self.core_app.project_manager();
+            let commits = self.core_app.executor().block_on(async {
+                let pm = project_manager.read().await;
+                let git = pm.active_project()?.git()?;
+                Some(
+                    git.file_history(&relative, DOCUMENT_COMMITS_LIMIT)
+                        .unwrap_or_else(|e| {
+                            tracing::warn!("Cannot read the history of {:?}: {}", relative, e);
+                            Vec::new()
+                        }),
+                )
+            })?;
+            Some(Arc::new(DocumentCommits {
+                path: file,
+                commits,
+            }))
+        });
+        self.plugin_context
+            .set_shared_state(DOCUMENT_COMMITS_KEY, commits);
+    }
+
+    /// Publish the changes of the active project since its last commit.
+    fn publish_project_changes(&mut self) {
+        let project_manager = self.core_app.project_manager();
+        let changes = self.core_app.executor().block_on(async {
+            let pm = project_manager.read().await;
+            let project = pm.active_project().filter(|p| p.git().is_some())?;
+            project
+                .changes()
+                .inspect_err(|e| tracing::warn!("Cannot read the changes of the project: {}", e))
+                .ok()
+        });
+        self.plugin_context
+            .set_shared_state(PROJECT_CHANGES_KEY, changes.map(Arc::new));
+    }
+
+    /// Text of the active document at the commit `id`, and at its first
+    /// parent `parent`.
+    fn active_document_at(&self, id: &str, parent: Option<&str>) -> Option<CommitFile> {
+        let (project, file) = self.active_project_file()?;
+        let relative = file.strip_prefix(&project).ok()?.to_path_buf();
+        let project_manager = self.core_app.project_manager();
+        let text_at = |git: &GitIntegration, id: &str| -> Option<Option<String>> {
+            let bytes = git
+                .file_at(id, &relative)
+                .inspect_err(|e| tracing::warn!("Cannot read {:?} at {}: {}", relative, id, e))
+                .ok()?;
+            Some(bytes.and_then(|bytes| decode_text(&bytes).ok().map(|(text, _, _)| text)))
+        };
+        let (content, previous) = self.core_app.executor().block_on(async {
+            let pm = project_manager.read().await;
+            let git = pm.active_project()?.git()?;
+            let content = text_at(git, id)?;
+            let previous = parent.and_then(|parent| text_at(git, parent)).flatten();
+            Some((content, previous))
+        })?;
+        Some(CommitFile {
+            commit: id.to_string(),
+            path: file,
+            content,
+            previous,
+        })
+    }
+
+    /// Serve the requests of plugins about the versions of the active
+    /// document in the Git repository of the project.
+    fn handle_git_history_request(&mut self) {
+        let Some(request) = self
+            .plugin_context
+            .get_shared_state::<Option<GitHistoryRequest>>(GIT_HISTORY_REQUEST)
+            .flatten()
+        else {
+            return;
+        };
+        self.plugin_context
+            .set_shared_state(GIT_HISTORY_REQUEST, None::<GitHistoryRequest>);
+
+        match request {
+            GitHistoryRequest::Refresh => {
+                self.publish_document_commits();
+                self.publish_project_changes();
+            }
+            GitHistoryRequest::Commit(message) => {
+                if let Err(e) = self.save_and_commit_current_project(Some(&message)) {
+                    tracing::error!("Failed to commit the project: {}", e);
+                }
+            }
+            GitHistoryRequest::Show(id) => {
+                let parent = self
+                    .plugin_context
+                    .get_shared_state::<Option<Arc<DocumentCommits>>>(DOCUMENT_COMMITS_KEY)
+                    .flatten()
+                    .and_then(|commits| commits.get(&id).and_then(|commit| commit.parent.clone()));
+                let file = self
+                    .active_document_at(&id, parent.as_deref())
+                    .map(Arc::new);
+                self.plugin_context.set_shared_state(COMMIT_FILE_KEY, file);
+            }
+            GitHistoryRequest::Restore(id) => {
+                self.sync_editor_content();
+                let Some((project, file)) = self.active_project_file() else {
+                    return;
+                };
+                let Some(content) = self
+                    .active_document_at(&id, None)
+                    .and_then(|file| file.content)
+                else {
+                    tracing::warn!("{:?} is not in commit {}", file, id);
+                    return;
+                };
+                let label = format!("Before restoring version {}", short_id(&id));
+                if let Err(e) = self.restore_active_document(&project, &file, &label, content) {
+                    tracing::error!("Failed to restore {:?} at {}: {}", file, id, e);
+                }
+                self.publish_document_snapshots();
+            }
+        }
+    }
+
+    /// Publish the branches of the active project, with the merge waiting
+    /// for its conflicts to be resolved, if any.
+    fn publish_branches(&mut self) {
+        let project_manager = self.core_app.project_manager();
+        let branches = self.core_app.executor().block_on(async {
+            let pm = project_manager.read().await;
+            let git = pm.active_project()?.git()?;
+            let read = || -> Result<Branches> {
+                let merge = match git.merging()? {
+                    Some(draft) => Some(MergeState {
+                        draft,
+                        conflicts: git.conflicts()?,
+                    }),
+                    None => None,
+                };
+                Ok(Branches {
+                    // No branch exists before the first commit
+                    current: git.head_commit_id()?.and(git.current_branch().ok()),
+                    branches: git.branches()?,
+                    merge,
+                })
+            };
+            read()
+                .inspect_err(|e| tracing::warn!("Cannot read the branches of the project: {}", e))
+                .ok()
+        });
+        self.plugin_context
+            .set_shared_state(BRANCHES_KEY, branches.map(Arc::new));
+    }
+
+    /// Serve the requests of plugins about the branches of the active
+    /// project.
+    ///
+    /// The project is saved first, and its changes committed before it
+    /// leaves the branch, whether it commits when saved or not. The project
+    /// and its open documents are then read again from the files Git left.
+    fn handle_branch_request(&mut self) {
+        let Some(request) = self
+            .plugin_context
+            .get_shared_state::<Option<BranchRequest>>(BRANCH_REQUEST)
+            .flatten()
+        else {
+            return;
+        };
+        self.plugin_context
+            .set_shared_state(BRANCH_REQUEST, None::<BranchRequest>);
+        if let Err(e) = self.save_current_project() {
+            tracing::error!("Failed to save the project: {}", e);
+            return;
+        }
+        self.wait_for_commit();
+
+        let leaves_branch = matches!(
+            request,
+            BranchRequest::StartDraft(_) | BranchRequest::Switch(_) | BranchRequest::Merge { .. }
+        );
+        let project_manager = self.core_app.project_manager();
+        let result = self.core_app.executor().block_on(async {
+            let pm = project_manager.read().await;
+            let project = pm
+                .active_project()
+                .ok_or_else(|| cosmarium_core::Error::project("No active project"))?;
+            let git = project.git().ok_or_else(|| {
+                cosmarium_core::Error::project("The project has no Git repository")
+            })?;
+            if leaves_branch {
+                if let Some(draft) = git.merging()? {
+                    return Err(cosmarium_core::Error::project(format!(
+                        "The merge of {} must be completed or aborted first",
+                        draft
+                    )));
+                }
+                project.commit_changes()?;
+            }
+            match request {
+                BranchRequest::StartDraft(name) => {
+                    git.create_branch(&name)?;
+                    git.switch_branch(&name)
+                }
+                BranchRequest::Switch(name) => git.switch_branch(&name),
+                BranchRequest::Merge { draft, into } => {
+                    if git.current_branch()? != into {
+                        git.switch_branch(&into)?;
+                    }
+                    match git.merge_branch(&draft)? {
+                        MergeOutcome::UpToDate => {
+                            tracing::info!("{} has nothing new for {}", draft, into)
+                        }
+                        MergeOutcome::FastForward => {
+                            tracing::info!("Merged {} into {}", draft, into)
+                        }
+                        MergeOutcome::Merged(id) => {
+                            tracing::info!("Merged {} into {} in {}", draft, into, short_id(&id))
+                        }
+                        MergeOutcome::Conflicts(files) => tracing::warn!(
+                            "Merging {} into {}: conflicts to resolve in {:?}",
+                            draft,
+                            into,
+                            files
+                        ),
+                    }
+                    Ok(())
+                }
+                BranchRequest::Delete(name) => git.delete_branch(&name),
+                BranchRequest::Resolve(path, side) => {
+                    let file = project.path().join(path);
+                    let text = std::fs::read_to_string(&file)?;
+                    std::fs::write(&file, merge::resolve(&text, side))?;
+                    Ok(())
+                }
+                BranchRequest::CompleteMerge => git
+                    .complete_merge()
+                    .map(|id| tracing::info!("Merge committed in {}", short_id(&id))),
+                BranchRequest::AbortMerge => git.abort_merge(),
+            }
+        });
+        if let Err(e) = result {
+            tracing::error!("Failed to change the drafts of the project: {}", e);
+        }
+        // Even a failed request may have changed some files
+        self.reload_project_files();
+    }
+
+    /// Read the active project and its open documents again from their
+    /// files, after Git changed them.
+    fn reload_project_files(&mut self) {
+        let Some(path) = self.current_project.clone() else {
+            return;
+        };
+        let project_manager = self.core_app.project_manager();
+        let document_manager = self.core_app.document_manager();
+        let changed = self.core_app.executor().block_on(async {
+            // Saved just before, so nothing is lost reopening it
+            if let Err(e) = project_manager.write().await.open_project(&path).await {
+                tracing::error!("Failed to read the project again: {}", e);
+            }
+            document_manager.write().await.reload_documents().await
+        });
+        self.replace_editor_documents(&changed);
+
+        self.refresh_current_branch();
+        self.publish_project_changes();
+        self.publish_branches();
+        self.publish_active_document_path();
+        self.load_word_count_rules();
+        self.load_project_dictionary();
+        self.load_scene_heading_format();
+        self.load_project_word_target();
+        self.load_line_ending();
+        self.load_auto_commit();
+        self.load_published_as();
+        self.load_quote_style();
+        self.load_autocorrect();
+        self.load_document_order();
+    }
+
+    /// Snapshot of the text of the active document now, under `label`.
+    fn snapshot_active_document(&self, label: &str) -> Option<DocumentSnapshot> {
+        let doc_id = self.active_document_id?;
+        let document_manager = self.core_app.document_manager();
+        self.core_app.executor().block_on(async {
+            let dm = document_manager.read().await;
+            dm.get_document(doc_id).map(|doc| doc.snapshot(label))
+        })
+    }
+
+    /// Replace the text of the active document, the file `file` of
+    /// `project`, with `content` in the editor, as a single undo step. The
+    /// text it replaces is first kept as a snapshot under `label`.
+    fn restore_active_document(
+        &mut self,
+        project: &std::path::Path,
+        file: &std::path::Path,
+        label: &str,
+        content: String,
+    ) -> Result<()> {
+        let Some(now) = self.snapshot_active_document(label) else {
+            return Ok(());
+        };
+        if now.content == content {
+            return Ok(());
+        }
+        let replaced = 0..now.content.len();
+        keep_snapshot(project, file, now)?;
+        let mut transaction = EditTransaction::begin();
+        transaction.replace(replaced, content);
+        transaction.commit(&mut self.plugin_context);
+        Ok(())
+    }
+
+    /// Serve the changes plugins ask for to the snapshots of the active
+    /// document.
+    ///
+    /// Restoring a snapshot keeps the text it replaces as a snapshot first,
+    /// then replaces the text in the editor, as a single undo step.
+    fn handle_document_snapshot_request(&mut self) {
+        let Some(request) = self
+            .plugin_context
+            .get_shared_state::<Option<DocumentSnapshotRequest>>(DOCUMENT_SNAPSHOT_REQUEST)
+            .flatten()
+        else {
+            return;
+        };
+        self.plugin_context
+            .set_shared_state(DOCUMENT_SNAPSHOT_REQUEST, None::<DocumentSnapshotRequest>);
+        self.sync_editor_content();
+        let Some((project, file)) = self.active_project_file() else {
+            tracing::warn!("Snapshots are kept of the documents of the active project only");
+            return;
+        };
+
+        let outcome = match request {
+            DocumentSnapshotRequest::Take(label) => match self.snapshot_active_document(&label) {
+                Some(snapshot) => keep_snapshot(&project, &file, snapshot),
+                None => Ok(()),
+            },
+            DocumentSnapshotRequest::Delete(id) => delete_snapshot(&project, &file, id).map(|_| ()),
+            DocumentSnapshotRequest::Restore(id) => {
+                load_snapshots(&project, &file).and_then(|snapshots| {
+                    match snapshots.into_iter().find(|snapshot| snapshot.id == id) {
+                        Some(snapshot) => {
+                            let label = format!("Before restoring “{}”", snapshot.label);
+                            self.restore_active_document(&project, &file, &label, snapshot.content)
+                        }
+                        None => Ok(()),
+                    }
+                })
+            }
+        };
+        if let Err(e) = outcome {
+            tracing::error!("Failed to update the snapshots of {:?}: {}", file, e);
+        }
+        self.publish_document_snapshots();
+    }
+
+    /// Start exporting a project document to the configured export directory.
+    ///
+    /// Unsaved edits are included when the document is open in the editor.
+    /// The export runs as a background task.
+    /// Anonymized exports remove every name of the project author and the
+    /// names listed in the export settings.
+    fn export_document(
+        &mut self,
+        path: &std::path::Path,
+        format: DocumentExportFormat,
+        anonymize: bool,
+    ) -> Result<()> {
+        self.sync_editor_content();
+
+        let document_manager = self.core_app.document_manager();
+        let project_manager = self.core_app.project_manager();
+        let profiles = &self.config.profiles;
+        let (content, project_name, identity) = self.core_app.executor().block_on(async {
+            let dm = document_manager.read().await;
+            let content = dm
+                .list_documents()
+                .into_iter()
+                .filter_map(|id| dm.get_document(id))
+                .find(|doc| doc.file_path() == Some(path))
+                .map(|doc| doc.content().to_string());
+
+            let pm = project_manager.read().await;
+            let (name, identity) = pm
+                .active_project()
+                .map(|p| {
+                    (
+                        p.name().to_string(),
+                        AuthorIdentity::of_project(p.metadata(), profiles),
+                    )
+                })
+                .unwrap_or_default();
+            (content, name, identity)
+        });
+        let mut content = match content {
+            Some(content) => content,
+            None => std::fs::read_to_string(path)?,
+        };
+        if self.config.export.append_glossary && !self.project_dictionary.is_empty() {
+            content = format!(
+                "{}\n\n{}",
+                content.trim_end(),
+                self.project_dictionary.to_glossary("Glossary")
+            );
+        }
+
+        let anonymization = anonymize.then(|| {
+            let settings = &self.config.export.anonymize;
+            Anonymization::new(
+                settings
+                    .identifying_names
+                    .iter()
+                    .chain(&identity.names)
+                    .map(String::as_str),
+                &settings.placeholder,
+            )
+        });
+
+        self.spawn_export(
+            path.to_path_buf(),
+            content,
+            format,
+            &project_name,
+            identity.byline,
+            anonymization,
+        );
+        Ok(())
+    }
+
+    /// Export the project dictionary as a glossary appendix.
+    fn export_glossary(&mut self, format: DocumentExportFormat) {
+        let Some(project_path) = self.current_project.clone() else {
+            tracing::warn!("Open a project before exporting its glossary");
+            return;
+        };
+        let project_manager = self.core_app.project_manager();
+        let profiles = &self.config.profiles;
+        let (project_name, identity) = self.core_app.executor().block_on(async {
+            let pm = project_manager.read().await;
+            pm.active_project()
+                .map(|p| {
+                    (
+                        p.name().to_string(),
+                        AuthorIdentity::of_project(p.metadata(), profiles),
+                    )
+                })
+                .unwrap_or_default()
+        });
+
+        let content = self.project_dictionary.to_glossary("Glossary");
+        self.spawn_export(
+            project_path.join("glossary.md"),
+            content,
+            format,
+            &project_name,
+            identity.byline,
+            None,
+        );
+    }
+
+    /// Look for glossary terms spelled inconsistently across the project's
+    /// documents, as a background task.
+    ///
+    /// Unsaved edits of open documents are checked rather than the files.
+    fn check_term_consistency(&mut self) {
+        let Some(project_path) = self.current_project.clone() else {
+            tracing::warn!("Open a project before checking its glossary terms");
+            return;
+        };
+        if self.term_check_task.is_some() {
+            return;
+        }
+        self.sync_editor_content();
+        let open_documents = self.open_document_contents();
+
+        let dictionary = self.project_dictionary.clone();
+        let task =
+            self.core_app
+                .task_manager()
+                .spawn_task("Check glossary terms", move |progress| {
+                    let files = project_documents(&project_path);
+                    let total = files.len() as u64;
+                    let mut issues = Vec::new();
+                    for (i, path) in files.into_iter().enumerate() {
+                        progress.check_cancelled()?;
+                        progress.set_position(i as u64, total);
+                        let text = match open_documents.get(&path) {
+                            Some(text) => text.clone(),
+                            None => match std::fs::read_to_string(&path) {
+                                Ok(text) => text,
+                                Err(e) => {
+                                    tracing::warn!("Cannot check {:?}: {}", path, e);
+                                    continue;
+                                }
+                            },
+                        };
+                        issues.extend(
+                            check_terms(&dictionary, &text)
+                                .into_iter()
+                                .map(|issue| (path.clone(), issue)),
+                        );
+                    }
+                    Ok(issues)
+                });
+        self.term_check_task = Some(task);
+    }
+
+    /// Content of the documents open, unsaved edits included, by file.
+    fn open_document_contents(&self) -> HashMap<std::path::PathBuf, String> {
+        let document_manager = self.core_app.document_manager();
+        self.core_app.executor().block_on(async {
+            let dm = document_manager.read().await;
+            dm.list_documents()
+                .into_iter()
+                .filter_map(|id| dm.get_document(id))
+                .filter_map(|doc| {
+                    let path = doc.file_path()?.to_path_buf();
+                    Some((path, doc.content().to_string()))
+                })
+                .collect()
+        })
+    }
+
+    /// Show the content of documents the application changed in their
+    /// editor tabs.
+    fn replace_editor_documents(&mut self, ids: &[Uuid]) {
+        let document_manager = self.core_app.document_manager();
+        let buffers: Vec<DocumentBuffer> = self.core_app.executor().block_on(async {
+            let dm = document_manager.read().await;
+            ids.iter()
+                .filter_map(|&id| {
+                    let doc = dm.get_document(id)?;
+                    Some(DocumentBuffer {
+                        id,
+                        title: doc.title().to_string(),
+                        path: doc.file_path().map(|p| p.to_path_buf()),
+                        content: doc.content().to_string(),
+                    })
+                })
+                .collect()
+        });
+        for buffer in buffers {
+            editor_documents::push(&mut self.plugin_context, REPLACE_DOCUMENTS_REQUEST, buffer);
+        }
+    }
+
+    /// Serve the replacement requests of plugins: find the matches of a
+    /// replacement across the project, replace the selected ones, or undo
+    /// the last replacement.
+    fn handle_replace_request(&mut self) {
+        let Some(request) = self
+            .plugin_context
+            .get_shared_state::<Option<ReplaceRequest>>(REPLACE_REQUEST)
+            .flatten()
+        else {
+            return;
+        };
+        self.plugin_context
+            .set_shared_state(REPLACE_REQUEST, None::<ReplaceRequest>);
+        self.sync_editor_content();
+
+        let status = match self.current_project.clone() {
+            None => ReplaceStatus::Failed("Open a project before replacing in it".to_string()),
+            Some(project_path) => match request {
+                ReplaceRequest::Preview(query) => ReplaceStatus::Preview(replace::preview(
+                    &project_path,
+                    &query,
+                    &self.open_document_contents(),
+                )),
+                ReplaceRequest::Apply(preview) => self.apply_replacement(&preview),
+                ReplaceRequest::Undo => self.undo_replacement(),
+            },
+        };
+        self.plugin_context
+            .set_shared_state(REPLACE_STATUS_KEY, Some(status));
+    }
+
+    /// Replace the selected matches of `preview` in all their documents, as
+    /// one change of the document manager.
+    fn apply_replacement(&mut self, preview: &ReplacePreview) -> ReplaceStatus {
+        let open = self.open_document_contents();
+        let mut edits = Vec::new();
+        for file in preview.files.iter().filter(|file| file.selected() > 0) {
+            let path = preview.file(file);
+            let text = match open.get(&path) {
+                Some(text) => text.clone(),
+                None => match std::fs::read_to_string(&path) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        return ReplaceStatus::Failed(format!("Cannot read {}: {}", file.path, e))
+                    }
+                },
+            };
+            match replace::replace_selected(&text, file, &preview.query.replace) {
+                Ok(text) => edits.push((path, text)),
+                Err(e) => return ReplaceStatus::Failed(e.to_string()),
+            }
+        }
+        if edits.is_empty() {
+            return ReplaceStatus::Failed("Select the matches to replace".to_string());
+        }
+
+        let label = format!(
+            "Replace “{}” with “{}”",
+            preview.query.find, preview.query.replace
+        );
+        let documents = edits.len();
+        let document_manager = self.core_app.document_manager();
+        let result = self.core_app.executor().block_on(async {
+            let mut dm = document_manager.write().await;
+            dm.apply_transaction(&label, edits).await
+        });
+        match result {
+            Ok(changed) => {
+                self.replace_editor_documents(&changed);
+                tracing::info!("{}: {} documents changed", label, documents);
+                ReplaceStatus::Applied {
+                    matches: preview.selected(),
+                    documents,
+                }
+            }
+            Err(e) => ReplaceStatus::Failed(e.to_string()),
+        }
+    }
+
+    /// Put the documents of the last replacement back as they were.
+    fn undo_replacement(&mut self) -> ReplaceStatus {
+        let document_manager = self.core_app.document_manager();
+        let result = self.core_app.executor().block_on(async {
+            let mut dm = document_manager.write().await;
+            let documents = dm.last_transaction().map_or(0, |t| t.len());
+            dm.undo_transaction()
+                .await
+                .map(|changed| (changed, documents))
+        });
+        match result {
+            Ok((changed, documents)) => {
+                self.replace_editor_documents(&changed);
+                ReplaceStatus::Undone { documents }
+            }
+            Err(e) => ReplaceStatus::Failed(e.to_string()),
+        }
+    }
+
+    /// Check the integrity of the active project. The documents open in the
+    /// editor are in use, whatever the project recorded.
+    fn check_project_integrity(&mut self) {
+        let document_manager = self.core_app.document_manager();
+        let project_manager = self.core_app.project_manager();
+        let report = self.core_app.executor().block_on(async {
+            let dm = document_manager.read().await;
+            let pm = project_manager.read().await;
+            pm.active_project()
+                .map(|project| check_project(project, &dm.list_documents()))
+        });
+        match report {
+            Some(report) => self.integrity_report = Some(report),
+            None => tracing::warn!("Open a project before checking its integrity"),
+        }
+    }
+
+    /// Apply `fix` to `issue`, then check the project again.
+    ///
+    /// Links of documents open in the editor are relinked in their tab, to be
+    /// saved with their other edits; other repairs are saved right away.
+    fn repair_project_issue(&mut self, issue: &Issue, fix: &Repair) {
+        self.sync_editor_content();
+        let document_manager = self.core_app.document_manager();
+        let project_manager = self.core_app.project_manager();
+        let result = self.core_app.executor().block_on(async {
+            let mut dm = document_manager.write().await;
+            let mut pm = project_manager.write().await;
+            let Some(project) = pm.active_project_mut() else {
+                return Err(cosmarium_core::Error::project("No active project"));
+            };
+            if let Subject::Link { document, .. } = &issue.subject {
+                let path = project.path().join(document);
+                let open = dm.list_documents().into_iter().find(|&id| {
+                    dm.get_document(id)
+                        .and_then(|doc| doc.file_path())
+                        .is_some_and(|file| file == path)
+                });
+                if let Some(doc) = open.and_then(|id| dm.get_document_mut(id)) {
+                    if let Some(content) = relink_document(issue, fix, doc.content()) {
+                        doc.set_content(&content);
+                        return Ok(Some(DocumentBuffer {
+                            id: doc.id(),
+                            title: doc.title().to_string(),
+                            path: Some(path),
+                            content,
+                        }));
+                    }
+                }
+            }
+            repair(project, issue, fix)?;
+            project.save().await?;
+            Ok(None)
+        });
+        match result {
+            Ok(Some(buffer)) => {
+                editor_documents::push(&mut self.plugin_context, REPLACE_DOCUMENTS_REQUEST, buffer)
+            }
+            Ok(None) => self.load_document_order(),
+            Err(e) => tracing::error!("Failed to repair the project ({}): {}", fix.label(), e),
+        }
+        self.check_project_integrity();
+    }
+
+    /// Serve the search requests of plugins, as a background task.
+    ///
+    /// The index of the project is built on the first search, then only the
+    /// documents changed since the previous search are indexed again, with
+    /// the unsaved edits of open documents.
+    fn handle_search_request(&mut self) {
+        if self.search_task.is_some() {
+            // Served once the running search is done
+            return;
+        }
+        let Some(query) = self
+            .plugin_context
+            .get_shared_state::<Option<SearchQuery>>(SEARCH_REQUEST)
+            .flatten()
+        else {
+            return;
+        };
+        self.plugin_context
+            .set_shared_state(SEARCH_REQUEST, None::<SearchQuery>);
+
+        let Some(project_path) = self.current_project.clone() else {
+            let error = "Open a project before searching it".to_string();
+            self.publish_search_response(query, Vec::new(), Some(error));
+            return;
+        };
+        if self.search_service.as_ref().map(SearchService::root) != Some(project_path.as_path()) {
+            let event_bus = self.core_app.event_bus();
+            let language = self.config.editor.spell_check_language.clone();
+            let service = self.core_app.executor().block_on(async {
+                let bus = event_bus.read().await;
+                SearchService::new(&project_path, &language, &bus).await
+            });
+            match service {
+                Ok(service) => self.search_service = Some(service),
+                Err(e) => {
+                    tracing::error!("Failed to index the project: {}", e);
+                    self.publish_search_response(query, Vec::new(), Some(e.to_string()));
+                    return;
+                }
+            }
+        }
+        self.sync_editor_content();
+        // Let the service see the edits just synchronized
+        self.process_plugin_events();
+
+        let Some(service) = &self.search_service else {
+            return;
+        };
+        let document_manager = self.core_app.document_manager();
+        let project_manager = self.core_app.project_manager();
+        let update = self.core_app.executor().block_on(async {
+            let dm = document_manager.read().await;
+            let pm = project_manager.read().await;
+            pm.active_project()
+                .map(|p| service.take_update(&dm, p.metadata(), p.structure()))
+        });
+        let Some(update) = update else {
+            return;
+        };
+        if update.is_rebuild() {
+            tracing::debug!("Indexing the project for search");
+        } else if !update.is_empty() {
+            tracing::debug!("Indexing {} changed documents for search", update.len());
+        }
+
+        let global = if query.all_projects {
+            Some(self.global_index())
+        } else {
+            None
+        };
+        let task_query = query.clone();
+        let task = self
+            .core_app
+            .task_manager()
+            .spawn_task("Search project", move |progress| {
+                progress.check_cancelled()?;
+                let mut results = update.search(&task_query)?;
+                if let Some(global) = global {
+                    progress.check_cancelled()?;
+                    results.merge(global.search(&task_query)?);
+                }
+                Ok(results)
+            });
+        self.search_task = Some((query, task));
+    }
+
+    /// Index of the recent projects other than the active one, built again
+    /// when they change.
+    fn global_index(&mut self) -> Arc<GlobalIndex> {
+        let paths: Vec<std::path::PathBuf> = self
+            .recent_projects
+            .iter()
+            .filter(|path| Some(*path) != self.current_project.as_ref())
+            .cloned()
+            .collect();
+        match &self.global_index {
+            Some((indexed, index)) if *indexed == paths => Arc::clone(index),
+            _ => {
+                tracing::debug!("Indexing {} recent projects for search", paths.len());
+                let language = self.config.editor.spell_check_language.clone();
+                let index = Arc::new(
+                    self.core_app
+                        .executor()
+                        .block_on(GlobalIndex::build(&paths, &language)),
+                );
+                self.global_index = Some((paths, Arc::clone(&index)));
+                index
+            }
+        }
+    }
+
+    /// Publish the results of a search for the search panel.
+    fn publish_search_response(
+        &mut self,
+        query: SearchQuery,
+        hits: Vec<cosmarium_core::search::SearchHit>,
+        error: Option<String>,
+    ) {
+        let response = SearchResponse {
+            query,
+            root: self.current_project.clone().unwrap_or_default(),
+            hits,
+            error,
+        };
+        self.plugin_context
+            .set_shared_state(SEARCH_RESULTS_KEY, Some(response));
+    }
+
+    /// Run an export to the project's export directory as a background task.
+    fn spawn_export(
+        &mut self,
+        source: std::path::PathBuf,
+        content: String,
+        format: DocumentExportFormat,
+        project_name: &str,
+        author: String,
+        anonymization: Option<Anonymization>,
+    ) {
+        let output_dir = self.config.export.default_directory.join(project_name);
+        let mut export_config = self.config.export.clone();
+        export_config.line_ending = self.line_ending;
+        export_config.locale = self.locale.clone();
+        let name = format!(
+            "Export {} ({})",
+            source.file_name().unwrap_or_default().to_string_lossy(),
+            format.display_name()
+        );
+        let task = self
+            .core_app
+            .task_manager()
+            .spawn_task(name, move |progress| {
+                progress.set_message("Writing file");
+                let output = cosmarium_core::export::export_document(
+                    &source,
+                    &content,
+                    format,
+                    &output_dir,
+                    &author,
+                    &export_config,
+                    anonymization.as_ref(),
+                )?;
+                Ok(output)
+            });
+        self.export_tasks.push(task);
+    }
+
+    /// Compile the project's documents, in the order of the project
+    /// structure, into one manuscript and export it as a background task.
+    ///
+    /// Unsaved edits of open documents are included.
+    fn compile_project(
+        &mut self,
+        target: CompileTarget,
+        anonymize: bool,
+        preset: Option<ExportPreset>,
+    ) {
+        let Some(project_path) = self.current_project.clone() else {
+            tracing::warn!("Open a project before compiling it");
+            return;
+        };
+        self.sync_editor_content();
+
+        let document_manager = self.core_app.document_manager();
+        let project_manager = self.core_app.project_manager();
+        let profiles = &self.config.profiles;
+        let (open_documents, project) = self.core_app.executor().block_on(async {
+            let dm = document_manager.read().await;
+            let open_documents: HashMap<std::path::PathBuf, String> = dm
+                .list_documents()
+                .into_iter()
+                .filter_map(|id| dm.get_document(id))
+                .filter_map(|doc| {
+                    let path = doc.file_path()?.to_path_buf();
+                    Some((path, doc.content().to_string()))
+                })
+                .collect();
+
+            let mut pm = project_manager.write().await;
+            let project = pm.active_project_mut().map(|p| {
+                p.sync_structure();
+                (
+                    p.name().to_string(),
+                    AuthorIdentity::of_project(p.metadata(), profiles),
+                    p.structure().clone(),
+                )
+            });
+            (open_documents, project)
+        });
+        let Some((project_name, identity, structure)) = project else {
+            return;
+        };
+
+        let anonymization = anonymize.then(|| {
+            let settings = &self.config.export.anonymize;
+            Anonymization::new(
+                settings
+                    .identifying_names
+                    .iter()
+                    .chain(&identity.names)
+                    .map(String::as_str),
+                &settings.placeholder,
+            )
+        });
+        let glossary = (self.config.export.append_glossary && !self.project_dictionary.is_empty())
+            .then(|| self.project_dictionary.to_glossary("Glossary"));
+        let mut export_config = match preset {
+            Some(preset) => preset.apply(&self.config.export),
+            None => self.config.export.clone(),
+        };
+        export_config.line_ending = self.line_ending;
+        export_config.locale = self.locale.clone();
+        export_config.compile = export_config.compile.for_identity(&identity);
+        let language = self.manuscript_language();
+        let output_dir = export_config.default_directory.join(&project_name);
+        let name = format!("Compile {} ({})", project_name, target.display_name());
+
+        let task = self
+            .core_app
+            .task_manager()
+            .spawn_task(name, move |progress| {
+                progress.set_message("Gathering documents");
+                let mut manuscript = compile_manuscript(
+                    &project_name,
+                    &identity.byline,
+                    &structure,
+                    &export_config.compile,
+                    |node| {
+                        let path = project_path.join(node.path.as_ref()?);
+                        match open_documents.get(&path) {
+                            Some(text) => Some(text.clone()),
+                            None => std::fs::read_to_string(&path)
+                                .map_err(|e| tracing::warn!("Cannot compile {:?}: {}", path, e))
+                                .ok(),
+                        }
+                    },
+                );
+                manuscript.language = language;
+                if let Some(glossary) = glossary {
+                    manuscript.sections.push(ManuscriptSection {
+                        kind: SectionKind::Document,
+                        title: "Glossary".to_string(),
+                        depth: 0,
+                        path: None,
+                        separator: export_config.compile.chapter_separator.clone(),
+                        markdown: glossary,
+                    });
+                }
+                if let Some(preset) = preset {
+                    // Blind submissions leave the contact details out
+                    let contact_info = match anonymization {
+                        Some(_) => "",
+                        None => export_config.compile.contact_info.as_str(),
+                    };
+                    manuscript.front_matter =
+                        preset.title_page(&manuscript, contact_info, &export_config.locale);
+                }
+
+                progress.check_cancelled()?;
+                progress.set_message("Writing file");
+                let output = export_manuscript(
+                    &manuscript,
+                    target,
+                    &output_dir,
+                    &export_config,
+                    anonymization.as_ref(),
+                )?;
+                Ok(output)
+            });
+        self.export_tasks.push(task);
+    }
+
+    /// Language of compiled manuscripts, as a BCP 47 tag: that of the spell
+    /// checker.
+    fn manuscript_language(&self) -> String {
+        self.config
+            .editor
+            .spell_check_language
+            .trim()
+            .replace('_', "-")
+    }
+
+    /// Compile the project's manuscript and list what makes it hard to read
+    /// with assistive technologies, as a background task: images without
+    /// alternative text, skipped heading levels, a missing title or language.
+    ///
+    /// Unsaved edits of open documents are checked rather than the files.
+    fn check_accessibility(&mut self) {
+        let Some(project_path) = self.current_project.clone() else {
+            tracing::warn!("Open a project before checking its accessibility");
+            return;
+        };
+        if self.accessibility_task.is_some() {
+            return;
+        }
+        self.sync_editor_content();
+        let open_documents = self.open_document_contents();
+
+        let project_manager = self.core_app.project_manager();
+        let profiles = &self.config.profiles;
+        let project = self.core_app.executor().block_on(async {
+            let mut pm = project_manager.write().await;
+            pm.active_project_mut().map(|p| {
+                p.sync_structure();
+                (
+                    p.name().to_string(),
+                    AuthorIdentity::of_project(p.metadata(), profiles),
+                    p.structure().clone(),
+                )
+            })
+        });
+        let Some((project_name, identity, structure)) = project else {
+            return;
+        };
+        let compile = self.config.export.compile.for_identity(&identity);
+        let language = self.manuscript_language();
+
+        let task =
+            self.core_app
+                .task_manager()
+                .spawn_task("Check accessibility", move |progress| {
+                    progress.set_message("Gathering documents");
+                    let read = |path: &std::path::Path| match open_documents.get(path) {
+                        Some(text) => Some(text.clone()),
+                        None => std::fs::read_to_string(path)
+                            .map_err(|e| tracing::warn!("Cannot check {:?}: {}", path, e))
+                            .ok(),
+                    };
+                    let mut manuscript = compile_manuscript(
+                        &project_name,
+                        &identity.byline,
+                        &structure,
+                        &compile,
+                        |node| read(&project_path.join(node.path.as_ref()?)),
+                    );
+                    manuscript.language = language;
+                    progress.check_cancelled()?;
+
+                    // Compiling adds epigraphs and removes front matter, so find
+                    // the line of each problem in its source file by its text
+                    let mut findings = Vec::new();
+                    for mut finding in accessibility::check(&manuscript) {
+                        let mut line = finding.line + 1;
+                        if let Some(path) = finding.path.take() {
+                            let path = project_path.join(path);
+                            let compiled = manuscript
+                                .sections
+                                .iter()
+                                .find(|s| s.title == finding.section)
+                                .and_then(|s| s.markdown.lines().nth(finding.line))
+                                .map(str::trim);
+                            if let (Some(compiled), Some(source)) = (compiled, read(&path)) {
+                                if let Some(i) = source.lines().position(|l| l.trim() == compiled) {
+                                    line = i + 1;
+                                }
+                            }
+                            finding.path = Some(path);
+                        }
+                        findings.push((finding, line));
+                    }
+                    Ok(findings)
+                });
+        self.accessibility_task = Some(task);
+    }
+
+    /// Sign a proof of the project's word count, of its documents and of
+    /// its last commit, and save it at `path`.
+    ///
+    /// The project is saved first, so that the proof is of the text on
+    /// screen. Words are counted with the project's word count rules.
+    fn export_progress_proof(&mut self, path: &std::path::Path) -> Result<()> {
+        self.save_current_project()?;
+        self.wait_for_commit();
+
+        let mut stats = WritingStats::new();
+        stats.set_rules(self.word_count_rules);
+        stats.set_language(Some(self.manuscript_language()).filter(|l| !l.is_empty()));
+        let project_manager = self.core_app.project_manager();
+        let proof = self.core_app.executor().block_on(async {
+            let pm = project_manager.read().await;
+            let project = pm
+                .active_project()
+                .ok_or_else(|| cosmarium_core::Error::project("No project is open"))?;
+            let mut words = 0;
+            for node in project.structure().reading_order() {
+                let Some(file) = &node.path else {
+                    continue;
+                };
+                stats.update(&std::fs::read_to_string(project.path().join(file))?);
+                words += stats.word_count();
+            }
+            ProgressProof::of_project(project, words)
+        })?;
+
+        let key = ProofKey::load_or_create(&ProofKey::default_path()?)?;
+        let proof = proof.sign(&key);
+        proof.save(path)?;
+        tracing::info!(
+            "Saved a progress proof of {} words to {:?}",
+            proof.word_count,
+            path
+        );
+        self.progress_proof = Some((proof, path.to_path_buf()));
+        Ok(())
+    }
+
+    /// Formats a manuscript can be compiled to: the built-in ones, then
+    /// those of export plugins.
+    fn compile_targets(&self) -> Vec<CompileTarget> {
+        DocumentExportFormat::ALL
+            .into_iter()
+            .map(CompileTarget::Builtin)
+            .chain(
+                self.export_plugins
+                    .iter()
+                    .cloned()
+                    .map(CompileTarget::Plugin),
+            )
+            .collect()
+    }
+
+    /// Export the document open in the editor.
+    fn export_active_document(&mut self, format: DocumentExportFormat, anonymize: bool) {
+        self.publish_active_document_path();
+        let path = self
+            .plugin_context
+            .get_shared_state::<Option<std::path::PathBuf>>("active_document_path")
+            .flatten();
+
+        match path {
+            Some(path) => {
+                if let Err(e) = self.export_document(&path, format, anonymize) {
+                    tracing::error!("Failed to export document {:?}: {}", path, e);
+                }
+            }
+            None => tracing::warn!("Save the document before exporting it"),
+        }
+    }
+
+    /// Serve document export requests posted by plugins.
+    fn handle_export_document_request(&mut self) {
+        let request = self
+            .plugin_context
+            .get_shared_state::<Option<(std::path::PathBuf, String)>>(EXPORT_DOCUMENT_REQUEST)
+            .flatten();
+
+        if let Some((path, format_id)) = request {
+            self.plugin_context
+                .set_shared_state::<Option<(std::path::PathBuf, String)>>(
+                    EXPORT_DOCUMENT_REQUEST,
+                    None,
+                );
+            let Some(format) = DocumentExportFormat::from_id(&format_id) else {
+                tracing::warn!("Unknown export format '{}'", format_id);
+                return;
+            };
+            if let Err(e) = self.export_document(&path, format, false) {
+                tracing::error!("Failed to export document {:?}: {}", path, e);
+            }
+        }
+    }
+
+    /// Show the New Project wizard, with the templates saved by the user.
+    fn open_new_project_wizard(&mut self) {
+        self.new_project.user_templates = match UserTemplate::templates_dir() {
+            Ok(dir) => UserTemplate::load_all(&dir),
+            Err(e) => {
+                tracing::warn!("Failed to find the user templates: {}", e);
+                Vec::new()
+            }
+        };
+        self.new_project.user_template = None;
+        self.show_new_project_dialog = true;
+    }
+
+    /// Copy the active project, saved first, with the choices of the
+    /// Duplicate Project dialog, and open the copy.
+    fn duplicate_current_project(&mut self, form: &DuplicateProjectForm) -> Result<()> {
+        let Some(source) = self.current_project.clone() else {
+            return Ok(());
+        };
+        self.save_current_project()?;
+        self.wait_for_commit();
+        let name = form.name.trim().to_string();
+        let dest = std::path::PathBuf::from(form.location.trim()).join(&name);
+        let reset_history = form.reset_history;
+        let copy = dest.clone();
+        self.core_app
+            .executor()
+            .block_on(async move { duplicate(&source, &copy, &name, reset_history).await })?;
+        tracing::info!("Duplicated the project to {:?}", dest);
+        self.open_project_async(dest)
+    }
+
+    /// Save the active project, saved first, as a template for new
+    /// projects.
+    fn save_project_as_template(&mut self, form: &SaveTemplateForm) -> Result<()> {
+        self.save_current_project()?;
+        let dir = UserTemplate::templates_dir()?;
+        let project_manager = self.core_app.project_manager();
+        let template = self.core_app.executor().block_on(async {
+            let pm = project_manager.read().await;
+            pm.active_project()
+                .map(|project| UserTemplate::save(project, &form.name, &form.description, &dir))
+                .transpose()
+        })?;
+        if let Some(template) = template {
+            tracing::info!("Saved template '{}' to {:?}", template.name, template.dir);
+        }
+        Ok(())
+    }
+
+    /// Create a new project with the choices of the New Project wizard.
+    fn create_new_project(&mut self, wizard: &NewProjectWizard) -> Result<()> {
+        let name = wizard.name.trim().to_string();
+        let project_path = std::path::PathBuf::from(wizard.location.trim()).join(&name);
+        let user_template = wizard.chosen_user_template().cloned();
+        let template = match &user_template {
+            Some(template) => template.name.clone(),
+            None => wizard.template.id().to_string(),
+        };
+        let remote_url = wizard.remote_url.trim().to_string();
+
+        tracing::info!("Creating new project '{}' at {:?}", name, project_path);
+        self.update_second_copy(true, false);
+
+        let project_manager = Arc::clone(&self.core_app.project_manager());
+        let path_buf = project_path.clone();
+
+        let executor = self.core_app.executor();
+        executor.block_on(async move {
+            let mut pm = project_manager.write().await;
+            pm.create_project(&name, &path_buf, &template).await?;
+            if let (Some(template), Some(project)) = (user_template, pm.active_project_mut()) {
+                template.create(project)?;
+            }
+            if remote_url.is_empty() {
+                return Ok(());
+            }
+            match pm.active_project().and_then(|project| project.git()) {
+                Some(git) => git.add_remote("origin", &remote_url),
+                None => {
+                    tracing::warn!("The project has no Git repository to add a remote to");
+                    Ok(())
+                }
+            }
+        })?;
+
+        self.current_project = Some(project_path.clone());
+        self.plugin_context
+            .set_project_path(Some(project_path.clone()));
+
+        // Update recent projects list
+        let project_manager = Arc::clone(&self.core_app.project_manager());
+        let recent = executor.block_on(async {
+            let pm = project_manager.read().await;
+            pm.recent_projects().to_vec()
+        });
+        self.recent_projects = recent;
+
+        // Initial goal
+        self.project_word_target = wizard.word_target;
+        self.project_deadline = wizard.deadline.trim().to_string();
+        self.save_project_word_target()?;
+
+        // Get current Git branch
+        self.refresh_current_branch();
+        self.publish_project_changes();
+        self.publish_branches();
+        self.load_word_count_rules();
+        self.load_project_dictionary();
+        self.load_scene_heading_format();
+        self.load_project_word_target();
+        self.load_line_ending();
+        self.load_auto_commit();
+        self.load_published_as();
+        self.load_quote_style();
+        self.load_autocorrect();
+        self.load_folder_watches();
+        self.load_document_order();
+
+        // Update session
+        self.session
+            .add_recent_project(project_path.clone(), self.config.app.max_recent_projects);
+        self.session.last_opened_project = Some(project_path);
+        if let Err(e) = self.session.save() {
+            tracing::warn!("Failed to save session: {}", e);
+        }
+        self.recent_projects = self.session.recent_projects.clone();
+
+        // Request focus for the editor
+        self.plugin_context
+            .set_shared_state("markdown_editor_focus_requested", true);
+
+        Ok(())
+    }
+
+    /// Render the main menu bar.
+    /// Render the menu bar (Zed-style: compact or expanded).
+    fn render_menu_bar(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.ui_state.menu_expanded {
+            self.render_expanded_menu_bar(ctx);
+        } else {
+            self.render_compact_menu_bar(ctx);
+        }
+    }
+
+    /// Render the compact menu bar (burger + project + branch).
+    fn render_compact_menu_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("compact_menu_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                // Configure visuals for "frameless feel but visible hover"
+                let mut visuals = ui.visuals().clone();
+                visuals.widgets.inactive.bg_fill = egui::Color32::TRANSPARENT;
+                visuals.widgets.inactive.weak_bg_fill = egui::Color32::TRANSPARENT;
+                visuals.widgets.inactive.bg_stroke = egui::Stroke::NONE;
+
+                visuals.widgets.hovered.bg_stroke = egui::Stroke::NONE;
+                visuals.widgets.active.bg_stroke = egui::Stroke::NONE;
+                visuals.widgets.open.bg_stroke = egui::Stroke::NONE;
+
+                ui.ctx().set_visuals(visuals);
+
+                // Burger menu button
+                if ui.button("☰").clicked() {
+                    self.ui_state.menu_expanded = true;
+                    self.ui_state.active_menu = Some(MenuId::Cosmarium);
+                }
+
+                // Project name button (now a submenu)
+                let project_name = self
+                    .current_project
+                    .as_ref()
+                    .and_then(|p| p.file_name())
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("No Project")
+                    .to_string();
+
+                ui.menu_button(project_name, |ui| {
+                    ui.set_min_width(200.0);
+                    ui.with_layout(egui::Layout::top_down_justified(egui::Align::Min), |ui| {
+                        if self.recent_projects.is_empty() {
+                            ui.label("No recent projects");
+                        } else {
+                            let mut path_to_open = None;
+                            for path in &self.recent_projects {
+                                let label = path
+                                    .file_name()
+                                    .and_then(|n| n.to_str())
+                                    .unwrap_or("Unknown Project");
+
+                                if ui.button(label).clicked() {
+                                    path_to_open = Some(path.clone());
+                                    ui.close_menu();
+                                }
+                            }
+
+                            if let Some(path) = path_to_open {
+                                if let Err(e) = self.open_project_async(path) {
+                                    tracing::error!("Failed to open recent project: {}", e);
+                                }
+                            }
+                        }
+
+                        ui.separator();
+
+                        if ui.button("New").clicked() {
+                            ui.close_menu();
+                            self.open_new_project_wizard();
+                        }
+
+                        if ui.button("Open").clicked() {
+                            ui.close_menu();
+                            // Open project selector
+                            if let Some(path) = rfd::FileDialog::new()
+                                .set_title("Open Project")
+                                .pick_folder()
+                            {
+                                if let Err(e) = self.open_project_async(path) {
+                                    tracing::error!("Failed to open project: {}", e);
+                                }
+                            }
+                        }
+                    });
+                });
+
+                self.render_branch_menu(ui);
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    self.render_activity_switcher(ui);
+                });
+            });
+        });
+    }
+
+    /// Render the menu of the current Git branch, switching to another
+    /// draft or opening the Drafts panel.
+    fn render_branch_menu(&mut self, ui: &mut egui::Ui) {
+        let Some(branch) = self.current_branch.clone() else {
+            return;
+        };
+        let others: Vec<String> = self
+            .plugin_context
+            .get_shared_state::<Option<Arc<Branches>>>(BRANCHES_KEY)
+            .flatten()
+            .map(|branches| branches.others().map(str::to_string).collect())
+            .unwrap_or_default();
+        ui.menu_button(format!("🌿 {}", branch), |ui| {
+            ui.set_min_width(200.0);
+            ui.with_layout(egui::Layout::top_down_justified(egui::Align::Min), |ui| {
+                for name in others {
+                    if ui
+                        .button(name.as_str())
+                        .on_hover_text("Commit the changes, then go on with this draft")
+                        .clicked()
+                    {
+                        self.plugin_context
+                            .set_shared_state(BRANCH_REQUEST, Some(BranchRequest::Switch(name)));
+                        ui.close();
+                    }
+                }
+                ui.separator();
+                if ui.button("Drafts…").clicked() {
+                    self.plugin_context.set_shared_state(
+                        FOCUS_PANEL_REQUEST,
+                        Some(cosmarium_drafts::PLUGIN_NAME.to_string()),
+                    );
+                    ui.close();
+                }
+            });
+        });
+    }
+
+    /// Render the expanded menu bar (custom implementation).
+    fn render_expanded_menu_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("expanded_menu_bar").show(ctx, |ui| {
+            // Horizontal menu bar
+            ui.horizontal(|ui| {
+                // Configure visuals for "frameless feel but visible hover"
+                let mut visuals = ui.visuals().clone();
+                visuals.widgets.inactive.bg_fill = egui::Color32::TRANSPARENT;
+                visuals.widgets.inactive.weak_bg_fill = egui::Color32::TRANSPARENT;
+                visuals.widgets.inactive.bg_stroke = egui::Stroke::NONE;
+
+                visuals.widgets.hovered.bg_stroke = egui::Stroke::NONE;
+                visuals.widgets.active.bg_stroke = egui::Stroke::NONE;
+                visuals.widgets.open.bg_stroke = egui::Stroke::NONE;
+
+                visuals.widgets.noninteractive.bg_stroke.width = 0.5;
+                visuals.window_stroke.width = 0.5;
+
+                ui.ctx().set_visuals(visuals);
+
+                // Helper closure to render a menu item
+                let mut render_menu_item =
+                    |ui: &mut egui::Ui,
+                     id: MenuId,
+                     label: &str,
+                     content: Box<dyn FnOnce(&mut Self, &mut egui::Ui)>| {
+                        let button_response = ui.button(label);
+
+                        // Click logic: toggle menu
+                        if button_response.clicked() {
+                            if self.ui_state.active_menu == Some(id) {
+                                self.ui_state.active_menu = None;
+                                self.ui_state.menu_expanded = false;
+                            } else {
+                                self.ui_state.active_menu = Some(id);
+                            }
+                        }
+
+                        // Hover logic: switch if another menu is open
+                        if self.ui_state.active_menu.is_some() && button_response.hovered() {
+                            self.ui_state.active_menu = Some(id);
+                        }
+
+                        // Popup rendering
+                        if self.ui_state.active_menu == Some(id) {
+                            let popup_id = ui.make_persistent_id(format!("{}_popup", label));
+
+                            // Sync with egui's internal state to ensure popup_below_widget renders
+                            if !ui.memory(|m| m.is_popup_open(popup_id)) {
+                                ui.memory_mut(|m| m.open_popup(popup_id));
+                            }
+
+                            egui::popup_below_widget(
+                                ui,
+                                popup_id,
+                                &button_response,
+                                egui::PopupCloseBehavior::CloseOnClickOutside,
+                                |ui| {
+                                    ui.set_min_width(150.0);
+                                    ui.with_layout(
+                                        egui::Layout::top_down_justified(egui::Align::Min),
+                                        |ui| {
+                                            content(self, ui);
+                                        },
+                                    );
+                                },
+                            );
+                        }
+                    };
+
+                // Cosmarium Menu
+                render_menu_item(
+                    ui,
+                    MenuId::Cosmarium,
+                    "Cosmarium",
+                    Box::new(|app, ui| {
+                        if ui.button("About").clicked() {
+                            app.show_about = true;
+                            app.ui_state.menu_expanded = false;
+                            app.ui_state.active_menu = None;
+                        }
+                        if ui.button("Settings").clicked() {
+                            app.show_settings = true;
+                            app.ui_state.menu_expanded = false;
+                            app.ui_state.active_menu = None;
+                        }
+                        ui.separator();
+                        if ui
+                            .add(
+                                egui::Button::new("Exit")
+                                    .shortcut_text(app.shortcut_text(AppCommand::Quit.id())),
+                            )
+                            .clicked()
+                        {
+                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                        }
+                    }),
+                );
+
+                // File Menu
+                render_menu_item(
+                    ui,
+                    MenuId::File,
+                    "File",
+                    Box::new(|app, ui| {
+                        if ui
+                            .add(
+                                egui::Button::new("New Project")
+                                    .shortcut_text(app.shortcut_text(AppCommand::NewProject.id())),
+                            )
+                            .clicked()
+                        {
+                            app.open_new_project_wizard();
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui
+                            .add(
+                                egui::Button::new("Open Project")
+                                    .shortcut_text(app.shortcut_text(AppCommand::OpenProject.id())),
+                            )
+                            .clicked()
+                        {
+                            // We need to close the menu before opening the dialog to avoid UI glitches
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+
+                            // Use a separate thread or deferred action for file dialog if possible,
+                            // but here we just call it. Note: rfd might block.
+                            if let Some(path) = rfd::FileDialog::new()
+                                .set_title("Open Project")
+                                .pick_folder()
+                            {
+                                if let Err(e) = app.open_project_async(path) {
+                                    tracing::error!("Failed to open project: {}", e);
+                                }
+                            }
+                        }
+
+                        if ui
+                            .add(
+                                egui::Button::new("Save Project")
+                                    .shortcut_text(app.shortcut_text(AppCommand::SaveProject.id())),
+                            )
+                            .clicked()
+                        {
+                            if let Err(e) = app.save_current_project() {
+                                tracing::error!("Failed to save project: {}", e);
+                            }
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        ui.add_enabled_ui(app.current_project.is_some(), |ui| {
+                            if ui
+                                .button("Duplicate Project…")
+                                .on_hover_text(
+                                    "Copy the project under a new name and open the copy",
+                                )
+                                .clicked()
+                            {
+                                if let Some(project) = &app.current_project {
+                                    let name =
+                                        project.file_name().unwrap_or_default().to_string_lossy();
+                                    app.duplicate_project = Some(DuplicateProjectForm {
+                                        name: format!("{} (copy)", name),
+                                        location: project
+                                            .parent()
+                                            .unwrap_or(project)
+                                            .to_string_lossy()
+                                            .to_string(),
+                                        reset_history: false,
+                                        location_check: LocationCheck::default(),
+                                    });
+                                }
+                                app.ui_state.active_menu = None;
+                                app.ui_state.menu_expanded = false;
+                            }
+                            if ui
+                                .button("Save as Template…")
+                                .on_hover_text(
+                                    "Start new projects with the structure, settings and \
+                                     headings of this one",
+                                )
+                                .clicked()
+                            {
+                                app.save_template = Some(SaveTemplateForm::default());
+                                app.ui_state.active_menu = None;
+                                app.ui_state.menu_expanded = false;
+                            }
+                        });
+                        ui.separator();
+                        ui.add_enabled_ui(app.current_project.is_some(), |ui| {
+                            if ui.button("Import Document...").clicked() {
+                                app.ui_state.active_menu = None;
+                                app.ui_state.menu_expanded = false;
+                                let extensions = ImportFormat::ALL.map(|format| format.extension());
+                                if let Some(paths) = rfd::FileDialog::new()
+                                    .set_title("Import Document")
+                                    .add_filter("Word or OpenDocument text", &extensions)
+                                    .pick_files()
+                                {
+                                    for path in paths {
+                                        if let Err(e) = app.import_document(&path) {
+                                            tracing::error!("Failed to import {:?}: {}", path, e);
+                                        }
+                                    }
+                                }
+                            }
+                            if ui
+                                .button("Import Folder…")
+                                .on_hover_text(
+                                    "Import a folder of Markdown, text, Word or OpenDocument \
+                                     files, keeping its subfolders",
+                                )
+                                .clicked()
+                            {
+                                app.ui_state.active_menu = None;
+                                app.ui_state.menu_expanded = false;
+                                if let Some(source) = rfd::FileDialog::new()
+                                    .set_title("Import Folder")
+                                    .pick_folder()
+                                {
+                                    app.import_folder = Some(ImportFolderForm {
+                                        source,
+                                        watch: false,
+                                    });
+                                }
+                            }
+                        });
+                        ui.add_enabled_ui(app.active_document_id.is_some(), |ui| {
+                            ui.menu_button("Export Document", |ui| {
+                                for format in DocumentExportFormat::ALL {
+                                    if ui.button(format.display_name()).clicked() {
+                                        app.export_active_document(format, false);
+                                        app.ui_state.active_menu = None;
+                                        app.ui_state.menu_expanded = false;
+                                        ui.close();
+                                    }
+                                }
+                                ui.separator();
+                                ui.menu_button("Anonymized (blind submission)", |ui| {
+                                    for format in DocumentExportFormat::ALL {
+                                        if ui.button(format.display_name()).clicked() {
+                                            app.export_active_document(format, true);
+                                            app.ui_state.active_menu = None;
+                                            app.ui_state.menu_expanded = false;
+                                            ui.close();
+                                        }
+                                    }
+                                });
+                            });
+                        });
+                        ui.add_enabled_ui(app.current_project.is_some(), |ui| {
+                            ui.menu_button("Compile Manuscript", |ui| {
+                                for target in app.compile_targets() {
+                                    if ui.button(target.display_name()).clicked() {
+                                        app.compile_project(target, false, None);
+                                        app.ui_state.active_menu = None;
+                                        app.ui_state.menu_expanded = false;
+                                        ui.close();
+                                    }
+                                }
+                                ui.separator();
+                                ui.menu_button("Anonymized (blind submission)", |ui| {
+                                    for target in app.compile_targets() {
+                                        if ui.button(target.display_name()).clicked() {
+                                            app.compile_project(target, true, None);
+                                            app.ui_state.active_menu = None;
+                                            app.ui_state.menu_expanded = false;
+                                            ui.close();
+                                        }
+                                    }
+                                });
+                                for preset in ExportPreset::ALL {
+                                    let targets: Vec<CompileTarget> = app
+                                        .compile_targets()
+                                        .into_iter()
+                                        .filter(|target| preset.supports(target))
+                                        .collect();
+                                    if targets.is_empty() {
+                                        continue;
+                                    }
+                                    ui.menu_button(preset.display_name(), |ui| {
+                                        for anonymize in [false, true] {
+                                            if anonymize {
+                                                ui.separator();
+                                                ui.label("Anonymized (blind submission)");
+                                            }
+                                            for target in &targets {
+                                                if ui.button(target.display_name()).clicked() {
+                                                    app.compile_project(
+                                                        target.clone(),
+                                                        anonymize,
+                                                        Some(preset),
+                                                    );
+                                                    app.ui_state.active_menu = None;
+                                                    app.ui_state.menu_expanded = false;
+                                                    ui.close();
+                                                }
+                                            }
+                                        }
+                                    });
+                                }
+                                ui.separator();
+                                if ui
+                                    .add_enabled(
+                                        app.accessibility_task.is_none(),
+                                        egui::Button::new("Accessibility Checklist…"),
+                                    )
+                                    .on_hover_text(
+                                        "List images without alternative text and other \
+                                         problems for screen readers before exporting",
+                                    )
+                                    .clicked()
+                                {
+                                    app.check_accessibility();
+                                    app.ui_state.active_menu = None;
+                                    app.ui_state.menu_expanded = false;
+                                    ui.close();
+                                }
+                            });
+                        });
+                        ui.add_enabled_ui(app.current_project.is_some(), |ui| {
+                            if ui
+                                .button("Export Progress Proof…")
+                                .on_hover_text(
+                                    "Save a signed statement of the word count, for \
+                                     contests and accountability groups",
+                                )
+                                .clicked()
+                            {
+                                app.ui_state.active_menu = None;
+                                app.ui_state.menu_expanded = false;
+                                if let Some(path) = rfd::FileDialog::new()
+                                    .set_title("Export Progress Proof")
+                                    .add_filter("Progress proof", &["json"])
+                                    .set_file_name("progress-proof.json")
+                                    .save_file()
+                                {
+                                    if let Err(e) = app.export_progress_proof(&path) {
+                                        tracing::error!("Failed to export a progress proof: {}", e);
+                                    }
+                                }
+                            }
+                        });
+                        ui.add_enabled_ui(!app.project_dictionary.is_empty(), |ui| {
+                            ui.menu_button("Export Glossary Appendix", |ui| {
+                                for format in DocumentExportFormat::ALL {
+                                    if ui.button(format.display_name()).clicked() {
+                                        app.export_glossary(format);
+                                        app.ui_state.active_menu = None;
+                                        app.ui_state.menu_expanded = false;
+                                        ui.close();
+                                    }
+                                }
+                            });
+                        });
+                    }),
+                );
+
+                // Edit Menu
                 render_menu_item(
                     ui,
-                    MenuId::File,
-                    "File",
+                    MenuId::Edit,
+                    "Edit",
                     Box::new(|app, ui| {
+                        let steps = app
+                            .plugin_context
+                            .get_shared_state::<UndoState>(UNDO_STATE_KEY)
+                            .unwrap_or_default();
+                        if ui
+                            .add_enabled(
+                                steps.undo > 0,
+                                egui::Button::new("Undo")
+                                    .shortcut_text(app.shortcut_text(EditorCommand::Undo.id())),
+                            )
+                            .clicked()
+                        {
+                            commands::send(&mut app.plugin_context, EditorCommand::Undo);
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui
+                            .add_enabled(
+                                steps.redo > 0,
+                                egui::Button::new("Redo")
+                                    .shortcut_text(app.shortcut_text(EditorCommand::Redo.id())),
+                            )
+                            .clicked()
+                        {
+                            commands::send(&mut app.plugin_context, EditorCommand::Redo);
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        ui.weak(format!(
+                            "History: {} of {} steps",
+                            app.locale.format_count(steps.undo),
+                            app.locale.format_count(app.config.editor.undo_depth)
+                        ));
+                        ui.separator();
+                        let selected = app
+                            .plugin_context
+                            .get_shared_state::<String>("markdown_editor_selection")
+                            .is_some_and(|selection| !selection.is_empty());
+                        if ui
+                            .add_enabled(
+                                selected,
+                                egui::Button::new("Cut")
+                                    .shortcut_text(egui::RichText::new("Ctrl+X").size(12.0).weak()),
+                            )
+                            .clicked()
+                        {
+                            commands::send(&mut app.plugin_context, EditorCommand::Cut);
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui
+                            .add_enabled(
+                                selected,
+                                egui::Button::new("Copy")
+                                    .shortcut_text(egui::RichText::new("Ctrl+C").size(12.0).weak()),
+                            )
+                            .clicked()
+                        {
+                            commands::send(&mut app.plugin_context, EditorCommand::Copy);
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
                         if ui
                             .add(
-                                egui::Button::new("New Project")
-                                    .shortcut_text(egui::RichText::new("Ctrl+N").size(12.0).weak()),
+                                egui::Button::new("Paste")
+                                    .shortcut_text(egui::RichText::new("Ctrl+V").size(12.0).weak()),
                             )
                             .clicked()
                         {
-                            app.show_new_project_dialog = true;
+                            app.paste_from_clipboard(false);
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui
+                            .add(egui::Button::new("Paste as Plain Text").shortcut_text(
+                                egui::RichText::new("Ctrl+Shift+V").size(12.0).weak(),
+                            ))
+                            .on_hover_text("Paste without cleaning up or converting formatting")
+                            .clicked()
+                        {
+                            app.paste_from_clipboard(true);
                             app.ui_state.active_menu = None;
                             app.ui_state.menu_expanded = false;
                         }
                         if ui
                             .add(
-                                egui::Button::new("Open Project")
-                                    .shortcut_text(egui::RichText::new("Ctrl+O").size(12.0).weak()),
+                                egui::Button::new("Select All")
+                                    .shortcut_text(egui::RichText::new("Ctrl+A").size(12.0).weak()),
                             )
                             .clicked()
                         {
-                            // We need to close the menu before opening the dialog to avoid UI glitches
+                            commands::send(&mut app.plugin_context, EditorCommand::SelectAll);
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        ui.separator();
+                        if ui
+                            .add(egui::Button::new("Reflow Paragraph").shortcut_text(
+                                app.shortcut_text(EditorCommand::ReflowParagraph.id()),
+                            ))
+                            .on_hover_text(
+                                "Fill the paragraphs under the caret up to the wrap column",
+                            )
+                            .clicked()
+                        {
+                            commands::send(&mut app.plugin_context, EditorCommand::ReflowParagraph);
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui
+                            .add(
+                                egui::Button::new("Go to Line…")
+                                    .shortcut_text(app.shortcut_text(EditorCommand::GoToLine.id())),
+                            )
+                            .clicked()
+                        {
+                            commands::send(&mut app.plugin_context, EditorCommand::GoToLine);
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui
+                            .add(
+                                egui::Button::new("Go to Heading…").shortcut_text(
+                                    app.shortcut_text(EditorCommand::GoToHeading.id()),
+                                ),
+                            )
+                            .on_hover_text("Pick a heading of the document from its outline")
+                            .clicked()
+                        {
+                            commands::send(&mut app.plugin_context, EditorCommand::GoToHeading);
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        ui.separator();
+                        if ui
+                            .add_enabled(
+                                app.current_project.is_some(),
+                                egui::Button::new("Search Project...")
+                                    .shortcut_text(app.shortcut_text(SEARCH_COMMAND)),
+                            )
+                            .clicked()
+                        {
+                            app.plugin_context.run_command(SEARCH_COMMAND);
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        ui.separator();
+                        if ui
+                            .add_enabled(
+                                app.navigation.can_go_back(),
+                                egui::Button::new("Back")
+                                    .shortcut_text(app.shortcut_text(AppCommand::Back.id())),
+                            )
+                            .clicked()
+                        {
+                            app.navigate_back();
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui
+                            .add_enabled(
+                                app.navigation.can_go_forward(),
+                                egui::Button::new("Forward")
+                                    .shortcut_text(app.shortcut_text(AppCommand::Forward.id())),
+                            )
+                            .clicked()
+                        {
+                            app.navigate_forward();
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        ui.add_enabled_ui(!app.navigation.recent_edits().is_empty(), |ui| {
+                            ui.menu_button("Recently Edited", |ui| {
+                                let mut target = None;
+                                for location in app.navigation.recent_edits() {
+                                    let name = location
+                                        .path
+                                        .file_name()
+                                        .map(|n| n.to_string_lossy())
+                                        .unwrap_or_default();
+                                    if ui
+                                        .button(format!("{}:{}", name, location.line))
+                                        .on_hover_text(location.path.display().to_string())
+                                        .clicked()
+                                    {
+                                        target = Some(location.clone());
+                                    }
+                                }
+                                if let Some(location) = target {
+                                    app.go_to_location(location);
+                                    app.ui_state.active_menu = None;
+                                    app.ui_state.menu_expanded = false;
+                                    ui.close();
+                                }
+                            });
+                        });
+                    }),
+                );
+
+                // View Menu
+                render_menu_item(
+                    ui,
+                    MenuId::View,
+                    "View",
+                    Box::new(|app, ui| {
+                        if ui
+                            .add(
+                                egui::Button::new("Command Palette…").shortcut_text(
+                                    app.shortcut_text(AppCommand::CommandPalette.id()),
+                                ),
+                            )
+                            .clicked()
+                        {
+                            app.command_palette = Some(CommandPalette::default());
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui
+                            .add_enabled(
+                                app.current_project.is_some(),
+                                egui::Button::new("Go to Document or Heading…")
+                                    .shortcut_text(app.shortcut_text(AppCommand::QuickOpen.id())),
+                            )
+                            .clicked()
+                        {
+                            app.open_quick_open();
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        ui.separator();
+
+                        // We need to collect changes to avoid borrowing issues
+                        let mut panels_to_toggle = Vec::new();
+
+                        for (panel_name, panel_plugin) in &app.panel_plugins {
+                            if !panel_plugin.is_closable() {
+                                continue;
+                            }
+                            let is_open =
+                                app.ui_state.open_panels.get(panel_name).unwrap_or(&false);
+                            let mut open = *is_open;
+
+                            if ui.checkbox(&mut open, panel_plugin.panel_title()).clicked() {
+                                panels_to_toggle.push((panel_name.clone(), open));
+                                // Don't close menu for checkboxes usually
+                            }
+                        }
+
+                        for (name, open) in panels_to_toggle {
+                            app.ui_state.open_panels.insert(name, open);
+                        }
+
+                        ui.separator();
+
+                        let current = app.with_layout(|layout_manager| {
+                            layout_manager.current_layout().activity()
+                        });
+                        for activity in Activity::ALL {
+                            let button =
+                                egui::Button::selectable(activity == current, activity.name())
+                                    .shortcut_text(
+                                        app.shortcut_text(AppCommand::Activity(activity).id()),
+                                    );
+                            if ui.add(button).clicked() {
+                                app.switch_activity(activity);
+                            }
+                        }
+
+                        ui.separator();
+
+                        if ui
+                            .checkbox(&mut app.ui_state.show_menu_bar, "Menu Bar")
+                            .clicked()
+                        {
+                            // app.ui_state.active_menu = None; // Optional
+                        }
+                        if ui
+                            .checkbox(&mut app.ui_state.show_status_bar, "Status Bar")
+                            .clicked()
+                        {
+                            // app.ui_state.active_menu = None; // Optional
+                        }
+                        if ui
+                            .add(egui::Button::new("Focus Mode").shortcut_text(
+                                app.shortcut_text(EditorCommand::ToggleFocusMode.id()),
+                            ))
+                            .clicked()
+                        {
+                            app.toggle_focus_mode();
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                    }),
+                );
+
+                // Tools Menu
+                render_menu_item(
+                    ui,
+                    MenuId::Tools,
+                    "Tools",
+                    Box::new(|app, ui| {
+                        if ui.button("Plugin Manager").clicked() {
+                            app.show_plugin_manager = true;
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui.button("Settings").clicked() {
+                            app.show_settings = true;
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        ui.separator();
+                        let can_check = app.current_project.is_some()
+                            && !app.project_dictionary.is_empty()
+                            && app.term_check_task.is_none();
+                        if ui
+                            .add_enabled(can_check, egui::Button::new("Check Glossary Terms"))
+                            .on_disabled_hover_text("Add words to the project dictionary first")
+                            .clicked()
+                        {
+                            app.check_term_consistency();
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui
+                            .add_enabled(
+                                app.current_project.is_some(),
+                                egui::Button::new("Check Project Integrity..."),
+                            )
+                            .clicked()
+                        {
+                            app.check_project_integrity();
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                    }),
+                );
+
+                // Help Menu
+                render_menu_item(
+                    ui,
+                    MenuId::Help,
+                    "Help",
+                    Box::new(|app, ui| {
+                        if ui.button("About Cosmarium").clicked() {
+                            app.show_about = true;
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui.button("Documentation").clicked() {
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui.button("Report Issue").clicked() {
+                            app.ui_state.active_menu = None;
+                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui.button("Diagnostics").clicked() {
+                            app.open_diagnostics();
                             app.ui_state.active_menu = None;
                             app.ui_state.menu_expanded = false;
+                        }
+                    }),
+                );
+            });
+        });
 
-                            // Use a separate thread or deferred action for file dialog if possible,
-                            // but here we just call it. Note: rfd might block.
-                            if let Some(path) = rfd::FileDialog::new()
-                                .set_title("Open Project")
-                                .pick_folder()
-                            {
-                                if let Err(e) = app.open_project_async(path) {
-                                    tracing::error!("Failed to open project: {}", e);
+        // Detect click outside menu to close
+        if self.ui_state.active_menu.is_some() {
+            ctx.input(|i| {
+                if i.pointer.any_click() {
+                    // Check if click is outside the top panel
+                    if let Some(pos) = i.pointer.interact_pos() {
+                        // Simple heuristic: if click is below y=100, close menu
+                        // (menu bar is typically at the top)
+                        if pos.y > 100.0 {
+                            self.ui_state.active_menu = None;
+                            self.ui_state.menu_expanded = false;
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    /// Render the status bar.
+    fn render_status_bar(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.show_status_bar || self.focus_mode() {
+            return;
+        }
+
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                // Project info
+                if let Some(ref project) = self.current_project {
+                    ui.label(format!(
+                        "📁 {}",
+                        project.file_stem().unwrap_or_default().to_string_lossy()
+                    ));
+                    ui.separator();
+                } else {
+                    ui.label("📝 No project");
+                    ui.separator();
+                }
+
+                // Editor stats (if available from shared state)
+                if let Some(word_count) = self
+                    .plugin_context
+                    .get_shared_state::<usize>("editor_word_count")
+                {
+                    ui.label(format!("Words: {}", self.locale.format_count(word_count)));
+                    ui.separator();
+                }
+                if let Some(char_count) = self
+                    .plugin_context
+                    .get_shared_state::<usize>("editor_char_count")
+                {
+                    ui.label(format!(
+                        "Characters: {}",
+                        self.locale.format_count(char_count)
+                    ));
+                    ui.separator();
+                }
+                if let Some(para_count) = self
+                    .plugin_context
+                    .get_shared_state::<usize>("editor_para_count")
+                {
+                    ui.label(format!(
+                        "Paragraphs: {}",
+                        self.locale.format_count(para_count)
+                    ));
+                    ui.separator();
+                }
+                if let Some(verse) = self
+                    .plugin_context
+                    .get_shared_state::<VerseStats>("editor_verse_stats")
+                    .filter(|verse| !verse.is_empty())
+                {
+                    ui.label(format!(
+                        "Lines: {} · Stanzas: {}",
+                        self.locale.format_count(verse.lines),
+                        self.locale.format_count(verse.stanzas)
+                    ))
+                    .on_hover_text(format!(
+                        "About {} syllables a line",
+                        self.locale
+                            .format_count(verse.syllables_per_line().round() as usize)
+                    ));
+                    ui.separator();
+                }
+
+                // Writing goals
+                let today = self.today();
+                for progress in self
+                    .writing_goals
+                    .progress(&self.session.writing_log, today)
+                {
+                    let written = self.locale.format_number(progress.written);
+                    let target = self.locale.format_count(progress.target);
+                    ui.add(
+                        egui::ProgressBar::new(progress.fraction())
+                            .desired_width(120.0)
+                            .text(format!("{}: {}/{}", progress.kind.label(), written, target)),
+                    )
+                    .on_hover_text({
+                        let mut text = format!(
+                            "{} goal: {} of {} words written",
+                            progress.kind.label(),
+                            written,
+                            target
+                        );
+                        let deadline = parse_deadline(&self.project_deadline).ok().flatten();
+                        if let (GoalKind::Project, Some(deadline)) = (progress.kind, deadline) {
+                            if let Some(pace) = progress.daily_pace(today, deadline) {
+                                text.push_str(&format!(
+                                    "\n{} words a day to finish by {}",
+                                    self.locale.format_count(pace),
+                                    self.locale.format_date(deadline, DateStyle::Medium)
+                                ));
+                            }
+                        }
+                        text
+                    });
+                    ui.separator();
+                }
+
+                // Writing sprint under way
+                if let Some(sprint) = self.sprint() {
+                    let seconds = sprint
+                        .remaining(self.plugin_context.clock().now())
+                        .as_secs();
+                    ui.label(format!(
+                        "⏱ {:02}:{:02} · {} words",
+                        seconds / 60,
+                        seconds % 60,
+                        self.locale.format_number(sprint.words)
+                    ));
+                    if ui
+                        .small_button("⏹")
+                        .on_hover_text("Stop the sprint")
+                        .clicked()
+                    {
+                        self.plugin_context
+                            .set_shared_state(SPRINT_STOP_REQUEST, true);
+                    }
+                    ui.separator();
+                    ctx.request_repaint_after(std::time::Duration::from_secs(1));
+                }
+
+                // Line ending and encoding of the active document
+                if let Some((line_ending, encoding)) = self.active_document_format() {
+                    let mut conversion = None;
+                    ui.menu_button(
+                        format!("{} · {}", line_ending.label(), encoding.label()),
+                        |ui| {
+                            for target in LineEnding::ALL {
+                                if ui
+                                    .radio(
+                                        line_ending == target,
+                                        format!("Line endings: {}", target.label()),
+                                    )
+                                    .clicked()
+                                {
+                                    conversion = Some((target, encoding));
+                                    ui.close();
                                 }
                             }
+                            ui.separator();
+                            for target in TextEncoding::ALL {
+                                if ui
+                                    .radio(
+                                        encoding == target,
+                                        format!("Encoding: {}", target.label()),
+                                    )
+                                    .clicked()
+                                {
+                                    conversion = Some((line_ending, target));
+                                    ui.close();
+                                }
+                            }
+                        },
+                    )
+                    .response
+                    .on_hover_text("Convert the line endings or encoding of the document");
+                    if let Some((line_ending, encoding)) = conversion {
+                        self.convert_active_document(line_ending, encoding);
+                    }
+                    ui.separator();
+                }
+
+                // Plugin status
+                ui.label(format!(
+                    "🔌 {} plugins",
+                    self.panel_plugins.len() + self.plugins.len()
+                ));
+
+                // Background tasks
+                let task_manager = self.core_app.task_manager();
+                for task in task_manager.running() {
+                    ui.separator();
+                    let text = if task.message.is_empty() {
+                        task.name.clone()
+                    } else {
+                        format!("{}: {}", task.name, task.message)
+                    };
+                    match task.fraction {
+                        Some(fraction) => {
+                            ui.add(
+                                egui::ProgressBar::new(fraction)
+                                    .desired_width(80.0)
+                                    .show_percentage(),
+                            )
+                            .on_hover_text(&text);
+                        }
+                        None => {
+                            ui.add(egui::Spinner::new().size(12.0)).on_hover_text(&text);
                         }
+                    }
+                    ui.label(&task.name);
+                    if task.cancel_requested {
+                        ui.weak("Cancelling…");
+                    } else if ui.small_button("✖").on_hover_text("Cancel").clicked() {
+                        task_manager.cancel(task.id);
+                    }
+                }
 
-                        if ui
-                            .add(
-                                egui::Button::new("Save Project")
-                                    .shortcut_text(egui::RichText::new("Ctrl+S").size(12.0).weak()),
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    // Application info
+                    ui.label(format!("Cosmarium v{}", env!("CARGO_PKG_VERSION")));
+                    ui.separator();
+
+                    // Atmosphere Status
+                    let sentiment = self
+                        .plugin_context
+                        .get_shared_state::<f32>("atmosphere_sentiment")
+                        .unwrap_or(0.0);
+
+                    let is_analyzing = self
+                        .plugin_context
+                        .get_shared_state::<bool>("atmosphere_analyzing")
+                        .unwrap_or(false);
+                    let emotions = self
+                        .plugin_context
+                        .get_shared_state::<Vec<(String, f32)>>("atmosphere_emotions")
+                        .unwrap_or_default();
+                    let p_idx = self
+                        .plugin_context
+                        .get_shared_state::<usize>("atmosphere_paragraph_idx")
+                        .unwrap_or(0);
+                    let emotion_name = self
+                        .plugin_context
+                        .get_shared_state::<String>("atmosphere_current_emotion")
+                        .unwrap_or_else(|| "Neutral".to_string());
+
+                    if p_idx > 0 {
+                        ui.label(format!("Atmo: P{}", p_idx));
+                    }
+
+                    if is_analyzing {
+                        ui.add(egui::Spinner::new().size(12.0));
+                    }
+
+                    // Color square using the current theme background
+                    let color = ui.visuals().panel_fill;
+
+                    let (rect, response) =
+                        ui.allocate_at_least(egui::vec2(12.0, 12.0), egui::Sense::click());
+                    ui.painter().rect_filled(rect, 2.0, color);
+                    ui.painter().rect_stroke(
+                        rect,
+                        2.0,
+                        egui::Stroke::new(1.0, egui::Color32::from_gray(128)),
+                        egui::StrokeKind::Outside,
+                    );
+
+                    let popup_id = ui.make_persistent_id("atmosphere_color_picker_popup");
+                    if response.clicked() {
+                        self.ui_state.show_atmosphere_picker =
+                            !self.ui_state.show_atmosphere_picker;
+                    }
+
+                    if self.ui_state.show_atmosphere_picker {
+                        let pos = response.rect.left_bottom();
+                        egui::Area::new(popup_id)
+                            .order(egui::Order::Foreground)
+                            .fixed_pos(pos)
+                            .constrain(true)
+                            .show(ui.ctx(), |ui| {
+                                let frame_response = egui::Frame::popup(ui.style())
+                                    .show(ui, |ui| {
+                                        ui.set_min_width(300.0);
+                                        ui.vertical(|ui| {
+                                            ui.label("Climat Manuel :");
+                                            let mut picker_color =
+                                                self.ui_state.atmosphere_picker_color;
+                                            if egui::color_picker::color_picker_color32(
+                                                ui,
+                                                &mut picker_color,
+                                                egui::color_picker::Alpha::Opaque,
+                                            ) {
+                                                self.ui_state.atmosphere_picker_color =
+                                                    picker_color;
+
+                                                // Generate palette and send request
+                                                let hsv = egui::ecolor::Hsva::from(picker_color);
+                                                let h_hsl = hsv.h * 360.0;
+                                                let s_hsl = hsv.s * 100.0;
+                                                let l_hsl = hsv.v * 100.0;
+
+                                                let h_ryb = RybWheel::hsl_to_ryb(h_hsl);
+                                                let palette = RybWheel::generate_palette(
+                                                    h_ryb,
+                                                    s_hsl,
+                                                    l_hsl,
+                                                    Harmony::Triad,
+                                                    1.0,
+                                                );
+
+                                                self.plugin_context.set_shared_state(
+                                                    "atmosphere_manual_palette_request",
+                                                    palette,
+                                                );
+                                            }
+
+                                            ui.add_space(4.0);
+                                            if ui.button("Réinitialiser (IA)").clicked() {
+                                                self.plugin_context.set_shared_state(
+                                                    "atmosphere_clear_manual_request",
+                                                    true,
+                                                );
+                                                self.ui_state.show_atmosphere_picker = false;
+                                                // Restore focus to editor
+                                                self.plugin_context.set_shared_state(
+                                                    "markdown_editor_focus_requested",
+                                                    true,
+                                                );
+                                            }
+                                        });
+                                    })
+                                    .response;
+
+                                // Robust click-outside detection:
+                                // If any click happened AND it wasn't on the popup AND it wasn't on the button itself...
+                                if ui.input(|i| i.pointer.any_click())
+                                    && !frame_response.hovered()
+                                    && !response.hovered()
+                                {
+                                    self.ui_state.show_atmosphere_picker = false;
+                                    // Restore focus to editor on close
+                                    self.plugin_context
+                                        .set_shared_state("markdown_editor_focus_requested", true);
+                                }
+                            });
+                    }
+
+                    if !emotions.is_empty() || p_idx > 0 {
+                        response.on_hover_ui(|ui| {
+                            ui.label(format!("Climat: {}", emotion_name));
+                            ui.label(format!("Sentiment: {:.2}", sentiment));
+                            if p_idx > 0 {
+                                ui.label(format!("Paragraphe: #{}", p_idx));
+                            }
+                            if !emotions.is_empty() {
+                                ui.separator();
+                                for (emotion, score) in emotions.iter().take(3) {
+                                    ui.label(format!("{}: {:.1}%", emotion, score * 100.0));
+                                }
+                            }
+                        });
+                    }
+                });
+            });
+        });
+    }
+
+    /// Render the tabbed side panels and the central panel.
+    fn render_panels(&mut self, ctx: &egui::Context) {
+        // While a tab is dragged, empty sides are shown to receive it
+        let dragging = egui::DragAndDrop::has_payload_of_type::<PanelTab>(ctx);
+        let mut actions = Vec::new();
+        let focus_mode = self.focus_mode();
+
+        for side in DOCK_SIDES.into_iter().filter(|_| !focus_mode) {
+            let (tabs, active) = self.visible_tabs(side);
+            if tabs.is_empty() && !dragging {
+                continue;
+            }
+
+            match side {
+                PanelPosition::Left => {
+                    let mut frame = egui::Frame::side_top_panel(&ctx.style());
+                    frame.inner_margin.bottom = 0;
+
+                    egui::SidePanel::left("left_panel")
+                        .width_range(200.0..=400.0)
+                        .default_width(self.ui_state.left_panel_width)
+                        .frame(frame)
+                        .show(ctx, |ui| {
+                            actions.extend(self.render_panel_group(ui, side, &tabs, active));
+                        });
+                }
+                PanelPosition::Right => {
+                    egui::SidePanel::right("right_panel")
+                        .width_range(200.0..=400.0)
+                        .default_width(self.ui_state.right_panel_width)
+                        .show(ctx, |ui| {
+                            actions.extend(self.render_panel_group(ui, side, &tabs, active));
+                        });
+                }
+                _ => {
+                    egui::TopBottomPanel::bottom("bottom_panel")
+                        .height_range(100.0..=300.0)
+                        .default_height(self.ui_state.bottom_panel_height)
+                        .show(ctx, |ui| {
+                            actions.extend(self.render_panel_group(ui, side, &tabs, active));
+                        });
+                }
+            }
+        }
+
+        // Central panel (main content area)
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.render_center_panels(ui);
+        });
+
+        if !focus_mode {
+            actions.extend(self.render_floating_panels(ctx));
+        }
+
+        // A tab released away from every tab bar floats where it was dropped
+        if ctx.input(|i| i.pointer.any_released()) {
+            if let Some(tab) = egui::DragAndDrop::take_payload::<PanelTab>(ctx) {
+                let position = ctx.input(|i| i.pointer.interact_pos());
+                actions.push(TabAction::Float(tab.0.clone(), position));
+            }
+        }
+
+        for action in actions {
+            self.apply_tab_action(action);
+        }
+    }
+
+    /// Get the tabs shown on a side and the one selected, if any.
+    ///
+    /// The left side lists all its panels while one of them is open so its
+    /// tab bar stays visible (Zed-style); other sides only list the open ones.
+    fn visible_tabs(&self, side: PanelPosition) -> (Vec<String>, Option<String>) {
+        let group = self.with_layout(|layout_manager| {
+            layout_manager.current_layout().panel_group(side).cloned()
+        });
+        let Some(group) = group else {
+            return (Vec::new(), None);
+        };
+
+        let is_open = |name: &String| {
+            self.panel_plugins.get(name).is_some_and(|plugin| {
+                !plugin.is_closable() || *self.ui_state.open_panels.get(name).unwrap_or(&false)
+            })
+        };
+        let list_all = side == PanelPosition::Left && group.tabs.iter().any(is_open);
+        let tabs: Vec<String> = group
+            .tabs
+            .into_iter()
+            .filter(|name| self.panel_plugins.contains_key(name) && (list_all || is_open(name)))
+            .filter(|name| !self.hidden_by_sprint(name))
+            .collect();
+        let active = group
+            .active
+            .filter(|name| tabs.contains(name))
+            .or_else(|| tabs.first().cloned());
+        (tabs, active)
+    }
+
+    /// Render the tab bar of a side and its selected panel.
+    ///
+    /// The tab bar sits at the bottom of the left side and at the top of the
+    /// others. Tabs can be dragged onto another side's tab bar.
+    fn render_panel_group(
+        &mut self,
+        ui: &mut egui::Ui,
+        side: PanelPosition,
+        tabs: &[String],
+        active: Option<String>,
+    ) -> Vec<TabAction> {
+        let mut actions = Vec::new();
+
+        let layout = if side == PanelPosition::Left {
+            egui::Layout::bottom_up(egui::Align::LEFT)
+        } else {
+            egui::Layout::top_down(egui::Align::LEFT)
+        };
+        ui.with_layout(layout, |ui| {
+            if side == PanelPosition::Left {
+                // Add spacing at the bottom to avoid overlapping the status bar
+                ui.add_space(10.0);
+            }
+
+            self.render_tab_bar(ui, side, tabs, active.as_deref(), &mut actions);
+            ui.separator();
+
+            // Render active panel content in remaining space
+            let zoom = active.as_deref().map_or(1.0, |name| self.panel_zoom(name));
+            match active.and_then(|name| self.panel_plugins.get_mut(&name)) {
+                Some(panel) => {
+                    ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
+                        zoom_text(ui, zoom);
+                        panel.render_panel(ui, &mut self.plugin_context);
+                    });
+                }
+                None => {
+                    ui.centered_and_justified(|ui| {
+                        ui.weak("Drop a panel tab here");
+                    });
+                }
+            }
+        });
+
+        actions
+    }
+
+    /// Render the tabs of a side as a drop zone for tabs of other sides.
+    fn render_tab_bar(
+        &self,
+        ui: &mut egui::Ui,
+        side: PanelPosition,
+        tabs: &[String],
+        active: Option<&str>,
+        actions: &mut Vec<TabAction>,
+    ) {
+        let (_, dropped) = ui.dnd_drop_zone::<PanelTab, _>(egui::Frame::NONE, |ui| {
+            ui.horizontal(|ui| {
+                ui.spacing_mut().item_spacing.x = 0.0; // Compact tabs
+                for name in tabs {
+                    let Some(panel) = self.panel_plugins.get(name) else {
+                        continue;
+                    };
+                    let is_active = active == Some(name.as_str());
+
+                    // Style the tab button
+                    let text =
+                        egui::RichText::new(panel.panel_icon())
+                            .size(16.0)
+                            .color(if is_active {
+                                ui.visuals().text_color()
+                            } else {
+                                ui.visuals().weak_text_color()
+                            });
+
+                    let id = egui::Id::new(("panel_tab", name));
+                    let response = ui
+                        .dnd_drag_source(id, PanelTab(name.clone()), |ui| {
+                            ui.add(
+                                egui::Button::new(text)
+                                    .frame(false)
+                                    .min_size(egui::vec2(40.0, 30.0)),
                             )
-                            .clicked()
-                        {
-                            if let Err(e) = app.save_current_project() {
-                                tracing::error!("Failed to save project: {}", e);
+                        })
+                        .inner;
+
+                    if response.clicked() {
+                        actions.push(TabAction::Select(name.clone()));
+                    }
+
+                    response.context_menu(|ui| {
+                        for other in DOCK_SIDES.into_iter().filter(|other| *other != side) {
+                            let label = format!("Move to {}", other.display_name());
+                            if ui.button(label).clicked() {
+                                actions.push(TabAction::Move(name.clone(), other));
+                                ui.close();
                             }
-                            app.ui_state.active_menu = None;
-                            app.ui_state.menu_expanded = false;
+                        }
+                        if ui.button("Float in Window").clicked() {
+                            actions.push(TabAction::Float(name.clone(), None));
+                            ui.close();
                         }
                         ui.separator();
-                        if ui.button("Export...").clicked() {
-                            app.ui_state.active_menu = None;
-                            app.ui_state.menu_expanded = false;
+                        panel_zoom_menu(ui, name, self.panel_zoom(name), actions);
+                        if panel.is_closable() && side != PanelPosition::Left {
+                            ui.separator();
+                            if ui.button("Close Panel").clicked() {
+                                actions.push(TabAction::Close(name.clone()));
+                                ui.close();
+                            }
                         }
-                    }),
-                );
+                    });
 
-                // Edit Menu
-                render_menu_item(
-                    ui,
-                    MenuId::Edit,
-                    "Edit",
-                    Box::new(|app, ui| {
-                        if ui
-                            .add(
-                                egui::Button::new("Undo")
-                                    .shortcut_text(egui::RichText::new("Ctrl+Z").size(12.0).weak()),
-                            )
-                            .clicked()
-                        {
-                            app.ui_state.active_menu = None;
-                            app.ui_state.menu_expanded = false;
-                        }
-                        if ui
-                            .add(
-                                egui::Button::new("Redo")
-                                    .shortcut_text(egui::RichText::new("Ctrl+Y").size(12.0).weak()),
-                            )
-                            .clicked()
-                        {
-                            app.ui_state.active_menu = None;
-                            app.ui_state.menu_expanded = false;
+                    // Tooltip with title
+                    response.on_hover_text(panel.panel_title());
+                }
+            });
+        });
+
+        if let Some(tab) = dropped {
+            actions.push(TabAction::Move(tab.0.clone(), side));
+        }
+    }
+
+    /// Apply a change made from a side's tab bar.
+    fn apply_tab_action(&mut self, action: TabAction) {
+        match action {
+            TabAction::Select(name) => {
+                self.with_layout(|layout_manager| layout_manager.select_panel(&name));
+            }
+            TabAction::Move(name, side) => {
+                self.with_layout(|layout_manager| layout_manager.move_panel(&name, side, None));
+                self.ui_state.open_panels.insert(name, true);
+            }
+            TabAction::Close(name) => {
+                self.ui_state.open_panels.insert(name, false);
+            }
+            TabAction::Float(name, position) => {
+                let settings = self.with_layout(|layout_manager| {
+                    layout_manager
+                        .current_layout()
+                        .floating_panel(&name)
+                        .cloned()
+                });
+                let mut settings = settings.unwrap_or_else(WindowSettings::floating);
+                if let Some(position) = position.filter(|_| !settings.always_on_top) {
+                    settings.x = Some(position.x);
+                    settings.y = Some(position.y);
+                    self.ui_state.window_moves.insert(name.clone(), position);
+                }
+                self.with_layout(|layout_manager| layout_manager.float_panel(&name, settings));
+                self.ui_state.open_panels.insert(name, true);
+            }
+            TabAction::Pin(name, pinned) => {
+                self.with_layout(|layout_manager| {
+                    let layout = layout_manager.current_layout();
+                    if let Some(settings) = layout.floating_panel(&name) {
+                        // Screen and workspace positions don't match, let the
+                        // new window find its own place
+                        let settings = WindowSettings {
+                            always_on_top: pinned,
+                            x: None,
+                            y: None,
+                            ..settings.clone()
+                        };
+                        layout_manager.float_panel(&name, settings);
+                    }
+                });
+                self.ui_state.pinned_windows.remove(&name);
+            }
+            TabAction::Dock(name) => {
+                let side = self
+                    .panel_plugins
+                    .get(&name)
+                    .map(|plugin| plugin.default_position())
+                    .filter(|position| DOCK_SIDES.contains(position))
+                    .unwrap_or(PanelPosition::Right);
+                self.with_layout(|layout_manager| layout_manager.move_panel(&name, side, None));
+                self.ui_state.pinned_windows.remove(&name);
+            }
+            TabAction::Zoom(name, zoom) => {
+                self.with_layout(|layout_manager| layout_manager.set_panel_zoom(&name, zoom));
+            }
+        }
+    }
+
+    /// Get the text scale of a panel in the current layout.
+    fn panel_zoom(&self, name: &str) -> f32 {
+        self.with_layout(|layout_manager| layout_manager.current_layout().panel_zoom(name))
+    }
+
+    /// Render the panels popped out into their own windows.
+    ///
+    /// Pinned panels get a window of their own, kept above other
+    /// applications; the others float over the workspace.
+    fn render_floating_panels(&mut self, ctx: &egui::Context) -> Vec<TabAction> {
+        let floating: Vec<(String, WindowSettings)> = self.with_layout(|layout_manager| {
+            layout_manager
+                .current_layout()
+                .floating_panels()
+                .map(|(name, settings)| (name.clone(), settings.clone()))
+                .collect()
+        });
+        let mut actions = Vec::new();
+
+        for (name, settings) in floating {
+            let Some(plugin) = self.panel_plugins.get(&name) else {
+                continue;
+            };
+            if plugin.is_closable() && !*self.ui_state.open_panels.get(&name).unwrap_or(&false) {
+                continue;
+            }
+            if self.hidden_by_sprint(&name) {
+                continue;
+            }
+            let title = plugin.panel_title().to_string();
+
+            let window = if settings.always_on_top {
+                self.render_pinned_panel(ctx, &name, &title, &settings, &mut actions)
+            } else {
+                self.render_floating_panel(ctx, &name, &title, &settings, &mut actions)
+            };
+
+            // Keep the window's place in the layout as the user moves it
+            if let Some(window) = window.filter(|window| *window != settings) {
+                self.with_layout(|layout_manager| {
+                    if let Some(settings) = layout_manager
+                        .current_layout_mut()
+                        .floating_panel_mut(&name)
+                    {
+                        *settings = window;
+                    }
+                });
+            }
+        }
+
+        actions
+    }
+
+    /// Render a floating panel in a window over the workspace.
+    ///
+    /// # Returns
+    ///
+    /// The window's place and size, if it was shown.
+    fn render_floating_panel(
+        &mut self,
+        ctx: &egui::Context,
+        name: &str,
+        title: &str,
+        settings: &WindowSettings,
+        actions: &mut Vec<TabAction>,
+    ) -> Option<WindowSettings> {
+        let mut open = true;
+        let mut size = None;
+
+        let mut window = egui::Window::new(title)
+            .id(egui::Id::new(("floating_panel", name)))
+            .default_size([settings.width, settings.height])
+            .open(&mut open);
+        if let (Some(x), Some(y)) = (settings.x, settings.y) {
+            window = window.default_pos([x, y]);
+        }
+        if let Some(position) = self.ui_state.window_moves.remove(name) {
+            window = window.current_pos(position);
+        }
+
+        let response = window.show(ctx, |ui| {
+            size = Some(ui.max_rect().size());
+            self.render_floating_content(ui, name, false, actions);
+        })?;
+
+        if !open {
+            actions.push(TabAction::Dock(name.to_string()));
+        }
+
+        let rect = response.response.rect;
+        let mut window = settings.clone();
+        window.x = Some(rect.min.x);
+        window.y = Some(rect.min.y);
+        if let Some(size) = size {
+            window.width = size.x;
+            window.height = size.y;
+        }
+        Some(window)
+    }
+
+    /// Render a pinned panel in a window of its own, above other
+    /// applications.
+    ///
+    /// # Returns
+    ///
+    /// The window's place and size, if known.
+    fn render_pinned_panel(
+        &mut self,
+        ctx: &egui::Context,
+        name: &str,
+        title: &str,
+        settings: &WindowSettings,
+        actions: &mut Vec<TabAction>,
+    ) -> Option<WindowSettings> {
+        let mut builder = egui::ViewportBuilder::default()
+            .with_title(title)
+            .with_window_level(egui::WindowLevel::AlwaysOnTop);
+        // The saved place only applies when the window opens, the user moves
+        // it afterwards
+        if self.ui_state.pinned_windows.insert(name.to_string()) {
+            builder = builder.with_inner_size([settings.width, settings.height]);
+            if let (Some(x), Some(y)) = (settings.x, settings.y) {
+                builder = builder.with_position([x, y]);
+            }
+        }
+
+        let id = egui::ViewportId::from_hash_of(("floating_panel", name));
+        ctx.show_viewport_immediate(id, builder, |ctx, class| {
+            if class == egui::ViewportClass::Embedded {
+                // Without native windows, float above everything else
+                egui::Window::new(title)
+                    .id(egui::Id::new(("pinned_panel", name)))
+                    .order(egui::Order::Foreground)
+                    .show(ctx, |ui| {
+                        self.render_floating_content(ui, name, true, actions);
+                    });
+                return None;
+            }
+
+            egui::CentralPanel::default().show(ctx, |ui| {
+                self.render_floating_content(ui, name, true, actions);
+            });
+
+            let (close_requested, outer, inner) = ctx.input(|i| {
+                let viewport = i.viewport();
+                (
+                    viewport.close_requested(),
+                    viewport.outer_rect,
+                    viewport.inner_rect,
+                )
+            });
+            if close_requested {
+                actions.push(TabAction::Dock(name.to_string()));
+            }
+
+            let mut window = settings.clone();
+            if let Some(outer) = outer {
+                window.x = Some(outer.min.x);
+                window.y = Some(outer.min.y);
+            }
+            if let Some(inner) = inner {
+                window.width = inner.width();
+                window.height = inner.height();
+            }
+            Some(window)
+        })
+    }
+
+    /// Render the header and content of a floating panel.
+    ///
+    /// The panel's icon can be dragged onto a side's tab bar to dock it
+    /// again; pinned windows, out of the workspace, offer a button instead.
+    fn render_floating_content(
+        &mut self,
+        ui: &mut egui::Ui,
+        name: &str,
+        pinned: bool,
+        actions: &mut Vec<TabAction>,
+    ) {
+        let zoom = self.panel_zoom(name);
+        let Some(panel) = self.panel_plugins.get_mut(name) else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            if pinned {
+                if ui
+                    .small_button("Dock")
+                    .on_hover_text("Put the panel back on its side")
+                    .clicked()
+                {
+                    actions.push(TabAction::Dock(name.to_string()));
+                }
+            } else {
+                let id = egui::Id::new(("floating_tab", name));
+                ui.dnd_drag_source(id, PanelTab(name.to_string()), |ui| {
+                    ui.label(egui::RichText::new(panel.panel_icon()).size(16.0))
+                })
+                .response
+                .on_hover_text("Drag onto a tab bar to dock the panel");
+            }
+            ui.menu_button("Aa", |ui| panel_zoom_menu(ui, name, zoom, actions))
+                .response
+                .on_hover_text("Text size of the panel");
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let hint = if pinned {
+                    "Stop keeping above other windows"
+                } else {
+                    "Keep above other windows"
+                };
+                if ui
+                    .selectable_label(pinned, "📌")
+                    .on_hover_text(hint)
+                    .clicked()
+                {
+                    actions.push(TabAction::Pin(name.to_string(), !pinned));
+                }
+            });
+        });
+        ui.separator();
+
+        ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
+            zoom_text(ui, zoom);
+            panel.render_panel(ui, &mut self.plugin_context);
+        });
+    }
+
+    /// Render the panels of the main content area.
+    fn render_center_panels(&mut self, ui: &mut egui::Ui) {
+        let panels_to_render: Vec<String> = self
+            .panel_plugins
+            .iter()
+            .filter(|(name, plugin)| {
+                plugin.default_position() == PanelPosition::Center
+                    && (!plugin.is_closable()
+                        || *self.ui_state.open_panels.get(*name).unwrap_or(&false))
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        // If no panels are open in center position, show a friendly message and return early.
+        if panels_to_render.is_empty() {
+            ui.centered_and_justified(|ui| {
+                ui.label("No panels open. Use the plugin manager to enable panels.");
+            });
+            return;
+        }
+
+        for panel_name in &panels_to_render {
+            if let Some(panel) = self.panel_plugins.get_mut(panel_name) {
+                if !panel.is_closable() {
+                    // Render directly without header for non-closable panels (like Editor)
+                    panel.render_panel(ui, &mut self.plugin_context);
+                } else {
+                    // Create a collapsing header for each panel
+                    let title = panel.panel_title().to_string();
+                    let header_response = ui.collapsing(title, |ui| {
+                        panel.render_panel(ui, &mut self.plugin_context);
+                    });
+
+                    // Handle panel closing if closable
+                    header_response.header_response.context_menu(|ui| {
+                        if ui.button("Close Panel").clicked() {
+                            self.ui_state.open_panels.insert(panel_name.clone(), false);
+                            ui.close();
                         }
+                    });
+                }
+            }
+        }
+    }
+
+    /// Render modal dialogs.
+    fn render_dialogs(&mut self, ctx: &egui::Context) {
+        // About dialog
+        if self.show_about {
+            egui::Window::new("About Cosmarium")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.heading("Cosmarium");
+                        ui.label(format!("Version {}", env!("CARGO_PKG_VERSION")));
                         ui.separator();
-                        if ui.button("Cut").clicked() {
-                            app.ui_state.active_menu = None;
-                            app.ui_state.menu_expanded = false;
-                        }
-                        if ui.button("Copy").clicked() {
-                            app.ui_state.active_menu = None;
-                            app.ui_state.menu_expanded = false;
+                        ui.label("Next-generation creative writing software");
+                        ui.label("for fiction authors");
+                        ui.separator();
+                        ui.label("Built with Rust and EGUI");
+                        ui.separator();
+                        if ui.button("Close").clicked() {
+                            self.show_about = false;
                         }
-                        if ui.button("Paste").clicked() {
-                            app.ui_state.active_menu = None;
-                            app.ui_state.menu_expanded = false;
+                    });
+                });
+        }
+
+        // Diagnostics: log filter, changed while the application runs
+        if self.show_diagnostics {
+            let mut open = true;
+            egui::Window::new("Diagnostics")
+                .open(&mut open)
+                .default_width(450.0)
+                .show(ctx, |ui| self.render_diagnostics(ui));
+            self.show_diagnostics &= open;
+        }
+
+        // Plugin manager dialog
+        if self.show_plugin_manager {
+            egui::Window::new("Plugin Manager")
+                .collapsible(false)
+                .default_width(600.0)
+                .show(ctx, |ui| {
+                    ui.label("Installed Plugins:");
+                    ui.separator();
+
+                    for (name, plugin) in &self.panel_plugins {
+                        ui.horizontal(|ui| {
+                            ui.label("🔌");
+                            ui.label(name);
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    let is_open =
+                                        self.ui_state.open_panels.get(name).unwrap_or(&false);
+                                    if *is_open {
+                                        ui.colored_label(egui::Color32::GREEN, "Active");
+                                    } else {
+                                        ui.colored_label(egui::Color32::GRAY, "Inactive");
+                                    }
+                                },
+                            );
+                        });
+                        ui.separator();
+                    }
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Close").clicked() {
+                            self.show_plugin_manager = false;
                         }
-                    }),
-                );
+                    });
+                });
+        }
 
-                // View Menu
-                render_menu_item(
-                    ui,
-                    MenuId::View,
-                    "View",
-                    Box::new(|app, ui| {
-                        // We need to collect changes to avoid borrowing issues
-                        let mut panels_to_toggle = Vec::new();
+        // Settings dialog
+        if self.show_settings {
+            egui::Window::new("Settings")
+                .collapsible(false)
+                .default_width(500.0)
+                .show(ctx, |ui| {
+                    ui.label("Application Settings");
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        ui.label("Theme:");
+                        egui::ComboBox::from_label("")
+                            .selected_text(&self.ui_state.current_theme)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(
+                                    &mut self.ui_state.current_theme,
+                                    "Dark".to_string(),
+                                    "Dark",
+                                );
+                                ui.selectable_value(
+                                    &mut self.ui_state.current_theme,
+                                    "Light".to_string(),
+                                    "Light",
+                                );
+                                ui.selectable_value(
+                                    &mut self.ui_state.current_theme,
+                                    "Auto".to_string(),
+                                    "Auto",
+                                );
+                            });
+                    });
 
-                        for (panel_name, panel_plugin) in &app.panel_plugins {
-                            if !panel_plugin.is_closable() {
-                                continue;
-                            }
-                            let is_open =
-                                app.ui_state.open_panels.get(panel_name).unwrap_or(&false);
-                            let mut open = *is_open;
+                    ui.horizontal(|ui| {
+                        ui.label("Language:");
+                        let language = &mut self.config.app.language;
+                        let selected = LANGUAGES
+                            .iter()
+                            .find(|(tag, _)| tag == language)
+                            .map_or(language.as_str(), |(_, name)| name)
+                            .to_string();
+                        egui::ComboBox::from_id_salt("app_language")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                for (tag, name) in LANGUAGES {
+                                    ui.selectable_value(language, tag.to_string(), name);
+                                }
+                            });
+                    })
+                    .response
+                    .on_hover_text(
+                        "Numbers and dates are written the way this language writes them",
+                    );
+
+                    if self.ui_state.current_theme == "Auto" {
+                        let schedule = &mut self.config.ui.theme_schedule;
+                        ui.horizontal(|ui| {
+                            ui.label("Follow:");
+                            ui.radio_value(&mut schedule.source, ThemeSource::System, "System");
+                            ui.radio_value(
+                                &mut schedule.source,
+                                ThemeSource::Sun,
+                                "Sunrise/Sunset",
+                            );
+                            ui.radio_value(&mut schedule.source, ThemeSource::Hours, "Fixed hours");
+                        });
 
-                            if ui.checkbox(&mut open, panel_plugin.panel_title()).clicked() {
-                                panels_to_toggle.push((panel_name.clone(), open));
-                                // Don't close menu for checkboxes usually
+                        match schedule.source {
+                            ThemeSource::Sun => {
+                                ui.horizontal(|ui| {
+                                    ui.label("Latitude:");
+                                    ui.add(
+                                        egui::DragValue::new(&mut schedule.latitude)
+                                            .range(-90.0..=90.0)
+                                            .speed(0.1),
+                                    );
+                                    ui.label("Longitude:");
+                                    ui.add(
+                                        egui::DragValue::new(&mut schedule.longitude)
+                                            .range(-180.0..=180.0)
+                                            .speed(0.1),
+                                    );
+                                });
                             }
+                            ThemeSource::Hours => {
+                                ui.horizontal(|ui| {
+                                    ui.label("Light from:");
+                                    minute_of_day_edit(ui, &mut schedule.day_start_minute);
+                                    ui.label("Dark from:");
+                                    minute_of_day_edit(ui, &mut schedule.night_start_minute);
+                                });
+                            }
+                            ThemeSource::System => {}
                         }
 
-                        for (name, open) in panels_to_toggle {
-                            app.ui_state.open_panels.insert(name, open);
+                        if schedule.source != ThemeSource::System {
+                            ui.horizontal(|ui| {
+                                ui.label("Transition (minutes):");
+                                ui.add(
+                                    egui::DragValue::new(&mut schedule.transition_minutes)
+                                        .range(0..=240),
+                                );
+                            });
                         }
+                    }
 
-                        ui.separator();
+                    ui.separator();
+                    ui.label("Editor");
+                    let editor = &mut self.config.editor;
+                    ui.horizontal(|ui| {
+                        ui.label("Line length guide at column:");
+                        ui.add(egui::DragValue::new(&mut editor.word_wrap_column).range(0..=200));
+                        ui.weak("(0 for none)");
+                    });
+                    ui.add_enabled(
+                        editor.word_wrap_column > 0,
+                        egui::Checkbox::new(
+                            &mut editor.hard_wrap_on_save,
+                            "Break longer lines on save (plain-text workflows)",
+                        ),
+                    );
+                    ui.checkbox(&mut editor.show_whitespace, "Show spaces and tabs");
+                    ui.horizontal(|ui| {
+                        ui.label("Focus mode column width:");
+                        ui.add(
+                            egui::DragValue::new(&mut editor.focus_width)
+                                .range(focus::MIN_WIDTH..=2000.0)
+                                .suffix(" pt"),
+                        );
+                    });
+                    ui.checkbox(
+                        &mut editor.focus_dim_paragraphs,
+                        "Dim the paragraphs around the one being written in focus mode",
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label("Undo steps kept per document:");
+                        ui.add(egui::DragValue::new(&mut editor.undo_depth).range(1..=10_000));
+                    });
+                    ui.checkbox(
+                        &mut editor.undo_by_sentence,
+                        "Undo typing a sentence at a time rather than a word",
+                    );
+                    ui.checkbox(
+                        &mut editor.trim_trailing_whitespace,
+                        "Trim trailing whitespace on save",
+                    );
+                    ui.checkbox(
+                        &mut editor.paste_cleanup,
+                        "Clean up text pasted from word processors",
+                    )
+                    .on_hover_text("Hold Shift while pasting to paste as plain text");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut editor.spell_check_enabled, "Check spelling in");
+                        ui.add_enabled(
+                            editor.spell_check_enabled,
+                            egui::TextEdit::singleline(&mut editor.spell_check_language)
+                                .desired_width(60.0),
+                        )
+                        .on_hover_text("Hunspell dictionary name, e.g. en_US or fr_FR");
+                    });
 
-                        if ui
-                            .checkbox(&mut app.ui_state.show_menu_bar, "Menu Bar")
-                            .clicked()
-                        {
-                            // app.ui_state.active_menu = None; // Optional
-                        }
-                        if ui
-                            .checkbox(&mut app.ui_state.show_status_bar, "Status Bar")
-                            .clicked()
-                        {
-                            // app.ui_state.active_menu = None; // Optional
-                        }
-                    }),
-                );
+                    ui.separator();
+                    ui.label("Writing Goals");
+                    let goals = &mut self.config.goals;
+                    ui.horizontal(|ui| {
+                        ui.label("Words per session:");
+                        ui.add(
+                            egui::DragValue::new(&mut goals.session_words)
+                                .range(0..=100_000)
+                                .speed(10),
+                        );
+                        ui.label("Words per day:");
+                        ui.add(
+                            egui::DragValue::new(&mut goals.daily_words)
+                                .range(0..=100_000)
+                                .speed(10),
+                        );
+                        ui.weak("(0 for none)");
+                    });
+                    ui.checkbox(&mut goals.celebrate, "Congratulate me when I reach a goal");
 
-                // Tools Menu
-                render_menu_item(
-                    ui,
-                    MenuId::Tools,
-                    "Tools",
-                    Box::new(|app, ui| {
-                        if ui.button("Plugin Manager").clicked() {
-                            app.show_plugin_manager = true;
-                            app.ui_state.active_menu = None;
-                            app.ui_state.menu_expanded = false;
-                        }
-                        if ui.button("Settings").clicked() {
-                            app.show_settings = true;
-                            app.ui_state.active_menu = None;
-                            app.ui_state.menu_expanded = false;
-                        }
-                    }),
-                );
+                    ui.separator();
+                    egui::CollapsingHeader::new("Keyboard Shortcuts")
+                        .id_salt("settings_keymap")
+                        .show(ui, |ui| self.render_keymap_settings(ui));
 
-                // Help Menu
-                render_menu_item(
-                    ui,
-                    MenuId::Help,
-                    "Help",
-                    Box::new(|app, ui| {
-                        if ui.button("About Cosmarium").clicked() {
-                            app.show_about = true;
-                            app.ui_state.active_menu = None;
-                            app.ui_state.menu_expanded = false;
-                        }
-                        if ui.button("Documentation").clicked() {
-                            app.ui_state.active_menu = None;
-                            app.ui_state.menu_expanded = false;
+                    ui.separator();
+                    ui.label("Second Copy");
+                    let second_copy = &mut self.config.project.second_copy;
+                    ui.checkbox(
+                        &mut second_copy.enabled,
+                        "Mirror projects to an external drive or network share",
+                    )
+                    .on_hover_text(
+                        "Each project is copied to a folder of its name, and only its \
+                         changed files are copied again",
+                    );
+                    ui.add_enabled_ui(second_copy.enabled, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Location:");
+                            let mut location = second_copy.location.display().to_string();
+                            if ui.text_edit_singleline(&mut location).changed() {
+                                second_copy.location = location.into();
+                            }
+                            if ui.button("Browse…").clicked() {
+                                if let Some(folder) = rfd::FileDialog::new()
+                                    .set_title("Second Copy Location")
+                                    .pick_folder()
+                                {
+                                    second_copy.location = folder;
+                                }
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Update it:");
+                            egui::ComboBox::from_id_salt("second_copy_schedule")
+                                .selected_text(second_copy.schedule.display_name())
+                                .show_ui(ui, |ui| {
+                                    for schedule in SecondCopySchedule::ALL {
+                                        ui.selectable_value(
+                                            &mut second_copy.schedule,
+                                            schedule,
+                                            schedule.display_name(),
+                                        );
+                                    }
+                                });
+                        });
+                        if !second_copy.location.as_os_str().is_empty()
+                            && !second_copy.location.is_dir()
+                        {
+                            ui.colored_label(
+                                ui.visuals().warn_fg_color,
+                                "Not available: is the drive plugged in?",
+                            );
                         }
-                        if ui.button("Report Issue").clicked() {
-                            app.ui_state.active_menu = None;
-                            app.ui_state.menu_expanded = false;
+                        if let Some(project) = &self.current_project {
+                            if let Some(last) = mirror::last_copy(project, &second_copy.location) {
+                                let last: chrono::DateTime<chrono::Local> = last.into();
+                                ui.weak(format!(
+                                    "Last copy of this project: {}",
+                                    last.format("%Y-%m-%d %H:%M")
+                                ));
+                            }
                         }
-                    }),
-                );
-            });
-        });
+                    });
 
-        // Detect click outside menu to close
-        if self.ui_state.active_menu.is_some() {
-            ctx.input(|i| {
-                if i.pointer.any_click() {
-                    // Check if click is outside the top panel
-                    if let Some(pos) = i.pointer.interact_pos() {
-                        // Simple heuristic: if click is below y=100, close menu
-                        // (menu bar is typically at the top)
-                        if pos.y > 100.0 {
-                            self.ui_state.active_menu = None;
-                            self.ui_state.menu_expanded = false;
-                        }
+                    ui.separator();
+                    ui.label("Author Profiles");
+                    let mut removed = None;
+                    for (i, author) in self.config.profiles.iter_mut().enumerate() {
+                        let title = if author.name.trim().is_empty() {
+                            "Unnamed profile"
+                        } else {
+                            author.name.as_str()
+                        };
+                        egui::CollapsingHeader::new(title.to_string())
+                            .id_salt(("author_profile", i))
+                            .show(ui, |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label("Profile name:");
+                                    ui.text_edit_singleline(&mut author.name);
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("Legal name:");
+                                    ui.text_edit_singleline(&mut author.legal_name);
+                                });
+                                ui.label("Pen names (one per line, the first by default):");
+                                let mut pen_names = author.pen_names.join("\n");
+                                if ui
+                                    .add(egui::TextEdit::multiline(&mut pen_names).desired_rows(2))
+                                    .changed()
+                                {
+                                    author.pen_names =
+                                        pen_names.split('\n').map(String::from).collect();
+                                }
+                                ui.label("Biography:");
+                                ui.add(egui::TextEdit::multiline(&mut author.bio).desired_rows(3));
+                                ui.label("Contact details (name, address, e-mail...):");
+                                ui.add(
+                                    egui::TextEdit::multiline(&mut author.contact_info)
+                                        .desired_rows(3),
+                                );
+                                if ui.button("Remove Profile").clicked() {
+                                    removed = Some(i);
+                                }
+                            });
+                    }
+                    if let Some(i) = removed {
+                        self.config.profiles.remove(i);
+                    }
+                    if ui.button("Add Profile").clicked() {
+                        let name = format!("Profile {}", self.config.profiles.len() + 1);
+                        self.config.profiles.push(AuthorProfile::new(&name));
                     }
-                }
-            });
-        }
-    }
-
-    /// Render the status bar.
-    fn render_status_bar(&mut self, ctx: &egui::Context) {
-        if !self.ui_state.show_status_bar {
-            return;
-        }
 
-        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                // Project info
-                if let Some(ref project) = self.current_project {
-                    ui.label(format!(
-                        "📁 {}",
-                        project.file_stem().unwrap_or_default().to_string_lossy()
-                    ));
                     ui.separator();
-                } else {
-                    ui.label("📝 No project");
+                    ui.checkbox(
+                        &mut self.config.export.append_glossary,
+                        "Append the project glossary to exported documents",
+                    );
+
                     ui.separator();
-                }
+                    ui.label("Manuscript Compilation");
+                    let compile = &mut self.config.export.compile;
+                    ui.checkbox(&mut compile.title_page, "Start with a title page");
+                    ui.checkbox(
+                        &mut compile.container_headings,
+                        "Add a heading for each part and chapter",
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label("Between chapters:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut compile.chapter_separator)
+                                .hint_text("Markdown, e.g. ***"),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Between scenes:");
+                        ui.text_edit_singleline(&mut compile.scene_separator);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Chapter epigraphs:");
+                        egui::ComboBox::from_id_salt("compile_epigraphs")
+                            .selected_text(compile.epigraphs.display_name())
+                            .show_ui(ui, |ui| {
+                                for placement in EpigraphPlacement::ALL {
+                                    ui.selectable_value(
+                                        &mut compile.epigraphs,
+                                        placement,
+                                        placement.display_name(),
+                                    );
+                                }
+                            });
+                    });
+                    ui.label("Front matter (dedication, epigraph...):");
+                    ui.add(egui::TextEdit::multiline(&mut compile.front_matter).desired_rows(3))
+                        .on_hover_text(
+                            "{author}, {legal_name}, {bio} and {contact} are replaced by \
+                             the identity the project is published under",
+                        );
+                    ui.label(
+                        "Contact details for standard manuscripts, unless the author profile \
+                         has some:",
+                    );
+                    ui.add(egui::TextEdit::multiline(&mut compile.contact_info).desired_rows(3));
+                    self.render_text_filters(ui);
 
-                // Editor stats (if available from shared state)
-                if let Some(word_count) = self
-                    .plugin_context
-                    .get_shared_state::<usize>("editor_word_count")
-                {
-                    ui.label(format!("Words: {}", word_count));
                     ui.separator();
-                }
-                if let Some(char_count) = self
-                    .plugin_context
-                    .get_shared_state::<usize>("editor_char_count")
-                {
-                    ui.label(format!("Characters: {}", char_count));
+                    ui.label("Word Export");
+                    let word = &mut self.config.export.word;
+                    ui.horizontal(|ui| {
+                        ui.label("Template:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut word.template)
+                                .hint_text("default, manuscript or a .docx/.dotx file"),
+                        );
+                    });
+                    ui.checkbox(
+                        &mut word.preserve_formatting,
+                        "Keep italics, bold and strikethrough",
+                    );
+                    ui.checkbox(
+                        &mut word.include_comments,
+                        "Include comments and annotations as Word comments",
+                    );
+                    ui.checkbox(
+                        &mut word.running_header,
+                        "Header with surname, title and page number",
+                    );
+
                     ui.separator();
-                }
-                if let Some(para_count) = self
-                    .plugin_context
-                    .get_shared_state::<usize>("editor_para_count")
-                {
-                    ui.label(format!("Paragraphs: {}", para_count));
+                    ui.label("Pandoc");
+                    let pandoc = &mut self.config.export.pandoc;
+                    ui.checkbox(
+                        &mut pandoc.enabled,
+                        "Offer OpenDocument, LaTeX and EPUB exports through pandoc (after restart)",
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label("Executable:");
+                        ui.text_edit_singleline(&mut pandoc.executable);
+                    });
+
                     ui.separator();
-                }
+                    ui.label("Anonymized Export");
+                    let anonymize = &mut self.config.export.anonymize;
+                    ui.label("Names to remove besides the project author (one per line):");
+                    let mut names = anonymize.identifying_names.join("\n");
+                    if ui
+                        .add(egui::TextEdit::multiline(&mut names).desired_rows(3))
+                        .changed()
+                    {
+                        anonymize.identifying_names = names.split('\n').map(String::from).collect();
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Replace with:");
+                        ui.text_edit_singleline(&mut anonymize.placeholder);
+                    });
 
-                // Plugin status
-                ui.label(format!(
-                    "🔌 {} plugins",
-                    self.panel_plugins.len() + self.plugins.len()
-                ));
+                    if self.current_project.is_some() {
+                        ui.separator();
+                        ui.label("Project Word Count");
+                        let rules = &mut self.word_count_rules;
+                        ui.checkbox(&mut rules.exclude_headings, "Exclude headings");
+                        ui.checkbox(&mut rules.exclude_front_matter, "Exclude front matter");
+                        ui.checkbox(
+                            &mut rules.exclude_comments,
+                            "Exclude comments and annotations",
+                        );
+                        ui.checkbox(
+                            &mut rules.exclude_bracketed_notes,
+                            "Exclude bracketed notes ([TODO: ...])",
+                        );
 
-                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    // Application info
-                    ui.label(format!("Cosmarium v{}", env!("CARGO_PKG_VERSION")));
-                    ui.separator();
+                        ui.horizontal(|ui| {
+                            ui.label("Words to write in this project:");
+                            ui.add(
+                                egui::DragValue::new(&mut self.project_word_target)
+                                    .range(0..=1_000_000)
+                                    .speed(100),
+                            );
+                            ui.weak("(0 for none)");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Due by:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.project_deadline)
+                                    .hint_text("YYYY-MM-DD")
+                                    .desired_width(90.0),
+                            );
+                            if parse_deadline(&self.project_deadline).is_err() {
+                                ui.colored_label(ui.visuals().error_fg_color, "Not a date");
+                            } else {
+                                ui.weak("(empty for none)");
+                            }
+                        });
 
-                    // Atmosphere Status
-                    let sentiment = self.plugin_context.get_shared_state::<f32>("atmosphere_sentiment").unwrap_or(0.0);
+                        ui.separator();
+                        ui.label("Published As");
+                        let (profile_name, pen_name) = &mut self.published_as;
+                        ui.horizontal(|ui| {
+                            ui.label("Author profile:");
+                            let selected = if profile_name.is_empty() {
+                                "None (project author)"
+                            } else {
+                                profile_name.as_str()
+                            };
+                            egui::ComboBox::from_id_salt("project_profile")
+                                .selected_text(selected.to_string())
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        profile_name,
+                                        String::new(),
+                                        "None (project author)",
+                                    );
+                                    for author in &self.config.profiles {
+                                        ui.selectable_value(
+                                            profile_name,
+                                            author.name.clone(),
+                                            &author.name,
+                                        );
+                                    }
+                                });
+                        });
+                        match profile::find(&self.config.profiles, profile_name) {
+                            Some(author) => {
+                                ui.horizontal(|ui| {
+                                    ui.label("Pen name:");
+                                    let byline = author.identity(pen_name).byline;
+                                    egui::ComboBox::from_id_salt("project_pen_name")
+                                        .selected_text(byline)
+                                        .show_ui(ui, |ui| {
+                                            for name in &author.pen_names {
+                                                if !name.trim().is_empty() {
+                                                    ui.selectable_value(
+                                                        pen_name,
+                                                        name.trim().to_string(),
+                                                        name.trim(),
+                                                    );
+                                                }
+                                            }
+                                        });
+                                });
+                            }
+                            None if !profile_name.is_empty() => {
+                                ui.colored_label(
+                                    ui.visuals().warn_fg_color,
+                                    "No such profile: the project author signs",
+                                );
+                            }
+                            None => {}
+                        }
+
+                        if !self.folder_watches.is_empty() {
+                            ui.separator();
+                            ui.label("Watched Folders");
+                            let mut stopped = None;
+                            for watch in &self.folder_watches {
+                                ui.horizontal(|ui| {
+                                    ui.label(format!(
+                                        "{} → {}",
+                                        watch.source().display(),
+                                        watch.target()
+                                    ));
+                                    if ui.small_button("Stop watching").clicked() {
+                                        stopped = Some(watch.source().to_path_buf());
+                                    }
+                                });
+                            }
+                            if let Some(source) = stopped {
+                                if let Err(e) = self.stop_watching_folder(&source) {
+                                    tracing::error!("Failed to stop watching {:?}: {}", source, e);
+                                }
+                            }
+                        }
 
-                    let is_analyzing = self.plugin_context.get_shared_state::<bool>("atmosphere_analyzing").unwrap_or(false);
-                    let emotions = self.plugin_context.get_shared_state::<Vec<(String, f32)>>("atmosphere_emotions").unwrap_or_default();
-                    let p_idx = self.plugin_context.get_shared_state::<usize>("atmosphere_paragraph_idx").unwrap_or(0);
-                    let emotion_name = self.plugin_context.get_shared_state::<String>("atmosphere_current_emotion").unwrap_or_else(|| "Neutral".to_string());
+                        ui.separator();
+                        ui.label("Scene Headings");
+                        ui.horizontal(|ui| {
+                            ui.label("Format:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.scene_heading_format)
+                                    .hint_text("### {location} — {time} — {POV}"),
+                            );
+                        });
+                        let pattern = self.scene_heading_format.trim();
+                        if !pattern.is_empty() {
+                            if let Err(e) = SceneHeadingFormat::parse(pattern) {
+                                ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+                            }
+                        }
 
-                    if p_idx > 0 {
-                        ui.label(format!("Atmo: P{}", p_idx));
-                    }
+                        ui.separator();
+                        ui.label("Line Endings");
+                        ui.horizontal(|ui| {
+                            ui.label("New documents and text exports:");
+                            egui::ComboBox::from_id_salt("project_line_ending")
+                                .selected_text(self.line_ending.label())
+                                .show_ui(ui, |ui| {
+                                    for line_ending in LineEnding::ALL {
+                                        ui.selectable_value(
+                                            &mut self.line_ending,
+                                            line_ending,
+                                            line_ending.label(),
+                                        );
+                                    }
+                                });
+                        });
 
-                    if is_analyzing {
-                        ui.add(egui::Spinner::new().size(12.0));
-                    }
+                        ui.separator();
+                        ui.label("Version Control");
+                        ui.checkbox(&mut self.auto_commit, "Commit the changes when saving")
+                            .on_hover_text(
+                                "Each save commits the changes to the project's Git repository, with a summary of them as message. Otherwise, commit them from the Git History panel.",
+                            );
 
-                    // Color square using the current theme background
-                    let color = ui.visuals().panel_fill;
-                    
-                    let (rect, response) = ui.allocate_at_least(egui::vec2(12.0, 12.0), egui::Sense::click());
-                    ui.painter().rect_filled(rect, 2.0, color);
-                    ui.painter().rect_stroke(rect, 2.0, egui::Stroke::new(1.0, egui::Color32::from_gray(128)), egui::StrokeKind::Outside);
+                        ui.separator();
+                        ui.label("Quotes");
+                        ui.horizontal(|ui| {
+                            ui.label("Typed and pasted text:");
+                            egui::ComboBox::from_id_salt("project_quote_style")
+                                .selected_text(self.quote_style.label())
+                                .show_ui(ui, |ui| {
+                                    for style in QuoteStyle::ALL {
+                                        ui.selectable_value(
+                                            &mut self.quote_style,
+                                            style,
+                                            style.label(),
+                                        );
+                                    }
+                                });
+                        });
 
-                    let popup_id = ui.make_persistent_id("atmosphere_color_picker_popup");
-                    if response.clicked() {
-                        self.ui_state.show_atmosphere_picker = !self.ui_state.show_atmosphere_picker;
-                    }
+                        ui.separator();
+                        ui.label("Auto-correction");
+                        let autocorrect = &mut self.autocorrect;
+                        ui.checkbox(&mut autocorrect.enabled, "Correct text as it is typed");
+                        ui.add_enabled_ui(autocorrect.enabled, |ui| {
+                            ui.checkbox(
+                                &mut autocorrect.sentence_case,
+                                "Capitalize the first word of sentences",
+                            );
+                            ui.checkbox(
+                                &mut autocorrect.standalone_i,
+                                "Capitalize a standalone \"i\"",
+                            );
+                            ui.checkbox(
+                                &mut autocorrect.single_space,
+                                "Drop a second space after a full stop",
+                            );
+                            let mut removed = None;
+                            egui::ScrollArea::vertical()
+                                .id_salt("project_autocorrect")
+                                .max_height(120.0)
+                                .show(ui, |ui| {
+                                    for (i, correction) in
+                                        autocorrect.corrections.iter_mut().enumerate()
+                                    {
+                                        ui.horizontal(|ui| {
+                                            ui.add(
+                                                egui::TextEdit::singleline(&mut correction.typo)
+                                                    .desired_width(100.0),
+                                            );
+                                            ui.label("→");
+                                            ui.add(
+                                                egui::TextEdit::singleline(&mut correction.fix)
+                                                    .desired_width(100.0),
+                                            );
+                                            if ui
+                                                .small_button("✖")
+                                                .on_hover_text("Remove")
+                                                .clicked()
+                                            {
+                                                removed = Some(i);
+                                            }
+                                        });
+                                    }
+                                });
+                            if let Some(i) = removed {
+                                autocorrect.corrections.remove(i);
+                            }
+                            ui.horizontal(|ui| {
+                                let (typo, fix) = &mut self.new_correction;
+                                ui.add(
+                                    egui::TextEdit::singleline(typo)
+                                        .hint_text("Typo")
+                                        .desired_width(100.0),
+                                );
+                                ui.label("→");
+                                ui.add(
+                                    egui::TextEdit::singleline(fix)
+                                        .hint_text("Correction")
+                                        .desired_width(100.0),
+                                );
+                                if ui.button("Add Correction").clicked()
+                                    && !typo.trim().is_empty()
+                                    && !fix.trim().is_empty()
+                                {
+                                    autocorrect
+                                        .corrections
+                                        .push(Correction::new(typo.trim(), fix.trim()));
+                                    typo.clear();
+                                    fix.clear();
+                                }
+                            });
+                        });
 
-                    if self.ui_state.show_atmosphere_picker {
-                        let pos = response.rect.left_bottom();
-                        egui::Area::new(popup_id)
-                            .order(egui::Order::Foreground)
-                            .fixed_pos(pos)
-                            .constrain(true)
-                            .show(ui.ctx(), |ui| {
-                                let frame_response = egui::Frame::popup(ui.style()).show(ui, |ui| {
-                                    ui.set_min_width(300.0);
-                                    ui.vertical(|ui| {
-                                        ui.label("Climat Manuel :");
-                                        let mut picker_color = self.ui_state.atmosphere_picker_color;
-                                        if egui::color_picker::color_picker_color32(ui, &mut picker_color, egui::color_picker::Alpha::Opaque) {
-                                            self.ui_state.atmosphere_picker_color = picker_color;
-                                            
-                                            // Generate palette and send request
-                                            let hsv = egui::ecolor::Hsva::from(picker_color);
-                                            let h_hsl = hsv.h * 360.0;
-                                            let s_hsl = hsv.s * 100.0;
-                                            let l_hsl = hsv.v * 100.0;
-                                            
-                                            let h_ryb = RybWheel::hsl_to_ryb(h_hsl);
-                                            let palette = RybWheel::generate_palette(h_ryb, s_hsl, l_hsl, Harmony::Triad, 1.0);
-                                            
-                                            self.plugin_context.set_shared_state("atmosphere_manual_palette_request", palette);
+                        ui.separator();
+                        ui.label("Project Dictionary");
+                        ui.horizontal(|ui| {
+                            ui.label("Plural/possessive suffixes:");
+                            ui.text_edit_singleline(&mut self.dictionary_suffixes);
+                        });
+                        let mut removed = None;
+                        egui::ScrollArea::vertical()
+                            .id_salt("project_dictionary")
+                            .max_height(160.0)
+                            .show(ui, |ui| {
+                                let entries = &mut self.project_dictionary.entries;
+                                for (i, entry) in entries.iter_mut().enumerate() {
+                                    ui.horizontal(|ui| {
+                                        ui.label(&entry.word);
+                                        ui.add(
+                                            egui::TextEdit::singleline(&mut entry.definition)
+                                                .hint_text("Definition"),
+                                        );
+                                        let mut forms = entry.forms.join(" ");
+                                        if ui
+                                            .add(
+                                                egui::TextEdit::singleline(&mut forms)
+                                                    .hint_text("Irregular forms")
+                                                    .desired_width(120.0),
+                                            )
+                                            .changed()
+                                        {
+                                            entry.forms =
+                                                forms.split(' ').map(String::from).collect();
                                         }
-                                        
-                                        ui.add_space(4.0);
-                                        if ui.button("Réinitialiser (IA)").clicked() {
-                                            self.plugin_context.set_shared_state("atmosphere_clear_manual_request", true);
-                                            self.ui_state.show_atmosphere_picker = false;
-                                            // Restore focus to editor
-                                            self.plugin_context.set_shared_state("markdown_editor_focus_requested", true);
+                                        if ui.small_button("✖").on_hover_text("Remove").clicked()
+                                        {
+                                            removed = Some(i);
                                         }
                                     });
-                                }).response;
-
-                                // Robust click-outside detection:
-                                // If any click happened AND it wasn't on the popup AND it wasn't on the button itself...
-                                if ui.input(|i| i.pointer.any_click()) 
-                                   && !frame_response.hovered() 
-                                   && !response.hovered() 
-                                {
-                                    self.ui_state.show_atmosphere_picker = false;
-                                    // Restore focus to editor on close
-                                    self.plugin_context.set_shared_state("markdown_editor_focus_requested", true);
                                 }
                             });
+                        if let Some(i) = removed {
+                            self.project_dictionary.entries.remove(i);
+                        }
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut self.new_dictionary_word);
+                            if ui.button("Add Word").clicked() {
+                                self.project_dictionary.add(&self.new_dictionary_word);
+                                self.new_dictionary_word.clear();
+                            }
+                        });
                     }
 
-                    if !emotions.is_empty() || p_idx > 0 {
-                        response.on_hover_ui(|ui| {
-                            ui.label(format!("Climat: {}", emotion_name));
-                            ui.label(format!("Sentiment: {:.2}", sentiment));
-                            if p_idx > 0 {
-                                ui.label(format!("Paragraphe: #{}", p_idx));
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Save").clicked() {
+                            self.config.ui.theme = self.ui_state.current_theme.to_lowercase();
+                            self.config
+                                .export
+                                .anonymize
+                                .identifying_names
+                                .retain(|name| !name.trim().is_empty());
+                            for author in &mut self.config.profiles {
+                                author.pen_names.retain(|name| !name.trim().is_empty());
                             }
-                            if !emotions.is_empty() {
-                                ui.separator();
-                                for (emotion, score) in emotions.iter().take(3) {
-                                    ui.label(format!("{}: {:.1}%", emotion, score * 100.0));
+                            self.apply_theme_config();
+                            self.apply_editor_config();
+                            self.apply_locale();
+                            self.store_plugin_settings();
+                            self.writing_goals.set_config(self.config.goals);
+                            if let Err(e) = self.config.save() {
+                                tracing::error!("Failed to save settings: {}", e);
+                            }
+                            self.recording_shortcut = None;
+                            if self.keymap != *self.plugin_context.keymap() {
+                                if let Err(e) = keymap::save(&self.keymap) {
+                                    tracing::error!("Failed to save the keymap: {}", e);
                                 }
+                                self.plugin_context.set_keymap(self.keymap.clone());
                             }
-                        });
-                    }
+                            if self.current_project.is_some() {
+                                if let Err(e) = self.save_word_count_rules() {
+                                    tracing::error!("Failed to save word count rules: {}", e);
+                                }
+                                if let Err(e) = self.save_project_word_target() {
+                                    tracing::error!(
+                                        "Failed to save the project word target: {}",
+                                        e
+                                    );
+                                }
+                                let dictionary = &mut self.project_dictionary;
+                                dictionary.morphology.suffixes = self
+                                    .dictionary_suffixes
+                                    .split_whitespace()
+                                    .map(String::from)
+                                    .collect();
+                                for entry in &mut dictionary.entries {
+                                    entry.forms.retain(|form| !form.trim().is_empty());
+                                }
+                                if let Err(e) = self.save_project_dictionary() {
+                                    tracing::error!("Failed to save the project dictionary: {}", e);
+                                }
+                                if let Err(e) = self.save_scene_heading_format() {
+                                    tracing::error!(
+                                        "Failed to save the scene heading format: {}",
+                                        e
+                                    );
+                                }
+                                self.save_line_ending();
+                                self.save_auto_commit();
+                                self.save_published_as();
+                                if let Err(e) = self.save_quote_style() {
+                                    tracing::error!("Failed to save the quote style: {}", e);
+                                }
+                                if let Err(e) = self.save_autocorrect() {
+                                    tracing::error!("Failed to save the auto-correction: {}", e);
+                                }
+                            }
+                            self.show_settings = false;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.keymap = self.plugin_context.keymap().clone();
+                            self.recording_shortcut = None;
+                            self.load_word_count_rules();
+                            self.load_project_dictionary();
+                            self.load_scene_heading_format();
+                            self.load_project_word_target();
+                            self.load_line_ending();
+                            self.load_auto_commit();
+                            self.load_published_as();
+                            self.load_quote_style();
+                            self.load_autocorrect();
+                            self.show_settings = false;
+                        }
+                    });
                 });
-            });
-        });
-    }
+        }
 
-    /// Render panels based on their position.
-    fn render_panels(&mut self, ctx: &egui::Context) {
-        // Left panel
-        if self.has_panels_in_position(cosmarium_plugin_api::PanelPosition::Left) {
-            let mut frame = egui::Frame::side_top_panel(&ctx.style());
-            frame.inner_margin.bottom = 0;
-
-            egui::SidePanel::left("left_panel")
-                .width_range(200.0..=400.0)
-                .default_width(self.ui_state.left_panel_width)
-                .frame(frame)
+        // Glossary term consistency report
+        if let Some(issues) = &self.term_issues {
+            let mut open = None;
+            let mut close = false;
+            egui::Window::new("Glossary Terms")
+                .collapsible(false)
+                .default_width(500.0)
                 .show(ctx, |ui| {
-                    self.render_panels_in_position(ui, cosmarium_plugin_api::PanelPosition::Left);
+                    if issues.is_empty() {
+                        ui.label("Every glossary term is spelled consistently.");
+                    } else {
+                        ui.label(format!(
+                            "{} occurrences differ from the glossary:",
+                            self.locale.format_count(issues.len())
+                        ));
+                        ui.separator();
+                        egui::ScrollArea::vertical()
+                            .id_salt("glossary_term_issues")
+                            .max_height(300.0)
+                            .show(ui, |ui| {
+                                for (path, issue) in issues {
+                                    let file =
+                                        path.file_name().unwrap_or_default().to_string_lossy();
+                                    ui.horizontal(|ui| {
+                                        if ui
+                                            .link(format!("{}:{}", file, issue.line))
+                                            .on_hover_text(path.display().to_string())
+                                            .clicked()
+                                        {
+                                            open = Some((path.clone(), issue.line));
+                                        }
+                                        ui.label(format!(
+                                            "“{}” for “{}” ({})",
+                                            issue.found,
+                                            issue.term,
+                                            issue.kind.label()
+                                        ));
+                                    });
+                                }
+                            });
+                    }
+                    ui.separator();
+                    if ui.button("Close").clicked() {
+                        close = true;
+                    }
                 });
+            if let Some((path, line)) = open {
+                if let Err(e) = self.open_document(&path, Some(line)) {
+                    tracing::error!("Failed to open document {:?}: {}", path, e);
+                }
+            }
+            if close {
+                self.term_issues = None;
+            }
         }
 
-        // Right panel
-        if self.has_panels_in_position(cosmarium_plugin_api::PanelPosition::Right) {
-            egui::SidePanel::right("right_panel")
-                .width_range(200.0..=400.0)
-                .default_width(self.ui_state.right_panel_width)
+        // Accessibility checklist of the manuscript
+        if let Some(findings) = &self.accessibility_report {
+            let mut open = None;
+            let mut close = false;
+            egui::Window::new("Accessibility Checklist")
+                .collapsible(false)
+                .default_width(500.0)
                 .show(ctx, |ui| {
-                    self.render_panels_in_position(ui, cosmarium_plugin_api::PanelPosition::Right);
+                    if findings.is_empty() {
+                        ui.label(
+                            "Every image has an alternative text and the headings \
+                             follow each other.",
+                        );
+                    } else {
+                        ui.label(format!(
+                            "{} problems for readers using assistive technologies:",
+                            self.locale.format_count(findings.len())
+                        ));
+                        ui.separator();
+                        egui::ScrollArea::vertical()
+                            .id_salt("accessibility_findings")
+                            .max_height(300.0)
+                            .show(ui, |ui| {
+                                for (finding, line) in findings {
+                                    ui.horizontal(|ui| {
+                                        if let Some(path) = &finding.path {
+                                            let file = path
+                                                .file_name()
+                                                .unwrap_or_default()
+                                                .to_string_lossy();
+                                            if ui
+                                                .link(format!("{}:{}", file, line))
+                                                .on_hover_text(path.display().to_string())
+                                                .clicked()
+                                            {
+                                                open = Some((path.clone(), *line));
+                                            }
+                                        } else if !finding.section.is_empty() {
+                                            ui.label(format!("{}:{}", finding.section, line));
+                                        }
+                                        let mut text = finding.kind.label().to_string();
+                                        if !finding.detail.is_empty() {
+                                            text = format!("{} ({})", text, finding.detail);
+                                        }
+                                        ui.label(text);
+                                    });
+                                }
+                            });
+                    }
+                    ui.separator();
+                    if ui.button("Close").clicked() {
+                        close = true;
+                    }
                 });
+            if let Some((path, line)) = open {
+                if let Err(e) = self.open_document(&path, Some(line)) {
+                    tracing::error!("Failed to open document {:?}: {}", path, e);
+                }
+            }
+            if close {
+                self.accessibility_report = None;
+            }
         }
 
-        // Bottom panel
-        if self.has_panels_in_position(cosmarium_plugin_api::PanelPosition::Bottom) {
-            egui::TopBottomPanel::bottom("bottom_panel")
-                .height_range(100.0..=300.0)
-                .default_height(self.ui_state.bottom_panel_height)
+        // Progress proof just exported
+        if let Some((proof, path)) = &self.progress_proof {
+            let mut close = false;
+            egui::Window::new("Progress Proof")
+                .collapsible(false)
+                .default_width(460.0)
                 .show(ctx, |ui| {
-                    self.render_panels_in_position(ui, cosmarium_plugin_api::PanelPosition::Bottom);
+                    ui.label(format!(
+                        "{} words in {}, signed on {}.",
+                        self.locale.format_count(proof.word_count),
+                        proof.project,
+                        proof.timestamp.format("%Y-%m-%d %H:%M UTC")
+                    ));
+                    if let Some(commit) = &proof.commit {
+                        ui.label(format!("Last commit: {}", commit));
+                    }
+                    ui.label(format!("Saved to {}", path.display()));
+                    ui.separator();
+                    ui.label("Public key, for others to check your proofs against:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut proof.public_key.as_str())
+                            .font(egui::TextStyle::Monospace)
+                            .desired_width(f32::INFINITY),
+                    );
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "Check it with: cosmarium verify-proof {} --project <folder>",
+                            path.display()
+                        ))
+                        .weak(),
+                    );
+                    ui.separator();
+                    if ui.button("Close").clicked() {
+                        close = true;
+                    }
                 });
-        }
-
-        // Central panel (main content area)
-        egui::CentralPanel::default().show(ctx, |ui| {
-            self.render_panels_in_position(ui, cosmarium_plugin_api::PanelPosition::Center);
-        });
-    }
-
-    /// Check if there are any panels in the specified position that should be shown.
-    fn has_panels_in_position(&self, position: cosmarium_plugin_api::PanelPosition) -> bool {
-        self.panel_plugins.iter().any(|(name, plugin)| {
-            if plugin.default_position() != position {
-                return false;
+            if close {
+                self.progress_proof = None;
             }
+        }
 
-            // For Left panel (Zed-style), we always show the container if plugins exist,
-            // so the tab bar is visible.
-            if position == cosmarium_plugin_api::PanelPosition::Left {
-                return true;
+        // Project format upgrade report
+        if let Some(report) = &self.migration_report {
+            let mut close = false;
+            egui::Window::new("Project Upgraded")
+                .collapsible(false)
+                .default_width(500.0)
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "This project was saved by an older version of Cosmarium and was \
+                         upgraded from format {} to {}.",
+                        report.from, report.to
+                    ));
+                    ui.horizontal(|ui| {
+                        ui.label("The previous files were backed up to");
+                        ui.monospace(report.backup.display().to_string());
+                    });
+                    ui.separator();
+                    for step in &report.steps {
+                        ui.label(format!("• {}", step.description));
+                        for note in &step.notes {
+                            ui.weak(format!("    {}", note));
+                        }
+                    }
+                    ui.separator();
+                    if ui.button("Close").clicked() {
+                        close = true;
+                    }
+                });
+            if close {
+                self.migration_report = None;
             }
-
-            !plugin.is_closable() || *self.ui_state.open_panels.get(name).unwrap_or(&false)
-        })
-    }
-
-    /// Render all panels in the specified position.
-    fn render_panels_in_position(
-        &mut self,
-        ui: &mut egui::Ui,
-        position: cosmarium_plugin_api::PanelPosition,
-    ) {
-        let panels_to_render: Vec<String> = self
-            .panel_plugins
-            .iter()
-            .filter_map(|(name, plugin)| {
-                if plugin.default_position() != position {
-                    return None;
-                }
-
-                // For Left panel (Zed-style), we include all plugins in the list
-                if position == cosmarium_plugin_api::PanelPosition::Left {
-                    return Some(name.clone());
-                }
-
-                if !plugin.is_closable() || *self.ui_state.open_panels.get(name).unwrap_or(&false) {
-                    Some(name.clone())
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        // If no panels are open in center position, show a friendly message and return early.
-        if panels_to_render.is_empty() && position == cosmarium_plugin_api::PanelPosition::Center {
-            ui.centered_and_justified(|ui| {
-                ui.label("No panels open. Use the plugin manager to enable panels.");
-            });
-            return;
         }
 
-        // Special handling for Left panel (Zed-style tabs)
-        if position == cosmarium_plugin_api::PanelPosition::Left {
-            if panels_to_render.is_empty() {
-                return;
+        // Project integrity report
+        if let Some(report) = &self.integrity_report {
+            let mut close = false;
+            let mut check_again = false;
+            let mut apply = None;
+            egui::Window::new("Project Integrity")
+                .collapsible(false)
+                .default_width(550.0)
+                .show(ctx, |ui| {
+                    if report.is_empty() {
+                        ui.label("No problems found.");
+                    } else {
+                        ui.label(format!(
+                            "{} problems found. Repairs to the project state are saved \
+                             right away.",
+                            report.issues.len()
+                        ));
+                        ui.separator();
+                        egui::ScrollArea::vertical()
+                            .id_salt("integrity_issues")
+                            .max_height(350.0)
+                            .show(ui, |ui| {
+                                for (kind, issues) in report.groups() {
+                                    ui.strong(format!("{} ({})", kind.label(), issues.len()));
+                                    for issue in issues {
+                                        ui.label(&issue.message);
+                                        ui.horizontal_wrapped(|ui| {
+                                            ui.add_space(12.0);
+                                            if issue.repairs.is_empty() {
+                                                ui.weak("Fix by hand");
+                                            }
+                                            for fix in &issue.repairs {
+                                                if ui.small_button(fix.label()).clicked() {
+                                                    apply = Some((issue.clone(), fix.clone()));
+                                                }
+                                            }
+                                        });
+                                    }
+                                    ui.add_space(6.0);
+                                }
+                            });
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        check_again = ui.button("Check Again").clicked();
+                        if ui.button("Close").clicked() {
+                            close = true;
+                        }
+                    });
+                });
+            if let Some((issue, fix)) = apply {
+                self.repair_project_issue(&issue, &fix);
+            } else if check_again {
+                self.check_project_integrity();
+            } else if close {
+                self.integrity_report = None;
             }
+        }
 
-            // Ensure we have an active panel
-            if self.ui_state.active_left_panel.is_none()
-                || !panels_to_render.contains(self.ui_state.active_left_panel.as_ref().unwrap())
-            {
-                self.ui_state.active_left_panel = Some(panels_to_render[0].clone());
+        // Congratulations on the writing goals reached, kept for the end of
+        // a sprint in focus
+        let focused = self.sprint().is_some_and(|sprint| sprint.focus);
+        if !self.reached_goals.is_empty() && !focused {
+            let mut close = false;
+            egui::Window::new("🎉 Goal Reached")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 60.0))
+                .show(ctx, |ui| {
+                    for progress in &self.reached_goals {
+                        ui.label(goal_reached_message(progress, &self.locale));
+                    }
+                    ui.separator();
+                    if ui.button("Keep writing").clicked() {
+                        close = true;
+                    }
+                });
+            if close {
+                self.reached_goals.clear();
             }
+        }
 
-            let active_panel_name = self.ui_state.active_left_panel.clone().unwrap();
-
-            ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
-                // Add spacing at the bottom to avoid overlapping the status bar
-                ui.add_space(10.0);
-
-                // Render tab bar at the bottom
-                ui.horizontal(|ui| {
-                    ui.spacing_mut().item_spacing.x = 0.0; // Compact tabs
-                    for panel_name in &panels_to_render {
-                        if let Some(panel) = self.panel_plugins.get(panel_name) {
-                            let icon = panel.panel_icon();
-                            let is_active = *panel_name == active_panel_name;
-
-                            // Style the tab button
-                            let text = egui::RichText::new(icon).size(16.0).color(if is_active {
-                                ui.visuals().text_color()
-                            } else {
-                                ui.visuals().weak_text_color()
-                            });
-
-                            let response = ui.add(
-                                egui::Button::new(text)
-                                    .frame(false)
-                                    .min_size(egui::vec2(40.0, 30.0)),
-                            );
-
-                            if response.clicked() {
-                                self.ui_state.active_left_panel = Some(panel_name.clone());
-                            }
-
-                            // Tooltip with title
-                            if response.hovered() {
-                                response.on_hover_text(panel.panel_title());
+        // Damaged project state report
+        if !self.load_diagnostics.is_empty() {
+            let mut close = false;
+            egui::Window::new("Project State Recovered")
+                .collapsible(false)
+                .default_width(500.0)
+                .show(ctx, |ui| {
+                    ui.label(
+                        "Parts of the project state could not be read. \
+                         They were recovered as follows; save the project to keep the result.",
+                    );
+                    ui.separator();
+                    egui::ScrollArea::vertical()
+                        .id_salt("load_diagnostics")
+                        .max_height(300.0)
+                        .show(ui, |ui| {
+                            for diagnostic in &self.load_diagnostics {
+                                ui.label(diagnostic.to_string());
                             }
-                        }
+                        });
+                    ui.separator();
+                    if ui.button("Close").clicked() {
+                        close = true;
                     }
                 });
-                ui.separator();
+            if close {
+                self.load_diagnostics.clear();
+            }
+        }
 
-                // Render active panel content in remaining space
-                if let Some(panel) = self.panel_plugins.get_mut(&active_panel_name) {
-                    // We want the panel to fill the space
-                    ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
-                        panel.render_panel(ui, &mut self.plugin_context);
-                    });
-                }
+        // Command palette
+        if let Some(palette) = &mut self.command_palette {
+            let query = palette.query.to_lowercase();
+            let matches: Vec<(String, String, String)> = self
+                .plugin_context
+                .commands()
+                .iter()
+                .filter(|command| {
+                    command.id != AppCommand::CommandPalette.id()
+                        && command.title.to_lowercase().contains(&query)
+                })
+                .map(|command| {
+                    let shortcut = self
+                        .plugin_context
+                        .keymap()
+                        .shortcut(command)
+                        .map(|shortcut| shortcut.to_string())
+                        .unwrap_or_default();
+                    (command.id.clone(), command.title.clone(), shortcut)
+                })
+                .collect();
+            let (down, up, enter, escape) = ctx.input_mut(|input| {
+                (
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+                )
             });
-        } else {
-            // Standard stacking behavior for other positions
-            for panel_name in &panels_to_render {
-                if let Some(panel) = self.panel_plugins.get_mut(panel_name) {
-                    if !panel.is_closable() {
-                        // Render directly without header for non-closable panels (like Editor)
-                        panel.render_panel(ui, &mut self.plugin_context);
-                    } else {
-                        // Create a collapsing header for each panel
-                        let title = panel.panel_title().to_string();
-                        let header_response = ui.collapsing(title, |ui| {
-                            panel.render_panel(ui, &mut self.plugin_context);
-                        });
-
-                        // Handle panel closing if closable
-                        header_response.header_response.context_menu(|ui| {
-                            if ui.button("Close Panel").clicked() {
-                                self.ui_state.open_panels.insert(panel_name.clone(), false);
-                                ui.close_menu();
+            if down {
+                palette.selected += 1;
+            }
+            if up {
+                palette.selected = palette.selected.saturating_sub(1);
+            }
+            palette.selected = palette.selected.min(matches.len().saturating_sub(1));
+            let mut run = if enter {
+                matches.get(palette.selected).map(|(id, _, _)| id.clone())
+            } else {
+                None
+            };
+            egui::Window::new("Command Palette")
+                .collapsible(false)
+                .resizable(false)
+                .title_bar(false)
+                .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+                .show(ctx, |ui| {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut palette.query)
+                            .hint_text("Type a command")
+                            .desired_width(360.0),
+                    );
+                    response.request_focus();
+                    if response.changed() {
+                        palette.selected = 0;
+                    }
+                    ui.separator();
+                    egui::ScrollArea::vertical()
+                        .max_height(320.0)
+                        .show(ui, |ui| {
+                            if matches.is_empty() {
+                                ui.weak("No matching command");
+                            }
+                            for (index, (id, title, shortcut)) in matches.iter().enumerate() {
+                                let button = egui::Button::selectable(
+                                    index == palette.selected,
+                                    title.as_str(),
+                                )
+                                .shortcut_text(egui::RichText::new(shortcut).weak())
+                                .min_size(egui::vec2(ui.available_width(), 0.0));
+                                if ui.add(button).clicked() {
+                                    run = Some(id.clone());
+                                }
                             }
                         });
-                    }
-                }
+                });
+            if let Some(id) = run {
+                self.command_palette = None;
+                self.plugin_context.run_command(&id);
+                ctx.request_repaint();
+            } else if escape {
+                self.command_palette = None;
             }
         }
-    }
 
-    /// Render modal dialogs.
-    fn render_dialogs(&mut self, ctx: &egui::Context) {
-        // About dialog
-        if self.show_about {
-            egui::Window::new("About Cosmarium")
+        // Quick open switcher
+        if let (Some(switcher), Some(index)) = (&mut self.quick_open, &self.quick_index) {
+            let matches = index.search(&switcher.query, QUICK_OPEN_RESULTS);
+            let (down, up, enter, escape) = ctx.input_mut(|input| {
+                (
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+                )
+            });
+            if down {
+                switcher.selected += 1;
+            }
+            if up {
+                switcher.selected = switcher.selected.saturating_sub(1);
+            }
+            switcher.selected = switcher.selected.min(matches.len().saturating_sub(1));
+            let mut chosen = if enter {
+                matches.get(switcher.selected).cloned()
+            } else {
+                None
+            };
+            egui::Window::new("Go to Document or Heading")
                 .collapsible(false)
                 .resizable(false)
+                .title_bar(false)
+                .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
                 .show(ctx, |ui| {
-                    ui.vertical_centered(|ui| {
-                        ui.heading("Cosmarium");
-                        ui.label(format!("Version {}", env!("CARGO_PKG_VERSION")));
-                        ui.separator();
-                        ui.label("Next-generation creative writing software");
-                        ui.label("for fiction authors");
-                        ui.separator();
-                        ui.label("Built with Rust and EGUI");
-                        ui.separator();
-                        if ui.button("Close").clicked() {
-                            self.show_about = false;
-                        }
-                    });
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut switcher.query)
+                            .hint_text("Type a few letters of a document or heading")
+                            .desired_width(360.0),
+                    );
+                    response.request_focus();
+                    if response.changed() {
+                        switcher.selected = 0;
+                    }
+                    ui.separator();
+                    egui::ScrollArea::vertical()
+                        .max_height(320.0)
+                        .show(ui, |ui| {
+                            if matches.is_empty() {
+                                ui.weak("No matching document or heading");
+                            }
+                            for (i, entry) in matches.iter().enumerate() {
+                                let (label, context) = match &entry.heading {
+                                    Some(heading) => (
+                                        format!("{}{}", "  ".repeat(entry.level as usize), heading),
+                                        format!("{} · line {}", entry.title, entry.line),
+                                    ),
+                                    None => (entry.title.clone(), entry.path.clone()),
+                                };
+                                let button =
+                                    egui::Button::selectable(i == switcher.selected, label)
+                                        .shortcut_text(egui::RichText::new(context).weak())
+                                        .min_size(egui::vec2(ui.available_width(), 0.0));
+                                if ui.add(button).clicked() {
+                                    chosen = Some(entry.clone());
+                                }
+                            }
+                        });
                 });
+            if let Some(entry) = chosen {
+                self.quick_open = None;
+                let path = index.root().join(&entry.path);
+                let line = entry.heading.is_some().then_some(entry.line);
+                if let Err(e) = self.open_document(&path, line) {
+                    tracing::error!("Failed to open {:?}: {}", path, e);
+                }
+            } else if escape {
+                self.quick_open = None;
+            }
         }
 
-        // Plugin manager dialog
-        if self.show_plugin_manager {
-            egui::Window::new("Plugin Manager")
+        // Duplicate Project dialog
+        if let Some(form) = &mut self.duplicate_project {
+            let mut duplicate = false;
+            let mut cancel = false;
+            egui::Window::new("Duplicate Project")
                 .collapsible(false)
-                .default_width(600.0)
+                .resizable(false)
                 .show(ctx, |ui| {
-                    ui.label("Installed Plugins:");
-                    ui.separator();
-
-                    for (name, plugin) in &self.panel_plugins {
-                        ui.horizontal(|ui| {
-                            ui.label("🔌");
-                            ui.label(name);
-                            ui.with_layout(
-                                egui::Layout::right_to_left(egui::Align::Center),
-                                |ui| {
-                                    let is_open =
-                                        self.ui_state.open_panels.get(name).unwrap_or(&false);
-                                    if *is_open {
-                                        ui.colored_label(egui::Color32::GREEN, "Active");
-                                    } else {
-                                        ui.colored_label(egui::Color32::GRAY, "Inactive");
+                    egui::Grid::new("duplicate_project")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Name of the copy:");
+                            ui.text_edit_singleline(&mut form.name);
+                            ui.end_row();
+
+                            ui.label("Location:");
+                            ui.horizontal(|ui| {
+                                ui.text_edit_singleline(&mut form.location);
+                                if ui.button("Browse...").clicked() {
+                                    if let Some(path) = rfd::FileDialog::new()
+                                        .set_title("Select Location of the Copy")
+                                        .pick_folder()
+                                    {
+                                        form.location = path.to_string_lossy().to_string();
                                     }
-                                },
-                            );
+                                }
+                            });
+                            ui.end_row();
                         });
-                        ui.separator();
+                    ui.checkbox(&mut form.reset_history, "Start a new Git history")
+                        .on_hover_text("Leave the commits of the project behind");
+                    let issues = form.location_check.issues(&form.location, &form.name);
+                    for issue in issues {
+                        let color = if issue.is_blocking() {
+                            ui.visuals().error_fg_color
+                        } else {
+                            ui.visuals().warn_fg_color
+                        };
+                        ui.colored_label(color, issue.to_string());
                     }
-
+                    ui.separator();
                     ui.horizontal(|ui| {
-                        if ui.button("Close").clicked() {
-                            self.show_plugin_manager = false;
+                        let valid = !issues.iter().any(LocationIssue::is_blocking);
+                        if ui
+                            .add_enabled(valid, egui::Button::new("Duplicate"))
+                            .clicked()
+                        {
+                            duplicate = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            cancel = true;
                         }
                     });
                 });
+            if duplicate {
+                let form = form.clone();
+                match self.duplicate_current_project(&form) {
+                    Ok(()) => self.duplicate_project = None,
+                    Err(e) => tracing::error!("Failed to duplicate the project: {}", e),
+                }
+            } else if cancel {
+                self.duplicate_project = None;
+            }
         }
 
-        // Settings dialog
-        if self.show_settings {
-            egui::Window::new("Settings")
+        // Save as Template dialog
+        if let Some(form) = &mut self.save_template {
+            let mut save = false;
+            let mut cancel = false;
+            egui::Window::new("Save as Template")
                 .collapsible(false)
-                .default_width(500.0)
+                .resizable(false)
                 .show(ctx, |ui| {
-                    ui.label("Application Settings");
+                    ui.label(
+                        "New projects made from the template start with the structure \
+                         and settings of this project, and the headings of its documents.",
+                    );
+                    egui::Grid::new("save_template")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Template name:");
+                            ui.text_edit_singleline(&mut form.name);
+                            ui.end_row();
+
+                            ui.label("Description:");
+                            ui.text_edit_multiline(&mut form.description);
+                            ui.end_row();
+                        });
+                    let exists = UserTemplate::templates_dir()
+                        .is_ok_and(|dir| dir.join(form.name.trim()).exists());
+                    if exists && !form.name.trim().is_empty() {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            "A template of this name will be replaced",
+                        );
+                    }
                     ui.separator();
-
                     ui.horizontal(|ui| {
-                        ui.label("Theme:");
-                        egui::ComboBox::from_label("")
-                            .selected_text(&self.ui_state.current_theme)
-                            .show_ui(ui, |ui| {
-                                ui.selectable_value(
-                                    &mut self.ui_state.current_theme,
-                                    "Dark".to_string(),
-                                    "Dark",
-                                );
-                                ui.selectable_value(
-                                    &mut self.ui_state.current_theme,
-                                    "Light".to_string(),
-                                    "Light",
-                                );
-                            });
+                        let valid = !form.name.trim().is_empty();
+                        if ui.add_enabled(valid, egui::Button::new("Save")).clicked() {
+                            save = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            cancel = true;
+                        }
                     });
+                });
+            if save {
+                let form = form.clone();
+                match self.save_project_as_template(&form) {
+                    Ok(()) => self.save_template = None,
+                    Err(e) => tracing::error!("Failed to save the template: {}", e),
+                }
+            } else if cancel {
+                self.save_template = None;
+            }
+        }
 
+        // Import Folder dialog
+        if let Some(form) = &mut self.import_folder {
+            let mut import = false;
+            let mut cancel = false;
+            egui::Window::new("Import Folder")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(format!("Import the documents of {}", form.source.display()));
+                    ui.weak(
+                        "Subfolders become folders of the binder. Documents are titled \
+                         after their first heading, or their file name.",
+                    );
+                    ui.checkbox(&mut form.watch, "Keep importing new and changed files")
+                        .on_hover_text(
+                            "Files are copied into the project: edits made in the project \
+                             are not written back to the folder",
+                        );
                     ui.separator();
-
                     ui.horizontal(|ui| {
-                        if ui.button("Save").clicked() {
-                            // TODO: Save settings
-                            self.show_settings = false;
+                        if ui.button("Import").clicked() {
+                            import = true;
                         }
                         if ui.button("Cancel").clicked() {
-                            self.show_settings = false;
+                            cancel = true;
                         }
                     });
                 });
+            if import {
+                let form = form.clone();
+                self.import_folder = None;
+                if let Err(e) = self.import_document_folder(&form.source, form.watch) {
+                    tracing::error!("Failed to import {:?}: {}", form.source, e);
+                }
+            } else if cancel {
+                self.import_folder = None;
+            }
         }
 
-        // New Project dialog
+        // New Project wizard
         if self.show_new_project_dialog {
+            let mut create = false;
+            let mut cancel = false;
+            let wizard = &mut self.new_project;
             egui::Window::new("New Project")
                 .collapsible(false)
-                .default_width(450.0)
+                .default_width(480.0)
                 .show(ctx, |ui| {
-                    ui.label("Create a new Cosmarium project");
-                    ui.separator();
-
-                    ui.horizontal(|ui| {
-                        ui.label("Project Name:");
-                        ui.text_edit_singleline(&mut self.new_project_name);
-                    });
-
                     ui.horizontal(|ui| {
-                        ui.label("Location:");
-                        ui.text_edit_singleline(&mut self.new_project_path);
-                        if ui.button("Browse...").clicked() {
-                            if let Some(path) = rfd::FileDialog::new()
-                                .set_title("Select Project Location")
-                                .pick_folder()
-                            {
-                                self.new_project_path = path.to_string_lossy().to_string();
+                        for (index, step) in NewProjectStep::ALL.into_iter().enumerate() {
+                            if index > 0 {
+                                ui.weak("›");
                             }
+                            ui.selectable_value(&mut wizard.step, step, step.title());
                         }
                     });
+                    ui.separator();
 
-                    ui.horizontal(|ui| {
-                        ui.label("Template:");
-                        egui::ComboBox::from_label("")
-                            .selected_text(&self.new_project_template)
-                            .show_ui(ui, |ui| {
-                                ui.selectable_value(
-                                    &mut self.new_project_template,
-                                    "novel".to_string(),
-                                    "Novel",
-                                );
-                                ui.selectable_value(
-                                    &mut self.new_project_template,
-                                    "short-story".to_string(),
-                                    "Short Story",
-                                );
-                                ui.selectable_value(
-                                    &mut self.new_project_template,
-                                    "screenplay".to_string(),
-                                    "Screenplay",
-                                );
-                                ui.selectable_value(
-                                    &mut self.new_project_template,
-                                    "blog".to_string(),
-                                    "Blog",
+                    match wizard.step {
+                        NewProjectStep::Template => {
+                            ui.horizontal(|ui| {
+                                ui.vertical(|ui| {
+                                    for template in ProjectTemplate::ALL {
+                                        let selected = wizard.user_template.is_none()
+                                            && wizard.template == template;
+                                        if ui
+                                            .selectable_label(selected, template.display_name())
+                                            .clicked()
+                                        {
+                                            wizard.template = template;
+                                            wizard.user_template = None;
+                                        }
+                                    }
+                                    if !wizard.user_templates.is_empty() {
+                                        ui.separator();
+                                        ui.weak("Your templates");
+                                    }
+                                    for (index, template) in
+                                        wizard.user_templates.iter().enumerate()
+                                    {
+                                        ui.selectable_value(
+                                            &mut wizard.user_template,
+                                            Some(index),
+                                            &template.name,
+                                        );
+                                    }
+                                });
+                                ui.separator();
+                                ui.vertical(|ui| {
+                                    let user_template = wizard.chosen_user_template();
+                                    if let Some(template) = user_template {
+                                        ui.strong(&template.name);
+                                        ui.label(&template.description);
+                                    } else {
+                                        ui.strong(wizard.template.display_name());
+                                        ui.label(wizard.template.description());
+                                    }
+                                    ui.add_space(4.0);
+                                    ui.weak("Starts with:");
+                                    egui::ScrollArea::vertical()
+                                        .max_height(160.0)
+                                        .id_salt("new_project_outline")
+                                        .show(ui, |ui| match user_template {
+                                            Some(template) => {
+                                                structure_tree(ui, template.structure.roots())
+                                            }
+                                            None => template_tree(ui, wizard.template.outline()),
+                                        });
+                                });
+                            });
+                        }
+                        NewProjectStep::Location => {
+                            egui::Grid::new("new_project_location")
+                                .num_columns(2)
+                                .show(ui, |ui| {
+                                    ui.label("Project name:");
+                                    ui.text_edit_singleline(&mut wizard.name);
+                                    ui.end_row();
+
+                                    ui.label("Location:");
+                                    ui.horizontal(|ui| {
+                                        ui.text_edit_singleline(&mut wizard.location);
+                                        if ui.button("Browse...").clicked() {
+                                            if let Some(path) = rfd::FileDialog::new()
+                                                .set_title("Select Project Location")
+                                                .pick_folder()
+                                            {
+                                                wizard.location =
+                                                    path.to_string_lossy().to_string();
+                                            }
+                                        }
+                                    });
+                                    ui.end_row();
+                                });
+                            let name = wizard.name.trim();
+                            if !name.is_empty() {
+                                ui.weak(format!(
+                                    "Creates {}",
+                                    std::path::Path::new(wizard.location.trim())
+                                        .join(name)
+                                        .display()
+                                ));
+                            }
+                            for issue in wizard.location_issues() {
+                                let color = if issue.is_blocking() {
+                                    ui.visuals().error_fg_color
+                                } else {
+                                    ui.visuals().warn_fg_color
+                                };
+                                ui.colored_label(color, issue.to_string());
+                            }
+                        }
+                        NewProjectStep::Remote => {
+                            ui.label(
+                                "The project is kept in a Git repository. Give the URL of \
+                                 a remote repository to push it to, or leave it empty.",
+                            );
+                            ui.horizontal(|ui| {
+                                ui.label("Remote URL:");
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut wizard.remote_url)
+                                        .hint_text("https://example.com/me/novel.git"),
                                 );
                             });
-                    });
+                            if !cfg!(feature = "git") {
+                                ui.colored_label(
+                                    ui.visuals().warn_fg_color,
+                                    "This build has no Git support: the remote is ignored",
+                                );
+                            }
+                        }
+                        NewProjectStep::Goal => {
+                            egui::Grid::new("new_project_goal")
+                                .num_columns(2)
+                                .show(ui, |ui| {
+                                    ui.label("Words to write:");
+                                    ui.horizontal(|ui| {
+                                        ui.add(
+                                            egui::DragValue::new(&mut wizard.word_target)
+                                                .range(0..=1_000_000)
+                                                .speed(100),
+                                        );
+                                        ui.weak("(0 for none)");
+                                    });
+                                    ui.end_row();
+
+                                    ui.label("Due by:");
+                                    ui.horizontal(|ui| {
+                                        ui.add(
+                                            egui::TextEdit::singleline(&mut wizard.deadline)
+                                                .hint_text("YYYY-MM-DD")
+                                                .desired_width(90.0),
+                                        );
+                                        if wizard.deadline().is_err() {
+                                            ui.colored_label(
+                                                ui.visuals().error_fg_color,
+                                                "Not a date",
+                                            );
+                                        } else {
+                                            ui.weak("(empty for none)");
+                                        }
+                                    });
+                                    ui.end_row();
+                                });
+                        }
+                    }
 
                     ui.separator();
 
+                    let index = NewProjectStep::ALL
+                        .iter()
+                        .position(|step| *step == wizard.step)
+                        .unwrap_or(0);
                     ui.horizontal(|ui| {
-                        if ui.button("Create").clicked() {
-                            if !self.new_project_name.is_empty() {
-                                if let Err(e) = self.create_new_project(
-                                    self.new_project_name.clone(),
-                                    self.new_project_path.clone(),
-                                    self.new_project_template.clone(),
-                                ) {
-                                    tracing::error!("Failed to create project: {}", e);
-                                } else {
-                                    self.show_new_project_dialog = false;
-                                    self.new_project_name.clear();
-                                }
+                        if ui
+                            .add_enabled(index > 0, egui::Button::new("Back"))
+                            .clicked()
+                        {
+                            wizard.step = NewProjectStep::ALL[index - 1];
+                        }
+                        if let Some(next) = NewProjectStep::ALL.get(index + 1) {
+                            if ui.button("Next").clicked() {
+                                wizard.step = *next;
                             }
                         }
+                        if ui
+                            .add_enabled(wizard.is_valid(), egui::Button::new("Create"))
+                            .clicked()
+                        {
+                            create = true;
+                        }
                         if ui.button("Cancel").clicked() {
-                            self.show_new_project_dialog = false;
-                            self.new_project_name.clear();
+                            cancel = true;
                         }
                     });
                 });
+
+            if create {
+                let wizard = self.new_project.clone();
+                if let Err(e) = self.create_new_project(&wizard) {
+                    tracing::error!("Failed to create project: {}", e);
+                } else {
+                    self.show_new_project_dialog = false;
+                    self.new_project.reset();
+                }
+            } else if cancel {
+                self.show_new_project_dialog = false;
+                self.new_project.reset();
+            }
         }
     }
 }
@@ -1601,6 +8164,9 @@ impl eframe::App for Cosmarium {
             }
         }
 
+        self.process_plugin_events();
+        self.poll_background_tasks(ctx);
+
         // Update plugins
         for plugin in self.plugins.values_mut() {
             if let Err(e) = plugin.update(&mut self.plugin_context) {
@@ -1615,60 +8181,48 @@ impl eframe::App for Cosmarium {
             }
         }
 
+        self.handle_open_document_request();
+        self.handle_focus_panel_request();
+        self.handle_project_snapshot_request();
+        self.handle_document_snapshot_request();
+        self.handle_git_history_request();
+        self.handle_branch_request();
+        self.handle_metadata_update_request();
+        self.handle_search_request();
+        self.handle_replace_request();
+        self.handle_export_document_request();
+        self.handle_add_to_dictionary_request();
+        self.handle_document_order_request();
+        self.sync_watched_folders(ctx);
+        self.sync_editor_content();
+        self.handle_editor_document_requests();
+        self.track_navigation();
+        self.track_writing_goals();
+        self.handle_sprint_record_request();
+        self.publish_clipboard_html(ctx);
+
         // Update atmosphere
         self.update_atmosphere(ctx);
 
-        // Keyboard shortcuts (Ctrl+N, Ctrl+O, Ctrl+S, Ctrl+Q)
-        // Keyboard shortcuts (Ctrl+N, Ctrl+O, Ctrl+S, Ctrl+Q)
-        let mut should_quit = false;
-        ctx.input(|input| {
-            if input.modifiers.ctrl {
-                if input.key_pressed(egui::Key::N) {
-                    // New project
-                    self.show_new_project_dialog = true;
-                } else if input.key_pressed(egui::Key::O) {
-                    // Open project (reuse file dialog logic)
-                    if let Some(path) = rfd::FileDialog::new()
-                        .set_title("Open Project")
-                        .pick_folder()
-                    {
-                        if let Err(e) = self.open_project_async(path) {
-                            tracing::error!("Failed to open project via shortcut: {}", e);
-                        }
-                    }
-                } else if input.key_pressed(egui::Key::S) {
-                    // Save current project
-                    if let Err(e) = self.save_current_project() {
-                        tracing::error!("Failed to save project via shortcut: {}", e);
-                    }
-                } else if input.key_pressed(egui::Key::Q) {
-                    // Quit application
-                    should_quit = true;
-                } else if input.key_pressed(egui::Key::Z) {
-                    // Undo
-                    if input.modifiers.shift {
-                        // Redo (Ctrl+Shift+Z)
-                        self.plugin_context
-                            .set_shared_state("markdown_editor_action", "redo".to_string());
-                    } else {
-                        // Undo (Ctrl+Z)
-                        self.plugin_context
-                            .set_shared_state("markdown_editor_action", "undo".to_string());
-                    }
-                } else if input.key_pressed(egui::Key::Y) {
-                    // Redo (Ctrl+Y)
-                    self.plugin_context
-                        .set_shared_state("markdown_editor_action", "redo".to_string());
-                }
-            }
-        });
+        // Shortcuts of the commands registered by the application and plugins
+        self.run_pressed_command(ctx);
+        self.handle_app_command_request(ctx);
 
-        if should_quit {
-            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        // Back/Forward with the mouse side buttons
+        let (go_back, go_forward) = ctx.input(|input| {
+            (
+                input.pointer.button_pressed(egui::PointerButton::Extra1),
+                input.pointer.button_pressed(egui::PointerButton::Extra2),
+            )
+        });
+        if go_back {
+            self.navigate_back();
+        } else if go_forward {
+            self.navigate_forward();
         }
 
-        // Render UI
-        if self.ui_state.show_menu_bar {
+        // Render UI, nothing but the editor in focus mode
+        if self.ui_state.show_menu_bar && !self.focus_mode() {
             self.render_menu_bar(ctx, frame);
         }
 
@@ -1682,6 +8236,7 @@ impl eframe::App for Cosmarium {
         tracing::info!("Saving application state");
 
         // TODO: Save application state and configuration
+        self.store_plugin_settings();
         if let Err(e) = self.config.save() {
             tracing::error!("Failed to save configuration: {}", e);
         }
@@ -1695,14 +8250,44 @@ impl eframe::App for Cosmarium {
         self.plugin_context.emit_event(event);
 
         // Shutdown plugins
+        let executor = self.core_app.executor();
         for plugin in self.plugins.values_mut() {
-            if let Err(e) = tokio::runtime::Runtime::new()
-                .unwrap()
-                .block_on(plugin.shutdown(&mut self.plugin_context))
-            {
+            if let Err(e) = executor.block_on(plugin.shutdown(&mut self.plugin_context)) {
                 tracing::error!("Plugin shutdown error: {}", e);
             }
         }
+
+        // A sprint cut short by quitting still counts
+        if let Some(sprint) = self.sprint() {
+            let record = sprint.record(self.plugin_context.clock().now());
+            self.log_sprint(record);
+        }
+
+        // Keep the state plugins want back in the next session
+        let plugin_names = self.plugins.keys().chain(self.panel_plugins.keys());
+        for plugin_name in plugin_names {
+            if let Some(state) = self
+                .plugin_context
+                .get_plugin_data::<serde_json::Value>(plugin_name, SESSION_STATE_KEY)
+            {
+                self.session.plugin_state.insert(plugin_name.clone(), state);
+            }
+        }
+        if let Err(e) = self.session.save() {
+            tracing::warn!("Failed to save session: {}", e);
+        }
+
+        // Keep the panel arrangement for the next session
+        self.store_open_panels();
+        let layout_manager = self.core_app.layout_manager();
+        let saved = executor
+            .block_on(async move { layout_manager.write().await.save_layout("default").await });
+        if let Err(e) = saved {
+            tracing::warn!("Failed to save layout: {}", e);
+        }
+
+        self.wait_for_commit();
+        self.update_second_copy(true, true);
     }
 }
 
@@ -1778,9 +8363,15 @@ impl Cosmarium {
     /// Update the atmosphere (theme) based on sentiment and intensity.
     fn update_atmosphere(&mut self, ctx: &egui::Context) {
         // Read raw values from shared state
-        let target_sentiment = self.plugin_context.get_shared_state::<f32>("atmosphere_sentiment").unwrap_or(0.0);
-        let target_intensity = self.plugin_context.get_shared_state::<f32>("atmosphere_intensity").unwrap_or(0.0);
-        
+        let target_sentiment = self
+            .plugin_context
+            .get_shared_state::<f32>("atmosphere_sentiment")
+            .unwrap_or(0.0);
+        let target_intensity = self
+            .plugin_context
+            .get_shared_state::<f32>("atmosphere_intensity")
+            .unwrap_or(0.0);
+
         // Update smoothed values (low-pass filter for stability)
         // We want to move towards target values over time
         let dt = ctx.input(|i| i.stable_dt).min(0.1); // Cap dt
@@ -1803,8 +8394,30 @@ impl Cosmarium {
             self.current_intensity = target_intensity;
         }
 
+        // Follow the configured light/dark appearance (auto theme fades between both)
+        let system = ctx.system_theme().map(|theme| match theme {
+            egui::Theme::Light => Appearance::Light,
+            egui::Theme::Dark => Appearance::Dark,
+        });
+        let target_daylight = self.target_daylight(system);
+        let d_speed = 2.0; // Appearance speed (gentle fade)
+
+        let d_diff = target_daylight - self.current_daylight;
+        if d_diff.abs() > 0.001 {
+            self.current_daylight += d_diff * d_speed * dt;
+            ctx.request_repaint();
+        } else {
+            self.current_daylight = target_daylight;
+        }
+
+        if self.config.ui.theme == AUTO_THEME {
+            // Re-evaluate the schedule periodically even when the UI is idle
+            ctx.request_repaint_after(std::time::Duration::from_secs(30));
+        }
+
         let sentiment = self.current_sentiment;
         let intensity = self.current_intensity;
+        let daylight = self.current_daylight;
 
         // 1. Try to get the rich RYB-based palette from shared state
         let shared_palette = self
@@ -1814,7 +8427,12 @@ impl Cosmarium {
 
         // 2. Determine target colors (either from SharedPalette or HSL fallback)
         let (hue, saturation, lightness_bg, _lightness_fg) = if let Some(p) = shared_palette {
-            (p.main_bg_h, p.main_bg_s / 100.0, p.main_bg_l / 100.0, p.main_fg_l / 100.0)
+            (
+                p.main_bg_h,
+                p.main_bg_s / 100.0,
+                p.main_bg_l / 100.0,
+                p.main_fg_l / 100.0,
+            )
         } else if sentiment > 0.0 {
             // Fallback Positive: Gold/Warm (Hue ~45)
             (45.0, 0.6 * sentiment.abs(), 0.95, 0.1)
@@ -1850,8 +8468,12 @@ impl Cosmarium {
             )
         }
 
-        // Base neutral (Dark Mode default)
-        let base_bg = egui::Color32::from_rgb(27, 27, 27);
+        // Base neutral, blended between the dark and light appearances
+        let base_bg = lerp_color(
+            egui::Color32::from_rgb(27, 27, 27),
+            egui::Color32::from_rgb(248, 248, 248),
+            daylight,
+        );
 
         // Target colors
         let target_bg = hsl_to_color(hue, saturation, lightness_bg);
@@ -1864,7 +8486,11 @@ impl Cosmarium {
 
         // Faint background (for panels/windows)
         let new_faint = lerp_color(
-            egui::Color32::from_additive_luminance(10),
+            lerp_color(
+                egui::Color32::from_additive_luminance(10),
+                egui::Color32::from_rgb(235, 235, 235),
+                daylight,
+            ),
             hsl_to_color(
                 hue,
                 saturation * 0.5,
@@ -1874,7 +8500,8 @@ impl Cosmarium {
         );
 
         // Dynamic Contrast Calculation
-        let bg_lum = 0.299 * new_bg.r() as f32 + 0.587 * new_bg.g() as f32 + 0.114 * new_bg.b() as f32;
+        let bg_lum =
+            0.299 * new_bg.r() as f32 + 0.587 * new_bg.g() as f32 + 0.114 * new_bg.b() as f32;
 
         let new_fg = if bg_lum > 140.0 {
             egui::Color32::from_rgb(10, 10, 15)
@@ -1902,10 +8529,212 @@ impl Cosmarium {
         visuals.selection.bg_fill = hsl_to_color(hue, 0.8, 0.5);
         visuals.selection.stroke.color = new_fg;
 
+        // Per-appearance editor color overrides
+        let editor_colors = resolve_editor_colors(&self.config.ui.theme_schedule, daylight);
+        if let Some(selection) = editor_colors.selection {
+            visuals.selection.bg_fill = selection;
+        }
+        self.plugin_context
+            .set_shared_state("markdown_editor_background_color", editor_colors.background);
+        self.plugin_context
+            .set_shared_state("markdown_editor_text_color", editor_colors.text);
+
         ctx.set_visuals(visuals);
     }
 }
 
+/// Editor colors resolved from the per-appearance overrides.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct EditorColors {
+    background: Option<egui::Color32>,
+    text: Option<egui::Color32>,
+    selection: Option<egui::Color32>,
+}
+
+/// First file of a supported format in the folder `content_dir`, shown
+/// when its project opens.
+fn first_content_file(content_dir: &std::path::Path) -> Option<std::path::PathBuf> {
+    std::fs::read_dir(content_dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    matches!(
+                        extension,
+                        "md" | "markdown" | "txt" | "rtf" | "html" | "htm"
+                    )
+                })
+        })
+}
+
+/// Resolve editor color overrides for the current light/dark blend.
+///
+/// When both appearances override a color it fades with the theme; otherwise
+/// the override only applies while its appearance is dominant.
+fn resolve_editor_colors(schedule: &ThemeScheduleConfig, daylight: f32) -> EditorColors {
+    let light = schedule.overrides_for(Appearance::Light);
+    let dark = schedule.overrides_for(Appearance::Dark);
+
+    let pick = |field: fn(&EditorColorOverrides) -> &Option<String>| {
+        let color = |o: Option<&EditorColorOverrides>| {
+            o.and_then(|o| field(o).as_deref())
+                .and_then(parse_hex_color)
+                .map(|[r, g, b]| egui::Color32::from_rgb(r, g, b))
+        };
+        match (color(dark), color(light)) {
+            (Some(d), Some(l)) => Some(lerp_color(d, l, daylight)),
+            (Some(d), None) if daylight < 0.5 => Some(d),
+            (None, Some(l)) if daylight >= 0.5 => Some(l),
+            _ => None,
+        }
+    };
+
+    EditorColors {
+        background: pick(|o| &o.background),
+        text: pick(|o| &o.text),
+        selection: pick(|o| &o.selection),
+    }
+}
+
+/// Log levels of targets, from lines of `target = level`.
+fn parse_log_targets(text: &str) -> Result<BTreeMap<String, String>> {
+    let mut targets = BTreeMap::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (target, level) = line
+            .split_once('=')
+            .map(|(target, level)| (target.trim(), level.trim().to_lowercase()))
+            .filter(|(target, level)| {
+                !target.is_empty() && logging::LEVELS.contains(&level.as_str())
+            })
+            .ok_or_else(|| {
+                cosmarium_core::Error::config(format!(
+                    "Expected 'target = level' with a level among {}: {}",
+                    logging::LEVELS.join(", "),
+                    line
+                ))
+            })?;
+        targets.insert(target.to_string(), level);
+    }
+    Ok(targets)
+}
+
+/// Congratulation for reaching a writing goal.
+fn goal_reached_message(progress: &GoalProgress, locale: &Locale) -> String {
+    let target = locale.format_count(progress.target);
+    match progress.kind {
+        GoalKind::Session => format!("You wrote the {} words you aimed for this session.", target),
+        GoalKind::Day => format!("You wrote your {} words for today.", target),
+        GoalKind::Project => format!(
+            "You wrote the {} words you aimed for in this project.",
+            target
+        ),
+    }
+}
+
+/// Read a deadline typed as `YYYY-MM-DD`, `None` if nothing is typed.
+fn parse_deadline(
+    text: &str,
+) -> std::result::Result<Option<chrono::NaiveDate>, chrono::ParseError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d").map(Some)
+}
+
+/// Show the folders and documents of a template outline as a tree.
+fn template_tree(ui: &mut egui::Ui, nodes: &[TemplateNode]) {
+    for node in nodes {
+        if node.children.is_empty() {
+            let icon = if node.kind == NodeKind::Document {
+                "📄"
+            } else {
+                "📁"
+            };
+            ui.label(format!("{} {}", icon, node.title));
+        } else {
+            egui::CollapsingHeader::new(format!("📁 {}", node.title))
+                .id_salt(node.name)
+                .default_open(true)
+                .show(ui, |ui| template_tree(ui, node.children));
+        }
+    }
+}
+
+/// Show the folders and documents of a user template as a tree.
+fn structure_tree(ui: &mut egui::Ui, nodes: &[StructureNode]) {
+    for node in nodes {
+        if node.children.is_empty() {
+            let icon = if node.kind == NodeKind::Document {
+                "📄"
+            } else {
+                "📁"
+            };
+            ui.label(format!("{} {}", icon, node.title));
+        } else {
+            egui::CollapsingHeader::new(format!("📁 {}", node.title))
+                .id_salt(node.id)
+                .default_open(true)
+                .show(ui, |ui| structure_tree(ui, &node.children));
+        }
+    }
+}
+
+/// Edit a minute-of-day value as hours and minutes.
+fn minute_of_day_edit(ui: &mut egui::Ui, minute_of_day: &mut u32) {
+    let mut hours = *minute_of_day / 60;
+    let mut minutes = *minute_of_day % 60;
+    ui.add(egui::DragValue::new(&mut hours).range(0..=23).suffix("h"));
+    ui.add(egui::DragValue::new(&mut minutes).range(0..=59).suffix("m"));
+    *minute_of_day = hours * 60 + minutes;
+}
+
+/// Add the entries scaling the text of a panel, zoomed to `zoom`, to one of
+/// its menus.
+fn panel_zoom_menu(ui: &mut egui::Ui, name: &str, zoom: f32, actions: &mut Vec<TabAction>) {
+    // Rounded to the step, for sums of tenths not to drift
+    let step = |by: f32| ((zoom + by) / PANEL_ZOOM_STEP).round() * PANEL_ZOOM_STEP;
+    let (larger, smaller) = (step(PANEL_ZOOM_STEP), step(-PANEL_ZOOM_STEP));
+    if ui
+        .add_enabled(
+            larger <= *PANEL_ZOOM_RANGE.end() + f32::EPSILON,
+            egui::Button::new("Larger Text"),
+        )
+        .clicked()
+    {
+        actions.push(TabAction::Zoom(name.to_string(), larger));
+    }
+    if ui
+        .add_enabled(
+            smaller >= *PANEL_ZOOM_RANGE.start() - f32::EPSILON,
+            egui::Button::new("Smaller Text"),
+        )
+        .clicked()
+    {
+        actions.push(TabAction::Zoom(name.to_string(), smaller));
+    }
+    let label = format!("Reset Text Size ({:.0}%)", zoom * 100.0);
+    if ui
+        .add_enabled((zoom - 1.0).abs() > f32::EPSILON, egui::Button::new(label))
+        .clicked()
+    {
+        actions.push(TabAction::Zoom(name.to_string(), 1.0));
+    }
+}
+
+/// Scale the text of `ui` and its children by `zoom`.
+fn zoom_text(ui: &mut egui::Ui, zoom: f32) {
+    if (zoom - 1.0).abs() > f32::EPSILON {
+        for font in ui.style_mut().text_styles.values_mut() {
+            font.size *= zoom;
+        }
+    }
+}
+
 fn lerp_color(a: egui::Color32, b: egui::Color32, t: f32) -> egui::Color32 {
     let t = t.clamp(0.0, 1.0);
     let r = (a.r() as f32 * (1.0 - t) + b.r() as f32 * t) as u8;
@@ -1927,6 +8756,90 @@ mod tests {
         assert_eq!(ui_state.left_panel_width, 250.0);
     }
 
+    #[test]
+    fn test_resolve_editor_colors() {
+        let mut schedule = ThemeScheduleConfig::default();
+        assert_eq!(
+            resolve_editor_colors(&schedule, 0.0),
+            EditorColors::default()
+        );
+
+        schedule.editor_overrides.insert(
+            "dark".to_string(),
+            EditorColorOverrides {
+                background: Some("#000000".to_string()),
+                text: Some("#c0c0c0".to_string()),
+                ..Default::default()
+            },
+        );
+        schedule.editor_overrides.insert(
+            "light".to_string(),
+            EditorColorOverrides {
+                background: Some("#ffffff".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let night = resolve_editor_colors(&schedule, 0.0);
+        assert_eq!(night.background, Some(egui::Color32::BLACK));
+        assert_eq!(night.text, Some(egui::Color32::from_rgb(192, 192, 192)));
+
+        // Background fades, text override only holds while dark dominates
+        let dusk = resolve_editor_colors(&schedule, 0.5);
+        assert_eq!(
+            dusk.background,
+            Some(egui::Color32::from_rgb(127, 127, 127))
+        );
+        assert_eq!(dusk.text, None);
+    }
+
+    #[test]
+    fn test_parse_deadline() {
+        assert_eq!(parse_deadline(" "), Ok(None));
+        assert_eq!(
+            parse_deadline("2024-11-30"),
+            Ok(chrono::NaiveDate::from_ymd_opt(2024, 11, 30))
+        );
+        assert!(parse_deadline("30/11/2024").is_err());
+    }
+
+    #[test]
+    fn test_location_is_checked_again_only_when_changed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let location = temp_dir.path().to_string_lossy().to_string();
+        let mut check = LocationCheck::default();
+        assert!(check.issues(&location, "Novel").is_empty());
+
+        std::fs::create_dir(temp_dir.path().join("Novel")).unwrap();
+        assert!(check.issues(&location, "Novel").is_empty());
+        assert_eq!(check.issues(&location, "Novel "), [LocationIssue::Exists]);
+    }
+
+    #[test]
+    fn test_command_shortcuts_are_unique() {
+        let mut ctx = PluginContext::new();
+        register_app_commands(&mut ctx);
+        commands::register(&mut ctx);
+        SearchPlugin::new().initialize(&mut ctx).unwrap();
+
+        let shortcuts: Vec<&Shortcut> = ctx
+            .commands()
+            .iter()
+            .filter_map(|command| command.default_shortcut.as_ref())
+            .collect();
+        let unique: HashSet<&&Shortcut> = shortcuts.iter().collect();
+        assert_eq!(unique.len(), shortcuts.len());
+
+        for command in AppCommand::ALL {
+            assert_eq!(AppCommand::from_id(command.id()), Some(command));
+        }
+        assert!(ctx.run_command(AppCommand::SaveProject.id()));
+        assert_eq!(
+            ctx.get_shared_state::<Option<String>>(APP_COMMAND_REQUEST),
+            Some(Some("app.save_project".to_string()))
+        );
+    }
+
     #[test]
     fn test_cosmarium_creation() {
         // This test would require mocking eframe::CreationContext
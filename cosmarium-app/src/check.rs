@@ -0,0 +1,95 @@
+//! `cosmarium check <project>`: validate a project from the command line.
+//!
+//! Lists the problems found by [`cosmarium_core::check`] and, when run in
+//! a terminal, offers the repairs of each one in turn. The project is saved
+//! if a repair was applied.
+
+use cosmarium_core::check::{check_project, repair, CheckReport};
+use cosmarium_core::project::Project;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+
+/// Check the project at `path`, repairing it interactively on a terminal.
+///
+/// Returns whether the project is sound in the end.
+pub fn run(path: &Path) -> anyhow::Result<bool> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut project = runtime.block_on(Project::load(path))?;
+    println!("Checking {}", path.display());
+
+    let report = check_project(&project, &[]);
+    if report.is_empty() {
+        println!("No problems found.");
+        return Ok(true);
+    }
+    print_summary(&report);
+
+    let interactive = std::io::stdin().is_terminal();
+    if !interactive {
+        for issue in &report.issues {
+            println!("  {}", issue);
+            for fix in &issue.repairs {
+                println!("      can be fixed: {}", fix.label());
+            }
+        }
+        println!("Run the check in a terminal to repair the project.");
+        return Ok(false);
+    }
+
+    let mut stdin = std::io::stdin().lock();
+    let mut repaired = 0;
+    let mut left = 0;
+    for issue in &report.issues {
+        println!();
+        println!("{}", issue);
+        if issue.repairs.is_empty() {
+            println!("  This must be fixed by hand.");
+            left += 1;
+            continue;
+        }
+        for (i, fix) in issue.repairs.iter().enumerate() {
+            println!("  {}) {}", i + 1, fix.label());
+        }
+        println!("  s) Skip");
+        let fix = loop {
+            print!("  Choice [s]: ");
+            std::io::stdout().flush()?;
+            let mut answer = String::new();
+            if stdin.read_line(&mut answer)? == 0 {
+                break None;
+            }
+            match answer.trim() {
+                "" | "s" | "S" => break None,
+                choice => match choice.parse::<usize>() {
+                    Ok(n) if (1..=issue.repairs.len()).contains(&n) => {
+                        break Some(&issue.repairs[n - 1]);
+                    }
+                    _ => println!("  Enter a number from 1 to {}, or s.", issue.repairs.len()),
+                },
+            }
+        };
+        match fix {
+            Some(fix) => match repair(&mut project, issue, fix) {
+                Ok(()) => repaired += 1,
+                Err(e) => {
+                    println!("  Failed: {}", e);
+                    left += 1;
+                }
+            },
+            None => left += 1,
+        }
+    }
+
+    if repaired > 0 {
+        runtime.block_on(project.save())?;
+    }
+    println!();
+    println!("{} repaired, {} left.", repaired, left);
+    Ok(left == 0)
+}
+
+fn print_summary(report: &CheckReport) {
+    for (kind, issues) in report.groups() {
+        println!("{}: {}", kind.label(), issues.len());
+    }
+}
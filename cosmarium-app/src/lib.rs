@@ -0,0 +1,46 @@
+//! # Cosmarium application
+//!
+//! The interface of Cosmarium, as a library so that the `cosmarium` binary
+//! and the end-to-end tests under `tests/` run the same application: the
+//! tests drive [`app::Cosmarium`] headless, frame by frame.
+
+pub mod app;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod check;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod verify;
+
+use std::path::PathBuf;
+
+/// Command line arguments for Cosmarium
+#[derive(Debug, Clone)]
+pub struct AppArgs {
+    /// Path to project file to open on startup
+    pub project_path: Option<PathBuf>,
+    /// Enable debug logging
+    pub debug: bool,
+    /// Window width
+    pub width: Option<f32>,
+    /// Window height
+    pub height: Option<f32>,
+    /// Project to check instead of starting the interface
+    pub check_path: Option<PathBuf>,
+    /// Progress proof to verify instead of starting the interface
+    pub proof_path: Option<PathBuf>,
+    /// Project the progress proof is verified against
+    pub proof_project: Option<PathBuf>,
+}
+
+impl Default for AppArgs {
+    fn default() -> Self {
+        Self {
+            project_path: None,
+            debug: false,
+            width: Some(1200.0),
+            height: Some(800.0),
+            check_path: None,
+            proof_path: None,
+            proof_project: None,
+        }
+    }
+}
@@ -22,40 +22,21 @@
 //!
 //! # Run in debug mode
 //! cosmarium --debug
+//!
+//! # Check a project for problems and repair them
+//! cosmarium check /path/to/project
+//!
+//! # Verify a progress proof, and that the project still matches it
+//! cosmarium verify-proof progress-proof.json --project /path/to/project
 //! ```
 
 use clap::{Arg, Command};
 use eframe::egui;
 use std::path::PathBuf;
 
+use cosmarium_app::{app, AppArgs};
 #[cfg(not(target_arch = "wasm32"))]
-use env_logger;
-
-mod app;
-
-/// Command line arguments for Cosmarium
-#[derive(Debug, Clone)]
-pub struct AppArgs {
-    /// Path to project file to open on startup
-    pub project_path: Option<PathBuf>,
-    /// Enable debug logging
-    pub debug: bool,
-    /// Window width
-    pub width: Option<f32>,
-    /// Window height
-    pub height: Option<f32>,
-}
-
-impl Default for AppArgs {
-    fn default() -> Self {
-        Self {
-            project_path: None,
-            debug: false,
-            width: Some(1200.0),
-            height: Some(800.0),
-        }
-    }
-}
+use cosmarium_app::{check, verify};
 
 /// Parse command line arguments
 fn parse_args() -> AppArgs {
@@ -92,26 +73,62 @@ fn parse_args() -> AppArgs {
                 .help("Initial window height")
                 .value_parser(clap::value_parser!(f32)),
         )
+        .subcommand(
+            Command::new("check")
+                .about("Check a project for problems and offer to repair them")
+                .arg(
+                    Arg::new("project")
+                        .value_name("PROJECT")
+                        .help("Project directory to check")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("verify-proof")
+                .about("Check the signature of a progress proof")
+                .arg(
+                    Arg::new("proof")
+                        .value_name("PROOF")
+                        .help("Progress proof to verify")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("project")
+                        .short('p')
+                        .long("project")
+                        .value_name("PROJECT")
+                        .help("Project directory whose documents the proof must match")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
         .get_matches();
+    let verify_proof = matches.subcommand_matches("verify-proof");
 
     AppArgs {
         project_path: matches.get_one::<PathBuf>("project").cloned(),
         debug: matches.get_flag("debug"),
         width: matches.get_one::<f32>("width").copied(),
         height: matches.get_one::<f32>("height").copied(),
+        check_path: matches
+            .subcommand_matches("check")
+            .and_then(|check| check.get_one::<PathBuf>("project").cloned()),
+        proof_path: verify_proof.and_then(|verify| verify.get_one::<PathBuf>("proof").cloned()),
+        proof_project: verify_proof
+            .and_then(|verify| verify.get_one::<PathBuf>("project").cloned()),
     }
 }
 
-/// Initialize logging based on arguments
+/// Initialize logging from the configuration and arguments
 fn init_logging(debug: bool) {
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        let log_level = if debug { "debug" } else { "info" };
-        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(log_level))
-            .init();
+    let mut config = cosmarium_core::Config::load()
+        .map(|config| config.advanced)
+        .unwrap_or_default();
+    if debug {
+        config.log_level = "debug".to_string();
     }
-
-    cosmarium_core::init_tracing();
+    cosmarium_core::init_tracing(&config);
 }
 
 /// Native application entry point
@@ -120,6 +137,19 @@ fn main() -> anyhow::Result<()> {
     let args = parse_args();
     init_logging(args.debug);
 
+    if let Some(path) = &args.check_path {
+        if !check::run(path)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(path) = &args.proof_path {
+        if !verify::run(path, args.proof_project.as_deref())? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     tracing::info!("Starting Cosmarium v{}", env!("CARGO_PKG_VERSION"));
 
     // Configure NativeOptions with IME support enabled for dead keys on Linux.
@@ -214,7 +244,15 @@ fn setup_custom_fonts(ctx: &egui::Context) {
     // Use the default font definitions. Do not insert a named family that has
     // no associated font data, which can cause a runtime panic when egui
     // attempts to resolve the family.
-    let fonts = egui::FontDefinitions::default();
+    let mut fonts = egui::FontDefinitions::default();
+
+    // Numbers formatted for languages such as French are grouped with narrow
+    // no-break spaces, which only the monospace font draws
+    if fonts.font_data.contains_key("Hack") {
+        if let Some(family) = fonts.families.get_mut(&egui::FontFamily::Proportional) {
+            family.push("Hack".to_string());
+        }
+    }
 
     // Apply fonts to the context.
     ctx.set_fonts(fonts);
@@ -245,6 +283,8 @@ mod tests {
         assert!(!args.debug);
         assert_eq!(args.width, Some(1200.0));
         assert_eq!(args.height, Some(800.0));
+        assert!(args.check_path.is_none());
+        assert!(args.proof_path.is_none());
     }
 
     #[test]
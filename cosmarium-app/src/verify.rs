@@ -0,0 +1,43 @@
+//! `cosmarium verify-proof <proof>`: check a progress proof from the
+//! command line.
+//!
+//! Checks that the proof was not changed since it was signed and, given
+//! the project it was made from, that its documents are still the same.
+//! The public key is printed, for comparison with the one the writer
+//! published.
+
+use cosmarium_core::project::Project;
+use cosmarium_core::proof::ProgressProof;
+use std::path::Path;
+
+/// Verify the proof at `path`, against the project at `project` if given.
+///
+/// Returns whether the proof holds.
+pub fn run(path: &Path, project: Option<&Path>) -> anyhow::Result<bool> {
+    let proof = ProgressProof::load(path)?;
+    println!("Progress proof of {}", proof.project);
+    println!("  Words:      {}", proof.word_count);
+    println!("  Signed on:  {}", proof.timestamp.to_rfc3339());
+    if let Some(commit) = &proof.commit {
+        println!("  Commit:     {}", commit);
+    }
+    println!("  Content:    {}", proof.content_hash);
+    println!("  Public key: {}", proof.public_key);
+
+    if let Err(e) = proof.verify() {
+        println!("Invalid signature: {}", e);
+        return Ok(false);
+    }
+    println!("The signature is valid.");
+
+    if let Some(project) = project {
+        let runtime = tokio::runtime::Runtime::new()?;
+        let project = runtime.block_on(Project::load(project))?;
+        if let Err(e) = proof.verify_project(&project) {
+            println!("The project does not match: {}", e);
+            return Ok(false);
+        }
+        println!("The documents of the project match the proof.");
+    }
+    Ok(true)
+}
@@ -0,0 +1,73 @@
+//! End-to-end flows through the whole application: the interface, the core
+//! managers and the plugins together, driven by the [`harness`].
+
+mod harness;
+
+use cosmarium_core::Project;
+use eframe::egui::{Key, Modifiers};
+use harness::Sandbox;
+use std::path::{Path, PathBuf};
+
+/// Create a project of one chapter in the sandbox.
+fn create_project(sandbox: &Sandbox) -> PathBuf {
+    let path = sandbox.path().join("The Inn");
+    let content = path.join("content");
+    std::fs::create_dir_all(&content).unwrap();
+    std::fs::write(content.join("chapter.md"), "It was a dark night.").unwrap();
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let mut project = Project::new("The Inn", &path, "novel").unwrap();
+        project.sync_structure();
+        project.save().await.unwrap();
+    });
+    path
+}
+
+fn chapter(project: &Path) -> String {
+    std::fs::read_to_string(project.join("content").join("chapter.md")).unwrap()
+}
+
+#[test]
+fn test_write_save_and_reopen() {
+    let sandbox = Sandbox::new();
+    let project = create_project(&sandbox);
+
+    let mut app = sandbox.launch(Some(&project));
+    assert!(app.get("The Inn").is_some(), "the project is open");
+    assert!(app
+        .text_inputs()
+        .iter()
+        .any(|text| text.contains("It was a dark night.")));
+
+    app.type_text(" Rain fell.");
+    app.press(Modifiers::CTRL, Key::S);
+    assert_eq!(chapter(&project), "It was a dark night. Rain fell.\n");
+
+    app.press(Modifiers::CTRL, Key::Num3);
+    assert!(app.is_selected("Planning"));
+    app.quit();
+
+    let app = sandbox.launch(Some(&project));
+    assert!(app
+        .text_inputs()
+        .iter()
+        .any(|text| text.contains("It was a dark night. Rain fell.")));
+    assert!(app.is_selected("Planning"), "the layout is restored");
+    app.quit();
+}
+
+#[test]
+fn test_switch_activity_from_the_menu_bar() {
+    let sandbox = Sandbox::new();
+    let mut app = sandbox.launch(None);
+    assert!(app.is_selected("Drafting"));
+
+    app.click("Revising");
+    assert!(app.is_selected("Revising"));
+    assert!(!app.is_selected("Drafting"));
+
+    app.click("Drafting");
+    assert!(app.is_selected("Drafting"));
+    app.quit();
+}
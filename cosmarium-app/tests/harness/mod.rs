@@ -0,0 +1,214 @@
+//! Headless harness driving the whole application.
+//!
+//! A [`Sandbox`] gives the application a home directory of its own, so the
+//! configuration, session and layouts it saves never touch the user's. The
+//! [`Harness`] then runs [`Cosmarium`] frame by frame without a window:
+//! input is fed as egui events, and widgets are found by their label in the
+//! accessibility tree egui builds every frame.
+
+use cosmarium_app::app::Cosmarium;
+use cosmarium_app::AppArgs;
+use eframe::egui;
+use eframe::App;
+use egui::accesskit::{Node, NodeId, Role, Toggled};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// Size of the simulated screen, in points.
+const SCREEN_SIZE: egui::Vec2 = egui::vec2(1200.0, 800.0);
+
+/// Time simulated between two frames.
+const FRAME_TIME: Duration = Duration::from_millis(16);
+
+/// Longest wait for the background tasks of the application to finish.
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sandboxes share the environment variables of the test process, so only
+/// one exists at a time.
+static SANDBOX_LOCK: Mutex<()> = Mutex::new(());
+
+/// A home directory for the application, for the length of a test.
+pub struct Sandbox {
+    home: TempDir,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        let lock = SANDBOX_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let home = tempfile::tempdir().expect("create sandbox home");
+        std::env::set_var("HOME", home.path());
+        for (var, dir) in [
+            ("XDG_CONFIG_HOME", ".config"),
+            ("XDG_DATA_HOME", ".local/share"),
+            ("XDG_CACHE_HOME", ".cache"),
+            ("XDG_DOCUMENTS_DIR", "Documents"),
+        ] {
+            std::env::set_var(var, home.path().join(dir));
+        }
+        Self { home, _lock: lock }
+    }
+
+    /// The home directory.
+    pub fn path(&self) -> &Path {
+        self.home.path()
+    }
+
+    /// Start the application, opening `project` if any.
+    pub fn launch(&self, project: Option<&Path>) -> Harness {
+        let args = AppArgs {
+            project_path: project.map(Path::to_path_buf),
+            ..AppArgs::default()
+        };
+        Harness::new(args)
+    }
+}
+
+/// The application running without a window.
+pub struct Harness {
+    ctx: egui::Context,
+    frame: eframe::Frame,
+    app: Cosmarium,
+    /// Input of the next frame
+    events: Vec<egui::Event>,
+    modifiers: egui::Modifiers,
+    time: Duration,
+    /// Accessibility tree of the last frame
+    nodes: HashMap<NodeId, Node>,
+}
+
+impl Harness {
+    fn new(args: AppArgs) -> Self {
+        let ctx = egui::Context::default();
+        ctx.enable_accesskit();
+        let cc = eframe::CreationContext::_new_kittest(ctx.clone());
+        let app = Cosmarium::new(&cc, args);
+        let mut harness = Self {
+            ctx,
+            frame: eframe::Frame::_new_kittest(),
+            app,
+            events: Vec::new(),
+            modifiers: egui::Modifiers::NONE,
+            time: Duration::ZERO,
+            nodes: HashMap::new(),
+        };
+        harness.run();
+        harness
+    }
+
+    /// Run the application until it settles: a few frames, so that
+    /// requests made through the shared state are handled, and more until
+    /// its background tasks, such as opening a project, are done.
+    pub fn run(&mut self) {
+        let deadline = Instant::now() + SETTLE_TIMEOUT;
+        loop {
+            for _ in 0..4 {
+                self.step();
+            }
+            if !self.app.is_busy() || Instant::now() > deadline {
+                break;
+            }
+            std::thread::sleep(FRAME_TIME);
+        }
+    }
+
+    /// Run one frame with the input queued since the last one.
+    pub fn step(&mut self) {
+        self.time += FRAME_TIME;
+        let input = egui::RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, SCREEN_SIZE)),
+            time: Some(self.time.as_secs_f64()),
+            predicted_dt: FRAME_TIME.as_secs_f32(),
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            ..egui::RawInput::default()
+        };
+        let (app, frame) = (&mut self.app, &mut self.frame);
+        let output = self.ctx.run(input, |ctx| app.update(ctx, frame));
+        if let Some(update) = output.platform_output.accesskit_update {
+            self.nodes = update.nodes.into_iter().collect();
+        }
+    }
+
+    /// Find the widget whose label, or text for a label, is `label`.
+    pub fn get(&self, label: &str) -> Option<&Node> {
+        self.nodes.values().find(|node| {
+            node.label() == Some(label)
+                || (node.role() == Role::Label && node.value() == Some(label))
+        })
+    }
+
+    /// Whether the widget labeled `label` is selected or checked.
+    pub fn is_selected(&self, label: &str) -> bool {
+        self.get(label)
+            .is_some_and(|node| node.toggled() == Some(Toggled::True))
+    }
+
+    /// Texts of the text fields and editors, in no particular order.
+    pub fn text_inputs(&self) -> Vec<&str> {
+        self.nodes
+            .values()
+            .filter(|node| matches!(node.role(), Role::TextInput | Role::MultilineTextInput))
+            .filter_map(|node| node.value())
+            .collect()
+    }
+
+    /// Click the widget labeled `label`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no such widget was shown in the last frame.
+    pub fn click(&mut self, label: &str) {
+        let bounds = self
+            .get(label)
+            .and_then(Node::bounds)
+            .unwrap_or_else(|| panic!("no widget labeled {:?}", label));
+        let pos = egui::pos2(
+            ((bounds.x0 + bounds.x1) / 2.0) as f32,
+            ((bounds.y0 + bounds.y1) / 2.0) as f32,
+        );
+        self.events.push(egui::Event::PointerMoved(pos));
+        self.step();
+        for pressed in [true, false] {
+            self.events.push(egui::Event::PointerButton {
+                pos,
+                button: egui::PointerButton::Primary,
+                pressed,
+                modifiers: self.modifiers,
+            });
+            self.step();
+        }
+        self.run();
+    }
+
+    /// Type `text` into the focused widget.
+    pub fn type_text(&mut self, text: &str) {
+        self.events.push(egui::Event::Text(text.to_string()));
+        self.run();
+    }
+
+    /// Press `key` with `modifiers` held.
+    pub fn press(&mut self, modifiers: egui::Modifiers, key: egui::Key) {
+        self.modifiers = modifiers;
+        for pressed in [true, false] {
+            self.events.push(egui::Event::Key {
+                key,
+                physical_key: None,
+                pressed,
+                repeat: false,
+                modifiers,
+            });
+            self.step();
+        }
+        self.modifiers = egui::Modifiers::NONE;
+        self.run();
+    }
+
+    /// Quit the application as closing its window does.
+    pub fn quit(mut self) {
+        self.app.on_exit(None);
+    }
+}
@@ -0,0 +1,68 @@
+//! Compile a project into a manuscript, without any window.
+//!
+//! ```text
+//! cargo run -p cosmarium-core --no-default-features --example compile_manuscript -- [PROJECT] [OUTPUT]
+//! ```
+//!
+//! Writes the manuscript of the project directory `PROJECT` to `OUTPUT`
+//! (`compiled` by default) as a standalone HTML page and as a Standard
+//! Manuscript Format text. Without a project, a small sample project is
+//! compiled.
+
+use cosmarium_core::config::{CompileConfig, ExportConfig};
+use cosmarium_core::{
+    compile_project, export_manuscript, CompileTarget, DocumentExportFormat, Project, Result,
+};
+use std::path::PathBuf;
+
+/// Write a sample project of one chapter of two scenes to `path`.
+fn write_sample(path: &std::path::Path) -> Result<Project> {
+    let chapter = path.join("content").join("01 Arrival");
+    std::fs::create_dir_all(&chapter)?;
+    std::fs::write(
+        chapter.join("01 Dawn.md"),
+        "The inn woke before the travellers did.",
+    )?;
+    std::fs::write(
+        chapter.join("02 Dusk.md"),
+        "By nightfall, every room was taken.",
+    )?;
+
+    let mut project = Project::new("The Inn", path, "novel")?;
+    project.metadata_mut().author = "Ann Author".to_string();
+    project.sync_structure();
+    Ok(project)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let sample = tempfile::tempdir()?;
+    let project = match args.next() {
+        Some(path) => Project::load(path).await?,
+        None => write_sample(sample.path())?,
+    };
+    let output_dir = args
+        .next()
+        .map_or_else(|| PathBuf::from("compiled"), PathBuf::from);
+
+    let manuscript = compile_project(&project, &[], &CompileConfig::default());
+    println!(
+        "Compiled {} documents of \"{}\"",
+        manuscript.document_count(),
+        manuscript.title
+    );
+
+    let config = ExportConfig::default();
+    for format in [DocumentExportFormat::Html, DocumentExportFormat::SmfText] {
+        let output = export_manuscript(
+            &manuscript,
+            CompileTarget::Builtin(format),
+            &output_dir,
+            &config,
+            None,
+        )?;
+        println!("{}: {}", format.display_name(), output.display());
+    }
+    Ok(())
+}
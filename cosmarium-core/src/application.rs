@@ -8,8 +8,8 @@
 //! provided through plugins, with the core handling coordination and infrastructure.
 
 use crate::{
-    config::Config, document::DocumentManager, error::Result, events::EventBus,
-    layout::LayoutManager, plugin::PluginManager, project::ProjectManager,
+    config::Config, document::DocumentManager, error::Result, events::EventBus, executor::Executor,
+    layout::LayoutManager, plugin::PluginManager, project::ProjectManager, task::TaskManager,
 };
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -52,6 +52,10 @@ pub struct Application {
     event_bus: Arc<RwLock<EventBus>>,
     /// Application configuration
     config: Arc<RwLock<Config>>,
+    /// Shared async executor
+    executor: Arc<Executor>,
+    /// Background task tracking
+    task_manager: Arc<TaskManager>,
     /// Whether the application has been initialized
     initialized: bool,
 }
@@ -73,6 +77,8 @@ impl Application {
     pub fn new() -> Self {
         info!("Creating new Cosmarium application instance");
 
+        let executor = Arc::new(Executor::new());
+
         Self {
             plugin_manager: Arc::new(RwLock::new(PluginManager::new())),
             project_manager: Arc::new(RwLock::new(ProjectManager::new())),
@@ -80,6 +86,8 @@ impl Application {
             layout_manager: Arc::new(RwLock::new(LayoutManager::new())),
             event_bus: Arc::new(RwLock::new(EventBus::new())),
             config: Arc::new(RwLock::new(Config::default())),
+            executor: Arc::clone(&executor),
+            task_manager: Arc::new(TaskManager::new(executor)),
             initialized: false,
         }
     }
@@ -195,6 +203,9 @@ impl Application {
 
         info!("Shutting down Cosmarium application");
 
+        // Stop background work before tearing down what it may rely on
+        self.task_manager.cancel_all();
+
         // Shutdown managers in reverse order
         {
             let mut layout_manager = self.layout_manager.write().await;
@@ -343,6 +354,35 @@ impl Application {
         Arc::clone(&self.config)
     }
 
+    /// Get a reference to the shared async executor.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::Application;
+    ///
+    /// let app = Application::new();
+    /// let executor = app.executor();
+    /// assert_eq!(executor.block_on(async { 2 + 2 }), 4);
+    /// ```
+    pub fn executor(&self) -> Arc<Executor> {
+        Arc::clone(&self.executor)
+    }
+
+    /// Get a reference to the background task manager.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::Application;
+    ///
+    /// let app = Application::new();
+    /// assert!(app.task_manager().running().is_empty());
+    /// ```
+    pub fn task_manager(&self) -> Arc<TaskManager> {
+        Arc::clone(&self.task_manager)
+    }
+
     /// Run the application update cycle.
     ///
     /// This method should be called regularly (typically once per frame)
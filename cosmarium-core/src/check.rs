@@ -0,0 +1,695 @@
+//! # Project integrity checks
+//!
+//! Validates a project against its files: document references no node
+//! uses, nodes of the structure whose file is gone, links of the documents
+//! to missing images and other assets, metadata fields that cannot be read,
+//! and the health of the Git repository.
+//!
+//! Each [`Issue`] comes with the [`Repair`]s that apply to it: pointing a
+//! node or a link at a file of the same name found elsewhere, removing the
+//! reference, or restoring the file from the last commit. Nothing is
+//! changed until a repair is chosen and applied with [`repair`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use cosmarium_core::check::{check_project, repair};
+//! use cosmarium_core::project::Project;
+//!
+//! # tokio_test::block_on(async {
+//! let mut project = Project::load("my_novel").await?;
+//! for issue in check_project(&project, &[]).issues {
+//!     println!("{}", issue);
+//!     if let Some(fix) = issue.repairs.first() {
+//!         repair(&mut project, &issue, fix)?;
+//!     }
+//! }
+//! project.save().await?;
+//! # Ok::<(), cosmarium_core::Error>(())
+//! # });
+//! ```
+
+use crate::project::Project;
+use crate::structure::StructureNode;
+use crate::{Error, Result};
+use cosmarium_plugin_api::metadata::{SceneMetadata, Status};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+/// File extensions of the documents whose links are checked.
+const TEXT_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// Folders of the project that are not searched for files to relink to.
+const SKIPPED_DIRS: &[&str] = &[".git", "meta"];
+
+/// What is wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IssueKind {
+    /// A document reference that nothing uses, or that is not registered
+    DanglingDocument,
+    /// A node of the structure whose folder or file is missing
+    MissingFile,
+    /// A link of a document to a file that is missing
+    BrokenAsset,
+    /// A metadata field whose value cannot be read
+    MalformedMetadata,
+    /// A problem of the Git repository
+    Repository,
+}
+
+impl IssueKind {
+    /// Label of the issues of this kind.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::DanglingDocument => "Dangling document references",
+            Self::MissingFile => "Missing content files",
+            Self::BrokenAsset => "Broken asset links",
+            Self::MalformedMetadata => "Malformed metadata",
+            Self::Repository => "Git repository",
+        }
+    }
+}
+
+/// What an issue is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Subject {
+    /// A document registered in the project
+    Document(Uuid),
+    /// A node of the structure
+    Node(Uuid),
+    /// A link of a document
+    Link {
+        /// Document, relative to the project root
+        document: String,
+        /// Line of the link, from 1
+        line: usize,
+        /// Target of the link, as written
+        target: String,
+    },
+    /// A metadata field of a node
+    Field {
+        /// Node of the field
+        node: Uuid,
+        /// Name of the field
+        key: String,
+    },
+    /// The Git repository
+    Repository,
+}
+
+/// A way to fix an issue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Repair {
+    /// Point the node or link at this file, relative to the project root
+    Relink(String),
+    /// Remove the reference, node or field
+    Remove,
+    /// Write the file back as it was in the last commit
+    RestoreFromGit,
+}
+
+impl Repair {
+    /// Label of the repair.
+    pub fn label(&self) -> String {
+        match self {
+            Self::Relink(path) => format!("Relink to {}", path),
+            Self::Remove => "Remove".to_string(),
+            Self::RestoreFromGit => "Restore from Git".to_string(),
+        }
+    }
+}
+
+/// A problem found in a project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// What is wrong
+    pub kind: IssueKind,
+    /// What it is about
+    pub subject: Subject,
+    /// Description of the problem
+    pub message: String,
+    /// Ways to fix it, the most likely first; empty if it must be fixed by
+    /// hand
+    pub repairs: Vec<Repair>,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind.label(), self.message)
+    }
+}
+
+/// Problems found in a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckReport {
+    /// Problems, grouped by kind
+    pub issues: Vec<Issue>,
+}
+
+impl CheckReport {
+    /// Whether nothing is wrong.
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Kinds with issues and their issues, in order.
+    pub fn groups(&self) -> Vec<(IssueKind, &[Issue])> {
+        self.issues
+            .chunk_by(|a, b| a.kind == b.kind)
+            .map(|issues| (issues[0].kind, issues))
+            .collect()
+    }
+}
+
+/// Check `project` against its files. Documents of `open_documents` are in
+/// use even if no node references them.
+pub fn check_project(project: &Project, open_documents: &[Uuid]) -> CheckReport {
+    let root = project.path();
+    let files = project_files(root);
+    let mut issues = Vec::new();
+    check_documents(project, open_documents, &mut issues);
+    check_structure(project, &files, &mut issues);
+    check_links(project, &files, &mut issues);
+    check_metadata(project, &mut issues);
+    check_repository(project, &mut issues);
+    issues.sort_by_key(|issue| issue.kind);
+    CheckReport { issues }
+}
+
+fn check_documents(project: &Project, open_documents: &[Uuid], issues: &mut Vec<Issue>) {
+    let registered: HashSet<Uuid> = project.documents().iter().copied().collect();
+    let linked: HashSet<Uuid> = project
+        .structure()
+        .iter()
+        .filter_map(|node| node.document)
+        .collect();
+    for id in project.documents() {
+        if !linked.contains(id) && !open_documents.contains(id) {
+            issues.push(Issue {
+                kind: IssueKind::DanglingDocument,
+                subject: Subject::Document(*id),
+                message: format!("Document {} is registered but used by no node", id),
+                repairs: vec![Repair::Remove],
+            });
+        }
+    }
+    for node in project.structure().iter() {
+        if let Some(id) = node.document.filter(|id| !registered.contains(id)) {
+            issues.push(Issue {
+                kind: IssueKind::DanglingDocument,
+                subject: Subject::Node(node.id),
+                message: format!("\"{}\" refers to unknown document {}", node.title, id),
+                repairs: vec![Repair::Remove],
+            });
+        }
+    }
+}
+
+fn check_structure(project: &Project, files: &[String], issues: &mut Vec<Issue>) {
+    let root = project.path();
+    let in_structure: HashSet<&str> = project
+        .structure()
+        .iter()
+        .filter_map(|node| node.path.as_deref())
+        .collect();
+    for node in project.structure().iter() {
+        let Some(path) = node.path.as_deref() else {
+            continue;
+        };
+        let file = root.join(path);
+        let present = if node.is_container() {
+            file.is_dir()
+        } else {
+            file.is_file()
+        };
+        if present {
+            continue;
+        }
+        let mut repairs = Vec::new();
+        if !node.is_container() {
+            repairs.extend(
+                same_name(files, path)
+                    .filter(|candidate| candidate.starts_with("content/"))
+                    .filter(|candidate| !in_structure.contains(candidate.as_str()))
+                    .map(Repair::Relink),
+            );
+            if in_last_commit(project, path) {
+                repairs.push(Repair::RestoreFromGit);
+            }
+        }
+        repairs.push(Repair::Remove);
+        issues.push(Issue {
+            kind: IssueKind::MissingFile,
+            subject: Subject::Node(node.id),
+            message: format!("\"{}\": {} is missing", node.title, path),
+            repairs,
+        });
+    }
+}
+
+fn check_links(project: &Project, files: &[String], issues: &mut Vec<Issue>) {
+    let root = project.path();
+    for document in files.iter().filter(|file| is_text(file)) {
+        let Ok(text) = std::fs::read_to_string(root.join(document)) else {
+            continue;
+        };
+        for (line, target) in links(&text) {
+            let Some(path) = resolve(document, &target) else {
+                continue;
+            };
+            if root.join(&path).exists() {
+                continue;
+            }
+            let mut repairs: Vec<Repair> = same_name(files, &path).map(Repair::Relink).collect();
+            if in_last_commit(project, &path) {
+                repairs.push(Repair::RestoreFromGit);
+            }
+            issues.push(Issue {
+                kind: IssueKind::BrokenAsset,
+                message: format!("{}:{}: {} is missing", document, line, target),
+                subject: Subject::Link {
+                    document: document.clone(),
+                    line,
+                    target,
+                },
+                repairs,
+            });
+        }
+    }
+}
+
+fn check_metadata(project: &Project, issues: &mut Vec<Issue>) {
+    for node in project.structure().iter() {
+        for key in malformed_fields(node) {
+            issues.push(Issue {
+                kind: IssueKind::MalformedMetadata,
+                message: format!(
+                    "\"{}\": {} cannot be read ({:?})",
+                    node.title, key, node.metadata[key]
+                ),
+                subject: Subject::Field {
+                    node: node.id,
+                    key: key.to_string(),
+                },
+                repairs: vec![Repair::Remove],
+            });
+        }
+    }
+}
+
+fn check_repository(project: &Project, issues: &mut Vec<Issue>) {
+    // Builds without Git support never version projects
+    if !cfg!(feature = "git") {
+        return;
+    }
+    let problems = match project.git() {
+        Some(git) => git.check(),
+        None => vec!["The project has no Git repository".to_string()],
+    };
+    issues.extend(problems.into_iter().map(|message| Issue {
+        kind: IssueKind::Repository,
+        subject: Subject::Repository,
+        message,
+        repairs: Vec::new(),
+    }));
+}
+
+/// Keys of the structured metadata fields of `node` that cannot be read.
+fn malformed_fields(node: &StructureNode) -> Vec<&str> {
+    let read = SceneMetadata::from_fields(&node.metadata);
+    let field = |key: &str| {
+        node.metadata
+            .get(key)
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    };
+    let mut keys = Vec::new();
+    if field("status").is_some_and(|value| Status::parse(value).is_none()) {
+        keys.push("status");
+    }
+    if field("label").is_some() && read.label.is_none() {
+        keys.push("label");
+    }
+    if field("target_words").is_some() && read.target_words.is_none() {
+        keys.push("target_words");
+    }
+    keys
+}
+
+/// Apply `fix` to `issue` of `project`. Links are fixed in the document
+/// files; save the project to keep the other repairs.
+///
+/// # Errors
+///
+/// Returns an error if the repair does not apply to the issue or fails.
+pub fn repair(project: &mut Project, issue: &Issue, fix: &Repair) -> Result<()> {
+    let root = project.path().to_path_buf();
+    match (&issue.subject, fix) {
+        (Subject::Document(id), Repair::Remove) => project.remove_document(*id),
+        (Subject::Node(id), Repair::Remove) if issue.kind == IssueKind::DanglingDocument => {
+            node_mut(project, *id)?.document = None;
+        }
+        (Subject::Node(id), Repair::Remove) => {
+            project.structure_mut().remove(*id);
+        }
+        (Subject::Node(id), Repair::Relink(path)) => {
+            node_mut(project, *id)?.path = Some(path.clone());
+        }
+        (Subject::Node(id), Repair::RestoreFromGit) => {
+            let path = project
+                .structure()
+                .find(*id)
+                .and_then(|node| node.path.clone())
+                .ok_or_else(|| Error::not_found(format!("file of structure node {}", id)))?;
+            restore(project, &path)?;
+        }
+        (Subject::Field { node, key }, Repair::Remove) => {
+            node_mut(project, *node)?.metadata.remove(key);
+        }
+        (Subject::Link { document, .. }, Repair::Relink(_)) => {
+            let file = root.join(document);
+            let text = std::fs::read_to_string(&file)?;
+            if let Some(relinked) = relink_document(issue, fix, &text) {
+                std::fs::write(&file, relinked)?;
+            }
+        }
+        (
+            Subject::Link {
+                document, target, ..
+            },
+            Repair::RestoreFromGit,
+        ) => {
+            let path = resolve(document, target)
+                .ok_or_else(|| Error::not_found(format!("file of link {}", target)))?;
+            restore(project, &path)?;
+        }
+        _ => {
+            return Err(Error::generic(format!(
+                "{} does not apply to: {}",
+                fix.label(),
+                issue
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn node_mut(project: &mut Project, id: Uuid) -> Result<&mut StructureNode> {
+    project
+        .structure_mut()
+        .find_mut(id)
+        .ok_or_else(|| Error::not_found(format!("structure node {}", id)))
+}
+
+/// Write the file at `path`, relative to the root of `project`, back as it
+/// was in the last commit.
+fn restore(project: &Project, path: &str) -> Result<()> {
+    let git = project
+        .git()
+        .ok_or_else(|| Error::project("The project has no Git repository"))?;
+    let content = git
+        .file_at_head(path)?
+        .ok_or_else(|| Error::not_found(format!("{} in the last commit", path)))?;
+    let file = project.path().join(path);
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(file, content)?;
+    Ok(())
+}
+
+fn in_last_commit(project: &Project, path: &str) -> bool {
+    project
+        .git()
+        .and_then(|git| git.file_at_head(path).ok().flatten())
+        .is_some()
+}
+
+/// `text`, the content of the document of a broken link, with the link
+/// relinked by `fix`; `None` for other issues and repairs.
+///
+/// Lets the application relink documents open with unsaved edits.
+pub fn relink_document(issue: &Issue, fix: &Repair, text: &str) -> Option<String> {
+    match (&issue.subject, fix) {
+        (
+            Subject::Link {
+                document,
+                line,
+                target,
+            },
+            Repair::Relink(path),
+        ) => Some(relink(text, *line, target, &relative_link(document, path))),
+        _ => None,
+    }
+}
+
+/// Replace the links to `target` on `line` (from 1) of `text` by links to
+/// `to`, keeping their fragment and title.
+pub fn relink(text: &str, line: usize, target: &str, to: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for (n, content) in text.split_inclusive('\n').enumerate() {
+        if n + 1 == line {
+            let relinked = content
+                .replace(&format!("]({}", target), &format!("]({}", to))
+                .replace(&format!("](<{}", target), &format!("](<{}", to));
+            result.push_str(&relinked);
+        } else {
+            result.push_str(content);
+        }
+    }
+    result
+}
+
+/// Local targets of the Markdown links and images of `text`, with their
+/// line from 1.
+fn links(text: &str) -> Vec<(usize, String)> {
+    let mut links = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let mut rest = line;
+        while let Some(start) = rest.find("](") {
+            rest = &rest[start + 2..];
+            let Some(end) = rest.find(')') else {
+                break;
+            };
+            let written = rest[..end].trim();
+            let target = match written.strip_prefix('<') {
+                Some(inner) => inner.split('>').next().unwrap_or_default(),
+                None => written.split_whitespace().next().unwrap_or_default(),
+            };
+            let external = target.contains("://")
+                || target.starts_with('#')
+                || target.starts_with("mailto:")
+                || target.is_empty();
+            if !external {
+                let path = target.split('#').next().unwrap_or_default();
+                links.push((n + 1, path.to_string()));
+            }
+            rest = &rest[end..];
+        }
+    }
+    links
+}
+
+/// Path relative to the project root of the `target` of a link in
+/// `document`, `None` if it points outside the project.
+fn resolve(document: &str, target: &str) -> Option<String> {
+    let target = target.replace("%20", " ");
+    let mut parts: Vec<&str> = Vec::new();
+    if !target.starts_with('/') {
+        parts.extend(document.split('/'));
+        // The folder of the document
+        parts.pop();
+    }
+    for part in target.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Link text from `document` to `path`, both relative to the project root.
+fn relative_link(document: &str, path: &str) -> String {
+    let from: Vec<&str> = document.split('/').collect();
+    let from = &from[..from.len() - 1];
+    let to: Vec<&str> = path.split('/').collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts = vec![".."; from.len() - common];
+    parts.extend(&to[common..]);
+    parts.join("/").replace(' ', "%20")
+}
+
+/// Files with the file name of `path`, other than `path`.
+fn same_name<'a>(files: &'a [String], path: &'a str) -> impl Iterator<Item = String> + 'a {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    files
+        .iter()
+        .filter(move |file| file.as_str() != path && file.rsplit('/').next() == Some(name))
+        .cloned()
+}
+
+fn is_text(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| TEXT_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Files of the project, relative to its root with `/` separators, sorted.
+fn project_files(root: &Path) -> Vec<String> {
+    let mut files: Vec<String> = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() != 1
+                || !SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref())
+        })
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let rel = entry.path().strip_prefix(root).ok()?;
+            Some(
+                rel.components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/"),
+            )
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::NodeKind;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_link_paths() {
+        let text = "# Dawn\n![Map](../assets/old/map.png \"Harbor\") and [site](https://x.org)\n\
+                    See [notes](<../notes/inn%20keeper.md>#top) and [top](#top).\n";
+        let links = links(text);
+        assert_eq!(
+            links,
+            vec![
+                (2, "../assets/old/map.png".to_string()),
+                (3, "../notes/inn%20keeper.md".to_string()),
+            ]
+        );
+        assert_eq!(
+            resolve("content/dawn.md", &links[1].1).as_deref(),
+            Some("notes/inn keeper.md")
+        );
+        assert_eq!(resolve("dawn.md", "../../x.png"), None);
+        assert_eq!(
+            resolve("content/a/dawn.md", "/assets/map.png").as_deref(),
+            Some("assets/map.png")
+        );
+        assert_eq!(
+            relative_link("content/a/dawn.md", "assets/maps/map.png"),
+            "../../assets/maps/map.png"
+        );
+        assert_eq!(
+            relink(text, 2, "../assets/old/map.png", "../assets/map.png"),
+            text.replace("old/map.png", "map.png")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_issues_and_repairs() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("content/arrival")).unwrap();
+        std::fs::create_dir_all(root.join("assets/maps")).unwrap();
+        std::fs::write(
+            root.join("content/arrival/dawn.md"),
+            "Fog.\n![Harbor](../../assets/map.png)\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("assets/maps/map.png"), b"png").unwrap();
+
+        let mut project = Project::new("Novel", root, "novel").unwrap();
+        project.sync_structure();
+        let stale = Uuid::new_v4();
+        project.add_document(stale);
+        let moved = project
+            .add_node(
+                None,
+                usize::MAX,
+                StructureNode::new(NodeKind::Document, "Dusk").with_path("content/dusk.md"),
+            )
+            .unwrap();
+        std::fs::write(root.join("content/arrival/dusk.md"), "Rain.").unwrap();
+        let dawn = project
+            .node_of_file(Path::new("content/arrival/dawn.md"))
+            .unwrap()
+            .id;
+        let metadata = SceneMetadata {
+            status: Status::Revised,
+            ..Default::default()
+        };
+        project.set_scene_metadata(dawn, &metadata).unwrap();
+        node_mut(&mut project, dawn)
+            .unwrap()
+            .metadata
+            .insert("label".to_string(), "red".to_string());
+
+        let report = check_project(&project, &[]);
+        let kinds: Vec<IssueKind> = report.issues.iter().map(|issue| issue.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                IssueKind::DanglingDocument,
+                IssueKind::MissingFile,
+                IssueKind::BrokenAsset,
+                IssueKind::MalformedMetadata,
+            ]
+        );
+        // An open document is in use
+        assert!(check_project(&project, &[stale])
+            .issues
+            .iter()
+            .all(|issue| issue.kind != IssueKind::DanglingDocument));
+
+        // The new file of the moved document was not in the structure yet
+        assert_eq!(
+            report.issues[1].repairs,
+            vec![
+                Repair::Relink("content/arrival/dusk.md".to_string()),
+                Repair::Remove
+            ]
+        );
+        assert_eq!(report.groups().len(), 4);
+        for issue in &report.issues {
+            repair(&mut project, issue, &issue.repairs[0]).unwrap();
+        }
+        assert!(check_project(&project, &[]).is_empty());
+        assert_eq!(
+            project.structure().find(moved).unwrap().path.as_deref(),
+            Some("content/arrival/dusk.md")
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("content/arrival/dawn.md")).unwrap(),
+            "Fog.\n![Harbor](../../assets/maps/map.png)\n"
+        );
+        let node = project.structure().find(dawn).unwrap();
+        assert_eq!(node.scene_metadata().status, Status::Revised);
+        assert!(!node.metadata.contains_key("label"));
+
+        let issue = Issue {
+            kind: IssueKind::Repository,
+            subject: Subject::Repository,
+            message: String::new(),
+            repairs: Vec::new(),
+        };
+        assert!(repair(&mut project, &issue, &Repair::Remove).is_err());
+    }
+}
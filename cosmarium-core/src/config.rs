@@ -12,9 +12,15 @@
 //! 3. Configuration file
 //! 4. Default values (lowest priority)
 
+use crate::document::LineEnding;
+use crate::export::compile::EpigraphPlacement;
+use crate::export::filter::TextFilters;
+use crate::profile::{AuthorIdentity, AuthorProfile};
+use crate::theme::ThemeScheduleConfig;
 use crate::{Error, Result};
+use cosmarium_plugin_api::locale::Locale;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 
 /// Main configuration structure for Cosmarium.
@@ -48,6 +54,12 @@ pub struct Config {
     pub export: ExportConfig,
     /// Advanced/experimental settings
     pub advanced: AdvancedConfig,
+    /// Writing goals
+    #[serde(default)]
+    pub goals: GoalsConfig,
+    /// Identities of the author, which projects are published under
+    #[serde(default)]
+    pub profiles: Vec<AuthorProfile>,
 }
 
 /// Application-wide configuration settings.
@@ -70,7 +82,7 @@ pub struct AppConfig {
 /// User interface configuration settings.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UiConfig {
-    /// UI theme name
+    /// UI theme name (`"dark"`, `"light"` or `"auto"`)
     pub theme: String,
     /// Base font size
     pub font_size: f32,
@@ -90,6 +102,9 @@ pub struct UiConfig {
     pub animation_duration: u64,
     /// Whether to use smooth scrolling
     pub smooth_scrolling: bool,
+    /// Automatic light/dark scheduling, used when `theme` is `"auto"`
+    #[serde(default)]
+    pub theme_schedule: ThemeScheduleConfig,
 }
 
 /// Text editor configuration settings.
@@ -111,12 +126,32 @@ pub struct EditorConfig {
     pub highlight_current_line: bool,
     /// Whether to enable word wrap
     pub word_wrap: bool,
-    /// Word wrap column (0 = use window width)
+    /// Word wrap column (0 = use window width), shown by a guide in the
+    /// editor
     pub word_wrap_column: usize,
+    /// Whether to break the lines longer than `word_wrap_column` on save,
+    /// for plain-text workflows
+    #[serde(default)]
+    pub hard_wrap_on_save: bool,
     /// Whether to show whitespace characters
     pub show_whitespace: bool,
     /// Whether to trim trailing whitespace on save
     pub trim_trailing_whitespace: bool,
+    /// Whether to clean up text pasted from word processors
+    #[serde(default = "default_true")]
+    pub paste_cleanup: bool,
+    /// Widest column of text in focus mode, in points
+    #[serde(default = "default_focus_width")]
+    pub focus_width: f32,
+    /// Whether focus mode dims all paragraphs but the one being written
+    #[serde(default = "default_true")]
+    pub focus_dim_paragraphs: bool,
+    /// Most undo steps kept for each document
+    #[serde(default = "default_undo_depth")]
+    pub undo_depth: usize,
+    /// Whether typing is undone a sentence at a time rather than a word
+    #[serde(default)]
+    pub undo_by_sentence: bool,
     /// Auto-indent style
     pub auto_indent: String,
     /// Spell check language
@@ -125,6 +160,18 @@ pub struct EditorConfig {
     pub spell_check_enabled: bool,
 }
 
+fn default_true() -> bool {
+    true
+}
+
+fn default_undo_depth() -> usize {
+    100
+}
+
+fn default_focus_width() -> f32 {
+    720.0
+}
+
 /// Plugin system configuration settings.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginConfig {
@@ -161,6 +208,48 @@ pub struct ProjectConfig {
     pub enable_templates: bool,
     /// Default project template
     pub default_template: String,
+    /// Mirror of each project kept in a second location
+    #[serde(default)]
+    pub second_copy: SecondCopyConfig,
+}
+
+/// When the second copy of a project is brought up to date.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecondCopySchedule {
+    /// Each time the project is closed
+    #[default]
+    OnClose,
+    /// When the project is opened or closed, once a day at most
+    Daily,
+}
+
+impl SecondCopySchedule {
+    /// All schedules, in settings order.
+    pub const ALL: [SecondCopySchedule; 2] = [Self::OnClose, Self::Daily];
+
+    /// Human-readable name for the settings.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::OnClose => "Each time the project is closed",
+            Self::Daily => "Once a day",
+        }
+    }
+}
+
+/// Mirror of the projects on an external drive or a network share, apart
+/// from the backups kept next to them.
+///
+/// See [`crate::project::mirror`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecondCopyConfig {
+    /// Whether projects are mirrored
+    pub enabled: bool,
+    /// Folder the projects are mirrored in, each in a folder of its name
+    pub location: PathBuf,
+    /// When the copy is brought up to date
+    pub schedule: SecondCopySchedule,
 }
 
 /// Export configuration settings.
@@ -176,10 +265,110 @@ pub struct ExportConfig {
     pub html: HtmlExportConfig,
     /// Word export settings
     pub word: WordExportConfig,
+    /// Anonymized (blind submission) export settings
+    #[serde(default)]
+    pub anonymize: AnonymizeExportConfig,
+    /// Whether to append the project glossary to exported documents
+    #[serde(default)]
+    pub append_glossary: bool,
+    /// Manuscript compilation settings
+    #[serde(default)]
+    pub compile: CompileConfig,
+    /// Pandoc export backend settings
+    #[serde(default)]
+    pub pandoc: PandocExportConfig,
+    /// Line ending of text exports, set from the project settings
+    #[serde(skip)]
+    pub line_ending: LineEnding,
+    /// Format of the numbers written in exports, set from the application
+    /// language
+    #[serde(skip)]
+    pub locale: Locale,
+}
+
+impl ExportConfig {
+    /// Settings of an export format, as handed to export plugins (`Null`
+    /// for formats without settings).
+    pub fn format_options(&self, format_id: &str) -> serde_json::Value {
+        let options = match format_id {
+            "pdf" => serde_json::to_value(&self.pdf),
+            "html" => serde_json::to_value(&self.html),
+            "docx" | "word" => serde_json::to_value(&self.word),
+            _ => return serde_json::Value::Null,
+        };
+        options.unwrap_or_default()
+    }
+}
+
+/// Word targets of writing sessions and days.
+///
+/// The target of a project is stored with the project; see
+/// [`crate::goals`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GoalsConfig {
+    /// Words to write in each session, 0 for no target
+    pub session_words: usize,
+    /// Words to write each day, 0 for no target
+    pub daily_words: usize,
+    /// Whether to congratulate the author when a target is reached
+    pub celebrate: bool,
+}
+
+impl Default for GoalsConfig {
+    fn default() -> Self {
+        Self {
+            session_words: 0,
+            daily_words: 0,
+            celebrate: true,
+        }
+    }
+}
+
+/// Settings for compiling a whole manuscript.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompileConfig {
+    /// Whether to open the manuscript with its title and author
+    pub title_page: bool,
+    /// Markdown placed after the title page (dedication, epigraph...)
+    pub front_matter: String,
+    /// Whether parts, chapters and folders get a heading
+    pub container_headings: bool,
+    /// Markdown written between chapters
+    pub chapter_separator: String,
+    /// Markdown written between the scenes of a chapter
+    pub scene_separator: String,
+    /// Name, address, e-mail and phone of the author, one per line, for the
+    /// title page of standard manuscripts
+    pub contact_info: String,
+    /// Names, redactions, dashes and spelling rewritten in the manuscript
+    pub filters: TextFilters,
+    /// Where the epigraphs declared by chapters appear
+    pub epigraphs: EpigraphPlacement,
+}
+
+impl CompileConfig {
+    /// The settings signing with `identity`: its placeholders filled in the
+    /// front matter and contact details, and its own contact details if it
+    /// has any.
+    pub fn for_identity(&self, identity: &AuthorIdentity) -> Self {
+        let contact_info = if identity.contact_info.is_empty() {
+            identity.fill(&self.contact_info)
+        } else {
+            identity.contact_info.clone()
+        };
+        Self {
+            front_matter: identity.fill(&self.front_matter),
+            contact_info,
+            ..self.clone()
+        }
+    }
 }
 
 /// PDF export specific settings.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PdfExportConfig {
     /// Paper size (A4, Letter, etc.)
     pub paper_size: String,
@@ -191,10 +380,16 @@ pub struct PdfExportConfig {
     /// Font settings
     pub font_family: String,
     pub font_size: f32,
+    /// Line height, as a multiple of the font size
+    pub line_spacing: f32,
+    /// Text of scene breaks
+    pub scene_break: String,
     /// Whether to include table of contents
     pub include_toc: bool,
     /// Whether to include page numbers
     pub include_page_numbers: bool,
+    /// Whether pages have a "Surname / Title / page" header
+    pub running_header: bool,
 }
 
 /// HTML export specific settings.
@@ -214,8 +409,10 @@ pub struct HtmlExportConfig {
 
 /// Word export specific settings.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WordExportConfig {
-    /// Document template to use
+    /// Document template to use: `default`, `manuscript`, or the path of a
+    /// `.docx` or `.dotx` file whose styles are used
     pub template: String,
     /// Whether to preserve formatting
     pub preserve_formatting: bool,
@@ -223,6 +420,29 @@ pub struct WordExportConfig {
     pub include_comments: bool,
     /// Whether to track changes
     pub track_changes: bool,
+    /// Whether pages after the title page have a "Surname / Title / page"
+    /// header
+    pub running_header: bool,
+}
+
+/// Anonymized export settings, for contests and blind submissions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnonymizeExportConfig {
+    /// Names removed from anonymized exports, besides the project author
+    pub identifying_names: Vec<String>,
+    /// Text replacing removed names
+    pub placeholder: String,
+}
+
+/// Settings of the export backend running pandoc.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PandocExportConfig {
+    /// Whether to offer the formats of pandoc when it is installed
+    pub enabled: bool,
+    /// Path or name of the pandoc executable
+    pub executable: String,
 }
 
 /// Advanced/experimental configuration settings.
@@ -234,6 +454,13 @@ pub struct AdvancedConfig {
     pub log_level: String,
     /// Log to file
     pub log_to_file: bool,
+    /// Log levels of particular targets, overriding `log_level`, e.g.
+    /// `cosmarium_markdown_editor = "debug"`
+    #[serde(default)]
+    pub log_targets: BTreeMap<String, String>,
+    /// Write log records as JSON lines
+    #[serde(default)]
+    pub log_json: bool,
     /// Performance profiling enabled
     pub profiling_enabled: bool,
     /// Memory limit in MB (0 = unlimited)
@@ -254,6 +481,8 @@ impl Default for Config {
             project: ProjectConfig::default(),
             export: ExportConfig::default(),
             advanced: AdvancedConfig::default(),
+            goals: GoalsConfig::default(),
+            profiles: Vec::new(),
         }
     }
 }
@@ -284,6 +513,7 @@ impl Default for UiConfig {
             show_splash: true,
             animation_duration: 200,
             smooth_scrolling: true,
+            theme_schedule: ThemeScheduleConfig::default(),
         }
     }
 }
@@ -300,8 +530,14 @@ impl Default for EditorConfig {
             highlight_current_line: true,
             word_wrap: true,
             word_wrap_column: 80,
+            hard_wrap_on_save: false,
             show_whitespace: false,
             trim_trailing_whitespace: true,
+            paste_cleanup: true,
+            focus_width: default_focus_width(),
+            focus_dim_paragraphs: true,
+            undo_depth: default_undo_depth(),
+            undo_by_sentence: false,
             auto_indent: "smart".to_string(),
             spell_check_language: "en_US".to_string(),
             spell_check_enabled: true,
@@ -345,6 +581,7 @@ impl Default for ProjectConfig {
             backup_interval: 10,
             enable_templates: true,
             default_template: "novel".to_string(),
+            second_copy: SecondCopyConfig::default(),
         }
     }
 }
@@ -359,6 +596,45 @@ impl Default for ExportConfig {
             pdf: PdfExportConfig::default(),
             html: HtmlExportConfig::default(),
             word: WordExportConfig::default(),
+            anonymize: AnonymizeExportConfig::default(),
+            append_glossary: false,
+            compile: CompileConfig::default(),
+            pandoc: PandocExportConfig::default(),
+            line_ending: LineEnding::default(),
+            locale: Locale::default(),
+        }
+    }
+}
+
+impl Default for CompileConfig {
+    fn default() -> Self {
+        Self {
+            title_page: true,
+            front_matter: String::new(),
+            container_headings: true,
+            chapter_separator: String::new(),
+            scene_separator: "***".to_string(),
+            contact_info: String::new(),
+            filters: TextFilters::default(),
+            epigraphs: EpigraphPlacement::default(),
+        }
+    }
+}
+
+impl Default for AnonymizeExportConfig {
+    fn default() -> Self {
+        Self {
+            identifying_names: Vec::new(),
+            placeholder: "[Author]".to_string(),
+        }
+    }
+}
+
+impl Default for PandocExportConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            executable: "pandoc".to_string(),
         }
     }
 }
@@ -373,8 +649,11 @@ impl Default for PdfExportConfig {
             margin_right: 25.0,
             font_family: "Liberation Serif".to_string(),
             font_size: 11.0,
+            line_spacing: 1.4,
+            scene_break: "*     *     *".to_string(),
             include_toc: true,
             include_page_numbers: true,
+            running_header: false,
         }
     }
 }
@@ -398,6 +677,7 @@ impl Default for WordExportConfig {
             preserve_formatting: true,
             include_comments: false,
             track_changes: false,
+            running_header: false,
         }
     }
 }
@@ -408,6 +688,8 @@ impl Default for AdvancedConfig {
             debug_mode: false,
             log_level: "info".to_string(),
             log_to_file: false,
+            log_targets: BTreeMap::new(),
+            log_json: false,
             profiling_enabled: false,
             memory_limit: 0,
             network_timeout: 30,
@@ -582,6 +864,23 @@ impl Config {
             ));
         }
 
+        let schedule = &self.ui.theme_schedule;
+        if !(-90.0..=90.0).contains(&schedule.latitude)
+            || !(-180.0..=180.0).contains(&schedule.longitude)
+        {
+            return Err(Error::validation(
+                "ui.theme_schedule",
+                "Latitude must be within ±90 and longitude within ±180 degrees",
+            ));
+        }
+
+        if schedule.day_start_minute >= 24 * 60 || schedule.night_start_minute >= 24 * 60 {
+            return Err(Error::validation(
+                "ui.theme_schedule",
+                "Schedule minutes must be within a single day (0-1439)",
+            ));
+        }
+
         // Validate editor settings
         if self.editor.font_size <= 0.0 || self.editor.font_size > 72.0 {
             return Err(Error::validation(
@@ -628,6 +927,14 @@ impl Config {
                 "Log level must be one of: error, warn, info, debug, trace",
             ));
         }
+        for level in self.advanced.log_targets.values() {
+            if !crate::logging::LEVELS.contains(&level.as_str()) {
+                return Err(Error::validation(
+                    "advanced.log_targets",
+                    "Log levels must be one of: off, error, warn, info, debug, trace",
+                ));
+            }
+        }
 
         Ok(())
     }
@@ -790,6 +1097,28 @@ mod tests {
         config = Config::default();
         config.advanced.log_level = "invalid".to_string();
         assert!(config.validate().is_err());
+
+        // Test theme schedule validation
+        config = Config::default();
+        config.ui.theme_schedule.latitude = 120.0;
+        assert!(config.validate().is_err());
+
+        config = Config::default();
+        config.ui.theme_schedule.night_start_minute = 24 * 60;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_without_theme_schedule() {
+        let mut value = toml::Value::try_from(Config::default()).unwrap();
+        value
+            .get_mut("ui")
+            .and_then(|ui| ui.as_table_mut())
+            .unwrap()
+            .remove("theme_schedule");
+
+        let config: Config = toml::from_str(&toml::to_string(&value).unwrap()).unwrap();
+        assert_eq!(config.ui.theme_schedule.transition_minutes, 30);
     }
 
     #[test]
@@ -7,11 +7,24 @@
 //! The document system supports multiple formats (Markdown, plain text, etc.)
 //! and provides automatic backup, change tracking, and collaborative editing
 //! features.
+//!
+//! Documents are edited with `\n` line endings whatever their files use: the
+//! line ending and byte order mark of a file are noted when it is opened (see
+//! [`decode_text`]) and written back when it is saved.
+//!
+//! The author can keep the text of a document under a label with
+//! [`Document::snapshot`], stored in the project by [`snapshots`].
+
+pub mod snapshots;
 
 use crate::{events::EventBus, Error, Result};
-use cosmarium_plugin_api::{Event, EventType};
+use cosmarium_plugin_api::document_snapshot::DocumentSnapshot;
+use cosmarium_plugin_api::event::DocumentRef;
+use cosmarium_plugin_api::front_matter::FrontMatter;
+use cosmarium_plugin_api::metadata::SceneMetadata;
+use cosmarium_plugin_api::Event;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
@@ -53,6 +66,12 @@ pub struct DocumentManager {
     initialized: bool,
     /// Maximum number of concurrent documents
     max_documents: usize,
+    /// Whether saved files lose their trailing whitespace
+    trim_trailing_whitespace: bool,
+    /// Line ending of new documents and of files without line breaks
+    default_line_ending: LineEnding,
+    /// Last change made to several documents at once, until undone
+    last_transaction: Option<DocumentTransaction>,
 }
 
 impl DocumentManager {
@@ -73,9 +92,37 @@ impl DocumentManager {
             auto_save_interval: Duration::from_secs(30),
             initialized: false,
             max_documents: 100,
+            trim_trailing_whitespace: false,
+            default_line_ending: LineEnding::default(),
+            last_transaction: None,
         }
     }
 
+    /// Set the line ending of the documents created from now on, such as the
+    /// default of the active project.
+    pub fn set_default_line_ending(&mut self, line_ending: LineEnding) {
+        self.default_line_ending = line_ending;
+    }
+
+    /// Set whether documents are saved without trailing whitespace and with
+    /// a single final newline (see [`trim_trailing_whitespace`]).
+    ///
+    /// Only the files are cleaned: open documents keep their content, so
+    /// that a space just typed is not lost to an auto-save.
+    pub fn set_trim_trailing_whitespace(&mut self, enabled: bool) {
+        self.trim_trailing_whitespace = enabled;
+    }
+
+    /// Content of `document` as written to its file.
+    fn file_content(&self, document: &Document) -> Vec<u8> {
+        let content = if self.trim_trailing_whitespace {
+            trim_trailing_whitespace(document.content(), document.format())
+        } else {
+            document.content().to_string()
+        };
+        encode_text(&content, document.line_ending(), document.encoding())
+    }
+
     /// Initialize the document manager.
     ///
     /// # Arguments
@@ -164,7 +211,7 @@ impl DocumentManager {
                 // Clone/own the values we need for the write operation.
                 (
                     doc_ref.title().to_string(),
-                    doc_ref.content().to_string(),
+                    self.file_content(doc_ref),
                     doc_ref.file_path().map(|p| p.to_path_buf()),
                 )
             };
@@ -183,9 +230,10 @@ impl DocumentManager {
                         // Emit document saved event after successful save.
                         if let Some(ref event_bus) = self.event_bus {
                             let bus = event_bus.write().await;
-                            let event = Event::new(
-                                EventType::DocumentSaved,
-                                format!("Saved document: {}", title),
+                            let event = Event::document_saved(
+                                DocumentRef::from_id(id)
+                                    .with_path(Some(&path))
+                                    .with_title(&title),
                             );
                             let _ = bus.emit(event).await;
                         }
@@ -260,17 +308,15 @@ impl DocumentManager {
         }
 
         let id = Uuid::new_v4();
-        let document = Document::new(id, title, content, format);
+        let mut document = Document::new(id, title, content, format);
+        document.line_ending = self.default_line_ending;
 
         self.documents.insert(id, document);
 
         // Emit document created event
         if let Some(ref event_bus) = self.event_bus {
             let bus = event_bus.write().await;
-            let event = Event::new(
-                EventType::DocumentCreated,
-                format!("Created document: {}", title),
-            );
+            let event = Event::document_created(DocumentRef::from_id(id).with_title(title));
             let _ = bus.emit(event).await;
         }
 
@@ -311,9 +357,11 @@ impl DocumentManager {
     /// ```
     pub async fn open_document<P: AsRef<Path>>(&mut self, path: P) -> Result<Uuid> {
         let path = path.as_ref();
-        let content = tokio::fs::read_to_string(path)
+        let bytes = tokio::fs::read(path)
             .await
             .map_err(|e| Error::document(format!("Failed to read document: {}", e)))?;
+        let (content, line_ending, encoding) = decode_text(&bytes)
+            .map_err(|e| Error::document(format!("Failed to read document: {}", e)))?;
 
         let format = DocumentFormat::from_extension(path.extension().and_then(|s| s.to_str()));
         let title = path
@@ -324,6 +372,8 @@ impl DocumentManager {
 
         let id = Uuid::new_v4();
         let mut document = Document::new(id, &title, &content, format);
+        document.line_ending = line_ending.unwrap_or(self.default_line_ending);
+        document.encoding = encoding;
         document.set_file_path(path);
         document.mark_saved(); // File was just loaded, so it's saved
 
@@ -332,9 +382,10 @@ impl DocumentManager {
         // Emit document opened event
         if let Some(ref event_bus) = self.event_bus {
             let bus = event_bus.write().await;
-            let event = Event::new(
-                EventType::DocumentOpened,
-                format!("Opened document: {}", title),
+            let event = Event::document_opened(
+                DocumentRef::from_id(id)
+                    .with_path(Some(path))
+                    .with_title(&title),
             );
             let _ = bus.emit(event).await;
         }
@@ -386,11 +437,11 @@ impl DocumentManager {
         let (title, content, path_opt) = {
             let document = self
                 .documents
-                .get_mut(&document_id)
+                .get(&document_id)
                 .ok_or_else(|| Error::document("Document not found"))?;
             (
                 document.title().to_string(),
-                document.content().to_string(),
+                self.file_content(document),
                 document.file_path().map(|p| p.to_path_buf()),
             )
         };
@@ -411,9 +462,10 @@ impl DocumentManager {
             // Emit document saved event after successful save.
             if let Some(ref event_bus) = self.event_bus {
                 let bus = event_bus.write().await;
-                let event = Event::new(
-                    EventType::DocumentSaved,
-                    format!("Saved document: {}", title),
+                let event = Event::document_saved(
+                    DocumentRef::from_id(document_id)
+                        .with_path(Some(&path))
+                        .with_title(&title),
                 );
                 let _ = bus.emit(event).await;
             }
@@ -527,6 +579,7 @@ impl DocumentManager {
             .ok_or_else(|| Error::document("Document not found"))?;
 
         let title = document.title().to_string();
+        let path = document.file_path().map(Path::to_path_buf);
 
         if save_if_modified && document.has_unsaved_changes() {
             self.save_document(document_id).await?;
@@ -537,9 +590,10 @@ impl DocumentManager {
         // Emit document closed event
         if let Some(ref event_bus) = self.event_bus {
             let bus = event_bus.write().await;
-            let event = Event::new(
-                EventType::DocumentClosed,
-                format!("Closed document: {}", title),
+            let event = Event::document_closed(
+                DocumentRef::from_id(document_id)
+                    .with_path(path)
+                    .with_title(&title),
             );
             let _ = bus.emit(event).await;
         }
@@ -575,6 +629,242 @@ impl DocumentManager {
         self.documents.keys().cloned().collect()
     }
 
+    /// Open document of the file at `path`.
+    pub fn find_document(&self, path: &Path) -> Option<Uuid> {
+        self.documents
+            .iter()
+            .find(|(_, document)| document.file_path() == Some(path))
+            .map(|(id, _)| *id)
+    }
+
+    /// Read the saved open documents again from their file, after it was
+    /// changed outside, by switching branches for instance. Documents with
+    /// unsaved changes, or whose file is gone, are left as they are.
+    ///
+    /// Returns the documents whose content changed.
+    pub async fn reload_documents(&mut self) -> Vec<Uuid> {
+        let mut changed = Vec::new();
+        for (id, document) in &mut self.documents {
+            let Some(path) = document.file_path().map(Path::to_path_buf) else {
+                continue;
+            };
+            if document.has_unsaved_changes() {
+                continue;
+            }
+            let text = match tokio::fs::read(&path).await {
+                Ok(bytes) => decode_text(&bytes),
+                Err(e) => {
+                    debug!("Not reloading {:?}: {}", path, e);
+                    continue;
+                }
+            };
+            match text {
+                Ok((content, _, _)) if content != document.content() => {
+                    document.set_content(&content);
+                    document.mark_saved();
+                    changed.push(*id);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to reload {:?}: {}", path, e),
+            }
+        }
+        changed
+    }
+
+    /// Replace the content of the files of `edits` as one change, which
+    /// [`undo_transaction`](Self::undo_transaction) reverts as a whole.
+    ///
+    /// Open documents are changed in place and left unsaved; the others are
+    /// opened, saved with their new content and closed again. Either every
+    /// document is changed or none is.
+    ///
+    /// Returns the open documents changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be read or written.
+    pub async fn apply_transaction(
+        &mut self,
+        label: &str,
+        edits: Vec<(PathBuf, String)>,
+    ) -> Result<Vec<Uuid>> {
+        let mut changes = Vec::new();
+        for (path, after) in edits {
+            let (id, saved) = match self.find_document(&path) {
+                Some(id) => (id, false),
+                None => match self.open_document(&path).await {
+                    Ok(id) => (id, true),
+                    Err(e) => {
+                        self.close_saved(&changes).await;
+                        return Err(e);
+                    }
+                },
+            };
+            let document = &self.documents[&id];
+            let before = document.content().to_string();
+            let after = if saved && self.trim_trailing_whitespace {
+                // As it will read back from the file
+                trim_trailing_whitespace(&after, document.format())
+            } else {
+                after
+            };
+            changes.push(TransactionChange {
+                id,
+                path,
+                saved,
+                before,
+                after,
+            });
+        }
+
+        let result = self.write_changes(&changes, false).await;
+        self.close_saved(&changes).await;
+        result?;
+        self.last_transaction = Some(DocumentTransaction {
+            label: label.to_string(),
+            changes: changes.clone(),
+        });
+        Ok(self.changed_open_documents(&changes).await)
+    }
+
+    /// Last change made with [`apply_transaction`](Self::apply_transaction),
+    /// if it can be undone.
+    pub fn last_transaction(&self) -> Option<&DocumentTransaction> {
+        self.last_transaction.as_ref()
+    }
+
+    /// Put the documents of the last transaction back as they were.
+    ///
+    /// Returns the open documents changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is nothing to undo, if a document was
+    /// changed since the transaction, or if a file cannot be read or written;
+    /// nothing is changed then.
+    pub async fn undo_transaction(&mut self) -> Result<Vec<Uuid>> {
+        let mut transaction = self
+            .last_transaction
+            .take()
+            .ok_or_else(|| Error::document("Nothing to undo"))?;
+
+        // Documents closed since are restored in their file
+        let mut changes = transaction.changes.clone();
+        for change in &mut changes {
+            if !self.documents.contains_key(&change.id) {
+                match self.open_document(&change.path).await {
+                    Ok(id) => {
+                        change.id = id;
+                        change.saved = true;
+                    }
+                    Err(e) => {
+                        self.close_saved(&changes).await;
+                        self.last_transaction = Some(transaction);
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        if let Some(change) = changes
+            .iter()
+            .find(|change| self.documents[&change.id].content() != change.after)
+        {
+            let title = self.documents[&change.id].title().to_string();
+            self.close_saved(&changes).await;
+            self.last_transaction = Some(transaction);
+            return Err(Error::document(format!(
+                "'{}' was changed since; undo its changes first",
+                title
+            )));
+        }
+
+        let result = self.write_changes(&changes, true).await;
+        self.close_saved(&changes).await;
+        if let Err(e) = result {
+            transaction.changes = changes;
+            self.last_transaction = Some(transaction);
+            return Err(e);
+        }
+        Ok(self.changed_open_documents(&changes).await)
+    }
+
+    /// Set the content of the documents of `changes`, after it or before
+    /// it when undoing, and save the documents not open. If a document
+    /// cannot be saved, the ones changed so far are put back.
+    async fn write_changes(&mut self, changes: &[TransactionChange], undo: bool) -> Result<()> {
+        let to = |change: &TransactionChange| {
+            if undo {
+                change.before.clone()
+            } else {
+                change.after.clone()
+            }
+        };
+        let from = |change: &TransactionChange| {
+            if undo {
+                change.after.clone()
+            } else {
+                change.before.clone()
+            }
+        };
+        for (i, change) in changes.iter().enumerate() {
+            if let Some(document) = self.documents.get_mut(&change.id) {
+                document.set_content(&to(change));
+            }
+            if !change.saved {
+                continue;
+            }
+            if let Err(e) = self.save_document(change.id).await {
+                for done in &changes[..=i] {
+                    if let Some(document) = self.documents.get_mut(&done.id) {
+                        document.set_content(&from(done));
+                    }
+                    if done.saved {
+                        if let Err(e) = self.save_document(done.id).await {
+                            error!("Failed to restore {:?}: {}", done.path, e);
+                        }
+                    }
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Close the documents of `changes` opened for them.
+    async fn close_saved(&mut self, changes: &[TransactionChange]) {
+        for change in changes.iter().filter(|change| change.saved) {
+            if self.documents.contains_key(&change.id) {
+                if let Err(e) = self.close_document(change.id, false).await {
+                    warn!("Failed to close {:?}: {}", change.path, e);
+                }
+            }
+        }
+    }
+
+    /// Announce the changes of the open documents of `changes`, and return
+    /// them.
+    async fn changed_open_documents(&self, changes: &[TransactionChange]) -> Vec<Uuid> {
+        let open: Vec<Uuid> = changes
+            .iter()
+            .filter(|change| !change.saved)
+            .map(|change| change.id)
+            .collect();
+        if let Some(ref event_bus) = self.event_bus {
+            let bus = event_bus.write().await;
+            for id in &open {
+                let document = &self.documents[id];
+                let event = Event::document_changed(
+                    DocumentRef::from_id(*id)
+                        .with_path(document.file_path())
+                        .with_title(document.title()),
+                    None,
+                );
+                let _ = bus.emit(event).await;
+            }
+        }
+        open
+    }
+
     /// Update method called regularly to handle auto-save and maintenance.
     ///
     /// # Errors
@@ -635,7 +925,7 @@ impl DocumentManager {
     async fn save_document_internal(&mut self, document: &mut Document) -> Result<()> {
         // Extract data needed for the write operation while holding a short borrow.
         let path_opt = document.file_path().map(|p| p.to_path_buf());
-        let content = document.content().to_string();
+        let content = self.file_content(document);
         let title = document.title().to_string();
 
         // Perform the write outside of any other borrows.
@@ -661,6 +951,40 @@ impl Default for DocumentManager {
     }
 }
 
+/// A change made to several documents at once, undone as a whole.
+#[derive(Debug, Clone)]
+pub struct DocumentTransaction {
+    /// What the change did, for the undo action
+    pub label: String,
+    changes: Vec<TransactionChange>,
+}
+
+impl DocumentTransaction {
+    /// Number of documents changed.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// The change of a document in a [`DocumentTransaction`].
+#[derive(Debug, Clone)]
+struct TransactionChange {
+    /// Document, while it is open
+    id: Uuid,
+    /// File of the document
+    path: PathBuf,
+    /// Whether the document was not open, so it was changed in its file
+    saved: bool,
+    /// Content before the change
+    before: String,
+    /// Content after the change
+    after: String,
+}
+
 /// A document in the Cosmarium system.
 ///
 /// Documents represent individual text files or content units within a project.
@@ -685,6 +1009,16 @@ pub struct Document {
     has_unsaved_changes: bool,
     /// Document metadata
     metadata: DocumentMetadata,
+    /// Line ending of the file
+    #[serde(default)]
+    line_ending: LineEnding,
+    /// Encoding of the file
+    #[serde(default)]
+    encoding: TextEncoding,
+    /// Number of times the content was set since the document was created
+    /// or opened
+    #[serde(default)]
+    revision: u64,
 }
 
 impl Document {
@@ -709,6 +1043,8 @@ impl Document {
     pub fn new(id: Uuid, title: &str, content: &str, format: DocumentFormat) -> Self {
         let now = SystemTime::now();
 
+        let mut metadata = DocumentMetadata::new();
+        metadata.read_front_matter(content);
         Self {
             id,
             title: title.to_string(),
@@ -718,7 +1054,10 @@ impl Document {
             created_at: now,
             modified_at: now,
             has_unsaved_changes: true,
-            metadata: DocumentMetadata::new(),
+            metadata,
+            line_ending: LineEnding::default(),
+            encoding: TextEncoding::default(),
+            revision: 0,
         }
     }
 
@@ -746,14 +1085,65 @@ impl Document {
     /// Set the document content.
     pub fn set_content(&mut self, content: &str) {
         self.content = content.to_string();
+        self.metadata.read_front_matter(&self.content);
+        self.revision += 1;
         self.mark_modified();
     }
 
+    /// Take a snapshot of the content now, kept under `label`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::document::{Document, DocumentFormat};
+    /// use uuid::Uuid;
+    ///
+    /// let mut doc = Document::new(Uuid::new_v4(), "The Inn", "It rained.", DocumentFormat::Markdown);
+    /// let snapshot = doc.snapshot("Before the rewrite");
+    /// doc.set_content("It poured.");
+    /// assert_eq!(snapshot.label, "Before the rewrite");
+    /// assert_eq!(snapshot.content, "It rained.");
+    /// ```
+    pub fn snapshot(&self, label: &str) -> DocumentSnapshot {
+        DocumentSnapshot::new(label, self.content.as_str(), chrono::Utc::now())
+    }
+
+    /// Get the revision of the content, the number of times it was set.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
     /// Get the document format.
     pub fn format(&self) -> DocumentFormat {
         self.format
     }
 
+    /// Get the line ending of the file.
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// Set the line ending the file is saved with.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if self.line_ending != line_ending {
+            self.line_ending = line_ending;
+            self.mark_modified();
+        }
+    }
+
+    /// Get the encoding of the file.
+    pub fn encoding(&self) -> TextEncoding {
+        self.encoding
+    }
+
+    /// Set the encoding the file is saved with.
+    pub fn set_encoding(&mut self, encoding: TextEncoding) {
+        if self.encoding != encoding {
+            self.encoding = encoding;
+            self.mark_modified();
+        }
+    }
+
     /// Get the file path.
     pub fn file_path(&self) -> Option<&Path> {
         self.file_path.as_deref()
@@ -789,6 +1179,13 @@ impl Document {
         &mut self.metadata
     }
 
+    /// Set the metadata of the document's structure node, the values set in
+    /// its front matter taking precedence.
+    pub fn set_scene_metadata(&mut self, scene: SceneMetadata) {
+        self.metadata.scene = scene;
+        self.metadata.read_front_matter(&self.content);
+    }
+
     /// Mark the document as modified.
     fn mark_modified(&mut self) {
         self.modified_at = SystemTime::now();
@@ -859,6 +1256,189 @@ impl DocumentFormat {
     }
 }
 
+/// Line ending of a text file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum LineEnding {
+    /// `\n`, as on Linux and macOS
+    #[default]
+    Lf,
+    /// `\r\n`, as on Windows
+    Crlf,
+}
+
+impl LineEnding {
+    /// All line endings, in menu order.
+    pub const ALL: [LineEnding; 2] = [Self::Lf, Self::Crlf];
+
+    /// The line ending of `text`: the one of most of its line breaks, or
+    /// `None` if it has none.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::document::LineEnding;
+    ///
+    /// assert_eq!(LineEnding::detect("One\r\nTwo\r\n"), Some(LineEnding::Crlf));
+    /// assert_eq!(LineEnding::detect("One"), None);
+    /// ```
+    pub fn detect(text: &str) -> Option<Self> {
+        let breaks = text.matches('\n').count();
+        let crlf = text.matches("\r\n").count();
+        match breaks {
+            0 => None,
+            _ if crlf * 2 > breaks => Some(Self::Crlf),
+            _ => Some(Self::Lf),
+        }
+    }
+
+    /// The characters ending lines.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+
+    /// Short name for the status bar.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+        }
+    }
+
+    /// `text`, whatever its line endings, with this line ending.
+    pub fn apply(&self, text: &str) -> String {
+        let text = text.replace("\r\n", "\n");
+        match self {
+            Self::Lf => text,
+            Self::Crlf => text.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// Encoding of a text file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum TextEncoding {
+    /// UTF-8
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark, as written by some Windows
+    /// editors
+    Utf8Bom,
+}
+
+impl TextEncoding {
+    /// All encodings, in menu order.
+    pub const ALL: [TextEncoding; 2] = [Self::Utf8, Self::Utf8Bom];
+
+    /// Byte order mark of UTF-8.
+    const BOM: &'static [u8] = b"\xEF\xBB\xBF";
+
+    /// Short name for the status bar.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf8Bom => "UTF-8 BOM",
+        }
+    }
+}
+
+/// Read the content of a text file: its text with `\n` line endings, its
+/// line ending (`None` if it has no line break) and its encoding.
+///
+/// # Errors
+///
+/// Returns an error if `bytes` are not UTF-8.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_core::document::{decode_text, encode_text, LineEnding, TextEncoding};
+///
+/// let bytes = b"\xEF\xBB\xBFOne\r\nTwo\r\n";
+/// let (text, line_ending, encoding) = decode_text(bytes)?;
+/// assert_eq!(text, "One\nTwo\n");
+/// assert_eq!(line_ending, Some(LineEnding::Crlf));
+/// assert_eq!(encoding, TextEncoding::Utf8Bom);
+/// assert_eq!(encode_text(&text, LineEnding::Crlf, encoding), bytes);
+/// # Ok::<(), std::str::Utf8Error>(())
+/// ```
+pub fn decode_text(
+    bytes: &[u8],
+) -> std::result::Result<(String, Option<LineEnding>, TextEncoding), std::str::Utf8Error> {
+    let (bytes, encoding) = match bytes.strip_prefix(TextEncoding::BOM) {
+        Some(rest) => (rest, TextEncoding::Utf8Bom),
+        None => (bytes, TextEncoding::Utf8),
+    };
+    let text = std::str::from_utf8(bytes)?;
+    let line_ending = LineEnding::detect(text);
+    Ok((LineEnding::Lf.apply(text), line_ending, encoding))
+}
+
+/// Bytes of a text file holding `text`, with `line_ending` and `encoding`.
+pub fn encode_text(text: &str, line_ending: LineEnding, encoding: TextEncoding) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    if encoding == TextEncoding::Utf8Bom {
+        bytes.extend_from_slice(TextEncoding::BOM);
+    }
+    bytes.extend_from_slice(line_ending.apply(text).as_bytes());
+    bytes
+}
+
+/// Remove the whitespace ending the lines of `content` and end it with a
+/// single newline.
+///
+/// In Markdown, two spaces ending a line are a hard line break; they are
+/// kept. Windows line endings are kept too.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_core::document::{trim_trailing_whitespace, DocumentFormat};
+///
+/// let content = "Roses are red,   \nviolets are blue. \t\n\n\n";
+/// assert_eq!(
+///     trim_trailing_whitespace(content, DocumentFormat::Markdown),
+///     "Roses are red,  \nviolets are blue.\n"
+/// );
+/// ```
+pub fn trim_trailing_whitespace(content: &str, format: DocumentFormat) -> String {
+    let line_ending = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut lines: Vec<String> = content
+        .split('\n')
+        .map(|line| {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let trimmed = line.trim_end();
+            let hard_break = format == DocumentFormat::Markdown
+                && !trimmed.is_empty()
+                && line[trimmed.len()..].starts_with("  ");
+            if hard_break {
+                format!("{}  ", trimmed)
+            } else {
+                trimmed.to_string()
+            }
+        })
+        .collect();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    if lines.is_empty() {
+        return String::new();
+    }
+    // The last line has nothing to break
+    if let Some(last) = lines.last_mut() {
+        last.truncate(last.trim_end().len());
+    }
+    let mut trimmed = lines.join(line_ending);
+    trimmed.push_str(line_ending);
+    trimmed
+}
+
 /// Document metadata.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocumentMetadata {
@@ -870,6 +1450,13 @@ pub struct DocumentMetadata {
     pub word_count: Option<usize>,
     /// Character count (cached)
     pub character_count: Option<usize>,
+    /// Synopsis, POV, status, label and target, as stored in the project
+    /// structure or set in the front matter
+    #[serde(default)]
+    pub scene: SceneMetadata,
+    /// Title set in the front matter
+    #[serde(default)]
+    pub title: Option<String>,
 }
 
 impl DocumentMetadata {
@@ -880,8 +1467,46 @@ impl DocumentMetadata {
             properties: HashMap::new(),
             word_count: None,
             character_count: None,
+            scene: SceneMetadata::default(),
+            title: None,
         }
     }
+
+    /// Read the title, tags, synopsis, POV and status set in the front
+    /// matter opening `content`, over the values known before.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::document::DocumentMetadata;
+    /// use cosmarium_plugin_api::metadata::Status;
+    ///
+    /// let mut metadata = DocumentMetadata::new();
+    /// metadata.read_front_matter("---\ntitle: The Inn\ntags: [night]\nstatus: final\n---\nIt rained.");
+    /// assert_eq!(metadata.title.as_deref(), Some("The Inn"));
+    /// assert_eq!(metadata.tags, ["night"]);
+    /// assert_eq!(metadata.scene.status, Status::Final);
+    /// ```
+    pub fn read_front_matter(&mut self, content: &str) {
+        let Some(front_matter) = FrontMatter::parse(content) else {
+            self.title = None;
+            return;
+        };
+        self.title = front_matter.get("title").map(str::to_string);
+        if front_matter.fields.contains_key("tags") {
+            self.tags = front_matter.list("tags");
+        }
+
+        let mut fields = BTreeMap::new();
+        self.scene.write_fields(&mut fields);
+        fields.extend(
+            front_matter
+                .fields
+                .into_iter()
+                .filter(|(_, value)| !value.is_empty()),
+        );
+        self.scene = SceneMetadata::from_fields(&fields);
+    }
 }
 
 impl Default for DocumentMetadata {
@@ -1015,6 +1640,189 @@ mod tests {
         assert!(manager.get_document(doc_id).is_none());
     }
 
+    #[tokio::test]
+    async fn test_save_trims_trailing_whitespace() {
+        let dir = make_tempdir();
+        let path = dir.join("chapter.md");
+        let event_bus = Arc::new(RwLock::new(EventBus::new()));
+        let mut manager = DocumentManager::new();
+        manager.initialize(event_bus).await.unwrap();
+        manager.set_trim_trailing_whitespace(true);
+
+        let content = "# Chapter \r\n\r\nShe waited.  \r\nNothing came.\t\r\n\r\n";
+        let doc_id = manager
+            .create_document("Chapter", content, DocumentFormat::Markdown)
+            .await
+            .unwrap();
+        let document = manager.get_document_mut(doc_id).unwrap();
+        document.set_file_path(&path);
+        document.set_line_ending(LineEnding::Crlf);
+        manager.save_document(doc_id).await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# Chapter\r\n\r\nShe waited.  \r\nNothing came.\r\n"
+        );
+        // The open document is left as it is
+        assert_eq!(manager.get_document(doc_id).unwrap().content(), content);
+        assert_eq!(
+            trim_trailing_whitespace("Plain.  \n", DocumentFormat::PlainText),
+            "Plain.\n"
+        );
+        assert_eq!(
+            trim_trailing_whitespace(" \n\n", DocumentFormat::Markdown),
+            ""
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_line_ending_and_encoding_preserved() {
+        let dir = make_tempdir();
+        let path = dir.join("notes.txt");
+        std::fs::write(&path, b"\xEF\xBB\xBFOne\r\nTwo\r\n").unwrap();
+
+        let event_bus = Arc::new(RwLock::new(EventBus::new()));
+        let mut manager = DocumentManager::new();
+        manager.initialize(event_bus).await.unwrap();
+        manager.set_default_line_ending(LineEnding::Crlf);
+
+        let doc_id = manager.open_document(&path).await.unwrap();
+        let document = manager.get_document_mut(doc_id).unwrap();
+        assert_eq!(document.content(), "One\nTwo\n");
+        assert_eq!(document.line_ending(), LineEnding::Crlf);
+        assert_eq!(document.encoding(), TextEncoding::Utf8Bom);
+
+        document.set_content("One\nTwo\nThree\n");
+        manager.save_document(doc_id).await.unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            b"\xEF\xBB\xBFOne\r\nTwo\r\nThree\r\n"
+        );
+
+        // Converted on request
+        let document = manager.get_document_mut(doc_id).unwrap();
+        document.set_line_ending(LineEnding::Lf);
+        document.set_encoding(TextEncoding::Utf8);
+        assert!(document.has_unsaved_changes());
+        manager.save_document(doc_id).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"One\nTwo\nThree\n");
+
+        // New documents follow the default
+        let new_id = manager
+            .create_document("New", "", DocumentFormat::Markdown)
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.get_document(new_id).unwrap().line_ending(),
+            LineEnding::Crlf
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_transaction_is_undone_as_a_whole() {
+        let dir = make_tempdir();
+        let (open_path, closed_path) = (dir.join("open.md"), dir.join("closed.md"));
+        std::fs::write(&open_path, "Ann left.").unwrap();
+        std::fs::write(&closed_path, "Ann stayed.").unwrap();
+
+        let event_bus = Arc::new(RwLock::new(EventBus::new()));
+        let mut manager = DocumentManager::new();
+        manager.initialize(event_bus).await.unwrap();
+        let open_id = manager.open_document(&open_path).await.unwrap();
+
+        let edits = vec![
+            (open_path.clone(), "Anna left.".to_string()),
+            (closed_path.clone(), "Anna stayed.".to_string()),
+        ];
+        let changed = manager.apply_transaction("Replace", edits).await.unwrap();
+        assert_eq!(changed, vec![open_id]);
+        // Open documents are left unsaved, the others are saved and closed
+        let open = manager.get_document(open_id).unwrap();
+        assert_eq!(open.content(), "Anna left.");
+        assert!(open.has_unsaved_changes());
+        assert_eq!(std::fs::read_to_string(&open_path).unwrap(), "Ann left.");
+        assert_eq!(
+            std::fs::read_to_string(&closed_path).unwrap(),
+            "Anna stayed."
+        );
+        assert_eq!(manager.list_documents(), vec![open_id]);
+        assert_eq!(manager.last_transaction().unwrap().len(), 2);
+
+        manager.undo_transaction().await.unwrap();
+        assert_eq!(
+            manager.get_document(open_id).unwrap().content(),
+            "Ann left."
+        );
+        assert_eq!(
+            std::fs::read_to_string(&closed_path).unwrap(),
+            "Ann stayed."
+        );
+        assert!(manager.last_transaction().is_none());
+        assert!(manager.undo_transaction().await.is_err());
+
+        // Documents edited since are not undone
+        let edits = vec![
+            (open_path.clone(), "Anna left.".to_string()),
+            (closed_path.clone(), "Anna stayed.".to_string()),
+        ];
+        manager.apply_transaction("Replace", edits).await.unwrap();
+        std::fs::write(&closed_path, "Anna stayed long.").unwrap();
+        assert!(manager.undo_transaction().await.is_err());
+        assert_eq!(
+            manager.get_document(open_id).unwrap().content(),
+            "Anna left."
+        );
+        assert_eq!(manager.list_documents(), vec![open_id]);
+        assert!(manager.last_transaction().is_some());
+
+        // Nothing changes if a file cannot be read
+        let missing = vec![
+            (open_path.clone(), "Bob left.".to_string()),
+            (dir.join("missing.md"), "Bob".to_string()),
+        ];
+        assert!(manager.apply_transaction("Replace", missing).await.is_err());
+        assert_eq!(
+            manager.get_document(open_id).unwrap().content(),
+            "Anna left."
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_reload_documents_changed_on_disk() {
+        let dir = make_tempdir();
+        let inn = dir.join("inn.md");
+        let road = dir.join("road.md");
+        std::fs::write(&inn, "It rained.").unwrap();
+        std::fs::write(&road, "Dust.").unwrap();
+
+        let event_bus = Arc::new(RwLock::new(EventBus::new()));
+        let mut manager = DocumentManager::new();
+        manager.initialize(event_bus).await.unwrap();
+        let inn_id = manager.open_document(&inn).await.unwrap();
+        let road_id = manager.open_document(&road).await.unwrap();
+        manager
+            .get_document_mut(road_id)
+            .unwrap()
+            .set_content("Mud.");
+
+        std::fs::write(&inn, "It poured.").unwrap();
+        std::fs::write(&road, "Gravel.").unwrap();
+        assert_eq!(manager.reload_documents().await, [inn_id]);
+        let document = manager.get_document(inn_id).unwrap();
+        assert_eq!(document.content(), "It poured.");
+        assert!(!document.has_unsaved_changes());
+        // Unsaved changes are kept
+        assert_eq!(manager.get_document(road_id).unwrap().content(), "Mud.");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
     #[test]
     fn test_document_creation_direct() {
         let id = Uuid::new_v4();
@@ -1026,4 +1834,24 @@ mod tests {
         assert_eq!(doc.format(), DocumentFormat::Markdown);
         assert!(doc.has_unsaved_changes());
     }
+
+    #[test]
+    fn test_front_matter_over_structure_metadata() {
+        let content = "+++\npov = \"Mira\"\n+++\nIt rained.";
+        let mut doc = Document::new(Uuid::new_v4(), "Inn", content, DocumentFormat::Markdown);
+        assert_eq!(doc.metadata().scene.pov, "Mira");
+
+        doc.set_scene_metadata(SceneMetadata {
+            pov: "Tam".to_string(),
+            synopsis: "A storm.".to_string(),
+            ..SceneMetadata::default()
+        });
+        assert_eq!(doc.metadata().scene.pov, "Mira");
+        assert_eq!(doc.metadata().scene.synopsis, "A storm.");
+
+        // The block stays in the text, as written
+        doc.set_content("---\ntitle: The Inn\n---\nIt rained.");
+        assert_eq!(doc.metadata().title.as_deref(), Some("The Inn"));
+        assert!(doc.content().starts_with("---\ntitle: The Inn\n---\n"));
+    }
 }
@@ -0,0 +1,133 @@
+//! # Labeled snapshots of documents
+//!
+//! The snapshots the author keeps of a document (see
+//! [`Document::snapshot`](super::Document::snapshot)) are stored in the
+//! project, next to the rest of its state: those of `content/inn.md` in
+//! `meta/snapshots/content/inn.md.json`, newest first.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_core::document::snapshots::{keep_snapshot, load_snapshots};
+//! use cosmarium_plugin_api::document_snapshot::DocumentSnapshot;
+//!
+//! let project = std::env::temp_dir().join("cosmarium-snapshots-example");
+//! let document = project.join("content/inn.md");
+//! let snapshot = DocumentSnapshot::new("First draft", "It rained.", chrono::Utc::now());
+//! keep_snapshot(&project, &document, snapshot.clone()).unwrap();
+//! assert_eq!(load_snapshots(&project, &document).unwrap()[0], snapshot);
+//! # std::fs::remove_dir_all(&project).unwrap();
+//! ```
+
+use crate::{Error, Result};
+use cosmarium_plugin_api::document_snapshot::DocumentSnapshot;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Folder of the snapshots, relative to the project root.
+pub const SNAPSHOTS_DIR: &str = "meta/snapshots";
+
+/// File of the snapshots of `document`, a file of the project at `project`.
+pub fn snapshots_file(project: &Path, document: &Path) -> Result<PathBuf> {
+    let relative = document
+        .strip_prefix(project)
+        .map_err(|_| Error::document(format!("{} is not in the project", document.display())))?;
+    let mut file = project.join(SNAPSHOTS_DIR).join(relative).into_os_string();
+    file.push(".json");
+    Ok(file.into())
+}
+
+/// Snapshots of `document`, newest first, none when it has no snapshot yet.
+pub fn load_snapshots(project: &Path, document: &Path) -> Result<Vec<DocumentSnapshot>> {
+    let path = snapshots_file(project, document)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Save `snapshots` as those of `document`, removing their file when there
+/// is none left.
+pub fn save_snapshots(
+    project: &Path,
+    document: &Path,
+    snapshots: &[DocumentSnapshot],
+) -> Result<()> {
+    let path = snapshots_file(project, document)?;
+    if snapshots.is_empty() {
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(snapshots)?)?;
+    Ok(())
+}
+
+/// Add `snapshot` to those of `document`.
+pub fn keep_snapshot(project: &Path, document: &Path, snapshot: DocumentSnapshot) -> Result<()> {
+    let mut snapshots = load_snapshots(project, document)?;
+    snapshots.push(snapshot);
+    snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.taken_at));
+    save_snapshots(project, document, &snapshots)
+}
+
+/// Forget the snapshot `id` of `document`. Returns whether it had it.
+pub fn delete_snapshot(project: &Path, document: &Path, id: Uuid) -> Result<bool> {
+    let mut snapshots = load_snapshots(project, document)?;
+    let count = snapshots.len();
+    snapshots.retain(|snapshot| snapshot.id != id);
+    if snapshots.len() == count {
+        return Ok(false);
+    }
+    save_snapshots(project, document, &snapshots)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration};
+
+    #[test]
+    fn test_snapshots_are_kept_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path();
+        let document = project.join("content/one/inn.md");
+        assert!(load_snapshots(project, &document).unwrap().is_empty());
+
+        let older = DocumentSnapshot::new("Draft", "It rained.", DateTime::UNIX_EPOCH);
+        let newer = DocumentSnapshot::new(
+            "Rewrite",
+            "It poured.",
+            DateTime::UNIX_EPOCH + Duration::days(1),
+        );
+        keep_snapshot(project, &document, newer.clone()).unwrap();
+        keep_snapshot(project, &document, older.clone()).unwrap();
+        assert!(project
+            .join("meta/snapshots/content/one/inn.md.json")
+            .is_file());
+        assert_eq!(
+            load_snapshots(project, &document).unwrap(),
+            [newer.clone(), older.clone()]
+        );
+
+        assert!(delete_snapshot(project, &document, older.id).unwrap());
+        assert!(!delete_snapshot(project, &document, older.id).unwrap());
+        assert!(delete_snapshot(project, &document, newer.id).unwrap());
+        assert!(!project
+            .join("meta/snapshots/content/one/inn.md.json")
+            .exists());
+    }
+
+    #[test]
+    fn test_only_project_files_have_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = std::env::temp_dir().join("inn.md");
+        assert!(snapshots_file(dir.path(), &outside).is_err());
+    }
+}
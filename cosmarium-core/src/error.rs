@@ -56,6 +56,10 @@ pub enum Error {
     #[error("Event error: {message}")]
     Event { message: String },
 
+    /// Export errors
+    #[error("Export error: {message}")]
+    Export { message: String },
+
     /// File I/O errors
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -72,6 +76,10 @@ pub enum Error {
     #[error("ZIP error: {0}")]
     Zip(#[from] zip::result::ZipError),
 
+    /// Search index errors
+    #[error("Search index error: {0}")]
+    Search(#[from] tantivy::TantivyError),
+
     /// File watching errors
     #[error("File watcher error: {0}")]
     Notify(#[from] notify::Error),
@@ -200,6 +208,21 @@ impl Error {
         }
     }
 
+    /// Create a new export error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::Error;
+    ///
+    /// let error = Error::export("No speech engine available");
+    /// ```
+    pub fn export<S: Into<String>>(message: S) -> Self {
+        Self::Export {
+            message: message.into(),
+        }
+    }
+
     /// Create a new generic error.
     ///
     /// # Example
@@ -396,10 +419,12 @@ impl Error {
             Self::Document { .. } => "Document",
             Self::Layout { .. } => "Layout",
             Self::Event { .. } => "Event",
+            Self::Export { .. } => "Export",
             Self::Io(_) => "IO",
             Self::Json(_) => "JSON",
             Self::Toml(_) => "TOML",
             Self::Zip(_) => "ZIP",
+            Self::Search(_) => "Search",
             Self::Notify(_) => "FileWatcher",
             Self::Generic { .. } => "Generic",
             Self::Validation { .. } => "Validation",
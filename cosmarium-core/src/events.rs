@@ -7,16 +7,25 @@
 //!
 //! The event bus supports both synchronous and asynchronous event handling,
 //! with automatic cleanup of disconnected handlers and priority-based
-//! event processing.
+//! event processing. Plugins reach it through an [`EventBusLink`]: events
+//! they emit and subscriptions they take are applied when the bus next
+//! processes its queue.
 
 use crate::{Error, Result};
-use cosmarium_plugin_api::{Event, EventHandler, EventType};
+use cosmarium_plugin_api::subscription::{EventBusMessage, SubscriptionRequest};
+use cosmarium_plugin_api::{
+    Event, EventBusLink, EventFilter, EventHandler, EventType, Subscription,
+};
 use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
 use tracing::{debug, error, warn};
 use uuid::Uuid;
 
+/// Rounds of [`EventBus::process_until_idle`] before it gives up.
+pub const MAX_PROCESSING_ROUNDS: usize = 64;
+
 /// Central event bus for system-wide communication.
 ///
 /// The [`EventBus`] provides a publish-subscribe pattern for event-driven
@@ -49,6 +58,10 @@ pub struct EventBus {
     max_queue_size: usize,
     /// Whether to process events asynchronously
     async_processing: bool,
+    /// Sending side of the channel used by plugins and subscription handles
+    link_sender: Sender<EventBusMessage>,
+    /// Messages from plugins, applied when events are processed
+    link_receiver: std::sync::Mutex<Receiver<EventBusMessage>>,
 }
 
 /// Handler entry with metadata
@@ -61,6 +74,17 @@ struct HandlerEntry {
     handler: Arc<Mutex<dyn EventHandler>>,
     /// Handler priority (higher = processed first)
     priority: i32,
+    /// Optional predicate on received events
+    filter: Option<EventFilter>,
+}
+
+/// Adapter storing a plugin's boxed handler in the handler table.
+struct BoxedHandler(Box<dyn EventHandler>);
+
+impl EventHandler for BoxedHandler {
+    fn handle(&mut self, event: &Event) -> anyhow::Result<()> {
+        self.0.handle(event)
+    }
 }
 
 impl EventBus {
@@ -74,15 +98,34 @@ impl EventBus {
     /// let event_bus = EventBus::new();
     /// ```
     pub fn new() -> Self {
+        let (link_sender, link_receiver) = mpsc::channel();
         Self {
             handlers: Arc::new(RwLock::new(HashMap::new())),
             event_queue: Arc::new(Mutex::new(VecDeque::new())),
             initialized: false,
             max_queue_size: 1000,
             async_processing: true,
+            link_sender,
+            link_receiver: std::sync::Mutex::new(link_receiver),
         }
     }
 
+    /// Create a link for plugins to publish and subscribe through.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::events::EventBus;
+    /// use cosmarium_plugin_api::PluginContext;
+    ///
+    /// let event_bus = EventBus::new();
+    /// let mut ctx = PluginContext::new();
+    /// ctx.connect_event_bus(event_bus.link());
+    /// ```
+    pub fn link(&self) -> EventBusLink {
+        EventBusLink::new(self.link_sender.clone())
+    }
+
     /// Initialize the event bus.
     ///
     /// # Errors
@@ -150,6 +193,7 @@ impl EventBus {
             let mut queue = self.event_queue.lock().await;
             queue.clear();
         }
+        self.take_link_messages();
 
         self.initialized = false;
         debug!("Event bus shutdown completed");
@@ -162,11 +206,10 @@ impl EventBus {
     ///
     /// * `event_type` - Type of events to subscribe to
     /// * `handler` - Event handler implementation
-    /// * `priority` - Handler priority (higher numbers = higher priority)
     ///
     /// # Returns
     ///
-    /// Subscription ID that can be used to unsubscribe
+    /// Subscription handle; the handler is unsubscribed when it is dropped
     ///
     /// # Example
     ///
@@ -189,7 +232,7 @@ impl EventBus {
     /// event_bus.initialize().await?;
     ///
     /// let handler = Arc::new(Mutex::new(MyHandler));
-    /// let subscription_id = event_bus.subscribe(EventType::DocumentChanged, handler, 0).await?;
+    /// let subscription = event_bus.subscribe(EventType::DocumentChanged, handler).await?;
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// # });
     /// ```
@@ -197,19 +240,99 @@ impl EventBus {
         &self,
         event_type: EventType,
         handler: Arc<Mutex<dyn EventHandler>>,
+    ) -> Result<Subscription> {
+        self.subscribe_with_priority(event_type, handler, 0).await
+    }
+
+    /// Subscribe to events of a specific type with a handler priority.
+    ///
+    /// Handlers with higher priorities are called first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event bus is not initialized.
+    pub async fn subscribe_with_priority(
+        &self,
+        event_type: EventType,
+        handler: Arc<Mutex<dyn EventHandler>>,
+        priority: i32,
+    ) -> Result<Subscription> {
+        self.add_handler(event_type, handler, priority, None).await
+    }
+
+    /// Subscribe to the events of a specific type accepted by `filter`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event bus is not initialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::events::EventBus;
+    /// use cosmarium_plugin_api::{Event, EventHandler, EventType};
+    /// use std::sync::Arc;
+    /// use tokio::sync::Mutex;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut event_bus = EventBus::new();
+    /// event_bus.initialize().await?;
+    ///
+    /// let handler = Arc::new(Mutex::new(|event: &Event| {
+    ///     println!("Autosaved: {}", event.data());
+    ///     Ok(())
+    /// }));
+    /// let subscription = event_bus
+    ///     .subscribe_filtered(
+    ///         EventType::DocumentSaved,
+    ///         |event: &Event| event.get_metadata("source") == Some("autosave"),
+    ///         handler,
+    ///     )
+    ///     .await?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # });
+    /// ```
+    pub async fn subscribe_filtered<F>(
+        &self,
+        event_type: EventType,
+        filter: F,
+        handler: Arc<Mutex<dyn EventHandler>>,
+    ) -> Result<Subscription>
+    where
+        F: Fn(&Event) -> bool + Send + Sync + 'static,
+    {
+        self.add_handler(event_type, handler, 0, Some(Box::new(filter)))
+            .await
+    }
+
+    async fn add_handler(
+        &self,
+        event_type: EventType,
+        handler: Arc<Mutex<dyn EventHandler>>,
         priority: i32,
-    ) -> Result<Uuid> {
+        filter: Option<EventFilter>,
+    ) -> Result<Subscription> {
         if !self.initialized {
             return Err(Error::event("Event bus not initialized"));
         }
 
         let id = Uuid::new_v4();
-        let entry = HandlerEntry {
-            id,
-            handler,
-            priority,
-        };
+        self.insert_handler(
+            event_type,
+            HandlerEntry {
+                id,
+                handler,
+                priority,
+                filter,
+            },
+        )
+        .await;
+
+        Ok(Subscription::new(id, event_type, self.link_sender.clone()))
+    }
 
+    async fn insert_handler(&self, event_type: EventType, entry: HandlerEntry) {
+        let (id, priority) = (entry.id, entry.priority);
         let mut handlers = self.handlers.write().await;
         let type_handlers = handlers.entry(event_type).or_insert_with(Vec::new);
         type_handlers.push(entry);
@@ -221,7 +344,6 @@ impl EventBus {
             "Subscribed handler {:?} to {:?} events with priority {}",
             id, event_type, priority
         );
-        Ok(id)
     }
 
     /// Unsubscribe from events.
@@ -254,8 +376,8 @@ impl EventBus {
     /// event_bus.initialize().await?;
     ///
     /// let handler = Arc::new(Mutex::new(MyHandler));
-    /// let id = event_bus.subscribe(EventType::DocumentChanged, handler, 0).await?;
-    /// event_bus.unsubscribe(id).await?;
+    /// let subscription = event_bus.subscribe(EventType::DocumentChanged, handler).await?;
+    /// event_bus.unsubscribe(subscription.id()).await?;
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// # });
     /// ```
@@ -309,7 +431,8 @@ impl EventBus {
 
     /// Process all queued events.
     ///
-    /// This method processes all events currently in the queue. It should be
+    /// This method first applies the subscriptions and events received from
+    /// plugins, then processes all events currently in the queue. It should be
     /// called regularly (e.g., once per frame) to ensure timely event processing.
     ///
     /// # Errors
@@ -329,8 +452,71 @@ impl EventBus {
     /// # });
     /// ```
     pub async fn process_events(&self) -> Result<()> {
+        self.process_pending().await.map(|_| ())
+    }
+
+    /// Process events until none are left, including the ones emitted by
+    /// handlers while they were processed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if events are still pending after
+    /// [`MAX_PROCESSING_ROUNDS`] rounds, as when two handlers keep answering
+    /// each other.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::events::EventBus;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut event_bus = EventBus::new();
+    /// event_bus.initialize().await?;
+    /// event_bus.process_until_idle().await?;
+    /// assert_eq!(event_bus.queue_size().await, 0);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # });
+    /// ```
+    pub async fn process_until_idle(&self) -> Result<()> {
+        for _ in 0..MAX_PROCESSING_ROUNDS {
+            if self.process_pending().await? == 0 {
+                return Ok(());
+            }
+        }
+        Err(Error::event(format!(
+            "Events still pending after {} rounds",
+            MAX_PROCESSING_ROUNDS
+        )))
+    }
+
+    /// Apply the link messages and process the queued events, returning how
+    /// many there were.
+    async fn process_pending(&self) -> Result<usize> {
         if !self.initialized {
-            return Ok(());
+            return Ok(0);
+        }
+
+        let messages = self.take_link_messages();
+        let message_count = messages.len();
+        for message in messages {
+            match message {
+                EventBusMessage::Publish(event) => self.queue_event(event).await?,
+                EventBusMessage::Subscribe(SubscriptionRequest {
+                    id,
+                    event_type,
+                    filter,
+                    handler,
+                }) => {
+                    let entry = HandlerEntry {
+                        id,
+                        handler: Arc::new(Mutex::new(BoxedHandler(handler))),
+                        priority: 0,
+                        filter,
+                    };
+                    self.insert_handler(event_type, entry).await;
+                }
+                EventBusMessage::Unsubscribe(id) => self.unsubscribe(id).await?,
+            }
         }
 
         let mut processed_count = 0;
@@ -356,7 +542,7 @@ impl EventBus {
             debug!("Processed {} events", processed_count);
         }
 
-        Ok(())
+        Ok(message_count + processed_count)
     }
 
     /// Get the number of queued events.
@@ -444,6 +630,14 @@ impl EventBus {
         self.async_processing = async_mode;
     }
 
+    /// Take the messages sent through links since the last call.
+    fn take_link_messages(&self) -> Vec<EventBusMessage> {
+        match self.link_receiver.lock() {
+            Ok(receiver) => receiver.try_iter().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
     /// Queue an event for later processing.
     async fn queue_event(&self, event: Event) -> Result<()> {
         let mut queue = self.event_queue.lock().await;
@@ -470,6 +664,13 @@ impl EventBus {
             );
 
             for handler_entry in type_handlers {
+                if handler_entry
+                    .filter
+                    .as_ref()
+                    .is_some_and(|filter| !filter(&event))
+                {
+                    continue;
+                }
                 let mut handler = handler_entry.handler.lock().await;
                 if let Err(e) = handler.handle(&event) {
                     error!(
@@ -567,12 +768,12 @@ mod tests {
         let call_count = Arc::new(AtomicUsize::new(0));
         let handler = Arc::new(Mutex::new(TestHandler::new(Arc::clone(&call_count))));
 
-        let subscription_id = event_bus
-            .subscribe(EventType::DocumentChanged, handler, 0)
+        let subscription = event_bus
+            .subscribe(EventType::DocumentChanged, handler)
             .await
             .unwrap();
 
-        assert_ne!(subscription_id, Uuid::nil());
+        assert_ne!(subscription.id(), Uuid::nil());
         assert_eq!(event_bus.handler_count(EventType::DocumentChanged).await, 1);
     }
 
@@ -584,8 +785,8 @@ mod tests {
         let call_count = Arc::new(AtomicUsize::new(0));
         let handler = Arc::new(Mutex::new(TestHandler::new(Arc::clone(&call_count))));
 
-        event_bus
-            .subscribe(EventType::DocumentChanged, handler, 0)
+        let _subscription = event_bus
+            .subscribe(EventType::DocumentChanged, handler)
             .await
             .unwrap();
 
@@ -606,14 +807,14 @@ mod tests {
         let call_count = Arc::new(AtomicUsize::new(0));
         let handler = Arc::new(Mutex::new(TestHandler::new(Arc::clone(&call_count))));
 
-        let subscription_id = event_bus
-            .subscribe(EventType::DocumentChanged, handler, 0)
+        let subscription = event_bus
+            .subscribe(EventType::DocumentChanged, handler)
             .await
             .unwrap();
 
         assert_eq!(event_bus.handler_count(EventType::DocumentChanged).await, 1);
 
-        event_bus.unsubscribe(subscription_id).await.unwrap();
+        event_bus.unsubscribe(subscription.id()).await.unwrap();
         assert_eq!(event_bus.handler_count(EventType::DocumentChanged).await, 0);
     }
 
@@ -671,8 +872,8 @@ mod tests {
         let call_count = Arc::new(AtomicUsize::new(0));
         let handler = Arc::new(Mutex::new(TestHandler::new(Arc::clone(&call_count))));
 
-        event_bus
-            .subscribe(EventType::DocumentChanged, handler, 0)
+        let _subscription = event_bus
+            .subscribe(EventType::DocumentChanged, handler)
             .await
             .unwrap();
 
@@ -681,6 +882,81 @@ mod tests {
         assert_eq!(event_bus.handler_count(EventType::DocumentChanged).await, 0);
     }
 
+    #[tokio::test]
+    async fn test_subscription_dropped_unsubscribes() {
+        let mut event_bus = EventBus::new();
+        event_bus.initialize().await.unwrap();
+
+        let call_count = Arc::new(AtomicUsize::new(0));
+        let handler = Arc::new(Mutex::new(TestHandler::new(Arc::clone(&call_count))));
+        let subscription = event_bus
+            .subscribe(EventType::DocumentChanged, handler)
+            .await
+            .unwrap();
+
+        drop(subscription);
+        event_bus.process_events().await.unwrap();
+        assert_eq!(event_bus.handler_count(EventType::DocumentChanged).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_filtered_subscription() {
+        let mut event_bus = EventBus::new();
+        event_bus.initialize().await.unwrap();
+
+        let call_count = Arc::new(AtomicUsize::new(0));
+        let handler = Arc::new(Mutex::new(TestHandler::new(Arc::clone(&call_count))));
+        let _subscription = event_bus
+            .subscribe_filtered(
+                EventType::DocumentSaved,
+                |event: &Event| event.data() == "manual",
+                handler,
+            )
+            .await
+            .unwrap();
+
+        event_bus
+            .emit(Event::new(EventType::DocumentSaved, "auto"))
+            .await
+            .unwrap();
+        event_bus
+            .emit(Event::new(EventType::DocumentSaved, "manual"))
+            .await
+            .unwrap();
+        event_bus.process_events().await.unwrap();
+
+        assert_eq!(call_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_plugin_link_delivery() {
+        let mut event_bus = EventBus::new();
+        event_bus.initialize().await.unwrap();
+
+        let mut ctx = cosmarium_plugin_api::PluginContext::new();
+        ctx.connect_event_bus(event_bus.link());
+
+        let call_count = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&call_count);
+        let subscription = ctx.subscribe(EventType::DocumentChanged, move |_: &Event| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        });
+
+        // Delivery happens when the bus processes its queue, not on emit
+        ctx.emit_event(Event::new(EventType::DocumentChanged, "edited"));
+        assert_eq!(call_count.load(Ordering::SeqCst), 0);
+
+        event_bus.process_events().await.unwrap();
+        assert_eq!(call_count.load(Ordering::SeqCst), 1);
+
+        drop(subscription);
+        ctx.emit_event(Event::new(EventType::DocumentChanged, "edited again"));
+        event_bus.process_events().await.unwrap();
+        assert_eq!(call_count.load(Ordering::SeqCst), 1);
+        assert_eq!(event_bus.handler_count(EventType::DocumentChanged).await, 0);
+    }
+
     #[tokio::test]
     async fn test_max_queue_size() {
         let mut event_bus = EventBus::new();
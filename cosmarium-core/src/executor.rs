@@ -0,0 +1,155 @@
+//! Shared async executor for the application and its plugins.
+//!
+//! The [`Executor`] owns the single Tokio runtime of the application. The UI
+//! thread uses [`Executor::block_on`] for short, lock-bound operations and
+//! [`Executor::spawn`] / [`Executor::spawn_blocking`] for anything that may
+//! take a while; results of spawned tasks are polled from the UI thread
+//! through a [`TaskHandle`]. Plugins receive a [`TaskSpawner`] to the same
+//! runtime through their context.
+
+use cosmarium_plugin_api::{TaskHandle, TaskSpawner};
+use std::future::Future;
+use std::sync::OnceLock;
+use tokio::runtime::{Builder, Handle, Runtime};
+
+/// Owner of the application's Tokio runtime.
+///
+/// The runtime starts on first use, so creating an [`Application`] stays
+/// cheap when nothing is ever spawned.
+///
+/// [`Application`]: crate::Application
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_core::Executor;
+///
+/// let executor = Executor::new();
+/// assert_eq!(executor.block_on(async { 1 + 1 }), 2);
+///
+/// let task = executor.spawn(async { 6 * 7 });
+/// assert_eq!(task.wait().unwrap(), 42);
+/// ```
+#[derive(Default)]
+pub struct Executor {
+    runtime: OnceLock<Runtime>,
+}
+
+impl Executor {
+    /// Create an executor. The runtime is started lazily.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runtime, started if needed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system refuses to start the runtime threads.
+    fn runtime(&self) -> &Runtime {
+        self.runtime.get_or_init(|| {
+            Builder::new_multi_thread()
+                .thread_name("cosmarium-worker")
+                .enable_all()
+                .build()
+                .expect("Failed to start the async runtime")
+        })
+    }
+
+    /// Handle to the runtime.
+    pub fn handle(&self) -> Handle {
+        self.runtime().handle().clone()
+    }
+
+    /// Spawner to hand over to plugins.
+    pub fn spawner(&self) -> TaskSpawner {
+        TaskSpawner::new(self.handle())
+    }
+
+    /// Run a future to completion on the calling thread.
+    ///
+    /// Meant for the UI thread and short operations such as taking a lock;
+    /// must not be called from a task running on the executor.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime().block_on(future)
+    }
+
+    /// Run a future in the background.
+    pub fn spawn<F>(&self, future: F) -> TaskHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.spawner().spawn(future)
+    }
+
+    /// Run blocking work in the background.
+    pub fn spawn_blocking<F, T>(&self, work: F) -> TaskHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.spawner().spawn_blocking(work)
+    }
+}
+
+impl Drop for Executor {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks until its tasks stop, which panics when
+        // the executor itself is dropped from async code.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl std::fmt::Debug for Executor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Executor")
+            .field("started", &self.runtime.get().is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmarium_plugin_api::PluginContext;
+
+    #[test]
+    fn test_runtime_starts_lazily() {
+        let executor = Executor::new();
+        assert!(executor.runtime.get().is_none());
+        assert_eq!(executor.block_on(async { 21 * 2 }), 42);
+        assert!(executor.runtime.get().is_some());
+    }
+
+    #[test]
+    fn test_spawned_results_reach_caller() {
+        let executor = Executor::new();
+        let task = executor.spawn(async {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            "async"
+        });
+        let blocking = executor.spawn_blocking(|| "blocking");
+        assert_eq!(task.wait().unwrap(), "async");
+        assert_eq!(blocking.wait().unwrap(), "blocking");
+    }
+
+    #[test]
+    fn test_plugins_share_the_runtime() {
+        let executor = Executor::new();
+        let mut ctx = PluginContext::new();
+        ctx.connect_task_spawner(executor.spawner());
+
+        let task = ctx.spawn(async { std::thread::current().name().map(String::from) });
+        assert_eq!(task.wait().unwrap().as_deref(), Some("cosmarium-worker"));
+    }
+
+    #[tokio::test]
+    async fn test_drop_inside_async_context() {
+        let executor = Executor::new();
+        let _ = executor.spawn(async {});
+        drop(executor);
+    }
+}
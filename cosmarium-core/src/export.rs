@@ -0,0 +1,957 @@
+//! # Document export for Cosmarium Core
+//!
+//! Turns a single document into a standalone artifact that can be shared
+//! outside of Cosmarium: a self-contained HTML reading page, a plain text
+//! manuscript following Standard Manuscript Format conventions, or, with the
+//! `tts` feature, an audio reading produced by the system speech engine.
+//!
+//! Any of them can be anonymized for contests and blind submissions: the
+//! author byline and manuscript header are left out, identifying names are
+//! replaced in the text, and the written file is checked for leftovers
+//! before the export is reported as successful.
+//!
+//! Whole manuscripts are compiled by the [`compile`] submodule, and laid
+//! out for submissions by the presets of [`preset`]; the text filters of
+//! [`filter`] rewrite names, dashes and spelling in them on the way out.
+
+pub mod compile;
+pub mod filter;
+pub mod preset;
+
+use crate::config::{ExportConfig, HtmlExportConfig};
+use crate::{Error, Result};
+use cosmarium_plugin_api::direction;
+use cosmarium_plugin_api::epigraph;
+use cosmarium_plugin_api::front_matter::{self, FrontMatter};
+use cosmarium_plugin_api::locale::Locale;
+use cosmarium_plugin_api::verse;
+use pulldown_cmark::{html, Event, Options, Parser, Tag};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "tts")]
+use std::process::Command;
+
+/// Artifact produced when exporting a single document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DocumentExportFormat {
+    /// Standalone HTML page for sharing a chapter
+    Html,
+    /// Plain text following Standard Manuscript Format conventions
+    SmfText,
+    /// Audio reading through the system speech engine
+    #[cfg(feature = "tts")]
+    Audio,
+}
+
+impl DocumentExportFormat {
+    /// All formats, in menu order.
+    #[cfg(feature = "tts")]
+    pub const ALL: [DocumentExportFormat; 3] = [Self::Html, Self::SmfText, Self::Audio];
+
+    /// All formats, in menu order.
+    #[cfg(not(feature = "tts"))]
+    pub const ALL: [DocumentExportFormat; 2] = [Self::Html, Self::SmfText];
+
+    /// Stable identifier, used by plugins to request an export.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::SmfText => "smf_text",
+            #[cfg(feature = "tts")]
+            Self::Audio => "audio",
+        }
+    }
+
+    /// Parse an identifier returned by [`DocumentExportFormat::id`].
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.id() == id)
+    }
+
+    /// Human-readable name for menus.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Html => "HTML page",
+            Self::SmfText => "Plain text (manuscript format)",
+            #[cfg(feature = "tts")]
+            Self::Audio => "Audio reading",
+        }
+    }
+}
+
+/// Export a document to `output_dir` and return the path of the artifact.
+///
+/// `markdown` is the document's current content, which may be newer than the
+/// copy on disk at `source`. The artifact is named after the source file.
+/// With an `anonymization`, the author is omitted, identifying names are
+/// redacted and the artifact is verified (see [`Anonymization::verify`]).
+/// Text artifacts end their lines with the line ending of `config`.
+///
+/// # Errors
+///
+/// Returns an error if the output cannot be written, if an anonymized
+/// artifact still contains identifying names, or for audio exports, if no
+/// speech engine is available or synthesis fails.
+pub fn export_document(
+    source: &Path,
+    markdown: &str,
+    format: DocumentExportFormat,
+    output_dir: &Path,
+    author: &str,
+    config: &ExportConfig,
+    anonymization: Option<&Anonymization>,
+) -> Result<PathBuf> {
+    let stem = source
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("document");
+    let title = document_title(markdown).unwrap_or_else(|| stem.replace('_', " "));
+
+    let (stem, title, markdown, author) = match anonymization {
+        Some(anonymization) => (
+            anonymization
+                .redact_with(&stem.replace('_', " "), "anonymous")
+                .replace(' ', "_"),
+            anonymization.redact(&title),
+            anonymization.redact(markdown),
+            "",
+        ),
+        None => (stem.to_string(), title, markdown.to_string(), author),
+    };
+    let output = write_export(&stem, &title, author, &markdown, format, output_dir, config)?;
+
+    if let Some(anonymization) = anonymization {
+        if let Err(e) = anonymization.verify(&output) {
+            std::fs::remove_file(&output).ok();
+            return Err(e);
+        }
+    }
+
+    tracing::info!("Exported {:?} to {:?}", source, output);
+    Ok(output)
+}
+
+/// Write `markdown` in `format` to `output_dir`, in a file named `stem`.
+fn write_export(
+    stem: &str,
+    title: &str,
+    author: &str,
+    markdown: &str,
+    format: DocumentExportFormat,
+    output_dir: &Path,
+    config: &ExportConfig,
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(output_dir)?;
+    let line_ending = config.line_ending;
+
+    let output = match format {
+        DocumentExportFormat::Html => {
+            let output = output_dir.join(format!("{}.html", stem));
+            std::fs::write(
+                &output,
+                line_ending.apply(&to_html(title, author, markdown, &config.html)),
+            )?;
+            output
+        }
+        DocumentExportFormat::SmfText => {
+            let output = output_dir.join(format!("{}.txt", stem));
+            std::fs::write(
+                &output,
+                line_ending.apply(&to_smf_text(title, author, markdown, &config.locale)),
+            )?;
+            output
+        }
+        #[cfg(feature = "tts")]
+        DocumentExportFormat::Audio => {
+            let engine = SpeechEngine::detect()
+                .ok_or_else(|| Error::export("No speech engine found on this system"))?;
+            let output = output_dir.join(format!("{}.{}", stem, engine.extension()));
+            let text = format!("{}.\n\n{}", title, to_plain_text(markdown));
+            engine.synthesize(&text, &output)?;
+            output
+        }
+    };
+    Ok(output)
+}
+
+/// Identifying names removed from anonymized exports.
+///
+/// Names match whole words, ignoring case, so `Ann` is redacted in
+/// "Ann's notes" but not in "Annual".
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_core::export::Anonymization;
+///
+/// let anonymization = Anonymization::new(["Ann Author", "Ann"], "[Author]");
+/// assert_eq!(
+///     anonymization.redact("ANN AUTHOR wrote this. Thanks, Ann!"),
+///     "[Author] wrote this. Thanks, [Author]!"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Anonymization {
+    /// Names to remove, longest first
+    names: Vec<String>,
+    /// Text replacing each removed name
+    placeholder: String,
+}
+
+impl Anonymization {
+    /// Create an anonymization removing `names`. Blank names are ignored.
+    pub fn new<I, S>(names: I, placeholder: &str) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut names: Vec<String> = names
+            .into_iter()
+            .map(|name| {
+                name.as_ref()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|name| !name.is_empty())
+            .collect();
+        // Longer names first, so a full name wins over the first name alone
+        names.sort_by_key(|name| (std::cmp::Reverse(name.chars().count()), name.to_lowercase()));
+        names.dedup_by(|a, b| a.to_lowercase() == b.to_lowercase());
+
+        Self {
+            names,
+            placeholder: placeholder.to_string(),
+        }
+    }
+
+    /// Names removed by this anonymization.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Replace every occurrence of the identifying names in `text`.
+    pub fn redact(&self, text: &str) -> String {
+        self.redact_with(text, &self.placeholder)
+    }
+
+    fn redact_with(&self, text: &str, placeholder: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        let mut previous: Option<char> = None;
+
+        while let Some(c) = rest.chars().next() {
+            if let Some(len) = self.match_at(rest, previous) {
+                out.push_str(placeholder);
+                previous = rest[..len].chars().next_back();
+                rest = &rest[len..];
+            } else {
+                out.push(c);
+                previous = Some(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        out
+    }
+
+    /// Identifying names found in `text`.
+    pub fn find_identifying(&self, text: &str) -> Vec<&str> {
+        self.names
+            .iter()
+            .filter(|name| {
+                let single = Self::new([name.as_str()], "");
+                let mut previous = None;
+                text.char_indices().any(|(i, c)| {
+                    let found = single.match_at(&text[i..], previous).is_some();
+                    previous = Some(c);
+                    found
+                })
+            })
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Check that the exported file at `output` contains no identifying name.
+    ///
+    /// Zip-based outputs (DOCX, ODT, EPUB) are compressed, so each of their
+    /// parts is read out of the archive and checked, with and without its
+    /// markup.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the leftovers, or if the file cannot be read.
+    pub fn verify(&self, output: &Path) -> Result<()> {
+        let bytes = std::fs::read(output)?;
+        let mut found: Vec<&str> = Vec::new();
+        for text in searchable_texts(&bytes)? {
+            for name in self.find_identifying(&text) {
+                if !found.contains(&name) {
+                    found.push(name);
+                }
+            }
+        }
+        if found.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::export(format!(
+                "Anonymized export still contains identifying text: {}",
+                found.join(", ")
+            )))
+        }
+    }
+
+    /// Byte length of the name starting `text`, if one matches a whole word.
+    fn match_at(&self, text: &str, previous: Option<char>) -> Option<usize> {
+        if previous.is_some_and(char::is_alphanumeric) {
+            return None;
+        }
+        self.names.iter().find_map(|name| {
+            let len = match_ignoring_case(text, name)?;
+            let next = text[len..].chars().next();
+            (!next.is_some_and(char::is_alphanumeric)).then_some(len)
+        })
+    }
+}
+
+/// Texts of the exported file `bytes` to look for names in: the file itself,
+/// or each part of it if it is a zip archive, also as text without markup.
+fn searchable_texts(bytes: &[u8]) -> Result<Vec<String>> {
+    if !bytes.starts_with(ZIP_SIGNATURE) {
+        return Ok(vec![String::from_utf8_lossy(bytes).into_owned()]);
+    }
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+    let mut texts = Vec::with_capacity(archive.len() * 2);
+    for i in 0..archive.len() {
+        let mut content = Vec::new();
+        archive.by_index(i)?.read_to_end(&mut content)?;
+        let text = String::from_utf8_lossy(&content).into_owned();
+        texts.push(markup_text(&text));
+        texts.push(text);
+    }
+    Ok(texts)
+}
+
+/// First bytes of a zip archive.
+const ZIP_SIGNATURE: &[u8] = b"PK\x03\x04";
+
+/// Text of the XML or HTML `markup`, without its tags and with the
+/// characters escaped in it restored.
+fn markup_text(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut rest = markup;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        rest = rest[start..]
+            .find('>')
+            .map_or("", |end| &rest[start + end + 1..]);
+    }
+    text.push_str(rest);
+    [
+        ("&lt;", "<"),
+        ("&gt;", ">"),
+        ("&quot;", "\""),
+        ("&apos;", "'"),
+        ("&#39;", "'"),
+        ("&amp;", "&"),
+    ]
+    .into_iter()
+    .fold(text, |text, (entity, c)| text.replace(entity, c))
+}
+
+/// Byte length of the prefix of `text` equal to `name` ignoring case and
+/// treating any run of whitespace as a single space.
+fn match_ignoring_case(text: &str, name: &str) -> Option<usize> {
+    let mut chars = text.char_indices().peekable();
+    for expected in name.chars() {
+        let (_, c) = chars.next()?;
+        if expected == ' ' {
+            if !c.is_whitespace() {
+                return None;
+            }
+            while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        } else if !c.to_lowercase().eq(expected.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(chars.peek().map_or(text.len(), |(i, _)| *i))
+}
+
+/// Render a document as a self-contained HTML page.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_core::config::HtmlExportConfig;
+/// use cosmarium_core::export::to_html;
+///
+/// let page = to_html("Chapter 1", "Ann Author", "It was *dark*.", &HtmlExportConfig::default());
+/// assert!(page.contains("<title>Chapter 1</title>"));
+/// assert!(page.contains("<em>dark</em>"));
+/// ```
+pub fn to_html(title: &str, author: &str, markdown: &str, config: &HtmlExportConfig) -> String {
+    // Epigraphs, verse, right-to-left and aligned paragraphs get elements of
+    // their own
+    let markdown = epigraph::wrap_epigraphs(strip_front_matter(markdown));
+    let markdown = direction::wrap_paragraphs(&verse::wrap_verse(&markdown));
+    let mut body = String::new();
+    html::push_html(&mut body, Parser::new_ext(&markdown, options()));
+
+    let mut css = String::from(HTML_STYLE);
+    if config.include_custom_css {
+        css.push_str(&config.custom_css);
+    }
+
+    let byline = if author.trim().is_empty() {
+        String::new()
+    } else {
+        format!("<p class=\"byline\">{}</p>\n", escape_html(author))
+    };
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta name=\"generator\" content=\"Cosmarium\">\n\
+         <title>{title}</title>\n<style>\n{css}</style>\n</head>\n<body>\n<main>\n\
+         {byline}{body}</main>\n</body>\n</html>\n",
+        title = escape_html(title),
+    )
+}
+
+/// Render a document as a plain text manuscript.
+///
+/// Follows the usual plain text submission conventions: author and
+/// approximate word count at the top, the title in capitals, italics marked
+/// with underscores, `#` for scene breaks and `END` after the last line.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_core::export::to_smf_text;
+/// use cosmarium_plugin_api::locale::Locale;
+///
+/// let text = to_smf_text(
+///     "The Inn",
+///     "Ann Author",
+///     "She *ran*.\n\n***\n\nDawn.",
+///     &Locale::default(),
+/// );
+/// assert!(text.contains("THE INN"));
+/// assert!(text.contains("She _ran_."));
+/// assert!(text.contains("\n#\n"));
+/// ```
+pub fn to_smf_text(title: &str, author: &str, markdown: &str, locale: &Locale) -> String {
+    let body =
+        ManuscriptWriter::new(false).render(&verse::break_lines(strip_front_matter(markdown)));
+    let words = body.split_whitespace().filter(|w| *w != "#").count();
+
+    let mut out = String::new();
+    if !author.trim().is_empty() {
+        out.push_str(&format!("{}\n", author.trim()));
+    }
+    out.push_str(&format!(
+        "About {} words\n\n\n",
+        locale.format_count(approximate_word_count(words))
+    ));
+    out.push_str(&format!("{}\n", title.trim().to_uppercase()));
+    if !author.trim().is_empty() {
+        out.push_str(&format!("by {}\n", author.trim()));
+    }
+    out.push_str("\n\n");
+    out.push_str(&body);
+    out.push_str("END\n");
+    out
+}
+
+/// Render a document as plain prose, without any markup, for reading aloud.
+pub fn to_plain_text(markdown: &str) -> String {
+    ManuscriptWriter::new(true).render(&verse::break_lines(strip_front_matter(markdown)))
+}
+
+/// Title of a document: its front matter `title`, or else its first heading.
+pub fn document_title(markdown: &str) -> Option<String> {
+    let title = FrontMatter::parse(markdown)
+        .and_then(|front_matter| front_matter.get("title").map(|t| t.trim().to_string()));
+    if title.is_some() {
+        return title;
+    }
+
+    strip_front_matter(markdown)
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+/// Speech synthesizer available on the system.
+#[cfg(feature = "tts")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeechEngine {
+    /// macOS `say`
+    Say,
+    /// `espeak-ng` (Linux and others)
+    EspeakNg,
+    /// Legacy `espeak`
+    Espeak,
+    /// Windows speech API through PowerShell
+    WindowsSpeech,
+}
+
+#[cfg(feature = "tts")]
+impl SpeechEngine {
+    /// Find a speech engine on this system.
+    pub fn detect() -> Option<Self> {
+        if cfg!(target_os = "macos") {
+            return Some(Self::Say);
+        }
+        if cfg!(target_os = "windows") {
+            return Some(Self::WindowsSpeech);
+        }
+
+        [Self::EspeakNg, Self::Espeak].into_iter().find(|engine| {
+            Command::new(engine.program())
+                .arg("--version")
+                .output()
+                .is_ok_and(|output| output.status.success())
+        })
+    }
+
+    /// Extension of the audio files produced by this engine.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Say => "aiff",
+            _ => "wav",
+        }
+    }
+
+    fn program(&self) -> &'static str {
+        match self {
+            Self::Say => "say",
+            Self::EspeakNg => "espeak-ng",
+            Self::Espeak => "espeak",
+            Self::WindowsSpeech => "powershell",
+        }
+    }
+
+    /// Read `text` aloud into the audio file at `output`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the engine cannot be run or fails.
+    pub fn synthesize(&self, text: &str, output: &Path) -> Result<()> {
+        // The text goes through a file so long documents do not hit
+        // command line length limits.
+        let input = output.with_extension("speech.txt");
+        std::fs::write(&input, text)?;
+
+        let result = self.command(&input, output).output();
+        std::fs::remove_file(&input).ok();
+
+        let output = result
+            .map_err(|e| Error::export(format!("Failed to run {}: {}", self.program(), e)))?;
+        if !output.status.success() {
+            return Err(Error::export(format!(
+                "{} failed: {}",
+                self.program(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Command reading the text file `input` aloud into `output`.
+    fn command(&self, input: &Path, output: &Path) -> Command {
+        let mut command = Command::new(self.program());
+        match self {
+            Self::Say => {
+                command.arg("-f").arg(input).arg("-o").arg(output);
+            }
+            Self::EspeakNg | Self::Espeak => {
+                command.arg("-f").arg(input).arg("-w").arg(output);
+            }
+            Self::WindowsSpeech => {
+                // The paths are handed over in the environment, never
+                // pasted into the script, so no file name is run as code
+                command
+                    .args(["-NoProfile", "-Command", WINDOWS_SPEECH_SCRIPT])
+                    .env(SPEECH_INPUT_VAR, input)
+                    .env(SPEECH_OUTPUT_VAR, output);
+            }
+        }
+        command
+    }
+}
+
+/// Variable holding the text file read aloud by the Windows speech script.
+#[cfg(feature = "tts")]
+const SPEECH_INPUT_VAR: &str = "COSMARIUM_SPEECH_INPUT";
+
+/// Variable holding the audio file written by the Windows speech script.
+#[cfg(feature = "tts")]
+const SPEECH_OUTPUT_VAR: &str = "COSMARIUM_SPEECH_OUTPUT";
+
+/// PowerShell script reading a text file aloud into a WAV file.
+#[cfg(feature = "tts")]
+const WINDOWS_SPEECH_SCRIPT: &str = "Add-Type -AssemblyName System.Speech; \
+     $s = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+     $s.SetOutputToWaveFile($env:COSMARIUM_SPEECH_OUTPUT); \
+     $s.Speak([IO.File]::ReadAllText($env:COSMARIUM_SPEECH_INPUT)); $s.Dispose()";
+
+/// Stylesheet of exported HTML pages, tuned for reading long prose.
+const HTML_STYLE: &str = "body { margin: 0; background: #fdfcf8; color: #222; }
+main { max-width: 38em; margin: 0 auto; padding: 3em 1.5em; font: 1.15em/1.7 Georgia, serif; }
+h1, h2, h3 { font-weight: normal; text-align: center; }
+.byline { text-align: center; font-style: italic; margin-bottom: 3em; }
+hr { border: 0; text-align: center; margin: 2em 0; }
+hr::after { content: \"* * *\"; }
+blockquote { margin-left: 1.5em; font-style: italic; }
+.verse { margin: 1.5em 2em; }
+.epigraph { margin: 2em 0 3em 35%; font-style: italic; }
+.epigraph .attribution { text-align: right; font-style: normal; }
+@media (prefers-color-scheme: dark) { body { background: #1e1e1e; color: #ddd; } }
+";
+
+fn options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+}
+
+fn strip_front_matter(markdown: &str) -> &str {
+    front_matter::strip(markdown)
+}
+
+/// Round a word count the way manuscript headers do.
+fn approximate_word_count(words: usize) -> usize {
+    (words.div_ceil(100) * 100).max(100)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Markdown to plain text renderer shared by manuscript and speech output.
+struct ManuscriptWriter {
+    /// Drop all markup (italics, scene break marks, list bullets)
+    speech: bool,
+    out: String,
+    /// Text of the block being rendered
+    block: String,
+    /// Bullet of the list item being rendered
+    item_prefix: Option<String>,
+    /// Open lists, with the next number of ordered ones
+    lists: Vec<Option<u64>>,
+    quote_depth: usize,
+}
+
+impl ManuscriptWriter {
+    fn new(speech: bool) -> Self {
+        Self {
+            speech,
+            out: String::new(),
+            block: String::new(),
+            item_prefix: None,
+            lists: Vec::new(),
+            quote_depth: 0,
+        }
+    }
+
+    fn render(mut self, markdown: &str) -> String {
+        for event in Parser::new_ext(markdown, options()) {
+            match event {
+                Event::Start(Tag::Heading(..)) | Event::Start(Tag::CodeBlock(_)) => self.flush(),
+                Event::End(Tag::Heading(..)) => {
+                    if !self.speech {
+                        self.block = self.block.to_uppercase();
+                    }
+                    self.flush();
+                }
+                Event::End(Tag::Paragraph)
+                | Event::End(Tag::CodeBlock(_))
+                | Event::End(Tag::Item)
+                | Event::End(Tag::TableHead)
+                | Event::End(Tag::TableRow) => self.flush(),
+                Event::Start(Tag::BlockQuote) => {
+                    self.flush();
+                    self.quote_depth += 1;
+                }
+                Event::End(Tag::BlockQuote) => {
+                    self.flush();
+                    self.quote_depth = self.quote_depth.saturating_sub(1);
+                }
+                Event::Start(Tag::List(start)) => {
+                    self.flush();
+                    self.lists.push(start);
+                }
+                Event::End(Tag::List(_)) => {
+                    self.flush();
+                    self.lists.pop();
+                }
+                Event::Start(Tag::Item) => {
+                    self.flush();
+                    let depth = self.lists.len().saturating_sub(1);
+                    let bullet = match self.lists.last_mut() {
+                        Some(Some(n)) => {
+                            *n += 1;
+                            format!("{}. ", *n - 1)
+                        }
+                        _ if self.speech => String::new(),
+                        _ => "- ".to_string(),
+                    };
+                    self.item_prefix = Some(format!("{}{}", "  ".repeat(depth), bullet));
+                }
+                Event::Start(Tag::Emphasis) | Event::End(Tag::Emphasis) if !self.speech => {
+                    self.block.push('_');
+                }
+                Event::End(Tag::TableCell) => self.block.push_str("  "),
+                Event::Text(text) | Event::Code(text) => self.block.push_str(&text),
+                Event::SoftBreak => self.block.push(' '),
+                Event::HardBreak => self.block.push('\n'),
+                Event::Rule => {
+                    self.flush();
+                    if !self.speech {
+                        self.out.push_str("#\n\n");
+                    }
+                }
+                Event::FootnoteReference(label) if !self.speech => {
+                    self.block.push_str(&format!("[{}]", label));
+                }
+                Event::TaskListMarker(checked) if !self.speech => {
+                    self.block.push_str(if checked { "[x] " } else { "[ ] " });
+                }
+                _ => {}
+            }
+        }
+        self.flush();
+        self.out
+    }
+
+    /// Write the pending block, indented for quotes and list items.
+    fn flush(&mut self) {
+        let text = self.block.trim_matches('\n').trim_end();
+        if text.is_empty() {
+            self.block.clear();
+            return;
+        }
+
+        let quote = "    ".repeat(self.quote_depth);
+        let prefix = self.item_prefix.take().unwrap_or_default();
+        let hanging = " ".repeat(prefix.chars().count());
+        for (i, line) in text.lines().enumerate() {
+            // Lines of verse are indented with no-break spaces, of two bytes
+            let verse = line.trim_start_matches('\u{a0}');
+            self.out.push_str(&quote);
+            self.out.push_str(if i == 0 { &prefix } else { &hanging });
+            self.out
+                .push_str(&" ".repeat((line.len() - verse.len()) / 2));
+            self.out.push_str(verse);
+            self.out.push('\n');
+        }
+        self.out.push('\n');
+        self.block.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::LineEnding;
+    #[cfg(feature = "tts")]
+    use std::collections::HashMap;
+    #[cfg(feature = "tts")]
+    use std::ffi::OsStr;
+
+    #[test]
+    fn test_format_ids_roundtrip() {
+        for format in DocumentExportFormat::ALL {
+            assert_eq!(DocumentExportFormat::from_id(format.id()), Some(format));
+        }
+        assert_eq!(DocumentExportFormat::from_id("epub"), None);
+    }
+
+    #[cfg(feature = "tts")]
+    #[test]
+    fn test_speech_paths_are_not_run_as_code() {
+        let input = Path::new("/novel/O'Brien'); Remove-Item -Recurse ~; ('.speech.txt");
+        let output = Path::new("/novel/O'Brien.wav");
+        let command = SpeechEngine::WindowsSpeech.command(input, output);
+        assert!(command
+            .get_args()
+            .all(|arg| !arg.to_string_lossy().contains("O'Brien")));
+        let envs: HashMap<_, _> = command.get_envs().collect();
+        assert_eq!(envs[OsStr::new(SPEECH_INPUT_VAR)], Some(input.as_os_str()));
+        assert_eq!(
+            envs[OsStr::new(SPEECH_OUTPUT_VAR)],
+            Some(output.as_os_str())
+        );
+        for var in [SPEECH_INPUT_VAR, SPEECH_OUTPUT_VAR] {
+            assert!(WINDOWS_SPEECH_SCRIPT.contains(&format!("$env:{}", var)));
+        }
+
+        let command = SpeechEngine::EspeakNg.command(input, output);
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                OsStr::new("-f"),
+                input.as_os_str(),
+                OsStr::new("-w"),
+                output.as_os_str()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_document_title() {
+        assert_eq!(
+            document_title("---\ntitle: \"The Inn\"\n---\n# Chapter 1").as_deref(),
+            Some("The Inn")
+        );
+        assert_eq!(
+            document_title("+++\ntitle = \"The Road\"\n+++\n# Chapter 2").as_deref(),
+            Some("The Road")
+        );
+        assert_eq!(
+            document_title("Intro\n# Chapter 1\nText").as_deref(),
+            Some("Chapter 1")
+        );
+        assert_eq!(document_title("No heading"), None);
+    }
+
+    #[test]
+    fn test_smf_text() {
+        let markdown = "---\nstatus: draft\n---\n# Chapter One\n\nShe *ran*\nfast.\n\n---\n\n> A **letter**.\n\n1. first\n2. second\n";
+        let text = to_smf_text("The Inn", "Ann Author", markdown, &Locale::default());
+
+        assert!(text.starts_with("Ann Author\nAbout 100 words\n"));
+        assert!(text.contains("THE INN\nby Ann Author\n"));
+        assert!(!text.contains("status: draft"));
+        assert!(text.contains("CHAPTER ONE\n\nShe _ran_ fast.\n\n#\n\n"));
+        assert!(text.contains("    A letter.\n"));
+        assert!(text.contains("1. first\n\n2. second\n"));
+        assert!(text.ends_with("END\n"));
+    }
+
+    #[test]
+    fn test_plain_text_has_no_markup() {
+        let text = to_plain_text("# Title\n\nShe *ran*.\n\n***\n\n- one");
+        assert_eq!(text, "Title\n\nShe ran.\n\none\n\n");
+    }
+
+    #[test]
+    fn test_verse_keeps_its_lines() {
+        let markdown = "Prose.\n\n<!-- verse -->\nOne,\n  two.\n<!-- /verse -->\n";
+        let text = to_smf_text("Poem", "Ann Author", markdown, &Locale::default());
+        assert!(text.contains("One,\n  two.\n"));
+        assert!(!text.contains("verse"));
+
+        let page = to_html("Poem", "", markdown, &HtmlExportConfig::default());
+        assert!(page.contains("<div class=\"verse\">"));
+        assert!(page.contains("padding-left: 3em"));
+    }
+
+    #[test]
+    fn test_epigraph_is_set_apart() {
+        let markdown = "# One\n\n<!-- epigraph -->\n> Go.\n>\n> — Ann\n<!-- /epigraph -->\n\nText.";
+        let page = to_html("One", "", markdown, &HtmlExportConfig::default());
+        assert!(page
+            .contains("<div class=\"epigraph\">\n<p>Go.</p>\n<p class=\"attribution\">— Ann</p>"));
+
+        let text = to_smf_text("One", "Ann Author", markdown, &Locale::default());
+        assert!(text.contains("    Go.\n"));
+        assert!(!text.contains("epigraph"));
+    }
+
+    #[test]
+    fn test_html_page() {
+        let config = HtmlExportConfig {
+            include_custom_css: true,
+            custom_css: "p { color: red; }".to_string(),
+            ..HtmlExportConfig::default()
+        };
+        let page = to_html(
+            "A <b> & c",
+            "",
+            "Text\n\nשלום\n\n<!-- align=center -->\nEnd",
+            &config,
+        );
+
+        assert!(page.contains("<title>A &lt;b&gt; &amp; c</title>"));
+        assert!(page.contains("p { color: red; }"));
+        assert!(page.contains("<p>Text</p>"));
+        assert!(page.contains("<div dir=\"rtl\">\n<p>שלום</p>\n</div>"));
+        assert!(page.contains("<div style=\"text-align: center\">\n<p>End</p>\n</div>"));
+        assert!(!page.contains("align=center"));
+        assert!(!page.contains("byline\">"));
+    }
+
+    #[test]
+    fn test_export_document_writes_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("chapter_one.md");
+        let output = export_document(
+            &source,
+            "Some *text*.",
+            DocumentExportFormat::SmfText,
+            &dir.path().join("exports"),
+            "",
+            &ExportConfig {
+                line_ending: LineEnding::Crlf,
+                ..ExportConfig::default()
+            },
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(output, dir.path().join("exports").join("chapter_one.txt"));
+        let text = std::fs::read_to_string(output).unwrap();
+        assert!(text.contains("CHAPTER ONE"));
+        assert!(text.contains("CHAPTER ONE\r\n"));
+        assert!(!text.replace("\r\n", "").contains('\n'));
+        assert!(text.contains("Some _text_."));
+    }
+
+    #[test]
+    fn test_redact_whole_words() {
+        let anonymization = Anonymization::new(["Ann", "  Ann   Author ", "", "ann"], "X");
+        assert_eq!(anonymization.names(), ["Ann Author", "Ann"]);
+
+        assert_eq!(
+            anonymization.redact("Ann\nAuthor met Ann's sister Annabel in Annecy."),
+            "X met X's sister Annabel in Annecy."
+        );
+        assert_eq!(anonymization.redact("Élodie"), "Élodie");
+        assert!(anonymization.find_identifying("Annual report").is_empty());
+        assert_eq!(anonymization.find_identifying("by ann."), ["Ann"]);
+    }
+
+    #[test]
+    fn test_anonymized_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("ann_author_story.md");
+        let anonymization = Anonymization::new(["Ann Author", "Tobermory"], "[Author]");
+        let output = export_document(
+            &source,
+            "# A story by Ann Author\n\nWritten in Tobermory.",
+            DocumentExportFormat::SmfText,
+            &dir.path().join("exports"),
+            "Ann Author",
+            &ExportConfig::default(),
+            Some(&anonymization),
+        )
+        .unwrap();
+
+        assert_eq!(output.file_name().unwrap(), "anonymous_story.txt");
+        let text = std::fs::read_to_string(&output).unwrap();
+        assert!(text.starts_with("About 100 words\n"));
+        assert!(text.contains("A STORY BY [AUTHOR]\n\n"));
+        assert!(text.contains("Written in [Author]."));
+        assert!(anonymization.verify(&output).is_ok());
+
+        std::fs::write(&output, "Contact: ann author").unwrap();
+        assert!(anonymization.verify(&output).is_err());
+    }
+}
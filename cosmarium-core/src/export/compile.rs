@@ -0,0 +1,629 @@
+//! # Manuscript compilation
+//!
+//! Gathers a project's documents, in the order of its structure, into one
+//! [`Manuscript`]: the title page and front matter first, then a heading
+//! for each part and chapter and the text of each document, with the
+//! configured separators between chapters and between scenes. The
+//! epigraphs declared in the front matter of documents are written where
+//! [`EpigraphPlacement`] says.
+//!
+//! The manuscript is then written either in one of the built-in formats of
+//! [`DocumentExportFormat`] or by an [`ExportPlugin`].
+
+use super::{strip_front_matter, write_export, Anonymization, DocumentExportFormat};
+use crate::config::ExportConfig;
+use crate::profile::{AuthorIdentity, AuthorProfile};
+use crate::project::Project;
+use crate::structure::{ProjectStructure, StructureNode};
+use crate::{config::CompileConfig, Error, Result};
+use cosmarium_plugin_api::epigraph::Epigraph;
+use cosmarium_plugin_api::export::{ExportPlugin, Manuscript, ManuscriptSection, SectionKind};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Where the epigraph of a chapter appears in the manuscript.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum EpigraphPlacement {
+    /// Between the chapter heading and the text
+    #[default]
+    BelowHeading,
+    /// Before the chapter heading
+    AboveHeading,
+    /// Left out of the manuscript
+    Omitted,
+}
+
+impl EpigraphPlacement {
+    /// All placements, in menu order.
+    pub const ALL: [EpigraphPlacement; 3] = [Self::BelowHeading, Self::AboveHeading, Self::Omitted];
+
+    /// Human-readable name of the placement.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::BelowHeading => "Below the chapter heading",
+            Self::AboveHeading => "Above the chapter heading",
+            Self::Omitted => "Omitted",
+        }
+    }
+}
+
+/// Compile the documents of `structure` into a manuscript.
+///
+/// `load` returns the Markdown of a document node, or `None` to leave the
+/// document out. Front matter blocks of the documents are removed, but for
+/// their epigraph, and the
+/// [text filters](crate::export::filter::TextFilters) of `config` applied.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_core::config::CompileConfig;
+/// use cosmarium_core::export::compile::compile_manuscript;
+/// use cosmarium_core::structure::{NodeKind, ProjectStructure, StructureNode};
+///
+/// let mut structure = ProjectStructure::default();
+/// let chapter = structure.insert(None, 0, StructureNode::new(NodeKind::Chapter, "Arrival"))?;
+/// for scene in ["Dawn", "Dusk"] {
+///     structure.insert(Some(chapter), 9, StructureNode::new(NodeKind::Document, scene))?;
+/// }
+///
+/// let config = CompileConfig { title_page: false, ..CompileConfig::default() };
+/// let manuscript = compile_manuscript("The Inn", "", &structure, &config, |node| {
+///     Some(format!("{}.", node.title))
+/// });
+/// assert_eq!(manuscript.to_markdown(), "# Arrival\n\nDawn.\n\n***\n\nDusk.\n");
+/// # Ok::<(), cosmarium_core::Error>(())
+/// ```
+pub fn compile_manuscript<F>(
+    title: &str,
+    author: &str,
+    structure: &ProjectStructure,
+    config: &CompileConfig,
+    mut load: F,
+) -> Manuscript
+where
+    F: FnMut(&StructureNode) -> Option<String>,
+{
+    let mut front_matter = String::new();
+    if config.title_page {
+        front_matter.push_str(&format!("# {}\n\n", title.trim()));
+        if !author.trim().is_empty() {
+            front_matter.push_str(&format!("by {}\n\n", author.trim()));
+        }
+    }
+    front_matter.push_str(config.front_matter.trim());
+
+    let mut compiler = Compiler {
+        config,
+        load: &mut load,
+        sections: Vec::new(),
+        previous: None,
+    };
+    compiler.walk(structure.roots(), None, 0);
+
+    let mut manuscript = Manuscript {
+        title: title.to_string(),
+        author: author.to_string(),
+        language: String::new(),
+        front_matter: front_matter.trim().to_string(),
+        sections: compiler.sections,
+    };
+    config.filters.apply_to(&mut manuscript);
+    manuscript
+}
+
+/// Compile the documents of `project`, as saved in its directory, into a
+/// manuscript titled after the project and signed with the identity it is
+/// published under among `profiles`.
+///
+/// This is the headless counterpart of compiling from the application:
+/// documents that cannot be read are left out with a warning.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use cosmarium_core::config::{CompileConfig, ExportConfig};
+/// use cosmarium_core::export::compile::{compile_project, export_manuscript, CompileTarget};
+/// use cosmarium_core::export::DocumentExportFormat;
+/// use cosmarium_core::Project;
+/// use std::path::Path;
+///
+/// # tokio_test::block_on(async {
+/// let project = Project::load("./my_novel").await?;
+/// let manuscript = compile_project(&project, &[], &CompileConfig::default());
+/// export_manuscript(
+///     &manuscript,
+///     CompileTarget::Builtin(DocumentExportFormat::Html),
+///     Path::new("./out"),
+///     &ExportConfig::default(),
+///     None,
+/// )?;
+/// # Ok::<(), cosmarium_core::Error>(())
+/// # });
+/// ```
+pub fn compile_project(
+    project: &Project,
+    profiles: &[AuthorProfile],
+    config: &CompileConfig,
+) -> Manuscript {
+    let identity = AuthorIdentity::of_project(project.metadata(), profiles);
+    compile_manuscript(
+        project.name(),
+        &identity.byline,
+        project.structure(),
+        &config.for_identity(&identity),
+        |node| {
+            let path = project.path().join(node.path.as_ref()?);
+            std::fs::read_to_string(&path)
+                .map_err(|e| tracing::warn!("Cannot compile {:?}: {}", path, e))
+                .ok()
+        },
+    )
+}
+
+/// State of a compilation walk.
+struct Compiler<'a, F> {
+    config: &'a CompileConfig,
+    load: &'a mut F,
+    sections: Vec<ManuscriptSection>,
+    /// Kind and parent of the last section written
+    previous: Option<(SectionKind, Option<Uuid>)>,
+}
+
+impl<F: FnMut(&StructureNode) -> Option<String>> Compiler<'_, F> {
+    fn walk(&mut self, nodes: &[StructureNode], parent: Option<Uuid>, depth: usize) {
+        for node in nodes {
+            if node.is_container() {
+                if self.config.container_headings {
+                    let heading = format!("{} {}", "#".repeat((depth + 1).min(6)), node.title);
+                    self.push(SectionKind::Heading, node, parent, depth, heading);
+                }
+                self.walk(&node.children, Some(node.id), depth + 1);
+            } else if let Some(markdown) = (self.load)(node) {
+                let epigraph = Epigraph::from_front_matter(&markdown);
+                let mut markdown = strip_front_matter(&markdown).trim().to_string();
+                if let Some(epigraph) = epigraph {
+                    markdown = self.place_epigraph(&epigraph, markdown);
+                }
+                self.push(SectionKind::Document, node, parent, depth, markdown);
+            }
+        }
+    }
+
+    /// `markdown` of a document with its `epigraph`, or the heading above
+    /// it given the epigraph.
+    fn place_epigraph(&mut self, epigraph: &Epigraph, markdown: String) -> String {
+        let block = epigraph.to_markdown();
+        // A document may open with its own heading
+        let heading_end = if markdown.starts_with('#') {
+            markdown.find('\n').unwrap_or(markdown.len())
+        } else {
+            0
+        };
+        match self.config.epigraphs {
+            EpigraphPlacement::Omitted => markdown,
+            EpigraphPlacement::AboveHeading => match self.sections.last_mut() {
+                Some(heading) if heading.kind == SectionKind::Heading && heading_end == 0 => {
+                    heading.markdown = format!("{}\n\n{}", block, heading.markdown);
+                    markdown
+                }
+                _ => format!("{}\n\n{}", block, markdown).trim_end().to_string(),
+            },
+            EpigraphPlacement::BelowHeading if heading_end > 0 => format!(
+                "{}\n\n{}\n\n{}",
+                &markdown[..heading_end],
+                block,
+                markdown[heading_end..].trim_start()
+            )
+            .trim_end()
+            .to_string(),
+            EpigraphPlacement::BelowHeading => {
+                format!("{}\n\n{}", block, markdown).trim_end().to_string()
+            }
+        }
+    }
+
+    fn push(
+        &mut self,
+        kind: SectionKind,
+        node: &StructureNode,
+        parent: Option<Uuid>,
+        depth: usize,
+        markdown: String,
+    ) {
+        // Scenes of a chapter are set apart by the scene separator, and
+        // chapters by the chapter separator. Whatever follows a heading
+        // comes right after it.
+        let config = self.config;
+        let separator = match (self.previous, kind) {
+            (None, _) | (Some((SectionKind::Heading, _)), _) => "",
+            (Some((SectionKind::Document, previous)), SectionKind::Document)
+                if previous.is_some() && previous == parent =>
+            {
+                config.scene_separator.as_str()
+            }
+            _ => config.chapter_separator.as_str(),
+        };
+
+        self.sections.push(ManuscriptSection {
+            kind,
+            title: node.title.clone(),
+            depth,
+            path: node.path.as_ref().map(PathBuf::from),
+            separator: separator.to_string(),
+            markdown,
+        });
+        self.previous = Some((kind, parent));
+    }
+}
+
+/// A copy of `manuscript` without the author nor any identifying name.
+pub fn anonymize(manuscript: &Manuscript, anonymization: &Anonymization) -> Manuscript {
+    Manuscript {
+        title: anonymization.redact(&manuscript.title),
+        author: String::new(),
+        language: manuscript.language.clone(),
+        front_matter: anonymization.redact(&manuscript.front_matter),
+        sections: manuscript
+            .sections
+            .iter()
+            .map(|section| ManuscriptSection {
+                title: anonymization.redact(&section.title),
+                separator: anonymization.redact(&section.separator),
+                markdown: anonymization.redact(&section.markdown),
+                ..section.clone()
+            })
+            .collect(),
+    }
+}
+
+/// Where a manuscript is written.
+#[derive(Clone)]
+pub enum CompileTarget {
+    /// One of the built-in formats
+    Builtin(DocumentExportFormat),
+    /// A format provided by an export plugin
+    Plugin(Arc<dyn ExportPlugin>),
+}
+
+impl CompileTarget {
+    /// Human-readable name of the format.
+    pub fn display_name(&self) -> &str {
+        match self {
+            Self::Builtin(format) => format.display_name(),
+            Self::Plugin(plugin) => plugin.format_name(),
+        }
+    }
+}
+
+/// Write a compiled manuscript to `output_dir` and return the path of the
+/// artifact, named after the manuscript title.
+///
+/// With an `anonymization`, the author is omitted, identifying names are
+/// redacted and the artifact is verified (see [`Anonymization::verify`]).
+///
+/// # Errors
+///
+/// Returns an error if the output cannot be written, if the export plugin
+/// fails, or if an anonymized artifact still contains identifying names.
+pub fn export_manuscript(
+    manuscript: &Manuscript,
+    target: CompileTarget,
+    output_dir: &Path,
+    config: &ExportConfig,
+    anonymization: Option<&Anonymization>,
+) -> Result<PathBuf> {
+    let stem = manuscript.title.trim().replace(' ', "_");
+    let stem = if stem.is_empty() { "manuscript" } else { &stem };
+    let (stem, manuscript) = match anonymization {
+        Some(anonymization) => (
+            anonymization
+                .redact_with(&stem.replace('_', " "), "anonymous")
+                .replace(' ', "_"),
+            anonymize(manuscript, anonymization),
+        ),
+        None => (stem.to_string(), manuscript.clone()),
+    };
+
+    let output = match target {
+        CompileTarget::Builtin(format) => write_export(
+            &stem,
+            &manuscript.title,
+            &manuscript.author,
+            &manuscript.to_markdown(),
+            format,
+            output_dir,
+            config,
+        )?,
+        CompileTarget::Plugin(plugin) => {
+            std::fs::create_dir_all(output_dir)?;
+            let output = output_dir.join(format!("{}.{}", stem, plugin.file_extension()));
+            let options = config.format_options(plugin.format_id());
+            plugin.export(&manuscript, &options, &output).map_err(|e| {
+                Error::export(format!("{} export failed: {}", plugin.format_name(), e))
+            })?;
+            output
+        }
+    };
+
+    if let Some(anonymization) = anonymization {
+        if let Err(e) = anonymization.verify(&output) {
+            std::fs::remove_file(&output).ok();
+            return Err(e);
+        }
+    }
+
+    tracing::info!(
+        "Compiled {} documents to {:?}",
+        manuscript.document_count(),
+        output
+    );
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::filter::{Alias, TextFilters};
+    use crate::structure::NodeKind;
+    use std::io::Write;
+
+    fn structure() -> ProjectStructure {
+        let mut structure = ProjectStructure::default();
+        let part = structure
+            .insert(None, 9, StructureNode::new(NodeKind::Part, "Part One"))
+            .unwrap();
+        for chapter in ["Arrival", "Departure"] {
+            let chapter = structure
+                .insert(
+                    Some(part),
+                    9,
+                    StructureNode::new(NodeKind::Chapter, chapter),
+                )
+                .unwrap();
+            for scene in ["a", "b"] {
+                structure
+                    .insert(
+                        Some(chapter),
+                        9,
+                        StructureNode::new(NodeKind::Document, scene),
+                    )
+                    .unwrap();
+            }
+        }
+        structure
+            .insert(None, 9, StructureNode::new(NodeKind::Document, "Epilogue"))
+            .unwrap();
+        structure
+    }
+
+    #[test]
+    fn test_separators_follow_the_structure() {
+        let config = CompileConfig {
+            front_matter: "*For Tam.*".to_string(),
+            chapter_separator: "<!-- chapter -->".to_string(),
+            ..CompileConfig::default()
+        };
+        let manuscript = compile_manuscript("The Inn", "Ann", &structure(), &config, |node| {
+            Some(format!("---\nstatus: draft\n---\n{}.", node.title))
+        });
+
+        assert_eq!(manuscript.document_count(), 5);
+        assert_eq!(
+            manuscript.to_markdown(),
+            "# The Inn\n\nby Ann\n\n*For Tam.*\n\n\
+             # Part One\n\n## Arrival\n\na.\n\n***\n\nb.\n\n\
+             <!-- chapter -->\n\n## Departure\n\na.\n\n***\n\nb.\n\n\
+             <!-- chapter -->\n\nEpilogue.\n"
+        );
+    }
+
+    #[test]
+    fn test_documents_can_be_left_out() {
+        let config = CompileConfig {
+            title_page: false,
+            container_headings: false,
+            ..CompileConfig::default()
+        };
+        let manuscript = compile_manuscript("The Inn", "", &structure(), &config, |node| {
+            (node.title != "a").then(|| node.title.clone())
+        });
+
+        assert_eq!(manuscript.front_matter, "");
+        assert_eq!(manuscript.to_markdown(), "b\n\nb\n\nEpilogue\n");
+    }
+
+    #[test]
+    fn test_filters_apply_to_the_whole_manuscript() {
+        let config = CompileConfig {
+            front_matter: "*For Tam.*".to_string(),
+            filters: TextFilters {
+                aliases: vec![Alias::new("Tam", "Mira")],
+                ..TextFilters::default()
+            },
+            ..CompileConfig::default()
+        };
+        let manuscript = compile_manuscript("Tam's Inn", "", &structure(), &config, |node| {
+            Some(format!("Tam saw {}.", node.title))
+        });
+
+        assert_eq!(manuscript.title, "Mira's Inn");
+        assert!(manuscript.front_matter.contains("*For Mira.*"));
+        assert_eq!(manuscript.sections[2].markdown, "Mira saw a.");
+        assert!(!manuscript.to_markdown().contains("Tam"));
+    }
+
+    #[test]
+    fn test_epigraphs_follow_their_placement() {
+        let mut structure = ProjectStructure::default();
+        let chapter = structure
+            .insert(None, 9, StructureNode::new(NodeKind::Chapter, "Arrival"))
+            .unwrap();
+        structure
+            .insert(
+                Some(chapter),
+                9,
+                StructureNode::new(NodeKind::Document, "a"),
+            )
+            .unwrap();
+        let compile = |epigraphs| {
+            let config = CompileConfig {
+                title_page: false,
+                epigraphs,
+                ..CompileConfig::default()
+            };
+            compile_manuscript("The Inn", "", &structure, &config, |_| {
+                Some("---\nepigraph: Go.\nepigraph_attribution: Ann\n---\nText.".to_string())
+            })
+            .to_markdown()
+        };
+        let epigraph = "<!-- epigraph -->\n> Go.\n>\n> — Ann\n<!-- /epigraph -->";
+
+        assert_eq!(
+            compile(EpigraphPlacement::BelowHeading),
+            format!("# Arrival\n\n{}\n\nText.\n", epigraph)
+        );
+        assert_eq!(
+            compile(EpigraphPlacement::AboveHeading),
+            format!("{}\n\n# Arrival\n\nText.\n", epigraph)
+        );
+        assert_eq!(compile(EpigraphPlacement::Omitted), "# Arrival\n\nText.\n");
+    }
+
+    #[test]
+    fn test_compile_project_reads_its_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let chapter = dir.path().join("content").join("01 Arrival");
+        std::fs::create_dir_all(&chapter).unwrap();
+        std::fs::write(chapter.join("01 dawn.md"), "Dawn.").unwrap();
+        std::fs::write(chapter.join("02 dusk.md"), "Dusk.").unwrap();
+
+        let mut project = Project::new("The Inn", dir.path(), "novel").unwrap();
+        project.sync_structure();
+        let config = CompileConfig {
+            title_page: false,
+            container_headings: false,
+            ..CompileConfig::default()
+        };
+        let manuscript = compile_project(&project, &[], &config);
+
+        assert_eq!(manuscript.title, "The Inn");
+        assert_eq!(manuscript.to_markdown(), "Dawn.\n\n***\n\nDusk.\n");
+
+        // The profile of the project signs, in the front matter too
+        project.metadata_mut().profile = "Fiction".to_string();
+        let profile = AuthorProfile {
+            pen_names: vec!["A. S. Moor".to_string()],
+            ..AuthorProfile::new("Fiction")
+        };
+        let config = CompileConfig {
+            title_page: true,
+            front_matter: "Also by {author}".to_string(),
+            ..config
+        };
+        let manuscript = compile_project(&project, &[profile], &config);
+        assert_eq!(manuscript.author, "A. S. Moor");
+        assert_eq!(
+            manuscript.front_matter,
+            "# The Inn\n\nby A. S. Moor\n\nAlso by A. S. Moor"
+        );
+    }
+
+    #[test]
+    fn test_export_manuscript() {
+        let dir = tempfile::tempdir().unwrap();
+        let manuscript = compile_manuscript(
+            "The Inn",
+            "Ann Author",
+            &structure(),
+            &CompileConfig::default(),
+            |node| Some(format!("Ann Author wrote {}.", node.title)),
+        );
+        let anonymization = Anonymization::new(["Ann Author"], "[Author]");
+        let output = export_manuscript(
+            &manuscript,
+            CompileTarget::Builtin(DocumentExportFormat::SmfText),
+            dir.path(),
+            &ExportConfig::default(),
+            Some(&anonymization),
+        )
+        .unwrap();
+
+        assert_eq!(output, dir.path().join("The_Inn.txt"));
+        let text = std::fs::read_to_string(output).unwrap();
+        assert!(text.starts_with("About 100 words\n"));
+        assert!(text.contains("[Author] wrote Epilogue."));
+        assert!(text.contains("\n#\n"));
+    }
+
+    /// Writes a compressed DOCX whose creator and running header come from
+    /// the exporter, not from the anonymized manuscript.
+    struct DocxWithCreator(&'static str);
+
+    impl ExportPlugin for DocxWithCreator {
+        fn format_id(&self) -> &str {
+            "docx"
+        }
+
+        fn format_name(&self) -> &str {
+            "Word"
+        }
+
+        fn file_extension(&self) -> &str {
+            "docx"
+        }
+
+        fn export(
+            &self,
+            manuscript: &Manuscript,
+            _options: &serde_json::Value,
+            output: &Path,
+        ) -> cosmarium_plugin_api::Result<()> {
+            let deflated = zip::write::FileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated);
+            let mut zip = zip::ZipWriter::new(std::fs::File::create(output)?);
+            zip.start_file("docProps/core.xml", deflated)?;
+            write!(
+                zip,
+                "<cp:coreProperties><dc:creator>{}</dc:creator></cp:coreProperties>",
+                self.0
+            )?;
+            zip.start_file("word/document.xml", deflated)?;
+            write!(zip, "<w:body><w:t>{}</w:t></w:body>", manuscript.title)?;
+            zip.finish()?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_anonymized_docx_is_checked_inside_the_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let manuscript = compile_manuscript(
+            "The Inn",
+            "Ann Author",
+            &structure(),
+            &CompileConfig::default(),
+            |node| Some(node.title.clone()),
+        );
+        let anonymization = Anonymization::new(["Ann Author"], "[Author]");
+        let export = |creator| {
+            export_manuscript(
+                &manuscript,
+                CompileTarget::Plugin(Arc::new(DocxWithCreator(creator))),
+                dir.path(),
+                &ExportConfig::default(),
+                Some(&anonymization),
+            )
+        };
+
+        let output = export("[Author]").unwrap();
+        assert!(anonymization.verify(&output).is_ok());
+
+        // Split over two runs, and compressed past a scan of the raw bytes
+        let error = export("<w:r>Ann</w:r> <w:r>AUTHOR</w:r>").unwrap_err();
+        assert!(error.to_string().contains("Ann Author"));
+        assert!(!dir.path().join("The_Inn.docx").exists());
+    }
+}
@@ -0,0 +1,546 @@
+//! # Compile filters
+//!
+//! Transformations of the text of a compiled manuscript, leaving the
+//! project's documents as they are: working names swapped for final ones,
+//! words and phrases blacked out, dashes set in one style, and British
+//! spelling converted to American or back.
+//!
+//! The filters of a compile profile are kept in its
+//! [`CompileConfig`](crate::config::CompileConfig) and applied by
+//! [`compile_manuscript`](super::compile::compile_manuscript).
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_core::export::filter::{Alias, DashStyle, SpellingConversion, TextFilters};
+//!
+//! let filters = TextFilters {
+//!     aliases: vec![Alias::new("Tamsin", "Mira")],
+//!     dashes: DashStyle::SpacedEn,
+//!     spelling: SpellingConversion::ToAmerican,
+//!     ..TextFilters::default()
+//! };
+//! assert_eq!(
+//!     filters.apply("Tamsin's favourite colour--grey."),
+//!     "Mira's favorite color – gray."
+//! );
+//! ```
+
+use super::Anonymization;
+use cosmarium_plugin_api::export::Manuscript;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// A working name and the name it is published under.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alias {
+    /// Name used in the documents
+    pub from: String,
+    /// Name written in the manuscript
+    pub to: String,
+}
+
+impl Alias {
+    /// Swap `from` for `to`.
+    pub fn new(from: &str, to: &str) -> Self {
+        Self {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+}
+
+/// How the dashes setting off a clause are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DashStyle {
+    /// As they are in the documents
+    #[default]
+    Keep,
+    /// Closed em dash, `word—word`, as in American publishing
+    ClosedEm,
+    /// Spaced em dash, `word — word`
+    SpacedEm,
+    /// Spaced en dash, `word – word`, as in British publishing
+    SpacedEn,
+}
+
+impl DashStyle {
+    /// All styles, in menu order.
+    pub const ALL: [DashStyle; 4] = [Self::Keep, Self::ClosedEm, Self::SpacedEm, Self::SpacedEn];
+
+    /// Human-readable name for menus.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Keep => "Keep as written",
+            Self::ClosedEm => "Closed em dash (word—word)",
+            Self::SpacedEm => "Spaced em dash (word — word)",
+            Self::SpacedEn => "Spaced en dash (word – word)",
+        }
+    }
+
+    /// The dash and the space around it.
+    fn parts(&self) -> Option<(char, &'static str)> {
+        match self {
+            Self::Keep => None,
+            Self::ClosedEm => Some(('—', "")),
+            Self::SpacedEm => Some(('—', " ")),
+            Self::SpacedEn => Some(('–', " ")),
+        }
+    }
+}
+
+/// Spelling the manuscript is converted to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpellingConversion {
+    /// As written in the documents
+    #[default]
+    Keep,
+    /// British spelling to American
+    ToAmerican,
+    /// American spelling to British
+    ToBritish,
+}
+
+impl SpellingConversion {
+    /// All conversions, in menu order.
+    pub const ALL: [SpellingConversion; 3] = [Self::Keep, Self::ToAmerican, Self::ToBritish];
+
+    /// Human-readable name for menus.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Keep => "Keep as written",
+            Self::ToAmerican => "British to American",
+            Self::ToBritish => "American to British",
+        }
+    }
+}
+
+/// Filters applied to the text of a compiled manuscript, in order: aliases,
+/// spelling, dashes, then redactions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextFilters {
+    /// Working names swapped for their final names, whole words ignoring
+    /// case
+    pub aliases: Vec<Alias>,
+    /// Words and phrases blacked out, whole words ignoring case
+    pub redactions: Vec<String>,
+    /// Text replacing each redacted word or phrase
+    pub redaction_mark: String,
+    /// Style of the dashes
+    pub dashes: DashStyle,
+    /// Spelling conversion
+    pub spelling: SpellingConversion,
+}
+
+impl Default for TextFilters {
+    fn default() -> Self {
+        Self {
+            aliases: Vec::new(),
+            redactions: Vec::new(),
+            redaction_mark: "█████".to_string(),
+            dashes: DashStyle::Keep,
+            spelling: SpellingConversion::Keep,
+        }
+    }
+}
+
+impl TextFilters {
+    /// Whether the filters leave text as it is.
+    pub fn is_empty(&self) -> bool {
+        self.aliases
+            .iter()
+            .all(|alias| alias.from.trim().is_empty())
+            && self.redactions.iter().all(|text| text.trim().is_empty())
+            && self.dashes == DashStyle::Keep
+            && self.spelling == SpellingConversion::Keep
+    }
+
+    /// `text` with the filters applied.
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for alias in &self.aliases {
+            text = Anonymization::new([alias.from.as_str()], &alias.to).redact(&text);
+        }
+        match self.spelling {
+            SpellingConversion::Keep => {}
+            SpellingConversion::ToAmerican => text = convert_spelling(&text, &spellings().0),
+            SpellingConversion::ToBritish => text = convert_spelling(&text, &spellings().1),
+        }
+        if let Some((dash, space)) = self.dashes.parts() {
+            text = restyle_dashes(&text, dash, space);
+        }
+        Anonymization::new(&self.redactions, &self.redaction_mark).redact(&text)
+    }
+
+    /// Apply the filters to the title, front matter and sections of
+    /// `manuscript`.
+    pub fn apply_to(&self, manuscript: &mut Manuscript) {
+        if self.is_empty() {
+            return;
+        }
+        manuscript.title = self.apply(&manuscript.title);
+        manuscript.front_matter = self.apply(&manuscript.front_matter);
+        for section in &mut manuscript.sections {
+            section.title = self.apply(&section.title);
+            section.markdown = self.apply(&section.markdown);
+        }
+    }
+
+    /// The lines of `text` the filters change, before and after, at most
+    /// `limit` of them.
+    pub fn preview(&self, text: &str, limit: usize) -> Vec<(String, String)> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        text.lines()
+            .filter_map(|line| {
+                let filtered = self.apply(line);
+                (filtered != line).then(|| (line.to_string(), filtered))
+            })
+            .take(limit)
+            .collect()
+    }
+}
+
+/// Replace the dashes of `text` by `dash` with `space` on each side.
+///
+/// Em dashes, spaced en dashes and double hyphens are dashes; unspaced en
+/// dashes, as in `1914–1918`, and thematic breaks or table rules are left
+/// alone.
+fn restyle_dashes(text: &str, dash: char, space: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let structural = content.contains("---")
+            || content
+                .chars()
+                .all(|c| matches!(c, '-' | '|' | ':' | ' ' | '\t'));
+        if structural {
+            out.push_str(line);
+            continue;
+        }
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut line_out = String::with_capacity(line.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let spaced_before = i > 0 && chars[i - 1] == ' ';
+            let spaced_after = chars.get(i + 1) == Some(&' ');
+            let length = match c {
+                '—' => 1,
+                '–' if spaced_before && spaced_after => 1,
+                '-' if chars.get(i + 1) == Some(&'-') => 2,
+                _ => 0,
+            };
+            if length == 0 {
+                line_out.push(c);
+                i += 1;
+                continue;
+            }
+
+            let trimmed = line_out.trim_end_matches(' ').len();
+            line_out.truncate(trimmed);
+            let mut next = i + length;
+            while chars.get(next) == Some(&' ') {
+                next += 1;
+            }
+            let at_end = matches!(chars.get(next), None | Some('\n' | '\r'));
+            if !line_out.is_empty() {
+                line_out.push_str(space);
+            }
+            line_out.push(dash);
+            if !at_end {
+                line_out.push_str(space);
+            }
+            i = next;
+        }
+        out.push_str(&line_out);
+    }
+    out
+}
+
+/// Replace the words of `text` found in `table`, keeping their case.
+fn convert_spelling(text: &str, table: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut word = String::new();
+    let flush = |word: &mut String, out: &mut String| {
+        match table.get(&word.to_lowercase()) {
+            Some(replacement) => out.push_str(&match_case(word, replacement)),
+            None => out.push_str(word),
+        }
+        word.clear();
+    };
+    for c in text.chars() {
+        if c.is_alphabetic() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut out);
+            out.push(c);
+        }
+    }
+    flush(&mut word, &mut out);
+    out
+}
+
+/// `replacement` in the case of `word`: all capitals, capitalized or lower.
+fn match_case(word: &str, replacement: &str) -> String {
+    let mut letters = word.chars();
+    let first_upper = letters.next().is_some_and(char::is_uppercase);
+    if first_upper && word.chars().count() > 1 && letters.all(char::is_uppercase) {
+        return replacement.to_uppercase();
+    }
+    if first_upper {
+        let mut chars = replacement.chars();
+        return chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default();
+    }
+    replacement.to_string()
+}
+
+/// Endings of the words spelled `-our` in British English.
+const OUR: &[&str] = &[
+    "", "s", "ed", "ing", "ful", "less", "able", "ably", "ite", "ites", "hood", "hoods",
+];
+
+/// Endings of the verbs spelled `-ise` in British English.
+const ISE: &[&str] = &["e", "es", "ed", "ing", "ation", "ations", "er", "ers"];
+
+/// Endings of the nouns spelled `-re` in British English.
+const RE: &[&str] = &["", "s"];
+
+/// British and American stems, and the endings they take.
+const STEMS: &[(&str, &str, &[&str])] = &[
+    ("armour", "armor", OUR),
+    ("behaviour", "behavior", OUR),
+    ("candour", "candor", OUR),
+    ("clamour", "clamor", OUR),
+    ("colour", "color", OUR),
+    ("endeavour", "endeavor", OUR),
+    ("favour", "favor", OUR),
+    ("flavour", "flavor", OUR),
+    ("harbour", "harbor", OUR),
+    ("honour", "honor", OUR),
+    ("humour", "humor", OUR),
+    ("labour", "labor", OUR),
+    ("neighbour", "neighbor", OUR),
+    ("odour", "odor", OUR),
+    ("parlour", "parlor", OUR),
+    ("rumour", "rumor", OUR),
+    ("savour", "savor", OUR),
+    ("splendour", "splendor", OUR),
+    ("valour", "valor", OUR),
+    ("vapour", "vapor", OUR),
+    ("vigour", "vigor", OUR),
+    ("apologis", "apologiz", ISE),
+    ("authoris", "authoriz", ISE),
+    ("categoris", "categoriz", ISE),
+    ("characteris", "characteriz", ISE),
+    ("civilis", "civiliz", ISE),
+    ("criticis", "criticiz", ISE),
+    ("emphasis", "emphasiz", ISE),
+    ("familiaris", "familiariz", ISE),
+    ("memoris", "memoriz", ISE),
+    ("organis", "organiz", ISE),
+    ("paralys", "paralyz", ISE),
+    ("prioritis", "prioritiz", ISE),
+    ("realis", "realiz", ISE),
+    ("recognis", "recogniz", ISE),
+    ("scrutinis", "scrutiniz", ISE),
+    ("summaris", "summariz", ISE),
+    ("sympathis", "sympathiz", ISE),
+    ("symbolis", "symboliz", ISE),
+    ("visualis", "visualiz", ISE),
+    ("calibre", "caliber", RE),
+    ("centre", "center", RE),
+    ("fibre", "fiber", RE),
+    ("litre", "liter", RE),
+    ("lustre", "luster", RE),
+    ("sabre", "saber", RE),
+    ("sombre", "somber", RE),
+    ("spectre", "specter", RE),
+    ("theatre", "theater", RE),
+];
+
+/// British and American words of irregular spelling.
+const WORDS: &[(&str, &str)] = &[
+    ("ageing", "aging"),
+    ("aeroplane", "airplane"),
+    ("aeroplanes", "airplanes"),
+    ("aluminium", "aluminum"),
+    ("cancelled", "canceled"),
+    ("cancelling", "canceling"),
+    ("catalogue", "catalog"),
+    ("catalogues", "catalogs"),
+    ("centred", "centered"),
+    ("cosy", "cozy"),
+    ("defence", "defense"),
+    ("defences", "defenses"),
+    ("enrol", "enroll"),
+    ("enrols", "enrolls"),
+    ("favourite", "favorite"),
+    ("favourites", "favorites"),
+    ("fuelled", "fueled"),
+    ("fulfil", "fulfill"),
+    ("fulfils", "fulfills"),
+    ("grey", "gray"),
+    ("greyer", "grayer"),
+    ("greyest", "grayest"),
+    ("greying", "graying"),
+    ("greyish", "grayish"),
+    ("greys", "grays"),
+    ("instalment", "installment"),
+    ("instalments", "installments"),
+    ("jewellery", "jewelry"),
+    ("judgement", "judgment"),
+    ("judgements", "judgments"),
+    ("labelled", "labeled"),
+    ("labelling", "labeling"),
+    ("manoeuvre", "maneuver"),
+    ("manoeuvred", "maneuvered"),
+    ("manoeuvres", "maneuvers"),
+    ("marvelled", "marveled"),
+    ("marvellous", "marvelous"),
+    ("modelled", "modeled"),
+    ("modelling", "modeling"),
+    ("mould", "mold"),
+    ("mouldy", "moldy"),
+    ("moustache", "mustache"),
+    ("moustaches", "mustaches"),
+    ("offence", "offense"),
+    ("offences", "offenses"),
+    ("plough", "plow"),
+    ("ploughed", "plowed"),
+    ("pretence", "pretense"),
+    ("pyjamas", "pajamas"),
+    ("quarrelled", "quarreled"),
+    ("quarrelling", "quarreling"),
+    ("sceptic", "skeptic"),
+    ("sceptical", "skeptical"),
+    ("sceptics", "skeptics"),
+    ("signalled", "signaled"),
+    ("signalling", "signaling"),
+    ("skilful", "skillful"),
+    ("smoulder", "smolder"),
+    ("smouldered", "smoldered"),
+    ("smouldering", "smoldering"),
+    ("travelled", "traveled"),
+    ("traveller", "traveler"),
+    ("travellers", "travelers"),
+    ("travelling", "traveling"),
+    ("wilful", "willful"),
+    ("woollen", "woolen"),
+    ("yoghurt", "yogurt"),
+];
+
+/// British to American and American to British spellings, in lower case.
+fn spellings() -> &'static (HashMap<String, String>, HashMap<String, String>) {
+    static SPELLINGS: OnceLock<(HashMap<String, String>, HashMap<String, String>)> =
+        OnceLock::new();
+    SPELLINGS.get_or_init(|| {
+        let pairs = STEMS
+            .iter()
+            .flat_map(|(british, american, endings)| {
+                endings
+                    .iter()
+                    .map(move |end| (format!("{british}{end}"), format!("{american}{end}")))
+            })
+            .chain(
+                WORDS
+                    .iter()
+                    .map(|(british, american)| (british.to_string(), american.to_string())),
+            );
+        let mut to_american = HashMap::new();
+        let mut to_british = HashMap::new();
+        for (british, american) in pairs {
+            to_british.insert(american.clone(), british.clone());
+            to_american.insert(british, american);
+        }
+        (to_american, to_british)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dashes(style: DashStyle, text: &str) -> String {
+        TextFilters {
+            dashes: style,
+            ..TextFilters::default()
+        }
+        .apply(text)
+    }
+
+    #[test]
+    fn test_dash_styles() {
+        let text = "She ran -- fast—then stopped – and turned.";
+        assert_eq!(
+            dashes(DashStyle::ClosedEm, text),
+            "She ran—fast—then stopped—and turned."
+        );
+        assert_eq!(
+            dashes(DashStyle::SpacedEm, text),
+            "She ran — fast — then stopped — and turned."
+        );
+        // Ranges, rules, tables and hyphens are left alone
+        let text = "1914–1918, well-known\n\n---\n\n|--|--|\n";
+        assert_eq!(dashes(DashStyle::SpacedEn, text), text);
+        // Dialogue dashes and interruptions hug the edges of the line
+        assert_eq!(
+            dashes(DashStyle::SpacedEn, "— Who?\nI was—\n"),
+            "– Who?\nI was –\n"
+        );
+    }
+
+    #[test]
+    fn test_spelling_keeps_case() {
+        let to_american = TextFilters {
+            spelling: SpellingConversion::ToAmerican,
+            ..TextFilters::default()
+        };
+        assert_eq!(
+            to_american.apply("Colours realised; the THEATRE's neighbourhood organisation. Grey"),
+            "Colors realized; the THEATER's neighborhood organization. Gray"
+        );
+        // Nouns and other words sharing a stem are not verbs
+        assert_eq!(
+            to_american.apply("emphasis greyhound"),
+            "emphasis greyhound"
+        );
+
+        let to_british = TextFilters {
+            spelling: SpellingConversion::ToBritish,
+            ..TextFilters::default()
+        };
+        assert_eq!(
+            to_british.apply("She traveled to the center, skeptical."),
+            "She travelled to the centre, sceptical."
+        );
+    }
+
+    #[test]
+    fn test_aliases_and_redactions() {
+        let filters = TextFilters {
+            aliases: vec![Alias::new("Old Tam", "Bren"), Alias::new("Tam", "Mira")],
+            redactions: vec!["Acme Corp".to_string()],
+            redaction_mark: "[redacted]".to_string(),
+            ..TextFilters::default()
+        };
+        assert_eq!(
+            filters.apply("Old Tam met tam at ACME CORP. Tamsin waved."),
+            "Bren met Mira at [redacted]. Tamsin waved."
+        );
+        assert_eq!(
+            filters.preview("Nothing here.\nTam left.", 10),
+            [("Tam left.".to_string(), "Mira left.".to_string())]
+        );
+        assert!(TextFilters::default().is_empty());
+    }
+}
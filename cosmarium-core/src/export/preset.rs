@@ -0,0 +1,206 @@
+//! # Export presets
+//!
+//! A preset lays a compiled manuscript out the way publishers expect it,
+//! whatever the user's own export settings.
+//!
+//! The standard manuscript preset follows William Shunn's "Proper Manuscript
+//! Format", asked for by most fiction magazines and agents: Courier 12pt,
+//! double spacing and one-inch margins on US Letter, a title page with the
+//! author's contact details and an approximate word count, a
+//! "Surname / Title / page" header on the following pages and `#` between
+//! scenes.
+
+use super::compile::CompileTarget;
+use super::{approximate_word_count, to_plain_text};
+use crate::config::{ExportConfig, PdfExportConfig};
+use cosmarium_plugin_api::export::{Manuscript, SectionKind};
+use cosmarium_plugin_api::locale::Locale;
+use serde::{Deserialize, Serialize};
+
+/// A layout of compiled manuscripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ExportPreset {
+    /// Shunn standard manuscript format, for submissions
+    StandardManuscript,
+}
+
+impl ExportPreset {
+    /// All presets, in menu order.
+    pub const ALL: [ExportPreset; 1] = [Self::StandardManuscript];
+
+    /// Human-readable name for menus.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::StandardManuscript => "Standard manuscript (Shunn)",
+        }
+    }
+
+    /// Whether the preset can lay out manuscripts written to `target`.
+    pub fn supports(&self, target: &CompileTarget) -> bool {
+        match self {
+            Self::StandardManuscript => matches!(
+                target,
+                CompileTarget::Plugin(plugin) if matches!(plugin.format_id(), "pdf" | "docx")
+            ),
+        }
+    }
+
+    /// Export settings following the preset. Settings it does not cover
+    /// keep their values from `config`.
+    pub fn apply(&self, config: &ExportConfig) -> ExportConfig {
+        let mut config = config.clone();
+        match self {
+            Self::StandardManuscript => {
+                // The title page replaces the configured one
+                config.compile.title_page = false;
+                config.compile.front_matter.clear();
+                // Chapters start on a new page; scene breaks are written `#`
+                config.compile.chapter_separator.clear();
+                config.compile.scene_separator = "***".to_string();
+
+                config.pdf = PdfExportConfig {
+                    paper_size: "Letter".to_string(),
+                    margin_top: 25.4,
+                    margin_bottom: 25.4,
+                    margin_left: 25.4,
+                    margin_right: 25.4,
+                    font_family: "Courier New".to_string(),
+                    font_size: 12.0,
+                    line_spacing: 2.0,
+                    scene_break: "#".to_string(),
+                    include_toc: false,
+                    include_page_numbers: false,
+                    running_header: true,
+                };
+                config.word.template = "manuscript".to_string();
+                config.word.preserve_formatting = true;
+                config.word.include_comments = false;
+                config.word.track_changes = false;
+                config.word.running_header = true;
+            }
+        }
+        config
+    }
+
+    /// Title page of `manuscript` following the preset, as Markdown.
+    ///
+    /// `contact_info` holds the author's name and address, one item per
+    /// line; it is left out when empty, as in anonymized exports. The word
+    /// count is written the way `locale` writes numbers.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::export::preset::ExportPreset;
+    /// use cosmarium_plugin_api::export::Manuscript;
+    /// use cosmarium_plugin_api::locale::Locale;
+    ///
+    /// let manuscript = Manuscript {
+    ///     title: "The Inn".to_string(),
+    ///     author: "Ann Author".to_string(),
+    ///     ..Manuscript::default()
+    /// };
+    /// let contact = "Ann Author\nann@example.com";
+    /// let page =
+    ///     ExportPreset::StandardManuscript.title_page(&manuscript, contact, &Locale::default());
+    /// assert_eq!(
+    ///     page,
+    ///     "Ann Author  \nann@example.com\n\nAbout 100 words\n\n# The Inn\n\nby Ann Author"
+    /// );
+    /// ```
+    pub fn title_page(
+        &self,
+        manuscript: &Manuscript,
+        contact_info: &str,
+        locale: &Locale,
+    ) -> String {
+        match self {
+            Self::StandardManuscript => {
+                let words: usize = manuscript
+                    .sections
+                    .iter()
+                    .filter(|section| section.kind == SectionKind::Document)
+                    .map(|section| to_plain_text(&section.markdown).split_whitespace().count())
+                    .sum();
+
+                let mut blocks = Vec::new();
+                let contact: Vec<&str> = contact_info
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .collect();
+                if !contact.is_empty() {
+                    blocks.push(contact.join("  \n"));
+                }
+                blocks.push(format!(
+                    "About {} words",
+                    locale.format_count(approximate_word_count(words))
+                ));
+                blocks.push(format!("# {}", manuscript.title.trim()));
+                if !manuscript.author.trim().is_empty() {
+                    blocks.push(format!("by {}", manuscript.author.trim()));
+                }
+                blocks.join("\n\n")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmarium_plugin_api::export::ManuscriptSection;
+
+    #[test]
+    fn test_standard_manuscript_settings() {
+        let mut config = ExportConfig::default();
+        config.compile.front_matter = "*For Tam.*".to_string();
+        config.word.template = "house.dotx".to_string();
+        config.default_format = "pdf".to_string();
+
+        let config = ExportPreset::StandardManuscript.apply(&config);
+        assert!(!config.compile.title_page);
+        assert!(config.compile.front_matter.is_empty());
+        assert_eq!(config.default_format, "pdf");
+
+        let pdf = config.format_options("pdf");
+        assert_eq!(pdf["paper_size"], "Letter");
+        assert_eq!(pdf["font_family"], "Courier New");
+        assert_eq!(pdf["line_spacing"], 2.0);
+        assert_eq!(pdf["scene_break"], "#");
+        assert_eq!(pdf["running_header"], true);
+        let word = config.format_options("docx");
+        assert_eq!(word["template"], "manuscript");
+        assert_eq!(word["running_header"], true);
+    }
+
+    #[test]
+    fn test_title_page_counts_words() {
+        let section = |kind, markdown: &str| ManuscriptSection {
+            kind,
+            title: String::new(),
+            depth: 0,
+            path: None,
+            separator: String::new(),
+            markdown: markdown.to_string(),
+        };
+        let manuscript = Manuscript {
+            title: "The Inn".to_string(),
+            author: String::new(),
+            language: String::new(),
+            front_matter: "# The Inn".to_string(),
+            sections: vec![
+                section(SectionKind::Heading, "# Arrival"),
+                section(SectionKind::Document, &"word ".repeat(1234)),
+            ],
+        };
+
+        // Anonymized: no contact details nor byline
+        let title_page = |language| {
+            let locale = Locale::new(language);
+            ExportPreset::StandardManuscript.title_page(&manuscript, " \n", &locale)
+        };
+        assert_eq!(title_page("en"), "About 1,300 words\n\n# The Inn");
+        assert_eq!(title_page("de"), "About 1.300 words\n\n# The Inn");
+    }
+}
@@ -1,18 +1,97 @@
 //! Git integration for Cosmarium projects.
 //!
-//! Provides version control functionality using the `gix` library.
+//! Provides version control functionality using the `gix` library. Builds
+//! without the `git` feature leave `gix` out: repositories then can never be
+//! initialized or opened, and projects go unversioned.
+//!
+//! The commits that changed a file are listed by [`GitIntegration::file_history`],
+//! and its content at any of them read by [`GitIntegration::file_at`].
+//! [`GitIntegration::changes`] tells which files changed since the last
+//! commit, and [`GitIntegration::commit`] commits them.
+//!
+//! Alternate drafts are kept on branches, listed by
+//! [`GitIntegration::branches`] and switched between with
+//! [`GitIntegration::switch_branch`]. [`GitIntegration::merge_branch`]
+//! merges one back, the texts changed on both line by line (see [`merge`]).
+
+pub mod merge;
 
 use crate::{Error, Result};
+use cosmarium_plugin_api::branch::ConflictedFile;
+use cosmarium_plugin_api::git_history::CommitInfo;
+#[cfg(feature = "git")]
+use gix::refs::transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog};
+#[cfg(feature = "git")]
+use gix::refs::{FullName, Target};
+#[cfg(feature = "git")]
 use gix::ThreadSafeRepository;
-use std::path::Path;
+#[cfg(feature = "git")]
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "git")]
 use tracing::{debug, info, warn};
 
+/// Name of the committer when Git has none configured.
+#[cfg(feature = "git")]
+const COMMITTER_NAME: &str = "Cosmarium";
+
+/// Email of the committer when Git has none configured.
+#[cfg(feature = "git")]
+const COMMITTER_EMAIL: &str = "cosmarium@localhost";
+
+/// Files the project keeps for itself, never committed: the backups of its
+/// state, and those made before migrating it (see [`crate::project`]).
+#[cfg(feature = "git")]
+const UNVERSIONED: [&str; 3] = [
+    "meta/core.toon.bak",
+    "meta/core.toon.corrupt",
+    "meta/migrations",
+];
+
+/// File of the repository holding the commit merged, while a merge waits
+/// for its conflicts to be resolved.
+#[cfg(feature = "git")]
+const MERGE_HEAD: &str = "MERGE_HEAD";
+
+/// File of the repository holding the message of the merge in progress.
+#[cfg(feature = "git")]
+const MERGE_MSG: &str = "MERGE_MSG";
+
+/// What merging a branch did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// The branch had nothing new
+    UpToDate,
+    /// The current branch was moved to the branch, which only added commits
+    FastForward,
+    /// The changes of both were merged in the commit of this identifier
+    Merged(String),
+    /// These files have conflicts to resolve before the merge is committed
+    Conflicts(Vec<PathBuf>),
+}
+
+/// A file changed since the last commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    /// Path of the file, relative to the repository root
+    pub path: PathBuf,
+    /// Content in the last commit, `None` if the file is new
+    pub before: Option<Vec<u8>>,
+    /// Content now, `None` if the file was deleted
+    pub after: Option<Vec<u8>>,
+}
+
 /// Git repository integration.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GitIntegration {
+    #[cfg(feature = "git")]
     repo: ThreadSafeRepository,
+    /// Without Git support, no repository is ever opened
+    #[cfg(not(feature = "git"))]
+    never: std::convert::Infallible,
 }
 
+#[cfg(feature = "git")]
 impl GitIntegration {
     /// Initialize a new Git repository at the specified path.
     pub fn init<P: AsRef<Path>>(path: P) -> Result<Self> {
@@ -36,25 +115,473 @@ impl GitIntegration {
         Ok(Self { repo: repo.into() })
     }
 
-    /// Commit changes to the repository.
+    /// Commit the files of the working directory, all but those ignored, on
+    /// the current branch with `message`. Returns the identifier of the commit,
+    /// `None` when nothing changed since the last one.
     ///
-    /// # Arguments
-    ///
-    /// * `message` - Commit message
-    pub fn commit(&self, message: &str) -> Result<()> {
-        debug!("Git commit requested: {}", message);
+    /// The author and committer are those configured for Git, or Cosmarium
+    /// when there is none.
+    pub fn commit(&self, message: &str) -> Result<Option<String>> {
+        let repo = self.repo.to_thread_local();
+        let failed = |e: &dyn std::fmt::Display| Error::project(format!("Failed to commit: {}", e));
+        let root = repo
+            .workdir()
+            .ok_or_else(|| Error::project("The repository has no working directory"))?;
+        let mut excludes = Self::excludes(&repo)?;
+        let tree = match Self::write_tree(&repo, &mut excludes, root, Path::new(""))? {
+            Some(tree) => tree,
+            None => repo.empty_tree().id,
+        };
+        let parent = repo.head_commit().ok();
+        let merged = Self::merge_head(&repo)?;
+        if merged.is_some() {
+            if let Some(file) = self.conflicts()?.first() {
+                return Err(Error::project(format!(
+                    "Resolve the conflicts in {:?} before committing the merge",
+                    file.path
+                )));
+            }
+        }
+        let unchanged = match &parent {
+            Some(parent) => parent.tree_id().map_err(|e| failed(&e))? == tree,
+            None => tree == repo.empty_tree().id,
+        };
+        // A merge is committed even when it changed nothing
+        if unchanged && merged.is_none() {
+            debug!("Nothing to commit");
+            return Ok(None);
+        }
+
+        let parents: Vec<gix::ObjectId> = parent
+            .map(|parent| parent.id)
+            .into_iter()
+            .chain(merged)
+            .collect();
+        let id = Self::as_committer(&repo, |committer, author| {
+            repo.commit_as(committer, author, "HEAD", message, tree, parents)
+        })
+        .map_err(|e| failed(&e))?;
+        if merged.is_some() {
+            Self::clear_merge(&repo)?;
+        }
+        info!(
+            "Committed {}: {}",
+            id,
+            message.lines().next().unwrap_or_default()
+        );
+        Ok(Some(id.to_string()))
+    }
+
+    /// Call `f` with the committer and the author configured for Git, or
+    /// Cosmarium when there is none.
+    fn as_committer<T>(
+        repo: &gix::Repository,
+        f: impl FnOnce(gix::actor::SignatureRef<'_>, gix::actor::SignatureRef<'_>) -> T,
+    ) -> T {
+        let time = format!("{} +0000", chrono::Utc::now().timestamp());
+        let fallback = gix::actor::SignatureRef {
+            name: COMMITTER_NAME.into(),
+            email: COMMITTER_EMAIL.into(),
+            time: &time,
+        };
+        let committer = repo.committer().and_then(|c| c.ok()).unwrap_or(fallback);
+        let author = repo.author().and_then(|a| a.ok()).unwrap_or(committer);
+        f(committer, author)
+    }
+
+    /// Apply `edit` to the references, logged as made by the committer.
+    fn edit_reference(
+        repo: &gix::Repository,
+        edit: RefEdit,
+    ) -> std::result::Result<(), gix::reference::edit::Error> {
+        Self::as_committer(repo, |committer, _| {
+            repo.edit_references_as(Some(edit), Some(committer))
+        })
+        .map(|_| ())
+    }
+
+    /// Edit pointing the reference `name` to `new`, if it was `expected`.
+    fn update(name: FullName, new: Target, expected: PreviousValue, message: String) -> RefEdit {
+        RefEdit {
+            change: Change::Update {
+                log: LogChange {
+                    mode: RefLog::AndReference,
+                    force_create_reflog: false,
+                    message: message.into(),
+                },
+                expected,
+                new,
+            },
+            name,
+            deref: false,
+        }
+    }
+
+    /// Full name of the branch `name`.
+    fn branch_ref(name: &str) -> Result<FullName> {
+        format!("refs/heads/{}", name)
+            .try_into()
+            .map_err(|e| Error::project(format!("Invalid branch name {:?}: {}", name, e)))
+    }
+
+    /// Write the files in `dir`, relative to the working directory `root`,
+    /// as a tree, `None` if it has none.
+    fn write_tree(
+        repo: &gix::Repository,
+        excludes: &mut gix::AttributeStack<'_>,
+        root: &Path,
+        dir: &Path,
+    ) -> Result<Option<gix::ObjectId>> {
+        use gix::objs::tree::{Entry, EntryKind};
+
+        let mut entries = Vec::new();
+        for (name, path) in Self::worktree_entries(excludes, root, dir)? {
+            let (kind, oid) = if path.is_dir() {
+                match Self::write_tree(repo, excludes, root, &dir.join(&name))? {
+                    Some(oid) => (EntryKind::Tree, oid),
+                    // Git keeps no empty folder
+                    None => continue,
+                }
+            } else {
+                let oid = repo
+                    .write_blob(std::fs::read(&path)?)
+                    .map_err(|e| Error::project(format!("Failed to store {:?}: {}", path, e)))?;
+                let kind = if Self::is_executable(&path)? {
+                    EntryKind::BlobExecutable
+                } else {
+                    EntryKind::Blob
+                };
+                (kind, oid.detach())
+            };
+            entries.push(Entry {
+                mode: kind.into(),
+                filename: gix::path::into_bstr(Path::new(&name)).into_owned(),
+                oid,
+            });
+        }
+        if entries.is_empty() {
+            return Ok(None);
+        }
+        entries.sort();
+        let tree = repo
+            .write_object(gix::objs::Tree { entries })
+            .map_err(|e| Error::project(format!("Failed to store {:?}: {}", dir, e)))?;
+        Ok(Some(tree.detach()))
+    }
+
+    /// Names and paths of the files and folders in `dir`, relative to the
+    /// working directory `root`, kept in commits: all but the repository
+    /// itself, links, the files the project keeps for itself and those
+    /// ignored.
+    fn worktree_entries(
+        excludes: &mut gix::AttributeStack<'_>,
+        root: &Path,
+        dir: &Path,
+    ) -> Result<Vec<(std::ffi::OsString, PathBuf)>> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(root.join(dir))? {
+            let entry = entry?;
+            let kind = entry.file_type()?;
+            let relative = dir.join(entry.file_name());
+            if entry.file_name() == ".git"
+                || kind.is_symlink()
+                || UNVERSIONED.iter().any(|path| relative == Path::new(path))
+            {
+                continue;
+            }
+            let mode = kind.is_dir().then_some(gix::index::entry::Mode::DIR);
+            if excludes.at_path(&relative, mode)?.is_excluded() {
+                continue;
+            }
+            entries.push((entry.file_name(), entry.path()));
+        }
+        Ok(entries)
+    }
+
+    /// Ignore rules of the repository: those of its `.gitignore` files and
+    /// of its configuration.
+    fn excludes(repo: &gix::Repository) -> Result<gix::AttributeStack<'_>> {
+        let failed = |e: &dyn std::fmt::Display| {
+            Error::project(format!("Failed to read the ignore rules: {}", e))
+        };
+        let index = repo.index_or_empty().map_err(|e| failed(&e))?;
+        repo.excludes(
+            &index,
+            None,
+            gix::worktree::stack::state::ignore::Source::WorktreeThenIdMappingIfNotSkipped,
+        )
+        .map_err(|e| failed(&e))
+    }
+
+    /// Whether the file at `path` can be run.
+    #[cfg(unix)]
+    fn is_executable(path: &Path) -> Result<bool> {
+        use std::os::unix::fs::PermissionsExt;
+        Ok(std::fs::metadata(path)?.permissions().mode() & 0o111 != 0)
+    }
+
+    /// Whether the file at `path` can be run: never known here.
+    #[cfg(not(unix))]
+    fn is_executable(_path: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Files of the working directory changed since the last commit, by
+    /// path, with their content in it and now.
+    pub fn changes(&self) -> Result<Vec<FileChange>> {
+        let repo = self.repo.to_thread_local();
+        let failed = |e: &dyn std::fmt::Display| {
+            Error::project(format!("Failed to read the changes: {}", e))
+        };
+        let root = repo
+            .workdir()
+            .ok_or_else(|| Error::project("The repository has no working directory"))?;
+
+        let mut committed = match repo.head_commit() {
+            Ok(commit) => Self::tree_files(&commit)?,
+            Err(_) => BTreeMap::new(),
+        };
+        let content = |oid: gix::ObjectId| -> Result<Vec<u8>> {
+            let object = repo.find_object(oid).map_err(|e| failed(&e))?;
+            Ok(object.detach().data)
+        };
+
+        let mut excludes = Self::excludes(&repo)?;
+        let mut changes = Vec::new();
+        let mut dirs = vec![PathBuf::new()];
+        while let Some(dir) = dirs.pop() {
+            for (name, path) in Self::worktree_entries(&mut excludes, root, &dir)? {
+                let relative = dir.join(name);
+                if path.is_dir() {
+                    dirs.push(relative);
+                    continue;
+                }
+                let data = std::fs::read(&path)?;
+                let oid =
+                    gix::objs::compute_hash(repo.object_hash(), gix::object::Kind::Blob, &data)
+                        .map_err(|e| failed(&e))?;
+                let before = match committed.remove(&relative) {
+                    Some(committed) if committed == oid => continue,
+                    Some(committed) => Some(content(committed)?),
+                    None => None,
+                };
+                changes.push(FileChange {
+                    path: relative,
+                    before,
+                    after: Some(data),
+                });
+            }
+        }
+        for (path, oid) in committed {
+            changes.push(FileChange {
+                path,
+                before: Some(content(oid)?),
+                after: None,
+            });
+        }
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(changes)
+    }
+
+    /// Files of the tree of `commit`, by path, with the identifiers of
+    /// their content.
+    fn tree_files(commit: &gix::Commit<'_>) -> Result<BTreeMap<PathBuf, gix::ObjectId>> {
+        let failed = |e: &dyn std::fmt::Display| {
+            Error::project(format!("Failed to read commit {}: {}", commit.id, e))
+        };
+        let mut recorder = gix::traverse::tree::Recorder::default();
+        commit
+            .tree()
+            .map_err(|e| failed(&e))?
+            .traverse()
+            .breadthfirst(&mut recorder)
+            .map_err(|e| failed(&e))?;
+        Ok(recorder
+            .records
+            .into_iter()
+            .filter(|entry| entry.mode.is_blob())
+            .map(|entry| (gix::path::from_bstring(entry.filepath), entry.oid))
+            .collect())
+    }
+
+    /// Content of the file at `path`, relative to the repository root, in
+    /// the last commit. `None` if there is no commit yet or the file is not
+    /// in it.
+    pub fn file_at_head<P: AsRef<Path>>(&self, path: P) -> Result<Option<Vec<u8>>> {
+        let repo = self.repo.to_thread_local();
+        let Ok(commit) = repo.head_commit() else {
+            return Ok(None);
+        };
+        Self::file_in(&commit, path.as_ref())
+    }
+
+    /// Content of the file at `path`, relative to the repository root, in
+    /// the commit `id`. `None` if the file is not in it.
+    pub fn file_at<P: AsRef<Path>>(&self, id: &str, path: P) -> Result<Option<Vec<u8>>> {
+        let repo = self.repo.to_thread_local();
+        let id = gix::ObjectId::from_hex(id.as_bytes())
+            .map_err(|e| Error::project(format!("Invalid commit {:?}: {}", id, e)))?;
+        let commit = repo
+            .find_commit(id)
+            .map_err(|e| Error::project(format!("Failed to read commit {}: {}", id, e)))?;
+        Self::file_in(&commit, path.as_ref())
+    }
 
-        // Git commit functionality is complex with gix
-        // For now, we just log the request
-        // Users can commit manually or we can implement this later
-        // when gix has more stable high-level APIs
+    /// Content of the file at `path` in `commit`.
+    fn file_in(commit: &gix::Commit<'_>, path: &Path) -> Result<Option<Vec<u8>>> {
+        let tree = commit
+            .tree()
+            .map_err(|e| Error::project(format!("Failed to read commit {}: {}", commit.id, e)))?;
+        let entry = tree
+            .lookup_entry_by_path(path)
+            .map_err(|e| Error::project(format!("Failed to read commit {}: {}", commit.id, e)))?;
+        match entry {
+            Some(entry) if entry.mode().is_blob() => {
+                let object = entry
+                    .object()
+                    .map_err(|e| Error::project(format!("Failed to read {:?}: {}", path, e)))?;
+                Ok(Some(object.detach().data))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Object of the file at `path` in `commit`, `None` if it is not in it.
+    fn blob_id(commit: &gix::Commit<'_>, path: &Path) -> Result<Option<gix::ObjectId>> {
+        let tree = commit
+            .tree()
+            .map_err(|e| Error::project(format!("Failed to read commit {}: {}", commit.id, e)))?;
+        let entry = tree
+            .lookup_entry_by_path(path)
+            .map_err(|e| Error::project(format!("Failed to read commit {}: {}", commit.id, e)))?;
+        Ok(entry
+            .filter(|entry| entry.mode().is_blob())
+            .map(|entry| entry.object_id()))
+    }
+
+    /// Commits that changed the file at `path`, relative to the repository
+    /// root, newest first and `limit` at most. A commit changed the file if
+    /// its content differs from that in the commit's first parent; commits
+    /// deleting it are left out.
+    pub fn file_history<P: AsRef<Path>>(&self, path: P, limit: usize) -> Result<Vec<CommitInfo>> {
+        use gix::revision::walk::Sorting;
+        use gix::traverse::commit::simple::CommitTimeOrder;
+
+        let path = path.as_ref();
+        let repo = self.repo.to_thread_local();
+        let Ok(head) = repo.head_id() else {
+            // Nothing was committed yet
+            return Ok(Vec::new());
+        };
+        let failed = |e: &dyn std::fmt::Display| {
+            Error::project(format!("Failed to read the history of {:?}: {}", path, e))
+        };
+        let walk = repo
+            .rev_walk([head])
+            .sorting(Sorting::ByCommitTime(CommitTimeOrder::NewestFirst))
+            .all()
+            .map_err(|e| failed(&e))?;
+
+        let mut commits = Vec::new();
+        for info in walk {
+            if commits.len() >= limit {
+                break;
+            }
+            let info = info.map_err(|e| failed(&e))?;
+            let commit = info.object().map_err(|e| failed(&e))?;
+            let Some(blob) = Self::blob_id(&commit, path)? else {
+                continue;
+            };
+            let parent_id = info.parent_ids().next().map(|id| id.detach());
+            let parent = match parent_id {
+                Some(parent) => {
+                    let parent = repo.find_commit(parent).map_err(|e| failed(&e))?;
+                    Self::blob_id(&parent, path)?
+                }
+                None => None,
+            };
+            if parent == Some(blob) {
+                continue;
+            }
 
-        debug!("Git commit is a placeholder - changes are tracked but not auto-committed");
+            let summary = commit
+                .message()
+                .map(|message| message.summary().to_string())
+                .map_err(|e| failed(&e))?;
+            let author = commit
+                .author()
+                .map(|author| author.name.to_string())
+                .map_err(|e| failed(&e))?;
+            let seconds = commit.time().map_err(|e| failed(&e))?.seconds;
+            commits.push(CommitInfo {
+                id: commit.id.to_string(),
+                summary,
+                author,
+                time: chrono::DateTime::from_timestamp(seconds, 0).unwrap_or_default(),
+                parent: parent_id.map(|id| id.to_string()),
+            });
+        }
+        Ok(commits)
+    }
 
-        // Return Ok to avoid errors during save
+    /// Identifier of the last commit, `None` if there is no commit yet.
+    pub fn head_commit_id(&self) -> Result<Option<String>> {
+        let repo = self.repo.to_thread_local();
+        match repo.head_id() {
+            Ok(id) => Ok(Some(id.to_string())),
+            Err(e) if repo.head().is_ok_and(|head| head.is_unborn()) => {
+                debug!("No commit yet: {}", e);
+                Ok(None)
+            }
+            Err(e) => Err(Error::project(format!(
+                "Failed to read the last commit: {}",
+                e
+            ))),
+        }
+    }
+
+    /// Add a remote `name` fetching from and pushing to `url`, saved in
+    /// the configuration of the repository.
+    pub fn add_remote(&self, name: &str, url: &str) -> Result<()> {
+        let repo = self.repo.to_thread_local();
+        let path = repo.git_dir().join("config");
+        let mut config =
+            gix::config::File::from_path_no_includes(path.clone(), gix::config::Source::Local)
+                .map_err(|e| Error::project(format!("Failed to read the git config: {}", e)))?;
+        let fetch = format!("+refs/heads/*:refs/remotes/{}/*", name);
+        repo.remote_at(url)
+            .map_err(|e| Error::project(format!("Invalid remote URL {:?}: {}", url, e)))?
+            .with_refspecs(Some(fetch.as_str()), gix::remote::Direction::Fetch)
+            .map_err(|e| Error::project(format!("Invalid remote name {:?}: {}", name, e)))?
+            .save_as_to(name, &mut config)
+            .map_err(|e| Error::project(format!("Failed to add remote {:?}: {}", name, e)))?;
+        let mut file = std::fs::File::create(&path)?;
+        config.write_to(&mut file)?;
+        info!("Added remote {:?} at {}", name, url);
         Ok(())
     }
 
+    /// Problems of the repository, empty if it is healthy.
+    pub fn check(&self) -> Vec<String> {
+        let repo = self.repo.to_thread_local();
+        let mut problems = Vec::new();
+        match repo.head() {
+            Err(e) => problems.push(format!("HEAD cannot be read: {}", e)),
+            // Nothing was committed yet
+            Ok(head) if head.is_unborn() => {}
+            Ok(_) => {
+                let tree = repo
+                    .head_commit()
+                    .map_err(|e| e.to_string())
+                    .and_then(|commit| commit.tree().map_err(|e| e.to_string()));
+                if let Err(e) = tree {
+                    problems.push(format!("The last commit cannot be read: {}", e));
+                }
+            }
+        }
+        problems
+    }
+
     /// Get the current branch name.
     pub fn current_branch(&self) -> Result<String> {
         let repo = self.repo.to_thread_local();
@@ -76,4 +603,740 @@ impl GitIntegration {
             }
         }
     }
+
+    /// Names of the local branches, in order.
+    pub fn branches(&self) -> Result<Vec<String>> {
+        let repo = self.repo.to_thread_local();
+        let failed = |e: &dyn std::fmt::Display| {
+            Error::project(format!("Failed to list the branches: {}", e))
+        };
+        let mut branches = Vec::new();
+        for reference in repo
+            .references()
+            .map_err(|e| failed(&e))?
+            .local_branches()
+            .map_err(|e| failed(&e))?
+        {
+            let reference = reference.map_err(|e| failed(&e))?;
+            branches.push(reference.name().shorten().to_string());
+        }
+        branches.sort();
+        Ok(branches)
+    }
+
+    /// Start the branch `name` at the last commit, staying on the current
+    /// branch.
+    pub fn create_branch(&self, name: &str) -> Result<()> {
+        let repo = self.repo.to_thread_local();
+        let failed = |e: &dyn std::fmt::Display| {
+            Error::project(format!("Failed to create branch {:?}: {}", name, e))
+        };
+        let branch = Self::branch_ref(name)?;
+        // Creating it again at the same commit would not fail
+        if repo
+            .try_find_reference(&branch)
+            .map_err(|e| failed(&e))?
+            .is_some()
+        {
+            return Err(Error::project(format!("Branch {:?} already exists", name)));
+        }
+        let head = repo.head_id().map_err(|e| failed(&e))?;
+        let edit = Self::update(
+            branch,
+            Target::Object(head.detach()),
+            PreviousValue::MustNotExist,
+            format!("branch: Created from {}", self.current_branch()?),
+        );
+        Self::edit_reference(&repo, edit).map_err(|e| failed(&e))?;
+        info!("Created branch {:?} at {}", name, head);
+        Ok(())
+    }
+
+    /// Switch to the branch `name`, bringing the files of the working
+    /// directory to its last commit. The changes since the last commit must
+    /// have been committed.
+    pub fn switch_branch(&self, name: &str) -> Result<()> {
+        let repo = self.repo.to_thread_local();
+        let failed = |e: &dyn std::fmt::Display| {
+            Error::project(format!("Failed to switch to branch {:?}: {}", name, e))
+        };
+        self.require_clean()?;
+        let target = Self::branch_commit(&repo, name)?;
+        let files = match repo.head_commit() {
+            Ok(commit) => Self::tree_files(&commit)?,
+            Err(_) => BTreeMap::new(),
+        };
+        Self::check_out(&repo, &files, &Self::tree_files(&target)?)?;
+
+        let edit = Self::update(
+            "HEAD".try_into().map_err(|e| failed(&e))?,
+            Target::Symbolic(Self::branch_ref(name)?),
+            PreviousValue::Any,
+            format!(
+                "checkout: moving from {} to {}",
+                self.current_branch()?,
+                name
+            ),
+        );
+        Self::edit_reference(&repo, edit).map_err(|e| failed(&e))?;
+        info!("Switched to branch {:?}", name);
+        Ok(())
+    }
+
+    /// Delete the branch `name`, which must not be the current one.
+    pub fn delete_branch(&self, name: &str) -> Result<()> {
+        let repo = self.repo.to_thread_local();
+        let failed = |e: &dyn std::fmt::Display| {
+            Error::project(format!("Failed to delete branch {:?}: {}", name, e))
+        };
+        if self.current_branch()? == name {
+            return Err(Error::project(format!(
+                "Branch {:?} is the current one and cannot be deleted",
+                name
+            )));
+        }
+        let branch = repo
+            .find_reference(&Self::branch_ref(name)?)
+            .map_err(|e| failed(&e))?;
+        let edit = RefEdit {
+            change: Change::Delete {
+                expected: PreviousValue::MustExistAndMatch(branch.target().into_owned()),
+                log: RefLog::AndReference,
+            },
+            name: branch.name().to_owned(),
+            deref: false,
+        };
+        Self::edit_reference(&repo, edit).map_err(|e| failed(&e))?;
+        info!("Deleted branch {:?}", name);
+        Ok(())
+    }
+
+    /// Merge the branch `name` into the current one.
+    ///
+    /// The current branch is moved to it when it only adds commits.
+    /// Otherwise each file changed on both since they parted is merged, a
+    /// text line by line with [`merge::merge_texts`]: without conflict the
+    /// merge is committed, else it waits for them to be resolved, then
+    /// [`complete_merge`](Self::complete_merge) commits it. A file deleted
+    /// on one branch and changed on the other, or which is not text, is
+    /// kept as it is on the current one.
+    pub fn merge_branch(&self, name: &str) -> Result<MergeOutcome> {
+        let repo = self.repo.to_thread_local();
+        let failed = |e: &dyn std::fmt::Display| {
+            Error::project(format!("Failed to merge branch {:?}: {}", name, e))
+        };
+        if let Some(draft) = self.merging()? {
+            return Err(Error::project(format!(
+                "The merge of {:?} must be completed or aborted first",
+                draft
+            )));
+        }
+        self.require_clean()?;
+        let ours = repo.head_commit().map_err(|e| failed(&e))?;
+        let theirs = Self::branch_commit(&repo, name)?;
+        let base = match repo.merge_base(ours.id, theirs.id) {
+            Ok(base) => Some(base.detach()),
+            Err(gix::repository::merge_base::Error::NotFound { .. }) => None,
+            Err(e) => return Err(failed(&e)),
+        };
+        if base == Some(theirs.id) {
+            return Ok(MergeOutcome::UpToDate);
+        }
+
+        let our_files = Self::tree_files(&ours)?;
+        let their_files = Self::tree_files(&theirs)?;
+        if base == Some(ours.id) {
+            Self::check_out(&repo, &our_files, &their_files)?;
+            let branch = repo
+                .head_name()
+                .map_err(|e| failed(&e))?
+                .ok_or_else(|| Error::project("HEAD is not on a branch"))?;
+            let edit = Self::update(
+                branch,
+                Target::Object(theirs.id),
+                PreviousValue::MustExistAndMatch(Target::Object(ours.id)),
+                format!("merge {}: Fast-forward", name),
+            );
+            Self::edit_reference(&repo, edit).map_err(|e| failed(&e))?;
+            info!("Fast-forwarded to branch {:?}", name);
+            return Ok(MergeOutcome::FastForward);
+        }
+
+        let base_files = match base {
+            Some(base) => Self::tree_files(&repo.find_commit(base).map_err(|e| failed(&e))?)?,
+            None => BTreeMap::new(),
+        };
+        let root = Self::workdir(&repo)?;
+        let content = |oid: gix::ObjectId| -> Result<Vec<u8>> {
+            let object = repo.find_object(oid).map_err(|e| failed(&e))?;
+            Ok(object.detach().data)
+        };
+        let paths: BTreeSet<&PathBuf> = our_files.keys().chain(their_files.keys()).collect();
+        let mut conflicted = Vec::new();
+        for path in paths {
+            let (before, ours, theirs) = (
+                base_files.get(path),
+                our_files.get(path),
+                their_files.get(path),
+            );
+            if ours == theirs || before == theirs {
+                continue;
+            }
+            let file = root.join(path);
+            if before == ours {
+                Self::write_file(&file, theirs.map(|&oid| content(oid)).transpose()?)?;
+                continue;
+            }
+            let (Some(&ours), Some(&theirs)) = (ours, theirs) else {
+                warn!(
+                    "{:?} was deleted on one branch and changed on the other, kept as it is",
+                    path
+                );
+                continue;
+            };
+            let texts = (
+                String::from_utf8(
+                    before
+                        .map(|&oid| content(oid))
+                        .transpose()?
+                        .unwrap_or_default(),
+                ),
+                String::from_utf8(content(ours)?),
+                String::from_utf8(content(theirs)?),
+            );
+            let (Ok(before), Ok(ours), Ok(theirs)) = texts else {
+                warn!(
+                    "{:?} changed on both branches and is not text, kept as it is",
+                    path
+                );
+                continue;
+            };
+            let merged = merge::merge_texts(&before, &ours, &theirs, name);
+            if merged.conflicts > 0 {
+                conflicted.push(path.clone());
+            }
+            Self::write_file(&file, Some(merged.text.into_bytes()))?;
+        }
+
+        let git_dir = repo.git_dir();
+        std::fs::write(git_dir.join(MERGE_HEAD), format!("{}\n", theirs.id))?;
+        std::fs::write(
+            git_dir.join(MERGE_MSG),
+            format!("Merge branch '{}'\n", name),
+        )?;
+        if !conflicted.is_empty() {
+            info!(
+                "Merging branch {:?}: {} files in conflict",
+                name,
+                conflicted.len()
+            );
+            return Ok(MergeOutcome::Conflicts(conflicted));
+        }
+        Ok(MergeOutcome::Merged(self.complete_merge()?))
+    }
+
+    /// Branch being merged into the current one, while its conflicts are
+    /// resolved; `None` when there is no merge in progress.
+    pub fn merging(&self) -> Result<Option<String>> {
+        let repo = self.repo.to_thread_local();
+        let Some(merged) = Self::merge_head(&repo)? else {
+            return Ok(None);
+        };
+        for name in self.branches()? {
+            if Self::branch_commit(&repo, &name)?.id == merged {
+                return Ok(Some(name));
+            }
+        }
+        // The branch moved or was deleted since
+        Ok(Some(merged.to_hex_with_len(7).to_string()))
+    }
+
+    /// Text files of the working directory changed since the last commit
+    /// with conflict markers in them, while a merge is in progress.
+    pub fn conflicts(&self) -> Result<Vec<ConflictedFile>> {
+        let repo = self.repo.to_thread_local();
+        if Self::merge_head(&repo)?.is_none() {
+            return Ok(Vec::new());
+        }
+        let mut files = Vec::new();
+        for change in self.changes()? {
+            let Some(Ok(text)) = change.after.map(String::from_utf8) else {
+                continue;
+            };
+            let conflicts = merge::conflicts(&text);
+            if !conflicts.is_empty() {
+                files.push(ConflictedFile {
+                    path: change.path,
+                    conflicts,
+                });
+            }
+        }
+        Ok(files)
+    }
+
+    /// Commit the merge in progress once its conflicts are resolved.
+    /// Returns the identifier of the commit.
+    pub fn complete_merge(&self) -> Result<String> {
+        let repo = self.repo.to_thread_local();
+        if Self::merge_head(&repo)?.is_none() {
+            return Err(Error::project("There is no merge in progress"));
+        }
+        let message = std::fs::read_to_string(repo.git_dir().join(MERGE_MSG))
+            .unwrap_or_else(|_| "Merge".to_string());
+        self.commit(message.trim_end())?
+            .ok_or_else(|| Error::project("The merge was not committed"))
+    }
+
+    /// Give up the merge in progress, bringing the files of the working
+    /// directory back to the last commit.
+    pub fn abort_merge(&self) -> Result<()> {
+        let repo = self.repo.to_thread_local();
+        let root = Self::workdir(&repo)?;
+        for change in self.changes()? {
+            Self::write_file(&root.join(&change.path), change.before)?;
+        }
+        Self::clear_merge(&repo)?;
+        info!("Merge aborted");
+        Ok(())
+    }
+
+    /// Fail when files changed since the last commit.
+    fn require_clean(&self) -> Result<()> {
+        match self.changes()?.len() {
+            0 => Ok(()),
+            count => Err(Error::project(format!(
+                "{} files changed since the last commit, commit them first",
+                count
+            ))),
+        }
+    }
+
+    /// Working directory of `repo`.
+    fn workdir(repo: &gix::Repository) -> Result<&Path> {
+        repo.workdir()
+            .ok_or_else(|| Error::project("The repository has no working directory"))
+    }
+
+    /// Last commit of the branch `name`.
+    fn branch_commit<'repo>(
+        repo: &'repo gix::Repository,
+        name: &str,
+    ) -> Result<gix::Commit<'repo>> {
+        let failed = |e: &dyn std::fmt::Display| {
+            Error::project(format!("Failed to read branch {:?}: {}", name, e))
+        };
+        repo.find_reference(&Self::branch_ref(name)?)
+            .map_err(|e| failed(&e))?
+            .peel_to_id()
+            .map_err(|e| failed(&e))?
+            .object()
+            .map_err(|e| failed(&e))?
+            .try_into_commit()
+            .map_err(|e| failed(&e))
+    }
+
+    /// Bring the files of the working directory from the tree `from` to
+    /// the tree `to`, both by path.
+    fn check_out(
+        repo: &gix::Repository,
+        from: &BTreeMap<PathBuf, gix::ObjectId>,
+        to: &BTreeMap<PathBuf, gix::ObjectId>,
+    ) -> Result<()> {
+        let root = Self::workdir(repo)?;
+        for (path, oid) in to {
+            if from.get(path) != Some(oid) {
+                let object = repo
+                    .find_object(*oid)
+                    .map_err(|e| Error::project(format!("Failed to read {:?}: {}", path, e)))?;
+                Self::write_file(&root.join(path), Some(object.detach().data))?;
+            }
+        }
+        for path in from.keys().filter(|path| !to.contains_key(*path)) {
+            Self::write_file(&root.join(path), None)?;
+        }
+        Ok(())
+    }
+
+    /// Write `content` to `file`, creating its folder, or delete it and
+    /// the folders it leaves empty when `None`.
+    fn write_file(file: &Path, content: Option<Vec<u8>>) -> Result<()> {
+        match content {
+            Some(content) => {
+                if let Some(dir) = file.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(file, content)?;
+            }
+            None => {
+                if file.exists() {
+                    std::fs::remove_file(file)?;
+                }
+                // Fails, and stops, at the first folder not empty
+                let mut dir = file.parent();
+                while let Some(empty) = dir.filter(|dir| std::fs::remove_dir(dir).is_ok()) {
+                    dir = empty.parent();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Commit merged into the current branch, while a merge is in progress.
+    fn merge_head(repo: &gix::Repository) -> Result<Option<gix::ObjectId>> {
+        let path = repo.git_dir().join(MERGE_HEAD);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let id = std::fs::read_to_string(&path)?;
+        gix::ObjectId::from_hex(id.trim().as_bytes())
+            .map(Some)
+            .map_err(|e| Error::project(format!("Invalid {}: {}", MERGE_HEAD, e)))
+    }
+
+    /// Remove the files telling a merge is in progress.
+    fn clear_merge(repo: &gix::Repository) -> Result<()> {
+        for file in [MERGE_HEAD, MERGE_MSG] {
+            let path = repo.git_dir().join(file);
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "git"))]
+impl GitIntegration {
+    /// Initialize a new Git repository at the specified path.
+    pub fn init<P: AsRef<Path>>(_path: P) -> Result<Self> {
+        Err(Self::unsupported())
+    }
+
+    /// Open an existing Git repository at the specified path.
+    pub fn open<P: AsRef<Path>>(_path: P) -> Result<Self> {
+        Err(Self::unsupported())
+    }
+
+    fn unsupported() -> Error {
+        Error::project("Git support was left out of this build")
+    }
+
+    /// Commit the files of the working directory with `message`.
+    pub fn commit(&self, _message: &str) -> Result<Option<String>> {
+        match self.never {}
+    }
+
+    /// Files of the working directory changed since the last commit.
+    pub fn changes(&self) -> Result<Vec<FileChange>> {
+        match self.never {}
+    }
+
+    /// Content of the file at `path` in the last commit.
+    pub fn file_at_head<P: AsRef<Path>>(&self, _path: P) -> Result<Option<Vec<u8>>> {
+        match self.never {}
+    }
+
+    /// Content of the file at `path` in the commit `id`.
+    pub fn file_at<P: AsRef<Path>>(&self, _id: &str, _path: P) -> Result<Option<Vec<u8>>> {
+        match self.never {}
+    }
+
+    /// Commits that changed the file at `path`, newest first.
+    pub fn file_history<P: AsRef<Path>>(&self, _path: P, _limit: usize) -> Result<Vec<CommitInfo>> {
+        match self.never {}
+    }
+
+    /// Identifier of the last commit, `None` if there is no commit yet.
+    pub fn head_commit_id(&self) -> Result<Option<String>> {
+        match self.never {}
+    }
+
+    /// Add a remote `name` fetching from and pushing to `url`.
+    pub fn add_remote(&self, _name: &str, _url: &str) -> Result<()> {
+        match self.never {}
+    }
+
+    /// Problems of the repository, empty if it is healthy.
+    pub fn check(&self) -> Vec<String> {
+        match self.never {}
+    }
+
+    /// Get the current branch name.
+    pub fn current_branch(&self) -> Result<String> {
+        match self.never {}
+    }
+
+    /// Names of the local branches, in order.
+    pub fn branches(&self) -> Result<Vec<String>> {
+        match self.never {}
+    }
+
+    /// Start the branch `name` at the last commit.
+    pub fn create_branch(&self, _name: &str) -> Result<()> {
+        match self.never {}
+    }
+
+    /// Switch to the branch `name`.
+    pub fn switch_branch(&self, _name: &str) -> Result<()> {
+        match self.never {}
+    }
+
+    /// Delete the branch `name`.
+    pub fn delete_branch(&self, _name: &str) -> Result<()> {
+        match self.never {}
+    }
+
+    /// Merge the branch `name` into the current one.
+    pub fn merge_branch(&self, _name: &str) -> Result<MergeOutcome> {
+        match self.never {}
+    }
+
+    /// Branch being merged into the current one, if any.
+    pub fn merging(&self) -> Result<Option<String>> {
+        match self.never {}
+    }
+
+    /// Text files with conflicts of the merge in progress.
+    pub fn conflicts(&self) -> Result<Vec<ConflictedFile>> {
+        match self.never {}
+    }
+
+    /// Commit the merge in progress.
+    pub fn complete_merge(&self) -> Result<String> {
+        match self.never {}
+    }
+
+    /// Give up the merge in progress.
+    pub fn abort_merge(&self) -> Result<()> {
+        match self.never {}
+    }
+}
+
+#[cfg(all(test, feature = "git"))]
+mod tests {
+    use super::*;
+    use cosmarium_plugin_api::branch::Side;
+
+    /// Commit `content` as the file `inn.md` of the repository at `git`,
+    /// at `seconds` since the epoch.
+    fn commit(git: &GitIntegration, content: &str, message: &str, seconds: i64) {
+        let repo = git.repo.to_thread_local();
+        let blob = repo.write_blob(content).unwrap().detach();
+        let tree = gix::objs::Tree {
+            entries: vec![gix::objs::tree::Entry {
+                mode: gix::objs::tree::EntryKind::Blob.into(),
+                filename: "inn.md".into(),
+                oid: blob,
+            }],
+        };
+        let tree = repo.write_object(&tree).unwrap().detach();
+        let time = format!("{} +0000", seconds);
+        let signature = gix::actor::SignatureRef {
+            name: "Ann".into(),
+            email: "ann@example.com".into(),
+            time: &time,
+        };
+        let parents: Vec<gix::ObjectId> = repo
+            .head_id()
+            .ok()
+            .map(|id| id.detach())
+            .into_iter()
+            .collect();
+        repo.commit_as(signature, signature, "HEAD", message, tree, parents)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_file_history_lists_the_commits_changing_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = GitIntegration::init(dir.path()).unwrap();
+        assert!(git.file_history("inn.md", 10).unwrap().is_empty());
+
+        commit(&git, "It rained.", "First draft", 1_700_000_000);
+        commit(&git, "It rained.", "Touch nothing", 1_700_000_100);
+        commit(
+            &git,
+            "It poured.",
+            "Rewrite the storm\n\nMore rain.",
+            1_700_000_200,
+        );
+
+        let history = git.file_history("inn.md", 10).unwrap();
+        let summaries: Vec<&str> = history.iter().map(|c| c.summary.as_str()).collect();
+        assert_eq!(summaries, ["Rewrite the storm", "First draft"]);
+        assert_eq!(history[0].author, "Ann");
+        assert_eq!(history[1].time.timestamp(), 1_700_000_000);
+        assert_eq!(history[1].parent, None);
+        assert!(history[0].parent.is_some());
+        assert_eq!(git.file_history("inn.md", 1).unwrap().len(), 1);
+        assert!(git.file_history("road.md", 10).unwrap().is_empty());
+
+        let first = git.file_at(&history[1].id, "inn.md").unwrap();
+        assert_eq!(first.as_deref(), Some(&b"It rained."[..]));
+        assert_eq!(git.file_at(&history[1].id, "road.md").unwrap(), None);
+        assert!(git.file_at("not a commit", "inn.md").is_err());
+    }
+
+    #[test]
+    fn test_commit_keeps_the_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = GitIntegration::init(dir.path()).unwrap();
+        std::fs::create_dir_all(dir.path().join("content/empty")).unwrap();
+        std::fs::write(dir.path().join("content/inn.md"), "It rained.").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "Rain").unwrap();
+
+        let changes = git.changes().unwrap();
+        let paths: Vec<&Path> = changes.iter().map(|c| c.path.as_path()).collect();
+        assert_eq!(paths, [Path::new("content/inn.md"), Path::new("notes.md")]);
+        assert_eq!(changes[0].before, None);
+
+        let first = git.commit("First draft").unwrap().unwrap();
+        assert!(git.changes().unwrap().is_empty());
+        assert_eq!(git.commit("Nothing").unwrap(), None);
+
+        std::fs::write(dir.path().join("content/inn.md"), "It poured.").unwrap();
+        std::fs::remove_file(dir.path().join("notes.md")).unwrap();
+        let changes = git.changes().unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].before.as_deref(), Some(&b"It rained."[..]));
+        assert_eq!(changes[0].after.as_deref(), Some(&b"It poured."[..]));
+        assert_eq!(changes[1].after, None);
+
+        git.commit("Rewrite the storm").unwrap().unwrap();
+        let history = git.file_history("content/inn.md", 10).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].summary, "Rewrite the storm");
+        assert_eq!(history[0].parent.as_deref(), Some(first.as_str()));
+        assert_eq!(
+            git.file_at(&first, "notes.md").unwrap().as_deref(),
+            Some(&b"Rain"[..])
+        );
+    }
+
+    #[test]
+    fn test_commit_leaves_ignored_files_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = GitIntegration::init(dir.path()).unwrap();
+        let write = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("content/inn.md", "It rained.");
+        write("meta/core.toon", "name: Inn");
+        write("meta/core.toon.bak", "name: Inn");
+        write("meta/core.toon.corrupt", "{{{");
+        write("meta/migrations/1/report.txt", "Migrated");
+        write(".gitignore", "*.log\nexports/\n");
+        write("build.log", "Built");
+        write("exports/inn.pdf", "PDF");
+        write("tools/build.sh", "#!/bin/sh");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let script = dir.path().join("tools/build.sh");
+            std::fs::set_permissions(script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let changes = git.changes().unwrap();
+        let paths: Vec<&Path> = changes.iter().map(|c| c.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            [
+                Path::new(".gitignore"),
+                Path::new("content/inn.md"),
+                Path::new("meta/core.toon"),
+                Path::new("tools/build.sh"),
+            ]
+        );
+        let id = git.commit("First draft").unwrap().unwrap();
+        assert!(git.changes().unwrap().is_empty());
+
+        let repo = git.repo.to_thread_local();
+        let commit = repo
+            .find_object(gix::ObjectId::from_hex(id.as_bytes()).unwrap())
+            .unwrap()
+            .into_commit();
+        assert_eq!(
+            GitIntegration::tree_files(&commit).unwrap().len(),
+            paths.len()
+        );
+        #[cfg(unix)]
+        {
+            let tree = commit.tree().unwrap();
+            let script = tree
+                .lookup_entry_by_path("tools/build.sh")
+                .unwrap()
+                .unwrap();
+            assert!(script.mode().is_executable());
+        }
+    }
+
+    #[test]
+    fn test_drafts_are_branched_and_merged_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = GitIntegration::init(dir.path()).unwrap();
+        let inn = dir.path().join("content/inn.md");
+        let write = |text: &str| std::fs::write(&inn, text).unwrap();
+        let read = || std::fs::read_to_string(&inn).unwrap();
+        std::fs::create_dir_all(dir.path().join("content")).unwrap();
+        write("One.\nTwo.\nThree.\n");
+        git.commit("First draft").unwrap();
+        let main = git.current_branch().unwrap();
+
+        git.create_branch("ending").unwrap();
+        assert!(git.create_branch("ending").is_err());
+        assert_eq!(git.branches().unwrap(), ["ending", main.as_str()]);
+        git.switch_branch("ending").unwrap();
+        assert_eq!(git.current_branch().unwrap(), "ending");
+        write("One.\nTwo.\nThree!\n");
+        std::fs::write(dir.path().join("content/road.md"), "Dust.").unwrap();
+        git.commit("Another ending").unwrap();
+
+        git.switch_branch(&main).unwrap();
+        assert_eq!(read(), "One.\nTwo.\nThree.\n");
+        assert!(!dir.path().join("content/road.md").exists());
+        write("Uno.\nTwo.\nThree.\n");
+        assert!(git.switch_branch("ending").is_err());
+        git.commit("Italian").unwrap();
+
+        let merged = git.merge_branch("ending").unwrap();
+        assert!(matches!(merged, MergeOutcome::Merged(_)));
+        assert_eq!(read(), "Uno.\nTwo.\nThree!\n");
+        assert!(dir.path().join("content/road.md").exists());
+        assert!(git.changes().unwrap().is_empty());
+        assert_eq!(git.merge_branch("ending").unwrap(), MergeOutcome::UpToDate);
+
+        // Changed differently on both
+        git.create_branch("storm").unwrap();
+        git.switch_branch("storm").unwrap();
+        write("Uno.\nZwei.\nThree!\n");
+        git.commit("German").unwrap();
+        git.switch_branch(&main).unwrap();
+        write("Uno.\nDeux.\nThree!\n");
+        git.commit("French").unwrap();
+        assert_eq!(
+            git.merge_branch("storm").unwrap(),
+            MergeOutcome::Conflicts(vec![PathBuf::from("content/inn.md")])
+        );
+        assert_eq!(git.merging().unwrap().as_deref(), Some("storm"));
+        let conflicts = git.conflicts().unwrap();
+        assert_eq!(conflicts[0].conflicts[0].draft, "Zwei.");
+        assert!(git.commit("Merge").is_err());
+        git.abort_merge().unwrap();
+        assert_eq!(git.merging().unwrap(), None);
+        assert_eq!(read(), "Uno.\nDeux.\nThree!\n");
+
+        git.merge_branch("storm").unwrap();
+        write(&merge::resolve(&read(), Side::Draft));
+        assert!(git.conflicts().unwrap().is_empty());
+        git.complete_merge().unwrap();
+        assert_eq!(git.merging().unwrap(), None);
+        let history = git.file_history("content/inn.md", 10).unwrap();
+        assert_eq!(history[0].summary, "Merge branch 'storm'");
+
+        assert!(git.delete_branch(&main).is_err());
+        git.delete_branch("storm").unwrap();
+        assert_eq!(git.branches().unwrap(), ["ending", main.as_str()]);
+    }
 }
@@ -0,0 +1,268 @@
+//! # Three-way merge of texts
+//!
+//! Merging a draft back merges each text changed on both branches line by
+//! line, from the version both started from: the passages changed on one
+//! branch only are taken as they are, and those changed differently on
+//! both are left between conflict markers, the current version first:
+//!
+//! ```text
+//! <<<<<<< current
+//! It rained.
+//! =======
+//! It poured.
+//! >>>>>>> mara-lives
+//! ```
+//!
+//! [`conflicts`] reads them back, and [`resolve`] keeps one side of each.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_core::git::merge::{conflicts, merge_texts};
+//!
+//! let base = "It rained.\nThe inn was full.\n";
+//! let current = "It rained all night.\nThe inn was full.\n";
+//! let draft = "It rained.\nThe inn was empty.\n";
+//! let merged = merge_texts(base, current, draft, "draft");
+//! assert_eq!(merged.text, "It rained all night.\nThe inn was empty.\n");
+//!
+//! let merged = merge_texts(base, current, "It poured.\nThe inn was full.\n", "draft");
+//! assert_eq!(merged.conflicts, 1);
+//! assert_eq!(conflicts(&merged.text)[0].draft, "It poured.");
+//! ```
+
+use cosmarium_plugin_api::branch::{Conflict, Side};
+use cosmarium_plugin_api::diff::{diff_lines, LineChange};
+
+/// Start of a conflict, followed by the current version.
+const CURRENT_MARKER: &str = "<<<<<<<";
+
+/// Between the two versions in conflict.
+const SEPARATOR: &str = "=======";
+
+/// End of a conflict, after the version of the draft.
+const DRAFT_MARKER: &str = ">>>>>>>";
+
+/// A text merged from two versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergedText {
+    pub text: String,
+    /// Passages left between conflict markers
+    pub conflicts: usize,
+}
+
+/// Lines `start..end` of the base replaced with `lines` on one branch.
+#[derive(Debug)]
+struct Hunk<'a> {
+    start: usize,
+    end: usize,
+    lines: Vec<&'a str>,
+}
+
+/// The passages of `base` changed in `changed`, in order.
+fn hunks<'a>(base: &'a str, changed: &'a str) -> Vec<Hunk<'a>> {
+    let mut hunks = Vec::new();
+    let mut hunk: Option<Hunk> = None;
+    let mut line = 0;
+    for change in diff_lines(base, changed) {
+        match change {
+            LineChange::Same(_) => {
+                hunks.extend(hunk.take());
+                line += 1;
+            }
+            LineChange::Removed(_) => {
+                hunk.get_or_insert_with(|| Hunk::at(line)).end = line + 1;
+                line += 1;
+            }
+            LineChange::Added(text) => hunk.get_or_insert_with(|| Hunk::at(line)).lines.push(text),
+        }
+    }
+    hunks.extend(hunk);
+    hunks
+}
+
+impl Hunk<'_> {
+    /// An empty passage before line `line`.
+    fn at(line: usize) -> Self {
+        Self {
+            start: line,
+            end: line,
+            lines: Vec::new(),
+        }
+    }
+}
+
+/// Lines `start..end` of `base` with the `hunks` in them applied.
+fn apply<'a>(base: &[&'a str], start: usize, end: usize, hunks: &[&Hunk<'a>]) -> Vec<&'a str> {
+    let mut lines = Vec::new();
+    let mut next = start;
+    for hunk in hunks {
+        lines.extend(&base[next..hunk.start]);
+        lines.extend(&hunk.lines);
+        next = hunk.end;
+    }
+    lines.extend(&base[next..end]);
+    lines
+}
+
+/// Merge the changes made from `base` in `current` and in `draft`, the
+/// branch named `draft_name`. Passages changed differently on both are left
+/// between conflict markers.
+pub fn merge_texts(base: &str, current: &str, draft: &str, draft_name: &str) -> MergedText {
+    let base_lines: Vec<&str> = base.lines().collect();
+    let mut all: Vec<(Side, Hunk)> = hunks(base, current)
+        .into_iter()
+        .map(|hunk| (Side::Current, hunk))
+        .chain(
+            hunks(base, draft)
+                .into_iter()
+                .map(|hunk| (Side::Draft, hunk)),
+        )
+        .collect();
+    all.sort_by_key(|(_, hunk)| hunk.start);
+
+    let start_marker = format!("{} current", CURRENT_MARKER);
+    let end_marker = format!("{} {}", DRAFT_MARKER, draft_name);
+    let mut lines = Vec::new();
+    let mut conflicts = 0;
+    let mut next = 0;
+    let mut i = 0;
+    while i < all.len() {
+        // The passages changed that overlap, on either branch
+        let start = all[i].1.start;
+        let mut end = all[i].1.end;
+        let mut j = i + 1;
+        while j < all.len() && (all[j].1.start < end || all[j].1.start == start) {
+            end = end.max(all[j].1.end);
+            j += 1;
+        }
+        let group = &all[i..j];
+        lines.extend(&base_lines[next..start]);
+
+        let side = |side: Side| -> Vec<&Hunk> {
+            group
+                .iter()
+                .filter(|(of, _)| *of == side)
+                .map(|(_, hunk)| hunk)
+                .collect()
+        };
+        let current = apply(&base_lines, start, end, &side(Side::Current));
+        let draft = apply(&base_lines, start, end, &side(Side::Draft));
+        let on_one_side = group.iter().all(|(side, _)| *side == group[0].0);
+        if on_one_side {
+            lines.extend(if group[0].0 == Side::Current {
+                current
+            } else {
+                draft
+            });
+        } else if current == draft {
+            lines.extend(current);
+        } else {
+            lines.push(&start_marker);
+            lines.extend(current);
+            lines.push(SEPARATOR);
+            lines.extend(draft);
+            lines.push(&end_marker);
+            conflicts += 1;
+        }
+        next = end;
+        i = j;
+    }
+    lines.extend(&base_lines[next..]);
+
+    let line_ending = if current.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut text = lines.join(line_ending);
+    if current.ends_with('\n') || current.is_empty() && draft.ends_with('\n') {
+        text.push_str(line_ending);
+    }
+    MergedText { text, conflicts }
+}
+
+/// The passages of `text` between conflict markers, in order.
+pub fn conflicts(text: &str) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    // Lines of the conflict read so far, on each side
+    let mut conflict: Option<(Vec<&str>, Vec<&str>, Side)> = None;
+    for line in text.lines() {
+        let Some((current, draft, side)) = &mut conflict else {
+            if line.starts_with(CURRENT_MARKER) {
+                conflict = Some((Vec::new(), Vec::new(), Side::Current));
+            }
+            continue;
+        };
+        match side {
+            Side::Current if line == SEPARATOR => *side = Side::Draft,
+            Side::Current => current.push(line),
+            Side::Draft if line.starts_with(DRAFT_MARKER) => {
+                conflicts.push(Conflict {
+                    current: current.join("\n"),
+                    draft: draft.join("\n"),
+                });
+                conflict = None;
+            }
+            Side::Draft => draft.push(line),
+        }
+    }
+    conflicts
+}
+
+/// `text` keeping the `side` of each passage between conflict markers.
+pub fn resolve(text: &str, side: Side) -> String {
+    let mut resolved = String::with_capacity(text.len());
+    let mut in_conflict: Option<Side> = None;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        match in_conflict {
+            None if content.starts_with(CURRENT_MARKER) => in_conflict = Some(Side::Current),
+            Some(Side::Current) if content == SEPARATOR => in_conflict = Some(Side::Draft),
+            Some(Side::Draft) if content.starts_with(DRAFT_MARKER) => in_conflict = None,
+            Some(of) if of != side => {}
+            _ => resolved.push_str(line),
+        }
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_on_one_side_are_taken() {
+        let base = "One.\nTwo.\nThree.\n";
+        let merged = merge_texts(base, "Zero.\nOne.\nTwo.\nThree.\n", "One.\nTwo.\n", "draft");
+        assert_eq!(merged.text, "Zero.\nOne.\nTwo.\n");
+        assert_eq!(merged.conflicts, 0);
+
+        // The same change on both sides
+        let merged = merge_texts(base, "One.\n2.\nThree.\n", "One.\n2.\nThree.\n", "draft");
+        assert_eq!(merged.text, "One.\n2.\nThree.\n");
+    }
+
+    #[test]
+    fn test_conflicts_are_marked_and_resolved() {
+        let base = "One.\r\nTwo.\r\nThree.\r\n";
+        let current = "One.\r\nDeux.\r\nThree.\r\n";
+        let draft = "One.\r\nZwei.\r\nDrei.\r\n";
+        let merged = merge_texts(base, current, draft, "german");
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.text,
+            "One.\r\n<<<<<<< current\r\nDeux.\r\nThree.\r\n=======\r\nZwei.\r\nDrei.\r\n>>>>>>> german\r\n"
+        );
+        assert_eq!(
+            conflicts(&merged.text),
+            [Conflict {
+                current: "Deux.\nThree.".to_string(),
+                draft: "Zwei.\nDrei.".to_string(),
+            }]
+        );
+        assert_eq!(resolve(&merged.text, Side::Current), current);
+        assert_eq!(resolve(&merged.text, Side::Draft), draft);
+        assert!(conflicts(current).is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+//! # Writing goals
+//!
+//! Word targets for the writing session, the day and the project, and the
+//! progress made towards them.
+//!
+//! Progress counts the words *written*, that is the changes of the word count
+//! of the document being edited: opening a long chapter counts nothing, and
+//! deleting a paragraph takes its words back. The [`WritingLog`] keeps the
+//! words written each day, in each project and in each document across
+//! sessions, while [`WritingGoals`] turns the successive word counts of the
+//! active document into entries of the log and tells when a target is
+//! reached.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_core::config::GoalsConfig;
+//! use cosmarium_core::goals::{GoalKind, WritingGoals, WritingLog};
+//! use chrono::NaiveDate;
+//! use uuid::Uuid;
+//!
+//! let mut log = WritingLog::default();
+//! let mut goals = WritingGoals::new(GoalsConfig { session_words: 5, ..GoalsConfig::default() });
+//! let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+//! let chapter = Uuid::new_v4();
+//!
+//! // The chapter already has 1000 words: opening it writes none
+//! assert!(goals.record(&mut log, chapter, None, 1000, today).is_empty());
+//! assert_eq!(
+//!     goals.record(&mut log, chapter, None, 1006, today),
+//!     vec![GoalKind::Session]
+//! );
+//! assert_eq!(log.day(today), 6);
+//! ```
+
+use crate::config::GoalsConfig;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+pub use cosmarium_plugin_api::history::WritingLog;
+
+/// Key of the project's word target in the project's custom settings.
+pub const PROJECT_WORD_TARGET_KEY: &str = "project_word_target";
+
+/// Key of the date the project's word target is due, in the project's
+/// custom settings.
+pub const PROJECT_DEADLINE_KEY: &str = "project_deadline";
+
+/// What a word target applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GoalKind {
+    /// Words written since the application started
+    Session,
+    /// Words written today, in any project
+    Day,
+    /// Words written in the open project, over all sessions
+    Project,
+}
+
+impl GoalKind {
+    /// All kinds, in the order they are shown.
+    pub const ALL: [GoalKind; 3] = [GoalKind::Session, GoalKind::Day, GoalKind::Project];
+
+    /// Name to show to the user.
+    pub fn label(self) -> &'static str {
+        match self {
+            GoalKind::Session => "Session",
+            GoalKind::Day => "Today",
+            GoalKind::Project => "Project",
+        }
+    }
+}
+
+/// Progress towards a word target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GoalProgress {
+    /// What the target applies to
+    pub kind: GoalKind,
+    /// Words written so far, negative if more were deleted
+    pub written: i64,
+    /// Words to write
+    pub target: usize,
+}
+
+impl GoalProgress {
+    /// Progress from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        (self.written.max(0) as f32 / self.target.max(1) as f32).min(1.0)
+    }
+
+    /// Whether the target is reached.
+    pub fn is_reached(&self) -> bool {
+        self.written >= self.target as i64
+    }
+
+    /// Words to write each day, `today` included, to reach the target by
+    /// the end of `deadline`. `None` once it is reached or the deadline
+    /// passed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::goals::{GoalKind, GoalProgress};
+    /// use chrono::NaiveDate;
+    ///
+    /// let progress = GoalProgress { kind: GoalKind::Project, written: 400, target: 1000 };
+    /// let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+    /// let deadline = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+    /// assert_eq!(progress.daily_pace(today, deadline), Some(150));
+    /// ```
+    pub fn daily_pace(&self, today: NaiveDate, deadline: NaiveDate) -> Option<usize> {
+        let days = (deadline - today).num_days() + 1;
+        if self.is_reached() || days <= 0 {
+            return None;
+        }
+        let left = self.target as i64 - self.written;
+        Some(((left + days - 1) / days) as usize)
+    }
+}
+
+/// Targets of the session, the day and the open project, and the words
+/// written this session.
+#[derive(Debug, Clone, Default)]
+pub struct WritingGoals {
+    /// Session and daily targets
+    config: GoalsConfig,
+    /// Open project, and its target (0 for none)
+    project: Option<(PathBuf, usize)>,
+    /// Document of the last count, and that count
+    last_count: Option<(Uuid, usize)>,
+    /// Words written this session
+    session_words: i64,
+    /// Targets already reached, with the day they were reached
+    reached: Vec<(GoalKind, NaiveDate)>,
+}
+
+impl WritingGoals {
+    /// Start a session with the targets of `config`.
+    pub fn new(config: GoalsConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Change the session and daily targets.
+    pub fn set_config(&mut self, config: GoalsConfig) {
+        self.config = config;
+    }
+
+    /// Count the words written from now on in `project`, whose target is
+    /// `target` words (0 for none), or in no project.
+    pub fn set_project(&mut self, project: Option<PathBuf>, target: usize) {
+        let changed = self.project.as_ref().map(|(path, _)| path) != project.as_ref();
+        if changed {
+            self.last_count = None;
+            self.reached.retain(|(kind, _)| *kind != GoalKind::Project);
+        }
+        self.project = project.map(|path| (path, target));
+    }
+
+    /// Take the next count of each document as a reference, e.g. after the
+    /// rules of the word count changed.
+    pub fn forget_counts(&mut self) {
+        self.last_count = None;
+    }
+
+    /// Words written this session.
+    pub fn session_words(&self) -> i64 {
+        self.session_words
+    }
+
+    /// Record that `document`, saved at `path` if it is, now counts `words`
+    /// words, and log the words written since its last count. The first
+    /// count of a document only serves as a reference.
+    ///
+    /// Returns the targets this reached for the first time on `today`.
+    pub fn record(
+        &mut self,
+        log: &mut WritingLog,
+        document: Uuid,
+        path: Option<&Path>,
+        words: usize,
+        today: NaiveDate,
+    ) -> Vec<GoalKind> {
+        let written = match self.last_count.replace((document, words)) {
+            Some((last, count)) if last == document => words as i64 - count as i64,
+            _ => 0,
+        };
+        if written == 0 {
+            return Vec::new();
+        }
+        self.session_words += written;
+        let project = self.project.as_ref().map(|(path, _)| path.as_path());
+        log.record(today, project, path, written);
+
+        // Targets count as reached when these words cross them, once a day
+        // for the daily target and once a session for the others
+        let mut reached = Vec::new();
+        for progress in self.progress(log, today) {
+            let crossed =
+                progress.is_reached() && progress.written - written < progress.target as i64;
+            let known = self.reached.iter().any(|(kind, day)| {
+                *kind == progress.kind && (*kind != GoalKind::Day || *day == today)
+            });
+            if crossed && !known {
+                self.reached.push((progress.kind, today));
+                reached.push(progress.kind);
+            }
+        }
+        reached
+    }
+
+    /// Progress towards the targets that are set, in the order of
+    /// [`GoalKind::ALL`].
+    pub fn progress(&self, log: &WritingLog, today: NaiveDate) -> Vec<GoalProgress> {
+        GoalKind::ALL
+            .into_iter()
+            .filter_map(|kind| {
+                let (written, target) = match kind {
+                    GoalKind::Session => (self.session_words, self.config.session_words),
+                    GoalKind::Day => (log.day(today), self.config.daily_words),
+                    GoalKind::Project => {
+                        let (path, target) = self.project.as_ref()?;
+                        (log.project(path), *target)
+                    }
+                };
+                (target > 0).then_some(GoalProgress {
+                    kind,
+                    written,
+                    target,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(n: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, n).unwrap()
+    }
+
+    #[test]
+    fn test_only_changes_count() {
+        let mut log = WritingLog::default();
+        let mut goals = WritingGoals::default();
+        goals.set_project(Some(PathBuf::from("/novel")), 0);
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let one = Path::new("/novel/one.md");
+        goals.record(&mut log, first, Some(one), 1000, day(1));
+        goals.record(&mut log, first, Some(one), 1050, day(1));
+        // Switching documents writes nothing, deleting takes words back
+        goals.record(&mut log, second, None, 300, day(1));
+        goals.record(&mut log, second, None, 290, day(1));
+
+        assert_eq!(goals.session_words(), 40);
+        assert_eq!(log.day(day(1)), 40);
+        assert_eq!(log.project(Path::new("/novel")), 40);
+        assert_eq!(
+            log.documents(Path::new("/novel"))[0],
+            (Path::new("one.md"), 50)
+        );
+    }
+
+    #[test]
+    fn test_targets_are_celebrated_once() {
+        let config = GoalsConfig {
+            session_words: 0,
+            daily_words: 10,
+            celebrate: true,
+        };
+        let mut log = WritingLog::default();
+        let mut goals = WritingGoals::new(config);
+        goals.set_project(Some(PathBuf::from("/novel")), 15);
+        let chapter = Uuid::new_v4();
+
+        goals.record(&mut log, chapter, None, 0, day(1));
+        assert_eq!(
+            goals.record(&mut log, chapter, None, 12, day(1)),
+            vec![GoalKind::Day]
+        );
+        goals.record(&mut log, chapter, None, 5, day(1));
+        assert!(goals.record(&mut log, chapter, None, 14, day(1)).is_empty());
+        assert_eq!(
+            goals.record(&mut log, chapter, None, 16, day(1)),
+            vec![GoalKind::Project]
+        );
+
+        // A new day has a new target
+        assert!(goals.record(&mut log, chapter, None, 20, day(2)).is_empty());
+        assert_eq!(
+            goals.record(&mut log, chapter, None, 26, day(2)),
+            vec![GoalKind::Day]
+        );
+
+        let progress = goals.progress(&log, day(2));
+        assert_eq!(progress.len(), 2);
+        assert_eq!(progress[1].written, 26);
+        assert_eq!(progress[1].fraction(), 1.0);
+
+        // The next session goes past a target reached in the last one
+        let mut goals = WritingGoals::new(config);
+        goals.set_project(Some(PathBuf::from("/novel")), 15);
+        goals.record(&mut log, chapter, None, 26, day(2));
+        assert!(goals.record(&mut log, chapter, None, 30, day(2)).is_empty());
+    }
+
+    #[test]
+    fn test_log_survives_a_round_trip() {
+        let mut log = WritingLog::default();
+        log.record(
+            day(1),
+            Some(Path::new("/novel")),
+            Some(Path::new("/novel/a.md")),
+            120,
+        );
+        log.record(day(2), None, None, -20);
+
+        let json = serde_json::to_string(&log).unwrap();
+        let read: WritingLog = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, log);
+        assert_eq!(read.day(day(2)), -20);
+        assert_eq!(read.project(Path::new("/novel")), 120);
+    }
+}
@@ -0,0 +1,1092 @@
+//! # Document import for Cosmarium Core
+//!
+//! Brings chapters drafted in word processors into a project. Word (`.docx`)
+//! and OpenDocument (`.odt`) files are converted to Markdown, keeping their
+//! headings, bold and italic text, bulleted and numbered lists, line breaks
+//! and footnotes, and open as new documents. Fonts, colors, comments and
+//! page layout are left behind.
+//!
+//! Whole folders of drafts are imported with [`folder`].
+
+pub mod folder;
+
+use crate::document::{DocumentFormat, DocumentManager};
+use crate::{Error, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+use std::path::Path;
+use uuid::Uuid;
+
+/// Word processor formats that can be imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImportFormat {
+    /// Office Open XML, as written by Microsoft Word
+    Docx,
+    /// OpenDocument text, as written by LibreOffice
+    Odt,
+}
+
+impl ImportFormat {
+    /// All formats, in file dialog order.
+    pub const ALL: [ImportFormat; 2] = [Self::Docx, Self::Odt];
+
+    /// The format of a file, from its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        Self::ALL
+            .into_iter()
+            .find(|format| extension.eq_ignore_ascii_case(format.extension()))
+    }
+
+    /// Extension of the files, without the dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Docx => "docx",
+            Self::Odt => "odt",
+        }
+    }
+
+    /// Human-readable name for file dialogs.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Docx => "Word document",
+            Self::Odt => "OpenDocument text",
+        }
+    }
+}
+
+/// A word processor document converted to Markdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedDocument {
+    /// Its first heading, or the name of its file
+    pub title: String,
+    pub markdown: String,
+}
+
+/// Convert a `.docx` or `.odt` file to Markdown.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, is not in a supported
+/// format, or is damaged.
+pub fn import_file(path: &Path) -> Result<ImportedDocument> {
+    let format = ImportFormat::from_path(path).ok_or_else(|| {
+        Error::document(format!(
+            "Cannot import {:?}: not a Word or OpenDocument file",
+            path
+        ))
+    })?;
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+    let (blocks, notes) = match format {
+        ImportFormat::Docx => read_docx(&mut archive)?,
+        ImportFormat::Odt => read_odt(&mut archive)?,
+    };
+
+    let title = blocks
+        .iter()
+        .find_map(|block| match block {
+            Block::Heading { text, .. } => Some(text.plain_text()),
+            _ => None,
+        })
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .replace('_', " ")
+        });
+    tracing::info!("Imported {:?} ({} blocks)", path, blocks.len());
+    Ok(ImportedDocument {
+        title,
+        markdown: to_markdown(&blocks, &notes),
+    })
+}
+
+/// Import a `.docx` or `.odt` file as a new Markdown document of `manager`
+/// and return its id.
+///
+/// The document has no file yet.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be imported (see [`import_file`]) or
+/// the document cannot be created.
+pub async fn import_document(manager: &mut DocumentManager, path: &Path) -> Result<Uuid> {
+    let imported = import_file(path)?;
+    manager
+        .create_document(
+            &imported.title,
+            &imported.markdown,
+            DocumentFormat::Markdown,
+        )
+        .await
+}
+
+/// A block of an imported document.
+#[derive(Debug)]
+enum Block {
+    Heading {
+        level: usize,
+        text: Inline,
+    },
+    Paragraph(Inline),
+    ListItem {
+        depth: usize,
+        ordered: bool,
+        text: Inline,
+    },
+}
+
+/// Emphasis of a piece of text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Emphasis {
+    bold: bool,
+    italic: bool,
+}
+
+impl Emphasis {
+    fn with(self, other: Emphasis) -> Self {
+        Self {
+            bold: self.bold || other.bold,
+            italic: self.italic || other.italic,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Span {
+    Text(String, Emphasis),
+    /// Markdown written as is, such as a footnote reference
+    Markdown(String),
+}
+
+/// Text of a paragraph, as it is read.
+#[derive(Debug, Default)]
+struct Inline {
+    spans: Vec<Span>,
+}
+
+impl Inline {
+    fn push_text(&mut self, text: &str, emphasis: Emphasis) {
+        if let Some(Span::Text(last, last_emphasis)) = self.spans.last_mut() {
+            if *last_emphasis == emphasis {
+                last.push_str(text);
+                return;
+            }
+        }
+        self.spans.push(Span::Text(text.to_string(), emphasis));
+    }
+
+    fn push_markdown(&mut self, markdown: &str) {
+        self.spans.push(Span::Markdown(markdown.to_string()));
+    }
+
+    fn line_break(&mut self) {
+        self.push_markdown("\\\n");
+    }
+
+    fn append(&mut self, other: Inline) {
+        if !self.spans.is_empty() {
+            self.line_break();
+        }
+        self.spans.extend(other.spans);
+    }
+
+    /// The text without its formatting.
+    fn plain_text(&self) -> String {
+        let text: String = self
+            .spans
+            .iter()
+            .filter_map(|span| match span {
+                Span::Text(text, _) => Some(text.as_str()),
+                Span::Markdown(_) => None,
+            })
+            .collect();
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+        for span in &self.spans {
+            match span {
+                Span::Markdown(text) => markdown.push_str(text),
+                Span::Text(text, emphasis) => {
+                    let marker = match (emphasis.bold, emphasis.italic) {
+                        (true, true) => "***",
+                        (true, false) => "**",
+                        (false, true) => "*",
+                        (false, false) => "",
+                    };
+                    // Markers must touch the words they emphasize
+                    let body = text.trim();
+                    if marker.is_empty() || body.is_empty() {
+                        markdown.push_str(&escape(text));
+                        continue;
+                    }
+                    let start = text.len() - text.trim_start().len();
+                    markdown.push_str(&text[..start]);
+                    markdown.push_str(marker);
+                    markdown.push_str(&escape(body));
+                    markdown.push_str(marker);
+                    markdown.push_str(&text[start + body.len()..]);
+                }
+            }
+        }
+        markdown.trim().to_string()
+    }
+}
+
+/// Escape the characters of `text` that Markdown would read as formatting.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '*' | '_' | '`' | '[' | ']' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            // Whitespace would start code blocks
+            '\t' | '\n' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape the start of a paragraph that would be read as another block.
+fn escape_block_start(text: String) -> String {
+    if text.starts_with(['#', '>', '+', '-']) {
+        return format!("\\{}", text);
+    }
+    let digits = text.bytes().take_while(u8::is_ascii_digit).count();
+    match text.as_bytes().get(digits) {
+        Some(b'.' | b')') if digits > 0 => format!("{}\\{}", &text[..digits], &text[digits..]),
+        _ => text,
+    }
+}
+
+/// Write `blocks` and their footnotes in Markdown.
+fn to_markdown(blocks: &[Block], notes: &[String]) -> String {
+    let mut markdown = String::new();
+    // Content column and last number of the open lists, by depth
+    let mut lists: Vec<(usize, usize)> = Vec::new();
+    let mut after_item = false;
+
+    for block in blocks {
+        let text = match block {
+            Block::Heading { text, .. } | Block::Paragraph(text) | Block::ListItem { text, .. } => {
+                text.to_markdown()
+            }
+        };
+        if text.is_empty() {
+            continue;
+        }
+        let is_item = matches!(block, Block::ListItem { .. });
+        if !markdown.is_empty() {
+            markdown.push_str(if after_item && is_item { "\n" } else { "\n\n" });
+        }
+        after_item = is_item;
+
+        match block {
+            Block::Heading { level, .. } => {
+                lists.clear();
+                markdown.push_str(&"#".repeat((*level).clamp(1, 6)));
+                markdown.push(' ');
+                markdown.push_str(&text);
+            }
+            Block::Paragraph(_) => {
+                lists.clear();
+                markdown.push_str(&escape_block_start(text));
+            }
+            Block::ListItem { depth, ordered, .. } => {
+                let depth = (*depth).min(lists.len());
+                lists.truncate(depth + 1);
+                let offset = match depth {
+                    0 => 0,
+                    _ => lists[depth - 1].0,
+                };
+                let number = match lists.get(depth) {
+                    Some((_, number)) => number + 1,
+                    None => 1,
+                };
+                let marker = if *ordered {
+                    format!("{}.", number)
+                } else {
+                    "-".to_string()
+                };
+                let column = offset + marker.len() + 1;
+                lists.truncate(depth);
+                lists.push((column, number));
+
+                markdown.push_str(&" ".repeat(offset));
+                markdown.push_str(&marker);
+                markdown.push(' ');
+                markdown.push_str(&indent(&text, column));
+            }
+        }
+    }
+
+    for (i, note) in notes.iter().enumerate() {
+        markdown.push_str(if i == 0 { "\n\n" } else { "\n" });
+        markdown.push_str(&format!("[^{}]: {}", i + 1, indent(note, 4)));
+    }
+    if !markdown.is_empty() {
+        markdown.push('\n');
+    }
+    markdown
+}
+
+/// Indent the lines of `text` after the first by `width` spaces.
+fn indent(text: &str, width: usize) -> String {
+    let mut lines = text.lines();
+    let mut indented = lines.next().unwrap_or_default().to_string();
+    for line in lines {
+        indented.push('\n');
+        if !line.is_empty() {
+            indented.push_str(&" ".repeat(width));
+            indented.push_str(line);
+        }
+    }
+    indented
+}
+
+/// Text of the file `name` of `archive`, if it has one.
+fn read_part<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> Result<Option<String>> {
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    Ok(Some(text))
+}
+
+/// Value of the attribute `name` of `element`, whatever its namespace.
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name.as_bytes())
+        .and_then(|a| a.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+/// An XML event reduced to what the readers need.
+enum Node<'a> {
+    Start(BytesStart<'a>),
+    /// An element without content, such as a tab
+    Empty(BytesStart<'a>),
+    End(Vec<u8>),
+    Text(String),
+}
+
+/// Go through the XML `xml`, calling `visit` on each element and text.
+fn walk(xml: &str, mut visit: impl FnMut(Node)) -> Result<()> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| Error::document(format!("Damaged document: {}", e)))?;
+        match event {
+            Event::Start(e) => visit(Node::Start(e)),
+            Event::Empty(e) => visit(Node::Empty(e)),
+            Event::End(e) => visit(Node::End(e.local_name().as_ref().to_vec())),
+            Event::Text(e) => {
+                let text = e
+                    .unescape()
+                    .map_err(|e| Error::document(format!("Damaged document: {}", e)))?;
+                visit(Node::Text(text.into_owned()));
+            }
+            Event::CData(e) => visit(Node::Text(String::from_utf8_lossy(&e).into_owned())),
+            Event::Eof => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+/// Whether a Word on/off property such as `<w:b w:val="0"/>` is on.
+fn docx_flag(element: &BytesStart) -> bool {
+    !matches!(
+        attribute(element, "val").as_deref(),
+        Some("0" | "false" | "off" | "none")
+    )
+}
+
+/// A style of a Word document.
+#[derive(Debug, Default)]
+struct DocxStyle {
+    heading: Option<usize>,
+    emphasis: Emphasis,
+}
+
+/// A paragraph of a Word document.
+#[derive(Debug, Default)]
+struct DocxParagraph {
+    style: Option<String>,
+    /// Numbering instance and level of a list item
+    numbering: Option<(String, usize)>,
+    text: Inline,
+}
+
+/// Styles of a Word document (`word/styles.xml`), by id.
+fn docx_styles(xml: &str) -> Result<HashMap<String, DocxStyle>> {
+    let mut styles = HashMap::new();
+    let mut current: Option<(String, DocxStyle)> = None;
+    walk(xml, |node| match node {
+        Node::Start(e) | Node::Empty(e) => match e.local_name().as_ref() {
+            b"style" => {
+                current = attribute(&e, "styleId").map(|id| (id, DocxStyle::default()));
+            }
+            b"name" => {
+                if let (Some((_, style)), Some(name)) = (&mut current, attribute(&e, "val")) {
+                    let name = name.to_lowercase();
+                    if name == "title" {
+                        style.heading = Some(1);
+                    } else if let Some(level) = name.strip_prefix("heading ") {
+                        style.heading = level.trim().parse().ok();
+                    }
+                }
+            }
+            b"outlineLvl" => {
+                if let Some((_, style)) = &mut current {
+                    let level = attribute(&e, "val").and_then(|v| v.parse::<usize>().ok());
+                    if let Some(level) = level.filter(|level| *level < 9) {
+                        style.heading.get_or_insert(level + 1);
+                    }
+                }
+            }
+            b"b" => {
+                if let Some((_, style)) = &mut current {
+                    style.emphasis.bold = docx_flag(&e);
+                }
+            }
+            b"i" => {
+                if let Some((_, style)) = &mut current {
+                    style.emphasis.italic = docx_flag(&e);
+                }
+            }
+            _ => {}
+        },
+        Node::End(name) if name == b"style" => {
+            if let Some((id, style)) = current.take() {
+                styles.insert(id, style);
+            }
+        }
+        _ => {}
+    })?;
+    Ok(styles)
+}
+
+/// Whether the levels of the lists of a Word document
+/// (`word/numbering.xml`) are numbered, by numbering instance and level.
+fn docx_numbering(xml: &str) -> Result<HashMap<(String, usize), bool>> {
+    let mut abstract_levels: HashMap<(String, usize), bool> = HashMap::new();
+    let mut instances: Vec<(String, String)> = Vec::new();
+    let mut abstract_id = None;
+    let mut level = 0;
+    let mut instance = None;
+    walk(xml, |node| {
+        if let Node::Start(e) | Node::Empty(e) = node {
+            match e.local_name().as_ref() {
+                b"abstractNum" => abstract_id = attribute(&e, "abstractNumId"),
+                b"lvl" => {
+                    level = attribute(&e, "ilvl")
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(0)
+                }
+                b"numFmt" => {
+                    if let Some(id) = &abstract_id {
+                        let ordered = !matches!(
+                            attribute(&e, "val").as_deref(),
+                            Some("bullet" | "none") | None
+                        );
+                        abstract_levels.insert((id.clone(), level), ordered);
+                    }
+                }
+                b"num" => {
+                    abstract_id = None;
+                    instance = attribute(&e, "numId");
+                }
+                b"abstractNumId" => {
+                    if let (Some(instance), Some(id)) = (instance.take(), attribute(&e, "val")) {
+                        instances.push((instance, id));
+                    }
+                }
+                _ => {}
+            }
+        }
+    })?;
+
+    let mut numbering = HashMap::new();
+    for (instance, id) in instances {
+        for ((abstract_id, level), ordered) in &abstract_levels {
+            if *abstract_id == id {
+                numbering.insert((instance.clone(), *level), *ordered);
+            }
+        }
+    }
+    Ok(numbering)
+}
+
+/// Paragraphs of a Word document part, with the id of the footnote holding
+/// them. Footnote references are numbered in the order of `references`,
+/// which gets the ids of the footnotes met.
+fn docx_paragraphs(
+    xml: &str,
+    styles: &HashMap<String, DocxStyle>,
+    references: &mut Vec<String>,
+) -> Result<Vec<(Option<String>, DocxParagraph)>> {
+    let mut paragraphs = Vec::new();
+    let mut footnote = None;
+    let mut paragraph: Option<DocxParagraph> = None;
+    let mut in_run = false;
+    let mut in_text = false;
+    let mut run_emphasis = Emphasis::default();
+    walk(xml, |node| match node {
+        Node::Start(e) | Node::Empty(e) => {
+            let Some(p) = paragraph.as_mut() else {
+                match e.local_name().as_ref() {
+                    b"footnote" => footnote = attribute(&e, "id"),
+                    b"p" => paragraph = Some(DocxParagraph::default()),
+                    _ => {}
+                }
+                return;
+            };
+            match e.local_name().as_ref() {
+                b"pStyle" => p.style = attribute(&e, "val"),
+                b"ilvl" => {
+                    let level = attribute(&e, "val").and_then(|v| v.parse().ok());
+                    let numbering = p.numbering.get_or_insert_with(Default::default);
+                    numbering.1 = level.unwrap_or(0);
+                }
+                b"numId" => {
+                    let id = attribute(&e, "val").unwrap_or_default();
+                    p.numbering.get_or_insert_with(Default::default).0 = id;
+                }
+                b"r" => {
+                    in_run = true;
+                    run_emphasis = Emphasis::default();
+                }
+                b"rStyle" if in_run => {
+                    if let Some(style) = attribute(&e, "val").and_then(|id| styles.get(&id)) {
+                        run_emphasis = run_emphasis.with(style.emphasis);
+                    }
+                }
+                b"b" if in_run => run_emphasis.bold = docx_flag(&e),
+                b"i" if in_run => run_emphasis.italic = docx_flag(&e),
+                b"t" if in_run => in_text = true,
+                b"tab" if in_run => p.text.push_text(" ", run_emphasis),
+                // Page and column breaks are dropped
+                b"br" | b"cr"
+                    if in_run
+                        && attribute(&e, "type").is_none_or(|kind| kind == "textWrapping") =>
+                {
+                    p.text.line_break()
+                }
+                b"footnoteReference" => {
+                    if let Some(id) = attribute(&e, "id") {
+                        let number = match references.iter().position(|r| *r == id) {
+                            Some(i) => i + 1,
+                            None => {
+                                references.push(id);
+                                references.len()
+                            }
+                        };
+                        p.text.push_markdown(&format!("[^{}]", number));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Node::Text(text) if in_text => {
+            if let Some(p) = paragraph.as_mut() {
+                p.text.push_text(&text, run_emphasis);
+            }
+        }
+        Node::End(name) => match name.as_slice() {
+            b"t" => in_text = false,
+            b"r" => in_run = false,
+            b"p" => {
+                if let Some(p) = paragraph.take() {
+                    paragraphs.push((footnote.clone(), p));
+                }
+            }
+            b"footnote" => footnote = None,
+            _ => {}
+        },
+        _ => {}
+    })?;
+    Ok(paragraphs)
+}
+
+/// Read the blocks and footnotes of a Word document.
+fn read_docx<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+) -> Result<(Vec<Block>, Vec<String>)> {
+    let document = read_part(archive, "word/document.xml")?
+        .ok_or_else(|| Error::document("Not a Word document: word/document.xml is missing"))?;
+    let styles = match read_part(archive, "word/styles.xml")? {
+        Some(xml) => docx_styles(&xml)?,
+        None => HashMap::new(),
+    };
+    let numbering = match read_part(archive, "word/numbering.xml")? {
+        Some(xml) => docx_numbering(&xml)?,
+        None => HashMap::new(),
+    };
+
+    let mut references = Vec::new();
+    let mut blocks = Vec::new();
+    for (_, paragraph) in docx_paragraphs(&document, &styles, &mut references)? {
+        let style = paragraph.style.as_ref().and_then(|id| styles.get(id));
+        let heading = style.and_then(|style| style.heading).or_else(|| {
+            // Documents without styles part
+            let id = paragraph.style.as_deref()?.to_lowercase();
+            id.strip_prefix("heading")?.parse().ok()
+        });
+        let list = paragraph
+            .numbering
+            .filter(|(id, _)| !id.is_empty() && id != "0");
+
+        let mut text = paragraph.text;
+        if let Some(level) = heading {
+            blocks.push(Block::Heading { level, text });
+            continue;
+        }
+        if let Some(style) = style.filter(|s| s.emphasis != Emphasis::default()) {
+            for span in &mut text.spans {
+                if let Span::Text(_, emphasis) = span {
+                    *emphasis = emphasis.with(style.emphasis);
+                }
+            }
+        }
+        blocks.push(match list {
+            Some((id, depth)) => Block::ListItem {
+                depth,
+                ordered: numbering.get(&(id, depth)).copied().unwrap_or(false),
+                text,
+            },
+            None => Block::Paragraph(text),
+        });
+    }
+
+    let mut notes = vec![String::new(); references.len()];
+    if let Some(xml) = read_part(archive, "word/footnotes.xml")? {
+        let mut unused = Vec::new();
+        for (id, paragraph) in docx_paragraphs(&xml, &styles, &mut unused)? {
+            let Some(i) = id.and_then(|id| references.iter().position(|r| *r == id)) else {
+                continue;
+            };
+            let text = paragraph.text.to_markdown();
+            if !text.is_empty() {
+                if !notes[i].is_empty() {
+                    notes[i].push_str("\n\n");
+                }
+                notes[i].push_str(&text);
+            }
+        }
+    }
+    Ok((blocks, notes))
+}
+
+/// Emphasis of the text styles of an OpenDocument file, by name.
+fn odt_styles(xml: &str, styles: &mut HashMap<String, (Option<String>, Emphasis)>) -> Result<()> {
+    let mut current: Option<String> = None;
+    walk(xml, |node| {
+        let (e, empty) = match node {
+            Node::Start(e) => (e, false),
+            Node::Empty(e) => (e, true),
+            Node::End(name) if name == b"style" => {
+                current = None;
+                return;
+            }
+            _ => return,
+        };
+        match e.local_name().as_ref() {
+            b"style" => {
+                let name = attribute(&e, "name");
+                if let Some(name) = &name {
+                    styles.insert(
+                        name.clone(),
+                        (attribute(&e, "parent-style-name"), Emphasis::default()),
+                    );
+                }
+                current = if empty { None } else { name };
+            }
+            b"text-properties" => {
+                if let Some((_, emphasis)) = current.as_ref().and_then(|n| styles.get_mut(n)) {
+                    if let Some(weight) = attribute(&e, "font-weight") {
+                        emphasis.bold = weight == "bold"
+                            || weight.parse::<u32>().is_ok_and(|weight| weight >= 600);
+                    }
+                    if let Some(style) = attribute(&e, "font-style") {
+                        emphasis.italic = matches!(style.as_str(), "italic" | "oblique");
+                    }
+                }
+            }
+            _ => {}
+        }
+    })
+}
+
+/// Whether the levels of the list styles of an OpenDocument file are
+/// numbered, by list style name and level (from 1).
+fn odt_list_styles(xml: &str, lists: &mut HashMap<(String, usize), bool>) -> Result<()> {
+    let mut current: Option<String> = None;
+    walk(xml, |node| {
+        if let Node::Start(e) | Node::Empty(e) = node {
+            let ordered = match e.local_name().as_ref() {
+                b"list-style" => {
+                    current = attribute(&e, "name");
+                    return;
+                }
+                b"list-level-style-number" => true,
+                b"list-level-style-bullet" | b"list-level-style-image" => false,
+                _ => return,
+            };
+            if let Some(name) = &current {
+                let level = attribute(&e, "level").and_then(|v| v.parse().ok());
+                lists.insert((name.clone(), level.unwrap_or(1)), ordered);
+            }
+        }
+    })
+}
+
+/// Emphasis of the style `name`, with the one it inherits.
+fn odt_emphasis(styles: &HashMap<String, (Option<String>, Emphasis)>, name: &str) -> Emphasis {
+    let mut emphasis = Emphasis::default();
+    let mut name = Some(name);
+    // Bounded, in case of a loop in the style chain
+    for _ in 0..8 {
+        let Some((parent, own)) = name.and_then(|name| styles.get(name)) else {
+            break;
+        };
+        emphasis = emphasis.with(*own);
+        name = parent.as_deref();
+    }
+    emphasis
+}
+
+/// Where a paragraph of an OpenDocument file is being read.
+#[derive(Debug)]
+struct OdtParagraph {
+    heading: Option<usize>,
+    /// Emphasis of the open spans, the paragraph's own first
+    emphasis: Vec<Emphasis>,
+    text: Inline,
+}
+
+/// Read the blocks and footnotes of an OpenDocument text.
+fn read_odt<R: Read + Seek>(archive: &mut zip::ZipArchive<R>) -> Result<(Vec<Block>, Vec<String>)> {
+    let content = read_part(archive, "content.xml")?
+        .ok_or_else(|| Error::document("Not an OpenDocument file: content.xml is missing"))?;
+    let mut styles = HashMap::new();
+    let mut list_styles = HashMap::new();
+    if let Some(xml) = read_part(archive, "styles.xml")? {
+        odt_styles(&xml, &mut styles)?;
+        odt_list_styles(&xml, &mut list_styles)?;
+    }
+    odt_styles(&content, &mut styles)?;
+    odt_list_styles(&content, &mut list_styles)?;
+
+    let mut blocks = Vec::new();
+    let mut notes = Vec::new();
+    // Paragraphs being read: a note's paragraphs stack on the one citing it
+    let mut paragraphs: Vec<OdtParagraph> = Vec::new();
+    let mut note_bodies: Vec<Vec<String>> = Vec::new();
+    // List style of the open lists, and whether their current item has a block
+    let mut lists: Vec<(Option<String>, bool)> = Vec::new();
+    let mut skipped = 0usize;
+    let mut in_body = false;
+
+    walk(&content, |node| match node {
+        Node::Start(_) if skipped > 0 => skipped += 1,
+        Node::Empty(_) if skipped > 0 => {}
+        Node::Start(e) => match e.local_name().as_ref() {
+            b"text" => in_body = true,
+            b"note-citation" | b"annotation" | b"tracked-changes" | b"sequence-decls" => {
+                skipped = 1;
+            }
+            b"list" if in_body => {
+                let style = attribute(&e, "style-name")
+                    .or_else(|| lists.last().and_then(|(style, _)| style.clone()));
+                lists.push((style, false));
+            }
+            b"list-item" => {
+                if let Some(list) = lists.last_mut() {
+                    list.1 = false;
+                }
+            }
+            b"p" | b"h" if in_body => {
+                let heading = (e.local_name().as_ref() == b"h").then(|| {
+                    attribute(&e, "outline-level")
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(1)
+                });
+                let emphasis = match (heading, attribute(&e, "style-name")) {
+                    (None, Some(style)) => odt_emphasis(&styles, &style),
+                    _ => Emphasis::default(),
+                };
+                paragraphs.push(OdtParagraph {
+                    heading,
+                    emphasis: vec![emphasis],
+                    text: Inline::default(),
+                });
+            }
+            b"span" => {
+                if let Some(p) = paragraphs.last_mut() {
+                    let outer = p.emphasis.last().copied().unwrap_or_default();
+                    let own = attribute(&e, "style-name")
+                        .map(|style| odt_emphasis(&styles, &style))
+                        .unwrap_or_default();
+                    p.emphasis.push(outer.with(own));
+                }
+            }
+            // Endnotes become footnotes too, Markdown having no others
+            b"note" => {
+                if let Some(p) = paragraphs.last_mut() {
+                    let number = notes.len() + note_bodies.len() + 1;
+                    p.text.push_markdown(&format!("[^{}]", number));
+                }
+                note_bodies.push(Vec::new());
+            }
+            _ => {}
+        },
+        Node::Empty(e) => {
+            let Some(p) = paragraphs.last_mut() else {
+                return;
+            };
+            let emphasis = p.emphasis.last().copied().unwrap_or_default();
+            match e.local_name().as_ref() {
+                b"s" => {
+                    let count = attribute(&e, "c").and_then(|v| v.parse().ok()).unwrap_or(1);
+                    p.text.push_text(&" ".repeat(count), emphasis);
+                }
+                b"tab" => p.text.push_text(" ", emphasis),
+                b"line-break" => p.text.line_break(),
+                _ => {}
+            }
+        }
+        Node::Text(text) if skipped == 0 => {
+            if let Some(p) = paragraphs.last_mut() {
+                let emphasis = p.emphasis.last().copied().unwrap_or_default();
+                p.text.push_text(&text, emphasis);
+            }
+        }
+        Node::End(_) if skipped > 0 => skipped -= 1,
+        Node::End(name) => match name.as_slice() {
+            b"text" => in_body = false,
+            b"span" => {
+                if let Some(p) = paragraphs.last_mut() {
+                    if p.emphasis.len() > 1 {
+                        p.emphasis.pop();
+                    }
+                }
+            }
+            b"p" | b"h" => {
+                let Some(p) = paragraphs.pop() else {
+                    return;
+                };
+                if let Some(body) = note_bodies.last_mut() {
+                    body.push(p.text.to_markdown());
+                    return;
+                }
+                let depth = lists.len().saturating_sub(1);
+                let block = match (p.heading, lists.last_mut()) {
+                    (Some(level), _) => Block::Heading {
+                        level,
+                        text: p.text,
+                    },
+                    (None, Some((_, true))) => {
+                        // A further paragraph of the same list item
+                        if let Some(Block::ListItem { text, .. }) = blocks.last_mut() {
+                            text.append(p.text);
+                            return;
+                        }
+                        Block::Paragraph(p.text)
+                    }
+                    (None, Some((style, started))) => {
+                        *started = true;
+                        let ordered = style
+                            .as_ref()
+                            .and_then(|style| list_styles.get(&(style.clone(), depth + 1)))
+                            .copied()
+                            .unwrap_or(false);
+                        Block::ListItem {
+                            depth,
+                            ordered,
+                            text: p.text,
+                        }
+                    }
+                    (None, None) => Block::Paragraph(p.text),
+                };
+                blocks.push(block);
+            }
+            b"list" => {
+                lists.pop();
+            }
+            b"note" => {
+                if let Some(body) = note_bodies.pop() {
+                    let body: Vec<String> = body.into_iter().filter(|p| !p.is_empty()).collect();
+                    notes.push(body.join("\n\n"));
+                }
+            }
+            _ => {}
+        },
+        _ => {}
+    })?;
+    Ok((blocks, notes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Write a zip archive holding `parts` to `path`.
+    fn write_archive(path: &Path, parts: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        for (name, content) in parts {
+            zip.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_import_docx() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chapter_one.docx");
+        let w = r#"xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main""#;
+        let document = format!(
+            r#"<w:document {w}><w:body>
+            <w:p><w:pPr><w:pStyle w:val="Titre1"/></w:pPr><w:r><w:t>The Inn</w:t></w:r></w:p>
+            <w:p><w:r><w:t xml:space="preserve">It was </w:t></w:r><w:r><w:rPr><w:i/></w:rPr><w:t>late </w:t></w:r><w:r><w:rPr><w:b/><w:b w:val="0"/></w:rPr><w:t>&amp; dark_</w:t></w:r><w:r><w:footnoteReference w:id="2"/></w:r><w:r><w:t>.</w:t></w:r></w:p>
+            <w:p><w:pPr><w:numPr><w:ilvl w:val="0"/><w:numId w:val="1"/></w:numPr></w:pPr><w:r><w:rPr><w:rStyle w:val="Strong"/></w:rPr><w:t>Bread</w:t></w:r></w:p>
+            <w:p><w:pPr><w:numPr><w:ilvl w:val="1"/><w:numId w:val="1"/></w:numPr></w:pPr><w:r><w:t>Rye</w:t><w:br/><w:t>or wheat</w:t></w:r></w:p>
+            <w:p><w:pPr><w:numPr><w:ilvl w:val="1"/><w:numId w:val="1"/></w:numPr></w:pPr><w:r><w:t>Oats</w:t></w:r></w:p>
+            <w:p/>
+            <w:p><w:r><w:t>- not a list</w:t></w:r></w:p>
+            </w:body></w:document>"#
+        );
+        let styles = format!(
+            r#"<w:styles {w}>
+            <w:style w:styleId="Titre1"><w:name w:val="heading 1"/><w:rPr><w:b/></w:rPr></w:style>
+            <w:style w:styleId="Strong"><w:name w:val="Strong"/><w:rPr><w:b/></w:rPr></w:style>
+            </w:styles>"#
+        );
+        let numbering = format!(
+            r#"<w:numbering {w}>
+            <w:abstractNum w:abstractNumId="7">
+            <w:lvl w:ilvl="0"><w:numFmt w:val="bullet"/></w:lvl>
+            <w:lvl w:ilvl="1"><w:numFmt w:val="decimal"/></w:lvl>
+            </w:abstractNum>
+            <w:num w:numId="1"><w:abstractNumId w:val="7"/></w:num>
+            </w:numbering>"#
+        );
+        let footnotes = format!(
+            r#"<w:footnotes {w}>
+            <w:footnote w:type="separator" w:id="-1"><w:p><w:r><w:separator/></w:r></w:p></w:footnote>
+            <w:footnote w:id="2"><w:p><w:r><w:footnoteRef/></w:r><w:r><w:t xml:space="preserve"> A true story.</w:t></w:r></w:p></w:footnote>
+            </w:footnotes>"#
+        );
+        write_archive(
+            &path,
+            &[
+                ("word/document.xml", &document),
+                ("word/styles.xml", &styles),
+                ("word/numbering.xml", &numbering),
+                ("word/footnotes.xml", &footnotes),
+            ],
+        );
+
+        let imported = import_file(&path).unwrap();
+        assert_eq!(imported.title, "The Inn");
+        assert_eq!(
+            imported.markdown,
+            "# The Inn\n\n\
+             It was *late* & dark\\_[^1].\n\n\
+             - **Bread**\n  \
+               1. Rye\\\n     or wheat\n  \
+               2. Oats\n\n\
+             \\- not a list\n\n\
+             [^1]: A true story.\n"
+        );
+    }
+
+    #[test]
+    fn test_import_odt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.odt");
+        let ns = r#"xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0""#;
+        let content = format!(
+            r#"<office:document-content {ns}>
+            <office:automatic-styles>
+            <style:style style:name="T1" style:family="text"><style:text-properties fo:font-weight="bold"/></style:style>
+            <style:style style:name="T2" style:family="text" style:parent-style-name="T1"><style:text-properties fo:font-style="italic"/></style:style>
+            <text:list-style style:name="L1">
+            <text:list-level-style-number text:level="1"/>
+            <text:list-level-style-bullet text:level="2"/>
+            </text:list-style>
+            </office:automatic-styles>
+            <office:body><office:text>
+            <text:sequence-decls><text:sequence-decl text:name="Table"/></text:sequence-decls>
+            <text:p>Before the heading</text:p>
+            <text:h text:outline-level="2">Day  one</text:h>
+            <text:p>She <text:span text:style-name="T1">ran</text:span>,<text:s text:c="2"/><text:span text:style-name="T2">fast</text:span><text:note text:note-class="footnote"><text:note-citation>1</text:note-citation><text:note-body><text:p>Very fast.</text:p><text:p>Really.</text:p></text:note-body></text:note>.<office:annotation><text:p>Check this</text:p></office:annotation></text:p>
+            <text:list text:style-name="L1">
+            <text:list-item><text:p>First</text:p><text:p>More of it</text:p>
+            <text:list><text:list-item><text:p>Inner</text:p></text:list-item></text:list>
+            </text:list-item>
+            <text:list-item><text:p>Second</text:p></text:list-item>
+            </text:list>
+            </office:text></office:body></office:document-content>"#
+        );
+        write_archive(&path, &[("content.xml", &content)]);
+
+        let imported = import_file(&path).unwrap();
+        assert_eq!(imported.title, "Day one");
+        assert_eq!(
+            imported.markdown,
+            "Before the heading\n\n\
+             ## Day  one\n\n\
+             She **ran**,  ***fast***[^1].\n\n\
+             1. First\\\n   More of it\n   \
+                - Inner\n\
+             2. Second\n\n\
+             [^1]: Very fast.\n\n    Really.\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_document() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("draft_two.odt");
+        write_archive(
+            &path,
+            &[(
+                "content.xml",
+                "<document><body><text><p>Hello.</p></text></body></document>",
+            )],
+        );
+
+        let event_bus = std::sync::Arc::new(tokio::sync::RwLock::new(crate::EventBus::new()));
+        let mut manager = DocumentManager::new();
+        manager.initialize(event_bus).await.unwrap();
+        let id = import_document(&mut manager, &path).await.unwrap();
+        let document = manager.get_document(id).unwrap();
+        assert_eq!(document.title(), "draft two");
+        assert_eq!(document.content(), "Hello.\n");
+        assert_eq!(document.format(), DocumentFormat::Markdown);
+
+        assert!(import_file(&dir.path().join("draft.doc")).is_err());
+        std::fs::write(dir.path().join("broken.docx"), "not a zip").unwrap();
+        assert!(import_file(&dir.path().join("broken.docx")).is_err());
+    }
+}
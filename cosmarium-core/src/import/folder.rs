@@ -0,0 +1,400 @@
+//! # Importing folders
+//!
+//! A folder of Markdown and text files, such as drafts written in another
+//! editor, is copied under the content directory of a project, its
+//! subfolders becoming folders of the binder. Word and OpenDocument files
+//! met on the way are converted to Markdown. Each new document is titled
+//! after its first heading, or after its file name when it has none.
+//!
+//! A [`FolderWatch`] keeps following the folder afterwards: new and changed
+//! files are imported again when [`sync_folder`] runs. The import is one
+//! way: edits made in the project are never written back, and files
+//! deleted from the folder stay in the project.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use cosmarium_core::import::folder::{import_folder, sync_folder, FolderWatch};
+//! use cosmarium_core::project::Project;
+//! use std::path::Path;
+//!
+//! # async fn example() -> cosmarium_core::Result<()> {
+//! let mut project = Project::load("Novels/The Inn").await?;
+//! let source = Path::new("Drafts/The Inn");
+//! let imported = import_folder(&mut project, source)?;
+//! println!("{} documents in {}", imported.documents.len(), imported.target);
+//!
+//! let watch = FolderWatch::new(source, &imported.target)?;
+//! if watch.take_changes() {
+//!     sync_folder(&mut project, watch.source(), watch.target())?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{import_file, ImportFormat};
+use crate::project::Project;
+use crate::{Error, Result};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Key of the folders the project follows, in the project's custom
+/// settings.
+pub const WATCHED_FOLDERS_KEY: &str = "watched_folders";
+
+/// Extensions of the files copied as they are.
+const TEXT_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// Time without changes in a watched folder before they are reported, so
+/// that files are imported once they are fully written.
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// A folder imported into a project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderImport {
+    /// Content folder the files went to, relative to the project root
+    pub target: String,
+    /// Documents written, relative to the project root
+    pub documents: Vec<String>,
+}
+
+/// A folder followed by a project, as stored under
+/// [`WATCHED_FOLDERS_KEY`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchedFolder {
+    /// The folder imported from
+    pub source: PathBuf,
+    /// Content folder it is imported to, relative to the project root
+    pub target: String,
+}
+
+/// Import the folder `source` into a new folder of the project's content
+/// directory, named after it.
+///
+/// # Errors
+///
+/// Returns an error if `source` is not a folder, holds the project, or
+/// cannot be copied.
+pub fn import_folder(project: &mut Project, source: &Path) -> Result<FolderImport> {
+    if !source.is_dir() {
+        return Err(Error::project(format!(
+            "{} is not a folder",
+            source.display()
+        )));
+    }
+    if project.path().starts_with(source) {
+        return Err(Error::project(
+            "A folder holding the project cannot be imported into it",
+        ));
+    }
+    let name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Imported".to_string());
+
+    // Never mix the files with those of an existing folder
+    let mut target = format!("content/{}", name);
+    let mut copy = 2;
+    while project.path().join(&target).exists() {
+        target = format!("content/{}_{}", name, copy);
+        copy += 1;
+    }
+    std::fs::create_dir_all(project.path().join(&target))?;
+
+    let documents = sync_folder(project, source, &target)?;
+    tracing::info!(
+        "Imported {} documents from {:?} to {}",
+        documents.len(),
+        source,
+        target
+    );
+    Ok(FolderImport { target, documents })
+}
+
+/// Copy the files of `source` that are new or changed since the last
+/// import to the content folder `target` of the project, and add them to
+/// the structure.
+///
+/// Hidden files and folders are left out. Returns the documents written,
+/// relative to the project root.
+///
+/// # Errors
+///
+/// Returns an error if `target` is outside the content directory, or if a
+/// file cannot be copied or converted.
+pub fn sync_folder(project: &mut Project, source: &Path, target: &str) -> Result<Vec<String>> {
+    let outside = target.split('/').any(|part| part == "..");
+    if outside || (target != "content" && !target.starts_with("content/")) {
+        return Err(Error::project(format!(
+            "{} is not in the content directory",
+            target
+        )));
+    }
+    let root = project.path().to_path_buf();
+    let entries = walkdir::WalkDir::new(source)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !is_hidden(entry.file_name()));
+
+    let mut written = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| Error::project(format!("Failed to import folder: {}", e)))?;
+        let Ok(rel) = entry.path().strip_prefix(source) else {
+            continue;
+        };
+        let dest = root.join(target).join(rel);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&dest)?;
+            continue;
+        }
+
+        let extension = entry
+            .path()
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+        let (dest, title) = if TEXT_EXTENSIONS.contains(&extension.as_str()) {
+            if !is_newer(entry.path(), &dest) {
+                continue;
+            }
+            std::fs::copy(entry.path(), &dest)?;
+            let text = std::fs::read_to_string(&dest).unwrap_or_default();
+            (dest, title_of(&text, entry.path()))
+        } else if ImportFormat::from_path(entry.path()).is_some() {
+            let dest = dest.with_extension("md");
+            if !is_newer(entry.path(), &dest) {
+                continue;
+            }
+            let imported = import_file(entry.path())?;
+            std::fs::write(&dest, imported.markdown)?;
+            (dest, imported.title)
+        } else {
+            continue;
+        };
+
+        let key = format!(
+            "{}/{}",
+            target,
+            dest.strip_prefix(root.join(target))
+                .unwrap_or(&dest)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        );
+        written.push((key, title));
+    }
+
+    // Title the new documents only, keeping those the author renamed
+    let new: Vec<_> = written
+        .iter()
+        .filter(|(key, _)| project.structure().find_by_path(key).is_none())
+        .cloned()
+        .collect();
+    project.sync_structure();
+    for (key, title) in new {
+        let Some(id) = project.structure().find_by_path(&key).map(|node| node.id) else {
+            continue;
+        };
+        if let Some(node) = project.structure_mut().find_mut(id) {
+            node.title = title;
+        }
+    }
+    Ok(written.into_iter().map(|(key, _)| key).collect())
+}
+
+/// Title of a document: its first heading, or else its file name with
+/// dashes and underscores read as spaces.
+pub fn title_of(text: &str, path: &Path) -> String {
+    text.lines()
+        .map(str::trim_start)
+        .find_map(|line| {
+            let heading = line.trim_start_matches('#');
+            let level = line.len() - heading.len();
+            ((1..=6).contains(&level) && heading.starts_with(' '))
+                .then(|| heading.trim().trim_end_matches('#').trim().to_string())
+        })
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .replace(['-', '_'], " ")
+                .trim()
+                .to_string()
+        })
+}
+
+/// Whether a file or folder name is hidden, like `.git`.
+fn is_hidden(name: &std::ffi::OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
+}
+
+/// Whether `source` was modified after `dest`, or `dest` does not exist.
+fn is_newer(source: &Path, dest: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(source), modified(dest)) {
+        (Some(source), Some(dest)) => source > dest,
+        (_, None) => true,
+        (None, Some(_)) => false,
+    }
+}
+
+/// Watch over a folder imported into a project.
+///
+/// Dropping the watch stops it.
+pub struct FolderWatch {
+    source: PathBuf,
+    target: String,
+    last_change: Arc<Mutex<Option<Instant>>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl std::fmt::Debug for FolderWatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FolderWatch")
+            .field("source", &self.source)
+            .field("target", &self.target)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FolderWatch {
+    /// Watch `source`, imported to the content folder `target`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the folder cannot be watched.
+    pub fn new(source: &Path, target: &str) -> Result<Self> {
+        let last_change = Arc::new(Mutex::new(None));
+        let changed = Arc::clone(&last_change);
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) if !event.kind.is_access() => {
+                    if let Ok(mut last) = changed.lock() {
+                        *last = Some(Instant::now());
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Folder watch error: {}", e),
+            })?;
+        watcher.watch(source, RecursiveMode::Recursive)?;
+        Ok(Self {
+            source: source.to_path_buf(),
+            target: target.to_string(),
+            last_change,
+            _watcher: watcher,
+        })
+    }
+
+    /// The folder watched.
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    /// Content folder the files are imported to, relative to the project
+    /// root.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Whether files changed in the folder since the last call, once it
+    /// has been quiet for a moment.
+    pub fn take_changes(&self) -> bool {
+        let Ok(mut last) = self.last_change.lock() else {
+            return false;
+        };
+        match *last {
+            Some(at) if at.elapsed() >= SETTLE_TIME => {
+                *last = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_title_of() {
+        let path = Path::new("drafts/the_old-inn.md");
+        assert_eq!(title_of("Intro\n\n## The Inn ##\ntext", path), "The Inn");
+        assert_eq!(title_of("#hashtag\ntext", path), "the old inn");
+        assert_eq!(title_of("", path), "the old inn");
+    }
+
+    #[test]
+    fn test_import_folder_keeps_hierarchy() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("Drafts");
+        std::fs::create_dir_all(source.join("part1")).unwrap();
+        std::fs::create_dir_all(source.join(".obsidian")).unwrap();
+        std::fs::write(source.join("part1/arrival.md"), "# Arrival\n\nThe inn.").unwrap();
+        std::fs::write(source.join("notes_on_names.txt"), "Ada, Bram").unwrap();
+        std::fs::write(source.join("cover.png"), "png").unwrap();
+        std::fs::write(source.join(".obsidian/app.md"), "{}").unwrap();
+
+        let mut project = Project::new("inn", temp_dir.path().join("inn"), "novel").unwrap();
+        let imported = import_folder(&mut project, &source).unwrap();
+        assert_eq!(imported.target, "content/Drafts");
+        assert_eq!(
+            imported.documents,
+            vec![
+                "content/Drafts/notes_on_names.txt".to_string(),
+                "content/Drafts/part1/arrival.md".to_string(),
+            ]
+        );
+
+        let structure = project.structure();
+        let folder = structure.find_by_path("content/Drafts/part1").unwrap();
+        assert_eq!(folder.children[0].title, "Arrival");
+        let notes = structure
+            .find_by_path("content/Drafts/notes_on_names.txt")
+            .unwrap();
+        assert_eq!(notes.title, "notes on names");
+        assert!(structure.find_by_path("content/Drafts/.obsidian").is_none());
+
+        // Importing again makes a second folder
+        let again = import_folder(&mut project, &source).unwrap();
+        assert_eq!(again.target, "content/Drafts_2");
+    }
+
+    #[test]
+    fn test_sync_folder_imports_changes_only() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("Drafts");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("one.md"), "# One").unwrap();
+
+        let mut project = Project::new("inn", temp_dir.path().join("inn"), "novel").unwrap();
+        let imported = import_folder(&mut project, &source).unwrap();
+        let id = project
+            .structure()
+            .find_by_path("content/Drafts/one.md")
+            .unwrap()
+            .id;
+        project.structure_mut().find_mut(id).unwrap().title = "Prologue".to_string();
+
+        std::fs::write(source.join("two.md"), "# Two").unwrap();
+        let written = sync_folder(&mut project, &source, &imported.target).unwrap();
+        assert_eq!(written, vec!["content/Drafts/two.md".to_string()]);
+        let structure = project.structure();
+        assert_eq!(structure.find(id).unwrap().title, "Prologue");
+        assert_eq!(
+            structure
+                .find_by_path("content/Drafts/two.md")
+                .unwrap()
+                .title,
+            "Two"
+        );
+        assert!(sync_folder(&mut project, &source, "../elsewhere").is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! # Keyboard shortcuts
+//!
+//! The shortcuts the user rebound are kept in `keymap.toml`, next to
+//! `config.toml` in the configuration directory. Commands missing from the
+//! file keep the default shortcut they were registered with, so that new
+//! commands and new defaults reach users who customized a few shortcuts.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use cosmarium_core::keymap::{self, Keymap};
+//! use cosmarium_plugin_api::{Command, Shortcut};
+//!
+//! let reflow = Command {
+//!     id: "editor.reflow".to_string(),
+//!     title: "Reflow Paragraph".to_string(),
+//!     default_shortcut: Some(Shortcut::new("Q").with_alt()),
+//! };
+//! let mut keymap = keymap::load()?;
+//! keymap.bind(&reflow, Some(Shortcut::ctrl("Q").with_shift()));
+//! keymap::save(&keymap)?;
+//! # Ok::<(), cosmarium_core::Error>(())
+//! ```
+
+use crate::{Config, Error, Result};
+use std::path::{Path, PathBuf};
+
+pub use cosmarium_plugin_api::command::Keymap;
+
+/// Name of the keymap file in the configuration directory.
+const KEYMAP_FILE: &str = "keymap.toml";
+
+/// Path of the user's keymap.
+///
+/// # Errors
+///
+/// Returns an error if the config directory cannot be determined.
+pub fn default_path() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join(KEYMAP_FILE))
+}
+
+/// Read the user's keymap, empty if the user rebound nothing yet.
+///
+/// # Errors
+///
+/// Returns an error if the keymap exists but cannot be read.
+pub fn load() -> Result<Keymap> {
+    load_from_file(default_path()?)
+}
+
+/// Save the user's keymap.
+///
+/// # Errors
+///
+/// Returns an error if the keymap cannot be written.
+pub fn save(keymap: &Keymap) -> Result<()> {
+    save_to_file(keymap, default_path()?)
+}
+
+/// Read a keymap from `path`, empty if there is no such file.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, or holds a shortcut that
+/// cannot be parsed.
+pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Keymap> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(Keymap::default());
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| Error::config(format!("Failed to read keymap: {}", e)))?;
+    toml::from_str(&content).map_err(|e| Error::config(format!("Failed to parse keymap: {}", e)))
+}
+
+/// Write `keymap` to `path`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn save_to_file<P: AsRef<Path>>(keymap: &Keymap, path: P) -> Result<()> {
+    let content = toml::to_string_pretty(keymap)
+        .map_err(|e| Error::config(format!("Failed to serialize keymap: {}", e)))?;
+    if let Some(parent) = path.as_ref().parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| Error::config(format!("Failed to create config directory: {}", e)))?;
+    }
+    std::fs::write(path, content)
+        .map_err(|e| Error::config(format!("Failed to write keymap: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmarium_plugin_api::{Command, Shortcut};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_keymap_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("cosmarium").join(KEYMAP_FILE);
+        assert_eq!(load_from_file(&path).unwrap(), Keymap::default());
+
+        let undo = Command {
+            id: "editor.undo".to_string(),
+            title: "Undo".to_string(),
+            default_shortcut: Some(Shortcut::ctrl("Z")),
+        };
+        let mut keymap = Keymap::default();
+        keymap.bind(&undo, Some(Shortcut::ctrl("U").with_alt()));
+        save_to_file(&keymap, &path).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains(r#""editor.undo" = "Ctrl+Alt+U""#));
+        assert_eq!(load_from_file(&path).unwrap(), keymap);
+
+        std::fs::write(&path, "[bindings]\n\"editor.undo\" = \"Hyper+U\"\n").unwrap();
+        assert!(load_from_file(&path).is_err());
+    }
+}
@@ -11,12 +11,16 @@ use crate::{events::EventBus, Error, Result};
 use cosmarium_plugin_api::{Event, EventType, Panel, PanelPosition};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+/// Text scales a panel can be zoomed to.
+pub const PANEL_ZOOM_RANGE: RangeInclusive<f32> = 0.5..=2.0;
+
 /// Layout management system for Cosmarium.
 ///
 /// The [`LayoutManager`] handles all aspects of UI layout including panel
@@ -374,6 +378,127 @@ impl LayoutManager {
         self.current_layout.get_panel_mut(panel_id)
     }
 
+    /// Move a panel's tab to a side of the workspace and select it there.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the panel's plugin
+    /// * `position` - Side receiving the tab
+    /// * `index` - Place among the side's tabs, at the end if `None`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::layout::LayoutManager;
+    /// use cosmarium_plugin_api::PanelPosition;
+    ///
+    /// let mut manager = LayoutManager::new();
+    /// manager.move_panel("outline", PanelPosition::Right, None);
+    /// assert_eq!(manager.current_layout().panel_side("outline"), Some(PanelPosition::Right));
+    /// ```
+    pub fn move_panel(&mut self, name: &str, position: PanelPosition, index: Option<usize>) {
+        self.current_layout.move_panel(name, position, index);
+        self.emit_layout_changed();
+    }
+
+    /// Pop a panel out of its side into its own window.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the panel's plugin
+    /// * `settings` - Size, position and level of the window
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::layout::{LayoutManager, WindowSettings};
+    ///
+    /// let mut manager = LayoutManager::new();
+    /// manager.float_panel("outline", WindowSettings::floating());
+    /// assert!(manager.current_layout().floating_panel("outline").is_some());
+    /// ```
+    pub fn float_panel(&mut self, name: &str, settings: WindowSettings) {
+        self.current_layout.float_panel(name, settings);
+        self.emit_layout_changed();
+    }
+
+    /// Scale the text of a panel, apart from the rest of the interface.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::layout::LayoutManager;
+    ///
+    /// let mut manager = LayoutManager::new();
+    /// manager.set_panel_zoom("outline", 0.8);
+    /// assert_eq!(manager.current_layout().panel_zoom("outline"), 0.8);
+    /// assert_eq!(manager.current_layout().panel_zoom("notes"), 1.0);
+    /// ```
+    pub fn set_panel_zoom(&mut self, name: &str, zoom: f32) {
+        self.current_layout.set_panel_zoom(name, zoom);
+        self.emit_layout_changed();
+    }
+
+    /// Select the tab of a panel on its side of the workspace.
+    ///
+    /// # Returns
+    ///
+    /// True if the panel has a tab, false otherwise.
+    pub fn select_panel(&mut self, name: &str) -> bool {
+        let selected = self.current_layout.select_panel(name);
+        if selected {
+            self.emit_layout_changed();
+        }
+        selected
+    }
+
+    /// Switch to the panels and arrangement of another activity.
+    ///
+    /// The arrangement being left is saved under the name of its activity.
+    /// An activity switched to for the first time starts from the current
+    /// arrangement with its default panels open.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the arrangement being left cannot be saved.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::layout::{Activity, LayoutManager};
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut manager = LayoutManager::new();
+    /// manager.switch_activity(Activity::Drafting).await?;
+    /// assert_eq!(manager.current_layout().activity(), Activity::Drafting);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # });
+    /// ```
+    pub async fn switch_activity(&mut self, activity: Activity) -> Result<()> {
+        let leaving = self.current_layout.activity();
+        if leaving == activity {
+            return Ok(());
+        }
+        self.save_layout(leaving.layout_name()).await?;
+
+        let name = self.current_layout.name().to_string();
+        let mut layout = match self.saved_layouts.get(activity.layout_name()) {
+            Some(layout) => layout.clone(),
+            None => {
+                let mut layout = self.current_layout.clone();
+                layout.set_open_panels(activity.default_panels().iter().copied());
+                layout
+            }
+        };
+        layout.set_name(&name);
+        layout.set_activity(activity);
+        self.current_layout = layout;
+        self.emit_layout_changed();
+
+        info!("Switched to {} activity", activity.name());
+        Ok(())
+    }
+
     /// Save the current layout with a name.
     ///
     /// # Arguments
@@ -590,6 +715,21 @@ pub struct Layout {
     window_settings: WindowSettings,
     /// Custom properties
     properties: HashMap<String, serde_json::Value>,
+    /// Tabbed panels sharing each side of the workspace
+    #[serde(default)]
+    groups: HashMap<PanelPosition, PanelGroup>,
+    /// Panels popped out of their side into their own window
+    #[serde(default)]
+    floating: HashMap<String, WindowSettings>,
+    /// Activity the layout is arranged for
+    #[serde(default)]
+    activity: Activity,
+    /// Panels open in this layout, if the layout keeps them
+    #[serde(default)]
+    open_panels: Option<Vec<String>>,
+    /// Text scale of the panels that don't use the global one, by name
+    #[serde(default)]
+    panel_zoom: HashMap<String, f32>,
 }
 
 impl Layout {
@@ -614,6 +754,11 @@ impl Layout {
             panels: HashMap::new(),
             window_settings: WindowSettings::default(),
             properties: HashMap::new(),
+            groups: HashMap::new(),
+            floating: HashMap::new(),
+            activity: Activity::default(),
+            open_panels: None,
+            panel_zoom: HashMap::new(),
         }
     }
 
@@ -675,6 +820,156 @@ impl Layout {
             .collect()
     }
 
+    /// Get the tabs sharing a side of the workspace.
+    pub fn panel_group(&self, position: PanelPosition) -> Option<&PanelGroup> {
+        self.groups.get(&position)
+    }
+
+    /// Get the side of the workspace holding a panel's tab.
+    pub fn panel_side(&self, name: &str) -> Option<PanelPosition> {
+        self.groups
+            .iter()
+            .find(|(_, group)| group.contains(name))
+            .map(|(position, _)| *position)
+    }
+
+    /// Give a panel a tab on a side unless it already has one elsewhere or
+    /// floats in its own window.
+    ///
+    /// # Returns
+    ///
+    /// The side holding the panel's tab, or `None` if the panel floats.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::layout::Layout;
+    /// use cosmarium_plugin_api::PanelPosition;
+    ///
+    /// let mut layout = Layout::default();
+    /// layout.move_panel("outline", PanelPosition::Right, None);
+    /// assert_eq!(layout.dock_panel("outline", PanelPosition::Left), Some(PanelPosition::Right));
+    /// ```
+    pub fn dock_panel(&mut self, name: &str, position: PanelPosition) -> Option<PanelPosition> {
+        if self.floating.contains_key(name) {
+            return None;
+        }
+        if let Some(side) = self.panel_side(name) {
+            return Some(side);
+        }
+        let group = self.groups.entry(position).or_default();
+        group.tabs.push(name.to_string());
+        if group.active.is_none() {
+            group.active = Some(name.to_string());
+        }
+        Some(position)
+    }
+
+    /// Move a panel's tab to a side of the workspace and select it there.
+    ///
+    /// The tab is inserted at `index` among the side's tabs, or appended when
+    /// `index` is `None` or past the end.
+    pub fn move_panel(&mut self, name: &str, position: PanelPosition, index: Option<usize>) {
+        self.floating.remove(name);
+        for group in self.groups.values_mut() {
+            group.remove(name);
+        }
+        let group = self.groups.entry(position).or_default();
+        let index = index.unwrap_or(group.tabs.len()).min(group.tabs.len());
+        group.tabs.insert(index, name.to_string());
+        group.active = Some(name.to_string());
+    }
+
+    /// Select the tab of a panel on its side of the workspace.
+    ///
+    /// # Returns
+    ///
+    /// True if the panel has a tab, false otherwise.
+    pub fn select_panel(&mut self, name: &str) -> bool {
+        match self.groups.values_mut().find(|group| group.contains(name)) {
+            Some(group) => {
+                group.active = Some(name.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pop a panel out of its side into its own window.
+    ///
+    /// A panel already floating keeps its window and takes the new settings.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::layout::{Layout, WindowSettings};
+    /// use cosmarium_plugin_api::PanelPosition;
+    ///
+    /// let mut layout = Layout::default();
+    /// layout.move_panel("outline", PanelPosition::Left, None);
+    /// layout.float_panel("outline", WindowSettings::floating());
+    /// assert_eq!(layout.panel_side("outline"), None);
+    /// assert!(layout.floating_panel("outline").is_some());
+    /// ```
+    pub fn float_panel(&mut self, name: &str, settings: WindowSettings) {
+        for group in self.groups.values_mut() {
+            group.remove(name);
+        }
+        self.floating.insert(name.to_string(), settings);
+    }
+
+    /// Get the window of a floating panel.
+    pub fn floating_panel(&self, name: &str) -> Option<&WindowSettings> {
+        self.floating.get(name)
+    }
+
+    /// Get the window of a floating panel mutably.
+    pub fn floating_panel_mut(&mut self, name: &str) -> Option<&mut WindowSettings> {
+        self.floating.get_mut(name)
+    }
+
+    /// Get the floating panels and their windows.
+    pub fn floating_panels(&self) -> impl Iterator<Item = (&String, &WindowSettings)> {
+        self.floating.iter()
+    }
+
+    /// Get the activity the layout is arranged for.
+    pub fn activity(&self) -> Activity {
+        self.activity
+    }
+
+    /// Set the activity the layout is arranged for.
+    pub fn set_activity(&mut self, activity: Activity) {
+        self.activity = activity;
+    }
+
+    /// Get the panels open in the layout, if it keeps them.
+    pub fn open_panels(&self) -> Option<&[String]> {
+        self.open_panels.as_deref()
+    }
+
+    /// Keep the panels open in the layout.
+    pub fn set_open_panels<S: Into<String>>(&mut self, names: impl IntoIterator<Item = S>) {
+        let mut names: Vec<String> = names.into_iter().map(Into::into).collect();
+        names.sort();
+        self.open_panels = Some(names);
+    }
+
+    /// Get the text scale of a panel, 1.0 unless it was zoomed.
+    pub fn panel_zoom(&self, name: &str) -> f32 {
+        self.panel_zoom.get(name).copied().unwrap_or(1.0)
+    }
+
+    /// Set the text scale of a panel, kept within [`PANEL_ZOOM_RANGE`].
+    pub fn set_panel_zoom(&mut self, name: &str, zoom: f32) {
+        let zoom = zoom.clamp(*PANEL_ZOOM_RANGE.start(), *PANEL_ZOOM_RANGE.end());
+        if (zoom - 1.0).abs() < f32::EPSILON {
+            self.panel_zoom.remove(name);
+        } else {
+            self.panel_zoom.insert(name.to_string(), zoom);
+        }
+    }
+
     /// Get window settings.
     pub fn window_settings(&self) -> &WindowSettings {
         &self.window_settings
@@ -713,8 +1008,83 @@ impl Default for Layout {
     }
 }
 
+/// What the author is doing, each activity with its own open panels and
+/// arrangement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Activity {
+    /// Writing new text, with nothing but the editor
+    #[default]
+    Drafting,
+    /// Reworking text, with the notes and checks that help it
+    Revising,
+    /// Arranging the story, with its structure and world at hand
+    Planning,
+}
+
+impl Activity {
+    /// All activities, in the order they are offered.
+    pub const ALL: [Activity; 3] = [Activity::Drafting, Activity::Revising, Activity::Planning];
+
+    /// Get the display name of the activity.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Activity::Drafting => "Drafting",
+            Activity::Revising => "Revising",
+            Activity::Planning => "Planning",
+        }
+    }
+
+    /// Get the name the activity's arrangement is saved under.
+    pub fn layout_name(&self) -> &'static str {
+        match self {
+            Activity::Drafting => "activity-drafting",
+            Activity::Revising => "activity-revising",
+            Activity::Planning => "activity-planning",
+        }
+    }
+
+    /// Get the panels open the first time the activity is entered.
+    pub fn default_panels(&self) -> &'static [&'static str] {
+        match self {
+            Activity::Drafting => &[],
+            Activity::Revising => &["tasks", "grammar", "prose", "inspector"],
+            Activity::Planning => &["binder", "outline", "wiki", "kanban"],
+        }
+    }
+}
+
+/// Panels sharing one side of the workspace as tabs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PanelGroup {
+    /// Names of the panels' plugins, in tab order
+    pub tabs: Vec<String>,
+    /// Name of the panel whose tab is selected
+    pub active: Option<String>,
+}
+
+impl PanelGroup {
+    /// Check whether a panel has a tab in this group.
+    pub fn contains(&self, name: &str) -> bool {
+        self.tabs.iter().any(|tab| tab == name)
+    }
+
+    /// Remove a panel's tab, selecting its neighbour if it was selected.
+    fn remove(&mut self, name: &str) {
+        let Some(index) = self.tabs.iter().position(|tab| tab == name) else {
+            return;
+        };
+        self.tabs.remove(index);
+        if self.active.as_deref() == Some(name) {
+            self.active = self
+                .tabs
+                .get(index.min(self.tabs.len().saturating_sub(1)))
+                .cloned();
+        }
+    }
+}
+
 /// Window-specific settings within a layout.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WindowSettings {
     /// Window width
     pub width: f32,
@@ -734,6 +1104,17 @@ pub struct WindowSettings {
     pub alpha: f32,
 }
 
+impl WindowSettings {
+    /// Settings for a small window holding a single panel.
+    pub fn floating() -> Self {
+        Self {
+            width: 320.0,
+            height: 400.0,
+            ..Self::default()
+        }
+    }
+}
+
 impl Default for WindowSettings {
     fn default() -> Self {
         Self {
@@ -854,4 +1235,133 @@ mod tests {
         assert_eq!(left_panels.len(), 2);
         assert_eq!(right_panels.len(), 1);
     }
+
+    #[test]
+    fn test_layout_panel_groups() {
+        let mut layout = Layout::new("Test");
+
+        layout.dock_panel("outline", PanelPosition::Left);
+        layout.dock_panel("characters", PanelPosition::Left);
+        layout.dock_panel("notes", PanelPosition::Right);
+        let left = layout.panel_group(PanelPosition::Left).unwrap();
+        assert_eq!(left.tabs, vec!["outline", "characters"]);
+        assert_eq!(left.active.as_deref(), Some("outline"));
+
+        layout.move_panel("notes", PanelPosition::Left, Some(1));
+        let left = layout.panel_group(PanelPosition::Left).unwrap();
+        assert_eq!(left.tabs, vec!["outline", "notes", "characters"]);
+        assert_eq!(left.active.as_deref(), Some("notes"));
+        assert!(layout
+            .panel_group(PanelPosition::Right)
+            .unwrap()
+            .tabs
+            .is_empty());
+
+        // Docking again keeps the tab where the user moved it
+        assert_eq!(
+            layout.dock_panel("notes", PanelPosition::Right),
+            Some(PanelPosition::Left)
+        );
+
+        layout.move_panel("notes", PanelPosition::Bottom, None);
+        let left = layout.panel_group(PanelPosition::Left).unwrap();
+        assert_eq!(left.active.as_deref(), Some("characters"));
+        assert!(layout.select_panel("outline"));
+        assert!(!layout.select_panel("missing"));
+    }
+
+    #[test]
+    fn test_layout_panel_groups_roundtrip() {
+        let mut layout = Layout::new("Test");
+        layout.dock_panel("outline", PanelPosition::Left);
+        layout.move_panel("tasks", PanelPosition::Bottom, None);
+
+        let json = serde_json::to_string(&layout).unwrap();
+        let restored: Layout = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            restored.panel_group(PanelPosition::Bottom),
+            layout.panel_group(PanelPosition::Bottom)
+        );
+        assert_eq!(restored.panel_side("outline"), Some(PanelPosition::Left));
+    }
+
+    #[test]
+    fn test_layout_floating_panels() {
+        let mut layout = Layout::new("Test");
+        layout.dock_panel("outline", PanelPosition::Left);
+        layout.dock_panel("stats", PanelPosition::Left);
+
+        let mut settings = WindowSettings::floating();
+        settings.always_on_top = true;
+        layout.float_panel("stats", settings);
+        let left = layout.panel_group(PanelPosition::Left).unwrap();
+        assert_eq!(left.tabs, vec!["outline"]);
+        assert_eq!(layout.panel_side("stats"), None);
+        assert!(layout.floating_panel("stats").unwrap().always_on_top);
+
+        // A floating panel isn't docked back on startup
+        assert_eq!(layout.dock_panel("stats", PanelPosition::Left), None);
+
+        let json = serde_json::to_string(&layout).unwrap();
+        let restored: Layout = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.floating_panels().count(), 1);
+        assert_eq!(restored.floating_panel("stats").unwrap().width, 320.0);
+
+        // Dragging the panel onto a side docks it again
+        layout.move_panel("stats", PanelPosition::Right, None);
+        assert!(layout.floating_panel("stats").is_none());
+        assert_eq!(layout.panel_side("stats"), Some(PanelPosition::Right));
+    }
+
+    #[test]
+    fn test_layout_panel_zoom() {
+        let mut layout = Layout::new("Test");
+        layout.set_panel_zoom("outline", 0.8);
+        layout.set_panel_zoom("notes", 9.0);
+        assert_eq!(layout.panel_zoom("outline"), 0.8);
+        assert_eq!(layout.panel_zoom("notes"), 2.0);
+        assert_eq!(layout.panel_zoom("editor"), 1.0);
+
+        let json = serde_json::to_string(&layout).unwrap();
+        let mut restored: Layout = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.panel_zoom("outline"), 0.8);
+
+        // Back at 1.0 the panel follows the global scale again
+        restored.set_panel_zoom("outline", 1.0);
+        assert!(!restored.panel_zoom.contains_key("outline"));
+    }
+
+    #[tokio::test]
+    async fn test_activities_keep_their_own_panels() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = LayoutManager::new();
+        manager.layouts_directory = dir.path().to_path_buf();
+        manager
+            .current_layout_mut()
+            .set_open_panels(["binder", "markdown-editor"]);
+
+        manager.switch_activity(Activity::Revising).await.unwrap();
+        let layout = manager.current_layout();
+        assert_eq!(layout.activity(), Activity::Revising);
+        assert_eq!(layout.name(), "default");
+        assert_eq!(
+            layout.open_panels().unwrap(),
+            ["grammar", "inspector", "prose", "tasks"]
+        );
+
+        // Arrange Revising, then come back to it after drafting
+        manager.move_panel("tasks", PanelPosition::Right, None);
+        manager.switch_activity(Activity::Drafting).await.unwrap();
+        assert_eq!(
+            manager.current_layout().open_panels().unwrap(),
+            ["binder", "markdown-editor"]
+        );
+        assert!(dir.path().join("activity-drafting.json").exists());
+
+        manager.switch_activity(Activity::Revising).await.unwrap();
+        assert_eq!(
+            manager.current_layout().panel_side("tasks"),
+            Some(PanelPosition::Right)
+        );
+    }
 }
@@ -25,17 +25,58 @@
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! # });
 //! ```
+//!
+//! ## Embedding
+//!
+//! The core can be used by other Rust applications to open projects and
+//! compile them without any window: [`Project`] and [`ProjectManager`] for
+//! projects, [`Document`] and [`DocumentManager`] for their documents, and
+//! [`compile_project`] then [`export_manuscript`] to write a manuscript.
+//! The `compile_manuscript` example does just that:
+//!
+//! ```text
+//! cargo run -p cosmarium-core --no-default-features --example compile_manuscript -- my-novel out
+//! ```
+//!
+//! ## Features
+//!
+//! - `ui` (default): panel plugins, which depend on `egui`. Leave it out
+//!   with `default-features = false` to embed the core headless.
+//! - `hot-reload`: native plugin libraries, loaded with `libloading`.
+//! - `wasm-plugins`: sandboxed WebAssembly plugins, with their panels.
+//!
+//! ## Testing
+//!
+//! [`simulation::Simulation`] runs plugins against a manual clock, seeded
+//! identifiers and synchronous event delivery, so that time-dependent
+//! behaviour can be tested reproducibly.
 
 pub mod application;
+pub mod check;
 pub mod config;
 pub mod document;
 pub mod error;
 pub mod events;
+pub mod executor;
+pub mod export;
 pub mod git;
+pub mod goals;
+pub mod import;
+pub mod keymap;
 pub mod layout;
+pub mod logging;
+pub mod navigation;
 pub mod plugin;
+pub mod profile;
 pub mod project;
+pub mod proof;
+pub mod search;
 pub mod session;
+pub mod simulation;
+pub mod snapshot;
+pub mod structure;
+pub mod task;
+pub mod theme;
 
 pub use application::Application;
 pub use config::Config;
@@ -43,30 +84,34 @@ pub use cosmarium_plugin_api::event::{Event, EventType};
 pub use document::{Document, DocumentManager};
 pub use error::{Error, Result};
 pub use events::EventBus;
+pub use executor::Executor;
+pub use export::compile::{compile_manuscript, compile_project, export_manuscript, CompileTarget};
+pub use export::DocumentExportFormat;
 pub use layout::{Layout, LayoutManager};
 pub use plugin::{PluginManager, PluginRegistry};
 pub use project::{Project, ProjectManager};
 pub use session::Session;
+pub use structure::ProjectStructure;
+pub use task::TaskManager;
 
 /// Initialize tracing for the application
 ///
-/// This sets up structured logging for the entire application.
+/// This sets up structured logging for the entire application, with the
+/// level, per-target overrides, log files and format of `config`; see
+/// [`logging`].
 ///
 /// # Example
 ///
 /// ```rust
-/// cosmarium_core::init_tracing();
+/// use cosmarium_core::config::AdvancedConfig;
+///
+/// cosmarium_core::init_tracing(&AdvancedConfig::default());
 /// tracing::info!("Application started");
 /// ```
-pub fn init_tracing() {
-    // Try to initialize a tracing subscriber but avoid panicking if a global
-    // subscriber has already been installed by another logger (for example
-    // env_logger). Use `try_init()` to attempt installation and ignore the
-    // error when the global subscriber is already set.
-    //
-    // This keeps startup robust when multiple logging initializers are used,
-    // while still ensuring tracing events are captured when possible.
-    let _ = tracing_subscriber::fmt::try_init();
+pub fn init_tracing(config: &config::AdvancedConfig) {
+    // Avoid panicking if a global subscriber has already been installed,
+    // by a test harness for example: keep that one.
+    let _ = logging::init(config);
 }
 
 #[cfg(test)]
@@ -75,8 +120,9 @@ mod tests {
 
     #[test]
     fn test_init_tracing() {
-        // Should not panic
-        init_tracing();
+        // Should not panic, even twice
+        init_tracing(&config::AdvancedConfig::default());
+        init_tracing(&config::AdvancedConfig::default());
     }
 }
 
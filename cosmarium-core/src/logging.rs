@@ -0,0 +1,230 @@
+//! # Logging
+//!
+//! [`init`] installs the global `tracing` subscriber as the
+//! [`AdvancedConfig`] describes it:
+//!
+//! - `log_level` is the level of every target, and `log_targets` overrides
+//!   it for some of them, e.g. `cosmarium_markdown_editor = "debug"`. The
+//!   `RUST_LOG` environment variable, when set, replaces both.
+//! - Records go to the standard error and, with `log_to_file`, to a log file
+//!   in the data directory too. A new file is started every day and the
+//!   files of the last week are kept.
+//! - `log_json` writes records as JSON lines, for log processing tools.
+//!
+//! The filter can then be changed while the application runs, through the
+//! [`LogControl`] that [`control`] returns.
+
+use crate::config::AdvancedConfig;
+use crate::{Error, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
+
+/// Levels a target can be set to.
+pub const LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+
+/// Number of log files kept, one a day.
+const KEPT_LOG_FILES: usize = 7;
+
+/// Control of the subscriber installed by [`init`]
+static CONTROL: OnceLock<LogControl> = OnceLock::new();
+
+/// Handle on the subscriber installed by [`init`].
+pub struct LogControl {
+    /// Filter of the subscriber
+    filter: reload::Handle<EnvFilter, Registry>,
+    /// Directives of the current filter
+    directives: Mutex<String>,
+    /// Directory of the log files, when logging to files
+    directory: Option<PathBuf>,
+}
+
+impl LogControl {
+    /// Directives of the current filter, such as
+    /// `info,cosmarium_markdown_editor=debug`.
+    pub fn directives(&self) -> String {
+        self.directives
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Filter records with `directives` from now on.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directives cannot be parsed.
+    pub fn set_directives(&self, directives: &str) -> Result<()> {
+        let filter = parse_filter(directives)?;
+        self.filter
+            .reload(filter)
+            .map_err(|e| Error::config(format!("Failed to change the log filter: {}", e)))?;
+        *self.directives.lock().unwrap_or_else(|e| e.into_inner()) = directives.to_string();
+        tracing::info!("Log filter changed to '{}'", directives);
+        Ok(())
+    }
+
+    /// Directory the log files are written to, if any.
+    pub fn directory(&self) -> Option<&Path> {
+        self.directory.as_deref()
+    }
+}
+
+/// Handle on the subscriber installed by [`init`], if it was.
+pub fn control() -> Option<&'static LogControl> {
+    CONTROL.get()
+}
+
+/// Directory of the log files, in the data directory.
+///
+/// # Errors
+///
+/// Returns an error if the data directory cannot be determined.
+pub fn log_directory() -> Result<PathBuf> {
+    let data_dir =
+        dirs::data_dir().ok_or_else(|| Error::config("Could not determine data directory"))?;
+    Ok(data_dir.join("cosmarium").join("logs"))
+}
+
+/// Filter directives setting every target to `level`, except `targets`.
+///
+/// Dashes in target names are read as underscores, as in crate names.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_core::logging::filter_directives;
+/// use std::collections::BTreeMap;
+///
+/// let targets = BTreeMap::from([("cosmarium-markdown-editor".to_string(), "debug".to_string())]);
+/// assert_eq!(
+///     filter_directives("warn", &targets),
+///     "warn,cosmarium_markdown_editor=debug"
+/// );
+/// ```
+pub fn filter_directives(level: &str, targets: &BTreeMap<String, String>) -> String {
+    let overrides = targets
+        .iter()
+        .filter(|(target, _)| !target.trim().is_empty())
+        .map(|(target, level)| format!("{}={}", target.trim().replace('-', "_"), level));
+    std::iter::once(level.to_string())
+        .chain(overrides)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Install the global subscriber described by `config`.
+///
+/// A filter that cannot be parsed falls back to `info`, and a log file that
+/// cannot be opened leaves the standard error alone; both are reported once
+/// the subscriber is installed.
+///
+/// # Errors
+///
+/// Returns an error if a global subscriber is already installed.
+pub fn init(config: &AdvancedConfig) -> Result<()> {
+    let mut problems = Vec::new();
+    let mut directives = std::env::var("RUST_LOG")
+        .ok()
+        .filter(|directives| !directives.trim().is_empty())
+        .unwrap_or_else(|| filter_directives(&config.log_level, &config.log_targets));
+    let filter = parse_filter(&directives).unwrap_or_else(|e| {
+        problems.push(e.to_string());
+        directives = "info".to_string();
+        EnvFilter::new(&directives)
+    });
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let mut outputs = vec![output(std::io::stderr, config.log_json, true)];
+    let mut directory = None;
+    if config.log_to_file {
+        match log_files() {
+            Ok((dir, files)) => {
+                outputs.push(output(files, config.log_json, false));
+                directory = Some(dir);
+            }
+            Err(e) => problems.push(e.to_string()),
+        }
+    }
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(outputs)
+        .try_init()
+        .map_err(|e| Error::config(format!("Failed to install the logger: {}", e)))?;
+    for problem in problems {
+        tracing::warn!("{}", problem);
+    }
+    if let Some(dir) = &directory {
+        tracing::info!("Logging to {}", dir.display());
+    }
+
+    let _ = CONTROL.set(LogControl {
+        filter: handle,
+        directives: Mutex::new(directives),
+        directory,
+    });
+    Ok(())
+}
+
+fn parse_filter(directives: &str) -> Result<EnvFilter> {
+    EnvFilter::try_new(directives)
+        .map_err(|e| Error::config(format!("Invalid log filter '{}': {}", directives, e)))
+}
+
+/// Daily log files in the log directory.
+fn log_files() -> Result<(PathBuf, RollingFileAppender)> {
+    let dir = log_directory()?;
+    let files = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("cosmarium")
+        .filename_suffix("log")
+        .max_log_files(KEPT_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| {
+            Error::config(format!(
+                "Failed to open a log file in {}: {}",
+                dir.display(),
+                e
+            ))
+        })?;
+    Ok((dir, files))
+}
+
+/// Formatting layer writing records to `writer`, as text or JSON lines.
+fn output<S, W>(writer: W, json: bool, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = fmt::layer().with_writer(writer).with_ansi(ansi);
+    if json {
+        layer.json().boxed()
+    } else {
+        layer.boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_directives_override_targets() {
+        let targets = BTreeMap::from([
+            ("wgpu".to_string(), "error".to_string()),
+            ("cosmarium-grammar".to_string(), "trace".to_string()),
+            (" ".to_string(), "debug".to_string()),
+        ]);
+        let directives = filter_directives("info", &targets);
+        assert_eq!(directives, "info,cosmarium_grammar=trace,wgpu=error");
+        assert!(parse_filter(&directives).is_ok());
+        assert!(parse_filter("info,=loud").is_err());
+    }
+}
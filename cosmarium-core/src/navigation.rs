@@ -0,0 +1,208 @@
+//! # Navigation history
+//!
+//! Records the places of the project the writer visits, so they can jump
+//! back and forth between them like in a web browser, and the places they
+//! edited most recently.
+//!
+//! Moving the caret within a few lines of the current location refines that
+//! location instead of adding a new one, so that only real jumps (another
+//! document, a search result, a far-away line) become history entries.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_core::navigation::{Location, NavigationHistory};
+//!
+//! let mut history = NavigationHistory::new();
+//! history.visit(Location::new("content/arrival.md", 12));
+//! history.visit(Location::new("content/departure.md", 3));
+//!
+//! let back = history.back().unwrap();
+//! assert_eq!(back, Location::new("content/arrival.md", 12));
+//! assert_eq!(history.forward().unwrap().line, 3);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Most locations kept in each direction of the history.
+const MAX_HISTORY: usize = 100;
+
+/// Most locations kept in the recently edited list.
+const MAX_RECENT_EDITS: usize = 10;
+
+/// Lines within which two locations of a document are the same place.
+const NEARBY_LINES: usize = 15;
+
+/// A line of a document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    /// Path of the document file
+    pub path: PathBuf,
+    /// Line, starting at 1
+    pub line: usize,
+}
+
+impl Location {
+    pub fn new(path: impl Into<PathBuf>, line: usize) -> Self {
+        Self {
+            path: path.into(),
+            line,
+        }
+    }
+
+    /// Whether `other` is in the same document, a few lines away at most.
+    pub fn is_near(&self, other: &Location) -> bool {
+        self.path == other.path && self.line.abs_diff(other.line) <= NEARBY_LINES
+    }
+}
+
+/// Back/forward history of visited locations and list of recently edited
+/// locations.
+#[derive(Debug, Clone, Default)]
+pub struct NavigationHistory {
+    /// Locations before the current one, oldest first
+    back: Vec<Location>,
+    /// Locations left by going back, the next one last
+    forward: Vec<Location>,
+    current: Option<Location>,
+    /// Edited locations, most recent first
+    recent_edits: Vec<Location>,
+}
+
+impl NavigationHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The location being visited.
+    pub fn current(&self) -> Option<&Location> {
+        self.current.as_ref()
+    }
+
+    /// Record that the caret is at `location`.
+    ///
+    /// A jump away from the current location pushes it on the back history
+    /// and clears the forward history.
+    pub fn visit(&mut self, location: Location) {
+        match &mut self.current {
+            Some(current) if current.is_near(&location) => current.line = location.line,
+            current => {
+                if let Some(previous) = current.replace(location) {
+                    self.back.push(previous);
+                    if self.back.len() > MAX_HISTORY {
+                        self.back.remove(0);
+                    }
+                }
+                self.forward.clear();
+            }
+        }
+    }
+
+    /// Whether [`NavigationHistory::back`] has somewhere to go.
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    /// Whether [`NavigationHistory::forward`] has somewhere to go.
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+
+    /// Step back to the previous location, which becomes the current one.
+    pub fn back(&mut self) -> Option<Location> {
+        let location = self.back.pop()?;
+        if let Some(current) = self.current.replace(location.clone()) {
+            self.forward.push(current);
+        }
+        Some(location)
+    }
+
+    /// Step forward to the location left by going back.
+    pub fn forward(&mut self) -> Option<Location> {
+        let location = self.forward.pop()?;
+        if let Some(current) = self.current.replace(location.clone()) {
+            self.back.push(current);
+        }
+        Some(location)
+    }
+
+    /// Record an edit at `location`, moving it to the top of the recently
+    /// edited locations.
+    pub fn record_edit(&mut self, location: Location) {
+        self.recent_edits.retain(|edit| !edit.is_near(&location));
+        self.recent_edits.insert(0, location);
+        self.recent_edits.truncate(MAX_RECENT_EDITS);
+    }
+
+    /// Recently edited locations, most recent first.
+    pub fn recent_edits(&self) -> &[Location] {
+        &self.recent_edits
+    }
+
+    /// Forget the locations of a document that no longer exists.
+    pub fn forget(&mut self, path: &Path) {
+        self.back.retain(|location| location.path != path);
+        self.forward.retain(|location| location.path != path);
+        self.recent_edits.retain(|location| location.path != path);
+        if self.current.as_ref().is_some_and(|c| c.path == path) {
+            self.current = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut history = NavigationHistory::new();
+        assert!(history.back().is_none());
+
+        history.visit(Location::new("a.md", 10));
+        // Moving the caret a little refines the location
+        history.visit(Location::new("a.md", 14));
+        assert!(!history.can_go_back());
+        history.visit(Location::new("a.md", 200));
+        history.visit(Location::new("b.md", 1));
+
+        assert_eq!(history.back(), Some(Location::new("a.md", 200)));
+        assert_eq!(history.back(), Some(Location::new("a.md", 14)));
+        assert!(!history.can_go_back());
+        assert_eq!(history.forward(), Some(Location::new("a.md", 200)));
+
+        // A new jump drops the forward history
+        history.visit(Location::new("c.md", 5));
+        assert!(!history.can_go_forward());
+        assert_eq!(history.back(), Some(Location::new("a.md", 200)));
+
+        history.forget(Path::new("a.md"));
+        assert!(history.current().is_none());
+        assert!(!history.can_go_back());
+        assert_eq!(history.forward(), Some(Location::new("c.md", 5)));
+    }
+
+    #[test]
+    fn test_recent_edits() {
+        let mut history = NavigationHistory::new();
+        for line in [1, 5, 100] {
+            history.record_edit(Location::new("a.md", line));
+        }
+        history.record_edit(Location::new("b.md", 7));
+        history.record_edit(Location::new("a.md", 3));
+
+        assert_eq!(
+            history.recent_edits(),
+            &[
+                Location::new("a.md", 3),
+                Location::new("b.md", 7),
+                Location::new("a.md", 100),
+            ]
+        );
+        for line in 0..20 {
+            history.record_edit(Location::new("c.md", line * 100));
+        }
+        assert_eq!(history.recent_edits().len(), MAX_RECENT_EDITS);
+    }
+}
@@ -5,7 +5,9 @@
 //! communication through a registry system.
 //!
 //! The plugin system is designed to be flexible and secure, supporting both
-//! native Rust plugins and potentially other plugin types in the future.
+//! native Rust plugins and sandboxed WebAssembly plugins (see [`wasm`]).
+
+pub mod wasm;
 
 use crate::{events::EventBus, Error, Result};
 use cosmarium_plugin_api::{Plugin, PluginContext, PluginInfo, PluginType};
@@ -49,6 +51,12 @@ pub struct PluginManager {
     plugin_directories: Vec<PathBuf>,
     /// Whether the plugin system is initialized
     initialized: bool,
+    /// Sandboxed plugin runtime
+    #[cfg(feature = "wasm-plugins")]
+    wasm_host: Option<wasm::WasmPluginHost>,
+    /// Discovered WebAssembly plugins, by name
+    #[cfg(feature = "wasm-plugins")]
+    wasm_plugins: HashMap<String, PathBuf>,
 }
 
 impl PluginManager {
@@ -72,6 +80,10 @@ impl PluginManager {
                 PathBuf::from("./target/debug"), // For development
             ],
             initialized: false,
+            #[cfg(feature = "wasm-plugins")]
+            wasm_host: None,
+            #[cfg(feature = "wasm-plugins")]
+            wasm_plugins: HashMap::new(),
         }
     }
 
@@ -226,6 +238,26 @@ impl PluginManager {
                 info!("Plugin '{}' loaded successfully", plugin_name);
                 Ok(())
             }
+            #[cfg(feature = "wasm-plugins")]
+            _ if self.wasm_plugins.contains_key(plugin_name) => {
+                let path = self.wasm_plugins[plugin_name].clone();
+                let mut plugin = Box::new(self.wasm_host()?.load(&path)?);
+                let mut context = PluginContext::new();
+
+                if let Err(e) = plugin.initialize(&mut context) {
+                    return Err(Error::plugin(format!(
+                        "Failed to initialize plugin '{}': {}",
+                        plugin_name, e
+                    )));
+                }
+
+                self.loaded_plugins.insert(plugin_name.to_string(), plugin);
+                self.plugin_contexts
+                    .insert(plugin_name.to_string(), context);
+
+                info!("WASM plugin '{}' loaded from {:?}", plugin_name, path);
+                Ok(())
+            }
             _ => Err(Error::plugin(format!("Unknown plugin: {}", plugin_name))),
         }
     }
@@ -413,16 +445,54 @@ impl PluginManager {
     async fn scan_directory(&mut self, dir: &Path) -> Result<()> {
         debug!("Scanning directory: {:?}", dir);
 
-        // In a real implementation, this would:
-        // 1. Look for plugin manifest files (plugin.toml)
-        // 2. Look for shared libraries (.so, .dll, .dylib)
-        // 3. Validate plugin signatures if required
-        // 4. Register discovered plugins
+        // Native plugins are still linked statically; only sandboxed
+        // WebAssembly plugins are discovered at runtime.
+        #[cfg(feature = "wasm-plugins")]
+        for entry in std::fs::read_dir(dir)?.flatten() {
+            let path = entry.path();
+            if !wasm::is_wasm_plugin(&path) {
+                continue;
+            }
+
+            match self.wasm_host()?.load(&path) {
+                Ok(plugin) => {
+                    let info = plugin.manifest().info.clone();
+                    debug!("Found WASM plugin '{}' in {:?}", info.name, path);
+                    self.wasm_plugins.insert(info.name.clone(), path);
+                    self.registry.register_plugin(info);
+                }
+                Err(e) => warn!("Skipping WASM plugin {:?}: {}", path, e),
+            }
+        }
 
-        // For now, this is a placeholder
         Ok(())
     }
 
+    /// Approve `capabilities` for the WebAssembly plugin named `plugin`,
+    /// which is otherwise granted none, for its next load.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the WebAssembly runtime cannot be created.
+    #[cfg(feature = "wasm-plugins")]
+    pub fn approve_wasm_plugin(
+        &mut self,
+        plugin: &str,
+        capabilities: wasm::WasmCapabilities,
+    ) -> Result<()> {
+        self.wasm_host()?.approve(plugin, capabilities);
+        Ok(())
+    }
+
+    /// Get the WebAssembly runtime, creating it on first use.
+    #[cfg(feature = "wasm-plugins")]
+    fn wasm_host(&mut self) -> Result<&mut wasm::WasmPluginHost> {
+        if self.wasm_host.is_none() {
+            self.wasm_host = Some(wasm::WasmPluginHost::new(wasm::WasmLimits::default())?);
+        }
+        Ok(self.wasm_host.as_mut().expect("host created above"))
+    }
+
     /// Get the plugin registry.
     pub fn registry(&self) -> &PluginRegistry {
         &self.registry
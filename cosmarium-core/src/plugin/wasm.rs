@@ -0,0 +1,879 @@
+//! # Sandboxed WebAssembly plugins
+//!
+//! Community plugins can be shipped as WebAssembly modules and run inside a
+//! sandbox: they get no file system, network or clock access, their memory
+//! and CPU time are capped, and they only see the shared state keys their
+//! manifest declares and the user approved (see [`WasmPluginHost::approve`]).
+//!
+//! The host itself (built on `wasmtime`) is only compiled with the
+//! `wasm-plugins` feature. The ABI types below are always available so that
+//! plugin authors and tooling can depend on them without pulling in the
+//! runtime.
+//!
+//! ## ABI (version 1)
+//!
+//! Strings cross the boundary as UTF-8 JSON stored in the guest's linear
+//! memory. Functions returning a string return an `i64` packing the pointer
+//! in the high 32 bits and the length in the low 32 bits (see [`pack_ptr_len`]).
+//!
+//! Guest exports, mirroring [`Plugin`](cosmarium_plugin_api::Plugin) and
+//! [`PanelPlugin`](cosmarium_plugin_api::PanelPlugin):
+//!
+//! | Export                             | Signature           | Purpose                                   |
+//! |------------------------------------|---------------------|-------------------------------------------|
+//! | `memory`                           | memory              | Linear memory shared with the host        |
+//! | `cosmarium_abi_version`            | `() -> i32`         | Must return [`WASM_ABI_VERSION`]          |
+//! | `cosmarium_alloc`                  | `(i32) -> i32`      | Allocate a buffer for host-written data   |
+//! | `cosmarium_manifest`               | `() -> i64`         | JSON [`WasmManifest`]                     |
+//! | `cosmarium_initialize`             | `() -> i32`         | `Plugin::initialize`, 0 on success        |
+//! | `cosmarium_update`                 | `() -> i32`         | `Plugin::update`, 0 on success            |
+//! | `cosmarium_shutdown`               | `() -> i32`         | `Plugin::shutdown`, 0 on success          |
+//! | `cosmarium_render_panel`           | `() -> i64`         | JSON list of [`WasmWidget`] (panels only) |
+//! | `cosmarium_on_ui_event`            | `(i32, i32) -> i32` | JSON [`WasmUiEvent`] (panels only)        |
+//!
+//! Host imports, in the `cosmarium` module:
+//!
+//! | Import             | Signature                 | Purpose                                        |
+//! |--------------------|---------------------------|------------------------------------------------|
+//! | `log`              | `(i32, i32, i32)`         | Log a message (level 0-4, pointer, length)     |
+//! | `shared_state_get` | `(i32, i32) -> i64`       | Read a declared string key, 0 if unset         |
+//! | `shared_state_set` | `(i32, i32, i32, i32) -> i32` | Write a declared string key, -1 if denied  |
+//! | `emit_event`       | `(i32, i32) -> i32`       | Emit a JSON [`WasmEvent`], -1 if denied        |
+
+use cosmarium_plugin_api::{EventType, PanelPosition, PluginInfo, PluginType};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Version of the guest/host ABI implemented by this host.
+pub const WASM_ABI_VERSION: i32 = 1;
+
+/// File extension of WebAssembly plugins.
+pub const WASM_EXTENSION: &str = "wasm";
+
+/// Plugin description returned by the guest's `cosmarium_manifest` export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WasmManifest {
+    /// Plugin metadata
+    pub info: PluginInfo,
+    /// Plugin category
+    pub plugin_type: PluginType,
+    /// Panel description, for plugins providing a panel
+    #[serde(default)]
+    pub panel: Option<WasmPanelManifest>,
+    /// Permissions requested by the plugin
+    #[serde(default)]
+    pub capabilities: WasmCapabilities,
+}
+
+/// Panel description of a WebAssembly plugin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WasmPanelManifest {
+    /// Panel title
+    pub title: String,
+    /// Panel icon (emoji or short text)
+    #[serde(default)]
+    pub icon: String,
+    /// Default docking position
+    #[serde(default = "default_panel_position")]
+    pub position: PanelPosition,
+}
+
+fn default_panel_position() -> PanelPosition {
+    PanelPosition::Right
+}
+
+/// Permissions a WebAssembly plugin asks for.
+///
+/// Anything not listed is denied by the host.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WasmCapabilities {
+    /// Shared state keys the plugin may read (string values only)
+    pub shared_state_read: Vec<String>,
+    /// Shared state keys the plugin may write (string values only)
+    pub shared_state_write: Vec<String>,
+    /// Whether the plugin may emit events
+    pub emit_events: bool,
+}
+
+impl WasmCapabilities {
+    /// Check whether reading `key` is allowed.
+    pub fn can_read(&self, key: &str) -> bool {
+        self.shared_state_read.iter().any(|k| k == key)
+    }
+
+    /// Check whether writing `key` is allowed.
+    pub fn can_write(&self, key: &str) -> bool {
+        self.shared_state_write.iter().any(|k| k == key)
+    }
+
+    /// The permissions both in `self` and in `other`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::plugin::wasm::WasmCapabilities;
+    ///
+    /// let requested = WasmCapabilities {
+    ///     shared_state_read: vec!["word_count".into(), "content".into()],
+    ///     emit_events: true,
+    ///     ..WasmCapabilities::default()
+    /// };
+    /// let approved = WasmCapabilities {
+    ///     shared_state_read: vec!["word_count".into()],
+    ///     ..WasmCapabilities::default()
+    /// };
+    /// let granted = requested.intersect(&approved);
+    /// assert!(granted.can_read("word_count"));
+    /// assert!(!granted.can_read("content"));
+    /// assert!(!granted.emit_events);
+    /// ```
+    pub fn intersect(&self, other: &WasmCapabilities) -> WasmCapabilities {
+        let common = |keys: &[String], allowed: &[String]| -> Vec<String> {
+            keys.iter()
+                .filter(|key| allowed.contains(key))
+                .cloned()
+                .collect()
+        };
+        WasmCapabilities {
+            shared_state_read: common(&self.shared_state_read, &other.shared_state_read),
+            shared_state_write: common(&self.shared_state_write, &other.shared_state_write),
+            emit_events: self.emit_events && other.emit_events,
+        }
+    }
+}
+
+/// Resource limits applied to every WebAssembly plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmLimits {
+    /// Fuel (roughly, instructions) available to each guest call
+    pub fuel_per_call: u64,
+    /// Maximum linear memory size in bytes
+    pub max_memory_bytes: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            fuel_per_call: 50_000_000,
+            max_memory_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Event emitted by a guest through the `emit_event` import.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WasmEvent {
+    /// Event type
+    pub event_type: EventType,
+    /// Human-readable description
+    #[serde(default)]
+    pub data: String,
+    /// Structured payload
+    #[serde(default)]
+    pub payload: serde_json::Value,
+}
+
+/// Declarative widget rendered by the host on behalf of a panel plugin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WasmWidget {
+    /// Section heading
+    Heading { text: String },
+    /// Plain text
+    Label { text: String },
+    /// Horizontal separator
+    Separator,
+    /// Clickable button
+    Button { id: String, label: String },
+    /// Checkbox reporting its new state when toggled
+    Checkbox {
+        id: String,
+        label: String,
+        checked: bool,
+    },
+}
+
+/// User interaction sent back to a panel plugin.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WasmUiEvent {
+    /// A button was clicked
+    Clicked { id: String },
+    /// A checkbox was toggled
+    Toggled { id: String, checked: bool },
+}
+
+/// Pack a guest pointer and length into the `i64` returned by string exports.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_core::plugin::wasm::{pack_ptr_len, unpack_ptr_len};
+///
+/// let packed = pack_ptr_len(1024, 17);
+/// assert_eq!(unpack_ptr_len(packed), (1024, 17));
+/// ```
+pub fn pack_ptr_len(ptr: u32, len: u32) -> i64 {
+    (((ptr as u64) << 32) | len as u64) as i64
+}
+
+/// Split a packed `i64` into guest pointer and length.
+pub fn unpack_ptr_len(packed: i64) -> (u32, u32) {
+    let packed = packed as u64;
+    ((packed >> 32) as u32, packed as u32)
+}
+
+/// Check whether a path looks like a WebAssembly plugin.
+pub fn is_wasm_plugin(path: &Path) -> bool {
+    path.is_file() && path.extension().and_then(|e| e.to_str()) == Some(WASM_EXTENSION)
+}
+
+#[cfg(feature = "wasm-plugins")]
+pub use host::{WasmPlugin, WasmPluginHost};
+
+#[cfg(feature = "wasm-plugins")]
+mod host {
+    use super::*;
+    use crate::{Error, Result};
+    use cosmarium_plugin_api::{Event, PanelPlugin, Plugin, PluginContext};
+    use std::collections::HashMap;
+    use wasmtime::{
+        Caller, Config, Engine, Instance, Linker, Memory, Module, Store, StoreLimits,
+        StoreLimitsBuilder,
+    };
+
+    /// Per-plugin state reachable from host imports.
+    struct HostState {
+        limits: StoreLimits,
+        capabilities: WasmCapabilities,
+        /// Values of readable keys, captured before each call
+        shared_state: HashMap<String, String>,
+        /// Shared state writes to apply after the call
+        pending_writes: Vec<(String, String)>,
+        /// Events to emit after the call
+        pending_events: Vec<WasmEvent>,
+        plugin_name: String,
+    }
+
+    /// Engine shared by all WebAssembly plugins.
+    pub struct WasmPluginHost {
+        engine: Engine,
+        limits: WasmLimits,
+        /// Permissions the user approved, by plugin name
+        approved: HashMap<String, WasmCapabilities>,
+    }
+
+    impl WasmPluginHost {
+        /// Create a host enforcing the given limits.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the engine cannot be created.
+        pub fn new(limits: WasmLimits) -> Result<Self> {
+            let mut config = Config::new();
+            config.consume_fuel(true);
+            let engine = Engine::new(&config)
+                .map_err(|e| Error::plugin(format!("Failed to create WASM engine: {}", e)))?;
+            Ok(Self {
+                engine,
+                limits,
+                approved: HashMap::new(),
+            })
+        }
+
+        /// Approve `capabilities` for the plugin named `plugin`, for the
+        /// plugins loaded from then on.
+        ///
+        /// A plugin is granted the permissions its manifest asks for that
+        /// are approved, none until they are.
+        pub fn approve(&mut self, plugin: impl Into<String>, capabilities: WasmCapabilities) {
+            self.approved.insert(plugin.into(), capabilities);
+        }
+
+        /// Load a plugin from a `.wasm` file.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the module cannot be read, compiled or
+        /// instantiated, or if it does not implement the expected ABI.
+        pub fn load(&self, path: &Path) -> Result<WasmPlugin> {
+            let bytes = std::fs::read(path)?;
+            self.load_bytes(&bytes)
+        }
+
+        /// Load a plugin from WebAssembly bytes (binary or text format).
+        ///
+        /// # Errors
+        ///
+        /// Same as [`WasmPluginHost::load`].
+        pub fn load_bytes(&self, bytes: &[u8]) -> Result<WasmPlugin> {
+            let module = Module::new(&self.engine, bytes)
+                .map_err(|e| Error::plugin(format!("Invalid WASM plugin: {}", e)))?;
+
+            let state = HostState {
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(self.limits.max_memory_bytes)
+                    .instances(1)
+                    .build(),
+                capabilities: WasmCapabilities::default(),
+                shared_state: HashMap::new(),
+                pending_writes: Vec::new(),
+                pending_events: Vec::new(),
+                plugin_name: String::new(),
+            };
+            let mut store = Store::new(&self.engine, state);
+            store.limiter(|state| &mut state.limits);
+
+            let linker = Self::linker(&self.engine)?;
+            store
+                .set_fuel(self.limits.fuel_per_call)
+                .map_err(wasm_error)?;
+            let instance = linker
+                .instantiate(&mut store, &module)
+                .map_err(|e| Error::plugin(format!("Failed to instantiate WASM plugin: {}", e)))?;
+            let memory = instance
+                .get_memory(&mut store, "memory")
+                .ok_or_else(|| Error::plugin("WASM plugin does not export its memory"))?;
+
+            let mut guest = Guest {
+                store,
+                instance,
+                memory,
+                limits: self.limits,
+            };
+
+            let version: i32 = guest.call("cosmarium_abi_version", ())?;
+            if version != WASM_ABI_VERSION {
+                return Err(Error::plugin(format!(
+                    "Unsupported WASM plugin ABI version {} (expected {})",
+                    version, WASM_ABI_VERSION
+                )));
+            }
+
+            let packed: i64 = guest.call("cosmarium_manifest", ())?;
+            let manifest: WasmManifest = serde_json::from_str(&guest.read_string(packed)?)?;
+            let approved = self
+                .approved
+                .get(&manifest.info.name)
+                .cloned()
+                .unwrap_or_default();
+            guest.store.data_mut().capabilities = manifest.capabilities.intersect(&approved);
+            guest.store.data_mut().plugin_name = manifest.info.name.clone();
+
+            Ok(WasmPlugin {
+                guest,
+                manifest,
+                enabled: true,
+            })
+        }
+
+        fn linker(engine: &Engine) -> Result<Linker<HostState>> {
+            let mut linker = Linker::new(engine);
+
+            linker
+                .func_wrap(
+                    "cosmarium",
+                    "log",
+                    |mut caller: Caller<'_, HostState>,
+                     level: i32,
+                     ptr: i32,
+                     len: i32|
+                     -> wasmtime::Result<()> {
+                        let message = read_guest_string(&mut caller, ptr, len)?;
+                        let name = caller.data().plugin_name.clone();
+                        match level {
+                            0 => tracing::error!("[wasm:{}] {}", name, message),
+                            1 => tracing::warn!("[wasm:{}] {}", name, message),
+                            2 => tracing::info!("[wasm:{}] {}", name, message),
+                            3 => tracing::debug!("[wasm:{}] {}", name, message),
+                            _ => tracing::trace!("[wasm:{}] {}", name, message),
+                        }
+                        Ok(())
+                    },
+                )
+                .map_err(wasm_error)?;
+
+            linker
+                .func_wrap(
+                    "cosmarium",
+                    "shared_state_get",
+                    |mut caller: Caller<'_, HostState>,
+                     ptr: i32,
+                     len: i32|
+                     -> wasmtime::Result<i64> {
+                        let key = read_guest_string(&mut caller, ptr, len)?;
+                        match caller.data().shared_state.get(&key).cloned() {
+                            Some(value) => write_guest_string(&mut caller, &value),
+                            None => Ok(0),
+                        }
+                    },
+                )
+                .map_err(wasm_error)?;
+
+            linker
+                .func_wrap(
+                    "cosmarium",
+                    "shared_state_set",
+                    |mut caller: Caller<'_, HostState>,
+                     key_ptr: i32,
+                     key_len: i32,
+                     value_ptr: i32,
+                     value_len: i32|
+                     -> wasmtime::Result<i32> {
+                        let key = read_guest_string(&mut caller, key_ptr, key_len)?;
+                        if !caller.data().capabilities.can_write(&key) {
+                            return Ok(-1);
+                        }
+                        let value = read_guest_string(&mut caller, value_ptr, value_len)?;
+                        let state = caller.data_mut();
+                        state.shared_state.insert(key.clone(), value.clone());
+                        state.pending_writes.push((key, value));
+                        Ok(0)
+                    },
+                )
+                .map_err(wasm_error)?;
+
+            linker
+                .func_wrap(
+                    "cosmarium",
+                    "emit_event",
+                    |mut caller: Caller<'_, HostState>,
+                     ptr: i32,
+                     len: i32|
+                     -> wasmtime::Result<i32> {
+                        if !caller.data().capabilities.emit_events {
+                            return Ok(-1);
+                        }
+                        let json = read_guest_string(&mut caller, ptr, len)?;
+                        match serde_json::from_str::<WasmEvent>(&json) {
+                            Ok(event) => {
+                                caller.data_mut().pending_events.push(event);
+                                Ok(0)
+                            }
+                            Err(_) => Ok(-1),
+                        }
+                    },
+                )
+                .map_err(wasm_error)?;
+
+            Ok(linker)
+        }
+    }
+
+    fn wasm_error(e: wasmtime::Error) -> Error {
+        Error::plugin(format!("WASM runtime error: {}", e))
+    }
+
+    fn guest_memory(caller: &mut Caller<'_, HostState>) -> wasmtime::Result<Memory> {
+        caller
+            .get_export("memory")
+            .and_then(|export| export.into_memory())
+            .ok_or_else(|| wasmtime::Error::msg("guest memory not exported"))
+    }
+
+    /// The `len` bytes at `ptr` in the guest memory `data`, if they are all
+    /// inside it.
+    fn guest_bytes(data: &[u8], ptr: u32, len: u32) -> Option<&[u8]> {
+        let start = ptr as usize;
+        data.get(start..start.checked_add(len as usize)?)
+    }
+
+    fn read_guest_string(
+        caller: &mut Caller<'_, HostState>,
+        ptr: i32,
+        len: i32,
+    ) -> wasmtime::Result<String> {
+        let memory = guest_memory(caller)?;
+        let bytes = guest_bytes(memory.data(&*caller), ptr as u32, len as u32)
+            .ok_or_else(|| wasmtime::Error::msg("string out of guest memory bounds"))?;
+        Ok(String::from_utf8(bytes.to_vec())?)
+    }
+
+    fn write_guest_string(
+        caller: &mut Caller<'_, HostState>,
+        value: &str,
+    ) -> wasmtime::Result<i64> {
+        let alloc = caller
+            .get_export("cosmarium_alloc")
+            .and_then(|export| export.into_func())
+            .ok_or_else(|| wasmtime::Error::msg("guest allocator not exported"))?
+            .typed::<i32, i32>(&*caller)?;
+        let ptr = alloc.call(&mut *caller, value.len() as i32)?;
+        let memory = guest_memory(caller)?;
+        memory.write(&mut *caller, ptr as u32 as usize, value.as_bytes())?;
+        Ok(pack_ptr_len(ptr as u32, value.len() as u32))
+    }
+
+    /// A loaded WebAssembly plugin.
+    ///
+    /// Implements [`Plugin`], and [`PanelPlugin`] when its manifest
+    /// describes a panel.
+    pub struct WasmPlugin {
+        guest: Guest,
+        manifest: WasmManifest,
+        enabled: bool,
+    }
+
+    /// Instance of a plugin's module and the store it runs in.
+    struct Guest {
+        store: Store<HostState>,
+        instance: Instance,
+        memory: Memory,
+        limits: WasmLimits,
+    }
+
+    impl Guest {
+        /// Call a guest export with a fresh fuel budget.
+        fn call<P, R>(&mut self, name: &str, params: P) -> Result<R>
+        where
+            P: wasmtime::WasmParams,
+            R: wasmtime::WasmResults,
+        {
+            self.store
+                .set_fuel(self.limits.fuel_per_call)
+                .map_err(wasm_error)?;
+            let func = self
+                .instance
+                .get_typed_func::<P, R>(&mut self.store, name)
+                .map_err(|e| Error::plugin(format!("Missing WASM export '{}': {}", name, e)))?;
+            func.call(&mut self.store, params)
+                .map_err(|e| Error::plugin(format!("WASM export '{}' failed: {}", name, e)))
+        }
+
+        fn read_string(&mut self, packed: i64) -> Result<String> {
+            let (ptr, len) = unpack_ptr_len(packed);
+            let bytes = guest_bytes(self.memory.data(&self.store), ptr, len)
+                .ok_or_else(|| Error::plugin("Invalid WASM memory access: out of bounds"))?;
+            String::from_utf8(bytes.to_vec())
+                .map_err(|e| Error::plugin(format!("WASM plugin returned invalid UTF-8: {}", e)))
+        }
+
+        fn write_string(&mut self, value: &str) -> Result<(i32, i32)> {
+            let ptr: i32 = self.call("cosmarium_alloc", value.len() as i32)?;
+            self.memory
+                .write(&mut self.store, ptr as u32 as usize, value.as_bytes())
+                .map_err(|e| Error::plugin(format!("Invalid WASM memory access: {}", e)))?;
+            Ok((ptr, value.len() as i32))
+        }
+    }
+
+    impl WasmPlugin {
+        /// Manifest declared by the plugin.
+        pub fn manifest(&self) -> &WasmManifest {
+            &self.manifest
+        }
+
+        /// Whether the plugin provides a panel.
+        pub fn has_panel(&self) -> bool {
+            self.manifest().panel.is_some()
+        }
+
+        /// Permissions granted to the plugin: those its manifest asks for
+        /// that the user approved.
+        pub fn capabilities(&self) -> &WasmCapabilities {
+            &self.guest.store.data().capabilities
+        }
+
+        /// Call a lifecycle export, exchanging shared state with the context.
+        fn call_lifecycle(&mut self, name: &str, ctx: &mut PluginContext) -> Result<()> {
+            self.load_shared_state(ctx);
+            let status: i32 = self.guest.call(name, ())?;
+            self.flush(ctx);
+            if status != 0 {
+                return Err(Error::plugin(format!(
+                    "WASM plugin '{}' returned status {} from {}",
+                    self.manifest().info.name,
+                    status,
+                    name
+                )));
+            }
+            Ok(())
+        }
+
+        /// Snapshot the readable shared state keys for the next call.
+        fn load_shared_state(&mut self, ctx: &PluginContext) {
+            let state = self.guest.store.data_mut();
+            state.shared_state = state
+                .capabilities
+                .shared_state_read
+                .iter()
+                .filter_map(|key| Some((key.clone(), ctx.get_shared_state::<String>(key)?)))
+                .collect();
+        }
+
+        /// Apply the writes and events produced by the last call.
+        fn flush(&mut self, ctx: &mut PluginContext) {
+            let state = self.guest.store.data_mut();
+            for (key, value) in state.pending_writes.drain(..) {
+                ctx.set_shared_state(&key, value);
+            }
+            for event in state.pending_events.drain(..) {
+                ctx.emit_event(
+                    Event::new(event.event_type, event.data).with_payload(event.payload),
+                );
+            }
+        }
+
+        fn render_widgets(&mut self, ui: &mut egui::Ui) -> Vec<WasmUiEvent> {
+            let widgets = self
+                .guest
+                .call::<(), i64>("cosmarium_render_panel", ())
+                .and_then(|packed| self.guest.read_string(packed))
+                .and_then(|json| Ok(serde_json::from_str::<Vec<WasmWidget>>(&json)?));
+            let widgets = match widgets {
+                Ok(widgets) => widgets,
+                Err(e) => {
+                    ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+                    return Vec::new();
+                }
+            };
+
+            let mut events = Vec::new();
+            for widget in widgets {
+                match widget {
+                    WasmWidget::Heading { text } => {
+                        ui.heading(text);
+                    }
+                    WasmWidget::Label { text } => {
+                        ui.label(text);
+                    }
+                    WasmWidget::Separator => {
+                        ui.separator();
+                    }
+                    WasmWidget::Button { id, label } => {
+                        if ui.button(label).clicked() {
+                            events.push(WasmUiEvent::Clicked { id });
+                        }
+                    }
+                    WasmWidget::Checkbox { id, label, checked } => {
+                        let mut value = checked;
+                        if ui.checkbox(&mut value, label).changed() {
+                            events.push(WasmUiEvent::Toggled { id, checked: value });
+                        }
+                    }
+                }
+            }
+            events
+        }
+
+        fn send_ui_event(&mut self, event: &WasmUiEvent) -> Result<()> {
+            let json = serde_json::to_string(event)?;
+            let (ptr, len) = self.guest.write_string(&json)?;
+            let _: i32 = self.guest.call("cosmarium_on_ui_event", (ptr, len))?;
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Plugin for WasmPlugin {
+        fn info(&self) -> PluginInfo {
+            self.manifest().info.clone()
+        }
+
+        fn initialize(&mut self, ctx: &mut PluginContext) -> cosmarium_plugin_api::Result<()> {
+            Ok(self.call_lifecycle("cosmarium_initialize", ctx)?)
+        }
+
+        async fn shutdown(&mut self, ctx: &mut PluginContext) -> cosmarium_plugin_api::Result<()> {
+            Ok(self.call_lifecycle("cosmarium_shutdown", ctx)?)
+        }
+
+        fn is_enabled(&self) -> bool {
+            self.enabled
+        }
+
+        fn set_enabled(
+            &mut self,
+            enabled: bool,
+            _ctx: &mut PluginContext,
+        ) -> cosmarium_plugin_api::Result<()> {
+            self.enabled = enabled;
+            Ok(())
+        }
+
+        fn plugin_type(&self) -> PluginType {
+            self.manifest().plugin_type
+        }
+
+        fn update(&mut self, ctx: &mut PluginContext) -> cosmarium_plugin_api::Result<()> {
+            Ok(self.call_lifecycle("cosmarium_update", ctx)?)
+        }
+    }
+
+    impl PanelPlugin for WasmPlugin {
+        fn panel_title(&self) -> &str {
+            self.manifest()
+                .panel
+                .as_ref()
+                .map_or(self.manifest().info.name.as_str(), |panel| {
+                    panel.title.as_str()
+                })
+        }
+
+        fn panel_icon(&self) -> &str {
+            self.manifest()
+                .panel
+                .as_ref()
+                .map_or("", |panel| panel.icon.as_str())
+        }
+
+        fn default_position(&self) -> PanelPosition {
+            self.manifest()
+                .panel
+                .as_ref()
+                .map_or_else(default_panel_position, |panel| panel.position)
+        }
+
+        fn update(&mut self, ctx: &mut PluginContext) -> cosmarium_plugin_api::Result<()> {
+            Ok(self.call_lifecycle("cosmarium_update", ctx)?)
+        }
+
+        fn render_panel(&mut self, ui: &mut egui::Ui, ctx: &mut PluginContext) {
+            self.load_shared_state(ctx);
+            let events = self.render_widgets(ui);
+            for event in &events {
+                if let Err(e) = self.send_ui_event(event) {
+                    tracing::error!("WASM plugin UI event failed: {}", e);
+                }
+            }
+            self.flush(ctx);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// Minimal guest written in the WebAssembly text format.
+        const GUEST: &str = r#"
+            (module
+              (import "cosmarium" "shared_state_set" (func $set (param i32 i32 i32 i32) (result i32)))
+              (memory (export "memory") 1)
+              (global $next (mut i32) (i32.const 4096))
+              (data (i32.const 0) "{\"info\":{\"name\":\"hello\",\"version\":\"1.0.0\",\"description\":\"Test\",\"author\":\"Me\"},\"plugin_type\":\"Utility\",\"capabilities\":{\"shared_state_write\":[\"greeting\"]}}")
+              (data (i32.const 2048) "greetinghi")
+              (func (export "cosmarium_abi_version") (result i32) (i32.const 1))
+              (func (export "cosmarium_alloc") (param $len i32) (result i32)
+                (local $ptr i32)
+                (local.set $ptr (global.get $next))
+                (global.set $next (i32.add (global.get $next) (local.get $len)))
+                (local.get $ptr))
+              (func (export "cosmarium_manifest") (result i64)
+                (i64.const 153))
+              (func (export "cosmarium_initialize") (result i32)
+                (call $set (i32.const 2048) (i32.const 8) (i32.const 2056) (i32.const 2)))
+              (func (export "cosmarium_update") (result i32) (i32.const 0))
+              (func (export "cosmarium_shutdown") (result i32) (i32.const 0))
+              (func (export "spin") (loop $l (br $l))))
+        "#;
+
+        #[test]
+        fn test_load_and_initialize_guest() {
+            let mut host = WasmPluginHost::new(WasmLimits::default()).unwrap();
+            let mut plugin = host.load_bytes(GUEST.as_bytes()).unwrap();
+            assert_eq!(plugin.info().name, "hello");
+            assert_eq!(plugin.plugin_type(), PluginType::Utility);
+            assert!(!plugin.has_panel());
+
+            // Nothing is granted until approved
+            assert_eq!(plugin.capabilities(), &WasmCapabilities::default());
+            let mut ctx = PluginContext::new();
+            assert!(plugin.initialize(&mut ctx).is_err());
+            assert!(ctx.get_shared_state::<String>("greeting").is_none());
+
+            host.approve(
+                "hello",
+                WasmCapabilities {
+                    shared_state_write: vec!["greeting".to_string()],
+                    emit_events: true,
+                    ..WasmCapabilities::default()
+                },
+            );
+            let mut plugin = host.load_bytes(GUEST.as_bytes()).unwrap();
+            assert!(plugin.capabilities().can_write("greeting"));
+            assert!(!plugin.capabilities().emit_events);
+            let mut ctx = PluginContext::new();
+            plugin.initialize(&mut ctx).unwrap();
+            assert_eq!(
+                ctx.get_shared_state::<String>("greeting").as_deref(),
+                Some("hi")
+            );
+        }
+
+        #[test]
+        fn test_runaway_guest_runs_out_of_fuel() {
+            let host = WasmPluginHost::new(WasmLimits {
+                fuel_per_call: 10_000,
+                ..WasmLimits::default()
+            })
+            .unwrap();
+            let mut plugin = host.load_bytes(GUEST.as_bytes()).unwrap();
+            assert!(plugin.guest.call::<(), ()>("spin", ()).is_err());
+        }
+
+        #[test]
+        fn test_out_of_bounds_strings_are_refused() {
+            let host = WasmPluginHost::new(WasmLimits::default()).unwrap();
+            let mut plugin = host.load_bytes(GUEST.as_bytes()).unwrap();
+            assert!(plugin.guest.read_string(pack_ptr_len(0, u32::MAX)).is_err());
+            assert!(plugin.guest.read_string(pack_ptr_len(u32::MAX, 2)).is_err());
+            assert_eq!(
+                plugin.guest.read_string(pack_ptr_len(2048, 8)).unwrap(),
+                "greeting"
+            );
+            assert_eq!(guest_bytes(b"abc", 1, 2), Some(&b"bc"[..]));
+            assert_eq!(guest_bytes(b"abc", 2, 2), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_roundtrip() {
+        assert_eq!(unpack_ptr_len(pack_ptr_len(0, 0)), (0, 0));
+        assert_eq!(
+            unpack_ptr_len(pack_ptr_len(u32::MAX, u32::MAX)),
+            (u32::MAX, u32::MAX)
+        );
+    }
+
+    #[test]
+    fn test_manifest_defaults() {
+        let manifest: WasmManifest = serde_json::from_str(
+            r#"{
+                "info": {"name": "word-goal", "version": "0.1.0", "description": "Goal", "author": "A"},
+                "plugin_type": "Panel",
+                "panel": {"title": "Goal"}
+            }"#,
+        )
+        .unwrap();
+
+        let panel = manifest.panel.unwrap();
+        assert_eq!(panel.position, PanelPosition::Right);
+        assert!(panel.icon.is_empty());
+        assert_eq!(manifest.capabilities, WasmCapabilities::default());
+        assert!(!manifest.capabilities.can_read("markdown_editor_content"));
+    }
+
+    #[test]
+    fn test_widget_json() {
+        let widgets: Vec<WasmWidget> = serde_json::from_str(
+            r#"[{"kind": "heading", "text": "Goal"},
+                {"kind": "separator"},
+                {"kind": "button", "id": "reset", "label": "Reset"}]"#,
+        )
+        .unwrap();
+        assert_eq!(widgets.len(), 3);
+        assert_eq!(widgets[1], WasmWidget::Separator);
+
+        let event = serde_json::to_string(&WasmUiEvent::Clicked {
+            id: "reset".to_string(),
+        })
+        .unwrap();
+        assert_eq!(event, r#"{"kind":"clicked","id":"reset"}"#);
+    }
+}
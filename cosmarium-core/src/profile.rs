@@ -0,0 +1,192 @@
+//! # Author profiles
+//!
+//! Writers publishing under several names keep a profile of each identity
+//! in the application [`Config`](crate::Config): their legal name, the pen
+//! names they sign with, a short biography and the contact details of their
+//! submissions. A project names in its metadata the profile and the pen
+//! name it is published under, and compiling and exporting it sign with the
+//! resulting [`AuthorIdentity`]. Projects without a profile are signed with
+//! the author of their metadata.
+//!
+//! Front matter templates refer to the identity with placeholders:
+//! `{author}` for the name on the cover, `{legal_name}`, `{bio}` and
+//! `{contact}`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_core::profile::{AuthorIdentity, AuthorProfile};
+//! use cosmarium_core::project::ProjectMetadata;
+//!
+//! let profile = AuthorProfile {
+//!     legal_name: "Ann Smith".to_string(),
+//!     pen_names: vec!["A. S. Moor".to_string()],
+//!     ..AuthorProfile::new("Thrillers")
+//! };
+//! let mut metadata = ProjectMetadata::new("The Inn", "novel");
+//! metadata.profile = "Thrillers".to_string();
+//! metadata.pen_name = "A. S. Moor".to_string();
+//!
+//! let identity = AuthorIdentity::of_project(&metadata, &[profile]);
+//! assert_eq!(identity.fill("by {author}"), "by A. S. Moor");
+//! ```
+
+use crate::project::ProjectMetadata;
+use serde::{Deserialize, Serialize};
+
+/// One identity of the author.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthorProfile {
+    /// Name of the profile, as projects refer to it
+    pub name: String,
+    /// Name of the author on contracts and submissions
+    pub legal_name: String,
+    /// Names the author signs with, the first one by default
+    pub pen_names: Vec<String>,
+    /// Short biography, for covers and author pages
+    pub bio: String,
+    /// Name, address, e-mail and phone, one per line, for the title page of
+    /// standard manuscripts
+    pub contact_info: String,
+}
+
+impl AuthorProfile {
+    /// Create an empty profile.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// The identity of the profile signing as `pen_name`: its first pen
+    /// name if `pen_name` is not one of them, and its legal name if it has
+    /// none.
+    pub fn identity(&self, pen_name: &str) -> AuthorIdentity {
+        let pen_names: Vec<&str> = self
+            .pen_names
+            .iter()
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .collect();
+        let byline = pen_names
+            .iter()
+            .find(|name| **name == pen_name.trim())
+            .or(pen_names.first())
+            .copied()
+            .unwrap_or(self.legal_name.trim());
+
+        let mut names: Vec<String> = Vec::new();
+        for name in std::iter::once(self.legal_name.trim()).chain(pen_names) {
+            if !name.is_empty() && !names.iter().any(|known| known == name) {
+                names.push(name.to_string());
+            }
+        }
+        AuthorIdentity {
+            byline: byline.to_string(),
+            legal_name: self.legal_name.trim().to_string(),
+            bio: self.bio.trim().to_string(),
+            contact_info: self.contact_info.trim().to_string(),
+            names,
+        }
+    }
+}
+
+/// Who a manuscript is by.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthorIdentity {
+    /// Name on the cover and title page
+    pub byline: String,
+    /// Legal name of the author
+    pub legal_name: String,
+    /// Short biography
+    pub bio: String,
+    /// Contact details, empty to use those of the compile settings
+    pub contact_info: String,
+    /// Legal name and every pen name of the author, removed from
+    /// anonymized exports
+    pub names: Vec<String>,
+}
+
+impl AuthorIdentity {
+    /// The identity `metadata` is published under: that of its profile
+    /// among `profiles`, or its author if it names none or an unknown one.
+    pub fn of_project(metadata: &ProjectMetadata, profiles: &[AuthorProfile]) -> Self {
+        if let Some(profile) = find(profiles, &metadata.profile) {
+            return profile.identity(&metadata.pen_name);
+        }
+        let author = metadata.author.trim().to_string();
+        Self {
+            byline: author.clone(),
+            legal_name: author.clone(),
+            names: (!author.is_empty()).then_some(author).into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// `template` with the placeholders of the identity replaced.
+    pub fn fill(&self, template: &str) -> String {
+        template
+            .replace("{author}", &self.byline)
+            .replace("{legal_name}", &self.legal_name)
+            .replace("{bio}", &self.bio)
+            .replace("{contact}", &self.contact_info)
+    }
+}
+
+/// The profile of `profiles` named `name`, if any.
+pub fn find<'a>(profiles: &'a [AuthorProfile], name: &str) -> Option<&'a AuthorProfile> {
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    profiles.iter().find(|profile| profile.name.trim() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> AuthorProfile {
+        AuthorProfile {
+            legal_name: "Ann Smith".to_string(),
+            pen_names: vec!["A. S. Moor".to_string(), " Annie Vale ".to_string()],
+            contact_info: "Ann Smith\nann@example.com\n".to_string(),
+            ..AuthorProfile::new("Fiction")
+        }
+    }
+
+    #[test]
+    fn test_identity_of_a_profile() {
+        let identity = profile().identity("Annie Vale");
+        assert_eq!(identity.byline, "Annie Vale");
+        assert_eq!(identity.contact_info, "Ann Smith\nann@example.com");
+        assert_eq!(identity.names, ["Ann Smith", "A. S. Moor", "Annie Vale"]);
+
+        // Unknown pen names fall back on the first one, then the legal name
+        assert_eq!(profile().identity("Bob").byline, "A. S. Moor");
+        let legal = AuthorProfile {
+            pen_names: vec![String::new()],
+            ..profile()
+        };
+        assert_eq!(legal.identity("").byline, "Ann Smith");
+    }
+
+    #[test]
+    fn test_identity_of_a_project() {
+        let mut metadata = ProjectMetadata::new("The Inn", "novel");
+        metadata.author = "Bob".to_string();
+        metadata.profile = "Fiction".to_string();
+        assert_eq!(
+            AuthorIdentity::of_project(&metadata, &[profile()]).byline,
+            "A. S. Moor"
+        );
+
+        // Without the profile, the project's author signs
+        let identity = AuthorIdentity::of_project(&metadata, &[]);
+        assert_eq!(identity.byline, "Bob");
+        assert_eq!(identity.names, ["Bob"]);
+        assert_eq!(identity.fill("{author} ({legal_name}){bio}"), "Bob (Bob)");
+    }
+}
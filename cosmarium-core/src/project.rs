@@ -8,17 +8,37 @@
 //! or as directory structures, providing flexibility for different workflows
 //! and collaboration needs.
 
+pub mod duplicate;
+pub mod location;
+pub mod migration;
+pub mod mirror;
+pub mod store;
+pub mod template;
+
+use crate::document::LineEnding;
+use crate::structure::{ProjectStructure, StructureNode};
 use crate::{events::EventBus, git::GitIntegration, Error, Result};
+use cosmarium_plugin_api::git_history::{ChangedDocument, ProjectChanges};
+use cosmarium_plugin_api::metadata::SceneMetadata;
 use cosmarium_plugin_api::{Event, EventType};
+use migration::{MigrationReport, FORMAT_VERSION};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::SystemTime;
+use store::LoadDiagnostic;
+use template::ProjectTemplate;
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+/// Folder of the documents, relative to the project root.
+const CONTENT_DIR: &str = "content";
+
+/// File extensions of the documents.
+const DOCUMENT_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
 /// Project management system for Cosmarium.
 ///
 /// The [`ProjectManager`] handles all project-related operations including
@@ -241,7 +261,10 @@ impl ProjectManager {
             .await
             .map_err(|e| Error::project(format!("Failed to create project directory: {}", e)))?;
 
-        let project = Project::new(name, path, template)?;
+        let mut project = Project::new(name, path, template)?;
+        if let Some(template) = ProjectTemplate::from_id(template) {
+            template.create(&mut project)?;
+        }
 
         // Save project to close current one if any
         let need_save = if let Some(current_project) = &self.active_project {
@@ -333,10 +356,29 @@ impl ProjectManager {
 
         // Load project
         let project = Project::load(path).await?;
+        self.activate_project(project).await
+    }
+
+    /// Make `project`, loaded with [`Project::load`], the active project,
+    /// saving the one it replaces if it has unsaved changes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project replaced cannot be saved.
+    pub async fn activate_project(&mut self, project: Project) -> Result<()> {
+        if let Some(current_project) = self
+            .active_project
+            .as_mut()
+            .filter(|current_project| current_project.has_unsaved_changes())
+        {
+            current_project.save().await?;
+        }
+
+        let path = project.path().to_path_buf();
         let project_name = project.name().to_string();
 
         self.active_project = Some(project);
-        self.add_to_recent_projects(path.to_path_buf());
+        self.add_to_recent_projects(path.clone());
 
         // Emit project opened event
         if let Some(ref event_bus) = self.event_bus {
@@ -375,6 +417,38 @@ impl ProjectManager {
     /// # });
     /// ```
     pub async fn save_project(&mut self) -> Result<()> {
+        self.save_and_commit_project(None).await.map(|_| ())
+    }
+
+    /// Save the current project, then commit its changes as
+    /// [`Project::save_and_commit`] does. Returns the identifier of the
+    /// commit made, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project cannot be saved, or its changes
+    /// cannot be committed under `message`.
+    pub async fn save_and_commit_project(
+        &mut self,
+        message: Option<&str>,
+    ) -> Result<Option<String>> {
+        match self.save_project_for_commit(message).await? {
+            Some(commit) => commit.run(),
+            None => Ok(None),
+        }
+    }
+
+    /// Save the current project, and return the commit of its changes to
+    /// make apart from it, as [`Project::save_for_commit`] does.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project cannot be saved, or its changes
+    /// cannot be committed under `message`.
+    pub async fn save_project_for_commit(
+        &mut self,
+        message: Option<&str>,
+    ) -> Result<Option<PendingCommit>> {
         let project = self
             .active_project
             .as_mut()
@@ -383,7 +457,7 @@ impl ProjectManager {
         let name = project.name().to_string();
 
         // Save the project
-        project.save().await?;
+        let commit = project.save_for_commit(message).await?;
 
         // Emit project saved event
         if let Some(ref event_bus) = self.event_bus {
@@ -392,7 +466,7 @@ impl ProjectManager {
             let _ = bus.emit(event).await;
         }
 
-        Ok(())
+        Ok(commit)
     }
 
     /// Close the current project.
@@ -666,17 +740,37 @@ pub struct Project {
     git: Option<GitIntegration>,
     /// Whether the project has unsaved changes
     has_unsaved_changes: bool,
+    /// Problems met while loading the project state
+    diagnostics: Vec<LoadDiagnostic>,
+    /// Upgrade of the project's format made while loading it
+    migration: Option<MigrationReport>,
+    /// Hashes of the state files as last saved
+    written: HashMap<&'static str, u64>,
 }
 
 /// Persisted state of the project, saved to `meta/core.toon`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectState {
+    /// Version of the format the state was saved with
+    #[serde(default = "migration::unversioned")]
+    format_version: u32,
     /// Project metadata
     metadata: ProjectMetadata,
     /// Document references
+    #[serde(default)]
     documents: Vec<Uuid>,
+    /// Order of the content folders and files saved by earlier versions,
+    /// read once to build the structure
+    #[serde(default, skip_serializing)]
+    document_order: Vec<String>,
+    /// Parts, chapters and documents of the project, in order
+    #[serde(default)]
+    structure: ProjectStructure,
     /// Project settings
     settings: ProjectSettings,
+    /// Sections saved to their own file under `meta/`
+    #[serde(default, skip_serializing)]
+    split: Vec<String>,
 }
 
 impl Project {
@@ -696,9 +790,13 @@ impl Project {
         let metadata = ProjectMetadata::new(name, template);
 
         let state = ProjectState {
+            format_version: FORMAT_VERSION,
             metadata,
             documents: Vec::new(),
+            document_order: Vec::new(),
+            structure: ProjectStructure::default(),
             settings: ProjectSettings::default(),
+            split: Vec::new(),
         };
 
         // Initialize Git repo
@@ -715,6 +813,9 @@ impl Project {
             path,
             git,
             has_unsaved_changes: true,
+            diagnostics: Vec::new(),
+            migration: None,
+            written: HashMap::new(),
         };
 
         Ok(project)
@@ -732,18 +833,14 @@ impl Project {
     pub async fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref();
         let meta_dir = path.join("meta");
-        let core_file = meta_dir.join("core.toon");
 
         // Fallback for legacy project.json
         let legacy_file = path.join("project.json");
 
-        let state = if core_file.exists() {
-            let content = tokio::fs::read_to_string(&core_file)
-                .await
-                .map_err(|e| Error::project(format!("Failed to read core metadata: {}", e)))?;
-
-            serde_toon2::from_str(&content)
-                .map_err(|e| Error::project(format!("Failed to parse core metadata: {}", e)))?
+        let mut diagnostics = Vec::new();
+        let mut state = if let Some((state, problems)) = store::load_state(path, &meta_dir).await {
+            diagnostics = problems;
+            state
         } else if legacy_file.exists() {
             // Legacy load
             let content = tokio::fs::read_to_string(&legacy_file).await.map_err(|e| {
@@ -763,9 +860,13 @@ impl Project {
             })?;
 
             ProjectState {
+                format_version: migration::LEGACY_VERSION,
                 metadata: legacy.metadata,
                 documents: legacy.documents,
+                document_order: Vec::new(),
+                structure: ProjectStructure::default(),
                 settings: legacy.settings,
+                split: Vec::new(),
             }
         } else {
             return Err(Error::project("Project metadata not found"));
@@ -786,20 +887,104 @@ impl Project {
             }
         };
 
-        Ok(Self {
+        let migration = migration::migrate(path, &mut state)?;
+
+        // A recovered state is saved again on the next save
+        let has_unsaved_changes = !diagnostics.is_empty();
+        let mut project = Self {
             state,
             path: path.to_path_buf(),
             git,
-            has_unsaved_changes: false,
-        })
+            has_unsaved_changes,
+            diagnostics,
+            migration,
+            written: HashMap::new(),
+        };
+        if let Some(report) = &project.migration {
+            // The old state must not be read again once the new one is saved
+            store::save_state(&meta_dir, &project.state, &mut project.written).await?;
+            for file in &report.obsolete {
+                if let Err(e) = tokio::fs::remove_file(file).await {
+                    warn!("Failed to remove {:?}: {}", file, e);
+                }
+            }
+            if let Some(git) = &project.git {
+                let message = format!("Upgrade project to format {}", report.to);
+                if let Err(e) = git.commit(&message) {
+                    warn!("Failed to commit changes: {}", e);
+                }
+            }
+        }
+
+        // Projects saved before the structure existed only had a flat order
+        if project.state.structure.is_empty() {
+            let order = std::mem::take(&mut project.state.document_order);
+            project.state.structure.sync_with_content(&project.path);
+            project.state.structure.sort_by_paths(&order);
+        }
+        Ok(project)
     }
 
-    /// Save the project to disk.
+    /// Read the metadata and structure of the project at `path` without
+    /// loading it: nothing is migrated, repaired or written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project state cannot be read.
+    pub async fn read_outline<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<(ProjectMetadata, ProjectStructure)> {
+        let path = path.as_ref();
+        let Some((state, _)) = store::load_state(path, &path.join("meta")).await else {
+            return Err(Error::project("Project metadata not found"));
+        };
+        let mut structure = state.structure;
+        if structure.is_empty() {
+            structure.sync_with_content(path);
+            structure.sort_by_paths(&state.document_order);
+        }
+        Ok((state.metadata, structure))
+    }
+
+    /// Save the project to disk, then commit its changes with a summary of
+    /// them if [`ProjectSettings::auto_commit`] is on.
     ///
     /// # Errors
     ///
     /// Returns an error if the project cannot be saved.
     pub async fn save(&mut self) -> Result<()> {
+        self.save_and_commit(None).await.map(|_| ())
+    }
+
+    /// Save the project to disk, then commit its changes: with `message`,
+    /// or with a summary of them (see [`ProjectChanges::message`]) when it
+    /// is `None` and [`ProjectSettings::auto_commit`] is on. Returns the
+    /// identifier of the commit made, `None` when nothing was committed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project cannot be saved, or its changes
+    /// cannot be committed under `message`. Failing to commit them under a
+    /// summary is only logged.
+    pub async fn save_and_commit(&mut self, message: Option<&str>) -> Result<Option<String>> {
+        match self.save_for_commit(message).await? {
+            Some(commit) => commit.run(),
+            None => Ok(None),
+        }
+    }
+
+    /// Save the project to disk, and return the commit of its changes that
+    /// [`Self::save_and_commit`] would make, to be made apart from the
+    /// project, on a background task for instance.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project cannot be saved, or if `message` is
+    /// empty or the project has no repository to commit under it.
+    pub async fn save_for_commit(
+        &mut self,
+        message: Option<&str>,
+    ) -> Result<Option<PendingCommit>> {
         let meta_dir = self.path.join("meta");
         let content_dir = self.path.join("content");
 
@@ -811,28 +996,80 @@ impl Project {
             .await
             .map_err(|e| Error::project(format!("Failed to create content directory: {}", e)))?;
 
-        let core_file = meta_dir.join("core.toon");
-
         // Update metadata
         self.state.metadata.last_modified = SystemTime::now();
 
-        // Serialize to TOON
-        let content = serde_toon2::to_string(&self.state)
-            .map_err(|e| Error::project(format!("Failed to serialize project state: {}", e)))?;
-
-        tokio::fs::write(&core_file, content)
-            .await
-            .map_err(|e| Error::project(format!("Failed to write core metadata: {}", e)))?;
+        store::save_state(&meta_dir, &self.state, &mut self.written).await?;
+        self.has_unsaved_changes = false;
 
-        // Commit changes if git is available
-        if let Some(git) = &self.git {
-            if let Err(e) = git.commit("Save project") {
-                warn!("Failed to commit changes: {}", e);
+        match (message, &self.git) {
+            (Some(message), _) => {
+                let message = message.trim();
+                if message.is_empty() {
+                    return Err(Error::project("A commit needs a message"));
+                }
+                Ok(Some(PendingCommit {
+                    git: self.require_git()?.clone(),
+                    message: Some(message.to_string()),
+                }))
             }
+            // A merge is committed once its conflicts are resolved
+            (None, Some(git))
+                if self.state.settings.auto_commit && !matches!(git.merging(), Ok(Some(_))) =>
+            {
+                Ok(Some(PendingCommit {
+                    git: git.clone(),
+                    message: None,
+                }))
+            }
+            (None, _) => Ok(None),
         }
+    }
 
-        self.has_unsaved_changes = false;
-        Ok(())
+    /// Commit the changes of the project with a summary of them (see
+    /// [`ProjectChanges::message`]). Returns the identifier of the commit,
+    /// `None` when nothing changed since the last one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project has no repository or the commit
+    /// fails.
+    pub fn commit_changes(&self) -> Result<Option<String>> {
+        commit_changes_in(self.require_git()?)
+    }
+
+    /// Changes of the project since its last commit: the documents changed,
+    /// with their words then and now, and the number of other files changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project has no repository or it cannot be
+    /// read.
+    pub fn changes(&self) -> Result<ProjectChanges> {
+        changes_in(self.require_git()?)
+    }
+
+    /// Commit the files of the project with `message`. Returns the
+    /// identifier of the commit, `None` when nothing changed since the last
+    /// one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project has no repository or the commit
+    /// fails.
+    pub fn commit(&self, message: &str) -> Result<Option<String>> {
+        let message = message.trim();
+        if message.is_empty() {
+            return Err(Error::project("A commit needs a message"));
+        }
+        self.require_git()?.commit(message)
+    }
+
+    /// The repository of the project, an error if it has none.
+    fn require_git(&self) -> Result<&GitIntegration> {
+        self.git
+            .as_ref()
+            .ok_or_else(|| Error::project("The project has no Git repository"))
     }
 
     /// Get the project name.
@@ -866,6 +1103,17 @@ impl Project {
         self.has_unsaved_changes
     }
 
+    /// Problems met while loading the project state, and how they were
+    /// recovered from.
+    pub fn load_diagnostics(&self) -> &[LoadDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Upgrade of the project's format made while loading it, if any.
+    pub fn migration_report(&self) -> Option<&MigrationReport> {
+        self.migration.as_ref()
+    }
+
     /// Get the project settings.
     pub fn settings(&self) -> &ProjectSettings {
         &self.state.settings
@@ -903,6 +1151,120 @@ impl Project {
         &self.state.documents
     }
 
+    /// Get the project structure.
+    pub fn structure(&self) -> &ProjectStructure {
+        &self.state.structure
+    }
+
+    /// Get the mutable project structure.
+    pub fn structure_mut(&mut self) -> &mut ProjectStructure {
+        self.mark_modified();
+        &mut self.state.structure
+    }
+
+    /// Add a node under `parent` (`None` for the top level) at `index`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parent does not exist or is a document.
+    pub fn add_node(
+        &mut self,
+        parent: Option<Uuid>,
+        index: usize,
+        node: StructureNode,
+    ) -> Result<Uuid> {
+        let id = self.state.structure.insert(parent, index, node)?;
+        self.mark_modified();
+        Ok(id)
+    }
+
+    /// Move a node with its children under `parent` (`None` for the top
+    /// level) at `index`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the move would break the structure.
+    pub fn move_node(&mut self, id: Uuid, parent: Option<Uuid>, index: usize) -> Result<()> {
+        self.state.structure.move_node(id, parent, index)?;
+        self.mark_modified();
+        Ok(())
+    }
+
+    /// Move a node to `index` among its siblings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node does not exist.
+    pub fn reorder_node(&mut self, id: Uuid, index: usize) -> Result<()> {
+        self.state.structure.reorder(id, index)?;
+        self.mark_modified();
+        Ok(())
+    }
+
+    /// Move a node to the end of `parent`'s children.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the move would break the structure.
+    pub fn nest_node(&mut self, id: Uuid, parent: Uuid) -> Result<()> {
+        self.state.structure.nest(id, parent)?;
+        self.mark_modified();
+        Ok(())
+    }
+
+    /// Structure node of the file at `path`, absolute or relative to the
+    /// project root.
+    pub fn node_of_file(&self, path: &Path) -> Option<&StructureNode> {
+        let rel = path.strip_prefix(&self.path).unwrap_or(path);
+        let key = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        self.state.structure.find_by_path(&key)
+    }
+
+    /// Set the synopsis, POV, status, label and target of a node.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node does not exist.
+    pub fn set_scene_metadata(&mut self, id: Uuid, metadata: &SceneMetadata) -> Result<()> {
+        let node = self
+            .state
+            .structure
+            .find_mut(id)
+            .ok_or_else(|| Error::not_found(format!("structure node {}", id)))?;
+        if node.scene_metadata() != *metadata {
+            node.set_scene_metadata(metadata);
+            self.mark_modified();
+        }
+        Ok(())
+    }
+
+    /// Bring the structure in line with the content directory.
+    pub fn sync_structure(&mut self) {
+        if self.state.structure.sync_with_content(&self.path) {
+            self.mark_modified();
+        }
+    }
+
+    /// Get the binder order of the content folders and files.
+    ///
+    /// Entries are paths relative to the project root, with `/` separators,
+    /// in structure order.
+    pub fn document_order(&self) -> Vec<String> {
+        self.state.structure.paths()
+    }
+
+    /// Sort the content folders and files of the structure in `order`,
+    /// among their siblings.
+    pub fn set_document_order(&mut self, order: Vec<String>) {
+        if self.state.structure.sort_by_paths(&order) {
+            self.mark_modified();
+        }
+    }
+
     /// Update method for project maintenance.
     pub async fn update(&mut self) -> Result<()> {
         // Project-specific update logic would go here
@@ -916,6 +1278,74 @@ impl Project {
     }
 }
 
+/// Changes of the project whose repository is `git` since its last commit
+/// (see [`Project::changes`]).
+fn changes_in(git: &GitIntegration) -> Result<ProjectChanges> {
+    let words = |content: &Option<Vec<u8>>| {
+        content
+            .as_deref()
+            .map(|content| String::from_utf8_lossy(content).split_whitespace().count())
+    };
+    let mut changes = ProjectChanges::default();
+    for file in git.changes()? {
+        let document = file.path.starts_with(CONTENT_DIR)
+            && file
+                .path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| DOCUMENT_EXTENSIONS.contains(&extension));
+        if document {
+            changes.documents.push(ChangedDocument {
+                words_before: words(&file.before),
+                words_after: words(&file.after),
+                path: file.path,
+            });
+        } else {
+            changes.other_files += 1;
+        }
+    }
+    Ok(changes)
+}
+
+/// Commit the changes of the project whose repository is `git` with a
+/// summary of them (see [`Project::commit_changes`]).
+fn commit_changes_in(git: &GitIntegration) -> Result<Option<String>> {
+    let changes = changes_in(git)?;
+    if changes.is_empty() {
+        return Ok(None);
+    }
+    git.commit(&changes.message())
+}
+
+/// Commit of the changes of a saved project, made apart from it (see
+/// [`Project::save_for_commit`]).
+#[derive(Debug, Clone)]
+pub struct PendingCommit {
+    /// Repository of the project
+    git: GitIntegration,
+    /// Message of the commit, a summary of the changes when `None`
+    message: Option<String>,
+}
+
+impl PendingCommit {
+    /// Commit the changes. Returns the identifier of the commit, `None`
+    /// when nothing changed since the last one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the commit with a message fails. Failing to
+    /// commit under a summary is only logged.
+    pub fn run(&self) -> Result<Option<String>> {
+        match &self.message {
+            Some(message) => self.git.commit(message),
+            None => Ok(commit_changes_in(&self.git).unwrap_or_else(|e| {
+                warn!("Failed to commit changes: {}", e);
+                None
+            })),
+        }
+    }
+}
+
 /// Project metadata.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectMetadata {
@@ -923,8 +1353,15 @@ pub struct ProjectMetadata {
     pub name: String,
     /// Project description
     pub description: String,
-    /// Project author
+    /// Project author, when it is published under no profile
     pub author: String,
+    /// Name of the author profile it is published under, empty for none;
+    /// see [`crate::profile`]
+    #[serde(default)]
+    pub profile: String,
+    /// Pen name of the profile it is signed with, empty for the first one
+    #[serde(default)]
+    pub pen_name: String,
     /// Project version
     pub version: String,
     /// Creation time
@@ -948,6 +1385,8 @@ impl ProjectMetadata {
             name: name.to_string(),
             description: String::new(),
             author: String::new(),
+            profile: String::new(),
+            pen_name: String::new(),
             version: "1.0.0".to_string(),
             created: now,
             last_modified: now,
@@ -969,6 +1408,12 @@ pub struct ProjectSettings {
     pub backup_enabled: bool,
     /// Number of backups to keep
     pub backup_count: usize,
+    /// Line ending of new documents and text exports
+    #[serde(default)]
+    pub line_ending: LineEnding,
+    /// Whether saving the project commits its changes
+    #[serde(default = "default_true")]
+    pub auto_commit: bool,
     /// Custom settings
     pub custom: HashMap<String, serde_json::Value>,
 }
@@ -980,11 +1425,17 @@ impl Default for ProjectSettings {
             auto_save_interval: 30,
             backup_enabled: true,
             backup_count: 5,
+            line_ending: LineEnding::default(),
+            auto_commit: true,
             custom: HashMap::new(),
         }
     }
 }
 
+fn default_true() -> bool {
+    true
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1071,6 +1522,78 @@ mod tests {
         assert_eq!(project.documents().len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_document_order_is_saved() {
+        let temp_dir = make_tempdir();
+        let project_path = temp_dir.join("order_test");
+        tokio::fs::create_dir_all(&project_path).await.unwrap();
+
+        for dir in ["content/part1", "content/part2"] {
+            tokio::fs::create_dir_all(project_path.join(dir))
+                .await
+                .unwrap();
+        }
+        tokio::fs::write(project_path.join("content/part1/b.md"), "Text")
+            .await
+            .unwrap();
+
+        let mut project = Project::new("Order Test", &project_path, "novel").unwrap();
+        project.save().await.unwrap();
+        project.sync_structure();
+        let order = vec![
+            "content/part2".to_string(),
+            "content/part1".to_string(),
+            "content/part1/b.md".to_string(),
+        ];
+        project.set_document_order(order.clone());
+        assert!(project.has_unsaved_changes());
+        project.save().await.unwrap();
+
+        let loaded = Project::load(&project_path).await.unwrap();
+        assert_eq!(loaded.document_order(), order.as_slice());
+    }
+
+    #[tokio::test]
+    async fn test_structure_is_saved() {
+        use crate::structure::NodeKind;
+
+        let temp_dir = make_tempdir();
+        let project_path = temp_dir.join("structure_test");
+        tokio::fs::create_dir_all(project_path.join("content"))
+            .await
+            .unwrap();
+        tokio::fs::write(project_path.join("content/arrival.md"), "Text")
+            .await
+            .unwrap();
+
+        let mut project = Project::new("Structure Test", &project_path, "novel").unwrap();
+        project.sync_structure();
+        let part = project
+            .add_node(None, 0, StructureNode::new(NodeKind::Part, "Part One"))
+            .unwrap();
+        let scene = project
+            .node_of_file(&project_path.join("content/arrival.md"))
+            .unwrap()
+            .id;
+        project.nest_node(scene, part).unwrap();
+        assert!(project.nest_node(part, scene).is_err());
+        let metadata = SceneMetadata {
+            synopsis: "The ship docks.".to_string(),
+            target_words: Some(2000),
+            ..Default::default()
+        };
+        project.set_scene_metadata(scene, &metadata).unwrap();
+        project.save().await.unwrap();
+
+        let loaded = Project::load(&project_path).await.unwrap();
+        let roots = loaded.structure().roots();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].title, "Part One");
+        assert_eq!(roots[0].children[0].id, scene);
+        assert_eq!(roots[0].children[0].scene_metadata(), metadata);
+        assert!(!loaded.has_unsaved_changes());
+    }
+
     #[test]
     fn test_project_metadata() {
         let metadata = ProjectMetadata::new("Test Project", "novel");
@@ -1143,6 +1666,7 @@ mod tests {
             project_path.join("content").is_dir(),
             "content/ directory should exist"
         );
+        #[cfg(feature = "git")]
         assert!(
             project_path.join(".git").is_dir(),
             ".git/ directory should exist"
@@ -1155,6 +1679,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "git")]
     #[tokio::test]
     async fn test_project_git_initialization() {
         let temp_dir = make_tempdir();
@@ -1178,6 +1703,64 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "git")]
+    #[tokio::test]
+    async fn test_saving_commits_a_summary_of_the_changes() {
+        let dir = tempdir().unwrap();
+        let mut project = Project::new("Storm", dir.path(), "novel").unwrap();
+        std::fs::create_dir_all(dir.path().join("content")).unwrap();
+        std::fs::write(dir.path().join("content/inn.md"), "It rained all night.").unwrap();
+
+        let changes = project.changes().unwrap();
+        assert_eq!(changes.documents.len(), 1);
+        assert_eq!(changes.documents[0].words_after, Some(4));
+        project.save().await.unwrap();
+        let git = project.git().unwrap();
+        let history = git.file_history("content/inn.md", 10).unwrap();
+        assert_eq!(history[0].summary, "Add inn (+4 words)");
+        assert!(project.changes().unwrap().is_empty());
+
+        // Without auto-commit, only a message commits
+        project.settings_mut().auto_commit = false;
+        std::fs::write(dir.path().join("content/inn.md"), "It poured.").unwrap();
+        assert_eq!(project.save_and_commit(None).await.unwrap(), None);
+        assert_eq!(project.changes().unwrap().word_delta(), -2);
+        assert!(project.save_and_commit(Some("  ")).await.is_err());
+        let commit = project
+            .save_and_commit(Some("Shorten the storm"))
+            .await
+            .unwrap();
+        let git = project.git().unwrap();
+        let history = git.file_history("content/inn.md", 10).unwrap();
+        assert_eq!(Some(&history[0].id), commit.as_ref());
+        assert_eq!(history[0].summary, "Shorten the storm");
+    }
+
+    #[cfg(feature = "git")]
+    #[tokio::test]
+    async fn test_commit_made_apart_from_the_project() {
+        let dir = tempdir().unwrap();
+        let mut project = Project::new("Storm", dir.path(), "novel").unwrap();
+        std::fs::create_dir_all(dir.path().join("content")).unwrap();
+        std::fs::write(dir.path().join("content/inn.md"), "It rained all night.").unwrap();
+
+        let commit = project.save_for_commit(None).await.unwrap().unwrap();
+        assert!(!project.has_unsaved_changes());
+        assert!(!project.changes().unwrap().is_empty());
+        let id = std::thread::spawn(move || commit.run()).join().unwrap();
+        let history = project
+            .git()
+            .unwrap()
+            .file_history("content/inn.md", 10)
+            .unwrap();
+        assert_eq!(Some(&history[0].id), id.unwrap().as_ref());
+        assert!(project.changes().unwrap().is_empty());
+
+        assert!(project.save_for_commit(Some(" ")).await.is_err());
+        project.settings_mut().auto_commit = false;
+        assert!(project.save_for_commit(None).await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn test_project_legacy_json_migration() {
         let temp_dir = make_tempdir();
@@ -1222,6 +1805,14 @@ mod tests {
         assert_eq!(loaded_project.name(), "Legacy Project");
         assert_eq!(loaded_project.metadata().template, "novel");
         assert_eq!(loaded_project.metadata().tags, vec!["test"]);
+
+        let report = loaded_project.migration_report().unwrap();
+        assert_eq!(report.from, 0);
+        assert!(report.backup.join("project.json").is_file());
+        assert!(!legacy_path.exists());
+        let reloaded = Project::load(&project_path).await.unwrap();
+        assert_eq!(reloaded.name(), "Legacy Project");
+        assert!(reloaded.migration_report().is_none());
     }
 
     #[tokio::test]
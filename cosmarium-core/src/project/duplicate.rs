@@ -0,0 +1,141 @@
+//! # Duplicating projects
+//!
+//! A duplicate is a full copy of the project folder under a new name. Its
+//! structure nodes and document references get new identifiers, so that
+//! nothing keyed by identifier mixes the copy up with the original. The Git
+//! history is copied along, or left behind for the copy to start a
+//! repository of its own.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use cosmarium_core::project::duplicate::duplicate;
+//! use std::path::Path;
+//!
+//! # async fn example() -> cosmarium_core::Result<()> {
+//! let copy = duplicate(
+//!     Path::new("Novels/The Inn"),
+//!     Path::new("Novels/The Inn (rewrite)"),
+//!     "The Inn (rewrite)",
+//!     true,
+//! )
+//! .await?;
+//! assert_eq!(copy.name(), "The Inn (rewrite)");
+//! # Ok(())
+//! # }
+//! ```
+
+use super::Project;
+use crate::{Error, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::SystemTime;
+use uuid::Uuid;
+
+/// Folder of the Git repository in a project folder.
+const GIT_DIR: &str = ".git";
+
+/// Copy the project at `source` to the folder `dest`, named `name`, and
+/// save the copy.
+///
+/// With `reset_history`, the Git repository is not copied: the copy starts
+/// a new one, its first commit being the copy as it is.
+///
+/// # Errors
+///
+/// Returns an error if `dest` exists or is inside `source`, or if the
+/// project cannot be copied, loaded or saved.
+pub async fn duplicate(
+    source: &Path,
+    dest: &Path,
+    name: &str,
+    reset_history: bool,
+) -> Result<Project> {
+    if dest.exists() {
+        return Err(Error::project(format!("{} already exists", dest.display())));
+    }
+    if dest.starts_with(source) {
+        return Err(Error::project("A project cannot be copied into itself"));
+    }
+    copy_folder(source, dest, reset_history)?;
+
+    let mut project = Project::load(dest).await?;
+    let documents: HashMap<Uuid, Uuid> = project
+        .state
+        .documents
+        .iter()
+        .map(|id| (*id, Uuid::new_v4()))
+        .collect();
+    for id in &mut project.state.documents {
+        *id = documents[id];
+    }
+    project.state.structure.renew_ids(&documents);
+    project.state.metadata.name = name.to_string();
+    project.state.metadata.created = SystemTime::now();
+    project.mark_modified();
+    project.save().await?;
+    Ok(project)
+}
+
+/// Copy the files of the folder `source` to `dest`, but for the Git
+/// repository with `skip_git`.
+fn copy_folder(source: &Path, dest: &Path, skip_git: bool) -> Result<()> {
+    let entries = walkdir::WalkDir::new(source)
+        .into_iter()
+        .filter_entry(|entry| !(skip_git && entry.depth() == 1 && entry.file_name() == GIT_DIR));
+    for entry in entries {
+        let entry = entry.map_err(|e| Error::project(format!("Failed to copy project: {}", e)))?;
+        let Ok(rel) = entry.path().strip_prefix(source) else {
+            continue;
+        };
+        let target = dest.join(rel);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::template::ProjectTemplate;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_duplicate_renews_identifiers() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("inn");
+        let mut project = Project::new("The Inn", &source, "novel").unwrap();
+        ProjectTemplate::Novel.create(&mut project).unwrap();
+        project.add_document(Uuid::new_v4());
+        project.save().await.unwrap();
+
+        let dest = temp_dir.path().join("rewrite");
+        let copy = duplicate(&source, &dest, "Rewrite", true).await.unwrap();
+        assert_eq!(copy.name(), "Rewrite");
+        assert!(dest
+            .join("content/part-one/chapter-01/scene-01.md")
+            .is_file());
+        assert_ne!(copy.documents(), project.documents());
+        assert_eq!(copy.documents().len(), 1);
+
+        let titles = |project: &Project| -> Vec<String> {
+            project
+                .structure()
+                .iter()
+                .map(|node| node.title.clone())
+                .collect()
+        };
+        assert_eq!(titles(&copy), titles(&project));
+        let first = |project: &Project| project.structure().roots()[0].id;
+        assert_ne!(first(&copy), first(&project));
+
+        assert!(duplicate(&source, &dest, "Again", false).await.is_err());
+        assert!(duplicate(&source, &source.join("copy"), "Inside", false)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,139 @@
+//! # Project locations
+//!
+//! Checks of the folder a new project is about to be created in, for the
+//! New Project wizard to tell what is wrong before anything is written.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_core::project::location::{check_location, LocationIssue};
+//!
+//! let parent = std::env::temp_dir();
+//! let issues = check_location(&parent, "a/b");
+//! assert_eq!(issues, vec![LocationIssue::InvalidName]);
+//! ```
+
+use super::store::CORE_FILE;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Something wrong, or worth knowing, about where a project would be
+/// created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocationIssue {
+    /// The project has no name
+    NoName,
+    /// The name cannot be a folder name
+    InvalidName,
+    /// Something already exists at the project folder
+    Exists,
+    /// The parent folder is not writable
+    NotWritable,
+    /// The parent folder does not exist yet and will be created
+    MissingParent,
+    /// The project would be inside the folder of another project
+    InsideProject(PathBuf),
+}
+
+impl LocationIssue {
+    /// Whether the project cannot be created there.
+    pub fn is_blocking(&self) -> bool {
+        !matches!(
+            self,
+            LocationIssue::MissingParent | LocationIssue::InsideProject(_)
+        )
+    }
+}
+
+impl fmt::Display for LocationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocationIssue::NoName => write!(f, "The project needs a name"),
+            LocationIssue::InvalidName => {
+                write!(f, "The name cannot contain / or \\ nor be . or ..")
+            }
+            LocationIssue::Exists => write!(f, "A file or folder of this name already exists"),
+            LocationIssue::NotWritable => write!(f, "The location is not writable"),
+            LocationIssue::MissingParent => write!(f, "The location will be created"),
+            LocationIssue::InsideProject(project) => write!(
+                f,
+                "The project would be inside the project at {}",
+                project.display()
+            ),
+        }
+    }
+}
+
+/// Whether `path` is the folder of a project.
+pub fn is_project_dir(path: &Path) -> bool {
+    path.join("meta").join(CORE_FILE).is_file()
+}
+
+/// Check creating a project named `name` in the folder `parent`.
+///
+/// Returns the issues found, blocking ones first; empty if the location
+/// is fine. Writing is checked by creating, then removing, a file in the
+/// nearest existing folder.
+pub fn check_location(parent: &Path, name: &str) -> Vec<LocationIssue> {
+    let mut issues = Vec::new();
+    let name = name.trim();
+    if name.is_empty() {
+        issues.push(LocationIssue::NoName);
+    } else if name.contains(['/', '\\']) || name == "." || name == ".." {
+        issues.push(LocationIssue::InvalidName);
+    } else if parent.join(name).exists() {
+        issues.push(LocationIssue::Exists);
+    }
+
+    let existing = parent.ancestors().find(|dir| dir.is_dir());
+    if !existing.is_some_and(is_writable) {
+        issues.push(LocationIssue::NotWritable);
+    }
+    if existing != Some(parent) {
+        issues.push(LocationIssue::MissingParent);
+    }
+    if let Some(project) = parent.ancestors().find(|dir| is_project_dir(dir)) {
+        issues.push(LocationIssue::InsideProject(project.to_path_buf()));
+    }
+    issues
+}
+
+/// Whether files can be created in the folder `dir`.
+fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".cosmarium-write-check-{}", std::process::id()));
+    match std::fs::File::create(&probe) {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_location() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        assert!(check_location(root, "Novel").is_empty());
+        assert_eq!(check_location(root, " "), vec![LocationIssue::NoName]);
+
+        std::fs::create_dir(root.join("Novel")).unwrap();
+        assert_eq!(check_location(root, "Novel"), vec![LocationIssue::Exists]);
+
+        let issues = check_location(&root.join("Novel/drafts"), "Sequel");
+        assert_eq!(issues, vec![LocationIssue::MissingParent]);
+        assert!(!issues[0].is_blocking());
+
+        std::fs::create_dir_all(root.join("Novel/meta")).unwrap();
+        std::fs::write(root.join("Novel/meta").join(CORE_FILE), "").unwrap();
+        assert_eq!(
+            check_location(&root.join("Novel"), "Sequel"),
+            vec![LocationIssue::InsideProject(root.join("Novel"))]
+        );
+    }
+}
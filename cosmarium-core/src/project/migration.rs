@@ -0,0 +1,316 @@
+//! Upgrades of projects saved in older on-disk formats.
+//!
+//! The project state records the [`FORMAT_VERSION`] it was saved with.
+//! Loading a project saved with an older format runs the [`MIGRATIONS`]
+//! from its version to the current one, in order, after copying the state
+//! files to a backup folder under `meta/migrations/`. The migrated state is
+//! then saved right away, and a [`MigrationReport`] tells the author what
+//! changed and where the backup is. Projects saved by a newer version are
+//! refused rather than risk losing what this version does not know about.
+//!
+//! Each change to the layout of the state, the document tree, the
+//! annotations or the assets adds a [`Migration`] to the list and bumps
+//! [`FORMAT_VERSION`].
+
+use super::ProjectState;
+use crate::structure::NodeKind;
+use crate::{Error, Result};
+use chrono::Local;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Version of the format projects are saved with.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Version of the state saved to `project.json`, before `meta/`.
+pub(super) const LEGACY_VERSION: u32 = 0;
+
+/// Version of the states saved to `meta/` before they recorded one.
+pub(super) fn unversioned() -> u32 {
+    1
+}
+
+/// Folder of `meta/` the pre-migration backups are kept in.
+const MIGRATIONS_DIR: &str = "migrations";
+
+/// Name of the report written next to each backup.
+const REPORT_FILE: &str = "report.txt";
+
+/// Project being migrated.
+pub struct MigrationContext<'a> {
+    /// Project directory
+    pub project: &'a Path,
+    /// State being migrated
+    pub state: &'a mut ProjectState,
+    backup: &'a Path,
+    obsolete: Vec<PathBuf>,
+}
+
+impl MigrationContext<'_> {
+    /// Copy the file at `path`, relative to the project, to the backup
+    /// before changing it. The state files are backed up already.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be copied.
+    pub fn back_up(&self, path: &Path) -> Result<()> {
+        let target = self.backup.join(path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(self.project.join(path), target)?;
+        Ok(())
+    }
+
+    /// Remove the file at `path`, relative to the project, once the migrated
+    /// state is saved.
+    pub fn remove_after_save(&mut self, path: &Path) {
+        self.obsolete.push(self.project.join(path));
+    }
+}
+
+/// An upgrade of the format from one version to the next.
+pub struct Migration {
+    /// Version upgraded from, to the next one
+    pub from: u32,
+    /// What the migration changes
+    pub description: &'static str,
+    /// Change the project, returning notes for the report
+    pub apply: fn(&mut MigrationContext) -> Result<Vec<String>>,
+}
+
+/// Migrations, in version order.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "Move the project state from project.json to meta/",
+        apply: move_to_meta,
+    },
+    Migration {
+        from: 1,
+        description: "Build the document tree from the flat document order",
+        apply: build_document_tree,
+    },
+];
+
+fn move_to_meta(ctx: &mut MigrationContext) -> Result<Vec<String>> {
+    ctx.remove_after_save(Path::new("project.json"));
+    Ok(vec!["project.json replaced by meta/core.toon".to_string()])
+}
+
+fn build_document_tree(ctx: &mut MigrationContext) -> Result<Vec<String>> {
+    let order = std::mem::take(&mut ctx.state.document_order);
+    if !ctx.state.structure.is_empty() {
+        return Ok(Vec::new());
+    }
+    ctx.state.structure.sync_with_content(ctx.project);
+    ctx.state.structure.sort_by_paths(&order);
+    let documents = ctx
+        .state
+        .structure
+        .iter()
+        .filter(|node| node.kind == NodeKind::Document)
+        .count();
+    Ok(vec![format!("{} documents placed in the tree", documents)])
+}
+
+/// A migration applied to a project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStep {
+    /// Version the step upgraded to
+    pub to: u32,
+    /// What the step changed
+    pub description: String,
+    /// Details of the changes
+    pub notes: Vec<String>,
+}
+
+/// What the migration of a project did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Version the project was saved with
+    pub from: u32,
+    /// Version the project was upgraded to
+    pub to: u32,
+    /// Folder the files were backed up to before the migration
+    pub backup: PathBuf,
+    /// Migrations applied, in order
+    pub steps: Vec<MigrationStep>,
+    /// Files to remove once the migrated state is saved
+    pub(super) obsolete: Vec<PathBuf>,
+}
+
+impl fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Project upgraded from format {} to {}.",
+            self.from, self.to
+        )?;
+        writeln!(f, "Backup: {}", self.backup.display())?;
+        for step in &self.steps {
+            writeln!(f, "- {} (format {})", step.description, step.to)?;
+            for note in &step.notes {
+                writeln!(f, "  {}", note)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bring `state`, loaded from the project at `project`, to the current
+/// format. `None` if it is current already.
+///
+/// # Errors
+///
+/// Returns an error if the project was saved by a newer version, or if the
+/// backup or a migration fails; nothing is saved then.
+pub(super) fn migrate(project: &Path, state: &mut ProjectState) -> Result<Option<MigrationReport>> {
+    let from = state.format_version;
+    if from > FORMAT_VERSION {
+        return Err(Error::project(format!(
+            "The project was saved by a newer version of Cosmarium (format {}, this version \
+             reads up to {})",
+            from, FORMAT_VERSION
+        )));
+    }
+    if from == FORMAT_VERSION {
+        return Ok(None);
+    }
+
+    let backup = back_up_state(project, from)?;
+    let mut ctx = MigrationContext {
+        project,
+        state,
+        backup: &backup,
+        obsolete: Vec::new(),
+    };
+    let mut steps = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= from) {
+        let notes = (migration.apply)(&mut ctx).map_err(|e| {
+            Error::project(format!(
+                "Failed to upgrade the project to format {} ({}): {}",
+                migration.from + 1,
+                migration.description,
+                e
+            ))
+        })?;
+        ctx.state.format_version = migration.from + 1;
+        steps.push(MigrationStep {
+            to: migration.from + 1,
+            description: migration.description.to_string(),
+            notes,
+        });
+    }
+
+    let report = MigrationReport {
+        from,
+        to: ctx.state.format_version,
+        obsolete: ctx.obsolete,
+        backup,
+        steps,
+    };
+    std::fs::write(report.backup.join(REPORT_FILE), report.to_string())?;
+    info!("{}", report);
+    Ok(Some(report))
+}
+
+/// Copy the state files of the project at `project`, saved with format
+/// `from`, to a new backup folder.
+fn back_up_state(project: &Path, from: u32) -> Result<PathBuf> {
+    let meta_dir = project.join("meta");
+    let name = format!("format-{}-{}", from, Local::now().format("%Y%m%d-%H%M%S"));
+    let mut backup = meta_dir.join(MIGRATIONS_DIR).join(&name);
+    let mut n = 1;
+    while backup.exists() {
+        n += 1;
+        backup = meta_dir
+            .join(MIGRATIONS_DIR)
+            .join(format!("{}-{}", name, n));
+    }
+    std::fs::create_dir_all(&backup)?;
+
+    let legacy = project.join("project.json");
+    if legacy.is_file() {
+        std::fs::copy(&legacy, backup.join("project.json"))?;
+    }
+    if let Ok(entries) = std::fs::read_dir(&meta_dir) {
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_file()) {
+                std::fs::copy(entry.path(), backup.join(entry.file_name()))?;
+            }
+        }
+    }
+    Ok(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Project;
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_migrations_are_contiguous() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.from, i as u32);
+        }
+        assert_eq!(MIGRATIONS.len() as u32, FORMAT_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_unversioned_project_is_migrated() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("content")).unwrap();
+        std::fs::write(root.join("content/a.md"), "A").unwrap();
+        std::fs::write(root.join("content/b.md"), "B").unwrap();
+
+        let mut project = Project::new("Old", root, "novel").unwrap();
+        project.save().await.unwrap();
+        // As saved before the format was versioned, with a flat order
+        let core = root.join("meta/core.toon");
+        let mut state: serde_json::Value =
+            serde_toon2::from_str(&std::fs::read_to_string(&core).unwrap()).unwrap();
+        let fields = state.as_object_mut().unwrap();
+        fields.remove("format_version");
+        fields.remove("structure");
+        fields.insert(
+            "document_order".into(),
+            serde_json::json!(["content/b.md", "content/a.md"]),
+        );
+        std::fs::write(&core, serde_toon2::to_string(&state).unwrap()).unwrap();
+
+        let project = Project::load(root).await.unwrap();
+        let report = project.migration_report().unwrap();
+        assert_eq!((report.from, report.to), (1, FORMAT_VERSION));
+        assert_eq!(report.steps.len(), 1);
+        assert!(report.backup.join("core.toon").is_file());
+        assert!(report.backup.join(REPORT_FILE).is_file());
+        let titles: Vec<&str> = project
+            .structure()
+            .iter()
+            .map(|node| node.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["b", "a"]);
+
+        // Saved right away, so it is not migrated again
+        assert!(!project.has_unsaved_changes());
+        let project = Project::load(root).await.unwrap();
+        assert!(project.migration_report().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_newer_format_is_refused() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let mut project = Project::new("New", root, "novel").unwrap();
+        project.state.format_version = FORMAT_VERSION + 1;
+        project.save().await.unwrap();
+
+        let error = Project::load(root).await.unwrap_err();
+        assert!(error.to_string().contains("newer version"));
+        assert!(!root.join("meta").join(MIGRATIONS_DIR).exists());
+    }
+}
@@ -0,0 +1,292 @@
+//! # Second copy of a project
+//!
+//! Writers without a backup habit lose their work with the drive it was on.
+//! The second copy mirrors the project folder, Git history included, to a
+//! folder of the same name in another location, such as an external drive
+//! or a network share, when the project is closed or once a day (see
+//! [`SecondCopyConfig`]). Only the files that changed since the last copy
+//! are written, each one read back and compared with the original, and the
+//! files deleted from the project are deleted from the copy.
+//!
+//! A location that is missing, such as an unplugged drive, is an error: it
+//! is never created, so that the copy does not end up on the computer's
+//! own disk. So is a folder of the project's name there that is not an
+//! earlier copy: nothing in it is ever deleted.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use cosmarium_core::project::mirror::mirror_project;
+//! use std::path::Path;
+//!
+//! let report = mirror_project(Path::new("Novels/The Inn"), Path::new("/media/usb/Novels"))?;
+//! println!("{} files copied to {:?}", report.copied, report.dest);
+//! # Ok::<(), cosmarium_core::Error>(())
+//! ```
+
+use crate::config::{SecondCopyConfig, SecondCopySchedule};
+use crate::{Error, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// File of the copy whose modification time is that of the last copy.
+const MARKER_FILE: &str = ".cosmarium-second-copy";
+
+/// Time between two copies on the daily schedule.
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Outcome of bringing a second copy up to date.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MirrorReport {
+    /// Folder of the copy
+    pub dest: PathBuf,
+    /// Files written and verified
+    pub copied: usize,
+    /// Files deleted from the copy, as they were from the project
+    pub removed: usize,
+}
+
+/// Folder of the second copy of the project at `project` in `location`.
+pub fn copy_folder(project: &Path, location: &Path) -> PathBuf {
+    location.join(project.file_name().unwrap_or_default())
+}
+
+/// When the second copy of the project at `project` in `location` was last
+/// brought up to date, if it ever was.
+pub fn last_copy(project: &Path, location: &Path) -> Option<SystemTime> {
+    std::fs::metadata(copy_folder(project, location).join(MARKER_FILE))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Whether the second copy of the project at `project` is due, on closing
+/// the project with `closing` or opening it otherwise.
+pub fn is_due(config: &SecondCopyConfig, project: &Path, closing: bool, now: SystemTime) -> bool {
+    if !config.enabled || config.location.as_os_str().is_empty() {
+        return false;
+    }
+    match config.schedule {
+        SecondCopySchedule::OnClose => closing,
+        SecondCopySchedule::Daily => last_copy(project, &config.location)
+            .is_none_or(|last| now.duration_since(last).unwrap_or_default() >= DAY),
+    }
+}
+
+/// Bring the copy of the project at `project` in `location` up to date.
+///
+/// # Errors
+///
+/// Returns an error if `location` does not exist or is inside the project,
+/// if the folder of the copy there holds files but is not an earlier copy,
+/// or if a file cannot be copied or differs from the original once copied.
+pub fn mirror_project(project: &Path, location: &Path) -> Result<MirrorReport> {
+    if !location.is_dir() {
+        return Err(Error::project(format!(
+            "The second copy location {} is not available",
+            location.display()
+        )));
+    }
+    let dest = copy_folder(project, location);
+    if dest.starts_with(project) {
+        return Err(Error::project(
+            "The second copy cannot be kept inside the project",
+        ));
+    }
+    if !is_copy(&dest)? {
+        return Err(Error::project(format!(
+            "{} is not a second copy of the project and is not empty",
+            dest.display()
+        )));
+    }
+    let mut report = MirrorReport {
+        dest: dest.clone(),
+        ..MirrorReport::default()
+    };
+
+    let mut kept = HashSet::new();
+    for entry in walkdir::WalkDir::new(project) {
+        let entry = entry.map_err(|e| Error::project(format!("Failed to copy project: {}", e)))?;
+        let Ok(rel) = entry.path().strip_prefix(project) else {
+            continue;
+        };
+        let target = dest.join(rel);
+        kept.insert(rel.to_path_buf());
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else if entry.file_type().is_file() && changed(entry.path(), &target)? {
+            copy_verified(entry.path(), &target)?;
+            report.copied += 1;
+        }
+    }
+
+    // Files deleted from the project, children before their folders
+    let stale: Vec<_> = walkdir::WalkDir::new(&dest)
+        .contents_first(true)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let rel = entry.path().strip_prefix(&dest).ok()?.to_path_buf();
+            let gone = !rel.as_os_str().is_empty() && rel != Path::new(MARKER_FILE);
+            (gone && !kept.contains(&rel)).then_some(entry)
+        })
+        .collect();
+    for entry in stale {
+        if entry.file_type().is_dir() {
+            std::fs::remove_dir(entry.path())?;
+        } else {
+            std::fs::remove_file(entry.path())?;
+            report.removed += 1;
+        }
+    }
+
+    std::fs::write(dest.join(MARKER_FILE), "")?;
+    tracing::info!(
+        "Second copy of {:?} up to date: {} files copied, {} removed",
+        project,
+        report.copied,
+        report.removed
+    );
+    Ok(report)
+}
+
+/// Whether the folder `dest` can be brought up to date, deleting what is
+/// not in the project: it is an earlier copy, empty or missing.
+fn is_copy(dest: &Path) -> Result<bool> {
+    if dest.join(MARKER_FILE).is_file() {
+        return Ok(true);
+    }
+    match std::fs::read_dir(dest) {
+        Ok(mut entries) => Ok(entries.next().is_none()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(true),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Whether the file `source` differs in size or modification time from its
+/// copy `target`, or has none.
+fn changed(source: &Path, target: &Path) -> Result<bool> {
+    let Ok(copy) = std::fs::metadata(target) else {
+        return Ok(true);
+    };
+    let original = std::fs::metadata(source)?;
+    Ok(original.len() != copy.len() || original.modified()? != copy.modified()?)
+}
+
+/// Copy `source` to `target` with its modification time, and check that
+/// the copy reads back the same.
+fn copy_verified(source: &Path, target: &Path) -> Result<()> {
+    std::fs::copy(source, target)?;
+    let modified = std::fs::metadata(source)?.modified()?;
+    std::fs::File::options()
+        .write(true)
+        .open(target)?
+        .set_modified(modified)?;
+    if digest(source)? != digest(target)? {
+        return Err(Error::project(format!(
+            "The second copy of {} differs from the original",
+            source.display()
+        )));
+    }
+    Ok(())
+}
+
+/// SHA-256 digest of the content of the file at `path`.
+fn digest(path: &Path) -> Result<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_mirror_copies_changes_and_deletions() {
+        let temp_dir = tempdir().unwrap();
+        let project = temp_dir.path().join("inn");
+        let location = temp_dir.path().join("usb");
+        std::fs::create_dir_all(project.join("content/part1")).unwrap();
+        std::fs::write(project.join("content/part1/one.md"), "One").unwrap();
+        std::fs::write(project.join("content/two.md"), "Two").unwrap();
+
+        // An unplugged drive is not created
+        assert!(mirror_project(&project, &location).is_err());
+        assert!(!location.exists());
+
+        std::fs::create_dir(&location).unwrap();
+        let report = mirror_project(&project, &location).unwrap();
+        assert_eq!(report.dest, location.join("inn"));
+        assert_eq!(report.copied, 2);
+        assert!(last_copy(&project, &location).is_some());
+
+        let report = mirror_project(&project, &location).unwrap();
+        assert_eq!((report.copied, report.removed), (0, 0));
+
+        std::fs::write(project.join("content/two.md"), "Two, longer").unwrap();
+        std::fs::remove_dir_all(project.join("content/part1")).unwrap();
+        let report = mirror_project(&project, &location).unwrap();
+        assert_eq!((report.copied, report.removed), (1, 1));
+        let copy = location.join("inn");
+        assert_eq!(
+            std::fs::read_to_string(copy.join("content/two.md")).unwrap(),
+            "Two, longer"
+        );
+        assert!(!copy.join("content/part1").exists());
+        assert!(mirror_project(&project, &project.join("content")).is_err());
+        assert!(mirror_project(&project, temp_dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_mirror_leaves_foreign_folder_alone() {
+        let temp_dir = tempdir().unwrap();
+        let project = temp_dir.path().join("inn");
+        let location = temp_dir.path().join("usb");
+        std::fs::create_dir_all(project.join("content")).unwrap();
+        std::fs::write(project.join("content/one.md"), "One").unwrap();
+        std::fs::create_dir_all(location.join("inn")).unwrap();
+        std::fs::write(location.join("inn/taxes.ods"), "Taxes").unwrap();
+
+        assert!(mirror_project(&project, &location).is_err());
+        let copy = location.join("inn");
+        assert_eq!(
+            std::fs::read_to_string(copy.join("taxes.ods")).unwrap(),
+            "Taxes"
+        );
+        assert!(!copy.join("content").exists());
+        assert!(last_copy(&project, &location).is_none());
+
+        // An empty folder is taken
+        std::fs::remove_file(copy.join("taxes.ods")).unwrap();
+        assert_eq!(mirror_project(&project, &location).unwrap().copied, 1);
+    }
+
+    #[test]
+    fn test_second_copy_schedule() {
+        let temp_dir = tempdir().unwrap();
+        let project = temp_dir.path().join("inn");
+        std::fs::create_dir_all(&project).unwrap();
+        let mut config = SecondCopyConfig {
+            enabled: true,
+            location: temp_dir.path().join("usb"),
+            schedule: SecondCopySchedule::OnClose,
+        };
+        let now = SystemTime::now();
+        assert!(is_due(&config, &project, true, now));
+        assert!(!is_due(&config, &project, false, now));
+
+        config.schedule = SecondCopySchedule::Daily;
+        assert!(is_due(&config, &project, false, now));
+        std::fs::create_dir_all(&config.location).unwrap();
+        mirror_project(&project, &config.location).unwrap();
+        assert!(!is_due(&config, &project, true, now));
+        let next_day = last_copy(&project, &config.location).unwrap() + DAY;
+        assert!(is_due(&config, &project, true, next_day));
+
+        config.enabled = false;
+        assert!(!is_due(&config, &project, true, next_day));
+    }
+}
@@ -0,0 +1,649 @@
+//! Storage of the project state under `meta/`.
+//!
+//! The state is saved to `core.toon`, except for its collections grown past
+//! [`SPLIT_THRESHOLD`] entries, each kept in its own file and listed in
+//! `core.toon`: the document references are streamed to `documents.list`,
+//! one identifier per line, and the structure goes to `structure.toon`.
+//! Files whose content did not change since the last save are not written
+//! again; the others are written to a temporary file, then renamed over the
+//! old one, so that an interrupted save never leaves a truncated file. The
+//! previous `core.toon` is kept as `core.toon.bak`.
+//!
+//! Loading does not give up on a damaged state. When `core.toon` cannot be
+//! read, the backup is used, and failing that the state is rebuilt from the
+//! content folder, the damaged file being kept as `core.toon.corrupt`.
+//! Unreadable lines of `documents.list` are skipped and an unreadable
+//! `structure.toon` is rebuilt. Each problem is reported as a
+//! [`LoadDiagnostic`].
+
+use super::{ProjectMetadata, ProjectSettings, ProjectState};
+use crate::structure::ProjectStructure;
+use crate::{Error, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Number of entries past which a collection is saved to its own file.
+pub const SPLIT_THRESHOLD: usize = 512;
+
+pub(super) const CORE_FILE: &str = "core.toon";
+const BACKUP_FILE: &str = "core.toon.bak";
+const CORRUPT_FILE: &str = "core.toon.corrupt";
+const DOCUMENTS_FILE: &str = "documents.list";
+const STRUCTURE_FILE: &str = "structure.toon";
+
+/// Names of the sections listed in `core.toon` when split.
+const DOCUMENTS: &str = "documents";
+const STRUCTURE: &str = "structure";
+
+/// What was done about a problem met while loading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// The state was read from the backup of the last save
+    Backup,
+    /// The state, or a part of it, was rebuilt from the content folder
+    Rebuilt,
+    /// The unreadable entries were left out
+    Skipped,
+}
+
+/// A problem met while loading the project state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadDiagnostic {
+    /// File at fault
+    pub file: PathBuf,
+    /// Line at fault, from 1, when known
+    pub line: Option<usize>,
+    /// Text of the line at fault
+    pub excerpt: Option<String>,
+    /// What went wrong
+    pub message: String,
+    /// What was done about it
+    pub recovery: Recovery,
+}
+
+impl LoadDiagnostic {
+    fn new(file: &Path, message: impl Into<String>, recovery: Recovery) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            line: None,
+            excerpt: None,
+            message: message.into(),
+            recovery,
+        }
+    }
+
+    /// Point at `line` of the file's `content`.
+    fn at_line(mut self, line: Option<usize>, content: &str) -> Self {
+        self.line = line;
+        self.excerpt = line
+            .and_then(|line| content.lines().nth(line.checked_sub(1)?))
+            .map(|text| text.trim().chars().take(80).collect());
+        self
+    }
+}
+
+impl fmt::Display for LoadDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(excerpt) = &self.excerpt {
+            write!(f, " (`{}`)", excerpt)?;
+        }
+        let recovery = match self.recovery {
+            Recovery::Backup => "using the backup of the last save",
+            Recovery::Rebuilt => "rebuilt from the content folder",
+            Recovery::Skipped => "unreadable entries skipped",
+        };
+        write!(f, "; {}", recovery)
+    }
+}
+
+/// The content of `core.toon`: the state without its split sections.
+#[derive(Serialize)]
+struct CoreFile<'a> {
+    format_version: u32,
+    metadata: &'a ProjectMetadata,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    documents: Option<&'a Vec<Uuid>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    structure: Option<&'a ProjectStructure>,
+    settings: &'a ProjectSettings,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    split: Vec<&'static str>,
+}
+
+/// Save `state` to `meta_dir`. `written` holds the hashes of the files as
+/// last saved, which are not written again while their content is the same.
+///
+/// Returns the number of files written.
+pub(super) async fn save_state(
+    meta_dir: &Path,
+    state: &ProjectState,
+    written: &mut HashMap<&'static str, u64>,
+) -> Result<usize> {
+    let split_documents = state.documents.len() > SPLIT_THRESHOLD;
+    let split_structure = state.structure.iter().count() > SPLIT_THRESHOLD;
+    let mut count = 0;
+
+    let documents_file = meta_dir.join(DOCUMENTS_FILE);
+    if split_documents {
+        if changed(written, DOCUMENTS_FILE, hash(&state.documents)) {
+            write_documents(&documents_file, &state.documents).await?;
+            count += 1;
+        }
+    } else {
+        remove_section(&documents_file, written, DOCUMENTS_FILE).await;
+    }
+
+    let structure_file = meta_dir.join(STRUCTURE_FILE);
+    if split_structure {
+        let content = serialize(&state.structure)?;
+        if changed(written, STRUCTURE_FILE, hash(&content)) {
+            write_atomic(&structure_file, content.as_bytes()).await?;
+            count += 1;
+        }
+    } else {
+        remove_section(&structure_file, written, STRUCTURE_FILE).await;
+    }
+
+    let core = CoreFile {
+        format_version: state.format_version,
+        metadata: &state.metadata,
+        documents: (!split_documents).then_some(&state.documents),
+        structure: (!split_structure).then_some(&state.structure),
+        settings: &state.settings,
+        split: [(split_documents, DOCUMENTS), (split_structure, STRUCTURE)]
+            .into_iter()
+            .filter_map(|(split, name)| split.then_some(name))
+            .collect(),
+    };
+    let content = serialize(&core)?;
+    if changed(written, CORE_FILE, hash(&content)) {
+        let core_file = meta_dir.join(CORE_FILE);
+        // A damaged state does not replace the last good backup
+        if read_core(&core_file).await.is_ok() {
+            if let Err(e) = tokio::fs::copy(&core_file, meta_dir.join(BACKUP_FILE)).await {
+                warn!("Failed to back up {:?}: {}", core_file, e);
+            }
+        }
+        write_atomic(&core_file, content.as_bytes()).await?;
+        count += 1;
+    }
+    debug!("Project state saved ({} files written)", count);
+    Ok(count)
+}
+
+/// Load the state of the project at `project` from `meta_dir`, recovering
+/// from what can be recovered. `None` if there is no `core.toon`.
+pub(super) async fn load_state(
+    project: &Path,
+    meta_dir: &Path,
+) -> Option<(ProjectState, Vec<LoadDiagnostic>)> {
+    let core_file = meta_dir.join(CORE_FILE);
+    if !core_file.exists() {
+        return None;
+    }
+    let mut diagnostics = Vec::new();
+
+    let mut state = match read_core(&core_file).await {
+        Ok(state) => state,
+        Err(diagnostic) => {
+            let backup = read_core(&meta_dir.join(BACKUP_FILE)).await;
+            diagnostics.push(LoadDiagnostic {
+                recovery: if backup.is_ok() {
+                    Recovery::Backup
+                } else {
+                    Recovery::Rebuilt
+                },
+                ..diagnostic
+            });
+            let corrupt = meta_dir.join(CORRUPT_FILE);
+            if let Err(e) = tokio::fs::copy(&core_file, &corrupt).await {
+                warn!("Failed to keep the damaged {:?}: {}", core_file, e);
+            }
+            match backup {
+                Ok(state) => state,
+                Err(_) => rebuilt_state(project, &corrupt).await,
+            }
+        }
+    };
+
+    let split = std::mem::take(&mut state.split);
+    if split.iter().any(|section| section == DOCUMENTS) {
+        let file = meta_dir.join(DOCUMENTS_FILE);
+        let (documents, diagnostic) = read_documents(&file).await;
+        state.documents = documents;
+        diagnostics.extend(diagnostic);
+    }
+    if split.iter().any(|section| section == STRUCTURE) {
+        let file = meta_dir.join(STRUCTURE_FILE);
+        match read_structure(&file).await {
+            Ok(structure) => state.structure = structure,
+            // The project rebuilds an empty structure from its content
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        }
+    }
+    for diagnostic in &diagnostics {
+        warn!("Project state: {}", diagnostic);
+    }
+    Some((state, diagnostics))
+}
+
+/// Read the state saved in the `core.toon` file at `path`.
+async fn read_core(path: &Path) -> std::result::Result<ProjectState, LoadDiagnostic> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| LoadDiagnostic::new(path, format!("cannot read: {}", e), Recovery::Rebuilt))?;
+    deserialize(path, &content)
+}
+
+/// Read the document references streamed to `path`, skipping the lines
+/// that are not identifiers.
+async fn read_documents(path: &Path) -> (Vec<Uuid>, Option<LoadDiagnostic>) {
+    let file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(e) => {
+            let message = format!("cannot read: {}", e);
+            return (
+                Vec::new(),
+                Some(LoadDiagnostic::new(path, message, Recovery::Skipped)),
+            );
+        }
+    };
+    let mut lines = tokio::io::BufReader::new(file).lines();
+    let mut documents = Vec::new();
+    let mut skipped: Vec<(usize, String)> = Vec::new();
+    let mut number = 0;
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                skipped.push((number + 1, format!("cannot read: {}", e)));
+                break;
+            }
+        };
+        number += 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match Uuid::parse_str(line) {
+            Ok(id) => documents.push(id),
+            Err(_) => skipped.push((number, line.to_string())),
+        }
+    }
+    let diagnostic = skipped.first().map(|(line, excerpt)| LoadDiagnostic {
+        file: path.to_path_buf(),
+        line: Some(*line),
+        excerpt: Some(excerpt.chars().take(80).collect()),
+        message: format!("{} unreadable document references", skipped.len()),
+        recovery: Recovery::Skipped,
+    });
+    (documents, diagnostic)
+}
+
+/// Read the structure saved to `path`.
+async fn read_structure(path: &Path) -> std::result::Result<ProjectStructure, LoadDiagnostic> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| LoadDiagnostic::new(path, format!("cannot read: {}", e), Recovery::Rebuilt))?;
+    deserialize(path, &content)
+}
+
+/// State of a project whose saved state is lost, named after the `name:`
+/// line left in the `damaged` file or else after its folder. The structure
+/// is left empty for the project to rebuild from its content.
+async fn rebuilt_state(project: &Path, damaged: &Path) -> ProjectState {
+    let content = tokio::fs::read(damaged).await.unwrap_or_default();
+    let name = String::from_utf8_lossy(&content)
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("name:"))
+        .map(|name| name.trim().trim_matches('"').to_string())
+        .filter(|name| !name.is_empty())
+        .or_else(|| {
+            project
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "Recovered Project".to_string());
+    ProjectState {
+        format_version: super::FORMAT_VERSION,
+        metadata: ProjectMetadata::new(&name, "novel"),
+        documents: Vec::new(),
+        document_order: Vec::new(),
+        structure: ProjectStructure::default(),
+        settings: ProjectSettings::default(),
+        split: Vec::new(),
+    }
+}
+
+/// Line number given in a parser's error `message`, such as `... at line 3
+/// column 7`.
+fn error_line(message: &str) -> Option<usize> {
+    let (_, after) = message.rsplit_once("line ")?;
+    let digits: String = after.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok().filter(|line| *line > 0)
+}
+
+/// Parse the TOON `content` of the file at `path`.
+///
+/// The content goes through a JSON value on its way: the TOON deserializer
+/// reads unit enum variants (line endings, node kinds) as plain strings,
+/// which only a self-describing value turns back into variants.
+fn deserialize<T: DeserializeOwned>(
+    path: &Path,
+    content: &str,
+) -> std::result::Result<T, LoadDiagnostic> {
+    let parse_error = |message: String| {
+        LoadDiagnostic::new(
+            path,
+            format!("cannot parse: {}", message),
+            Recovery::Rebuilt,
+        )
+        .at_line(error_line(&message), content)
+    };
+    let value: serde_json::Value =
+        serde_toon2::from_str(content).map_err(|e| parse_error(e.to_string()))?;
+    serde_json::from_value(value).map_err(|e| parse_error(e.to_string()))
+}
+
+/// Write `value` as TOON.
+///
+/// The value goes through a JSON value on its way, which sorts the fields
+/// of objects: the TOON deserializer only reads back the table of child
+/// nodes of a structure node when it comes first in the node.
+fn serialize<T: Serialize>(value: &T) -> Result<String> {
+    let error = |e: String| Error::project(format!("Failed to serialize project state: {}", e));
+    let value = serde_json::to_value(value).map_err(|e| error(e.to_string()))?;
+    serde_toon2::to_string(&value).map_err(|e| error(e.to_string()))
+}
+
+fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Record the `hash` of `file`, returning whether it differs from the last.
+fn changed(written: &mut HashMap<&'static str, u64>, file: &'static str, hash: u64) -> bool {
+    written.insert(file, hash) != Some(hash)
+}
+
+/// Remove the file of a section no longer split.
+async fn remove_section(path: &Path, written: &mut HashMap<&'static str, u64>, file: &str) {
+    written.remove(file);
+    if path.exists() {
+        if let Err(e) = tokio::fs::remove_file(path).await {
+            warn!("Failed to remove {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Stream the document references to `path`, one per line.
+async fn write_documents(path: &Path, documents: &[Uuid]) -> Result<()> {
+    let write = async {
+        let temp = temp_path(path);
+        let mut writer = tokio::io::BufWriter::new(tokio::fs::File::create(&temp).await?);
+        for id in documents {
+            writer
+                .write_all(id.hyphenated().to_string().as_bytes())
+                .await?;
+            writer.write_all(b"\n").await?;
+        }
+        writer.flush().await?;
+        writer.into_inner().sync_all().await?;
+        tokio::fs::rename(&temp, path).await
+    };
+    write
+        .await
+        .map_err(|e| Error::project(format!("Failed to write {:?}: {}", path, e)))
+}
+
+/// Write `content` to `path` through a temporary file.
+async fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let write = async {
+        let temp = temp_path(path);
+        let mut file = tokio::fs::File::create(&temp).await?;
+        file.write_all(content).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&temp, path).await
+    };
+    write
+        .await
+        .map_err(|e| Error::project(format!("Failed to write {:?}: {}", path, e)))
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::Project;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_large_sections_are_split() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("saga");
+        let mut project = Project::new("Saga", &root, "novel").unwrap();
+        let documents: Vec<Uuid> = (0..SPLIT_THRESHOLD + 1).map(|_| Uuid::new_v4()).collect();
+        for id in &documents {
+            project.add_document(*id);
+        }
+        project.save().await.unwrap();
+        let meta = root.join("meta");
+        assert!(meta.join(DOCUMENTS_FILE).exists());
+        assert!(!meta.join(STRUCTURE_FILE).exists());
+
+        // Only the changed files are written again
+        let mut written = HashMap::new();
+        assert_eq!(
+            save_state(&meta, &project.state, &mut written)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            save_state(&meta, &project.state, &mut written)
+                .await
+                .unwrap(),
+            0
+        );
+        project.state.metadata.description = "Ten books".to_string();
+        assert_eq!(
+            save_state(&meta, &project.state, &mut written)
+                .await
+                .unwrap(),
+            1
+        );
+
+        let loaded = Project::load(&root).await.unwrap();
+        assert_eq!(loaded.documents(), documents.as_slice());
+        assert_eq!(loaded.metadata().description, "Ten books");
+        assert!(loaded.load_diagnostics().is_empty());
+
+        // Back under the threshold, the section returns to core.toon
+        project.remove_document(documents[0]);
+        project.save().await.unwrap();
+        assert!(!meta.join(DOCUMENTS_FILE).exists());
+        let loaded = Project::load(&root).await.unwrap();
+        assert_eq!(loaded.documents().len(), SPLIT_THRESHOLD);
+    }
+
+    #[tokio::test]
+    async fn test_state_round_trip() {
+        use crate::document::LineEnding;
+        use crate::structure::{NodeKind, StructureNode};
+
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("novel");
+        let mut project = Project::new("Harbor Lights", &root, "novel").unwrap();
+        project.settings_mut().line_ending = LineEnding::Crlf;
+        let structure = project.structure_mut();
+        let part = structure
+            .insert(None, 0, StructureNode::new(NodeKind::Part, "Part One"))
+            .unwrap();
+        let chapter = structure
+            .insert(
+                Some(part),
+                0,
+                StructureNode::new(NodeKind::Chapter, "Arrival"),
+            )
+            .unwrap();
+        let mut scene = StructureNode::new(NodeKind::Document, "Docks")
+            .with_path("content/part-one/arrival/docks.md");
+        scene
+            .metadata
+            .insert("status".to_string(), "draft".to_string());
+        structure.insert(Some(chapter), 0, scene).unwrap();
+        project.save().await.unwrap();
+
+        let loaded = Project::load(&root).await.unwrap();
+        assert!(loaded.load_diagnostics().is_empty());
+        assert_eq!(loaded.settings().line_ending, LineEnding::Crlf);
+        assert_eq!(loaded.structure(), project.structure());
+
+        // And from its own file once split
+        for i in 0..SPLIT_THRESHOLD {
+            let node = StructureNode::new(NodeKind::Document, format!("Scene {}", i));
+            project
+                .structure_mut()
+                .insert(Some(chapter), i, node)
+                .unwrap();
+        }
+        project.save().await.unwrap();
+        assert!(root.join("meta").join(STRUCTURE_FILE).exists());
+        let loaded = Project::load(&root).await.unwrap();
+        assert!(loaded.load_diagnostics().is_empty());
+        assert_eq!(loaded.structure(), project.structure());
+    }
+
+    #[tokio::test]
+    async fn test_damaged_state_is_recovered() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("novel");
+        let mut project = Project::new("Harbor Lights", &root, "novel").unwrap();
+        project.save().await.unwrap();
+        project.metadata_mut().author = "Ann".to_string();
+        project.save().await.unwrap();
+
+        let meta = root.join("meta");
+        let core = meta.join(CORE_FILE);
+        std::fs::write(&core, "metadata:\n  name: Harbor Lights\n  {{{\n").unwrap();
+        let loaded = Project::load(&root).await.unwrap();
+        assert_eq!(loaded.name(), "Harbor Lights");
+        assert!(loaded.metadata().author.is_empty());
+        assert!(loaded.has_unsaved_changes());
+        let diagnostic = &loaded.load_diagnostics()[0];
+        assert_eq!(diagnostic.recovery, Recovery::Backup);
+        assert_eq!(diagnostic.file, core);
+        assert!(meta.join(CORRUPT_FILE).exists());
+
+        // Without a backup, the state is rebuilt, keeping the name if it can
+        std::fs::remove_file(meta.join(BACKUP_FILE)).unwrap();
+        let loaded = Project::load(&root).await.unwrap();
+        assert_eq!(loaded.name(), "Harbor Lights");
+        assert_eq!(loaded.load_diagnostics()[0].recovery, Recovery::Rebuilt);
+        assert!(loaded.load_diagnostics()[0]
+            .to_string()
+            .contains("rebuilt from the content folder"));
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_document_references_are_skipped() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join(DOCUMENTS_FILE);
+        let id = Uuid::new_v4();
+        std::fs::write(&path, format!("{}\n\nnot-an-id\n{}\n", id, id)).unwrap();
+        let (documents, diagnostic) = read_documents(&path).await;
+        assert_eq!(documents, vec![id, id]);
+        let diagnostic = diagnostic.unwrap();
+        assert_eq!(diagnostic.line, Some(3));
+        assert_eq!(diagnostic.excerpt.as_deref(), Some("not-an-id"));
+        assert_eq!(diagnostic.recovery, Recovery::Skipped);
+    }
+
+    #[test]
+    fn test_error_line() {
+        assert_eq!(error_line("expected `:` at line 12 column 4"), Some(12));
+        assert_eq!(error_line("unexpected end of input"), None);
+        assert_eq!(error_line("line 0"), None);
+    }
+
+    /// Loading damaged states never fails nor panics: the damaged `core.toon`
+    /// files are truncated, have bytes replaced, or lines removed or
+    /// repeated, from a fixed seed.
+    #[tokio::test]
+    async fn test_fuzz_loader() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("fuzz");
+        std::fs::create_dir_all(root.join("content")).unwrap();
+        std::fs::write(root.join("content/one.md"), "One").unwrap();
+        let mut project = Project::new("Fuzz", &root, "novel").unwrap();
+        project.sync_structure();
+        project.metadata_mut().tags = vec!["a".to_string(), "b".to_string()];
+        project.save().await.unwrap();
+        let core = root.join("meta").join(CORE_FILE);
+        let backup = root.join("meta").join(BACKUP_FILE);
+        let valid = std::fs::read(&core).unwrap();
+
+        let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut next = move |bound: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % bound.max(1) as u64) as usize
+        };
+        for round in 0..150 {
+            let mut damaged = valid.clone();
+            match next(4) {
+                0 => damaged.truncate(next(damaged.len())),
+                1 => {
+                    for _ in 0..=next(4) {
+                        let at = next(damaged.len());
+                        damaged[at] = next(256) as u8;
+                    }
+                }
+                2 => {
+                    let mut lines: Vec<&[u8]> = valid.split(|b| *b == b'\n').collect();
+                    lines.remove(next(lines.len()));
+                    damaged = lines.join(&b'\n');
+                }
+                _ => {
+                    let mut lines: Vec<&[u8]> = valid.split(|b| *b == b'\n').collect();
+                    let line = lines[next(lines.len())];
+                    lines.insert(next(lines.len()), line);
+                    damaged = lines.join(&b'\n');
+                }
+            }
+            std::fs::write(&core, &damaged).unwrap();
+            if round % 2 == 0 {
+                let _ = std::fs::remove_file(&backup);
+            } else {
+                std::fs::write(&backup, &valid).unwrap();
+            }
+
+            let loaded = Project::load(&root).await.unwrap();
+            assert!(loaded.load_diagnostics().len() <= 1, "round {}", round);
+            if !loaded.load_diagnostics().is_empty() && round % 2 == 1 {
+                assert_eq!(loaded.name(), "Fuzz", "round {}", round);
+            }
+        }
+    }
+}
@@ -0,0 +1,473 @@
+//! # Project templates
+//!
+//! The kinds of project a new project can start from. Each template has a
+//! description, for the New Project wizard to show, and an outline: the
+//! parts, chapters and documents created in the content directory, with
+//! their nodes in the project structure.
+//!
+//! Projects can also be saved as [`UserTemplate`]s, in the configuration
+//! directory: their structure, their settings, and a placeholder of each
+//! document keeping only its headings.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_core::project::template::ProjectTemplate;
+//! use cosmarium_core::structure::NodeKind;
+//!
+//! let template = ProjectTemplate::from_id("novel").unwrap();
+//! assert_eq!(template.outline()[0].kind, NodeKind::Part);
+//! ```
+
+use super::{Project, ProjectSettings};
+use crate::structure::{NodeKind, ProjectStructure, StructureNode};
+use crate::{Config, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use uuid::Uuid;
+
+/// File describing a user template, in its folder.
+const USER_TEMPLATE_FILE: &str = "template.json";
+
+/// A node of a template outline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TemplateNode {
+    /// What the node stands for
+    pub kind: NodeKind,
+    /// Title shown in the binder
+    pub title: &'static str,
+    /// Name of its folder, or of its file for documents
+    pub name: &'static str,
+    /// Child nodes, in order
+    pub children: &'static [TemplateNode],
+}
+
+impl TemplateNode {
+    const fn new(
+        kind: NodeKind,
+        title: &'static str,
+        name: &'static str,
+        children: &'static [TemplateNode],
+    ) -> Self {
+        Self {
+            kind,
+            title,
+            name,
+            children,
+        }
+    }
+
+    const fn document(title: &'static str, name: &'static str) -> Self {
+        Self::new(NodeKind::Document, title, name, &[])
+    }
+}
+
+const NOVEL: &[TemplateNode] = &[TemplateNode::new(
+    NodeKind::Part,
+    "Part One",
+    "part-one",
+    &[
+        TemplateNode::new(
+            NodeKind::Chapter,
+            "Chapter 1",
+            "chapter-01",
+            &[TemplateNode::document("Scene 1", "scene-01.md")],
+        ),
+        TemplateNode::new(
+            NodeKind::Chapter,
+            "Chapter 2",
+            "chapter-02",
+            &[TemplateNode::document("Scene 1", "scene-01.md")],
+        ),
+    ],
+)];
+
+const SHORT_STORY: &[TemplateNode] = &[TemplateNode::document("Story", "story.md")];
+
+const SCREENPLAY: &[TemplateNode] = &[
+    TemplateNode::new(
+        NodeKind::Part,
+        "Act One",
+        "act-1",
+        &[TemplateNode::document("Setup", "setup.md")],
+    ),
+    TemplateNode::new(
+        NodeKind::Part,
+        "Act Two",
+        "act-2",
+        &[TemplateNode::document("Confrontation", "confrontation.md")],
+    ),
+    TemplateNode::new(
+        NodeKind::Part,
+        "Act Three",
+        "act-3",
+        &[TemplateNode::document("Resolution", "resolution.md")],
+    ),
+];
+
+const BLOG: &[TemplateNode] = &[
+    TemplateNode::new(
+        NodeKind::Folder,
+        "Drafts",
+        "drafts",
+        &[TemplateNode::document("First Post", "first-post.md")],
+    ),
+    TemplateNode::new(NodeKind::Folder, "Published", "published", &[]),
+];
+
+/// Kind of project a new project starts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProjectTemplate {
+    /// Parts, chapters and scenes
+    Novel,
+    /// One document
+    ShortStory,
+    /// Three acts
+    Screenplay,
+    /// Drafts and published posts
+    Blog,
+}
+
+impl ProjectTemplate {
+    /// All templates, in the order they are offered.
+    pub const ALL: [ProjectTemplate; 4] = [
+        ProjectTemplate::Novel,
+        ProjectTemplate::ShortStory,
+        ProjectTemplate::Screenplay,
+        ProjectTemplate::Blog,
+    ];
+
+    /// Identifier saved in the project metadata.
+    pub fn id(self) -> &'static str {
+        match self {
+            ProjectTemplate::Novel => "novel",
+            ProjectTemplate::ShortStory => "short-story",
+            ProjectTemplate::Screenplay => "screenplay",
+            ProjectTemplate::Blog => "blog",
+        }
+    }
+
+    /// Template of an identifier, `None` if it is unknown.
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|template| template.id() == id)
+    }
+
+    /// Name to show to the user.
+    pub fn display_name(self) -> &'static str {
+        match self {
+            ProjectTemplate::Novel => "Novel",
+            ProjectTemplate::ShortStory => "Short Story",
+            ProjectTemplate::Screenplay => "Screenplay",
+            ProjectTemplate::Blog => "Blog",
+        }
+    }
+
+    /// What the template is for.
+    pub fn description(self) -> &'static str {
+        match self {
+            ProjectTemplate::Novel => {
+                "A long work in parts and chapters, each chapter written as \
+                 one or more scenes."
+            }
+            ProjectTemplate::ShortStory => "A single document, for stories and novellas.",
+            ProjectTemplate::Screenplay => {
+                "A script in three acts, each act written as a sequence of scenes."
+            }
+            ProjectTemplate::Blog => {
+                "Posts drafted in one folder and moved to another once published."
+            }
+        }
+    }
+
+    /// Folders and documents the template starts with, in the content
+    /// directory.
+    pub fn outline(self) -> &'static [TemplateNode] {
+        match self {
+            ProjectTemplate::Novel => NOVEL,
+            ProjectTemplate::ShortStory => SHORT_STORY,
+            ProjectTemplate::Screenplay => SCREENPLAY,
+            ProjectTemplate::Blog => BLOG,
+        }
+    }
+
+    /// Create the folders and empty documents of the outline in the content
+    /// directory of `project`, with their nodes in its structure.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a folder or a document cannot be created.
+    pub fn create(self, project: &mut Project) -> Result<()> {
+        fn create_nodes(
+            project: &mut Project,
+            parent: Option<Uuid>,
+            dir: &str,
+            nodes: &[TemplateNode],
+        ) -> Result<()> {
+            for (index, node) in nodes.iter().enumerate() {
+                let key = format!("{}/{}", dir, node.name);
+                let path = project.path().join(&key);
+                if node.kind == NodeKind::Document {
+                    std::fs::write(&path, "")?;
+                } else {
+                    std::fs::create_dir_all(&path)?;
+                }
+                let id = project.add_node(
+                    parent,
+                    index,
+                    StructureNode::new(node.kind, node.title).with_path(key.clone()),
+                )?;
+                create_nodes(project, Some(id), &key, node.children)?;
+            }
+            Ok(())
+        }
+
+        std::fs::create_dir_all(project.path().join("content"))?;
+        create_nodes(project, None, "content", self.outline())
+    }
+}
+
+/// A template saved from a project.
+///
+/// Its folder holds the description of the template and, under `content/`,
+/// the placeholders of the documents of the project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserTemplate {
+    /// Name of the template, and of its folder
+    pub name: String,
+    /// What the template is for
+    #[serde(default)]
+    pub description: String,
+    /// Settings new projects start with
+    pub settings: ProjectSettings,
+    /// Structure new projects start with
+    pub structure: ProjectStructure,
+    /// Folder the template is saved in
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+impl UserTemplate {
+    /// Folder of the user templates.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config directory cannot be determined.
+    pub fn templates_dir() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join("templates"))
+    }
+
+    /// Templates saved in the folder `dir`, by name. Folders that cannot be
+    /// read as templates are skipped.
+    pub fn load_all(dir: &Path) -> Vec<UserTemplate> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut templates: Vec<UserTemplate> = entries
+            .flatten()
+            .filter(|entry| entry.path().join(USER_TEMPLATE_FILE).is_file())
+            .filter_map(|entry| match Self::load(&entry.path()) {
+                Ok(template) => Some(template),
+                Err(e) => {
+                    warn!("Failed to read template {:?}: {}", entry.path(), e);
+                    None
+                }
+            })
+            .collect();
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        templates
+    }
+
+    /// Read the template saved in the folder `dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if its description cannot be read.
+    pub fn load(dir: &Path) -> Result<UserTemplate> {
+        let content = std::fs::read_to_string(dir.join(USER_TEMPLATE_FILE))?;
+        let mut template: UserTemplate = serde_json::from_str(&content)
+            .map_err(|e| Error::project(format!("Invalid template: {}", e)))?;
+        template.dir = dir.to_path_buf();
+        Ok(template)
+    }
+
+    /// Save `project` as a template named `name` in the folder `dir`,
+    /// replacing any template of that name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name cannot be a folder name, or if the
+    /// template cannot be written.
+    pub fn save(
+        project: &Project,
+        name: &str,
+        description: &str,
+        dir: &Path,
+    ) -> Result<UserTemplate> {
+        let name = name.trim();
+        if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+            return Err(Error::validation(
+                "name",
+                "a template name cannot be empty, contain / or \\ nor be . or ..",
+            ));
+        }
+        let mut template = UserTemplate {
+            name: name.to_string(),
+            description: description.trim().to_string(),
+            settings: project.settings().clone(),
+            structure: project.structure().clone(),
+            dir: dir.join(name),
+        };
+        // Documents of new projects are registered as they are opened
+        let ids: Vec<Uuid> = template.structure.iter().map(|node| node.id).collect();
+        for id in ids {
+            if let Some(node) = template.structure.find_mut(id) {
+                node.document = None;
+            }
+        }
+        if template.dir.exists() {
+            std::fs::remove_dir_all(&template.dir)?;
+        }
+        std::fs::create_dir_all(&template.dir)?;
+
+        for node in template.structure.iter() {
+            let Some(path) = &node.path else {
+                continue;
+            };
+            let source = project.path().join(path);
+            let target = template.dir.join(path);
+            if node.kind == NodeKind::Document {
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let text = std::fs::read_to_string(&source).unwrap_or_default();
+                std::fs::write(&target, placeholder(&text))?;
+            } else {
+                std::fs::create_dir_all(&target)?;
+            }
+        }
+
+        let content = serde_json::to_string_pretty(&template)
+            .map_err(|e| Error::project(format!("Failed to serialize template: {}", e)))?;
+        std::fs::write(template.dir.join(USER_TEMPLATE_FILE), content)?;
+        Ok(template)
+    }
+
+    /// Create the folders and placeholder documents of the template in
+    /// `project`, and give it the template's structure and settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a folder or a document cannot be created.
+    pub fn create(&self, project: &mut Project) -> Result<()> {
+        std::fs::create_dir_all(project.path().join("content"))?;
+        for node in self.structure.iter() {
+            let Some(path) = &node.path else {
+                continue;
+            };
+            let target = project.path().join(path);
+            if node.kind == NodeKind::Document {
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let text = std::fs::read_to_string(self.dir.join(path)).unwrap_or_default();
+                std::fs::write(&target, text)?;
+            } else {
+                std::fs::create_dir_all(&target)?;
+            }
+        }
+
+        let mut structure = self.structure.clone();
+        structure.renew_ids(&HashMap::new());
+        *project.structure_mut() = structure;
+        *project.settings_mut() = self.settings.clone();
+        Ok(())
+    }
+}
+
+/// Placeholder of a document in a template: its headings, each in a
+/// paragraph of its own.
+fn placeholder(text: &str) -> String {
+    let headings: Vec<&str> = text
+        .lines()
+        .filter(|line| line.starts_with('#'))
+        .map(str::trim_end)
+        .collect();
+    if headings.is_empty() {
+        String::new()
+    } else {
+        headings.join("\n\n") + "\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_template_creates_its_outline() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("novel");
+        let mut project = Project::new("Novel", &root, "novel").unwrap();
+        ProjectTemplate::Novel.create(&mut project).unwrap();
+
+        let scene = root.join("content/part-one/chapter-02/scene-01.md");
+        assert!(scene.is_file());
+        let part = &project.structure().roots()[0];
+        assert_eq!(part.kind, NodeKind::Part);
+        assert_eq!(part.title, "Part One");
+        assert_eq!(part.children[1].kind, NodeKind::Chapter);
+
+        // The disk and the structure agree
+        let before = project.structure().clone();
+        project.sync_structure();
+        assert_eq!(project.structure(), &before);
+        assert_eq!(project.structure().reading_order().len(), 2);
+
+        for template in ProjectTemplate::ALL {
+            assert_eq!(ProjectTemplate::from_id(template.id()), Some(template));
+        }
+    }
+
+    #[test]
+    fn test_user_template_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("inn");
+        let mut project = Project::new("The Inn", &root, "novel").unwrap();
+        ProjectTemplate::Novel.create(&mut project).unwrap();
+        let scene = root.join("content/part-one/chapter-01/scene-01.md");
+        std::fs::write(&scene, "# Arrival\n\nThe rain had not stopped.\n## Night\n").unwrap();
+        project
+            .settings_mut()
+            .custom
+            .insert("key".into(), "value".into());
+
+        let templates = temp_dir.path().join("templates");
+        let saved = UserTemplate::save(&project, "Mystery", "Clues first", &templates).unwrap();
+        assert!(UserTemplate::save(&project, "a/b", "", &templates).is_err());
+        let loaded = UserTemplate::load_all(&templates);
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].description, "Clues first");
+        assert_eq!(loaded[0].dir, saved.dir);
+
+        let new_root = temp_dir.path().join("sequel");
+        let mut sequel = Project::new("Sequel", &new_root, "Mystery").unwrap();
+        loaded[0].create(&mut sequel).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(new_root.join("content/part-one/chapter-01/scene-01.md"))
+                .unwrap(),
+            "# Arrival\n\n## Night\n"
+        );
+        assert_eq!(
+            sequel.settings().custom.get("key"),
+            Some(&serde_json::Value::from("value"))
+        );
+        assert_eq!(sequel.structure().reading_order().len(), 2);
+        assert_ne!(
+            sequel.structure().roots()[0].id,
+            project.structure().roots()[0].id
+        );
+    }
+}
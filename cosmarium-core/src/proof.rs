@@ -0,0 +1,306 @@
+//! # Progress proofs
+//!
+//! Writing contests and accountability groups ask writers to show how far
+//! they went. A [`ProgressProof`] states, at a given time, the word count of
+//! a project, a hash of its documents and, for versioned projects, the last
+//! commit. It is signed with the writer's [`ProofKey`], so that anyone can
+//! later check that it was not edited, and, given the project, that the
+//! documents are still the ones it was made from.
+//!
+//! The key is created on first use and kept in the configuration
+//! directory; its public half, printed in every proof, is what others
+//! compare proofs against.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_core::proof::{ProgressProof, ProofKey};
+//!
+//! let key = ProofKey::generate()?;
+//! let proof = ProgressProof::new("The Inn", "9f2c…".to_string(), 12_500, None).sign(&key);
+//! assert!(proof.verify().is_ok());
+//!
+//! let mut forged = proof.clone();
+//! forged.word_count = 50_000;
+//! assert!(forged.verify().is_err());
+//! # Ok::<(), cosmarium_core::Error>(())
+//! ```
+
+use crate::{Config, Error, Project, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Version of the signed statement, bumped if its layout changes.
+const PROOF_VERSION: u32 = 1;
+
+/// File of the signing key in the configuration directory.
+const KEY_FILE: &str = "progress_proof.key";
+
+/// Word count of a project at a given time, signed by its writer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgressProof {
+    /// Version of the signed statement
+    pub version: u32,
+    /// Name of the project
+    pub project: String,
+    /// SHA-256 of the documents of the project, see [`content_hash`]
+    pub content_hash: String,
+    /// Words counted in the documents
+    pub word_count: usize,
+    /// When the proof was made
+    pub timestamp: DateTime<Utc>,
+    /// Last commit of the project repository, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Public key of the writer, in hexadecimal
+    pub public_key: String,
+    /// Signature of the statement, in hexadecimal
+    pub signature: String,
+}
+
+impl ProgressProof {
+    /// Create an unsigned proof made now.
+    pub fn new(
+        project: &str,
+        content_hash: String,
+        word_count: usize,
+        commit: Option<String>,
+    ) -> Self {
+        Self {
+            version: PROOF_VERSION,
+            project: project.to_string(),
+            content_hash,
+            word_count,
+            timestamp: Utc::now(),
+            commit,
+            public_key: String::new(),
+            signature: String::new(),
+        }
+    }
+
+    /// Create an unsigned proof of `project`, hashing its documents and
+    /// reading its last commit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a document cannot be read.
+    pub fn of_project(project: &Project, word_count: usize) -> Result<Self> {
+        let commit = project
+            .git()
+            .and_then(|git| git.head_commit_id().ok().flatten());
+        Ok(Self::new(
+            project.name(),
+            content_hash(project)?,
+            word_count,
+            commit,
+        ))
+    }
+
+    /// Sign the proof with `key`.
+    pub fn sign(mut self, key: &ProofKey) -> Self {
+        self.public_key = key.public_key();
+        self.signature = hex::encode(key.signing.sign(&self.statement()).to_bytes());
+        self
+    }
+
+    /// Check that the proof was signed by the owner of its public key and
+    /// not changed since.
+    ///
+    /// # Errors
+    ///
+    /// Returns a validation error if the key or the signature is malformed,
+    /// or if the signature does not match.
+    pub fn verify(&self) -> Result<()> {
+        let key: [u8; 32] = decode_hex(&self.public_key, "public_key")?;
+        let key = VerifyingKey::from_bytes(&key)
+            .map_err(|_| Error::validation("public_key", "not a valid key"))?;
+        let signature: [u8; 64] = decode_hex(&self.signature, "signature")?;
+        key.verify(&self.statement(), &Signature::from_bytes(&signature))
+            .map_err(|_| Error::validation("signature", "the proof was changed after signing"))
+    }
+
+    /// Check that the documents of `project` are the ones the proof was
+    /// made from.
+    ///
+    /// # Errors
+    ///
+    /// Returns a validation error if they differ, or an error if a document
+    /// cannot be read.
+    pub fn verify_project(&self, project: &Project) -> Result<()> {
+        if content_hash(project)? != self.content_hash {
+            return Err(Error::validation(
+                "content_hash",
+                "the documents changed since the proof was made",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Load a proof from a JSON file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Save the proof as a JSON file.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Bytes that are signed: every field but the key and the signature,
+    /// one per line.
+    fn statement(&self) -> Vec<u8> {
+        format!(
+            "cosmarium progress proof {}\nproject: {}\ncontent: {}\nwords: {}\ntime: {}\ncommit: {}\n",
+            self.version,
+            self.project,
+            self.content_hash,
+            self.word_count,
+            self.timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            self.commit.as_deref().unwrap_or("")
+        )
+        .into_bytes()
+    }
+}
+
+/// Key a writer signs their proofs with.
+pub struct ProofKey {
+    signing: SigningKey,
+}
+
+impl ProofKey {
+    /// Create a new random key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the system has no source of randomness.
+    pub fn generate() -> Result<Self> {
+        let mut secret = [0u8; 32];
+        getrandom::getrandom(&mut secret)
+            .map_err(|e| Error::generic(format!("Cannot create a signing key: {}", e)))?;
+        Ok(Self {
+            signing: SigningKey::from_bytes(&secret),
+        })
+    }
+
+    /// Path of the writer's key, in the configuration directory.
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join(KEY_FILE))
+    }
+
+    /// Load the key saved at `path`, creating and saving a new one if there
+    /// is none.
+    pub fn load_or_create(path: &Path) -> Result<Self> {
+        if path.exists() {
+            let secret = decode_hex(std::fs::read_to_string(path)?.trim(), "key")?;
+            return Ok(Self {
+                signing: SigningKey::from_bytes(&secret),
+            });
+        }
+
+        let key = Self::generate()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, hex::encode(key.signing.to_bytes()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(key)
+    }
+
+    /// Public half of the key, in hexadecimal.
+    pub fn public_key(&self) -> String {
+        hex::encode(self.signing.verifying_key().to_bytes())
+    }
+}
+
+/// SHA-256, in hexadecimal, of the documents of `project` in reading order.
+///
+/// Each document adds its path, its length and its text, so that moving
+/// text from one document to another changes the hash.
+///
+/// # Errors
+///
+/// Returns an error if a document cannot be read.
+pub fn content_hash(project: &Project) -> Result<String> {
+    let mut hasher = Sha256::new();
+    for node in project.structure().reading_order() {
+        let Some(path) = &node.path else {
+            continue;
+        };
+        let content = std::fs::read(project.path().join(path))?;
+        hasher.update(path.as_bytes());
+        hasher.update([0]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(&content);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Decode a hexadecimal field of a known length.
+fn decode_hex<const N: usize>(text: &str, field: &str) -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    hex::decode_to_slice(text, &mut bytes)
+        .map_err(|e| Error::validation(field.to_string(), format!("not valid: {}", e)))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_proof_survives_a_round_trip_but_not_an_edit() {
+        let temp_dir = tempdir().unwrap();
+        let key_path = temp_dir.path().join("keys").join(KEY_FILE);
+        let key = ProofKey::load_or_create(&key_path).unwrap();
+        let again = ProofKey::load_or_create(&key_path).unwrap();
+        assert_eq!(key.public_key(), again.public_key());
+
+        let proof = ProgressProof::new("Novel", "abc".to_string(), 1200, Some("f00d".to_string()))
+            .sign(&key);
+        let path = temp_dir.path().join("proof.json");
+        proof.save(&path).unwrap();
+        let loaded = ProgressProof::load(&path).unwrap();
+        assert_eq!(loaded, proof);
+        assert!(loaded.verify().is_ok());
+
+        let mut forged = loaded.clone();
+        forged.commit = None;
+        assert!(forged.verify().is_err());
+        let mut forged = loaded.clone();
+        forged.timestamp += chrono::Duration::days(1);
+        assert!(forged.verify().is_err());
+        let mut forged = loaded;
+        forged.public_key = ProofKey::generate().unwrap().public_key();
+        assert!(forged.verify().is_err());
+    }
+
+    #[test]
+    fn test_proof_checks_the_documents() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("novel");
+        std::fs::create_dir_all(root.join("content")).unwrap();
+        std::fs::write(root.join("content/dawn.md"), "Fog.").unwrap();
+        std::fs::write(root.join("content/dusk.md"), "Rain.").unwrap();
+        let mut project = Project::new("Novel", &root, "novel").unwrap();
+        project.sync_structure();
+
+        let key = ProofKey::generate().unwrap();
+        let proof = ProgressProof::of_project(&project, 2).unwrap().sign(&key);
+        assert!(proof.verify_project(&project).is_ok());
+
+        std::fs::write(root.join("content/dusk.md"), "Rain, then snow.").unwrap();
+        assert!(proof.verify_project(&project).is_err());
+        // The signature itself still holds
+        assert!(proof.verify().is_ok());
+    }
+}
@@ -0,0 +1,849 @@
+//! Workspace search.
+//!
+//! Searches a project beyond the text of its documents: the author's notes,
+//! the annotations left in the manuscript, synopses, entity sheets and
+//! metadata fields. A [`WorkspaceIndex`] holds the searchable text of a
+//! project, each piece tagged with its [`SearchSource`]; a [`SearchQuery`]
+//! selects the sources to search and results come back grouped by source.
+//!
+//! Files are found by convention under the project root:
+//!
+//! - `content/`: manuscript documents; their HTML comments (`<!-- -->`) and
+//!   CriticMarkup comments (`{>> <<}`) are annotations
+//! - `notes/`: research and planning notes
+//! - `entities/`: character sheets, places and other story entities, one
+//!   folder per kind (`entities/characters/`, `entities/places/`)
+//!
+//! The YAML front matter of these files is indexed as metadata, as are the
+//! project's description, tags and properties and the metadata of
+//! structure nodes, whose `synopsis` field is indexed as a synopsis.
+//!
+//! Queries accept quoted phrases and boolean operators (`AND`, `OR`, `NOT`,
+//! `+word`, `-word`), match words by their stem in the manuscript's
+//! language and tolerate one typo per word. Hits are ranked by relevance:
+//! matches in titles and headings weigh more, and recently modified files
+//! come first.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_core::search::{SearchQuery, SearchSource, WorkspaceIndex};
+//!
+//! let mut index = WorkspaceIndex::new();
+//! index.add_file("content/arrival.md", "She ran. <!-- check the inn's name -->");
+//! index.add_file("entities/characters/ann.md", "Ann owns the inn.");
+//!
+//! let results = index.search(&SearchQuery::new("inn"))?;
+//! assert_eq!(results.len(), 2);
+//!
+//! let query = SearchQuery::new("inn").in_sources([SearchSource::Annotation]);
+//! let results = index.search(&query)?;
+//! assert_eq!(results.hits(SearchSource::Annotation)[0].snippet, "check the inn's name");
+//! # Ok::<(), cosmarium_core::Error>(())
+//! ```
+
+mod engine;
+pub mod global;
+pub mod quick;
+pub mod replace;
+mod service;
+
+use crate::project::ProjectMetadata;
+use crate::structure::ProjectStructure;
+use crate::Result;
+pub use cosmarium_plugin_api::search::{SearchHit, SearchQuery, SearchSource};
+use engine::{stemming_language, Engine};
+pub use service::{IndexUpdate, SearchService};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+use tantivy::tokenizer::Language;
+
+/// Extensions of the text files indexed.
+const TEXT_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// Folders of the project indexed, with the source of their files.
+const FOLDERS: &[(&str, SearchSource)] = &[
+    ("content", SearchSource::Document),
+    ("notes", SearchSource::Note),
+    ("entities", SearchSource::Entity),
+];
+
+/// Longest snippet shown for a hit, in characters.
+const SNIPPET_LENGTH: usize = 100;
+
+/// Hits returned by a search at most.
+const MAX_HITS: usize = 500;
+
+/// Days for the boost of recently modified files to halve.
+const RECENCY_HALF_LIFE_DAYS: f32 = 30.0;
+
+/// Search hits grouped by source, most relevant first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchResults {
+    groups: BTreeMap<SearchSource, Vec<SearchHit>>,
+}
+
+impl SearchResults {
+    /// Sources with hits and their hits, in [`SearchSource::ALL`] order.
+    pub fn groups(&self) -> impl Iterator<Item = (SearchSource, &[SearchHit])> {
+        self.groups
+            .iter()
+            .map(|(source, hits)| (*source, hits.as_slice()))
+    }
+
+    /// Hits of one source.
+    pub fn hits(&self, source: SearchSource) -> &[SearchHit] {
+        self.groups.get(&source).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Number of hits.
+    pub fn len(&self) -> usize {
+        self.groups.values().map(Vec::len).sum()
+    }
+
+    /// Whether nothing was found.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Add the hits of `other`, keeping each source's hits most relevant
+    /// first.
+    pub fn merge(&mut self, other: SearchResults) {
+        for (source, hits) in other.groups {
+            let group = self.groups.entry(source).or_default();
+            group.extend(hits);
+            group.sort_by(|a, b| b.score.total_cmp(&a.score));
+            group.truncate(MAX_HITS);
+        }
+    }
+}
+
+/// What indexed a piece of text, and replaces it when indexing again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Origin {
+    File,
+    Structure,
+    Project,
+}
+
+/// A piece of searchable text.
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    origin: Origin,
+    source: SearchSource,
+    path: Option<String>,
+    title: String,
+    field: Option<String>,
+    /// Line of the text's start in its file, starting at 1 (0 when the text
+    /// is not from a file)
+    line: usize,
+    /// Column of the text's start in its first line, starting at 1
+    column: usize,
+    text: String,
+    /// Last modification of the file
+    modified: Option<SystemTime>,
+}
+
+impl Entry {
+    /// Whether the entry was indexed from the file at `path`.
+    fn is_from_file(&self, path: &str) -> bool {
+        self.origin == Origin::File && self.path.as_deref() == Some(path)
+    }
+
+    /// Whether the entry is the text of a note, entity or document, whose
+    /// title is searchable too.
+    fn is_file_text(&self) -> bool {
+        self.origin == Origin::File
+            && self.field.is_none()
+            && self.source != SearchSource::Annotation
+    }
+
+    /// Weight of the entry's matches for how recently its file changed:
+    /// up to 1.5 for a file modified now, halving with age towards 1.
+    fn recency_boost(&self, now: SystemTime) -> f32 {
+        let Some(age) = self
+            .modified
+            .and_then(|modified| now.duration_since(modified).ok())
+        else {
+            return if self.modified.is_some() { 1.5 } else { 1.0 };
+        };
+        let days = age.as_secs_f32() / 86_400.0;
+        1.0 + 0.5 * 0.5f32.powf(days / RECENCY_HALF_LIFE_DAYS)
+    }
+}
+
+/// Searchable text of a project.
+///
+/// The inverted index is built on the first search, then updated as text
+/// is indexed again.
+#[derive(Debug)]
+pub struct WorkspaceIndex {
+    /// Pieces of text by key, in the order they were indexed
+    entries: BTreeMap<u64, Entry>,
+    next_key: u64,
+    language: Option<Language>,
+    engine: OnceLock<Engine>,
+}
+
+impl Clone for WorkspaceIndex {
+    /// Copy the text indexed; the copy builds its own inverted index, which
+    /// updates would otherwise change for both.
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+            next_key: self.next_key,
+            language: self.language,
+            engine: OnceLock::new(),
+        }
+    }
+}
+
+impl Default for WorkspaceIndex {
+    fn default() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            next_key: 0,
+            language: Some(Language::English),
+            engine: OnceLock::new(),
+        }
+    }
+}
+
+impl WorkspaceIndex {
+    /// Create an empty index, stemming English words.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stem words in the language of an ISO 639-1 code or locale (`fr`,
+    /// `en_US`); words of languages without a stemmer are matched whole.
+    pub fn with_language(mut self, code: &str) -> Self {
+        self.language = stemming_language(code);
+        self.engine = OnceLock::new();
+        self
+    }
+
+    /// Index the files, structure and metadata of the project at `root`.
+    ///
+    /// Files that cannot be read are skipped.
+    pub fn build(root: &Path, metadata: &ProjectMetadata, structure: &ProjectStructure) -> Self {
+        let mut index = Self::new();
+        for (folder, _) in FOLDERS {
+            for entry in walkdir::WalkDir::new(root.join(folder))
+                .sort_by_file_name()
+                .into_iter()
+                .flatten()
+                .filter(|entry| entry.file_type().is_file())
+            {
+                let is_text = is_text(entry.path());
+                let Some(key) = is_text.then(|| relative_key(root, entry.path())).flatten() else {
+                    continue;
+                };
+                let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+                match std::fs::read_to_string(entry.path()) {
+                    Ok(text) => index.add_file_at(&key, &text, modified),
+                    Err(e) => tracing::warn!("Cannot index {:?}: {}", entry.path(), e),
+                }
+            }
+        }
+        index.add_structure(structure);
+        index.add_project_metadata(metadata);
+        index
+    }
+
+    /// Number of pieces of text indexed.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether nothing is indexed.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Index the text of a file being edited, replacing what was indexed
+    /// for it.
+    ///
+    /// `path` is relative to the project root, with `/` separators; files
+    /// outside the `content`, `notes` and `entities` folders are ignored.
+    pub fn add_file(&mut self, path: &str, text: &str) {
+        self.add_file_at(path, text, Some(SystemTime::now()));
+    }
+
+    /// Index the text of a file last modified at `modified`, replacing what
+    /// was indexed for it.
+    pub fn add_file_at(&mut self, path: &str, text: &str, modified: Option<SystemTime>) {
+        let Some(source) = FOLDERS
+            .iter()
+            .find(|(folder, _)| path.starts_with(&format!("{}/", folder)))
+            .map(|(_, source)| *source)
+        else {
+            self.remove_file(path);
+            return;
+        };
+
+        let title = Path::new(path)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(path)
+            .to_string();
+        let entry = |source, field: Option<String>, line, column, text: String| Entry {
+            origin: Origin::File,
+            source,
+            path: Some(path.to_string()),
+            title: title.clone(),
+            field,
+            line,
+            column,
+            text,
+            modified,
+        };
+
+        let mut entries = Vec::new();
+        let (fields, body_start) = front_matter(text);
+        for (line, key, value) in fields {
+            entries.push(entry(SearchSource::Metadata, Some(key), line, 1, value));
+        }
+
+        // Blank the front matter so that lines keep their numbers
+        let mut body: String = text[..body_start].chars().filter(|c| *c == '\n').collect();
+        body.push_str(&text[body_start..]);
+        if source == SearchSource::Document {
+            for (line, column, comment) in take_annotations(&mut body) {
+                entries.push(entry(SearchSource::Annotation, None, line, column, comment));
+            }
+        }
+        entries.push(entry(source, None, 1, 1, body));
+        self.replace_entries(|entry| entry.is_from_file(path), entries);
+    }
+
+    /// Forget the text of a file.
+    pub fn remove_file(&mut self, path: &str) {
+        self.replace_entries(|entry| entry.is_from_file(path), Vec::new());
+    }
+
+    /// Index the metadata of structure nodes, replacing what was indexed.
+    pub fn add_structure(&mut self, structure: &ProjectStructure) {
+        let mut entries = Vec::new();
+        for node in structure.iter() {
+            for (key, value) in &node.metadata {
+                let source = if key == "synopsis" {
+                    SearchSource::Synopsis
+                } else {
+                    SearchSource::Metadata
+                };
+                entries.push(Entry {
+                    origin: Origin::Structure,
+                    source,
+                    path: node.path.clone(),
+                    title: node.title.clone(),
+                    field: Some(key.clone()),
+                    line: 0,
+                    column: 1,
+                    text: value.clone(),
+                    modified: None,
+                });
+            }
+        }
+        self.replace_entries(|entry| entry.origin == Origin::Structure, entries);
+    }
+
+    /// Index the project's description, author, tags and properties,
+    /// replacing what was indexed.
+    pub fn add_project_metadata(&mut self, metadata: &ProjectMetadata) {
+        let mut fields = vec![
+            ("description".to_string(), metadata.description.clone()),
+            ("author".to_string(), metadata.author.clone()),
+            ("tags".to_string(), metadata.tags.join(", ")),
+        ];
+        let mut properties: Vec<_> = metadata.properties.iter().collect();
+        properties.sort();
+        fields.extend(properties.into_iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        let mut entries = Vec::new();
+        for (field, text) in fields {
+            if text.trim().is_empty() {
+                continue;
+            }
+            entries.push(Entry {
+                origin: Origin::Project,
+                source: SearchSource::Metadata,
+                path: None,
+                title: "Project".to_string(),
+                field: Some(field),
+                line: 0,
+                column: 1,
+                text,
+                modified: None,
+            });
+        }
+        self.replace_entries(|entry| entry.origin == Origin::Project, entries);
+    }
+
+    /// Replace the entries matching `stale` with `fresh`, updating the
+    /// inverted index when it is built.
+    fn replace_entries(&mut self, stale: impl Fn(&Entry) -> bool, fresh: Vec<Entry>) {
+        let removed: Vec<u64> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| stale(entry))
+            .map(|(key, _)| *key)
+            .collect();
+        if removed.len() == fresh.len()
+            && removed
+                .iter()
+                .zip(&fresh)
+                .all(|(key, entry)| self.entries.get(key) == Some(entry))
+        {
+            return;
+        }
+
+        for key in &removed {
+            self.entries.remove(key);
+        }
+        let added = self.next_key..self.next_key + fresh.len() as u64;
+        self.entries.extend(added.clone().zip(fresh));
+        self.next_key = added.end;
+
+        let Some(engine) = self.engine.get_mut() else {
+            return;
+        };
+        let entries = &self.entries;
+        let added = added.filter_map(|key| entries.get(&key).map(|entry| (key, entry)));
+        if let Err(e) = engine.update(&removed, added) {
+            tracing::warn!("Cannot update the search index, building it again: {}", e);
+            self.engine = OnceLock::new();
+        }
+    }
+
+    /// Find the lines and titles matching a query.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the inverted index cannot be built.
+    pub fn search(&self, query: &SearchQuery) -> Result<SearchResults> {
+        let mut results = SearchResults::default();
+        if query.text.trim().is_empty() || self.entries.is_empty() {
+            return Ok(results);
+        }
+
+        let engine = match self.engine.get() {
+            Some(engine) => engine,
+            None => {
+                let engine = Engine::build(&self.entries, self.language)?;
+                self.engine.get_or_init(|| engine)
+            }
+        };
+        let (matches, words) = engine.search(
+            &query.text,
+            query.sources.iter().copied(),
+            query.folder(),
+            MAX_HITS,
+        )?;
+
+        let now = SystemTime::now();
+        let typed = query.words();
+        let mut seen = HashSet::new();
+        for found in matches {
+            let Some(entry) = self.entries.get(&found.entry) else {
+                continue;
+            };
+            let text = match found.line {
+                Some(n) => entry.text.lines().nth(n).unwrap_or_default(),
+                None => entry.title.as_str(),
+            };
+            if query.case_sensitive && !typed.iter().any(|word| text.contains(word)) {
+                continue;
+            }
+            if !seen.insert((found.entry, found.line)) {
+                continue;
+            }
+
+            // Typos in short words are not tolerated
+            let Some((start, end)) = engine.highlight(text, &words) else {
+                continue;
+            };
+            let hit = match found.line {
+                Some(n) => {
+                    let offset = if n == 0 { entry.column - 1 } else { 0 };
+                    SearchHit {
+                        source: entry.source,
+                        path: entry.path.clone(),
+                        title: entry.title.clone(),
+                        field: entry.field.clone(),
+                        line: (entry.line > 0).then_some(entry.line + n),
+                        column: offset + text[..start].chars().count() + 1,
+                        before: n.checked_sub(1).and_then(|n| context(&entry.text, n)),
+                        snippet: snippet(text, start, end),
+                        after: context(&entry.text, n + 1),
+                        score: found.score * entry.recency_boost(now),
+                        project: None,
+                    }
+                }
+                None => {
+                    // Titles show the opening of their text
+                    let opening = entry
+                        .text
+                        .lines()
+                        .find(|line| !line.trim().is_empty())
+                        .unwrap_or_default();
+                    SearchHit {
+                        source: entry.source,
+                        path: entry.path.clone(),
+                        title: entry.title.clone(),
+                        field: None,
+                        line: None,
+                        column: 1,
+                        before: None,
+                        snippet: snippet(opening, 0, 0),
+                        after: None,
+                        score: found.score * entry.recency_boost(now),
+                        project: None,
+                    }
+                }
+            };
+            results.groups.entry(entry.source).or_default().push(hit);
+        }
+
+        for hits in results.groups.values_mut() {
+            hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        }
+        Ok(results)
+    }
+}
+
+/// Whether the file at `path` is a text file indexed.
+fn is_text(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| TEXT_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Path of `path` relative to `root`, with `/` separators.
+fn relative_key(root: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(root).ok()?;
+    Some(
+        rel.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// Fields of the YAML front matter opening `text`, with their line, and the
+/// byte offset of the text after it.
+///
+/// Only `key: value` lines are read; list items are added to the value of
+/// the key above them.
+fn front_matter(text: &str) -> (Vec<(usize, String, String)>, usize) {
+    let mut lines = text.split_inclusive('\n');
+    let Some(first) = lines.next().filter(|line| line.trim_end() == "---") else {
+        return (Vec::new(), 0);
+    };
+
+    let mut fields: Vec<(usize, String, String)> = Vec::new();
+    let mut offset = first.len();
+    for (i, line) in lines.enumerate() {
+        offset += line.len();
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            return (fields, offset);
+        }
+        if let Some(item) = trimmed.trim_start().strip_prefix("- ") {
+            if let Some((_, _, value)) = fields.last_mut() {
+                if !value.is_empty() {
+                    value.push_str(", ");
+                }
+                value.push_str(item.trim());
+            }
+        } else if let Some((key, value)) = trimmed.split_once(':') {
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+            fields.push((i + 2, key.trim().to_string(), value.to_string()));
+        }
+    }
+    // Unclosed front matter is text
+    (Vec::new(), 0)
+}
+
+/// Remove the comments of `body`, replacing them with spaces, and return
+/// them with the line and column where their text starts.
+fn take_annotations(body: &mut String) -> Vec<(usize, usize, String)> {
+    let mut annotations = Vec::new();
+    let mut blanked = String::with_capacity(body.len());
+    let mut rest = body.as_str();
+    let (mut line, mut column) = (1, 1);
+
+    let advance = |text: &str, line: &mut usize, column: &mut usize| {
+        for c in text.chars() {
+            if c == '\n' {
+                *line += 1;
+                *column = 1;
+            } else {
+                *column += 1;
+            }
+        }
+    };
+
+    loop {
+        let next = [("<!--", "-->"), ("{>>", "<<}")]
+            .iter()
+            .filter_map(|(open, close)| rest.find(open).map(|at| (at, *open, *close)))
+            .min_by_key(|(at, _, _)| *at);
+        let Some((at, open, close)) = next else {
+            blanked.push_str(rest);
+            break;
+        };
+        let Some(len) = rest[at + open.len()..].find(close) else {
+            blanked.push_str(rest);
+            break;
+        };
+
+        blanked.push_str(&rest[..at]);
+        advance(&rest[..at], &mut line, &mut column);
+        let inner = &rest[at + open.len()..at + open.len() + len];
+        let whole = &rest[at..at + open.len() + len + close.len()];
+
+        // Start the annotation at its first non-blank character
+        let lead = &inner[..inner.len() - inner.trim_start().len()];
+        let (mut text_line, mut text_column) = (line, column + open.chars().count());
+        advance(lead, &mut text_line, &mut text_column);
+        if !inner.trim().is_empty() {
+            annotations.push((text_line, text_column, inner.trim().to_string()));
+        }
+
+        blanked.extend(whole.chars().map(|c| if c == '\n' { '\n' } else { ' ' }));
+        advance(whole, &mut line, &mut column);
+        rest = &rest[at + whole.len()..];
+    }
+
+    *body = blanked;
+    annotations
+}
+
+/// Line `n` of `text`, shortened, unless it is blank.
+fn context(text: &str, n: usize) -> Option<String> {
+    let line = text.lines().nth(n)?;
+    (!line.trim().is_empty()).then(|| snippet(line.trim_end(), 0, 0))
+}
+
+/// `line` trimmed and, if too long, shortened around the byte range of a
+/// match.
+fn snippet(line: &str, start: usize, end: usize) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let match_start = line[..start].chars().count();
+    let match_end = line[..end].chars().count();
+
+    let (mut from, mut to) = (0, chars.len());
+    if to - from > SNIPPET_LENGTH {
+        from = match_start.saturating_sub(SNIPPET_LENGTH / 3);
+        to = (from + SNIPPET_LENGTH).max(match_end).min(chars.len());
+    }
+    let mut snippet: String = chars[from..to]
+        .iter()
+        .collect::<String>()
+        .trim()
+        .to_string();
+    if from > 0 {
+        snippet.insert(0, '…');
+    }
+    if to < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::{NodeKind, StructureNode};
+
+    #[test]
+    fn test_sources_and_positions() {
+        let mut index = WorkspaceIndex::new();
+        index.add_file(
+            "content/ch1/inn.md",
+            "---\nstatus: draft\ntags:\n  - inn\n---\nThe Inn was dark.\nShe <!-- rename\nthe inn? --> ran.",
+        );
+        index.add_file("notes/research.md", "Coaching inns of 1820.");
+        index.add_file("entities/characters/ann.md", "Ann keeps the INN.");
+        index.add_file("meta/core.toon", "inn");
+
+        let results = index.search(&SearchQuery::new("inn")).unwrap();
+        let sources: Vec<_> = results.groups().map(|(source, _)| source).collect();
+        assert_eq!(
+            sources,
+            vec![
+                SearchSource::Document,
+                SearchSource::Annotation,
+                SearchSource::Note,
+                SearchSource::Entity,
+                SearchSource::Metadata,
+            ]
+        );
+
+        // The document keeps its line numbers and loses its comments; its
+        // title matches too
+        let documents = results.hits(SearchSource::Document);
+        assert_eq!(documents.len(), 2);
+        let title = documents.iter().find(|hit| hit.line.is_none()).unwrap();
+        assert_eq!(title.snippet, "The Inn was dark.");
+        let line = documents.iter().find(|hit| hit.line.is_some()).unwrap();
+        assert_eq!((line.line, line.column), (Some(6), 5));
+        assert_eq!(line.snippet, "The Inn was dark.");
+
+        let annotations = results.hits(SearchSource::Annotation);
+        assert_eq!(annotations[0].line, Some(8));
+        assert_eq!(annotations[0].column, 5);
+        assert_eq!(annotations[0].snippet, "the inn?");
+
+        // "ann" is one letter away, but short words must match exactly
+        assert_eq!(results.hits(SearchSource::Entity).len(), 1);
+
+        let metadata = results.hits(SearchSource::Metadata);
+        assert_eq!(metadata[0].field.as_deref(), Some("tags"));
+        assert_eq!(metadata[0].line, Some(3));
+
+        // Replacing a file drops its old text
+        index.add_file("notes/research.md", "Stagecoaches.");
+        assert!(index
+            .search(&SearchQuery::new("inn"))
+            .unwrap()
+            .hits(SearchSource::Note)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_scoped_queries() {
+        let mut index = WorkspaceIndex::new();
+        index.add_file("entities/characters/ann.md", "Ann, innkeeper.");
+        index.add_file("entities/places/inn.md", "The innkeeper's inn.");
+        index.add_file("content/one.md", "The innkeeper smiled.");
+
+        let mut structure = ProjectStructure::default();
+        let mut chapter = StructureNode::new(NodeKind::Chapter, "Arrival");
+        chapter.metadata.insert(
+            "synopsis".to_string(),
+            "Ann meets the innkeeper".to_string(),
+        );
+        chapter
+            .metadata
+            .insert("status".to_string(), "Innkeeper draft".to_string());
+        structure.insert(None, 0, chapter).unwrap();
+        index.add_structure(&structure);
+
+        let mut metadata = ProjectMetadata::new("Inn", "novel");
+        metadata.description = "A novel about an innkeeper".to_string();
+        index.add_project_metadata(&metadata);
+
+        let characters = SearchQuery::new("innkeeper").within("entities/characters");
+        let results = index.search(&characters).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results.hits(SearchSource::Entity)[0].title, "ann");
+
+        let synopses = SearchQuery::new("innkeeper").in_sources([SearchSource::Synopsis]);
+        let results = index.search(&synopses).unwrap();
+        assert_eq!(results.len(), 1);
+        let hit = &results.hits(SearchSource::Synopsis)[0];
+        assert_eq!((hit.title.as_str(), hit.line), ("Arrival", None));
+
+        let metadata = index
+            .search(&SearchQuery::new("innkeeper").in_sources([SearchSource::Metadata]))
+            .unwrap();
+        let mut titles: Vec<_> = metadata
+            .hits(SearchSource::Metadata)
+            .iter()
+            .map(|hit| hit.title.as_str())
+            .collect();
+        titles.sort();
+        assert_eq!(titles, vec!["Arrival", "Project"]);
+
+        let mut exact = SearchQuery::new("Innkeeper");
+        exact.case_sensitive = true;
+        assert_eq!(index.search(&exact).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_edits_update_the_built_index() {
+        let mut index = WorkspaceIndex::new();
+        index.add_file("content/one.md", "The inn was dark.");
+        index.add_file("notes/names.md", "Find a name for the inn.");
+        let mut metadata = ProjectMetadata::new("Inn", "novel");
+        metadata.description = "A novel about an inn".to_string();
+        index.add_project_metadata(&metadata);
+        assert_eq!(index.search(&SearchQuery::new("inn")).unwrap().len(), 3);
+
+        index.add_file("content/one.md", "The stable was dark.");
+        index.remove_file("notes/names.md");
+        index.add_project_metadata(&metadata);
+        assert!(index.engine.get().is_some());
+        let results = index.search(&SearchQuery::new("inn")).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results.hits(SearchSource::Metadata).len(), 1);
+        let results = index.search(&SearchQuery::new("stable")).unwrap();
+        assert_eq!(results.hits(SearchSource::Document)[0].line, Some(1));
+
+        let mut copy = index.clone();
+        copy.add_file("content/one.md", "The inn again.");
+        assert_eq!(copy.search(&SearchQuery::new("inn")).unwrap().len(), 2);
+        assert_eq!(index.search(&SearchQuery::new("inn")).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_query_syntax_and_ranking() {
+        let day = std::time::Duration::from_secs(86_400);
+        let now = SystemTime::now();
+        let mut index = WorkspaceIndex::new();
+        index.add_file_at(
+            "content/a.md",
+            "# The Lighthouse\n\nNothing here.",
+            Some(now),
+        );
+        index.add_file_at("content/b.md", "She saw the lighthouse at dusk.", Some(now));
+        index.add_file_at(
+            "notes/old.md",
+            "He walked to the harbour.",
+            Some(now - 365 * day),
+        );
+        index.add_file_at("notes/new.md", "He walked to the harbour.", Some(now));
+
+        let paths = |query: &str| -> Vec<String> {
+            let results = index.search(&SearchQuery::new(query)).unwrap();
+            results
+                .groups()
+                .flat_map(|(_, hits)| hits)
+                .filter_map(|hit| hit.path.clone())
+                .collect()
+        };
+
+        // Headings rank above text, recent files above old ones
+        assert_eq!(paths("lighthouse"), vec!["content/a.md", "content/b.md"]);
+        assert_eq!(paths("harbour"), vec!["notes/new.md", "notes/old.md"]);
+
+        assert_eq!(paths("\"saw the lighthouse\""), vec!["content/b.md"]);
+        assert!(paths("\"lighthouse saw\"").is_empty());
+        assert_eq!(paths("lighthouse AND dusk"), vec!["content/b.md"]);
+        assert_eq!(paths("lighthouse -dusk"), vec!["content/a.md"]);
+
+        // Typos, stems
+        assert_eq!(paths("lighthose").len(), 2);
+        assert!(paths("dsk").is_empty());
+        assert_eq!(paths("walking").len(), 2);
+
+        let mut french = WorkspaceIndex::new().with_language("fr_FR");
+        french.add_file("content/choir.md", "Elles chantaient.");
+        let results = french.search(&SearchQuery::new("chanter")).unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn test_snippets_shorten_long_lines() {
+        let line = format!("{}needle{}", "a".repeat(200), "b".repeat(200));
+        let start = line.find("needle").unwrap();
+        let snippet = snippet(&line, start, start + 6);
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.contains("needle"));
+        assert_eq!(snippet.chars().count(), SNIPPET_LENGTH + 2);
+    }
+}
@@ -0,0 +1,370 @@
+//! Inverted index of workspace search.
+//!
+//! Every line of indexed text becomes a document of an in-memory Tantivy
+//! index, and every file one more document holding its title. Queries use
+//! Tantivy's syntax (`"phrase"`, `AND`, `OR`, `NOT`, `+word`, `-word`) and
+//! match words by their stem; a second, typo-tolerant reading of the query
+//! catches words one edit away, with a lower weight than exact matches.
+//! Typos are only tolerated in words of [`MIN_FUZZY_CHARS`] letters or more,
+//! which [`Engine::highlight`] checks on the matching lines.
+
+use super::{Entry, SearchSource};
+use std::collections::BTreeMap;
+use std::fmt;
+use tantivy::collector::TopDocs;
+use tantivy::query::{
+    BooleanQuery, BoostQuery, ConstScoreQuery, Occur, Query, QueryParser, TermQuery,
+};
+use tantivy::schema::{
+    Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value, INDEXED, STORED,
+    STRING,
+};
+use tantivy::tokenizer::{
+    AsciiFoldingFilter, Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer,
+    TextAnalyzer,
+};
+use tantivy::{Index, IndexReader, IndexWriter, TantivyDocument, Term};
+
+/// Name of the analyzer of searchable text.
+const ANALYZER: &str = "cosmarium";
+
+/// Memory of the index writer, in bytes (Tantivy's minimum).
+const WRITER_MEMORY: usize = 15_000_000;
+
+/// Weight of title and heading matches against text matches.
+const TITLE_BOOST: f32 = 3.0;
+const HEADING_BOOST: f32 = 2.0;
+
+/// Weight of the typo-tolerant reading of a query.
+const FUZZY_WEIGHT: f32 = 0.5;
+
+/// Shortest word, in characters, whose typos are tolerated.
+const MIN_FUZZY_CHARS: usize = 5;
+
+/// A line, or a title when `line` is `None`, matching a query.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct Match {
+    pub score: f32,
+    /// Key of the entry in the workspace index
+    pub entry: u64,
+    /// Line in the entry's text, starting at 0
+    pub line: Option<usize>,
+}
+
+#[derive(Clone, Copy)]
+struct Fields {
+    entry: Field,
+    line: Field,
+    text: Field,
+    heading: Field,
+    title: Field,
+    source: Field,
+    folder: Field,
+}
+
+/// Search engine over the entries of a workspace index.
+pub(super) struct Engine {
+    index: Index,
+    reader: IndexReader,
+    fields: Fields,
+    analyzer: TextAnalyzer,
+}
+
+impl fmt::Debug for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Engine").finish_non_exhaustive()
+    }
+}
+
+/// Stemming language of an ISO 639-1 code or locale (`fr`, `en_US`).
+pub(super) fn stemming_language(code: &str) -> Option<Language> {
+    let code = code.split(['_', '-']).next().unwrap_or(code).to_lowercase();
+    Some(match code.as_str() {
+        "ar" => Language::Arabic,
+        "da" => Language::Danish,
+        "nl" => Language::Dutch,
+        "en" => Language::English,
+        "fi" => Language::Finnish,
+        "fr" => Language::French,
+        "de" => Language::German,
+        "el" => Language::Greek,
+        "hu" => Language::Hungarian,
+        "it" => Language::Italian,
+        "no" | "nb" | "nn" => Language::Norwegian,
+        "pt" => Language::Portuguese,
+        "ro" => Language::Romanian,
+        "ru" => Language::Russian,
+        "es" => Language::Spanish,
+        "sv" => Language::Swedish,
+        "ta" => Language::Tamil,
+        "tr" => Language::Turkish,
+        _ => return None,
+    })
+}
+
+/// Words in lowercase, stemmed and without accents.
+fn analyzer(language: Option<Language>) -> TextAnalyzer {
+    let builder = TextAnalyzer::builder(SimpleTokenizer::default())
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+        .dynamic();
+    match language {
+        Some(language) => builder
+            .filter_dynamic(Stemmer::new(language))
+            .filter_dynamic(AsciiFoldingFilter)
+            .build(),
+        None => builder.filter_dynamic(AsciiFoldingFilter).build(),
+    }
+}
+
+/// `path` and the folders above it (`entities`, `entities/characters`...).
+fn folders(path: &str) -> Vec<&str> {
+    path.match_indices('/')
+        .map(|(at, _)| &path[..at])
+        .chain([path])
+        .collect()
+}
+
+/// Whether a line is a Markdown heading.
+fn is_heading(line: &str) -> bool {
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    (1..=6).contains(&hashes) && line[hashes..].starts_with(' ')
+}
+
+/// Index the lines of an entry, and its title when it is a file's text.
+fn add_entry(writer: &IndexWriter, fields: Fields, id: u64, entry: &Entry) -> tantivy::Result<()> {
+    let folders = entry.path.as_deref().map(folders).unwrap_or_default();
+    let document = |line: Option<usize>| {
+        let mut doc = TantivyDocument::default();
+        doc.add_u64(fields.entry, id);
+        if let Some(line) = line {
+            doc.add_u64(fields.line, line as u64);
+        }
+        doc.add_text(fields.source, entry.source.id());
+        for folder in &folders {
+            doc.add_text(fields.folder, folder);
+        }
+        doc
+    };
+
+    if entry.is_file_text() {
+        let mut doc = document(None);
+        doc.add_text(fields.title, &entry.title);
+        writer.add_document(doc)?;
+    }
+    for (n, line) in entry.text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut doc = document(Some(n));
+        doc.add_text(fields.text, line);
+        if entry.source != SearchSource::Metadata && is_heading(line) {
+            doc.add_text(fields.heading, line.trim_start_matches('#'));
+        }
+        writer.add_document(doc)?;
+    }
+    Ok(())
+}
+
+impl Engine {
+    /// Index `entries`, stemming words in `language`.
+    pub fn build(
+        entries: &BTreeMap<u64, Entry>,
+        language: Option<Language>,
+    ) -> tantivy::Result<Self> {
+        let indexed = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(ANALYZER)
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let mut schema = Schema::builder();
+        let fields = Fields {
+            entry: schema.add_u64_field("entry", STORED | INDEXED),
+            line: schema.add_u64_field("line", STORED),
+            text: schema.add_text_field("text", indexed.clone()),
+            heading: schema.add_text_field("heading", indexed.clone()),
+            title: schema.add_text_field("title", indexed),
+            source: schema.add_text_field("source", STRING),
+            folder: schema.add_text_field("folder", STRING),
+        };
+        let index = Index::create_in_ram(schema.build());
+        let analyzer = analyzer(language);
+        index.tokenizers().register(ANALYZER, analyzer.clone());
+
+        let mut writer: IndexWriter = index.writer_with_num_threads(1, WRITER_MEMORY)?;
+        for (id, entry) in entries {
+            add_entry(&writer, fields, *id, entry)?;
+        }
+        writer.commit()?;
+
+        let reader = index.reader()?;
+        Ok(Self {
+            index,
+            reader,
+            fields,
+            analyzer,
+        })
+    }
+
+    /// Forget the entries whose keys are `removed` and index `added`.
+    pub fn update<'a>(
+        &mut self,
+        removed: &[u64],
+        added: impl IntoIterator<Item = (u64, &'a Entry)>,
+    ) -> tantivy::Result<()> {
+        let mut writer: IndexWriter = self.index.writer_with_num_threads(1, WRITER_MEMORY)?;
+        for id in removed {
+            writer.delete_term(Term::from_field_u64(self.fields.entry, *id));
+        }
+        for (id, entry) in added {
+            add_entry(&writer, self.fields, id, entry)?;
+        }
+        writer.commit()?;
+        self.reader.reload()
+    }
+
+    /// The best matches of a query, most relevant first, with the indexed
+    /// words (stems) of the query.
+    ///
+    /// Parts of the query that cannot be parsed are ignored.
+    pub fn search(
+        &self,
+        text: &str,
+        sources: impl IntoIterator<Item = SearchSource>,
+        folder: Option<&str>,
+        limit: usize,
+    ) -> tantivy::Result<(Vec<Match>, Vec<String>)> {
+        let f = self.fields;
+        let default_fields = vec![f.text, f.heading, f.title];
+        let mut parser = QueryParser::for_index(&self.index, default_fields.clone());
+        parser.set_field_boost(f.title, TITLE_BOOST);
+        parser.set_field_boost(f.heading, HEADING_BOOST);
+        let (exact, _) = parser.parse_query_lenient(text);
+        for field in default_fields {
+            parser.set_field_fuzzy(field, false, 1, true);
+        }
+        let (fuzzy, _) = parser.parse_query_lenient(text);
+
+        let mut words = Vec::new();
+        exact.query_terms(&mut |term, _| {
+            if let Some(word) = term.value().as_str() {
+                words.push(word.to_string());
+            }
+        });
+
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(
+            Occur::Must,
+            Box::new(BooleanQuery::new(vec![
+                (Occur::Should, exact),
+                (
+                    Occur::Should,
+                    Box::new(BoostQuery::new(fuzzy, FUZZY_WEIGHT)),
+                ),
+            ])),
+        )];
+        let filter = |field, value: &str| -> Box<dyn Query> {
+            let term = Term::from_field_text(field, value);
+            Box::new(ConstScoreQuery::new(
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+                0.0,
+            ))
+        };
+        let sources: Vec<(Occur, Box<dyn Query>)> = sources
+            .into_iter()
+            .map(|source| (Occur::Should, filter(f.source, source.id())))
+            .collect();
+        if !sources.is_empty() {
+            clauses.push((Occur::Must, Box::new(BooleanQuery::new(sources))));
+        }
+        if let Some(folder) = folder {
+            clauses.push((Occur::Must, filter(f.folder, folder)));
+        }
+
+        let searcher = self.reader.searcher();
+        let top = searcher.search(&BooleanQuery::new(clauses), &TopDocs::with_limit(limit))?;
+        let mut matches = Vec::with_capacity(top.len());
+        for (score, address) in top {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            let Some(entry) = doc.get_first(f.entry).and_then(|v| v.as_u64()) else {
+                continue;
+            };
+            matches.push(Match {
+                score,
+                entry,
+                line: doc
+                    .get_first(f.line)
+                    .and_then(|v| v.as_u64())
+                    .map(|line| line as usize),
+            });
+        }
+        Ok((matches, words))
+    }
+
+    /// Byte range of the first word of `line` matching one of `words`,
+    /// exactly or, for long enough words, one edit away.
+    pub fn highlight(&self, line: &str, words: &[String]) -> Option<(usize, usize)> {
+        let mut analyzer = self.analyzer.clone();
+        let mut stream = analyzer.token_stream(line);
+        let mut fuzzy = None;
+        while stream.advance() {
+            let token = stream.token();
+            let range = (token.offset_from, token.offset_to);
+            if words.contains(&token.text) {
+                return Some(range);
+            }
+            let close = |word: &String| {
+                word.chars().count() >= MIN_FUZZY_CHARS && within_one_edit(word, &token.text)
+            };
+            if fuzzy.is_none() && words.iter().any(close) {
+                fuzzy = Some(range);
+            }
+        }
+        fuzzy
+    }
+}
+
+/// Whether `a` becomes `b` with at most one insertion, deletion,
+/// substitution or transposition of adjacent characters.
+fn within_one_edit(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    match (a.len(), b.len()) {
+        (0, 0) | (1, 0) | (0, 1) => true,
+        (x, y) if x == y => {
+            a[1..] == b[1..] || (x >= 2 && a[0] == b[1] && a[1] == b[0] && a[2..] == b[2..])
+        }
+        (x, y) if x == y + 1 => a[1..] == *b,
+        (x, y) if x + 1 == y => *a == b[1..],
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edits_and_headings() {
+        assert!(within_one_edit("inn", "inn"));
+        assert!(within_one_edit("keeper", "keper"));
+        assert!(within_one_edit("keeper", "kepeer"));
+        assert!(within_one_edit("stable", "stabel"));
+        assert!(!within_one_edit("stable", "table "));
+        assert!(!within_one_edit("inn", "ink well"));
+
+        assert!(is_heading("## Arrival"));
+        assert!(!is_heading("#hashtag"));
+        assert_eq!(
+            folders("entities/characters/ann.md"),
+            vec![
+                "entities",
+                "entities/characters",
+                "entities/characters/ann.md"
+            ]
+        );
+        assert_eq!(stemming_language("fr_FR"), Some(Language::French));
+        assert_eq!(stemming_language("tlh"), None);
+    }
+}
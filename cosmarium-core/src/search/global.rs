@@ -0,0 +1,115 @@
+//! Searching across projects.
+//!
+//! A [`GlobalIndex`] holds the searchable text of several projects, such as
+//! the recent ones, for an author to find a passage without remembering
+//! which project it was written in. Projects are read without being opened:
+//! nothing in them is migrated or written. Each hit names the folder of its
+//! project in [`SearchHit::project`](super::SearchHit::project).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use cosmarium_core::search::global::GlobalIndex;
+//! use cosmarium_core::search::SearchQuery;
+//! use std::path::PathBuf;
+//!
+//! # async fn example() -> cosmarium_core::Result<()> {
+//! let projects = [PathBuf::from("Novels/The Inn"), PathBuf::from("Novels/Lighthouse")];
+//! let index = GlobalIndex::build(&projects, "en").await;
+//! let results = index.search(&SearchQuery::new("lighthouse keeper").across_projects())?;
+//! for (_, hits) in results.groups() {
+//!     for hit in hits {
+//!         println!("{:?}: {}", hit.project, hit.snippet);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{SearchQuery, SearchResults, WorkspaceIndex};
+use crate::project::Project;
+use crate::Result;
+use std::path::{Path, PathBuf};
+
+/// Searchable text of several projects.
+#[derive(Debug, Clone, Default)]
+pub struct GlobalIndex {
+    projects: Vec<(PathBuf, WorkspaceIndex)>,
+}
+
+impl GlobalIndex {
+    /// Index the projects at `paths`, stemming words in `language`.
+    ///
+    /// Projects that cannot be read are skipped.
+    pub async fn build(paths: &[PathBuf], language: &str) -> Self {
+        let mut projects = Vec::new();
+        for path in paths {
+            match Project::read_outline(path).await {
+                Ok((metadata, structure)) => {
+                    let index =
+                        WorkspaceIndex::build(path, &metadata, &structure).with_language(language);
+                    projects.push((path.clone(), index));
+                }
+                Err(e) => tracing::warn!("Cannot index the project {:?}: {}", path, e),
+            }
+        }
+        Self { projects }
+    }
+
+    /// Folders of the projects indexed.
+    pub fn projects(&self) -> impl Iterator<Item = &Path> {
+        self.projects.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// Search every project, each hit naming its project.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the inverted index of a project cannot be built.
+    pub fn search(&self, query: &SearchQuery) -> Result<SearchResults> {
+        let mut results = SearchResults::default();
+        for (path, index) in &self.projects {
+            let mut found = index.search(query)?;
+            for hits in found.groups.values_mut() {
+                for hit in hits {
+                    hit.project = Some(path.clone());
+                }
+            }
+            results.merge(found);
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchSource;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_search_across_projects() {
+        let temp_dir = tempdir().unwrap();
+        let mut paths = Vec::new();
+        for (name, text) in [
+            ("inn", "The inn stood by the road."),
+            ("lighthouse", "The keeper climbed the lighthouse."),
+        ] {
+            let path = temp_dir.path().join(name);
+            let mut project = Project::new(name, &path, "novel").unwrap();
+            project.save().await.unwrap();
+            std::fs::create_dir_all(path.join("content")).unwrap();
+            std::fs::write(path.join("content/one.md"), text).unwrap();
+            paths.push(path);
+        }
+        paths.push(temp_dir.path().join("missing"));
+
+        let index = GlobalIndex::build(&paths, "en").await;
+        assert_eq!(index.projects().count(), 2);
+
+        let results = index.search(&SearchQuery::new("lighthouse")).unwrap();
+        let hits = results.hits(SearchSource::Document);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].project.as_deref(), Some(paths[1].as_path()));
+    }
+}
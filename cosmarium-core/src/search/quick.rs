@@ -0,0 +1,299 @@
+//! Quick open.
+//!
+//! A [`QuickIndex`] lists the documents of a project by the title of their
+//! structure node, and the headings in each of them, for the author to jump
+//! to a scene by typing a few letters of its name. It is kept light: only
+//! the headings are read, and a refresh reads again only the files modified
+//! since the last one and the open documents.
+//!
+//! Entries are matched fuzzily: the letters typed must appear in order,
+//! and matches on consecutive letters and at the start of words rank first.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_core::search::quick::QuickIndex;
+//! use cosmarium_core::structure::{NodeKind, ProjectStructure, StructureNode};
+//!
+//! let mut structure = ProjectStructure::default();
+//! let node = StructureNode::new(NodeKind::Document, "Arrival").with_path("content/arrival.md");
+//! structure.insert(None, 0, node)?;
+//!
+//! let mut index = QuickIndex::new("Novels/The Inn");
+//! index.refresh(&structure, &[("content/arrival.md", "# The inn\n\n## Night falls")]);
+//! let found = index.search("nfal", 10);
+//! assert_eq!(found[0].heading.as_deref(), Some("Night falls"));
+//! assert_eq!(found[0].line, 3);
+//! # Ok::<(), cosmarium_core::Error>(())
+//! ```
+
+use crate::structure::ProjectStructure;
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A document, or a heading in it, that quick open jumps to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuickEntry {
+    /// Document, relative to the project root, with `/` separators
+    pub path: String,
+    /// Title of the document in the structure
+    pub title: String,
+    /// Text of the heading, `None` for the document itself
+    pub heading: Option<String>,
+    /// Level of the heading, 0 for the document itself
+    pub level: u32,
+    /// Line of the heading, 1-based; 1 for the document itself
+    pub line: usize,
+}
+
+impl QuickEntry {
+    /// Text shown for the entry: its heading, or the document title.
+    pub fn label(&self) -> &str {
+        self.heading.as_deref().unwrap_or(&self.title)
+    }
+}
+
+/// Headings of a file, as last read.
+#[derive(Debug, Clone, Default)]
+struct Outline {
+    /// Modification time of the file read, `None` for open content
+    modified: Option<SystemTime>,
+    /// Level, text and line of each heading
+    headings: Vec<(u32, String, usize)>,
+}
+
+/// Titles and headings of the documents of a project.
+#[derive(Debug, Clone)]
+pub struct QuickIndex {
+    root: PathBuf,
+    /// Path and title of each document, in reading order
+    documents: Vec<(String, String)>,
+    outlines: BTreeMap<String, Outline>,
+}
+
+impl QuickIndex {
+    /// Empty index of the project at `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            documents: Vec::new(),
+            outlines: BTreeMap::new(),
+        }
+    }
+
+    /// Project directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Follow the documents of `structure`, reading the headings of the
+    /// `open` documents from their content, given by path, and those of
+    /// the other files modified since the last refresh from disk.
+    pub fn refresh(&mut self, structure: &ProjectStructure, open: &[(&str, &str)]) {
+        self.documents = structure
+            .reading_order()
+            .into_iter()
+            .filter_map(|node| Some((node.path.clone()?, node.title.clone())))
+            .collect();
+        self.outlines
+            .retain(|path, _| self.documents.iter().any(|(p, _)| p == path));
+
+        for (path, _) in &self.documents {
+            if let Some((_, text)) = open.iter().find(|(open, _)| open == path) {
+                self.outlines.insert(
+                    path.clone(),
+                    Outline {
+                        modified: None,
+                        headings: headings(text),
+                    },
+                );
+                continue;
+            }
+            let file = self.root.join(path);
+            let modified = std::fs::metadata(&file).and_then(|m| m.modified()).ok();
+            let known = self.outlines.get(path).and_then(|outline| outline.modified);
+            if modified.is_some() && known == modified {
+                continue;
+            }
+            let headings = std::fs::read_to_string(&file)
+                .map(|text| headings(&text))
+                .unwrap_or_default();
+            self.outlines
+                .insert(path.clone(), Outline { modified, headings });
+        }
+    }
+
+    /// Every document followed by its headings, in reading order.
+    pub fn entries(&self) -> Vec<QuickEntry> {
+        let mut entries = Vec::new();
+        for (path, title) in &self.documents {
+            entries.push(QuickEntry {
+                path: path.clone(),
+                title: title.clone(),
+                heading: None,
+                level: 0,
+                line: 1,
+            });
+            let headings = self.outlines.get(path).map(|o| o.headings.as_slice());
+            for (level, text, line) in headings.unwrap_or_default() {
+                entries.push(QuickEntry {
+                    path: path.clone(),
+                    title: title.clone(),
+                    heading: Some(text.clone()),
+                    level: *level,
+                    line: *line,
+                });
+            }
+        }
+        entries
+    }
+
+    /// The `limit` best entries for `query`, best first; all entries in
+    /// reading order for an empty query.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<QuickEntry> {
+        let query = query.trim();
+        let entries = self.entries();
+        if query.is_empty() {
+            return entries.into_iter().take(limit).collect();
+        }
+        let mut scored: Vec<(u32, usize, QuickEntry)> = entries
+            .into_iter()
+            .enumerate()
+            .filter_map(|(order, entry)| {
+                // A heading also matches with the title of its document
+                let within = entry
+                    .heading
+                    .as_ref()
+                    .and_then(|heading| fuzzy_score(query, &format!("{} {}", entry.title, heading)))
+                    .map(|score| score / 2);
+                let score = fuzzy_score(query, entry.label()).max(within)?;
+                Some((score, order, entry))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, _, entry)| entry)
+            .collect()
+    }
+}
+
+/// How well `text` matches `pattern`, if all the letters of `pattern`
+/// appear in `text` in order, case ignored.
+///
+/// Each letter matched scores, more so right after the previous one or at
+/// the start of a word; spaces in `pattern` are ignored.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<u32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+    {
+        let at = next + text[next..].iter().position(|c| *c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == at) {
+            score += 4;
+        }
+        if at == 0 || !text[at - 1].is_alphanumeric() {
+            score += 6;
+        }
+        previous = Some(at);
+        next = at + 1;
+    }
+    Some(score)
+}
+
+/// Level, text and 1-based line of the headings of Markdown `text`.
+fn headings(text: &str) -> Vec<(u32, String, usize)> {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let mut headings = Vec::new();
+    let mut current: Option<(u32, usize, String)> = None;
+    for (event, range) in Parser::new_ext(text, Options::empty()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading(level, _, _)) => {
+                current = Some((level as u32, range.start, String::new()));
+            }
+            Event::Text(part) | Event::Code(part) => {
+                if let Some((_, _, heading)) = &mut current {
+                    heading.push_str(&part);
+                }
+            }
+            Event::End(Tag::Heading(..)) => {
+                if let Some((level, start, heading)) = current.take() {
+                    let line = match line_starts.binary_search(&start) {
+                        Ok(i) => i,
+                        Err(i) => i.saturating_sub(1),
+                    };
+                    if !heading.trim().is_empty() {
+                        headings.push((level, heading.trim().to_string(), line + 1));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    headings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::{NodeKind, StructureNode};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_fuzzy_score_prefers_word_starts() {
+        assert!(fuzzy_score("inn", "The Inn").is_some());
+        assert!(fuzzy_score("nni", "The Inn").is_none());
+        let start = fuzzy_score("ti", "The Inn").unwrap();
+        let inside = fuzzy_score("ti", "Whatnot is").unwrap();
+        assert!(start > inside);
+    }
+
+    #[test]
+    fn test_refresh_reads_changed_files() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("content")).unwrap();
+        std::fs::write(root.join("content/a.md"), "# Dawn\n\ntext\n\n## Ride").unwrap();
+        std::fs::write(root.join("content/b.md"), "Dusk without heading").unwrap();
+        let mut structure = ProjectStructure::default();
+        for (path, title) in [
+            ("content/a.md", "Chapter One"),
+            ("content/b.md", "Chapter Two"),
+        ] {
+            let node = StructureNode::new(NodeKind::Document, title).with_path(path);
+            structure.insert(None, usize::MAX, node).unwrap();
+        }
+
+        let mut index = QuickIndex::new(root);
+        index.refresh(&structure, &[]);
+        let labels: Vec<String> = index
+            .entries()
+            .iter()
+            .map(|e| format!("{}:{}", e.label(), e.line))
+            .collect();
+        assert_eq!(
+            labels,
+            ["Chapter One:1", "Dawn:1", "Ride:5", "Chapter Two:1"]
+        );
+
+        // Unsaved edits count
+        index.refresh(&structure, &[("content/b.md", "# Dusk")]);
+        assert_eq!(index.search("dusk", 5)[0].path, "content/b.md");
+        assert_eq!(index.search("ctwo", 5)[0].title, "Chapter Two");
+        // Headings match with their document title too
+        assert_eq!(index.search("one ride", 5)[0].label(), "Ride");
+        assert!(index.search("zebra", 5).is_empty());
+        assert_eq!(index.search("", 2).len(), 2);
+    }
+}
@@ -0,0 +1,279 @@
+//! Replacing text across a project.
+//!
+//! [`preview`] finds the text of a [`ReplaceQuery`] in the documents, notes
+//! and entity sheets of a project, exactly as typed, and
+//! [`replace_selected`] gives the new content of a file from the matches
+//! left selected. The files are then changed together through
+//! [`DocumentManager::apply_transaction`], so that the replacement can be
+//! undone as a whole.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_core::search::replace::{find_matches, replace_selected, FileReplacement, ReplaceQuery};
+//!
+//! let text = "The inn. Innkeepers of the inn.";
+//! let mut query = ReplaceQuery::new("inn", "tavern");
+//! query.whole_word = true;
+//! let mut file = FileReplacement {
+//!     path: "content/arrival.md".to_string(),
+//!     title: "arrival".to_string(),
+//!     matches: find_matches(text, &query),
+//! };
+//! assert_eq!(file.matches.len(), 2);
+//!
+//! file.matches[0].selected = false;
+//! assert_eq!(replace_selected(text, &file, "tavern")?, "The inn. Innkeepers of the tavern.");
+//! # Ok::<(), cosmarium_core::Error>(())
+//! ```
+//!
+//! [`DocumentManager::apply_transaction`]: crate::document::DocumentManager::apply_transaction
+
+use super::{is_text, relative_key, FOLDERS};
+use crate::{Error, Result};
+pub use cosmarium_plugin_api::search::{
+    FileReplacement, ReplaceMatch, ReplacePreview, ReplaceQuery,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Characters of the line kept on each side of a match.
+const CONTEXT_LENGTH: usize = 40;
+
+/// Find the matches of `query` in the project at `root`, in the text of
+/// `open` documents for the files open, with unsaved edits.
+pub fn preview(
+    root: &Path,
+    query: &ReplaceQuery,
+    open: &HashMap<PathBuf, String>,
+) -> ReplacePreview {
+    let within = query
+        .folder
+        .as_deref()
+        .map(|f| f.trim_matches('/'))
+        .filter(|f| !f.is_empty());
+    let mut files = Vec::new();
+    for (folder, source) in FOLDERS {
+        if query.find.is_empty() || !query.sources.is_empty() && !query.sources.contains(source) {
+            continue;
+        }
+        for entry in walkdir::WalkDir::new(root.join(folder))
+            .sort_by_file_name()
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file() && is_text(entry.path()))
+        {
+            let path = entry.path();
+            let Some(key) = relative_key(root, path) else {
+                continue;
+            };
+            if within.is_some_and(|within| !key.starts_with(&format!("{}/", within))) {
+                continue;
+            }
+            let matches = match open.get(path) {
+                Some(text) => find_matches(text, query),
+                None => match std::fs::read_to_string(path) {
+                    Ok(text) => find_matches(&text, query),
+                    Err(e) => {
+                        tracing::warn!("Cannot search {:?} to replace in it: {}", path, e);
+                        continue;
+                    }
+                },
+            };
+            if !matches.is_empty() {
+                let title = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| key.clone());
+                files.push(FileReplacement {
+                    path: key,
+                    title,
+                    matches,
+                });
+            }
+        }
+    }
+    ReplacePreview {
+        query: query.clone(),
+        root: root.to_path_buf(),
+        files,
+    }
+}
+
+/// Matches of the text of `query` in `text`, in order, all selected.
+///
+/// Matches do not span lines.
+pub fn find_matches(text: &str, query: &ReplaceQuery) -> Vec<ReplaceMatch> {
+    let find: Vec<char> = query.find.chars().collect();
+    let mut matches = Vec::new();
+    if find.is_empty() {
+        return matches;
+    }
+    let mut offset = 0;
+    for (n, line) in text.split_inclusive('\n').enumerate() {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let (mut i, mut column) = (0, 1);
+        let mut previous = None;
+        while i < content.len() {
+            let rest = &content[i..];
+            let len = match_at(rest, &find, query.case_sensitive).filter(|&len| {
+                !query.whole_word
+                    || !previous.is_some_and(is_word)
+                        && !rest[len..].chars().next().is_some_and(is_word)
+            });
+            let len = match len {
+                Some(len) => {
+                    matches.push(ReplaceMatch {
+                        line: n + 1,
+                        column,
+                        start: offset + i,
+                        found: rest[..len].to_string(),
+                        prefix: shorten_start(&content[..i]),
+                        suffix: shorten_end(&rest[len..]),
+                        selected: true,
+                    });
+                    len
+                }
+                None => rest.chars().next().map_or(1, char::len_utf8),
+            };
+            previous = rest[..len].chars().last();
+            column += rest[..len].chars().count();
+            i += len;
+        }
+        offset += line.len();
+    }
+    matches
+}
+
+/// Length in bytes of the text `find` opening `text`, if it does.
+fn match_at(text: &str, find: &[char], case_sensitive: bool) -> Option<usize> {
+    let mut chars = text.char_indices();
+    for &expected in find {
+        let (_, c) = chars.next()?;
+        let same = c == expected || !case_sensitive && c.to_lowercase().eq(expected.to_lowercase());
+        if !same {
+            return None;
+        }
+    }
+    Some(chars.next().map_or(text.len(), |(i, _)| i))
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The end of `text`, shortened.
+fn shorten_start(text: &str) -> String {
+    let count = text.chars().count();
+    if count <= CONTEXT_LENGTH {
+        return text.trim_start().to_string();
+    }
+    let kept: String = text.chars().skip(count - CONTEXT_LENGTH).collect();
+    format!("…{}", kept)
+}
+
+/// The start of `text`, shortened.
+fn shorten_end(text: &str) -> String {
+    if text.chars().count() <= CONTEXT_LENGTH {
+        return text.trim_end().to_string();
+    }
+    let kept: String = text.chars().take(CONTEXT_LENGTH).collect();
+    format!("{}…", kept)
+}
+
+/// `text`, the content of the file of `file`, with its selected matches
+/// replaced by `replacement`.
+///
+/// # Errors
+///
+/// Returns an error if the text changed since the matches were found.
+pub fn replace_selected(text: &str, file: &FileReplacement, replacement: &str) -> Result<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut end = 0;
+    for found in file.matches.iter().filter(|m| m.selected) {
+        let stop = found.start + found.found.len();
+        if found.start < end || text.get(found.start..stop) != Some(found.found.as_str()) {
+            return Err(Error::document(format!(
+                "{} changed since the matches were found; preview the replacement again",
+                file.path
+            )));
+        }
+        result.push_str(&text[end..found.start]);
+        result.push_str(replacement);
+        end = stop;
+    }
+    result.push_str(&text[end..]);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchSource;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_matches() {
+        let text = "The Inn was full.\nÉlan at the inn, innkeepers.\n";
+        let query = ReplaceQuery::new("inn", "tavern");
+        let matches = find_matches(text, &query);
+        let found: Vec<(usize, usize, &str)> = matches
+            .iter()
+            .map(|m| (m.line, m.column, m.found.as_str()))
+            .collect();
+        assert_eq!(found, vec![(1, 5, "Inn"), (2, 13, "inn"), (2, 18, "inn")]);
+        assert_eq!(&text[matches[1].start..][..3], "inn");
+        assert_eq!(matches[1].prefix, "Élan at the ");
+        assert_eq!(matches[1].suffix, ", innkeepers.");
+
+        let query = ReplaceQuery {
+            case_sensitive: true,
+            whole_word: true,
+            ..ReplaceQuery::new("inn", "tavern")
+        };
+        let found: Vec<usize> = find_matches(text, &query).iter().map(|m| m.line).collect();
+        assert_eq!(found, vec![2]);
+    }
+
+    #[test]
+    fn test_preview_and_replace() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("content/part1")).unwrap();
+        std::fs::create_dir_all(root.join("notes")).unwrap();
+        std::fs::write(root.join("content/part1/dawn.md"), "Ann left.").unwrap();
+        std::fs::write(root.join("content/dusk.md"), "Unsaved on disk.").unwrap();
+        std::fs::write(root.join("notes/cast.md"), "Ann, the innkeeper.").unwrap();
+
+        let open = HashMap::from([(root.join("content/dusk.md"), "Ann and Ann.".to_string())]);
+        let query = ReplaceQuery::new("ann", "Anna");
+        let mut preview = preview(root, &query, &open);
+        let paths: Vec<&str> = preview.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["content/dusk.md", "content/part1/dawn.md", "notes/cast.md"]
+        );
+        assert_eq!((preview.len(), preview.selected()), (4, 4));
+
+        let within = ReplaceQuery {
+            sources: [SearchSource::Document].into(),
+            folder: Some("content/part1".to_string()),
+            ..query.clone()
+        };
+        let paths: Vec<String> = super::preview(root, &within, &open)
+            .files
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+        assert_eq!(paths, vec!["content/part1/dawn.md"]);
+
+        let dusk = &mut preview.files[0];
+        dusk.matches[0].selected = false;
+        assert_eq!(
+            replace_selected("Ann and Ann.", dusk, "Anna").unwrap(),
+            "Ann and Anna."
+        );
+        assert!(replace_selected("Bob and Ann.", dusk, "Anna").is_ok());
+        assert!(replace_selected("Bob and Bob.", dusk, "Anna").is_err());
+    }
+}
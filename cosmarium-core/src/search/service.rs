@@ -0,0 +1,282 @@
+//! Keeping a workspace index up to date.
+//!
+//! A [`SearchService`] indexes the project once, then follows the document
+//! events of the [`EventBus`]: the documents changed, saved, created or
+//! closed since the last search are indexed again before the next one, from
+//! their open content or else from their file, without scanning the project
+//! again.
+
+use super::{SearchQuery, SearchResults, WorkspaceIndex};
+use crate::document::DocumentManager;
+use crate::events::EventBus;
+use crate::project::ProjectMetadata;
+use crate::structure::ProjectStructure;
+use crate::Result;
+use cosmarium_plugin_api::event::{DocumentChange, DocumentRef};
+use cosmarium_plugin_api::{Event, EventHandler, EventType, Subscription};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Document events after which a document is indexed again.
+const EVENT_TYPES: [EventType; 4] = [
+    EventType::DocumentChanged,
+    EventType::DocumentSaved,
+    EventType::DocumentCreated,
+    EventType::DocumentClosed,
+];
+
+/// What changed since the index was last updated.
+#[derive(Debug, Default)]
+struct Changes {
+    /// Whether the whole project must be indexed
+    rebuild: bool,
+    /// Documents to index again
+    documents: Vec<DocumentRef>,
+}
+
+/// Records the documents the events are about.
+struct ChangeTracker(Arc<Mutex<Changes>>);
+
+impl EventHandler for ChangeTracker {
+    fn handle(&mut self, event: &Event) -> anyhow::Result<()> {
+        let document = match event.event_type() {
+            EventType::DocumentChanged => event
+                .payload_as::<DocumentChange>()
+                .map(|change| change.document),
+            _ => event.payload_as::<DocumentRef>(),
+        };
+        if let (Some(document), Ok(mut changes)) = (document, self.0.lock()) {
+            changes.documents.push(document);
+        }
+        Ok(())
+    }
+}
+
+/// An index of a project, updated as its documents change.
+pub struct SearchService {
+    root: PathBuf,
+    language: String,
+    index: Arc<Mutex<WorkspaceIndex>>,
+    changes: Arc<Mutex<Changes>>,
+    /// Kept for the service to follow the events while it lives
+    _subscriptions: Vec<Subscription>,
+}
+
+impl SearchService {
+    /// Service for the project at `root`, in the manuscript's `language`,
+    /// following the document events of `event_bus`. The project is indexed
+    /// on the first update.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event bus is not initialized.
+    pub async fn new(root: &Path, language: &str, event_bus: &EventBus) -> Result<Self> {
+        let changes = Arc::new(Mutex::new(Changes {
+            rebuild: true,
+            documents: Vec::new(),
+        }));
+        let mut subscriptions = Vec::new();
+        for event_type in EVENT_TYPES {
+            let tracker = Arc::new(tokio::sync::Mutex::new(ChangeTracker(changes.clone())));
+            subscriptions.push(event_bus.subscribe(event_type, tracker).await?);
+        }
+        Ok(Self {
+            root: root.to_path_buf(),
+            language: language.to_string(),
+            index: Arc::new(Mutex::new(WorkspaceIndex::new())),
+            changes,
+            _subscriptions: subscriptions,
+        })
+    }
+
+    /// Project directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Index the whole project again on the next update.
+    pub fn invalidate(&self) {
+        if let Ok(mut changes) = self.changes.lock() {
+            changes.rebuild = true;
+        }
+    }
+
+    /// Take what changed since the last update, with the content of the
+    /// changed documents open in `documents` and the project's `metadata`
+    /// and `structure`.
+    pub fn take_update(
+        &self,
+        documents: &DocumentManager,
+        metadata: &ProjectMetadata,
+        structure: &ProjectStructure,
+    ) -> IndexUpdate {
+        let (rebuild, changed) = match self.changes.lock() {
+            Ok(mut changes) => (
+                std::mem::take(&mut changes.rebuild),
+                std::mem::take(&mut changes.documents),
+            ),
+            Err(_) => (true, Vec::new()),
+        };
+
+        let mut files = BTreeSet::new();
+        for document in changed {
+            let path = document.path.or_else(|| {
+                let doc = documents.get_document(document.id?)?;
+                Some(doc.file_path()?.to_path_buf())
+            });
+            files.extend(path);
+        }
+        // Open documents are indexed with their unsaved edits
+        let open = documents
+            .list_documents()
+            .into_iter()
+            .filter_map(|id| documents.get_document(id))
+            .filter_map(|doc| Some((doc.file_path()?.to_path_buf(), doc.content().to_string())));
+        let open: Vec<(PathBuf, String)> = if rebuild {
+            open.collect()
+        } else {
+            open.filter(|(path, _)| files.contains(path)).collect()
+        };
+        let files = files
+            .into_iter()
+            .filter(|path| !open.iter().any(|(open, _)| open == path))
+            .collect();
+
+        IndexUpdate {
+            root: self.root.clone(),
+            language: self.language.clone(),
+            index: self.index.clone(),
+            rebuild: rebuild.then(|| (metadata.clone(), structure.clone())),
+            open,
+            files,
+            metadata: metadata.clone(),
+            structure: structure.clone(),
+        }
+    }
+}
+
+/// Changes to bring to the index of a [`SearchService`], applied away from
+/// the UI thread.
+pub struct IndexUpdate {
+    root: PathBuf,
+    language: String,
+    index: Arc<Mutex<WorkspaceIndex>>,
+    /// Metadata and structure to index the whole project with
+    rebuild: Option<(ProjectMetadata, ProjectStructure)>,
+    /// Open documents and their content
+    open: Vec<(PathBuf, String)>,
+    /// Other documents, read from their file
+    files: Vec<PathBuf>,
+    metadata: ProjectMetadata,
+    structure: ProjectStructure,
+}
+
+impl IndexUpdate {
+    /// Whether the whole project is indexed again.
+    pub fn is_rebuild(&self) -> bool {
+        self.rebuild.is_some()
+    }
+
+    /// Number of documents indexed again.
+    pub fn len(&self) -> usize {
+        self.open.len() + self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rebuild.is_none() && self.open.is_empty() && self.files.is_empty()
+    }
+
+    /// Update the index, then run `query` on it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index cannot be searched.
+    pub fn search(self, query: &SearchQuery) -> Result<SearchResults> {
+        let mut index = self
+            .index
+            .lock()
+            .map_err(|_| crate::Error::generic("The search index is poisoned"))?;
+        if let Some((metadata, structure)) = &self.rebuild {
+            *index = WorkspaceIndex::build(&self.root, metadata, structure)
+                .with_language(&self.language);
+        } else {
+            index.add_project_metadata(&self.metadata);
+            index.add_structure(&self.structure);
+        }
+        for path in &self.files {
+            let Some(key) = super::relative_key(&self.root, path) else {
+                continue;
+            };
+            match std::fs::read_to_string(path) {
+                Ok(text) => {
+                    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+                    index.add_file_at(&key, &text, modified);
+                }
+                Err(_) => index.remove_file(&key),
+            }
+        }
+        for (path, text) in &self.open {
+            if let Some(key) = super::relative_key(&self.root, path) {
+                index.add_file(&key, text);
+            }
+        }
+        index.search(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchSource;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_documents_are_indexed_again_on_events() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("content")).unwrap();
+        std::fs::write(root.join("content/dawn.md"), "The inn was dark.").unwrap();
+        std::fs::write(root.join("content/dusk.md"), "Rain.").unwrap();
+
+        let mut event_bus = EventBus::new();
+        event_bus.initialize().await.unwrap();
+        let service = SearchService::new(root, "en", &event_bus).await.unwrap();
+        let documents = DocumentManager::new();
+        let metadata = ProjectMetadata::new("Novel", "novel");
+        let structure = ProjectStructure::default();
+
+        let update = service.take_update(&documents, &metadata, &structure);
+        assert!(update.is_rebuild());
+        let results = update.search(&SearchQuery::new("inn")).unwrap();
+        assert_eq!(results.hits(SearchSource::Document)[0].title, "dawn");
+
+        // Only the saved document is read again
+        std::fs::write(root.join("content/dusk.md"), "Rain on the inn.\nThen fog.").unwrap();
+        std::fs::write(root.join("content/dawn.md"), "Unchanged on disk.").unwrap();
+        let saved = Event::document_saved(DocumentRef::from_path(root.join("content/dusk.md")));
+        event_bus.emit(saved).await.unwrap();
+        event_bus.process_events().await.unwrap();
+
+        let update = service.take_update(&documents, &metadata, &structure);
+        assert!(!update.is_rebuild());
+        assert_eq!(update.len(), 1);
+        let results = update.search(&SearchQuery::new("inn")).unwrap();
+        let titles: Vec<&str> = results
+            .hits(SearchSource::Document)
+            .iter()
+            .map(|hit| hit.title.as_str())
+            .collect();
+        assert_eq!(titles.len(), 2);
+        let dusk = results
+            .hits(SearchSource::Document)
+            .iter()
+            .find(|hit| hit.title == "dusk")
+            .unwrap();
+        assert_eq!(dusk.after.as_deref(), Some("Then fog."));
+
+        assert!(service
+            .take_update(&documents, &metadata, &structure)
+            .is_empty());
+    }
+}
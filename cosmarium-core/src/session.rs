@@ -1,11 +1,14 @@
 //! # Session management for Cosmarium
 //!
 //! This module handles the persistence of user session data, such as the list of
-//! recent projects and the last opened project. This data is stored separately
-//! from the application configuration to keep user state distinct from settings.
+//! recent projects, the last opened project, the state plugins keep between
+//! sessions and the words written towards the writing goals. This data is stored separately from the application configuration
+//! to keep user state distinct from settings.
 
+use crate::goals::WritingLog;
 use crate::{Error, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 /// User session data.
@@ -15,6 +18,12 @@ pub struct Session {
     pub recent_projects: Vec<PathBuf>,
     /// The last project that was opened.
     pub last_opened_project: Option<PathBuf>,
+    /// State plugins keep between sessions, by plugin name.
+    #[serde(default)]
+    pub plugin_state: HashMap<String, serde_json::Value>,
+    /// Words written by day and by project.
+    #[serde(default)]
+    pub writing_log: WritingLog,
 }
 
 impl Default for Session {
@@ -22,6 +31,8 @@ impl Default for Session {
         Self {
             recent_projects: Vec::new(),
             last_opened_project: None,
+            plugin_state: HashMap::new(),
+            writing_log: WritingLog::default(),
         }
     }
 }
@@ -0,0 +1,230 @@
+//! Deterministic simulation of the event and shared state layer.
+//!
+//! A [`Simulation`] runs plugins the way the application does, through a
+//! [`PluginContext`] connected to an [`EventBus`] and to an executor, but on
+//! a single thread and with nothing left to chance:
+//!
+//! - time stands still until [`Simulation::advance`] moves it, so delays,
+//!   debounces and autosave intervals elapse exactly when the test says;
+//! - identifiers, of events among others, are drawn from a seed;
+//! - events are delivered before the call that emitted them returns, along
+//!   with the events their handlers emit in turn;
+//! - spawned tasks run to completion before their handle is returned.
+//!
+//! Two simulations with the same seed and the same calls therefore end in
+//! the same state.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_core::simulation::Simulation;
+//! use cosmarium_plugin_api::{Event, EventType};
+//! use std::sync::{Arc, Mutex};
+//! use std::time::Duration;
+//!
+//! let mut sim = Simulation::new(7);
+//! let saved = Arc::new(Mutex::new(Vec::new()));
+//! let log = saved.clone();
+//! let _subscription = sim.context_mut().subscribe(EventType::DocumentSaved, move |event: &Event| {
+//!     log.lock().unwrap().push(event.timestamp());
+//!     Ok(())
+//! });
+//!
+//! sim.advance(Duration::from_secs(30)).unwrap();
+//! sim.run(|ctx| ctx.emit_event(Event::new(EventType::DocumentSaved, "chapter.md")))
+//!     .unwrap();
+//! assert_eq!(saved.lock().unwrap()[0].to_rfc3339(), "2024-01-01T00:00:30+00:00");
+//! ```
+
+use crate::{EventBus, Result};
+use cosmarium_plugin_api::clock::{Clock, IdSource};
+use cosmarium_plugin_api::{Event, PluginContext, TaskSpawner};
+use std::future::Future;
+use std::time::Duration;
+use tokio::runtime::{Builder, Runtime};
+
+/// Plugins' environment, on a single thread, with a manual clock and seeded
+/// identifiers.
+pub struct Simulation {
+    /// Single-threaded runtime running the bus and the spawned futures
+    runtime: Runtime,
+    event_bus: EventBus,
+    context: PluginContext,
+}
+
+impl Simulation {
+    /// Start a simulation at [`Clock::EPOCH`], drawing identifiers from
+    /// `seed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runtime cannot be started.
+    pub fn new(seed: u64) -> Self {
+        let runtime = Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("Failed to start the simulation runtime");
+
+        let mut event_bus = EventBus::new();
+        event_bus.set_async_processing(false);
+        runtime
+            .block_on(event_bus.initialize())
+            .expect("A new event bus always initializes");
+
+        let mut context = PluginContext::new();
+        context.set_clock(Clock::manual(Clock::EPOCH));
+        context.set_id_source(IdSource::seeded(seed));
+        context.connect_event_bus(event_bus.link());
+        context.connect_task_spawner(TaskSpawner::new(runtime.handle().clone()).inline());
+
+        Self {
+            runtime,
+            event_bus,
+            context,
+        }
+    }
+
+    /// Context to hand to the simulated plugins.
+    pub fn context(&self) -> &PluginContext {
+        &self.context
+    }
+
+    /// Mutable context to hand to the simulated plugins.
+    pub fn context_mut(&mut self) -> &mut PluginContext {
+        &mut self.context
+    }
+
+    /// The simulated clock.
+    pub fn clock(&self) -> &Clock {
+        self.context.clock()
+    }
+
+    /// The event bus the context is connected to.
+    pub fn event_bus(&self) -> &EventBus {
+        &self.event_bus
+    }
+
+    /// Run `f` on the context, typically a plugin's `update`, then deliver
+    /// the events it emitted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the events never stop coming; see
+    /// [`EventBus::process_until_idle`].
+    pub fn run<R>(&mut self, f: impl FnOnce(&mut PluginContext) -> R) -> Result<R> {
+        let output = f(&mut self.context);
+        self.settle()?;
+        Ok(output)
+    }
+
+    /// Emit `event` from the context and deliver it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the events never stop coming.
+    pub fn emit(&mut self, event: Event) -> Result<()> {
+        self.run(|ctx| ctx.emit_event(event))
+    }
+
+    /// Move the clock forward by `duration`, then deliver pending events.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the events never stop coming.
+    pub fn advance(&mut self, duration: Duration) -> Result<()> {
+        self.context.clock().advance(duration);
+        self.settle()
+    }
+
+    /// Deliver the pending events, and the ones their handlers emit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the events never stop coming.
+    pub fn settle(&self) -> Result<()> {
+        self.runtime.block_on(self.event_bus.process_until_idle())
+    }
+
+    /// Run a future to completion, such as a call to the event bus.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmarium_plugin_api::EventType;
+    use std::sync::{Arc, Mutex};
+
+    /// Identifiers, dates and types of the events a simulation delivers.
+    fn record(seed: u64) -> Vec<(uuid::Uuid, String, EventType)> {
+        let mut sim = Simulation::new(seed);
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+
+        let log = delivered.clone();
+        let _opened =
+            sim.context_mut()
+                .subscribe(EventType::ProjectOpened, move |event: &Event| {
+                    log.lock().unwrap().push((
+                        event.id(),
+                        event.timestamp().to_rfc3339(),
+                        event.event_type(),
+                    ));
+                    Ok(())
+                });
+        // A handler answering with an event of its own, through its link
+        let mut link = PluginContext::new();
+        link.set_clock(sim.clock().clone());
+        link.set_id_source(IdSource::seeded(seed + 1));
+        link.connect_event_bus(sim.event_bus().link());
+        let _answer = sim
+            .context_mut()
+            .subscribe(EventType::ProjectOpened, move |_: &Event| {
+                link.emit_event(Event::new(EventType::DocumentOpened, "chapter.md"));
+                Ok(())
+            });
+        let log = delivered.clone();
+        let _documents =
+            sim.context_mut()
+                .subscribe(EventType::DocumentOpened, move |event: &Event| {
+                    log.lock().unwrap().push((
+                        event.id(),
+                        event.timestamp().to_rfc3339(),
+                        event.event_type(),
+                    ));
+                    Ok(())
+                });
+        sim.settle().unwrap();
+
+        for _ in 0..2 {
+            sim.advance(Duration::from_secs(60)).unwrap();
+            sim.emit(Event::new(EventType::ProjectOpened, "The Inn"))
+                .unwrap();
+        }
+        let delivered = delivered.lock().unwrap().clone();
+        delivered
+    }
+
+    #[test]
+    fn test_same_seed_same_run() {
+        let run = record(3);
+        assert_eq!(run.len(), 4, "answers are delivered within the call");
+        assert_eq!(run[0].1, "2024-01-01T00:01:00+00:00");
+        assert_eq!(run[1].2, EventType::DocumentOpened);
+        assert_eq!(run[2].1, "2024-01-01T00:02:00+00:00");
+
+        assert_eq!(record(3), run);
+        assert_ne!(record(4)[0].0, run[0].0);
+    }
+
+    #[test]
+    fn test_tasks_are_done_when_spawned() {
+        let sim = Simulation::new(0);
+        let mut task = sim.context().spawn(async {
+            tokio::task::yield_now().await;
+            21 * 2
+        });
+        assert_eq!(task.try_take().unwrap().unwrap(), 42);
+    }
+}
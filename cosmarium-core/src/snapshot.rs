@@ -0,0 +1,167 @@
+//! # Project snapshots
+//!
+//! Takes the read-only [`ProjectSnapshot`]s handed to export and analysis
+//! plugins: the metadata and structure of a project, the text of its
+//! documents, notes and entries, and its other files as assets.
+//!
+//! The text of the documents open in the application, unsaved edits
+//! included, is taken from the [`DocumentManager`] with its revision; the
+//! other files are read from disk. The `meta` folder and hidden files are
+//! left out.
+
+use crate::document::DocumentManager;
+use crate::project::{Project, ProjectMetadata};
+use crate::structure::{NodeKind, StructureNode};
+use cosmarium_plugin_api::snapshot::{
+    ProjectSnapshot, SnapshotDocument, SnapshotMetadata, SnapshotNode,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// File extensions of the text files of a snapshot.
+const TEXT_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// Folder of the project state, left out of snapshots.
+const META_DIR: &str = "meta";
+
+/// Take a snapshot of `project`, with the open documents of `documents`.
+pub fn take_snapshot(project: &Project, documents: &DocumentManager) -> ProjectSnapshot {
+    let root = project.path();
+    let open: HashMap<PathBuf, SnapshotDocument> = documents
+        .list_documents()
+        .into_iter()
+        .filter_map(|id| documents.get_document(id))
+        .filter_map(|doc| {
+            let document = SnapshotDocument::new(doc.content(), doc.revision());
+            Some((doc.file_path()?.to_path_buf(), document))
+        })
+        .collect();
+
+    let nodes = project
+        .structure()
+        .roots()
+        .iter()
+        .map(snapshot_node)
+        .collect();
+    let mut snapshot =
+        ProjectSnapshot::new(root, nodes).with_metadata(metadata(project.metadata()));
+    let mut assets = Vec::new();
+    for entry in walkdir::WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(name.starts_with('.') || entry.depth() == 1 && name == META_DIR)
+        })
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+    {
+        let path = entry.path();
+        let Ok(rel) = path.strip_prefix(root) else {
+            continue;
+        };
+        let is_text = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| TEXT_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+        if !is_text {
+            assets.push(rel.to_path_buf());
+            continue;
+        }
+        let document = match open.get(path) {
+            Some(document) => document.clone(),
+            None => match std::fs::read_to_string(path) {
+                Ok(content) => SnapshotDocument::new(content, 0),
+                Err(e) => {
+                    tracing::warn!("Cannot read {:?} for the project snapshot: {}", path, e);
+                    continue;
+                }
+            },
+        };
+        snapshot = snapshot.with_document(rel, document);
+    }
+    snapshot.with_assets(assets)
+}
+
+/// Snapshot of a structure node and its children.
+fn snapshot_node(node: &StructureNode) -> SnapshotNode {
+    let kind = match node.kind {
+        NodeKind::Folder => "folder",
+        NodeKind::Part => "part",
+        NodeKind::Chapter => "chapter",
+        NodeKind::Document => "document",
+    };
+    SnapshotNode {
+        id: node.id,
+        kind: kind.to_string(),
+        title: node.title.clone(),
+        path: node
+            .path
+            .as_deref()
+            .map(|path| Path::new(path).to_path_buf()),
+        metadata: node.metadata.clone(),
+        children: node.children.iter().map(snapshot_node).collect(),
+    }
+}
+
+fn metadata(metadata: &ProjectMetadata) -> SnapshotMetadata {
+    SnapshotMetadata {
+        title: metadata.name.clone(),
+        author: metadata.author.clone(),
+        description: metadata.description.clone(),
+        tags: metadata.tags.clone(),
+        properties: metadata
+            .properties
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_snapshot_takes_open_documents() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("novel");
+        for dir in ["content/one", "notes", "maps", "meta"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        std::fs::write(root.join("content/one/dawn.md"), "Fog.").unwrap();
+        std::fs::write(root.join("content/one/dusk.md"), "Rain.").unwrap();
+        std::fs::write(root.join("notes/todo.md"), "Fix dusk.").unwrap();
+        std::fs::write(root.join("maps/harbor.png"), [0u8; 4]).unwrap();
+        std::fs::write(root.join("meta/core.toon"), "").unwrap();
+
+        let mut project = Project::new("Novel", &root, "novel").unwrap();
+        project.sync_structure();
+        let mut documents = DocumentManager::new();
+        let id = documents
+            .open_document(root.join("content/one/dusk.md"))
+            .await
+            .unwrap();
+        documents
+            .get_document_mut(id)
+            .unwrap()
+            .set_content("Rain, then snow.");
+
+        let snapshot = take_snapshot(&project, &documents);
+        assert_eq!(snapshot.metadata().title, "Novel");
+        assert_eq!(snapshot.nodes()[0].kind, "folder");
+        let texts: Vec<(&str, u64)> = snapshot
+            .documents()
+            .map(|(_, document)| (document.content.as_str(), document.revision))
+            .collect();
+        assert_eq!(texts, vec![("Fog.", 0), ("Rain, then snow.", 1)]);
+        assert!(snapshot.document(Path::new("notes/todo.md")).is_some());
+        assert_eq!(snapshot.assets(), [PathBuf::from("maps/harbor.png")]);
+
+        // Later edits do not reach the snapshot
+        documents.get_document_mut(id).unwrap().set_content("Sun.");
+        let dusk = snapshot.document(&root.join("content/one/dusk.md"));
+        assert_eq!(dusk.unwrap().content, "Rain, then snow.");
+    }
+}
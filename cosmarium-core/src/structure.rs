@@ -0,0 +1,541 @@
+//! # Project structure
+//!
+//! Hierarchical organization of a project: folders, parts, chapters and
+//! documents, each with a title, a place among its siblings and free-form
+//! metadata. The structure is saved with the project state in
+//! `meta/core.toon` and gives the binder and compile order of the project,
+//! whatever the names of the files in the content directory.
+//!
+//! Folders and files of the content directory get a node of their own when
+//! the structure is synchronized with the disk; parts and chapters may also
+//! exist without any folder behind them.
+
+use crate::{Error, Result};
+use cosmarium_plugin_api::metadata::SceneMetadata;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use uuid::Uuid;
+
+/// File extensions of the documents of the content directory.
+const DOCUMENT_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// What a structure node stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    /// A plain folder
+    Folder,
+    /// A part of the manuscript
+    Part,
+    /// A chapter of the manuscript
+    Chapter,
+    /// A document
+    Document,
+}
+
+/// A node of the project structure.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructureNode {
+    /// Unique node identifier
+    pub id: Uuid,
+    /// What the node stands for
+    pub kind: NodeKind,
+    /// Title shown in the binder
+    pub title: String,
+    /// Folder or file of the node, relative to the project root with `/`
+    /// separators
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Document of the node, if it is registered in the project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<Uuid>,
+    /// Free-form metadata (status, label, synopsis...)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    /// Child nodes, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<StructureNode>,
+}
+
+impl StructureNode {
+    /// Create a node with a new identifier.
+    pub fn new(kind: NodeKind, title: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            title: title.into(),
+            path: None,
+            document: None,
+            metadata: BTreeMap::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Set the folder or file of the node.
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Whether other nodes can be nested in this one.
+    pub fn is_container(&self) -> bool {
+        self.kind != NodeKind::Document
+    }
+
+    /// Synopsis, POV, status, label and target of the node, read from its
+    /// metadata.
+    pub fn scene_metadata(&self) -> SceneMetadata {
+        SceneMetadata::from_fields(&self.metadata)
+    }
+
+    /// Store the synopsis, POV, status, label and target of the node in its
+    /// metadata, keeping its other fields.
+    pub fn set_scene_metadata(&mut self, metadata: &SceneMetadata) {
+        metadata.write_fields(&mut self.metadata);
+    }
+}
+
+/// The tree of a project's nodes.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_core::structure::{NodeKind, ProjectStructure, StructureNode};
+///
+/// let mut structure = ProjectStructure::default();
+/// let part = structure.insert(None, 0, StructureNode::new(NodeKind::Part, "Part One"))?;
+/// let scene = structure.insert(None, 1, StructureNode::new(NodeKind::Document, "Arrival"))?;
+///
+/// structure.nest(scene, part)?;
+/// assert_eq!(structure.find(part).unwrap().children[0].id, scene);
+/// # Ok::<(), cosmarium_core::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ProjectStructure {
+    /// Top-level nodes, in order
+    nodes: Vec<StructureNode>,
+}
+
+impl ProjectStructure {
+    /// Top-level nodes, in order.
+    pub fn roots(&self) -> &[StructureNode] {
+        &self.nodes
+    }
+
+    /// Whether the structure has no node.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Every node, parents before their children, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &StructureNode> {
+        let mut nodes = Vec::new();
+        for node in &self.nodes {
+            collect(node, &mut nodes);
+        }
+        nodes.into_iter()
+    }
+
+    /// Find a node by identifier.
+    pub fn find(&self, id: Uuid) -> Option<&StructureNode> {
+        self.iter().find(|node| node.id == id)
+    }
+
+    /// Find a node by identifier, for modification.
+    pub fn find_mut(&mut self, id: Uuid) -> Option<&mut StructureNode> {
+        fn walk(nodes: &mut [StructureNode], id: Uuid) -> Option<&mut StructureNode> {
+            for node in nodes {
+                if node.id == id {
+                    return Some(node);
+                }
+                if let Some(found) = walk(&mut node.children, id) {
+                    return Some(found);
+                }
+            }
+            None
+        }
+
+        walk(&mut self.nodes, id)
+    }
+
+    /// Find the node of a folder or file.
+    pub fn find_by_path(&self, path: &str) -> Option<&StructureNode> {
+        self.iter().find(|node| node.path.as_deref() == Some(path))
+    }
+
+    /// Parent of a node (`None` for a top-level node) and its index among
+    /// its siblings.
+    pub fn position(&self, id: Uuid) -> Option<(Option<Uuid>, usize)> {
+        fn walk(
+            nodes: &[StructureNode],
+            parent: Option<Uuid>,
+            id: Uuid,
+        ) -> Option<(Option<Uuid>, usize)> {
+            if let Some(index) = nodes.iter().position(|node| node.id == id) {
+                return Some((parent, index));
+            }
+            nodes
+                .iter()
+                .find_map(|node| walk(&node.children, Some(node.id), id))
+        }
+
+        walk(&self.nodes, None, id)
+    }
+
+    /// Document nodes in reading order.
+    pub fn reading_order(&self) -> Vec<&StructureNode> {
+        self.iter()
+            .filter(|node| node.kind == NodeKind::Document)
+            .collect()
+    }
+
+    /// Paths of the nodes backed by a folder or file, in order.
+    pub fn paths(&self) -> Vec<String> {
+        self.iter().filter_map(|node| node.path.clone()).collect()
+    }
+
+    fn children_mut(&mut self, parent: Option<Uuid>) -> Result<&mut Vec<StructureNode>> {
+        match parent {
+            None => Ok(&mut self.nodes),
+            Some(id) => {
+                let node = self
+                    .find_mut(id)
+                    .ok_or_else(|| Error::not_found(format!("structure node {}", id)))?;
+                if !node.is_container() {
+                    return Err(Error::validation(
+                        "parent",
+                        "nodes cannot be nested in a document",
+                    ));
+                }
+                Ok(&mut node.children)
+            }
+        }
+    }
+
+    /// Insert a node under `parent` (`None` for the top level) at `index`,
+    /// or last if `index` is past the end.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parent does not exist or is a document.
+    pub fn insert(
+        &mut self,
+        parent: Option<Uuid>,
+        index: usize,
+        node: StructureNode,
+    ) -> Result<Uuid> {
+        let id = node.id;
+        let siblings = self.children_mut(parent)?;
+        siblings.insert(index.min(siblings.len()), node);
+        Ok(id)
+    }
+
+    /// Remove a node with its children.
+    pub fn remove(&mut self, id: Uuid) -> Option<StructureNode> {
+        let (parent, index) = self.position(id)?;
+        self.children_mut(parent)
+            .ok()
+            .map(|siblings| siblings.remove(index))
+    }
+
+    /// Move a node with its children under `parent` (`None` for the top
+    /// level) at `index` among its new siblings, or last if `index` is past
+    /// the end.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a node does not exist, if the parent is a
+    /// document, or if the parent is the node itself or one of its
+    /// descendants.
+    pub fn move_node(&mut self, id: Uuid, parent: Option<Uuid>, index: usize) -> Result<()> {
+        let node = self
+            .find(id)
+            .ok_or_else(|| Error::not_found(format!("structure node {}", id)))?;
+        if let Some(parent) = parent {
+            let mut subtree = Vec::new();
+            collect(node, &mut subtree);
+            if subtree.iter().any(|node| node.id == parent) {
+                return Err(Error::validation(
+                    "parent",
+                    "a node cannot be nested in itself",
+                ));
+            }
+        }
+        // Check the parent before detaching the node
+        self.children_mut(parent)?;
+
+        let node = self.remove(id).expect("node was found above");
+        self.insert(parent, index, node).map(|_| ())
+    }
+
+    /// Move a node to `index` among its siblings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node does not exist.
+    pub fn reorder(&mut self, id: Uuid, index: usize) -> Result<()> {
+        let (parent, _) = self
+            .position(id)
+            .ok_or_else(|| Error::not_found(format!("structure node {}", id)))?;
+        self.move_node(id, parent, index)
+    }
+
+    /// Move a node to the end of `parent`'s children.
+    ///
+    /// # Errors
+    ///
+    /// See [`ProjectStructure::move_node`].
+    pub fn nest(&mut self, id: Uuid, parent: Uuid) -> Result<()> {
+        self.move_node(id, Some(parent), usize::MAX)
+    }
+
+    /// Give every node a new identifier, and the nodes of documents listed
+    /// in `documents` the new identifier of their document.
+    pub fn renew_ids(&mut self, documents: &HashMap<Uuid, Uuid>) {
+        fn renew(nodes: &mut [StructureNode], documents: &HashMap<Uuid, Uuid>) {
+            for node in nodes {
+                node.id = Uuid::new_v4();
+                if let Some(document) = node.document.and_then(|id| documents.get(&id)) {
+                    node.document = Some(*document);
+                }
+                renew(&mut node.children, documents);
+            }
+        }
+
+        renew(&mut self.nodes, documents);
+    }
+
+    /// Sort the siblings of every level by `order`, a list of node paths.
+    /// Nodes missing from the list keep their place after the listed ones.
+    ///
+    /// Returns whether the structure changed.
+    pub fn sort_by_paths(&mut self, order: &[String]) -> bool {
+        fn sort(nodes: &mut [StructureNode], positions: &HashMap<&str, usize>) {
+            nodes.sort_by_key(|node| {
+                node.path
+                    .as_deref()
+                    .and_then(|path| positions.get(path).copied())
+                    .unwrap_or(usize::MAX)
+            });
+            for node in nodes {
+                sort(&mut node.children, positions);
+            }
+        }
+
+        let positions: HashMap<&str, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(i, path)| (path.as_str(), i))
+            .collect();
+        let before = self.clone();
+        sort(&mut self.nodes, &positions);
+        *self != before
+    }
+
+    /// Bring the structure in line with the content directory of `project`:
+    /// nodes of deleted folders and files are removed, their other children
+    /// taking their place, and new folders and files are added after their
+    /// siblings, under the node of their folder.
+    ///
+    /// Returns whether the structure changed.
+    pub fn sync_with_content(&mut self, project: &Path) -> bool {
+        let mut entries = Vec::new();
+        scan_content(project, &project.join("content"), &mut entries);
+        let on_disk: HashSet<&str> = entries.iter().map(|(key, _)| key.as_str()).collect();
+
+        let mut changed = prune(&mut self.nodes, &on_disk);
+        for (key, is_dir) in &entries {
+            if self.find_by_path(key).is_some() {
+                continue;
+            }
+            let (folder, name) = key.rsplit_once('/').unwrap_or(("", key));
+            let parent = self
+                .find_by_path(folder)
+                .filter(|node| node.is_container())
+                .map(|node| node.id);
+            let node = if *is_dir {
+                StructureNode::new(NodeKind::Folder, name)
+            } else {
+                let title = Path::new(name)
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or(name);
+                StructureNode::new(NodeKind::Document, title)
+            };
+            changed |= self
+                .insert(parent, usize::MAX, node.with_path(key.clone()))
+                .is_ok();
+        }
+        changed
+    }
+}
+
+/// `node` and its descendants, parents before their children.
+fn collect<'a>(node: &'a StructureNode, out: &mut Vec<&'a StructureNode>) {
+    out.push(node);
+    for child in &node.children {
+        collect(child, out);
+    }
+}
+
+/// Remove the nodes whose folder or file is not on disk, lifting their
+/// remaining children in their place.
+fn prune(nodes: &mut Vec<StructureNode>, on_disk: &HashSet<&str>) -> bool {
+    let mut changed = false;
+    let mut i = 0;
+    while i < nodes.len() {
+        changed |= prune(&mut nodes[i].children, on_disk);
+        let gone = nodes[i]
+            .path
+            .as_deref()
+            .is_some_and(|path| !on_disk.contains(path));
+        if gone {
+            let node = nodes.remove(i);
+            let lifted = node.children.len();
+            nodes.splice(i..i, node.children);
+            i += lifted;
+            changed = true;
+        } else {
+            i += 1;
+        }
+    }
+    changed
+}
+
+/// Folders and documents under `dir`, as `(key, is_dir)` pairs, parents
+/// before their children and alphabetically among siblings.
+fn scan_content(project: &Path, dir: &Path, out: &mut Vec<(String, bool)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        let Ok(rel) = path.strip_prefix(project) else {
+            continue;
+        };
+        let key = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if path.is_dir() {
+            out.push((key, true));
+            scan_content(project, &path, out);
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| DOCUMENT_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        {
+            out.push((key, false));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles(nodes: &[StructureNode]) -> Vec<&str> {
+        nodes.iter().map(|n| n.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_move_reorder_and_nest() {
+        let mut structure = ProjectStructure::default();
+        let part = structure
+            .insert(None, 0, StructureNode::new(NodeKind::Part, "Part"))
+            .unwrap();
+        let chapter = structure
+            .insert(
+                Some(part),
+                0,
+                StructureNode::new(NodeKind::Chapter, "Chapter"),
+            )
+            .unwrap();
+        let a = structure
+            .insert(None, 9, StructureNode::new(NodeKind::Document, "a"))
+            .unwrap();
+        let b = structure
+            .insert(None, 9, StructureNode::new(NodeKind::Document, "b"))
+            .unwrap();
+
+        structure.nest(a, chapter).unwrap();
+        structure.nest(b, chapter).unwrap();
+        structure.reorder(b, 0).unwrap();
+        assert_eq!(
+            titles(&structure.find(chapter).unwrap().children),
+            vec!["b", "a"]
+        );
+        assert_eq!(structure.position(a), Some((Some(chapter), 1)));
+
+        structure.move_node(chapter, None, 0).unwrap();
+        assert_eq!(titles(structure.roots()), vec!["Chapter", "Part"]);
+        let reading: Vec<&str> = structure
+            .reading_order()
+            .iter()
+            .map(|n| n.title.as_str())
+            .collect();
+        assert_eq!(reading, vec!["b", "a"]);
+
+        // Invalid moves leave the structure untouched
+        assert!(structure.nest(chapter, a).is_err());
+        assert!(structure.nest(chapter, b).is_err());
+        assert!(structure.move_node(part, Some(part), 0).is_err());
+        assert!(structure.nest(Uuid::new_v4(), part).is_err());
+        assert_eq!(titles(structure.roots()), vec!["Chapter", "Part"]);
+    }
+
+    #[test]
+    fn test_sync_with_content() {
+        let root = std::env::temp_dir().join(format!(
+            "cosmarium_structure_test_{}",
+            Uuid::new_v4().simple()
+        ));
+        std::fs::create_dir_all(root.join("content/part1")).unwrap();
+        for file in ["part1/a.md", "part1/b.md", "notes.txt", "cover.png"] {
+            std::fs::write(root.join("content").join(file), "Text").unwrap();
+        }
+
+        let mut structure = ProjectStructure::default();
+        assert!(structure.sync_with_content(&root));
+        assert!(!structure.sync_with_content(&root));
+        assert_eq!(
+            structure.paths(),
+            vec![
+                "content/notes.txt",
+                "content/part1",
+                "content/part1/a.md",
+                "content/part1/b.md",
+            ]
+        );
+
+        // A part without folder keeps the documents nested in it
+        let part = structure
+            .insert(None, 0, StructureNode::new(NodeKind::Part, "Part"))
+            .unwrap();
+        let a = structure.find_by_path("content/part1/a.md").unwrap().id;
+        structure.nest(a, part).unwrap();
+        std::fs::remove_file(root.join("content/part1/b.md")).unwrap();
+        std::fs::write(root.join("content/part1/c.md"), "Text").unwrap();
+        assert!(structure.sync_with_content(&root));
+        assert_eq!(
+            structure.paths(),
+            vec![
+                "content/part1/a.md",
+                "content/notes.txt",
+                "content/part1",
+                "content/part1/c.md",
+            ]
+        );
+        assert_eq!(structure.find(part).unwrap().children[0].title, "a");
+
+        assert!(structure.sort_by_paths(&["content/part1".to_string()]));
+        assert_eq!(titles(structure.roots()), vec!["part1", "Part", "notes"]);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
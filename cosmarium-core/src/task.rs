@@ -0,0 +1,175 @@
+//! Background task management.
+//!
+//! The [`TaskManager`] runs long operations (exports, downloads, pushes)
+//! on the shared [`Executor`] and keeps track of them so the UI can show
+//! their progress and let the user cancel them. Plugins start tracked tasks
+//! through [`PluginContext::spawn_task`], whose spawner reports to the same
+//! manager.
+//!
+//! [`PluginContext::spawn_task`]: cosmarium_plugin_api::PluginContext::spawn_task
+
+use crate::executor::Executor;
+use cosmarium_plugin_api::{TaskHandle, TaskProgress, TaskSpawner, TaskStatus};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Tracker of the application's background tasks.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_core::{Executor, TaskManager};
+/// use std::sync::Arc;
+///
+/// let manager = TaskManager::new(Arc::new(Executor::new()));
+/// let task = manager.spawn_task("Export", |progress| {
+///     progress.set_message("Writing file");
+///     Ok(())
+/// });
+/// task.wait().unwrap();
+/// assert_eq!(manager.tasks().len(), 1);
+/// ```
+pub struct TaskManager {
+    /// Executor running the tasks
+    executor: Arc<Executor>,
+    /// Sender handed to spawners so they report new tasks
+    sender: Sender<TaskProgress>,
+    /// New tasks not yet listed
+    receiver: Mutex<Receiver<TaskProgress>>,
+    /// Known tasks, oldest first
+    tasks: Mutex<Vec<TaskProgress>>,
+}
+
+impl TaskManager {
+    /// Create a task manager running tasks on `executor`.
+    pub fn new(executor: Arc<Executor>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            executor,
+            sender,
+            receiver: Mutex::new(receiver),
+            tasks: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Spawner whose tracked tasks are listed by this manager.
+    pub fn spawner(&self) -> TaskSpawner {
+        self.executor.spawner().with_tracker(self.sender.clone())
+    }
+
+    /// Run a long blocking operation as a tracked task.
+    ///
+    /// See [`TaskSpawner::spawn_task`].
+    pub fn spawn_task<F, T>(&self, name: impl Into<String>, work: F) -> TaskHandle<T>
+    where
+        F: FnOnce(&TaskProgress) -> cosmarium_plugin_api::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.spawner().spawn_task(name, work)
+    }
+
+    /// Run `f` on the task list, after adding the tasks started since the
+    /// last call.
+    fn with_tasks<R>(&self, f: impl FnOnce(&mut Vec<TaskProgress>) -> R) -> R {
+        let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        if let Ok(receiver) = self.receiver.lock() {
+            tasks.extend(receiver.try_iter());
+        }
+        f(&mut tasks)
+    }
+
+    /// Status of every known task, oldest first.
+    pub fn tasks(&self) -> Vec<TaskStatus> {
+        self.with_tasks(|tasks| tasks.iter().map(TaskProgress::status).collect())
+    }
+
+    /// Status of the tasks still running.
+    pub fn running(&self) -> Vec<TaskStatus> {
+        self.with_tasks(|tasks| {
+            tasks
+                .iter()
+                .map(TaskProgress::status)
+                .filter(|status| !status.state.is_finished())
+                .collect()
+        })
+    }
+
+    /// Ask a task to stop.
+    ///
+    /// Returns `false` if no task has this identifier.
+    pub fn cancel(&self, id: Uuid) -> bool {
+        self.with_tasks(|tasks| match tasks.iter().find(|task| task.id() == id) {
+            Some(task) => {
+                task.cancel();
+                true
+            }
+            None => false,
+        })
+    }
+
+    /// Ask every running task to stop.
+    pub fn cancel_all(&self) {
+        self.with_tasks(|tasks| tasks.iter().for_each(TaskProgress::cancel));
+    }
+
+    /// Forget the tasks that have ended.
+    pub fn clear_finished(&self) {
+        self.with_tasks(|tasks| tasks.retain(|task| !task.status().state.is_finished()));
+    }
+}
+
+impl std::fmt::Debug for TaskManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskManager")
+            .field("tasks", &self.tasks())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmarium_plugin_api::{PluginContext, TaskState};
+
+    #[test]
+    fn test_plugin_tasks_are_tracked() {
+        let manager = TaskManager::new(Arc::new(Executor::new()));
+        let mut ctx = PluginContext::new();
+        ctx.connect_task_spawner(manager.spawner());
+
+        let (release, wait_release) = mpsc::channel::<()>();
+        let task = ctx.spawn_task("Download model", move |progress| {
+            progress.set_position(1, 4);
+            wait_release.recv()?;
+            Ok("model.onnx")
+        });
+
+        let running = manager.running();
+        assert_eq!(running.len(), 1);
+        assert_eq!(running[0].name, "Download model");
+
+        release.send(()).unwrap();
+        assert_eq!(task.wait().unwrap(), "model.onnx");
+        assert!(manager.running().is_empty());
+        assert_eq!(manager.tasks()[0].state, TaskState::Completed);
+
+        manager.clear_finished();
+        assert!(manager.tasks().is_empty());
+    }
+
+    #[test]
+    fn test_cancel_by_id() {
+        let manager = TaskManager::new(Arc::new(Executor::new()));
+        let task = manager.spawn_task("Push", |progress| loop {
+            progress.check_cancelled()?;
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        });
+
+        let id = manager.running()[0].id;
+        assert!(manager.cancel(id));
+        assert!(!manager.cancel(Uuid::new_v4()));
+        assert!(task.wait().map(|_: ()| ()).is_err());
+        assert_eq!(manager.tasks()[0].state, TaskState::Cancelled);
+    }
+}
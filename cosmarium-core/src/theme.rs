@@ -0,0 +1,490 @@
+//! # Theme scheduling for Cosmarium Core
+//!
+//! This module provides the logic behind the automatic ("auto") theme. It
+//! decides how much of the light appearance should be applied at a given
+//! moment, either by following the operating system preference, by following
+//! the sun at a configured location, or by following fixed local hours.
+//!
+//! The scheduler only produces a *daylight factor* in the `0.0..=1.0` range
+//! (`0.0` = fully dark, `1.0` = fully light). Around each switch point the
+//! factor ramps linearly over the configured transition window, so the UI can
+//! fade between appearances instead of flipping abruptly.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Theme name that enables automatic light/dark switching.
+pub const AUTO_THEME: &str = "auto";
+
+/// Resolved appearance of the user interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Appearance {
+    /// Light background, dark text
+    Light,
+    /// Dark background, light text
+    Dark,
+}
+
+impl Appearance {
+    /// Get the configuration key used for this appearance.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_core::theme::Appearance;
+    ///
+    /// assert_eq!(Appearance::Light.key(), "light");
+    /// assert_eq!(Appearance::Dark.key(), "dark");
+    /// ```
+    pub fn key(&self) -> &'static str {
+        match self {
+            Appearance::Light => "light",
+            Appearance::Dark => "dark",
+        }
+    }
+
+    /// Daylight factor matching this appearance.
+    pub fn daylight(&self) -> f32 {
+        match self {
+            Appearance::Light => 1.0,
+            Appearance::Dark => 0.0,
+        }
+    }
+}
+
+/// Source used by the auto theme to decide between light and dark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeSource {
+    /// Follow the operating system dark/light preference
+    System,
+    /// Light between sunrise and sunset at the configured location
+    Sun,
+    /// Light between fixed local hours
+    Hours,
+}
+
+/// Editor colors overriding the theme defaults, as `#rrggbb` strings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditorColorOverrides {
+    /// Editor background color
+    pub background: Option<String>,
+    /// Editor text color
+    pub text: Option<String>,
+    /// Selection highlight color
+    pub selection: Option<String>,
+}
+
+impl EditorColorOverrides {
+    /// Check whether no color is overridden.
+    pub fn is_empty(&self) -> bool {
+        self.background.is_none() && self.text.is_none() && self.selection.is_none()
+    }
+}
+
+/// Automatic theme configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeScheduleConfig {
+    /// What drives the light/dark decision
+    pub source: ThemeSource,
+    /// Latitude in degrees, used by [`ThemeSource::Sun`]
+    pub latitude: f64,
+    /// Longitude in degrees (east positive), used by [`ThemeSource::Sun`]
+    pub longitude: f64,
+    /// Local minute of the day at which the light theme starts ([`ThemeSource::Hours`])
+    pub day_start_minute: u32,
+    /// Local minute of the day at which the dark theme starts ([`ThemeSource::Hours`])
+    pub night_start_minute: u32,
+    /// Length of the fade between appearances, in minutes
+    pub transition_minutes: u32,
+    /// Per-appearance editor color overrides, keyed by `"light"` or `"dark"`
+    pub editor_overrides: HashMap<String, EditorColorOverrides>,
+}
+
+impl Default for ThemeScheduleConfig {
+    fn default() -> Self {
+        Self {
+            source: ThemeSource::System,
+            latitude: 48.85,
+            longitude: 2.35,
+            day_start_minute: 7 * 60,
+            night_start_minute: 20 * 60,
+            transition_minutes: 30,
+            editor_overrides: HashMap::new(),
+        }
+    }
+}
+
+impl ThemeScheduleConfig {
+    /// Get the editor color overrides for an appearance, if any.
+    pub fn overrides_for(&self, appearance: Appearance) -> Option<&EditorColorOverrides> {
+        self.editor_overrides
+            .get(appearance.key())
+            .filter(|overrides| !overrides.is_empty())
+    }
+}
+
+/// Sunrise and sunset for a given day and location.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SunEvents {
+    /// The sun rises and sets during the day
+    Normal {
+        /// Sunrise instant
+        sunrise: DateTime<Utc>,
+        /// Sunset instant
+        sunset: DateTime<Utc>,
+    },
+    /// The sun never sets (polar summer)
+    PolarDay,
+    /// The sun never rises (polar winter)
+    PolarNight,
+}
+
+impl SunEvents {
+    /// Compute sunrise and sunset using the NOAA solar approximation.
+    ///
+    /// The result is accurate to within a few minutes, which is far more than
+    /// a theme switch needs.
+    ///
+    /// # Arguments
+    ///
+    /// * `date` - UTC calendar day
+    /// * `latitude` - Latitude in degrees (north positive)
+    /// * `longitude` - Longitude in degrees (east positive)
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use chrono::NaiveDate;
+    /// use cosmarium_core::theme::SunEvents;
+    ///
+    /// let date = NaiveDate::from_ymd_opt(2024, 3, 20).unwrap();
+    /// let events = SunEvents::compute(date, 0.0, 0.0);
+    /// assert!(matches!(events, SunEvents::Normal { .. }));
+    /// ```
+    pub fn compute(date: NaiveDate, latitude: f64, longitude: f64) -> Self {
+        let day_of_year = date.ordinal0() as f64;
+        let gamma = 2.0 * std::f64::consts::PI / 365.0 * day_of_year;
+
+        let eq_time = 229.18
+            * (0.000075 + 0.001868 * gamma.cos()
+                - 0.032077 * gamma.sin()
+                - 0.014615 * (2.0 * gamma).cos()
+                - 0.040849 * (2.0 * gamma).sin());
+        let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+            - 0.006758 * (2.0 * gamma).cos()
+            + 0.000907 * (2.0 * gamma).sin()
+            - 0.002697 * (3.0 * gamma).cos()
+            + 0.00148 * (3.0 * gamma).sin();
+
+        let lat = latitude.to_radians();
+        let cos_hour_angle = 90.833_f64.to_radians().cos() / (lat.cos() * declination.cos())
+            - lat.tan() * declination.tan();
+
+        if cos_hour_angle > 1.0 {
+            return SunEvents::PolarNight;
+        }
+        if cos_hour_angle < -1.0 {
+            return SunEvents::PolarDay;
+        }
+
+        let hour_angle = cos_hour_angle.acos().to_degrees();
+        let sunrise_minutes = 720.0 - 4.0 * (longitude + hour_angle) - eq_time;
+        let sunset_minutes = 720.0 - 4.0 * (longitude - hour_angle) - eq_time;
+
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+        SunEvents::Normal {
+            sunrise: midnight + Duration::seconds((sunrise_minutes * 60.0) as i64),
+            sunset: midnight + Duration::seconds((sunset_minutes * 60.0) as i64),
+        }
+    }
+}
+
+/// Computes the daylight factor for the auto theme.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_core::theme::{Appearance, ThemeScheduleConfig, ThemeScheduler};
+///
+/// let scheduler = ThemeScheduler::new(ThemeScheduleConfig::default());
+/// let daylight = scheduler.daylight(chrono::Utc::now(), Some(Appearance::Light));
+/// assert_eq!(daylight, 1.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ThemeScheduler {
+    config: ThemeScheduleConfig,
+}
+
+impl ThemeScheduler {
+    /// Create a scheduler from its configuration.
+    pub fn new(config: ThemeScheduleConfig) -> Self {
+        Self { config }
+    }
+
+    /// Get the scheduler configuration.
+    pub fn config(&self) -> &ThemeScheduleConfig {
+        &self.config
+    }
+
+    /// Compute the target daylight factor at `now`.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current instant
+    /// * `system` - Appearance reported by the operating system, if known
+    ///
+    /// # Returns
+    ///
+    /// `0.0` for fully dark, `1.0` for fully light, and intermediate values
+    /// during a transition window.
+    pub fn daylight(&self, now: DateTime<Utc>, system: Option<Appearance>) -> f32 {
+        match self.config.source {
+            ThemeSource::System => system.unwrap_or(Appearance::Dark).daylight(),
+            ThemeSource::Sun => self.sun_daylight(now),
+            ThemeSource::Hours => {
+                let local = now.with_timezone(&Local);
+                let minute =
+                    (local.hour() * 60 + local.minute()) as f64 + local.second() as f64 / 60.0;
+                self.hours_daylight(minute)
+            }
+        }
+    }
+
+    /// Compute the target daylight factor for the current instant.
+    ///
+    /// # Arguments
+    ///
+    /// * `system` - Appearance reported by the operating system, if known
+    pub fn current_daylight(&self, system: Option<Appearance>) -> f32 {
+        self.daylight(Utc::now(), system)
+    }
+
+    /// Resolve the dominant appearance at `now`.
+    pub fn appearance(&self, now: DateTime<Utc>, system: Option<Appearance>) -> Appearance {
+        if self.daylight(now, system) >= 0.5 {
+            Appearance::Light
+        } else {
+            Appearance::Dark
+        }
+    }
+
+    fn half_transition(&self) -> f64 {
+        self.config.transition_minutes as f64 / 2.0
+    }
+
+    fn sun_daylight(&self, now: DateTime<Utc>) -> f32 {
+        let today = now.date_naive();
+        let half = self.half_transition();
+
+        // Check the neighbouring days as well: far from Greenwich a local
+        // evening can fall on the next UTC date.
+        let mut daylight: f64 = 0.0;
+        for offset in -1..=1 {
+            let date = today + Duration::days(offset);
+            match SunEvents::compute(date, self.config.latitude, self.config.longitude) {
+                SunEvents::Normal { sunrise, sunset } => {
+                    let minutes = |t: DateTime<Utc>| (t - now).num_seconds() as f64 / 60.0;
+                    daylight = daylight.max(window(0.0, minutes(sunrise), minutes(sunset), half));
+                }
+                SunEvents::PolarDay if offset == 0 => return 1.0,
+                SunEvents::PolarNight if offset == 0 => return 0.0,
+                _ => {}
+            }
+        }
+        daylight as f32
+    }
+
+    fn hours_daylight(&self, minute: f64) -> f32 {
+        let start = self.config.day_start_minute as f64;
+        let mut end = self.config.night_start_minute as f64;
+        if end <= start {
+            end += DAY_MINUTES;
+        }
+        let half = self.half_transition();
+
+        [-DAY_MINUTES, 0.0, DAY_MINUTES]
+            .iter()
+            .map(|shift| window(minute, start + shift, end + shift, half))
+            .fold(0.0, f64::max) as f32
+    }
+}
+
+const DAY_MINUTES: f64 = 24.0 * 60.0;
+
+/// Trapezoid window: `1.0` between `start` and `end`, ramping linearly over
+/// `half` minutes on either side of each edge.
+fn window(t: f64, start: f64, end: f64, half: f64) -> f64 {
+    let edge = |distance: f64| {
+        if half <= 0.0 {
+            if distance >= 0.0 {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            ((distance + half) / (2.0 * half)).clamp(0.0, 1.0)
+        }
+    };
+    edge(t - start).min(edge(end - t))
+}
+
+/// Parse a `#rrggbb` (or `rrggbb`) color string into RGB components.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_core::theme::parse_hex_color;
+///
+/// assert_eq!(parse_hex_color("#ff8000"), Some([255, 128, 0]));
+/// assert_eq!(parse_hex_color("nope"), None);
+/// ```
+pub fn parse_hex_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn sun_config() -> ThemeScheduleConfig {
+        ThemeScheduleConfig {
+            source: ThemeSource::Sun,
+            latitude: 48.85,
+            longitude: 2.35,
+            transition_minutes: 60,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sun_events_paris_summer() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        match SunEvents::compute(date, 48.85, 2.35) {
+            SunEvents::Normal { sunrise, sunset } => {
+                let expected_rise = Utc.with_ymd_and_hms(2024, 6, 21, 3, 47, 0).unwrap();
+                let expected_set = Utc.with_ymd_and_hms(2024, 6, 21, 19, 58, 0).unwrap();
+                assert!((sunrise - expected_rise).num_minutes().abs() <= 10);
+                assert!((sunset - expected_set).num_minutes().abs() <= 10);
+            }
+            other => panic!("unexpected sun events: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_sun_events_polar() {
+        let summer = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let winter = NaiveDate::from_ymd_opt(2024, 12, 21).unwrap();
+        assert_eq!(SunEvents::compute(summer, 80.0, 0.0), SunEvents::PolarDay);
+        assert_eq!(SunEvents::compute(winter, 80.0, 0.0), SunEvents::PolarNight);
+    }
+
+    #[test]
+    fn test_sun_daylight_transitions() {
+        let scheduler = ThemeScheduler::new(sun_config());
+
+        let noon = Utc.with_ymd_and_hms(2024, 6, 21, 12, 0, 0).unwrap();
+        let midnight = Utc.with_ymd_and_hms(2024, 6, 21, 0, 0, 0).unwrap();
+        assert_eq!(scheduler.daylight(noon, None), 1.0);
+        assert_eq!(scheduler.daylight(midnight, None), 0.0);
+
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        if let SunEvents::Normal { sunrise, .. } = SunEvents::compute(date, 48.85, 2.35) {
+            let fading = scheduler.daylight(sunrise, None);
+            assert!((fading - 0.5).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_sun_daylight_across_utc_midnight() {
+        // Los Angeles: summer sunset happens after midnight UTC.
+        let scheduler = ThemeScheduler::new(ThemeScheduleConfig {
+            latitude: 34.05,
+            longitude: -118.24,
+            ..sun_config()
+        });
+        let local_evening = Utc.with_ymd_and_hms(2024, 6, 22, 2, 0, 0).unwrap();
+        assert_eq!(scheduler.daylight(local_evening, None), 1.0);
+    }
+
+    #[test]
+    fn test_hours_daylight() {
+        let scheduler = ThemeScheduler::new(ThemeScheduleConfig {
+            source: ThemeSource::Hours,
+            transition_minutes: 20,
+            ..Default::default()
+        });
+
+        assert_eq!(scheduler.hours_daylight(12.0 * 60.0), 1.0);
+        assert_eq!(scheduler.hours_daylight(23.0 * 60.0), 0.0);
+        assert!((scheduler.hours_daylight(7.0 * 60.0) - 0.5).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_hours_daylight_wrapping() {
+        // Night owl schedule: light from 22:00 to 06:00.
+        let scheduler = ThemeScheduler::new(ThemeScheduleConfig {
+            source: ThemeSource::Hours,
+            day_start_minute: 22 * 60,
+            night_start_minute: 6 * 60,
+            transition_minutes: 0,
+            ..Default::default()
+        });
+
+        assert_eq!(scheduler.hours_daylight(23.0 * 60.0), 1.0);
+        assert_eq!(scheduler.hours_daylight(3.0 * 60.0), 1.0);
+        assert_eq!(scheduler.hours_daylight(12.0 * 60.0), 0.0);
+    }
+
+    #[test]
+    fn test_system_source() {
+        let scheduler = ThemeScheduler::new(ThemeScheduleConfig::default());
+        let now = Utc::now();
+        assert_eq!(scheduler.daylight(now, Some(Appearance::Light)), 1.0);
+        assert_eq!(
+            scheduler.appearance(now, Some(Appearance::Dark)),
+            Appearance::Dark
+        );
+        assert_eq!(scheduler.appearance(now, None), Appearance::Dark);
+    }
+
+    #[test]
+    fn test_editor_overrides() {
+        let mut config = ThemeScheduleConfig::default();
+        config
+            .editor_overrides
+            .insert("dark".to_string(), EditorColorOverrides::default());
+        config.editor_overrides.insert(
+            "light".to_string(),
+            EditorColorOverrides {
+                background: Some("#fdf6e3".to_string()),
+                ..Default::default()
+            },
+        );
+
+        assert!(config.overrides_for(Appearance::Dark).is_none());
+        let light = config.overrides_for(Appearance::Light).unwrap();
+        assert_eq!(
+            light.background.as_deref().and_then(parse_hex_color),
+            Some([0xfd, 0xf6, 0xe3])
+        );
+    }
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("#000000"), Some([0, 0, 0]));
+        assert_eq!(parse_hex_color("FFFFFF"), Some([255, 255, 255]));
+        assert_eq!(parse_hex_color("#fff"), None);
+        assert_eq!(parse_hex_color("#gg0000"), None);
+    }
+}
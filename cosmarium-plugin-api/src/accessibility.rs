@@ -0,0 +1,445 @@
+//! Accessibility of exports.
+//!
+//! Readers using screen readers, braille displays or read-aloud rely on what
+//! exports say about themselves: the language of the text, headings that
+//! nest without skipping levels, and an alternative text for every image,
+//! written in the brackets of its Markdown:
+//!
+//! ```markdown
+//! ![The harbor at dawn, three boats moored at the pier](../assets/harbor.png)
+//! ```
+//!
+//! [`check`] lists what a manuscript lacks before it is exported, and
+//! [`AccessibilityMetadata`] describes it the way EPUB books declare their
+//! accessibility, with the schema.org properties.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::accessibility::{check, FindingKind};
+//! use cosmarium_plugin_api::export::{Manuscript, ManuscriptSection, SectionKind};
+//!
+//! let manuscript = Manuscript {
+//!     title: "The Inn".to_string(),
+//!     language: "en".to_string(),
+//!     sections: vec![ManuscriptSection {
+//!         kind: SectionKind::Document,
+//!         title: "Dawn".to_string(),
+//!         depth: 0,
+//!         path: None,
+//!         separator: String::new(),
+//!         markdown: "# Dawn\n\n![](harbor.png)".to_string(),
+//!     }],
+//!     ..Manuscript::default()
+//! };
+//! let findings = check(&manuscript);
+//! assert_eq!(findings.len(), 1);
+//! assert_eq!(findings[0].kind, FindingKind::MissingAltText);
+//! assert_eq!(findings[0].section, "Dawn");
+//! ```
+
+use crate::export::Manuscript;
+use std::fmt;
+use std::path::PathBuf;
+
+/// An image of a Markdown text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    /// Line of the image, counted from 0
+    pub line: usize,
+    /// File or URL of the image, as written
+    pub target: String,
+    /// Alternative text, empty if there is none
+    pub alt: String,
+}
+
+/// Images of `markdown`, outside of code.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_plugin_api::accessibility::images;
+///
+/// let images = images("Dawn.\n\n![The *harbor*](maps/harbor.png \"Harbor\") and `![x](y)`");
+/// assert_eq!(images.len(), 1);
+/// assert_eq!(images[0].line, 2);
+/// assert_eq!(images[0].target, "maps/harbor.png");
+/// assert_eq!(images[0].alt, "The harbor");
+/// ```
+pub fn images(markdown: &str) -> Vec<Image> {
+    let mut images = Vec::new();
+    let mut fence: Option<&str> = None;
+    for (i, line) in markdown.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            match fence {
+                Some(open) if trimmed.starts_with(open) => fence = None,
+                None => fence = Some(&trimmed[..3]),
+                _ => {}
+            }
+            continue;
+        }
+        if fence.is_some() || line.starts_with("    ") || line.starts_with('\t') {
+            continue;
+        }
+
+        let mut rest = line;
+        let mut in_code = false;
+        while let Some(at) = rest.find(['`', '!']) {
+            let (before, after) = rest.split_at(at);
+            if let Some(code) = after.strip_prefix('`') {
+                in_code = !in_code;
+                rest = code;
+                continue;
+            }
+            if in_code || before.ends_with('\\') || !after.starts_with("![") {
+                rest = &after[1..];
+                continue;
+            }
+            match image_at(after) {
+                Some((target, alt, len)) => {
+                    images.push(Image {
+                        line: i,
+                        target,
+                        alt,
+                    });
+                    rest = &after[len..];
+                }
+                None => rest = &after[1..],
+            }
+        }
+    }
+    images
+}
+
+/// The target and alternative text of the image `text` starts with, and
+/// its length.
+fn image_at(text: &str) -> Option<(String, String, usize)> {
+    let mut depth = 0;
+    let close = text[1..].char_indices().find_map(|(i, c)| {
+        match c {
+            '[' => depth += 1,
+            ']' if depth == 1 => return Some(i + 1),
+            ']' => depth -= 1,
+            _ => {}
+        }
+        None
+    })?;
+    let destination = text[close + 1..].strip_prefix('(')?;
+    let end = destination.find(')')?;
+    let destination = destination[..end].trim_start();
+    let target = match destination.strip_prefix('<') {
+        Some(bracketed) => bracketed.split('>').next().unwrap_or_default(),
+        None => destination.split_whitespace().next().unwrap_or_default(),
+    }
+    .to_string();
+
+    // Emphasis marks are not part of the text read
+    let alt = text[2..close]
+        .chars()
+        .filter(|c| !matches!(c, '*' | '_' | '`'))
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some((target, alt, close + 2 + end + 1))
+}
+
+/// Level of the ATX heading `line`, if it is one.
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with([' ', '\t'])))
+        .then_some(level)
+}
+
+/// What an item of the checklist is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FindingKind {
+    /// The manuscript has no title
+    MissingTitle,
+    /// The language of the text is not known
+    MissingLanguage,
+    /// An image has no alternative text
+    MissingAltText,
+    /// A heading is more than one level below the heading before it
+    SkippedHeadingLevel,
+}
+
+impl FindingKind {
+    /// Label of the findings of this kind.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::MissingTitle => "No title",
+            Self::MissingLanguage => "No language",
+            Self::MissingAltText => "Images without alternative text",
+            Self::SkippedHeadingLevel => "Skipped heading levels",
+        }
+    }
+}
+
+/// Something that makes a manuscript harder to read with assistive
+/// technologies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub kind: FindingKind,
+    /// Title of the section it is in, empty for the manuscript as a whole
+    /// and its front matter
+    pub section: String,
+    /// Source file of that section, if it is a document
+    pub path: Option<PathBuf>,
+    /// Line of the section's Markdown, counted from 0
+    pub line: usize,
+    /// What is wrong, e.g. the image without alternative text
+    pub detail: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.section.is_empty() {
+            write!(f, "{}:{}: ", self.section, self.line + 1)?;
+        }
+        write!(f, "{}", self.kind.label())?;
+        if !self.detail.is_empty() {
+            write!(f, " ({})", self.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// The accessibility checklist of `manuscript`, in reading order.
+pub fn check(manuscript: &Manuscript) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let whole = |kind| Finding {
+        kind,
+        section: String::new(),
+        path: None,
+        line: 0,
+        detail: String::new(),
+    };
+    if manuscript.title.trim().is_empty() {
+        findings.push(whole(FindingKind::MissingTitle));
+    }
+    if manuscript.language.trim().is_empty() {
+        findings.push(whole(FindingKind::MissingLanguage));
+    }
+
+    let parts = std::iter::once((String::new(), None, &manuscript.front_matter)).chain(
+        manuscript.sections.iter().map(|section| {
+            (
+                section.title.clone(),
+                section.path.clone(),
+                &section.markdown,
+            )
+        }),
+    );
+    let mut previous = 0;
+    for (section, path, markdown) in parts {
+        let finding = |kind, line, detail| Finding {
+            kind,
+            section: section.clone(),
+            path: path.clone(),
+            line,
+            detail,
+        };
+        let mut fence = false;
+        for (line, text) in markdown.lines().enumerate() {
+            let trimmed = text.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                fence = !fence;
+            }
+            let Some(level) = heading_level(text).filter(|_| !fence) else {
+                continue;
+            };
+            if level > previous + 1 {
+                let detail = match previous {
+                    0 => format!("starts at level {}", level),
+                    _ => format!("level {} after level {}", level, previous),
+                };
+                findings.push(finding(FindingKind::SkippedHeadingLevel, line, detail));
+            }
+            previous = level;
+        }
+        for image in images(markdown) {
+            if image.alt.is_empty() {
+                findings.push(finding(
+                    FindingKind::MissingAltText,
+                    image.line,
+                    image.target,
+                ));
+            }
+        }
+    }
+    findings
+}
+
+/// How an e-book can be read, as the schema.org accessibility properties
+/// of its package metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessibilityMetadata {
+    /// Senses the content is perceived with: `textual`, and `visual` when
+    /// it has images
+    pub access_modes: Vec<&'static str>,
+    /// Access modes which are enough to read the whole content
+    pub access_mode_sufficient: Vec<&'static str>,
+    /// Features helping to read it
+    pub features: Vec<&'static str>,
+    /// Hazards of reading it
+    pub hazards: Vec<&'static str>,
+    /// Summary of the above, in English
+    pub summary: String,
+}
+
+impl AccessibilityMetadata {
+    /// Metadata of `manuscript`: an alternative text for every image makes
+    /// text alone enough to read it.
+    pub fn of(manuscript: &Manuscript) -> Self {
+        let images: Vec<Image> = std::iter::once(manuscript.front_matter.as_str())
+            .chain(manuscript.sections.iter().map(|s| s.markdown.as_str()))
+            .flat_map(images)
+            .collect();
+        let described = images.iter().all(|image| !image.alt.is_empty());
+
+        let mut access_modes = vec!["textual"];
+        let mut features = vec!["structuralNavigation", "tableOfContents", "readingOrder"];
+        if !images.is_empty() {
+            access_modes.push("visual");
+            if described {
+                features.push("alternativeText");
+            }
+        }
+        let access_mode_sufficient = if described {
+            vec!["textual"]
+        } else {
+            vec!["textual,visual"]
+        };
+        let summary = if described {
+            "The text is structured with headings and navigable from its table of \
+             contents; images have alternative text."
+        } else {
+            "The text is structured with headings and navigable from its table of \
+             contents; some images have no alternative text."
+        };
+        Self {
+            access_modes,
+            access_mode_sufficient,
+            features,
+            hazards: vec!["none"],
+            summary: summary.to_string(),
+        }
+    }
+
+    /// The metadata as a YAML block opening Pandoc's Markdown.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::accessibility::AccessibilityMetadata;
+    /// use cosmarium_plugin_api::export::Manuscript;
+    ///
+    /// let metadata = AccessibilityMetadata::of(&Manuscript::default());
+    /// let yaml = metadata.to_pandoc_yaml();
+    /// assert!(yaml.starts_with("---\naccessModes:\n- textual\n"));
+    /// assert!(yaml.ends_with("\n...\n\n"));
+    /// ```
+    pub fn to_pandoc_yaml(&self) -> String {
+        let mut yaml = String::from("---\n");
+        let lists = [
+            ("accessModes", &self.access_modes),
+            ("accessModeSufficient", &self.access_mode_sufficient),
+            ("accessibilityFeatures", &self.features),
+            ("accessibilityHazards", &self.hazards),
+        ];
+        for (key, values) in lists {
+            yaml.push_str(&format!("{}:\n", key));
+            for value in values {
+                yaml.push_str(&format!("- {}\n", value));
+            }
+        }
+        yaml.push_str(&format!(
+            "accessibilitySummary: \"{}\"\n...\n\n",
+            self.summary.replace('"', "\\\"")
+        ));
+        yaml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{ManuscriptSection, SectionKind};
+
+    fn manuscript(markdown: &str) -> Manuscript {
+        Manuscript {
+            title: "The Inn".to_string(),
+            language: "en".to_string(),
+            front_matter: "# The Inn".to_string(),
+            sections: vec![ManuscriptSection {
+                kind: SectionKind::Document,
+                title: "Dawn".to_string(),
+                depth: 0,
+                path: Some(PathBuf::from("content/dawn.md")),
+                separator: String::new(),
+                markdown: markdown.to_string(),
+            }],
+            ..Manuscript::default()
+        }
+    }
+
+    #[test]
+    fn test_images() {
+        let markdown = "```\n![](code.png)\n```\n\\![no](a.png) ![[1] map](<b c.png>)\n![x]";
+        let images = images(markdown);
+        assert_eq!(
+            images,
+            [Image {
+                line: 3,
+                target: "b c.png".to_string(),
+                alt: "[1] map".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_checklist() {
+        assert!(check(&manuscript("## Dawn\n\n![Boats](boats.png)")).is_empty());
+
+        let findings = check(&Manuscript {
+            title: String::new(),
+            language: String::new(),
+            ..manuscript("### Dawn\n\n```\n# code\n```\n![ ](boats.png)")
+        });
+        let kinds: Vec<FindingKind> = findings.iter().map(|f| f.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                FindingKind::MissingTitle,
+                FindingKind::MissingLanguage,
+                FindingKind::SkippedHeadingLevel,
+                FindingKind::MissingAltText,
+            ]
+        );
+        assert_eq!(
+            findings[2].to_string(),
+            "Dawn:1: Skipped heading levels (level 3 after level 1)"
+        );
+        assert_eq!(findings[3].line, 5);
+        assert_eq!(findings[3].path, Some(PathBuf::from("content/dawn.md")));
+    }
+
+    #[test]
+    fn test_metadata() {
+        let described = AccessibilityMetadata::of(&manuscript("![Boats](boats.png)"));
+        assert_eq!(described.access_modes, ["textual", "visual"]);
+        assert_eq!(described.access_mode_sufficient, ["textual"]);
+        assert!(described.features.contains(&"alternativeText"));
+
+        let undescribed = AccessibilityMetadata::of(&manuscript("![](boats.png)"));
+        assert_eq!(undescribed.access_mode_sufficient, ["textual,visual"]);
+        assert!(!undescribed.features.contains(&"alternativeText"));
+
+        let text = AccessibilityMetadata::of(&manuscript("Dawn."));
+        assert_eq!(text.access_modes, ["textual"]);
+        assert!(!text.features.contains(&"alternativeText"));
+    }
+}
@@ -0,0 +1,124 @@
+//! Branches of the Git repository of a project, for alternate drafts.
+//!
+//! An alternate draft is a branch started from the current one to try
+//! another take, a different ending or a chapter told in the first person,
+//! without losing the text as it is. The application publishes the branches
+//! of the active project under [`BRANCHES_KEY`] and serves the
+//! [`BranchRequest`]s plugins send with [`BRANCH_REQUEST`]. The changes of
+//! the project are committed before it leaves a branch, so nothing is lost
+//! going back and forth.
+//!
+//! Merging a draft back brings its changes into another branch. A text
+//! changed differently on both is left with conflict markers around each
+//! passage in conflict, listed in [`MergeState::conflicts`] until it is
+//! resolved, keeping one side or editing the text; the merge is then
+//! committed.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::branch::branch_name;
+//!
+//! assert_eq!(branch_name("Alternate ending: Mara lives!"), "alternate-ending-mara-lives");
+//! assert_eq!(branch_name("  "), "");
+//! ```
+
+use std::path::PathBuf;
+
+/// Shared state key (`Option<Arc<Branches>>`) of the branches of the active
+/// project, `None` when it has no repository.
+pub const BRANCHES_KEY: &str = "branches";
+
+/// Shared state key (`Option<BranchRequest>`) of a request about the
+/// branches of the active project, served by the application.
+pub const BRANCH_REQUEST: &str = "branch_request";
+
+/// Branches of a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Branches {
+    /// Branch the project is on, `None` before the first commit
+    pub current: Option<String>,
+    /// All the branches, by name
+    pub branches: Vec<String>,
+    /// Merge waiting for its conflicts to be resolved, if any
+    pub merge: Option<MergeState>,
+}
+
+impl Branches {
+    /// The branches but the current one.
+    pub fn others(&self) -> impl Iterator<Item = &str> {
+        self.branches
+            .iter()
+            .map(String::as_str)
+            .filter(|name| Some(*name) != self.current.as_deref())
+    }
+}
+
+/// A merge waiting for its conflicts to be resolved.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeState {
+    /// Branch merged into the current one
+    pub draft: String,
+    /// Files still in conflict, by path
+    pub conflicts: Vec<ConflictedFile>,
+}
+
+/// A text file changed differently on both branches of a merge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictedFile {
+    /// Path of the file, relative to the project root
+    pub path: PathBuf,
+    /// Passages in conflict, in order
+    pub conflicts: Vec<Conflict>,
+}
+
+/// A passage of a text changed differently on both branches.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Conflict {
+    /// The passage on the current branch
+    pub current: String,
+    /// The passage on the draft merged
+    pub draft: String,
+}
+
+/// Side of a merge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// The branch merged into
+    Current,
+    /// The draft merged
+    Draft,
+}
+
+/// Request about the branches of the active project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BranchRequest {
+    /// Commit the changes, then start a branch of this name from the
+    /// current one and switch to it
+    StartDraft(String),
+    /// Commit the changes, then switch to this branch
+    Switch(String),
+    /// Commit the changes, then merge the branch `draft` into `into`,
+    /// switching to it first
+    Merge { draft: String, into: String },
+    /// Delete this branch, which must not be the current one
+    Delete(String),
+    /// Keep one side of every conflict of the file at this path, relative
+    /// to the project root
+    Resolve(PathBuf, Side),
+    /// Commit the merge once its conflicts are resolved
+    CompleteMerge,
+    /// Give up the merge, bringing back the files as they were
+    AbortMerge,
+}
+
+/// Name of a branch for a draft labeled `label`: its words, in lowercase,
+/// joined with dashes.
+pub fn branch_name(label: &str) -> String {
+    label
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
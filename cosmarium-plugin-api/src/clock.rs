@@ -0,0 +1,195 @@
+//! Time and identifiers, as plugins should read them.
+//!
+//! Plugins ask their [`PluginContext`](crate::PluginContext) for the time and
+//! for new identifiers instead of asking the system. In the application the
+//! context reads the system clock and draws random identifiers; a test or a
+//! simulation replaces them with a [`Clock::manual`] that only moves when
+//! told to and an [`IdSource::seeded`] that draws the same identifiers on
+//! every run, so that delays, debounces and timestamps are reproducible.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::clock::Clock;
+//! use std::time::Duration;
+//!
+//! let clock = Clock::manual(Clock::EPOCH);
+//! let start = clock.instant();
+//! clock.advance(Duration::from_secs(2));
+//! assert_eq!(clock.instant() - start, Duration::from_secs(2));
+//! assert_eq!(clock.now() - Clock::EPOCH, chrono::Duration::seconds(2));
+//! ```
+
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Source of the current time.
+///
+/// Clones share the same time: advancing one advances them all.
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    /// Time of a manual clock, `None` for the system clock
+    manual: Option<Arc<ManualTime>>,
+}
+
+#[derive(Debug)]
+struct ManualTime {
+    /// Date and time the clock started at
+    start: DateTime<Utc>,
+    /// Instant standing for `start`
+    origin: Instant,
+    /// Time the clock was advanced by
+    elapsed: Mutex<Duration>,
+}
+
+impl Clock {
+    /// Start of the manual clocks of simulations: 2024-01-01, midnight UTC.
+    pub const EPOCH: DateTime<Utc> = DateTime::from_timestamp_nanos(1_704_067_200_000_000_000);
+
+    /// The system clock.
+    pub fn system() -> Self {
+        Self::default()
+    }
+
+    /// A clock standing still at `start` until it is advanced.
+    pub fn manual(start: DateTime<Utc>) -> Self {
+        Self {
+            manual: Some(Arc::new(ManualTime {
+                start,
+                origin: Instant::now(),
+                elapsed: Mutex::new(Duration::ZERO),
+            })),
+        }
+    }
+
+    /// Whether the clock only moves when advanced.
+    pub fn is_manual(&self) -> bool {
+        self.manual.is_some()
+    }
+
+    /// Current date and time.
+    pub fn now(&self) -> DateTime<Utc> {
+        match &self.manual {
+            Some(time) => time.start + time.elapsed(),
+            None => Utc::now(),
+        }
+    }
+
+    /// Current instant, to measure delays with.
+    pub fn instant(&self) -> Instant {
+        match &self.manual {
+            Some(time) => time.origin + time.elapsed(),
+            None => Instant::now(),
+        }
+    }
+
+    /// Time elapsed since `earlier`, an instant of this clock.
+    pub fn since(&self, earlier: Instant) -> Duration {
+        self.instant().saturating_duration_since(earlier)
+    }
+
+    /// Move a manual clock forward by `duration`.
+    ///
+    /// The system clock cannot be moved; the call is then ignored.
+    pub fn advance(&self, duration: Duration) {
+        match &self.manual {
+            Some(time) => {
+                let mut elapsed = time.elapsed.lock().unwrap_or_else(|e| e.into_inner());
+                *elapsed += duration;
+            }
+            None => tracing::warn!("The system clock cannot be advanced"),
+        }
+    }
+}
+
+impl ManualTime {
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Source of new unique identifiers.
+///
+/// Clones share the same sequence.
+#[derive(Debug, Clone, Default)]
+pub struct IdSource {
+    /// State of a seeded source, `None` for random identifiers
+    seeded: Option<Arc<Mutex<u64>>>,
+}
+
+impl IdSource {
+    /// Random identifiers (UUID v4).
+    pub fn random() -> Self {
+        Self::default()
+    }
+
+    /// Identifiers drawn from `seed`: the same seed gives the same sequence.
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            seeded: Some(Arc::new(Mutex::new(seed))),
+        }
+    }
+
+    /// Draw a new identifier.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::clock::IdSource;
+    ///
+    /// let (a, b) = (IdSource::seeded(7), IdSource::seeded(7));
+    /// assert_eq!(a.next_id(), b.next_id());
+    /// assert_ne!(a.next_id(), IdSource::seeded(7).next_id());
+    /// ```
+    pub fn next_id(&self) -> Uuid {
+        let Some(state) = &self.seeded else {
+            return Uuid::new_v4();
+        };
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut bytes = [0; 16];
+        for half in bytes.chunks_mut(8) {
+            half.copy_from_slice(&splitmix64(&mut state).to_le_bytes());
+        }
+        uuid::Builder::from_random_bytes(bytes).into_uuid()
+    }
+}
+
+/// Next number of the SplitMix64 sequence at `state`.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock_clones_share_time() {
+        let clock = Clock::manual(Clock::EPOCH);
+        let start = clock.instant();
+        let other = clock.clone();
+        other.advance(Duration::from_millis(1500));
+
+        assert_eq!(clock.since(start), Duration::from_millis(1500));
+        assert_eq!(clock.now().to_rfc3339(), "2024-01-01T00:00:01.500+00:00");
+        assert!(!Clock::system().is_manual());
+    }
+
+    #[test]
+    fn test_seeded_ids_are_reproducible_versioned_uuids() {
+        let ids: Vec<_> = (0..3).map(|_| IdSource::seeded(42).next_id()).collect();
+        assert!(ids.windows(2).all(|pair| pair[0] == pair[1]));
+
+        let source = IdSource::seeded(42);
+        let (first, second) = (source.next_id(), source.next_id());
+        assert_eq!(first, ids[0]);
+        assert_ne!(first, second);
+        assert_eq!(first.get_version_num(), 4);
+    }
+}
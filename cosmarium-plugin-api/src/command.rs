@@ -0,0 +1,391 @@
+//! Commands that plugins and the application expose to the user.
+//!
+//! A command is an action with an identifier, a title shown in the command
+//! palette and menus, and an optional default keyboard shortcut. Plugins
+//! register theirs with [`PluginContext::register_command`]; the application
+//! lists them in the command palette, runs the command bound to a pressed
+//! shortcut, and shows the shortcuts next to its menu items.
+//!
+//! The user rebinds commands in a [`Keymap`], which only records the
+//! shortcuts that differ from the defaults.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::command::Shortcut;
+//! use cosmarium_plugin_api::PluginContext;
+//!
+//! let mut ctx = PluginContext::new();
+//! ctx.register_command(
+//!     "word_count.refresh",
+//!     "Refresh Word Count",
+//!     Some("Ctrl+Shift+W".parse().unwrap()),
+//!     |ctx: &mut PluginContext| ctx.set_shared_state("word_count_stale", true),
+//! );
+//!
+//! let shortcut: Shortcut = "Ctrl+Shift+W".parse().unwrap();
+//! assert_eq!(ctx.command_for_shortcut(&shortcut), Some("word_count.refresh"));
+//! assert!(ctx.run_command("word_count.refresh"));
+//! assert_eq!(ctx.get_shared_state::<bool>("word_count_stale"), Some(true));
+//! ```
+
+use crate::PluginContext;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Code run when a command is invoked, from the palette, a menu or its
+/// shortcut.
+pub type CommandHandler = Arc<dyn Fn(&mut PluginContext) + Send + Sync>;
+
+/// A registered command, without its handler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    /// Unique identifier, prefixed by the plugin name by convention
+    /// (`"editor.undo"`)
+    pub id: String,
+    /// Title shown in the command palette
+    pub title: String,
+    /// Shortcut the command is bound to unless the user says otherwise
+    pub default_shortcut: Option<Shortcut>,
+}
+
+/// A key pressed with modifiers, written `Ctrl+Shift+F`.
+///
+/// The key is named as egui names it: a letter, a digit, `F11`, `Left`,
+/// `Enter`, and so on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Shortcut {
+    /// Control (Command on macOS) held down
+    pub ctrl: bool,
+    /// Shift held down
+    pub shift: bool,
+    /// Alt (Option on macOS) held down
+    pub alt: bool,
+    /// Name of the key
+    pub key: String,
+}
+
+impl Shortcut {
+    /// Shortcut of `key` alone.
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            key: key.into(),
+        }
+    }
+
+    /// Shortcut of Ctrl and `key`.
+    pub fn ctrl(key: impl Into<String>) -> Self {
+        Self {
+            ctrl: true,
+            ..Self::new(key)
+        }
+    }
+
+    /// Same shortcut with Shift held down.
+    pub fn with_shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    /// Same shortcut with Alt held down.
+    pub fn with_alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+
+    /// Whether the shortcut was pressed during this frame, with exactly its
+    /// modifiers held down.
+    ///
+    /// Only available with the `ui` feature.
+    #[cfg(feature = "ui")]
+    pub fn pressed(&self, input: &egui::InputState) -> bool {
+        let Some(key) = egui::Key::from_name(&self.key) else {
+            return false;
+        };
+        input.modifiers.ctrl == self.ctrl
+            && input.modifiers.shift == self.shift
+            && input.modifiers.alt == self.alt
+            && input.key_pressed(key)
+    }
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// Error parsing a [`Shortcut`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid shortcut: {0}")]
+pub struct ParseShortcutError(String);
+
+impl FromStr for Shortcut {
+    type Err = ParseShortcutError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = match parts.pop() {
+            Some(key) if !key.is_empty() => key,
+            _ => return Err(ParseShortcutError(s.to_string())),
+        };
+        let mut shortcut = Self::new(key.to_string());
+        // A single letter is named in upper case
+        if key.chars().count() == 1 {
+            shortcut.key = key.to_uppercase();
+        }
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "cmd" | "control" => shortcut.ctrl = true,
+                "shift" => shortcut.shift = true,
+                "alt" | "option" => shortcut.alt = true,
+                _ => return Err(ParseShortcutError(s.to_string())),
+            }
+        }
+        Ok(shortcut)
+    }
+}
+
+impl TryFrom<String> for Shortcut {
+    type Error = ParseShortcutError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Shortcut> for String {
+    fn from(shortcut: Shortcut) -> Self {
+        shortcut.to_string()
+    }
+}
+
+/// Shortcuts the user bound commands to, in place of their defaults.
+///
+/// Serialized as a table of command identifiers to shortcuts, an empty
+/// shortcut leaving the command unbound:
+///
+/// ```toml
+/// [bindings]
+/// "editor.reflow" = "Ctrl+Shift+Q"
+/// "editor.toggle_focus_mode" = ""
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keymap {
+    /// Shortcut of each rebound command, `None` when unbound
+    #[serde(default, with = "bindings")]
+    bindings: BTreeMap<String, Option<Shortcut>>,
+}
+
+impl Keymap {
+    /// Shortcut `command` is bound to.
+    pub fn shortcut(&self, command: &Command) -> Option<Shortcut> {
+        match self.bindings.get(&command.id) {
+            Some(shortcut) => shortcut.clone(),
+            None => command.default_shortcut.clone(),
+        }
+    }
+
+    /// Bind `command` to `shortcut`, or leave it unbound with `None`.
+    pub fn bind(&mut self, command: &Command, shortcut: Option<Shortcut>) {
+        if shortcut == command.default_shortcut {
+            self.bindings.remove(&command.id);
+        } else {
+            self.bindings.insert(command.id.clone(), shortcut);
+        }
+    }
+
+    /// Bind the command `id` to its default shortcut again.
+    pub fn reset(&mut self, id: &str) {
+        self.bindings.remove(id);
+    }
+
+    /// Whether the command `id` is not bound to its default shortcut.
+    pub fn is_rebound(&self, id: &str) -> bool {
+        self.bindings.contains_key(id)
+    }
+
+    /// Identifier of the first of `commands` bound to `shortcut`.
+    pub fn command_for<'a>(&self, commands: &'a [Command], shortcut: &Shortcut) -> Option<&'a str> {
+        commands
+            .iter()
+            .find(|command| self.shortcut(command).as_ref() == Some(shortcut))
+            .map(|command| command.id.as_str())
+    }
+
+    /// Shortcuts bound to several of `commands`, with the identifiers of
+    /// those commands, in order of first binding.
+    pub fn conflicts(&self, commands: &[Command]) -> Vec<(Shortcut, Vec<String>)> {
+        let mut bound: Vec<(Shortcut, Vec<String>)> = Vec::new();
+        for command in commands {
+            let Some(shortcut) = self.shortcut(command) else {
+                continue;
+            };
+            match bound.iter_mut().find(|(s, _)| *s == shortcut) {
+                Some((_, ids)) => ids.push(command.id.clone()),
+                None => bound.push((shortcut, vec![command.id.clone()])),
+            }
+        }
+        bound.retain(|(_, ids)| ids.len() > 1);
+        bound
+    }
+}
+
+/// Serialization of the keymap bindings, an unbound command being written
+/// as an empty shortcut.
+mod bindings {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        bindings: &BTreeMap<String, Option<Shortcut>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(bindings.iter().map(|(id, shortcut)| {
+            let text = shortcut
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            (id, text)
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<String, Option<Shortcut>>, D::Error> {
+        let texts = BTreeMap::<String, String>::deserialize(deserializer)?;
+        texts
+            .into_iter()
+            .map(|(id, text)| {
+                let shortcut = match text.trim() {
+                    "" => None,
+                    text => Some(text.parse().map_err(serde::de::Error::custom)?),
+                };
+                Ok((id, shortcut))
+            })
+            .collect()
+    }
+}
+
+/// Commands registered in a [`PluginContext`], in registration order.
+#[derive(Default)]
+pub(crate) struct CommandRegistry {
+    commands: Vec<Command>,
+    handlers: std::collections::HashMap<String, CommandHandler>,
+}
+
+impl CommandRegistry {
+    /// Register `command`, replacing the one with the same identifier.
+    pub(crate) fn register(&mut self, command: Command, handler: CommandHandler) {
+        self.handlers.insert(command.id.clone(), handler);
+        match self.commands.iter_mut().find(|c| c.id == command.id) {
+            Some(existing) => *existing = command,
+            None => self.commands.push(command),
+        }
+    }
+
+    pub(crate) fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    pub(crate) fn get(&self, id: &str) -> Option<&Command> {
+        self.commands.iter().find(|c| c.id == id)
+    }
+
+    pub(crate) fn handler(&self, id: &str) -> Option<CommandHandler> {
+        self.handlers.get(id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shortcut_parse_and_display() {
+        let shortcut: Shortcut = "ctrl+shift+f".parse().unwrap();
+        assert_eq!(shortcut, Shortcut::ctrl("F").with_shift());
+        assert_eq!(shortcut.to_string(), "Ctrl+Shift+F");
+        assert_eq!("Alt+Left".parse(), Ok(Shortcut::new("Left").with_alt()));
+        assert_eq!("F11".parse(), Ok(Shortcut::new("F11")));
+        assert!("Ctrl+".parse::<Shortcut>().is_err());
+        assert!("Hyper+K".parse::<Shortcut>().is_err());
+    }
+
+    #[test]
+    fn test_register_replaces_command() {
+        let mut ctx = PluginContext::new();
+        ctx.register_command("demo.run", "Run", None, |ctx: &mut PluginContext| {
+            ctx.set_shared_state("ran", 1)
+        });
+        ctx.register_command(
+            "demo.run",
+            "Run Again",
+            Some(Shortcut::ctrl("R")),
+            |ctx: &mut PluginContext| ctx.set_shared_state("ran", 2),
+        );
+        assert_eq!(ctx.commands().len(), 1);
+        assert_eq!(ctx.command("demo.run").unwrap().title, "Run Again");
+        assert!(ctx.run_command("demo.run"));
+        assert_eq!(ctx.get_shared_state::<i32>("ran"), Some(2));
+        assert!(!ctx.run_command("demo.missing"));
+    }
+
+    #[test]
+    fn test_keymap_rebinds_and_detects_conflicts() {
+        let command = |id: &str, shortcut: Option<Shortcut>| Command {
+            id: id.to_string(),
+            title: id.to_string(),
+            default_shortcut: shortcut,
+        };
+        let commands = vec![
+            command("editor.undo", Some(Shortcut::ctrl("Z"))),
+            command("editor.redo", Some(Shortcut::ctrl("Y"))),
+            command("editor.reflow", Some(Shortcut::new("Q").with_alt())),
+        ];
+        let mut keymap = Keymap::default();
+        assert!(keymap.conflicts(&commands).is_empty());
+
+        keymap.bind(&commands[1], Some(Shortcut::ctrl("Z")));
+        keymap.bind(&commands[2], None);
+        assert_eq!(keymap.shortcut(&commands[2]), None);
+        assert_eq!(
+            keymap.conflicts(&commands),
+            vec![(
+                Shortcut::ctrl("Z"),
+                vec!["editor.undo".to_string(), "editor.redo".to_string()]
+            )]
+        );
+        assert_eq!(
+            keymap.command_for(&commands, &Shortcut::ctrl("Z")),
+            Some("editor.undo")
+        );
+
+        let json = serde_json::to_string(&keymap).unwrap();
+        assert_eq!(
+            json,
+            r#"{"bindings":{"editor.redo":"Ctrl+Z","editor.reflow":""}}"#
+        );
+        assert_eq!(serde_json::from_str::<Keymap>(&json).unwrap(), keymap);
+
+        // Binding the default shortcut again forgets the binding
+        keymap.bind(&commands[1], Some(Shortcut::ctrl("Y")));
+        keymap.reset("editor.reflow");
+        assert_eq!(keymap, Keymap::default());
+    }
+}
@@ -4,12 +4,24 @@
 //! with the Cosmarium core and other plugins. It provides access to shared state,
 //! event system, configuration, and other core services.
 
-use crate::{Event, EventHandler};
+use crate::clock::{Clock, IdSource};
+use crate::command::{Command, CommandHandler, CommandRegistry, Keymap, Shortcut};
+use crate::snapshot::{ProjectSnapshot, PROJECT_SNAPSHOT_KEY, PROJECT_SNAPSHOT_REQUEST};
+use crate::subscription::{EventBusLink, EventFilter, Subscription};
+use crate::task::{TaskHandle, TaskProgress, TaskSpawner};
+use crate::{Event, EventHandler, EventType};
 use serde::{Deserialize, Serialize};
 use std::any::{Any, TypeId};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
+/// Plugin data key (`serde_json::Value`) of the state a plugin keeps from
+/// one session to the next.
+///
+/// The application sets it from the user session before initializing the
+/// plugin, and saves what the plugin left under it when it exits.
+pub const SESSION_STATE_KEY: &str = "session_state";
+
 /// Context object providing plugins access to core services and shared state.
 ///
 /// The plugin context acts as the main communication channel between plugins
@@ -48,6 +60,18 @@ pub struct PluginContext {
     plugin_data: HashMap<String, HashMap<String, Box<dyn Any + Send + Sync>>>,
     /// Path to the currently active project (if any)
     project_path: Arc<RwLock<Option<std::path::PathBuf>>>,
+    /// Connection to the application event bus
+    event_bus: Option<EventBusLink>,
+    /// Spawner of the shared async executor
+    task_spawner: Option<TaskSpawner>,
+    /// Source of the current time
+    clock: Clock,
+    /// Source of new identifiers
+    ids: IdSource,
+    /// Commands exposed to the command palette and keymap
+    commands: CommandRegistry,
+    /// Shortcuts the user bound commands to
+    keymap: Keymap,
 }
 
 impl PluginContext {
@@ -67,6 +91,12 @@ impl PluginContext {
             config: HashMap::new(),
             plugin_data: HashMap::new(),
             project_path: Arc::new(RwLock::new(None)),
+            event_bus: None,
+            task_spawner: None,
+            clock: Clock::system(),
+            ids: IdSource::random(),
+            commands: CommandRegistry::default(),
+            keymap: Keymap::default(),
         }
     }
 
@@ -110,6 +140,10 @@ impl PluginContext {
 
     /// Emit an event to be handled by registered handlers.
     ///
+    /// Handlers registered on this context run immediately; subscribers on
+    /// the application event bus receive the event when the bus next
+    /// processes its queue.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -120,6 +154,7 @@ impl PluginContext {
     /// ctx.emit_event(event);
     /// ```
     pub fn emit_event(&mut self, event: Event) {
+        let event = event.with_stamp(self.ids.next_id(), self.clock.now());
         let event_type = format!("{:?}", event.event_type());
         if let Some(handlers) = self.event_handlers.get_mut(&event_type) {
             for handler in handlers.iter_mut() {
@@ -128,6 +163,217 @@ impl PluginContext {
                 }
             }
         }
+        if let Some(bus) = &self.event_bus {
+            bus.publish(event);
+        }
+    }
+
+    /// Connect this context to the application event bus.
+    pub fn connect_event_bus(&mut self, link: EventBusLink) {
+        self.event_bus = Some(link);
+    }
+
+    /// Clock plugins should read the time from, to measure delays and date
+    /// what they record.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::clock::Clock;
+    /// use cosmarium_plugin_api::PluginContext;
+    /// use std::time::Duration;
+    ///
+    /// let mut ctx = PluginContext::new();
+    /// ctx.set_clock(Clock::manual(Clock::EPOCH));
+    /// let edited = ctx.clock().instant();
+    /// ctx.clock().advance(Duration::from_secs(3));
+    /// assert_eq!(ctx.clock().since(edited), Duration::from_secs(3));
+    /// ```
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Replace the system clock, typically by a manual one in tests.
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+    }
+
+    /// Draw a new unique identifier.
+    pub fn new_id(&self) -> uuid::Uuid {
+        self.ids.next_id()
+    }
+
+    /// Replace the random identifiers by another source, typically a seeded
+    /// one in tests.
+    pub fn set_id_source(&mut self, ids: IdSource) {
+        self.ids = ids;
+    }
+
+    /// Connect this context to the application's shared async executor.
+    pub fn connect_task_spawner(&mut self, spawner: TaskSpawner) {
+        self.task_spawner = Some(spawner);
+    }
+
+    /// Spawner of the shared async executor, if connected.
+    pub fn task_spawner(&self) -> Option<&TaskSpawner> {
+        self.task_spawner.as_ref()
+    }
+
+    /// Run a future on the shared executor.
+    ///
+    /// Poll the returned handle from `update` or `render_panel` to get the
+    /// result on the UI thread. When no executor is connected, the handle
+    /// reports an error.
+    pub fn spawn<F>(&self, future: F) -> TaskHandle<F::Output>
+    where
+        F: std::future::Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match &self.task_spawner {
+            Some(spawner) => spawner.spawn(future),
+            None => {
+                tracing::debug!("No executor connected, task not started");
+                TaskHandle::failed()
+            }
+        }
+    }
+
+    /// Run blocking work on the shared executor's blocking thread pool.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::{PluginContext, TaskSpawner};
+    ///
+    /// let runtime = tokio::runtime::Runtime::new().unwrap();
+    /// let mut ctx = PluginContext::new();
+    /// ctx.connect_task_spawner(TaskSpawner::new(runtime.handle().clone()));
+    ///
+    /// let task = ctx.spawn_blocking(|| std::fs::read_dir(".").is_ok());
+    /// assert!(task.wait().unwrap());
+    /// ```
+    pub fn spawn_blocking<F, T>(&self, work: F) -> TaskHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        match &self.task_spawner {
+            Some(spawner) => spawner.spawn_blocking(work),
+            None => {
+                tracing::debug!("No executor connected, task not started");
+                TaskHandle::failed()
+            }
+        }
+    }
+
+    /// Run a long operation in the background, with progress reporting and
+    /// cancellation.
+    ///
+    /// The task shows up in the application's task list under `name`. The
+    /// work should report through the given [`TaskProgress`] and return early
+    /// once [`TaskProgress::check_cancelled`] fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::{PluginContext, TaskSpawner};
+    ///
+    /// let runtime = tokio::runtime::Runtime::new().unwrap();
+    /// let mut ctx = PluginContext::new();
+    /// ctx.connect_task_spawner(TaskSpawner::new(runtime.handle().clone()));
+    ///
+    /// let task = ctx.spawn_task("Index documents", |progress| {
+    ///     for i in 0..10 {
+    ///         progress.check_cancelled()?;
+    ///         progress.set_position(i, 10);
+    ///     }
+    ///     Ok(10)
+    /// });
+    /// assert_eq!(task.wait().unwrap(), 10);
+    /// ```
+    pub fn spawn_task<F, T>(&self, name: impl Into<String>, work: F) -> TaskHandle<T>
+    where
+        F: FnOnce(&TaskProgress) -> crate::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        match &self.task_spawner {
+            Some(spawner) => spawner.spawn_task(name, work),
+            None => {
+                tracing::debug!("No executor connected, task not started");
+                TaskHandle::failed()
+            }
+        }
+    }
+
+    /// Subscribe to events of a given type on the application event bus.
+    ///
+    /// Events are delivered asynchronously, after the call that emitted
+    /// them. The subscription lasts as long as the returned handle; when the
+    /// context is not connected to a bus, the handle never receives events.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::{Event, EventType, PluginContext};
+    ///
+    /// let mut ctx = PluginContext::new();
+    /// let subscription = ctx.subscribe(EventType::ProjectOpened, |event: &Event| {
+    ///     println!("Opened {}", event.data());
+    ///     Ok(())
+    /// });
+    /// assert_eq!(subscription.event_type(), EventType::ProjectOpened);
+    /// ```
+    pub fn subscribe<H: EventHandler + 'static>(
+        &mut self,
+        event_type: EventType,
+        handler: H,
+    ) -> Subscription {
+        self.subscribe_with(event_type, None, Box::new(handler))
+    }
+
+    /// Subscribe to the events of a given type accepted by `filter`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::{Event, EventType, PluginContext};
+    ///
+    /// let mut ctx = PluginContext::new();
+    /// let _autosaves = ctx.subscribe_filtered(
+    ///     EventType::DocumentSaved,
+    ///     |event: &Event| event.get_metadata("source") == Some("autosave"),
+    ///     |_: &Event| Ok(()),
+    /// );
+    /// ```
+    pub fn subscribe_filtered<F, H>(
+        &mut self,
+        event_type: EventType,
+        filter: F,
+        handler: H,
+    ) -> Subscription
+    where
+        F: Fn(&Event) -> bool + Send + Sync + 'static,
+        H: EventHandler + 'static,
+    {
+        self.subscribe_with(event_type, Some(Box::new(filter)), Box::new(handler))
+    }
+
+    fn subscribe_with(
+        &mut self,
+        event_type: EventType,
+        filter: Option<EventFilter>,
+        handler: Box<dyn EventHandler>,
+    ) -> Subscription {
+        match &self.event_bus {
+            Some(bus) => bus.subscribe(event_type, filter, handler),
+            None => {
+                tracing::debug!(
+                    "No event bus connected, {:?} subscription is inactive",
+                    event_type
+                );
+                Subscription::detached(event_type)
+            }
+        }
     }
 
     /// Register an event handler for a specific event type.
@@ -260,6 +506,108 @@ impl PluginContext {
     pub fn project_path(&self) -> Option<std::path::PathBuf> {
         self.project_path.read().ok().and_then(|lock| lock.clone())
     }
+
+    /// Latest snapshot of the active project published by the application.
+    ///
+    /// It may predate the latest edits; see
+    /// [`PluginContext::request_project_snapshot`] and
+    /// [`ProjectSnapshot::taken_at`].
+    pub fn project_snapshot(&self) -> Option<Arc<ProjectSnapshot>> {
+        self.get_shared_state(PROJECT_SNAPSHOT_KEY)
+    }
+
+    /// Ask the application for a fresh snapshot of the active project,
+    /// published before the next update of the plugins.
+    pub fn request_project_snapshot(&mut self) {
+        self.set_shared_state(PROJECT_SNAPSHOT_REQUEST, true);
+    }
+
+    /// Register a command shown in the command palette and bound to
+    /// `default_shortcut` in the keymap, replacing any command registered
+    /// with the same `id`.
+    ///
+    /// The `handler` runs on the application thread, between plugin
+    /// updates; it usually sets a shared state request that the plugin
+    /// serves on its next update.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::command::Shortcut;
+    /// use cosmarium_plugin_api::PluginContext;
+    ///
+    /// let mut ctx = PluginContext::new();
+    /// ctx.register_command(
+    ///     "outline.refresh",
+    ///     "Refresh Outline",
+    ///     Some(Shortcut::ctrl("R").with_shift()),
+    ///     |ctx: &mut PluginContext| ctx.set_shared_state("outline_refresh_request", true),
+    /// );
+    /// assert_eq!(ctx.commands()[0].title, "Refresh Outline");
+    /// ```
+    pub fn register_command<F>(
+        &mut self,
+        id: impl Into<String>,
+        title: impl Into<String>,
+        default_shortcut: Option<Shortcut>,
+        handler: F,
+    ) where
+        F: Fn(&mut PluginContext) + Send + Sync + 'static,
+    {
+        let command = Command {
+            id: id.into(),
+            title: title.into(),
+            default_shortcut,
+        };
+        let handler: CommandHandler = Arc::new(handler);
+        self.commands.register(command, handler);
+    }
+
+    /// Registered commands, in registration order.
+    pub fn commands(&self) -> &[Command] {
+        self.commands.commands()
+    }
+
+    /// The command registered with `id`.
+    pub fn command(&self, id: &str) -> Option<&Command> {
+        self.commands.get(id)
+    }
+
+    /// Identifier of the command bound to `shortcut` in the keymap.
+    pub fn command_for_shortcut(&self, shortcut: &Shortcut) -> Option<&str> {
+        self.keymap.command_for(self.commands(), shortcut)
+    }
+
+    /// Shortcut the command `id` is bound to in the keymap.
+    pub fn shortcut(&self, id: &str) -> Option<Shortcut> {
+        self.command(id)
+            .and_then(|command| self.keymap.shortcut(command))
+    }
+
+    /// Shortcuts the user bound commands to.
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// Replace the shortcuts the user bound commands to.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    /// Run the command registered with `id`; returns `false` if there is
+    /// none.
+    pub fn run_command(&mut self, id: &str) -> bool {
+        match self.commands.handler(id) {
+            Some(handler) => {
+                handler(self);
+                true
+            }
+            None => {
+                tracing::warn!("No command registered as {}", id);
+                false
+            }
+        }
+    }
 }
 
 impl Default for PluginContext {
@@ -445,6 +793,19 @@ mod tests {
         assert_eq!(ctx.get_config::<i32>("font_size"), None);
     }
 
+    #[test]
+    fn test_emit_event_publishes_to_bus() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut ctx = PluginContext::new();
+        ctx.connect_event_bus(EventBusLink::new(sender));
+
+        let subscription = ctx.subscribe(EventType::DocumentChanged, |_: &Event| Ok(()));
+        ctx.emit_event(Event::new(EventType::DocumentChanged, "edited"));
+        drop(subscription);
+
+        assert_eq!(receiver.try_iter().count(), 3);
+    }
+
     #[test]
     fn test_plugin_context_plugin_data() {
         let mut ctx = PluginContext::new();
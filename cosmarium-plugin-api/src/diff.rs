@@ -0,0 +1,288 @@
+//! Lines that changed between two versions of a text.
+//!
+//! The lines kept are those of a longest common subsequence of the two
+//! texts, around which the lines removed and added are listed. Past
+//! [`MAX_COMPARED`] pairs of lines, what lies between the lines both texts
+//! start and end with is shown as removed, then added. A [`Comparison`]
+//! holds the changes ready to show, long runs of unchanged lines folded.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::diff::{diff_lines, LineChange};
+//!
+//! let changes = diff_lines("It rained.\nThe inn was full.", "It rained.\nThe inn was empty.");
+//! assert_eq!(
+//!     changes,
+//!     [
+//!         LineChange::Same("It rained."),
+//!         LineChange::Removed("The inn was full."),
+//!         LineChange::Added("The inn was empty."),
+//!     ]
+//! );
+//! ```
+
+/// Pairs of lines compared at most, past which a block of lines that
+/// changed is not looked into.
+pub const MAX_COMPARED: usize = 4_000_000;
+
+/// A line of one text or the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChange<'a> {
+    /// In both texts
+    Same(&'a str),
+    /// In the older text only
+    Removed(&'a str),
+    /// In the newer text only
+    Added(&'a str),
+}
+
+/// A row of a diff whose long runs of unchanged lines are folded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffRow {
+    Same(String),
+    Removed(String),
+    Added(String),
+    /// Number of unchanged lines not shown
+    Folded(usize),
+}
+
+/// Lines of `before` and `after`, in order, with those removed listed before
+/// those added in their place.
+pub fn diff_lines<'a>(before: &'a str, after: &'a str) -> Vec<LineChange<'a>> {
+    let before: Vec<&str> = before.lines().collect();
+    let after: Vec<&str> = after.lines().collect();
+    let prefix = before
+        .iter()
+        .zip(&after)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = before[prefix..]
+        .iter()
+        .rev()
+        .zip(after[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &before[prefix..before.len() - suffix];
+    let new = &after[prefix..after.len() - suffix];
+
+    let mut changes: Vec<LineChange> = before[..prefix]
+        .iter()
+        .map(|line| LineChange::Same(line))
+        .collect();
+    if old.len().saturating_mul(new.len()) > MAX_COMPARED {
+        changes.extend(old.iter().map(|line| LineChange::Removed(line)));
+        changes.extend(new.iter().map(|line| LineChange::Added(line)));
+    } else {
+        compare(old, new, &mut changes);
+    }
+    changes.extend(
+        before[before.len() - suffix..]
+            .iter()
+            .map(|line| LineChange::Same(line)),
+    );
+    changes
+}
+
+/// Add the changes from `old` to `new` to `changes`, keeping a longest
+/// common subsequence of their lines.
+fn compare<'a>(old: &[&'a str], new: &[&'a str], changes: &mut Vec<LineChange<'a>>) {
+    // Length of the longest common subsequence of `old[i..]` and `new[j..]`
+    let width = new.len() + 1;
+    let mut common = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i * width + j] = if old[i] == new[j] {
+                common[(i + 1) * width + j + 1] + 1
+            } else {
+                common[(i + 1) * width + j].max(common[i * width + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            changes.push(LineChange::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if j == new.len()
+            || i < old.len() && common[(i + 1) * width + j] >= common[i * width + j + 1]
+        {
+            changes.push(LineChange::Removed(old[i]));
+            i += 1;
+        } else {
+            changes.push(LineChange::Added(new[j]));
+            j += 1;
+        }
+    }
+}
+
+/// Lines removed and added among `changes`.
+pub fn counts(changes: &[LineChange]) -> (usize, usize) {
+    changes
+        .iter()
+        .fold((0, 0), |(removed, added), change| match change {
+            LineChange::Same(_) => (removed, added),
+            LineChange::Removed(_) => (removed + 1, added),
+            LineChange::Added(_) => (removed, added + 1),
+        })
+}
+
+/// Rows of `changes`, showing `context` unchanged lines around the lines
+/// that changed and folding the others.
+pub fn fold_unchanged(changes: &[LineChange], context: usize) -> Vec<DiffRow> {
+    let mut rows = Vec::new();
+    let mut i = 0;
+    while i < changes.len() {
+        if !matches!(changes[i], LineChange::Same(_)) {
+            rows.push(row(&changes[i]));
+            i += 1;
+            continue;
+        }
+        let run = changes[i..]
+            .iter()
+            .take_while(|change| matches!(change, LineChange::Same(_)))
+            .count();
+        // No context before the first change, nor after the last
+        let shown_before = if i == 0 { 0 } else { context };
+        let shown_after = if i + run == changes.len() { 0 } else { context };
+        if run > shown_before + shown_after {
+            rows.extend(changes[i..i + shown_before].iter().map(row));
+            rows.push(DiffRow::Folded(run - shown_before - shown_after));
+            rows.extend(changes[i + run - shown_after..i + run].iter().map(row));
+        } else {
+            rows.extend(changes[i..i + run].iter().map(row));
+        }
+        i += run;
+    }
+    rows
+}
+
+/// The lines changed from one text to another, ready to show.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comparison {
+    pub rows: Vec<DiffRow>,
+    /// Lines removed
+    pub removed: usize,
+    /// Lines added
+    pub added: usize,
+}
+
+impl Comparison {
+    /// Changes from `before` to `after`, with `context` unchanged lines
+    /// shown around each.
+    pub fn new(before: &str, after: &str, context: usize) -> Self {
+        let changes = diff_lines(before, after);
+        let (removed, added) = counts(&changes);
+        Self {
+            rows: fold_unchanged(&changes, context),
+            removed,
+            added,
+        }
+    }
+
+    /// Whether no line changed.
+    pub fn is_unchanged(&self) -> bool {
+        self.removed + self.added == 0
+    }
+
+    /// Show the rows, removed lines struck through, in a scroll area.
+    #[cfg(feature = "ui")]
+    pub fn show(&self, ui: &mut egui::Ui, id_salt: &str) {
+        use egui::RichText;
+
+        let added_color = if ui.visuals().dark_mode {
+            egui::Color32::LIGHT_GREEN
+        } else {
+            egui::Color32::DARK_GREEN
+        };
+        let removed_color = ui.visuals().error_fg_color;
+        egui::ScrollArea::both().id_salt(id_salt).show(ui, |ui| {
+            for row in &self.rows {
+                match row {
+                    DiffRow::Same(line) => {
+                        ui.label(RichText::new(format!("  {}", line)).monospace());
+                    }
+                    DiffRow::Removed(line) => {
+                        ui.label(
+                            RichText::new(format!("− {}", line))
+                                .monospace()
+                                .strikethrough()
+                                .color(removed_color),
+                        );
+                    }
+                    DiffRow::Added(line) => {
+                        ui.label(
+                            RichText::new(format!("+ {}", line))
+                                .monospace()
+                                .color(added_color),
+                        );
+                    }
+                    DiffRow::Folded(count) => {
+                        ui.weak(format!("⋯ {} unchanged lines", count));
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Row showing `change`.
+fn row(change: &LineChange) -> DiffRow {
+    match change {
+        LineChange::Same(line) => DiffRow::Same(line.to_string()),
+        LineChange::Removed(line) => DiffRow::Removed(line.to_string()),
+        LineChange::Added(line) => DiffRow::Added(line.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_keeps_common_lines() {
+        let before = "a\nb\nc\nd\ne";
+        let after = "a\nc\nd\nx\ne\nf";
+        let changes = diff_lines(before, after);
+        assert_eq!(
+            changes,
+            [
+                LineChange::Same("a"),
+                LineChange::Removed("b"),
+                LineChange::Same("c"),
+                LineChange::Same("d"),
+                LineChange::Added("x"),
+                LineChange::Same("e"),
+                LineChange::Added("f"),
+            ]
+        );
+        assert_eq!(counts(&changes), (1, 2));
+        assert!(diff_lines("same", "same")
+            .iter()
+            .all(|change| matches!(change, LineChange::Same(_))));
+        assert_eq!(diff_lines("", "new"), [LineChange::Added("new")]);
+    }
+
+    #[test]
+    fn test_unchanged_runs_are_folded() {
+        let before: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        let mut after = before.clone();
+        after[10] = "ten".to_string();
+        let (before, after) = (before.join("\n"), after.join("\n"));
+        let changes = diff_lines(&before, &after);
+        let rows = fold_unchanged(&changes, 2);
+        assert_eq!(rows[0], DiffRow::Folded(8));
+        assert_eq!(rows[1], DiffRow::Same("8".to_string()));
+        assert_eq!(rows[3], DiffRow::Removed("10".to_string()));
+        assert_eq!(rows[4], DiffRow::Added("ten".to_string()));
+        assert_eq!(rows[7], DiffRow::Folded(7));
+        assert_eq!(rows.len(), 8);
+
+        let comparison = Comparison::new(&before, &after, 2);
+        assert_eq!((comparison.removed, comparison.added), (1, 1));
+        assert_eq!(comparison.rows, rows);
+        assert!(Comparison::new(&before, &before, 2).is_unchanged());
+    }
+}
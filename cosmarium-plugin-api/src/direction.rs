@@ -0,0 +1,374 @@
+//! Direction and alignment of paragraphs.
+//!
+//! Manuscripts can mix left-to-right and right-to-left paragraphs, as
+//! multilingual fiction and quoted passages do. A paragraph takes the
+//! direction of its first strong character, as in the Unicode bidirectional
+//! algorithm: Hebrew or Arabic letters make it right-to-left, Latin letters
+//! left-to-right. A marker on the line before a paragraph, written as an
+//! HTML comment that other Markdown tools leave out, overrides its direction
+//! or sets its alignment:
+//!
+//! ```markdown
+//! <!-- dir=rtl align=center -->
+//! A centered paragraph, laid out right-to-left.
+//! ```
+//!
+//! Exporters writing HTML wrap such paragraphs in `<div>` elements with
+//! [`wrap_paragraphs`]; the others read the markers with
+//! [`ParagraphFormat::parse_marker`] and detect directions with [`detect`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::direction::{paragraphs, Alignment, Direction};
+//!
+//! let markdown = "It began.\n\nשלום.\n\n<!-- align=center -->\nThe end.";
+//! let paragraphs = paragraphs(markdown);
+//!
+//! assert_eq!(paragraphs[0].direction, Direction::Ltr);
+//! assert_eq!(paragraphs[1].direction, Direction::Rtl);
+//! assert_eq!(paragraphs[2].marker, Some(4));
+//! assert_eq!(paragraphs[2].format.alignment, Some(Alignment::Center));
+//! ```
+
+use std::ops::Range;
+
+/// Direction of the text of a paragraph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Direction {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+impl Direction {
+    /// Value of the direction in markers and HTML `dir` attributes.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ltr => "ltr",
+            Self::Rtl => "rtl",
+        }
+    }
+
+    /// Human-readable name for menus.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Ltr => "Left-to-Right",
+            Self::Rtl => "Right-to-Left",
+        }
+    }
+}
+
+/// Alignment of the lines of a paragraph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+    Justify,
+}
+
+impl Alignment {
+    /// All alignments, in menu order.
+    pub const ALL: [Alignment; 4] = [Self::Left, Self::Center, Self::Right, Self::Justify];
+
+    /// Value of the alignment in markers and CSS `text-align` properties.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Left => "left",
+            Self::Center => "center",
+            Self::Right => "right",
+            Self::Justify => "justify",
+        }
+    }
+}
+
+/// Direction and alignment set by the marker of a paragraph; `None` leaves
+/// the paragraph's own.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ParagraphFormat {
+    pub direction: Option<Direction>,
+    pub alignment: Option<Alignment>,
+}
+
+impl ParagraphFormat {
+    /// Format written by a marker line, such as `<!-- dir=rtl -->`.
+    ///
+    /// Comments holding anything else than `dir` and `align` settings are
+    /// not markers.
+    pub fn parse_marker(line: &str) -> Option<Self> {
+        let settings = line
+            .trim()
+            .strip_prefix("<!--")?
+            .strip_suffix("-->")?
+            .trim();
+        let mut format = Self::default();
+        for setting in settings.split_whitespace() {
+            match setting.split_once('=')? {
+                ("dir", "ltr") => format.direction = Some(Direction::Ltr),
+                ("dir", "rtl") => format.direction = Some(Direction::Rtl),
+                ("dir", "auto") => format.direction = None,
+                ("align", value) => {
+                    format.alignment = Some(*Alignment::ALL.iter().find(|a| a.as_str() == value)?);
+                }
+                _ => return None,
+            }
+        }
+        (!settings.is_empty()).then_some(format)
+    }
+
+    /// Marker line writing this format, if it sets anything.
+    pub fn marker(&self) -> Option<String> {
+        let mut settings = Vec::new();
+        if let Some(direction) = self.direction {
+            settings.push(format!("dir={}", direction.as_str()));
+        }
+        if let Some(alignment) = self.alignment {
+            settings.push(format!("align={}", alignment.as_str()));
+        }
+        (!settings.is_empty()).then(|| format!("<!-- {} -->", settings.join(" ")))
+    }
+
+    /// Whether the format leaves the paragraph as it is.
+    pub fn is_empty(&self) -> bool {
+        self.direction.is_none() && self.alignment.is_none()
+    }
+
+    /// Direction of a paragraph of this format with `text`.
+    pub fn resolve(&self, text: &str) -> Direction {
+        self.direction.or_else(|| detect(text)).unwrap_or_default()
+    }
+}
+
+/// Direction of the first strong character of `text`, if any.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_plugin_api::direction::{detect, Direction};
+///
+/// assert_eq!(detect("« مرحبا » she said"), Some(Direction::Rtl));
+/// assert_eq!(detect("— 1984"), None);
+/// ```
+pub fn detect(text: &str) -> Option<Direction> {
+    text.chars().find_map(|c| {
+        if is_rtl(c) {
+            Some(Direction::Rtl)
+        } else if c == '\u{200e}' || (c.is_alphabetic() && !is_neutral_rtl_block(c)) {
+            Some(Direction::Ltr)
+        } else {
+            None
+        }
+    })
+}
+
+/// Whether `c` is a strong right-to-left character.
+fn is_rtl(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200f}'
+            | '\u{0590}'..='\u{08ff}'
+            | '\u{fb1d}'..='\u{fdff}'
+            | '\u{fe70}'..='\u{feff}'
+            | '\u{10800}'..='\u{10fff}'
+            | '\u{1e800}'..='\u{1efff}'
+    ) && !is_neutral_rtl_block(c)
+}
+
+/// Digits and marks of the right-to-left blocks, which do not set a
+/// direction.
+fn is_neutral_rtl_block(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0591}'..='\u{05bd}'
+            | '\u{0600}'..='\u{0605}'
+            | '\u{0610}'..='\u{061a}'
+            | '\u{064b}'..='\u{0669}'
+            | '\u{06f0}'..='\u{06f9}'
+            | '\u{feff}'
+    )
+}
+
+/// A paragraph of a Markdown text, as a run of non-blank lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paragraph {
+    /// Lines of the paragraph, counted from 0
+    pub lines: Range<usize>,
+    /// Line of its marker, if it has one
+    pub marker: Option<usize>,
+    pub format: ParagraphFormat,
+    /// Direction, from the marker or detected
+    pub direction: Direction,
+}
+
+impl Paragraph {
+    /// Whether the paragraph is laid out otherwise than the left-to-right
+    /// default.
+    pub fn is_formatted(&self) -> bool {
+        self.direction == Direction::Rtl || !self.format.is_empty()
+    }
+
+    /// Attributes of an HTML element laying out the paragraph.
+    pub fn html_attributes(&self) -> String {
+        let mut attributes = String::new();
+        if self.direction == Direction::Rtl || self.format.direction.is_some() {
+            attributes.push_str(&format!(" dir=\"{}\"", self.direction.as_str()));
+        }
+        if let Some(alignment) = self.format.alignment {
+            attributes.push_str(&format!(" style=\"text-align: {}\"", alignment.as_str()));
+        }
+        attributes
+    }
+}
+
+/// Paragraphs of `markdown` outside of its front matter and code blocks.
+pub fn paragraphs(markdown: &str) -> Vec<Paragraph> {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let is_fence = |line: &str| {
+        let line = line.trim_start();
+        line.starts_with("```") || line.starts_with("~~~")
+    };
+
+    let mut i = 0;
+    if lines.first() == Some(&"---") {
+        i = lines[1..]
+            .iter()
+            .position(|line| *line == "---" || *line == "...")
+            .map_or(0, |end| end + 2);
+    }
+
+    let mut paragraphs = Vec::new();
+    let mut pending: Option<(usize, ParagraphFormat)> = None;
+    while i < lines.len() {
+        let line = lines[i];
+        if line.trim().is_empty() {
+            i += 1;
+        } else if let Some(format) = ParagraphFormat::parse_marker(line) {
+            pending = Some((i, format));
+            i += 1;
+        } else if is_fence(line) {
+            let fence = &line.trim_start()[..3];
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with(fence) {
+                i += 1;
+            }
+            i += 1;
+            pending = None;
+        } else {
+            let start = i;
+            while i < lines.len()
+                && !lines[i].trim().is_empty()
+                && !is_fence(lines[i])
+                && ParagraphFormat::parse_marker(lines[i]).is_none()
+            {
+                i += 1;
+            }
+            let (marker, format) = match pending.take() {
+                Some((line, format)) => (Some(line), format),
+                None => (None, ParagraphFormat::default()),
+            };
+            let text = lines[start..i].join("\n");
+            paragraphs.push(Paragraph {
+                lines: start..i,
+                marker,
+                direction: format.resolve(&text),
+                format,
+            });
+        }
+    }
+    paragraphs
+}
+
+/// `markdown` with its right-to-left and marked paragraphs wrapped in
+/// `<div>` elements laying them out, and their markers removed.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_plugin_api::direction::wrap_paragraphs;
+///
+/// assert_eq!(
+///     wrap_paragraphs("Hello.\n\n<!-- align=center -->\nשלום."),
+///     "Hello.\n\n<div dir=\"rtl\" style=\"text-align: center\">\n\nשלום.\n\n</div>\n"
+/// );
+/// ```
+pub fn wrap_paragraphs(markdown: &str) -> String {
+    let paragraphs = paragraphs(markdown);
+    if !paragraphs
+        .iter()
+        .any(|p| p.marker.is_some() || p.direction == Direction::Rtl)
+    {
+        return markdown.to_string();
+    }
+
+    let mut out = String::with_capacity(markdown.len());
+    let mut paragraphs = paragraphs.iter().peekable();
+    // Paragraph being wrapped
+    let mut open: Option<usize> = None;
+    for (i, line) in markdown.lines().enumerate() {
+        if paragraphs.peek().is_some_and(|p| p.marker == Some(i)) {
+            continue;
+        }
+        if let Some(paragraph) = paragraphs.next_if(|p| p.lines.start == i) {
+            // Indented lines and HTML blocks cannot be wrapped
+            let first = line.chars().next();
+            if paragraph.is_formatted() && first.is_some_and(|c| !c.is_whitespace() && c != '<') {
+                out.push_str(&format!("<div{}>\n\n", paragraph.html_attributes()));
+                open = Some(paragraph.lines.end);
+            }
+        }
+        out.push_str(line);
+        out.push('\n');
+        if open == Some(i + 1) {
+            out.push_str("\n</div>\n");
+            open = None;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markers() {
+        let format = ParagraphFormat::parse_marker(" <!-- dir=rtl  align=justify --> ").unwrap();
+        assert_eq!(format.direction, Some(Direction::Rtl));
+        assert_eq!(format.alignment, Some(Alignment::Justify));
+        assert_eq!(
+            format.marker().as_deref(),
+            Some("<!-- dir=rtl align=justify -->")
+        );
+
+        assert_eq!(
+            ParagraphFormat::parse_marker("<!-- check the inn -->"),
+            None
+        );
+        assert_eq!(ParagraphFormat::parse_marker("<!-- align=middle -->"), None);
+        assert_eq!(ParagraphFormat::parse_marker("<!-- -->"), None);
+        assert_eq!(ParagraphFormat::default().marker(), None);
+    }
+
+    #[test]
+    fn test_paragraphs_skip_front_matter_and_code() {
+        let markdown = "---\ntitle: שלום\n---\n<!-- dir=ltr -->\n\nשלום\nעולם\n\n```\nשלום\n```\n\n- one\n- שתיים";
+        let paragraphs = paragraphs(markdown);
+        assert_eq!(paragraphs.len(), 2);
+        assert_eq!(paragraphs[0].lines, 5..7);
+        assert_eq!(paragraphs[0].marker, Some(3));
+        assert_eq!(paragraphs[0].direction, Direction::Ltr);
+        assert_eq!(paragraphs[1].lines, 12..14);
+        assert_eq!(paragraphs[1].direction, Direction::Ltr);
+    }
+
+    #[test]
+    fn test_wrap_paragraphs() {
+        let markdown = "# Title\n\n<!-- dir=rtl -->\nHello\nworld\n\n    indented שלום\n";
+        assert_eq!(
+            wrap_paragraphs(markdown),
+            "# Title\n\n<div dir=\"rtl\">\n\nHello\nworld\n\n</div>\n\n    indented שלום\n"
+        );
+        assert_eq!(wrap_paragraphs("Plain text."), "Plain text.");
+    }
+}
@@ -0,0 +1,100 @@
+//! Labeled snapshots of a document.
+//!
+//! The author keeps the text of a document as it is under a label, "before
+//! the rewrite of chapter 3" for instance, to read it again later, compare
+//! it with the text now, or go back to it. The application keeps the
+//! snapshots of each document of a project in the project's `meta` folder.
+//!
+//! It publishes those of the active document under
+//! [`DOCUMENT_SNAPSHOTS_KEY`] and serves the [`DocumentSnapshotRequest`]s
+//! plugins send with [`DOCUMENT_SNAPSHOT_REQUEST`]. A snapshot is restored
+//! through the editor, as a single undo step, after the text it replaces is
+//! kept as a snapshot of its own.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::document_snapshot::{DocumentSnapshot, DocumentSnapshots};
+//!
+//! let older = DocumentSnapshot::new("First draft", "It rained.", chrono::Utc::now());
+//! let newer = DocumentSnapshot::new("Before rewrite", "It rained all night.", chrono::Utc::now());
+//! let snapshots = DocumentSnapshots {
+//!     path: "/novel/content/inn.md".into(),
+//!     snapshots: vec![newer.clone(), older],
+//! };
+//! assert_eq!(snapshots.get(newer.id), Some(&newer));
+//! assert_eq!(newer.words(), 4);
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Shared state key (`Option<Arc<DocumentSnapshots>>`) of the snapshots of
+/// the active document, `None` when it is not a file of the project.
+pub const DOCUMENT_SNAPSHOTS_KEY: &str = "document_snapshots";
+
+/// Shared state key (`Option<DocumentSnapshotRequest>`) of a change to the
+/// snapshots of the active document, served by the application.
+pub const DOCUMENT_SNAPSHOT_REQUEST: &str = "document_snapshot_request";
+
+/// The text of a document at one moment, kept under a label.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentSnapshot {
+    pub id: Uuid,
+    /// Name the author kept the snapshot under
+    pub label: String,
+    /// When the snapshot was taken
+    pub taken_at: DateTime<Utc>,
+    /// Text of the document, front matter included
+    pub content: String,
+}
+
+impl DocumentSnapshot {
+    /// Snapshot of `content` under `label`, taken at `taken_at`.
+    pub fn new(
+        label: impl Into<String>,
+        content: impl Into<String>,
+        taken_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            label: label.into(),
+            taken_at,
+            content: content.into(),
+        }
+    }
+
+    /// Words of the snapshot.
+    pub fn words(&self) -> usize {
+        self.content.split_whitespace().count()
+    }
+}
+
+/// Snapshots of a document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentSnapshots {
+    /// File of the document
+    pub path: PathBuf,
+    /// Snapshots of the document, newest first
+    pub snapshots: Vec<DocumentSnapshot>,
+}
+
+impl DocumentSnapshots {
+    /// The snapshot `id`, if the document has it.
+    pub fn get(&self, id: Uuid) -> Option<&DocumentSnapshot> {
+        self.snapshots.iter().find(|snapshot| snapshot.id == id)
+    }
+}
+
+/// Change to the snapshots of the active document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentSnapshotRequest {
+    /// Keep the text of the document now under this label
+    Take(String),
+    /// Replace the text of the document with that of a snapshot
+    Restore(Uuid),
+    /// Forget a snapshot
+    Delete(Uuid),
+}
@@ -0,0 +1,307 @@
+//! Epigraphs: the quotations opening chapters.
+//!
+//! A chapter declares its epigraph in the front matter of its document:
+//!
+//! ```markdown
+//! ---
+//! epigraph: "Not all those who wander are lost."
+//! epigraph_attribution: J. R. R. Tolkien
+//! ---
+//! ```
+//!
+//! Compiling writes it into the manuscript as a block quote, signed with a
+//! dash, between marker lines written as HTML comments. Markdown readers
+//! unaware of epigraphs show the quote; exporters writing HTML give it its
+//! own styling with [`wrap_epigraphs`], those writing Pandoc's Markdown with
+//! [`fence_epigraphs`], and the others read it with [`epigraphs`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::epigraph::{epigraphs, Epigraph};
+//!
+//! let document = "---\nepigraph: \"All is well.\"\nepigraph_attribution: Anon\n---\nIt rained.";
+//! let epigraph = Epigraph::from_front_matter(document).unwrap();
+//! assert_eq!(epigraph.text, "All is well.");
+//!
+//! let markdown = format!("# One\n\n{}\n\nIt rained.", epigraph.to_markdown());
+//! assert_eq!(epigraphs(&markdown), [(2..7, epigraph)]);
+//! ```
+
+use std::ops::Range;
+
+/// Marker line starting an epigraph.
+pub const EPIGRAPH_MARKER: &str = "<!-- epigraph -->";
+
+/// Marker line ending an epigraph.
+pub const EPIGRAPH_END_MARKER: &str = "<!-- /epigraph -->";
+
+/// Front matter key of the quotation.
+pub const TEXT_KEY: &str = "epigraph";
+
+/// Front matter key of its author or source.
+pub const ATTRIBUTION_KEY: &str = "epigraph_attribution";
+
+/// Dash before the attribution.
+pub const ATTRIBUTION_DASH: &str = "—";
+
+/// A quotation and its source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Epigraph {
+    /// Text of the quotation, its paragraphs parted by blank lines
+    pub text: String,
+    /// Author or source of the quotation, empty if it is not given
+    pub attribution: String,
+}
+
+impl Epigraph {
+    /// The epigraph declared in the front matter opening `markdown`, if
+    /// any.
+    pub fn from_front_matter(markdown: &str) -> Option<Self> {
+        let mut lines = markdown.lines();
+        if lines.next()?.trim_end() != "---" {
+            return None;
+        }
+        let (mut text, mut attribution) = (String::new(), String::new());
+        for line in lines {
+            if matches!(line.trim_end(), "---" | "...") {
+                return (!text.is_empty()).then_some(Self { text, attribution });
+            }
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .or_else(|| {
+                    value
+                        .strip_prefix('\'')
+                        .and_then(|value| value.strip_suffix('\''))
+                })
+                .unwrap_or(value)
+                .trim()
+                .to_string();
+            match key.trim() {
+                TEXT_KEY => text = value,
+                ATTRIBUTION_KEY => attribution = value,
+                _ => {}
+            }
+        }
+        // Unclosed front matter is text
+        None
+    }
+
+    /// The epigraph as a block of the manuscript: a quote signed with a
+    /// dash, between markers.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("{}\n", EPIGRAPH_MARKER);
+        for line in self.text.lines() {
+            out.push_str(format!("> {}", line.trim()).trim_end());
+            out.push('\n');
+        }
+        if !self.attribution.is_empty() {
+            out.push_str(&format!(">\n> {} {}\n", ATTRIBUTION_DASH, self.attribution));
+        }
+        out.push_str(EPIGRAPH_END_MARKER);
+        out
+    }
+}
+
+/// Which marker `line` is: `Some(true)` for an epigraph marker,
+/// `Some(false)` for an end marker.
+pub fn parse_marker(line: &str) -> Option<bool> {
+    match line.trim() {
+        EPIGRAPH_MARKER => Some(true),
+        EPIGRAPH_END_MARKER => Some(false),
+        _ => None,
+    }
+}
+
+/// Epigraphs of `markdown`, with their lines from the marker to the end
+/// marker included, outside of code blocks.
+pub fn epigraphs(markdown: &str) -> Vec<(Range<usize>, Epigraph)> {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut epigraphs = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim_start();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            match fence {
+                Some(open) if line.starts_with(open) => fence = None,
+                None => fence = Some(&line[..3]),
+                _ => {}
+            }
+        }
+        if fence.is_some() || parse_marker(lines[i]) != Some(true) {
+            i += 1;
+            continue;
+        }
+        let Some(end) = lines[i + 1..]
+            .iter()
+            .position(|line| parse_marker(line).is_some())
+            .map(|end| i + 1 + end)
+            .filter(|&end| parse_marker(lines[end]) == Some(false))
+        else {
+            i += 1;
+            continue;
+        };
+
+        let mut quote: Vec<&str> = lines[i + 1..end]
+            .iter()
+            .map(|line| {
+                let line = line.trim();
+                let line = line.strip_prefix('>').unwrap_or(line);
+                line.strip_prefix(' ').unwrap_or(line).trim_end()
+            })
+            .collect();
+        let attribution = match quote.iter().rposition(|line| !line.is_empty()) {
+            Some(last) if quote[last].starts_with(ATTRIBUTION_DASH) => {
+                let attribution = quote[last][ATTRIBUTION_DASH.len()..].trim().to_string();
+                quote.truncate(last);
+                attribution
+            }
+            _ => String::new(),
+        };
+        epigraphs.push((
+            i..end + 1,
+            Epigraph {
+                text: quote.join("\n").trim().to_string(),
+                attribution,
+            },
+        ));
+        i = end + 1;
+    }
+    epigraphs
+}
+
+/// `markdown` with its epigraphs in HTML elements: a `<div>` of class
+/// `epigraph` around the quotation, and a paragraph of class `attribution`
+/// for its source. The markers are removed.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_plugin_api::epigraph::{wrap_epigraphs, Epigraph};
+///
+/// let epigraph = Epigraph { text: "*All* is well.".into(), attribution: "Anon".into() };
+/// assert_eq!(
+///     wrap_epigraphs(&epigraph.to_markdown()),
+///     "<div class=\"epigraph\">\n\n*All* is well.\n\n\
+///      <p class=\"attribution\">— Anon</p>\n\n</div>\n"
+/// );
+/// ```
+pub fn wrap_epigraphs(markdown: &str) -> String {
+    replace_epigraphs(markdown, |epigraph, out| {
+        out.push_str("<div class=\"epigraph\">\n\n");
+        out.push_str(&epigraph.text);
+        out.push_str("\n\n");
+        if !epigraph.attribution.is_empty() {
+            out.push_str(&format!(
+                "<p class=\"attribution\">{} {}</p>\n\n",
+                ATTRIBUTION_DASH, epigraph.attribution
+            ));
+        }
+        out.push_str("</div>\n");
+    })
+}
+
+/// `markdown` with its epigraphs in Pandoc fenced divs of class `epigraph`,
+/// with the `Epigraph` and `Epigraph Attribution` custom styles for word
+/// processor formats. The markers are removed.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_plugin_api::epigraph::{fence_epigraphs, Epigraph};
+///
+/// let epigraph = Epigraph { text: "All is well.".into(), attribution: "Anon".into() };
+/// assert_eq!(
+///     fence_epigraphs(&epigraph.to_markdown()),
+///     "::::: {.epigraph custom-style=\"Epigraph\"}\nAll is well.\n\n\
+///      ::: {.attribution custom-style=\"Epigraph Attribution\"}\n— Anon\n:::\n:::::\n"
+/// );
+/// ```
+pub fn fence_epigraphs(markdown: &str) -> String {
+    replace_epigraphs(markdown, |epigraph, out| {
+        out.push_str("::::: {.epigraph custom-style=\"Epigraph\"}\n");
+        out.push_str(&epigraph.text);
+        out.push('\n');
+        if !epigraph.attribution.is_empty() {
+            out.push_str(&format!(
+                "\n::: {{.attribution custom-style=\"Epigraph Attribution\"}}\n{} {}\n:::\n",
+                ATTRIBUTION_DASH, epigraph.attribution
+            ));
+        }
+        out.push_str(":::::\n");
+    })
+}
+
+/// `markdown` with each epigraph written by `write`, as a block of its own.
+fn replace_epigraphs(markdown: &str, mut write: impl FnMut(&Epigraph, &mut String)) -> String {
+    let epigraphs = epigraphs(markdown);
+    if epigraphs.is_empty() {
+        return markdown.to_string();
+    }
+
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut out = String::with_capacity(markdown.len() * 2);
+    let mut epigraphs = epigraphs.iter().peekable();
+    let mut skip_to = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if let Some((range, epigraph)) = epigraphs.next_if(|(range, _)| range.start == i) {
+            if !out.is_empty() && !out.ends_with("\n\n") {
+                out.push('\n');
+            }
+            write(epigraph, &mut out);
+            skip_to = range.end;
+            // Set apart from the block after it
+            if lines
+                .get(range.end)
+                .is_some_and(|line| !line.trim().is_empty())
+            {
+                out.push('\n');
+            }
+        }
+        if i < skip_to {
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_front_matter() {
+        let markdown = "---\nstatus: draft\nepigraph: 'Go, go.'\n---\nText.";
+        assert_eq!(
+            Epigraph::from_front_matter(markdown),
+            Some(Epigraph {
+                text: "Go, go.".to_string(),
+                attribution: String::new(),
+            })
+        );
+        assert_eq!(Epigraph::from_front_matter("---\nepigraph: Go.\n"), None);
+        assert_eq!(Epigraph::from_front_matter("epigraph: Go.\n"), None);
+        assert_eq!(Epigraph::from_front_matter("---\ntitle: Go\n---\n"), None);
+    }
+
+    #[test]
+    fn test_epigraphs_round_trip() {
+        let epigraph = Epigraph {
+            text: "One.\n\nTwo.".to_string(),
+            attribution: "Ann, *Odes*".to_string(),
+        };
+        let markdown = format!("```\n{}\n```\n{}", EPIGRAPH_MARKER, epigraph.to_markdown());
+        assert_eq!(epigraphs(&markdown), [(3..10, epigraph)]);
+
+        // Without an end marker, the quote is left as it is
+        assert!(epigraphs("<!-- epigraph -->\n> One.").is_empty());
+    }
+}
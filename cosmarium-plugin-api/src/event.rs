@@ -23,9 +23,34 @@
 //!     }
 //! }
 //! ```
-
+//!
+//! Besides their human-readable data, events carry a structured payload.
+//! Standard events are built with helpers such as [`Event::document_changed`]
+//! and their payload is read back with [`Event::payload_as`]:
+//!
+//! ```rust
+//! use cosmarium_plugin_api::event::{DocumentChange, DocumentRef};
+//! use cosmarium_plugin_api::Event;
+//!
+//! let event = Event::document_changed(DocumentRef::from_path("chapter1.md"), Some(4..9));
+//! let change: DocumentChange = event.payload_as().unwrap();
+//! assert_eq!(change.range, Some(4..9));
+//! ```
+//!
+//! The editor describes each edit of a document with the range it replaced
+//! and the length of the text inserted, and numbers the revisions of the
+//! content. Plugins keeping results about a document, such as spelling
+//! mistakes or outline entries, move them along with
+//! [`DocumentChange::map_offset`] and check again only the
+//! [`DocumentChange::word_range`] of the edit; a revision other than the one
+//! following the last they saw means they missed changes, and should start
+//! over from the whole content.
+
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ops::Range;
+use std::path::PathBuf;
 use uuid::Uuid;
 
 /// Core trait for handling events in the plugin system.
@@ -66,6 +91,15 @@ pub trait EventHandler: Send + Sync {
     fn handle(&mut self, event: &Event) -> anyhow::Result<()>;
 }
 
+impl<F> EventHandler for F
+where
+    F: FnMut(&Event) -> anyhow::Result<()> + Send + Sync,
+{
+    fn handle(&mut self, event: &Event) -> anyhow::Result<()> {
+        self(event)
+    }
+}
+
 /// An event that can be emitted and handled by the plugin system.
 ///
 /// Events carry information about something that happened in the application,
@@ -76,8 +110,11 @@ pub struct Event {
     id: Uuid,
     /// Type of the event
     event_type: EventType,
-    /// Optional data payload
+    /// Human-readable description of the event
     data: String,
+    /// Structured payload, `Null` when the event has none
+    #[serde(default)]
+    payload: serde_json::Value,
     /// Additional metadata
     metadata: HashMap<String, String>,
     /// Timestamp when the event was created
@@ -102,6 +139,7 @@ impl Event {
             id: Uuid::new_v4(),
             event_type,
             data: data.into(),
+            payload: serde_json::Value::Null,
             metadata: HashMap::new(),
             timestamp: chrono::Utc::now(),
         }
@@ -133,11 +171,87 @@ impl Event {
             id: Uuid::new_v4(),
             event_type,
             data: data.into(),
+            payload: serde_json::Value::Null,
             metadata,
             timestamp: chrono::Utc::now(),
         }
     }
 
+    /// Attach a structured payload to the event.
+    ///
+    /// Payloads that cannot be represented as JSON are dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::{Event, EventType};
+    ///
+    /// let event = Event::new(EventType::Custom, "Word goal reached")
+    ///     .with_payload(serde_json::json!({ "goal": 1000 }));
+    /// assert_eq!(event.payload()["goal"], 1000);
+    /// ```
+    pub fn with_payload<P: Serialize>(mut self, payload: P) -> Self {
+        match serde_json::to_value(payload) {
+            Ok(value) => self.payload = value,
+            Err(e) => tracing::warn!("Dropping unserializable event payload: {}", e),
+        }
+        self
+    }
+
+    /// Replace the identifier and timestamp the event was created with.
+    ///
+    /// Contexts stamp the events they emit with their own
+    /// [`Clock`](crate::clock::Clock) and
+    /// [`IdSource`](crate::clock::IdSource), so that simulated events are
+    /// reproducible.
+    pub fn with_stamp(mut self, id: Uuid, timestamp: chrono::DateTime<chrono::Utc>) -> Self {
+        self.id = id;
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Create a [`EventType::DocumentChanged`] event.
+    ///
+    /// `range` is the changed byte range in the new content, if known.
+    pub fn document_changed(document: DocumentRef, range: Option<Range<usize>>) -> Self {
+        Self::document_change(DocumentChange {
+            document,
+            range,
+            ..DocumentChange::default()
+        })
+    }
+
+    /// Create a [`EventType::DocumentChanged`] event describing `change`.
+    pub fn document_change(change: DocumentChange) -> Self {
+        let data = format!("Changed document: {}", change.document.label());
+        Self::new(EventType::DocumentChanged, data).with_payload(change)
+    }
+
+    /// Create a [`EventType::DocumentCreated`] event.
+    pub fn document_created(document: DocumentRef) -> Self {
+        Self::document_event(EventType::DocumentCreated, "Created", document)
+    }
+
+    /// Create a [`EventType::DocumentOpened`] event.
+    pub fn document_opened(document: DocumentRef) -> Self {
+        Self::document_event(EventType::DocumentOpened, "Opened", document)
+    }
+
+    /// Create a [`EventType::DocumentSaved`] event.
+    pub fn document_saved(document: DocumentRef) -> Self {
+        Self::document_event(EventType::DocumentSaved, "Saved", document)
+    }
+
+    /// Create a [`EventType::DocumentClosed`] event.
+    pub fn document_closed(document: DocumentRef) -> Self {
+        Self::document_event(EventType::DocumentClosed, "Closed", document)
+    }
+
+    fn document_event(event_type: EventType, verb: &str, document: DocumentRef) -> Self {
+        let data = format!("{} document: {}", verb, document.label());
+        Self::new(event_type, data).with_payload(document)
+    }
+
     /// Get the unique identifier of this event.
     ///
     /// # Example
@@ -180,6 +294,38 @@ impl Event {
         &self.data
     }
 
+    /// Get the structured payload of this event.
+    pub fn payload(&self) -> &serde_json::Value {
+        &self.payload
+    }
+
+    /// Decode the payload as `T`.
+    ///
+    /// Returns `None` if the event has no payload or it does not match `T`.
+    /// Document payloads all decode as [`DocumentRef`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::event::DocumentRef;
+    /// use cosmarium_plugin_api::Event;
+    ///
+    /// let event = Event::document_saved(DocumentRef::from_path("notes.md"));
+    /// let document: DocumentRef = event.payload_as().unwrap();
+    /// assert_eq!(document.path.unwrap().to_str(), Some("notes.md"));
+    /// ```
+    pub fn payload_as<T: DeserializeOwned>(&self) -> Option<T> {
+        if self.payload.is_null() {
+            return None;
+        }
+        serde_json::from_value(self.payload.clone()).ok()
+    }
+
+    /// Get the document a document event is about.
+    pub fn document(&self) -> Option<DocumentRef> {
+        self.payload_as()
+    }
+
     /// Get the metadata associated with this event.
     ///
     /// # Example
@@ -243,6 +389,188 @@ impl Event {
     }
 }
 
+/// Document a document event is about.
+///
+/// The core knows documents by identifier, plugins mostly by path; either
+/// may be missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentRef {
+    /// Document identifier in the document manager
+    #[serde(default)]
+    pub id: Option<Uuid>,
+    /// Path of the document file
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Document title
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+impl DocumentRef {
+    /// Refer to a document by identifier.
+    pub fn from_id(id: Uuid) -> Self {
+        Self {
+            id: Some(id),
+            ..Self::default()
+        }
+    }
+
+    /// Refer to a document by path.
+    pub fn from_path<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Set the document path.
+    pub fn with_path<P: Into<PathBuf>>(mut self, path: Option<P>) -> Self {
+        self.path = path.map(Into::into);
+        self
+    }
+
+    /// Set the document title.
+    pub fn with_title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Short human-readable name of the document.
+    fn label(&self) -> String {
+        if let Some(title) = &self.title {
+            title.clone()
+        } else if let Some(path) = &self.path {
+            path.display().to_string()
+        } else if let Some(id) = &self.id {
+            id.to_string()
+        } else {
+            "untitled".to_string()
+        }
+    }
+}
+
+/// Payload of [`EventType::DocumentChanged`] events.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentChange {
+    /// Changed document
+    #[serde(flatten)]
+    pub document: DocumentRef,
+    /// Changed byte range in the new content, `None` if unknown
+    #[serde(default)]
+    pub range: Option<Range<usize>>,
+    /// Byte range of the previous content replaced, `None` if unknown
+    #[serde(default)]
+    pub replaced: Option<Range<usize>>,
+    /// Length in bytes of the text inserted in place of `replaced`
+    #[serde(default)]
+    pub inserted_len: usize,
+    /// Revision of the content after the change, one more than the
+    /// revision before it; 0 when the sender does not number revisions
+    #[serde(default)]
+    pub revision: u64,
+}
+
+impl DocumentChange {
+    /// Change of `document` from the `old` content to the `new` one, which
+    /// is its `revision`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::event::{DocumentChange, DocumentRef};
+    ///
+    /// let change = DocumentChange::edit(DocumentRef::default(), "a cat", "a black cat", 2);
+    /// assert_eq!(change.replaced, Some(2..2));
+    /// assert_eq!(change.inserted_len, 6);
+    /// assert_eq!(change.map_offset(3), Some(9));
+    /// ```
+    pub fn edit(document: DocumentRef, old: &str, new: &str, revision: u64) -> Self {
+        let range = Self::changed_range(old, new);
+        let suffix = new.len() - range.end;
+        Self {
+            document,
+            replaced: Some(range.start..old.len() - suffix),
+            inserted_len: range.len(),
+            range: Some(range),
+            revision,
+        }
+    }
+
+    /// Offset in the new content of the byte `offset` of the previous one.
+    ///
+    /// Offsets before the replaced range stay, those after it move with the
+    /// end of the inserted text. Offsets inside it, and every offset of a
+    /// change whose range is unknown, have none.
+    pub fn map_offset(&self, offset: usize) -> Option<usize> {
+        let replaced = self.replaced.as_ref()?;
+        if offset <= replaced.start {
+            Some(offset)
+        } else if offset >= replaced.end {
+            Some(offset - replaced.end + replaced.start + self.inserted_len)
+        } else {
+            None
+        }
+    }
+
+    /// Range of the new `content` covering the changed text and the words it
+    /// touches, `None` if the change is unknown.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::event::{DocumentChange, DocumentRef};
+    ///
+    /// let change = DocumentChange::edit(DocumentRef::default(), "the cat sat", "the cart sat", 1);
+    /// assert_eq!(change.word_range("the cart sat"), Some(4..8));
+    /// ```
+    pub fn word_range(&self, content: &str) -> Option<Range<usize>> {
+        let range = self.range.clone()?;
+        let is_word = |c: char| c.is_alphanumeric() || matches!(c, '\'' | '’');
+        let start = content[..range.start]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_word(c))
+            .last()
+            .map_or(range.start, |(i, _)| i);
+        let end = range.end
+            + content[range.end..]
+                .chars()
+                .take_while(|&c| is_word(c))
+                .map(char::len_utf8)
+                .sum::<usize>();
+        Some(start..end)
+    }
+
+    /// Byte range of `new` that differs from `old`.
+    ///
+    /// The range covers everything between the common prefix and the common
+    /// suffix of both texts; it is empty for a pure deletion.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::event::DocumentChange;
+    ///
+    /// assert_eq!(DocumentChange::changed_range("a cat", "a black cat"), 2..8);
+    /// ```
+    pub fn changed_range(old: &str, new: &str) -> Range<usize> {
+        let prefix: usize = old
+            .chars()
+            .zip(new.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum();
+        let suffix: usize = old[prefix..]
+            .chars()
+            .rev()
+            .zip(new[prefix..].chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum();
+        prefix..new.len() - suffix
+    }
+}
+
 /// Types of events that can be emitted in the Cosmarium plugin system.
 ///
 /// This enum defines all the standard event types that plugins and the core
@@ -483,4 +811,72 @@ mod tests {
         assert_eq!(event.data(), deserialized.data());
         assert_eq!(event.timestamp(), deserialized.timestamp());
     }
+
+    #[test]
+    fn test_document_event_payloads() {
+        let id = Uuid::new_v4();
+        let document = DocumentRef::from_id(id)
+            .with_path(Some("content/ch1.md"))
+            .with_title("Chapter 1");
+
+        let saved = Event::document_saved(document.clone());
+        assert_eq!(saved.event_type(), EventType::DocumentSaved);
+        assert_eq!(saved.data(), "Saved document: Chapter 1");
+        assert_eq!(saved.document(), Some(document.clone()));
+
+        let changed = Event::document_changed(document.clone(), Some(3..7));
+        let change: DocumentChange = changed.payload_as().unwrap();
+        assert_eq!(change.range, Some(3..7));
+        // Every document payload decodes as a document reference
+        assert_eq!(changed.document(), Some(document));
+
+        let json = serde_json::to_string(&changed).unwrap();
+        let deserialized: Event = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.payload(), changed.payload());
+    }
+
+    #[test]
+    fn test_event_without_payload() {
+        let event = Event::new(EventType::ThemeChanged, "dark");
+        assert!(event.payload().is_null());
+        assert_eq!(event.document(), None);
+
+        // Events serialized before payloads existed still load
+        let legacy = r#"{"id":"00000000-0000-0000-0000-000000000000","event_type":"Custom",
+            "data":"x","metadata":{},"timestamp":"2024-01-01T00:00:00Z"}"#;
+        let event: Event = serde_json::from_str(legacy).unwrap();
+        assert!(event.payload().is_null());
+    }
+
+    #[test]
+    fn test_changed_range() {
+        assert_eq!(DocumentChange::changed_range("hello", "hello"), 5..5);
+        assert_eq!(DocumentChange::changed_range("hello", "help"), 3..4);
+        assert_eq!(DocumentChange::changed_range("aaa", "aaaa"), 3..4);
+        assert_eq!(DocumentChange::changed_range("abc", "ac"), 1..1);
+        assert_eq!(DocumentChange::changed_range("é", "è"), 0..2);
+        assert_eq!(DocumentChange::changed_range("", "new"), 0..3);
+    }
+
+    #[test]
+    fn test_document_edits() {
+        let change = DocumentChange::edit(DocumentRef::default(), "one two", "one", 4);
+        assert_eq!(change.replaced, Some(3..7));
+        assert_eq!((change.range.clone(), change.inserted_len), (Some(3..3), 0));
+        assert_eq!(change.map_offset(7), Some(3));
+        assert_eq!(change.map_offset(5), None);
+
+        let event = Event::document_change(change.clone());
+        let decoded: DocumentChange = event.payload_as().unwrap();
+        assert_eq!(decoded, change);
+
+        // Changes of unknown extent
+        let unknown: DocumentChange = Event::document_changed(DocumentRef::default(), None)
+            .payload_as()
+            .unwrap();
+        assert_eq!((unknown.revision, unknown.map_offset(0)), (0, None));
+        assert_eq!(unknown.word_range("text"), None);
+        let edit = DocumentChange::edit(DocumentRef::default(), "l’été", "l’étés", 1);
+        assert_eq!(edit.word_range("l’étés"), Some(0.."l’étés".len()));
+    }
 }
@@ -0,0 +1,197 @@
+//! Export system for Cosmarium output format plugins.
+//!
+//! The application compiles a project into a [`Manuscript`]: its documents
+//! in reading order, with the front matter and the separators between
+//! chapters and scenes already in place. Export plugins implement the
+//! [`ExportPlugin`] trait to turn a manuscript into a file of their format.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::export::{ExportPlugin, Manuscript};
+//! use std::path::Path;
+//!
+//! struct MarkdownExport;
+//!
+//! impl ExportPlugin for MarkdownExport {
+//!     fn format_id(&self) -> &str {
+//!         "markdown"
+//!     }
+//!
+//!     fn format_name(&self) -> &str {
+//!         "Markdown"
+//!     }
+//!
+//!     fn file_extension(&self) -> &str {
+//!         "md"
+//!     }
+//!
+//!     fn export(
+//!         &self,
+//!         manuscript: &Manuscript,
+//!         _options: &serde_json::Value,
+//!         output: &Path,
+//!     ) -> anyhow::Result<()> {
+//!         std::fs::write(output, manuscript.to_markdown())?;
+//!         Ok(())
+//!     }
+//! }
+//! ```
+
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Trait for plugins that write manuscripts in an output format.
+///
+/// Exports run on a background thread, so implementations should not touch
+/// the UI.
+pub trait ExportPlugin: Send + Sync {
+    /// Stable identifier of the format, such as `"pdf"`.
+    ///
+    /// The application passes the matching section of its export settings
+    /// to [`ExportPlugin::export`].
+    fn format_id(&self) -> &str;
+
+    /// Human-readable name of the format, for menus.
+    fn format_name(&self) -> &str;
+
+    /// Extension of the files written, without the dot.
+    fn file_extension(&self) -> &str;
+
+    /// Write `manuscript` to the file at `output`.
+    ///
+    /// # Arguments
+    ///
+    /// * `manuscript` - The compiled manuscript
+    /// * `options` - Settings of this format (`Null` if there are none)
+    /// * `output` - File to write, in an existing directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    fn export(
+        &self,
+        manuscript: &Manuscript,
+        options: &serde_json::Value,
+        output: &Path,
+    ) -> Result<()>;
+}
+
+/// What a manuscript section comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SectionKind {
+    /// Heading of a part, chapter or folder
+    Heading,
+    /// Text of a document
+    Document,
+}
+
+/// A heading or document of a compiled manuscript.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManuscriptSection {
+    /// Heading or document
+    pub kind: SectionKind,
+    /// Title of the part, chapter or document
+    pub title: String,
+    /// Nesting depth in the project structure, starting at 0
+    pub depth: usize,
+    /// Source file of a document, relative to the project root
+    pub path: Option<PathBuf>,
+    /// Separator written before the section, as Markdown (may be empty)
+    pub separator: String,
+    /// Markdown content of the section
+    pub markdown: String,
+}
+
+/// A project's documents compiled for export, in reading order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manuscript {
+    /// Manuscript title
+    pub title: String,
+    /// Author shown in bylines (empty for anonymized exports)
+    pub author: String,
+    /// Language of the text, as a BCP 47 tag such as `en-US`, empty if it
+    /// is not known
+    #[serde(default)]
+    pub language: String,
+    /// Title page, dedication and other pages before the text, as Markdown
+    pub front_matter: String,
+    /// Headings and documents
+    pub sections: Vec<ManuscriptSection>,
+}
+
+impl Manuscript {
+    /// The whole manuscript as one Markdown text: the front matter, then
+    /// every section preceded by its separator.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::export::{Manuscript, ManuscriptSection, SectionKind};
+    ///
+    /// let section = |markdown: &str, separator: &str| ManuscriptSection {
+    ///     kind: SectionKind::Document,
+    ///     title: String::new(),
+    ///     depth: 0,
+    ///     path: None,
+    ///     separator: separator.to_string(),
+    ///     markdown: markdown.to_string(),
+    /// };
+    /// let manuscript = Manuscript {
+    ///     sections: vec![section("Dawn.", ""), section("Dusk.", "***")],
+    ///     ..Manuscript::default()
+    /// };
+    /// assert_eq!(manuscript.to_markdown(), "Dawn.\n\n***\n\nDusk.\n");
+    /// ```
+    pub fn to_markdown(&self) -> String {
+        let mut blocks = Vec::new();
+        if !self.front_matter.trim().is_empty() {
+            blocks.push(self.front_matter.trim());
+        }
+        for section in &self.sections {
+            if !section.separator.trim().is_empty() {
+                blocks.push(section.separator.trim());
+            }
+            if !section.markdown.trim().is_empty() {
+                blocks.push(section.markdown.trim());
+            }
+        }
+
+        let mut markdown = blocks.join("\n\n");
+        markdown.push('\n');
+        markdown
+    }
+
+    /// Number of documents in the manuscript.
+    pub fn document_count(&self) -> usize {
+        self.sections
+            .iter()
+            .filter(|s| s.kind == SectionKind::Document)
+            .count()
+    }
+
+    /// Header of manuscript pages, the page number aside: the author's
+    /// surname and the title, as in "Author / The Inn".
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::export::Manuscript;
+    ///
+    /// let manuscript = Manuscript {
+    ///     title: "The Inn".to_string(),
+    ///     author: "Ann Author".to_string(),
+    ///     ..Manuscript::default()
+    /// };
+    /// assert_eq!(manuscript.running_header(), "Author / The Inn");
+    /// ```
+    pub fn running_header(&self) -> String {
+        let surname = self.author.split_whitespace().last().unwrap_or_default();
+        [surname, self.title.trim()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+}
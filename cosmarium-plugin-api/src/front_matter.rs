@@ -0,0 +1,232 @@
+//! Front matter: the metadata block opening a document.
+//!
+//! The block is YAML between `---` lines (closed by `---` or `...`), or TOML
+//! between `+++` lines:
+//!
+//! ```markdown
+//! ---
+//! title: The Inn
+//! pov: Mira
+//! tags: [night, storm]
+//! ---
+//! ```
+//!
+//! Only the top-level keys are read, each as text: lists become their items
+//! parted by commas, and nested maps and tables are left to the plugins that
+//! read them (the section targets of the outline, for one). The document
+//! keeps its block as written; [`FrontMatter::body`] is the text after it.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::front_matter::{FrontMatter, FrontMatterFormat};
+//!
+//! let document = "+++\ntitle = \"The Inn\"\ntags = [\"night\", \"storm\"]\n+++\nIt rained.";
+//! let front_matter = FrontMatter::parse(document).unwrap();
+//! assert_eq!(front_matter.format, FrontMatterFormat::Toml);
+//! assert_eq!(front_matter.get("title"), Some("The Inn"));
+//! assert_eq!(front_matter.list("tags"), ["night", "storm"]);
+//! assert_eq!(front_matter.body(document), "It rained.");
+//! ```
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// Syntax of a front matter block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrontMatterFormat {
+    /// YAML, between `---` lines
+    Yaml,
+    /// TOML, between `+++` lines
+    Toml,
+}
+
+impl FrontMatterFormat {
+    /// Line opening a block of this format.
+    pub fn fence(&self) -> &'static str {
+        match self {
+            Self::Yaml => "---",
+            Self::Toml => "+++",
+        }
+    }
+}
+
+/// The front matter block opening a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrontMatter {
+    /// Syntax of the block
+    pub format: FrontMatterFormat,
+    /// Byte range of the block, from its opening fence to the line ending
+    /// of its closing one
+    pub range: Range<usize>,
+    /// Top-level fields, by key
+    pub fields: BTreeMap<String, String>,
+}
+
+impl FrontMatter {
+    /// The front matter opening `text`, if it has a closed one.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut lines = text.split_inclusive('\n');
+        let first = lines.next()?;
+        let format = match first.trim_end() {
+            "---" => FrontMatterFormat::Yaml,
+            "+++" => FrontMatterFormat::Toml,
+            _ => return None,
+        };
+
+        let mut fields: BTreeMap<String, String> = BTreeMap::new();
+        // Key of the YAML list being read, or whether TOML keys are still
+        // top-level ones
+        let mut list_key: Option<String> = None;
+        let mut top_level = true;
+        let mut end = first.len();
+        for line in lines {
+            end += line.len();
+            let trimmed = line.trim_end();
+            let closed = match format {
+                FrontMatterFormat::Yaml => matches!(trimmed, "---" | "..."),
+                FrontMatterFormat::Toml => trimmed == "+++",
+            };
+            if closed {
+                return Some(Self {
+                    format,
+                    range: 0..end,
+                    fields,
+                });
+            }
+            if trimmed.trim_start().starts_with('#') {
+                continue;
+            }
+            match format {
+                FrontMatterFormat::Yaml => {
+                    let item = trimmed.trim_start().strip_prefix("- ");
+                    if let (Some(item), Some(key)) = (item, &list_key) {
+                        let value = fields.entry(key.clone()).or_default();
+                        if !value.is_empty() {
+                            value.push_str(", ");
+                        }
+                        value.push_str(unquote(item.trim()));
+                        continue;
+                    }
+                    // Indented keys belong to a nested map
+                    if trimmed.starts_with([' ', '\t']) {
+                        continue;
+                    }
+                    list_key = None;
+                    if let Some((key, value)) = trimmed.split_once(':') {
+                        let key = key.trim().to_string();
+                        if value.trim().is_empty() {
+                            list_key = Some(key.clone());
+                        }
+                        fields.insert(key, read_value(value));
+                    }
+                }
+                FrontMatterFormat::Toml => {
+                    if trimmed.trim_start().starts_with('[') {
+                        top_level = false;
+                    } else if let Some((key, value)) = trimmed.split_once('=') {
+                        if top_level {
+                            fields.insert(unquote(key.trim()).to_string(), read_value(value));
+                        }
+                    }
+                }
+            }
+        }
+        // Unclosed front matter is text
+        None
+    }
+
+    /// Text of the field `key`, if the block has it.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .get(key)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Items of the list field `key`, a single one if it is not a list.
+    pub fn list(&self, key: &str) -> Vec<String> {
+        self.get(key)
+            .into_iter()
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// The text of the document `text` after the block.
+    pub fn body<'a>(&self, text: &'a str) -> &'a str {
+        text.get(self.range.end..).unwrap_or_default()
+    }
+}
+
+/// The text of `text` after its front matter, all of it if it has none.
+pub fn strip(text: &str) -> &str {
+    match FrontMatter::parse(text) {
+        Some(front_matter) => front_matter.body(text),
+        None => text,
+    }
+}
+
+/// Text of a value: a quoted string without its quotes, a list as its items
+/// parted by commas.
+fn read_value(value: &str) -> String {
+    let value = value.trim();
+    match value
+        .strip_prefix('[')
+        .and_then(|list| list.strip_suffix(']'))
+    {
+        Some(list) => list
+            .split(',')
+            .map(|item| unquote(item.trim()))
+            .filter(|item| !item.is_empty())
+            .collect::<Vec<_>>()
+            .join(", "),
+        None => unquote(value).to_string(),
+    }
+}
+
+/// `value` without the quotes around it.
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yaml_front_matter() {
+        let text = "---\ntitle: \"The Inn\"\n# draft\ntags:\n  - night\n  - 'storm'\ntargets:\n  The Inn: 3000\nstatus: revised\n...\nIt rained.";
+        let front_matter = FrontMatter::parse(text).unwrap();
+        assert_eq!(front_matter.format, FrontMatterFormat::Yaml);
+        assert_eq!(front_matter.get("title"), Some("The Inn"));
+        assert_eq!(front_matter.list("tags"), ["night", "storm"]);
+        assert_eq!(front_matter.get("status"), Some("revised"));
+        assert_eq!(front_matter.get("targets"), None);
+        assert!(!front_matter.fields.contains_key("The Inn"));
+        assert_eq!(front_matter.body(text), "It rained.");
+    }
+
+    #[test]
+    fn test_toml_tables_are_not_top_level() {
+        let text = "+++\npov = 'Mira'\ntags = [\"night\"]\n[targets]\nThe_Inn = 3000\n+++\n";
+        let front_matter = FrontMatter::parse(text).unwrap();
+        assert_eq!(front_matter.get("pov"), Some("Mira"));
+        assert_eq!(front_matter.list("tags"), ["night"]);
+        assert_eq!(front_matter.fields.len(), 2);
+        assert_eq!(front_matter.range, 0..text.len());
+    }
+
+    #[test]
+    fn test_unclosed_front_matter_is_text() {
+        assert!(FrontMatter::parse("---\ntitle: The Inn\n").is_none());
+        assert!(FrontMatter::parse("+++\ntitle = 'x'\n---\n").is_none());
+        assert!(FrontMatter::parse("# The Inn\n---\n").is_none());
+        assert_eq!(strip("It rained."), "It rained.");
+    }
+}
@@ -0,0 +1,314 @@
+//! Versions of a document in the Git repository of its project.
+//!
+//! The application publishes the commits that changed the active document
+//! under [`DOCUMENT_COMMITS_KEY`], newest first, and serves the
+//! [`GitHistoryRequest`]s plugins send with [`GIT_HISTORY_REQUEST`]: the
+//! text of the document at a commit is then published under
+//! [`COMMIT_FILE_KEY`]. Restoring a version replaces the text in the editor
+//! as a single undo step, after the text it replaces is kept as a
+//! [snapshot](crate::document_snapshot).
+//!
+//! The changes of the project since its last commit are published under
+//! [`PROJECT_CHANGES_KEY`], with the documents changed and the words added
+//! or removed in each: [`ProjectChanges::message`] summarizes them as the
+//! message of the next commit.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::git_history::CommitInfo;
+//!
+//! let commit = CommitInfo {
+//!     id: "3f2a9c41d0e8b7a6c5d4e3f2a1b0c9d8e7f6a5b4".to_string(),
+//!     summary: "Rewrite the storm".to_string(),
+//!     author: "Ann".to_string(),
+//!     time: chrono::Utc::now(),
+//!     parent: None,
+//! };
+//! assert_eq!(commit.short_id(), "3f2a9c4");
+//! ```
+
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+
+/// Shared state key (`Option<Arc<DocumentCommits>>`) of the commits that
+/// changed the active document, `None` when its project has no repository.
+pub const DOCUMENT_COMMITS_KEY: &str = "document_commits";
+
+/// Shared state key (`Option<Arc<CommitFile>>`) of the text of the active
+/// document at the commit last asked for.
+pub const COMMIT_FILE_KEY: &str = "commit_file";
+
+/// Shared state key (`Option<Arc<ProjectChanges>>`) of the changes of the
+/// active project since its last commit, `None` when it has no repository.
+pub const PROJECT_CHANGES_KEY: &str = "project_changes";
+
+/// Shared state key (`Option<GitHistoryRequest>`) of a request about the
+/// versions of the active document, served by the application.
+pub const GIT_HISTORY_REQUEST: &str = "git_history_request";
+
+/// Length of the abbreviated identifier of a commit.
+const SHORT_ID_LEN: usize = 7;
+
+/// A commit of the repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitInfo {
+    /// Identifier of the commit, in hexadecimal
+    pub id: String,
+    /// First line of the message
+    pub summary: String,
+    /// Name of the author
+    pub author: String,
+    /// When the commit was made
+    pub time: DateTime<Utc>,
+    /// Identifier of its first parent, `None` for the first commit
+    pub parent: Option<String>,
+}
+
+impl CommitInfo {
+    /// Abbreviated identifier of the commit.
+    pub fn short_id(&self) -> &str {
+        short_id(&self.id)
+    }
+}
+
+/// Abbreviated form of the commit identifier `id`.
+pub fn short_id(id: &str) -> &str {
+    id.get(..SHORT_ID_LEN).unwrap_or(id)
+}
+
+/// Commits that changed a document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentCommits {
+    /// File of the document
+    pub path: PathBuf,
+    /// Commits, newest first
+    pub commits: Vec<CommitInfo>,
+}
+
+impl DocumentCommits {
+    /// The commit `id`, if it changed the document.
+    pub fn get(&self, id: &str) -> Option<&CommitInfo> {
+        self.commits.iter().find(|commit| commit.id == id)
+    }
+}
+
+/// Text of a document at a commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitFile {
+    /// Identifier of the commit
+    pub commit: String,
+    /// File of the document
+    pub path: PathBuf,
+    /// Text of the document, `None` if it was not in the commit or is not
+    /// text
+    pub content: Option<String>,
+    /// Text of the document at the first parent of the commit, `None` if
+    /// it was not in it
+    pub previous: Option<String>,
+}
+
+/// A document changed since the last commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedDocument {
+    /// File of the document, relative to the project root
+    pub path: PathBuf,
+    /// Words in the last commit, `None` if the document is new
+    pub words_before: Option<usize>,
+    /// Words now, `None` if the document was deleted
+    pub words_after: Option<usize>,
+}
+
+impl ChangedDocument {
+    /// Name of the document, that of its file without extension.
+    pub fn name(&self) -> String {
+        self.path
+            .file_stem()
+            .unwrap_or(self.path.as_os_str())
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Name of the document, telling whether it is new or was deleted.
+    pub fn label(&self) -> String {
+        match (self.words_before, self.words_after) {
+            (None, _) => format!("{} (new)", self.name()),
+            (_, None) => format!("{} (deleted)", self.name()),
+            _ => self.name(),
+        }
+    }
+
+    /// Words added to the document, negative when more were removed.
+    pub fn word_delta(&self) -> i64 {
+        self.words_after.unwrap_or(0) as i64 - self.words_before.unwrap_or(0) as i64
+    }
+
+    /// What was done to the document, as the verb of a commit message.
+    fn verb(&self) -> &'static str {
+        match (self.words_before, self.words_after) {
+            (None, _) => "Add",
+            (_, None) => "Delete",
+            _ => "Edit",
+        }
+    }
+}
+
+/// Changes of a project since its last commit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectChanges {
+    /// Documents changed, by path
+    pub documents: Vec<ChangedDocument>,
+    /// Other files changed, the state of the project among them
+    pub other_files: usize,
+}
+
+impl ProjectChanges {
+    /// Whether nothing changed since the last commit.
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty() && self.other_files == 0
+    }
+
+    /// Words added to the documents, negative when more were removed.
+    pub fn word_delta(&self) -> i64 {
+        self.documents.iter().map(ChangedDocument::word_delta).sum()
+    }
+
+    /// Message of a commit of the changes: a summary line, then a line per
+    /// document when several changed.
+    ///
+    /// ```rust
+    /// use cosmarium_plugin_api::git_history::{ChangedDocument, ProjectChanges};
+    ///
+    /// let changes = ProjectChanges {
+    ///     documents: vec![ChangedDocument {
+    ///         path: "content/inn.md".into(),
+    ///         words_before: Some(1200),
+    ///         words_after: Some(1320),
+    ///     }],
+    ///     other_files: 1,
+    /// };
+    /// assert_eq!(changes.message(), "Edit inn (+120 words)\n\n- 1 other file");
+    /// ```
+    pub fn message(&self) -> String {
+        let mut message = match self.documents.as_slice() {
+            [] if self.other_files == 0 => return "Save project".to_string(),
+            [] => {
+                return format!(
+                    "Update {} project {}",
+                    self.other_files,
+                    plural(self.other_files, "file")
+                )
+            }
+            [document] => format!(
+                "{} {} ({})",
+                document.verb(),
+                document.name(),
+                words(document.word_delta())
+            ),
+            documents => format!(
+                "Edit {} documents ({})",
+                documents.len(),
+                words(self.word_delta())
+            ),
+        };
+        let mut details = Vec::new();
+        if self.documents.len() > 1 {
+            for document in &self.documents {
+                details.push(format!(
+                    "- {}: {}",
+                    document.label(),
+                    words(document.word_delta())
+                ));
+            }
+        }
+        if self.other_files > 0 {
+            details.push(format!(
+                "- {} other {}",
+                self.other_files,
+                plural(self.other_files, "file")
+            ));
+        }
+        if !details.is_empty() {
+            message.push_str("\n\n");
+            message.push_str(&details.join("\n"));
+        }
+        message
+    }
+}
+
+/// `noun`, in the plural unless `count` is one.
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        noun.to_string()
+    } else {
+        format!("{}s", noun)
+    }
+}
+
+/// `delta` words, signed.
+fn words(delta: i64) -> String {
+    format!(
+        "{:+} {}",
+        delta,
+        plural(delta.unsigned_abs() as usize, "word")
+    )
+}
+
+/// Request about the versions of the active document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitHistoryRequest {
+    /// Read the commits and the changes of the project again
+    Refresh,
+    /// Publish the text of the document at this commit
+    Show(String),
+    /// Replace the text of the document with that at this commit
+    Restore(String),
+    /// Save the project and commit its changes with this message
+    Commit(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(path: &str, before: Option<usize>, after: Option<usize>) -> ChangedDocument {
+        ChangedDocument {
+            path: path.into(),
+            words_before: before,
+            words_after: after,
+        }
+    }
+
+    #[test]
+    fn test_message_summarizes_documents_and_words() {
+        assert_eq!(ProjectChanges::default().message(), "Save project");
+        let settings = ProjectChanges {
+            documents: Vec::new(),
+            other_files: 2,
+        };
+        assert_eq!(settings.message(), "Update 2 project files");
+
+        let new = ProjectChanges {
+            documents: vec![document("content/road.md", None, Some(1))],
+            other_files: 0,
+        };
+        assert_eq!(new.message(), "Add road (+1 word)");
+
+        let changes = ProjectChanges {
+            documents: vec![
+                document("content/inn.md", Some(300), Some(250)),
+                document("content/road.md", None, Some(80)),
+                document("content/old.md", Some(40), None),
+            ],
+            other_files: 0,
+        };
+        assert_eq!(changes.word_delta(), -10);
+        assert_eq!(
+            changes.message(),
+            "Edit 3 documents (-10 words)\n\n\
+             - inn: -50 words\n\
+             - road (new): +80 words\n\
+             - old (deleted): -40 words"
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Grammar and style issues in the editor's text.
+//!
+//! A checker plugin reads the editor's content, finds what reads wrong in
+//! it and publishes a [`GrammarReport`] under [`GRAMMAR_ISSUES_KEY`]. The
+//! report names the text it was made for by its [`text_hash`], so the editor
+//! only underlines issues whose offsets still hold. Fixes chosen outside the
+//! editor are posted as a [`GrammarFix`] under [`GRAMMAR_FIX_REQUEST`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::grammar::{text_hash, GrammarIssue, GrammarReport, IssueKind};
+//!
+//! let text = "She have a cat.";
+//! let mut report = GrammarReport::new(text_hash(text));
+//! report.issues.push(GrammarIssue {
+//!     range: 4..8,
+//!     message: "Use “has” with a singular subject.".to_string(),
+//!     rule: "HE_VERB_AGR".to_string(),
+//!     kind: IssueKind::Grammar,
+//!     replacements: vec!["has".to_string()],
+//! });
+//!
+//! let fixed = report.fix(0, 0).unwrap();
+//! assert_eq!(fixed, "has");
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+/// Shared state key (`Option<GrammarReport>`) of the issues found in the
+/// editor's text.
+pub const GRAMMAR_ISSUES_KEY: &str = "grammar_issues";
+
+/// Shared state key (`Option<GrammarFix>`) of a fix to apply to the editor's
+/// text, served by the editor.
+pub const GRAMMAR_FIX_REQUEST: &str = "grammar_fix_request";
+
+/// What kind of problem an issue points out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum IssueKind {
+    /// Grammar, agreement and punctuation mistakes
+    #[default]
+    Grammar,
+    /// Wordiness, repetitions and other matters of style
+    Style,
+    /// Words not found in the checker's dictionary
+    Spelling,
+}
+
+/// A problem found in a range of the text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GrammarIssue {
+    /// Byte range of the text in question
+    pub range: Range<usize>,
+    /// Explanation shown to the author
+    pub message: String,
+    /// Identifier of the checker's rule that matched
+    pub rule: String,
+    /// What kind of problem it is
+    pub kind: IssueKind,
+    /// Suggested replacements for the range, best first
+    pub replacements: Vec<String>,
+}
+
+/// Issues found in one version of the editor's text.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GrammarReport {
+    /// [`text_hash`] of the text checked
+    pub text_hash: u64,
+    /// Issues in text order
+    pub issues: Vec<GrammarIssue>,
+}
+
+impl GrammarReport {
+    /// Create an empty report for the text with the given hash.
+    pub fn new(text_hash: u64) -> Self {
+        Self {
+            text_hash,
+            issues: Vec::new(),
+        }
+    }
+
+    /// Get a suggested replacement of an issue.
+    pub fn fix(&self, issue: usize, replacement: usize) -> Option<&str> {
+        self.issues
+            .get(issue)?
+            .replacements
+            .get(replacement)
+            .map(String::as_str)
+    }
+
+    /// Follow a replacement of `range` by `len` bytes of text: issues
+    /// touching the range are dropped, those after it shift.
+    ///
+    /// This keeps the other issues in place after a fix, until the text is
+    /// checked again.
+    pub fn replaced(&mut self, range: Range<usize>, len: usize, text_hash: u64) {
+        self.issues
+            .retain(|issue| issue.range.end <= range.start || issue.range.start >= range.end);
+        for issue in &mut self.issues {
+            if issue.range.start >= range.end {
+                issue.range.start = issue.range.start - range.end + range.start + len;
+                issue.range.end = issue.range.end - range.end + range.start + len;
+            }
+        }
+        self.text_hash = text_hash;
+    }
+}
+
+/// A replacement chosen for an issue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GrammarFix {
+    /// [`text_hash`] of the text the range belongs to
+    pub text_hash: u64,
+    /// Byte range to replace
+    pub range: Range<usize>,
+    /// Text replacing the range
+    pub replacement: String,
+}
+
+/// Hash identifying a version of the editor's text.
+pub fn text_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(range: Range<usize>) -> GrammarIssue {
+        GrammarIssue {
+            range,
+            message: String::new(),
+            rule: String::new(),
+            kind: IssueKind::Style,
+            replacements: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_replaced_shifts_later_issues() {
+        let mut report = GrammarReport::new(1);
+        report.issues = vec![issue(0..3), issue(4..8), issue(10..12)];
+
+        // "have" (4..8) becomes "has"
+        report.replaced(4..8, 3, 2);
+        assert_eq!(report.text_hash, 2);
+        let ranges: Vec<_> = report.issues.iter().map(|i| i.range.clone()).collect();
+        assert_eq!(ranges, vec![0..3, 9..11]);
+    }
+}
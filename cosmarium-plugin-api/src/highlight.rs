@@ -0,0 +1,91 @@
+//! Passages of the editor's text highlighted by analysis plugins.
+//!
+//! An analysis plugin publishes the passages it wants the author to look at
+//! as [`TextHighlights`] in the map under [`TEXT_HIGHLIGHTS_KEY`], by its own
+//! name so several plugins can highlight at once. Like a grammar report, the
+//! highlights name the text they were found in by its
+//! [`text_hash`](crate::grammar::text_hash), and the editor only paints those
+//! that still match its content.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::grammar::text_hash;
+//! use cosmarium_plugin_api::highlight::{self, Highlight, TextHighlights};
+//! use cosmarium_plugin_api::PluginContext;
+//! use egui::Color32;
+//!
+//! let mut ctx = PluginContext::new();
+//! let text = "She walked slowly.";
+//! let mut highlights = TextHighlights::new(text_hash(text));
+//! highlights.highlights.push(Highlight::new(11..17, Color32::YELLOW));
+//! highlight::publish(&mut ctx, "prose", Some(highlights));
+//!
+//! assert_eq!(highlight::published(&ctx).len(), 1);
+//! highlight::publish(&mut ctx, "prose", None);
+//! assert!(highlight::published(&ctx).is_empty());
+//! ```
+
+use crate::PluginContext;
+use egui::Color32;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Shared state key (`HashMap<String, TextHighlights>`) of the highlights of
+/// the editor's text, by name of the plugin publishing them.
+pub const TEXT_HIGHLIGHTS_KEY: &str = "text_highlights";
+
+/// A highlighted range of the text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    /// Byte range of the passage
+    pub range: Range<usize>,
+    /// Background color, usually translucent
+    pub color: Color32,
+}
+
+impl Highlight {
+    /// Create a highlight of a byte range.
+    pub fn new(range: Range<usize>, color: Color32) -> Self {
+        Self { range, color }
+    }
+}
+
+/// Highlights found in one version of the editor's text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextHighlights {
+    /// [`text_hash`](crate::grammar::text_hash) of the text analyzed
+    pub text_hash: u64,
+    /// Highlights in text order
+    pub highlights: Vec<Highlight>,
+}
+
+impl TextHighlights {
+    /// Create empty highlights for the text with the given hash.
+    pub fn new(text_hash: u64) -> Self {
+        Self {
+            text_hash,
+            highlights: Vec::new(),
+        }
+    }
+}
+
+/// Publish the highlights of a plugin, or withdraw them with `None`.
+pub fn publish(ctx: &mut PluginContext, source: &str, highlights: Option<TextHighlights>) {
+    let mut all = published(ctx);
+    match highlights {
+        Some(highlights) => {
+            all.insert(source.to_string(), highlights);
+        }
+        None => {
+            all.remove(source);
+        }
+    }
+    ctx.set_shared_state(TEXT_HIGHLIGHTS_KEY, all);
+}
+
+/// Get the highlights published, by name of the plugin publishing them.
+pub fn published(ctx: &PluginContext) -> HashMap<String, TextHighlights> {
+    ctx.get_shared_state(TEXT_HIGHLIGHTS_KEY)
+        .unwrap_or_default()
+}
@@ -0,0 +1,259 @@
+//! Words written over time, for writing goals and productivity panels.
+//!
+//! The application counts the words *written*, the changes of the word count
+//! of the document being edited, and keeps them in a [`WritingLog`] across
+//! sessions: by day, by project and day, and by document, along with the
+//! writing sprints. It publishes the log, with the open project, under
+//! [`WRITING_HISTORY_KEY`] whenever words are written.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::history::WritingLog;
+//! use chrono::NaiveDate;
+//! use std::path::Path;
+//!
+//! let day = |n| NaiveDate::from_ymd_opt(2024, 11, n).unwrap();
+//! let novel = Path::new("/novel");
+//! let mut log = WritingLog::default();
+//! log.record(day(1), Some(novel), Some(&novel.join("one.md")), 1700);
+//! log.record(day(2), Some(novel), Some(&novel.join("two.md")), 1800);
+//! log.record(day(2), None, None, 300);
+//!
+//! assert_eq!(log.day(day(2)), 2100);
+//! assert_eq!(log.project_day(novel, day(2)), 1800);
+//! assert_eq!(log.streak(Some(novel), day(2)), 2);
+//! assert_eq!(log.documents(novel)[0], (Path::new("two.md"), 1800));
+//! ```
+
+use crate::sprint::SprintRecord;
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Shared state key ([`WritingHistory`]) of the words written, published by
+/// the application whenever they change.
+pub const WRITING_HISTORY_KEY: &str = "writing_history";
+
+/// Number of days the log remembers.
+const KEPT_DAYS: usize = 366;
+
+/// Number of sprints the log remembers.
+const KEPT_SPRINTS: usize = 1000;
+
+/// Words written over the sessions, by day, by project and by document.
+///
+/// Counts may be negative where more words were deleted than written.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WritingLog {
+    /// Words written each day, in any project
+    pub days: BTreeMap<NaiveDate, i64>,
+    /// Words written in each project, by project path
+    pub projects: HashMap<PathBuf, i64>,
+    /// Words written each day in each project
+    pub project_days: HashMap<PathBuf, BTreeMap<NaiveDate, i64>>,
+    /// Words written in the documents of each project, by document path
+    /// relative to the project
+    pub documents: HashMap<PathBuf, HashMap<PathBuf, i64>>,
+    /// Writing sprints, oldest first
+    pub sprints: Vec<SprintRecord>,
+}
+
+impl WritingLog {
+    /// Add `words`, which may be negative, to the counts of `date`, of
+    /// `project` and of `document` in it. Days older than a year are
+    /// forgotten.
+    pub fn record(
+        &mut self,
+        date: NaiveDate,
+        project: Option<&Path>,
+        document: Option<&Path>,
+        words: i64,
+    ) {
+        if words == 0 {
+            return;
+        }
+        add_day(&mut self.days, date, words);
+        let Some(project) = project else {
+            return;
+        };
+        *self.projects.entry(project.to_path_buf()).or_default() += words;
+        add_day(
+            self.project_days.entry(project.to_path_buf()).or_default(),
+            date,
+            words,
+        );
+        if let Some(document) = document {
+            let document = document.strip_prefix(project).unwrap_or(document);
+            let documents = self.documents.entry(project.to_path_buf()).or_default();
+            *documents.entry(document.to_path_buf()).or_default() += words;
+        }
+    }
+
+    /// Words written on `date`.
+    pub fn day(&self, date: NaiveDate) -> i64 {
+        self.days.get(&date).copied().unwrap_or(0)
+    }
+
+    /// Words written in `project`.
+    pub fn project(&self, project: &Path) -> i64 {
+        self.projects.get(project).copied().unwrap_or(0)
+    }
+
+    /// Words written in `project` on `date`.
+    pub fn project_day(&self, project: &Path, date: NaiveDate) -> i64 {
+        self.project_days
+            .get(project)
+            .and_then(|days| days.get(&date))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Words written each day in `project`, or in any project.
+    pub fn daily(&self, project: Option<&Path>) -> Option<&BTreeMap<NaiveDate, i64>> {
+        match project {
+            Some(project) => self.project_days.get(project),
+            None => Some(&self.days),
+        }
+    }
+
+    /// Number of days in a row, up to `today`, words were written in
+    /// `project`, or in any project. A day without words yet does not
+    /// break the streak before the day is over.
+    pub fn streak(&self, project: Option<&Path>, today: NaiveDate) -> usize {
+        let Some(days) = self.daily(project) else {
+            return 0;
+        };
+        let wrote = |date: &NaiveDate| days.get(date).is_some_and(|words| *words > 0);
+        let mut date = today;
+        if !wrote(&date) {
+            date = date - Days::new(1);
+        }
+        let mut streak = 0;
+        while wrote(&date) {
+            streak += 1;
+            date = date - Days::new(1);
+        }
+        streak
+    }
+
+    /// Words written in the documents of `project`, most first.
+    pub fn documents(&self, project: &Path) -> Vec<(&Path, i64)> {
+        let mut documents: Vec<_> = self
+            .documents
+            .get(project)
+            .into_iter()
+            .flatten()
+            .map(|(path, words)| (path.as_path(), *words))
+            .collect();
+        documents.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        documents
+    }
+
+    /// Keep `sprint`, forgetting the oldest sprints beyond the thousandth.
+    pub fn record_sprint(&mut self, sprint: SprintRecord) {
+        self.sprints.push(sprint);
+        let excess = self.sprints.len().saturating_sub(KEPT_SPRINTS);
+        self.sprints.drain(..excess);
+    }
+
+    /// Sprints written in `project`, or in any project, latest first.
+    pub fn sprints<'a>(
+        &'a self,
+        project: Option<&'a Path>,
+    ) -> impl Iterator<Item = &'a SprintRecord> + 'a {
+        self.sprints
+            .iter()
+            .rev()
+            .filter(move |sprint| project.is_none() || sprint.project.as_deref() == project)
+    }
+}
+
+/// Add `words` to `date`, forgetting the oldest days beyond a year.
+fn add_day(days: &mut BTreeMap<NaiveDate, i64>, date: NaiveDate, words: i64) {
+    *days.entry(date).or_default() += words;
+    while days.len() > KEPT_DAYS {
+        days.pop_first();
+    }
+}
+
+/// The words written, as published under [`WRITING_HISTORY_KEY`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WritingHistory {
+    /// Open project, if any
+    pub project: Option<PathBuf>,
+    /// Words written over the sessions
+    pub log: WritingLog,
+    /// Words written since the application started
+    pub session_words: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(n: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 11, n).unwrap()
+    }
+
+    #[test]
+    fn test_streak_counts_days_in_a_row() {
+        let novel = Path::new("/novel");
+        let mut log = WritingLog::default();
+        for n in [1, 3, 4, 5] {
+            log.record(day(n), Some(novel), None, 500);
+        }
+        log.record(day(2), None, None, 100);
+        log.record(day(5), Some(novel), None, -500);
+        log.record(day(5), None, None, 200);
+
+        assert_eq!(log.streak(None, day(5)), 5);
+        assert_eq!(log.streak(None, day(6)), 5, "today is not over");
+        assert_eq!(log.streak(None, day(7)), 0);
+        // Deleting as much as was written is no writing
+        assert_eq!(log.streak(Some(novel), day(5)), 2);
+        assert_eq!(log.streak(Some(Path::new("/other")), day(5)), 0);
+    }
+
+    #[test]
+    fn test_old_days_are_forgotten() {
+        let mut log = WritingLog::default();
+        let start = day(1);
+        for n in 0..400 {
+            log.record(start + Days::new(n), Some(Path::new("/novel")), None, 1);
+        }
+        assert_eq!(log.days.len(), KEPT_DAYS);
+        assert_eq!(log.day(start), 0);
+        assert_eq!(log.project(Path::new("/novel")), 400);
+    }
+
+    #[test]
+    fn test_sprints_by_project() {
+        let sprint = |project: Option<&str>, words| SprintRecord {
+            started_at: crate::clock::Clock::EPOCH,
+            minutes: 25,
+            seconds: 25 * 60,
+            words,
+            project: project.map(PathBuf::from),
+        };
+        let mut log = WritingLog::default();
+        log.record_sprint(sprint(Some("/novel"), 400));
+        log.record_sprint(sprint(None, 100));
+        log.record_sprint(sprint(Some("/novel"), 600));
+
+        let novel: Vec<_> = log
+            .sprints(Some(Path::new("/novel")))
+            .map(|s| s.words)
+            .collect();
+        assert_eq!(novel, [600, 400]);
+        assert_eq!(log.sprints(None).count(), 3);
+
+        for _ in 0..KEPT_SPRINTS {
+            log.record_sprint(sprint(None, 1));
+        }
+        assert_eq!(log.sprints.len(), KEPT_SPRINTS);
+        assert_eq!(log.sprints(Some(Path::new("/novel"))).count(), 0);
+    }
+}
@@ -13,6 +13,12 @@
 //! - **AI Plugins**: Integrate AI functionality
 //! - **Tool Plugins**: Provide utility functions
 //!
+//! ## Features
+//!
+//! - `ui` (default): the [`PanelPlugin`] trait and the [`highlight`] module,
+//!   which depend on `egui`. Turn it off to use the API headless, without
+//!   any GUI dependency.
+//!
 //! ## Example Plugin
 //!
 //! ```rust
@@ -48,15 +54,47 @@
 //! }
 //! ```
 
+pub mod accessibility;
+pub mod branch;
+pub mod clock;
+pub mod command;
 pub mod context;
+pub mod diff;
+pub mod direction;
+pub mod document_snapshot;
+pub mod epigraph;
 pub mod event;
+pub mod export;
+pub mod front_matter;
+pub mod git_history;
+pub mod grammar;
+#[cfg(feature = "ui")]
+pub mod highlight;
+pub mod history;
+pub mod locale;
+pub mod metadata;
 pub mod panel;
 pub mod plugin;
+pub mod scene;
+pub mod search;
+pub mod snapshot;
+pub mod sprint;
+pub mod subscription;
+pub mod task;
+pub mod transaction;
+pub mod verse;
+pub mod wiki;
 
-pub use context::{PluginContext, SharedState};
+pub use command::{Command, Keymap, Shortcut};
+pub use context::{PluginContext, SharedState, SESSION_STATE_KEY};
 pub use event::{Event, EventHandler, EventType};
-pub use panel::{Panel, PanelContextMenuItem, PanelPlugin, PanelPosition, PanelSize};
+pub use export::{ExportPlugin, Manuscript, ManuscriptSection, SectionKind};
+#[cfg(feature = "ui")]
+pub use panel::PanelPlugin;
+pub use panel::{Panel, PanelContextMenuItem, PanelPosition, PanelSize, FOCUS_PANEL_REQUEST};
 pub use plugin::{Plugin, PluginInfo, PluginType};
+pub use subscription::{EventBusLink, EventFilter, Subscription};
+pub use task::{Cancelled, TaskHandle, TaskProgress, TaskSpawner, TaskState, TaskStatus};
 
 /// Result type used throughout the plugin API
 pub type Result<T> = std::result::Result<T, anyhow::Error>;
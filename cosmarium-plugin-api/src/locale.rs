@@ -0,0 +1,252 @@
+//! Numbers and dates written the way the user's language writes them.
+//!
+//! A [`Locale`] formats word counts with the digits and the thousands
+//! separators of a language, and dates with its date styles, from the CLDR
+//! data compiled into ICU4X. The application publishes the locale of its
+//! configured language under [`LOCALE_KEY`], for plugins to format what they
+//! show.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::locale::{DateStyle, Locale};
+//! use chrono::NaiveDate;
+//!
+//! let date = NaiveDate::from_ymd_opt(2024, 11, 20).unwrap();
+//!
+//! let english = Locale::new("en");
+//! assert_eq!(english.format_count(84_250), "84,250");
+//! assert_eq!(english.format_date(date, DateStyle::Medium), "Nov 20, 2024");
+//!
+//! let german = Locale::new("de");
+//! assert_eq!(german.format_count(84_250), "84.250");
+//! assert_eq!(german.format_date(date, DateStyle::Short), "20.11.24");
+//! ```
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use fixed_decimal::FixedDecimal;
+use icu_calendar::{Date, DateTime, Gregorian};
+use icu_datetime::options::length;
+use icu_datetime::{TypedDateFormatter, TypedDateTimeFormatter};
+use icu_decimal::FixedDecimalFormatter;
+use icu_provider::DataLocale;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+/// Shared state key ([`Locale`]) of the locale of the application language.
+pub const LOCALE_KEY: &str = "app_locale";
+
+/// Language used when the configured one cannot be read.
+const FALLBACK_LANGUAGE: &str = "en";
+
+/// Length of a formatted date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DateStyle {
+    /// Digits only, e.g. `11/20/24`
+    Short,
+    /// Abbreviated month, e.g. `Nov 20, 2024`
+    #[default]
+    Medium,
+    /// Month in full, e.g. `November 20, 2024`
+    Long,
+    /// With the day of the week, e.g. `Wednesday, November 20, 2024`
+    Full,
+}
+
+impl DateStyle {
+    fn length(self) -> length::Date {
+        match self {
+            Self::Short => length::Date::Short,
+            Self::Medium => length::Date::Medium,
+            Self::Long => length::Date::Long,
+            Self::Full => length::Date::Full,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Formats of numbers and dates in a language.
+///
+/// Formatters are loaded the first time they are used, and shared by the
+/// clones of the locale. Values that cannot be formatted, which the compiled
+/// data should not allow, fall back to plain Rust formatting.
+#[derive(Clone)]
+pub struct Locale {
+    inner: Arc<Formatters>,
+}
+
+struct Formatters {
+    /// BCP 47 tag of the language, e.g. `pt-BR`
+    tag: String,
+    data: DataLocale,
+    numbers: OnceLock<Option<FixedDecimalFormatter>>,
+    dates: [OnceLock<Option<TypedDateFormatter<Gregorian>>>; 4],
+    date_times: [OnceLock<Option<TypedDateTimeFormatter<Gregorian>>>; 4],
+}
+
+impl Locale {
+    /// Locale of `language`, an ISO 639-1 code or a locale such as `fr-CA`
+    /// or `pt_BR`. Languages that cannot be read fall back to English.
+    pub fn new(language: &str) -> Self {
+        let tag = language.trim().replace('_', "-");
+        let locale = tag.parse::<icu_locid::Locale>().unwrap_or_else(|e| {
+            tracing::warn!("Unknown language '{}', using English: {}", language, e);
+            icu_locid::Locale::UND
+        });
+        let locale = if locale == icu_locid::Locale::UND {
+            FALLBACK_LANGUAGE.parse().unwrap_or(locale)
+        } else {
+            locale
+        };
+        Self {
+            inner: Arc::new(Formatters {
+                tag: locale.to_string(),
+                data: DataLocale::from(&locale),
+                numbers: OnceLock::new(),
+                dates: Default::default(),
+                date_times: Default::default(),
+            }),
+        }
+    }
+
+    /// BCP 47 tag of the language, e.g. `en` or `pt-BR`.
+    pub fn language(&self) -> &str {
+        &self.inner.tag
+    }
+
+    /// A count, with the separators of the language, e.g. `12,500` in
+    /// English and `12 500` in French.
+    pub fn format_count(&self, count: usize) -> String {
+        self.format_decimal(FixedDecimal::from(count))
+            .unwrap_or_else(|| count.to_string())
+    }
+
+    /// A number that may be negative, such as the words written in a day
+    /// where more were deleted.
+    pub fn format_number(&self, number: i64) -> String {
+        self.format_decimal(FixedDecimal::from(number))
+            .unwrap_or_else(|| number.to_string())
+    }
+
+    /// `date` in the `style` of the language.
+    pub fn format_date(&self, date: NaiveDate, style: DateStyle) -> String {
+        let formatter = self.inner.dates[style.index()].get_or_init(|| {
+            TypedDateFormatter::try_new_with_length(&self.inner.data, style.length())
+                .inspect_err(|e| tracing::warn!("No date format for {}: {}", self.language(), e))
+                .ok()
+        });
+        let formatted = formatter.as_ref().and_then(|formatter| {
+            let date =
+                Date::try_new_gregorian_date(date.year(), date.month() as u8, date.day() as u8)
+                    .ok()?;
+            Some(formatter.format_to_string(&date))
+        });
+        formatted.unwrap_or_else(|| date.format("%Y-%m-%d").to_string())
+    }
+
+    /// `time`, to the minute, with its date in the `style` of the language.
+    pub fn format_date_time(&self, time: NaiveDateTime, style: DateStyle) -> String {
+        let formatter = self.inner.date_times[style.index()].get_or_init(|| {
+            let options = length::Bag::from_date_time_style(style.length(), length::Time::Short);
+            TypedDateTimeFormatter::try_new(&self.inner.data, options.into())
+                .inspect_err(|e| tracing::warn!("No time format for {}: {}", self.language(), e))
+                .ok()
+        });
+        let formatted = formatter.as_ref().and_then(|formatter| {
+            let time = DateTime::try_new_gregorian_datetime(
+                time.year(),
+                time.month() as u8,
+                time.day() as u8,
+                time.hour() as u8,
+                time.minute() as u8,
+                time.second() as u8,
+            )
+            .ok()?;
+            Some(formatter.format_to_string(&time))
+        });
+        formatted.unwrap_or_else(|| time.format("%Y-%m-%d %H:%M").to_string())
+    }
+
+    fn format_decimal(&self, number: FixedDecimal) -> Option<String> {
+        let formatter = self.inner.numbers.get_or_init(|| {
+            FixedDecimalFormatter::try_new(&self.inner.data, Default::default())
+                .inspect_err(|e| tracing::warn!("No number format for {}: {}", self.language(), e))
+                .ok()
+        });
+        formatter
+            .as_ref()
+            .map(|formatter| formatter.format_to_string(&number))
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::new(FALLBACK_LANGUAGE)
+    }
+}
+
+impl PartialEq for Locale {
+    fn eq(&self, other: &Self) -> bool {
+        self.language() == other.language()
+    }
+}
+
+impl fmt::Debug for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Locale").field(&self.language()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_separators_follow_the_language() {
+        assert_eq!(Locale::new("en").format_count(1_234_567), "1,234,567");
+        assert_eq!(Locale::new("fr").format_count(12_500), "12\u{202f}500");
+        assert_eq!(Locale::new("de_CH").format_count(12_500), "12’500");
+        assert_eq!(Locale::new("en-IN").format_count(1_234_567), "12,34,567");
+        assert_eq!(Locale::new("en").format_number(-1200), "-1,200");
+        assert_eq!(Locale::new("ar-EG").format_count(42), "٤٢");
+    }
+
+    #[test]
+    fn test_date_styles() {
+        let date = NaiveDate::from_ymd_opt(2024, 11, 20).unwrap();
+        let english = Locale::new("en");
+        assert_eq!(english.format_date(date, DateStyle::Short), "11/20/24");
+        assert_eq!(
+            english.format_date(date, DateStyle::Full),
+            "Wednesday, November 20, 2024"
+        );
+        assert_eq!(
+            Locale::new("fr").format_date(date, DateStyle::Long),
+            "20 novembre 2024"
+        );
+        assert_eq!(
+            Locale::new("en-GB").format_date(date, DateStyle::Short),
+            "20/11/2024"
+        );
+
+        let time = date.and_hms_opt(14, 5, 0).unwrap();
+        assert_eq!(
+            Locale::new("de").format_date_time(time, DateStyle::Medium),
+            "20.11.2024, 14:05"
+        );
+    }
+
+    #[test]
+    fn test_unknown_languages_fall_back_to_english() {
+        for language in ["", "not a language!"] {
+            let locale = Locale::new(language);
+            assert_eq!(locale.language(), "en");
+            assert_eq!(locale.format_count(1000), "1,000");
+        }
+        assert_eq!(Locale::new("pt_BR").language(), "pt-BR");
+        assert_eq!(Locale::new("fr"), Locale::new(" fr "));
+    }
+}
@@ -0,0 +1,226 @@
+//! Structured metadata of the scenes and chapters of a project.
+//!
+//! Each part, chapter and document of the project structure carries a
+//! [`SceneMetadata`]: a synopsis, the point-of-view character, a revision
+//! [`Status`], a label color and a target word count. They are stored with
+//! the free-form metadata of the structure nodes, under the keys of
+//! [`SceneMetadata::from_fields`], so the binder, the outline, the compile
+//! settings and the search all read the same values.
+//!
+//! The application publishes the metadata of the active document under
+//! [`ACTIVE_METADATA_KEY`] and stores the changes plugins send with
+//! [`METADATA_UPDATE_REQUEST`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::metadata::{SceneMetadata, Status};
+//! use std::collections::BTreeMap;
+//!
+//! let mut fields = BTreeMap::new();
+//! fields.insert("status".to_string(), "revised".to_string());
+//! fields.insert("pov".to_string(), "Mira".to_string());
+//!
+//! let mut metadata = SceneMetadata::from_fields(&fields);
+//! assert_eq!(metadata.status, Status::Revised);
+//! assert_eq!(metadata.pov, "Mira");
+//!
+//! metadata.target_words = Some(2500);
+//! metadata.write_fields(&mut fields);
+//! assert_eq!(fields["target_words"], "2500");
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Shared state key (`Option<NodeMetadata>`) of the metadata of the active
+/// document, `None` when it is not part of the project structure.
+pub const ACTIVE_METADATA_KEY: &str = "active_document_metadata";
+
+/// Shared state key (`Option<NodeMetadata>`) of metadata to store in the
+/// project structure, served by the application.
+pub const METADATA_UPDATE_REQUEST: &str = "document_metadata_update_request";
+
+const SYNOPSIS: &str = "synopsis";
+const POV: &str = "pov";
+const STATUS: &str = "status";
+const LABEL: &str = "label";
+const TARGET_WORDS: &str = "target_words";
+
+/// How far the writing of a scene or chapter is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// First draft
+    #[default]
+    Draft,
+    /// Revised at least once
+    Revised,
+    /// Ready to be compiled
+    Final,
+}
+
+impl Status {
+    /// Every status, in writing order.
+    pub const ALL: [Status; 3] = [Self::Draft, Self::Revised, Self::Final];
+
+    /// Stored name of the status.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Revised => "revised",
+            Self::Final => "final",
+        }
+    }
+
+    /// Human-readable name of the status.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Draft => "Draft",
+            Self::Revised => "Revised",
+            Self::Final => "Final",
+        }
+    }
+
+    /// Status stored as `name`, regardless of case.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|status| status.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// Metadata of a scene or chapter.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SceneMetadata {
+    /// Summary of what happens, for the corkboard and the outline
+    #[serde(default)]
+    pub synopsis: String,
+    /// Point-of-view character, empty if none
+    #[serde(default)]
+    pub pov: String,
+    /// Revision status
+    #[serde(default)]
+    pub status: Status,
+    /// Label color, as RGB
+    #[serde(default)]
+    pub label: Option<[u8; 3]>,
+    /// Word count to reach
+    #[serde(default)]
+    pub target_words: Option<usize>,
+}
+
+impl SceneMetadata {
+    /// Metadata read from the free-form `fields` of a structure node:
+    /// `synopsis`, `pov`, `status` (`draft`, `revised` or `final`), `label`
+    /// (`#rrggbb`) and `target_words`. Unreadable values are left out.
+    pub fn from_fields(fields: &BTreeMap<String, String>) -> Self {
+        let field = |key: &str| fields.get(key).map(|value| value.trim());
+        Self {
+            synopsis: field(SYNOPSIS).unwrap_or_default().to_string(),
+            pov: field(POV).unwrap_or_default().to_string(),
+            status: field(STATUS).and_then(Status::parse).unwrap_or_default(),
+            label: field(LABEL).and_then(parse_color),
+            target_words: field(TARGET_WORDS).and_then(|value| value.parse().ok()),
+        }
+    }
+
+    /// Write the metadata to the free-form `fields` of a structure node,
+    /// removing the keys of the values left empty.
+    pub fn write_fields(&self, fields: &mut BTreeMap<String, String>) {
+        let mut set = |key: &str, value: Option<String>| match value {
+            Some(value) => {
+                fields.insert(key.to_string(), value);
+            }
+            None => {
+                fields.remove(key);
+            }
+        };
+        let text = |value: &str| (!value.trim().is_empty()).then(|| value.trim().to_string());
+        set(SYNOPSIS, text(&self.synopsis));
+        set(POV, text(&self.pov));
+        set(
+            STATUS,
+            (self.status != Status::Draft).then(|| self.status.as_str().to_string()),
+        );
+        set(
+            LABEL,
+            self.label
+                .map(|[r, g, b]| format!("#{:02x}{:02x}{:02x}", r, g, b)),
+        );
+        set(TARGET_WORDS, self.target_words.map(|n| n.to_string()));
+    }
+
+    /// Progress towards the target word count given the `words` written,
+    /// from 0 to 1, if there is a target.
+    pub fn progress(&self, words: usize) -> Option<f32> {
+        let target = self.target_words.filter(|target| *target > 0)?;
+        Some((words as f32 / target as f32).min(1.0))
+    }
+}
+
+/// `#rrggbb` as RGB.
+fn parse_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Metadata of a node of the project structure.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeMetadata {
+    /// Structure node
+    pub node: Uuid,
+    /// Title of the node
+    pub title: String,
+    /// Metadata of the node
+    pub metadata: SceneMetadata,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields_round_trip() {
+        let metadata = SceneMetadata {
+            synopsis: "Mira reaches the harbor.".to_string(),
+            pov: "Mira".to_string(),
+            status: Status::Final,
+            label: Some([0xd0, 0x40, 0x10]),
+            target_words: Some(1800),
+        };
+        let mut fields = BTreeMap::new();
+        fields.insert("location".to_string(), "Harbor".to_string());
+        metadata.write_fields(&mut fields);
+        assert_eq!(fields["label"], "#d04010");
+        assert_eq!(fields["location"], "Harbor");
+        assert_eq!(SceneMetadata::from_fields(&fields), metadata);
+
+        // Default values take no field
+        SceneMetadata::default().write_fields(&mut fields);
+        assert_eq!(fields.keys().collect::<Vec<_>>(), vec!["location"]);
+
+        fields.insert("status".to_string(), " Revised ".to_string());
+        fields.insert("label".to_string(), "red".to_string());
+        fields.insert("target_words".to_string(), "many".to_string());
+        let read = SceneMetadata::from_fields(&fields);
+        assert_eq!(
+            (read.status, read.label, read.target_words),
+            (Status::Revised, None, None)
+        );
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut metadata = SceneMetadata::default();
+        assert_eq!(metadata.progress(100), None);
+        metadata.target_words = Some(400);
+        assert_eq!(metadata.progress(100), Some(0.25));
+        assert_eq!(metadata.progress(900), Some(1.0));
+    }
+}
@@ -30,15 +30,24 @@
 //! }
 //! ```
 
+#[cfg(feature = "ui")]
 use crate::{PluginContext, Result};
+#[cfg(feature = "ui")]
 use egui::Ui;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// Shared state key (`Option<String>`) of the panel plugin, by name, that a
+/// plugin asks the application to show and bring forward.
+pub const FOCUS_PANEL_REQUEST: &str = "focus_panel_request";
+
 /// Trait for plugins that provide UI panels.
 ///
 /// Panel plugins create dockable UI components that can be positioned around
 /// the main content area. They handle their own rendering and state management.
+///
+/// Only available with the `ui` feature.
+#[cfg(feature = "ui")]
 pub trait PanelPlugin: Send + Sync {
     /// Get the display title for this panel.
     ///
@@ -396,7 +405,7 @@ impl Panel {
 }
 
 /// Position where a panel can be docked in the UI.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PanelPosition {
     /// Docked to the left side of the main content
     Left,
@@ -601,13 +610,16 @@ impl PanelContextMenuItem {
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "ui")]
     use crate::PluginContext;
 
+    #[cfg(feature = "ui")]
     struct TestPanel {
         title: String,
         content: String,
     }
 
+    #[cfg(feature = "ui")]
     impl PanelPlugin for TestPanel {
         fn panel_title(&self) -> &str {
             &self.title
@@ -718,6 +730,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "ui")]
     fn test_panel_plugin_defaults() {
         let panel = TestPanel {
             title: "Test Panel".to_string(),
@@ -737,6 +750,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "ui")]
     fn test_panel_id_generation() {
         let panel1 = TestPanel {
             title: "Same Title".to_string(),
@@ -0,0 +1,326 @@
+//! Scene metadata read from scene headings.
+//!
+//! Many authors open each scene with a heading that follows a fixed
+//! convention, such as `### Harbor — Dawn — Mira`. A project declares its
+//! convention as a [`SceneHeadingFormat`] (`### {location} — {time} — {POV}`)
+//! and every heading of that level which follows it yields a [`Scene`]
+//! with its fields, so that plugins get scene metadata without any front
+//! matter.
+//!
+//! The application publishes the active project's format under
+//! [`SCENE_HEADING_FORMAT_KEY`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::scene::{parse_scenes, SceneHeadingFormat};
+//!
+//! let format = SceneHeadingFormat::parse("### {location} — {time} — {POV}").unwrap();
+//! let scenes = parse_scenes("# One\n\n### Harbor — Dawn — Mira\n\nFog.", &format);
+//!
+//! assert_eq!(scenes[0].line, 3);
+//! assert_eq!(scenes[0].field("location"), Some("Harbor"));
+//! assert_eq!(scenes[0].field("pov"), Some("Mira"));
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Configuration key under which the active project's scene heading format
+/// is published to plugins.
+pub const SCENE_HEADING_FORMAT_KEY: &str = "scene_heading_format";
+
+/// Why a scene heading format was rejected.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SceneFormatError {
+    /// The format does not start with `#` markers
+    #[error("a scene heading format starts with 1 to 6 '#' and a space")]
+    MissingHeadingLevel,
+    /// A `{` has no matching `}`
+    #[error("unclosed '{{' in scene heading format")]
+    UnclosedField,
+    /// A field has no name
+    #[error("empty field name in scene heading format")]
+    EmptyField,
+    /// Two fields follow each other without text between them
+    #[error("fields '{0}' and '{1}' need a separator between them")]
+    AdjacentFields(String, String),
+    /// The format has no field at all
+    #[error("a scene heading format needs at least one {{field}}")]
+    NoFields,
+}
+
+/// A piece of a scene heading format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// Text written as is, spaces around it being optional
+    Literal(String),
+    /// A field, by lowercase name
+    Field(String),
+}
+
+/// A project's scene heading convention.
+///
+/// Fields are written `{name}`; their names are case-insensitive. Text
+/// between fields separates them, and spaces around it do not matter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SceneHeadingFormat {
+    /// The format as written
+    pattern: String,
+    /// Heading level of scene headings
+    level: usize,
+    /// Literals and fields following the heading markers
+    segments: Vec<Segment>,
+}
+
+impl SceneHeadingFormat {
+    /// Parse a format such as `### {location} — {time} — {POV}`.
+    pub fn parse(pattern: &str) -> Result<Self, SceneFormatError> {
+        let pattern = pattern.trim();
+        let level = pattern.chars().take_while(|&c| c == '#').count();
+        let rest = pattern[level..]
+            .strip_prefix(' ')
+            .filter(|_| (1..=6).contains(&level))
+            .ok_or(SceneFormatError::MissingHeadingLevel)?;
+
+        let mut segments = Vec::new();
+        let mut remaining = rest.trim_start();
+        while !remaining.is_empty() {
+            match remaining.find('{') {
+                Some(0) => {
+                    let end = remaining.find('}').ok_or(SceneFormatError::UnclosedField)?;
+                    let name = remaining[1..end].trim().to_lowercase();
+                    if name.is_empty() {
+                        return Err(SceneFormatError::EmptyField);
+                    }
+                    if let Some(Segment::Field(previous)) = segments.last() {
+                        return Err(SceneFormatError::AdjacentFields(previous.clone(), name));
+                    }
+                    segments.push(Segment::Field(name));
+                    remaining = &remaining[end + 1..];
+                }
+                found => {
+                    let end = found.unwrap_or(remaining.len());
+                    let literal = remaining[..end].trim();
+                    if literal.is_empty() {
+                        // Only spaces between two fields
+                        if let (Some(Segment::Field(previous)), Some(start)) =
+                            (segments.last(), found)
+                        {
+                            let next = remaining[start + 1..]
+                                .split('}')
+                                .next()
+                                .unwrap_or_default()
+                                .trim()
+                                .to_lowercase();
+                            return Err(SceneFormatError::AdjacentFields(previous.clone(), next));
+                        }
+                    } else {
+                        segments.push(Segment::Literal(literal.to_string()));
+                    }
+                    remaining = &remaining[end..];
+                }
+            }
+        }
+
+        if !segments.iter().any(|s| matches!(s, Segment::Field(_))) {
+            return Err(SceneFormatError::NoFields);
+        }
+        Ok(Self {
+            pattern: pattern.to_string(),
+            level,
+            segments,
+        })
+    }
+
+    /// The format as written.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Heading level of scene headings.
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Field names, lowercase, in heading order.
+    pub fn fields(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|s| match s {
+            Segment::Field(name) => Some(name.as_str()),
+            Segment::Literal(_) => None,
+        })
+    }
+
+    /// Fields of a heading line, if it follows the format.
+    pub fn match_heading(&self, line: &str) -> Option<BTreeMap<String, String>> {
+        let line = line.trim_end();
+        let level = line.chars().take_while(|&c| c == '#').count();
+        if level != self.level {
+            return None;
+        }
+        let mut remaining = line[level..].strip_prefix(' ')?.trim();
+
+        let mut fields = BTreeMap::new();
+        let mut segments = self.segments.iter().peekable();
+        while let Some(segment) = segments.next() {
+            match segment {
+                Segment::Literal(literal) => {
+                    remaining = remaining.strip_prefix(literal.as_str())?.trim_start();
+                }
+                Segment::Field(name) => {
+                    let value = match segments.peek() {
+                        Some(Segment::Literal(next)) => {
+                            let end = remaining.find(next.as_str())?;
+                            let value = &remaining[..end];
+                            remaining = &remaining[end..];
+                            value
+                        }
+                        _ => std::mem::take(&mut remaining),
+                    };
+                    let value = value.trim();
+                    if !value.is_empty() {
+                        fields.insert(name.clone(), value.to_string());
+                    }
+                }
+            }
+        }
+        remaining.is_empty().then_some(fields)
+    }
+}
+
+impl TryFrom<String> for SceneHeadingFormat {
+    type Error = SceneFormatError;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Self::parse(&pattern)
+    }
+}
+
+impl From<SceneHeadingFormat> for String {
+    fn from(format: SceneHeadingFormat) -> Self {
+        format.pattern
+    }
+}
+
+/// A scene found by its heading.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scene {
+    /// Line of the heading, starting at 1
+    pub line: usize,
+    /// Heading text, without the `#` markers
+    pub heading: String,
+    /// Fields of the heading, by lowercase name
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Scene {
+    /// Value of a field, whatever the case of `name`.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(&name.to_lowercase()).map(String::as_str)
+    }
+}
+
+/// Scenes of a Markdown document whose headings follow `format`.
+///
+/// Headings inside fenced code blocks are ignored.
+pub fn parse_scenes(markdown: &str, format: &SceneHeadingFormat) -> Vec<Scene> {
+    let mut scenes = Vec::new();
+    let mut in_code_block = false;
+    for (i, line) in markdown.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        if let Some(fields) = format.match_heading(line) {
+            scenes.push(Scene {
+                line: i + 1,
+                heading: line.trim_start_matches('#').trim().to_string(),
+                fields,
+            });
+        }
+    }
+    scenes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(pattern: &str) -> SceneHeadingFormat {
+        SceneHeadingFormat::parse(pattern).unwrap()
+    }
+
+    #[test]
+    fn test_format_parsing() {
+        let format = format("### {Location} — {time} — {POV}");
+        assert_eq!(format.level(), 3);
+        assert_eq!(
+            format.fields().collect::<Vec<_>>(),
+            vec!["location", "time", "pov"]
+        );
+
+        assert_eq!(
+            SceneHeadingFormat::parse("{location}"),
+            Err(SceneFormatError::MissingHeadingLevel)
+        );
+        assert_eq!(
+            SceneHeadingFormat::parse("## {location"),
+            Err(SceneFormatError::UnclosedField)
+        );
+        assert_eq!(
+            SceneHeadingFormat::parse("## Scene"),
+            Err(SceneFormatError::NoFields)
+        );
+        assert_eq!(
+            SceneHeadingFormat::parse("## {location} {time}"),
+            Err(SceneFormatError::AdjacentFields(
+                "location".into(),
+                "time".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_headings_yield_fields() {
+        let format = format("## Scene {number}: {location} ({time})");
+        let fields = format
+            .match_heading("## Scene 4: The old mill (night)")
+            .unwrap();
+        assert_eq!(fields["number"], "4");
+        assert_eq!(fields["location"], "The old mill");
+        assert_eq!(fields["time"], "night");
+
+        // Wrong level, missing separator or trailing text
+        assert!(format.match_heading("### Scene 4: Mill (night)").is_none());
+        assert!(format.match_heading("## Scene 4 Mill (night)").is_none());
+        assert!(format
+            .match_heading("## Scene 4: Mill (night) again")
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_scenes_skips_code_blocks() {
+        let format = format("### {location} — {POV}");
+        let text = "### Harbor — Mira\n\n```\n### Code — Block\n```\n### Keep—Tam\n### Harbor";
+        let scenes = parse_scenes(text, &format);
+        assert_eq!(scenes.len(), 2);
+        assert_eq!(scenes[0].heading, "Harbor — Mira");
+        assert_eq!(scenes[1].line, 6);
+        assert_eq!(scenes[1].field("POV"), Some("Tam"));
+    }
+
+    #[test]
+    fn test_format_is_stored_as_its_pattern() {
+        let format = format("### {location} — {time}");
+        let json = serde_json::to_string(&format).unwrap();
+        assert_eq!(json, "\"### {location} — {time}\"");
+        let back: SceneHeadingFormat = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, format);
+        assert!(serde_json::from_str::<SceneHeadingFormat>("\"{location}\"").is_err());
+    }
+}
@@ -0,0 +1,432 @@
+//! Project-wide search.
+//!
+//! The application keeps an index of the documents, notes, entity sheets,
+//! synopses and metadata of the active project, updated as documents
+//! change. Panels post a [`SearchQuery`] under [`SEARCH_REQUEST`]; the
+//! application runs it in the background and publishes the
+//! [`SearchResponse`] under [`SEARCH_RESULTS_KEY`], with each match's
+//! neighbouring lines for context. A query can also reach the recent
+//! projects, whose matches name the project they were found in.
+//!
+//! Text is replaced across the project in three steps, each posted as a
+//! [`ReplaceRequest`] under [`REPLACE_REQUEST`]: the application previews
+//! every match of a [`ReplaceQuery`], the panel unselects the matches to
+//! keep, then the application replaces the others in all their documents at
+//! once, a change that can be undone as a whole. The outcome of each step is
+//! published as a [`ReplaceStatus`] under [`REPLACE_STATUS_KEY`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::search::{SearchQuery, SearchSource, SEARCH_REQUEST};
+//! use cosmarium_plugin_api::PluginContext;
+//!
+//! let query = SearchQuery::new("\"the old inn\" -tavern").in_sources([SearchSource::Document]);
+//! assert_eq!(query.words(), vec!["the", "old", "inn", "tavern"]);
+//!
+//! let mut ctx = PluginContext::new();
+//! ctx.set_shared_state(SEARCH_REQUEST, Some(query));
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// Shared state key (`Option<SearchQuery>`) of the search to run, served by
+/// the application.
+pub const SEARCH_REQUEST: &str = "project_search_request";
+
+/// Shared state key (`Option<SearchResponse>`) of the results of the last
+/// search.
+pub const SEARCH_RESULTS_KEY: &str = "project_search_results";
+
+/// Shared state key (`Option<ReplaceRequest>`) of the replacement step to
+/// run, served by the application.
+pub const REPLACE_REQUEST: &str = "project_replace_request";
+
+/// Shared state key (`Option<ReplaceStatus>`) of the outcome of the last
+/// replacement step.
+pub const REPLACE_STATUS_KEY: &str = "project_replace_status";
+
+/// Where a piece of searchable text comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchSource {
+    /// Text of manuscript documents
+    Document,
+    /// Comments left in manuscript documents
+    Annotation,
+    /// Research and planning notes
+    Note,
+    /// Synopses of structure nodes
+    Synopsis,
+    /// Character sheets and other entity descriptions
+    Entity,
+    /// Front matter, node and project metadata fields
+    Metadata,
+}
+
+impl SearchSource {
+    /// All sources, in the order results are grouped.
+    pub const ALL: [SearchSource; 6] = [
+        Self::Document,
+        Self::Annotation,
+        Self::Note,
+        Self::Synopsis,
+        Self::Entity,
+        Self::Metadata,
+    ];
+
+    /// Stable identifier, as serialized.
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Document => "document",
+            Self::Annotation => "annotation",
+            Self::Note => "note",
+            Self::Synopsis => "synopsis",
+            Self::Entity => "entity",
+            Self::Metadata => "metadata",
+        }
+    }
+
+    /// Label of the results group.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Document => "Documents",
+            Self::Annotation => "Annotations",
+            Self::Note => "Notes",
+            Self::Synopsis => "Synopses",
+            Self::Entity => "Entities",
+            Self::Metadata => "Metadata",
+        }
+    }
+}
+
+/// What to search for, and where.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SearchQuery {
+    /// Text to find, in query syntax
+    pub text: String,
+    /// Whether capitals must match the words of the query
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Sources to search, every source when empty
+    #[serde(default)]
+    pub sources: BTreeSet<SearchSource>,
+    /// Only search the files under this folder, relative to the project
+    /// root (such as `entities/characters`)
+    #[serde(default)]
+    pub folder: Option<String>,
+    /// Whether the recent projects are searched too, not only the active
+    /// one
+    #[serde(default)]
+    pub all_projects: bool,
+}
+
+impl SearchQuery {
+    /// Search every source for `text`, ignoring case.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Restrict the search to some sources.
+    pub fn in_sources(mut self, sources: impl IntoIterator<Item = SearchSource>) -> Self {
+        self.sources = sources.into_iter().collect();
+        self
+    }
+
+    /// Restrict the search to the files under a folder.
+    pub fn within(mut self, folder: impl Into<String>) -> Self {
+        self.folder = Some(folder.into());
+        self
+    }
+
+    /// Search the recent projects too.
+    pub fn across_projects(mut self) -> Self {
+        self.all_projects = true;
+        self
+    }
+
+    /// Folder the search is restricted to, without its outer slashes.
+    pub fn folder(&self) -> Option<&str> {
+        self.folder
+            .as_deref()
+            .map(|f| f.trim_matches('/'))
+            .filter(|f| !f.is_empty())
+    }
+
+    /// Words of the query as typed, without operators and quotes.
+    pub fn words(&self) -> Vec<&str> {
+        self.text
+            .split(|c: char| c.is_whitespace() || matches!(c, '"' | '(' | ')'))
+            .map(|word| word.trim_start_matches(['+', '-']))
+            .filter(|word| !word.is_empty() && !matches!(*word, "AND" | "OR" | "NOT"))
+            .collect()
+    }
+}
+
+/// A match of a search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit {
+    /// Where the text comes from
+    pub source: SearchSource,
+    /// File of the match, relative to the project root with `/` separators
+    pub path: Option<String>,
+    /// File name, node title or `"Project"`
+    pub title: String,
+    /// Metadata field of the match
+    pub field: Option<String>,
+    /// Line of the match in its file, starting at 1 (`None` for matches
+    /// outside files and in titles)
+    pub line: Option<usize>,
+    /// Column of the match in characters, starting at 1
+    pub column: usize,
+    /// The line before the match, shortened, if it is not blank
+    #[serde(default)]
+    pub before: Option<String>,
+    /// The line of the match, shortened around it
+    pub snippet: String,
+    /// The line after the match, shortened, if it is not blank
+    #[serde(default)]
+    pub after: Option<String>,
+    /// Relevance of the match, higher first
+    pub score: f32,
+    /// Folder of the project of the match, if it is not the active project
+    #[serde(default)]
+    pub project: Option<PathBuf>,
+}
+
+/// Results of a search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResponse {
+    /// The search
+    pub query: SearchQuery,
+    /// Project directory the paths of the matches are relative to
+    pub root: PathBuf,
+    /// Matches grouped by source in [`SearchSource::ALL`] order, the most
+    /// relevant first in each group
+    pub hits: Vec<SearchHit>,
+    /// Why the search failed, if it did
+    pub error: Option<String>,
+}
+
+impl SearchResponse {
+    /// File of `hit`, if it has one.
+    pub fn file(&self, hit: &SearchHit) -> Option<PathBuf> {
+        let path = hit.path.as_deref()?;
+        let root = hit.project.as_ref().unwrap_or(&self.root);
+        Some(
+            path.split('/')
+                .fold(root.clone(), |file, part| file.join(part)),
+        )
+    }
+
+    /// Sources with matches and their matches, in order.
+    pub fn groups(&self) -> Vec<(SearchSource, &[SearchHit])> {
+        self.hits
+            .chunk_by(|a, b| a.source == b.source)
+            .map(|hits| (hits[0].source, hits))
+            .collect()
+    }
+}
+
+/// Text to replace across the project, and its replacement.
+///
+/// Unlike a [`SearchQuery`], the text is found exactly as typed.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ReplaceQuery {
+    /// Text to find
+    pub find: String,
+    /// Text to put in its place
+    pub replace: String,
+    /// Whether capitals must match
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Whether only whole words match
+    #[serde(default)]
+    pub whole_word: bool,
+    /// Sources to replace in, among documents, notes and entities; all of
+    /// them when empty
+    #[serde(default)]
+    pub sources: BTreeSet<SearchSource>,
+    /// Only replace in the files under this folder, relative to the project
+    /// root
+    #[serde(default)]
+    pub folder: Option<String>,
+}
+
+impl ReplaceQuery {
+    /// Replace `find` by `replace` everywhere, ignoring case.
+    pub fn new(find: impl Into<String>, replace: impl Into<String>) -> Self {
+        Self {
+            find: find.into(),
+            replace: replace.into(),
+            ..Self::default()
+        }
+    }
+}
+
+/// A match of a replacement, shown as the change it makes to its line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplaceMatch {
+    /// Line of the match, starting at 1
+    pub line: usize,
+    /// Column of the match in characters, starting at 1
+    pub column: usize,
+    /// Byte offset of the match in its document
+    pub start: usize,
+    /// The text matched
+    pub found: String,
+    /// The line before the match, shortened
+    pub prefix: String,
+    /// The line after the match, shortened
+    pub suffix: String,
+    /// Whether the match is replaced
+    pub selected: bool,
+}
+
+/// The matches of a replacement in a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileReplacement {
+    /// File, relative to the project root with `/` separators
+    pub path: String,
+    /// File name
+    pub title: String,
+    /// Matches, in order
+    pub matches: Vec<ReplaceMatch>,
+}
+
+impl FileReplacement {
+    /// Number of matches replaced.
+    pub fn selected(&self) -> usize {
+        self.matches.iter().filter(|m| m.selected).count()
+    }
+}
+
+/// Every match of a replacement, before it is applied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplacePreview {
+    /// The replacement
+    pub query: ReplaceQuery,
+    /// Project directory the paths of the files are relative to
+    pub root: PathBuf,
+    /// Files with matches, in path order
+    pub files: Vec<FileReplacement>,
+}
+
+impl ReplacePreview {
+    /// Number of matches.
+    pub fn len(&self) -> usize {
+        self.files.iter().map(|file| file.matches.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Number of matches replaced.
+    pub fn selected(&self) -> usize {
+        self.files.iter().map(FileReplacement::selected).sum()
+    }
+
+    /// Full path of `file`.
+    pub fn file(&self, file: &FileReplacement) -> PathBuf {
+        file.path
+            .split('/')
+            .fold(self.root.clone(), |path, part| path.join(part))
+    }
+}
+
+/// A step of a replacement across the project.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplaceRequest {
+    /// Find the matches of the query
+    Preview(ReplaceQuery),
+    /// Replace the selected matches of the preview
+    Apply(ReplacePreview),
+    /// Restore the documents changed by the last replacement
+    Undo,
+}
+
+/// Outcome of a [`ReplaceRequest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplaceStatus {
+    /// The matches found
+    Preview(ReplacePreview),
+    /// The matches were replaced
+    Applied {
+        /// Matches replaced
+        matches: usize,
+        /// Documents changed
+        documents: usize,
+    },
+    /// The last replacement was undone
+    Undone {
+        /// Documents restored
+        documents: usize,
+    },
+    /// Why the step failed; nothing was changed
+    Failed(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn hit(source: SearchSource, title: &str) -> SearchHit {
+        SearchHit {
+            source,
+            path: None,
+            title: title.to_string(),
+            field: None,
+            line: None,
+            column: 1,
+            before: None,
+            snippet: String::new(),
+            after: None,
+            score: 1.0,
+            project: None,
+        }
+    }
+
+    #[test]
+    fn test_response_groups() {
+        let response = SearchResponse {
+            query: SearchQuery::new("inn").within("/entities/"),
+            root: PathBuf::from("/novel"),
+            hits: vec![
+                hit(SearchSource::Document, "a"),
+                hit(SearchSource::Document, "b"),
+                hit(SearchSource::Entity, "c"),
+            ],
+            error: None,
+        };
+        assert_eq!(response.query.folder(), Some("entities"));
+        let groups: Vec<(SearchSource, usize)> = response
+            .groups()
+            .into_iter()
+            .map(|(source, hits)| (source, hits.len()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![(SearchSource::Document, 2), (SearchSource::Entity, 1)]
+        );
+
+        let mut hit = hit(SearchSource::Note, "inn");
+        assert_eq!(response.file(&hit), None);
+        hit.path = Some("notes/places/inn.md".to_string());
+        assert_eq!(
+            response.file(&hit).as_deref(),
+            Some(Path::new("/novel/notes/places/inn.md"))
+        );
+        hit.project = Some(PathBuf::from("/draft"));
+        assert_eq!(
+            response.file(&hit).as_deref(),
+            Some(Path::new("/draft/notes/places/inn.md"))
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Read-only snapshots of a project for export and analysis plugins.
+//!
+//! A [`ProjectSnapshot`] is the state of the active project at one moment:
+//! its metadata, its tree of parts, chapters and documents, the content of
+//! every document at a known revision, unsaved edits included, and the list
+//! of its asset files. Nothing in a snapshot changes afterwards, so a plugin
+//! can walk it on a background thread while the author keeps writing.
+//!
+//! Plugins ask for a fresh snapshot with
+//! [`PluginContext::request_project_snapshot`]; the application takes it
+//! after the plugins' update and publishes it, shared, under
+//! [`PROJECT_SNAPSHOT_KEY`], where [`PluginContext::project_snapshot`]
+//! finds it.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::snapshot::{ProjectSnapshot, SnapshotDocument, SnapshotNode};
+//!
+//! let chapter = SnapshotNode::new("chapter", "Arrival").with_children(vec![
+//!     SnapshotNode::new("document", "Dawn").with_path("content/dawn.md"),
+//! ]);
+//! let snapshot = ProjectSnapshot::new("/novel", vec![chapter])
+//!     .with_document("content/dawn.md", SnapshotDocument::new("Fog.", 3));
+//!
+//! let words = std::thread::spawn(move || {
+//!     snapshot
+//!         .documents()
+//!         .map(|(_, document)| document.content.split_whitespace().count())
+//!         .sum::<usize>()
+//! })
+//! .join()
+//! .unwrap();
+//! assert_eq!(words, 1);
+//! ```
+
+use crate::metadata::SceneMetadata;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Shared state key (`Arc<ProjectSnapshot>`) of the latest snapshot of the
+/// active project.
+pub const PROJECT_SNAPSHOT_KEY: &str = "project_snapshot";
+
+/// Shared state key (`bool`) set when a plugin wants a fresh snapshot.
+pub const PROJECT_SNAPSHOT_REQUEST: &str = "project_snapshot_request";
+
+/// Metadata of a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotMetadata {
+    /// Project title
+    pub title: String,
+    /// Project author
+    pub author: String,
+    /// Project description
+    pub description: String,
+    /// Project tags
+    pub tags: Vec<String>,
+    /// Custom properties
+    pub properties: BTreeMap<String, String>,
+}
+
+/// A node of the project tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotNode {
+    /// Node identifier
+    pub id: Uuid,
+    /// What the node stands for: `folder`, `part`, `chapter` or `document`
+    pub kind: String,
+    /// Title shown in the binder
+    pub title: String,
+    /// Folder or file of the node, relative to the project root
+    pub path: Option<PathBuf>,
+    /// Free-form metadata (status, label, synopsis...)
+    pub metadata: BTreeMap<String, String>,
+    /// Child nodes, in order
+    pub children: Vec<SnapshotNode>,
+}
+
+impl SnapshotNode {
+    pub fn new(kind: impl Into<String>, title: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind: kind.into(),
+            title: title.into(),
+            path: None,
+            metadata: BTreeMap::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_children(mut self, children: Vec<SnapshotNode>) -> Self {
+        self.children = children;
+        self
+    }
+
+    /// Whether the node is a document rather than a folder, part or chapter.
+    pub fn is_document(&self) -> bool {
+        self.kind == "document"
+    }
+
+    /// Synopsis, status and other structured metadata of the node.
+    pub fn scene_metadata(&self) -> SceneMetadata {
+        SceneMetadata::from_fields(&self.metadata)
+    }
+}
+
+/// Content of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotDocument {
+    /// Markdown content
+    pub content: String,
+    /// Revision of the content in the application, 0 for a document read
+    /// from its file
+    pub revision: u64,
+}
+
+impl SnapshotDocument {
+    pub fn new(content: impl Into<String>, revision: u64) -> Self {
+        Self {
+            content: content.into(),
+            revision,
+        }
+    }
+}
+
+/// The state of a project at one moment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectSnapshot {
+    root: PathBuf,
+    taken_at: DateTime<Utc>,
+    metadata: SnapshotMetadata,
+    nodes: Vec<SnapshotNode>,
+    documents: BTreeMap<PathBuf, SnapshotDocument>,
+    assets: Vec<PathBuf>,
+}
+
+impl ProjectSnapshot {
+    /// Snapshot of the project at `root` with the tree `nodes`, taken now.
+    pub fn new(root: impl Into<PathBuf>, nodes: Vec<SnapshotNode>) -> Self {
+        Self {
+            root: root.into(),
+            taken_at: Utc::now(),
+            metadata: SnapshotMetadata::default(),
+            nodes,
+            documents: BTreeMap::new(),
+            assets: Vec::new(),
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: SnapshotMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Add the content of the document at `path`, relative to the root.
+    pub fn with_document(mut self, path: impl Into<PathBuf>, document: SnapshotDocument) -> Self {
+        self.documents.insert(path.into(), document);
+        self
+    }
+
+    /// Set the asset files, relative to the root.
+    pub fn with_assets(mut self, mut assets: Vec<PathBuf>) -> Self {
+        assets.sort();
+        self.assets = assets;
+        self
+    }
+
+    /// Project directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// When the snapshot was taken.
+    pub fn taken_at(&self) -> DateTime<Utc> {
+        self.taken_at
+    }
+
+    pub fn metadata(&self) -> &SnapshotMetadata {
+        &self.metadata
+    }
+
+    /// Top-level nodes of the project tree, in order.
+    pub fn nodes(&self) -> &[SnapshotNode] {
+        &self.nodes
+    }
+
+    /// Every node with its depth, parents before their children, in
+    /// reading order.
+    pub fn walk(&self) -> Vec<(usize, &SnapshotNode)> {
+        fn collect<'a>(
+            nodes: &'a [SnapshotNode],
+            depth: usize,
+            out: &mut Vec<(usize, &'a SnapshotNode)>,
+        ) {
+            for node in nodes {
+                out.push((depth, node));
+                collect(&node.children, depth + 1, out);
+            }
+        }
+        let mut nodes = Vec::new();
+        collect(&self.nodes, 0, &mut nodes);
+        nodes
+    }
+
+    /// Content of the document at `path`, relative to the root or absolute.
+    pub fn document(&self, path: &Path) -> Option<&SnapshotDocument> {
+        let path = path.strip_prefix(&self.root).unwrap_or(path);
+        self.documents.get(path)
+    }
+
+    /// Documents of the project tree with their content, in reading order.
+    pub fn documents(&self) -> impl Iterator<Item = (&SnapshotNode, &SnapshotDocument)> {
+        self.walk()
+            .into_iter()
+            .filter(|(_, node)| node.is_document())
+            .filter_map(|(_, node)| Some((node, self.document(node.path.as_deref()?)?)))
+    }
+
+    /// Asset files (images, maps...), relative to the root, sorted.
+    pub fn assets(&self) -> &[PathBuf] {
+        &self.assets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PluginContext;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_documents_in_reading_order() {
+        let part = SnapshotNode::new("part", "One").with_children(vec![
+            SnapshotNode::new("document", "B").with_path("content/b.md"),
+            SnapshotNode::new("document", "Missing").with_path("content/gone.md"),
+        ]);
+        let snapshot = ProjectSnapshot::new(
+            "/novel",
+            vec![
+                part,
+                SnapshotNode::new("document", "A").with_path("content/a.md"),
+            ],
+        )
+        .with_document("content/a.md", SnapshotDocument::new("Last.", 0))
+        .with_document("content/b.md", SnapshotDocument::new("First.", 7))
+        .with_assets(vec!["maps/z.png".into(), "maps/a.png".into()]);
+
+        let depths: Vec<usize> = snapshot.walk().iter().map(|(depth, _)| *depth).collect();
+        assert_eq!(depths, vec![0, 1, 1, 0]);
+        let contents: Vec<&str> = snapshot
+            .documents()
+            .map(|(_, document)| document.content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["First.", "Last."]);
+        assert_eq!(
+            snapshot
+                .document(Path::new("/novel/content/b.md"))
+                .map(|d| d.revision),
+            Some(7)
+        );
+        assert_eq!(snapshot.assets()[0], PathBuf::from("maps/a.png"));
+
+        let mut ctx = PluginContext::new();
+        assert!(ctx.project_snapshot().is_none());
+        ctx.request_project_snapshot();
+        assert_eq!(
+            ctx.get_shared_state::<bool>(PROJECT_SNAPSHOT_REQUEST),
+            Some(true)
+        );
+        ctx.set_shared_state(PROJECT_SNAPSHOT_KEY, Arc::new(snapshot));
+        assert_eq!(ctx.project_snapshot().unwrap().nodes().len(), 2);
+    }
+}
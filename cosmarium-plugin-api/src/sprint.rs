@@ -0,0 +1,151 @@
+//! Timed writing sprints.
+//!
+//! A sprint plugin publishes the sprint under way under [`SPRINT_KEY`], for
+//! the application to keep distractions away while it runs, and sends each
+//! sprint that ends with [`SPRINT_RECORD_REQUEST`], for the application to
+//! keep it in the [`WritingLog`](crate::history::WritingLog).
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::clock::Clock;
+//! use cosmarium_plugin_api::sprint::Sprint;
+//! use std::time::Duration;
+//!
+//! let clock = Clock::manual(Clock::EPOCH);
+//! let mut sprint = Sprint::new(clock.now(), 25, true);
+//! clock.advance(Duration::from_secs(10 * 60));
+//! sprint.words = 320;
+//!
+//! assert_eq!(sprint.remaining(clock.now()), Duration::from_secs(15 * 60));
+//! assert!(!sprint.is_over(clock.now()));
+//! assert_eq!(sprint.record(clock.now()).words, 320);
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Shared state key (`Option<Sprint>`) of the sprint under way.
+pub const SPRINT_KEY: &str = "writing_sprint";
+
+/// Shared state key (`Option<SprintRecord>`) of a sprint that ended, to log
+/// in the writing history, served by the application.
+pub const SPRINT_RECORD_REQUEST: &str = "writing_sprint_record_request";
+
+/// Shared state key (`bool`) of a request to end the sprint under way before
+/// its time.
+pub const SPRINT_STOP_REQUEST: &str = "writing_sprint_stop_request";
+
+/// A sprint under way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprint {
+    /// When the sprint started
+    pub started_at: DateTime<Utc>,
+    /// Length of the sprint, in minutes
+    pub minutes: u32,
+    /// Words written since the start, which may be negative
+    pub words: i64,
+    /// Whether to hide everything but the editor and the sprint meanwhile
+    pub focus: bool,
+}
+
+impl Sprint {
+    /// A sprint of `minutes` starting at `started_at`, with no words yet.
+    pub fn new(started_at: DateTime<Utc>, minutes: u32, focus: bool) -> Self {
+        Self {
+            started_at,
+            minutes,
+            words: 0,
+            focus,
+        }
+    }
+
+    /// When the sprint is over.
+    pub fn ends_at(&self) -> DateTime<Utc> {
+        self.started_at + chrono::Duration::minutes(i64::from(self.minutes))
+    }
+
+    /// Time left at `now`.
+    pub fn remaining(&self, now: DateTime<Utc>) -> Duration {
+        (self.ends_at() - now).to_std().unwrap_or_default()
+    }
+
+    /// Whether the sprint is over at `now`.
+    pub fn is_over(&self, now: DateTime<Utc>) -> bool {
+        now >= self.ends_at()
+    }
+
+    /// The sprint ending at `now`, for the writing history.
+    pub fn record(&self, now: DateTime<Utc>) -> SprintRecord {
+        let elapsed = (now.min(self.ends_at()) - self.started_at)
+            .to_std()
+            .unwrap_or_default();
+        SprintRecord {
+            started_at: self.started_at,
+            minutes: self.minutes,
+            seconds: elapsed.as_secs(),
+            words: self.words,
+            project: None,
+        }
+    }
+}
+
+/// A sprint that ended, as kept in the writing history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SprintRecord {
+    /// When the sprint started
+    pub started_at: DateTime<Utc>,
+    /// Planned length, in minutes
+    pub minutes: u32,
+    /// Time actually written, in seconds, shorter when stopped early
+    pub seconds: u64,
+    /// Words written, which may be negative
+    pub words: i64,
+    /// Project written in, filled in by the application
+    #[serde(default)]
+    pub project: Option<PathBuf>,
+}
+
+impl SprintRecord {
+    /// Whether the sprint ran its full length.
+    pub fn is_complete(&self) -> bool {
+        self.seconds >= u64::from(self.minutes) * 60
+    }
+
+    /// Words written per minute.
+    pub fn words_per_minute(&self) -> f64 {
+        if self.seconds == 0 {
+            return 0.0;
+        }
+        self.words as f64 * 60.0 / self.seconds as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Clock;
+
+    #[test]
+    fn test_stopped_sprints_are_incomplete() {
+        let clock = Clock::manual(Clock::EPOCH);
+        let mut sprint = Sprint::new(clock.now(), 15, false);
+        sprint.words = 300;
+
+        clock.advance(Duration::from_secs(5 * 60));
+        let stopped = sprint.record(clock.now());
+        assert!(!stopped.is_complete());
+        assert_eq!(stopped.words_per_minute(), 60.0);
+
+        // Time past the end does not count
+        clock.advance(Duration::from_secs(20 * 60));
+        assert!(sprint.is_over(clock.now()));
+        assert_eq!(sprint.remaining(clock.now()), Duration::ZERO);
+        let finished = sprint.record(clock.now());
+        assert!(finished.is_complete());
+        assert_eq!(finished.seconds, 15 * 60);
+        assert_eq!(finished.words_per_minute(), 20.0);
+    }
+}
@@ -0,0 +1,198 @@
+//! Event subscriptions delivered through the application event bus.
+//!
+//! Plugins do not own the event bus: they talk to it through an
+//! [`EventBusLink`], a cheap handle that forwards published events and
+//! subscription requests to the core. The core delivers events to
+//! subscribers when it processes its queue, so a handler never runs inside
+//! the call that emitted the event.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::{Event, EventType, PluginContext};
+//! use std::sync::atomic::{AtomicBool, Ordering};
+//! use std::sync::Arc;
+//!
+//! let mut ctx = PluginContext::new();
+//! let changed = Arc::new(AtomicBool::new(false));
+//!
+//! let flag = Arc::clone(&changed);
+//! let subscription = ctx.subscribe(EventType::DocumentChanged, move |_: &Event| {
+//!     flag.store(true, Ordering::Relaxed);
+//!     Ok(())
+//! });
+//! // Keep `subscription` alive for as long as events should be received.
+//! ```
+
+use crate::{Event, EventHandler, EventType};
+use std::sync::mpsc::Sender;
+use uuid::Uuid;
+
+/// Predicate deciding whether a subscriber receives an event.
+pub type EventFilter = Box<dyn Fn(&Event) -> bool + Send + Sync>;
+
+/// Message sent from plugins to the application event bus.
+pub enum EventBusMessage {
+    /// Deliver an event to its subscribers
+    Publish(Event),
+    /// Register a subscriber
+    Subscribe(SubscriptionRequest),
+    /// Remove a subscriber
+    Unsubscribe(Uuid),
+}
+
+/// Subscriber registration carried by [`EventBusMessage::Subscribe`].
+pub struct SubscriptionRequest {
+    /// Subscription identifier
+    pub id: Uuid,
+    /// Type of events to receive
+    pub event_type: EventType,
+    /// Optional predicate on received events
+    pub filter: Option<EventFilter>,
+    /// Handler called for each matching event
+    pub handler: Box<dyn EventHandler>,
+}
+
+/// Handle through which plugins publish events and subscribe to them.
+#[derive(Clone)]
+pub struct EventBusLink {
+    sender: Sender<EventBusMessage>,
+}
+
+impl EventBusLink {
+    /// Create a link forwarding messages to `sender`.
+    pub fn new(sender: Sender<EventBusMessage>) -> Self {
+        Self { sender }
+    }
+
+    /// Publish an event. It is delivered when the bus next processes events.
+    pub fn publish(&self, event: Event) {
+        if self.sender.send(EventBusMessage::Publish(event)).is_err() {
+            tracing::warn!("Event bus is gone, event dropped");
+        }
+    }
+
+    /// Subscribe `handler` to events of `event_type` accepted by `filter`.
+    pub fn subscribe(
+        &self,
+        event_type: EventType,
+        filter: Option<EventFilter>,
+        handler: Box<dyn EventHandler>,
+    ) -> Subscription {
+        let id = Uuid::new_v4();
+        let request = SubscriptionRequest {
+            id,
+            event_type,
+            filter,
+            handler,
+        };
+        if self
+            .sender
+            .send(EventBusMessage::Subscribe(request))
+            .is_err()
+        {
+            tracing::warn!(
+                "Event bus is gone, subscription to {:?} ignored",
+                event_type
+            );
+        }
+        Subscription::new(id, event_type, self.sender.clone())
+    }
+}
+
+/// Active event subscription.
+///
+/// The subscriber is removed from the bus when the handle is dropped, so a
+/// plugin's subscriptions end with the plugin itself.
+#[must_use = "the subscription ends when the handle is dropped"]
+pub struct Subscription {
+    id: Uuid,
+    event_type: EventType,
+    sender: Option<Sender<EventBusMessage>>,
+}
+
+impl Subscription {
+    /// Create a handle that unsubscribes through `sender` when dropped.
+    pub fn new(id: Uuid, event_type: EventType, sender: Sender<EventBusMessage>) -> Self {
+        Self {
+            id,
+            event_type,
+            sender: Some(sender),
+        }
+    }
+
+    /// Create a handle that is not attached to any bus.
+    pub fn detached(event_type: EventType) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            event_type,
+            sender: None,
+        }
+    }
+
+    /// Subscription identifier.
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Type of events received.
+    pub fn event_type(&self) -> EventType {
+        self.event_type
+    }
+
+    /// End the subscription now.
+    pub fn unsubscribe(self) {}
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            // The bus may already be shut down, in which case there is
+            // nothing left to unsubscribe from.
+            let _ = sender.send(EventBusMessage::Unsubscribe(self.id));
+        }
+    }
+}
+
+impl std::fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .field("event_type", &self.event_type)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_link_forwards_messages() {
+        let (sender, receiver) = mpsc::channel();
+        let link = EventBusLink::new(sender);
+
+        link.publish(Event::new(EventType::DocumentSaved, "saved"));
+        let subscription = link.subscribe(
+            EventType::DocumentChanged,
+            None,
+            Box::new(|_: &Event| Ok(())),
+        );
+        let id = subscription.id();
+        drop(subscription);
+
+        let messages: Vec<EventBusMessage> = receiver.try_iter().collect();
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(&messages[0], EventBusMessage::Publish(e) if e.data() == "saved"));
+        assert!(matches!(&messages[1], EventBusMessage::Subscribe(r) if r.id == id));
+        assert!(matches!(&messages[2], EventBusMessage::Unsubscribe(u) if *u == id));
+    }
+
+    #[test]
+    fn test_detached_subscription() {
+        let subscription = Subscription::detached(EventType::ThemeChanged);
+        assert_eq!(subscription.event_type(), EventType::ThemeChanged);
+        subscription.unsubscribe();
+    }
+}
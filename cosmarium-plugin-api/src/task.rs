@@ -0,0 +1,533 @@
+//! Background tasks run on the application's shared async executor.
+//!
+//! Plugins never create their own runtime: they spawn work through a
+//! [`TaskSpawner`] handed over by the core, and poll the returned
+//! [`TaskHandle`] from the UI thread until the result arrives. Results travel
+//! back over a channel, so polling never blocks a frame.
+//!
+//! Long operations (downloads, exports, pushes) should use
+//! [`TaskSpawner::spawn_task`]: the work receives a [`TaskProgress`] to report
+//! its progress and check for cancellation, and the task is listed by the
+//! application's task manager so the user can follow and cancel it.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::PluginContext;
+//!
+//! let mut ctx = PluginContext::new();
+//! let mut task = ctx.spawn_blocking(|| 6 * 7);
+//!
+//! // Later, typically once per frame:
+//! if let Some(result) = task.try_take() {
+//!     // Without a connected executor the task cannot run
+//!     assert!(result.is_err());
+//! }
+//! ```
+
+use crate::Result;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Handle;
+use uuid::Uuid;
+
+/// Error returned by a task that stopped because it was cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Task was cancelled")]
+pub struct Cancelled;
+
+/// Lifecycle state of a tracked task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskState {
+    /// The task is running
+    Running,
+    /// The task finished successfully
+    Completed,
+    /// The task failed with the given message
+    Failed(String),
+    /// The task stopped after a cancellation request
+    Cancelled,
+}
+
+impl TaskState {
+    /// Whether the task has ended, one way or another.
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, Self::Running)
+    }
+}
+
+/// Snapshot of a tracked task, for display.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskStatus {
+    /// Task identifier
+    pub id: Uuid,
+    /// Human-readable task name
+    pub name: String,
+    /// Completed fraction between 0 and 1, if known
+    pub fraction: Option<f32>,
+    /// Latest progress message
+    pub message: String,
+    /// Lifecycle state
+    pub state: TaskState,
+    /// Whether cancellation was requested
+    pub cancel_requested: bool,
+}
+
+#[derive(Debug)]
+struct ProgressData {
+    fraction: Option<f32>,
+    message: String,
+    state: TaskState,
+}
+
+#[derive(Debug)]
+struct ProgressShared {
+    id: Uuid,
+    name: String,
+    cancelled: AtomicBool,
+    data: Mutex<ProgressData>,
+}
+
+/// Progress reporter and cancellation token of a tracked task.
+///
+/// Clones share the same state: the task reports through one, while the
+/// task manager and the task's [`TaskHandle`] read and cancel through others.
+#[derive(Debug, Clone)]
+pub struct TaskProgress {
+    shared: Arc<ProgressShared>,
+}
+
+impl TaskProgress {
+    /// Create the progress of a new running task.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            shared: Arc::new(ProgressShared {
+                id: Uuid::new_v4(),
+                name: name.into(),
+                cancelled: AtomicBool::new(false),
+                data: Mutex::new(ProgressData {
+                    fraction: None,
+                    message: String::new(),
+                    state: TaskState::Running,
+                }),
+            }),
+        }
+    }
+
+    /// Task identifier.
+    pub fn id(&self) -> Uuid {
+        self.shared.id
+    }
+
+    /// Task name.
+    pub fn name(&self) -> &str {
+        &self.shared.name
+    }
+
+    fn update(&self, f: impl FnOnce(&mut ProgressData)) {
+        if let Ok(mut data) = self.shared.data.lock() {
+            f(&mut data);
+        }
+    }
+
+    /// Report the completed fraction, between 0 and 1.
+    pub fn set_fraction(&self, fraction: f32) {
+        self.update(|data| data.fraction = Some(fraction.clamp(0.0, 1.0)));
+    }
+
+    /// Report `done` units of work out of `total`.
+    ///
+    /// An unknown total (0) leaves the fraction undetermined.
+    pub fn set_position(&self, done: u64, total: u64) {
+        self.update(|data| {
+            data.fraction = (total > 0).then(|| (done as f64 / total as f64).min(1.0) as f32)
+        });
+    }
+
+    /// Report what the task is doing.
+    pub fn set_message(&self, message: impl Into<String>) {
+        let message = message.into();
+        self.update(|data| data.message = message);
+    }
+
+    /// Ask the task to stop. The task notices at its next check.
+    pub fn cancel(&self) {
+        self.shared.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Fail with [`Cancelled`] if cancellation was requested.
+    ///
+    /// Call it between units of work: `progress.check_cancelled()?;`
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Cancelled.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Record how the task ended.
+    fn finish<T>(&self, result: &Result<T>) {
+        let state = match result {
+            Ok(_) => TaskState::Completed,
+            Err(e) if e.is::<Cancelled>() || self.is_cancelled() => TaskState::Cancelled,
+            Err(e) => TaskState::Failed(e.to_string()),
+        };
+        self.update(|data| {
+            if state == TaskState::Completed {
+                data.fraction = Some(1.0);
+            }
+            data.state = state;
+        });
+    }
+
+    /// Current status of the task.
+    pub fn status(&self) -> TaskStatus {
+        let (fraction, message, state) = match self.shared.data.lock() {
+            Ok(data) => (data.fraction, data.message.clone(), data.state.clone()),
+            Err(_) => (
+                None,
+                String::new(),
+                TaskState::Failed("Task panicked".into()),
+            ),
+        };
+        TaskStatus {
+            id: self.id(),
+            name: self.shared.name.clone(),
+            fraction,
+            message,
+            state,
+            cancel_requested: self.is_cancelled(),
+        }
+    }
+}
+
+/// Handle through which plugins spawn tasks on the shared executor.
+#[derive(Clone)]
+pub struct TaskSpawner {
+    handle: Handle,
+    tracker: Option<Sender<TaskProgress>>,
+    /// Whether tasks run on the calling thread
+    inline: bool,
+}
+
+impl TaskSpawner {
+    /// Create a spawner running tasks on the runtime behind `handle`.
+    pub fn new(handle: Handle) -> Self {
+        Self {
+            handle,
+            tracker: None,
+            inline: false,
+        }
+    }
+
+    /// Run every task to completion on the calling thread, before returning
+    /// its handle, so that simulations are single-threaded and reproducible.
+    ///
+    /// Futures are then run with [`Handle::block_on`], which panics when
+    /// called from within an asynchronous context.
+    pub fn inline(mut self) -> Self {
+        self.inline = true;
+        self
+    }
+
+    /// Report the tasks started with [`spawn_task`](Self::spawn_task) to
+    /// `tracker`.
+    pub fn with_tracker(mut self, tracker: Sender<TaskProgress>) -> Self {
+        self.tracker = Some(tracker);
+        self
+    }
+
+    /// Runtime handle, for APIs that need one directly.
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// Run a future on the executor.
+    pub fn spawn<F>(&self, future: F) -> TaskHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        if self.inline {
+            let _ = sender.send(Ok(self.handle.block_on(future)));
+            return TaskHandle::new(receiver);
+        }
+        self.handle.spawn(async move {
+            // The handle may have been dropped, nobody wants the result then
+            let _ = sender.send(Ok(future.await));
+        });
+        TaskHandle::new(receiver)
+    }
+
+    /// Run blocking work (file system, external processes) on the
+    /// executor's blocking thread pool.
+    pub fn spawn_blocking<F, T>(&self, work: F) -> TaskHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        if self.inline {
+            let _ = sender.send(Ok(work()));
+            return TaskHandle::new(receiver);
+        }
+        self.handle.spawn_blocking(move || {
+            let _ = sender.send(Ok(work()));
+        });
+        TaskHandle::new(receiver)
+    }
+
+    /// Run a long blocking operation with progress reporting and
+    /// cancellation.
+    ///
+    /// The work runs on the blocking thread pool and should call
+    /// [`TaskProgress::check_cancelled`] regularly. The task is listed by the
+    /// task manager under `name` until it is cleared.
+    pub fn spawn_task<F, T>(&self, name: impl Into<String>, work: F) -> TaskHandle<T>
+    where
+        F: FnOnce(&TaskProgress) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let progress = TaskProgress::new(name);
+        if let Some(tracker) = &self.tracker {
+            let _ = tracker.send(progress.clone());
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let reporter = progress.clone();
+        let run = move || {
+            let result = std::panic::catch_unwind(AssertUnwindSafe(|| work(&reporter)))
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("Task panicked")));
+            reporter.finish(&result);
+            let _ = sender.send(result);
+        };
+        if self.inline {
+            run();
+        } else {
+            self.handle.spawn_blocking(run);
+        }
+        TaskHandle::new(receiver).with_progress(progress)
+    }
+}
+
+impl std::fmt::Debug for TaskSpawner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskSpawner").finish_non_exhaustive()
+    }
+}
+
+/// Pending result of a spawned task.
+///
+/// Dropping the handle does not cancel the task; its result is discarded.
+#[must_use = "the task result is lost if the handle is dropped"]
+pub struct TaskHandle<T> {
+    /// Behind a mutex only to make the handle `Sync`, so plugins can keep it
+    receiver: Option<Mutex<Receiver<Result<T>>>>,
+    progress: Option<TaskProgress>,
+}
+
+impl<T> TaskHandle<T> {
+    fn new(receiver: Receiver<Result<T>>) -> Self {
+        Self {
+            receiver: Some(Mutex::new(receiver)),
+            progress: None,
+        }
+    }
+
+    fn with_progress(mut self, progress: TaskProgress) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Create a handle for a task that could not be started.
+    pub fn failed() -> Self {
+        let (_, receiver) = mpsc::channel();
+        Self::new(receiver)
+    }
+
+    /// Take the task result if it is available, without blocking.
+    ///
+    /// Returns `None` while the task runs, then its result exactly once. An
+    /// error is returned when the task failed, panicked or the executor shut
+    /// down before it finished.
+    pub fn try_take(&mut self) -> Option<Result<T>> {
+        let receiver = self.receiver.as_mut()?;
+        let receiver = receiver.get_mut().unwrap_or_else(|e| e.into_inner());
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => {
+                Err(anyhow::anyhow!("Task ended without producing a result"))
+            }
+        };
+        self.receiver = None;
+        Some(result)
+    }
+
+    /// Wait for the task result, blocking the calling thread.
+    ///
+    /// Must not be called from a task running on the executor.
+    pub fn wait(mut self) -> Result<T> {
+        let receiver = self
+            .receiver
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Task result was already taken"))?;
+        receiver
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner())
+            .recv()
+            .map_err(|_| anyhow::anyhow!("Task ended without producing a result"))?
+    }
+
+    /// Whether the result has already been taken.
+    pub fn is_done(&self) -> bool {
+        self.receiver.is_none()
+    }
+
+    /// Progress of a task started with [`TaskSpawner::spawn_task`].
+    pub fn progress(&self) -> Option<&TaskProgress> {
+        self.progress.as_ref()
+    }
+
+    /// Ask a task started with [`TaskSpawner::spawn_task`] to stop.
+    pub fn cancel(&self) {
+        if let Some(progress) = &self.progress {
+            progress.cancel();
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for TaskHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskHandle")
+            .field("done", &self.is_done())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_can_be_kept_by_plugins() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<TaskHandle<Vec<String>>>();
+    }
+
+    #[test]
+    fn test_spawn_and_take() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let spawner = TaskSpawner::new(runtime.handle().clone());
+
+        let mut task = spawner.spawn(async { 40 + 2 });
+        let mut blocking = spawner.spawn_blocking(|| "done".to_string());
+
+        assert_eq!(blocking.wait_for_test(), "done");
+        assert_eq!(task.wait_for_test(), 42);
+        assert!(task.is_done());
+        assert!(task.try_take().is_none());
+    }
+
+    #[test]
+    fn test_inline_tasks_are_done_when_spawned() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let spawner = TaskSpawner::new(runtime.handle().clone()).inline();
+
+        let mut task = spawner.spawn(async { 40 + 2 });
+        let mut blocking = spawner.spawn_blocking(|| "done".to_string());
+        let mut tracked = spawner.spawn_task("Count", |_| Ok(4));
+
+        assert_eq!(task.try_take().unwrap().unwrap(), 42);
+        assert_eq!(blocking.try_take().unwrap().unwrap(), "done");
+        assert_eq!(tracked.try_take().unwrap().unwrap(), 4);
+    }
+
+    #[test]
+    fn test_panicking_task_reports_error() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let spawner = TaskSpawner::new(runtime.handle().clone());
+
+        let task = spawner.spawn_blocking(|| -> u32 { panic!("boom") });
+        assert!(task.wait().is_err());
+        assert!(TaskHandle::<u32>::failed().wait().is_err());
+    }
+
+    #[test]
+    fn test_tracked_task_reports_progress() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (tracker, tracked) = mpsc::channel();
+        let spawner = TaskSpawner::new(runtime.handle().clone()).with_tracker(tracker);
+
+        let task = spawner.spawn_task("Count", |progress| {
+            for i in 0..4 {
+                progress.set_position(i, 4);
+                progress.set_message(format!("Step {}", i));
+            }
+            Ok(4)
+        });
+        let progress = task.progress().unwrap().clone();
+        assert_eq!(tracked.try_recv().unwrap().id(), progress.id());
+
+        assert_eq!(task.wait().unwrap(), 4);
+        let status = progress.status();
+        assert_eq!(status.name, "Count");
+        assert_eq!(status.message, "Step 3");
+        assert_eq!(status.fraction, Some(1.0));
+        assert_eq!(status.state, TaskState::Completed);
+    }
+
+    #[test]
+    fn test_tracked_task_cancellation() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let spawner = TaskSpawner::new(runtime.handle().clone());
+
+        let (started, wait_started) = mpsc::channel();
+        let task = spawner.spawn_task("Loop", move |progress| {
+            let _ = started.send(());
+            loop {
+                progress.check_cancelled()?;
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+        });
+        let progress = task.progress().unwrap().clone();
+        wait_started.recv().unwrap();
+        task.cancel();
+
+        let error = task.wait().map(|_: ()| ()).unwrap_err();
+        assert!(error.is::<Cancelled>());
+        assert_eq!(progress.status().state, TaskState::Cancelled);
+
+        let failing = spawner.spawn_task("Fail", |_| -> Result<()> { anyhow::bail!("disk full") });
+        let progress = failing.progress().unwrap().clone();
+        assert!(failing.wait().is_err());
+        assert_eq!(
+            progress.status().state,
+            TaskState::Failed("disk full".into())
+        );
+    }
+
+    impl<T> TaskHandle<T> {
+        /// Poll until the result arrives, like the UI thread does each frame.
+        fn wait_for_test(&mut self) -> T {
+            loop {
+                if let Some(result) = self.try_take() {
+                    return result.unwrap();
+                }
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+        }
+    }
+}
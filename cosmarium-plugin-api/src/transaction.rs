@@ -0,0 +1,403 @@
+//! Edit transactions: several programmatic edits of a document applied as
+//! one.
+//!
+//! Plugins changing a document in several steps, such as replacing every
+//! occurrence of a name, moving a section or inserting a snippet, group the
+//! steps in an [`EditTransaction`]. The editor applies a transaction as a
+//! whole: a single undo step undoes it, subscribers get a single
+//! `DocumentChanged` event, and when one of its steps fails, none of them is
+//! applied.
+//!
+//! A transaction is built step by step after [`EditTransaction::begin`],
+//! then either committed to the [`EDIT_TRANSACTION_REQUEST`] queue or rolled
+//! back. The editor reports how each committed transaction went in
+//! [`EDIT_TRANSACTION_RESULTS`]. Each step addresses the content as the
+//! steps before it left it.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::transaction::EditTransaction;
+//!
+//! let mut transaction = EditTransaction::begin();
+//! transaction
+//!     .replace_all("Bob", "Robert")
+//!     .insert_snippet(0, "# ${1:Title}\n\n");
+//!
+//! let applied = transaction.apply("Ann met Bob.").unwrap();
+//! assert_eq!(applied.content, "# Title\n\nAnn met Robert.");
+//! assert_eq!(&applied.content[applied.placeholders[0].clone()], "Title");
+//! ```
+
+use crate::PluginContext;
+use serde::{Deserialize, Serialize};
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::Chars;
+use uuid::Uuid;
+
+/// Shared state queue (`Vec<EditTransaction>`) of the transactions committed
+/// for the editor to apply.
+pub const EDIT_TRANSACTION_REQUEST: &str = "edit_transaction_request";
+
+/// Shared state queue (`Vec<TransactionResult>`) of the outcomes of the
+/// applied transactions.
+pub const EDIT_TRANSACTION_RESULTS: &str = "edit_transaction_results";
+
+/// A step of an [`EditTransaction`]. Offsets and ranges are in bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EditStep {
+    /// Replace `range` with `text`
+    Replace { range: Range<usize>, text: String },
+    /// Replace every occurrence of `find` with `replacement`
+    ReplaceAll { find: String, replacement: String },
+    /// Move the text of `range` to the offset `to`, outside of `range`
+    Move { range: Range<usize>, to: usize },
+    /// Insert `snippet` at `at`; `$1` and `${1:default}` are placeholders to
+    /// fill in order, `$0` the caret once they are filled
+    InsertSnippet { at: usize, snippet: String },
+}
+
+/// Why a transaction was not applied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+pub enum TransactionError {
+    /// A range is out of the content or splits a character
+    #[error("step {step}: {start}..{end} is not a range of the content")]
+    InvalidRange {
+        step: usize,
+        start: usize,
+        end: usize,
+    },
+    /// A move targets the inside of the moved range
+    #[error("step {step}: a range cannot move inside itself")]
+    MoveInsideRange { step: usize },
+    /// A replacement has nothing to find
+    #[error("step {step}: nothing to find")]
+    EmptyPattern { step: usize },
+    /// A snippet has an unclosed or unnumbered `${` placeholder
+    #[error("step {step}: invalid placeholder in snippet")]
+    InvalidSnippet { step: usize },
+    /// The transaction is for another document than the active one
+    #[error("the document is not the active one in the editor")]
+    DocumentNotActive,
+}
+
+/// Edits of a document applied as one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EditTransaction {
+    /// Transaction identifier, used by its result
+    pub id: Uuid,
+    /// Document to edit, the active one if `None`
+    pub document: Option<Uuid>,
+    /// Steps, in order
+    pub steps: Vec<EditStep>,
+}
+
+/// Content produced by a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedTransaction {
+    /// New content
+    pub content: String,
+    /// Byte ranges of the placeholders of the inserted snippets, in the order
+    /// to fill them, without those later steps edited
+    pub placeholders: Vec<Range<usize>>,
+}
+
+/// Outcome of a committed transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionResult {
+    /// Transaction identifier
+    pub id: Uuid,
+    /// Why the transaction was not applied, if it was not
+    pub error: Option<TransactionError>,
+}
+
+impl EditTransaction {
+    /// Begin a transaction on the active document.
+    pub fn begin() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            document: None,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Make the transaction apply only if `document` is still the active
+    /// one when the editor gets it.
+    pub fn on_document(mut self, document: Uuid) -> Self {
+        self.document = Some(document);
+        self
+    }
+
+    /// Replace `range` with `text`.
+    pub fn replace(&mut self, range: Range<usize>, text: impl Into<String>) -> &mut Self {
+        self.steps.push(EditStep::Replace {
+            range,
+            text: text.into(),
+        });
+        self
+    }
+
+    /// Replace every occurrence of `find` with `replacement`.
+    pub fn replace_all(
+        &mut self,
+        find: impl Into<String>,
+        replacement: impl Into<String>,
+    ) -> &mut Self {
+        self.steps.push(EditStep::ReplaceAll {
+            find: find.into(),
+            replacement: replacement.into(),
+        });
+        self
+    }
+
+    /// Move the text of `range` to the offset `to`, such as a section before
+    /// another.
+    pub fn move_range(&mut self, range: Range<usize>, to: usize) -> &mut Self {
+        self.steps.push(EditStep::Move { range, to });
+        self
+    }
+
+    /// Insert `snippet` at `at` (see [`EditStep::InsertSnippet`]).
+    pub fn insert_snippet(&mut self, at: usize, snippet: impl Into<String>) -> &mut Self {
+        self.steps.push(EditStep::InsertSnippet {
+            at,
+            snippet: snippet.into(),
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Queue the transaction for the editor, returning its identifier.
+    pub fn commit(self, ctx: &mut PluginContext) -> Uuid {
+        let id = self.id;
+        let mut queue: Vec<EditTransaction> = ctx
+            .get_shared_state(EDIT_TRANSACTION_REQUEST)
+            .unwrap_or_default();
+        queue.push(self);
+        ctx.set_shared_state(EDIT_TRANSACTION_REQUEST, queue);
+        id
+    }
+
+    /// Drop the transaction: none of its steps reaches the document.
+    pub fn rollback(self) {
+        tracing::debug!(
+            "Edit transaction {} rolled back ({} steps)",
+            self.id,
+            self.steps.len()
+        );
+    }
+
+    /// Apply the steps to `content`, all of them or, on error, none.
+    pub fn apply(&self, content: &str) -> Result<AppliedTransaction, TransactionError> {
+        let mut applied = AppliedTransaction {
+            content: content.to_string(),
+            placeholders: Vec::new(),
+        };
+        for (step, edit) in self.steps.iter().enumerate() {
+            let check = |content: &str, range: &Range<usize>| {
+                let valid = range.start <= range.end
+                    && content.is_char_boundary(range.start)
+                    && content.is_char_boundary(range.end);
+                if valid {
+                    Ok(())
+                } else {
+                    Err(TransactionError::InvalidRange {
+                        step,
+                        start: range.start,
+                        end: range.end,
+                    })
+                }
+            };
+            match edit {
+                EditStep::Replace { range, text } => {
+                    check(&applied.content, range)?;
+                    applied.splice(range.clone(), text);
+                }
+                EditStep::ReplaceAll { find, replacement } => {
+                    if find.is_empty() {
+                        return Err(TransactionError::EmptyPattern { step });
+                    }
+                    let starts: Vec<usize> = applied
+                        .content
+                        .match_indices(find.as_str())
+                        .map(|(start, _)| start)
+                        .collect();
+                    for start in starts.into_iter().rev() {
+                        applied.splice(start..start + find.len(), replacement);
+                    }
+                }
+                EditStep::Move { range, to } => {
+                    check(&applied.content, range)?;
+                    check(&applied.content, &(*to..*to))?;
+                    if range.start < *to && *to < range.end {
+                        return Err(TransactionError::MoveInsideRange { step });
+                    }
+                    let text = applied.content[range.clone()].to_string();
+                    applied.splice(range.clone(), "");
+                    let to = if *to >= range.end {
+                        to - range.len()
+                    } else {
+                        *to
+                    };
+                    applied.splice(to..to, &text);
+                }
+                EditStep::InsertSnippet { at, snippet } => {
+                    check(&applied.content, &(*at..*at))?;
+                    let (text, placeholders) =
+                        parse_snippet(snippet).ok_or(TransactionError::InvalidSnippet { step })?;
+                    applied.splice(*at..*at, &text);
+                    applied.placeholders.extend(
+                        placeholders
+                            .into_iter()
+                            .map(|range| at + range.start..at + range.end),
+                    );
+                }
+            }
+        }
+        Ok(applied)
+    }
+}
+
+impl AppliedTransaction {
+    /// Replace `range` of the content with `text`, moving the placeholders
+    /// after it and dropping those it overlaps.
+    fn splice(&mut self, range: Range<usize>, text: &str) {
+        self.content.replace_range(range.clone(), text);
+        self.placeholders.retain_mut(|placeholder| {
+            if placeholder.end <= range.start {
+                true
+            } else if placeholder.start >= range.end {
+                placeholder.start = placeholder.start - range.len() + text.len();
+                placeholder.end = placeholder.end - range.len() + text.len();
+                true
+            } else {
+                false
+            }
+        });
+    }
+}
+
+/// Text of `snippet` without its placeholder syntax, with the ranges of its
+/// placeholders in the order to fill them, `$0` last. `None` if a `${` is
+/// not a placeholder.
+fn parse_snippet(snippet: &str) -> Option<(String, Vec<Range<usize>>)> {
+    let mut text = String::new();
+    let mut fields: Vec<(u32, Range<usize>)> = Vec::new();
+    let mut chars = snippet.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => text.push(chars.next().unwrap_or('\\')),
+            '$' if chars.peek().is_some_and(char::is_ascii_digit) => {
+                let number = placeholder_number(&mut chars)?;
+                fields.push((number, text.len()..text.len()));
+            }
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let number = placeholder_number(&mut chars)?;
+                let start = text.len();
+                match chars.next()? {
+                    '}' => {}
+                    ':' => loop {
+                        match chars.next()? {
+                            '}' => break,
+                            '\\' => text.push(chars.next()?),
+                            c => text.push(c),
+                        }
+                    },
+                    _ => return None,
+                }
+                fields.push((number, start..text.len()));
+            }
+            c => text.push(c),
+        }
+    }
+    fields.sort_by_key(|(number, _)| if *number == 0 { u32::MAX } else { *number });
+    Some((text, fields.into_iter().map(|(_, range)| range).collect()))
+}
+
+/// Number of the placeholder `chars` start with.
+fn placeholder_number(chars: &mut Peekable<Chars>) -> Option<u32> {
+    let mut digits = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        digits.push(digit);
+    }
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_apply_in_order() {
+        let content = "## One\nFirst.\n## Two\nSecond.\n";
+        let two = content.find("## Two").unwrap();
+        let mut transaction = EditTransaction::begin();
+        transaction
+            .move_range(two..content.len(), 0)
+            .replace_all(".", "!")
+            .replace(0..2, "#");
+        let applied = transaction.apply(content).unwrap();
+        assert_eq!(applied.content, "# Two\nSecond!\n## One\nFirst!\n");
+        assert!(applied.placeholders.is_empty());
+    }
+
+    #[test]
+    fn test_failing_step_applies_nothing() {
+        let mut transaction = EditTransaction::begin();
+        transaction.replace_all("a", "b").replace(2..9, "");
+        assert_eq!(
+            transaction.apply("abc"),
+            Err(TransactionError::InvalidRange {
+                step: 1,
+                start: 2,
+                end: 9
+            })
+        );
+
+        let mut transaction = EditTransaction::begin();
+        transaction.move_range(0..4, 2);
+        assert_eq!(
+            transaction.apply("abcdef"),
+            Err(TransactionError::MoveInsideRange { step: 0 })
+        );
+        let mut transaction = EditTransaction::begin();
+        transaction.replace(1..2, "x");
+        assert!(transaction.apply("été").is_err());
+    }
+
+    #[test]
+    fn test_snippet_placeholders() {
+        let mut transaction = EditTransaction::begin();
+        transaction
+            .insert_snippet(0, "[$0](${2:url} \"${1:title}\") \\$3")
+            .replace(0..0, ">");
+        let applied = transaction.apply("").unwrap();
+        assert_eq!(applied.content, ">[](url \"title\") $3");
+        let filled: Vec<&str> = applied
+            .placeholders
+            .iter()
+            .map(|range| &applied.content[range.clone()])
+            .collect();
+        assert_eq!(filled, vec!["title", "url", ""]);
+        assert_eq!(applied.placeholders[2], 2..2);
+
+        // Placeholders a later step edits are dropped
+        let mut transaction = EditTransaction::begin();
+        transaction
+            .insert_snippet(0, "${1:a} ${2:b}")
+            .replace(0..1, "x");
+        assert_eq!(transaction.apply("").unwrap().placeholders, vec![2..3]);
+
+        let mut transaction = EditTransaction::begin();
+        transaction.insert_snippet(0, "${1:open");
+        assert_eq!(
+            transaction.apply(""),
+            Err(TransactionError::InvalidSnippet { step: 0 })
+        );
+    }
+}
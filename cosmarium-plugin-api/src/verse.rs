@@ -0,0 +1,460 @@
+//! Verse: poems and song lyrics, whose lines are the author's own.
+//!
+//! Markdown joins the lines of a paragraph and takes lines indented by four
+//! spaces for code, which mangles poems. A marker line, written as an HTML
+//! comment that other Markdown tools leave out, starts verse; it runs to an
+//! end marker or to the end of the text:
+//!
+//! ```markdown
+//! <!-- verse -->
+//! I wandered lonely as a cloud
+//!     That floats on high o'er vales and hills,
+//!
+//! When all at once I saw a crowd,
+//! <!-- /verse -->
+//! ```
+//!
+//! Within verse, each line is a line of the poem, kept as it is, leading
+//! spaces indent it by half an em each, and blank lines part the stanzas.
+//! Lines too long for the page wrap under a hanging indent.
+//!
+//! Exporters writing HTML lay the lines out with [`wrap_verse`]; those
+//! writing other Markdown readers' input break them with [`break_lines`];
+//! the others read the verse with [`verses`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::verse::{verses, VerseStats};
+//!
+//! let markdown = "A prologue.\n\n<!-- verse -->\nRoses are red,\n  violets are blue.\n\nSugar is sweet.";
+//! let verses = verses(markdown);
+//!
+//! assert_eq!(verses[0].lines, 2..7);
+//! assert_eq!(verses[0].stanzas[0][1].indent, 2);
+//! assert_eq!(verses[0].stanzas[0][1].text, "violets are blue.");
+//! assert_eq!(
+//!     VerseStats::of(markdown),
+//!     VerseStats { lines: 3, stanzas: 2, syllables: 12 }
+//! );
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Marker line starting verse.
+pub const VERSE_MARKER: &str = "<!-- verse -->";
+
+/// Marker line ending verse.
+pub const VERSE_END_MARKER: &str = "<!-- /verse -->";
+
+/// Indent of each leading space of a line, in ems.
+pub const INDENT_PER_SPACE: f32 = 0.5;
+
+/// Indent of the continuation of lines too long for the page, in ems.
+pub const HANGING_INDENT: f32 = 2.0;
+
+/// A line of verse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerseLine {
+    /// Line of the text, counted from 0
+    pub line: usize,
+    /// Leading spaces, a tab counting for four
+    pub indent: usize,
+    /// Text of the line, without its indentation
+    pub text: String,
+}
+
+impl VerseLine {
+    /// Left indent of the line, in ems.
+    pub fn indent_em(&self) -> f32 {
+        self.indent as f32 * INDENT_PER_SPACE
+    }
+}
+
+/// A run of verse in a Markdown text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verse {
+    /// Lines of the verse, from its marker to its end marker included, or
+    /// to the end of the text
+    pub lines: Range<usize>,
+    /// Lines of each stanza
+    pub stanzas: Vec<Vec<VerseLine>>,
+}
+
+impl Verse {
+    /// Line, stanza and syllable counts of the verse.
+    pub fn stats(&self) -> VerseStats {
+        let lines = self.stanzas.iter().flatten();
+        VerseStats {
+            lines: lines.clone().count(),
+            stanzas: self.stanzas.len(),
+            syllables: lines
+                .flat_map(|line| line.text.split_whitespace())
+                .map(syllables)
+                .sum(),
+        }
+    }
+}
+
+/// Line, stanza and syllable counts of verse.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerseStats {
+    pub lines: usize,
+    pub stanzas: usize,
+    /// Estimated syllables, see [`syllables`]
+    pub syllables: usize,
+}
+
+impl VerseStats {
+    /// Counts of all the verse of `markdown`.
+    pub fn of(markdown: &str) -> Self {
+        verses(markdown)
+            .iter()
+            .map(Verse::stats)
+            .fold(Self::default(), |total, stats| Self {
+                lines: total.lines + stats.lines,
+                stanzas: total.stanzas + stats.stanzas,
+                syllables: total.syllables + stats.syllables,
+            })
+    }
+
+    /// Whether there is no verse at all.
+    pub fn is_empty(&self) -> bool {
+        self.lines == 0
+    }
+
+    /// Average syllables of a line.
+    pub fn syllables_per_line(&self) -> f32 {
+        if self.lines == 0 {
+            0.0
+        } else {
+            self.syllables as f32 / self.lines as f32
+        }
+    }
+}
+
+/// Which marker `line` is: `Some(true)` for a verse marker, `Some(false)`
+/// for an end marker.
+pub fn parse_marker(line: &str) -> Option<bool> {
+    match line.trim() {
+        VERSE_MARKER => Some(true),
+        VERSE_END_MARKER => Some(false),
+        _ => None,
+    }
+}
+
+/// Verse of `markdown`, outside of its front matter and code blocks.
+pub fn verses(markdown: &str) -> Vec<Verse> {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let is_fence = |line: &str| {
+        let line = line.trim_start();
+        line.starts_with("```") || line.starts_with("~~~")
+    };
+
+    let mut i = 0;
+    if lines.first() == Some(&"---") {
+        i = lines[1..]
+            .iter()
+            .position(|line| *line == "---" || *line == "...")
+            .map_or(0, |end| end + 2);
+    }
+
+    let mut verses = Vec::new();
+    while i < lines.len() {
+        if is_fence(lines[i]) {
+            let fence = &lines[i].trim_start()[..3];
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with(fence) {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+        if parse_marker(lines[i]) != Some(true) {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let mut stanzas: Vec<Vec<VerseLine>> = Vec::new();
+        let mut in_stanza = false;
+        i += 1;
+        while i < lines.len() {
+            let line = lines[i];
+            match parse_marker(line) {
+                Some(false) => {
+                    i += 1;
+                    break;
+                }
+                // A new marker starts the verse anew
+                Some(true) => break,
+                None => {}
+            }
+            let text = line.trim();
+            if text.is_empty() {
+                in_stanza = false;
+            } else {
+                let indentation = &line[..line.len() - line.trim_start().len()];
+                let line = VerseLine {
+                    line: i,
+                    indent: indentation
+                        .chars()
+                        .map(|c| if c == '\t' { 4 } else { 1 })
+                        .sum(),
+                    text: text.to_string(),
+                };
+                match stanzas.last_mut() {
+                    Some(stanza) if in_stanza => stanza.push(line),
+                    _ => stanzas.push(vec![line]),
+                }
+                in_stanza = true;
+            }
+            i += 1;
+        }
+        verses.push(Verse {
+            lines: start..i,
+            stanzas,
+        });
+    }
+    verses
+}
+
+/// Estimated syllables of `word`: its groups of vowels, less a silent final
+/// `e`, and at least one. The estimate is made for English, and is close for
+/// most languages written in the Latin alphabet.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_plugin_api::verse::syllables;
+///
+/// assert_eq!(syllables("wandered"), 2);
+/// assert_eq!(syllables("cloud"), 1);
+/// assert_eq!(syllables("table"), 2);
+/// assert_eq!(syllables("—"), 0);
+/// ```
+pub fn syllables(word: &str) -> usize {
+    let word: Vec<char> = word
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if word.is_empty() {
+        return 0;
+    }
+    let is_vowel = |c: char| "aeiouyàáâäæèéêëìíîïòóôöœùúûüÿ".contains(c);
+
+    let mut groups = 0;
+    let mut previous = false;
+    for &c in &word {
+        let vowel = is_vowel(c);
+        if vowel && !previous {
+            groups += 1;
+        }
+        previous = vowel;
+    }
+    // A final e is silent, but for a final "le" after a consonant, and so
+    // is that of "-es" and "-ed" but after the sounds they are said after
+    let silent = match word[..] {
+        [.., before, 'l', 'e'] => is_vowel(before),
+        [.., last, 'e'] => !is_vowel(last),
+        [.., last, 'e', 's'] => !"scxzgh".contains(last) && !is_vowel(last),
+        [.., last, 'e', 'd'] => !"td".contains(last) && !is_vowel(last),
+        _ => false,
+    };
+    if silent && groups > 1 {
+        groups -= 1;
+    }
+    groups.max(1)
+}
+
+/// `text` with a backslash before the marks that would make a Markdown line
+/// of it something else than text: a heading, a quote, a list item, a rule
+/// or a fence.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_plugin_api::verse::escape_line;
+///
+/// assert_eq!(escape_line("- and so it ends"), "\\- and so it ends");
+/// assert_eq!(escape_line("1984, the year"), "1984, the year");
+/// assert_eq!(escape_line("1. first"), "1\\. first");
+/// assert_eq!(escape_line("*soft* rain"), "*soft* rain");
+/// ```
+pub fn escape_line(text: &str) -> String {
+    let digits = text.bytes().take_while(u8::is_ascii_digit).count();
+    let after_digits = &text[digits..];
+    if digits > 0 && after_digits.starts_with(['.', ')']) {
+        let rest = &after_digits[1..];
+        if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+            return format!("{}\\{}", &text[..digits], after_digits);
+        }
+        return text.to_string();
+    }
+
+    let starts_block = match text.chars().next() {
+        Some('#' | '>' | '|' | '=' | '`' | '~') => true,
+        Some(mark @ ('-' | '+' | '*' | '_')) => {
+            let rest = &text[1..];
+            rest.is_empty()
+                || (mark != '_' && rest.starts_with(char::is_whitespace))
+                || rest.chars().all(|c| c == mark || c == ' ')
+        }
+        _ => false,
+    };
+    if starts_block {
+        format!("\\{}", text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// `markdown` with its verse laid out in HTML elements: a `<div>` for each
+/// run of verse, a paragraph for each stanza, and a block `<span>` for each
+/// line, indented and hanging. The markers are removed.
+///
+/// The Markdown of the lines is left as it is, for the reader of the result
+/// to render.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_plugin_api::verse::wrap_verse;
+///
+/// assert_eq!(
+///     wrap_verse("<!-- verse -->\nSo *it* goes,\n  and goes."),
+///     "<div class=\"verse\">\n\n\
+///      <span class=\"line\" style=\"display: block; padding-left: 2em; text-indent: -2em\">So *it* goes,</span>\n\
+///      <span class=\"line\" style=\"display: block; padding-left: 3em; text-indent: -2em\">and goes.</span>\n\
+///      \n</div>\n"
+/// );
+/// ```
+pub fn wrap_verse(markdown: &str) -> String {
+    replace_verse(markdown, |verse, out| {
+        out.push_str("<div class=\"verse\">\n\n");
+        for stanza in &verse.stanzas {
+            for line in stanza {
+                out.push_str(&format!(
+                    "<span class=\"line\" style=\"display: block; padding-left: {}em; \
+                     text-indent: -{}em\">{}</span>\n",
+                    HANGING_INDENT + line.indent_em(),
+                    HANGING_INDENT,
+                    line.text
+                ));
+            }
+            out.push('\n');
+        }
+        out.push_str("</div>\n");
+    })
+}
+
+/// `markdown` with the lines of its verse ended by hard line breaks and
+/// their indentation made of no-break spaces, for Markdown readers without
+/// HTML. The markers are removed.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_plugin_api::verse::break_lines;
+///
+/// assert_eq!(
+///     break_lines("<!-- verse -->\nSo it goes,\n  and goes.\n\n- and ends."),
+///     "So it goes,\\\n\u{a0}\u{a0}and goes.\n\n\\- and ends.\n"
+/// );
+/// ```
+pub fn break_lines(markdown: &str) -> String {
+    replace_verse(markdown, |verse, out| {
+        for (i, stanza) in verse.stanzas.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            for (j, line) in stanza.iter().enumerate() {
+                out.push_str(&"\u{a0}".repeat(line.indent));
+                out.push_str(&escape_line(&line.text));
+                if j + 1 < stanza.len() {
+                    out.push('\\');
+                }
+                out.push('\n');
+            }
+        }
+    })
+}
+
+/// `markdown` with each run of verse written by `write`, from its first
+/// line to its last.
+fn replace_verse(markdown: &str, mut write: impl FnMut(&Verse, &mut String)) -> String {
+    let verses = verses(markdown);
+    if verses.is_empty() {
+        return markdown.to_string();
+    }
+
+    let mut out = String::with_capacity(markdown.len() * 2);
+    let mut verses = verses.iter().peekable();
+    let mut skip_to = 0;
+    for (i, line) in markdown.lines().enumerate() {
+        if let Some(verse) = verses.next_if(|verse| verse.lines.start == i) {
+            // Verse is a block of its own
+            if !out.is_empty() && !out.ends_with("\n\n") {
+                out.push('\n');
+            }
+            write(verse, &mut out);
+            skip_to = verse.lines.end;
+            if verse.lines.end < markdown.lines().count() {
+                out.push('\n');
+            }
+        }
+        if i < skip_to {
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verses_skip_front_matter_and_code() {
+        let markdown = "---\ntitle: Odes\n---\n```\n<!-- verse -->\n```\n\n<!-- verse -->\n\tOne\n\n\n  Two\nThree\n<!-- /verse -->\nProse.";
+        let verses = verses(markdown);
+        assert_eq!(verses.len(), 1);
+        assert_eq!(verses[0].lines, 7..14);
+        let stanzas: Vec<Vec<(usize, usize, &str)>> = verses[0]
+            .stanzas
+            .iter()
+            .map(|stanza| {
+                stanza
+                    .iter()
+                    .map(|line| (line.line, line.indent, line.text.as_str()))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            stanzas,
+            [vec![(8, 4, "One")], vec![(11, 2, "Two"), (12, 0, "Three")]]
+        );
+    }
+
+    #[test]
+    fn test_syllables() {
+        let line = "That floats on high o'er vales and hills";
+        let count: usize = line.split_whitespace().map(syllables).sum();
+        assert_eq!(count, 8);
+        assert_eq!(syllables("beautiful"), 3);
+        assert_eq!(syllables("the"), 1);
+        assert_eq!(syllables("rhythm"), 1);
+        assert_eq!(syllables("ÉTÉ"), 2);
+    }
+
+    #[test]
+    fn test_verse_is_replaced_in_place() {
+        let markdown = "Before.\n<!-- verse -->\nOne\n<!-- /verse -->\nAfter.\n";
+        assert_eq!(break_lines(markdown), "Before.\n\nOne\n\nAfter.\n");
+        assert_eq!(break_lines("No verse."), "No verse.");
+    }
+}
@@ -0,0 +1,337 @@
+//! Wiki links between the manuscript and its worldbuilding entries.
+//!
+//! Worldbuilding entries are the Markdown files of the project's `entities/`
+//! folder, one folder per kind of entry (`entities/places/`,
+//! `entities/factions/`, `entities/items/`...). An entry is named by its
+//! first `#` heading, or by its file name when it has none.
+//!
+//! Documents link to entries with `[[Name]]`, `[[Name|shown text]]` when the
+//! sentence needs another form of the name, or `[[kind/Name]]` when entries
+//! of two kinds share a name. Names match regardless of case, and of spaces,
+//! hyphens and underscores being swapped for one another.
+//!
+//! The wiki plugin publishes a [`LinkResolver`] of the project's entries
+//! under [`LINK_RESOLVER_KEY`], and serves [`OPEN_ENTRY_REQUEST`]s from the
+//! editor and the other plugins.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_plugin_api::wiki::{links, LinkResolver, WikiEntry};
+//!
+//! let resolver = LinkResolver::new(vec![WikiEntry::new(
+//!     "Silver Harbor",
+//!     "places",
+//!     "/novel/entities/places/harbor.md",
+//! )]);
+//!
+//! let text = "They sailed to [[silver harbor|the harbor]].";
+//! let link = &links(text)[0];
+//! assert_eq!(link.text(), "the harbor");
+//! assert_eq!(resolver.resolve(&link.target).unwrap().kind, "places");
+//! ```
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Folder of the project holding the worldbuilding entries.
+pub const ENTITIES_DIR: &str = "entities";
+
+/// Shared state key ([`LinkResolver`]) of the entries of the active project.
+pub const LINK_RESOLVER_KEY: &str = "wiki_link_resolver";
+
+/// Shared state key (`Option<String>`) of the target of a link to open in
+/// the entry panel.
+pub const OPEN_ENTRY_REQUEST: &str = "wiki_open_entry_request";
+
+/// A `[[...]]` link of a text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikiLink {
+    /// Byte range of the link, brackets included
+    pub range: Range<usize>,
+    /// Name of the entry, possibly qualified by its kind
+    pub target: String,
+    /// Text shown instead of the name, if any
+    pub label: Option<String>,
+}
+
+impl WikiLink {
+    /// Text the link reads as.
+    pub fn text(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.target)
+    }
+}
+
+/// Links of `text`, outside of code.
+pub fn links(text: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    let mut in_fence = false;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+
+        let mut in_code = false;
+        let mut i = 0;
+        while i < line.len() {
+            let rest = &line[i..];
+            if rest.starts_with('`') {
+                in_code = !in_code;
+            } else if !in_code && rest.starts_with("[[") {
+                if let Some(link) = parse_link(rest) {
+                    let len = link.range.end;
+                    links.push(WikiLink {
+                        range: start + i..start + i + len,
+                        ..link
+                    });
+                    i += len;
+                    continue;
+                }
+            }
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    links
+}
+
+/// Link at the start of `text`, with its range in `text`.
+fn parse_link(text: &str) -> Option<WikiLink> {
+    let inner = &text[2..];
+    let end = inner.find("]]")?;
+    let inner = &inner[..end];
+    if inner.contains(['[', '\n']) {
+        return None;
+    }
+    let (target, label) = match inner.split_once('|') {
+        Some((target, label)) => (target.trim(), Some(label.trim().to_string())),
+        None => (inner.trim(), None),
+    };
+    if target.is_empty() {
+        return None;
+    }
+    Some(WikiLink {
+        range: 0..end + 4,
+        target: target.to_string(),
+        label: label.filter(|label| !label.is_empty()),
+    })
+}
+
+/// Link of `text` around the byte offset `at`, if any.
+pub fn link_at(text: &str, at: usize) -> Option<WikiLink> {
+    links(text)
+        .into_iter()
+        .find(|link| link.range.start <= at && at <= link.range.end)
+}
+
+/// A worldbuilding entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikiEntry {
+    /// Name of the entry
+    pub name: String,
+    /// Folder of the entry under `entities/`, empty at its root
+    pub kind: String,
+    /// Entry file
+    pub path: PathBuf,
+}
+
+impl WikiEntry {
+    pub fn new(name: impl Into<String>, kind: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            kind: kind.into(),
+            path: path.into(),
+        }
+    }
+
+    /// Entry of the file at `path` with `content`, of `kind`.
+    pub fn from_file(path: &Path, kind: &str, content: &str) -> Self {
+        let stem = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+        let name = heading(content).unwrap_or(stem);
+        Self::new(name, kind, path)
+    }
+
+    /// Whether `name` names this entry, by its heading or its file name.
+    fn is_named(&self, name: &str) -> bool {
+        let name = normalize(name);
+        normalize(&self.name) == name
+            || self
+                .path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| normalize(stem) == name)
+    }
+}
+
+/// Text of the first `#` heading of `content`, after its front matter.
+fn heading(content: &str) -> Option<&str> {
+    let mut lines = content.lines();
+    let mut first = lines.next();
+    if first == Some("---") {
+        lines.by_ref().find(|line| *line == "---" || *line == "...");
+        first = lines.next();
+    }
+    std::iter::once(first?)
+        .chain(lines)
+        .find_map(|line| line.strip_prefix("# "))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// `name` lowercased, with its runs of spaces, hyphens and underscores
+/// made single spaces.
+fn normalize(name: &str) -> String {
+    name.split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Resolves link targets to the worldbuilding entries of a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkResolver {
+    /// Entries, sorted by kind and name
+    entries: Vec<WikiEntry>,
+}
+
+impl LinkResolver {
+    pub fn new(mut entries: Vec<WikiEntry>) -> Self {
+        entries.sort_by_cached_key(|entry| (entry.kind.clone(), normalize(&entry.name)));
+        Self { entries }
+    }
+
+    /// Resolver of the entries of the project at `project`.
+    pub fn scan(project: &Path) -> Self {
+        let mut entries = Vec::new();
+        collect_entries(&project.join(ENTITIES_DIR), "", &mut entries);
+        Self::new(entries)
+    }
+
+    /// Entries, sorted by kind and name.
+    pub fn entries(&self) -> &[WikiEntry] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Kinds of the entries, in order.
+    pub fn kinds(&self) -> Vec<&str> {
+        let mut kinds: Vec<&str> = self.entries.iter().map(|e| e.kind.as_str()).collect();
+        kinds.dedup();
+        kinds
+    }
+
+    /// Entry a link to `target` points to.
+    ///
+    /// Unqualified targets name an entry of any kind, the first in order when
+    /// several share the name.
+    pub fn resolve(&self, target: &str) -> Option<&WikiEntry> {
+        let target = target.trim();
+        let by_name = |name: &str| self.entries.iter().find(|entry| entry.is_named(name));
+        match target.rsplit_once('/') {
+            Some((kind, name)) => self
+                .entries
+                .iter()
+                .find(|entry| normalize(&entry.kind) == normalize(kind) && entry.is_named(name))
+                .or_else(|| by_name(target)),
+            None => by_name(target),
+        }
+    }
+
+    /// Entry stored in `path`.
+    pub fn entry_at(&self, path: &Path) -> Option<&WikiEntry> {
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+}
+
+/// Collect the entries of the folder `dir`, of `kind`, and of its
+/// subfolders.
+fn collect_entries(dir: &Path, kind: &str, entries: &mut Vec<WikiEntry>) {
+    let Ok(dir_entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for dir_entry in dir_entries.flatten() {
+        let path = dir_entry.path();
+        let name = dir_entry.file_name().to_string_lossy().into_owned();
+        if path.is_dir() {
+            let kind = if kind.is_empty() {
+                name
+            } else {
+                format!("{}/{}", kind, name)
+            };
+            collect_entries(&path, &kind, entries);
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                entries.push(WikiEntry::from_file(&path, kind, &content));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links_outside_code() {
+        let text = "See [[Ann]] and [[places/Inn|the inn]].\n`[[code]]` [[]] [[open\n```\n[[fenced]]\n```\n[[Last]]";
+        let links = links(text);
+        let targets: Vec<&str> = links.iter().map(|l| l.target.as_str()).collect();
+        assert_eq!(targets, vec!["Ann", "places/Inn", "Last"]);
+        assert_eq!(&text[links[0].range.clone()], "[[Ann]]");
+        assert_eq!(links[1].text(), "the inn");
+
+        let at = text.find("Inn").unwrap();
+        assert_eq!(link_at(text, at).unwrap().target, "places/Inn");
+        assert_eq!(link_at(text, 0), None);
+    }
+
+    #[test]
+    fn test_resolve_names_and_kinds() {
+        let resolver = LinkResolver::new(vec![
+            WikiEntry::new("The Crow", "places", "/p/entities/places/crow.md"),
+            WikiEntry::new("The Crow", "factions", "/p/entities/factions/crow.md"),
+            WikiEntry::new("Ann Vell", "characters", "/p/entities/characters/ann.md"),
+        ]);
+        assert_eq!(resolver.kinds(), vec!["characters", "factions", "places"]);
+        assert_eq!(resolver.resolve("the_crow").unwrap().kind, "factions");
+        assert_eq!(resolver.resolve("Places/The Crow").unwrap().kind, "places");
+        // By file name
+        assert_eq!(resolver.resolve("ann").unwrap().name, "Ann Vell");
+        assert_eq!(resolver.resolve("Bob"), None);
+    }
+
+    #[test]
+    fn test_scan_project_entities() {
+        let root = std::env::temp_dir().join(format!("cosmarium_wiki_test_{}", std::process::id()));
+        let places = root.join(ENTITIES_DIR).join("places");
+        std::fs::create_dir_all(&places).unwrap();
+        std::fs::write(
+            places.join("harbor.md"),
+            "---\ntags: [sea]\n---\n# Silver Harbor\n",
+        )
+        .unwrap();
+        std::fs::write(places.join("well.md"), "A deep well.").unwrap();
+        std::fs::write(places.join("map.png"), "").unwrap();
+
+        let resolver = LinkResolver::scan(&root);
+        let names: Vec<&str> = resolver.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Silver Harbor", "well"]);
+        assert_eq!(resolver.entries()[0].kind, "places");
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
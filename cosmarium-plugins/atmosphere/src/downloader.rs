@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use cosmarium_plugin_api::TaskProgress;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::fs;
 use std::io::{Write, Read};
@@ -25,7 +26,10 @@ pub fn get_model_cache_dir() -> Result<PathBuf> {
 }
 
 /// Download a file with progress indicator
-fn download_with_progress(url: &str, dest: &Path, file_name: &str) -> Result<()> {
+///
+/// Progress is reported to `progress`; a cancelled download removes the
+/// partial file.
+fn download_with_progress(url: &str, dest: &Path, file_name: &str, progress: &TaskProgress) -> Result<()> {
     tracing::info!("Downloading {} from {}", file_name, url);
     
     let client = reqwest::blocking::Client::builder()
@@ -48,12 +52,19 @@ fn download_with_progress(url: &str, dest: &Path, file_name: &str) -> Result<()>
             .progress_chars("#>-"),
     );
     pb.set_message(format!("Downloading {}", file_name));
+    progress.set_message(format!("Downloading {}", file_name));
     
     let mut file = fs::File::create(dest)?;
     let mut downloaded: u64 = 0;
     let mut buffer = [0; 8192];
     
     loop {
+        if progress.is_cancelled() {
+            drop(file);
+            let _ = fs::remove_file(dest);
+            return progress.check_cancelled();
+        }
+
         let bytes_read = response.read(&mut buffer)?;
         if bytes_read == 0 {
             break;
@@ -62,6 +73,7 @@ fn download_with_progress(url: &str, dest: &Path, file_name: &str) -> Result<()>
         file.write_all(&buffer[..bytes_read])?;
         downloaded += bytes_read as u64;
         pb.set_position(downloaded);
+        progress.set_position(downloaded, total_size);
     }
     
     pb.finish_with_message(format!("{} downloaded successfully", file_name));
@@ -69,7 +81,7 @@ fn download_with_progress(url: &str, dest: &Path, file_name: &str) -> Result<()>
 }
 
 /// Ensure model and tokenizer are downloaded
-pub fn ensure_model_downloaded() -> Result<(PathBuf, PathBuf)> {
+pub fn ensure_model_downloaded(progress: &TaskProgress) -> Result<(PathBuf, PathBuf)> {
     let cache_dir = get_model_cache_dir()?;
     let model_path = cache_dir.join("roberta_go_emotions_quantized.onnx");
     let tokenizer_path = cache_dir.join("tokenizer.json");
@@ -77,7 +89,7 @@ pub fn ensure_model_downloaded() -> Result<(PathBuf, PathBuf)> {
     // Download model if not exists
     if !model_path.exists() {
         tracing::info!("Model not found in cache, downloading...");
-        download_with_progress(MODEL_URL, &model_path, "Emotion Detection Model (125 MB)")?;
+        download_with_progress(MODEL_URL, &model_path, "Emotion Detection Model (125 MB)", progress)?;
     } else {
         tracing::info!("Model found in cache: {:?}", model_path);
     }
@@ -85,7 +97,7 @@ pub fn ensure_model_downloaded() -> Result<(PathBuf, PathBuf)> {
     // Download tokenizer if not exists
     if !tokenizer_path.exists() {
         tracing::info!("Tokenizer not found in cache, downloading...");
-        download_with_progress(TOKENIZER_URL, &tokenizer_path, "Tokenizer Config")?;
+        download_with_progress(TOKENIZER_URL, &tokenizer_path, "Tokenizer Config", progress)?;
     } else {
         tracing::info!("Tokenizer found in cache: {:?}", tokenizer_path);
     }
@@ -1,4 +1,6 @@
 use cosmarium_plugin_api::{Plugin, PluginContext, PluginInfo, PluginType, Result};
+#[cfg(feature = "ml-emotions")]
+use cosmarium_plugin_api::{TaskHandle, TaskSpawner};
 use async_trait::async_trait;
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -7,7 +9,6 @@ use std::sync::atomic::{AtomicBool, Ordering};
 pub mod downloader;
 #[cfg(feature = "ml-emotions")]
 pub mod classifier;
-#[cfg(feature = "ml-emotions")]
 pub mod color;
 
 #[cfg(feature = "ml-emotions")]
@@ -57,6 +58,12 @@ pub struct AtmospherePlugin {
     /// ML classifier (loaded in background)
     classifier: Arc<Mutex<Option<EmotionClassifier>>>,
     #[cfg(feature = "ml-emotions")]
+    /// Background download and loading of the model
+    model_task: Option<TaskHandle<()>>,
+    #[cfg(feature = "ml-emotions")]
+    /// Spawner of the shared executor, for analysis work
+    task_spawner: Option<TaskSpawner>,
+    #[cfg(feature = "ml-emotions")]
     /// Flag indicating if analysis is currently running
     analysis_in_progress: Arc<AtomicBool>,
     #[cfg(feature = "ml-emotions")]
@@ -98,6 +105,10 @@ impl Default for AtmospherePlugin {
             #[cfg(feature = "ml-emotions")]
             classifier: Arc::new(Mutex::new(None)),
             #[cfg(feature = "ml-emotions")]
+            model_task: None,
+            #[cfg(feature = "ml-emotions")]
+            task_spawner: None,
+            #[cfg(feature = "ml-emotions")]
             analysis_in_progress: Arc::new(AtomicBool::new(false)),
             #[cfg(feature = "ml-emotions")]
             pending_sentiment: Arc::new(Mutex::new(None)),
@@ -121,36 +132,39 @@ impl Default for AtmospherePlugin {
 
 impl AtmospherePlugin {
     pub fn new() -> Self {
-        let plugin = Self::default();
-        
-        #[cfg(feature = "ml-emotions")]
-        {
-            // Start model loading in background
-            let classifier_arc = plugin.classifier.clone();
-            std::thread::spawn(move || {
-                tracing::info!("Loading emotion detection model in background...");
-                match downloader::ensure_model_downloaded() {
-                    Ok((model_path, tokenizer_path)) => {
-                        match EmotionClassifier::new(&model_path, &tokenizer_path) {
-                            Ok(classifier) => {
-                                tracing::info!("✓ Emotion detection model loaded successfully");
-                                let mut lock = classifier_arc.lock().unwrap();
-                                *lock = Some(classifier);
-                            }
-                            Err(e) => {
-                                tracing::warn!("Failed to load classifier: {}. Using lexicon fallback.", e);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to download model: {}. Using lexicon fallback.", e);
-                    }
-                }
-            });
-            tracing::info!("Model loading started in background. Using lexicon until ready...");
+        Self::default()
+    }
+
+    #[cfg(feature = "ml-emotions")]
+    /// Start downloading and loading the emotion model as a background task
+    fn start_model_loading(&mut self, ctx: &PluginContext) {
+        let classifier_arc = self.classifier.clone();
+        self.model_task = Some(ctx.spawn_task("Emotion model", move |progress| {
+            tracing::info!("Loading emotion detection model in background...");
+            let (model_path, tokenizer_path) = downloader::ensure_model_downloaded(progress)
+                .map_err(|e| e.context("Failed to download model"))?;
+            progress.check_cancelled()?;
+            progress.set_message("Loading model");
+            let classifier = EmotionClassifier::new(&model_path, &tokenizer_path)
+                .map_err(|e| e.context("Failed to load classifier"))?;
+            tracing::info!("✓ Emotion detection model loaded successfully");
+            if let Ok(mut lock) = classifier_arc.lock() {
+                *lock = Some(classifier);
+            }
+            Ok(())
+        }));
+        tracing::info!("Model loading started in background. Using lexicon until ready...");
+    }
+
+    #[cfg(feature = "ml-emotions")]
+    /// Report the outcome of model loading once it is known
+    fn check_model_task(&mut self) {
+        if let Some(result) = self.model_task.as_mut().and_then(TaskHandle::try_take) {
+            self.model_task = None;
+            if let Err(e) = result {
+                tracing::warn!("{:#}. Using lexicon fallback.", e);
+            }
         }
-        
-        plugin
     }
 
     #[cfg(feature = "ml-emotions")]
@@ -284,14 +298,20 @@ impl AtmospherePlugin {
             return;
         }
         
-        // Start analysis in background thread
+        let Some(spawner) = self.task_spawner.clone() else {
+            self.analyze_sentiment_lexicon(&content, relative_cursor);
+            return;
+        };
+
+        // Start analysis on the executor's blocking pool
         self.analysis_in_progress.store(true, Ordering::Relaxed);
         
         let classifier_arc = self.classifier.clone();
         let in_progress_flag = self.analysis_in_progress.clone();
         let result_arc = self.pending_sentiment.clone();
         
-        std::thread::spawn(move || {
+        // The outcome is delivered through `pending_sentiment`
+        let _ = spawner.spawn_blocking(move || {
             // ... (worker logic same as before)
             tracing::info!("[P#{}] ML Emotion analysis started...", p_idx);
             let result = {
@@ -530,7 +550,14 @@ impl Plugin for AtmospherePlugin {
         )
     }
 
-    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+    fn initialize(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        #[cfg(feature = "ml-emotions")]
+        {
+            self.task_spawner = ctx.task_spawner().cloned();
+            self.start_model_loading(ctx);
+        }
+        #[cfg(not(feature = "ml-emotions"))]
+        let _ = ctx;
         tracing::info!("Atmosphere plugin initialized (ML emotion detection)");
         Ok(())
     }
@@ -554,6 +581,7 @@ impl Plugin for AtmospherePlugin {
                 self.last_project_path = None;
             }
 
+            self.check_model_task();
             self.check_pending_analysis();
         }
 
@@ -0,0 +1,296 @@
+//! # Project binder plugin for Cosmarium
+//!
+//! Shows the project's content directory as a tree of folders and
+//! documents. Clicking a document opens it in the editor; dragging a node
+//! onto one of its siblings reorders them. The order is stored in the
+//! project state by the application, which publishes it back to the binder
+//! under [`DOCUMENT_ORDER_KEY`]. A document is exported from its context
+//! menu (see [`EXPORT_DOCUMENT_REQUEST`]).
+
+pub mod tree;
+
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+};
+use egui::Ui;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tree::BinderNode;
+
+/// Configuration key under which the application publishes the project's
+/// document order (`Vec<String>`).
+pub const DOCUMENT_ORDER_KEY: &str = "document_order";
+
+/// Shared state key of a new document order to store in the project
+/// (`Option<Vec<String>>`), served by the application.
+pub const DOCUMENT_ORDER_REQUEST: &str = "document_order_request";
+
+/// Shared state key of a document to export (`Option<(PathBuf, String)>`,
+/// its path and the identifier of the format), served by the application.
+pub const EXPORT_DOCUMENT_REQUEST: &str = "export_document_request";
+
+/// Single document exports offered in the context menu (format id, label).
+const EXPORT_FORMATS: &[(&str, &str)] = &[
+    ("html", "HTML page"),
+    ("smf_text", "Plain text (manuscript format)"),
+    #[cfg(feature = "tts")]
+    ("audio", "Audio reading"),
+];
+
+/// How often the content directory is rescanned.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Drag-and-drop payload carrying a node key.
+struct NodeDrag(String);
+
+/// What the user did in the binder during a frame.
+enum BinderAction {
+    Open(PathBuf),
+    Export(PathBuf, &'static str),
+    Move { moved: String, target: String },
+    Shift { moved: String, delta: isize },
+}
+
+#[derive(Default)]
+pub struct BinderPlugin {
+    /// Folders and documents of the content directory
+    nodes: Vec<BinderNode>,
+    /// Document order of the project
+    order: Vec<String>,
+    /// Project the tree belongs to
+    project: Option<PathBuf>,
+    /// Time of the last scan
+    last_scan: Option<Instant>,
+}
+
+impl BinderPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a reordering and ask the application to store it.
+    fn reorder(&mut self, ctx: &mut PluginContext, action: BinderAction) {
+        let moved = match action {
+            BinderAction::Move { moved, target } => tree::move_to(&mut self.nodes, &moved, &target),
+            BinderAction::Shift { moved, delta } => tree::shift(&mut self.nodes, &moved, delta),
+            BinderAction::Open(_) | BinderAction::Export(..) => false,
+        };
+        if moved {
+            self.order = tree::order_of(&self.nodes);
+            ctx.set_shared_state(DOCUMENT_ORDER_REQUEST, Some(self.order.clone()));
+        }
+    }
+
+    fn render_node(
+        ui: &mut Ui,
+        node: &BinderNode,
+        active: Option<&PathBuf>,
+        actions: &mut Vec<BinderAction>,
+    ) {
+        let id = egui::Id::new(("binder_node", &node.key));
+        let icon = if node.is_folder() { "📁" } else { "📄" };
+        let is_active = active == Some(&node.path);
+
+        let mut header = |ui: &mut Ui| {
+            let drag = ui.dnd_drag_source(id, NodeDrag(node.key.clone()), |ui| {
+                ui.selectable_label(is_active, format!("{} {}", icon, node.title))
+            });
+            let label = drag.inner;
+            let response = drag.response;
+
+            if !node.is_folder() && label.clicked() {
+                actions.push(BinderAction::Open(node.path.clone()));
+            }
+            label.context_menu(|ui| {
+                if !node.is_folder() {
+                    if ui.button("Open").clicked() {
+                        actions.push(BinderAction::Open(node.path.clone()));
+                        ui.close();
+                    }
+                    ui.menu_button("Export", |ui| {
+                        for (format, label) in EXPORT_FORMATS {
+                            if ui.button(*label).clicked() {
+                                actions.push(BinderAction::Export(node.path.clone(), format));
+                                ui.close();
+                            }
+                        }
+                    });
+                }
+                if ui.button("Move up").clicked() {
+                    actions.push(BinderAction::Shift {
+                        moved: node.key.clone(),
+                        delta: -1,
+                    });
+                    ui.close();
+                }
+                if ui.button("Move down").clicked() {
+                    actions.push(BinderAction::Shift {
+                        moved: node.key.clone(),
+                        delta: 1,
+                    });
+                    ui.close();
+                }
+            });
+
+            // Mark where a dragged sibling would land
+            if let Some(dragged) = response.dnd_hover_payload::<NodeDrag>() {
+                if dragged.0 != node.key {
+                    let rect = response.rect;
+                    ui.painter().hline(
+                        rect.x_range(),
+                        rect.center().y,
+                        ui.visuals().selection.stroke,
+                    );
+                }
+            }
+            if let Some(dragged) = response.dnd_release_payload::<NodeDrag>() {
+                if dragged.0 != node.key {
+                    actions.push(BinderAction::Move {
+                        moved: dragged.0.clone(),
+                        target: node.key.clone(),
+                    });
+                }
+            }
+        };
+
+        if node.is_folder() {
+            egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, true)
+                .show_header(ui, |ui| header(ui))
+                .body(|ui| {
+                    for child in &node.children {
+                        Self::render_node(ui, child, active, actions);
+                    }
+                });
+        } else {
+            header(ui);
+        }
+    }
+}
+
+impl Plugin for BinderPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new("binder", "0.1.0", "Project binder", "Cosmarium Team")
+    }
+
+    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+
+    fn update(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl PanelPlugin for BinderPlugin {
+    fn panel_title(&self) -> &str {
+        "Binder"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "🗃"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Left
+    }
+
+    fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        let project = ctx.project_path();
+        if project != self.project {
+            self.project = project;
+            self.nodes.clear();
+            self.last_scan = None;
+        }
+
+        let order: Vec<String> = ctx.get_config(DOCUMENT_ORDER_KEY).unwrap_or_default();
+        if order != self.order {
+            self.order = order;
+            tree::sort(&mut self.nodes, &self.order);
+        }
+
+        if let Some(project) = &self.project {
+            if self
+                .last_scan
+                .is_none_or(|t| t.elapsed() >= RESCAN_INTERVAL)
+            {
+                self.nodes = tree::scan(project, &self.order);
+                self.last_scan = Some(Instant::now());
+            }
+        }
+        Ok(())
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        if self.project.is_none() {
+            ui.label("Open a project to see its documents.");
+            return;
+        }
+        if self.nodes.is_empty() {
+            ui.label("The project has no documents yet.");
+            return;
+        }
+
+        let active = ctx
+            .get_shared_state::<Option<PathBuf>>("active_document_path")
+            .flatten();
+        let mut actions = Vec::new();
+        egui::ScrollArea::vertical()
+            .id_salt("binder_tree")
+            .show(ui, |ui| {
+                for node in &self.nodes {
+                    Self::render_node(ui, node, active.as_ref(), &mut actions);
+                }
+            });
+
+        for action in actions {
+            match action {
+                BinderAction::Open(path) => {
+                    ctx.set_shared_state("open_document_request", Some((path, 0usize)));
+                }
+                BinderAction::Export(path, format) => {
+                    ctx.set_shared_state(EXPORT_DOCUMENT_REQUEST, Some((path, format.to_string())));
+                }
+                action => self.reorder(ctx, action),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(key: &str) -> BinderNode {
+        BinderNode {
+            key: key.to_string(),
+            path: PathBuf::from("/projects/novel").join(key),
+            title: key.to_string(),
+            kind: tree::NodeKind::Document,
+            children: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_reorder_requests_the_new_order() {
+        let mut plugin = BinderPlugin::new();
+        plugin.nodes = vec![document("content/a.md"), document("content/b.md")];
+        let mut ctx = PluginContext::new();
+
+        plugin.reorder(
+            &mut ctx,
+            BinderAction::Move {
+                moved: "content/b.md".into(),
+                target: "content/a.md".into(),
+            },
+        );
+
+        let expected = vec!["content/b.md".to_string(), "content/a.md".to_string()];
+        assert_eq!(plugin.order, expected);
+        let request: Option<Vec<String>> = ctx.get_shared_state(DOCUMENT_ORDER_REQUEST).unwrap();
+        assert_eq!(request, Some(expected));
+    }
+}
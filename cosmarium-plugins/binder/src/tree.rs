@@ -0,0 +1,271 @@
+//! Binder tree of a project's content directory.
+//!
+//! Folders hold parts and chapters, files hold chapters and scenes. The
+//! order of the nodes comes from the project's document order, a list of
+//! node keys: nodes missing from it follow the listed ones, alphabetically.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// File extensions shown in the binder.
+const DOCUMENT_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// What a binder node stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// A folder of the content directory
+    Folder,
+    /// A document file
+    Document,
+}
+
+/// A folder or document of the binder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinderNode {
+    /// Path relative to the project root, with `/` separators
+    pub key: String,
+    /// Absolute path
+    pub path: PathBuf,
+    /// Name shown in the binder
+    pub title: String,
+    /// Folder or document
+    pub kind: NodeKind,
+    /// Nodes of a folder, in binder order
+    pub children: Vec<BinderNode>,
+}
+
+impl BinderNode {
+    /// Whether this node is a folder.
+    pub fn is_folder(&self) -> bool {
+        self.kind == NodeKind::Folder
+    }
+}
+
+/// Key of a path: relative to the project root, with `/` separators so the
+/// order stays portable across platforms.
+pub fn node_key(project: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(project).ok()?;
+    Some(
+        rel.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// Build the binder of `project`'s content directory, in `order`.
+pub fn scan(project: &Path, order: &[String]) -> Vec<BinderNode> {
+    let mut nodes = scan_dir(project, &project.join("content"));
+    sort(&mut nodes, order);
+    nodes
+}
+
+fn scan_dir(project: &Path, dir: &Path) -> Vec<BinderNode> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut nodes = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(key) = node_key(project, &path) else {
+            continue;
+        };
+        if path.is_dir() {
+            nodes.push(BinderNode {
+                key,
+                title: path
+                    .file_name()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("Folder")
+                    .to_string(),
+                children: scan_dir(project, &path),
+                path,
+                kind: NodeKind::Folder,
+            });
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| DOCUMENT_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        {
+            nodes.push(BinderNode {
+                key,
+                title: path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("Untitled")
+                    .to_string(),
+                path,
+                kind: NodeKind::Document,
+                children: Vec::new(),
+            });
+        }
+    }
+    nodes
+}
+
+/// Sort `nodes` and their children in `order`.
+pub fn sort(nodes: &mut [BinderNode], order: &[String]) {
+    let positions: HashMap<&str, usize> = order
+        .iter()
+        .enumerate()
+        .map(|(i, key)| (key.as_str(), i))
+        .collect();
+    sort_with(nodes, &positions);
+}
+
+fn sort_with(nodes: &mut [BinderNode], positions: &HashMap<&str, usize>) {
+    nodes.sort_by(|a, b| {
+        let position = |node: &BinderNode| positions.get(node.key.as_str()).copied();
+        match (position(a), position(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => a.key.cmp(&b.key),
+        }
+    });
+    for node in nodes {
+        sort_with(&mut node.children, positions);
+    }
+}
+
+/// Keys of every node, parents before their children, in binder order.
+pub fn order_of(nodes: &[BinderNode]) -> Vec<String> {
+    fn walk(nodes: &[BinderNode], order: &mut Vec<String>) {
+        for node in nodes {
+            order.push(node.key.clone());
+            walk(&node.children, order);
+        }
+    }
+
+    let mut order = Vec::new();
+    walk(nodes, &mut order);
+    order
+}
+
+/// Move the node `moved` to the place of its sibling `target`, shifting the
+/// nodes in between.
+///
+/// Returns `false` if the two nodes are not siblings.
+pub fn move_to(nodes: &mut Vec<BinderNode>, moved: &str, target: &str) -> bool {
+    let from = nodes.iter().position(|n| n.key == moved);
+    let to = nodes.iter().position(|n| n.key == target);
+    if let (Some(from), Some(to)) = (from, to) {
+        if from == to {
+            return false;
+        }
+        let node = nodes.remove(from);
+        nodes.insert(to, node);
+        return true;
+    }
+    nodes
+        .iter_mut()
+        .any(|node| move_to(&mut node.children, moved, target))
+}
+
+/// Move the node `moved` one place up (`-1`) or down (`1`) among its
+/// siblings.
+///
+/// Returns `false` if it cannot move that way.
+pub fn shift(nodes: &mut [BinderNode], moved: &str, delta: isize) -> bool {
+    if let Some(from) = nodes.iter().position(|n| n.key == moved) {
+        let Some(to) = from
+            .checked_add_signed(delta)
+            .filter(|&to| to < nodes.len())
+        else {
+            return false;
+        };
+        nodes.swap(from, to);
+        return true;
+    }
+    nodes
+        .iter_mut()
+        .any(|node| shift(&mut node.children, moved, delta))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "cosmarium_binder_test_{}_{:?}",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        std::fs::create_dir_all(root.join("content/part1")).unwrap();
+        std::fs::create_dir_all(root.join("content/part2")).unwrap();
+        for file in [
+            "part1/a.md",
+            "part1/b.md",
+            "part2/c.md",
+            "notes.txt",
+            "cover.png",
+        ] {
+            std::fs::write(root.join("content").join(file), "Text").unwrap();
+        }
+        root
+    }
+
+    fn titles(nodes: &[BinderNode]) -> Vec<&str> {
+        nodes.iter().map(|n| n.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_scan_follows_the_order() {
+        let root = project();
+
+        let nodes = scan(&root, &[]);
+        assert_eq!(titles(&nodes), vec!["notes", "part1", "part2"]);
+        assert_eq!(titles(&nodes[1].children), vec!["a", "b"]);
+        assert_eq!(nodes[1].children[0].key, "content/part1/a.md");
+        assert!(nodes[1].is_folder());
+
+        let order = vec![
+            "content/part2".to_string(),
+            "content/part1".to_string(),
+            "content/part1/b.md".to_string(),
+        ];
+        let nodes = scan(&root, &order);
+        assert_eq!(titles(&nodes), vec!["part2", "part1", "notes"]);
+        assert_eq!(titles(&nodes[1].children), vec!["b", "a"]);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_moves_stay_among_siblings() {
+        let root = project();
+        let mut nodes = scan(&root, &[]);
+
+        assert!(move_to(&mut nodes, "content/notes.txt", "content/part2"));
+        assert_eq!(titles(&nodes), vec!["part1", "part2", "notes"]);
+        assert!(move_to(
+            &mut nodes,
+            "content/part1/b.md",
+            "content/part1/a.md"
+        ));
+        assert_eq!(titles(&nodes[0].children), vec!["b", "a"]);
+        assert!(!move_to(
+            &mut nodes,
+            "content/part1/b.md",
+            "content/part2/c.md"
+        ));
+
+        assert!(shift(&mut nodes, "content/part2", -1));
+        assert!(!shift(&mut nodes, "content/part2", -1));
+        assert_eq!(
+            order_of(&nodes),
+            vec![
+                "content/part2",
+                "content/part2/c.md",
+                "content/part1",
+                "content/part1/b.md",
+                "content/part1/a.md",
+                "content/notes.txt",
+            ]
+        );
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
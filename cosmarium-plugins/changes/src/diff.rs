@@ -0,0 +1,290 @@
+//! What changed from one [`StatsRecord`] to another: the words added or
+//! removed in each chapter, the scenes written or deleted, and the change
+//! of the number of scenes each character appears in.
+//!
+//! # Example
+//!
+//! ```rust
+//! use chrono::Utc;
+//! use cosmarium_changes::diff::StatsDiff;
+//! use cosmarium_changes::record::{ChapterRecord, SceneRecord, StatsRecord};
+//! use uuid::Uuid;
+//!
+//! let scene = SceneRecord { id: Uuid::new_v4(), title: "Dawn".into(), words: 900 };
+//! let chapter = ChapterRecord { id: None, title: "Arrival".into(), scenes: vec![scene.clone()] };
+//! let before = StatsRecord {
+//!     taken_at: Utc::now(),
+//!     label: String::new(),
+//!     chapters: vec![chapter.clone()],
+//!     characters: Default::default(),
+//! };
+//! let mut after = before.clone();
+//! after.chapters[0].scenes[0].words = 1200;
+//!
+//! let diff = StatsDiff::between(&before, &after);
+//! assert_eq!(diff.chapters[0].net(), 300);
+//! assert!(diff.scenes.is_empty());
+//! ```
+
+use crate::record::{ChapterRecord, StatsRecord};
+use std::fmt::Write;
+use uuid::Uuid;
+
+/// Name shown for the scenes at the top of the project, outside chapters.
+pub const TOP_LEVEL: &str = "(top level)";
+
+/// A count before and after, `None` where the thing did not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub name: String,
+    pub before: Option<usize>,
+    pub after: Option<usize>,
+}
+
+impl Change {
+    /// Difference of the counts, absent ones counting as 0.
+    pub fn net(&self) -> i64 {
+        self.after.unwrap_or(0) as i64 - self.before.unwrap_or(0) as i64
+    }
+
+    /// Whether the thing is new.
+    pub fn is_new(&self) -> bool {
+        self.before.is_none()
+    }
+
+    /// Whether the thing was deleted.
+    pub fn is_deleted(&self) -> bool {
+        self.after.is_none()
+    }
+}
+
+/// Changes from one record to another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsDiff {
+    /// Words of every chapter, in the order of the later record, then the
+    /// deleted chapters
+    pub chapters: Vec<Change>,
+    /// Words of the scenes written or deleted in between
+    pub scenes: Vec<Change>,
+    /// Appearances of the characters whose count changed
+    pub characters: Vec<Change>,
+    /// Words of the whole project
+    pub words: Change,
+}
+
+impl StatsDiff {
+    /// Changes from `before` to `after`. Chapters and scenes are told apart
+    /// by their node in the project structure, whatever their title.
+    pub fn between(before: &StatsRecord, after: &StatsRecord) -> Self {
+        let chapter_words = |record: &StatsRecord, id: Option<Uuid>| {
+            record
+                .chapters
+                .iter()
+                .filter(|chapter| chapter.id == id)
+                .map(ChapterRecord::words)
+                .reduce(|a, b| a + b)
+        };
+        let mut chapters: Vec<Change> = Vec::new();
+        let mut seen = Vec::new();
+        for chapter in after.chapters.iter().chain(&before.chapters) {
+            if seen.contains(&chapter.id) {
+                continue;
+            }
+            seen.push(chapter.id);
+            chapters.push(Change {
+                name: chapter_name(chapter),
+                before: chapter_words(before, chapter.id),
+                after: chapter_words(after, chapter.id),
+            });
+        }
+
+        let mut scenes = Vec::new();
+        for (chapter, scene) in after.scenes() {
+            if !before.scenes().any(|(_, old)| old.id == scene.id) {
+                scenes.push(Change {
+                    name: scene_name(chapter, &scene.title),
+                    before: None,
+                    after: Some(scene.words),
+                });
+            }
+        }
+        for (chapter, scene) in before.scenes() {
+            if !after.scenes().any(|(_, new)| new.id == scene.id) {
+                scenes.push(Change {
+                    name: scene_name(chapter, &scene.title),
+                    before: Some(scene.words),
+                    after: None,
+                });
+            }
+        }
+
+        let mut characters: Vec<Change> = after
+            .characters
+            .iter()
+            .map(|(name, count)| Change {
+                name: name.clone(),
+                before: before.characters.get(name).copied(),
+                after: Some(*count),
+            })
+            .chain(
+                before
+                    .characters
+                    .iter()
+                    .filter(|(name, _)| !after.characters.contains_key(*name))
+                    .map(|(name, count)| Change {
+                        name: name.clone(),
+                        before: Some(*count),
+                        after: None,
+                    }),
+            )
+            .filter(|change| change.before != change.after)
+            .collect();
+        characters.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            chapters,
+            scenes,
+            characters,
+            words: Change {
+                name: "Words".to_string(),
+                before: Some(before.words()),
+                after: Some(after.words()),
+            },
+        }
+    }
+
+    /// The changes as CSV, one line per chapter, scene and character.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("section,name,before,after,change\n");
+        let sections = [
+            ("project", std::slice::from_ref(&self.words)),
+            ("chapter", self.chapters.as_slice()),
+            ("scene", self.scenes.as_slice()),
+            ("character", self.characters.as_slice()),
+        ];
+        for (section, changes) in sections {
+            for change in changes {
+                let count = |count: Option<usize>| count.map(|c| c.to_string()).unwrap_or_default();
+                let _ = writeln!(
+                    csv,
+                    "{},{},{},{},{}",
+                    section,
+                    csv_field(&change.name),
+                    count(change.before),
+                    count(change.after),
+                    change.net()
+                );
+            }
+        }
+        csv
+    }
+}
+
+fn chapter_name(chapter: &ChapterRecord) -> String {
+    if chapter.title.is_empty() {
+        TOP_LEVEL.to_string()
+    } else {
+        chapter.title.clone()
+    }
+}
+
+/// Scene title, after that of its chapter.
+fn scene_name(chapter: &ChapterRecord, title: &str) -> String {
+    if chapter.title.is_empty() {
+        title.to_string()
+    } else {
+        format!("{} / {}", chapter.title, title)
+    }
+}
+
+/// `field` quoted when it holds a comma, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::SceneRecord;
+    use chrono::Utc;
+
+    fn record(chapters: Vec<ChapterRecord>, characters: &[(&str, usize)]) -> StatsRecord {
+        StatsRecord {
+            taken_at: Utc::now(),
+            label: String::new(),
+            chapters,
+            characters: characters
+                .iter()
+                .map(|(name, count)| (name.to_string(), *count))
+                .collect(),
+        }
+    }
+
+    fn scene(id: Uuid, title: &str, words: usize) -> SceneRecord {
+        SceneRecord {
+            id,
+            title: title.to_string(),
+            words,
+        }
+    }
+
+    #[test]
+    fn test_changes_by_chapter_scene_and_character() {
+        let (one, two) = (Uuid::new_v4(), Uuid::new_v4());
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let chapter = |id, title: &str, scenes| ChapterRecord {
+            id: Some(id),
+            title: title.to_string(),
+            scenes,
+        };
+        let before = record(
+            vec![
+                chapter(one, "One", vec![scene(a, "a", 100), scene(b, "b", 50)]),
+                chapter(two, "Two", vec![]),
+            ],
+            &[("Ann", 2), ("Bo", 1), ("Cy", 1)],
+        );
+        let after = record(
+            vec![chapter(
+                one,
+                "One, revised",
+                vec![scene(a, "a", 180), scene(c, "c", 40)],
+            )],
+            &[("Ann", 2), ("Bo", 2)],
+        );
+
+        let diff = StatsDiff::between(&before, &after);
+        let chapters: Vec<_> = diff
+            .chapters
+            .iter()
+            .map(|c| (c.name.as_str(), c.net(), c.is_deleted()))
+            .collect();
+        assert_eq!(chapters, [("One, revised", 70, false), ("Two", 0, true)]);
+        let scenes: Vec<_> = diff
+            .scenes
+            .iter()
+            .map(|s| (s.name.as_str(), s.is_new(), s.net()))
+            .collect();
+        assert_eq!(
+            scenes,
+            [("One, revised / c", true, 40), ("One / b", false, -50)]
+        );
+        let characters: Vec<_> = diff
+            .characters
+            .iter()
+            .map(|c| (c.name.as_str(), c.net()))
+            .collect();
+        assert_eq!(characters, [("Bo", 1), ("Cy", -1)]);
+        assert_eq!(diff.words.net(), 70);
+
+        let csv = diff.to_csv();
+        assert!(csv.starts_with("section,name,before,after,change\nproject,Words,150,220,70\n"));
+        assert!(csv.contains("\nchapter,\"One, revised\",150,220,70\n"));
+        assert!(csv.contains("\nscene,One / b,50,,-50\n"));
+        assert!(csv.ends_with("\ncharacter,Cy,1,,-1\n"));
+    }
+}
@@ -0,0 +1,487 @@
+//! # "What changed" statistics plugin for Cosmarium
+//!
+//! Compares the statistics of the project at two moments: the words added
+//! or removed in each chapter, the scenes written or deleted, and the change
+//! of the number of scenes each character of the wiki appears in, shown as
+//! tables and a chart, and exported as CSV to `exports/statistics/`.
+//!
+//! The moments are the [records](record) kept in the project: one taken
+//! each day the project is open, and those the author keeps under a label,
+//! before a revision for instance. The project as it is now can be compared
+//! with any of them.
+
+pub mod diff;
+pub mod record;
+
+use chrono::{DateTime, Utc};
+use cosmarium_plugin_api::locale::{DateStyle, Locale, LOCALE_KEY};
+use cosmarium_plugin_api::wiki::{LinkResolver, LINK_RESOLVER_KEY};
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+};
+use diff::{Change, StatsDiff};
+use egui::Ui;
+use record::{keep_record, load_records, save_records, StatsRecord};
+use std::path::{Path, PathBuf};
+
+/// Name of the plugin and of its panel.
+pub const PLUGIN_NAME: &str = "changes";
+
+/// Height of a bar of the chart, and the width of the widest.
+const BAR_HEIGHT: f32 = 12.0;
+const BAR_WIDTH: f32 = 220.0;
+
+/// A snapshot of the project asked for, and the label to keep its
+/// statistics under, if any.
+struct Pending {
+    asked_at: DateTime<Utc>,
+    keep: Option<String>,
+}
+
+#[derive(Default)]
+pub struct ChangesPlugin {
+    /// Project the records belong to
+    project: Option<PathBuf>,
+    /// Records of the project, oldest first
+    records: Vec<StatsRecord>,
+    /// Whether the records file could not be read, and must not be
+    /// overwritten
+    unreadable: bool,
+    /// Statistics of the project now, from the latest snapshot
+    current: Option<StatsRecord>,
+    pending: Option<Pending>,
+    /// Record compared from
+    from: Option<usize>,
+    /// Record compared to, `None` for the project now
+    to: Option<usize>,
+    /// Label of the next record kept
+    label: String,
+    /// Outcome of the last export or save
+    status: Option<String>,
+}
+
+impl ChangesPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the records of `project`, comparing the latest with now.
+    fn open_project(&mut self, project: Option<PathBuf>) {
+        self.records.clear();
+        self.unreadable = false;
+        self.status = None;
+        if let Some(project) = &project {
+            match load_records(project) {
+                Ok(records) => self.records = records,
+                Err(e) => {
+                    tracing::warn!("Cannot read the statistics records of {:?}: {}", project, e);
+                    self.unreadable = true;
+                    self.status = Some(format!("The statistics records cannot be read: {}", e));
+                }
+            }
+        }
+        self.project = project;
+        self.current = None;
+        self.pending = None;
+        self.from = self.records.len().checked_sub(1);
+        self.to = None;
+    }
+
+    /// Ask for a snapshot of the project, keeping its statistics under
+    /// `keep` when given.
+    fn request(&mut self, ctx: &mut PluginContext, keep: Option<String>) {
+        ctx.request_project_snapshot();
+        let keep = keep.or_else(|| self.pending.take().and_then(|pending| pending.keep));
+        self.pending = Some(Pending {
+            asked_at: ctx.clock().now(),
+            keep,
+        });
+    }
+
+    /// Take the statistics of the snapshot asked for, once published.
+    fn receive(&mut self, ctx: &PluginContext) {
+        let Some(pending) = &self.pending else {
+            return;
+        };
+        let Some(snapshot) = ctx.project_snapshot() else {
+            return;
+        };
+        if snapshot.taken_at() < pending.asked_at {
+            return;
+        }
+        let resolver = ctx
+            .get_shared_state::<LinkResolver>(LINK_RESOLVER_KEY)
+            .unwrap_or_else(|| LinkResolver::scan(snapshot.root()));
+        let current = StatsRecord::take(&snapshot, &resolver);
+        if let Some(label) = self.pending.take().and_then(|pending| pending.keep) {
+            self.keep(StatsRecord {
+                label,
+                ..current.clone()
+            });
+        }
+        self.current = Some(current);
+    }
+
+    /// Add `record` to the records and save them.
+    fn keep(&mut self, record: StatsRecord) {
+        let Some(project) = &self.project else {
+            return;
+        };
+        if self.unreadable {
+            return;
+        }
+        keep_record(&mut self.records, record);
+        if let Err(e) = save_records(project, &self.records) {
+            tracing::error!("Cannot save the statistics records: {}", e);
+            self.status = Some(format!("The statistics records cannot be saved: {}", e));
+        }
+        if self.from.is_none() {
+            self.from = Some(0);
+        }
+    }
+
+    /// Whether a record of the day was already kept today.
+    fn kept_today(&self, ctx: &PluginContext) -> bool {
+        let today = ctx.clock().now().with_timezone(&chrono::Local).date_naive();
+        self.records
+            .iter()
+            .any(|record| record.label.is_empty() && record.local_date() == today)
+    }
+
+    /// Write the changes from `from` to `to` as CSV in the project at
+    /// `project`.
+    fn export(
+        project: &Path,
+        diff: &StatsDiff,
+        from: &StatsRecord,
+        to: Option<&StatsRecord>,
+    ) -> anyhow::Result<PathBuf> {
+        let dir = project.join("exports/statistics");
+        std::fs::create_dir_all(&dir)?;
+        let stamp = |record: &StatsRecord| record.taken_at.format("%Y-%m-%d-%H%M").to_string();
+        let name = format!(
+            "changes-{}-to-{}.csv",
+            stamp(from),
+            to.map_or_else(|| "now".to_string(), stamp)
+        );
+        let path = dir.join(name);
+        std::fs::write(&path, diff.to_csv())?;
+        Ok(path)
+    }
+
+    /// Choice of the records to compare, and of a label to keep the project
+    /// as it is now under.
+    fn render_choice(&mut self, ui: &mut Ui, ctx: &mut PluginContext, locale: &Locale) {
+        let name = |record: &StatsRecord| {
+            let date = locale.format_date_time(
+                record.taken_at.with_timezone(&chrono::Local).naive_local(),
+                DateStyle::Short,
+            );
+            if record.label.is_empty() {
+                date
+            } else {
+                format!("{} · {}", date, record.label)
+            }
+        };
+        egui::Grid::new("changes_choice").show(ui, |ui| {
+            ui.label("From:");
+            let selected = self.from.and_then(|i| self.records.get(i)).map(name);
+            egui::ComboBox::from_id_salt("changes_from")
+                .selected_text(selected.unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for (index, record) in self.records.iter().enumerate().rev() {
+                        ui.selectable_value(&mut self.from, Some(index), name(record));
+                    }
+                });
+            ui.end_row();
+
+            ui.label("To:");
+            let selected = self
+                .to
+                .and_then(|i| self.records.get(i))
+                .map_or_else(|| "Now".to_string(), name);
+            egui::ComboBox::from_id_salt("changes_to")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.to, None, "Now");
+                    for (index, record) in self.records.iter().enumerate().rev() {
+                        ui.selectable_value(&mut self.to, Some(index), name(record));
+                    }
+                });
+            if self.to.is_none() && ui.small_button("⟳").on_hover_text("Refresh").clicked() {
+                self.request(ctx, None);
+            }
+            ui.end_row();
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.label)
+                    .hint_text("Label, e.g. First draft")
+                    .desired_width(160.0),
+            );
+            let keep = ui
+                .add_enabled(
+                    !self.label.trim().is_empty() && !self.unreadable,
+                    egui::Button::new("Keep statistics"),
+                )
+                .on_hover_text("Keep the statistics of the project now, to compare with later");
+            if keep.clicked() {
+                let label = std::mem::take(&mut self.label).trim().to_string();
+                self.request(ctx, Some(label));
+            }
+        });
+    }
+}
+
+/// Count with its sign, e.g. `+1,200` or `−35`.
+fn signed(locale: &Locale, net: i64) -> String {
+    match net {
+        0 => "0".to_string(),
+        n if n > 0 => format!("+{}", locale.format_number(n)),
+        n => format!("−{}", locale.format_number(-n)),
+    }
+}
+
+/// Count, or a dash where there was none.
+fn count(locale: &Locale, count: Option<usize>) -> String {
+    count.map_or_else(|| "—".to_string(), |c| locale.format_count(c))
+}
+
+/// Rows of `changes`: name, before, after and the change.
+fn render_table(ui: &mut Ui, id: &str, changes: &[Change], locale: &Locale) {
+    egui::Grid::new(id).striped(true).show(ui, |ui| {
+        ui.strong("");
+        ui.strong("Before");
+        ui.strong("After");
+        ui.strong("Change");
+        ui.end_row();
+        for change in changes {
+            ui.label(&change.name);
+            ui.label(count(locale, change.before));
+            ui.label(count(locale, change.after));
+            ui.label(signed(locale, change.net()));
+            ui.end_row();
+        }
+    });
+}
+
+/// Bars of the words added, to the right, or removed, to the left, of each
+/// chapter that changed.
+fn render_chart(ui: &mut Ui, chapters: &[Change]) {
+    let changed: Vec<&Change> = chapters.iter().filter(|c| c.net() != 0).collect();
+    let most = changed.iter().map(|c| c.net().abs()).max().unwrap_or(0);
+    if most == 0 {
+        return;
+    }
+    let added = ui.visuals().selection.bg_fill;
+    let removed = ui.visuals().error_fg_color;
+    for change in changed {
+        ui.horizontal(|ui| {
+            let (rect, response) =
+                ui.allocate_exact_size(egui::vec2(BAR_WIDTH, BAR_HEIGHT), egui::Sense::hover());
+            let half = BAR_WIDTH / 2.0;
+            let length = half * change.net().abs() as f32 / most as f32;
+            let bar = if change.net() > 0 {
+                egui::Rect::from_min_size(
+                    rect.center_top(),
+                    egui::vec2(length.max(1.0), BAR_HEIGHT),
+                )
+            } else {
+                egui::Rect::from_min_size(
+                    rect.center_top() - egui::vec2(length.max(1.0), 0.0),
+                    egui::vec2(length.max(1.0), BAR_HEIGHT),
+                )
+            };
+            let color = if change.net() > 0 { added } else { removed };
+            ui.painter().rect_filled(bar, 2.0, color);
+            ui.painter().vline(
+                rect.center().x,
+                rect.y_range(),
+                ui.visuals().widgets.noninteractive.bg_stroke,
+            );
+            response.on_hover_text(format!("{}: {:+}", change.name, change.net()));
+            ui.label(&change.name);
+        });
+    }
+}
+
+impl Plugin for ChangesPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            PLUGIN_NAME,
+            "0.1.0",
+            "Statistics of what changed in the project between two moments",
+            "Cosmarium Team",
+        )
+    }
+
+    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+}
+
+impl PanelPlugin for ChangesPlugin {
+    fn panel_title(&self) -> &str {
+        "What Changed"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "Δ"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Right
+    }
+
+    fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        let project = ctx.project_path();
+        if project != self.project {
+            self.open_project(project);
+        }
+        if self.project.is_none() {
+            return Ok(());
+        }
+        self.receive(ctx);
+        // A record of each day the project is open
+        if self.pending.is_none() && !self.unreadable && !self.kept_today(ctx) {
+            self.request(ctx, Some(String::new()));
+        }
+        Ok(())
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        let Some(project) = self.project.clone() else {
+            ui.label("Open a project to see what changed in it over time.");
+            return;
+        };
+        let locale = ctx
+            .get_shared_state::<Locale>(LOCALE_KEY)
+            .unwrap_or_default();
+        if self.current.is_none() && self.pending.is_none() {
+            self.request(ctx, None);
+        }
+
+        self.render_choice(ui, ctx, &locale);
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+        ui.separator();
+
+        let from = self.from.and_then(|i| self.records.get(i));
+        let to = match self.to {
+            Some(index) => self.records.get(index),
+            None => self.current.as_ref(),
+        };
+        let (Some(from), Some(to)) = (from, to) else {
+            ui.weak("Statistics are kept each day the project is open. Come back tomorrow, or keep them under a label, to see what changed.");
+            return;
+        };
+        let diff = StatsDiff::between(from, to);
+
+        ui.horizontal(|ui| {
+            ui.heading(signed(&locale, diff.words.net()));
+            ui.label(format!(
+                "words ({} → {})",
+                count(&locale, diff.words.before),
+                count(&locale, diff.words.after)
+            ));
+        });
+        if ui.button("Export CSV").clicked() {
+            let to = self.to.and_then(|i| self.records.get(i));
+            self.status = Some(match Self::export(&project, &diff, from, to) {
+                Ok(path) => format!("Saved {}", path.display()),
+                Err(e) => format!("Export failed: {}", e),
+            });
+        }
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::CollapsingHeader::new("Chapters")
+                .default_open(true)
+                .show(ui, |ui| {
+                    render_chart(ui, &diff.chapters);
+                    ui.add_space(4.0);
+                    render_table(ui, "changes_chapters", &diff.chapters, &locale);
+                });
+            let (new, deleted): (Vec<Change>, Vec<Change>) =
+                diff.scenes.iter().cloned().partition(Change::is_new);
+            egui::CollapsingHeader::new(format!(
+                "Scenes: {} new, {} deleted",
+                locale.format_count(new.len()),
+                locale.format_count(deleted.len())
+            ))
+            .show(ui, |ui| {
+                for scene in &new {
+                    ui.label(format!(
+                        "＋ {} ({} words)",
+                        scene.name,
+                        count(&locale, scene.after)
+                    ));
+                }
+                for scene in &deleted {
+                    ui.label(format!(
+                        "− {} ({} words)",
+                        scene.name,
+                        count(&locale, scene.before)
+                    ));
+                }
+            });
+            egui::CollapsingHeader::new("Character appearances").show(ui, |ui| {
+                if diff.characters.is_empty() {
+                    ui.weak("The characters appear in as many scenes as before.");
+                } else {
+                    render_table(ui, "changes_characters", &diff.characters, &locale);
+                }
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmarium_plugin_api::snapshot::{
+        ProjectSnapshot, SnapshotDocument, SnapshotNode, PROJECT_SNAPSHOT_KEY,
+    };
+    use std::sync::Arc;
+
+    fn publish(ctx: &mut PluginContext, root: &Path, text: &str) {
+        let node = SnapshotNode::new("document", "Dawn").with_path("content/dawn.md");
+        let snapshot = ProjectSnapshot::new(root, vec![node])
+            .with_document("content/dawn.md", SnapshotDocument::new(text, 1));
+        ctx.set_shared_state(PROJECT_SNAPSHOT_KEY, Arc::new(snapshot));
+    }
+
+    #[test]
+    fn test_a_record_is_kept_each_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = PluginContext::new();
+        ctx.set_project_path(Some(dir.path().to_path_buf()));
+        let mut plugin = ChangesPlugin::new();
+
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(plugin.pending.is_some());
+        publish(&mut ctx, dir.path(), "Fog over the bay.");
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+
+        assert_eq!(plugin.records.len(), 1);
+        assert_eq!(plugin.records[0].words(), 4);
+        assert_eq!(plugin.current.as_ref().map(StatsRecord::words), Some(4));
+        // Not again the same day
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(plugin.pending.is_none());
+        assert_eq!(load_records(dir.path()).unwrap().len(), 1);
+
+        // Labeled records compare with now
+        plugin.request(&mut ctx, Some("Draft".to_string()));
+        publish(&mut ctx, dir.path(), "Fog over the bay, and rain.");
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert_eq!(plugin.records[1].label, "Draft");
+        let diff = StatsDiff::between(&plugin.records[0], plugin.current.as_ref().unwrap());
+        assert_eq!(diff.words.net(), 2);
+    }
+}
@@ -0,0 +1,311 @@
+//! Statistics of a project at one moment, kept to compare with later ones.
+//!
+//! A [`StatsRecord`] holds the words of each scene, grouped by chapter, and
+//! the number of scenes each character of the wiki appears in. The records
+//! of a project are kept in `meta/statistics.json`: one a day, taken the
+//! first time the project is open that day, and those the author kept under
+//! a label.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use cosmarium_plugin_api::snapshot::{ProjectSnapshot, SnapshotNode};
+use cosmarium_plugin_api::wiki::{links, LinkResolver, WikiEntry};
+use cosmarium_plugin_api::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// File of the records, relative to the project root.
+pub const RECORDS_FILE: &str = "meta/statistics.json";
+
+/// Records of the day kept, besides the labeled ones.
+const KEPT_DAILY: usize = 366;
+
+/// Words of a scene.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SceneRecord {
+    /// Node of the scene in the project structure
+    pub id: Uuid,
+    pub title: String,
+    pub words: usize,
+}
+
+/// Scenes of a chapter, or of the part or folder holding them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChapterRecord {
+    /// Node of the chapter, `None` for the scenes at the top of the project
+    pub id: Option<Uuid>,
+    /// Title of the chapter, empty at the top of the project
+    pub title: String,
+    pub scenes: Vec<SceneRecord>,
+}
+
+impl ChapterRecord {
+    /// Words of the chapter's scenes.
+    pub fn words(&self) -> usize {
+        self.scenes.iter().map(|scene| scene.words).sum()
+    }
+}
+
+/// Statistics of a project at one moment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatsRecord {
+    /// When the statistics were taken
+    pub taken_at: DateTime<Utc>,
+    /// Name the author kept the record under, empty for records of the day
+    #[serde(default)]
+    pub label: String,
+    /// Chapters with scenes, in reading order
+    pub chapters: Vec<ChapterRecord>,
+    /// Number of scenes each character appears in, by name
+    #[serde(default)]
+    pub characters: BTreeMap<String, usize>,
+}
+
+impl StatsRecord {
+    /// Statistics of `snapshot`, with the appearances of the character
+    /// entries of `resolver`.
+    pub fn take(snapshot: &ProjectSnapshot, resolver: &LinkResolver) -> Self {
+        let characters: Vec<&WikiEntry> = resolver
+            .entries()
+            .iter()
+            .filter(|entry| {
+                let kind = entry.kind.to_lowercase();
+                kind == "characters" || kind == "character"
+            })
+            .collect();
+        let mut record = Self {
+            taken_at: snapshot.taken_at(),
+            label: String::new(),
+            chapters: Vec::new(),
+            characters: characters
+                .iter()
+                .map(|entry| (entry.name.clone(), 0))
+                .collect(),
+        };
+        record.collect(snapshot, snapshot.nodes(), None, resolver, &characters);
+        record
+    }
+
+    /// Add the scenes of `nodes`, in `chapter`, and of their children.
+    fn collect(
+        &mut self,
+        snapshot: &ProjectSnapshot,
+        nodes: &[SnapshotNode],
+        chapter: Option<&SnapshotNode>,
+        resolver: &LinkResolver,
+        characters: &[&WikiEntry],
+    ) {
+        for node in nodes {
+            if !node.is_document() {
+                self.collect(snapshot, &node.children, Some(node), resolver, characters);
+                continue;
+            }
+            let Some(document) = node
+                .path
+                .as_deref()
+                .and_then(|path| snapshot.document(path))
+            else {
+                continue;
+            };
+            let text = body(&document.content);
+            for entry in characters {
+                if appears(text, entry, resolver) {
+                    *self.characters.entry(entry.name.clone()).or_default() += 1;
+                }
+            }
+
+            let id = chapter.map(|chapter| chapter.id);
+            if self.chapters.last().is_none_or(|last| last.id != id) {
+                self.chapters.push(ChapterRecord {
+                    id,
+                    title: chapter.map(|c| c.title.clone()).unwrap_or_default(),
+                    scenes: Vec::new(),
+                });
+            }
+            if let Some(last) = self.chapters.last_mut() {
+                last.scenes.push(SceneRecord {
+                    id: node.id,
+                    title: node.title.clone(),
+                    words: text.split_whitespace().count(),
+                });
+            }
+        }
+    }
+
+    /// Words of the whole project.
+    pub fn words(&self) -> usize {
+        self.chapters.iter().map(ChapterRecord::words).sum()
+    }
+
+    /// Scenes with their chapter, in reading order.
+    pub fn scenes(&self) -> impl Iterator<Item = (&ChapterRecord, &SceneRecord)> {
+        self.chapters
+            .iter()
+            .flat_map(|chapter| chapter.scenes.iter().map(move |scene| (chapter, scene)))
+    }
+
+    /// Day the record was taken, in local time.
+    pub fn local_date(&self) -> NaiveDate {
+        self.taken_at.with_timezone(&chrono::Local).date_naive()
+    }
+}
+
+/// `content` without its front matter block.
+fn body(content: &str) -> &str {
+    let Some(rest) = content.strip_prefix("---\n") else {
+        return content;
+    };
+    match rest.find("\n---") {
+        Some(end) => rest[end + 4..].trim_start_matches(['\r', '\n']),
+        None => content,
+    }
+}
+
+/// Whether `text` links to `entry` or names it, as a whole word.
+fn appears(text: &str, entry: &WikiEntry, resolver: &LinkResolver) -> bool {
+    let linked = links(text)
+        .iter()
+        .any(|link| resolver.resolve(&link.target) == Some(entry));
+    linked
+        || !entry.name.is_empty()
+            && text.match_indices(entry.name.as_str()).any(|(at, name)| {
+                let before = text[..at].chars().next_back();
+                let after = text[at + name.len()..].chars().next();
+                !before.is_some_and(char::is_alphanumeric)
+                    && !after.is_some_and(char::is_alphanumeric)
+            })
+}
+
+/// Records of the project at `project`, oldest first, none when it has not
+/// kept any yet.
+pub fn load_records(project: &Path) -> Result<Vec<StatsRecord>> {
+    let path = project.join(RECORDS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Save `records` in the project at `project`.
+pub fn save_records(project: &Path, records: &[StatsRecord]) -> Result<PathBuf> {
+    let path = project.join(RECORDS_FILE);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(records)?)?;
+    Ok(path)
+}
+
+/// Add `record` to `records`, forgetting the oldest records of the day past
+/// [`KEPT_DAILY`].
+pub fn keep_record(records: &mut Vec<StatsRecord>, record: StatsRecord) {
+    records.push(record);
+    records.sort_by_key(|record| record.taken_at);
+    let mut excess = records
+        .iter()
+        .filter(|record| record.label.is_empty())
+        .count()
+        .saturating_sub(KEPT_DAILY);
+    records.retain(|record| {
+        let forget = excess > 0 && record.label.is_empty();
+        excess -= usize::from(forget);
+        !forget
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmarium_plugin_api::snapshot::SnapshotDocument;
+
+    /// A project of two chapters, with Ann in two scenes and Bo in one.
+    fn snapshot() -> ProjectSnapshot {
+        let scene = |title: &str| {
+            SnapshotNode::new("document", title).with_path(format!("content/{}.md", title))
+        };
+        let one = SnapshotNode::new("chapter", "One").with_children(vec![scene("a"), scene("b")]);
+        let two = SnapshotNode::new("chapter", "Two").with_children(vec![scene("c")]);
+        ProjectSnapshot::new("/novel", vec![one, two, scene("coda")])
+            .with_document(
+                "content/a.md",
+                SnapshotDocument::new("---\npov: Ann\n---\nAnn met [[bo]].", 0),
+            )
+            .with_document("content/b.md", SnapshotDocument::new("Annie left.", 0))
+            .with_document(
+                "content/c.md",
+                SnapshotDocument::new("Ann, alone, wept.", 0),
+            )
+            .with_document("content/coda.md", SnapshotDocument::new("The end.", 0))
+    }
+
+    fn resolver() -> LinkResolver {
+        LinkResolver::new(vec![
+            WikiEntry::new("Ann", "characters", "/novel/entities/characters/ann.md"),
+            WikiEntry::new("Bo", "characters", "/novel/entities/characters/bo.md"),
+            WikiEntry::new("Inn", "places", "/novel/entities/places/inn.md"),
+        ])
+    }
+
+    #[test]
+    fn test_record_groups_scenes_by_chapter() {
+        let record = StatsRecord::take(&snapshot(), &resolver());
+
+        let chapters: Vec<_> = record
+            .chapters
+            .iter()
+            .map(|chapter| {
+                (
+                    chapter.title.as_str(),
+                    chapter.scenes.len(),
+                    chapter.words(),
+                )
+            })
+            .collect();
+        assert_eq!(chapters, [("One", 2, 5), ("Two", 1, 3), ("", 1, 2)]);
+        assert_eq!(record.words(), 10);
+        assert_eq!(record.characters["Ann"], 2);
+        assert_eq!(record.characters["Bo"], 1);
+        assert!(!record.characters.contains_key("Inn"));
+    }
+
+    #[test]
+    fn test_records_are_saved_in_the_project() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_records(dir.path()).unwrap().is_empty());
+
+        let mut records = Vec::new();
+        let record = StatsRecord::take(&snapshot(), &resolver());
+        keep_record(&mut records, record.clone());
+        save_records(dir.path(), &records).unwrap();
+        assert_eq!(load_records(dir.path()).unwrap(), [record]);
+
+        std::fs::write(dir.path().join(RECORDS_FILE), "{").unwrap();
+        assert!(load_records(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_only_recent_daily_records_are_kept() {
+        let record = StatsRecord::take(&snapshot(), &resolver());
+        let labeled = StatsRecord {
+            label: "First draft".to_string(),
+            taken_at: DateTime::UNIX_EPOCH,
+            ..record.clone()
+        };
+        let mut records = vec![labeled];
+        for day in 0..KEPT_DAILY as i64 + 5 {
+            let daily = StatsRecord {
+                taken_at: DateTime::UNIX_EPOCH + chrono::Duration::days(day + 1),
+                ..record.clone()
+            };
+            keep_record(&mut records, daily);
+        }
+        assert_eq!(records.len(), KEPT_DAILY + 1);
+        assert_eq!(records[0].label, "First draft");
+        assert_eq!(
+            records[1].taken_at,
+            DateTime::UNIX_EPOCH + chrono::Duration::days(6)
+        );
+    }
+}
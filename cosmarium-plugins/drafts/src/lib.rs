@@ -0,0 +1,379 @@
+//! # Alternate drafts plugin for Cosmarium
+//!
+//! Starts an alternate draft of the project, "Mara lives" for instance, on
+//! a Git branch of its own, and switches between the drafts. The changes
+//! of the project are committed before it leaves a draft, so nothing is
+//! lost going back and forth.
+//!
+//! A draft is merged back into another one: a text changed differently on
+//! both keeps each passage in conflict between markers, shown here side by
+//! side, until one side is kept or the text is edited. The merge is then
+//! completed, or given up.
+//!
+//! The branches are read and changed by the application (see
+//! [`branch`](cosmarium_plugin_api::branch)).
+
+use cosmarium_plugin_api::branch::{
+    branch_name, BranchRequest, Branches, ConflictedFile, MergeState, Side, BRANCHES_KEY,
+    BRANCH_REQUEST,
+};
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+};
+use egui::{RichText, Ui};
+use std::sync::Arc;
+
+/// Name of the plugin and of its panel.
+pub const PLUGIN_NAME: &str = "drafts";
+
+/// Branches a draft is merged back into by default, the first found.
+const MAIN_BRANCHES: [&str; 2] = ["main", "master"];
+
+#[derive(Default)]
+pub struct DraftsPlugin {
+    /// Branches of the active project, as published
+    branches: Option<Arc<Branches>>,
+    /// Label of the next draft
+    label: String,
+    /// Branch the current draft is merged back into
+    target: Option<String>,
+    /// Branch whose deletion awaits confirmation
+    deleting: Option<String>,
+}
+
+impl DraftsPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send `request` to the application.
+    fn request(ctx: &mut PluginContext, request: BranchRequest) {
+        ctx.set_shared_state(BRANCH_REQUEST, Some(request));
+    }
+
+    /// Branch to merge the current one back into when none was chosen: the
+    /// main one, or else the first other.
+    fn default_target(branches: &Branches) -> Option<String> {
+        MAIN_BRANCHES
+            .into_iter()
+            .find(|main| branches.others().any(|other| other == *main))
+            .or_else(|| branches.others().next())
+            .map(str::to_string)
+    }
+
+    /// Label of the next draft, and the button starting it.
+    fn render_start(&mut self, ui: &mut Ui, ctx: &mut PluginContext, branches: &Branches) {
+        let name = branch_name(&self.label);
+        let taken = branches.branches.contains(&name);
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.label)
+                    .hint_text("Label, e.g. Mara lives")
+                    .desired_width(200.0),
+            );
+            let entered =
+                response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            let start = ui
+                .add_enabled(!name.is_empty() && !taken, egui::Button::new("Start Draft"))
+                .on_hover_text(
+                    "Commit the changes, then go on with the text as it is on a branch of its own",
+                );
+            if (start.clicked() || entered) && !name.is_empty() && !taken {
+                self.label.clear();
+                Self::request(ctx, BranchRequest::StartDraft(name.clone()));
+            }
+        });
+        if taken {
+            ui.weak(format!("There is already a draft named {}.", name));
+        } else if !name.is_empty() {
+            ui.weak(format!("Branch: {}", name));
+        }
+    }
+
+    /// The other drafts, to switch to, merge here or delete.
+    fn render_list(&mut self, ui: &mut Ui, ctx: &mut PluginContext, branches: &Branches) {
+        let current = branches.current.clone().unwrap_or_default();
+        egui::ScrollArea::vertical()
+            .id_salt("drafts_list")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for name in branches.others() {
+                    ui.horizontal(|ui| {
+                        ui.label(name);
+                        if self.deleting.as_deref() == Some(name) {
+                            ui.label("Delete it?");
+                            if ui.button("Delete").clicked() {
+                                Self::request(ctx, BranchRequest::Delete(name.to_string()));
+                                self.deleting = None;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                self.deleting = None;
+                            }
+                            return;
+                        }
+                        if ui
+                            .button("Switch")
+                            .on_hover_text("Commit the changes, then go on with this draft")
+                            .clicked()
+                        {
+                            Self::request(ctx, BranchRequest::Switch(name.to_string()));
+                        }
+                        if ui
+                            .button("Merge Here")
+                            .on_hover_text(format!(
+                                "Bring the changes of this draft into {}",
+                                current
+                            ))
+                            .clicked()
+                        {
+                            Self::request(
+                                ctx,
+                                BranchRequest::Merge {
+                                    draft: name.to_string(),
+                                    into: current.clone(),
+                                },
+                            );
+                        }
+                        if ui.button("Delete…").clicked() {
+                            self.deleting = Some(name.to_string());
+                        }
+                    });
+                }
+            });
+    }
+
+    /// The branch to merge the current draft back into, and the button
+    /// merging it.
+    fn render_merge_back(&mut self, ui: &mut Ui, ctx: &mut PluginContext, branches: &Branches) {
+        let (Some(current), Some(target)) = (branches.current.clone(), self.target.clone()) else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("drafts_merge_target")
+                .selected_text(target.as_str())
+                .show_ui(ui, |ui| {
+                    for name in branches.others() {
+                        ui.selectable_value(&mut self.target, Some(name.to_string()), name);
+                    }
+                });
+            if ui
+                .button("Merge Back")
+                .on_hover_text(format!(
+                    "Commit the changes, then bring those of {} into {} and go on with it",
+                    current, target
+                ))
+                .clicked()
+            {
+                Self::request(
+                    ctx,
+                    BranchRequest::Merge {
+                        draft: current,
+                        into: target,
+                    },
+                );
+            }
+        });
+    }
+
+    /// The merge waiting for its conflicts to be resolved.
+    fn render_merge(&mut self, ui: &mut Ui, ctx: &mut PluginContext, merge: &MergeState) {
+        ui.label(RichText::new(format!("Merging {}", merge.draft)).strong());
+        if merge.conflicts.is_empty() {
+            ui.label("Every conflict is resolved.");
+        } else {
+            ui.weak(
+                "These passages were changed differently on both drafts. Keep one side, \
+                 or edit the text between the markers and save.",
+            );
+            egui::ScrollArea::vertical()
+                .id_salt("drafts_conflicts")
+                .show(ui, |ui| {
+                    for file in &merge.conflicts {
+                        Self::render_conflicted_file(ui, ctx, file, &merge.draft);
+                    }
+                });
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    merge.conflicts.is_empty(),
+                    egui::Button::new("Complete Merge"),
+                )
+                .on_hover_text("Commit the merge")
+                .clicked()
+            {
+                Self::request(ctx, BranchRequest::CompleteMerge);
+            }
+            if ui
+                .button("Abort Merge")
+                .on_hover_text("Give up the merge, bringing back the text as it was")
+                .clicked()
+            {
+                Self::request(ctx, BranchRequest::AbortMerge);
+            }
+        });
+    }
+
+    /// The passages in conflict in `file`, side by side.
+    fn render_conflicted_file(
+        ui: &mut Ui,
+        ctx: &mut PluginContext,
+        file: &ConflictedFile,
+        draft: &str,
+    ) {
+        let count = file.conflicts.len();
+        egui::CollapsingHeader::new(format!(
+            "{} ({} {})",
+            file.path.display(),
+            count,
+            if count == 1 { "conflict" } else { "conflicts" }
+        ))
+        .id_salt(&file.path)
+        .default_open(true)
+        .show(ui, |ui| {
+            for (i, conflict) in file.conflicts.iter().enumerate() {
+                ui.columns(2, |columns| {
+                    columns[0].label(RichText::new("Current").weak());
+                    columns[0].label(conflict.current.as_str());
+                    columns[1].label(RichText::new(draft).weak());
+                    columns[1].label(conflict.draft.as_str());
+                });
+                if i + 1 < count {
+                    ui.separator();
+                }
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Keep Current").clicked() {
+                    Self::request(
+                        ctx,
+                        BranchRequest::Resolve(file.path.clone(), Side::Current),
+                    );
+                }
+                if ui.button("Keep Draft").clicked() {
+                    Self::request(ctx, BranchRequest::Resolve(file.path.clone(), Side::Draft));
+                }
+            });
+        });
+    }
+}
+
+impl Plugin for DraftsPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            PLUGIN_NAME,
+            "0.1.0",
+            "Alternate drafts on Git branches, merged back into the text",
+            "Cosmarium Team",
+        )
+    }
+
+    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+}
+
+impl PanelPlugin for DraftsPlugin {
+    fn panel_title(&self) -> &str {
+        "Drafts"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "🌿"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Right
+    }
+
+    fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        self.branches = ctx
+            .get_shared_state::<Option<Arc<Branches>>>(BRANCHES_KEY)
+            .flatten();
+        let Some(branches) = &self.branches else {
+            self.target = None;
+            self.deleting = None;
+            return Ok(());
+        };
+        let other = |name: &String| branches.others().any(|other| other == name);
+        if !self.target.as_ref().is_some_and(other) {
+            self.target = Self::default_target(branches);
+        }
+        if !self.deleting.as_ref().is_some_and(other) {
+            self.deleting = None;
+        }
+        Ok(())
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        let Some(branches) = self.branches.clone() else {
+            ui.label("Open a project with a Git repository to keep alternate drafts of it.");
+            return;
+        };
+        if let Some(merge) = &branches.merge {
+            self.render_merge(ui, ctx, merge);
+            return;
+        }
+        let Some(current) = &branches.current else {
+            ui.weak("Save the project once to start drafts from it.");
+            return;
+        };
+
+        ui.label(format!("On {}", current));
+        self.render_start(ui, ctx, &branches);
+        ui.separator();
+        if branches.others().next().is_none() {
+            ui.weak(
+                "No other draft yet. Start one to try another take without losing the text as it is.",
+            );
+            return;
+        }
+        self.render_merge_back(ui, ctx, &branches);
+        ui.separator();
+        self.render_list(ui, ctx, &branches);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn branches(current: &str, names: &[&str]) -> Branches {
+        Branches {
+            current: Some(current.to_string()),
+            branches: names.iter().map(|name| name.to_string()).collect(),
+            merge: None,
+        }
+    }
+
+    #[test]
+    fn test_drafts_merge_back_into_the_main_branch() {
+        let mut ctx = PluginContext::new();
+        let mut plugin = DraftsPlugin::new();
+        let published = branches("mara-lives", &["alt", "main", "mara-lives"]);
+        ctx.set_shared_state(BRANCHES_KEY, Some(Arc::new(published)));
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert_eq!(plugin.target.as_deref(), Some("main"));
+
+        plugin.target = Some("alt".to_string());
+        plugin.deleting = Some("alt".to_string());
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert_eq!(plugin.target.as_deref(), Some("alt"));
+
+        // Deleted elsewhere
+        let published = branches("mara-lives", &["main", "mara-lives"]);
+        ctx.set_shared_state(BRANCHES_KEY, Some(Arc::new(published)));
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert_eq!(plugin.target.as_deref(), Some("main"));
+        assert!(plugin.deleting.is_none());
+
+        let published = branches("main", &["ending", "main"]);
+        ctx.set_shared_state(BRANCHES_KEY, Some(Arc::new(published)));
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert_eq!(plugin.target.as_deref(), Some("ending"));
+    }
+}
@@ -0,0 +1,732 @@
+//! Conversion of Markdown to WordprocessingML paragraphs.
+//!
+//! Headings, block quotes, lists, code blocks and scene breaks become
+//! paragraphs of the matching Word styles (see [`crate::styles`]), so that
+//! editors can restyle the whole manuscript from the template. Emphasis,
+//! strong emphasis and strikethrough become run formatting.
+//!
+//! Comments, written as HTML comments (`<!-- -->`) or CriticMarkup
+//! annotations (`{>> <<}`), are either dropped or turned into Word comments
+//! anchored where they appear. Direction and alignment markers (see
+//! [`cosmarium_plugin_api::direction`]) become paragraph properties, and
+//! right-to-left paragraphs are marked as such. Epigraphs (see
+//! [`cosmarium_plugin_api::epigraph`]) get styles of their own.
+
+use cosmarium_plugin_api::direction::{Alignment, Direction, ParagraphFormat};
+use cosmarium_plugin_api::epigraph::{self, ATTRIBUTION_DASH};
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use std::fmt::Write as _;
+
+/// Markers of a comment in the text handed to the Markdown parser.
+const COMMENT_START: char = '\u{E000}';
+const COMMENT_END: char = '\u{E001}';
+
+/// Indentation of nested quotes and list items, in twentieths of a point.
+const INDENT_STEP: usize = 720;
+
+/// Formatting of a run of text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub strike: bool,
+    pub code: bool,
+}
+
+/// A piece of a paragraph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Run {
+    Text(RunStyle, String),
+    /// Line break within the paragraph
+    Break,
+    /// Reference to a comment, by its index
+    Comment(usize),
+}
+
+/// A paragraph of the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paragraph {
+    /// Word style identifier
+    pub style: &'static str,
+    pub runs: Vec<Run>,
+    /// Nesting level of quotes and lists
+    pub level: usize,
+    /// Whether the paragraph starts a new page
+    pub page_break: bool,
+    pub direction: Direction,
+    /// Alignment set by a marker
+    pub alignment: Option<Alignment>,
+}
+
+impl Paragraph {
+    pub fn new(style: &'static str, runs: Vec<Run>) -> Self {
+        Self {
+            style,
+            runs,
+            level: 0,
+            page_break: false,
+            direction: Direction::Ltr,
+            alignment: None,
+        }
+    }
+
+    /// Text of the paragraph, without comments.
+    pub fn text(&self) -> String {
+        self.runs
+            .iter()
+            .map(|run| match run {
+                Run::Text(_, text) => text.as_str(),
+                Run::Break => "\n",
+                Run::Comment(_) => "",
+            })
+            .collect()
+    }
+
+    fn has_text(&self) -> bool {
+        self.runs
+            .iter()
+            .any(|run| matches!(run, Run::Text(_, text) if !text.trim().is_empty()))
+    }
+}
+
+/// How Markdown is converted.
+#[derive(Debug, Clone, Copy)]
+pub struct Conversion<'a> {
+    /// Whether to keep emphasis, strong emphasis and strikethrough
+    pub preserve_formatting: bool,
+    /// Whether to keep comments, as Word comments
+    pub include_comments: bool,
+    /// Text of scene break paragraphs
+    pub scene_break: &'a str,
+}
+
+/// Paragraphs and comments of a document being converted.
+#[derive(Debug, Default)]
+pub struct Document {
+    pub paragraphs: Vec<Paragraph>,
+    /// Text of the comments, referenced by [`Run::Comment`]
+    pub comments: Vec<String>,
+    /// Comments of blocks without text, attached to the next paragraph
+    pending: Vec<Run>,
+    /// Bullet or number of the list item being converted
+    bullet: Option<String>,
+    /// Format of the paragraph being converted
+    format: ParagraphFormat,
+}
+
+impl Document {
+    /// Convert `markdown` and append its paragraphs, with the style of
+    /// first-level headings replaced by `title_style` if given.
+    pub fn push_markdown(
+        &mut self,
+        markdown: &str,
+        conversion: &Conversion,
+        title_style: Option<&'static str>,
+    ) {
+        let markdown = self.extract_comments(markdown, conversion.include_comments);
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_STRIKETHROUGH);
+        options.insert(Options::ENABLE_FOOTNOTES);
+
+        let mut runs: Vec<Run> = Vec::new();
+        let mut style = RunStyle::default();
+        let (mut bold, mut italic, mut strike) = (0usize, 0usize, 0usize);
+        let mut quote_depth = 0usize;
+        let mut lists: Vec<Option<u64>> = Vec::new();
+        let mut code: Option<String> = None;
+        let mut in_epigraph = false;
+
+        for event in Parser::new_ext(&markdown, options) {
+            let level = (quote_depth + lists.len()).saturating_sub(1);
+            let body_style = if !lists.is_empty() {
+                "ListParagraph"
+            } else if in_epigraph {
+                let attribution = matches!(runs.first(),
+                    Some(Run::Text(_, text)) if text.starts_with(ATTRIBUTION_DASH));
+                if attribution {
+                    "EpigraphAttribution"
+                } else {
+                    "Epigraph"
+                }
+            } else if quote_depth > 0 {
+                "Quote"
+            } else {
+                "Normal"
+            };
+            match event {
+                Event::Start(Tag::Heading(..))
+                | Event::Start(Tag::Paragraph)
+                | Event::Start(Tag::BlockQuote)
+                | Event::Start(Tag::List(_))
+                | Event::Start(Tag::CodeBlock(_))
+                | Event::End(Tag::List(_)) => {
+                    self.flush(&mut runs, body_style, level);
+                    match event {
+                        Event::Start(Tag::BlockQuote) => quote_depth += 1,
+                        Event::Start(Tag::List(start)) => lists.push(start),
+                        Event::End(Tag::List(_)) => {
+                            lists.pop();
+                        }
+                        Event::Start(Tag::CodeBlock(_)) => code = Some(String::new()),
+                        _ => {}
+                    }
+                }
+                Event::End(Tag::Heading(level, ..)) => {
+                    let style = match (level as usize, title_style) {
+                        (1, Some(title)) => title,
+                        (1, None) => "Heading1",
+                        (2, _) => "Heading2",
+                        (3, _) => "Heading3",
+                        (4, _) => "Heading4",
+                        (5, _) => "Heading5",
+                        _ => "Heading6",
+                    };
+                    self.flush(&mut runs, style, 0);
+                }
+                Event::End(Tag::Paragraph) if in_epigraph => self.flush(&mut runs, body_style, 0),
+                Event::End(Tag::Paragraph) | Event::End(Tag::Item) => {
+                    self.flush(&mut runs, body_style, level)
+                }
+                Event::End(Tag::BlockQuote) => {
+                    let level = if in_epigraph { 0 } else { level };
+                    self.flush(&mut runs, body_style, level);
+                    quote_depth = quote_depth.saturating_sub(1);
+                }
+                Event::Start(Tag::Item) => {
+                    self.flush(&mut runs, body_style, level);
+                    self.bullet = Some(match lists.last_mut() {
+                        Some(Some(n)) => {
+                            *n += 1;
+                            format!("{}.\t", *n - 1)
+                        }
+                        _ => "\u{2022}\t".to_string(),
+                    });
+                }
+                Event::End(Tag::CodeBlock(_)) => {
+                    if let Some(text) = code.take() {
+                        let code_style = RunStyle {
+                            code: true,
+                            ..RunStyle::default()
+                        };
+                        for line in text.trim_end_matches('\n').split('\n') {
+                            self.push_text(&mut runs, code_style, line);
+                            runs.push(Run::Break);
+                        }
+                        runs.pop();
+                        self.flush(&mut runs, "SourceCode", 0);
+                    }
+                }
+                Event::Start(Tag::Emphasis) => {
+                    italic += 1;
+                    style.italic = conversion.preserve_formatting;
+                }
+                Event::End(Tag::Emphasis) => {
+                    italic = italic.saturating_sub(1);
+                    style.italic = conversion.preserve_formatting && italic > 0;
+                }
+                Event::Start(Tag::Strong) => {
+                    bold += 1;
+                    style.bold = conversion.preserve_formatting;
+                }
+                Event::End(Tag::Strong) => {
+                    bold = bold.saturating_sub(1);
+                    style.bold = conversion.preserve_formatting && bold > 0;
+                }
+                Event::Start(Tag::Strikethrough) => {
+                    strike += 1;
+                    style.strike = conversion.preserve_formatting;
+                }
+                Event::End(Tag::Strikethrough) => {
+                    strike = strike.saturating_sub(1);
+                    style.strike = conversion.preserve_formatting && strike > 0;
+                }
+                Event::Text(text) => match &mut code {
+                    Some(code) => code.push_str(&text),
+                    None => self.push_text(&mut runs, style, &text),
+                },
+                Event::Html(html) => {
+                    if let Some(format) = ParagraphFormat::parse_marker(&html) {
+                        self.format = format;
+                    }
+                    if let Some(start) = epigraph::parse_marker(&html) {
+                        in_epigraph = start;
+                    }
+                }
+                Event::Code(text) => {
+                    let code_style = RunStyle {
+                        code: conversion.preserve_formatting,
+                        ..style
+                    };
+                    self.push_text(&mut runs, code_style, &text);
+                }
+                Event::SoftBreak => runs.push(Run::Text(style, " ".to_string())),
+                Event::HardBreak => runs.push(Run::Break),
+                Event::FootnoteReference(label) => {
+                    runs.push(Run::Text(style, format!("[{}]", label)))
+                }
+                Event::Rule => {
+                    self.flush(&mut runs, body_style, level);
+                    let runs = vec![Run::Text(
+                        RunStyle::default(),
+                        conversion.scene_break.to_string(),
+                    )];
+                    self.paragraphs.push(Paragraph::new("SceneBreak", runs));
+                }
+                _ => {}
+            }
+        }
+        self.flush(&mut runs, "Normal", 0);
+    }
+
+    /// Replace the comments of `markdown` with markers holding their index,
+    /// or remove them.
+    fn extract_comments(&mut self, markdown: &str, include: bool) -> String {
+        let mut out = String::with_capacity(markdown.len());
+        let mut rest = markdown;
+        loop {
+            let next = [("<!--", "-->"), ("{>>", "<<}")]
+                .into_iter()
+                .filter_map(|(open, close)| Some((rest.find(open)?, open, close)))
+                .min_by_key(|(at, ..)| *at);
+            let Some((at, open, close)) = next else {
+                break;
+            };
+            let Some(len) = rest[at + open.len()..].find(close) else {
+                break;
+            };
+            out.push_str(&rest[..at]);
+            let end = at + open.len() + len + close.len();
+            let text = rest[at + open.len()..at + open.len() + len].trim();
+            if ParagraphFormat::parse_marker(&rest[at..end]).is_some()
+                || epigraph::parse_marker(&rest[at..end]).is_some()
+            {
+                // Read as an HTML block by the parser
+                out.push_str(&rest[at..end]);
+                rest = &rest[end..];
+                continue;
+            }
+            if include && !text.is_empty() {
+                let _ = write!(
+                    out,
+                    "{}{}{}",
+                    COMMENT_START,
+                    self.comments.len(),
+                    COMMENT_END
+                );
+                self.comments.push(text.to_string());
+            }
+            rest = &rest[end..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// Append `text` to `runs`, turning comment markers into references.
+    fn push_text(&self, runs: &mut Vec<Run>, style: RunStyle, text: &str) {
+        let mut rest = text;
+        while let Some(start) = rest.find(COMMENT_START) {
+            let Some(len) = rest[start..].find(COMMENT_END) else {
+                break;
+            };
+            if start > 0 {
+                runs.push(Run::Text(style, rest[..start].to_string()));
+            }
+            let index = &rest[start + COMMENT_START.len_utf8()..start + len];
+            if let Ok(index) = index.parse() {
+                runs.push(Run::Comment(index));
+            }
+            rest = &rest[start + len + COMMENT_END.len_utf8()..];
+        }
+        if !rest.is_empty() {
+            runs.push(Run::Text(style, rest.to_string()));
+        }
+    }
+
+    /// End the current paragraph. Comments of a paragraph without text move
+    /// to the next one.
+    fn flush(&mut self, runs: &mut Vec<Run>, style: &'static str, level: usize) {
+        let mut paragraph = Paragraph::new(style, std::mem::take(runs));
+        if !paragraph.has_text() {
+            self.pending.extend(
+                paragraph
+                    .runs
+                    .into_iter()
+                    .filter(|run| matches!(run, Run::Comment(_))),
+            );
+            return;
+        }
+        paragraph.level = level;
+        let format = std::mem::take(&mut self.format);
+        paragraph.direction = format.resolve(&paragraph.text());
+        paragraph.alignment = format.alignment;
+        if let Some(bullet) = self.bullet.take() {
+            paragraph
+                .runs
+                .insert(0, Run::Text(RunStyle::default(), bullet));
+        }
+        paragraph.runs.splice(0..0, self.pending.drain(..));
+        self.paragraphs.push(paragraph);
+    }
+
+    /// The `w:document` part.
+    pub fn document_xml(&mut self, section: &Section) -> String {
+        // Comments after the last paragraph
+        if !self.pending.is_empty() {
+            match self.paragraphs.last_mut() {
+                Some(last) => last.runs.append(&mut self.pending),
+                None => {
+                    let runs = std::mem::take(&mut self.pending);
+                    self.paragraphs.push(Paragraph::new("Normal", runs));
+                }
+            }
+        }
+
+        let mut xml = String::from(XML_DECLARATION);
+        xml.push_str(&format!("<w:document {}><w:body>", NAMESPACES));
+        for paragraph in &self.paragraphs {
+            paragraph_xml(&mut xml, paragraph);
+        }
+        xml.push_str("<w:sectPr>");
+        if section.running_header {
+            xml.push_str(&format!(
+                "<w:headerReference w:type=\"default\" r:id=\"{}\"/>",
+                HEADER_RELATIONSHIP
+            ));
+        }
+        let (width, height) = section.page_size;
+        let _ = write!(
+            xml,
+            "<w:pgSz w:w=\"{}\" w:h=\"{}\"/>\
+             <w:pgMar w:top=\"1440\" w:right=\"1440\" w:bottom=\"1440\" w:left=\"1440\" \
+             w:header=\"708\" w:footer=\"708\" w:gutter=\"0\"/>",
+            width, height
+        );
+        if section.running_header && section.title_page {
+            xml.push_str("<w:titlePg/>");
+        }
+        xml.push_str("</w:sectPr>");
+        xml.push_str("</w:body></w:document>");
+        xml
+    }
+
+    /// The `w:comments` part, if there are comments.
+    pub fn comments_xml(&self, author: &str) -> Option<String> {
+        if self.comments.is_empty() {
+            return None;
+        }
+        let author = if author.trim().is_empty() {
+            "Author"
+        } else {
+            author.trim()
+        };
+        let mut xml = String::from(XML_DECLARATION);
+        xml.push_str(&format!("<w:comments {}>", NAMESPACES));
+        for (id, text) in self.comments.iter().enumerate() {
+            let _ = write!(
+                xml,
+                "<w:comment w:id=\"{}\" w:author=\"{}\">",
+                id,
+                escape(author)
+            );
+            for line in text.lines().filter(|line| !line.trim().is_empty()) {
+                let _ = write!(
+                    xml,
+                    "<w:p><w:pPr><w:pStyle w:val=\"CommentText\"/></w:pPr>\
+                     <w:r><w:t xml:space=\"preserve\">{}</w:t></w:r></w:p>",
+                    escape(line.trim())
+                );
+            }
+            xml.push_str("</w:comment>");
+        }
+        xml.push_str("</w:comments>");
+        Some(xml)
+    }
+}
+
+/// Page settings of a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Section {
+    /// Page width and height, in twentieths of a point
+    pub page_size: (usize, usize),
+    /// Whether pages have the header of [`header_xml`]
+    pub running_header: bool,
+    /// Whether the first page is a title page, without header
+    pub title_page: bool,
+}
+
+impl Default for Section {
+    fn default() -> Self {
+        Self {
+            page_size: A4,
+            running_header: false,
+            title_page: false,
+        }
+    }
+}
+
+/// A4 page size, in twentieths of a point.
+pub const A4: (usize, usize) = (11906, 16838);
+
+/// US Letter page size, in twentieths of a point.
+pub const LETTER: (usize, usize) = (12240, 15840);
+
+/// Relationship identifier of the header part.
+pub const HEADER_RELATIONSHIP: &str = "rId3";
+
+/// The `w:hdr` part: `text`, then the page number, against the right
+/// margin.
+pub fn header_xml(text: &str) -> String {
+    let mut xml = String::from(XML_DECLARATION);
+    let _ = write!(
+        xml,
+        "<w:hdr {}><w:p><w:pPr><w:jc w:val=\"right\"/><w:ind w:firstLine=\"0\"/></w:pPr>\
+         <w:r><w:t xml:space=\"preserve\">{} / </w:t></w:r>\
+         <w:r><w:fldChar w:fldCharType=\"begin\"/></w:r>\
+         <w:r><w:instrText xml:space=\"preserve\"> PAGE </w:instrText></w:r>\
+         <w:r><w:fldChar w:fldCharType=\"separate\"/></w:r><w:r><w:t>1</w:t></w:r>\
+         <w:r><w:fldChar w:fldCharType=\"end\"/></w:r></w:p></w:hdr>",
+        NAMESPACES,
+        escape(text)
+    );
+    xml
+}
+
+pub const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+const NAMESPACES: &str =
+    "xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\" \
+     xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\"";
+
+fn paragraph_xml(xml: &mut String, paragraph: &Paragraph) {
+    let _ = write!(xml, "<w:p><w:pPr><w:pStyle w:val=\"{}\"/>", paragraph.style);
+    if paragraph.page_break {
+        xml.push_str("<w:pageBreakBefore/>");
+    }
+    if paragraph.direction == Direction::Rtl {
+        xml.push_str("<w:bidi/>");
+    }
+    if paragraph.level > 0 {
+        let _ = write!(
+            xml,
+            "<w:ind w:left=\"{}\"/>",
+            INDENT_STEP * (paragraph.level + 1)
+        );
+    }
+    if let Some(alignment) = paragraph.alignment {
+        let value = match alignment {
+            Alignment::Left => "left",
+            Alignment::Center => "center",
+            Alignment::Right => "right",
+            Alignment::Justify => "both",
+        };
+        let _ = write!(xml, "<w:jc w:val=\"{}\"/>", value);
+    }
+    xml.push_str("</w:pPr>");
+
+    for run in &paragraph.runs {
+        match run {
+            Run::Text(style, text) => {
+                xml.push_str("<w:r>");
+                if *style != RunStyle::default() {
+                    xml.push_str("<w:rPr>");
+                    if style.code {
+                        xml.push_str("<w:rStyle w:val=\"VerbatimChar\"/>");
+                    }
+                    if style.bold {
+                        xml.push_str("<w:b/>");
+                    }
+                    if style.italic {
+                        xml.push_str("<w:i/>");
+                    }
+                    if style.strike {
+                        xml.push_str("<w:strike/>");
+                    }
+                    xml.push_str("</w:rPr>");
+                }
+                // Tabs of list bullets are elements of their own
+                for (i, part) in text.split('\t').enumerate() {
+                    if i > 0 {
+                        xml.push_str("<w:tab/>");
+                    }
+                    if !part.is_empty() {
+                        let _ = write!(xml, "<w:t xml:space=\"preserve\">{}</w:t>", escape(part));
+                    }
+                }
+                xml.push_str("</w:r>");
+            }
+            Run::Break => xml.push_str("<w:r><w:br/></w:r>"),
+            Run::Comment(id) => {
+                let _ = write!(
+                    xml,
+                    "<w:commentRangeStart w:id=\"{id}\"/><w:commentRangeEnd w:id=\"{id}\"/>\
+                     <w:r><w:rPr><w:rStyle w:val=\"CommentReference\"/></w:rPr>\
+                     <w:commentReference w:id=\"{id}\"/></w:r>"
+                );
+            }
+        }
+    }
+    xml.push_str("</w:p>");
+}
+
+/// Escape `text` for XML, dropping characters XML does not allow.
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c < ' ' || c == COMMENT_START || c == COMMENT_END => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONVERSION: Conversion = Conversion {
+        preserve_formatting: true,
+        include_comments: true,
+        scene_break: "#",
+    };
+
+    #[test]
+    fn test_blocks_become_styled_paragraphs() {
+        let mut document = Document::default();
+        document.push_markdown(
+            "# Arrival\n\nShe *ran* **home**.\n\n> Said the *inn*.\n>\n> > Nested\n\n\
+             ***\n\n- one\n- two\n\n1. first\n\n```\nlet a;\nlet b;\n```",
+            &CONVERSION,
+            None,
+        );
+        let styles: Vec<_> = document
+            .paragraphs
+            .iter()
+            .map(|p| (p.style, p.level, p.text()))
+            .collect();
+        assert_eq!(
+            styles,
+            vec![
+                ("Heading1", 0, "Arrival".to_string()),
+                ("Normal", 0, "She ran home.".to_string()),
+                ("Quote", 0, "Said the inn.".to_string()),
+                ("Quote", 1, "Nested".to_string()),
+                ("SceneBreak", 0, "#".to_string()),
+                ("ListParagraph", 0, "\u{2022}\tone".to_string()),
+                ("ListParagraph", 0, "\u{2022}\ttwo".to_string()),
+                ("ListParagraph", 0, "1.\tfirst".to_string()),
+                ("SourceCode", 0, "let a;\nlet b;".to_string()),
+            ]
+        );
+        assert!(document.paragraphs[1].runs.contains(&Run::Text(
+            RunStyle {
+                italic: true,
+                ..RunStyle::default()
+            },
+            "ran".to_string()
+        )));
+
+        let xml = document.document_xml(&Section::default());
+        assert!(xml.contains("<w:pStyle w:val=\"Quote\"/><w:ind w:left=\"1440\"/>"));
+        assert!(xml.contains("<w:rPr><w:b/></w:rPr><w:t xml:space=\"preserve\">home</w:t>"));
+        assert!(xml.contains("<w:tab/></w:r><w:r><w:t xml:space=\"preserve\">one</w:t>"));
+    }
+
+    #[test]
+    fn test_epigraphs_have_their_styles() {
+        let mut document = Document::default();
+        document.push_markdown(
+            "# One\n\n<!-- epigraph -->\n> Go.\n>\n> — Ann\n<!-- /epigraph -->\n\n> Quote",
+            &CONVERSION,
+            None,
+        );
+        let styles: Vec<_> = document
+            .paragraphs
+            .iter()
+            .map(|p| (p.style, p.text()))
+            .collect();
+        assert_eq!(
+            styles,
+            vec![
+                ("Heading1", "One".to_string()),
+                ("Epigraph", "Go.".to_string()),
+                ("EpigraphAttribution", "— Ann".to_string()),
+                ("Quote", "Quote".to_string()),
+            ]
+        );
+        assert!(document.comments.is_empty());
+    }
+
+    #[test]
+    fn test_comments_and_plain_formatting() {
+        let markdown = "<!-- check the dates -->\n\nShe ran{>>too fast?<<} & hid.";
+        let mut document = Document::default();
+        document.push_markdown(markdown, &CONVERSION, Some("Title"));
+        assert_eq!(document.paragraphs.len(), 1);
+        assert_eq!(
+            document.paragraphs[0].runs,
+            vec![
+                Run::Comment(0),
+                Run::Text(RunStyle::default(), "She ran".to_string()),
+                Run::Comment(1),
+                Run::Text(RunStyle::default(), " & hid.".to_string()),
+            ]
+        );
+        let comments = document.comments_xml("Ann").unwrap();
+        assert!(comments.contains("<w:comment w:id=\"1\" w:author=\"Ann\">"));
+        assert!(comments.contains(">too fast?</w:t>"));
+        assert!(document
+            .document_xml(&Section::default())
+            .contains(" &amp; hid."));
+
+        let conversion = Conversion {
+            preserve_formatting: false,
+            include_comments: false,
+            ..CONVERSION
+        };
+        let mut document = Document::default();
+        document.push_markdown(
+            &format!("# The Inn\n\n{}*!*", markdown),
+            &conversion,
+            Some("Title"),
+        );
+        assert_eq!(document.paragraphs[0].style, "Title");
+        assert_eq!(
+            document.paragraphs[1].runs,
+            vec![
+                Run::Text(RunStyle::default(), "She ran & hid.".to_string()),
+                Run::Text(RunStyle::default(), "!".to_string()),
+            ]
+        );
+        assert!(document.comments_xml("Ann").is_none());
+    }
+
+    #[test]
+    fn test_direction_and_alignment() {
+        let markdown = "Hello.\n\n<!-- dir=rtl align=center -->\n# Title\n\nשלום.";
+        let mut document = Document::default();
+        document.push_markdown(markdown, &CONVERSION, None);
+        let formats: Vec<_> = document
+            .paragraphs
+            .iter()
+            .map(|p| (p.text(), p.direction, p.alignment))
+            .collect();
+        assert_eq!(
+            formats,
+            vec![
+                ("Hello.".to_string(), Direction::Ltr, None),
+                ("Title".to_string(), Direction::Rtl, Some(Alignment::Center)),
+                ("שלום.".to_string(), Direction::Rtl, None),
+            ]
+        );
+        assert!(document.comments.is_empty());
+
+        let xml = document.document_xml(&Section::default());
+        assert!(xml.contains("<w:pStyle w:val=\"Heading1\"/><w:bidi/><w:jc w:val=\"center\"/>"));
+    }
+}
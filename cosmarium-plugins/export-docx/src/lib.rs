@@ -0,0 +1,349 @@
+//! # Word export plugin for Cosmarium
+//!
+//! Compiles a manuscript to a Word document (`.docx`) following the Word
+//! export settings: template, formatting and comments.
+//!
+//! Markdown structure maps to Word styles rather than direct formatting:
+//! headings use `Heading1` to `Heading6`, block quotes `Quote`, epigraphs
+//! `Epigraph` and `EpigraphAttribution`, and scene breaks `SceneBreak`, so
+//! the document can be restyled by publishers. The title of the front
+//! matter uses `Title`, and every part and chapter
+//! starts on a new page. Manuscripts for submission can get a running
+//! header with the author's surname, the title and the page number.
+
+pub mod document;
+pub mod styles;
+
+use cosmarium_plugin_api::export::{ExportPlugin, Manuscript, SectionKind};
+use cosmarium_plugin_api::{Plugin, PluginContext, PluginInfo, PluginType, Result};
+use document::{
+    escape, header_xml, Conversion, Document, Section, HEADER_RELATIONSHIP, XML_DECLARATION,
+};
+use serde::Deserialize;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use styles::Template;
+use zip::write::FileOptions;
+
+/// Word export settings, as published by the application.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct WordOptions {
+    /// `default`, `manuscript`, or the path of a `.docx` or `.dotx` file
+    /// whose styles are used
+    pub template: String,
+    /// Whether to keep emphasis, strong emphasis and strikethrough
+    pub preserve_formatting: bool,
+    /// Whether to turn comments and annotations into Word comments
+    pub include_comments: bool,
+    /// Whether pages after the title page have a "Surname / Title / page"
+    /// header
+    pub running_header: bool,
+}
+
+impl Default for WordOptions {
+    fn default() -> Self {
+        Self {
+            template: "default".to_string(),
+            preserve_formatting: true,
+            include_comments: false,
+            running_header: false,
+        }
+    }
+}
+
+/// Convert a manuscript to the paragraphs and comments of a Word document.
+pub fn convert(manuscript: &Manuscript, options: &WordOptions) -> Document {
+    let template = Template::from_setting(&options.template);
+    let conversion = Conversion {
+        preserve_formatting: options.preserve_formatting,
+        include_comments: options.include_comments,
+        scene_break: template.scene_break(),
+    };
+
+    let mut document = Document::default();
+    if !manuscript.front_matter.trim().is_empty() {
+        document.push_markdown(&manuscript.front_matter, &conversion, Some("Title"));
+    }
+    for section in &manuscript.sections {
+        let start = document.paragraphs.len();
+        document.push_markdown(&section.separator, &conversion, None);
+        document.push_markdown(&section.markdown, &conversion, None);
+        if section.kind == SectionKind::Heading && start > 0 {
+            if let Some(first) = document.paragraphs.get_mut(start) {
+                first.page_break = true;
+            }
+        }
+    }
+    document
+}
+
+/// Write a manuscript as a Word document.
+pub fn to_docx(manuscript: &Manuscript, options: &WordOptions) -> anyhow::Result<Vec<u8>> {
+    let template = Template::from_setting(&options.template);
+    let styles = template.styles_xml()?;
+    let mut document = convert(manuscript, options);
+    let section = Section {
+        page_size: template.page_size(),
+        running_header: options.running_header,
+        title_page: !manuscript.front_matter.trim().is_empty(),
+    };
+    let body = document.document_xml(&section);
+    let comments = document.comments_xml(&manuscript.author);
+
+    let mut content_types = String::from(XML_DECLARATION);
+    content_types.push_str(
+        "<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+         <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+         <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+         <Override PartName=\"/word/document.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml\"/>\
+         <Override PartName=\"/word/styles.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml\"/>\
+         <Override PartName=\"/docProps/core.xml\" ContentType=\"application/vnd.openxmlformats-package.core-properties+xml\"/>",
+    );
+    let mut relationships = String::from(XML_DECLARATION);
+    relationships.push_str(
+        "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+         <Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles\" Target=\"styles.xml\"/>",
+    );
+    if comments.is_some() {
+        content_types.push_str(
+            "<Override PartName=\"/word/comments.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.comments+xml\"/>",
+        );
+        relationships.push_str(
+            "<Relationship Id=\"rId2\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/comments\" Target=\"comments.xml\"/>",
+        );
+    }
+    if options.running_header {
+        content_types.push_str(
+            "<Override PartName=\"/word/header1.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.header+xml\"/>",
+        );
+        relationships.push_str(&format!(
+            "<Relationship Id=\"{}\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/header\" Target=\"header1.xml\"/>",
+            HEADER_RELATIONSHIP
+        ));
+    }
+    content_types.push_str("</Types>");
+    relationships.push_str("</Relationships>");
+
+    let mut package_relationships = String::from(XML_DECLARATION);
+    package_relationships.push_str(
+        "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+         <Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"word/document.xml\"/>\
+         <Relationship Id=\"rId2\" Type=\"http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties\" Target=\"docProps/core.xml\"/>\
+         </Relationships>",
+    );
+    let mut core = String::from(XML_DECLARATION);
+    core.push_str(&format!(
+        "<cp:coreProperties xmlns:cp=\"http://schemas.openxmlformats.org/package/2006/metadata/core-properties\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\
+         <dc:title>{}</dc:title><dc:creator>{}</dc:creator></cp:coreProperties>",
+        escape(&manuscript.title),
+        escape(&manuscript.author)
+    ));
+
+    let mut parts = vec![
+        ("[Content_Types].xml", content_types),
+        ("_rels/.rels", package_relationships),
+        ("docProps/core.xml", core),
+        ("word/_rels/document.xml.rels", relationships),
+        ("word/document.xml", body),
+        ("word/styles.xml", styles),
+    ];
+    if let Some(comments) = comments {
+        parts.push(("word/comments.xml", comments));
+    }
+    if options.running_header {
+        parts.push(("word/header1.xml", header_xml(&manuscript.running_header())));
+    }
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, xml) in parts {
+        zip.start_file(name, FileOptions::default())?;
+        zip.write_all(xml.as_bytes())?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+#[derive(Default)]
+pub struct DocxExportPlugin;
+
+impl DocxExportPlugin {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Plugin for DocxExportPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            "export-docx",
+            "0.1.0",
+            "Word export of compiled manuscripts",
+            "Cosmarium Team",
+        )
+    }
+
+    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Export
+    }
+}
+
+impl ExportPlugin for DocxExportPlugin {
+    fn format_id(&self) -> &str {
+        "docx"
+    }
+
+    fn format_name(&self) -> &str {
+        "Word document"
+    }
+
+    fn file_extension(&self) -> &str {
+        "docx"
+    }
+
+    fn export(
+        &self,
+        manuscript: &Manuscript,
+        options: &serde_json::Value,
+        output: &Path,
+    ) -> Result<()> {
+        let options: WordOptions = if options.is_null() {
+            WordOptions::default()
+        } else {
+            serde_json::from_value(options.clone())?
+        };
+        std::fs::write(output, to_docx(manuscript, &options)?)?;
+        tracing::info!("Wrote Word document to {:?}", output);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmarium_plugin_api::export::ManuscriptSection;
+    use std::io::Read;
+
+    fn section(kind: SectionKind, markdown: &str) -> ManuscriptSection {
+        ManuscriptSection {
+            kind,
+            title: String::new(),
+            depth: 0,
+            path: None,
+            separator: String::new(),
+            markdown: markdown.to_string(),
+        }
+    }
+
+    fn manuscript() -> Manuscript {
+        Manuscript {
+            title: "The Inn".to_string(),
+            author: "Ann Author".to_string(),
+            language: "en".to_string(),
+            front_matter: "# The Inn\n\nby Ann Author".to_string(),
+            sections: vec![
+                section(SectionKind::Heading, "# Arrival"),
+                section(SectionKind::Document, "She *ran*.<!-- too fast? -->"),
+                section(SectionKind::Heading, "# Departure"),
+                ManuscriptSection {
+                    separator: "***".to_string(),
+                    ..section(SectionKind::Document, "> Dawn.")
+                },
+            ],
+        }
+    }
+
+    fn part(docx: &[u8], name: &str) -> Option<String> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(docx)).unwrap();
+        let mut file = archive.by_name(name).ok()?;
+        let mut xml = String::new();
+        file.read_to_string(&mut xml).unwrap();
+        Some(xml)
+    }
+
+    #[test]
+    fn test_chapters_start_pages() {
+        let document = convert(&manuscript(), &WordOptions::default());
+        let paragraphs: Vec<_> = document
+            .paragraphs
+            .iter()
+            .map(|p| (p.style, p.page_break, p.text()))
+            .collect();
+        assert_eq!(
+            paragraphs,
+            vec![
+                ("Title", false, "The Inn".to_string()),
+                ("Normal", false, "by Ann Author".to_string()),
+                ("Heading1", true, "Arrival".to_string()),
+                ("Normal", false, "She ran.".to_string()),
+                ("Heading1", true, "Departure".to_string()),
+                ("SceneBreak", false, "*\u{2003}*\u{2003}*".to_string()),
+                ("Quote", false, "Dawn.".to_string()),
+            ]
+        );
+        assert!(document.comments.is_empty());
+    }
+
+    #[test]
+    fn test_export_writes_docx() {
+        let dir = std::env::temp_dir().join(format!("cosmarium_docx_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("the_inn.docx");
+        let options = serde_json::json!({
+            "template": "manuscript",
+            "preserve_formatting": true,
+            "include_comments": true,
+            "track_changes": false,
+            "running_header": true
+        });
+
+        DocxExportPlugin::new()
+            .export(&manuscript(), &options, &output)
+            .unwrap();
+        let docx = std::fs::read(&output).unwrap();
+        let body = part(&docx, "word/document.xml").unwrap();
+        assert!(body.contains("<w:pStyle w:val=\"Heading1\"/><w:pageBreakBefore/>"));
+        assert!(body.contains("<w:t xml:space=\"preserve\">#</w:t>"));
+        assert!(body.contains("<w:commentReference w:id=\"0\"/>"));
+        assert!(body.contains("<w:pgSz w:w=\"12240\" w:h=\"15840\"/>"));
+        assert!(body.contains("<w:titlePg/>"));
+        assert!(part(&docx, "word/header1.xml")
+            .unwrap()
+            .contains("Author / The Inn / </w:t>"));
+        assert!(part(&docx, "word/comments.xml")
+            .unwrap()
+            .contains("w:author=\"Ann Author\""));
+        assert!(part(&docx, "word/styles.xml")
+            .unwrap()
+            .contains("Courier New"));
+        assert!(part(&docx, "docProps/core.xml")
+            .unwrap()
+            .contains("<dc:title>The Inn</dc:title>"));
+
+        // The styles of a template file replace the built-in ones
+        let template = dir.join("house.docx");
+        std::fs::write(&template, &docx).unwrap();
+        let options = WordOptions {
+            template: template.to_string_lossy().to_string(),
+            ..WordOptions::default()
+        };
+        let styled = to_docx(&manuscript(), &options).unwrap();
+        assert!(part(&styled, "word/styles.xml")
+            .unwrap()
+            .contains("Courier New"));
+        assert!(part(&styled, "word/comments.xml").is_none());
+        assert!(part(&styled, "word/header1.xml").is_none());
+
+        let missing = WordOptions {
+            template: dir.join("missing.dotx").to_string_lossy().to_string(),
+            ..WordOptions::default()
+        };
+        assert!(to_docx(&manuscript(), &missing).is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,228 @@
+//! Word styles of exported documents.
+//!
+//! The built-in templates define every style the converter uses (see
+//! [`crate::document`]); a template file (`.docx` or `.dotx`) brings its own
+//! style definitions, which Word matches to the paragraphs by identifier
+//! (`Heading1`, `Quote`...).
+
+use crate::document::{A4, LETTER, XML_DECLARATION};
+use anyhow::Context;
+use std::fmt::Write as _;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Styles of an exported document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Template {
+    /// Book typography: serif font, single spacing, space between paragraphs
+    Default,
+    /// Submission format: Courier 12pt, double spacing, indented paragraphs
+    /// and `#` scene breaks
+    Manuscript,
+    /// Styles of a Word document or template file
+    File(PathBuf),
+}
+
+impl Template {
+    /// Template named in the settings: `default`, `manuscript`, or the path
+    /// of a Word file.
+    pub fn from_setting(setting: &str) -> Self {
+        match setting.trim().to_lowercase().as_str() {
+            "" | "default" => Self::Default,
+            "manuscript" | "standard manuscript" => Self::Manuscript,
+            _ => Self::File(PathBuf::from(setting.trim())),
+        }
+    }
+
+    /// Text of scene break paragraphs.
+    pub fn scene_break(&self) -> &'static str {
+        match self {
+            Self::Manuscript => "#",
+            _ => "*\u{2003}*\u{2003}*",
+        }
+    }
+
+    /// Page width and height, in twentieths of a point: US Letter for
+    /// submissions, A4 otherwise.
+    pub fn page_size(&self) -> (usize, usize) {
+        match self {
+            Self::Manuscript => LETTER,
+            _ => A4,
+        }
+    }
+
+    /// The `w:styles` part.
+    pub fn styles_xml(&self) -> anyhow::Result<String> {
+        match self {
+            Self::Default => Ok(builtin_styles(&Typography {
+                font: "Times New Roman",
+                size: 24,
+                line: 276,
+                after: 160,
+                first_line: 0,
+            })),
+            Self::Manuscript => Ok(builtin_styles(&Typography {
+                font: "Courier New",
+                size: 24,
+                line: 480,
+                after: 0,
+                first_line: 720,
+            })),
+            Self::File(path) => template_styles(path),
+        }
+    }
+}
+
+/// Body text settings of a built-in template.
+struct Typography {
+    font: &'static str,
+    /// In half-points
+    size: usize,
+    /// Line spacing, in 240ths of a line
+    line: usize,
+    /// Space after paragraphs, in twentieths of a point
+    after: usize,
+    /// First line indent, in twentieths of a point
+    first_line: usize,
+}
+
+fn builtin_styles(t: &Typography) -> String {
+    let mut xml = String::from(XML_DECLARATION);
+    xml.push_str(
+        "<w:styles xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">",
+    );
+    let _ = write!(
+        xml,
+        "<w:docDefaults><w:rPrDefault><w:rPr>\
+         <w:rFonts w:ascii=\"{font}\" w:hAnsi=\"{font}\" w:eastAsia=\"{font}\" w:cs=\"{font}\"/>\
+         <w:sz w:val=\"{size}\"/><w:szCs w:val=\"{size}\"/></w:rPr></w:rPrDefault>\
+         <w:pPrDefault><w:pPr><w:spacing w:after=\"{after}\" w:line=\"{line}\" w:lineRule=\"auto\"/>\
+         </w:pPr></w:pPrDefault></w:docDefaults>",
+        font = t.font,
+        size = t.size,
+        after = t.after,
+        line = t.line,
+    );
+
+    let _ = write!(
+        xml,
+        "<w:style w:type=\"paragraph\" w:default=\"1\" w:styleId=\"Normal\">\
+         <w:name w:val=\"Normal\"/><w:qFormat/>\
+         <w:pPr><w:ind w:firstLine=\"{}\"/></w:pPr></w:style>",
+        t.first_line
+    );
+    paragraph_style(
+        &mut xml,
+        "Title",
+        "Title",
+        "<w:spacing w:before=\"2400\" w:after=\"480\"/><w:jc w:val=\"center\"/><w:ind w:firstLine=\"0\"/>",
+        &format!("<w:b/><w:sz w:val=\"{0}\"/><w:szCs w:val=\"{0}\"/>", t.size * 2),
+    );
+    for level in 1..=6 {
+        let size = match level {
+            1 => t.size * 3 / 2,
+            2 => t.size * 4 / 3,
+            _ => t.size,
+        };
+        let jc = if level == 1 {
+            "<w:jc w:val=\"center\"/>"
+        } else {
+            ""
+        };
+        paragraph_style(
+            &mut xml,
+            &format!("Heading{}", level),
+            &format!("heading {}", level),
+            &format!(
+                "<w:keepNext/><w:spacing w:before=\"480\" w:after=\"240\"/>{}\
+                 <w:ind w:firstLine=\"0\"/><w:outlineLvl w:val=\"{}\"/>",
+                jc,
+                level - 1
+            ),
+            &format!(
+                "<w:b/>{}<w:sz w:val=\"{size}\"/><w:szCs w:val=\"{size}\"/>",
+                if level > 3 { "<w:i/>" } else { "" },
+            ),
+        );
+    }
+    paragraph_style(
+        &mut xml,
+        "Quote",
+        "Quote",
+        "<w:ind w:left=\"720\" w:right=\"720\" w:firstLine=\"0\"/>",
+        "<w:i/>",
+    );
+    paragraph_style(
+        &mut xml,
+        "Epigraph",
+        "Epigraph",
+        "<w:spacing w:after=\"120\"/><w:ind w:left=\"3600\" w:firstLine=\"0\"/>",
+        "<w:i/>",
+    );
+    paragraph_style(
+        &mut xml,
+        "EpigraphAttribution",
+        "Epigraph Attribution",
+        "<w:spacing w:after=\"480\"/><w:ind w:left=\"3600\" w:firstLine=\"0\"/><w:jc w:val=\"right\"/>",
+        "",
+    );
+    paragraph_style(
+        &mut xml,
+        "ListParagraph",
+        "List Paragraph",
+        "<w:ind w:left=\"720\" w:hanging=\"360\"/>",
+        "",
+    );
+    paragraph_style(
+        &mut xml,
+        "SourceCode",
+        "Source Code",
+        "<w:spacing w:line=\"240\" w:lineRule=\"auto\"/><w:ind w:firstLine=\"0\"/>",
+        "<w:rFonts w:ascii=\"Courier New\" w:hAnsi=\"Courier New\"/>",
+    );
+    paragraph_style(
+        &mut xml,
+        "SceneBreak",
+        "Scene Break",
+        "<w:spacing w:before=\"240\" w:after=\"240\"/><w:jc w:val=\"center\"/><w:ind w:firstLine=\"0\"/>",
+        "",
+    );
+    paragraph_style(
+        &mut xml,
+        "CommentText",
+        "annotation text",
+        "<w:spacing w:after=\"0\" w:line=\"240\" w:lineRule=\"auto\"/><w:ind w:firstLine=\"0\"/>",
+        "<w:sz w:val=\"20\"/><w:szCs w:val=\"20\"/>",
+    );
+    xml.push_str(
+        "<w:style w:type=\"character\" w:styleId=\"VerbatimChar\"><w:name w:val=\"Verbatim Char\"/>\
+         <w:rPr><w:rFonts w:ascii=\"Courier New\" w:hAnsi=\"Courier New\"/></w:rPr></w:style>\
+         <w:style w:type=\"character\" w:styleId=\"CommentReference\">\
+         <w:name w:val=\"annotation reference\"/><w:rPr><w:sz w:val=\"16\"/></w:rPr></w:style>",
+    );
+    xml.push_str("</w:styles>");
+    xml
+}
+
+fn paragraph_style(xml: &mut String, id: &str, name: &str, paragraph: &str, run: &str) {
+    let _ = write!(
+        xml,
+        "<w:style w:type=\"paragraph\" w:styleId=\"{id}\"><w:name w:val=\"{name}\"/>\
+         <w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>\
+         <w:pPr>{paragraph}</w:pPr><w:rPr>{run}</w:rPr></w:style>"
+    );
+}
+
+/// Style definitions of a Word document or template file.
+fn template_styles(path: &Path) -> anyhow::Result<String> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Cannot open Word template {:?}", path))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .with_context(|| format!("{:?} is not a Word document or template", path))?;
+    let mut styles = archive
+        .by_name("word/styles.xml")
+        .with_context(|| format!("Word template {:?} has no styles", path))?;
+    let mut xml = String::new();
+    styles.read_to_string(&mut xml)?;
+    Ok(xml)
+}
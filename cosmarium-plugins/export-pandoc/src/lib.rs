@@ -0,0 +1,472 @@
+//! # Pandoc export backend for Cosmarium
+//!
+//! Writes compiled manuscripts in the output formats of
+//! [pandoc](https://pandoc.org) when it is installed: OpenDocument, LaTeX,
+//! EPUB and Word.
+//!
+//! [`Pandoc::detect`] runs the executable once to learn its version and
+//! output formats, and [`Pandoc::plugins`] gives an export plugin for each
+//! format it can write. A plugin can fall back on a native exporter of the
+//! same format, used when pandoc fails or is no longer installed.
+//!
+//! Pandoc reads the manuscript as Markdown. Its title and author are passed
+//! as document properties only, so that the compiled title page is not
+//! repeated, except in EPUB books where they are required. Right-to-left
+//! and aligned paragraphs are handed over as `<div>` elements, which pandoc
+//! reads as blocks with a `dir` attribute. Verse is handed over to EPUB as
+//! lines laid out in HTML, with their hanging indents, and to the other
+//! formats as lines ended by hard line breaks. Epigraphs are handed over as
+//! fenced divs of class `epigraph`, with custom styles for Word.
+//!
+//! The language of the manuscript is passed to every format. EPUB books
+//! also declare how accessible they are, with the schema.org metadata of
+//! [`AccessibilityMetadata`], and images keep their alternative text.
+
+use anyhow::{bail, Context};
+use cosmarium_plugin_api::accessibility::AccessibilityMetadata;
+use cosmarium_plugin_api::export::{ExportPlugin, Manuscript};
+use cosmarium_plugin_api::{direction, epigraph, verse};
+use cosmarium_plugin_api::{Plugin, PluginContext, PluginInfo, PluginType, Result};
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+/// An installed pandoc executable and what it can write.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pandoc {
+    executable: PathBuf,
+    version: String,
+    output_formats: Vec<String>,
+}
+
+impl Pandoc {
+    /// Find out whether `executable` (a path, or a name looked up in the
+    /// `PATH`) runs pandoc, and which formats it writes.
+    pub fn detect(executable: impl Into<PathBuf>) -> Option<Self> {
+        let executable = executable.into();
+        let run = |argument: &str| -> Option<String> {
+            let output = Command::new(&executable)
+                .arg(argument)
+                .stdin(Stdio::null())
+                .output()
+                .ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+        };
+
+        let version = run("--version")?;
+        let version = version
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("pandoc"))
+            .map(|rest| rest.trim().to_string())?;
+        let output_formats = run("--list-output-formats")?
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        tracing::info!("Found pandoc {} at {:?}", version, executable);
+        Some(Self {
+            executable,
+            version,
+            output_formats,
+        })
+    }
+
+    /// Version of pandoc, such as `3.1.3`.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Whether pandoc can write `format`.
+    pub fn supports(&self, format: PandocFormat) -> bool {
+        self.output_formats.iter().any(|f| f == format.writer())
+    }
+
+    /// An export plugin for each format pandoc can write.
+    pub fn plugins(&self) -> Vec<PandocExportPlugin> {
+        PandocFormat::ALL
+            .into_iter()
+            .filter(|format| self.supports(*format))
+            .map(|format| PandocExportPlugin::new(self.clone(), format))
+            .collect()
+    }
+}
+
+/// Output formats offered through pandoc.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PandocFormat {
+    Odt,
+    Latex,
+    Epub,
+    Docx,
+}
+
+impl PandocFormat {
+    /// All formats, in menu order.
+    pub const ALL: [PandocFormat; 4] = [Self::Odt, Self::Latex, Self::Epub, Self::Docx];
+
+    /// Format identifier, shared with the native exporter of the format.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Odt => "odt",
+            Self::Latex => "latex",
+            Self::Epub => "epub",
+            Self::Docx => "docx",
+        }
+    }
+
+    /// Name of the pandoc writer.
+    fn writer(&self) -> &'static str {
+        match self {
+            Self::Odt => "odt",
+            Self::Latex => "latex",
+            Self::Epub => "epub3",
+            Self::Docx => "docx",
+        }
+    }
+
+    /// Human-readable name for menus.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Odt => "OpenDocument text (Pandoc)",
+            Self::Latex => "LaTeX (Pandoc)",
+            Self::Epub => "EPUB e-book (Pandoc)",
+            Self::Docx => "Word document (Pandoc)",
+        }
+    }
+
+    /// Extension of the files written, without the dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Odt => "odt",
+            Self::Latex => "tex",
+            Self::Epub => "epub",
+            Self::Docx => "docx",
+        }
+    }
+}
+
+/// Export plugin writing one format through pandoc.
+pub struct PandocExportPlugin {
+    pandoc: Pandoc,
+    format: PandocFormat,
+    /// Native exporter of the same format, used when pandoc fails
+    fallback: Option<Arc<dyn ExportPlugin>>,
+}
+
+impl PandocExportPlugin {
+    pub fn new(pandoc: Pandoc, format: PandocFormat) -> Self {
+        Self {
+            pandoc,
+            format,
+            fallback: None,
+        }
+    }
+
+    /// Fall back on `fallback` when pandoc fails.
+    pub fn with_fallback(mut self, fallback: Arc<dyn ExportPlugin>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Command line arguments writing `manuscript`, read from the standard
+    /// input, to `output`.
+    ///
+    /// Word exports use the styles of the template of the Word settings,
+    /// when it is a `.docx` file.
+    pub fn arguments(
+        &self,
+        manuscript: &Manuscript,
+        options: &serde_json::Value,
+        output: &Path,
+    ) -> Vec<OsString> {
+        let mut arguments: Vec<OsString> = vec![
+            "--from".into(),
+            "markdown".into(),
+            "--to".into(),
+            self.format.writer().into(),
+            "--standalone".into(),
+            "--output".into(),
+            output.into(),
+        ];
+        let (title, author) = match self.format {
+            PandocFormat::Epub => ("title", "author"),
+            _ => ("title-meta", "author-meta"),
+        };
+        let mut metadata = |key: &str, value: &str| {
+            if !value.trim().is_empty() {
+                arguments.push("--metadata".into());
+                arguments.push(format!("{}={}", key, value.trim()).into());
+            }
+        };
+        metadata(title, &manuscript.title);
+        metadata(author, &manuscript.author);
+        metadata("lang", &manuscript.language);
+
+        match self.format {
+            PandocFormat::Epub => arguments.push("--toc".into()),
+            PandocFormat::Docx => {
+                let template = options["template"].as_str().map(Path::new);
+                if let Some(template) = template.filter(|path| {
+                    path.extension()
+                        .is_some_and(|e| e.eq_ignore_ascii_case("docx"))
+                }) {
+                    arguments.push("--reference-doc".into());
+                    arguments.push(template.into());
+                }
+            }
+            _ => {}
+        }
+        arguments
+    }
+
+    /// Markdown of `manuscript` as handed over to pandoc.
+    fn input(&self, manuscript: &Manuscript) -> String {
+        let markdown = epigraph::fence_epigraphs(&manuscript.to_markdown());
+        let markdown = match self.format {
+            PandocFormat::Epub => verse::wrap_verse(&markdown),
+            _ => verse::break_lines(&markdown),
+        };
+        let markdown = direction::wrap_paragraphs(&markdown);
+        match self.format {
+            PandocFormat::Epub => {
+                AccessibilityMetadata::of(manuscript).to_pandoc_yaml() + &markdown
+            }
+            _ => markdown,
+        }
+    }
+
+    fn run(
+        &self,
+        manuscript: &Manuscript,
+        options: &serde_json::Value,
+        output: &Path,
+    ) -> anyhow::Result<()> {
+        let mut child = Command::new(&self.pandoc.executable)
+            .args(self.arguments(manuscript, options, output))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Cannot run pandoc at {:?}", self.pandoc.executable))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(self.input(manuscript).as_bytes())?;
+        }
+        let result = child.wait_with_output()?;
+        if !result.status.success() {
+            bail!(
+                "pandoc failed ({}): {}",
+                result.status,
+                String::from_utf8_lossy(&result.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Plugin for PandocExportPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            &format!("export-pandoc-{}", self.format.id()),
+            "0.1.0",
+            "Export of compiled manuscripts through pandoc",
+            "Cosmarium Team",
+        )
+    }
+
+    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Export
+    }
+}
+
+impl ExportPlugin for PandocExportPlugin {
+    fn format_id(&self) -> &str {
+        self.format.id()
+    }
+
+    fn format_name(&self) -> &str {
+        self.format.display_name()
+    }
+
+    fn file_extension(&self) -> &str {
+        self.format.extension()
+    }
+
+    fn export(
+        &self,
+        manuscript: &Manuscript,
+        options: &serde_json::Value,
+        output: &Path,
+    ) -> Result<()> {
+        match self.run(manuscript, options, output) {
+            Ok(()) => {
+                tracing::info!("pandoc wrote {:?}", output);
+                Ok(())
+            }
+            Err(e) => match &self.fallback {
+                Some(fallback) => {
+                    tracing::warn!("{}; using the {} exporter", e, fallback.format_name());
+                    fallback.export(manuscript, options, output)
+                }
+                None => Err(e),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pandoc(executable: &str) -> Pandoc {
+        Pandoc {
+            executable: PathBuf::from(executable),
+            version: "3.1".to_string(),
+            output_formats: vec!["docx".to_string(), "epub3".to_string()],
+        }
+    }
+
+    fn manuscript() -> Manuscript {
+        Manuscript {
+            title: "The Inn".to_string(),
+            author: "Ann Author".to_string(),
+            language: "en".to_string(),
+            ..Manuscript::default()
+        }
+    }
+
+    struct Native;
+
+    impl ExportPlugin for Native {
+        fn format_id(&self) -> &str {
+            "docx"
+        }
+
+        fn format_name(&self) -> &str {
+            "Word document"
+        }
+
+        fn file_extension(&self) -> &str {
+            "docx"
+        }
+
+        fn export(&self, _: &Manuscript, _: &serde_json::Value, output: &Path) -> Result<()> {
+            std::fs::write(output, "native")?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_formats_and_arguments() {
+        assert!(Pandoc::detect("cosmarium-no-such-pandoc").is_none());
+
+        let pandoc = pandoc("pandoc");
+        let formats: Vec<_> = pandoc.plugins().iter().map(|p| p.format).collect();
+        assert_eq!(formats, vec![PandocFormat::Epub, PandocFormat::Docx]);
+
+        let epub = PandocExportPlugin::new(pandoc.clone(), PandocFormat::Epub);
+        let arguments = epub.arguments(
+            &manuscript(),
+            &serde_json::Value::Null,
+            Path::new("inn.epub"),
+        );
+        let arguments: Vec<_> = arguments.iter().map(|a| a.to_string_lossy()).collect();
+        assert_eq!(
+            arguments.join(" "),
+            "--from markdown --to epub3 --standalone --output inn.epub \
+             --metadata title=The Inn --metadata author=Ann Author --metadata lang=en --toc"
+        );
+
+        let docx = PandocExportPlugin::new(pandoc, PandocFormat::Docx);
+        let options = serde_json::json!({ "template": "house.docx" });
+        let arguments = docx.arguments(&manuscript(), &options, Path::new("inn.docx"));
+        let arguments: Vec<_> = arguments.iter().map(|a| a.to_string_lossy()).collect();
+        assert!(arguments.contains(&"title-meta=The Inn".into()));
+        assert!(arguments.ends_with(&["--reference-doc".into(), "house.docx".into()]));
+    }
+
+    #[test]
+    fn test_verse_is_laid_out_for_the_format() {
+        use cosmarium_plugin_api::export::{ManuscriptSection, SectionKind};
+
+        let mut manuscript = manuscript();
+        manuscript.sections.push(ManuscriptSection {
+            kind: SectionKind::Document,
+            title: "Song".to_string(),
+            depth: 0,
+            path: None,
+            separator: String::new(),
+            markdown: "<!-- verse -->\nLa la,\n  la.".to_string(),
+        });
+
+        let epub = PandocExportPlugin::new(pandoc("pandoc"), PandocFormat::Epub);
+        assert!(epub.input(&manuscript).contains("<div class=\"verse\">"));
+        let docx = PandocExportPlugin::new(pandoc("pandoc"), PandocFormat::Docx);
+        assert!(docx
+            .input(&manuscript)
+            .contains("La la,\\\n\u{a0}\u{a0}la.\n"));
+    }
+
+    #[test]
+    fn test_epub_declares_its_accessibility() {
+        let epub = PandocExportPlugin::new(pandoc("pandoc"), PandocFormat::Epub);
+        let input = epub.input(&manuscript());
+        assert!(input.starts_with("---\naccessModes:\n- textual\n"));
+        assert!(input.contains("accessibilityHazards:\n- none\n"));
+
+        let docx = PandocExportPlugin::new(pandoc("pandoc"), PandocFormat::Docx);
+        assert!(!docx.input(&manuscript()).contains("accessModes"));
+    }
+
+    #[test]
+    fn test_epigraphs_are_fenced() {
+        use cosmarium_plugin_api::export::{ManuscriptSection, SectionKind};
+
+        let mut manuscript = manuscript();
+        manuscript.sections.push(ManuscriptSection {
+            kind: SectionKind::Document,
+            title: "One".to_string(),
+            depth: 0,
+            path: None,
+            separator: String::new(),
+            markdown: "<!-- epigraph -->\n> Go.\n<!-- /epigraph -->\n\nText.".to_string(),
+        });
+
+        let odt = PandocExportPlugin::new(pandoc("pandoc"), PandocFormat::Odt);
+        let input = odt.input(&manuscript);
+        assert!(input.contains("::::: {.epigraph custom-style=\"Epigraph\"}\nGo.\n:::::\n\nText."));
+        assert!(!input.contains("<!--"));
+    }
+
+    #[test]
+    fn test_falls_back_on_native_exporter() {
+        let dir =
+            std::env::temp_dir().join(format!("cosmarium_pandoc_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("inn.docx");
+
+        let plugin =
+            PandocExportPlugin::new(pandoc("cosmarium-no-such-pandoc"), PandocFormat::Docx);
+        assert!(plugin
+            .export(&manuscript(), &serde_json::Value::Null, &output)
+            .is_err());
+
+        let plugin = plugin.with_fallback(Arc::new(Native));
+        plugin
+            .export(&manuscript(), &serde_json::Value::Null, &output)
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "native");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
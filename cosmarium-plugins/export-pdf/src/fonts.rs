@@ -0,0 +1,192 @@
+//! Fonts of PDF exports.
+//!
+//! Exports use the standard Type 1 fonts every PDF reader provides, so no
+//! font file has to be embedded. The configured font family picks the
+//! closest of them: Times for serif families, Helvetica for sans-serif ones
+//! and Courier for monospaced ones. Text is encoded in WinAnsi, which
+//! covers Latin-1 and typographic punctuation.
+
+/// Family of standard fonts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Family {
+    Times,
+    Helvetica,
+    Courier,
+}
+
+impl Family {
+    /// Closest standard family to a configured font family name.
+    pub fn from_name(name: &str) -> Self {
+        let name = name.to_lowercase();
+        if ["mono", "courier", "consol", "typewriter"]
+            .iter()
+            .any(|hint| name.contains(hint))
+        {
+            Self::Courier
+        } else if ["sans", "helvetica", "arial", "verdana", "inter", "roboto"]
+            .iter()
+            .any(|hint| name.contains(hint))
+        {
+            Self::Helvetica
+        } else {
+            Self::Times
+        }
+    }
+}
+
+/// Weight and slant of a run of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// A standard font: a family in a style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Font {
+    pub family: Family,
+    pub style: Style,
+}
+
+impl Font {
+    pub fn new(family: Family, style: Style) -> Self {
+        Self { family, style }
+    }
+
+    /// PostScript name of the font.
+    pub fn base_name(&self) -> &'static str {
+        match (self.family, self.style.bold, self.style.italic) {
+            (Family::Times, false, false) => "Times-Roman",
+            (Family::Times, true, false) => "Times-Bold",
+            (Family::Times, false, true) => "Times-Italic",
+            (Family::Times, true, true) => "Times-BoldItalic",
+            (Family::Helvetica, false, false) => "Helvetica",
+            (Family::Helvetica, true, false) => "Helvetica-Bold",
+            (Family::Helvetica, false, true) => "Helvetica-Oblique",
+            (Family::Helvetica, true, true) => "Helvetica-BoldOblique",
+            (Family::Courier, false, false) => "Courier",
+            (Family::Courier, true, false) => "Courier-Bold",
+            (Family::Courier, false, true) => "Courier-Oblique",
+            (Family::Courier, true, true) => "Courier-BoldOblique",
+        }
+    }
+
+    /// Width of `text` at `size` points.
+    ///
+    /// Widths come from the regular weight of the family; bold text is
+    /// widened slightly to stay on the safe side when lines are wrapped.
+    pub fn text_width(&self, text: &str, size: f32) -> f32 {
+        let units: u32 = text.chars().map(|c| self.char_width(c)).sum();
+        let width = units as f32 * size / 1000.0;
+        if self.style.bold {
+            width * 1.08
+        } else {
+            width
+        }
+    }
+
+    /// Width of a character in thousandths of the font size.
+    fn char_width(&self, c: char) -> u32 {
+        if self.family == Family::Courier {
+            return 600;
+        }
+        let table = match self.family {
+            Family::Helvetica => &HELVETICA_WIDTHS,
+            _ => &TIMES_WIDTHS,
+        };
+        match c {
+            ' '..='~' => table[c as usize - 32] as u32,
+            '\u{2013}' => 500,
+            '\u{2014}' | '\u{2026}' => 1000,
+            '\u{2018}' | '\u{2019}' | '\u{201A}' => 333,
+            '\u{201C}' | '\u{201D}' | '\u{201E}' => 444,
+            // Latin-1 letters are about as wide as a lowercase letter
+            _ => table['n' as usize - 32] as u32,
+        }
+    }
+}
+
+/// Widths of Times-Roman for the characters from ' ' to '~'.
+const TIMES_WIDTHS: [u16; 95] = [
+    250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250, 278, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564, 564, 444, 921, 722, 667, 667, 722, 611,
+    556, 722, 722, 333, 389, 722, 611, 889, 722, 722, 556, 722, 667, 556, 611, 722, 722, 944, 722,
+    722, 611, 333, 278, 333, 469, 500, 333, 444, 500, 444, 500, 444, 333, 500, 500, 278, 278, 500,
+    278, 778, 500, 500, 500, 500, 333, 389, 278, 500, 500, 722, 500, 500, 444, 480, 200, 480, 541,
+];
+
+/// Widths of Helvetica for the characters from ' ' to '~'.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// WinAnsi byte of a character, `?` for characters outside the encoding.
+pub fn win_ansi(c: char) -> u8 {
+    match c {
+        ' '..='~' | '\u{A0}'..='\u{FF}' => c as u8,
+        '\u{20AC}' => 0x80,
+        '\u{201A}' => 0x82,
+        '\u{0192}' => 0x83,
+        '\u{201E}' => 0x84,
+        '\u{2026}' => 0x85,
+        '\u{2020}' => 0x86,
+        '\u{2021}' => 0x87,
+        '\u{02C6}' => 0x88,
+        '\u{2030}' => 0x89,
+        '\u{0160}' => 0x8A,
+        '\u{2039}' => 0x8B,
+        '\u{0152}' => 0x8C,
+        '\u{017D}' => 0x8E,
+        '\u{2018}' => 0x91,
+        '\u{2019}' => 0x92,
+        '\u{201C}' => 0x93,
+        '\u{201D}' => 0x94,
+        '\u{2022}' => 0x95,
+        '\u{2013}' => 0x96,
+        '\u{2014}' => 0x97,
+        '\u{02DC}' => 0x98,
+        '\u{2122}' => 0x99,
+        '\u{0161}' => 0x9A,
+        '\u{203A}' => 0x9B,
+        '\u{0153}' => 0x9C,
+        '\u{017E}' => 0x9E,
+        '\u{0178}' => 0x9F,
+        '\t' => b' ',
+        _ => b'?',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_family_and_widths() {
+        assert_eq!(Family::from_name("Liberation Serif"), Family::Times);
+        assert_eq!(Family::from_name("Liberation Sans"), Family::Helvetica);
+        assert_eq!(Family::from_name("DejaVu Sans Mono"), Family::Courier);
+
+        let times = Font::new(Family::Times, Style::default());
+        assert_eq!(times.text_width("Hi", 10.0), 10.0);
+        assert_eq!(times.base_name(), "Times-Roman");
+        let courier = Font::new(
+            Family::Courier,
+            Style {
+                bold: true,
+                italic: true,
+            },
+        );
+        assert_eq!(courier.base_name(), "Courier-BoldOblique");
+        assert!(courier.text_width("ab", 10.0) > 12.0);
+    }
+
+    #[test]
+    fn test_win_ansi() {
+        let bytes: Vec<u8> = "é—“€”✓".chars().map(win_ansi).collect();
+        assert_eq!(bytes, vec![0xE9, 0x97, 0x93, 0x80, 0x94, b'?']);
+    }
+}
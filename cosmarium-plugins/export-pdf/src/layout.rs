@@ -0,0 +1,1061 @@
+//! Page layout of PDF exports.
+//!
+//! The manuscript's Markdown is broken into blocks (headings, paragraphs,
+//! list items, quotes, code and scene breaks), then into lines that fill
+//! the pages between the margins. Text is left aligned; prose paragraphs
+//! get a first-line indent, except after a heading or a break.
+//!
+//! Right-to-left paragraphs are right aligned, and direction and alignment
+//! markers (see [`cosmarium_plugin_api::direction`]) center or align
+//! paragraphs to either side. Justified paragraphs are set ragged.
+//!
+//! Verse (see [`cosmarium_plugin_api::verse`]) is set line by line, each
+//! indented as written, the lines too long for the page wrapping under a
+//! hanging indent.
+//!
+//! Epigraphs (see [`cosmarium_plugin_api::epigraph`]) are set in italics,
+//! far in from the left margin, their attribution against the right one.
+//!
+//! Every text item has the [`Role`] of its block in the structure of the
+//! document, which the writer turns into the tags of a tagged PDF. Images
+//! cannot be drawn: their alternative text is set in their place.
+
+use crate::fonts::{Family, Font, Style};
+use cosmarium_plugin_api::direction::{Alignment, Direction, ParagraphFormat};
+use cosmarium_plugin_api::epigraph::{self, ATTRIBUTION_DASH};
+use cosmarium_plugin_api::verse::{self, Verse, HANGING_INDENT};
+use pulldown_cmark::{Event, Options, Parser, Tag};
+
+/// Default line height, as a multiple of the font size.
+pub const LINE_SPACING: f32 = 1.4;
+
+/// Default text of scene breaks.
+pub const BREAK_MARK: &str = "*     *     *";
+
+/// Indent of verse from the left margin, in multiples of the font size.
+const VERSE_INDENT: f32 = 2.0;
+
+/// Indent of epigraphs from the left margin, in multiples of the font size.
+const EPIGRAPH_INDENT: usize = 12;
+
+/// Page size and margins, in points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageSetup {
+    pub width: f32,
+    pub height: f32,
+    pub margin_top: f32,
+    pub margin_bottom: f32,
+    pub margin_left: f32,
+    pub margin_right: f32,
+}
+
+impl PageSetup {
+    /// Width of the text between the side margins.
+    pub fn text_width(&self) -> f32 {
+        (self.width - self.margin_left - self.margin_right).max(72.0)
+    }
+}
+
+/// What a block of text is in the structure of the document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Role {
+    #[default]
+    Paragraph,
+    /// Heading of a level from 1
+    Heading(usize),
+    /// Item of a bulleted or numbered list
+    ListItem,
+    Code,
+    /// Paragraph of images, with their alternative text (empty if they have
+    /// none)
+    Figure(String),
+    /// Page numbers, headers and scene breaks, left out of the structure
+    Artifact,
+}
+
+/// A run of text placed on a page, `x` and `y` being the start of its
+/// baseline from the bottom left corner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextItem {
+    pub x: f32,
+    pub y: f32,
+    pub font: Font,
+    pub size: f32,
+    pub text: String,
+    /// Role of the block of the item
+    pub role: Role,
+    /// Whether the item starts its block, the next items belonging to it up
+    /// to the next one starting a block
+    pub starts_block: bool,
+}
+
+/// A laid out page.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Page {
+    pub items: Vec<TextItem>,
+}
+
+impl Page {
+    /// All the text of the page, for tests and diagnostics.
+    pub fn text(&self) -> String {
+        self.items
+            .iter()
+            .map(|item| item.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Side the lines of a block are aligned to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    /// Alignment of a block with `spans` and the `format` of its marker.
+    fn of(format: &ParagraphFormat, spans: &[(Style, String)]) -> Self {
+        match format.alignment {
+            Some(Alignment::Left) => Self::Left,
+            Some(Alignment::Center) => Self::Center,
+            Some(Alignment::Right) => Self::Right,
+            None | Some(Alignment::Justify) => {
+                let text: String = spans.iter().map(|(_, text)| text.as_str()).collect();
+                match format.resolve(&text) {
+                    Direction::Ltr => Self::Left,
+                    Direction::Rtl => Self::Right,
+                }
+            }
+        }
+    }
+}
+
+/// A block of a Markdown text.
+#[derive(Debug, Clone, PartialEq)]
+enum Block {
+    Heading(usize, Vec<(Style, String)>, Align),
+    Paragraph {
+        spans: Vec<(Style, String)>,
+        /// Left indent, in multiples of the font size
+        indent: usize,
+        /// List bullet or number
+        prefix: Option<String>,
+        align: Align,
+        /// Alternative text of the images of the paragraph, if it has some
+        figure: Option<String>,
+    },
+    Code(String),
+    Break,
+}
+
+/// Blocks of a Markdown text.
+fn blocks(markdown: &str) -> Vec<Block> {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_FOOTNOTES);
+
+    let mut blocks = Vec::new();
+    let mut spans: Vec<(Style, String)> = Vec::new();
+    let mut style = Style::default();
+    let (mut bold, mut italic) = (0usize, 0usize);
+    let mut quote_depth = 0;
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut prefix: Option<String> = None;
+    let mut code: Option<String> = None;
+    // Format of the marker of the next block
+    let mut format = ParagraphFormat::default();
+    // First block of the epigraph being read
+    let mut epigraph_start: Option<usize> = None;
+    // Alternative text of the images of the paragraph being read, and of
+    // the image being read
+    let mut figure: Option<String> = None;
+    let mut image: Option<String> = None;
+
+    let flush = |spans: &mut Vec<(Style, String)>,
+                 blocks: &mut Vec<Block>,
+                 prefix: &mut Option<String>,
+                 format: &mut ParagraphFormat,
+                 figure: &mut Option<String>,
+                 indent: usize| {
+        if spans.iter().any(|(_, text)| !text.trim().is_empty()) {
+            blocks.push(Block::Paragraph {
+                align: Align::of(&std::mem::take(format), spans),
+                spans: std::mem::take(spans),
+                indent,
+                prefix: prefix.take(),
+                figure: figure.take(),
+            });
+        }
+        spans.clear();
+        *figure = None;
+    };
+
+    for event in Parser::new_ext(markdown, options) {
+        let indent = 2 * (quote_depth + lists.len().saturating_sub(1));
+        match event {
+            Event::Start(Tag::Heading(..)) | Event::Start(Tag::Paragraph) => flush(
+                &mut spans,
+                &mut blocks,
+                &mut prefix,
+                &mut format,
+                &mut figure,
+                indent,
+            ),
+            Event::End(Tag::Heading(level, ..)) => {
+                let align = Align::of(&std::mem::take(&mut format), &spans);
+                blocks.push(Block::Heading(
+                    level as usize,
+                    std::mem::take(&mut spans),
+                    align,
+                ));
+            }
+            Event::End(Tag::Paragraph) | Event::End(Tag::Item) => flush(
+                &mut spans,
+                &mut blocks,
+                &mut prefix,
+                &mut format,
+                &mut figure,
+                indent,
+            ),
+            Event::Start(Tag::BlockQuote) => {
+                flush(
+                    &mut spans,
+                    &mut blocks,
+                    &mut prefix,
+                    &mut format,
+                    &mut figure,
+                    indent,
+                );
+                quote_depth += 1;
+            }
+            Event::End(Tag::BlockQuote) => {
+                flush(
+                    &mut spans,
+                    &mut blocks,
+                    &mut prefix,
+                    &mut format,
+                    &mut figure,
+                    indent,
+                );
+                quote_depth -= 1;
+            }
+            Event::Start(Tag::List(start)) => {
+                flush(
+                    &mut spans,
+                    &mut blocks,
+                    &mut prefix,
+                    &mut format,
+                    &mut figure,
+                    indent,
+                );
+                lists.push(start);
+            }
+            Event::End(Tag::List(_)) => {
+                flush(
+                    &mut spans,
+                    &mut blocks,
+                    &mut prefix,
+                    &mut format,
+                    &mut figure,
+                    indent,
+                );
+                lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                flush(
+                    &mut spans,
+                    &mut blocks,
+                    &mut prefix,
+                    &mut format,
+                    &mut figure,
+                    indent,
+                );
+                prefix = Some(match lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}.", *n - 1)
+                    }
+                    _ => "\u{2022}".to_string(),
+                });
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                flush(
+                    &mut spans,
+                    &mut blocks,
+                    &mut prefix,
+                    &mut format,
+                    &mut figure,
+                    indent,
+                );
+                code = Some(String::new());
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                if let Some(code) = code.take() {
+                    blocks.push(Block::Code(code));
+                }
+            }
+            Event::Start(Tag::Emphasis) => {
+                italic += 1;
+                style.italic = true;
+            }
+            Event::End(Tag::Emphasis) => {
+                italic = italic.saturating_sub(1);
+                style.italic = italic > 0;
+            }
+            Event::Start(Tag::Strong) => {
+                bold += 1;
+                style.bold = true;
+            }
+            Event::End(Tag::Strong) => {
+                bold = bold.saturating_sub(1);
+                style.bold = bold > 0;
+            }
+            Event::Start(Tag::Image(..)) => image = Some(String::new()),
+            Event::End(Tag::Image(..)) => {
+                let alt = image.take().unwrap_or_default();
+                let alt = alt.trim();
+                figure = Some(match figure.take() {
+                    Some(before) if !before.is_empty() && !alt.is_empty() => {
+                        format!("{} {}", before, alt)
+                    }
+                    Some(before) if alt.is_empty() => before,
+                    _ => alt.to_string(),
+                });
+            }
+            Event::Text(text) | Event::Code(text) => match &mut code {
+                Some(code) => code.push_str(&text),
+                None => {
+                    if let Some(alt) = &mut image {
+                        alt.push_str(&text);
+                    }
+                    spans.push((style, text.to_string()));
+                }
+            },
+            Event::SoftBreak => spans.push((style, " ".to_string())),
+            Event::HardBreak => spans.push((style, "\n".to_string())),
+            Event::FootnoteReference(label) => spans.push((style, format!("[{}]", label))),
+            Event::Rule => {
+                flush(
+                    &mut spans,
+                    &mut blocks,
+                    &mut prefix,
+                    &mut format,
+                    &mut figure,
+                    indent,
+                );
+                blocks.push(Block::Break);
+            }
+            Event::Html(html) => {
+                if let Some(marker) = ParagraphFormat::parse_marker(&html) {
+                    format = marker;
+                }
+                match epigraph::parse_marker(&html) {
+                    Some(true) => epigraph_start = Some(blocks.len()),
+                    Some(false) => {
+                        if let Some(start) = epigraph_start.take() {
+                            set_epigraph(&mut blocks[start..]);
+                        }
+                    }
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    let indent = 2 * quote_depth;
+    flush(
+        &mut spans,
+        &mut blocks,
+        &mut prefix,
+        &mut format,
+        &mut figure,
+        indent,
+    );
+    blocks
+}
+
+/// Set the paragraphs of an epigraph in italics, far in from the left
+/// margin, and its attribution against the right one.
+fn set_epigraph(blocks: &mut [Block]) {
+    for block in blocks {
+        if let Block::Paragraph {
+            spans,
+            indent,
+            align,
+            ..
+        } = block
+        {
+            *indent = EPIGRAPH_INDENT;
+            let attribution = spans
+                .first()
+                .is_some_and(|(_, text)| text.starts_with(ATTRIBUTION_DASH));
+            if attribution {
+                *align = Align::Right;
+            } else {
+                for (style, _) in spans.iter_mut() {
+                    style.italic = true;
+                }
+            }
+        }
+    }
+}
+
+/// Styled spans of a line of verse.
+fn verse_spans(text: &str) -> Vec<(Style, String)> {
+    match blocks(&verse::escape_line(text)).into_iter().next() {
+        Some(Block::Paragraph { spans, .. }) => spans,
+        _ => vec![(Style::default(), text.to_string())],
+    }
+}
+
+/// A piece of a word in one style.
+struct Piece {
+    style: Style,
+    text: String,
+}
+
+/// A word, or a forced line break (no pieces).
+struct Word {
+    pieces: Vec<Piece>,
+}
+
+/// Words of styled spans, splitting at whitespace only, so that "*ran*."
+/// stays one word.
+fn words(spans: &[(Style, String)]) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut current: Vec<Piece> = Vec::new();
+    for (style, text) in spans {
+        let mut piece = String::new();
+        for c in text.chars() {
+            if c.is_whitespace() {
+                if !piece.is_empty() {
+                    current.push(Piece {
+                        style: *style,
+                        text: std::mem::take(&mut piece),
+                    });
+                }
+                if !current.is_empty() {
+                    words.push(Word {
+                        pieces: std::mem::take(&mut current),
+                    });
+                }
+                if c == '\n' {
+                    words.push(Word { pieces: Vec::new() });
+                }
+            } else {
+                piece.push(c);
+            }
+        }
+        if !piece.is_empty() {
+            current.push(Piece {
+                style: *style,
+                text: piece,
+            });
+        }
+    }
+    if !current.is_empty() {
+        words.push(Word { pieces: current });
+    }
+    words
+}
+
+/// Lays out text on pages.
+pub struct Typesetter {
+    setup: PageSetup,
+    family: Family,
+    size: f32,
+    /// Line height, as a multiple of the font size
+    line_spacing: f32,
+    /// Text of scene breaks
+    break_mark: String,
+    pages: Vec<Page>,
+    /// Top of the next line, from the bottom of the page
+    y: f32,
+    /// Whether the next paragraph follows a heading or a break
+    after_break: bool,
+    /// Role of the block being laid out
+    role: Role,
+    /// Whether the next item placed starts the block
+    starts_block: bool,
+}
+
+impl Typesetter {
+    pub fn new(setup: PageSetup, family: Family, size: f32) -> Self {
+        Self {
+            setup,
+            family,
+            size,
+            line_spacing: LINE_SPACING,
+            break_mark: BREAK_MARK.to_string(),
+            pages: vec![Page::default()],
+            y: setup.height - setup.margin_top,
+            after_break: true,
+            role: Role::default(),
+            starts_block: false,
+        }
+    }
+
+    /// Set the line height, as a multiple of the font size.
+    pub fn with_line_spacing(mut self, line_spacing: f32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    /// Set the text of scene breaks.
+    pub fn with_break_mark(mut self, break_mark: &str) -> Self {
+        self.break_mark = break_mark.to_string();
+        self
+    }
+
+    /// Index of the page being filled.
+    pub fn page_index(&self) -> usize {
+        self.pages.len() - 1
+    }
+
+    /// Start a new page, unless the current one is still empty.
+    pub fn new_page(&mut self) {
+        if !self.page_is_empty() {
+            self.pages.push(Page::default());
+            self.y = self.setup.height - self.setup.margin_top;
+        }
+        self.after_break = true;
+    }
+
+    fn page_is_empty(&self) -> bool {
+        self.pages.last().is_none_or(|page| page.items.is_empty())
+    }
+
+    fn line_height(&self, size: f32) -> f32 {
+        size * self.line_spacing
+    }
+
+    /// Leave `height` points blank, if the page has room for it.
+    pub fn skip(&mut self, height: f32) {
+        if self.y - height > self.setup.margin_bottom {
+            self.y -= height;
+        }
+    }
+
+    /// Make room for `height` points of text, starting a new page if needed.
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < self.setup.margin_bottom && !self.page_is_empty() {
+            self.pages.push(Page::default());
+            self.y = self.setup.height - self.setup.margin_top;
+        }
+    }
+
+    /// Start a block of `role`, which the next items placed belong to.
+    fn begin(&mut self, role: Role) {
+        self.role = role;
+        self.starts_block = true;
+    }
+
+    fn place(&mut self, x: f32, font: Font, size: f32, text: String) {
+        let baseline = self.y - size;
+        if let Some(page) = self.pages.last_mut() {
+            page.items.push(TextItem {
+                x,
+                y: baseline,
+                font,
+                size,
+                text,
+                role: self.role.clone(),
+                starts_block: std::mem::take(&mut self.starts_block),
+            });
+        }
+    }
+
+    /// Write a line of text centered between the margins, in the block
+    /// being laid out.
+    pub fn centered_line(&mut self, text: &str, style: Style, size: f32) {
+        let font = Font::new(self.family, style);
+        let height = self.line_height(size);
+        self.reserve(height);
+        let width = font.text_width(text, size);
+        let x = self.setup.margin_left + ((self.setup.text_width() - width) / 2.0).max(0.0);
+        self.place(x, font, size, text.to_string());
+        self.y -= height;
+    }
+
+    /// Write a line with `left` at the left margin plus `indent` and
+    /// `right` against the right margin, as a paragraph.
+    pub fn aligned_line(&mut self, indent: f32, left: &str, right: &str) {
+        self.begin(Role::Paragraph);
+        let font = Font::new(self.family, Style::default());
+        let height = self.line_height(self.size);
+        self.reserve(height);
+        let right_width = font.text_width(right, self.size);
+        let right_x = self.setup.width - self.setup.margin_right - right_width;
+
+        // Shorten the left text so that it does not run into the right one
+        let room = right_x - self.setup.margin_left - indent - self.size;
+        let mut left = left.to_string();
+        if font.text_width(&left, self.size) > room {
+            while !left.is_empty() && font.text_width(&format!("{}…", left), self.size) > room {
+                left.pop();
+            }
+            left.push('…');
+        }
+
+        self.place(self.setup.margin_left + indent, font, self.size, left);
+        self.place(right_x, font, self.size, right.to_string());
+        self.y -= height;
+    }
+
+    /// Lay out a Markdown text.
+    pub fn markdown(&mut self, markdown: &str) {
+        let verses = verse::verses(markdown);
+        if verses.is_empty() {
+            self.prose(markdown);
+            return;
+        }
+        let lines: Vec<&str> = markdown.lines().collect();
+        let mut start = 0;
+        for verse in &verses {
+            self.prose(&lines[start..verse.lines.start].join("\n"));
+            self.verse(verse);
+            start = verse.lines.end;
+        }
+        self.prose(&lines[start..].join("\n"));
+    }
+
+    /// Lay out Markdown without verse.
+    fn prose(&mut self, markdown: &str) {
+        for block in blocks(markdown) {
+            match block {
+                Block::Heading(level, spans, align) => self.heading(level, &spans, align),
+                Block::Paragraph {
+                    spans,
+                    indent,
+                    prefix,
+                    align,
+                    figure,
+                } => {
+                    self.begin(match figure {
+                        Some(alt) => Role::Figure(alt),
+                        None if prefix.is_some() => Role::ListItem,
+                        None => Role::Paragraph,
+                    });
+                    self.paragraph(&spans, indent, prefix, align)
+                }
+                Block::Code(code) => self.code(&code),
+                Block::Break => {
+                    self.begin(Role::Artifact);
+                    self.skip(self.line_height(self.size) / 2.0);
+                    let mark = self.break_mark.clone();
+                    self.centered_line(&mark, Style::default(), self.size);
+                    self.skip(self.line_height(self.size) / 2.0);
+                    self.after_break = true;
+                }
+            }
+        }
+    }
+
+    /// Lay out verse set in from the margin, a blank line between the
+    /// stanzas.
+    fn verse(&mut self, verse: &Verse) {
+        let height = self.line_height(self.size);
+        let hanging = HANGING_INDENT * self.size;
+        self.skip(height / 2.0);
+        for (i, stanza) in verse.stanzas.iter().enumerate() {
+            if i > 0 {
+                self.skip(height);
+            }
+            self.begin(Role::Paragraph);
+            for line in stanza {
+                let left = (VERSE_INDENT + line.indent_em()) * self.size + hanging;
+                self.lines(
+                    &verse_spans(&line.text),
+                    self.size,
+                    left,
+                    -hanging,
+                    None,
+                    Align::Left,
+                );
+            }
+        }
+        self.skip(height / 2.0);
+        self.after_break = true;
+    }
+
+    fn heading(&mut self, level: usize, spans: &[(Style, String)], align: Align) {
+        let scale = match level {
+            1 => 1.8,
+            2 => 1.5,
+            3 => 1.25,
+            _ => 1.1,
+        };
+        let size = self.size * scale;
+        self.begin(Role::Heading(level));
+        // Keep the heading with the first lines after it
+        self.reserve(self.line_height(size) * 1.5 + self.line_height(self.size) * 2.0);
+        if !self.page_is_empty() {
+            self.skip(self.line_height(size) / 2.0);
+        }
+        let spans: Vec<(Style, String)> = spans
+            .iter()
+            .map(|(style, text)| {
+                let style = Style {
+                    bold: true,
+                    ..*style
+                };
+                (style, text.clone())
+            })
+            .collect();
+        self.lines(&spans, size, 0.0, 0.0, None, align);
+        self.skip(self.line_height(self.size) / 2.0);
+        self.after_break = true;
+    }
+
+    fn paragraph(
+        &mut self,
+        spans: &[(Style, String)],
+        indent: usize,
+        prefix: Option<String>,
+        align: Align,
+    ) {
+        let left = indent as f32 * self.size;
+        let first_indent =
+            if prefix.is_none() && indent == 0 && !self.after_break && align != Align::Center {
+                2.0 * self.size
+            } else {
+                0.0
+            };
+        let hanging = prefix.as_ref().map(|_| 1.5 * self.size).unwrap_or(0.0);
+        self.lines(
+            spans,
+            self.size,
+            left + hanging,
+            first_indent,
+            prefix,
+            align,
+        );
+        self.after_break = false;
+    }
+
+    /// Wrap styled spans into lines starting at `left`, the first one
+    /// further indented by `first_indent`, with an optional list bullet
+    /// hanging before the first line, and align them.
+    ///
+    /// Right aligned lines are indented and have their bullet on the right.
+    fn lines(
+        &mut self,
+        spans: &[(Style, String)],
+        size: f32,
+        left: f32,
+        first_indent: f32,
+        prefix: Option<String>,
+        align: Align,
+    ) {
+        let height = self.line_height(size);
+        let width = self.setup.text_width() - left;
+        let space = Font::new(self.family, Style::default()).text_width(" ", size);
+        let word_width = |word: &Word| -> f32 {
+            word.pieces
+                .iter()
+                .map(|p| Font::new(self.family, p.style).text_width(&p.text, size))
+                .sum()
+        };
+
+        let words = words(spans);
+        // Words of the lines, with the width they fill
+        let mut lines: Vec<(Vec<&Word>, f32)> = vec![(Vec::new(), first_indent)];
+        for word in &words {
+            if word.pieces.is_empty() {
+                lines.push((Vec::new(), 0.0));
+                continue;
+            }
+            let w = word_width(word);
+            let (current, line_width) = lines.last_mut().expect("lines start with one line");
+            if !current.is_empty() && *line_width + space + w > width {
+                lines.push((vec![word], w));
+            } else {
+                if !current.is_empty() {
+                    *line_width += space;
+                }
+                current.push(word);
+                *line_width += w;
+            }
+        }
+
+        let bullet = prefix.is_some();
+        let mut prefix = prefix;
+        for (i, (line, line_width)) in lines.into_iter().enumerate() {
+            self.reserve(height);
+            let x0 = self.setup.margin_left + left;
+            if let Some(prefix) = prefix.take() {
+                let font = Font::new(self.family, Style::default());
+                let x = match align {
+                    Align::Right => x0 + width - font.text_width(&prefix, size),
+                    _ => x0 - 1.5 * self.size,
+                };
+                self.place(x, font, size, prefix);
+            }
+
+            let mut x = match align {
+                Align::Left if i == 0 => x0 + first_indent,
+                Align::Left => x0,
+                Align::Center => x0 + (width - line_width) / 2.0,
+                // The hanging space of bullets moves to the right
+                Align::Right if bullet && i == 0 => x0 + width - line_width - 1.5 * self.size,
+                Align::Right => x0 + width - line_width,
+            };
+            // Runs of the same style are written as one item
+            let mut run: Option<(f32, Style, String)> = None;
+            for (j, word) in line.into_iter().enumerate() {
+                for (k, piece) in word.pieces.iter().enumerate() {
+                    let gap = if j > 0 && k == 0 { " " } else { "" };
+                    match &mut run {
+                        Some((_, style, text)) if *style == piece.style => {
+                            text.push_str(gap);
+                            text.push_str(&piece.text);
+                        }
+                        _ => {
+                            if let Some((start, style, text)) = run.take() {
+                                let font = Font::new(self.family, style);
+                                x = start + font.text_width(&text, size);
+                                self.place(start, font, size, text);
+                            }
+                            let start = if gap.is_empty() { x } else { x + space };
+                            run = Some((start, piece.style, piece.text.clone()));
+                        }
+                    }
+                }
+            }
+            if let Some((start, style, text)) = run {
+                self.place(start, Font::new(self.family, style), size, text);
+            }
+            self.y -= height;
+        }
+    }
+
+    fn code(&mut self, code: &str) {
+        self.begin(Role::Code);
+        let size = self.size * 0.9;
+        let font = Font::new(Family::Courier, Style::default());
+        let height = self.line_height(size);
+        let columns = (self.setup.text_width() / font.text_width("m", size)).max(1.0) as usize;
+        for line in code.trim_end_matches('\n').lines() {
+            let chars: Vec<char> = line.chars().collect();
+            for chunk in chars.chunks(columns).map(|c| c.iter().collect::<String>()) {
+                self.reserve(height);
+                self.place(self.setup.margin_left, font, size, chunk);
+                self.y -= height;
+            }
+            if chars.is_empty() {
+                self.reserve(height);
+                self.y -= height;
+            }
+        }
+        self.after_break = true;
+    }
+
+    /// The laid out pages.
+    pub fn finish(self) -> Vec<Page> {
+        self.pages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> PageSetup {
+        PageSetup {
+            width: 300.0,
+            height: 200.0,
+            margin_top: 20.0,
+            margin_bottom: 20.0,
+            margin_left: 20.0,
+            margin_right: 20.0,
+        }
+    }
+
+    #[test]
+    fn test_blocks() {
+        let blocks = blocks("# Title\n\nShe *ran*.\n\n***\n\n- one\n\n> quote\n\n```\ncode\n```");
+        assert_eq!(blocks.len(), 6);
+        assert_eq!(
+            blocks[0],
+            Block::Heading(
+                1,
+                vec![(Style::default(), "Title".to_string())],
+                Align::Left
+            )
+        );
+        let italic = Style {
+            italic: true,
+            ..Style::default()
+        };
+        assert_eq!(
+            blocks[1],
+            Block::Paragraph {
+                spans: vec![
+                    (Style::default(), "She ".to_string()),
+                    (italic, "ran".to_string()),
+                    (Style::default(), ".".to_string()),
+                ],
+                indent: 0,
+                prefix: None,
+                align: Align::Left,
+                figure: None,
+            }
+        );
+        assert_eq!(blocks[2], Block::Break);
+        assert!(matches!(&blocks[3], Block::Paragraph { prefix: Some(p), .. } if p == "\u{2022}"));
+        assert!(matches!(&blocks[4], Block::Paragraph { indent: 2, .. }));
+        assert_eq!(blocks[5], Block::Code("code\n".to_string()));
+    }
+
+    #[test]
+    fn test_lines_wrap_and_pages_break() {
+        let mut typesetter = Typesetter::new(setup(), Family::Times, 10.0);
+        let paragraph = "word ".repeat(200);
+        typesetter.markdown(&paragraph);
+        let pages = typesetter.finish();
+
+        assert!(pages.len() > 1);
+        for page in &pages {
+            for item in &page.items {
+                let font_width = item.font.text_width(&item.text, item.size);
+                assert!(item.x + font_width <= 280.5, "{:?} overflows", item);
+                assert!(item.y >= 20.0);
+            }
+        }
+        let words: usize = pages
+            .iter()
+            .map(|page| page.text().split_whitespace().count())
+            .sum();
+        assert_eq!(words, 200);
+    }
+
+    #[test]
+    fn test_styled_runs_keep_their_place() {
+        let mut typesetter = Typesetter::new(setup(), Family::Times, 10.0);
+        typesetter.markdown("She *ran*.");
+        let page = &typesetter.finish()[0];
+
+        let texts: Vec<&str> = page.items.iter().map(|i| i.text.as_str()).collect();
+        assert_eq!(texts, vec!["She", "ran", "."]);
+        assert!(page.items[1].font.style.italic);
+        assert!(page.items[1].x > page.items[0].x);
+        assert!(page.items[2].x > page.items[1].x);
+        assert_eq!(page.items[0].y, page.items[2].y);
+    }
+
+    #[test]
+    fn test_aligned_paragraphs() {
+        let mut typesetter = Typesetter::new(setup(), Family::Times, 10.0);
+        typesetter.markdown("<!-- align=right -->\nEnd.\n\n<!-- align=center -->\nMiddle\n\nStart");
+        let page = &typesetter.finish()[0];
+
+        let right = &page.items[0];
+        let end = right.x + right.font.text_width(&right.text, right.size);
+        assert!(
+            (end - 280.0).abs() < 0.01,
+            "{:?} is not right aligned",
+            right
+        );
+        let center = &page.items[1];
+        let middle = center.x + center.font.text_width(&center.text, center.size) / 2.0;
+        assert!(
+            (middle - 150.0).abs() < 0.01,
+            "{:?} is not centered",
+            center
+        );
+        // The marker applies to one paragraph only
+        assert!(page.items[2].x < 50.0);
+    }
+
+    #[test]
+    fn test_epigraph_is_set_apart() {
+        let blocks = blocks("<!-- epigraph -->\n> Go.\n>\n> — Ann\n<!-- /epigraph -->\n\nText.");
+        let italic = Style {
+            italic: true,
+            ..Style::default()
+        };
+        assert_eq!(
+            blocks,
+            [
+                Block::Paragraph {
+                    spans: vec![(italic, "Go.".to_string())],
+                    indent: EPIGRAPH_INDENT,
+                    prefix: None,
+                    align: Align::Left,
+                    figure: None,
+                },
+                Block::Paragraph {
+                    spans: vec![(Style::default(), "— Ann".to_string())],
+                    indent: EPIGRAPH_INDENT,
+                    prefix: None,
+                    align: Align::Right,
+                    figure: None,
+                },
+                Block::Paragraph {
+                    spans: vec![(Style::default(), "Text.".to_string())],
+                    indent: 0,
+                    prefix: None,
+                    align: Align::Left,
+                    figure: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_items_have_the_role_of_their_block() {
+        let mut typesetter = Typesetter::new(setup(), Family::Times, 10.0);
+        typesetter.markdown("# One\n\nShe *ran*.\n\n***\n\n![A *map*](map.png)\n\n- Item");
+        let page = &typesetter.finish()[0];
+
+        let roles: Vec<(&str, &Role, bool)> = page
+            .items
+            .iter()
+            .map(|item| (item.text.as_str(), &item.role, item.starts_block))
+            .collect();
+        let figure = Role::Figure("A map".to_string());
+        assert_eq!(
+            roles,
+            [
+                ("One", &Role::Heading(1), true),
+                ("She", &Role::Paragraph, true),
+                ("ran", &Role::Paragraph, false),
+                (".", &Role::Paragraph, false),
+                (BREAK_MARK, &Role::Artifact, true),
+                ("A", &figure, true),
+                ("map", &figure, false),
+                ("\u{2022}", &Role::ListItem, true),
+                ("Item", &Role::ListItem, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verse_keeps_lines_and_hangs() {
+        let mut typesetter = Typesetter::new(setup(), Family::Times, 10.0);
+        let long = "word ".repeat(25);
+        typesetter.markdown(&format!(
+            "Prose.\n\n<!-- verse -->\nOne\n  - two\n{}\n<!-- /verse -->\nMore prose.",
+            long
+        ));
+        let page = &typesetter.finish()[0];
+
+        let lines: Vec<(f32, &str)> = page
+            .items
+            .iter()
+            .map(|item| (item.x, item.text.as_str()))
+            .collect();
+        assert_eq!(lines[0], (20.0, "Prose."));
+        assert_eq!(lines[1], (40.0, "One"));
+        // Indented by half an em a space, and not taken for a list item
+        assert_eq!(lines[2], (50.0, "- two"));
+        assert_eq!(lines[3].0, 40.0);
+        // The rest of the long line hangs
+        assert_eq!(lines[4].0, 60.0);
+        assert_eq!(lines.last().map(|line| line.1), Some("More prose."));
+        assert!(page.items[1].y > page.items[2].y);
+    }
+}
@@ -0,0 +1,376 @@
+//! # PDF export plugin for Cosmarium
+//!
+//! Compiles a manuscript to a PDF document following the PDF export
+//! settings: paper size, margins, font family and size, line spacing,
+//! scene breaks, table of contents, page numbers and running header.
+//!
+//! The front matter opens the document, followed by the table of contents
+//! and the text; every part and chapter starts on a new page. The PDF uses
+//! the standard fonts of PDF readers (see [`fonts`]), so the configured
+//! font family selects the closest serif, sans-serif or monospaced one.
+//!
+//! The PDF is tagged: its headings, paragraphs, lists, code and figures make
+//! a structure tree in reading order, which assistive technologies read
+//! with the language of the manuscript, page numbers and headers left out.
+//! Figures take the alternative text of their images.
+
+pub mod fonts;
+pub mod layout;
+pub mod writer;
+
+use cosmarium_plugin_api::export::{ExportPlugin, Manuscript, SectionKind};
+use cosmarium_plugin_api::{Plugin, PluginContext, PluginInfo, PluginType, Result};
+use fonts::{Family, Font, Style};
+use layout::{Page, PageSetup, Role, TextItem, Typesetter, BREAK_MARK, LINE_SPACING};
+use serde::Deserialize;
+use std::path::Path;
+use writer::DocumentInfo;
+
+/// Points per millimeter.
+const POINTS_PER_MM: f32 = 72.0 / 25.4;
+
+/// PDF export settings, as published by the application.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PdfOptions {
+    /// Paper size (A4, A5, Letter, Legal)
+    pub paper_size: String,
+    /// Margins in millimeters
+    pub margin_top: f32,
+    pub margin_bottom: f32,
+    pub margin_left: f32,
+    pub margin_right: f32,
+    /// Font family, matched to the closest standard font
+    pub font_family: String,
+    /// Body text size in points
+    pub font_size: f32,
+    /// Line height, as a multiple of the font size
+    pub line_spacing: f32,
+    /// Text of scene breaks
+    pub scene_break: String,
+    /// Whether to include a table of contents
+    pub include_toc: bool,
+    /// Whether to number the pages of the text
+    pub include_page_numbers: bool,
+    /// Whether pages of the text have a "Surname / Title / page" header
+    pub running_header: bool,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            paper_size: "A4".to_string(),
+            margin_top: 25.0,
+            margin_bottom: 25.0,
+            margin_left: 25.0,
+            margin_right: 25.0,
+            font_family: "Liberation Serif".to_string(),
+            font_size: 11.0,
+            line_spacing: LINE_SPACING,
+            scene_break: BREAK_MARK.to_string(),
+            include_toc: true,
+            include_page_numbers: true,
+            running_header: false,
+        }
+    }
+}
+
+impl PdfOptions {
+    /// Page size and margins in points. Unknown paper sizes fall back to A4.
+    pub fn page_setup(&self) -> PageSetup {
+        let (width, height) = match self.paper_size.trim().to_lowercase().as_str() {
+            "letter" => (612.0, 792.0),
+            "legal" => (612.0, 1008.0),
+            "a5" => (419.53, 595.28),
+            _ => (595.28, 841.89),
+        };
+        let margin = |mm: f32| mm.clamp(0.0, 100.0) * POINTS_PER_MM;
+        PageSetup {
+            width,
+            height,
+            margin_top: margin(self.margin_top),
+            margin_bottom: margin(self.margin_bottom),
+            margin_left: margin(self.margin_left),
+            margin_right: margin(self.margin_right),
+        }
+    }
+
+    fn font_size(&self) -> f32 {
+        self.font_size.clamp(6.0, 36.0)
+    }
+
+    fn line_spacing(&self) -> f32 {
+        self.line_spacing.clamp(1.0, 3.0)
+    }
+
+    fn typesetter(&self) -> Typesetter {
+        Typesetter::new(
+            self.page_setup(),
+            Family::from_name(&self.font_family),
+            self.font_size(),
+        )
+        .with_line_spacing(self.line_spacing())
+        .with_break_mark(&self.scene_break)
+    }
+}
+
+/// Lay out a manuscript on pages.
+pub fn typeset(manuscript: &Manuscript, options: &PdfOptions) -> Vec<Page> {
+    let setup = options.page_setup();
+    let family = Family::from_name(&options.font_family);
+    let size = options.font_size();
+
+    // Front matter, a third of the way down its first page
+    let mut front = Vec::new();
+    if !manuscript.front_matter.trim().is_empty() {
+        let mut typesetter = options.typesetter();
+        typesetter.skip((setup.height - setup.margin_top - setup.margin_bottom) / 3.0);
+        typesetter.markdown(&manuscript.front_matter);
+        front = typesetter.finish();
+    }
+
+    // Text, every heading starting a page
+    let mut body = options.typesetter();
+    let mut headings = Vec::new();
+    for section in &manuscript.sections {
+        if section.kind == SectionKind::Heading {
+            body.new_page();
+            headings.push((section.title.clone(), section.depth, body.page_index()));
+        }
+        body.markdown(&section.separator);
+        body.markdown(&section.markdown);
+    }
+    let mut body = body.finish();
+
+    // Table of contents, whose length is known before the page numbers
+    let mut contents = Vec::new();
+    if options.include_toc && !headings.is_empty() {
+        let line_height = size * options.line_spacing();
+        let heading_height = size * 1.8 * 1.4 + line_height;
+        let per_page = ((setup.height - setup.margin_top - setup.margin_bottom - heading_height)
+            / line_height)
+            .floor()
+            .max(1.0) as usize;
+        let first_body_page = front.len() + headings.len().div_ceil(per_page);
+
+        let mut typesetter = options.typesetter();
+        typesetter.markdown("# Contents");
+        for (title, depth, page) in &headings {
+            let number = (first_body_page + page + 1).to_string();
+            typesetter.aligned_line(*depth as f32 * 2.0 * size, title, &number);
+        }
+        contents = typesetter.finish();
+    }
+
+    let first_body_page = front.len() + contents.len();
+    let font = Font::new(family, Style::default());
+    let number_size = size * 0.9;
+    for (i, page) in body.iter_mut().enumerate() {
+        let number = first_body_page + i + 1;
+        if options.include_page_numbers {
+            // Centered in the bottom margin
+            let text = number.to_string();
+            let width = font.text_width(&text, number_size);
+            page.items.push(TextItem {
+                x: setup.margin_left + (setup.text_width() - width) / 2.0,
+                y: (setup.margin_bottom - number_size) / 2.0,
+                font,
+                size: number_size,
+                text,
+                role: Role::Artifact,
+                starts_block: false,
+            });
+        }
+        if options.running_header {
+            // Against the right margin, in the top margin
+            let text = format!("{} / {}", manuscript.running_header(), number);
+            let width = font.text_width(&text, size);
+            page.items.push(TextItem {
+                x: setup.width - setup.margin_right - width,
+                y: setup.height - (setup.margin_top + size) / 2.0,
+                font,
+                size,
+                text,
+                role: Role::Artifact,
+                starts_block: false,
+            });
+        }
+    }
+
+    front.into_iter().chain(contents).chain(body).collect()
+}
+
+#[derive(Default)]
+pub struct PdfExportPlugin;
+
+impl PdfExportPlugin {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Plugin for PdfExportPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            "export-pdf",
+            "0.1.0",
+            "PDF export of compiled manuscripts",
+            "Cosmarium Team",
+        )
+    }
+
+    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Export
+    }
+}
+
+impl ExportPlugin for PdfExportPlugin {
+    fn format_id(&self) -> &str {
+        "pdf"
+    }
+
+    fn format_name(&self) -> &str {
+        "PDF document"
+    }
+
+    fn file_extension(&self) -> &str {
+        "pdf"
+    }
+
+    fn export(
+        &self,
+        manuscript: &Manuscript,
+        options: &serde_json::Value,
+        output: &Path,
+    ) -> Result<()> {
+        let options: PdfOptions = if options.is_null() {
+            PdfOptions::default()
+        } else {
+            serde_json::from_value(options.clone())?
+        };
+        let pages = typeset(manuscript, &options);
+        let info = DocumentInfo {
+            title: manuscript.title.clone(),
+            author: manuscript.author.clone(),
+            language: manuscript.language.clone(),
+        };
+        std::fs::write(
+            output,
+            writer::write_pdf(&pages, &options.page_setup(), &info),
+        )?;
+        tracing::info!("Wrote {} PDF pages to {:?}", pages.len(), output);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmarium_plugin_api::export::ManuscriptSection;
+
+    fn section(kind: SectionKind, title: &str, markdown: &str) -> ManuscriptSection {
+        ManuscriptSection {
+            kind,
+            title: title.to_string(),
+            depth: 0,
+            path: None,
+            separator: String::new(),
+            markdown: markdown.to_string(),
+        }
+    }
+
+    fn manuscript() -> Manuscript {
+        Manuscript {
+            title: "The Inn".to_string(),
+            author: "Ann Author".to_string(),
+            language: "en".to_string(),
+            front_matter: "# The Inn\n\nby Ann Author".to_string(),
+            sections: vec![
+                section(SectionKind::Heading, "Arrival", "# Arrival"),
+                section(SectionKind::Document, "a", "She *ran*."),
+                section(SectionKind::Heading, "Departure", "# Departure"),
+                section(SectionKind::Document, "b", "Dawn."),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_chapters_start_pages_listed_in_contents() {
+        let pages = typeset(&manuscript(), &PdfOptions::default());
+
+        // Title page, contents, then one page per chapter
+        assert_eq!(pages.len(), 4);
+        assert!(pages[0].text().contains("by Ann Author"));
+        assert!(pages[1]
+            .text()
+            .starts_with("Contents Arrival 3 Departure 4"));
+        assert!(pages[2].text().starts_with("Arrival She ran . 3"));
+        assert!(pages[3].text().ends_with("4"));
+
+        let options = PdfOptions {
+            include_toc: false,
+            include_page_numbers: false,
+            ..PdfOptions::default()
+        };
+        let pages = typeset(&manuscript(), &options);
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[2].text(), "Departure Dawn.");
+    }
+
+    #[test]
+    fn test_manuscript_layout() {
+        let mut manuscript = manuscript();
+        manuscript.sections[3].separator = "***".to_string();
+        manuscript.sections[3].markdown = "Dawn.  \nDusk.".to_string();
+        let options = PdfOptions {
+            font_family: "Courier New".to_string(),
+            font_size: 12.0,
+            line_spacing: 2.0,
+            scene_break: "#".to_string(),
+            include_toc: false,
+            include_page_numbers: false,
+            running_header: true,
+            ..PdfOptions::default()
+        };
+        let pages = typeset(&manuscript, &options);
+
+        // No header on the title page
+        assert!(!pages[0].text().contains(" / "));
+        assert!(pages[1].text().ends_with("Author / The Inn / 2"));
+        assert_eq!(
+            pages[2].text(),
+            "Departure # Dawn. Dusk. Author / The Inn / 3"
+        );
+
+        // Lines are twice the font size apart
+        let y = |text: &str| pages[2].items.iter().find(|i| i.text == text).unwrap().y;
+        assert_eq!(y("Dawn.") - y("Dusk."), 24.0);
+    }
+
+    #[test]
+    fn test_export_writes_pdf() {
+        let dir = std::env::temp_dir().join(format!("cosmarium_pdf_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("the_inn.pdf");
+        let options = serde_json::json!({ "paper_size": "Letter", "font_family": "Arial" });
+
+        PdfExportPlugin::new()
+            .export(&manuscript(), &options, &output)
+            .unwrap();
+        let pdf = std::fs::read(&output).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("/MediaBox [0 0 612.00 792.00]"));
+        assert!(text.contains("/BaseFont /Helvetica"));
+        assert!(text.contains("/Author (Ann Author)"));
+        assert!(text.contains("/Lang (en)"));
+        assert!(text.contains("/StructTreeRoot"));
+        assert!(text.trim_end().ends_with("%%EOF"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
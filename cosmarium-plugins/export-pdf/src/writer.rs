@@ -0,0 +1,435 @@
+//! Serialization of laid out pages to a PDF file.
+//!
+//! Writes a tagged PDF 1.4 document: one uncompressed content stream per
+//! page, the standard fonts used by the text, an information dictionary
+//! with the title and author, and the structure tree of the text. Each
+//! block of text is the marked content of a structure element, the
+//! elements following the reading order of the pages; list items are
+//! gathered in lists, and page numbers and headers are marked as
+//! artifacts.
+
+use crate::fonts::{win_ansi, Font};
+use crate::layout::{Page, PageSetup, Role};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+/// Document information written in the PDF.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentInfo {
+    pub title: String,
+    pub author: String,
+    /// Language of the text, as a BCP 47 tag, empty if it is not known
+    pub language: String,
+}
+
+/// Encode `text` as a PDF literal string, parentheses included.
+fn literal(text: &str) -> Vec<u8> {
+    let mut out = vec![b'('];
+    for byte in text.chars().map(win_ansi) {
+        if matches!(byte, b'(' | b')' | b'\\') {
+            out.push(b'\\');
+        }
+        out.push(byte);
+    }
+    out.push(b')');
+    out
+}
+
+/// Encode `text` as a PDF text string in UTF-16, hexadecimal digits
+/// included, for strings shown outside of the pages.
+fn text_string(text: &str) -> String {
+    let mut out = String::from("<FEFF");
+    for unit in text.encode_utf16() {
+        let _ = write!(out, "{:04X}", unit);
+    }
+    out.push('>');
+    out
+}
+
+/// Structure type of the blocks of `role`, `None` for artifacts.
+fn structure_type(role: &Role) -> Option<&'static str> {
+    match role {
+        Role::Paragraph => Some("P"),
+        Role::Heading(level) => Some(["H1", "H2", "H3", "H4", "H5", "H6"][level.clamp(&1, &6) - 1]),
+        Role::ListItem => Some("LI"),
+        Role::Code => Some("Code"),
+        Role::Figure(_) => Some("Figure"),
+        Role::Artifact => None,
+    }
+}
+
+/// A structure element of a block of text.
+struct Element {
+    id: usize,
+    role: Role,
+    /// Page object and marked content identifier of each of its items
+    content: Vec<(usize, usize)>,
+}
+
+/// Objects of a PDF file being written, numbered from 1.
+struct PdfObjects {
+    objects: Vec<Vec<u8>>,
+}
+
+impl PdfObjects {
+    /// Reserve an object number, filled later with [`PdfObjects::set`].
+    fn reserve(&mut self) -> usize {
+        self.objects.push(Vec::new());
+        self.objects.len()
+    }
+
+    fn add(&mut self, body: Vec<u8>) -> usize {
+        self.objects.push(body);
+        self.objects.len()
+    }
+
+    fn set(&mut self, id: usize, body: Vec<u8>) {
+        self.objects[id - 1] = body;
+    }
+
+    fn stream(content: &[u8]) -> Vec<u8> {
+        let mut body = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        body.extend_from_slice(content);
+        body.extend_from_slice(b"\nendstream");
+        body
+    }
+
+    /// The whole file, with its cross-reference table and trailer.
+    fn finish(self, root: usize, info: usize) -> Vec<u8> {
+        let mut out = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(self.objects.len());
+        for (i, body) in self.objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.objects.len() + 1,
+            root,
+            info,
+            xref
+        );
+        out.extend_from_slice(table.as_bytes());
+        out
+    }
+}
+
+/// Write `pages` as a PDF document.
+pub fn write_pdf(pages: &[Page], setup: &PageSetup, info: &DocumentInfo) -> Vec<u8> {
+    let mut objects = PdfObjects {
+        objects: Vec::new(),
+    };
+    let catalog = objects.reserve();
+    let pages_id = objects.reserve();
+
+    let mut info_body = b"<< /Producer (Cosmarium)".to_vec();
+    if !info.title.trim().is_empty() {
+        info_body.extend_from_slice(b" /Title ");
+        info_body.extend_from_slice(&literal(info.title.trim()));
+    }
+    if !info.author.trim().is_empty() {
+        info_body.extend_from_slice(b" /Author ");
+        info_body.extend_from_slice(&literal(info.author.trim()));
+    }
+    info_body.extend_from_slice(b" >>");
+    let info_id = objects.add(info_body);
+
+    // One font resource per standard font used
+    let mut fonts: BTreeMap<&'static str, (String, usize)> = BTreeMap::new();
+    for item in pages.iter().flat_map(|page| &page.items) {
+        let base = item.font.base_name();
+        if !fonts.contains_key(base) {
+            let id = objects.add(
+                format!(
+                    "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                    base
+                )
+                .into_bytes(),
+            );
+            fonts.insert(base, (format!("F{}", fonts.len() + 1), id));
+        }
+    }
+    let mut resources = String::from("<< /Font <<");
+    for (name, id) in fonts.values() {
+        let _ = write!(resources, " /{} {} 0 R", name, id);
+    }
+    resources.push_str(" >> >>");
+
+    let font_name = |font: &Font| fonts[font.base_name()].0.as_str();
+    let mut kids = Vec::new();
+    let mut elements: Vec<Element> = Vec::new();
+    // Elements of the marked content of each page, by identifier
+    let mut parent_tree: Vec<Vec<usize>> = Vec::new();
+    for (index, page) in pages.iter().enumerate() {
+        let page_id = objects.reserve();
+        let mut content = Vec::new();
+        let mut marked = Vec::new();
+        for item in &page.items {
+            match structure_type(&item.role) {
+                Some(tag) => {
+                    let continued = elements
+                        .last()
+                        .is_some_and(|element| element.role == item.role);
+                    if item.starts_block || !continued {
+                        elements.push(Element {
+                            id: objects.reserve(),
+                            role: item.role.clone(),
+                            content: Vec::new(),
+                        });
+                    }
+                    let element = elements.last_mut().expect("an element was just pushed");
+                    element.content.push((page_id, marked.len()));
+                    content.extend_from_slice(
+                        format!("/{} <</MCID {}>> BDC ", tag, marked.len()).as_bytes(),
+                    );
+                    marked.push(element.id);
+                }
+                None => content.extend_from_slice(b"/Artifact BMC "),
+            }
+            content.extend_from_slice(
+                format!(
+                    "BT /{} {:.2} Tf {:.2} {:.2} Td ",
+                    font_name(&item.font),
+                    item.size,
+                    item.x,
+                    item.y
+                )
+                .as_bytes(),
+            );
+            content.extend_from_slice(&literal(&item.text));
+            content.extend_from_slice(b" Tj ET EMC\n");
+        }
+        let content_id = objects.add(PdfObjects::stream(&content));
+        objects.set(
+            page_id,
+            format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources {} /Contents {} 0 R /StructParents {} >>",
+                pages_id, setup.width, setup.height, resources, content_id, index
+            )
+            .into_bytes(),
+        );
+        kids.push(format!("{} 0 R", page_id));
+        parent_tree.push(marked);
+    }
+
+    // Structure tree, the runs of list items in lists
+    let tree_root = objects.reserve();
+    let document = objects.reserve();
+    let mut document_kids = Vec::new();
+    let mut i = 0;
+    while i < elements.len() {
+        let run = elements[i..]
+            .iter()
+            .take_while(|element| element.role == Role::ListItem)
+            .count();
+        let (parent, members) = if run > 0 {
+            let list = objects.reserve();
+            let items: Vec<String> = elements[i..i + run]
+                .iter()
+                .map(|element| format!("{} 0 R", element.id))
+                .collect();
+            objects.set(
+                list,
+                format!(
+                    "<< /Type /StructElem /S /L /P {} 0 R /K [{}] >>",
+                    document,
+                    items.join(" ")
+                )
+                .into_bytes(),
+            );
+            document_kids.push(format!("{} 0 R", list));
+            (list, &elements[i..i + run])
+        } else {
+            document_kids.push(format!("{} 0 R", elements[i].id));
+            (document, &elements[i..i + 1])
+        };
+        for element in members {
+            let marked: Vec<String> = element
+                .content
+                .iter()
+                .map(|(page, mcid)| format!("<< /Type /MCR /Pg {} 0 R /MCID {} >>", page, mcid))
+                .collect();
+            let mut body = format!(
+                "<< /Type /StructElem /S /{} /P {} 0 R /K [{}]",
+                structure_type(&element.role).unwrap_or("P"),
+                parent,
+                marked.join(" ")
+            );
+            if let Role::Figure(alt) = &element.role {
+                if !alt.trim().is_empty() {
+                    let _ = write!(body, " /Alt {}", text_string(alt.trim()));
+                }
+            }
+            body.push_str(" >>");
+            objects.set(element.id, body.into_bytes());
+        }
+        i += members.len();
+    }
+    objects.set(
+        document,
+        format!(
+            "<< /Type /StructElem /S /Document /P {} 0 R /K [{}] >>",
+            tree_root,
+            document_kids.join(" ")
+        )
+        .into_bytes(),
+    );
+    let mut numbers = String::new();
+    for (index, marked) in parent_tree.iter().enumerate() {
+        let refs: Vec<String> = marked.iter().map(|id| format!("{} 0 R", id)).collect();
+        let _ = write!(numbers, "{} [{}] ", index, refs.join(" "));
+    }
+    let parent_tree_id = objects.add(format!("<< /Nums [{}] >>", numbers.trim_end()).into_bytes());
+    objects.set(
+        tree_root,
+        format!(
+            "<< /Type /StructTreeRoot /K {} 0 R /ParentTree {} 0 R /ParentTreeNextKey {} >>",
+            document,
+            parent_tree_id,
+            pages.len()
+        )
+        .into_bytes(),
+    );
+
+    objects.set(
+        pages_id,
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            kids.len()
+        )
+        .into_bytes(),
+    );
+    let mut catalog_body = format!(
+        "<< /Type /Catalog /Pages {} 0 R /StructTreeRoot {} 0 R /MarkInfo << /Marked true >> \
+         /ViewerPreferences << /DisplayDocTitle true >>",
+        pages_id, tree_root
+    )
+    .into_bytes();
+    if !info.language.trim().is_empty() {
+        catalog_body.extend_from_slice(b" /Lang ");
+        catalog_body.extend_from_slice(&literal(info.language.trim()));
+    }
+    catalog_body.extend_from_slice(b" >>");
+    objects.set(catalog, catalog_body);
+    objects.finish(catalog, info_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fonts::{Family, Style};
+    use crate::layout::TextItem;
+
+    #[test]
+    fn test_literal_escapes() {
+        assert_eq!(literal("a (b) \\ é"), b"(a \\(b\\) \\\\ \xE9)".to_vec());
+    }
+
+    #[test]
+    fn test_structure_follows_the_blocks() {
+        let item = |text: &str, role: Role, starts_block: bool| TextItem {
+            x: 72.0,
+            y: 700.0,
+            font: Font::new(Family::Times, Style::default()),
+            size: 12.0,
+            text: text.to_string(),
+            role,
+            starts_block,
+        };
+        let pages = [
+            Page {
+                items: vec![
+                    item("Map", Role::Figure("Harbor, é".to_string()), true),
+                    item("One", Role::ListItem, true),
+                    item("Two", Role::ListItem, true),
+                    item("1", Role::Artifact, false),
+                ],
+            },
+            Page {
+                items: vec![item("more", Role::ListItem, false)],
+            },
+        ];
+        let setup = PageSetup {
+            width: 612.0,
+            height: 792.0,
+            margin_top: 72.0,
+            margin_bottom: 72.0,
+            margin_left: 72.0,
+            margin_right: 72.0,
+        };
+        let pdf = write_pdf(&pages, &setup, &DocumentInfo::default());
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(text.contains("/Artifact BMC BT"));
+        assert!(text.contains("/Alt <FEFF0048006100720062006F0072002C002000E9>"));
+        assert!(!text.contains("/Lang"));
+        // The second item continues on the next page, in the same list
+        assert_eq!(text.matches("/S /LI").count(), 2);
+        assert_eq!(text.matches("/S /L ").count(), 1);
+        assert!(text.contains("/MCID 2 >> << /Type /MCR /Pg"));
+        assert!(text.contains("/StructParents 1"));
+    }
+
+    #[test]
+    fn test_cross_references_point_at_objects() {
+        let page = Page {
+            items: vec![TextItem {
+                x: 72.0,
+                y: 700.0,
+                font: Font::new(Family::Times, Style::default()),
+                size: 12.0,
+                text: "Hello".to_string(),
+                role: Role::Paragraph,
+                starts_block: true,
+            }],
+        };
+        let setup = PageSetup {
+            width: 612.0,
+            height: 792.0,
+            margin_top: 72.0,
+            margin_bottom: 72.0,
+            margin_left: 72.0,
+            margin_right: 72.0,
+        };
+        let info = DocumentInfo {
+            title: "T".to_string(),
+            author: "A".to_string(),
+            language: "en".to_string(),
+        };
+        let pdf = write_pdf(&[page], &setup, &info);
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("/BaseFont /Times-Roman"));
+        assert!(
+            text.contains("/P <</MCID 0>> BDC BT /F1 12.00 Tf 72.00 700.00 Td (Hello) Tj ET EMC")
+        );
+        assert!(text.contains("/Count 1"));
+
+        let startxref: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|rest| rest.lines().next())
+            .and_then(|n| n.parse().ok())
+            .unwrap();
+        let xref = String::from_utf8_lossy(&pdf[startxref..]);
+        assert!(xref.starts_with("xref"));
+        for (i, line) in xref.lines().skip(3).enumerate() {
+            let Some(offset) = line.strip_suffix(" 00000 n ") else {
+                break;
+            };
+            let offset: usize = offset.parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", i + 1).as_bytes()));
+        }
+    }
+}
@@ -0,0 +1,426 @@
+//! # Git history plugin for Cosmarium
+//!
+//! Lists the commits of the project's Git repository that changed the
+//! active document, newest first. For each, the panel shows the text of the
+//! document at that commit, the changes the commit made to it, or those
+//! made since, and can restore that version into the editor.
+//!
+//! Above them, the documents of the project changed since the last commit
+//! are listed with the words added or removed, to commit them under a
+//! message of the author's or a summary of the changes.
+//!
+//! The commits are read by the application (see
+//! [`git_history`](cosmarium_plugin_api::git_history)). Restoring a version
+//! replaces the text in the editor as a single undo step, and first keeps
+//! the text it replaces as a snapshot.
+
+use cosmarium_plugin_api::diff::Comparison;
+use cosmarium_plugin_api::git_history::{
+    CommitFile, CommitInfo, DocumentCommits, GitHistoryRequest, ProjectChanges, COMMIT_FILE_KEY,
+    DOCUMENT_COMMITS_KEY, GIT_HISTORY_REQUEST, PROJECT_CHANGES_KEY,
+};
+use cosmarium_plugin_api::grammar::text_hash;
+use cosmarium_plugin_api::locale::{DateStyle, Locale, LOCALE_KEY};
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+};
+use egui::{RichText, Ui};
+use std::sync::Arc;
+
+/// Name of the plugin and of its panel.
+pub const PLUGIN_NAME: &str = "git_history";
+
+/// Unchanged lines shown around the lines that changed.
+const DIFF_CONTEXT: usize = 3;
+
+/// How the version of a commit is shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum View {
+    /// The changes the commit made to the document
+    #[default]
+    Changes,
+    /// The changes made to the document since
+    Since,
+    /// The text of the document at the commit
+    Text,
+}
+
+#[derive(Default)]
+pub struct GitHistoryPlugin {
+    /// Commits of the active document, as published
+    commits: Option<Arc<DocumentCommits>>,
+    /// Text of the document at the selected commit, once published
+    file: Option<Arc<CommitFile>>,
+    /// Changes of the project since the last commit, as published
+    changes: Option<Arc<ProjectChanges>>,
+    /// Message of the next commit, the summary of the changes when empty
+    message: String,
+    /// Identifier of the selected commit
+    selected: Option<String>,
+    view: View,
+    /// Changes shown, with the view and commit they were compared for, and
+    /// the hash of the text now
+    comparison: Option<(View, String, u64, Comparison)>,
+}
+
+impl GitHistoryPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send `request` to the application.
+    fn request(ctx: &mut PluginContext, request: GitHistoryRequest) {
+        ctx.set_shared_state(GIT_HISTORY_REQUEST, Some(request));
+    }
+
+    /// Text of the document at the selected commit, if published.
+    fn selected_file(&self) -> Option<&CommitFile> {
+        let file = self.file.as_deref()?;
+        let commits = self.commits.as_deref()?;
+        (Some(&file.commit) == self.selected.as_ref() && file.path == commits.path).then_some(file)
+    }
+
+    /// The changes `view` shows of `file`, compared again only when the
+    /// texts changed.
+    fn compare(&mut self, view: View, file: &CommitFile, current: &str) -> &Comparison {
+        let content = file.content.as_deref().unwrap_or_default();
+        let (before, after) = match view {
+            View::Since => (content, current),
+            _ => (file.previous.as_deref().unwrap_or_default(), content),
+        };
+        // Only the changes since depend on the text now
+        let hash = if view == View::Since {
+            text_hash(current)
+        } else {
+            0
+        };
+        if self
+            .comparison
+            .as_ref()
+            .is_some_and(|(shown, commit, compared, _)| {
+                *shown != view || *commit != file.commit || *compared != hash
+            })
+        {
+            self.comparison = None;
+        }
+        let (_, _, _, comparison) = self.comparison.get_or_insert_with(|| {
+            let comparison = Comparison::new(before, after, DIFF_CONTEXT);
+            (view, file.commit.clone(), hash, comparison)
+        });
+        comparison
+    }
+
+    /// Changes of the project since the last commit, and the message to
+    /// commit them with.
+    fn render_commit(
+        &mut self,
+        ui: &mut Ui,
+        ctx: &mut PluginContext,
+        changes: &ProjectChanges,
+        locale: &Locale,
+    ) {
+        let summary = changes.message();
+        egui::CollapsingHeader::new(format!("Changes ({})", changes.documents.len()))
+            .id_salt("git_history_changes_header")
+            .default_open(true)
+            .show(ui, |ui| {
+                if changes.is_empty() {
+                    ui.weak("Nothing changed since the last commit.");
+                }
+                for document in &changes.documents {
+                    ui.horizontal(|ui| {
+                        ui.label(document.label());
+                        ui.weak(format!("{} words", signed(locale, document.word_delta())));
+                    });
+                }
+                if changes.other_files > 0 {
+                    ui.weak(format!(
+                        "{} other files",
+                        locale.format_count(changes.other_files)
+                    ));
+                }
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.message)
+                        .hint_text(summary.as_str())
+                        .desired_rows(2)
+                        .desired_width(f32::INFINITY),
+                );
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Commit")
+                        .on_hover_text(
+                            "Save the project and commit its changes, unsaved edits included, with this message",
+                        )
+                        .clicked()
+                    {
+                        let message = std::mem::take(&mut self.message);
+                        let message = match message.trim() {
+                            "" => summary.clone(),
+                            message => message.to_string(),
+                        };
+                        Self::request(ctx, GitHistoryRequest::Commit(message));
+                    }
+                    if ui
+                        .add_enabled(!changes.is_empty(), egui::Button::new("Use Summary"))
+                        .on_hover_text("Start the message from the summary of the changes")
+                        .clicked()
+                    {
+                        self.message = summary.clone();
+                    }
+                });
+            });
+    }
+
+    /// Commits that changed the document, newest first.
+    fn render_list(
+        &mut self,
+        ui: &mut Ui,
+        ctx: &mut PluginContext,
+        commits: &DocumentCommits,
+        locale: &Locale,
+    ) {
+        egui::ScrollArea::vertical()
+            .id_salt("git_history_list")
+            .max_height(180.0)
+            .show(ui, |ui| {
+                for commit in &commits.commits {
+                    let date = locale.format_date_time(
+                        commit.time.with_timezone(&chrono::Local).naive_local(),
+                        DateStyle::Short,
+                    );
+                    let selected = self.selected.as_ref() == Some(&commit.id);
+                    let button = egui::Button::selectable(selected, commit.summary.as_str())
+                        .shortcut_text(RichText::new(date).weak())
+                        .min_size(egui::vec2(ui.available_width(), 0.0));
+                    let response = ui.add(button).on_hover_text(format!(
+                        "{} · {}",
+                        commit.short_id(),
+                        commit.author
+                    ));
+                    if response.clicked() {
+                        self.select(ctx, (!selected).then_some(commit));
+                    }
+                }
+            });
+    }
+
+    /// Select `commit`, asking for the text of the document at it.
+    fn select(&mut self, ctx: &mut PluginContext, commit: Option<&CommitInfo>) {
+        self.selected = commit.map(|commit| commit.id.clone());
+        self.comparison = None;
+        if let Some(commit) = commit {
+            Self::request(ctx, GitHistoryRequest::Show(commit.id.clone()));
+        }
+    }
+
+    /// The version of the document at the selected commit.
+    fn render_version(&mut self, ui: &mut Ui, ctx: &mut PluginContext, commit: &CommitInfo) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.view, View::Changes, "Changes")
+                .on_hover_text("What this commit changed in the document");
+            ui.selectable_value(&mut self.view, View::Since, "Since")
+                .on_hover_text("What changed in the document since this commit");
+            ui.selectable_value(&mut self.view, View::Text, "Text");
+            ui.separator();
+            if ui
+                .button("Restore")
+                .on_hover_text(
+                    "Replace the text of the document with this version, keeping the text now as a snapshot",
+                )
+                .clicked()
+            {
+                Self::request(ctx, GitHistoryRequest::Restore(commit.id.clone()));
+            }
+        });
+
+        let Some(file) = self.selected_file().cloned() else {
+            ui.spinner();
+            return;
+        };
+        let Some(content) = file.content.as_deref() else {
+            ui.weak("The document cannot be read at this commit.");
+            return;
+        };
+        match self.view {
+            View::Text => {
+                egui::ScrollArea::vertical()
+                    .id_salt("git_history_text")
+                    .show(ui, |ui| {
+                        ui.label(content);
+                    });
+            }
+            view => {
+                let current = ctx
+                    .get_shared_state::<String>("markdown_editor_content")
+                    .unwrap_or_default();
+                let comparison = self.compare(view, &file, &current);
+                if comparison.is_unchanged() {
+                    ui.weak(match view {
+                        View::Since => "The text has not changed since this commit.",
+                        _ => "This commit did not change the text.",
+                    });
+                    return;
+                }
+                ui.label(format!(
+                    "{} lines removed, {} added",
+                    comparison.removed, comparison.added
+                ));
+                comparison.show(ui, "git_history_changes");
+            }
+        }
+    }
+}
+
+impl Plugin for GitHistoryPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            PLUGIN_NAME,
+            "0.1.0",
+            "Versions of a document in the Git repository of its project",
+            "Cosmarium Team",
+        )
+    }
+
+    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+}
+
+impl PanelPlugin for GitHistoryPlugin {
+    fn panel_title(&self) -> &str {
+        "Git History"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "🕓"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Right
+    }
+
+    fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        self.changes = ctx
+            .get_shared_state::<Option<Arc<ProjectChanges>>>(PROJECT_CHANGES_KEY)
+            .flatten();
+        self.commits = ctx
+            .get_shared_state::<Option<Arc<DocumentCommits>>>(DOCUMENT_COMMITS_KEY)
+            .flatten();
+        self.file = ctx
+            .get_shared_state::<Option<Arc<CommitFile>>>(COMMIT_FILE_KEY)
+            .flatten();
+        let listed = self.selected.as_ref().is_some_and(|id| {
+            self.commits
+                .as_ref()
+                .is_some_and(|commits| commits.get(id).is_some())
+        });
+        if !listed {
+            self.selected = None;
+            self.comparison = None;
+        }
+        Ok(())
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        let locale = ctx
+            .get_shared_state::<Locale>(LOCALE_KEY)
+            .unwrap_or_default();
+        if let Some(changes) = self.changes.clone() {
+            self.render_commit(ui, ctx, &changes, &locale);
+            ui.separator();
+        }
+        let Some(commits) = self.commits.clone() else {
+            ui.label("Open a document of a project kept in Git to see its history.");
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} commits changed this document",
+                locale.format_count(commits.commits.len())
+            ));
+            if ui.small_button("⟳").on_hover_text("Refresh").clicked() {
+                Self::request(ctx, GitHistoryRequest::Refresh);
+            }
+        });
+        ui.separator();
+        if commits.commits.is_empty() {
+            ui.weak("No commit changed this document yet.");
+            return;
+        }
+        self.render_list(ui, ctx, &commits, &locale);
+
+        let Some(commit) = self.selected.as_deref().and_then(|id| commits.get(id)) else {
+            return;
+        };
+        ui.separator();
+        self.render_version(ui, ctx, commit);
+    }
+}
+
+/// `number`, with a plus sign when positive.
+fn signed(locale: &Locale, number: i64) -> String {
+    let sign = if number > 0 { "+" } else { "" };
+    format!("{}{}", sign, locale.format_number(number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(id: &str, parent: Option<&str>) -> CommitInfo {
+        CommitInfo {
+            id: id.to_string(),
+            summary: format!("Commit {}", id),
+            author: "Ann".to_string(),
+            time: chrono::Utc::now(),
+            parent: parent.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_selected_version_compares_with_its_parent_and_now() {
+        let mut ctx = PluginContext::new();
+        let mut plugin = GitHistoryPlugin::new();
+        let commits = DocumentCommits {
+            path: "/novel/content/inn.md".into(),
+            commits: vec![commit("b2", Some("a1")), commit("a1", None)],
+        };
+        ctx.set_shared_state(DOCUMENT_COMMITS_KEY, Some(Arc::new(commits.clone())));
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+
+        plugin.select(&mut ctx, commits.get("b2"));
+        assert_eq!(
+            ctx.get_shared_state::<Option<GitHistoryRequest>>(GIT_HISTORY_REQUEST),
+            Some(Some(GitHistoryRequest::Show("b2".to_string())))
+        );
+        assert!(plugin.selected_file().is_none());
+
+        let file = CommitFile {
+            commit: "b2".to_string(),
+            path: commits.path.clone(),
+            content: Some("It rained.\nIt poured.".to_string()),
+            previous: Some("It rained.".to_string()),
+        };
+        ctx.set_shared_state(COMMIT_FILE_KEY, Some(Arc::new(file.clone())));
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert_eq!(plugin.selected_file(), Some(&file));
+
+        let changes = plugin.compare(View::Changes, &file, "");
+        assert_eq!((changes.removed, changes.added), (0, 1));
+        let since = plugin.compare(View::Since, &file, "It rained.");
+        assert_eq!((since.removed, since.added), (1, 0));
+
+        // Another document
+        ctx.set_shared_state(
+            DOCUMENT_COMMITS_KEY,
+            Some(Arc::new(DocumentCommits::default())),
+        );
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(plugin.selected.is_none());
+    }
+}
@@ -0,0 +1,381 @@
+//! Client of the LanguageTool HTTP API.
+//!
+//! The text is posted to `/v2/check` as annotated data: Markdown syntax and
+//! code are sent as markup, which LanguageTool keeps for the offsets but
+//! does not check. The matches come back as [`GrammarIssue`]s over byte
+//! ranges of the text.
+
+use crate::GrammarSettings;
+use anyhow::{Context, Result};
+use cosmarium_plugin_api::grammar::{GrammarIssue, IssueKind};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::time::Duration;
+
+/// How long to wait for the server's answer.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Rule categories of LanguageTool offered in the settings, with their
+/// names.
+pub const CATEGORIES: &[(&str, &str)] = &[
+    ("GRAMMAR", "Grammar"),
+    ("PUNCTUATION", "Punctuation"),
+    ("CASING", "Capitalization"),
+    ("CONFUSED_WORDS", "Easily confused words"),
+    ("REDUNDANCY", "Redundant phrases"),
+    ("STYLE", "Style"),
+    ("TYPOGRAPHY", "Typography"),
+    ("SEMANTICS", "Semantics"),
+    ("TYPOS", "Possible typos"),
+];
+
+/// Part of the text sent to LanguageTool.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+enum Annotation<'a> {
+    /// Prose to check
+    Text { text: &'a str },
+    /// Syntax to skip
+    Markup { markup: &'a str },
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckResponse {
+    matches: Vec<Match>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Match {
+    message: String,
+    offset: usize,
+    length: usize,
+    #[serde(default)]
+    replacements: Vec<Replacement>,
+    rule: Rule,
+}
+
+#[derive(Debug, Deserialize)]
+struct Replacement {
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: String,
+    #[serde(default)]
+    issue_type: String,
+    category: Category,
+}
+
+#[derive(Debug, Deserialize)]
+struct Category {
+    id: String,
+}
+
+/// Check a text with the LanguageTool server of the settings.
+///
+/// # Errors
+///
+/// Returns an error if the server cannot be reached or its answer cannot
+/// be read.
+pub fn check(settings: &GrammarSettings, text: &str) -> Result<Vec<GrammarIssue>> {
+    let url = format!("{}/v2/check", settings.server_url.trim_end_matches('/'));
+    let data = serde_json::json!({ "annotation": annotate(text) }).to_string();
+    let mut form = vec![("data", data), ("language", settings.language.clone())];
+    if !settings.categories.is_empty() {
+        form.push(("enabledCategories", settings.categories.join(",")));
+        form.push(("enabledOnly", "true".to_string()));
+    }
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    let response = client
+        .post(&url)
+        .form(&form)
+        .send()
+        .with_context(|| format!("Could not reach LanguageTool at {}", settings.server_url))?;
+    if !response.status().is_success() {
+        anyhow::bail!("LanguageTool answered with HTTP {}", response.status());
+    }
+
+    parse_matches(text, &response.text()?)
+}
+
+/// Read the issues of a text out of LanguageTool's answer.
+///
+/// Matches whose offsets fall outside the text or inside a character are
+/// left out.
+pub fn parse_matches(text: &str, body: &str) -> Result<Vec<GrammarIssue>> {
+    let response: CheckResponse =
+        serde_json::from_str(body).context("Unexpected answer from LanguageTool")?;
+    let index = Utf16Index::new(text);
+
+    Ok(response
+        .matches
+        .into_iter()
+        .filter_map(|found| {
+            let range = index.byte(found.offset)?..index.byte(found.offset + found.length)?;
+            Some(GrammarIssue {
+                range,
+                message: found.message,
+                kind: issue_kind(&found.rule),
+                rule: found.rule.id,
+                replacements: found
+                    .replacements
+                    .into_iter()
+                    .map(|replacement| replacement.value)
+                    .collect(),
+            })
+        })
+        .collect())
+}
+
+/// The kind of issue a rule reports.
+fn issue_kind(rule: &Rule) -> IssueKind {
+    match (rule.issue_type.as_str(), rule.category.id.as_str()) {
+        ("misspelling", _) | (_, "TYPOS") => IssueKind::Spelling,
+        ("style" | "register" | "duplication", _)
+        | (_, "STYLE" | "REDUNDANCY" | "TYPOGRAPHY" | "PLAIN_ENGLISH") => IssueKind::Style,
+        _ => IssueKind::Grammar,
+    }
+}
+
+/// Byte offsets of the UTF-16 code units LanguageTool counts in.
+struct Utf16Index(Vec<Option<usize>>);
+
+impl Utf16Index {
+    fn new(text: &str) -> Self {
+        let mut offsets = Vec::with_capacity(text.len() + 1);
+        for (i, c) in text.char_indices() {
+            offsets.push(Some(i));
+            // The second half of a surrogate pair is not a boundary
+            offsets.extend(std::iter::repeat_n(None, c.len_utf16() - 1));
+        }
+        offsets.push(Some(text.len()));
+        Self(offsets)
+    }
+
+    fn byte(&self, unit: usize) -> Option<usize> {
+        self.0.get(unit).copied().flatten()
+    }
+}
+
+/// Split a Markdown text into the prose to check and the markup to skip.
+fn annotate(text: &str) -> Vec<Annotation<'_>> {
+    let mut parts = Vec::new();
+    let mut at = 0;
+    for span in markup_spans(text) {
+        if span.start > at {
+            parts.push(Annotation::Text {
+                text: &text[at..span.start],
+            });
+        }
+        parts.push(Annotation::Markup {
+            markup: &text[span.clone()],
+        });
+        at = span.end;
+    }
+    if at < text.len() {
+        parts.push(Annotation::Text { text: &text[at..] });
+    }
+    parts
+}
+
+/// Byte ranges of the Markdown syntax and code of a text, in order.
+fn markup_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    let mut in_fence = false;
+
+    for line in text.split_inclusive('\n') {
+        let body = line.trim_end_matches(['\n', '\r']);
+        let trimmed = body.trim_start();
+        let fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+        if fence || in_fence {
+            if !body.is_empty() {
+                spans.push(offset..offset + body.len());
+            }
+            in_fence ^= fence;
+        } else if !trimmed.is_empty()
+            && trimmed.len() >= 3
+            && trimmed.chars().all(|c| matches!(c, '-' | '*' | '_' | ' '))
+        {
+            // Thematic break
+            spans.push(offset..offset + body.len());
+        } else {
+            let prefix = block_prefix(body);
+            if prefix > 0 {
+                spans.push(offset..offset + prefix);
+            }
+            inline_spans(&body[prefix..], offset + prefix, &mut spans);
+        }
+        offset += line.len();
+    }
+
+    spans
+}
+
+/// Length of the heading, quote and list markers starting a line.
+fn block_prefix(line: &str) -> usize {
+    let mut rest = line.trim_start();
+    loop {
+        let before = rest.len();
+        if let Some(quoted) = rest.strip_prefix('>') {
+            rest = quoted.trim_start();
+        } else if rest.starts_with('#') {
+            let title = rest.trim_start_matches('#');
+            if title.is_empty() || title.starts_with(' ') {
+                rest = title.trim_start();
+            }
+        } else if let Some(item) = rest
+            .strip_prefix(['-', '*', '+'])
+            .filter(|item| item.starts_with(' '))
+        {
+            rest = item.trim_start();
+            for checkbox in ["[ ] ", "[x] ", "[X] "] {
+                rest = rest.strip_prefix(checkbox).unwrap_or(rest);
+            }
+        } else {
+            let number = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+            if number.len() < rest.len() {
+                if let Some(item) = number
+                    .strip_prefix(['.', ')'])
+                    .filter(|item| item.starts_with(' '))
+                {
+                    rest = item.trim_start();
+                }
+            }
+        }
+        if rest.len() == before {
+            break;
+        }
+    }
+    line.len() - rest.len()
+}
+
+/// Add the inline syntax of a line: code, link brackets and destinations,
+/// HTML tags and comments, and emphasis markers.
+fn inline_spans(line: &str, offset: usize, spans: &mut Vec<Range<usize>>) {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &line[i..];
+        let len = if let Some(code) = rest.strip_prefix('`') {
+            code.find('`').map(|end| end + 2)
+        } else if rest.starts_with("[[") || rest.starts_with("]]") {
+            Some(2)
+        } else if rest.starts_with("](") {
+            rest.find(')').map(|end| end + 1)
+        } else if rest.starts_with('[') {
+            // Opening bracket of a link, if it has a destination
+            rest.find(']')
+                .filter(|end| rest[*end..].starts_with("]("))
+                .map(|_| 1)
+        } else if rest.starts_with("<!--") {
+            Some(rest.find("-->").map_or(rest.len(), |end| end + 3))
+        } else if rest.starts_with('<')
+            && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/')
+        {
+            rest.find('>').map(|end| end + 1)
+        } else if rest.starts_with(['*', '~']) {
+            let marker = rest.as_bytes()[0];
+            Some(rest.bytes().take_while(|b| *b == marker).count())
+        } else if rest.starts_with('_') {
+            // Not within a word like snake_case
+            let run = rest.bytes().take_while(|b| *b == b'_').count();
+            let before = line[..i].chars().next_back();
+            let after = rest[run..].chars().next();
+            let inside = before.is_some_and(char::is_alphanumeric)
+                && after.is_some_and(char::is_alphanumeric);
+            (!inside).then_some(run)
+        } else {
+            None
+        };
+
+        match len {
+            Some(len) if len > 0 => {
+                match spans.last_mut() {
+                    Some(last) if last.end == offset + i => last.end += len,
+                    _ => spans.push(offset + i..offset + i + len),
+                }
+                i += len;
+            }
+            _ => i += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markup(text: &str) -> Vec<&str> {
+        markup_spans(text)
+            .into_iter()
+            .map(|span| &text[span])
+            .collect()
+    }
+
+    #[test]
+    fn test_markup_spans() {
+        let text = "# The *Inn*\n\n- [ ] Ask [Mara](notes/mara.md) about `x` and [[Velmari]]\n\
+                    ```\nlet code = 1;\n```\nsnake_case stays, _this_ goes.";
+        assert_eq!(
+            markup(text),
+            vec![
+                "# ",
+                "*",
+                "*",
+                "- [ ] ",
+                "[",
+                "](notes/mara.md)",
+                "`x`",
+                "[[",
+                "]]",
+                "```",
+                "let code = 1;",
+                "```",
+                "_",
+                "_",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_annotation_covers_the_text() {
+        let text = "A **bold** claim.\n> Quoted";
+        let joined: String = annotate(text)
+            .into_iter()
+            .map(|part| match part {
+                Annotation::Text { text } | Annotation::Markup { markup: text } => text,
+            })
+            .collect();
+        assert_eq!(joined, text);
+    }
+
+    #[test]
+    fn test_parse_matches_converts_utf16_offsets() {
+        // “🐉” takes two UTF-16 code units and four bytes
+        let text = "🐉 She have a cat.";
+        let body = r#"{"matches": [{
+            "message": "Use “has”.",
+            "offset": 7,
+            "length": 4,
+            "replacements": [{"value": "has"}],
+            "rule": {"id": "HE_VERB_AGR", "issueType": "grammar", "category": {"id": "GRAMMAR"}}
+        }, {
+            "message": "Out of the text.",
+            "offset": 90,
+            "length": 1,
+            "rule": {"id": "X", "category": {"id": "STYLE"}}
+        }]}"#;
+
+        let issues = parse_matches(text, body).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(&text[issues[0].range.clone()], "have");
+        assert_eq!(issues[0].kind, IssueKind::Grammar);
+        assert_eq!(issues[0].replacements, vec!["has"]);
+    }
+}
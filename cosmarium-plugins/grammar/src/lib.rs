@@ -0,0 +1,401 @@
+//! # Grammar checking plugin for Cosmarium
+//!
+//! Sends the text of the editor to a LanguageTool server, local or remote,
+//! a moment after the author stops typing, and publishes the grammar and
+//! style issues it finds for the editor to underline. The panel lists the
+//! issues with their explanation and offers their fixes in one click.
+//!
+//! The server address, the language and the rule categories checked are
+//! settings of the plugin, kept under its name in the application's plugin
+//! settings.
+
+pub mod languagetool;
+
+use cosmarium_plugin_api::grammar::{
+    text_hash, GrammarFix, GrammarIssue, GrammarReport, IssueKind, GRAMMAR_FIX_REQUEST,
+    GRAMMAR_ISSUES_KEY,
+};
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result, TaskHandle,
+};
+use egui::Ui;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Name of the plugin and of its panel.
+pub const PLUGIN_NAME: &str = "grammar";
+
+/// Configuration key for [`GrammarSettings`], under the plugin's name.
+pub const CONFIG_KEY: &str = PLUGIN_NAME;
+
+/// Delay after an edit before the text is checked again.
+const CHECK_DELAY: Duration = Duration::from_millis(1500);
+
+/// Delay before trying a server that failed to answer again.
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Maximum number of fixes offered for an issue.
+const MAX_FIXES: usize = 5;
+
+/// Settings of the grammar checker.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GrammarSettings {
+    /// Whether the text is sent for checking
+    pub enabled: bool,
+    /// Address of the LanguageTool server
+    pub server_url: String,
+    /// Language of the text, `auto` for the server to detect it
+    pub language: String,
+    /// Rule categories checked, all of the server's when empty
+    pub categories: Vec<String>,
+}
+
+impl Default for GrammarSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            server_url: "http://localhost:8081".to_string(),
+            language: "auto".to_string(),
+            // Typos are left to the spell checker
+            categories: languagetool::CATEGORIES
+                .iter()
+                .map(|(id, _)| id.to_string())
+                .filter(|id| id != "TYPOS")
+                .collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GrammarPlugin {
+    /// Settings of the checker
+    settings: GrammarSettings,
+    /// Hash of the editor's text and when it was first seen
+    edited: Option<(u64, Instant)>,
+    /// Hash of the text last sent for checking
+    checked: Option<u64>,
+    /// Check in progress, with the hash of its text
+    task: Option<(u64, TaskHandle<anyhow::Result<Vec<GrammarIssue>>>)>,
+    /// Why the last check failed, and when
+    error: Option<(String, Instant)>,
+}
+
+impl GrammarPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publish the issues of a check, or keep the error.
+    fn poll_check(&mut self, ctx: &mut PluginContext) {
+        let Some(result) = self.task.as_mut().and_then(|(_, task)| task.try_take()) else {
+            return;
+        };
+        let Some((hash, _)) = self.task.take() else {
+            return;
+        };
+
+        match result.and_then(|issues| issues) {
+            Ok(issues) => {
+                self.error = None;
+                ctx.set_shared_state(
+                    GRAMMAR_ISSUES_KEY,
+                    Some(GrammarReport {
+                        text_hash: hash,
+                        issues,
+                    }),
+                );
+            }
+            Err(e) => {
+                tracing::warn!("Grammar check failed: {:#}", e);
+                self.error = Some((format!("{:#}", e), ctx.clock().instant()));
+            }
+        }
+    }
+
+    /// Check the editor's text once it has rested for a moment.
+    fn schedule_check(&mut self, ctx: &mut PluginContext) {
+        let Some(content) = ctx.get_shared_state::<String>("markdown_editor_content") else {
+            return;
+        };
+        let hash = text_hash(&content);
+        let since = match self.edited {
+            Some((edited, since)) if edited == hash => since,
+            _ => {
+                self.edited = Some((hash, ctx.clock().instant()));
+                return;
+            }
+        };
+
+        let clock = ctx.clock();
+        let resting = clock.since(since) >= CHECK_DELAY;
+        let retry = self
+            .error
+            .as_ref()
+            .is_none_or(|(_, failed)| clock.since(*failed) >= RETRY_DELAY);
+        if !resting || !retry || self.task.is_some() || self.checked == Some(hash) {
+            return;
+        }
+
+        let settings = self.settings.clone();
+        let task = ctx.spawn_blocking(move || languagetool::check(&settings, &content));
+        self.task = Some((hash, task));
+        self.checked = Some(hash);
+    }
+
+    /// Save the settings and check the text again with them.
+    fn settings_changed(&mut self, ctx: &mut PluginContext) {
+        ctx.set_config(CONFIG_KEY, &self.settings);
+        self.checked = None;
+        self.error = None;
+        if !self.settings.enabled {
+            ctx.set_shared_state::<Option<GrammarReport>>(GRAMMAR_ISSUES_KEY, None);
+        }
+    }
+
+    fn render_settings(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        egui::Grid::new("grammar_settings")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Server");
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut self.settings.server_url)
+                            .hint_text("http://localhost:8081"),
+                    )
+                    .on_hover_text("Address of a LanguageTool server, on this computer or online")
+                    .lost_focus();
+                ui.end_row();
+
+                ui.label("Language");
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut self.settings.language).desired_width(80.0),
+                    )
+                    .on_hover_text("Language code such as en-US or fr, or auto to detect it")
+                    .lost_focus();
+                ui.end_row();
+            });
+
+        ui.label("Check for");
+        for (id, name) in languagetool::CATEGORIES {
+            let mut checked = self.settings.categories.iter().any(|c| c == id);
+            if ui.checkbox(&mut checked, *name).changed() {
+                if checked {
+                    self.settings.categories.push(id.to_string());
+                } else {
+                    self.settings.categories.retain(|c| c != id);
+                }
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn render_issues(
+        &self,
+        ui: &mut Ui,
+        ctx: &mut PluginContext,
+        content: &str,
+        report: &GrammarReport,
+    ) {
+        let mut fix = None;
+        let mut goto = None;
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for issue in &report.issues {
+                let Some(text) = content.get(issue.range.clone()) else {
+                    continue;
+                };
+                let color = match issue.kind {
+                    IssueKind::Grammar => ui.visuals().warn_fg_color,
+                    IssueKind::Style => ui.visuals().hyperlink_color,
+                    IssueKind::Spelling => ui.visuals().error_fg_color,
+                };
+
+                ui.group(|ui| {
+                    if ui
+                        .add(
+                            egui::Label::new(egui::RichText::new(text).strong().color(color))
+                                .sense(egui::Sense::click()),
+                        )
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        goto = Some(content[..issue.range.start].matches('\n').count() + 1);
+                    }
+                    ui.label(&issue.message);
+                    ui.horizontal_wrapped(|ui| {
+                        for replacement in issue.replacements.iter().take(MAX_FIXES) {
+                            let label = if replacement.is_empty() {
+                                "(remove)"
+                            } else {
+                                replacement.as_str()
+                            };
+                            if ui.small_button(label).clicked() {
+                                fix = Some(GrammarFix {
+                                    text_hash: report.text_hash,
+                                    range: issue.range.clone(),
+                                    replacement: replacement.clone(),
+                                });
+                            }
+                        }
+                    });
+                });
+            }
+        });
+
+        if let Some(fix) = fix {
+            ctx.set_shared_state(GRAMMAR_FIX_REQUEST, Some(fix));
+        }
+        if let Some(line) = goto {
+            ctx.set_shared_state("markdown_editor_goto_line", line);
+        }
+    }
+}
+
+impl Plugin for GrammarPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            PLUGIN_NAME,
+            "0.1.0",
+            "Grammar and style checking with LanguageTool",
+            "Cosmarium Team",
+        )
+        .with_dependency("markdown-editor")
+    }
+
+    fn initialize(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        if let Some(settings) = ctx.get_config::<GrammarSettings>(CONFIG_KEY) {
+            self.settings = settings;
+        } else {
+            ctx.set_config(CONFIG_KEY, &self.settings);
+        }
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+
+    fn update(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl PanelPlugin for GrammarPlugin {
+    fn panel_title(&self) -> &str {
+        "Grammar"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "✍"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Right
+    }
+
+    fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        self.poll_check(ctx);
+        if self.settings.enabled {
+            self.schedule_check(ctx);
+        }
+        Ok(())
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        let mut changed = ui
+            .checkbox(&mut self.settings.enabled, "Check grammar and style")
+            .changed();
+        egui::CollapsingHeader::new("Settings")
+            .id_salt("grammar_settings_header")
+            .show(ui, |ui| {
+                changed |= self.render_settings(ui);
+            });
+        if changed {
+            self.settings_changed(ctx);
+        }
+        ui.separator();
+
+        if !self.settings.enabled {
+            ui.weak("Grammar checking is off.");
+            return;
+        }
+        if let Some((error, _)) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        let content = ctx
+            .get_shared_state::<String>("markdown_editor_content")
+            .unwrap_or_default();
+        let report = ctx
+            .get_shared_state::<Option<GrammarReport>>(GRAMMAR_ISSUES_KEY)
+            .flatten()
+            .filter(|report| report.text_hash == text_hash(&content));
+        match report {
+            Some(report) if report.issues.is_empty() => {
+                ui.label("No issues found");
+            }
+            Some(report) => {
+                ui.label(format!("{} issues", report.issues.len()));
+                self.render_issues(ui, ctx, &content, &report);
+            }
+            None if self.error.is_none() => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.weak("Checking…");
+                });
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmarium_plugin_api::clock::Clock;
+
+    #[test]
+    fn test_settings_roundtrip_through_config() {
+        let mut ctx = PluginContext::new();
+        let mut plugin = GrammarPlugin::new();
+        plugin.initialize(&mut ctx).unwrap();
+        assert!(!plugin.settings.enabled);
+        assert!(!plugin.settings.categories.iter().any(|c| c == "TYPOS"));
+
+        plugin.settings.enabled = true;
+        plugin.settings.server_url = "https://api.languagetool.org".to_string();
+        plugin.settings_changed(&mut ctx);
+
+        let mut restored = GrammarPlugin::new();
+        restored.initialize(&mut ctx).unwrap();
+        assert_eq!(restored.settings, plugin.settings);
+    }
+
+    #[test]
+    fn test_check_waits_for_the_text_to_rest() {
+        let mut ctx = PluginContext::new();
+        ctx.set_clock(Clock::manual(Clock::EPOCH));
+        let mut plugin = GrammarPlugin::new();
+        plugin.settings.enabled = true;
+        ctx.set_shared_state("markdown_editor_content", "She have a cat.".to_string());
+
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        ctx.clock().advance(CHECK_DELAY / 2);
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(plugin.task.is_none());
+
+        // Rested long enough: the check starts, though without an executor
+        // it fails right away
+        let hash = text_hash("She have a cat.");
+        ctx.clock().advance(CHECK_DELAY / 2);
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert_eq!(plugin.checked, Some(hash));
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(plugin.task.is_none());
+        assert!(plugin.error.is_some());
+    }
+}
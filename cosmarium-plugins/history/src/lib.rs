@@ -0,0 +1,240 @@
+//! # Writing history plugin for Cosmarium
+//!
+//! A dashboard of the words written over time, read from the history the
+//! application publishes: a calendar heatmap of the last year, the streak of
+//! days in a row with words written, the totals of the last week and month,
+//! and the documents the words went to. It shows the open project, or all
+//! projects together.
+
+use chrono::{Datelike, Days, NaiveDate};
+use cosmarium_plugin_api::history::{WritingHistory, WRITING_HISTORY_KEY};
+use cosmarium_plugin_api::locale::{DateStyle, Locale, LOCALE_KEY};
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+};
+use egui::Ui;
+use std::collections::BTreeMap;
+
+/// Weeks shown in the heatmap.
+const WEEKS: u64 = 53;
+
+/// Side of a day in the heatmap, and the gap between days.
+const CELL: f32 = 10.0;
+const GAP: f32 = 2.0;
+
+/// Documents listed in the breakdown.
+const MAX_DOCUMENTS: usize = 20;
+
+#[derive(Default)]
+pub struct HistoryPlugin {
+    /// Whether to count all projects rather than the open one
+    all_projects: bool,
+}
+
+impl HistoryPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Days of the last year, laid out in weeks from Monday to Sunday, with
+    /// the words written on each.
+    fn render_heatmap(
+        ui: &mut Ui,
+        days: &BTreeMap<NaiveDate, i64>,
+        today: NaiveDate,
+        locale: &Locale,
+    ) {
+        let start = heatmap_start(today);
+        let most = days
+            .range(start..=today)
+            .map(|(_, w)| *w)
+            .max()
+            .unwrap_or(0);
+        let size = egui::vec2(WEEKS as f32 * (CELL + GAP), 7.0 * (CELL + GAP));
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+
+        let empty = ui.visuals().faint_bg_color;
+        let full = ui.visuals().selection.bg_fill;
+        let cell_at = |date: NaiveDate| {
+            let week = (date - start).num_days() / 7;
+            let day = date.weekday().num_days_from_monday();
+            let min = rect.min + egui::vec2(week as f32, day as f32) * (CELL + GAP);
+            egui::Rect::from_min_size(min, egui::vec2(CELL, CELL))
+        };
+
+        let painter = ui.painter_at(rect);
+        let mut hovered = None;
+        for date in start.iter_days().take_while(|date| *date <= today) {
+            let words = days.get(&date).copied().unwrap_or(0);
+            let color = match heat_level(words, most) {
+                0 => empty,
+                level => full.gamma_multiply(level as f32 / 4.0),
+            };
+            let cell = cell_at(date);
+            painter.rect_filled(cell, 2.0, color);
+            if response.hover_pos().is_some_and(|pos| cell.contains(pos)) {
+                hovered = Some((date, words));
+            }
+        }
+        if let Some((date, words)) = hovered {
+            response.on_hover_text(format!(
+                "{}: {} words",
+                locale.format_date(date, DateStyle::Full),
+                locale.format_number(words)
+            ));
+        }
+    }
+}
+
+/// Monday of the first week of the heatmap ending on `today`.
+fn heatmap_start(today: NaiveDate) -> NaiveDate {
+    let weekday = today.weekday().num_days_from_monday() as u64;
+    today - Days::new(weekday + (WEEKS - 1) * 7)
+}
+
+/// Shade of a day with `words` written, from 0 for none to 4 for the most
+/// of the period.
+fn heat_level(words: i64, most: i64) -> usize {
+    if words <= 0 || most <= 0 {
+        return 0;
+    }
+    1 + (3 * words.min(most) / most) as usize
+}
+
+/// Words written in the `count` days up to `today`.
+fn last_days(days: &BTreeMap<NaiveDate, i64>, today: NaiveDate, count: u64) -> i64 {
+    let first = today - Days::new(count - 1);
+    days.range(first..=today).map(|(_, words)| words).sum()
+}
+
+impl Plugin for HistoryPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            "history",
+            "0.1.0",
+            "Words written by day, streaks and documents worked on",
+            "Cosmarium Team",
+        )
+    }
+
+    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+}
+
+impl PanelPlugin for HistoryPlugin {
+    fn panel_title(&self) -> &str {
+        "Writing History"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "📈"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Bottom
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        let history = ctx
+            .get_shared_state::<WritingHistory>(WRITING_HISTORY_KEY)
+            .unwrap_or_default();
+        let today = ctx.clock().now().with_timezone(&chrono::Local).date_naive();
+        let locale = ctx
+            .get_shared_state::<Locale>(LOCALE_KEY)
+            .unwrap_or_default();
+
+        if history.project.is_some() {
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.all_projects, false, "This project");
+                ui.radio_value(&mut self.all_projects, true, "All projects");
+            });
+        }
+        let project = history.project.as_deref().filter(|_| !self.all_projects);
+        let Some(days) = history.log.daily(project).filter(|days| !days.is_empty()) else {
+            ui.label("No words written yet. They are counted as you write.");
+            return;
+        };
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let streak = history.log.streak(project, today);
+                ui.heading(format!("🔥 {}", locale.format_count(streak)))
+                    .on_hover_text(
+                        "Days in a row with words written, today included once you write",
+                    );
+                ui.label(if streak == 1 { "day" } else { "days" });
+                ui.separator();
+                let today_words = days.get(&today).copied().unwrap_or(0);
+                ui.label(format!("Today: {}", locale.format_number(today_words)));
+                ui.separator();
+                let week = last_days(days, today, 7);
+                ui.label(format!("Last 7 days: {}", locale.format_number(week)));
+                ui.separator();
+                let month = last_days(days, today, 30);
+                ui.label(format!("Last 30 days: {}", locale.format_number(month)));
+            });
+            ui.add_space(4.0);
+            Self::render_heatmap(ui, days, today, &locale);
+
+            let Some(project) = project else {
+                return;
+            };
+            let documents = history.log.documents(project);
+            let most = documents.first().map_or(0, |(_, words)| *words);
+            if most <= 0 {
+                return;
+            }
+            ui.separator();
+            ui.label("Words written by document");
+            for (path, words) in documents.into_iter().take(MAX_DOCUMENTS) {
+                ui.add(
+                    egui::ProgressBar::new(words.max(0) as f32 / most as f32).text(format!(
+                        "{}: {}",
+                        path.display(),
+                        locale.format_number(words)
+                    )),
+                );
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_heatmap_starts_on_a_monday_a_year_back() {
+        // A Wednesday
+        let today = day(2024, 11, 20);
+        let start = heatmap_start(today);
+        assert_eq!(start.weekday(), chrono::Weekday::Mon);
+        assert_eq!((today - start).num_days(), 52 * 7 + 2);
+    }
+
+    #[test]
+    fn test_heat_levels_and_totals() {
+        assert_eq!(heat_level(0, 1000), 0);
+        assert_eq!(heat_level(-50, 1000), 0);
+        assert_eq!(heat_level(10, 1000), 1);
+        assert_eq!(heat_level(500, 1000), 2);
+        assert_eq!(heat_level(1000, 1000), 4);
+
+        let days = BTreeMap::from([
+            (day(2024, 11, 1), 1000),
+            (day(2024, 11, 14), 200),
+            (day(2024, 11, 20), 300),
+        ]);
+        assert_eq!(last_days(&days, day(2024, 11, 20), 7), 500);
+        assert_eq!(last_days(&days, day(2024, 11, 20), 30), 1500);
+    }
+}
@@ -0,0 +1,274 @@
+//! # Inspector plugin for Cosmarium
+//!
+//! Shows the metadata of the active scene or chapter and edits it: its
+//! synopsis, point-of-view character, revision status, label color and
+//! target word count. The application stores the changes in the project
+//! structure, where the binder, the outline and the compile settings read
+//! them.
+//!
+//! The point-of-view field suggests the character entries of the
+//! worldbuilding wiki.
+
+use cosmarium_plugin_api::locale::{Locale, LOCALE_KEY};
+use cosmarium_plugin_api::metadata::{
+    NodeMetadata, SceneMetadata, Status, ACTIVE_METADATA_KEY, METADATA_UPDATE_REQUEST,
+};
+use cosmarium_plugin_api::wiki::{LinkResolver, LINK_RESOLVER_KEY};
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+};
+use egui::Ui;
+
+/// Label color of a node given a label.
+const DEFAULT_LABEL: [u8; 3] = [0x4a, 0x90, 0xd9];
+
+/// Target word count of a node given a target.
+const DEFAULT_TARGET: usize = 2000;
+
+#[derive(Default)]
+pub struct InspectorPlugin {
+    /// Node being edited, with the metadata as edited
+    editing: Option<NodeMetadata>,
+}
+
+impl InspectorPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follow the node of the active document, `published` by the
+    /// application.
+    ///
+    /// The edits of a node are kept while it stays active, so that the
+    /// values the application normalizes when storing them do not reach the
+    /// field being typed in.
+    fn follow(&mut self, published: Option<NodeMetadata>) {
+        let same = match (&self.editing, &published) {
+            (Some(editing), Some(published)) => editing.node == published.node,
+            (None, None) => true,
+            _ => false,
+        };
+        if !same {
+            self.editing = published;
+        }
+    }
+
+    fn render_metadata(
+        ui: &mut Ui,
+        metadata: &mut SceneMetadata,
+        characters: &[String],
+        words: usize,
+        locale: &Locale,
+    ) {
+        ui.label("Synopsis");
+        ui.add(
+            egui::TextEdit::multiline(&mut metadata.synopsis)
+                .desired_rows(4)
+                .desired_width(f32::INFINITY)
+                .hint_text("What happens in this scene"),
+        );
+
+        egui::Grid::new("inspector_fields")
+            .num_columns(2)
+            .spacing([8.0, 6.0])
+            .show(ui, |ui| {
+                ui.label("POV:");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut metadata.pov)
+                            .desired_width(140.0)
+                            .hint_text("Character"),
+                    );
+                    if !characters.is_empty() {
+                        ui.menu_button("▾", |ui| {
+                            for name in characters {
+                                if ui.selectable_label(metadata.pov == *name, name).clicked() {
+                                    metadata.pov = name.clone();
+                                    ui.close();
+                                }
+                            }
+                        })
+                        .response
+                        .on_hover_text("Characters of the wiki");
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Status:");
+                egui::ComboBox::from_id_salt("inspector_status")
+                    .selected_text(metadata.status.label())
+                    .show_ui(ui, |ui| {
+                        for status in Status::ALL {
+                            ui.selectable_value(&mut metadata.status, status, status.label());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Label:");
+                ui.horizontal(|ui| {
+                    let mut labeled = metadata.label.is_some();
+                    if ui.checkbox(&mut labeled, "").changed() {
+                        metadata.label = labeled.then_some(DEFAULT_LABEL);
+                    }
+                    if let Some(color) = &mut metadata.label {
+                        ui.color_edit_button_srgb(color);
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Target:");
+                ui.horizontal(|ui| {
+                    let mut targeted = metadata.target_words.is_some();
+                    if ui.checkbox(&mut targeted, "").changed() {
+                        metadata.target_words = targeted.then_some(DEFAULT_TARGET);
+                    }
+                    if let Some(target) = &mut metadata.target_words {
+                        ui.add(egui::DragValue::new(target).range(1..=1_000_000).speed(50));
+                        ui.label("words");
+                    }
+                });
+                ui.end_row();
+            });
+
+        if let (Some(progress), Some(target)) = (metadata.progress(words), metadata.target_words) {
+            ui.add(
+                egui::ProgressBar::new(progress)
+                    .text(format!(
+                        "{} / {} words",
+                        locale.format_count(words),
+                        locale.format_count(target)
+                    ))
+                    .desired_width(ui.available_width()),
+            );
+        }
+    }
+}
+
+/// Names of the character entries of the wiki, in order.
+fn character_names(resolver: &LinkResolver) -> Vec<String> {
+    resolver
+        .entries()
+        .iter()
+        .filter(|entry| {
+            let kind = entry.kind.to_lowercase();
+            kind == "characters" || kind == "character"
+        })
+        .map(|entry| entry.name.clone())
+        .collect()
+}
+
+impl Plugin for InspectorPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            "inspector",
+            "0.1.0",
+            "Synopsis, POV, status, label and target of scenes",
+            "Cosmarium Team",
+        )
+    }
+
+    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+
+    fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        self.follow(
+            ctx.get_shared_state::<Option<NodeMetadata>>(ACTIVE_METADATA_KEY)
+                .flatten(),
+        );
+        Ok(())
+    }
+}
+
+impl PanelPlugin for InspectorPlugin {
+    fn panel_title(&self) -> &str {
+        "Inspector"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "🏷"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Right
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        let Some(editing) = &mut self.editing else {
+            ui.label("Open a document of the project to see its metadata.");
+            return;
+        };
+
+        let characters = ctx
+            .get_shared_state::<LinkResolver>(LINK_RESOLVER_KEY)
+            .map(|resolver| character_names(&resolver))
+            .unwrap_or_default();
+        let words = ctx
+            .get_shared_state::<usize>("editor_word_count")
+            .unwrap_or(0);
+        let locale = ctx
+            .get_shared_state::<Locale>(LOCALE_KEY)
+            .unwrap_or_default();
+
+        let before = editing.metadata.clone();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.heading(&editing.title);
+            ui.separator();
+            Self::render_metadata(ui, &mut editing.metadata, &characters, words, &locale);
+        });
+
+        if editing.metadata != before {
+            tracing::debug!("Metadata of {} edited", editing.title);
+            ctx.set_shared_state(METADATA_UPDATE_REQUEST, Some(editing.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmarium_plugin_api::wiki::WikiEntry;
+    use uuid::Uuid;
+
+    fn node(id: Uuid, title: &str, synopsis: &str) -> NodeMetadata {
+        NodeMetadata {
+            node: id,
+            title: title.to_string(),
+            metadata: SceneMetadata {
+                synopsis: synopsis.to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_edits_survive_the_stored_values() {
+        let (dawn, dusk) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut plugin = InspectorPlugin::new();
+        plugin.follow(Some(node(dawn, "Dawn", "Fog.")));
+        plugin.editing.as_mut().unwrap().metadata.synopsis = "Fog. ".to_string();
+
+        // The stored synopsis is trimmed
+        plugin.follow(Some(node(dawn, "Dawn", "Fog.")));
+        assert_eq!(plugin.editing.as_ref().unwrap().metadata.synopsis, "Fog. ");
+
+        plugin.follow(Some(node(dusk, "Dusk", "Rain.")));
+        assert_eq!(plugin.editing.as_ref().unwrap().metadata.synopsis, "Rain.");
+        plugin.follow(None);
+        assert!(plugin.editing.is_none());
+    }
+
+    #[test]
+    fn test_character_names() {
+        let resolver = LinkResolver::new(vec![
+            WikiEntry::new("Mira", "Characters", "entities/Characters/mira.md"),
+            WikiEntry::new("Harbor", "places", "entities/places/harbor.md"),
+            WikiEntry::new("Abel", "characters", "entities/characters/abel.md"),
+        ]);
+        assert_eq!(character_names(&resolver), vec!["Mira", "Abel"]);
+    }
+}
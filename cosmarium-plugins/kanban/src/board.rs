@@ -0,0 +1,333 @@
+//! # Kanban board model
+//!
+//! A board is a list of named columns and the cards placed in them. Cards
+//! either stand for a project document, in which case their column is the
+//! document's revision status, or for a free-form task typed by the author.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Columns of a new board.
+pub const DEFAULT_COLUMNS: &[&str] = &["To revise", "In progress", "Done"];
+
+/// A card on the board.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KanbanCard {
+    /// Unique card identifier
+    pub id: Uuid,
+    /// Card title
+    pub title: String,
+    /// Document path relative to the project root, `None` for a task card
+    pub document: Option<String>,
+    /// Column holding the card
+    pub column: String,
+}
+
+impl KanbanCard {
+    /// Check whether the card stands for a document.
+    pub fn is_document(&self) -> bool {
+        self.document.is_some()
+    }
+}
+
+/// A Kanban revision board.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_kanban::board::KanbanBoard;
+///
+/// let mut board = KanbanBoard::default();
+/// let id = board.add_task("Check timeline");
+/// assert!(board.move_card(id, "Done"));
+/// assert_eq!(board.cards_in("Done").count(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KanbanBoard {
+    /// Column names, in display order
+    pub columns: Vec<String>,
+    /// Cards, in display order within each column
+    pub cards: Vec<KanbanCard>,
+}
+
+impl Default for KanbanBoard {
+    fn default() -> Self {
+        Self {
+            columns: DEFAULT_COLUMNS.iter().map(|c| c.to_string()).collect(),
+            cards: Vec::new(),
+        }
+    }
+}
+
+impl KanbanBoard {
+    /// Cards in the given column, in display order.
+    pub fn cards_in<'a>(&'a self, column: &'a str) -> impl Iterator<Item = &'a KanbanCard> {
+        self.cards.iter().filter(move |card| card.column == column)
+    }
+
+    /// Add a task card to the first column and return its identifier.
+    pub fn add_task(&mut self, title: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        self.cards.push(KanbanCard {
+            id,
+            title: title.to_string(),
+            document: None,
+            column: self.first_column(),
+        });
+        id
+    }
+
+    /// Remove a card. Document cards come back on the next sync.
+    pub fn remove_card(&mut self, id: Uuid) {
+        self.cards.retain(|card| card.id != id);
+    }
+
+    /// Move a card to the end of `column`.
+    ///
+    /// Returns `false` if the card or the column does not exist.
+    pub fn move_card(&mut self, id: Uuid, column: &str) -> bool {
+        if !self.columns.iter().any(|c| c == column) {
+            return false;
+        }
+        let Some(pos) = self.cards.iter().position(|card| card.id == id) else {
+            return false;
+        };
+
+        let mut card = self.cards.remove(pos);
+        card.column = column.to_string();
+        self.cards.push(card);
+        true
+    }
+
+    /// Add a column at the end of the board.
+    ///
+    /// Returns `false` if the name is empty or already used.
+    pub fn add_column(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        if name.is_empty() || self.columns.iter().any(|c| c == name) {
+            return false;
+        }
+        self.columns.push(name.to_string());
+        true
+    }
+
+    /// Rename a column, keeping its cards.
+    pub fn rename_column(&mut self, from: &str, to: &str) -> bool {
+        let to = to.trim();
+        if to.is_empty() || self.columns.iter().any(|c| c == to) {
+            return false;
+        }
+        let Some(column) = self.columns.iter_mut().find(|c| *c == from) else {
+            return false;
+        };
+
+        *column = to.to_string();
+        for card in self.cards.iter_mut().filter(|card| card.column == from) {
+            card.column = to.to_string();
+        }
+        true
+    }
+
+    /// Remove a column, moving its cards to the first remaining column.
+    ///
+    /// The last column cannot be removed.
+    pub fn remove_column(&mut self, name: &str) -> bool {
+        if self.columns.len() <= 1 {
+            return false;
+        }
+        let Some(pos) = self.columns.iter().position(|c| c == name) else {
+            return false;
+        };
+
+        self.columns.remove(pos);
+        let fallback = self.first_column();
+        for card in self.cards.iter_mut().filter(|card| card.column == name) {
+            card.column = fallback.clone();
+        }
+        true
+    }
+
+    /// Swap a column with its neighbour (`-1` for left, `1` for right).
+    pub fn shift_column(&mut self, name: &str, offset: isize) -> bool {
+        let Some(pos) = self.columns.iter().position(|c| c == name) else {
+            return false;
+        };
+        let target = pos as isize + offset;
+        if target < 0 || target as usize >= self.columns.len() {
+            return false;
+        }
+        self.columns.swap(pos, target as usize);
+        true
+    }
+
+    /// Make the document cards match the project's documents.
+    ///
+    /// New documents get a card in the first column; cards of documents that
+    /// no longer exist are dropped. Cards left in a column that was removed
+    /// are moved to the first column. Returns `true` if the board changed.
+    pub fn sync_documents(&mut self, documents: &[String]) -> bool {
+        let before = self.cards.len();
+        self.cards.retain(|card| {
+            card.document
+                .as_ref()
+                .is_none_or(|doc| documents.contains(doc))
+        });
+        let mut changed = self.cards.len() != before;
+
+        for doc in documents {
+            if !self.cards.iter().any(|c| c.document.as_ref() == Some(doc)) {
+                self.cards.push(KanbanCard {
+                    id: Uuid::new_v4(),
+                    title: document_title(doc),
+                    document: Some(doc.clone()),
+                    column: self.first_column(),
+                });
+                changed = true;
+            }
+        }
+
+        let first = self.first_column();
+        for card in &mut self.cards {
+            if !self.columns.contains(&card.column) {
+                card.column = first.clone();
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// Status (column) of each document on the board.
+    pub fn document_status(&self) -> HashMap<String, String> {
+        self.cards
+            .iter()
+            .filter_map(|card| Some((card.document.clone()?, card.column.clone())))
+            .collect()
+    }
+
+    /// Set a document's status, creating the column if needed.
+    ///
+    /// Returns `true` if the board changed.
+    pub fn set_document_status(&mut self, document: &str, status: &str) -> bool {
+        let status = status.trim();
+        if status.is_empty() {
+            return false;
+        }
+        let Some(id) = self
+            .cards
+            .iter()
+            .find(|card| card.document.as_deref() == Some(document))
+            .map(|card| card.id)
+        else {
+            return false;
+        };
+        if self.document_status().get(document).map(String::as_str) == Some(status) {
+            return false;
+        }
+
+        self.add_column(status);
+        self.move_card(id, status)
+    }
+
+    fn first_column(&self) -> String {
+        self.columns.first().cloned().unwrap_or_default()
+    }
+}
+
+/// Card title for a document path such as `content/part1/chapter_one.md`.
+fn document_title(document: &str) -> String {
+    let name = document.rsplit('/').next().unwrap_or(document);
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    stem.replace('_', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_columns() {
+        let board = KanbanBoard::default();
+        assert_eq!(board.columns, vec!["To revise", "In progress", "Done"]);
+        assert!(board.cards.is_empty());
+    }
+
+    #[test]
+    fn test_task_lifecycle() {
+        let mut board = KanbanBoard::default();
+        let id = board.add_task("Fix timeline");
+        assert_eq!(board.cards_in("To revise").count(), 1);
+
+        assert!(board.move_card(id, "In progress"));
+        assert!(!board.move_card(id, "Nowhere"));
+        assert_eq!(
+            board.cards_in("In progress").next().unwrap().title,
+            "Fix timeline"
+        );
+
+        board.remove_card(id);
+        assert!(board.cards.is_empty());
+    }
+
+    #[test]
+    fn test_sync_documents() {
+        let mut board = KanbanBoard::default();
+        let docs = vec![
+            "content/chapter_one.md".to_string(),
+            "content/b.md".to_string(),
+        ];
+
+        assert!(board.sync_documents(&docs));
+        assert!(!board.sync_documents(&docs));
+        assert_eq!(board.cards.len(), 2);
+        assert_eq!(board.cards[0].title, "chapter one");
+
+        board.add_task("Keep me");
+        assert!(board.sync_documents(&docs[1..]));
+        assert_eq!(board.cards.len(), 2);
+        assert!(board.cards.iter().any(|c| !c.is_document()));
+    }
+
+    #[test]
+    fn test_document_status_roundtrip() {
+        let mut board = KanbanBoard::default();
+        board.sync_documents(&["content/a.md".to_string()]);
+
+        assert!(board.set_document_status("content/a.md", "Done"));
+        assert!(!board.set_document_status("content/a.md", "Done"));
+        assert_eq!(
+            board
+                .document_status()
+                .get("content/a.md")
+                .map(String::as_str),
+            Some("Done")
+        );
+
+        // Unknown statuses become new columns
+        assert!(board.set_document_status("content/a.md", "Beta read"));
+        assert_eq!(board.columns.last().unwrap(), "Beta read");
+        assert!(!board.set_document_status("content/missing.md", "Done"));
+    }
+
+    #[test]
+    fn test_column_editing() {
+        let mut board = KanbanBoard::default();
+        let id = board.add_task("Task");
+        board.move_card(id, "Done");
+
+        assert!(!board.add_column("Done"));
+        assert!(board.rename_column("Done", "Finished"));
+        assert_eq!(board.cards[0].column, "Finished");
+
+        assert!(board.shift_column("Finished", -1));
+        assert_eq!(board.columns[1], "Finished");
+        assert!(!board.shift_column("To revise", -1));
+
+        assert!(board.remove_column("Finished"));
+        assert_eq!(board.cards[0].column, "To revise");
+
+        board.remove_column("In progress");
+        assert!(!board.remove_column("To revise"));
+    }
+}
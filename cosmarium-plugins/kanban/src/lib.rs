@@ -0,0 +1,400 @@
+//! # Kanban revision board plugin for Cosmarium
+//!
+//! Shows the project's documents and the author's own tasks as cards moving
+//! through configurable columns. A document card's column is that document's
+//! revision status: it is published to other plugins through shared state
+//! and can be changed by them, so the board and the rest of the UI stay in
+//! sync. The board is stored in `meta/plugins/kanban/board.toon`.
+
+pub mod board;
+
+use board::KanbanBoard;
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+};
+use egui::Ui;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Shared state key holding each document's status (`HashMap<PathBuf, String>`).
+pub const DOCUMENT_STATUS_KEY: &str = "document_status";
+
+/// Shared state key other plugins use to change a document's status
+/// (`Option<(PathBuf, String)>`).
+pub const DOCUMENT_STATUS_REQUEST_KEY: &str = "document_status_request";
+
+/// How often the project's documents are rescanned.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// File extensions shown as document cards.
+const DOCUMENT_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// Drag-and-drop payload carrying a card identifier.
+struct CardDrag(Uuid);
+
+#[derive(Default)]
+pub struct KanbanPlugin {
+    /// The board being displayed
+    board: KanbanBoard,
+    /// Project the board belongs to
+    project: Option<PathBuf>,
+    /// Time of the last document scan
+    last_scan: Option<Instant>,
+    /// Whether the board must be published and saved
+    dirty: bool,
+    /// Title typed for a new task
+    new_task_title: String,
+    /// Name typed for a new column
+    new_column_name: String,
+}
+
+impl KanbanPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn board_file(project: &Path) -> PathBuf {
+        project.join("meta/plugins/kanban/board.toon")
+    }
+
+    /// Load the board of `project`, or start a fresh one.
+    fn load_board(&mut self, project: &Path) {
+        let file = Self::board_file(project);
+        self.board = match std::fs::read_to_string(&file) {
+            Ok(content) => serde_toon2::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Failed to parse kanban board {:?}: {}", file, e);
+                KanbanBoard::default()
+            }),
+            Err(_) => KanbanBoard::default(),
+        };
+    }
+
+    /// Save the board to project metadata.
+    fn save_board(&self) {
+        let Some(project) = &self.project else {
+            return;
+        };
+        let file = Self::board_file(project);
+        if let Some(dir) = file.parent() {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                tracing::error!("Failed to create kanban plugin directory: {}", e);
+                return;
+            }
+        }
+
+        match serde_toon2::to_string(&self.board) {
+            Ok(content) => {
+                if let Err(e) = std::fs::write(&file, content) {
+                    tracing::error!("Failed to write kanban board: {}", e);
+                }
+            }
+            Err(e) => tracing::error!("Failed to serialize kanban board: {}", e),
+        }
+    }
+
+    /// Document paths relative to the project root, with `/` separators.
+    fn project_documents(project: &Path) -> Vec<String> {
+        let mut files = Vec::new();
+        collect_documents(&project.join("content"), &mut files);
+
+        let mut documents: Vec<String> = files
+            .iter()
+            .filter_map(|path| document_key(project, path))
+            .collect();
+        documents.sort();
+        documents
+    }
+
+    /// Publish document statuses keyed by absolute path.
+    fn publish_status(&self, ctx: &mut PluginContext) {
+        let Some(project) = &self.project else {
+            ctx.set_shared_state(DOCUMENT_STATUS_KEY, HashMap::<PathBuf, String>::new());
+            return;
+        };
+        let status: HashMap<PathBuf, String> = self
+            .board
+            .document_status()
+            .into_iter()
+            .map(|(doc, column)| (project.join(doc), column))
+            .collect();
+        ctx.set_shared_state(DOCUMENT_STATUS_KEY, status);
+    }
+
+    /// Apply a status change requested by another plugin.
+    fn handle_status_request(&mut self, ctx: &mut PluginContext) {
+        let Some((path, status)) = ctx
+            .get_shared_state::<Option<(PathBuf, String)>>(DOCUMENT_STATUS_REQUEST_KEY)
+            .flatten()
+        else {
+            return;
+        };
+        ctx.set_shared_state::<Option<(PathBuf, String)>>(DOCUMENT_STATUS_REQUEST_KEY, None);
+
+        let Some(project) = &self.project else {
+            return;
+        };
+        if let Some(document) = document_key(project, &path) {
+            if self.board.set_document_status(&document, &status) {
+                self.dirty = true;
+            }
+        }
+    }
+
+    fn render_card(&mut self, ui: &mut Ui, ctx: &mut PluginContext, card: &board::KanbanCard) {
+        let id = egui::Id::new(("kanban_card", card.id));
+        let response = ui
+            .dnd_drag_source(id, CardDrag(card.id), |ui| {
+                egui::Frame::group(ui.style()).show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    let icon = if card.is_document() { "📄" } else { "☐" };
+                    ui.label(format!("{} {}", icon, card.title));
+                });
+            })
+            .response;
+
+        response.context_menu(|ui| {
+            if let (Some(doc), Some(project)) = (&card.document, &self.project) {
+                if ui.button("Open").clicked() {
+                    ctx.set_shared_state(
+                        "open_document_request",
+                        Some((project.join(doc), 0usize)),
+                    );
+                    ui.close();
+                }
+            }
+            for column in self.board.columns.clone() {
+                if column != card.column && ui.button(format!("Move to {}", column)).clicked() {
+                    self.dirty |= self.board.move_card(card.id, &column);
+                    ui.close();
+                }
+            }
+            if !card.is_document() && ui.button("Delete").clicked() {
+                self.board.remove_card(card.id);
+                self.dirty = true;
+                ui.close();
+            }
+        });
+    }
+
+    fn render_column(&mut self, ui: &mut Ui, ctx: &mut PluginContext, column: &str) {
+        let cards: Vec<board::KanbanCard> = self.board.cards_in(column).cloned().collect();
+
+        ui.vertical(|ui| {
+            ui.set_width(180.0);
+            let header = ui.strong(format!("{} ({})", column, cards.len()));
+            header.context_menu(|ui| {
+                if ui.button("Move left").clicked() {
+                    self.dirty |= self.board.shift_column(column, -1);
+                    ui.close();
+                }
+                if ui.button("Move right").clicked() {
+                    self.dirty |= self.board.shift_column(column, 1);
+                    ui.close();
+                }
+                if !self.new_column_name.trim().is_empty()
+                    && ui
+                        .button(format!("Rename to \"{}\"", self.new_column_name.trim()))
+                        .clicked()
+                {
+                    let to = std::mem::take(&mut self.new_column_name);
+                    self.dirty |= self.board.rename_column(column, &to);
+                    ui.close();
+                }
+                if ui.button("Remove column").clicked() {
+                    self.dirty |= self.board.remove_column(column);
+                    ui.close();
+                }
+            });
+
+            let frame = egui::Frame::default().inner_margin(4.0);
+            let (_, dropped) = ui.dnd_drop_zone::<CardDrag, ()>(frame, |ui| {
+                ui.set_min_size(egui::vec2(172.0, 60.0));
+                for card in &cards {
+                    self.render_card(ui, ctx, card);
+                }
+            });
+
+            if let Some(drag) = dropped {
+                self.dirty |= self.board.move_card(drag.0, column);
+            }
+        });
+    }
+}
+
+/// Board key of a document: its path relative to the project root, with `/`
+/// separators so boards stay portable across platforms.
+fn document_key(project: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(project).ok()?;
+    Some(
+        rel.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// Recursively collect document files under `dir`.
+fn collect_documents(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_documents(&path, files);
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| DOCUMENT_EXTENSIONS.contains(&e))
+        {
+            files.push(path);
+        }
+    }
+}
+
+impl Plugin for KanbanPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new("kanban", "0.1.0", "Kanban revision board", "Cosmarium Team")
+    }
+
+    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+
+    fn update(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl PanelPlugin for KanbanPlugin {
+    fn panel_title(&self) -> &str {
+        "Kanban"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "🗂"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Bottom
+    }
+
+    fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        let project = ctx.project_path();
+        if project != self.project {
+            match &project {
+                Some(path) => self.load_board(path),
+                None => self.board = KanbanBoard::default(),
+            }
+            self.project = project;
+            self.last_scan = None;
+            self.publish_status(ctx);
+        }
+
+        if let Some(project) = self.project.clone() {
+            if self
+                .last_scan
+                .is_none_or(|t| t.elapsed() >= RESCAN_INTERVAL)
+            {
+                let documents = Self::project_documents(&project);
+                self.dirty |= self.board.sync_documents(&documents);
+                self.last_scan = Some(Instant::now());
+            }
+        }
+
+        self.handle_status_request(ctx);
+
+        if self.dirty {
+            self.publish_status(ctx);
+            self.save_board();
+            self.dirty = false;
+        }
+
+        Ok(())
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_task_title)
+                    .hint_text("New task")
+                    .desired_width(160.0),
+            );
+            if ui.button("Add task").clicked() && !self.new_task_title.trim().is_empty() {
+                let title = std::mem::take(&mut self.new_task_title);
+                self.board.add_task(title.trim());
+                self.dirty = true;
+            }
+            ui.separator();
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_column_name)
+                    .hint_text("Column name")
+                    .desired_width(120.0),
+            );
+            if ui.button("Add column").clicked() && self.board.add_column(&self.new_column_name) {
+                self.new_column_name.clear();
+                self.dirty = true;
+            }
+        });
+        ui.separator();
+
+        egui::ScrollArea::both().show(ui, |ui| {
+            ui.horizontal_top(|ui| {
+                for column in self.board.columns.clone() {
+                    self.render_column(ui, ctx, &column);
+                }
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_request_moves_document_card() {
+        let project = PathBuf::from("/projects/novel");
+        let mut plugin = KanbanPlugin::new();
+        plugin.project = Some(project.clone());
+        plugin.board.sync_documents(&["content/one.md".to_string()]);
+
+        let mut ctx = PluginContext::new();
+        let doc = project.join("content/one.md");
+        ctx.set_shared_state(
+            DOCUMENT_STATUS_REQUEST_KEY,
+            Some((doc.clone(), "Done".to_string())),
+        );
+        plugin.handle_status_request(&mut ctx);
+        assert!(plugin.dirty);
+
+        plugin.publish_status(&mut ctx);
+        let status: HashMap<PathBuf, String> = ctx.get_shared_state(DOCUMENT_STATUS_KEY).unwrap();
+        assert_eq!(status.get(&doc).map(String::as_str), Some("Done"));
+
+        let pending: Option<(PathBuf, String)> =
+            ctx.get_shared_state(DOCUMENT_STATUS_REQUEST_KEY).unwrap();
+        assert!(pending.is_none());
+    }
+
+    #[test]
+    fn test_project_documents_are_relative() {
+        let root =
+            std::env::temp_dir().join(format!("cosmarium_kanban_test_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("content/part1")).unwrap();
+        std::fs::write(root.join("content/part1/scene.md"), "Text").unwrap();
+        std::fs::write(root.join("content/cover.png"), "").unwrap();
+
+        let documents = KanbanPlugin::project_documents(&root);
+        assert_eq!(documents, vec!["content/part1/scene.md"]);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
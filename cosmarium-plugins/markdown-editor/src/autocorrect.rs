@@ -0,0 +1,341 @@
+//! # Auto-correction
+//!
+//! An opt-in layer fixing slips as they are typed, when a word is ended by a
+//! space or a punctuation mark: the first word of a sentence is capitalized,
+//! so is a standalone "i", a second space after a full stop is dropped, and
+//! typos from an editable list are corrected. Each correction is its own undo
+//! step, so that Undo right after it brings back what was typed.
+//!
+//! Auto-correction is set per project: the application stores the
+//! [`AutocorrectSettings`] in the project settings and publishes them to
+//! plugins under [`AUTOCORRECT_KEY`]. Code, front matter and wiki links are
+//! left alone.
+
+use serde::{Deserialize, Serialize};
+
+use crate::lists;
+use crate::pairs::Edit;
+
+/// Configuration key under which the active project's auto-correction
+/// settings are published to plugins.
+pub const AUTOCORRECT_KEY: &str = "autocorrect";
+
+/// Characters ending a word, which trigger the corrections.
+const WORD_ENDS: &[char] = &[
+    '.', ',', ';', ':', '!', '?', ')', ']', '}', '"', '”', '»', '…',
+];
+
+/// Characters that may open a sentence or a word, before its first letter.
+const OPENERS: &[char] = &['"', '“', '«', '‘', '\'', '(', '[', '*', '_', '\u{a0}'];
+
+/// Characters that may close a sentence, after its final punctuation.
+const CLOSERS: &[char] = &['"', '”', '»', '’', '\'', ')', ']', '*', '_', '\u{a0}'];
+
+/// Abbreviations followed by a full stop that do not end a sentence.
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "st", "mt", "jr", "sr", "vs", "e.g", "i.e", "cf",
+];
+
+/// A typo and its correction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Correction {
+    /// Word as mistyped, matched ignoring case
+    pub typo: String,
+    /// Word replacing it
+    pub fix: String,
+}
+
+impl Correction {
+    pub fn new(typo: &str, fix: &str) -> Self {
+        Self {
+            typo: typo.to_string(),
+            fix: fix.to_string(),
+        }
+    }
+}
+
+/// Auto-correction settings of a project.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::autocorrect::AutocorrectSettings;
+///
+/// let settings = AutocorrectSettings::default();
+/// assert!(!settings.enabled);
+/// assert!(settings.corrections.iter().any(|c| c.typo == "teh"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutocorrectSettings {
+    /// Whether text is corrected as it is typed
+    pub enabled: bool,
+    /// Capitalize the first word of sentences
+    pub sentence_case: bool,
+    /// Capitalize a standalone "i", and "i'm", "i'd", ...
+    pub standalone_i: bool,
+    /// Drop a second space typed after a full stop
+    pub single_space: bool,
+    /// Typos corrected as they are typed
+    pub corrections: Vec<Correction>,
+}
+
+impl Default for AutocorrectSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sentence_case: true,
+            standalone_i: true,
+            single_space: true,
+            corrections: [
+                ("teh", "the"),
+                ("hte", "the"),
+                ("adn", "and"),
+                ("taht", "that"),
+                ("wich", "which"),
+                ("thier", "their"),
+                ("becuase", "because"),
+                ("untill", "until"),
+                ("recieve", "receive"),
+                ("beleive", "believe"),
+                ("seperate", "separate"),
+                ("definately", "definitely"),
+                ("occured", "occurred"),
+                ("alot", "a lot"),
+            ]
+            .into_iter()
+            .map(|(typo, fix)| Correction::new(typo, fix))
+            .collect(),
+        }
+    }
+}
+
+/// Correction of the text just typed in `content`, the caret being at byte
+/// `cursor` right after the typed character, if there is one to make.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::autocorrect::{correct, AutocorrectSettings};
+///
+/// let settings = AutocorrectSettings {
+///     enabled: true,
+///     ..AutocorrectSettings::default()
+/// };
+/// let content = "It rained. teh ";
+/// let edit = correct(content, content.len(), &settings).unwrap();
+/// assert_eq!((edit.range, edit.text.as_str()), (11..14, "The"));
+/// ```
+pub fn correct(content: &str, cursor: usize, settings: &AutocorrectSettings) -> Option<Edit> {
+    if !settings.enabled {
+        return None;
+    }
+    let typed = content[..cursor].chars().next_back()?;
+    let end = cursor - typed.len_utf8();
+    if !typed.is_whitespace() && !WORD_ENDS.contains(&typed) {
+        return None;
+    }
+    if is_verbatim(content, end) {
+        return None;
+    }
+
+    // A second space after a full stop
+    if settings.single_space && typed == ' ' {
+        let before = &content[..end];
+        if let Some(sentence) = before.strip_suffix(' ') {
+            if ends_sentence(sentence) {
+                return Some(Edit {
+                    range: end..cursor,
+                    text: String::new(),
+                    selection: end..end,
+                });
+            }
+        }
+    }
+
+    let start = word_start(content, end)?;
+    let word = &content[start..end];
+    let mut fixed = settings
+        .corrections
+        .iter()
+        .find(|c| !c.typo.is_empty() && c.typo.to_lowercase() == word.to_lowercase())
+        .map_or_else(|| word.to_string(), |c| match_case(word, &c.fix));
+
+    let standalone_i = word == "i"
+        || word
+            .strip_prefix('i')
+            .is_some_and(|rest| rest.starts_with(['\'', '’']));
+    // "i.e." is no pronoun
+    if settings.standalone_i && standalone_i && typed != '.' {
+        fixed = capitalize(&fixed);
+    }
+    if settings.sentence_case && starts_sentence(content, start) {
+        fixed = capitalize(&fixed);
+    }
+
+    (fixed != word).then(|| {
+        let caret = cursor - word.len() + fixed.len();
+        Edit {
+            range: start..end,
+            text: fixed,
+            selection: caret..caret,
+        }
+    })
+}
+
+/// Start of the word ending at byte `end` of `content`, if it is a whole
+/// word: letters and apostrophes after a space, an opening mark or the start
+/// of a line.
+fn word_start(content: &str, end: usize) -> Option<usize> {
+    let before = &content[..end];
+    let word = before.trim_end_matches(|c: char| c.is_alphabetic() || matches!(c, '\'' | '’'));
+    let start = word.len();
+    let whole = word
+        .chars()
+        .next_back()
+        .is_none_or(|c| c.is_whitespace() || OPENERS.contains(&c));
+    let first = content[start..end].chars().next()?;
+    (whole && first.is_alphabetic()).then_some(start)
+}
+
+/// Whether the text at byte `at` of `content` is code, front matter or a
+/// wiki link, which are never corrected.
+fn is_verbatim(content: &str, at: usize) -> bool {
+    let line_start = content[..at].rfind('\n').map_or(0, |i| i + 1);
+    let line = &content[line_start..at];
+    let front_matter = content.starts_with("---\n")
+        && !content[4..at.max(4)]
+            .lines()
+            .any(|line| matches!(line.trim(), "---" | "..."));
+    front_matter
+        || lists::in_code(content, line_start)
+        || line.matches('`').count() % 2 == 1
+        || line.rfind("[[").map(|open| open + 2) > line.rfind("]]").map(|close| close + 2)
+}
+
+/// Whether the word at byte `start` of `content` begins a sentence: it
+/// follows a full stop, a question or exclamation mark, starts a paragraph,
+/// or comes after a heading, quote or list marker.
+fn starts_sentence(content: &str, start: usize) -> bool {
+    let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+    let before = content[line_start..start].trim_end_matches(OPENERS);
+    let text = before.trim_start_matches('#');
+    let markers = lists::markers_len(text.trim_start());
+    if text.trim_start()[markers..].trim().is_empty() {
+        let marked = !before.trim().is_empty();
+        // A line of prose goes on from the line before it, unless a blank
+        // line parts them
+        let previous = content[..line_start.saturating_sub(1)]
+            .rsplit('\n')
+            .next()
+            .unwrap_or("");
+        return marked || line_start == 0 || previous.trim().is_empty() || ends_sentence(previous);
+    }
+    before.ends_with(char::is_whitespace) && ends_sentence(before)
+}
+
+/// Whether `text` ends with the end of a sentence, not counting closing
+/// marks and spaces after it.
+fn ends_sentence(text: &str) -> bool {
+    let text = text.trim_end().trim_end_matches(CLOSERS);
+    let Some(stop) = text.chars().next_back() else {
+        return false;
+    };
+    if matches!(stop, '!' | '?') {
+        return true;
+    }
+    if stop != '.' || text.ends_with("..") {
+        return false;
+    }
+    // Abbreviations and initials, such as "Dr." or "J. R. R."
+    let word = text[..text.len() - 1]
+        .rsplit(|c: char| c.is_whitespace() || OPENERS.contains(&c))
+        .next()
+        .unwrap_or("");
+    word.chars().count() > 1 && !ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+/// `fix` with the case of the `word` it replaces: capitalized, in capitals,
+/// or as written.
+fn match_case(word: &str, fix: &str) -> String {
+    let letters = word.chars().filter(|c| c.is_alphabetic()).count();
+    if letters > 1 && word.chars().all(|c| !c.is_lowercase()) {
+        fix.to_uppercase()
+    } else if word.chars().next().is_some_and(char::is_uppercase) {
+        capitalize(fix)
+    } else {
+        fix.to_string()
+    }
+}
+
+/// `word` with its first letter in capitals.
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `content` after typing its last character, with auto-correction on.
+    fn typed(content: &str) -> String {
+        let settings = AutocorrectSettings {
+            enabled: true,
+            ..AutocorrectSettings::default()
+        };
+        let mut content = content.to_string();
+        if let Some(edit) = correct(&content, content.len(), &settings) {
+            content.replace_range(edit.range, &edit.text);
+        }
+        content
+    }
+
+    #[test]
+    fn test_sentence_case() {
+        assert_eq!(typed("she ran. then "), "she ran. Then ");
+        assert_eq!(typed("Why? “who "), "Why? “Who ");
+        assert_eq!(typed("Past.\n\nnow,"), "Past.\n\nNow,");
+        assert_eq!(typed("# chapter "), "# Chapter ");
+        assert_eq!(typed("- one "), "- One ");
+        assert_eq!(typed("the"), "the");
+        assert_eq!(typed("he said\nand "), "he said\nand ");
+
+        // Abbreviations, initials and ellipses go on with the sentence
+        assert_eq!(typed("Dr. who "), "Dr. who ");
+        assert_eq!(typed("J. r. "), "J. r. ");
+        assert_eq!(typed("Well... maybe "), "Well... maybe ");
+        assert_eq!(typed("see file.txt "), "see file.txt ");
+    }
+
+    #[test]
+    fn test_standalone_i_and_spaces() {
+        assert_eq!(typed("and i "), "and I ");
+        assert_eq!(typed("so i'm "), "so I'm ");
+        assert_eq!(typed("and i."), "and i.");
+        assert_eq!(typed("Stop.  "), "Stop. ");
+        assert_eq!(typed("Stop, "), "Stop, ");
+    }
+
+    #[test]
+    fn test_typos_keep_their_case() {
+        assert_eq!(typed("in teh "), "in the ");
+        assert_eq!(typed("in TEH "), "in THE ");
+        assert_eq!(typed("in alot,"), "in a lot,");
+        assert_eq!(typed("in tehran "), "in tehran ");
+    }
+
+    #[test]
+    fn test_verbatim_text_is_left_alone() {
+        assert_eq!(typed("Run `teh "), "Run `teh ");
+        assert_eq!(typed("```\nOk. teh "), "```\nOk. teh ");
+        assert_eq!(typed("---\ntitle: teh "), "---\ntitle: teh ");
+        assert_eq!(typed("See [[teh "), "See [[teh ");
+
+        let settings = AutocorrectSettings::default();
+        assert!(correct("in teh ", 7, &settings).is_none());
+    }
+}
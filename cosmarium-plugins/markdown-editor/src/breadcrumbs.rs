@@ -0,0 +1,104 @@
+//! # Breadcrumbs of the caret
+//!
+//! A strip above the text shows the chain of headings the caret is under,
+//! from the part down to the scene (`Part One › The Inn › Night Falls`).
+//! Clicking a heading moves the caret to it. Like the heading picker, the
+//! strip reads the headings the outline published under
+//! [`OUTLINE_HEADINGS_KEY`](crate::jump::OUTLINE_HEADINGS_KEY).
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_markdown_editor::breadcrumbs::heading_path;
+//!
+//! let headings = vec![
+//!     (1, "Part One".to_string(), 1),
+//!     (2, "The Inn".to_string(), 3),
+//!     (3, "Night Falls".to_string(), 9),
+//!     (2, "The Road".to_string(), 20),
+//! ];
+//! let path: Vec<&str> = heading_path(&headings, 12)
+//!     .iter()
+//!     .map(|(_, text, _)| text.as_str())
+//!     .collect();
+//! assert_eq!(path, ["Part One", "The Inn", "Night Falls"]);
+//! ```
+
+use crate::jump::Heading;
+use egui::Ui;
+
+/// Headings containing the 1-based `line`, outermost first.
+pub fn heading_path(headings: &[Heading], line: usize) -> Vec<&Heading> {
+    let mut path: Vec<&Heading> = Vec::new();
+    for heading in headings.iter().take_while(|(_, _, start)| *start <= line) {
+        while path.last().is_some_and(|(level, _, _)| *level >= heading.0) {
+            path.pop();
+        }
+        path.push(heading);
+    }
+    path
+}
+
+/// Show the headings of `path` as breadcrumbs.
+///
+/// # Returns
+///
+/// The 1-based line of the heading clicked, if any.
+pub fn show(ui: &mut Ui, path: &[&Heading]) -> Option<usize> {
+    let mut clicked = None;
+    egui::ScrollArea::horizontal()
+        .id_salt("markdown_editor_breadcrumbs")
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.spacing_mut().item_spacing.x = 2.0;
+                if path.is_empty() {
+                    ui.weak("No heading above the caret");
+                }
+                for (i, (_, text, line)) in path.iter().enumerate() {
+                    if i > 0 {
+                        ui.weak("›");
+                    }
+                    let last = i + 1 == path.len();
+                    let text = if last {
+                        egui::RichText::new(text.as_str())
+                    } else {
+                        egui::RichText::new(text.as_str()).weak()
+                    };
+                    if ui
+                        .add(egui::Button::new(text.small()).frame(false))
+                        .on_hover_text(format!("Go to line {}", line))
+                        .clicked()
+                    {
+                        clicked = Some(*line);
+                    }
+                }
+            });
+        });
+    clicked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heading_path_skips_closed_sections() {
+        let headings = vec![
+            (1, "One".to_string(), 1),
+            (3, "Deep".to_string(), 2),
+            (2, "Scene".to_string(), 5),
+            (1, "Two".to_string(), 10),
+        ];
+        let lines = |line| -> Vec<usize> {
+            heading_path(&headings, line)
+                .iter()
+                .map(|(_, _, start)| *start)
+                .collect()
+        };
+        assert!(lines(0).is_empty());
+        assert_eq!(lines(3), [1, 2]);
+        // A level skipped above is closed by a shallower heading
+        assert_eq!(lines(6), [1, 5]);
+        assert_eq!(lines(10), [10]);
+    }
+}
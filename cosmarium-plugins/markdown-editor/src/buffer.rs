@@ -0,0 +1,191 @@
+//! # Text buffer
+//!
+//! The TextEdit of the editor works on a `String`. Beside it, the text as
+//! it was last recorded is kept in a rope, so that finding what an edit
+//! changed, and keeping the record up to date, copies only the changed
+//! text rather than the whole document. Undo history stores these changes,
+//! as [`Delta`]s.
+
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Replacement of a range of text by another.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Delta {
+    /// Byte offset of the change
+    pub at: usize,
+    /// Text removed
+    pub removed: String,
+    /// Text inserted in its place
+    pub inserted: String,
+}
+
+impl Delta {
+    /// Range of the text before the change that was removed.
+    pub fn removed_range(&self) -> Range<usize> {
+        self.at..self.at + self.removed.len()
+    }
+
+    /// Range of the text after the change that was inserted.
+    pub fn inserted_range(&self) -> Range<usize> {
+        self.at..self.at + self.inserted.len()
+    }
+
+    /// Make the change in `text`.
+    pub fn apply(&self, text: &mut String) {
+        text.replace_range(self.removed_range(), &self.inserted);
+    }
+
+    /// The change undoing this one.
+    pub fn inverse(&self) -> Self {
+        Self {
+            at: self.at,
+            removed: self.inserted.clone(),
+            inserted: self.removed.clone(),
+        }
+    }
+}
+
+/// The text of a document as it was last recorded.
+#[derive(Debug, Clone, Default)]
+pub struct TextBuffer {
+    rope: Rope,
+}
+
+impl TextBuffer {
+    /// Create a buffer recording `text`.
+    pub fn new(text: &str) -> Self {
+        Self {
+            rope: Rope::from_str(text),
+        }
+    }
+
+    /// Length of the text recorded, in bytes.
+    pub fn len(&self) -> usize {
+        self.rope.len_bytes()
+    }
+
+    /// Whether the text recorded is empty.
+    pub fn is_empty(&self) -> bool {
+        self.rope.len_bytes() == 0
+    }
+
+    /// Record `text`, edited from the text recorded, and tell what changed,
+    /// `None` if it is the same.
+    ///
+    /// The change is the one range between the text both share at their
+    /// start and at their end.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_markdown_editor::buffer::TextBuffer;
+    ///
+    /// let mut buffer = TextBuffer::new("a cat");
+    /// let delta = buffer.commit("a black cat").unwrap();
+    /// assert_eq!((delta.at, delta.removed.as_str(), delta.inserted.as_str()), (2, "", "black "));
+    /// assert_eq!(buffer.commit("a black cat"), None);
+    /// ```
+    pub fn commit(&mut self, text: &str) -> Option<Delta> {
+        let old_len = self.rope.len_bytes();
+        let bytes = text.as_bytes();
+
+        let mut prefix = 0;
+        for chunk in self.rope.chunks() {
+            let same = chunk
+                .bytes()
+                .zip(&bytes[prefix..])
+                .take_while(|(a, b)| a == *b)
+                .count();
+            prefix += same;
+            if same < chunk.len() {
+                break;
+            }
+        }
+        if prefix == old_len && prefix == text.len() {
+            return None;
+        }
+        while !text.is_char_boundary(prefix) {
+            prefix -= 1;
+        }
+
+        let longest = old_len.min(text.len()) - prefix;
+        let mut old_bytes = self.rope.bytes_at(old_len);
+        let mut suffix = 0;
+        for byte in bytes.iter().rev().take(longest) {
+            if old_bytes.prev() != Some(*byte) {
+                break;
+            }
+            suffix += 1;
+        }
+        while !text.is_char_boundary(text.len() - suffix) {
+            suffix -= 1;
+        }
+
+        let delta = Delta {
+            at: prefix,
+            removed: self.rope.byte_slice(prefix..old_len - suffix).to_string(),
+            inserted: text[prefix..text.len() - suffix].to_string(),
+        };
+        self.apply(&delta);
+        Some(delta)
+    }
+
+    /// Make a change of the text recorded.
+    pub fn apply(&mut self, delta: &Delta) {
+        let start = self.rope.byte_to_char(delta.at);
+        let end = self.rope.byte_to_char(delta.at + delta.removed.len());
+        self.rope.remove(start..end);
+        self.rope.insert(start, &delta.inserted);
+    }
+
+    /// Record `text` without telling what changed, when the text was
+    /// replaced rather than edited.
+    pub fn reset(&mut self, text: &str) {
+        if self.rope != text {
+            self.rope = Rope::from_str(text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_finds_the_change() {
+        let mut buffer = TextBuffer::new("the cat sat");
+        let delta = buffer.commit("the cart sat").unwrap();
+        assert_eq!(delta.at, 6);
+        assert_eq!(delta.removed, "");
+        assert_eq!(delta.inserted, "r");
+
+        // Repeated text around the change, and changes of multi-byte
+        // characters, stay on character boundaries
+        let delta = buffer.commit("the cart säät").unwrap();
+        assert_eq!(delta.removed_range(), 10..11);
+        assert_eq!(delta.inserted, "ää");
+        let delta = buffer.commit("the cart sät").unwrap();
+        assert_eq!((delta.removed.as_str(), delta.inserted.as_str()), ("ä", ""));
+
+        let mut text = "the cart sät".to_string();
+        delta.inverse().apply(&mut text);
+        assert_eq!(text, "the cart säät");
+        assert_eq!(buffer.len(), "the cart sät".len());
+    }
+
+    #[test]
+    fn test_commit_across_chunks() {
+        let long = "word ".repeat(5000);
+        let mut buffer = TextBuffer::new(&long);
+        let mut edited = long.clone();
+        edited.insert(12_000, 'é');
+        let delta = buffer.commit(&edited).unwrap();
+        assert_eq!(delta.inserted_range(), 12_000..12_002);
+        assert_eq!(buffer.commit(&edited), None);
+
+        buffer.reset("");
+        assert!(buffer.is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+//! # Editor commands
+//!
+//! The menus and shortcuts of the application act on the active tab by
+//! setting the [`EDITOR_COMMAND`] shared state to an [`EditorCommand`]. The
+//! editor runs the command on its next update and clears it; commands on the
+//! selection wait for the view of the active tab to be drawn.
+//!
+//! Each command is also registered with the plugin context by [`register`],
+//! for the command palette and keymap to offer it.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_markdown_editor::commands::{self, EditorCommand, EDITOR_COMMAND};
+//! use cosmarium_plugin_api::PluginContext;
+//!
+//! let mut ctx = PluginContext::new();
+//! commands::send(&mut ctx, EditorCommand::Undo);
+//! assert_eq!(
+//!     ctx.get_shared_state::<Option<EditorCommand>>(EDITOR_COMMAND),
+//!     Some(Some(EditorCommand::Undo))
+//! );
+//! ```
+
+use cosmarium_plugin_api::{PluginContext, Shortcut};
+
+/// Shared state key (`Option<EditorCommand>`) of the command asked for,
+/// served by the active tab.
+pub const EDITOR_COMMAND: &str = "markdown_editor_command";
+
+/// What the application asks the editor to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EditorCommand {
+    /// Undo the last change
+    Undo,
+    /// Redo the last change undone
+    Redo,
+    /// Copy the selection to the clipboard and remove it, when the view is
+    /// drawn
+    Cut,
+    /// Copy the selection to the clipboard, when the view is drawn
+    Copy,
+    /// Select the whole document, when the view is drawn
+    SelectAll,
+    /// Fill the paragraphs under the caret up to the wrap column
+    ReflowParagraph,
+    /// Turn focus mode on or off
+    ToggleFocusMode,
+    /// Ask for a line number and move the caret there
+    GoToLine,
+    /// List the headings of the document and move the caret to the one
+    /// picked
+    GoToHeading,
+}
+
+impl EditorCommand {
+    /// All commands, in palette order.
+    pub const ALL: [EditorCommand; 9] = [
+        EditorCommand::Undo,
+        EditorCommand::Redo,
+        EditorCommand::Cut,
+        EditorCommand::Copy,
+        EditorCommand::SelectAll,
+        EditorCommand::ReflowParagraph,
+        EditorCommand::ToggleFocusMode,
+        EditorCommand::GoToLine,
+        EditorCommand::GoToHeading,
+    ];
+
+    /// Identifier of the command registered with the plugin context.
+    pub fn id(self) -> &'static str {
+        match self {
+            EditorCommand::Undo => "editor.undo",
+            EditorCommand::Redo => "editor.redo",
+            EditorCommand::Cut => "editor.cut",
+            EditorCommand::Copy => "editor.copy",
+            EditorCommand::SelectAll => "editor.select_all",
+            EditorCommand::ReflowParagraph => "editor.reflow_paragraph",
+            EditorCommand::ToggleFocusMode => "editor.toggle_focus_mode",
+            EditorCommand::GoToLine => "editor.go_to_line",
+            EditorCommand::GoToHeading => "editor.go_to_heading",
+        }
+    }
+
+    /// Title shown in the command palette.
+    pub fn title(self) -> &'static str {
+        match self {
+            EditorCommand::Undo => "Undo",
+            EditorCommand::Redo => "Redo",
+            EditorCommand::Cut => "Cut",
+            EditorCommand::Copy => "Copy",
+            EditorCommand::SelectAll => "Select All",
+            EditorCommand::ReflowParagraph => "Reflow Paragraph",
+            EditorCommand::ToggleFocusMode => "Toggle Focus Mode",
+            EditorCommand::GoToLine => "Go to Line",
+            EditorCommand::GoToHeading => "Go to Heading",
+        }
+    }
+
+    /// Shortcut of the command, if the application handles it. Cut, Copy
+    /// and Select All have none: the text view answers Ctrl+X, Ctrl+C and
+    /// Ctrl+A itself.
+    pub fn default_shortcut(self) -> Option<Shortcut> {
+        match self {
+            EditorCommand::Undo => Some(Shortcut::ctrl("Z")),
+            EditorCommand::Redo => Some(Shortcut::ctrl("Y")),
+            EditorCommand::ReflowParagraph => Some(Shortcut::new("Q").with_alt()),
+            EditorCommand::ToggleFocusMode => Some(Shortcut::new("F11")),
+            EditorCommand::GoToLine => Some(Shortcut::ctrl("G")),
+            EditorCommand::GoToHeading => Some(Shortcut::ctrl("J")),
+            EditorCommand::Cut | EditorCommand::Copy | EditorCommand::SelectAll => None,
+        }
+    }
+}
+
+/// Register every command with `ctx`, each sending itself to the active tab.
+pub fn register(ctx: &mut PluginContext) {
+    for command in EditorCommand::ALL {
+        ctx.register_command(
+            command.id(),
+            command.title(),
+            command.default_shortcut(),
+            move |ctx: &mut PluginContext| send(ctx, command),
+        );
+    }
+}
+
+/// Ask the active tab to run `command`.
+pub fn send(ctx: &mut PluginContext, command: EditorCommand) {
+    ctx.set_shared_state(EDITOR_COMMAND, Some(command));
+}
+
+/// Command asked for, cleared so that it runs once.
+pub fn take(ctx: &mut PluginContext) -> Option<EditorCommand> {
+    let command = ctx
+        .get_shared_state::<Option<EditorCommand>>(EDITOR_COMMAND)
+        .flatten()?;
+    ctx.set_shared_state::<Option<EditorCommand>>(EDITOR_COMMAND, None);
+    Some(command)
+}
@@ -0,0 +1,656 @@
+//! # Corpus-based word completion for the Markdown Editor plugin
+//!
+//! This module builds a small language model of the project's own prose and
+//! uses it to complete words as the author types. Character names, invented
+//! terms and recurring phrases are learned from the project's documents, so
+//! the suggestions follow the author's vocabulary rather than a generic
+//! dictionary.
+//!
+//! The model keeps word frequencies, the capitalization of each word in the
+//! middle of a sentence (to tell names from ordinary words) and which word
+//! follows which (to rank words by context and complete whole phrases).
+
+use cosmarium_plugin_api::TaskProgress;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Minimum number of characters typed before suggestions are offered.
+pub const MIN_PREFIX_CHARS: usize = 2;
+
+/// Ordinary words must appear this often before they are suggested.
+const MIN_OCCURRENCES: u32 = 2;
+
+/// Weight of the preceding word when ranking candidates.
+const CONTEXT_WEIGHT: f32 = 2.0;
+
+/// Score bonus of names and other always-capitalized terms.
+const NAME_BONUS: f32 = 1.0;
+
+/// Maximum number of words appended to a suggestion to complete a phrase.
+const MAX_PHRASE_EXTENSION: usize = 3;
+
+/// File extensions indexed when building the model from a project.
+const INDEXED_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// Where a suggestion comes from.
+///
+/// Suggestions learned from the project always rank above dictionary words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SuggestionSource {
+    /// A name or term the project always capitalizes
+    Name,
+    /// A phrase the project has used before
+    Phrase,
+    /// A word used in the project
+    Corpus,
+    /// A dictionary word
+    Dictionary,
+}
+
+impl SuggestionSource {
+    /// Short label shown next to the suggestion.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Phrase => "phrase",
+            Self::Corpus => "project",
+            Self::Dictionary => "dictionary",
+        }
+    }
+
+    /// Whether the suggestion was learned from the project.
+    pub fn is_project(self) -> bool {
+        !matches!(self, Self::Dictionary)
+    }
+}
+
+/// A completion candidate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// Text replacing the typed prefix
+    pub text: String,
+    /// Origin of the suggestion
+    pub source: SuggestionSource,
+    /// Ranking score, higher is better
+    pub score: f32,
+}
+
+/// Sort suggestions best first, project suggestions before dictionary ones,
+/// and drop duplicates.
+pub fn rank(suggestions: &mut Vec<Suggestion>, limit: usize) {
+    suggestions.sort_by(|a, b| {
+        b.source
+            .is_project()
+            .cmp(&a.source.is_project())
+            .then(b.score.total_cmp(&a.score))
+            .then_with(|| a.text.cmp(&b.text))
+    });
+    let mut seen = std::collections::HashSet::new();
+    suggestions.retain(|s| seen.insert(s.text.to_lowercase()));
+    suggestions.truncate(limit);
+}
+
+/// Occurrences of one word, keyed by its lowercase form.
+#[derive(Debug, Clone, Default)]
+struct WordCount {
+    /// All occurrences
+    total: u32,
+    /// Occurrences written in lowercase
+    lowercase: u32,
+    /// Capitalized spellings seen in the middle of a sentence
+    capitalized: HashMap<String, u32>,
+}
+
+/// Word and word-pair counts of some text.
+#[derive(Debug, Clone, Default)]
+struct Counts {
+    words: HashMap<String, WordCount>,
+    /// Successors of each word within a sentence
+    next: HashMap<String, HashMap<String, u32>>,
+}
+
+impl Counts {
+    fn from_text(text: &str) -> Self {
+        let mut counts = Self::default();
+        let mut previous: Option<String> = None;
+
+        for token in tokenize(text) {
+            let key = token.word.to_lowercase();
+            let entry = counts.words.entry(key.clone()).or_default();
+            entry.total += 1;
+            if token.word == key {
+                entry.lowercase += 1;
+            } else if !token.sentence_start {
+                *entry.capitalized.entry(token.word.to_string()).or_default() += 1;
+            }
+
+            if token.sentence_start {
+                previous = None;
+            }
+            if let Some(previous) = previous {
+                *counts
+                    .next
+                    .entry(previous)
+                    .or_default()
+                    .entry(key.clone())
+                    .or_default() += 1;
+            }
+            previous = Some(key);
+        }
+        counts
+    }
+
+    /// Add (`sign = 1`) or remove (`sign = -1`) `other` from these counts.
+    fn apply(&mut self, other: &Counts, sign: i64) {
+        fn adjust(value: &mut u32, delta: u32, sign: i64) {
+            *value = (*value as i64 + sign * delta as i64).max(0) as u32;
+        }
+
+        for (key, count) in &other.words {
+            let entry = self.words.entry(key.clone()).or_default();
+            adjust(&mut entry.total, count.total, sign);
+            adjust(&mut entry.lowercase, count.lowercase, sign);
+            for (form, n) in &count.capitalized {
+                let value = entry.capitalized.entry(form.clone()).or_default();
+                adjust(value, *n, sign);
+            }
+            entry.capitalized.retain(|_, n| *n > 0);
+            if entry.total == 0 {
+                self.words.remove(key);
+            }
+        }
+
+        for (key, successors) in &other.next {
+            let entry = self.next.entry(key.clone()).or_default();
+            for (word, n) in successors {
+                let value = entry.entry(word.clone()).or_default();
+                adjust(value, *n, sign);
+            }
+            entry.retain(|_, n| *n > 0);
+            if entry.is_empty() {
+                self.next.remove(key);
+            }
+        }
+    }
+}
+
+/// Language model of a project's prose.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::completion::{CorpusModel, SuggestionSource};
+///
+/// let mut model = CorpusModel::new();
+/// model.set_document("ch1.md", "Then Aldric drew his sword. Aldric smiled.");
+///
+/// let suggestions = model.suggest("ald", None, 5);
+/// assert_eq!(suggestions[0].text, "Aldric");
+/// assert_eq!(suggestions[0].source, SuggestionSource::Name);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CorpusModel {
+    /// Counts of each indexed document
+    documents: HashMap<String, Counts>,
+    /// Sum of all document counts
+    total: Counts,
+}
+
+impl CorpusModel {
+    /// Create an empty model.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index the text of a document, replacing its previous version.
+    pub fn set_document(&mut self, key: impl Into<String>, text: &str) {
+        let key = key.into();
+        let counts = Counts::from_text(text);
+        if let Some(old) = self.documents.remove(&key) {
+            self.total.apply(&old, -1);
+        }
+        self.total.apply(&counts, 1);
+        self.documents.insert(key, counts);
+    }
+
+    /// Forget a document.
+    pub fn remove_document(&mut self, key: &str) {
+        if let Some(old) = self.documents.remove(key) {
+            self.total.apply(&old, -1);
+        }
+    }
+
+    /// Number of indexed documents.
+    pub fn document_count(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Number of distinct words.
+    pub fn vocabulary_size(&self) -> usize {
+        self.total.words.len()
+    }
+
+    /// Whether the word is a name or term the project always capitalizes.
+    fn is_name(count: &WordCount) -> bool {
+        count.lowercase == 0 && !count.capitalized.is_empty()
+    }
+
+    /// Spelling used when suggesting a word.
+    fn display_form(key: &str, count: &WordCount) -> String {
+        if Self::is_name(count) {
+            if let Some((form, _)) = count
+                .capitalized
+                .iter()
+                .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            {
+                return form.clone();
+            }
+        }
+        key.to_string()
+    }
+
+    /// Most likely word after `key`, if it clearly dominates.
+    fn dominant_successor(&self, key: &str) -> Option<&str> {
+        let successors = self.total.next.get(key)?;
+        let sum: u32 = successors.values().sum();
+        let (word, count) = successors
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))?;
+        (*count >= MIN_OCCURRENCES && count * 3 >= sum * 2).then_some(word.as_str())
+    }
+
+    /// Extend `key` with the words that usually follow it.
+    fn phrase_from(&self, key: &str, first: &str) -> Option<String> {
+        let mut phrase = first.to_string();
+        let mut current = key.to_string();
+        let mut used = vec![current.clone()];
+
+        for _ in 0..MAX_PHRASE_EXTENSION {
+            let Some(next) = self.dominant_successor(&current) else {
+                break;
+            };
+            if used.iter().any(|w| w == next) {
+                break;
+            }
+            let count = &self.total.words[next];
+            phrase.push(' ');
+            phrase.push_str(&Self::display_form(next, count));
+            current = next.to_string();
+            used.push(current.clone());
+        }
+
+        (used.len() > 1).then_some(phrase)
+    }
+
+    /// Suggest completions of `prefix`, ranked by frequency and by how often
+    /// they follow `previous`, the word before the prefix.
+    pub fn suggest(&self, prefix: &str, previous: Option<&str>, limit: usize) -> Vec<Suggestion> {
+        let prefix_len = prefix.chars().count();
+        if prefix_len < MIN_PREFIX_CHARS {
+            return Vec::new();
+        }
+        let prefix_lower = prefix.to_lowercase();
+        let capitalize = prefix.chars().next().is_some_and(char::is_uppercase);
+        let context = previous
+            .map(str::to_lowercase)
+            .and_then(|p| self.total.next.get(&p));
+
+        let mut suggestions = Vec::new();
+        for (key, count) in &self.total.words {
+            if !key.starts_with(&prefix_lower) || key.chars().count() <= prefix_len {
+                continue;
+            }
+            let name = Self::is_name(count);
+            let follows = context.and_then(|c| c.get(key)).copied().unwrap_or(0);
+            if count.total < MIN_OCCURRENCES && !name && follows == 0 {
+                continue;
+            }
+
+            let mut score = (1.0 + count.total as f32).ln();
+            score += CONTEXT_WEIGHT * (1.0 + follows as f32).ln();
+            if name {
+                score += NAME_BONUS;
+            }
+
+            let mut text = Self::display_form(key, count);
+            if capitalize && !name {
+                text = capitalize_first(&text);
+            }
+
+            if let Some(phrase) = self.phrase_from(key, &text) {
+                suggestions.push(Suggestion {
+                    text: phrase,
+                    source: SuggestionSource::Phrase,
+                    score: score - 0.5,
+                });
+            }
+            suggestions.push(Suggestion {
+                text,
+                source: if name {
+                    SuggestionSource::Name
+                } else {
+                    SuggestionSource::Corpus
+                },
+                score,
+            });
+        }
+
+        rank(&mut suggestions, limit);
+        suggestions
+    }
+}
+
+/// Uppercase the first character of `word`.
+fn capitalize_first(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// A word of the text and whether it starts a sentence.
+struct Token<'a> {
+    word: &'a str,
+    sentence_start: bool,
+}
+
+/// Whether `c` is part of a word, given its neighbors.
+///
+/// Apostrophes and hyphens only count between letters, so that `O'Brien`
+/// and `half-elf` are single words.
+pub(crate) fn is_word_char(previous: Option<char>, c: char, next: Option<char>) -> bool {
+    c.is_alphabetic()
+        || (matches!(c, '\'' | '’' | '-')
+            && previous.is_some_and(char::is_alphabetic)
+            && next.is_some_and(char::is_alphabetic))
+}
+
+/// Split prose into words, noting sentence starts.
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut sentence_start = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i].1;
+
+        // Words start with a letter
+        if c.is_alphabetic() {
+            let start = chars[i].0;
+            let mut j = i + 1;
+            while j < chars.len() {
+                let next = chars.get(j + 1).map(|&(_, c)| c);
+                if !is_word_char(Some(chars[j - 1].1), chars[j].1, next) {
+                    break;
+                }
+                j += 1;
+            }
+            let end = chars.get(j).map_or(text.len(), |&(b, _)| b);
+            tokens.push(Token {
+                word: &text[start..end],
+                sentence_start,
+            });
+            sentence_start = false;
+            i = j;
+            continue;
+        }
+
+        if matches!(c, '.' | '!' | '?' | '…' | '\n') {
+            sentence_start = true;
+        }
+        i += 1;
+    }
+    tokens
+}
+
+/// The word being typed at `cursor` (a char index), ready for completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordAtCursor {
+    /// Char index where the word starts
+    pub start: usize,
+    /// Characters typed so far
+    pub prefix: String,
+    /// Previous word in the same sentence
+    pub previous: Option<String>,
+}
+
+/// Find the word ending at `cursor`.
+///
+/// Returns `None` when the cursor is inside a word or not right after one.
+pub fn word_at_cursor(text: &str, cursor: usize) -> Option<WordAtCursor> {
+    let chars: Vec<char> = text.chars().collect();
+    if cursor > chars.len() {
+        return None;
+    }
+    if chars.get(cursor).is_some_and(|c| c.is_alphanumeric()) {
+        return None;
+    }
+
+    let word_start = |end: usize| {
+        let mut start = end;
+        while start > 0
+            && is_word_char(
+                start.checked_sub(2).map(|p| chars[p]),
+                chars[start - 1],
+                chars.get(start).copied(),
+            )
+        {
+            start -= 1;
+        }
+        // Never start on a connecting apostrophe or hyphen
+        while start < end && !chars[start].is_alphabetic() {
+            start += 1;
+        }
+        start
+    };
+
+    let start = word_start(cursor);
+    if start == cursor {
+        return None;
+    }
+
+    // Previous word, if only spaces and commas separate it from the prefix
+    let mut end = start;
+    while end > 0 && matches!(chars[end - 1], ' ' | '\t' | ',' | ';' | ':') {
+        end -= 1;
+    }
+    let previous = (end < start && end > 0 && chars[end - 1].is_alphabetic()).then(|| {
+        let previous_start = word_start(end);
+        chars[previous_start..end].iter().collect()
+    });
+
+    Some(WordAtCursor {
+        start,
+        prefix: chars[start..cursor].iter().collect(),
+        previous,
+    })
+}
+
+/// Completion popup state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    /// Word being completed
+    pub word: WordAtCursor,
+    /// Char index of the cursor
+    pub cursor: usize,
+    /// Ranked suggestions
+    pub suggestions: Vec<Suggestion>,
+    /// Highlighted suggestion
+    pub selected: usize,
+}
+
+impl Completion {
+    /// Highlight the next suggestion, wrapping around.
+    pub fn select_next(&mut self) {
+        if !self.suggestions.is_empty() {
+            self.selected = (self.selected + 1) % self.suggestions.len();
+        }
+    }
+
+    /// Highlight the previous suggestion, wrapping around.
+    pub fn select_previous(&mut self) {
+        if !self.suggestions.is_empty() {
+            self.selected = (self.selected + self.suggestions.len() - 1) % self.suggestions.len();
+        }
+    }
+
+    /// The highlighted suggestion.
+    pub fn selected(&self) -> Option<&Suggestion> {
+        self.suggestions.get(self.selected)
+    }
+}
+
+/// Documents of a project whose prose is indexed.
+pub fn project_documents(project: &Path) -> Vec<PathBuf> {
+    fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                walk(&path, files);
+            } else if path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| INDEXED_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+            {
+                files.push(path);
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    walk(&project.join("content"), &mut files);
+    files.sort();
+    files
+}
+
+/// Build the model of a project's documents, reporting progress.
+pub fn index_project(project: &Path, progress: &TaskProgress) -> anyhow::Result<CorpusModel> {
+    let files = project_documents(project);
+    let mut model = CorpusModel::new();
+
+    for (i, path) in files.iter().enumerate() {
+        progress.check_cancelled()?;
+        progress.set_position(i as u64, files.len() as u64);
+        match std::fs::read_to_string(path) {
+            Ok(text) => model.set_document(document_key(path), &text),
+            Err(e) => tracing::warn!("Cannot index {:?}: {}", path, e),
+        }
+    }
+
+    tracing::info!(
+        "Indexed {} documents ({} distinct words) for completion",
+        model.document_count(),
+        model.vocabulary_size()
+    );
+    Ok(model)
+}
+
+/// Key under which a document file is indexed.
+pub fn document_key(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(text: &str) -> CorpusModel {
+        let mut model = CorpusModel::new();
+        model.set_document("doc", text);
+        model
+    }
+
+    #[test]
+    fn test_names_keep_their_capitalization() {
+        let model = model("The Velmari came at dawn. We fled the Velmari. The valley burned.");
+        let suggestions = model.suggest("vel", None, 5);
+        assert_eq!(suggestions[0].text, "Velmari");
+        assert_eq!(suggestions[0].source, SuggestionSource::Name);
+
+        // "The" is capitalized only at sentence starts
+        assert!(model.suggest("th", None, 5).iter().all(|s| s.text != "The"));
+        assert_eq!(model.suggest("Th", None, 5)[0].text, "The");
+    }
+
+    #[test]
+    fn test_context_ranks_candidates() {
+        let model = model(
+            "a silver blade. a silver blade. a silent night. a silent night. a silent night. \
+             the silent town.",
+        );
+        assert_eq!(
+            model.suggest("sil", None, 5)[0].text.split(' ').next(),
+            Some("silent")
+        );
+        let after_a = model.suggest("sil", Some("a"), 5);
+        assert!(after_a.iter().any(|s| s.text.starts_with("silent")));
+        let after_the = model.suggest("sil", Some("the"), 5);
+        assert!(after_the[0].text.starts_with("silent"));
+    }
+
+    #[test]
+    fn test_phrase_completion() {
+        let model = model("by the light of twin moons. We walked by the light of twin moons.");
+        let suggestions = model.suggest("lig", Some("the"), 5);
+        assert!(suggestions
+            .iter()
+            .any(|s| s.source == SuggestionSource::Phrase && s.text == "light of twin moons"));
+    }
+
+    #[test]
+    fn test_rare_words_need_context_or_capitals() {
+        let model = model("an unusual thing happened");
+        assert!(model.suggest("unu", None, 5).is_empty());
+        assert_eq!(model.suggest("unu", Some("an"), 5)[0].text, "unusual");
+    }
+
+    #[test]
+    fn test_replacing_a_document_updates_counts() {
+        let mut model = model("Kethra laughed. Kethra left.");
+        assert_eq!(model.suggest("ket", None, 5).len(), 1);
+
+        model.set_document("doc", "Nobody laughed.");
+        assert!(model.suggest("ket", None, 5).is_empty());
+        model.remove_document("doc");
+        assert_eq!(model.vocabulary_size(), 0);
+    }
+
+    #[test]
+    fn test_project_words_rank_above_dictionary() {
+        let mut suggestions = vec![
+            Suggestion {
+                text: "dragon".into(),
+                source: SuggestionSource::Dictionary,
+                score: 9.0,
+            },
+            Suggestion {
+                text: "Drakmoor".into(),
+                source: SuggestionSource::Name,
+                score: 1.0,
+            },
+        ];
+        rank(&mut suggestions, 5);
+        assert_eq!(suggestions[0].text, "Drakmoor");
+    }
+
+    #[test]
+    fn test_word_at_cursor() {
+        let text = "She met O'Bri";
+        let word = word_at_cursor(text, text.chars().count()).unwrap();
+        assert_eq!(word.prefix, "O'Bri");
+        assert_eq!(word.previous.as_deref(), Some("met"));
+
+        // Inside a word, or after a space
+        assert!(word_at_cursor("walking", 3).is_none());
+        assert!(word_at_cursor("walk ", 5).is_none());
+
+        // The previous word must be in the same sentence
+        let word = word_at_cursor("Done. Th", 8).unwrap();
+        assert_eq!(word.previous, None);
+    }
+}
@@ -0,0 +1,341 @@
+//! # Project dictionary for the Markdown Editor plugin
+//!
+//! Fantasy and science fiction projects are full of invented words. The
+//! project dictionary lists them, with an optional definition, so that the
+//! editor accepts them and offers them as completions. Simple morphology
+//! rules (plural and possessive suffixes) let one entry cover its inflected
+//! forms, and the whole dictionary can be exported as a glossary appendix.
+//!
+//! The application stores the dictionary in the project settings and
+//! publishes it to plugins under [`PROJECT_DICTIONARY_KEY`]. Plugins ask for
+//! a word to be added by posting it under [`ADD_TO_DICTIONARY_REQUEST`].
+
+use crate::completion::{is_word_char, Suggestion, SuggestionSource, MIN_PREFIX_CHARS};
+use serde::{Deserialize, Serialize};
+
+/// Configuration key under which the active project's dictionary is
+/// published to plugins.
+pub const PROJECT_DICTIONARY_KEY: &str = "project_dictionary";
+
+/// Shared state key (`Option<String>`) of a word to add to the project
+/// dictionary, served by the application.
+pub const ADD_TO_DICTIONARY_REQUEST: &str = "add_to_dictionary_request";
+
+/// Suffixes stripped one after the other, so that `s` then `'` covers
+/// plural possessives.
+const MAX_SUFFIXES: usize = 2;
+
+/// Inflection rules of the project's invented words.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MorphologyRules {
+    /// Suffixes that may follow any dictionary word (plurals, possessives)
+    pub suffixes: Vec<String>,
+}
+
+impl Default for MorphologyRules {
+    fn default() -> Self {
+        Self {
+            suffixes: ["s", "es", "'s", "’s", "'", "’"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }
+    }
+}
+
+/// A word of the project dictionary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DictionaryEntry {
+    /// The word as it should be written
+    pub word: String,
+    /// Meaning, shown in the glossary appendix
+    #[serde(default)]
+    pub definition: String,
+    /// Irregular forms the suffix rules do not cover
+    #[serde(default)]
+    pub forms: Vec<String>,
+}
+
+impl DictionaryEntry {
+    /// Create an entry without definition.
+    pub fn new(word: impl Into<String>) -> Self {
+        Self {
+            word: word.into(),
+            definition: String::new(),
+            forms: Vec::new(),
+        }
+    }
+
+    /// Whether `word` is this entry or one of its irregular forms.
+    ///
+    /// Lowercase entries match any capitalization; entries with capitals
+    /// (names) must keep them, though an all-caps spelling is accepted.
+    fn matches(&self, word: &str) -> bool {
+        std::iter::once(&self.word)
+            .chain(&self.forms)
+            .any(|form| spelling_matches(form, word))
+    }
+}
+
+/// Whether `word` is an acceptable spelling of `form`.
+fn spelling_matches(form: &str, word: &str) -> bool {
+    if form == word {
+        return true;
+    }
+    if !form.to_lowercase().eq(&word.to_lowercase()) {
+        return false;
+    }
+    let form_is_lowercase = !form.chars().any(char::is_uppercase);
+    let word_is_uppercase = !word.chars().any(char::is_lowercase);
+    let capitalized = |w: &str| {
+        let mut chars = w.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+    };
+    word_is_uppercase || (form_is_lowercase && capitalized(form).as_deref() == Some(word))
+}
+
+/// The project's own words.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::dictionary::ProjectDictionary;
+///
+/// let mut dictionary = ProjectDictionary::default();
+/// dictionary.add("Velmari");
+///
+/// assert!(dictionary.contains("Velmari"));
+/// assert!(dictionary.contains("Velmaris"));
+/// assert!(dictionary.contains("Velmaris'"));
+/// assert!(!dictionary.contains("velmari"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectDictionary {
+    /// Entries, in the order they were added
+    #[serde(default)]
+    pub entries: Vec<DictionaryEntry>,
+    /// Inflection rules
+    #[serde(default)]
+    pub morphology: MorphologyRules,
+}
+
+impl ProjectDictionary {
+    /// Whether the dictionary has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entry spelled exactly `word`, ignoring case.
+    pub fn get(&self, word: &str) -> Option<&DictionaryEntry> {
+        let word = word.to_lowercase();
+        self.entries.iter().find(|e| e.word.to_lowercase() == word)
+    }
+
+    /// Add a word. Returns `false` if it is blank or already listed.
+    pub fn add(&mut self, word: &str) -> bool {
+        let word = word.trim();
+        if word.is_empty() || self.get(word).is_some() {
+            return false;
+        }
+        self.entries.push(DictionaryEntry::new(word));
+        true
+    }
+
+    /// Remove a word. Returns `false` if it was not listed.
+    pub fn remove(&mut self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        let before = self.entries.len();
+        self.entries.retain(|e| e.word.to_lowercase() != word);
+        self.entries.len() != before
+    }
+
+    /// Entry of which `word` is a form, if any.
+    pub fn lemma(&self, word: &str) -> Option<&DictionaryEntry> {
+        self.lemma_within(word, MAX_SUFFIXES)
+    }
+
+    fn lemma_within(&self, word: &str, suffixes_left: usize) -> Option<&DictionaryEntry> {
+        if let Some(entry) = self.entries.iter().find(|e| e.matches(word)) {
+            return Some(entry);
+        }
+        if suffixes_left == 0 {
+            return None;
+        }
+        let lowercase = word.to_lowercase();
+        self.morphology
+            .suffixes
+            .iter()
+            .filter(|suffix| !suffix.is_empty() && lowercase.ends_with(&suffix.to_lowercase()))
+            .find_map(|suffix| {
+                let stem_len = word.len().checked_sub(suffix.len())?;
+                let stem = word.get(..stem_len).filter(|stem| !stem.is_empty())?;
+                self.lemma_within(stem, suffixes_left - 1)
+            })
+    }
+
+    /// Whether the spell checker should accept `word`.
+    pub fn contains(&self, word: &str) -> bool {
+        self.lemma(word).is_some()
+    }
+
+    /// Dictionary words starting with `prefix`, for the completion popup.
+    pub fn suggest(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        if prefix.chars().count() < MIN_PREFIX_CHARS {
+            return Vec::new();
+        }
+        let prefix_lower = prefix.to_lowercase();
+        let capitalize = prefix.chars().next().is_some_and(char::is_uppercase);
+        let mut suggestions: Vec<Suggestion> = self
+            .entries
+            .iter()
+            .filter(|e| {
+                let word = e.word.to_lowercase();
+                word.starts_with(&prefix_lower) && word != prefix_lower
+            })
+            .map(|e| {
+                let mut text = e.word.clone();
+                if capitalize {
+                    let mut chars = text.chars();
+                    if let Some(first) = chars.next() {
+                        text = first.to_uppercase().chain(chars).collect();
+                    }
+                }
+                Suggestion {
+                    text,
+                    source: SuggestionSource::Dictionary,
+                    // Shorter words first
+                    score: -(e.word.chars().count() as f32),
+                }
+            })
+            .collect();
+        crate::completion::rank(&mut suggestions, limit);
+        suggestions
+    }
+
+    /// The dictionary as a Markdown glossary, sorted alphabetically.
+    pub fn to_glossary(&self, title: &str) -> String {
+        let mut entries: Vec<&DictionaryEntry> = self.entries.iter().collect();
+        entries.sort_by_cached_key(|e| (e.word.to_lowercase(), e.word.clone()));
+
+        let mut glossary = format!("# {}\n", title);
+        for entry in entries {
+            glossary.push_str(&format!("\n**{}**", entry.word));
+            let definition = entry.definition.trim();
+            if !definition.is_empty() {
+                glossary.push_str(&format!(" — {}", definition));
+            }
+            glossary.push('\n');
+        }
+        glossary
+    }
+}
+
+/// The word around `cursor` (a char index), if any.
+pub fn word_at(text: &str, cursor: usize) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let is_word = |i: usize| {
+        chars.get(i).is_some_and(|&c| {
+            is_word_char(
+                i.checked_sub(1).map(|p| chars[p]),
+                c,
+                chars.get(i + 1).copied(),
+            )
+        })
+    };
+
+    let mut start = cursor.min(chars.len());
+    while start > 0 && is_word(start - 1) {
+        start -= 1;
+    }
+    let mut end = cursor.min(chars.len());
+    while is_word(end) {
+        end += 1;
+    }
+    (start < end).then(|| chars[start..end].iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(words: &[&str]) -> ProjectDictionary {
+        let mut dictionary = ProjectDictionary::default();
+        for word in words {
+            dictionary.add(word);
+        }
+        dictionary
+    }
+
+    #[test]
+    fn test_inflected_forms_are_accepted() {
+        let dictionary = dictionary(&["Velmari", "kethra"]);
+        for word in [
+            "Velmari",
+            "Velmaris",
+            "Velmari's",
+            "Velmari’s",
+            "Velmaris'",
+            "VELMARI",
+        ] {
+            assert!(dictionary.contains(word), "{word}");
+        }
+        for word in ["kethra", "Kethra", "kethras", "kethraes"] {
+            assert!(dictionary.contains(word), "{word}");
+        }
+        for word in ["velmari", "Velmarix", "kethrass's", "s"] {
+            assert!(!dictionary.contains(word), "{word}");
+        }
+        assert_eq!(dictionary.lemma("Velmaris").unwrap().word, "Velmari");
+    }
+
+    #[test]
+    fn test_irregular_forms_and_custom_rules() {
+        let mut dictionary = dictionary(&["grael"]);
+        assert!(!dictionary.contains("grelin"));
+        dictionary.entries[0].forms.push("grelin".into());
+        assert!(dictionary.contains("grelin"));
+
+        dictionary.morphology.suffixes = vec!["im".into()];
+        assert!(dictionary.contains("graelim"));
+        assert!(!dictionary.contains("graels"));
+    }
+
+    #[test]
+    fn test_add_and_remove() {
+        let mut dictionary = dictionary(&["Velmari"]);
+        assert!(!dictionary.add("velmari"));
+        assert!(!dictionary.add("  "));
+        assert!(dictionary.remove("VELMARI"));
+        assert!(dictionary.is_empty());
+    }
+
+    #[test]
+    fn test_glossary_is_sorted() {
+        let mut dictionary = dictionary(&["zorth", "Aldric"]);
+        dictionary.entries[0].definition = "a two-headed goat".into();
+        assert_eq!(
+            dictionary.to_glossary("Glossary"),
+            "# Glossary\n\n**Aldric**\n\n**zorth** — a two-headed goat\n"
+        );
+    }
+
+    #[test]
+    fn test_suggestions_come_from_the_dictionary() {
+        let dictionary = dictionary(&["zorth", "zorthling", "Zael"]);
+        let suggestions = dictionary.suggest("Zor", 5);
+        assert_eq!(suggestions[0].text, "Zorth");
+        assert_eq!(suggestions[1].text, "Zorthling");
+        assert!(suggestions
+            .iter()
+            .all(|s| s.source == SuggestionSource::Dictionary));
+    }
+
+    #[test]
+    fn test_word_at() {
+        assert_eq!(word_at("the half-elf's", 6).as_deref(), Some("half-elf's"));
+        assert_eq!(word_at("the half-elf's", 3).as_deref(), Some("the"));
+        assert_eq!(word_at("a  b", 2), None);
+    }
+}
@@ -0,0 +1,185 @@
+//! # Paragraph direction
+//!
+//! The editor lays text out left to right, as it is typed. Right-to-left
+//! paragraphs and the markers setting the direction or alignment of
+//! paragraphs (see [`cosmarium_plugin_api::direction`]) are shown with
+//! badges in the right margin, and the context menu writes the marker of the
+//! paragraph under the caret. The preview and the exports lay the paragraphs
+//! out as marked.
+
+use cosmarium_plugin_api::direction::{paragraphs, Alignment, Direction, Paragraph};
+use egui::{Align2, Color32, FontId, Galley, Painter, Pos2};
+
+use crate::pairs::Edit;
+
+/// Change of the format of a paragraph asked for from the menus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatChange {
+    /// Direction to set, `None` detecting it from the text
+    Direction(Option<Direction>),
+    /// Alignment to set, `None` leaving the direction's
+    Alignment(Option<Alignment>),
+}
+
+/// Badge shown by a paragraph laid out otherwise than left-to-right and
+/// left aligned, such as `RTL` or `RTL · center`.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::direction::badge;
+/// use cosmarium_plugin_api::direction::paragraphs;
+///
+/// let paragraphs = paragraphs("Hello.\n\n<!-- align=center -->\nשלום.");
+/// assert_eq!(badge(&paragraphs[0]), None);
+/// assert_eq!(badge(&paragraphs[1]).as_deref(), Some("RTL · center"));
+/// ```
+pub fn badge(paragraph: &Paragraph) -> Option<String> {
+    let mut parts = Vec::new();
+    if paragraph.direction == Direction::Rtl || paragraph.format.direction.is_some() {
+        parts.push(paragraph.direction.as_str().to_uppercase());
+    }
+    if let Some(alignment) = paragraph.format.alignment {
+        parts.push(alignment.as_str().to_string());
+    }
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+/// Change of `content` applying `change` to the paragraph at byte `cursor`,
+/// writing, rewriting or removing its marker line, if it changes anything.
+///
+/// The selection of the edit is the caret, kept in its place in the text.
+pub fn set_format(content: &str, cursor: usize, change: FormatChange) -> Option<Edit> {
+    let line = content[..cursor].matches('\n').count();
+    let paragraph = paragraphs(content)
+        .into_iter()
+        .find(|p| p.lines.contains(&line) || p.marker == Some(line))?;
+
+    let mut format = paragraph.format;
+    match change {
+        FormatChange::Direction(direction) => format.direction = direction,
+        FormatChange::Alignment(alignment) => format.alignment = alignment,
+    }
+    if format == paragraph.format {
+        return None;
+    }
+
+    let line_start = |line: usize| {
+        content
+            .split_inclusive('\n')
+            .take(line)
+            .map(str::len)
+            .sum::<usize>()
+    };
+    let range = match paragraph.marker {
+        Some(marker) => line_start(marker)..line_start(marker + 1),
+        None => {
+            let start = line_start(paragraph.lines.start);
+            start..start
+        }
+    };
+    let text = format
+        .marker()
+        .map(|marker| format!("{}\n", marker))
+        .unwrap_or_default();
+    let caret = if cursor >= range.end {
+        cursor + text.len() - range.len()
+    } else {
+        // On the replaced marker line
+        range.start
+    };
+    Some(Edit {
+        range,
+        text,
+        selection: caret..caret,
+    })
+}
+
+/// Draw the badges of the formatted paragraphs of `content`, laid out in
+/// `galley` at `galley_pos`, by the right edge of `painter`'s clip rect.
+pub fn paint(
+    painter: &Painter,
+    content: &str,
+    galley: &Galley,
+    galley_pos: Pos2,
+    font_id: &FontId,
+    color: Color32,
+) {
+    let badges: Vec<(usize, String)> = paragraphs(content)
+        .iter()
+        .filter_map(|p| Some((p.lines.start, badge(p)?)))
+        .collect();
+    if badges.is_empty() {
+        return;
+    }
+
+    let clip = painter.clip_rect();
+    let mut badges = badges.into_iter().peekable();
+    // Line of the content the row starts
+    let mut line = 0;
+    let mut line_start = true;
+    for row in &galley.rows {
+        let badge = line_start
+            .then(|| badges.next_if(|(start, _)| *start == line))
+            .flatten();
+        if let Some((_, badge)) = badge {
+            let rect = row.rect().translate(galley_pos.to_vec2());
+            if clip.intersects(rect) {
+                let pos = Pos2::new(clip.right() - 4.0, rect.center().y);
+                painter.text(pos, Align2::RIGHT_CENTER, badge, font_id.clone(), color);
+            }
+        }
+        line_start = row.ends_with_newline;
+        line += usize::from(row.ends_with_newline);
+        if badges.peek().is_none() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(content: &str, edit: &Edit) -> String {
+        let mut content = content.to_string();
+        content.replace_range(edit.range.clone(), &edit.text);
+        content
+    }
+
+    #[test]
+    fn test_set_format_writes_markers() {
+        let text = "Intro.\n\nשלום\nעולם\n";
+        let cursor = text.find("עולם").unwrap();
+        let edit = set_format(
+            text,
+            cursor,
+            FormatChange::Alignment(Some(Alignment::Center)),
+        )
+        .unwrap();
+        let centered = apply(text, &edit);
+        assert_eq!(centered, "Intro.\n\n<!-- align=center -->\nשלום\nעולם\n");
+        // The caret stays on its word
+        assert!(centered[edit.selection.start..].starts_with("עולם"));
+
+        let cursor = centered.find("עולם").unwrap();
+        let edit = set_format(
+            &centered,
+            cursor,
+            FormatChange::Direction(Some(Direction::Ltr)),
+        )
+        .unwrap();
+        let marked = apply(&centered, &edit);
+        assert_eq!(
+            marked,
+            "Intro.\n\n<!-- dir=ltr align=center -->\nשלום\nעולם\n"
+        );
+
+        // Removing the last setting removes the marker
+        let edit = set_format(&marked, 9, FormatChange::Direction(None)).unwrap();
+        let edit = set_format(&apply(&marked, &edit), 9, FormatChange::Alignment(None)).unwrap();
+        assert_eq!(edit.text, "");
+        assert!(set_format(text, 0, FormatChange::Direction(None)).is_none());
+        assert!(set_format(text, 7, FormatChange::Direction(None)).is_none());
+    }
+}
@@ -0,0 +1,286 @@
+//! # Document tabs of the Markdown Editor plugin
+//!
+//! The application owns the documents (in its `DocumentManager`); the editor
+//! shows each open document in a tab and edits a copy of its content. The two
+//! sides exchange documents through shared state queues:
+//!
+//! - [`OPEN_DOCUMENTS_REQUEST`]: documents the application asks the editor
+//!   to show, as [`DocumentBuffer`]s. The last one becomes the active tab.
+//! - [`DOCUMENT_UPDATES`]: edited content the application writes back to its
+//!   documents, as [`DocumentBuffer`]s.
+//! - [`REPLACE_DOCUMENTS_REQUEST`]: content the application changed itself,
+//!   such as lines wrapped on save, shown in the tabs of the documents.
+//! - [`SAVE_DOCUMENTS_REQUEST`] and [`CLOSE_DOCUMENTS_REQUEST`]: documents
+//!   the editor asks the application to save or close, by identifier.
+//!
+//! The editor publishes the identifier of its active tab under
+//! [`ACTIVE_DOCUMENT_KEY`], its word count under [`WORD_COUNT_KEY`] and the
+//! position of its caret under [`CURSOR_LOCATION_KEY`].
+//!
+//! The caret, selection and scroll offset of every document are remembered
+//! in [`DocumentPositions`], by file, and restored when the document is shown
+//! again, in this session or the next one.
+
+use cosmarium_plugin_api::PluginContext;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Shared state queue (`Vec<DocumentBuffer>`) of documents to open in tabs.
+pub const OPEN_DOCUMENTS_REQUEST: &str = "editor_open_documents";
+
+/// Shared state queue (`Vec<DocumentBuffer>`) of edited document content.
+pub const DOCUMENT_UPDATES: &str = "editor_document_updates";
+
+/// Shared state queue (`Vec<DocumentBuffer>`) of document content changed by
+/// the application, replacing the content of their tabs.
+pub const REPLACE_DOCUMENTS_REQUEST: &str = "editor_replace_documents";
+
+/// Shared state queue (`Vec<Uuid>`) of documents to save.
+pub const SAVE_DOCUMENTS_REQUEST: &str = "editor_save_documents";
+
+/// Shared state queue (`Vec<Uuid>`) of documents whose tab was closed.
+pub const CLOSE_DOCUMENTS_REQUEST: &str = "editor_close_documents";
+
+/// Shared state key (`Option<Uuid>`) of the document in the active tab.
+pub const ACTIVE_DOCUMENT_KEY: &str = "editor_active_document";
+
+/// Shared state key (`(Uuid, usize)`) of the word count of the document in
+/// the active tab, with its identifier.
+pub const WORD_COUNT_KEY: &str = "editor_document_word_count";
+
+/// Shared state key (`Option<(PathBuf, usize)>`) of the file and line (from
+/// 1) of the caret, when the active tab shows a saved document.
+pub const CURSOR_LOCATION_KEY: &str = "editor_cursor_location";
+
+/// Most documents whose positions are remembered.
+const MAX_REMEMBERED_DOCUMENTS: usize = 200;
+
+/// A document and its content, as exchanged between the application and
+/// the editor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentBuffer {
+    /// Document identifier in the document manager
+    pub id: Uuid,
+    /// Tab title
+    #[serde(default)]
+    pub title: String,
+    /// Path of the document file, if saved
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Document content
+    pub content: String,
+}
+
+/// Append `item` to the shared state queue `key`.
+pub fn push<T: Clone + Send + Sync + 'static>(ctx: &mut PluginContext, key: &str, item: T) {
+    let mut queue: Vec<T> = ctx.get_shared_state(key).unwrap_or_default();
+    queue.push(item);
+    ctx.set_shared_state(key, queue);
+}
+
+/// Take every item of the shared state queue `key`.
+pub fn drain<T: Clone + Send + Sync + 'static>(ctx: &mut PluginContext, key: &str) -> Vec<T> {
+    let queue: Vec<T> = ctx.get_shared_state(key).unwrap_or_default();
+    if !queue.is_empty() {
+        ctx.set_shared_state(key, Vec::<T>::new());
+    }
+    queue
+}
+
+/// Queue the edited content of a document for the application, replacing
+/// any update of the same document not yet taken.
+pub fn push_update(ctx: &mut PluginContext, buffer: DocumentBuffer) {
+    let mut queue: Vec<DocumentBuffer> = ctx.get_shared_state(DOCUMENT_UPDATES).unwrap_or_default();
+    queue.retain(|pending| pending.id != buffer.id);
+    queue.push(buffer);
+    ctx.set_shared_state(DOCUMENT_UPDATES, queue);
+}
+
+/// Caret, selection and scroll offset of a document in an editor view.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ViewPosition {
+    /// Name of the editor view (split pane)
+    pub view: String,
+    /// Character index of the caret
+    pub caret: usize,
+    /// Character index of the other end of the selection
+    pub anchor: usize,
+    /// Vertical scroll offset, in points
+    pub scroll: f32,
+}
+
+impl ViewPosition {
+    /// TextEdit state putting the caret and selection back.
+    pub fn text_edit_state(&self) -> egui::text_edit::TextEditState {
+        let mut state = egui::text_edit::TextEditState::default();
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::two(
+                egui::text::CCursor::new(self.anchor),
+                egui::text::CCursor::new(self.caret),
+            )));
+        state
+    }
+}
+
+/// Last positions of documents in the editor views, by file, most recently
+/// used first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DocumentPositions(Vec<(PathBuf, Vec<ViewPosition>)>);
+
+impl DocumentPositions {
+    /// Position of a document in a view, or in any view when it was never
+    /// shown in this one (views are not the same from one session to the
+    /// next).
+    pub fn get(&self, path: &Path, view: &str) -> Option<&ViewPosition> {
+        let (_, views) = self.0.iter().find(|(p, _)| p == path)?;
+        views
+            .iter()
+            .find(|position| position.view == view)
+            .or_else(|| views.first())
+    }
+
+    /// Remember the position of a document in a view. Returns whether it
+    /// changed.
+    pub fn set(&mut self, path: &Path, position: ViewPosition) -> bool {
+        let index = match self.0.iter().position(|(p, _)| p == path) {
+            Some(index) => index,
+            None => {
+                self.0.insert(0, (path.to_path_buf(), Vec::new()));
+                self.0.truncate(MAX_REMEMBERED_DOCUMENTS);
+                0
+            }
+        };
+        let views = &mut self.0[index].1;
+        let changed = match views.iter_mut().find(|p| p.view == position.view) {
+            Some(known) if *known == position => false,
+            Some(known) => {
+                *known = position;
+                true
+            }
+            None => {
+                views.push(position);
+                true
+            }
+        };
+        if index > 0 {
+            let entry = self.0.remove(index);
+            self.0.insert(0, entry);
+        }
+        changed || index > 0
+    }
+}
+
+/// A document open in a tab of the editor.
+#[derive(Clone)]
+pub(crate) struct DocumentTab {
+    /// The document; its content is stale while the tab is active
+    pub buffer: DocumentBuffer,
+    /// Whether the tab holds edits not saved yet
+    pub has_changes: bool,
+    /// Undo history of the tab, while another tab is active
+    pub history: crate::editor::MarkdownEditor,
+    /// Caret and selection of each editor view, while another tab is active
+    pub edit_states: Vec<(String, egui::text_edit::TextEditState)>,
+    /// Revision of the content published last, while another tab is active
+    pub revision: u64,
+}
+
+impl DocumentTab {
+    pub fn new(buffer: DocumentBuffer) -> Self {
+        Self {
+            buffer,
+            has_changes: false,
+            history: crate::editor::MarkdownEditor::new(),
+            edit_states: Vec::new(),
+            revision: 0,
+        }
+    }
+
+    /// Title shown on the tab.
+    pub fn title(&self) -> &str {
+        if self.buffer.title.is_empty() {
+            "Untitled"
+        } else {
+            &self.buffer.title
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(content: &str) -> DocumentBuffer {
+        DocumentBuffer {
+            id: Uuid::new_v4(),
+            title: "Chapter".into(),
+            path: None,
+            content: content.into(),
+        }
+    }
+
+    #[test]
+    fn test_queues_are_drained_once() {
+        let mut ctx = PluginContext::new();
+        let id = Uuid::new_v4();
+        push(&mut ctx, SAVE_DOCUMENTS_REQUEST, id);
+        push(&mut ctx, SAVE_DOCUMENTS_REQUEST, id);
+
+        assert_eq!(
+            drain::<Uuid>(&mut ctx, SAVE_DOCUMENTS_REQUEST),
+            vec![id, id]
+        );
+        assert!(drain::<Uuid>(&mut ctx, SAVE_DOCUMENTS_REQUEST).is_empty());
+    }
+
+    #[test]
+    fn test_updates_keep_latest_content() {
+        let mut ctx = PluginContext::new();
+        let first = buffer("one");
+        let other = buffer("other");
+        push_update(&mut ctx, first.clone());
+        push_update(&mut ctx, other.clone());
+        push_update(
+            &mut ctx,
+            DocumentBuffer {
+                content: "one, edited".into(),
+                ..first.clone()
+            },
+        );
+
+        let updates: Vec<DocumentBuffer> = drain(&mut ctx, DOCUMENT_UPDATES);
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0], other);
+        assert_eq!(updates[1].content, "one, edited");
+    }
+
+    #[test]
+    fn test_positions_by_document_and_view() {
+        let position = |view: &str, caret| ViewPosition {
+            view: view.into(),
+            caret,
+            anchor: caret,
+            scroll: 0.0,
+        };
+        let (one, two) = (Path::new("one.md"), Path::new("two.md"));
+        let mut positions = DocumentPositions::default();
+        assert!(positions.set(one, position("Main View", 10)));
+        assert!(positions.set(one, position("Editor 2", 500)));
+        assert!(!positions.set(one, position("Main View", 10)));
+        assert!(positions.set(two, position("Main View", 3)));
+
+        assert_eq!(positions.get(one, "Editor 2").unwrap().caret, 500);
+        // Views of another session fall back to the first known one
+        assert_eq!(positions.get(one, "Editor 3").unwrap().caret, 10);
+        assert!(positions.get(Path::new("three.md"), "Main View").is_none());
+
+        // Using a document again moves it first
+        assert!(positions.set(one, position("Main View", 10)));
+        assert_eq!(positions.0[0].0, one);
+
+        let state = position("Main View", 7).text_edit_state();
+        assert_eq!(state.cursor.char_range().unwrap().primary.index, 7);
+    }
+}
@@ -3,9 +3,80 @@
 //! This module provides the core markdown editing functionality for the
 //! Cosmarium creative writing software. It handles text editing, syntax
 //! highlighting, and editor-specific features for an optimal writing experience.
+//!
+//! Undo history stores the changes of the content as [`Delta`]s. Characters
+//! typed or deleted one after the other are undone together, a word or a
+//! sentence at a time as the [`UndoSettings`] of the application tell; the
+//! editor publishes how many steps can be undone and redone as an
+//! [`UndoState`].
 
+use crate::buffer::Delta;
 use serde::{Deserialize, Serialize};
 
+/// Shared state key ([`UndoSettings`]) of the undo settings, set by the
+/// application.
+pub const UNDO_SETTINGS_KEY: &str = "markdown_editor_undo_settings";
+
+/// Shared state key ([`UndoState`]) of the steps of the active document
+/// that can be undone and redone, set by the editor.
+pub const UNDO_STATE_KEY: &str = "markdown_editor_undo_state";
+
+/// How much of the typing one undo step takes back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UndoGrouping {
+    /// A word and the spaces after it
+    #[default]
+    Word,
+    /// A sentence and the spaces after it
+    Sentence,
+}
+
+impl UndoGrouping {
+    /// Whether typing `next` after `typed` starts a new step.
+    fn breaks(self, typed: &str, next: &str) -> bool {
+        let after_space = typed.ends_with(char::is_whitespace);
+        let starts_word = !next.starts_with(char::is_whitespace);
+        match self {
+            Self::Word => after_space && starts_word,
+            Self::Sentence => {
+                after_space
+                    && starts_word
+                    && typed
+                        .trim_end()
+                        .ends_with(['.', '!', '?', '…', '"', '»', '”'])
+            }
+        }
+    }
+}
+
+/// Undo settings of the application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UndoSettings {
+    /// Most steps kept in the history of each document
+    pub depth: usize,
+    /// How typing is grouped into steps
+    pub grouping: UndoGrouping,
+}
+
+impl Default for UndoSettings {
+    fn default() -> Self {
+        Self {
+            depth: 100,
+            grouping: UndoGrouping::Word,
+        }
+    }
+}
+
+/// Steps of a document's history.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UndoState {
+    /// Steps that can be undone
+    pub undo: usize,
+    /// Steps undone that can be redone
+    pub redo: usize,
+}
+
 /// Core markdown editor implementation.
 ///
 /// The [`MarkdownEditor`] provides text editing capabilities optimized
@@ -16,12 +87,18 @@ pub struct MarkdownEditor {
     cursor_position: usize,
     /// Current selection range (start, end)
     selection: Option<(usize, usize)>,
-    /// Undo history
-    undo_history: Vec<String>,
-    /// Redo history
-    redo_history: Vec<String>,
+    /// Changes that can be undone, the last one last
+    undo_history: Vec<Delta>,
+    /// Changes undone that can be redone, the last one undone last
+    redo_history: Vec<Delta>,
     /// Maximum undo history size
     max_undo_history: usize,
+    /// How typing is grouped into steps
+    #[serde(default)]
+    grouping: UndoGrouping,
+    /// Whether the last step may take in more typing
+    #[serde(skip)]
+    open: bool,
 }
 
 impl MarkdownEditor {
@@ -41,9 +118,23 @@ impl MarkdownEditor {
             undo_history: Vec::new(),
             redo_history: Vec::new(),
             max_undo_history: 100,
+            grouping: UndoGrouping::Word,
+            open: false,
         }
     }
 
+    /// Follow the undo settings of the application, forgetting the oldest
+    /// steps beyond the new depth.
+    pub fn set_settings(&mut self, settings: &UndoSettings) {
+        self.max_undo_history = settings.depth.max(1);
+        self.grouping = settings.grouping;
+        let excess = self
+            .undo_history
+            .len()
+            .saturating_sub(self.max_undo_history);
+        self.undo_history.drain(..excess);
+    }
+
     /// Get the current cursor position.
     pub fn cursor_position(&self) -> usize {
         self.cursor_position
@@ -69,37 +160,59 @@ impl MarkdownEditor {
         self.selection = None;
     }
 
-    /// Add a state to the undo history.
-    pub fn add_to_history(&mut self, content: String) {
-        self.undo_history.push(content);
+    /// Add a change of the content to the undo history.
+    ///
+    /// A character typed or deleted next to the last ones joins their step,
+    /// unless it starts a new word or sentence; other changes are steps of
+    /// their own.
+    pub fn add_to_history(&mut self, delta: Delta) {
+        // Clear redo history when new changes are made
+        self.redo_history.clear();
+
+        let typing = matches!(
+            (
+                delta.removed.chars().count(),
+                delta.inserted.chars().count()
+            ),
+            (0, 1) | (1, 0)
+        );
+        // A new word or sentence starts a new step
+        let grouping = self.grouping;
+        let joins = |last: &Delta| {
+            delta.inserted.is_empty() || !grouping.breaks(&last.inserted, &delta.inserted)
+        };
+        if let Some(last) = self.undo_history.last_mut().filter(|_| typing && self.open) {
+            if joins(last) && merge(last, &delta) {
+                return;
+            }
+        }
+        self.open = typing;
+        self.undo_history.push(delta);
 
         // Limit history size
         if self.undo_history.len() > self.max_undo_history {
             self.undo_history.remove(0);
         }
-
-        // Clear redo history when new changes are made
-        self.redo_history.clear();
     }
 
-    /// Undo the last change.
-    pub fn undo(&mut self, current_content: String) -> Option<String> {
-        if let Some(prev) = self.undo_history.pop() {
-            self.redo_history.push(current_content);
-            Some(prev)
-        } else {
-            None
-        }
+    /// Undo the last change of `content`, and return the change made to
+    /// undo it.
+    pub fn undo(&mut self, content: &mut String) -> Option<Delta> {
+        let delta = self.undo_history.pop()?;
+        self.open = false;
+        let inverse = delta.inverse();
+        inverse.apply(content);
+        self.redo_history.push(delta);
+        Some(inverse)
     }
 
-    /// Redo the last undone change.
-    pub fn redo(&mut self, current_content: String) -> Option<String> {
-        if let Some(next) = self.redo_history.pop() {
-            self.undo_history.push(current_content);
-            Some(next)
-        } else {
-            None
-        }
+    /// Redo the last change undone in `content`, and return it.
+    pub fn redo(&mut self, content: &mut String) -> Option<Delta> {
+        let delta = self.redo_history.pop()?;
+        self.open = false;
+        delta.apply(content);
+        self.undo_history.push(delta.clone());
+        Some(delta)
     }
 
     /// Check if undo is available.
@@ -111,6 +224,39 @@ impl MarkdownEditor {
     pub fn can_redo(&self) -> bool {
         !self.redo_history.is_empty()
     }
+
+    /// Steps that can be undone and redone.
+    pub fn state(&self) -> UndoState {
+        UndoState {
+            undo: self.undo_history.len(),
+            redo: self.redo_history.len(),
+        }
+    }
+}
+
+/// Make `next`, a change right after `last`, part of it, if it types or
+/// deletes at its end.
+fn merge(last: &mut Delta, next: &Delta) -> bool {
+    let end = last.at + last.inserted.len();
+    if next.removed.is_empty() && next.at == end {
+        last.inserted.push_str(&next.inserted);
+    } else if next.inserted.is_empty() && next.at == end {
+        // Deleted forward
+        last.removed.push_str(&next.removed);
+    } else if next.inserted.is_empty() && next.at + next.removed.len() == end {
+        // Deleted backward, into the text typed or before it
+        if next.at >= last.at {
+            last.inserted.truncate(next.at - last.at);
+        } else {
+            let before = last.at - next.at;
+            last.removed.insert_str(0, &next.removed[..before]);
+            last.at = next.at;
+            last.inserted.clear();
+        }
+    } else {
+        return false;
+    }
+    true
 }
 
 impl Default for MarkdownEditor {
@@ -154,19 +300,88 @@ mod tests {
         let mut editor = MarkdownEditor::new();
 
         // Simulate change from "first state" to "second state"
-        editor.add_to_history("first state".to_string());
+        let mut content = "second state".to_string();
+        editor.add_to_history(Delta {
+            at: 0,
+            removed: "first".to_string(),
+            inserted: "second".to_string(),
+        });
 
         assert!(editor.can_undo());
         assert!(!editor.can_redo());
 
-        // Undo from "second state"
-        let undone = editor.undo("second state".to_string()).unwrap();
-        assert_eq!(undone, "first state");
+        let undone = editor.undo(&mut content).unwrap();
+        assert_eq!(content, "first state");
+        assert_eq!(undone.inserted, "first");
         assert!(editor.can_redo());
 
-        // Redo from "first state"
-        let redone = editor.redo("first state".to_string()).unwrap();
-        assert_eq!(redone, "second state");
+        editor.redo(&mut content).unwrap();
+        assert_eq!(content, "second state");
         assert!(!editor.can_redo());
     }
+
+    /// Type `text` one character at a time at the end of `content`.
+    fn type_text(editor: &mut MarkdownEditor, content: &mut String, text: &str) {
+        for c in text.chars() {
+            editor.add_to_history(Delta {
+                at: content.len(),
+                removed: String::new(),
+                inserted: c.to_string(),
+            });
+            content.push(c);
+        }
+    }
+
+    #[test]
+    fn test_typing_is_undone_by_word() {
+        let mut editor = MarkdownEditor::new();
+        let mut content = String::new();
+        type_text(&mut editor, &mut content, "The cat  sat");
+        // Backspace twice, into the word typed
+        for _ in 0..2 {
+            let at = content.len() - 1;
+            editor.add_to_history(Delta {
+                at,
+                removed: content.split_off(at),
+                inserted: String::new(),
+            });
+        }
+        assert_eq!(content, "The cat  s");
+        assert_eq!(editor.state(), UndoState { undo: 3, redo: 0 });
+
+        editor.undo(&mut content);
+        assert_eq!(content, "The cat  ");
+        editor.undo(&mut content);
+        assert_eq!(content, "The ");
+
+        // Undoing closes the step: typing again starts a new one
+        type_text(&mut editor, &mut content, "dog");
+        assert_eq!(editor.state(), UndoState { undo: 2, redo: 0 });
+
+        // Pasted text is a step of its own
+        editor.add_to_history(Delta {
+            at: content.len(),
+            removed: String::new(),
+            inserted: " ran".to_string(),
+        });
+        assert_eq!(editor.state().undo, 3);
+    }
+
+    #[test]
+    fn test_typing_is_undone_by_sentence() {
+        let mut editor = MarkdownEditor::new();
+        editor.set_settings(&UndoSettings {
+            depth: 2,
+            grouping: UndoGrouping::Sentence,
+        });
+        let mut content = String::new();
+        type_text(&mut editor, &mut content, "One. Two, three! Four");
+        assert_eq!(editor.state().undo, 2);
+
+        editor.undo(&mut content);
+        assert_eq!(content, "One. Two, three! ");
+        editor.undo(&mut content);
+        assert_eq!(content, "One. ");
+        assert!(!editor.can_undo());
+    }
 }
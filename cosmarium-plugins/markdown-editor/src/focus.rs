@@ -0,0 +1,153 @@
+//! # Focus mode
+//!
+//! Distraction-free writing: the application hides its menus, side panels
+//! and status bar, the editor sets the text in a column of limited width in
+//! the middle of the view, and can dim every paragraph but the one with the
+//! caret. Escape leaves focus mode.
+//!
+//! The editor publishes whether focus mode is on under [`FOCUS_MODE_KEY`],
+//! and turns it on or off when the application sends it
+//! [`EditorCommand::ToggleFocusMode`](crate::commands::EditorCommand::ToggleFocusMode).
+//! The width of the column and the dimming are [`FocusSettings`] of the
+//! application.
+
+use egui::text::{LayoutJob, LayoutSection};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Shared state key (`bool`) of whether focus mode is on, set by the
+/// editor.
+pub const FOCUS_MODE_KEY: &str = "markdown_editor_focus_mode";
+
+/// Shared state key ([`FocusSettings`]) of the focus mode settings, set by
+/// the application.
+pub const FOCUS_SETTINGS_KEY: &str = "markdown_editor_focus_settings";
+
+/// Opacity of the dimmed paragraphs.
+pub const DIMMED_OPACITY: f32 = 0.35;
+
+/// Narrowest column of text, in points.
+pub const MIN_WIDTH: f32 = 200.0;
+
+/// How focus mode shows the text.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FocusSettings {
+    /// Widest column of text, in points
+    pub width: f32,
+    /// Whether all paragraphs but the one with the caret are dimmed
+    pub dim_paragraphs: bool,
+}
+
+impl Default for FocusSettings {
+    fn default() -> Self {
+        Self {
+            width: 720.0,
+            dim_paragraphs: true,
+        }
+    }
+}
+
+/// Byte range of the paragraph of `content` around byte `offset`: its
+/// lines up to the blank lines around them. On a blank line, the line
+/// itself.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::focus::paragraph_at;
+///
+/// let content = "One.\n\nTwo,\nthree.\n\nFour.";
+/// assert_eq!(&content[paragraph_at(content, 8)], "Two,\nthree.");
+/// ```
+pub fn paragraph_at(content: &str, offset: usize) -> Range<usize> {
+    let is_blank = |line: &str| line.trim().is_empty();
+    let line_start = content[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = content[offset..]
+        .find('\n')
+        .map_or(content.len(), |i| offset + i);
+    if is_blank(&content[line_start..line_end]) {
+        return line_start..line_end;
+    }
+
+    let mut start = line_start;
+    while start > 0 {
+        let previous = content[..start - 1].rfind('\n').map_or(0, |i| i + 1);
+        if is_blank(&content[previous..start - 1]) {
+            break;
+        }
+        start = previous;
+    }
+    let mut end = line_end;
+    while end < content.len() {
+        let next = content[end + 1..]
+            .find('\n')
+            .map_or(content.len(), |i| end + 1 + i);
+        if is_blank(&content[end + 1..next]) {
+            break;
+        }
+        end = next;
+    }
+    start..end
+}
+
+/// Dim the text of `job` outside of the byte range `keep`.
+pub fn dim(job: &mut LayoutJob, keep: Range<usize>) {
+    for section in std::mem::take(&mut job.sections) {
+        let range = section.byte_range.clone();
+        let cuts = [keep.start, keep.end]
+            .into_iter()
+            .filter(|cut| range.start < *cut && *cut < range.end);
+        let mut start = range.start;
+        for end in cuts.chain([range.end]) {
+            let mut format = section.format.clone();
+            if end <= keep.start || keep.end <= start {
+                format.color = format.color.gamma_multiply(DIMMED_OPACITY);
+            }
+            job.sections.push(LayoutSection {
+                leading_space: if start == range.start {
+                    section.leading_space
+                } else {
+                    0.0
+                },
+                byte_range: start..end,
+                format,
+            });
+            start = end;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{Color32, FontId};
+
+    #[test]
+    fn test_paragraph_at() {
+        let content = "# One\n\nTwo\nthree\n  \nfour";
+        assert_eq!(&content[paragraph_at(content, 0)], "# One");
+        assert_eq!(&content[paragraph_at(content, 7)], "Two\nthree");
+        assert_eq!(&content[paragraph_at(content, 16)], "Two\nthree");
+        assert_eq!(&content[paragraph_at(content, 6)], "");
+        assert_eq!(&content[paragraph_at(content, content.len())], "four");
+    }
+
+    #[test]
+    fn test_dim_outside_the_paragraph() {
+        let text = "One.\n\nTwo.";
+        let job = LayoutJob::simple(text.to_string(), FontId::default(), Color32::WHITE, 100.0);
+        let mut dimmed = job.clone();
+        dim(&mut dimmed, 6..10);
+        let sections: Vec<(Range<usize>, bool)> = dimmed
+            .sections
+            .iter()
+            .map(|section| {
+                (
+                    section.byte_range.clone(),
+                    section.format.color == Color32::WHITE,
+                )
+            })
+            .collect();
+        assert_eq!(sections, [(0..6, false), (6..10, true)]);
+    }
+}
@@ -0,0 +1,267 @@
+//! # Glossary term consistency
+//!
+//! The words of the project dictionary are the glossary terms of the
+//! manuscript. Once a term is defined, every occurrence should be spelled
+//! the same way: this module finds the places where a term is written with
+//! another capitalization (`velmari` for `Velmari`) or another hyphenation
+//! (`star-ship` or `star ship` for `starship`).
+
+use crate::completion::is_word_char;
+use crate::dictionary::ProjectDictionary;
+use std::collections::HashMap;
+
+/// Longest run of words checked against multi-word terms.
+const MAX_TERM_WORDS: usize = 4;
+
+/// How an occurrence differs from its glossary term.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TermIssueKind {
+    /// Same letters and separators, other capitals
+    Capitalization,
+    /// Hyphens or spaces added, removed or swapped
+    Hyphenation,
+}
+
+impl TermIssueKind {
+    /// Label shown in consistency reports.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Capitalization => "capitalization",
+            Self::Hyphenation => "hyphenation",
+        }
+    }
+}
+
+/// An occurrence of a glossary term spelled differently from the glossary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermIssue {
+    /// Line of the occurrence, starting at 1
+    pub line: usize,
+    /// Column of the occurrence in characters, starting at 1
+    pub column: usize,
+    /// The occurrence as written
+    pub found: String,
+    /// The glossary term
+    pub term: String,
+    /// What differs
+    pub kind: TermIssueKind,
+}
+
+/// A word of a line.
+struct Word {
+    /// Byte range in the line
+    start: usize,
+    end: usize,
+    /// Column, in characters from 1
+    column: usize,
+    /// Whether only spaces separate it from the next word
+    joined_to_next: bool,
+}
+
+/// Letters of `text`, lowercased, without hyphens and spaces.
+fn fold(text: &str) -> String {
+    text.chars()
+        .filter(|c| !matches!(c, '-' | ' '))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn words(line: &str) -> Vec<Word> {
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let is_word = |i: usize| {
+        is_word_char(
+            i.checked_sub(1).map(|p| chars[p].1),
+            chars[i].1,
+            chars.get(i + 1).map(|&(_, c)| c),
+        )
+    };
+
+    let mut words: Vec<Word> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if !is_word(i) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && is_word(i) {
+            i += 1;
+        }
+        let end = chars.get(i).map_or(line.len(), |&(offset, _)| offset);
+        let mut next = i;
+        while chars.get(next).is_some_and(|&(_, c)| c == ' ') {
+            next += 1;
+        }
+        words.push(Word {
+            start: chars[start].0,
+            end,
+            column: start + 1,
+            joined_to_next: next > i && next < chars.len() && is_word(next),
+        });
+    }
+    words
+}
+
+/// Occurrences of the dictionary's terms that are not spelled as in the
+/// dictionary.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::dictionary::ProjectDictionary;
+/// use cosmarium_markdown_editor::glossary::{check_terms, TermIssueKind};
+///
+/// let mut dictionary = ProjectDictionary::default();
+/// dictionary.add("starship");
+///
+/// let issues = check_terms(&dictionary, "The star-ship landed.");
+/// assert_eq!(issues[0].found, "star-ship");
+/// assert_eq!(issues[0].kind, TermIssueKind::Hyphenation);
+/// ```
+pub fn check_terms(dictionary: &ProjectDictionary, text: &str) -> Vec<TermIssue> {
+    // Terms and their forms, by folded spelling
+    let mut terms: HashMap<String, Vec<&str>> = HashMap::new();
+    for entry in &dictionary.entries {
+        for form in std::iter::once(&entry.word).chain(&entry.forms) {
+            if !form.trim().is_empty() {
+                terms.entry(fold(form)).or_default().push(&entry.word);
+            }
+        }
+    }
+    if terms.is_empty() {
+        return Vec::new();
+    }
+    let suffixes: Vec<String> = dictionary
+        .morphology
+        .suffixes
+        .iter()
+        .map(|s| fold(s))
+        .filter(|s| !s.is_empty())
+        .collect();
+    let find_term = |span: &str| -> Option<&str> {
+        let folded = fold(span);
+        terms
+            .get(&folded)
+            .or_else(|| {
+                suffixes.iter().find_map(|suffix| {
+                    folded
+                        .strip_suffix(suffix.as_str())
+                        .filter(|stem| !stem.is_empty())
+                        .and_then(|stem| terms.get(stem))
+                })
+            })
+            .and_then(|words| words.first().copied())
+    };
+
+    let mut issues = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let words = words(line);
+        let mut i = 0;
+        while i < words.len() {
+            // Longest run of words first, so that "star ship" is one occurrence
+            let mut longest = 1;
+            while longest < MAX_TERM_WORDS
+                && i + longest < words.len()
+                && words[i + longest - 1].joined_to_next
+            {
+                longest += 1;
+            }
+
+            let occurrence = (1..=longest).rev().find_map(|len| {
+                let span = &line[words[i].start..words[i + len - 1].end];
+                find_term(span).map(|term| (len, span, term))
+            });
+
+            match occurrence {
+                Some((len, span, term)) => {
+                    if !dictionary.contains(span) {
+                        let kind = if dictionary.contains(&span.to_lowercase())
+                            || dictionary.contains(&capitalize_like(span, term))
+                        {
+                            TermIssueKind::Capitalization
+                        } else {
+                            TermIssueKind::Hyphenation
+                        };
+                        issues.push(TermIssue {
+                            line: line_index + 1,
+                            column: words[i].column,
+                            found: span.to_string(),
+                            term: term.to_string(),
+                            kind,
+                        });
+                    }
+                    i += len;
+                }
+                None => i += 1,
+            }
+        }
+    }
+    issues
+}
+
+/// `span` with the capitals of `term`, letter for letter.
+fn capitalize_like(span: &str, term: &str) -> String {
+    let mut term_chars = term.chars();
+    span.chars()
+        .map(|c| match term_chars.next() {
+            Some(t) if t.is_uppercase() => c.to_uppercase().next().unwrap_or(c),
+            Some(_) => c.to_lowercase().next().unwrap_or(c),
+            None => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(words: &[&str]) -> ProjectDictionary {
+        let mut dictionary = ProjectDictionary::default();
+        for word in words {
+            dictionary.add(word);
+        }
+        dictionary
+    }
+
+    #[test]
+    fn test_consistent_text_has_no_issues() {
+        let dictionary = dictionary(&["Velmari", "starship", "sky-lord"]);
+        let text = "The Velmari's starships came.\nVELMARI! A sky-lord watched.";
+        assert!(check_terms(&dictionary, text).is_empty());
+    }
+
+    #[test]
+    fn test_capitalization_issues() {
+        let dictionary = dictionary(&["Velmari"]);
+        let issues = check_terms(&dictionary, "The Velmari met\nthe velmaris at dawn.");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, 2);
+        assert_eq!(issues[0].column, 5);
+        assert_eq!(issues[0].found, "velmaris");
+        assert_eq!(issues[0].term, "Velmari");
+        assert_eq!(issues[0].kind, TermIssueKind::Capitalization);
+    }
+
+    #[test]
+    fn test_hyphenation_issues() {
+        let dictionary = dictionary(&["starship", "sky-lord"]);
+        let issues = check_terms(&dictionary, "A star ship, a star-ship and two skylords.");
+        let found: Vec<(&str, TermIssueKind)> =
+            issues.iter().map(|i| (i.found.as_str(), i.kind)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("star ship", TermIssueKind::Hyphenation),
+                ("star-ship", TermIssueKind::Hyphenation),
+                ("skylords", TermIssueKind::Hyphenation),
+            ]
+        );
+        assert_eq!(issues[2].term, "sky-lord");
+    }
+
+    #[test]
+    fn test_words_across_punctuation_are_not_joined() {
+        let dictionary = dictionary(&["starship"]);
+        assert!(check_terms(&dictionary, "The star, ship and all.").is_empty());
+    }
+}
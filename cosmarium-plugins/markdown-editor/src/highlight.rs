@@ -0,0 +1,52 @@
+//! # Highlights
+//!
+//! Passages highlighted by analysis plugins (see
+//! [`cosmarium_plugin_api::highlight`]) get their background color in the
+//! editor, row by row when they wrap.
+
+use cosmarium_plugin_api::highlight::Highlight;
+use egui::{Galley, Painter, Pos2, Rect};
+
+/// Fill the background of the `highlights` of `content`, laid out in
+/// `galley` at `galley_pos`.
+pub fn paint(
+    painter: &Painter,
+    content: &str,
+    galley: &Galley,
+    galley_pos: Pos2,
+    highlights: &[Highlight],
+) {
+    let clip = painter.clip_rect();
+    for highlight in highlights {
+        let (Some(before), Some(passage)) = (
+            content.get(..highlight.range.start),
+            content.get(highlight.range.clone()),
+        ) else {
+            continue;
+        };
+        let start = before.chars().count();
+        let end = start + passage.chars().count();
+
+        // Rows hold consecutive characters, each followed by its newline
+        let mut row_start = 0;
+        for row in &galley.rows {
+            let row_end = row_start + row.char_count_excluding_newline();
+            let (from, to) = (start.max(row_start), end.min(row_end));
+            if from < to {
+                let rect = Rect::from_x_y_ranges(
+                    row.pos.x + row.x_offset(from - row_start)
+                        ..=row.pos.x + row.x_offset(to - row_start),
+                    row.min_y()..=row.max_y(),
+                )
+                .translate(galley_pos.to_vec2());
+                if clip.intersects(rect) {
+                    painter.rect_filled(rect, 2.0, highlight.color);
+                }
+            }
+            row_start += row.char_count_including_newline();
+            if row_start >= end {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! # Jumping to a line or a heading
+//!
+//! Ctrl+G asks for a line number and Ctrl+J lists the headings of the
+//! document, narrowed down as the writer types. The headings are those the
+//! outline plugin parsed and published under [`OUTLINE_HEADINGS_KEY`], so
+//! that the document is not parsed twice; without the outline the list is
+//! empty. Both dialogs move the caret through the
+//! [`GOTO_LINE_KEY`] request that the outline's entries already use.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_markdown_editor::jump::{filter_headings, parse_line};
+//!
+//! assert_eq!(parse_line(" 12 ", 40), Some(12));
+//! assert_eq!(parse_line("400", 40), Some(40));
+//! assert_eq!(parse_line("twelve", 40), None);
+//!
+//! let headings = vec![(1, "The Inn".to_string(), 1), (2, "Night Falls".to_string(), 9)];
+//! assert_eq!(filter_headings(&headings, "night")[0].2, 9);
+//! ```
+
+use egui::Context;
+
+/// Shared state key (`Vec<(u32, String, usize)>`) of the level, text and
+/// 1-based line of the headings of the active document, published by the
+/// outline plugin.
+pub const OUTLINE_HEADINGS_KEY: &str = "outline_headings";
+
+/// Shared state key (`usize`) of the 1-based line to move the caret of the
+/// active tab to, 0 once done.
+pub const GOTO_LINE_KEY: &str = "markdown_editor_goto_line";
+
+/// Level, text and 1-based line of a heading.
+pub type Heading = (u32, String, usize);
+
+/// Dialog open over the active tab.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JumpDialog {
+    /// Line number typed so far
+    Line { input: String },
+    /// Text the headings are filtered by, and the heading highlighted among
+    /// those shown
+    Heading { query: String, selected: usize },
+}
+
+impl JumpDialog {
+    /// Empty go-to-line dialog.
+    pub fn line() -> Self {
+        JumpDialog::Line {
+            input: String::new(),
+        }
+    }
+
+    /// Heading picker listing all headings.
+    pub fn heading() -> Self {
+        JumpDialog::Heading {
+            query: String::new(),
+            selected: 0,
+        }
+    }
+}
+
+/// What the writer did with a jump dialog this frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JumpOutcome {
+    /// Go to this 1-based line
+    Go(usize),
+    /// Close the dialog without moving
+    Cancel,
+}
+
+/// Line typed in the go-to-line dialog, kept within the `line_count` lines
+/// of the document.
+pub fn parse_line(input: &str, line_count: usize) -> Option<usize> {
+    let line = input.trim().parse::<usize>().ok()?;
+    (line > 0).then(|| line.min(line_count.max(1)))
+}
+
+/// The headings containing every word of `query`, case ignored, in
+/// document order.
+pub fn filter_headings<'a>(headings: &'a [Heading], query: &str) -> Vec<&'a Heading> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    headings
+        .iter()
+        .filter(|(_, text, _)| {
+            let text = text.to_lowercase();
+            words.iter().all(|word| text.contains(word.as_str()))
+        })
+        .collect()
+}
+
+/// Show `dialog` centered at the top of the window.
+pub fn show(
+    ctx: &Context,
+    dialog: &mut JumpDialog,
+    headings: &[Heading],
+    line_count: usize,
+) -> Option<JumpOutcome> {
+    let (down, up, enter, escape) = ctx.input_mut(|input| {
+        (
+            input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+            input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+            input.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+            input.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+        )
+    });
+    let mut outcome = escape.then_some(JumpOutcome::Cancel);
+
+    let title = match dialog {
+        JumpDialog::Line { .. } => "Go to Line",
+        JumpDialog::Heading { .. } => "Go to Heading",
+    };
+    egui::Window::new(title)
+        .id(egui::Id::new("markdown_editor_jump"))
+        .collapsible(false)
+        .resizable(false)
+        .title_bar(false)
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+        .show(ctx, |ui| match dialog {
+            JumpDialog::Line { input } => {
+                let line = parse_line(input, line_count);
+                ui.add(
+                    egui::TextEdit::singleline(input)
+                        .hint_text(format!("Line number, 1 to {}", line_count.max(1)))
+                        .desired_width(240.0),
+                )
+                .request_focus();
+                if enter {
+                    if let Some(line) = line {
+                        outcome = Some(JumpOutcome::Go(line));
+                    }
+                }
+            }
+            JumpDialog::Heading { query, selected } => {
+                let response = ui.add(
+                    egui::TextEdit::singleline(query)
+                        .hint_text("Type a few letters of a heading")
+                        .desired_width(320.0),
+                );
+                response.request_focus();
+                if response.changed() {
+                    *selected = 0;
+                }
+                let shown = filter_headings(headings, query);
+                if down {
+                    *selected += 1;
+                }
+                if up {
+                    *selected = selected.saturating_sub(1);
+                }
+                *selected = (*selected).min(shown.len().saturating_sub(1));
+                if enter {
+                    if let Some((_, _, line)) = shown.get(*selected) {
+                        outcome = Some(JumpOutcome::Go(*line));
+                    }
+                }
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(320.0)
+                    .show(ui, |ui| {
+                        if shown.is_empty() {
+                            ui.weak("No matching heading");
+                        }
+                        for (i, (level, text, line)) in shown.iter().enumerate() {
+                            let label = format!(
+                                "{}{}",
+                                "  ".repeat(level.saturating_sub(1) as usize),
+                                text
+                            );
+                            let button = egui::Button::selectable(i == *selected, label)
+                                .shortcut_text(egui::RichText::new(format!("line {}", line)).weak())
+                                .min_size(egui::vec2(ui.available_width(), 0.0));
+                            let response = ui.add(button);
+                            if i == *selected && (up || down) {
+                                response.scroll_to_me(None);
+                            }
+                            if response.clicked() {
+                                outcome = Some(JumpOutcome::Go(*line));
+                            }
+                        }
+                    });
+            }
+        });
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line_stays_in_document() {
+        assert_eq!(parse_line("3", 10), Some(3));
+        assert_eq!(parse_line("0", 10), None);
+        assert_eq!(parse_line("", 10), None);
+        assert_eq!(parse_line("-2", 10), None);
+        assert_eq!(parse_line("99", 10), Some(10));
+        assert_eq!(parse_line("5", 0), Some(1));
+    }
+
+    #[test]
+    fn test_filter_headings_by_words() {
+        let headings = vec![
+            (1, "Part One".to_string(), 1),
+            (2, "The Inn at Dusk".to_string(), 4),
+            (2, "Dawn on the Road".to_string(), 30),
+        ];
+        assert_eq!(filter_headings(&headings, "").len(), 3);
+        let lines: Vec<usize> = filter_headings(&headings, "the")
+            .iter()
+            .map(|h| h.2)
+            .collect();
+        assert_eq!(lines, [4, 30]);
+        assert_eq!(filter_headings(&headings, "ROAD dawn")[0].2, 30);
+        assert!(filter_headings(&headings, "castle").is_empty());
+    }
+}
@@ -7,9 +7,31 @@
 //! ## Features
 //!
 //! - Immersive markdown editing with syntax highlighting
+//! - One tab per open project document, reopened where it was left
 //! - Optional live preview panel
-//! - Word count and writing statistics
-//! - Distraction-free writing mode
+//! - Word count and writing statistics, and counts and readability of the
+//!   selection in a popover over it
+//! - Completion of names and phrases learned from the project's prose
+//! - Project dictionary of invented words
+//! - Spell checking with Hunspell dictionaries and suggestions
+//! - Underlines and fixes of the grammar issues found by a checker plugin
+//! - Highlights of the passages pointed out by analysis plugins
+//! - Line-length guide and reflow of paragraphs to a column
+//! - Optional marks for spaces and tabs
+//! - Cleanup of text pasted from word processors
+//! - Selection wrapping and auto-pairing of brackets and quotes
+//! - Lists and block quotes continued on Enter
+//! - Opt-in auto-correction of capitals, spaces and typos, set per project
+//! - Direction and alignment markers of paragraphs, for mixed scripts
+//! - Verse, keeping the lines and indentation of poems
+//! - Footnotes, annotations and author notes collapsed into badges while drafting
+//! - Consistency checks of glossary terms
+//! - `[[Wiki links]]` to worldbuilding entries, opened with Ctrl+click
+//! - Edit transactions from plugins, undone in a single step
+//! - Typed commands from the application's menus, run on the active tab
+//! - Jumps to a line number (Ctrl+G) or to a heading of the outline (Ctrl+J)
+//! - Focus mode, hiding all but a centered column of text and dimming the
+//!   paragraphs around the one being written
 //! - Auto-save functionality
 //! - Custom shortcuts for writers
 //!
@@ -24,18 +46,78 @@
 //! assert_eq!(info.name, "markdown-editor");
 //! ```
 
+pub mod autocorrect;
+pub mod breadcrumbs;
+pub mod buffer;
+pub mod commands;
+pub mod completion;
+pub mod dictionary;
+pub mod direction;
+pub mod documents;
 pub mod editor;
+pub mod focus;
+pub mod glossary;
+pub mod highlight;
+pub mod jump;
+pub mod lists;
+pub mod notes;
+pub mod pairs;
+pub mod paste;
 pub mod preview;
+pub mod spellcheck;
 pub mod stats;
 pub mod syntax;
-
+pub mod verse;
+pub mod whitespace;
+pub mod wiki;
+pub mod wrap;
+
+use cosmarium_plugin_api::event::{DocumentChange, DocumentRef};
+use cosmarium_plugin_api::grammar::{
+    self, GrammarFix, GrammarIssue, GrammarReport, IssueKind, GRAMMAR_FIX_REQUEST,
+    GRAMMAR_ISSUES_KEY,
+};
+use cosmarium_plugin_api::highlight::{self as highlights, TextHighlights};
+use cosmarium_plugin_api::transaction::{
+    EditTransaction, TransactionError, TransactionResult, EDIT_TRANSACTION_REQUEST,
+    EDIT_TRANSACTION_RESULTS,
+};
+use cosmarium_plugin_api::wiki::{link_at, LinkResolver, LINK_RESOLVER_KEY, OPEN_ENTRY_REQUEST};
 use cosmarium_plugin_api::{
     Event, EventType, PanelPlugin, Plugin, PluginContext, PluginInfo, PluginType, Result,
+    TaskHandle, FOCUS_PANEL_REQUEST, SESSION_STATE_KEY,
 };
 use egui::text_edit::TextEditState;
 use egui::Ui;
 use egui_dock::{DockArea, DockState, Node, NodeIndex, Split, Style, SurfaceIndex, TabViewer};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Delay after an edit before the active document is indexed again for
+/// completion.
+const CORPUS_REFRESH_DELAY: Duration = Duration::from_millis(1500);
+
+/// Maximum number of entries in the completion popup.
+const MAX_SUGGESTIONS: usize = 8;
+
+/// Maximum number of fixes offered for a grammar issue.
+const MAX_GRAMMAR_FIXES: usize = 5;
+
+/// Maximum number of undo histories kept for documents closed.
+const MAX_CLOSED_HISTORIES: usize = 20;
+
+/// State of the editor kept from one session to the next.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct EditorSession {
+    /// Where each document was left
+    positions: documents::DocumentPositions,
+}
 
 /// Configuration for the markdown editor plugin
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +126,9 @@ pub struct EditorConfig {
     pub syntax_highlighting: bool,
     /// Enable live preview
     pub live_preview: bool,
+    /// Scroll the live preview along with the editor, and back
+    #[serde(default = "default_true")]
+    pub sync_scroll: bool,
     /// Font size for the editor
     pub font_size: f32,
     /// Tab size in spaces
@@ -54,8 +139,36 @@ pub struct EditorConfig {
     pub auto_save_interval: u64,
     /// Show line numbers
     pub show_line_numbers: bool,
-    /// Distraction free mode
+    /// Show the headings the caret is under above the text
+    #[serde(default = "default_true")]
+    pub show_breadcrumbs: bool,
+    /// Focus mode, hiding all but the text
     pub distraction_free: bool,
+    /// Suggest completions learned from the project's prose
+    #[serde(default = "default_true")]
+    pub autocomplete: bool,
+    /// Type brackets and quotes in pairs, stepping over the closing one
+    #[serde(default = "default_true")]
+    pub auto_pair: bool,
+    /// Continue lists and block quotes on Enter, ending them on an empty line
+    #[serde(default = "default_true")]
+    pub continue_lists: bool,
+    /// Collapse footnotes into badges while drafting
+    #[serde(default)]
+    pub collapse_footnotes: bool,
+    /// Collapse annotations into badges while drafting
+    #[serde(default)]
+    pub collapse_annotations: bool,
+    /// Collapse bracketed author notes into badges while drafting
+    #[serde(default)]
+    pub collapse_author_notes: bool,
+    /// Collapse the front matter opening documents into a badge
+    #[serde(default = "default_true")]
+    pub collapse_front_matter: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Default for EditorConfig {
@@ -63,19 +176,45 @@ impl Default for EditorConfig {
         Self {
             syntax_highlighting: true,
             live_preview: false,
+            sync_scroll: true,
             font_size: 14.0,
             tab_size: 4,
             word_wrap: true,
             auto_save_interval: 30,
             show_line_numbers: true,
+            show_breadcrumbs: true,
             distraction_free: false,
+            autocomplete: true,
+            auto_pair: true,
+            continue_lists: true,
+            collapse_footnotes: false,
+            collapse_annotations: false,
+            collapse_author_notes: false,
+            collapse_front_matter: true,
         }
     }
 }
 
+impl EditorConfig {
+    /// Kinds of notes collapsed in the editor.
+    pub fn collapsed_notes(&self) -> Vec<notes::NoteKind> {
+        [
+            (self.collapse_footnotes, notes::NoteKind::Footnote),
+            (self.collapse_annotations, notes::NoteKind::Annotation),
+            (self.collapse_author_notes, notes::NoteKind::AuthorNote),
+            (self.collapse_front_matter, notes::NoteKind::FrontMatter),
+        ]
+        .into_iter()
+        .filter_map(|(collapsed, kind)| collapsed.then_some(kind))
+        .collect()
+    }
+}
+
 /// Core editor logic separated from UI
 struct EditorCore {
     content: String,
+    /// `content` as it was last recorded, to find what edits change
+    buffer: buffer::TextBuffer,
     config: EditorConfig,
     stats: stats::WritingStats,
     has_changes: bool,
@@ -84,12 +223,82 @@ struct EditorCore {
     current_title: String,
     last_cursor_char_idx: Option<usize>,
     last_save: std::time::Instant,
+    /// Model of the project's prose used for completion
+    corpus: completion::CorpusModel,
+    /// When the content was first edited since it was last indexed
+    corpus_edited: Option<Instant>,
+    /// Open completion popup
+    completion: Option<completion::Completion>,
+    /// Invented words of the active project
+    dictionary: dictionary::ProjectDictionary,
+    /// Spell check settings published by the application
+    spell_settings: spellcheck::SpellCheckSettings,
+    /// Dictionary of the spell check language, once loaded
+    spell_checker: Option<spellcheck::SpellChecker>,
+    /// Misspelled words of `content`, by byte range
+    misspellings: Vec<Range<usize>>,
+    /// Hash of the content `misspellings` were found in
+    spell_checked: Option<u64>,
+    /// Misspelled word right-clicked, with its suggestions
+    spell_target: Option<(Range<usize>, Vec<String>)>,
+    /// Grammar and style issues published by a checker
+    grammar: Option<GrammarReport>,
+    /// Grammar issue right-clicked
+    grammar_target: Option<GrammarIssue>,
+    /// Passages highlighted by analysis plugins
+    highlights: Vec<TextHighlights>,
+    /// Open documents, in tab order
+    tabs: Vec<documents::DocumentTab>,
+    /// Document whose content is being edited
+    active_tab: Option<Uuid>,
+    /// Caret and scroll offset of the documents, by file
+    positions: documents::DocumentPositions,
+    /// Whether `positions` changed since they were last published
+    positions_changed: bool,
+    /// Undo settings published by the application
+    undo_settings: editor::UndoSettings,
+    /// Undo histories of documents closed, with the hash of the content
+    /// they end at, the last closed last
+    closed_histories: Vec<(PathBuf, u64, editor::MarkdownEditor)>,
+    /// Scroll offsets to restore in the views on their next render
+    pending_scroll: HashMap<String, f32>,
+    /// Styled layout of the text of each view, from its last frame
+    layout_caches: HashMap<String, syntax::LayoutCache>,
+    /// Whether the paragraphs under the caret are to be reflowed
+    reflow_requested: bool,
+    /// Command on the selection, run when the active tab is drawn
+    selection_command: Option<commands::EditorCommand>,
+    /// Go-to-line dialog or heading picker, while it is open
+    jump: Option<jump::JumpDialog>,
+    /// Counts of the selection, with the revision and character range
+    /// they were made for
+    selection_stats: Option<(u64, Range<usize>, stats::SelectionStats)>,
+    /// Change of the format of the paragraph under the caret
+    format_requested: Option<direction::FormatChange>,
+    /// Whether the paragraphs under the caret are to be set as verse, or
+    /// back to prose
+    verse_requested: bool,
+    /// Revision of `content`, counting the changes published
+    revision: u64,
+    /// Renderer of the live preview, while it is shown
+    #[cfg(feature = "live-preview")]
+    preview: Option<preview::PreviewRenderer>,
+    /// Content of the live preview, laid out
+    #[cfg(feature = "live-preview")]
+    live_preview: preview::LivePreview,
+    /// Scroll offsets of the editor and the live preview, kept in step
+    #[cfg(feature = "live-preview")]
+    scroll_sync: preview::ScrollSync,
+    /// Layout of the view the live preview follows, from its last frame
+    #[cfg(feature = "live-preview")]
+    editor_view: Option<preview::EditorView>,
 }
 
 impl EditorCore {
     fn new() -> Self {
         Self {
             content: String::new(),
+            buffer: buffer::TextBuffer::default(),
             config: EditorConfig::default(),
             stats: stats::WritingStats::default(),
             has_changes: false,
@@ -98,13 +307,49 @@ impl EditorCore {
             current_title: "Editor".to_string(),
             last_cursor_char_idx: None,
             last_save: std::time::Instant::now(),
+            corpus: completion::CorpusModel::new(),
+            corpus_edited: None,
+            completion: None,
+            dictionary: dictionary::ProjectDictionary::default(),
+            spell_settings: spellcheck::SpellCheckSettings::default(),
+            spell_checker: None,
+            misspellings: Vec::new(),
+            spell_checked: None,
+            spell_target: None,
+            grammar: None,
+            grammar_target: None,
+            highlights: Vec::new(),
+            tabs: Vec::new(),
+            active_tab: None,
+            positions: documents::DocumentPositions::default(),
+            positions_changed: false,
+            undo_settings: editor::UndoSettings::default(),
+            closed_histories: Vec::new(),
+            pending_scroll: HashMap::new(),
+            layout_caches: HashMap::new(),
+            reflow_requested: false,
+            selection_command: None,
+            jump: None,
+            selection_stats: None,
+            format_requested: None,
+            verse_requested: false,
+            revision: 0,
+            #[cfg(feature = "live-preview")]
+            preview: None,
+            #[cfg(feature = "live-preview")]
+            live_preview: preview::LivePreview::new(),
+            #[cfg(feature = "live-preview")]
+            scroll_sync: preview::ScrollSync::new(),
+            #[cfg(feature = "live-preview")]
+            editor_view: None,
         }
     }
 
     /// Render the main editor UI
     fn render_editor(&mut self, ui: &mut Ui, ctx: &mut PluginContext, tab_id: &str) {
-        // Capture old content before editing
-        let old_content = self.content.clone();
+        // Content replaced since the last frame, rather than edited, is
+        // not a change to undo
+        self.buffer.reset(&self.content);
 
         // Calculate row height for scrolling
         let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
@@ -114,8 +359,13 @@ impl EditorCore {
         // Initialize focus request flag
         let mut request_focus = self.text_edit_id.is_none();
 
+        // Scroll back to where the document was left
+        if let Some(offset) = self.pending_scroll.remove(tab_id) {
+            scroll_area = scroll_area.vertical_scroll_offset(offset);
+        }
+
         // Handle goto line request by setting scroll offset
-        if let Some(target_line) = ctx.get_shared_state::<usize>("markdown_editor_goto_line") {
+        if let Some(target_line) = ctx.get_shared_state::<usize>(jump::GOTO_LINE_KEY) {
             if target_line > 0 {
                 // Check if we are the target tab (last active tab)
                 let last_active = ctx.get_shared_state::<String>("markdown_editor_last_active_tab");
@@ -151,7 +401,7 @@ impl EditorCore {
                     request_focus = true;
 
                     // Clear request
-                    ctx.set_shared_state("markdown_editor_goto_line", 0usize);
+                    ctx.set_shared_state(jump::GOTO_LINE_KEY, 0usize);
                 }
             }
         }
@@ -183,26 +433,460 @@ impl EditorCore {
             }
         }
 
+        // Theme-specific editor colors published by the application
+        let background_color = ctx
+            .get_shared_state::<Option<egui::Color32>>("markdown_editor_background_color")
+            .flatten();
+        let text_color = ctx
+            .get_shared_state::<Option<egui::Color32>>("markdown_editor_text_color")
+            .flatten();
+        let syntax_highlighting = self.config.syntax_highlighting;
+
+        // Keyboard navigation of the completion popup, before the TextEdit
+        // sees the keys
+        let edit_id = egui::Id::new("markdown_editor_textedit").with(tab_id);
+        let mut completed = false;
+        if self.completion.is_some() && ui.ctx().memory(|m| m.has_focus(edit_id)) {
+            let (down, up, accept, dismiss) = ui.ctx().input_mut(|input| {
+                (
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::Tab)
+                        || input.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+                )
+            });
+            if let Some(popup) = self.completion.as_mut() {
+                if down {
+                    popup.select_next();
+                }
+                if up {
+                    popup.select_previous();
+                }
+            }
+            if accept {
+                completed = self.accept_completion(ui.ctx(), edit_id);
+            } else if dismiss {
+                self.completion = None;
+            }
+        }
+
+        // Escape leaves focus mode, once the completion popup is closed
+        if self.config.distraction_free
+            && self.completion.is_none()
+            && ui.ctx().memory(|m| m.has_focus(edit_id))
+            && ui
+                .ctx()
+                .input_mut(|input| input.consume_key(egui::Modifiers::NONE, egui::Key::Escape))
+        {
+            self.set_focus_mode(ctx, false);
+        }
+
+        // Reflow asked for from the menus or its shortcut
+        let wrap_column = ctx
+            .get_shared_state::<usize>(wrap::WRAP_COLUMN_KEY)
+            .unwrap_or(0);
+        let last_active = ctx.get_shared_state::<String>("markdown_editor_last_active_tab");
+        let is_target = last_active.as_deref() == Some(tab_id) || last_active.is_none();
+        let mut reflowed = false;
+        if self.reflow_requested && is_target {
+            self.reflow_requested = false;
+            let column = if wrap_column > 0 {
+                wrap_column
+            } else {
+                wrap::DEFAULT_COLUMN
+            };
+            reflowed = self.reflow_paragraphs(ui.ctx(), edit_id, column);
+            request_focus = true;
+        }
+
+        // Clipboard and selection commands from the menus
+        let mut cut = false;
+        if is_target {
+            if let Some(command) = self.selection_command.take() {
+                cut = self.run_selection_command(ui.ctx(), edit_id, command);
+                request_focus = true;
+            }
+        }
+
+        // Go-to-line dialog and heading picker, moving the caret on the
+        // next frame
+        if is_target {
+            if let Some(dialog) = &mut self.jump {
+                let headings = ctx
+                    .get_shared_state::<Vec<jump::Heading>>(jump::OUTLINE_HEADINGS_KEY)
+                    .unwrap_or_default();
+                let line_count = self.content.lines().count();
+                match jump::show(ui.ctx(), dialog, &headings, line_count) {
+                    Some(jump::JumpOutcome::Go(line)) => {
+                        self.jump = None;
+                        ctx.set_shared_state(jump::GOTO_LINE_KEY, line);
+                        ui.ctx().request_repaint();
+                    }
+                    Some(jump::JumpOutcome::Cancel) => {
+                        self.jump = None;
+                        request_focus = true;
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        // Direction and alignment markers set from the context menu
+        let mut formatted = false;
+        if is_target {
+            if let Some(change) = self.format_requested.take() {
+                formatted = self.set_paragraph_format(ui.ctx(), edit_id, change);
+                request_focus = true;
+            }
+            if std::mem::take(&mut self.verse_requested) {
+                formatted |= self.toggle_verse(ui.ctx(), edit_id);
+                request_focus = true;
+            }
+        }
+
+        // Edit transactions committed by plugins, each its own undo step
+        if is_target {
+            for transaction in documents::drain::<EditTransaction>(ctx, EDIT_TRANSACTION_REQUEST) {
+                let Some(placeholders) = self.apply_transaction(ctx, transaction) else {
+                    continue;
+                };
+                if let Some(first) = placeholders.first() {
+                    let mut state =
+                        egui::TextEdit::load_state(ui.ctx(), edit_id).unwrap_or_default();
+                    let char_at = |byte: usize| self.content[..byte].chars().count();
+                    state
+                        .cursor
+                        .set_char_range(Some(egui::text::CCursorRange::two(
+                            egui::text::CCursor::new(char_at(first.start)),
+                            egui::text::CCursor::new(char_at(first.end)),
+                        )));
+                    state.store(ui.ctx(), edit_id);
+                    request_focus = true;
+                }
+                self.record_edit(ctx);
+            }
+        }
+
+        // Pasted text is cleaned up before it reaches the TextEdit; Shift
+        // pastes it as it is
+        let mut request = None;
+        let mut pasted = false;
+        if ui.ctx().memory(|m| m.has_focus(edit_id)) {
+            ui.ctx().input_mut(|input| {
+                let plain = input.modifiers.shift;
+                input.events.retain(|event| match event {
+                    egui::Event::Paste(text) => {
+                        request = Some(paste::PasteRequest {
+                            text: text.clone(),
+                            html: None,
+                            plain,
+                        });
+                        false
+                    }
+                    _ => true,
+                });
+            });
+            if let Some(request) = &mut request {
+                request.html = ctx
+                    .get_shared_state::<Option<String>>(paste::CLIPBOARD_HTML_KEY)
+                    .flatten();
+            }
+        }
+        if is_target {
+            if let Some(menu_request) = ctx
+                .get_shared_state::<Option<paste::PasteRequest>>(paste::PASTE_REQUEST)
+                .flatten()
+            {
+                ctx.set_shared_state::<Option<paste::PasteRequest>>(paste::PASTE_REQUEST, None);
+                request = Some(menu_request);
+                request_focus = true;
+            }
+        }
+        if let Some(request) = request {
+            let cleanup = ctx
+                .get_shared_state::<bool>(paste::PASTE_CLEANUP_KEY)
+                .unwrap_or(true);
+            let quotes = ctx
+                .get_config::<paste::QuoteStyle>(paste::QUOTE_STYLE_KEY)
+                .unwrap_or_default();
+            let text = paste::prepare(
+                &request.text,
+                request.html.as_deref(),
+                cleanup,
+                quotes,
+                request.plain,
+            );
+            pasted = self.paste_text(ui.ctx(), edit_id, &text);
+        }
+
+        // Brackets and quotes wrap the selection, or come in pairs, and
+        // Enter continues lists
+        let paired =
+            ui.ctx().memory(|m| m.has_focus(edit_id)) && self.assist_typing(ctx, ui.ctx(), edit_id);
+
+        let note_kinds = self.config.collapsed_notes();
+        // Notes touched by the selection stay expanded
+        let selected_chars = egui::TextEdit::load_state(ui.ctx(), edit_id)
+            .and_then(|state| state.cursor.char_range())
+            .map(|range| {
+                let [start, end] = range.sorted_cursors();
+                start.index..end.index
+            });
+        let badge_font = egui::FontId::proportional(10.0);
+        // Paragraphs but the one with the caret are dimmed in focus mode
+        let settings = ctx
+            .get_shared_state::<focus::FocusSettings>(focus::FOCUS_SETTINGS_KEY)
+            .unwrap_or_default();
+        let dimmed = self.config.distraction_free && settings.dim_paragraphs;
+        let caret_char = selected_chars.as_ref().map(|range| range.end);
+        let focus_width = settings.width.max(focus::MIN_WIDTH);
+
+        let mut layout_cache = self.layout_caches.remove(tab_id).unwrap_or_default();
         let output = scroll_area.show(ui, |ui| {
-            ui.add_sized(
-                ui.available_size(),
-                egui::TextEdit::multiline(&mut self.content)
-                    .id(egui::Id::new("markdown_editor_textedit").with(tab_id))
-                    .font(egui::TextStyle::Monospace)
-                    .desired_width(f32::INFINITY)
-                    .lock_focus(true), // Maintain stable focus to avoid IME/dead key resets
-            )
+            let mut text_edit = egui::TextEdit::multiline(&mut self.content)
+                .id(egui::Id::new("markdown_editor_textedit").with(tab_id))
+                .font(egui::TextStyle::Monospace)
+                .desired_width(f32::INFINITY)
+                .lock_focus(true); // Maintain stable focus to avoid IME/dead key resets
+            if let Some(color) = background_color {
+                text_edit = text_edit.background_color(color);
+            }
+            if let Some(color) = text_color {
+                text_edit = text_edit.text_color(color);
+            }
+            // Markdown styled as it is typed, notes collapsed
+            let mut layouter = |ui: &Ui, text: &dyn egui::TextBuffer, wrap_width: f32| {
+                let text = text.as_str();
+                let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+                let color = text_color
+                    .or(ui.visuals().override_text_color)
+                    .unwrap_or_else(|| ui.visuals().widgets.inactive.text_color());
+                let mut job = if syntax_highlighting {
+                    layout_cache.layout_job(text, &font_id, color, ui.visuals(), wrap_width)
+                } else {
+                    egui::text::LayoutJob::simple(text.to_string(), font_id, color, wrap_width)
+                };
+                let collapsed = notes::collapsed(
+                    text,
+                    notes::notes(text, &note_kinds),
+                    selected_chars.clone(),
+                );
+                if let Some(caret) = caret_char.filter(|_| dimmed) {
+                    let byte = text
+                        .char_indices()
+                        .nth(caret)
+                        .map_or(text.len(), |(byte, _)| byte);
+                    focus::dim(&mut job, focus::paragraph_at(text, byte));
+                }
+                notes::collapse(&mut job, &collapsed, |kind| {
+                    notes::badge_width(ui, kind, &badge_font)
+                });
+                ui.fonts_mut(|fonts| fonts.layout_job(job))
+            };
+            if syntax_highlighting || !note_kinds.is_empty() || dimmed {
+                text_edit = text_edit.layouter(&mut layouter);
+            }
+            // Same layout as `ui.add_sized`, keeping the galley to place the
+            // completion popup. In focus mode, the column is centered.
+            let size = if self.config.distraction_free {
+                egui::vec2(ui.available_width().min(focus_width), ui.available_height())
+            } else {
+                ui.available_size()
+            };
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                ui.allocate_ui_with_layout(
+                    size,
+                    egui::Layout::centered_and_justified(egui::Direction::TopDown),
+                    |ui| text_edit.show(ui),
+                )
+                .inner
+            })
+            .inner
         });
+        self.layout_caches.insert(tab_id.to_string(), layout_cache);
+
+        let scroll_offset = output.state.offset.y;
+        let edit_output = output.inner;
+        let response = edit_output.response.clone();
 
-        let response = output.inner;
+        // Layout of the text, for the live preview to follow this view
+        #[cfg(feature = "live-preview")]
+        if self.preview.is_some() && is_target {
+            let top = edit_output.galley_pos.y - output.inner_rect.top() + scroll_offset;
+            let mut view = preview::EditorView::new(
+                tab_id,
+                response.id,
+                edit_output.galley.clone(),
+                top,
+                scroll_offset,
+            );
+            if response.clicked() {
+                view.clicked = edit_output.cursor_range.map(|range| {
+                    self.content
+                        .char_indices()
+                        .nth(range.primary.index)
+                        .map_or(self.content.len(), |(byte, _)| byte)
+                });
+            }
+            self.editor_view = Some(view);
+        }
+
+        // Line-length guide at the wrap column
+        let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+        if wrap_column > 0 {
+            let char_width = ui.fonts_mut(|fonts| fonts.glyph_width(&font_id, ' '));
+            let x = edit_output.galley_pos.x + wrap_column as f32 * char_width;
+            if output.inner_rect.x_range().contains(x) {
+                let color = ui.visuals().weak_text_color().gamma_multiply(0.3);
+                ui.painter_at(output.inner_rect).vline(
+                    x,
+                    output.inner_rect.y_range(),
+                    egui::Stroke::new(1.0, color),
+                );
+            }
+        }
+
+        // Marks over spaces and tabs
+        if ctx
+            .get_shared_state::<bool>(whitespace::SHOW_WHITESPACE_KEY)
+            .unwrap_or(false)
+        {
+            whitespace::paint(
+                &ui.painter_at(output.inner_rect),
+                &edit_output.galley,
+                edit_output.galley_pos,
+                &font_id,
+                ui.visuals().weak_text_color().gamma_multiply(0.6),
+            );
+        }
+
+        // Backgrounds of the passages highlighted, while they match the text
+        if !self.highlights.is_empty() {
+            let hash = grammar::text_hash(&self.content);
+            for published in self.highlights.iter().filter(|h| h.text_hash == hash) {
+                highlight::paint(
+                    &ui.painter_at(output.inner_rect),
+                    &self.content,
+                    &edit_output.galley,
+                    edit_output.galley_pos,
+                    &published.highlights,
+                );
+            }
+        }
+
+        // Badges of right-to-left and marked paragraphs
+        direction::paint(
+            &ui.painter_at(output.inner_rect),
+            &self.content,
+            &edit_output.galley,
+            edit_output.galley_pos,
+            &egui::FontId::proportional(10.0),
+            ui.visuals().weak_text_color(),
+        );
+
+        // Badges of the collapsed notes, showing the note when hovered and
+        // expanding it when clicked
+        let collapsed = notes::collapsed(
+            &self.content,
+            notes::notes(&self.content, &note_kinds),
+            selected_chars,
+        );
+        if !collapsed.is_empty() {
+            let badges = notes::badges(
+                &self.content,
+                &edit_output.galley,
+                edit_output.galley_pos,
+                &collapsed,
+                |kind| notes::badge_width(ui, kind, &badge_font),
+            );
+            notes::paint(
+                &ui.painter_at(output.inner_rect),
+                &collapsed,
+                &badges,
+                &badge_font,
+                (
+                    ui.visuals().widgets.inactive.weak_bg_fill,
+                    ui.visuals().weak_text_color(),
+                ),
+            );
+            let hovered = response
+                .hover_pos()
+                .and_then(|pos| badges.iter().position(|badge| badge.contains(pos)));
+            if let Some(note) = hovered.map(|i| &collapsed[i]) {
+                ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                let text = &self.content[note.text.clone()];
+                response.clone().on_hover_text_at_pointer(text);
+                if response.clicked() {
+                    let mut state =
+                        egui::TextEdit::load_state(ui.ctx(), response.id).unwrap_or_default();
+                    let caret =
+                        egui::text::CCursor::new(self.content[..note.text.start].chars().count());
+                    state
+                        .cursor
+                        .set_char_range(Some(egui::text::CCursorRange::one(caret)));
+                    state.store(ui.ctx(), response.id);
+                }
+            }
+        }
+
+        // Underlines of the wiki links, in the warning color when missing
+        let resolver = ctx.get_shared_state::<LinkResolver>(LINK_RESOLVER_KEY);
+        wiki::paint(
+            &ui.painter_at(output.inner_rect),
+            &self.content,
+            &edit_output.galley,
+            edit_output.galley_pos,
+            resolver.as_ref(),
+            (
+                ui.visuals().hyperlink_color.gamma_multiply(0.6),
+                ui.visuals().warn_fg_color.gamma_multiply(0.6),
+            ),
+        );
+
+        // Wavy underlines of the misspelled words
+        self.refresh_misspellings();
+        spellcheck::paint(
+            &ui.painter_at(output.inner_rect),
+            &self.content,
+            &edit_output.galley,
+            edit_output.galley_pos,
+            &self.misspellings,
+            ui.visuals().error_fg_color,
+        );
+
+        // and those of the grammar issues, while they match the text
+        if let Some(report) = self.current_grammar() {
+            for (kind, color) in [
+                (IssueKind::Grammar, ui.visuals().warn_fg_color),
+                (IssueKind::Style, ui.visuals().hyperlink_color),
+                (IssueKind::Spelling, ui.visuals().error_fg_color),
+            ] {
+                let ranges: Vec<Range<usize>> = report
+                    .issues
+                    .iter()
+                    .filter(|issue| issue.kind == kind)
+                    .map(|issue| issue.range.clone())
+                    .collect();
+                spellcheck::paint(
+                    &ui.painter_at(output.inner_rect),
+                    &self.content,
+                    &edit_output.galley,
+                    edit_output.galley_pos,
+                    &ranges,
+                    color,
+                );
+            }
+        }
 
         // Track last active tab for multi-tab coordination
         if response.has_focus() {
             ctx.set_shared_state("markdown_editor_last_active_tab", tab_id.to_string());
         }
 
-        // If we requested focus, explicitly request it from memory as well to be sure
-        if request_focus {
+        // If we requested focus, explicitly request it from memory as well to be sure,
+        // unless an open jump dialog has the keyboard
+        if request_focus && self.jump.is_none() {
             ui.ctx().memory_mut(|m| m.request_focus(response.id));
         }
 
@@ -277,7 +961,7 @@ impl EditorCore {
                     let new_range = egui::text::CCursorRange::one(new_cursor);
                     state.cursor.set_char_range(Some(new_range));
                     state.store(ui.ctx(), response.id);
-                    
+
                     // Apply dialogue replacements after manual insertion
                     self.apply_dialogue_replacements(ui, response.id);
 
@@ -323,6 +1007,16 @@ impl EditorCore {
                 ctx.set_shared_state("markdown_editor_cursor_idx", cursor_idx);
                 tracing::debug!("MarkdownEditor: cursor_idx={}", cursor_idx);
 
+                // Publish the selected text for other plugins (e.g. Quote Card)
+                let start = cursor.primary.index.min(cursor.secondary.index);
+                let end = cursor.primary.index.max(cursor.secondary.index);
+                let selection: String = if start == end {
+                    String::new()
+                } else {
+                    self.content.chars().skip(start).take(end - start).collect()
+                };
+                ctx.set_shared_state("markdown_editor_selection", selection);
+
                 if self
                     .last_cursor_char_idx
                     .map(|prev| prev != cursor_idx)
@@ -348,21 +1042,204 @@ impl EditorCore {
                 self.content.len(),
                 response.has_focus()
             );
-            self.has_changes = true;
-            self.update_stats();
+            let typed = self.record_edit(ctx);
+            let correction =
+                typed.and_then(|delta| self.autocorrection(ctx, ui.ctx(), response.id, &delta));
+
+            // Corrections are an undo step of their own, after the typing
+            if let Some(edit) = correction {
+                self.content.replace_range(edit.range, &edit.text);
+                if let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), response.id) {
+                    let caret = self.content[..edit.selection.start].chars().count();
+                    state
+                        .cursor
+                        .set_char_range(Some(egui::text::CCursorRange::one(
+                            egui::text::CCursor::new(caret),
+                        )));
+                    state.store(ui.ctx(), response.id);
+                }
+                self.record_edit(ctx);
+            }
+        } else if completed || reflowed || formatted || pasted || paired || cut {
+            self.record_edit(ctx);
+        }
 
-            // Emit document changed event
-            let event = Event::new(EventType::DocumentChanged, "Document content modified");
-            ctx.emit_event(event);
+        // Quick actions: open the wiki link under the caret, fix the
+        // misspelled word or grammar issue right-clicked, or add the word
+        // under the caret to the project dictionary
+        if response.changed() {
+            self.spell_target = None;
+            self.grammar_target = None;
+        }
+        if response.secondary_clicked() {
+            let at = response.interact_pointer_pos().map(|pos| {
+                let cursor = edit_output
+                    .galley
+                    .cursor_from_pos(pos - edit_output.galley_pos);
+                self.content
+                    .char_indices()
+                    .nth(cursor.index)
+                    .map(|(i, _)| i)
+                    .unwrap_or(self.content.len())
+            });
+            self.grammar_target = at.and_then(|at| {
+                self.current_grammar()?
+                    .issues
+                    .iter()
+                    .find(|issue| issue.range.start <= at && at <= issue.range.end)
+                    .cloned()
+            });
+            self.spell_target = at.and_then(|at| {
+                let range = self
+                    .misspellings
+                    .iter()
+                    .find(|range| range.start <= at && at <= range.end)?
+                    .clone();
+                let checker = self.spell_checker.as_ref()?;
+                let word = self.content.get(range.clone())?;
+                let suggestions = checker.suggest(word, spellcheck::MAX_SPELL_SUGGESTIONS);
+                Some((range, suggestions))
+            });
+        }
+        let misspelled = self.spell_target.clone().filter(|(range, _)| {
+            self.content
+                .get(range.clone())
+                .is_some_and(|word| !self.dictionary.contains(word))
+        });
+        let caret = egui::TextEdit::load_state(ui.ctx(), response.id)
+            .and_then(|state| state.cursor.char_range())
+            .map(|range| range.primary.index);
+        let unknown_word = misspelled
+            .as_ref()
+            .and_then(|(range, _)| self.content.get(range.clone()))
+            .map(str::to_string)
+            .or_else(|| caret.and_then(|caret| dictionary::word_at(&self.content, caret)))
+            .filter(|word| !self.dictionary.contains(word));
+        let link = caret.and_then(|caret| {
+            let at = self
+                .content
+                .char_indices()
+                .nth(caret)
+                .map(|(i, _)| i)
+                .unwrap_or(self.content.len());
+            link_at(&self.content, at)
+        });
+        if let Some(link) = &link {
+            if response.clicked() && ui.input(|input| input.modifiers.command) {
+                ctx.set_shared_state(OPEN_ENTRY_REQUEST, Some(link.target.clone()));
+            }
+        }
+        let grammar_target = self.grammar_target.clone();
+        let mut correction = None;
+        if link.is_some()
+            || unknown_word.is_some()
+            || misspelled.is_some()
+            || grammar_target.is_some()
+        {
+            response.context_menu(|ui| {
+                if let Some(issue) = &grammar_target {
+                    ui.set_max_width(320.0);
+                    ui.label(&issue.message);
+                    for replacement in issue.replacements.iter().take(MAX_GRAMMAR_FIXES) {
+                        if ui.button(replacement).clicked() {
+                            correction = Some((issue.range.clone(), replacement.clone()));
+                            ui.close();
+                        }
+                    }
+                    ui.separator();
+                }
+                if let Some((range, suggestions)) = &misspelled {
+                    if suggestions.is_empty() {
+                        ui.weak("No spelling suggestions");
+                    }
+                    for suggestion in suggestions {
+                        if ui.button(suggestion).clicked() {
+                            correction = Some((range.clone(), suggestion.clone()));
+                            ui.close();
+                        }
+                    }
+                    ui.separator();
+                }
+                if let Some(link) = &link {
+                    if ui.button(format!("Open Entry “{}”", link.target)).clicked() {
+                        ctx.set_shared_state(OPEN_ENTRY_REQUEST, Some(link.target.clone()));
+                        ui.close();
+                    }
+                }
+                if let Some(word) = &unknown_word {
+                    if ui
+                        .button(format!("Add “{}” to Project Dictionary", word))
+                        .clicked()
+                    {
+                        ctx.set_shared_state(
+                            dictionary::ADD_TO_DICTIONARY_REQUEST,
+                            Some(word.clone()),
+                        );
+                        ui.close();
+                    }
+                }
+            });
+        }
+        if let Some((range, suggestion)) = correction {
+            self.apply_correction(ctx, range, &suggestion);
+        }
 
-            // Push OLD content to history
-            self.editor_state.add_to_history(old_content);
+        // Open, refresh or close the completion popup
+        let cursor_idx = egui::TextEdit::load_state(ui.ctx(), response.id)
+            .and_then(|state| state.cursor.char_range())
+            .filter(|range| range.primary == range.secondary)
+            .map(|range| range.primary.index);
+        if !response.has_focus() || !self.config.autocomplete {
+            self.completion = None;
+        } else if response.changed() {
+            self.completion = cursor_idx.and_then(|cursor| self.complete_at(cursor));
+        } else if self.completion.as_ref().map(|popup| popup.cursor) != cursor_idx {
+            self.completion = None;
+        }
+        if let Some(clicked) = self.show_completion_popup(ui, &edit_output) {
+            if let Some(popup) = self.completion.as_mut() {
+                popup.selected = clicked;
+            }
+            if self.accept_completion(ui.ctx(), response.id) {
+                self.record_edit(ctx);
+            }
+            ui.ctx().memory_mut(|m| m.request_focus(response.id));
+        }
+
+        // Counts of the selection, once the mouse let go of it
+        let selection = egui::TextEdit::load_state(ui.ctx(), response.id)
+            .and_then(|state| state.cursor.char_range())
+            .filter(|range| range.primary != range.secondary)
+            .map(|range| range.as_sorted_char_range());
+        let popover_id = response.id.with("selection_stats");
+        let over_popover = ui
+            .ctx()
+            .memory(|m| m.area_rect(popover_id))
+            .zip(ui.ctx().pointer_latest_pos())
+            .is_some_and(|(rect, pos)| rect.contains(pos));
+        let selecting = ui.input(|input| input.pointer.primary_down());
+        match selection {
+            Some(range)
+                if is_target
+                    && self.completion.is_none()
+                    && (response.has_focus() || over_popover)
+                    && !selecting =>
+            {
+                if self.show_selection_popover(ui, ctx, &edit_output, popover_id, range) {
+                    ui.ctx().memory_mut(|m| m.request_focus(response.id));
+                }
+            }
+            _ => self.selection_stats = None,
         }
 
         // Publish stats to shared state for status bar
         ctx.set_shared_state("editor_word_count", self.stats.word_count());
         ctx.set_shared_state("editor_char_count", self.stats.char_count());
         ctx.set_shared_state("editor_para_count", self.stats.paragraph_count());
+        ctx.set_shared_state("editor_verse_stats", self.stats.verse_stats());
+        if let Some(id) = self.active_tab {
+            ctx.set_shared_state(documents::WORD_COUNT_KEY, (id, self.stats.word_count()));
+        }
 
         // Publish cursor line and detect cursor movement
         if let Some(state) = egui::TextEdit::load_state(ui.ctx(), response.id) {
@@ -377,6 +1254,12 @@ impl EditorCore {
                     .count()
                     + 1;
                 ctx.set_shared_state("markdown_editor_cursor_line", line);
+                let location = self
+                    .active_document()
+                    .and_then(|tab| tab.buffer.path.clone())
+                    .map(|path| (path, line));
+                ctx.set_shared_state(documents::CURSOR_LOCATION_KEY, location);
+                self.remember_position(tab_id, cursor, scroll_offset);
 
                 // If the cursor moved, update last cursor index (title updates removed)
                 let cursor_changed = match self.last_cursor_char_idx {
@@ -396,6 +1279,93 @@ impl EditorCore {
         }
     }
 
+    /// Keep the live preview and the view it follows in step, after both
+    /// were shown: `preview_offset` is the scroll offset of the preview and
+    /// `clicked` the source of a block clicked in it.
+    #[cfg(feature = "live-preview")]
+    fn sync_scroll(
+        &mut self,
+        egui_ctx: &egui::Context,
+        ctx: &mut PluginContext,
+        preview_offset: f32,
+        clicked: Option<usize>,
+    ) {
+        let Some(view) = self.editor_view.take() else {
+            return;
+        };
+        let scrolled = self.scroll_sync.scrolled(view.offset, preview_offset);
+        if !self.config.sync_scroll {
+            return;
+        }
+        let content = &self.content;
+        let live_preview = &self.live_preview;
+        let sync = &mut self.scroll_sync;
+
+        if let Some(source) = clicked {
+            // Caret to the block clicked, at the same height in the editor
+            if let Some(height) = live_preview.height_of(source) {
+                sync.scroll_editor(view.height_of(content, source) - (height - preview_offset));
+            }
+            if let Some(mut state) = egui::TextEdit::load_state(egui_ctx, view.id) {
+                let before = content.get(..source).unwrap_or(content);
+                let caret = egui::text::CCursor::new(before.chars().count());
+                state
+                    .cursor
+                    .set_char_range(Some(egui::text::CCursorRange::one(caret)));
+                state.store(egui_ctx, view.id);
+            }
+            ctx.set_shared_state("markdown_editor_focus_requested", true);
+        } else if let Some(source) = view.clicked {
+            // Block of the caret to the same height in the preview
+            let height = view.height_of(content, source) - view.offset;
+            if let Some(top) = live_preview.height_of(source) {
+                sync.scroll_preview(top - height);
+            }
+        } else {
+            match scrolled {
+                Some(preview::ScrollSide::Editor) => {
+                    let source = view.source_at(content, view.offset);
+                    if let Some(top) = live_preview.height_of(source) {
+                        sync.scroll_preview(top);
+                    }
+                }
+                Some(preview::ScrollSide::Preview) => {
+                    if let Some(source) = live_preview.source_at(preview_offset) {
+                        sync.scroll_editor(view.height_of(content, source));
+                    }
+                }
+                None => {}
+            }
+        }
+
+        if sync.is_pending() {
+            egui_ctx.request_repaint();
+        }
+        if let Some(offset) = sync.take_editor_target() {
+            self.pending_scroll.insert(view.tab, offset);
+        }
+    }
+
+    /// Remember where the active document is in a view, to show it there
+    /// again.
+    fn remember_position(&mut self, view: &str, cursor: egui::text::CCursorRange, scroll: f32) {
+        let Some(path) = self
+            .active_document()
+            .and_then(|tab| tab.buffer.path.clone())
+        else {
+            return;
+        };
+        let position = documents::ViewPosition {
+            view: view.to_string(),
+            caret: cursor.primary.index,
+            anchor: cursor.secondary.index,
+            scroll,
+        };
+        if self.positions.set(&path, position) {
+            self.positions_changed = true;
+        }
+    }
+
     /// Render the statistics bar
     fn render_stats_bar(&self, ui: &mut Ui) {
         ui.horizontal(|ui| {
@@ -404,7 +1374,693 @@ impl EditorCore {
             ui.label(format!("Chars: {}", self.stats.char_count()));
             ui.separator();
             ui.label(format!("Paras: {}", self.stats.paragraph_count()));
+            let verse = self.stats.verse_stats();
+            if !verse.is_empty() {
+                ui.separator();
+                ui.label(format!(
+                    "Lines: {} · Stanzas: {} · Syllables: {}",
+                    verse.lines, verse.stanzas, verse.syllables
+                ));
+            }
+        });
+    }
+
+    /// Turn focus mode on or off, and tell the application.
+    fn set_focus_mode(&mut self, ctx: &mut PluginContext, on: bool) {
+        self.config.distraction_free = on;
+        ctx.set_config("markdown_editor", &self.config);
+        ctx.set_shared_state(focus::FOCUS_MODE_KEY, on);
+    }
+
+    /// Record an edit of the content since it was last recorded: statistics,
+    /// subscribers, undo history. Returns the change, `None` if the content
+    /// is the same.
+    fn record_edit(&mut self, ctx: &mut PluginContext) -> Option<buffer::Delta> {
+        let delta = self.buffer.commit(&self.content)?;
+        self.has_changes = true;
+        self.corpus_edited.get_or_insert_with(Instant::now);
+        self.update_stats();
+
+        self.publish_change(ctx, Some(&delta));
+        self.editor_state.add_to_history(delta.clone());
+        Some(delta)
+    }
+
+    /// Run a command of the application, or keep it for the active tab's
+    /// view if it acts on the selection.
+    fn run_command(&mut self, ctx: &mut PluginContext, command: commands::EditorCommand) {
+        use commands::EditorCommand;
+        match command {
+            EditorCommand::Undo => self.undo(ctx),
+            EditorCommand::Redo => self.redo(ctx),
+            EditorCommand::ReflowParagraph => self.reflow_requested = true,
+            EditorCommand::ToggleFocusMode => {
+                let on = !self.config.distraction_free;
+                self.set_focus_mode(ctx, on);
+            }
+            EditorCommand::Cut | EditorCommand::Copy | EditorCommand::SelectAll => {
+                self.selection_command = Some(command)
+            }
+            EditorCommand::GoToLine => self.jump = Some(jump::JumpDialog::line()),
+            EditorCommand::GoToHeading => self.jump = Some(jump::JumpDialog::heading()),
+        }
+    }
+
+    /// Run a command on the selection of the view `id`: copy it to the
+    /// clipboard, cut it, or select the whole document.
+    ///
+    /// Returns `true` if the content changed.
+    fn run_selection_command(
+        &mut self,
+        ctx: &egui::Context,
+        id: egui::Id,
+        command: commands::EditorCommand,
+    ) -> bool {
+        use commands::EditorCommand;
+        let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+        if command == EditorCommand::SelectAll {
+            state
+                .cursor
+                .set_char_range(Some(egui::text::CCursorRange::two(
+                    egui::text::CCursor::new(0),
+                    egui::text::CCursor::new(self.content.chars().count()),
+                )));
+            state.store(ctx, id);
+            return false;
+        }
+
+        let Some([start, end]) = state
+            .cursor
+            .char_range()
+            .map(|range| range.sorted_cursors())
+        else {
+            return false;
+        };
+        if start.index == end.index {
+            return false;
+        }
+        let selection: String = self
+            .content
+            .chars()
+            .skip(start.index)
+            .take(end.index - start.index)
+            .collect();
+        ctx.copy_text(selection);
+        command == EditorCommand::Cut && self.paste_text(ctx, id, "")
+    }
+
+    /// Undo the last change recorded.
+    fn undo(&mut self, ctx: &mut PluginContext) {
+        self.buffer.reset(&self.content);
+        if let Some(delta) = self.editor_state.undo(&mut self.content) {
+            self.history_changed(ctx, &delta);
+        }
+    }
+
+    /// Redo the last change undone.
+    fn redo(&mut self, ctx: &mut PluginContext) {
+        self.buffer.reset(&self.content);
+        if let Some(delta) = self.editor_state.redo(&mut self.content) {
+            self.history_changed(ctx, &delta);
+        }
+    }
+
+    /// Keep the undo history of a tab being closed, for when its document is
+    /// opened again unchanged.
+    fn keep_history(&mut self, tab: &mut documents::DocumentTab) {
+        let Some(path) = tab.buffer.path.clone() else {
+            return;
+        };
+        self.closed_histories
+            .retain(|(closed, _, _)| *closed != path);
+        if self.closed_histories.len() == MAX_CLOSED_HISTORIES {
+            self.closed_histories.remove(0);
+        }
+        let hash = grammar::text_hash(&tab.buffer.content);
+        self.closed_histories
+            .push((path, hash, std::mem::take(&mut tab.history)));
+    }
+
+    /// Undo history of a document being opened: the one it had when it was
+    /// closed, if its content is the same, or a new one.
+    fn reopened_history(&mut self, buffer: &documents::DocumentBuffer) -> editor::MarkdownEditor {
+        let hash = grammar::text_hash(&buffer.content);
+        let closed = self
+            .closed_histories
+            .iter()
+            .position(|(path, closed, _)| Some(path) == buffer.path.as_ref() && *closed == hash);
+        let mut history = match closed {
+            Some(index) => self.closed_histories.remove(index).2,
+            None => editor::MarkdownEditor::new(),
+        };
+        history.set_settings(&self.undo_settings);
+        history
+    }
+
+    /// Record a change of the content made by undoing or redoing.
+    fn history_changed(&mut self, ctx: &mut PluginContext, delta: &buffer::Delta) {
+        self.buffer.apply(delta);
+        self.has_changes = true;
+        self.update_stats();
+        self.publish_change(ctx, Some(delta));
+    }
+
+    /// Replace a range of the content by a suggested correction, keeping the
+    /// grammar issues found elsewhere in place.
+    fn apply_correction(&mut self, ctx: &mut PluginContext, range: Range<usize>, text: &str) {
+        self.buffer.reset(&self.content);
+        let before = grammar::text_hash(&self.content);
+        self.content.replace_range(range.clone(), text);
+        self.spell_target = None;
+        self.grammar_target = None;
+
+        if let Some(report) = self
+            .grammar
+            .as_mut()
+            .filter(|report| report.text_hash == before)
+        {
+            report.replaced(range, text.len(), grammar::text_hash(&self.content));
+            ctx.set_shared_state(GRAMMAR_ISSUES_KEY, Some(report.clone()));
+        }
+        self.record_edit(ctx);
+    }
+
+    /// The grammar issues published for the content as it is now.
+    fn current_grammar(&self) -> Option<&GrammarReport> {
+        self.grammar
+            .as_ref()
+            .filter(|report| report.text_hash == grammar::text_hash(&self.content))
+    }
+
+    /// Find the misspelled words again if the content changed since they
+    /// were last looked for.
+    fn refresh_misspellings(&mut self) {
+        let Some(checker) = &self.spell_checker else {
+            self.misspellings.clear();
+            return;
+        };
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        std::hash::Hash::hash(&self.content, &mut hasher);
+        let hash = std::hash::Hasher::finish(&hasher);
+        if self.spell_checked != Some(hash) {
+            self.misspellings = spellcheck::misspellings(&self.content, checker, &self.dictionary);
+            self.spell_checked = Some(hash);
+        }
+    }
+
+    /// Completion popup for the word ending at `cursor`, if anything matches.
+    fn complete_at(&self, cursor: usize) -> Option<completion::Completion> {
+        let word = completion::word_at_cursor(&self.content, cursor)?;
+        let mut suggestions =
+            self.corpus
+                .suggest(&word.prefix, word.previous.as_deref(), MAX_SUGGESTIONS);
+        suggestions.extend(self.dictionary.suggest(&word.prefix, MAX_SUGGESTIONS));
+        completion::rank(&mut suggestions, MAX_SUGGESTIONS);
+        if suggestions.is_empty() {
+            return None;
+        }
+        Some(completion::Completion {
+            word,
+            cursor,
+            suggestions,
+            selected: 0,
+        })
+    }
+
+    /// Replace the word being completed with the selected suggestion.
+    ///
+    /// Returns `true` if the content changed.
+    fn accept_completion(&mut self, ctx: &egui::Context, id: egui::Id) -> bool {
+        let Some(popup) = self.completion.take() else {
+            return false;
+        };
+        let Some(suggestion) = popup.selected() else {
+            return false;
+        };
+
+        let byte_at = |content: &str, char_idx: usize| {
+            content
+                .char_indices()
+                .nth(char_idx)
+                .map(|(i, _)| i)
+                .unwrap_or(content.len())
+        };
+        let start = byte_at(&self.content, popup.word.start);
+        let end = byte_at(&self.content, popup.cursor);
+        self.content.replace_range(start..end, &suggestion.text);
+
+        let cursor = popup.word.start + suggestion.text.chars().count();
+        let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::one(
+                egui::text::CCursor::new(cursor),
+            )));
+        state.store(ctx, id);
+        tracing::debug!(
+            "markdown-editor: completed {:?} as {:?} ({})",
+            popup.word.prefix,
+            suggestion.text,
+            suggestion.source.label()
+        );
+        true
+    }
+
+    /// Fill the paragraphs under the caret or the selection up to `column`.
+    ///
+    /// Returns `true` if the content changed.
+    fn reflow_paragraphs(&mut self, ctx: &egui::Context, id: egui::Id, column: usize) -> bool {
+        let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+        let Some(range) = state.cursor.char_range() else {
+            return false;
+        };
+        let byte_at = |content: &str, char_idx: usize| {
+            content
+                .char_indices()
+                .nth(char_idx)
+                .map(|(i, _)| i)
+                .unwrap_or(content.len())
+        };
+        let (first, last) = (range.primary.index, range.secondary.index);
+        let start = byte_at(&self.content, first.min(last));
+        let end = byte_at(&self.content, first.max(last));
+        let paragraphs = wrap::paragraphs_around(&self.content, start..end);
+        // Verse keeps its lines
+        if verse::in_verse(&self.content, paragraphs.clone()) {
+            return false;
+        }
+        let reflowed = wrap::reflow(&self.content[paragraphs.clone()], column);
+        if reflowed == self.content[paragraphs.clone()] {
+            return false;
+        }
+        self.content.replace_range(paragraphs.clone(), &reflowed);
+
+        // Caret after the reflowed paragraphs
+        let cursor = self.content[..paragraphs.start + reflowed.len()]
+            .chars()
+            .count();
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::one(
+                egui::text::CCursor::new(cursor),
+            )));
+        state.store(ctx, id);
+        true
+    }
+
+    /// Apply `change` to the marker of the paragraph under the caret.
+    ///
+    /// Returns `true` if the content changed.
+    fn set_paragraph_format(
+        &mut self,
+        ctx: &egui::Context,
+        id: egui::Id,
+        change: direction::FormatChange,
+    ) -> bool {
+        let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+        let Some(range) = state.cursor.char_range() else {
+            return false;
+        };
+        let cursor = self
+            .content
+            .char_indices()
+            .nth(range.primary.index)
+            .map(|(i, _)| i)
+            .unwrap_or(self.content.len());
+        let Some(edit) = direction::set_format(&self.content, cursor, change) else {
+            return false;
+        };
+        self.content.replace_range(edit.range, &edit.text);
+
+        let cursor = self.content[..edit.selection.start].chars().count();
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::one(
+                egui::text::CCursor::new(cursor),
+            )));
+        state.store(ctx, id);
+        true
+    }
+
+    /// Set the paragraphs under the caret as verse, or the verse under it
+    /// back to prose.
+    ///
+    /// Returns `true` if the content changed.
+    fn toggle_verse(&mut self, ctx: &egui::Context, id: egui::Id) -> bool {
+        let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+        let Some(range) = state.cursor.char_range() else {
+            return false;
+        };
+        let byte_at = |char_idx: usize| {
+            self.content
+                .char_indices()
+                .nth(char_idx)
+                .map(|(i, _)| i)
+                .unwrap_or(self.content.len())
+        };
+        let (first, last) = (range.primary.index, range.secondary.index);
+        let selection = byte_at(first.min(last))..byte_at(first.max(last));
+        let Some(edit) = verse::toggle_verse(&self.content, selection) else {
+            return false;
+        };
+        self.content.replace_range(edit.range, &edit.text);
+
+        let cursor = self.content[..edit.selection.start].chars().count();
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::one(
+                egui::text::CCursor::new(cursor),
+            )));
+        state.store(ctx, id);
+        true
+    }
+
+    /// Replace the selection with `text`, leaving the caret after it.
+    ///
+    /// Returns `true` if the content changed.
+    fn paste_text(&mut self, ctx: &egui::Context, id: egui::Id, text: &str) -> bool {
+        let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+        let char_count = self.content.chars().count();
+        let (first, last) = state
+            .cursor
+            .char_range()
+            .map(|range| (range.primary.index, range.secondary.index))
+            .unwrap_or((char_count, char_count));
+        let byte_at = |content: &str, char_idx: usize| {
+            content
+                .char_indices()
+                .nth(char_idx)
+                .map(|(i, _)| i)
+                .unwrap_or(content.len())
+        };
+        let start = byte_at(&self.content, first.min(last));
+        let end = byte_at(&self.content, first.max(last));
+        if text.is_empty() && start == end {
+            return false;
+        }
+        self.content.replace_range(start..end, text);
+
+        let cursor = first.min(last) + text.chars().count();
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::one(
+                egui::text::CCursor::new(cursor),
+            )));
+        state.store(ctx, id);
+        true
+    }
+
+    /// Apply the typed brackets and quotes that wrap the selection or come in
+    /// pairs, and the list markers continued by Enter, taking their events
+    /// out of the input.
+    ///
+    /// Returns `true` if the content changed.
+    fn assist_typing(
+        &mut self,
+        ctx: &PluginContext,
+        egui_ctx: &egui::Context,
+        id: egui::Id,
+    ) -> bool {
+        let Some(mut state) = egui::TextEdit::load_state(egui_ctx, id) else {
+            return false;
+        };
+        let Some(range) = state.cursor.char_range() else {
+            return false;
+        };
+        let quotes = ctx
+            .get_config::<paste::QuoteStyle>(paste::QUOTE_STYLE_KEY)
+            .unwrap_or_default();
+        let auto_pair = self.config.auto_pair;
+        let continue_lists = self.config.continue_lists;
+        let byte_at = |content: &str, char_idx: usize| {
+            content
+                .char_indices()
+                .nth(char_idx)
+                .map(|(i, _)| i)
+                .unwrap_or(content.len())
+        };
+        let (first, last) = (range.primary.index, range.secondary.index);
+        let mut selection =
+            byte_at(&self.content, first.min(last))..byte_at(&self.content, first.max(last));
+
+        let content = &mut self.content;
+        let mut changed = false;
+        egui_ctx.input_mut(|input| {
+            input.events.retain(|event| {
+                let edit = match event {
+                    egui::Event::Text(text) => {
+                        let mut chars = text.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => {
+                                pairs::type_char(content, selection.clone(), c, quotes, auto_pair)
+                            }
+                            _ => None,
+                        }
+                    }
+                    egui::Event::Key {
+                        key: egui::Key::Backspace,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } if auto_pair && modifiers.is_none() && selection.is_empty() => {
+                        pairs::delete_pair(content, selection.start, quotes)
+                    }
+                    egui::Event::Key {
+                        key: egui::Key::Enter,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } if continue_lists && modifiers.is_none() && selection.is_empty() => {
+                        lists::press_enter(content, selection.start)
+                    }
+                    _ => None,
+                };
+                let Some(edit) = edit else {
+                    return true;
+                };
+                content.replace_range(edit.range, &edit.text);
+                selection = edit.selection;
+                changed = true;
+                false
+            });
         });
+        if !changed {
+            return false;
+        }
+
+        let char_at = |byte: usize| self.content[..byte].chars().count();
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::two(
+                egui::text::CCursor::new(char_at(selection.start)),
+                egui::text::CCursor::new(char_at(selection.end)),
+            )));
+        state.store(egui_ctx, id);
+        true
+    }
+
+    /// Apply an edit transaction committed by a plugin, and report how it
+    /// went.
+    ///
+    /// Returns the placeholders of the snippets it inserted if it changed the
+    /// content.
+    fn apply_transaction(
+        &mut self,
+        ctx: &mut PluginContext,
+        transaction: EditTransaction,
+    ) -> Option<Vec<Range<usize>>> {
+        let applied = match transaction.document {
+            Some(document) if self.active_tab != Some(document) => {
+                Err(TransactionError::DocumentNotActive)
+            }
+            _ => transaction.apply(&self.content),
+        };
+        let (error, placeholders) = match applied {
+            Ok(applied) if applied.content != self.content => {
+                self.content = applied.content;
+                (None, Some(applied.placeholders))
+            }
+            Ok(_) => (None, None),
+            Err(error) => {
+                tracing::warn!("Edit transaction {} not applied: {}", transaction.id, error);
+                (Some(error), None)
+            }
+        };
+        documents::push(
+            ctx,
+            EDIT_TRANSACTION_RESULTS,
+            TransactionResult {
+                id: transaction.id,
+                error,
+            },
+        );
+        placeholders
+    }
+
+    /// Show the completion popup under the word being completed.
+    ///
+    /// Returns the index of the suggestion clicked, if any.
+    fn show_completion_popup(
+        &self,
+        ui: &Ui,
+        output: &egui::text_edit::TextEditOutput,
+    ) -> Option<usize> {
+        let popup = self.completion.as_ref()?;
+        let anchor = output
+            .galley
+            .pos_from_cursor(egui::text::CCursor::new(popup.word.start))
+            .left_bottom();
+        let mut clicked = None;
+
+        egui::Area::new(output.response.id.with("completion"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(output.galley_pos + anchor.to_vec2())
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for (i, suggestion) in popup.suggestions.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui
+                                .selectable_label(i == popup.selected, &suggestion.text)
+                                .clicked()
+                            {
+                                clicked = Some(i);
+                            }
+                            ui.weak(suggestion.source.label());
+                        });
+                    }
+                });
+            });
+        clicked
+    }
+
+    /// Show the counts and readability of the selection `range`, in
+    /// characters, over its start, with actions on them.
+    ///
+    /// Returns `true` if an action was clicked.
+    fn show_selection_popover(
+        &mut self,
+        ui: &Ui,
+        ctx: &mut PluginContext,
+        output: &egui::text_edit::TextEditOutput,
+        id: egui::Id,
+        range: Range<usize>,
+    ) -> bool {
+        let byte_at = |content: &str, char_index: usize| {
+            content
+                .char_indices()
+                .nth(char_index)
+                .map_or(content.len(), |(i, _)| i)
+        };
+        let bytes = byte_at(&self.content, range.start)..byte_at(&self.content, range.end);
+        let stats = match &self.selection_stats {
+            Some((revision, counted, stats)) if *revision == self.revision && *counted == range => {
+                *stats
+            }
+            _ => {
+                let stats = stats::SelectionStats::of(&self.content[bytes.clone()]);
+                self.selection_stats = Some((self.revision, range.clone(), stats));
+                stats
+            }
+        };
+        let anchor = output
+            .galley
+            .pos_from_cursor(egui::text::CCursor::new(range.start))
+            .left_top();
+
+        let mut acted = false;
+        egui::Area::new(id)
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::LEFT_BOTTOM)
+            .fixed_pos(output.galley_pos + anchor.to_vec2() - egui::vec2(0.0, 4.0))
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{} words · {} characters · {} sentences",
+                            stats.words, stats.chars, stats.sentences
+                        ));
+                        if let Some(score) = stats.reading_ease() {
+                            ui.separator();
+                            ui.label(format!("Reading ease {:.0}", score))
+                                .on_hover_text(format!(
+                                    "Flesch reading ease: {} (made for English)",
+                                    stats::SelectionStats::ease_label(score)
+                                ));
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.small_button("Copy Stats").clicked() {
+                            ui.ctx().copy_text(stats.summary());
+                            acted = true;
+                        }
+                        if ui
+                            .small_button("Check Style")
+                            .on_hover_text("Check the style of the selection alone")
+                            .clicked()
+                        {
+                            ctx.set_shared_state(stats::STYLE_CHECK_REQUEST, Some(bytes.clone()));
+                            ctx.set_shared_state(
+                                FOCUS_PANEL_REQUEST,
+                                Some(stats::STYLE_PANEL.to_string()),
+                            );
+                            acted = true;
+                        }
+                    });
+                });
+            });
+        acted
+    }
+
+    /// Publish the content and notify subscribers that it changed.
+    ///
+    /// The content is published first so that subscribers reading it back
+    /// from shared state see the new text. Every change moves the content to
+    /// its next revision; when `delta` tells what changed, the event also
+    /// tells the range replaced and the length of the text inserted.
+    ///
+    /// Edits of a document open in a tab are also sent back to the
+    /// application, which owns the document.
+    fn publish_change(&mut self, ctx: &mut PluginContext, delta: Option<&buffer::Delta>) {
+        ctx.set_shared_state("markdown_editor_content", self.content.clone());
+        let path = ctx
+            .get_shared_state::<Option<PathBuf>>("active_document_path")
+            .flatten();
+        let mut document = DocumentRef::default().with_path(path);
+
+        if let Some(tab) = self.active_document() {
+            document.id = Some(tab.buffer.id);
+            if self.has_changes {
+                documents::push_update(
+                    ctx,
+                    documents::DocumentBuffer {
+                        content: self.content.clone(),
+                        ..tab.buffer.clone()
+                    },
+                );
+            }
+        }
+        self.revision += 1;
+        let change = match delta {
+            Some(delta) => DocumentChange {
+                document,
+                range: Some(delta.inserted_range()),
+                replaced: Some(delta.removed_range()),
+                inserted_len: delta.inserted.len(),
+                revision: self.revision,
+            },
+            None => DocumentChange {
+                document,
+                revision: self.revision,
+                ..DocumentChange::default()
+            },
+        };
+        ctx.emit_event(Event::document_change(change));
+    }
+
+    /// Tab of the document being edited.
+    fn active_document(&self) -> Option<&documents::DocumentTab> {
+        let id = self.active_tab?;
+        self.tabs.iter().find(|tab| tab.buffer.id == id)
     }
 
     /// Update writing statistics based on current content
@@ -424,11 +2080,13 @@ impl EditorCore {
                     if text_near_cursor == "-- " {
                         // Find byte indices for replacement
                         // We want to replace the first two characters (the dashes) while keeping the space
-                        let start_byte = self.content
+                        let start_byte = self
+                            .content
                             .char_indices()
                             .nth(cursor_idx - 3)
                             .map(|(i, _)| i);
-                        let end_dash_byte = self.content
+                        let end_dash_byte = self
+                            .content
                             .char_indices()
                             .nth(cursor_idx - 1)
                             .map(|(i, _)| i);
@@ -439,13 +2097,15 @@ impl EditorCore {
 
                             // Adjust cursor: since 2 chars became 1, moved back by 1
                             let mut new_state = state.clone();
-                            new_state.cursor.set_char_range(Some(
-                                egui::text::CCursorRange::one(egui::text::CCursor::new(
-                                    cursor_idx - 1,
-                                )),
-                            ));
+                            new_state
+                                .cursor
+                                .set_char_range(Some(egui::text::CCursorRange::one(
+                                    egui::text::CCursor::new(cursor_idx - 1),
+                                )));
                             new_state.store(ui.ctx(), id);
-                            tracing::debug!("Dialogue Assistance: Replaced -- with — (triggered by space)");
+                            tracing::debug!(
+                                "Dialogue Assistance: Replaced -- with — (triggered by space)"
+                            );
                         }
                     }
                 }
@@ -453,6 +2113,49 @@ impl EditorCore {
         }
     }
 
+    /// Correction of the character just typed, the content changing by
+    /// `delta`, if the project turned auto-correction on and there is one to
+    /// make.
+    fn autocorrection(
+        &self,
+        ctx: &PluginContext,
+        egui_ctx: &egui::Context,
+        id: egui::Id,
+        delta: &buffer::Delta,
+    ) -> Option<pairs::Edit> {
+        let settings =
+            ctx.get_config::<autocorrect::AutocorrectSettings>(autocorrect::AUTOCORRECT_KEY)?;
+        if !settings.enabled {
+            return None;
+        }
+        let range = egui::TextEdit::load_state(egui_ctx, id)?
+            .cursor
+            .char_range()?;
+        if range.primary.index != range.secondary.index {
+            return None;
+        }
+        let cursor = self
+            .content
+            .char_indices()
+            .nth(range.primary.index)
+            .map_or(self.content.len(), |(i, _)| i);
+
+        // Only a single character typed is corrected, not pasted text
+        let typed = self.content[..cursor].chars().next_back()?;
+        let start = cursor - typed.len_utf8();
+        // In a run of the character, the change found may be after the caret
+        let single = delta.removed.is_empty()
+            && delta.inserted.chars().eq([typed])
+            && self
+                .content
+                .get(start..delta.inserted_range().end)
+                .is_some_and(|run| run.chars().all(|c| c == typed));
+        if !single {
+            return None;
+        }
+        autocorrect::correct(&self.content, cursor, &settings)
+    }
+
     /// Get dynamic title based on cursor position
     fn get_dynamic_title(&self, ctx: &egui::Context) -> String {
         if let Some(id) = self.text_edit_id {
@@ -498,6 +2201,16 @@ pub struct MarkdownEditorPlugin {
     core: EditorCore,
     /// Docking tree for layout management
     tree: DockState<String>,
+    /// Indexing of the project's prose, while it runs
+    corpus_task: Option<TaskHandle<completion::CorpusModel>>,
+    /// Project the completion model was built from
+    corpus_project: Option<PathBuf>,
+    /// Tab to show once the UI is available
+    pending_tab: Option<Uuid>,
+    /// Documents saved by the application since the last update
+    saved_documents: Arc<Mutex<Vec<Uuid>>>,
+    /// Subscription to document saved events
+    saved_subscription: Option<cosmarium_plugin_api::Subscription>,
 }
 
 impl Default for MarkdownEditorPlugin {
@@ -569,46 +2282,530 @@ impl MarkdownEditorPlugin {
         Self {
             core: EditorCore::new(),
             tree,
+            corpus_task: None,
+            corpus_project: None,
+            pending_tab: None,
+            saved_documents: Arc::new(Mutex::new(Vec::new())),
+            saved_subscription: None,
+        }
+    }
+
+    pub fn content(&self) -> &str {
+        &self.core.content
+    }
+
+    pub fn set_content<S: Into<String>>(&mut self, content: S) {
+        self.core.content = content.into();
+        self.core.has_changes = true;
+        self.core.update_stats();
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.core.has_changes
+    }
+
+    pub fn stats(&self) -> &stats::WritingStats {
+        &self.core.stats
+    }
+
+    fn handle_auto_save(&mut self, ctx: &mut PluginContext) {
+        if !self.core.has_changes && !self.core.tabs.iter().any(|tab| tab.has_changes) {
+            return;
+        }
+
+        let elapsed = ctx.clock().since(self.core.last_save);
+        if elapsed.as_secs() >= self.core.config.auto_save_interval {
+            if let Err(e) = self.auto_save(ctx) {
+                tracing::error!("Auto-save failed: {}", e);
+            }
+        }
+    }
+
+    /// Apply content loaded from disk by the host, replacing the buffer even
+    /// when it holds local changes (the host saves them before switching).
+    fn apply_loaded_content(&mut self, ctx: &mut PluginContext) {
+        if let Some(loaded) = ctx.get_plugin_data::<String>("markdown-editor", "loaded_content") {
+            tracing::debug!(
+                "markdown-editor.update: found plugin_data loaded_content (len={})",
+                loaded.len()
+            );
+            if loaded != self.core.content && !loaded.is_empty() {
+                tracing::info!(
+                    "markdown-editor.update: applying plugin_data loaded_content to editor core"
+                );
+                self.core.content = loaded;
+                self.core.has_changes = false;
+                self.core.update_stats();
+                self.core.publish_change(ctx, None);
+                ctx.set_plugin_data("markdown-editor", "loaded_content", String::new());
+            }
+        }
+    }
+
+    /// Open the documents the application asked for and note the ones it
+    /// saved.
+    fn sync_documents(&mut self, ctx: &mut PluginContext) {
+        for buffer in
+            documents::drain::<documents::DocumentBuffer>(ctx, documents::OPEN_DOCUMENTS_REQUEST)
+        {
+            self.open_tab(buffer, ctx);
+        }
+        for buffer in
+            documents::drain::<documents::DocumentBuffer>(ctx, documents::REPLACE_DOCUMENTS_REQUEST)
+        {
+            self.replace_tab_content(buffer, ctx);
+        }
+
+        let saved: Vec<Uuid> = match self.saved_documents.lock() {
+            Ok(mut saved) => saved.drain(..).collect(),
+            Err(_) => Vec::new(),
+        };
+        for id in saved {
+            if self.core.active_tab == Some(id) {
+                self.core.has_changes = false;
+            } else if let Some(tab) = self.core.tabs.iter_mut().find(|t| t.buffer.id == id) {
+                tab.has_changes = false;
+            }
+        }
+    }
+
+    /// Show a document in a tab, reusing its tab if it is already open.
+    ///
+    /// The tab becomes active on the next render.
+    fn open_tab(&mut self, buffer: documents::DocumentBuffer, ctx: &mut PluginContext) {
+        let id = buffer.id;
+        match self.core.tabs.iter_mut().find(|t| t.buffer.id == id) {
+            Some(tab) => {
+                tab.buffer.title = buffer.title;
+                tab.buffer.path = buffer.path;
+                // Content on disk may have changed; local edits win
+                if self.core.active_tab == Some(id) {
+                    if !self.core.has_changes && self.core.content != buffer.content {
+                        self.core.content = buffer.content;
+                        self.core.update_stats();
+                        self.core.publish_change(ctx, None);
+                    }
+                } else if !tab.has_changes {
+                    tab.buffer.content = buffer.content;
+                }
+            }
+            None => {
+                let mut tab = documents::DocumentTab::new(buffer);
+                tab.history = self.core.reopened_history(&tab.buffer);
+                self.core.tabs.push(tab);
+            }
+        }
+        self.pending_tab = Some(id);
+    }
+
+    /// Show content the application changed in the tab of its document,
+    /// keeping the previous content in the undo history.
+    fn replace_tab_content(&mut self, buffer: documents::DocumentBuffer, ctx: &mut PluginContext) {
+        if self.core.active_tab == Some(buffer.id) {
+            if self.core.content != buffer.content {
+                self.core.buffer.reset(&self.core.content);
+                self.core.content = buffer.content;
+                let delta = self.core.buffer.commit(&self.core.content);
+                if let Some(delta) = &delta {
+                    self.core.editor_state.add_to_history(delta.clone());
+                }
+                self.core.update_stats();
+                self.core.publish_change(ctx, delta.as_ref());
+            }
+        } else if let Some(tab) = self.core.tabs.iter_mut().find(|t| t.buffer.id == buffer.id) {
+            tab.buffer.content = buffer.content;
+        }
+    }
+
+    /// Names of the editor views, used in their TextEdit ids.
+    fn views(&self) -> Vec<String> {
+        self.tree
+            .iter_all_tabs()
+            .map(|(_, view)| view.clone())
+            .collect()
+    }
+
+    /// Make the tab requested by the application active.
+    fn activate_pending_tab(&mut self, ctx: &mut PluginContext, egui_ctx: Option<&egui::Context>) {
+        if let Some(id) = self.pending_tab.take() {
+            self.activate_tab(id, ctx, egui_ctx);
+        }
+    }
+
+    /// Edit the document of another tab.
+    ///
+    /// The content, undo history and caret of the current tab are kept in
+    /// its tab until it is shown again.
+    fn activate_tab(
+        &mut self,
+        id: Uuid,
+        ctx: &mut PluginContext,
+        egui_ctx: Option<&egui::Context>,
+    ) {
+        if self.core.active_tab == Some(id) {
+            return;
+        }
+        let Some(index) = self.core.tabs.iter().position(|t| t.buffer.id == id) else {
+            return;
+        };
+        let views = self.views();
+        let edit_id = |view: &str| egui::Id::new("markdown_editor_textedit").with(view);
+
+        // Park the current tab
+        let core = &mut self.core;
+        if let Some(current) = core.active_tab {
+            if let Some(tab) = core.tabs.iter_mut().find(|t| t.buffer.id == current) {
+                tab.buffer.content = std::mem::take(&mut core.content);
+                tab.has_changes = core.has_changes;
+                tab.history = std::mem::take(&mut core.editor_state);
+                tab.revision = core.revision;
+                tab.edit_states = egui_ctx
+                    .map(|egui_ctx| {
+                        views
+                            .iter()
+                            .filter_map(|view| {
+                                egui::TextEdit::load_state(egui_ctx, edit_id(view))
+                                    .map(|state| (view.clone(), state))
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+            }
+        }
+
+        // Bring in the new one
+        let tab = &mut core.tabs[index];
+        core.content = std::mem::take(&mut tab.buffer.content);
+        core.has_changes = std::mem::take(&mut tab.has_changes);
+        core.editor_state = std::mem::take(&mut tab.history);
+        core.revision = tab.revision;
+        let path = tab.buffer.path.clone();
+        let remembered = |view: &str| {
+            path.as_deref()
+                .and_then(|path| core.positions.get(path, view))
+        };
+        if let Some(egui_ctx) = egui_ctx {
+            let mut states = std::mem::take(&mut tab.edit_states);
+            for view in &views {
+                // A document not shown yet in this session gets its caret
+                // back from the last one
+                let state = states
+                    .iter()
+                    .position(|(name, _)| name == view)
+                    .map(|i| states.swap_remove(i).1)
+                    .or_else(|| remembered(view).map(|position| position.text_edit_state()))
+                    .unwrap_or_default();
+                egui::TextEdit::store_state(egui_ctx, edit_id(view), state);
+            }
+        }
+        for view in &views {
+            let scroll = remembered(view).map_or(0.0, |position| position.scroll);
+            core.pending_scroll.insert(view.clone(), scroll);
+        }
+
+        core.active_tab = Some(id);
+        core.completion = None;
+        core.last_cursor_char_idx = None;
+        core.update_stats();
+        ctx.set_shared_state(documents::ACTIVE_DOCUMENT_KEY, Some(id));
+        ctx.set_shared_state("active_document_path", path);
+        core.publish_change(ctx, None);
+    }
+
+    /// Close a tab, handing its last edits to the application.
+    fn close_tab(&mut self, id: Uuid, ctx: &mut PluginContext, egui_ctx: Option<&egui::Context>) {
+        let Some(index) = self.core.tabs.iter().position(|t| t.buffer.id == id) else {
+            return;
+        };
+
+        if self.core.active_tab == Some(id) {
+            let next = self
+                .core
+                .tabs
+                .get(index + 1)
+                .or_else(|| index.checked_sub(1).and_then(|i| self.core.tabs.get(i)))
+                .map(|tab| tab.buffer.id);
+            match next {
+                Some(next) => self.activate_tab(next, ctx, egui_ctx),
+                None => {
+                    // Last tab: flush its edits and leave an empty editor
+                    self.core.publish_change(ctx, None);
+                    self.core.active_tab = None;
+                    let core = &mut self.core;
+                    core.tabs[index].buffer.content = std::mem::take(&mut core.content);
+                    core.tabs[index].history = std::mem::take(&mut core.editor_state);
+                    core.has_changes = false;
+                    self.core.update_stats();
+                    ctx.set_shared_state(documents::ACTIVE_DOCUMENT_KEY, None::<Uuid>);
+                    ctx.set_shared_state("active_document_path", None::<PathBuf>);
+                    self.core.publish_change(ctx, None);
+                }
+            }
+        }
+
+        let mut tab = self.core.tabs.remove(index);
+        self.core.keep_history(&mut tab);
+        if tab.has_changes {
+            documents::push_update(ctx, tab.buffer);
+        }
+        documents::push(ctx, documents::CLOSE_DOCUMENTS_REQUEST, id);
+        if self.pending_tab == Some(id) {
+            self.pending_tab = None;
+        }
+    }
+
+    /// Render the bar of document tabs.
+    fn render_document_tabs(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        if self.core.tabs.is_empty() {
+            return;
+        }
+
+        let mut activate = None;
+        let mut close = None;
+        egui::ScrollArea::horizontal()
+            .id_salt("markdown_editor_document_tabs")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for tab in &self.core.tabs {
+                        let id = tab.buffer.id;
+                        let active = self.core.active_tab == Some(id);
+                        let changed = if active {
+                            self.core.has_changes
+                        } else {
+                            tab.has_changes
+                        };
+                        let label = if changed {
+                            format!("● {}", tab.title())
+                        } else {
+                            tab.title().to_string()
+                        };
+
+                        let mut response = ui.selectable_label(active, label);
+                        if let Some(path) = &tab.buffer.path {
+                            response = response.on_hover_text(path.display().to_string());
+                        }
+                        if response.clicked() {
+                            activate = Some(id);
+                        }
+                        if ui.small_button("✖").on_hover_text("Close").clicked() {
+                            close = Some(id);
+                        }
+                        ui.separator();
+                    }
+                });
+            });
+        ui.separator();
+
+        if let Some(id) = close {
+            self.close_tab(id, ctx, Some(ui.ctx()));
+        } else if let Some(id) = activate {
+            self.activate_tab(id, ctx, Some(ui.ctx()));
+            ctx.set_shared_state("markdown_editor_focus_requested", true);
+        }
+    }
+
+    /// Show the headings the caret is under, moving the caret to the one
+    /// clicked.
+    fn render_breadcrumbs(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        let headings = ctx
+            .get_shared_state::<Vec<jump::Heading>>(jump::OUTLINE_HEADINGS_KEY)
+            .unwrap_or_default();
+        let line = ctx
+            .get_shared_state::<usize>("markdown_editor_cursor_line")
+            .unwrap_or(1);
+        let path = breadcrumbs::heading_path(&headings, line);
+        if let Some(line) = breadcrumbs::show(ui, &path) {
+            ctx.set_shared_state(jump::GOTO_LINE_KEY, line);
+            ctx.set_shared_state("markdown_editor_focus_requested", true);
+        }
+        ui.separator();
+    }
+
+    /// Keep the completion model in step with the project and the buffer.
+    ///
+    /// The whole project is indexed in the background when it changes; the
+    /// active document is then indexed again from the buffer a moment after
+    /// each burst of edits.
+    fn refresh_corpus(&mut self, ctx: &mut PluginContext) {
+        if !self.core.config.autocomplete {
+            return;
+        }
+
+        let project = ctx.project_path();
+        if project != self.corpus_project {
+            if let Some(task) = self.corpus_task.take() {
+                task.cancel();
+            }
+            self.core.corpus = completion::CorpusModel::new();
+            self.core.completion = None;
+            if let Some(root) = project.clone() {
+                self.corpus_task = Some(ctx.spawn_task("Index project prose", move |progress| {
+                    completion::index_project(&root, progress)
+                }));
+            }
+            self.corpus_project = project;
+        }
+
+        if let Some(result) = self.corpus_task.as_mut().and_then(TaskHandle::try_take) {
+            self.corpus_task = None;
+            match result {
+                Ok(model) => {
+                    self.core.corpus = model;
+                    // The buffer may be ahead of the file on disk
+                    if self.core.has_changes {
+                        self.core.corpus_edited = Some(Instant::now() - CORPUS_REFRESH_DELAY);
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to index the project for completion: {}", e),
+            }
+        }
+
+        if self
+            .core
+            .corpus_edited
+            .is_some_and(|edited| edited.elapsed() >= CORPUS_REFRESH_DELAY)
+        {
+            let key = ctx
+                .get_shared_state::<Option<PathBuf>>("active_document_path")
+                .flatten()
+                .map(|path| completion::document_key(&path))
+                .unwrap_or_default();
+            self.core.corpus.set_document(key, &self.core.content);
+            self.core.corpus_edited = None;
         }
     }
 
-    pub fn content(&self) -> &str {
-        &self.core.content
+    /// Follow the dictionary published for the active project.
+    fn sync_dictionary(&mut self, ctx: &PluginContext) {
+        let dictionary = ctx
+            .get_config::<dictionary::ProjectDictionary>(dictionary::PROJECT_DICTIONARY_KEY)
+            .unwrap_or_default();
+        if dictionary != self.core.dictionary {
+            self.core.dictionary = dictionary;
+            self.core.spell_checked = None;
+        }
     }
 
-    pub fn set_content<S: Into<String>>(&mut self, content: S) {
-        self.core.content = content.into();
-        self.core.has_changes = true;
-        self.core.update_stats();
+    /// Follow the undo settings, and publish the steps of the active
+    /// document's history.
+    fn sync_undo(&mut self, ctx: &mut PluginContext) {
+        let settings = ctx
+            .get_shared_state::<editor::UndoSettings>(editor::UNDO_SETTINGS_KEY)
+            .unwrap_or_default();
+        if settings != self.core.undo_settings {
+            self.core.undo_settings = settings;
+            let core = &mut self.core;
+            std::iter::once(&mut core.editor_state)
+                .chain(core.tabs.iter_mut().map(|tab| &mut tab.history))
+                .chain(
+                    core.closed_histories
+                        .iter_mut()
+                        .map(|(_, _, history)| history),
+                )
+                .for_each(|history| history.set_settings(&settings));
+        }
+        ctx.set_shared_state(editor::UNDO_STATE_KEY, self.core.editor_state.state());
     }
 
-    pub fn has_changes(&self) -> bool {
-        self.core.has_changes
-    }
+    /// Follow the spell check settings, loading the dictionary of a new
+    /// language.
+    fn sync_spell_check(&mut self, ctx: &PluginContext) {
+        let settings = ctx
+            .get_shared_state::<spellcheck::SpellCheckSettings>(spellcheck::SPELL_CHECK_KEY)
+            .unwrap_or_default();
+        if settings == self.core.spell_settings {
+            return;
+        }
 
-    pub fn stats(&self) -> &stats::WritingStats {
-        &self.core.stats
+        let loaded = self
+            .core
+            .spell_checker
+            .as_ref()
+            .is_some_and(|checker| checker.language() == settings.language);
+        if !settings.enabled {
+            self.core.spell_checker = None;
+        } else if !loaded {
+            self.core.spell_checker = match spellcheck::SpellChecker::load(&settings) {
+                Ok(checker) => {
+                    tracing::info!("Loaded the {} spell check dictionary", settings.language);
+                    Some(checker)
+                }
+                Err(e) => {
+                    tracing::warn!("Spell check disabled: {}", e);
+                    None
+                }
+            };
+        }
+        // Words are counted in the language of the manuscript
+        let language = Some(settings.language.clone()).filter(|language| !language.is_empty());
+        if self.core.stats.language() != language.as_deref() {
+            self.core.stats.set_language(language);
+            self.core.update_stats();
+        }
+        self.core.spell_settings = settings;
+        self.core.misspellings.clear();
+        self.core.spell_checked = None;
     }
 
-    fn handle_auto_save(&mut self, ctx: &mut PluginContext) {
-        if !self.core.has_changes {
+    /// Follow the grammar issues published by a checker and apply the fixes
+    /// posted for them.
+    fn sync_grammar(&mut self, ctx: &mut PluginContext) {
+        self.core.grammar = ctx
+            .get_shared_state::<Option<GrammarReport>>(GRAMMAR_ISSUES_KEY)
+            .flatten();
+
+        let Some(fix) = ctx
+            .get_shared_state::<Option<GrammarFix>>(GRAMMAR_FIX_REQUEST)
+            .flatten()
+        else {
             return;
+        };
+        ctx.set_shared_state::<Option<GrammarFix>>(GRAMMAR_FIX_REQUEST, None);
+        // A fix for a text since edited would land in the wrong place
+        if fix.text_hash == grammar::text_hash(&self.core.content)
+            && self.core.content.get(fix.range.clone()).is_some()
+        {
+            self.core.apply_correction(ctx, fix.range, &fix.replacement);
         }
+    }
 
-        let elapsed = self.core.last_save.elapsed();
-        if elapsed.as_secs() >= self.core.config.auto_save_interval {
-            if let Err(e) = self.auto_save(ctx) {
-                tracing::error!("Auto-save failed: {}", e);
-            }
+    /// Follow the passages highlighted by analysis plugins.
+    fn sync_highlights(&mut self, ctx: &PluginContext) {
+        self.core.highlights = highlights::published(ctx).into_values().collect();
+    }
+
+    /// Follow the word count rules published for the active project.
+    fn sync_word_count_rules(&mut self, ctx: &PluginContext) {
+        let rules = ctx
+            .get_config::<stats::WordCountRules>(stats::WORD_COUNT_RULES_KEY)
+            .unwrap_or_default();
+        if rules != self.core.stats.rules() {
+            self.core.stats.set_rules(rules);
+            self.core.update_stats();
         }
     }
 
+    /// Save the edited documents.
+    ///
+    /// Documents open in tabs are saved by the application through its
+    /// document manager; an editor without tabs only publishes its content.
     fn auto_save(&mut self, ctx: &mut PluginContext) -> Result<()> {
         ctx.set_shared_state("markdown_editor_content", self.core.content.clone());
-        let event = Event::new(EventType::DocumentSaved, "Auto-saved document");
-        ctx.emit_event(event);
+        if let Some(id) = self.core.active_tab {
+            if self.core.has_changes {
+                self.core.publish_change(ctx, None);
+                documents::push(ctx, documents::SAVE_DOCUMENTS_REQUEST, id);
+            }
+            for tab in self.core.tabs.iter_mut().filter(|t| t.has_changes) {
+                documents::push(ctx, documents::SAVE_DOCUMENTS_REQUEST, tab.buffer.id);
+                tab.has_changes = false;
+            }
+        } else {
+            let event = Event::new(EventType::DocumentSaved, "Auto-saved document");
+            ctx.emit_event(event);
+        }
         self.core.has_changes = false;
-        self.core.last_save = std::time::Instant::now();
+        self.core.last_save = ctx.clock().instant();
         tracing::info!("Document auto-saved");
         Ok(())
     }
@@ -632,17 +2829,29 @@ impl Plugin for MarkdownEditorPlugin {
         } else {
             ctx.set_config("markdown_editor", &self.core.config);
         }
-
-        #[cfg(feature = "syntax-highlighting")]
-        if self.core.config.syntax_highlighting {
-            self.core.highlighter = Some(syntax::MarkdownHighlighter::new()?);
-        }
+        ctx.set_shared_state(focus::FOCUS_MODE_KEY, self.core.config.distraction_free);
+        commands::register(ctx);
 
         #[cfg(feature = "live-preview")]
         if self.core.config.live_preview {
             self.core.preview = Some(preview::PreviewRenderer::new());
         }
 
+        self.core.last_save = ctx.clock().instant();
+
+        let saved = Arc::clone(&self.saved_documents);
+        self.saved_subscription = Some(ctx.subscribe(
+            EventType::DocumentSaved,
+            move |event: &Event| {
+                if let Some(id) = event.document().and_then(|document| document.id) {
+                    if let Ok(mut saved) = saved.lock() {
+                        saved.push(id);
+                    }
+                }
+                Ok(())
+            },
+        ));
+
         if let Some(content) = ctx.get_shared_state::<String>("markdown_editor_content") {
             tracing::debug!(
                 "markdown-editor.initialize: received shared_state content (len={})",
@@ -653,6 +2862,15 @@ impl Plugin for MarkdownEditorPlugin {
             self.core.update_stats();
         }
 
+        if let Some(state) =
+            ctx.get_plugin_data::<serde_json::Value>("markdown-editor", SESSION_STATE_KEY)
+        {
+            match serde_json::from_value::<EditorSession>(state) {
+                Ok(session) => self.core.positions = session.positions,
+                Err(e) => tracing::warn!("Ignoring the editor session state: {}", e),
+            }
+        }
+
         tracing::info!("Markdown editor plugin initialized");
         Ok(())
     }
@@ -663,6 +2881,13 @@ impl Plugin for MarkdownEditorPlugin {
 
     fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
         self.handle_auto_save(ctx);
+        self.sync_word_count_rules(ctx);
+        self.sync_dictionary(ctx);
+        self.sync_spell_check(ctx);
+        self.sync_undo(ctx);
+        self.sync_grammar(ctx);
+        self.sync_highlights(ctx);
+        self.refresh_corpus(ctx);
 
         // Sync inbound shared state content into editor if provided
         if let Some(in_content) = ctx.get_shared_state::<String>("markdown_editor_content") {
@@ -682,51 +2907,17 @@ impl Plugin for MarkdownEditorPlugin {
                     // Content loaded from disk should be treated as saved
                     self.core.has_changes = false;
                     self.core.update_stats();
+                    self.core.publish_change(ctx, None);
                 }
             }
         }
 
         // Also check plugin-specific data for loaded content (fallback channel)
-        if let Some(loaded) = ctx.get_plugin_data::<String>("markdown-editor", "loaded_content") {
-            tracing::debug!(
-                "markdown-editor.update: found plugin_data loaded_content (len={})",
-                loaded.len()
-            );
-            if loaded != self.core.content && !loaded.is_empty() {
-                tracing::info!(
-                    "markdown-editor.update: applying plugin_data loaded_content to editor core"
-                );
-                self.core.content = loaded;
-                self.core.has_changes = false;
-                self.core.update_stats();
-                ctx.set_plugin_data("markdown-editor", "loaded_content", String::new());
-            }
-        }
+        self.apply_loaded_content(ctx);
+        self.sync_documents(ctx);
 
-        if let Some(action) = ctx.get_shared_state::<String>("markdown_editor_action") {
-            match action.as_str() {
-                "undo" => {
-                    if let Some(previous_content) =
-                        self.core.editor_state.undo(self.core.content.clone())
-                    {
-                        self.core.content = previous_content;
-                        self.core.has_changes = true;
-                        self.core.update_stats();
-                    }
-                    ctx.set_shared_state("markdown_editor_action", "".to_string());
-                }
-                "redo" => {
-                    if let Some(next_content) =
-                        self.core.editor_state.redo(self.core.content.clone())
-                    {
-                        self.core.content = next_content;
-                        self.core.has_changes = true;
-                        self.core.update_stats();
-                    }
-                    ctx.set_shared_state("markdown_editor_action", "".to_string());
-                }
-                _ => {}
-            }
+        if let Some(command) = commands::take(ctx) {
+            self.core.run_command(ctx, command);
         }
 
         Ok(())
@@ -744,6 +2935,14 @@ impl PanelPlugin for MarkdownEditorPlugin {
 
     fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
         self.handle_auto_save(ctx);
+        self.sync_word_count_rules(ctx);
+        self.sync_dictionary(ctx);
+        self.sync_spell_check(ctx);
+        self.sync_undo(ctx);
+        self.sync_grammar(ctx);
+        self.sync_highlights(ctx);
+        self.refresh_corpus(ctx);
+        self.apply_loaded_content(ctx);
 
         // Sync inbound shared state content into editor if provided
         if let Some(in_content) = ctx.get_shared_state::<String>("markdown_editor_content") {
@@ -761,37 +2960,18 @@ impl PanelPlugin for MarkdownEditorPlugin {
                     // Content loaded from disk should be treated as saved
                     self.core.has_changes = false;
                     self.core.update_stats();
+                    self.core.publish_change(ctx, None);
                 }
             }
         }
 
+        self.sync_documents(ctx);
+
         // Publish current content to shared state for other plugins (like Atmosphere)
         ctx.set_shared_state("markdown_editor_content", self.core.content.clone());
 
-        if let Some(action) = ctx.get_shared_state::<String>("markdown_editor_action") {
-            match action.as_str() {
-                "undo" => {
-                    if let Some(previous_content) =
-                        self.core.editor_state.undo(self.core.content.clone())
-                    {
-                        self.core.content = previous_content;
-                        self.core.has_changes = true;
-                        self.core.update_stats();
-                    }
-                    ctx.set_shared_state("markdown_editor_action", "".to_string());
-                }
-                "redo" => {
-                    if let Some(next_content) =
-                        self.core.editor_state.redo(self.core.content.clone())
-                    {
-                        self.core.content = next_content;
-                        self.core.has_changes = true;
-                        self.core.update_stats();
-                    }
-                    ctx.set_shared_state("markdown_editor_action", "".to_string());
-                }
-                _ => {}
-            }
+        if let Some(command) = commands::take(ctx) {
+            self.core.run_command(ctx, command);
         }
 
         Ok(())
@@ -815,6 +2995,39 @@ impl PanelPlugin for MarkdownEditorPlugin {
             );
         }
 
+        self.activate_pending_tab(ctx, Some(ui.ctx()));
+        // Nothing but the text in focus mode
+        let focus_mode = self.core.config.distraction_free;
+        if !focus_mode {
+            self.render_document_tabs(ui, ctx);
+            if self.core.config.show_breadcrumbs {
+                self.render_breadcrumbs(ui, ctx);
+            }
+        }
+
+        // Live preview beside the editor, updated as the content changes
+        #[cfg(feature = "live-preview")]
+        let mut preview_frame = None;
+        #[cfg(feature = "live-preview")]
+        if let Some(renderer) = self.core.preview.as_ref().filter(|_| !focus_mode) {
+            let live_preview = &mut self.core.live_preview;
+            live_preview.update(&self.core.content, renderer.options());
+            let target = self.core.scroll_sync.take_preview_target();
+            egui::SidePanel::right("markdown_editor_preview")
+                .resizable(true)
+                .default_width(ui.available_width() / 2.0)
+                .show_inside(ui, |ui| {
+                    let mut scroll_area = egui::ScrollArea::vertical()
+                        .id_salt("markdown_editor_preview_scroll")
+                        .auto_shrink(false);
+                    if let Some(offset) = target {
+                        scroll_area = scroll_area.vertical_scroll_offset(offset);
+                    }
+                    let output = scroll_area.show(ui, |ui| live_preview.show(ui));
+                    preview_frame = Some((output.state.offset.y, output.inner));
+                });
+        }
+
         let mut pending_action = None;
 
         let mut viewer = EditorViewer {
@@ -825,7 +3038,34 @@ impl PanelPlugin for MarkdownEditorPlugin {
 
         DockArea::new(&mut self.tree)
             .style(Style::from_egui(ui.style().as_ref()))
-            .show(ui.ctx(), &mut viewer);
+            .show_inside(ui, &mut viewer);
+
+        #[cfg(feature = "live-preview")]
+        if let Some((offset, clicked)) = preview_frame {
+            self.core.sync_scroll(ui.ctx(), ctx, offset, clicked);
+        }
+
+        // Show what was typed in this frame in the next
+        #[cfg(feature = "live-preview")]
+        if let Some(renderer) = &self.core.preview {
+            if self
+                .core
+                .live_preview
+                .update(&self.core.content, renderer.options())
+            {
+                ui.ctx().request_repaint();
+            }
+        }
+
+        if std::mem::take(&mut self.core.positions_changed) {
+            let session = EditorSession {
+                positions: self.core.positions.clone(),
+            };
+            match serde_json::to_value(&session) {
+                Ok(state) => ctx.set_plugin_data("markdown-editor", SESSION_STATE_KEY, state),
+                Err(e) => tracing::warn!("Cannot keep the editor session state: {}", e),
+            }
+        }
 
         // Handle any pending actions from context menus
         if let Some(action) = pending_action {
@@ -896,7 +3136,7 @@ impl PanelPlugin for MarkdownEditorPlugin {
     fn context_menu_items(&self) -> Vec<cosmarium_plugin_api::PanelContextMenuItem> {
         use cosmarium_plugin_api::PanelContextMenuItem;
 
-        vec![
+        let mut items = vec![
             PanelContextMenuItem::new("save", "Save Document"),
             PanelContextMenuItem::new("export", "Export..."),
             PanelContextMenuItem::separator(),
@@ -916,6 +3156,14 @@ impl PanelPlugin for MarkdownEditorPlugin {
                     "Show Line Numbers"
                 },
             ),
+            PanelContextMenuItem::new(
+                "breadcrumbs",
+                if self.core.config.show_breadcrumbs {
+                    "Hide Breadcrumbs"
+                } else {
+                    "Show Breadcrumbs"
+                },
+            ),
             PanelContextMenuItem::new(
                 "distraction_free",
                 if self.core.config.distraction_free {
@@ -924,9 +3172,104 @@ impl PanelPlugin for MarkdownEditorPlugin {
                     "Enter Focus Mode"
                 },
             ),
+            PanelContextMenuItem::new(
+                "autocomplete",
+                if self.core.config.autocomplete {
+                    "Disable Autocomplete"
+                } else {
+                    "Enable Autocomplete"
+                },
+            ),
+            PanelContextMenuItem::new(
+                "auto_pair",
+                if self.core.config.auto_pair {
+                    "Disable Bracket and Quote Pairing"
+                } else {
+                    "Enable Bracket and Quote Pairing"
+                },
+            ),
+            PanelContextMenuItem::new(
+                "continue_lists",
+                if self.core.config.continue_lists {
+                    "Disable List Continuation"
+                } else {
+                    "Enable List Continuation"
+                },
+            ),
+            PanelContextMenuItem::new(
+                "syntax_highlighting",
+                if self.core.config.syntax_highlighting {
+                    "Disable Syntax Highlighting"
+                } else {
+                    "Enable Syntax Highlighting"
+                },
+            ),
+            PanelContextMenuItem::new(
+                "collapse_footnotes",
+                if self.core.config.collapse_footnotes {
+                    "Expand Footnotes"
+                } else {
+                    "Collapse Footnotes"
+                },
+            ),
+            PanelContextMenuItem::new(
+                "collapse_annotations",
+                if self.core.config.collapse_annotations {
+                    "Expand Annotations"
+                } else {
+                    "Collapse Annotations"
+                },
+            ),
+            PanelContextMenuItem::new(
+                "collapse_author_notes",
+                if self.core.config.collapse_author_notes {
+                    "Expand Author Notes"
+                } else {
+                    "Collapse Author Notes"
+                },
+            ),
+            PanelContextMenuItem::new(
+                "collapse_front_matter",
+                if self.core.config.collapse_front_matter {
+                    "Expand Front Matter"
+                } else {
+                    "Collapse Front Matter"
+                },
+            ),
+        ];
+        #[cfg(feature = "live-preview")]
+        items.push(PanelContextMenuItem::new(
+            "live_preview",
+            if self.core.config.live_preview {
+                "Hide Preview"
+            } else {
+                "Show Preview"
+            },
+        ));
+        #[cfg(feature = "live-preview")]
+        if self.core.config.live_preview {
+            items.push(PanelContextMenuItem::new(
+                "sync_scroll",
+                if self.core.config.sync_scroll {
+                    "Scroll Preview Separately"
+                } else {
+                    "Scroll Preview with Editor"
+                },
+            ));
+        }
+        items.extend([
+            PanelContextMenuItem::new("reflow", "Reflow Paragraph (Alt+Q)"),
+            PanelContextMenuItem::new("paragraph_rtl", "Paragraph Direction: Right-to-Left"),
+            PanelContextMenuItem::new("paragraph_ltr", "Paragraph Direction: Left-to-Right"),
+            PanelContextMenuItem::new("paragraph_auto", "Paragraph Direction: From Text"),
+            PanelContextMenuItem::new("paragraph_center", "Center Paragraph"),
+            PanelContextMenuItem::new("paragraph_right", "Align Paragraph Right"),
+            PanelContextMenuItem::new("paragraph_unaligned", "Reset Paragraph Alignment"),
+            PanelContextMenuItem::new("verse", "Toggle Verse"),
             PanelContextMenuItem::separator(),
             PanelContextMenuItem::new("settings", "Editor Settings"),
-        ]
+        ]);
+        items
     }
 
     fn handle_context_menu(&mut self, item_id: &str, ctx: &mut PluginContext) -> Result<()> {
@@ -938,14 +3281,96 @@ impl PanelPlugin for MarkdownEditorPlugin {
                 self.core.config.word_wrap = !self.core.config.word_wrap;
                 ctx.set_config("markdown_editor", &self.core.config);
             }
+            "reflow" => {
+                self.core.reflow_requested = true;
+            }
+            "paragraph_rtl"
+            | "paragraph_ltr"
+            | "paragraph_auto"
+            | "paragraph_center"
+            | "paragraph_right"
+            | "paragraph_unaligned" => {
+                use cosmarium_plugin_api::direction::{Alignment, Direction};
+                use direction::FormatChange;
+                self.core.format_requested = Some(match item_id {
+                    "paragraph_rtl" => FormatChange::Direction(Some(Direction::Rtl)),
+                    "paragraph_ltr" => FormatChange::Direction(Some(Direction::Ltr)),
+                    "paragraph_auto" => FormatChange::Direction(None),
+                    "paragraph_center" => FormatChange::Alignment(Some(Alignment::Center)),
+                    "paragraph_right" => FormatChange::Alignment(Some(Alignment::Right)),
+                    _ => FormatChange::Alignment(None),
+                });
+            }
+            "verse" => {
+                self.core.verse_requested = true;
+            }
             "line_numbers" => {
                 self.core.config.show_line_numbers = !self.core.config.show_line_numbers;
                 ctx.set_config("markdown_editor", &self.core.config);
             }
+            "breadcrumbs" => {
+                self.core.config.show_breadcrumbs = !self.core.config.show_breadcrumbs;
+                ctx.set_config("markdown_editor", &self.core.config);
+            }
             "distraction_free" => {
-                self.core.config.distraction_free = !self.core.config.distraction_free;
+                let on = !self.core.config.distraction_free;
+                self.core.set_focus_mode(ctx, on);
+            }
+            "auto_pair" => {
+                self.core.config.auto_pair = !self.core.config.auto_pair;
+                ctx.set_config("markdown_editor", &self.core.config);
+            }
+            "continue_lists" => {
+                self.core.config.continue_lists = !self.core.config.continue_lists;
+                ctx.set_config("markdown_editor", &self.core.config);
+            }
+            "syntax_highlighting" => {
+                self.core.config.syntax_highlighting = !self.core.config.syntax_highlighting;
+                ctx.set_config("markdown_editor", &self.core.config);
+            }
+            "collapse_footnotes" => {
+                self.core.config.collapse_footnotes = !self.core.config.collapse_footnotes;
+                ctx.set_config("markdown_editor", &self.core.config);
+            }
+            "collapse_annotations" => {
+                self.core.config.collapse_annotations = !self.core.config.collapse_annotations;
                 ctx.set_config("markdown_editor", &self.core.config);
             }
+            "collapse_author_notes" => {
+                self.core.config.collapse_author_notes = !self.core.config.collapse_author_notes;
+                ctx.set_config("markdown_editor", &self.core.config);
+            }
+            "collapse_front_matter" => {
+                self.core.config.collapse_front_matter = !self.core.config.collapse_front_matter;
+                ctx.set_config("markdown_editor", &self.core.config);
+            }
+            #[cfg(feature = "live-preview")]
+            "live_preview" => {
+                self.core.config.live_preview = !self.core.config.live_preview;
+                ctx.set_config("markdown_editor", &self.core.config);
+                self.core.preview = self
+                    .core
+                    .config
+                    .live_preview
+                    .then(preview::PreviewRenderer::new);
+            }
+            #[cfg(feature = "live-preview")]
+            "sync_scroll" => {
+                self.core.config.sync_scroll = !self.core.config.sync_scroll;
+                ctx.set_config("markdown_editor", &self.core.config);
+            }
+            "autocomplete" => {
+                self.core.config.autocomplete = !self.core.config.autocomplete;
+                ctx.set_config("markdown_editor", &self.core.config);
+                if !self.core.config.autocomplete {
+                    // Rebuilt from scratch when turned back on
+                    if let Some(task) = self.corpus_task.take() {
+                        task.cancel();
+                    }
+                    self.corpus_project = None;
+                    self.core.corpus = completion::CorpusModel::new();
+                }
+            }
             _ => {
                 tracing::warn!("Unhandled context menu item: {}", item_id);
             }
@@ -957,6 +3382,7 @@ impl PanelPlugin for MarkdownEditorPlugin {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cosmarium_plugin_api::clock::Clock;
     use cosmarium_plugin_api::PluginContext;
 
     #[test]
@@ -998,6 +3424,80 @@ mod tests {
         assert!(saved_config.is_some());
     }
 
+    #[test]
+    fn test_selection_commands() {
+        use commands::EditorCommand;
+        use egui::text::{CCursor, CCursorRange};
+
+        let egui_ctx = egui::Context::default();
+        let id = egui::Id::new("view");
+        let mut editor = MarkdownEditorPlugin::new();
+        editor.core.content = "Hello brave world".into();
+        let mut state = TextEditState::default();
+        state
+            .cursor
+            .set_char_range(Some(CCursorRange::two(CCursor::new(12), CCursor::new(6))));
+        state.store(&egui_ctx, id);
+
+        assert!(!editor
+            .core
+            .run_selection_command(&egui_ctx, id, EditorCommand::Copy));
+        assert!(editor
+            .core
+            .run_selection_command(&egui_ctx, id, EditorCommand::Cut));
+        assert_eq!(editor.content(), "Hello world");
+
+        // Nothing left selected to cut
+        assert!(!editor
+            .core
+            .run_selection_command(&egui_ctx, id, EditorCommand::Cut));
+        editor
+            .core
+            .run_selection_command(&egui_ctx, id, EditorCommand::SelectAll);
+        let range = egui::TextEdit::load_state(&egui_ctx, id)
+            .and_then(|state| state.cursor.char_range())
+            .unwrap();
+        assert_eq!(range.sorted_cursors().map(|cursor| cursor.index), [0, 11]);
+    }
+
+    #[test]
+    fn test_focus_mode_is_toggled_and_published() {
+        let mut ctx = PluginContext::new();
+        let mut editor = MarkdownEditorPlugin::new();
+        editor.initialize(&mut ctx).unwrap();
+        assert_eq!(
+            ctx.get_shared_state::<bool>(focus::FOCUS_MODE_KEY),
+            Some(false)
+        );
+
+        commands::send(&mut ctx, commands::EditorCommand::ToggleFocusMode);
+        Plugin::update(&mut editor, &mut ctx).unwrap();
+        assert_eq!(
+            ctx.get_shared_state::<bool>(focus::FOCUS_MODE_KEY),
+            Some(true)
+        );
+        let config: EditorConfig = ctx.get_config("markdown_editor").unwrap();
+        assert!(config.distraction_free);
+    }
+
+    #[test]
+    fn test_auto_save_after_the_interval() {
+        let mut ctx = PluginContext::new();
+        ctx.set_clock(Clock::manual(Clock::EPOCH));
+        let mut editor = MarkdownEditorPlugin::new();
+        editor.initialize(&mut ctx).unwrap();
+        editor.set_content("Draft");
+        let interval = Duration::from_secs(editor.core.config.auto_save_interval);
+
+        ctx.clock().advance(interval - Duration::from_secs(1));
+        Plugin::update(&mut editor, &mut ctx).unwrap();
+        assert!(editor.has_changes());
+
+        ctx.clock().advance(Duration::from_secs(1));
+        Plugin::update(&mut editor, &mut ctx).unwrap();
+        assert!(!editor.has_changes());
+    }
+
     #[test]
     fn test_auto_save_logic() {
         let mut editor = MarkdownEditorPlugin::new();
@@ -1031,4 +3531,245 @@ mod tests {
             "Content loaded from shared state should be treated as saved"
         );
     }
+
+    #[test]
+    fn test_edits_feed_completion() {
+        let mut editor = MarkdownEditorPlugin::new();
+        let mut ctx = PluginContext::new();
+
+        editor.set_content("Mirela opened the gate. Mirela ran.\nMir");
+        editor.core.corpus_edited = Some(Instant::now() - CORPUS_REFRESH_DELAY);
+        editor.refresh_corpus(&mut ctx);
+        assert!(editor.core.corpus_edited.is_none());
+
+        let cursor = editor.content().chars().count();
+        let popup = editor.core.complete_at(cursor).unwrap();
+        assert_eq!(popup.word.prefix, "Mir");
+        assert_eq!(popup.suggestions[0].text, "Mirela");
+    }
+
+    #[test]
+    fn test_document_tabs() {
+        let mut editor = MarkdownEditorPlugin::new();
+        let mut ctx = PluginContext::new();
+        let buffer = |title: &str, content: &str| documents::DocumentBuffer {
+            id: Uuid::new_v4(),
+            title: title.into(),
+            path: None,
+            content: content.into(),
+        };
+        let first = buffer("One", "First chapter");
+        let second = buffer("Two", "Second chapter");
+        documents::push(&mut ctx, documents::OPEN_DOCUMENTS_REQUEST, first.clone());
+        documents::push(&mut ctx, documents::OPEN_DOCUMENTS_REQUEST, second.clone());
+
+        assert!(cosmarium_plugin_api::Plugin::update(&mut editor, &mut ctx).is_ok());
+        editor.activate_pending_tab(&mut ctx, None);
+        assert_eq!(editor.core.tabs.len(), 2);
+        assert_eq!(editor.content(), "Second chapter");
+
+        // Edits stay with their tab
+        editor.activate_tab(first.id, &mut ctx, None);
+        editor.set_content("First chapter, edited");
+        editor.activate_tab(second.id, &mut ctx, None);
+        assert_eq!(editor.content(), "Second chapter");
+        editor.activate_tab(first.id, &mut ctx, None);
+        assert_eq!(editor.content(), "First chapter, edited");
+        assert_eq!(
+            ctx.get_shared_state::<Option<Uuid>>(documents::ACTIVE_DOCUMENT_KEY),
+            Some(Some(first.id))
+        );
+
+        // Saving goes through the application's document manager
+        assert!(editor.auto_save(&mut ctx).is_ok());
+        let saves: Vec<Uuid> = documents::drain(&mut ctx, documents::SAVE_DOCUMENTS_REQUEST);
+        assert_eq!(saves, vec![first.id]);
+        let updates: Vec<documents::DocumentBuffer> =
+            documents::drain(&mut ctx, documents::DOCUMENT_UPDATES);
+        assert!(updates
+            .iter()
+            .any(|u| u.id == first.id && u.content == "First chapter, edited"));
+
+        editor.close_tab(first.id, &mut ctx, None);
+        assert_eq!(editor.core.active_tab, Some(second.id));
+        let closes: Vec<Uuid> = documents::drain(&mut ctx, documents::CLOSE_DOCUMENTS_REQUEST);
+        assert_eq!(closes, vec![first.id]);
+    }
+
+    #[test]
+    fn test_undo_history_outlives_its_tab() {
+        let mut editor = MarkdownEditorPlugin::new();
+        let mut ctx = PluginContext::new();
+        let buffer = |content: &str| documents::DocumentBuffer {
+            id: Uuid::new_v4(),
+            title: "One".into(),
+            path: Some(PathBuf::from("one.md")),
+            content: content.into(),
+        };
+        // Steps that can be undone in `buffer` once opened
+        fn reopen(
+            editor: &mut MarkdownEditorPlugin,
+            ctx: &mut PluginContext,
+            buffer: documents::DocumentBuffer,
+        ) -> usize {
+            let id = buffer.id;
+            documents::push(ctx, documents::OPEN_DOCUMENTS_REQUEST, buffer);
+            assert!(cosmarium_plugin_api::Plugin::update(editor, ctx).is_ok());
+            editor.activate_pending_tab(ctx, None);
+            // The steps are published on the next update
+            assert!(cosmarium_plugin_api::Plugin::update(editor, ctx).is_ok());
+            let state = ctx.get_shared_state::<editor::UndoState>(editor::UNDO_STATE_KEY);
+            editor.close_tab(id, ctx, None);
+            state.unwrap().undo
+        }
+
+        let first = buffer("Once");
+        documents::push(&mut ctx, documents::OPEN_DOCUMENTS_REQUEST, first.clone());
+        assert!(cosmarium_plugin_api::Plugin::update(&mut editor, &mut ctx).is_ok());
+        editor.activate_pending_tab(&mut ctx, None);
+        editor.core.buffer.reset("Once");
+        editor.core.content = "Once upon".to_string();
+        editor.core.record_edit(&mut ctx);
+        editor.close_tab(first.id, &mut ctx, None);
+        assert_eq!(editor.content(), "");
+
+        // Opened again as it was left, its edits can be undone
+        assert_eq!(reopen(&mut editor, &mut ctx, buffer("Once upon")), 1);
+        // Changed elsewhere, they cannot
+        assert_eq!(reopen(&mut editor, &mut ctx, buffer("Once upon a time")), 0);
+    }
+
+    #[test]
+    fn test_edits_publish_revisions() {
+        struct Changes(Arc<Mutex<Vec<DocumentChange>>>);
+        impl cosmarium_plugin_api::EventHandler for Changes {
+            fn handle(&mut self, event: &Event) -> Result<()> {
+                self.0.lock().unwrap().extend(event.payload_as());
+                Ok(())
+            }
+        }
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let mut ctx = PluginContext::new();
+        ctx.register_event_handler("DocumentChanged", Box::new(Changes(changes.clone())));
+
+        let mut editor = MarkdownEditorPlugin::new();
+        editor.core.buffer.reset("The sat.");
+        editor.core.content = "The cat sat.".to_string();
+        editor.core.record_edit(&mut ctx);
+        editor.core.content = "The cat sat down.".to_string();
+        editor.core.record_edit(&mut ctx);
+        // Recording the same content again changes nothing
+        assert_eq!(editor.core.record_edit(&mut ctx), None);
+
+        let changes = changes.lock().unwrap();
+        let revisions: Vec<u64> = changes.iter().map(|c| c.revision).collect();
+        assert_eq!(revisions, vec![1, 2]);
+        assert_eq!(changes[0].replaced, Some(4..4));
+        assert_eq!(changes[0].inserted_len, 4);
+        assert_eq!(changes[1].word_range("The cat sat down."), Some(8..16));
+    }
+
+    #[test]
+    fn test_transactions_apply_whole() {
+        let mut editor = MarkdownEditorPlugin::new();
+        let mut ctx = PluginContext::new();
+        editor.core.content = "Ann met Bob.".to_string();
+        editor.core.buffer.reset("Ann met Bob.");
+
+        let mut transaction = EditTransaction::begin();
+        transaction
+            .replace_all("Bob", "Robert")
+            .insert_snippet(0, "${1:Title}\n");
+        let placeholders = editor
+            .core
+            .apply_transaction(&mut ctx, transaction)
+            .unwrap();
+        assert_eq!(placeholders.first(), Some(&(0..5)));
+        assert_eq!(editor.core.content, "Title\nAnn met Robert.");
+        editor.core.record_edit(&mut ctx);
+        editor.core.undo(&mut ctx);
+        assert_eq!(editor.core.content, "Ann met Bob.");
+        editor.core.redo(&mut ctx);
+        assert_eq!(editor.core.content, "Title\nAnn met Robert.");
+
+        // Failing transactions, and those for another document, change
+        // nothing
+        let mut failing = EditTransaction::begin();
+        failing.replace_all("Ann", "Anna").replace(0..99, "");
+        let failing_id = failing.id;
+        assert_eq!(editor.core.apply_transaction(&mut ctx, failing), None);
+        let elsewhere = EditTransaction::begin().on_document(Uuid::new_v4());
+        assert_eq!(editor.core.apply_transaction(&mut ctx, elsewhere), None);
+        assert_eq!(editor.core.content, "Title\nAnn met Robert.");
+
+        let results: Vec<TransactionResult> = documents::drain(&mut ctx, EDIT_TRANSACTION_RESULTS);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].error, None);
+        assert_eq!(results[1].id, failing_id);
+        assert!(matches!(
+            results[1].error,
+            Some(TransactionError::InvalidRange { step: 1, .. })
+        ));
+        assert_eq!(results[2].error, Some(TransactionError::DocumentNotActive));
+    }
+
+    #[test]
+    fn test_content_replaced_by_application() {
+        let mut editor = MarkdownEditorPlugin::new();
+        let mut ctx = PluginContext::new();
+        let mut buffer = documents::DocumentBuffer {
+            id: Uuid::new_v4(),
+            title: "One".into(),
+            path: None,
+            content: "A line far too long".into(),
+        };
+        documents::push(&mut ctx, documents::OPEN_DOCUMENTS_REQUEST, buffer.clone());
+        assert!(cosmarium_plugin_api::Plugin::update(&mut editor, &mut ctx).is_ok());
+        editor.activate_pending_tab(&mut ctx, None);
+
+        // Wrapped on save by the application
+        buffer.content = wrap::hard_wrap(&buffer.content, 10);
+        documents::push(&mut ctx, documents::REPLACE_DOCUMENTS_REQUEST, buffer);
+        assert!(cosmarium_plugin_api::Plugin::update(&mut editor, &mut ctx).is_ok());
+        assert_eq!(editor.content(), "A line far\ntoo long");
+
+        commands::send(&mut ctx, commands::EditorCommand::Undo);
+        assert!(cosmarium_plugin_api::Plugin::update(&mut editor, &mut ctx).is_ok());
+        assert_eq!(editor.content(), "A line far too long");
+    }
+
+    #[test]
+    fn test_positions_restored_from_session() {
+        let path = PathBuf::from("chapters/one.md");
+        let mut positions = documents::DocumentPositions::default();
+        positions.set(
+            &path,
+            documents::ViewPosition {
+                view: "Main View".into(),
+                caret: 40,
+                anchor: 40,
+                scroll: 120.0,
+            },
+        );
+        let mut ctx = PluginContext::new();
+        ctx.set_plugin_data(
+            "markdown-editor",
+            SESSION_STATE_KEY,
+            serde_json::to_value(EditorSession { positions }).unwrap(),
+        );
+
+        let mut editor = MarkdownEditorPlugin::new();
+        editor.initialize(&mut ctx).unwrap();
+        let buffer = documents::DocumentBuffer {
+            id: Uuid::new_v4(),
+            title: "One".into(),
+            path: Some(path),
+            content: "First chapter".into(),
+        };
+        documents::push(&mut ctx, documents::OPEN_DOCUMENTS_REQUEST, buffer);
+        cosmarium_plugin_api::Plugin::update(&mut editor, &mut ctx).unwrap();
+        editor.activate_pending_tab(&mut ctx, None);
+
+        assert_eq!(editor.core.pending_scroll.get("Main View"), Some(&120.0));
+    }
 }
@@ -0,0 +1,189 @@
+//! # List continuation
+//!
+//! Enter at the end of a list item starts the next item, with the same
+//! bullet or the next number, and an unchecked box after a task. In a block
+//! quote, the new line is quoted too. Enter on an item or a quote line left
+//! empty removes its marker instead, ending the list or the quote, so that
+//! pressing Enter twice gets out of them. Code blocks are left alone.
+
+use crate::pairs::Edit;
+use crate::wrap;
+
+/// Markers starting a line, as byte lengths.
+struct Markers {
+    /// Indentation and block quote markers
+    quote: usize,
+    /// List marker with the spaces after it and a task box, if any
+    item: usize,
+}
+
+impl Markers {
+    fn of(line: &str) -> Self {
+        let mut rest = line.trim_start_matches([' ', '\t']);
+        while let Some(after) = rest.strip_prefix('>') {
+            rest = after.strip_prefix(' ').unwrap_or(after);
+        }
+        let quote = line.len() - rest.len();
+        let item = item_marker(rest).map_or(0, |marker| {
+            let after = &rest[marker..];
+            let task = ["[ ] ", "[x] ", "[X] "]
+                .iter()
+                .any(|task| after.starts_with(task));
+            marker + if task { 4 } else { 0 }
+        });
+        Self { quote, item }
+    }
+}
+
+/// Length of the list marker starting `text`, with the spaces after it.
+fn item_marker(text: &str) -> Option<usize> {
+    let digits = text.bytes().take_while(u8::is_ascii_digit).count();
+    let marker = match text.as_bytes().get(digits) {
+        Some(b'.' | b')') if (1..10).contains(&digits) => digits + 1,
+        Some(b'-' | b'*' | b'+') if digits == 0 => 1,
+        _ => return None,
+    };
+    let spaces = text[marker..].len() - text[marker..].trim_start_matches([' ', '\t']).len();
+    (spaces > 0).then_some(marker + spaces)
+}
+
+/// Marker of the item following the one marked with `marker`.
+fn next_marker(marker: &str) -> String {
+    let digits = marker.bytes().take_while(u8::is_ascii_digit).count();
+    let next = match marker[..digits].parse::<u64>() {
+        Ok(number) => format!("{}{}", number + 1, &marker[digits..]),
+        Err(_) => marker.to_string(),
+    };
+    match next.find('[') {
+        Some(task) => format!("{}[ ] ", &next[..task]),
+        None => next,
+    }
+}
+
+/// Length of the indentation, block quote and list markers starting `line`.
+pub(crate) fn markers_len(line: &str) -> usize {
+    let markers = Markers::of(line);
+    markers.quote + markers.item
+}
+
+/// Whether `offset` of `content` is within a fenced code block.
+pub(crate) fn in_code(content: &str, offset: usize) -> bool {
+    content[..offset]
+        .lines()
+        .filter(|line| {
+            let line = line.trim_start();
+            line.starts_with("```") || line.starts_with("~~~")
+        })
+        .count()
+        % 2
+        == 1
+}
+
+/// Change made by pressing Enter at `cursor` in `content`, if it continues a
+/// list or a quote, or ends one.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::lists::press_enter;
+///
+/// let content = "1. Draft";
+/// let edit = press_enter(content, content.len()).unwrap();
+/// assert_eq!(edit.text, "\n2. ");
+///
+/// // A second Enter ends the list
+/// let content = "1. Draft\n2. ";
+/// let edit = press_enter(content, content.len()).unwrap();
+/// assert_eq!((edit.range, edit.text.as_str()), (9..12, ""));
+/// ```
+pub fn press_enter(content: &str, cursor: usize) -> Option<Edit> {
+    let line_start = content[..cursor].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = content[cursor..]
+        .find('\n')
+        .map_or(content.len(), |i| cursor + i);
+    let line = content[line_start..line_end].trim_end_matches('\r');
+    if wrap::is_rule(line) || in_code(content, line_start) {
+        return None;
+    }
+
+    let markers = Markers::of(line);
+    let prefix = markers.quote + markers.item;
+    let quoted = line[..markers.quote].contains('>');
+    if (!quoted && markers.item == 0) || cursor < line_start + prefix {
+        return None;
+    }
+
+    if line[prefix..].trim().is_empty() {
+        // An empty item ends the list, an empty quote line the quote
+        let kept = if markers.item > 0 {
+            &line[..markers.quote]
+        } else {
+            ""
+        };
+        let caret = line_start + kept.len();
+        return Some(Edit {
+            range: line_start..line_start + line.len(),
+            text: kept.to_string(),
+            selection: caret..caret,
+        });
+    }
+
+    let text = format!(
+        "\n{}{}",
+        &line[..markers.quote],
+        next_marker(&line[markers.quote..prefix])
+    );
+    let caret = cursor + text.len();
+    Some(Edit {
+        range: cursor..cursor,
+        text,
+        selection: caret..caret,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Content after pressing Enter at the end of `content`, if it did more
+    /// than a newline, with the caret as `|`.
+    fn enter(content: &str) -> Option<String> {
+        let edit = press_enter(content, content.len())?;
+        let mut content = content.to_string();
+        content.replace_range(edit.range, &edit.text);
+        content.insert(edit.selection.start, '|');
+        Some(content)
+    }
+
+    #[test]
+    fn test_continue_lists_and_quotes() {
+        assert_eq!(enter("- one").as_deref(), Some("- one\n- |"));
+        assert_eq!(enter("  * one").as_deref(), Some("  * one\n  * |"));
+        assert_eq!(enter("9) nine").as_deref(), Some("9) nine\n10) |"));
+        assert_eq!(enter("- [x] done").as_deref(), Some("- [x] done\n- [ ] |"));
+        assert_eq!(enter("> She said").as_deref(), Some("> She said\n> |"));
+        assert_eq!(enter("> > 1. a").as_deref(), Some("> > 1. a\n> > 2. |"));
+
+        // Prose, rules and code go on as usual
+        assert_eq!(enter("Prose"), None);
+        assert_eq!(enter("* * *"), None);
+        assert_eq!(enter("```\n- code"), None);
+        assert_eq!(enter("-1 degrees"), None);
+    }
+
+    #[test]
+    fn test_empty_item_ends_the_list() {
+        assert_eq!(enter("- one\n- ").as_deref(), Some("- one\n|"));
+        assert_eq!(enter("> - one\n> - ").as_deref(), Some("> - one\n> |"));
+        assert_eq!(enter("> one\n> ").as_deref(), Some("> one\n|"));
+    }
+
+    #[test]
+    fn test_enter_within_an_item() {
+        let content = "- one two";
+        let edit = press_enter(content, 5).unwrap();
+        assert_eq!((edit.range, edit.text.as_str()), (5..5, "\n- "));
+        // Before the marker, a newline only
+        assert!(press_enter(content, 0).is_none());
+    }
+}
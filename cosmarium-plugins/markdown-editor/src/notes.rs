@@ -0,0 +1,444 @@
+//! # Collapsed notes
+//!
+//! While drafting, footnotes, annotations and bracketed author notes can be
+//! collapsed into small badges, each kind with its own toggle in the context
+//! menu. Hovering a badge shows its note; clicking it, or moving the caret
+//! into the note, expands the note for editing. Only the layout changes: the
+//! text, as saved, keeps its notes.
+//!
+//! Notes are:
+//!
+//! - footnotes: inline footnotes (`^[note]`) and footnote definitions
+//!   (`[^1]: note`)
+//! - annotations: HTML comments (`<!-- note -->`), other than the markers of
+//!   paragraph direction and verse, and CriticMarkup comments (`{>> note <<}`)
+//! - author notes: a word and a colon in square brackets (`[TK: name the
+//!   inn]`, `[Note: check the dates]`)
+//! - front matter: the YAML or TOML block opening the document, collapsed
+//!   unless turned off
+
+use cosmarium_plugin_api::direction::ParagraphFormat;
+use cosmarium_plugin_api::front_matter::FrontMatter;
+use cosmarium_plugin_api::verse;
+use egui::text::{CCursor, LayoutJob, LayoutSection, TextFormat};
+use egui::{Align2, Color32, FontId, Galley, Painter, Pos2, Rect, Ui, Vec2};
+use std::ops::Range;
+
+/// Font size of the text of collapsed notes, small enough for it to take
+/// no room.
+const COLLAPSED_SIZE: f32 = 0.01;
+
+/// Kind of a note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NoteKind {
+    /// Inline footnote or footnote definition
+    Footnote,
+    /// HTML or CriticMarkup comment
+    Annotation,
+    /// Bracketed note of the author
+    AuthorNote,
+    /// Metadata block opening the document
+    FrontMatter,
+}
+
+impl NoteKind {
+    /// Label of the badges of collapsed notes of this kind.
+    pub fn badge(&self) -> &'static str {
+        match self {
+            Self::Footnote => "fn",
+            Self::Annotation => "comment",
+            Self::AuthorNote => "note",
+            Self::FrontMatter => "front matter",
+        }
+    }
+}
+
+/// A note of the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    /// Byte range of the note, with its delimiters
+    pub range: Range<usize>,
+    /// Byte range of the text of the note
+    pub text: Range<usize>,
+    /// Kind of the note
+    pub kind: NoteKind,
+}
+
+/// Notes of `content` of the given `kinds`, in order, outside front matter
+/// and code.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::notes::{notes, NoteKind};
+///
+/// let content = "She ran.^[Too fast?] <!-- check -->\n\n[TK: name]";
+/// let found = notes(content, &[NoteKind::Footnote, NoteKind::AuthorNote]);
+/// let texts: Vec<&str> = found.iter().map(|note| &content[note.text.clone()]).collect();
+/// assert_eq!(texts, ["Too fast?", "name"]);
+/// ```
+pub fn notes(content: &str, kinds: &[NoteKind]) -> Vec<Note> {
+    let mut notes = Vec::new();
+    if kinds.is_empty() {
+        return notes;
+    }
+    let front_matter = FrontMatter::parse(content).map_or(0..0, |front_matter| front_matter.range);
+    if kinds.contains(&NoteKind::FrontMatter) && !front_matter.is_empty() {
+        // Up to the closing fence, the text after it keeping its own line
+        let block = content[..front_matter.end].trim_end_matches(['\n', '\r']);
+        let open = block.find('\n').map_or(block.len(), |i| i + 1);
+        let close = block.rfind('\n').map_or(open, |i| i + 1).max(open);
+        notes.push(Note {
+            range: 0..block.len(),
+            text: trimmed(content, open..close),
+            kind: NoteKind::FrontMatter,
+        });
+    }
+    let mut fence: Option<&str> = None;
+    let mut start = 0;
+    // End of the last note, which may span lines
+    let mut scanned = 0;
+    for line in content.split_inclusive('\n') {
+        let body = line.trim();
+        let line_start = start;
+        start += line.len();
+        if line_start < front_matter.end {
+            continue;
+        } else if let Some(marker) = fence {
+            if body.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        } else if line_start >= scanned && (body.starts_with("```") || body.starts_with("~~~")) {
+            fence = Some(&body[..3]);
+            continue;
+        }
+
+        let mut at = line_start.max(scanned);
+        while at < start {
+            let Some(note) = note_at(content, at, line_start == at) else {
+                at += content[at..].chars().next().map_or(1, char::len_utf8);
+                continue;
+            };
+            at = note.range.end;
+            if kinds.contains(&note.kind) {
+                notes.push(note);
+            }
+        }
+        scanned = at;
+    }
+    notes
+}
+
+/// The note starting at byte `at` of `content`, if there is one there.
+fn note_at(content: &str, at: usize, line_start: bool) -> Option<Note> {
+    let rest = &content[at..];
+    let delimited = |open: &str, close: &str, kind| {
+        let len = rest.strip_prefix(open)?.find(close)?;
+        Some(Note {
+            range: at..at + open.len() + len + close.len(),
+            text: trimmed(content, at + open.len()..at + open.len() + len),
+            kind,
+        })
+    };
+
+    match rest.as_bytes().first()? {
+        b'<' => delimited("<!--", "-->", NoteKind::Annotation).filter(|note| {
+            let marker = &content[note.range.clone()];
+            ParagraphFormat::parse_marker(marker).is_none() && verse::parse_marker(marker).is_none()
+        }),
+        b'{' => delimited("{>>", "<<}", NoteKind::Annotation),
+        b'^' if rest.starts_with("^[") => {
+            let close = closing_bracket(&rest[1..])?;
+            Some(Note {
+                range: at..at + close + 2,
+                text: trimmed(content, at + 2..at + close + 1),
+                kind: NoteKind::Footnote,
+            })
+        }
+        b'[' if line_start && rest.starts_with("[^") => {
+            // A definition, up to the end of its line
+            let label = rest.find("]:")?;
+            let end = rest.find('\n').unwrap_or(rest.len());
+            (label < end).then(|| Note {
+                range: at..at + end,
+                text: trimmed(content, at + label + 2..at + end),
+                kind: NoteKind::Footnote,
+            })
+        }
+        b'[' => {
+            let close = closing_bracket(rest)?;
+            let word = rest[1..].find(':')? + 1;
+            let is_word = word > 1
+                && word < close
+                && rest[1..word].chars().all(char::is_alphabetic)
+                && !rest[close + 1..].starts_with(['(', '[']);
+            is_word.then(|| Note {
+                range: at..at + close + 1,
+                text: trimmed(content, at + word + 1..at + close),
+                kind: NoteKind::AuthorNote,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Byte of the bracket closing the one starting `text`, within its
+/// paragraph.
+fn closing_bracket(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            '\n' if text[i + 1..]
+                .trim_start_matches([' ', '\t'])
+                .starts_with('\n') =>
+            {
+                return None;
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `range` of `content` without its surrounding whitespace.
+fn trimmed(content: &str, range: Range<usize>) -> Range<usize> {
+    let text = &content[range.clone()];
+    let start = range.start + text.len() - text.trim_start().len();
+    start..start + text.trim().len()
+}
+
+/// Notes to collapse among `notes`: those not touching the selected
+/// `chars` of `content`, the caret being in the note being edited.
+pub fn collapsed(content: &str, notes: Vec<Note>, chars: Option<Range<usize>>) -> Vec<Note> {
+    let Some(chars) = chars else {
+        return notes;
+    };
+    let byte = |char_index: usize| {
+        content
+            .char_indices()
+            .nth(char_index)
+            .map_or(content.len(), |(byte, _)| byte)
+    };
+    let (start, end) = (byte(chars.start), byte(chars.end));
+    notes
+        .into_iter()
+        .filter(|note| end < note.range.start || note.range.end < start)
+        .collect()
+}
+
+/// Collapse the text of `notes` in `job`, leaving room before each for its
+/// badge, `badge_width` wide.
+pub fn collapse(job: &mut LayoutJob, notes: &[Note], badge_width: impl Fn(NoteKind) -> f32) {
+    if notes.is_empty() {
+        return;
+    }
+    let mut notes = notes.iter().peekable();
+    for section in std::mem::take(&mut job.sections) {
+        let end = section.byte_range.end;
+        let mut start = section.byte_range.start;
+        while start < end {
+            while notes.next_if(|note| note.range.end <= start).is_some() {}
+            let (until, leading_space, format) = match notes.peek() {
+                Some(note) if note.range.start <= start => {
+                    let format = TextFormat {
+                        font_id: FontId::new(COLLAPSED_SIZE, section.format.font_id.family.clone()),
+                        color: Color32::TRANSPARENT,
+                        ..TextFormat::default()
+                    };
+                    let badge = if note.range.start == start {
+                        badge_width(note.kind)
+                    } else {
+                        0.0
+                    };
+                    (note.range.end.min(end), badge, format)
+                }
+                next => {
+                    let until = next.map_or(end, |note| note.range.start.min(end));
+                    let leading_space = if start == section.byte_range.start {
+                        section.leading_space
+                    } else {
+                        0.0
+                    };
+                    (until, leading_space, section.format.clone())
+                }
+            };
+            job.sections.push(LayoutSection {
+                leading_space,
+                byte_range: start..until,
+                format,
+            });
+            start = until;
+        }
+    }
+}
+
+/// Width of the badges of collapsed notes of `kind`, labelled in
+/// `font_id`.
+pub fn badge_width(ui: &Ui, kind: NoteKind, font_id: &FontId) -> f32 {
+    let label = ui.fonts_mut(|fonts| {
+        fonts.layout_no_wrap(
+            kind.badge().to_string(),
+            font_id.clone(),
+            Color32::PLACEHOLDER,
+        )
+    });
+    label.size().x + 8.0
+}
+
+/// Rectangles of the badges of the collapsed `notes` of `content`, laid out
+/// in `galley` at `galley_pos`, each `badge_width` wide.
+pub fn badges(
+    content: &str,
+    galley: &Galley,
+    galley_pos: Pos2,
+    notes: &[Note],
+    badge_width: impl Fn(NoteKind) -> f32,
+) -> Vec<Rect> {
+    // Characters before the last note
+    let (mut byte, mut chars) = (0, 0);
+    notes
+        .iter()
+        .map(|note| {
+            chars += content[byte..note.range.start].chars().count();
+            byte = note.range.start;
+            let caret = galley
+                .pos_from_cursor(CCursor::new(chars))
+                .translate(galley_pos.to_vec2());
+            Rect::from_min_max(
+                Pos2::new(caret.left() - badge_width(note.kind), caret.top()),
+                caret.left_bottom(),
+            )
+        })
+        .collect()
+}
+
+/// Draw the badges of the collapsed `notes` in their `rects`.
+pub fn paint(
+    painter: &Painter,
+    notes: &[Note],
+    rects: &[Rect],
+    font_id: &FontId,
+    colors: (Color32, Color32),
+) {
+    let (fill, text) = colors;
+    let clip = painter.clip_rect();
+    for (note, rect) in notes.iter().zip(rects) {
+        let rect = rect.shrink2(Vec2::new(1.0, 2.0));
+        if clip.intersects(rect) {
+            painter.rect_filled(rect, 4.0, fill);
+            painter.text(
+                rect.center(),
+                Align2::CENTER_CENTER,
+                note.kind.badge(),
+                font_id.clone(),
+                text,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [NoteKind; 3] = [
+        NoteKind::Footnote,
+        NoteKind::Annotation,
+        NoteKind::AuthorNote,
+    ];
+
+    #[test]
+    fn test_notes() {
+        let content = "---\nnote: <!-- no -->\n---\n<!-- align=center -->\n\
+            She ran.^[Too [very] fast?] {>> tense <<} [TK: inn\nname] [a](b) [x: y](z)\n\
+            <!-- a\nlong one -->\n\n[^1]: The inn.\n```\n<!-- code -->\n```\n[Note:] [left";
+        let found = notes(content, &ALL);
+        let texts: Vec<(&str, NoteKind)> = found
+            .iter()
+            .map(|note| (&content[note.text.clone()], note.kind))
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                ("Too [very] fast?", NoteKind::Footnote),
+                ("tense", NoteKind::Annotation),
+                ("inn\nname", NoteKind::AuthorNote),
+                ("a\nlong one", NoteKind::Annotation),
+                ("The inn.", NoteKind::Footnote),
+                ("", NoteKind::AuthorNote),
+            ]
+        );
+        assert_eq!(&content[found[0].range.clone()], "^[Too [very] fast?]");
+        assert_eq!(&content[found[4].range.clone()], "[^1]: The inn.");
+
+        let annotations = notes(content, &[NoteKind::Annotation]);
+        assert_eq!(annotations.len(), 2);
+        assert!(notes(content, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_front_matter_folds_to_its_closing_fence() {
+        let content = "+++\ntitle = \"Inn\"\n+++\n[TK: name]";
+        let found = notes(content, &[NoteKind::FrontMatter, NoteKind::AuthorNote]);
+        assert_eq!(found.len(), 2);
+        assert_eq!(
+            &content[found[0].range.clone()],
+            "+++\ntitle = \"Inn\"\n+++"
+        );
+        assert_eq!(&content[found[0].text.clone()], "title = \"Inn\"");
+        assert_eq!(found[1].kind, NoteKind::AuthorNote);
+
+        // Unclosed, it is text
+        assert!(notes("---\n[TK: name]", &[NoteKind::FrontMatter]).is_empty());
+    }
+
+    #[test]
+    fn test_notes_under_the_caret_stay_expanded() {
+        let content = "Café <!-- one --> and <!-- two -->";
+        let found = notes(content, &ALL);
+        let caret = content[..content.find("two").unwrap()].chars().count();
+        let folded = collapsed(content, found.clone(), Some(caret..caret));
+        assert_eq!(folded, found[..1]);
+        assert_eq!(collapsed(content, found.clone(), None), found);
+    }
+
+    #[test]
+    fn test_collapse_splits_sections() {
+        let content = "Ab <!-- c --> d";
+        let found = notes(content, &ALL);
+        let mut job = LayoutJob::simple(
+            content.to_string(),
+            FontId::monospace(14.0),
+            Color32::WHITE,
+            f32::INFINITY,
+        );
+        collapse(&mut job, &found, |_| 30.0);
+        let sections: Vec<(&str, f32, f32)> = job
+            .sections
+            .iter()
+            .map(|section| {
+                (
+                    &content[section.byte_range.clone()],
+                    section.leading_space,
+                    section.format.font_id.size,
+                )
+            })
+            .collect();
+        assert_eq!(
+            sections,
+            [
+                ("Ab ", 0.0, 14.0),
+                ("<!-- c -->", 30.0, COLLAPSED_SIZE),
+                (" d", 0.0, 14.0)
+            ]
+        );
+    }
+}
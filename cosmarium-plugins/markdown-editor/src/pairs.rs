@@ -0,0 +1,210 @@
+//! # Brackets and quotes
+//!
+//! Typing a bracket, a quote or an emphasis marker (`*`, `_`, `` ` ``) with
+//! text selected wraps the selection in the pair. Without a selection,
+//! brackets and quotes can come in pairs, with the caret between them: typing
+//! the closing character in front of its pair steps over it, and Backspace
+//! between an empty pair removes both.
+//!
+//! Quotes follow the project's [`QuoteStyle`], so that `"` types
+//! “curly quotes” or « guillemets » as the project's language wants.
+
+use std::ops::Range;
+
+use crate::paste::{opens_quote, QuoteStyle};
+
+/// Change of the text made by typing a character, with byte offsets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    /// Text replaced
+    pub range: Range<usize>,
+    /// Text replacing it
+    pub text: String,
+    /// Selection after the change
+    pub selection: Range<usize>,
+}
+
+/// Opening and closing marks of the pairs `c` belongs to, and whether it is
+/// a quote.
+fn marks(c: char, quotes: QuoteStyle) -> Option<(&'static str, &'static str, bool)> {
+    let [open_double, close_double, open_single, close_single] =
+        quotes.marks().unwrap_or(["\"", "\"", "'", "'"]);
+    match c {
+        '(' | ')' => Some(("(", ")", false)),
+        '[' | ']' => Some(("[", "]", false)),
+        '{' | '}' => Some(("{", "}", false)),
+        '"' => Some((open_double, close_double, true)),
+        '\'' => Some((open_single, close_single, true)),
+        _ => None,
+    }
+}
+
+/// Change made by typing `c` over the `selection` of `content`, if typing
+/// it does more than inserting it.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::pairs::type_char;
+/// use cosmarium_markdown_editor::paste::QuoteStyle;
+///
+/// // Wrapping the selection, which stays selected
+/// let edit = type_char("a word", 2..6, '*', QuoteStyle::Keep, true).unwrap();
+/// assert_eq!((edit.range, edit.text.as_str(), edit.selection), (2..6, "*word*", 3..7));
+///
+/// // Pairing, with the caret in between
+/// let edit = type_char("say ", 4..4, '"', QuoteStyle::Curly, true).unwrap();
+/// assert_eq!((edit.text.as_str(), edit.selection), ("“”", 7..7));
+/// ```
+pub fn type_char(
+    content: &str,
+    selection: Range<usize>,
+    c: char,
+    quotes: QuoteStyle,
+    auto_pair: bool,
+) -> Option<Edit> {
+    if !selection.is_empty() {
+        let (open, close) = match c {
+            '*' | '_' | '`' => (c.to_string(), c.to_string()),
+            '(' | '[' | '{' | '"' | '\'' => {
+                let (open, close, _) = marks(c, quotes)?;
+                (open.to_string(), close.to_string())
+            }
+            _ => return None,
+        };
+        let selected = &content[selection.clone()];
+        let start = selection.start + open.len();
+        return Some(Edit {
+            range: selection.clone(),
+            text: format!("{}{}{}", open, selected, close),
+            selection: start..start + selected.len(),
+        });
+    }
+    if !auto_pair {
+        return None;
+    }
+
+    let cursor = selection.start;
+    let (open, close, quote) = marks(c, quotes)?;
+    let after = &content[cursor..];
+    let before = content[..cursor].chars().next_back();
+
+    // Stepping over the closing character of a pair
+    let closing = if quote {
+        !opens_quote(before)
+    } else {
+        matches!(c, ')' | ']' | '}')
+    };
+    if closing && after.starts_with(close) {
+        let end = cursor + close.len();
+        return Some(Edit {
+            range: cursor..cursor,
+            text: String::new(),
+            selection: end..end,
+        });
+    }
+    if closing {
+        // A closing quote or an apostrophe, in the project's style
+        return (quote && close != c.to_string()).then(|| insert(cursor, close));
+    }
+
+    // Pairs are only opened in front of a space or a closing punctuation mark
+    let next = after.chars().next();
+    let opens_pair = next.is_none_or(|next| {
+        next.is_whitespace() || matches!(next, ')' | ']' | '}' | '.' | ',' | ';' | ':' | '!' | '?')
+    });
+    if !opens_pair {
+        return (open != c.to_string()).then(|| insert(cursor, open));
+    }
+    let caret = cursor + open.len();
+    Some(Edit {
+        range: cursor..cursor,
+        text: format!("{}{}", open, close),
+        selection: caret..caret,
+    })
+}
+
+/// Change inserting `text` at `cursor`, leaving the caret after it.
+fn insert(cursor: usize, text: &str) -> Edit {
+    let end = cursor + text.len();
+    Edit {
+        range: cursor..cursor,
+        text: text.to_string(),
+        selection: end..end,
+    }
+}
+
+/// Change made by Backspace at `cursor` between the two halves of an empty
+/// pair, removing both.
+pub fn delete_pair(content: &str, cursor: usize, quotes: QuoteStyle) -> Option<Edit> {
+    let (before, after) = content.split_at(cursor);
+    ['(', '[', '{', '"', '\'']
+        .into_iter()
+        .filter_map(|c| marks(c, quotes))
+        .find(|(open, close, _)| before.ends_with(open) && after.starts_with(close))
+        .map(|(open, close, _)| {
+            let start = cursor - open.len();
+            Edit {
+                range: start..cursor + close.len(),
+                text: String::new(),
+                selection: start..start,
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(content: &str, edit: &Edit) -> String {
+        let mut content = content.to_string();
+        content.replace_range(edit.range.clone(), &edit.text);
+        content
+    }
+
+    #[test]
+    fn test_wrap_selection() {
+        let edit = type_char("He left.", 3..7, '(', QuoteStyle::Keep, false).unwrap();
+        assert_eq!(apply("He left.", &edit), "He (left).");
+        assert_eq!(edit.selection, 4..8);
+
+        let edit = type_char("oui", 0..3, '"', QuoteStyle::Guillemets, false).unwrap();
+        assert_eq!(apply("oui", &edit), "«\u{a0}oui\u{a0}»");
+        assert!(type_char("oui", 0..3, 'x', QuoteStyle::Keep, true).is_none());
+    }
+
+    #[test]
+    fn test_auto_pair_and_skip_over() {
+        let edit = type_char("(", 1..1, '[', QuoteStyle::Keep, true).unwrap();
+        assert_eq!(
+            (apply("(", &edit), edit.selection),
+            ("([]".to_string(), 2..2)
+        );
+        // Not in front of a word, nor when turned off
+        assert!(type_char("word", 0..0, '(', QuoteStyle::Keep, true).is_none());
+        assert!(type_char("", 0..0, '(', QuoteStyle::Keep, false).is_none());
+        assert!(type_char("", 0..0, '*', QuoteStyle::Keep, true).is_none());
+
+        // The closing character steps over its pair
+        let edit = type_char("(a)", 2..2, ')', QuoteStyle::Keep, true).unwrap();
+        assert_eq!((edit.text.as_str(), edit.selection), ("", 3..3));
+        let text = "“yes”";
+        let cursor = "“yes".len();
+        let edit = type_char(text, cursor..cursor, '"', QuoteStyle::Curly, true).unwrap();
+        assert_eq!(edit.selection, text.len()..text.len());
+
+        // Apostrophes take the style of the project
+        let edit = type_char("it", 2..2, '\'', QuoteStyle::Curly, true).unwrap();
+        assert_eq!(edit.text, "’");
+        assert!(type_char("it", 2..2, '\'', QuoteStyle::Keep, true).is_none());
+    }
+
+    #[test]
+    fn test_delete_pair() {
+        let text = "say «\u{a0}\u{a0}»";
+        let cursor = "say «\u{a0}".len();
+        let edit = delete_pair(text, cursor, QuoteStyle::Guillemets).unwrap();
+        assert_eq!(apply(text, &edit), "say ");
+        assert!(delete_pair("(a)", 2, QuoteStyle::Keep).is_none());
+    }
+}
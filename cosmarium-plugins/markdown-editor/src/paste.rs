@@ -0,0 +1,767 @@
+//! # Paste cleanup
+//!
+//! Text copied from Word or Google Docs comes with artifacts: zero-width
+//! characters, no-break spaces in place of spaces, bullet characters, tab
+//! indents and runs of empty paragraphs. Pasting cleans them up, converts the
+//! quotes to those of the project, and when the application hands over the
+//! HTML flavour of the clipboard, turns its headings, emphasis, lists and
+//! links into Markdown.
+//!
+//! Pasting with Shift held, or with "Paste as Plain Text", inserts the text
+//! as it is.
+
+use serde::{Deserialize, Serialize};
+
+/// Shared state key (`bool`) of whether pasted text is cleaned up, set by the
+/// application.
+pub const PASTE_CLEANUP_KEY: &str = "markdown_editor_paste_cleanup";
+
+/// Shared state key (`Option<String>`) of the HTML flavour of the clipboard,
+/// set by the application when text is pasted.
+pub const CLIPBOARD_HTML_KEY: &str = "markdown_editor_clipboard_html";
+
+/// Shared state key (`Option<PasteRequest>`) of a paste asked for from the
+/// menus, served by the active tab.
+pub const PASTE_REQUEST: &str = "markdown_editor_paste_request";
+
+/// Configuration key of the project's quote style.
+pub const QUOTE_STYLE_KEY: &str = "quote_style";
+
+/// Clipboard contents to paste in the active tab.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PasteRequest {
+    pub text: String,
+    pub html: Option<String>,
+    /// Whether to insert the text as it is
+    pub plain: bool,
+}
+
+/// Quotes used in the text of a project.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum QuoteStyle {
+    /// Quotes are left as they are pasted
+    #[default]
+    Keep,
+    /// `"` and `'`
+    Straight,
+    /// “English” and ‘single’
+    Curly,
+    /// « French », with no-break spaces
+    Guillemets,
+    /// „German“ and ‚single‘
+    Low,
+}
+
+impl QuoteStyle {
+    /// All styles, in menu order.
+    pub const ALL: [QuoteStyle; 5] = [
+        Self::Keep,
+        Self::Straight,
+        Self::Curly,
+        Self::Guillemets,
+        Self::Low,
+    ];
+
+    /// Human-readable name for menus.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Keep => "As pasted",
+            Self::Straight => "Straight \"quotes\"",
+            Self::Curly => "Curly “quotes”",
+            Self::Guillemets => "Guillemets « quotes »",
+            Self::Low => "Low „quotes“",
+        }
+    }
+
+    /// Opening and closing double quotes, and opening and closing single
+    /// quotes (the closing one is also the apostrophe).
+    pub(crate) fn marks(&self) -> Option<[&'static str; 4]> {
+        match self {
+            Self::Keep => None,
+            Self::Straight => Some(["\"", "\"", "'", "'"]),
+            Self::Curly => Some(["“", "”", "‘", "’"]),
+            Self::Guillemets => Some(["«\u{a0}", "\u{a0}»", "‘", "’"]),
+            Self::Low => Some(["„", "“", "‚", "’"]),
+        }
+    }
+}
+
+/// Text to insert for a paste of `text`, whose clipboard also had `html`.
+///
+/// A `plain` paste only gets `\n` line endings; otherwise the text is
+/// cleaned up with [`clean`] when `cleanup` is on, after the HTML, if any,
+/// is converted to Markdown.
+pub fn prepare(
+    text: &str,
+    html: Option<&str>,
+    cleanup: bool,
+    quotes: QuoteStyle,
+    plain: bool,
+) -> String {
+    if plain || !cleanup {
+        return text.replace("\r\n", "\n").replace('\r', "\n");
+    }
+    let markdown = html
+        .map(html_to_markdown)
+        .filter(|markdown| !markdown.trim().is_empty());
+    clean(markdown.as_deref().unwrap_or(text), quotes)
+}
+
+/// Clean up text copied from a word processor.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::paste::{clean, QuoteStyle};
+///
+/// let pasted = "\t\"Run,\" she said.\u{200b}\u{a0}\r\n\r\n\r\n• Bread\r\n• Milk";
+/// assert_eq!(
+///     clean(pasted, QuoteStyle::Curly),
+///     "“Run,” she said.\n\n- Bread\n- Milk"
+/// );
+/// ```
+pub fn clean(text: &str, quotes: QuoteStyle) -> String {
+    let text = text
+        .replace("\r\n", "\n")
+        .replace(['\r', '\u{b}', '\u{2028}'], "\n")
+        .replace('\u{2029}', "\n\n");
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            lines.push(line.trim_end().to_string());
+            continue;
+        }
+        if in_fence {
+            lines.push(line.to_string());
+            continue;
+        }
+        let line = strip_artifacts(line);
+        let line = line.trim_matches([' ', '\t']);
+        let line = match split_bullet(line) {
+            Some(rest) => format!("- {}", rest),
+            None => line.to_string(),
+        };
+        lines.push(convert_quotes(&line, quotes));
+    }
+
+    // One blank line at most between paragraphs
+    let mut cleaned = String::new();
+    let mut blank = false;
+    for line in lines {
+        if line.is_empty() {
+            blank = !cleaned.is_empty();
+            continue;
+        }
+        if !cleaned.is_empty() {
+            cleaned.push_str(if blank { "\n\n" } else { "\n" });
+        }
+        blank = false;
+        cleaned.push_str(&line);
+    }
+    if text.ends_with('\n') && !cleaned.is_empty() {
+        cleaned.push('\n');
+    }
+    cleaned
+}
+
+/// Remove zero-width characters and soft hyphens, and turn no-break spaces
+/// into spaces except around French punctuation.
+fn strip_artifacts(line: &str) -> String {
+    let chars: Vec<char> = line
+        .chars()
+        .filter(|c| !matches!(c, '\u{200b}' | '\u{2060}' | '\u{feff}' | '\u{ad}'))
+        .collect();
+    let mut stripped = String::with_capacity(line.len());
+    for (i, &c) in chars.iter().enumerate() {
+        if matches!(c, '\u{a0}' | '\u{202f}') {
+            let before = i.checked_sub(1).map(|i| chars[i]);
+            let after = chars.get(i + 1).copied();
+            let french = before == Some('«') || matches!(after, Some(';' | ':' | '!' | '?' | '»'));
+            stripped.push(if french { c } else { ' ' });
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// The text of a line starting with a bullet character, such as Word's `•`.
+fn split_bullet(line: &str) -> Option<&str> {
+    let mut chars = line.chars();
+    let bullet = chars.next()?;
+    if !matches!(
+        bullet,
+        '•' | '◦' | '▪' | '▫' | '‣' | '●' | '○' | '■' | '□' | '·' | '\u{f0b7}' | '\u{f0a7}'
+    ) {
+        return None;
+    }
+    let rest = chars.as_str();
+    let text = rest.trim_start_matches([' ', '\t', '\u{a0}']);
+    (text.len() < rest.len() && !text.is_empty()).then_some(text)
+}
+
+/// Replace the quotes of `line` with those of `style`, out of code spans.
+fn convert_quotes(line: &str, style: QuoteStyle) -> String {
+    let Some([open_double, close_double, open_single, close_single]) = style.marks() else {
+        return line.to_string();
+    };
+    let chars: Vec<char> = line.chars().collect();
+    let mut converted = String::with_capacity(line.len());
+    let mut in_code = false;
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '`' {
+            in_code = !in_code;
+        }
+        let double = matches!(c, '"' | '“' | '”' | '„');
+        let single = matches!(c, '\'' | '‘' | '’' | '‚');
+        if in_code || !(double || single) {
+            converted.push(c);
+            continue;
+        }
+        let before = i.checked_sub(1).map(|i| chars[i]);
+        let opening =
+            opens_quote(before) && chars.get(i + 1).is_some_and(|next| !next.is_whitespace());
+        let mark = match (double, opening) {
+            (true, true) => open_double,
+            (true, false) => close_double,
+            (false, true) => open_single,
+            (false, false) => close_single,
+        };
+        // Guillemets bring their own spaces
+        if mark.ends_with('»') && converted.ends_with(' ') {
+            converted.pop();
+        }
+        converted.push_str(mark);
+    }
+    if style == QuoteStyle::Guillemets {
+        converted = converted.replace("\u{a0} ", "\u{a0}");
+    }
+    converted
+}
+
+/// Whether a quote after `before` is an opening one: at the start of the
+/// text, after a space or after an opening punctuation mark.
+pub(crate) fn opens_quote(before: Option<char>) -> bool {
+    match before {
+        None => true,
+        Some(b) => b.is_whitespace() || matches!(b, '(' | '[' | '{' | '—' | '–' | '-' | '/'),
+    }
+}
+
+/// Emphasis of a piece of text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Emphasis {
+    bold: bool,
+    italic: bool,
+}
+
+/// Kind of the block being converted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BlockKind {
+    Paragraph,
+    Heading(usize),
+    /// A list item, with its marker and nesting depth
+    Item(String, usize),
+    Code,
+}
+
+/// Markdown written from HTML, block by block.
+#[derive(Debug, Default)]
+struct Writer {
+    markdown: String,
+    /// Text of the current block, with its emphasis
+    spans: Vec<(String, Option<Emphasis>)>,
+    kind: Option<BlockKind>,
+    quote_depth: usize,
+    /// Whether the last block written is a list item
+    last_was_item: bool,
+}
+
+impl Writer {
+    fn text(&mut self, text: &str, emphasis: Emphasis) {
+        if let Some((last, Some(last_emphasis))) = self.spans.last_mut() {
+            if *last_emphasis == emphasis {
+                last.push_str(text);
+                return;
+            }
+        }
+        self.spans.push((text.to_string(), Some(emphasis)));
+    }
+
+    /// Append Markdown written as is.
+    fn raw(&mut self, markdown: &str) {
+        self.spans.push((markdown.to_string(), None));
+    }
+
+    /// End the current block, and start a block of `kind`.
+    fn block(&mut self, kind: BlockKind) {
+        self.flush();
+        self.kind = Some(kind);
+    }
+
+    fn flush(&mut self) {
+        let kind = self.kind.take().unwrap_or(BlockKind::Paragraph);
+        let spans = std::mem::take(&mut self.spans);
+        let text = if kind == BlockKind::Code {
+            let code: String = spans.into_iter().map(|(text, _)| text).collect();
+            format!("```\n{}\n```", code.trim_matches('\n'))
+        } else {
+            let mut text = String::new();
+            for (span, emphasis) in spans {
+                match emphasis {
+                    None => text.push_str(&span),
+                    Some(emphasis) => text.push_str(&emphasize(&span, emphasis)),
+                }
+            }
+            text.trim().to_string()
+        };
+        if text.is_empty() || text == "\\" {
+            return;
+        }
+        let (prefix, indent) = match &kind {
+            BlockKind::Heading(level) => (format!("{} ", "#".repeat(*level)), 0),
+            BlockKind::Item(marker, depth) => (
+                format!("{}{} ", "   ".repeat(*depth), marker),
+                depth * 3 + marker.len() + 1,
+            ),
+            _ => (String::new(), 0),
+        };
+        let quote = "> ".repeat(self.quote_depth);
+        let continuation = format!("\n{}{}", quote, " ".repeat(indent));
+        let block = format!("{}{}{}", quote, prefix, text.replace('\n', &continuation));
+
+        // Items of a list follow each other on consecutive lines
+        let item = matches!(kind, BlockKind::Item(..));
+        if !self.markdown.is_empty() {
+            let tight = item && self.last_was_item;
+            self.markdown.push_str(if tight { "\n" } else { "\n\n" });
+        }
+        self.markdown.push_str(&block);
+        self.last_was_item = item;
+    }
+
+    fn finish(mut self) -> String {
+        self.flush();
+        self.markdown
+    }
+}
+
+/// `text` with the Markdown markers of `emphasis` around its words.
+fn emphasize(text: &str, emphasis: Emphasis) -> String {
+    let escaped: String = text
+        .chars()
+        .flat_map(|c| match c {
+            '*' | '_' | '`' | '\\' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect();
+    let marker = match (emphasis.bold, emphasis.italic) {
+        (true, true) => "***",
+        (true, false) => "**",
+        (false, true) => "*",
+        (false, false) => return escaped,
+    };
+    let body = escaped.trim();
+    if body.is_empty() {
+        return escaped;
+    }
+    let start = escaped.len() - escaped.trim_start().len();
+    format!(
+        "{}{}{}{}{}",
+        &escaped[..start],
+        marker,
+        body,
+        marker,
+        &escaped[start + body.len()..]
+    )
+}
+
+/// An element open while converting HTML.
+#[derive(Debug)]
+struct Open {
+    name: String,
+    emphasis: Emphasis,
+    /// Link target, for `a` elements
+    href: Option<String>,
+    /// Whether the element's text is left out
+    hidden: bool,
+}
+
+/// Convert HTML copied from a word processor or a web page to Markdown.
+///
+/// Headings, paragraphs, bold and italic text (as tags or as inline
+/// styles), lists, links, quotes and preformatted text are kept; the rest
+/// of the markup is dropped.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::paste::html_to_markdown;
+///
+/// let html = "<h2>Day one</h2><p>It was <b>very</b> <i>late</i>.</p>\
+///             <ul><li>Bread</li><li>Milk</li></ul>";
+/// assert_eq!(
+///     html_to_markdown(html),
+///     "## Day one\n\nIt was **very** *late*.\n\n- Bread\n- Milk"
+/// );
+/// ```
+pub fn html_to_markdown(html: &str) -> String {
+    // The Windows clipboard wraps the copied fragment in a full document
+    let html = match (
+        html.find("<!--StartFragment-->"),
+        html.find("<!--EndFragment-->"),
+    ) {
+        (Some(start), Some(end)) if start < end => &html[start + 20..end],
+        _ => html.find('<').map_or(html, |start| &html[start..]),
+    };
+
+    let mut writer = Writer::default();
+    let mut open: Vec<Open> = Vec::new();
+    // Kind and number of the last item of the open lists
+    let mut lists: Vec<(bool, usize)> = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            push_text(&mut writer, &open, rest);
+            break;
+        };
+        push_text(&mut writer, &open, &rest[..start]);
+        rest = &rest[start..];
+
+        // Comments, declarations and processing instructions
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with(['!', '?']) {
+            continue;
+        }
+
+        let closing = tag.starts_with('/');
+        let tag = tag.trim_start_matches('/').trim_end_matches('/');
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let name = tag[..name_end].to_ascii_lowercase();
+        let attributes = &tag[name_end..];
+
+        if closing {
+            close(&mut writer, &mut open, &mut lists, &name);
+        } else {
+            start_element(&mut writer, &mut open, &mut lists, &name, attributes);
+        }
+    }
+    writer.finish()
+}
+
+/// Whether elements named `name` have no content.
+fn is_void(name: &str) -> bool {
+    matches!(
+        name,
+        "br" | "hr" | "img" | "meta" | "link" | "input" | "col" | "wbr" | "area" | "base"
+    )
+}
+
+fn start_element(
+    writer: &mut Writer,
+    open: &mut Vec<Open>,
+    lists: &mut Vec<(bool, usize)>,
+    name: &str,
+    attributes: &str,
+) {
+    let outer = open.last();
+    let mut emphasis = outer.map(|o| o.emphasis).unwrap_or_default();
+    let mut hidden = outer.is_some_and(|o| o.hidden)
+        || matches!(name, "head" | "style" | "script" | "title" | "xml");
+    let style = attribute(attributes, "style")
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+        .replace(' ', "");
+    // Word's list bullets, repeated in the text
+    hidden |= style.contains("mso-list:ignore");
+    match name {
+        "b" | "strong" => emphasis.bold = true,
+        "i" | "em" | "cite" => emphasis.italic = true,
+        _ => {}
+    }
+    // Google Docs wraps everything in <b style="font-weight:normal">
+    if let Some(weight) = css_value(&style, "font-weight") {
+        emphasis.bold =
+            weight == "bold" || weight == "bolder" || weight.parse::<u32>().is_ok_and(|w| w >= 600);
+    }
+    if let Some(font_style) = css_value(&style, "font-style") {
+        emphasis.italic = font_style == "italic" || font_style == "oblique";
+    }
+
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            writer.block(BlockKind::Heading(usize::from(name.as_bytes()[1] - b'0')));
+            // Headings are bold by nature
+            emphasis.bold = false;
+        }
+        "p" | "div" | "tr" | "dt" | "dd" => {
+            let class = attribute(attributes, "class").unwrap_or_default();
+            if matches!(writer.kind, Some(BlockKind::Item(..))) && writer.spans.is_empty() {
+                // Google Docs wraps the text of list items in paragraphs
+            } else if class.starts_with("MsoListParagraph") {
+                writer.block(BlockKind::Item("-".to_string(), 0));
+            } else {
+                writer.block(BlockKind::Paragraph);
+            }
+        }
+        "ul" | "ol" => {
+            writer.flush();
+            lists.push((name == "ol", 0));
+        }
+        "li" => {
+            let depth = lists.len().saturating_sub(1);
+            let marker = match lists.last_mut() {
+                Some((true, number)) => {
+                    *number += 1;
+                    format!("{}.", number)
+                }
+                _ => "-".to_string(),
+            };
+            writer.block(BlockKind::Item(marker, depth));
+        }
+        "blockquote" => {
+            writer.flush();
+            writer.quote_depth += 1;
+        }
+        "pre" => writer.block(BlockKind::Code),
+        "br" if !hidden => writer.raw("\\\n"),
+        "hr" => {
+            writer.flush();
+            writer.raw("---");
+            writer.flush();
+        }
+        "code" if writer.kind != Some(BlockKind::Code) => writer.raw("`"),
+        "a" => {
+            let href = attribute(attributes, "href")
+                .filter(|href| !href.is_empty() && !href.starts_with(['#', 'j']));
+            if href.is_some() {
+                writer.raw("[");
+            }
+            open.push(Open {
+                name: name.to_string(),
+                emphasis,
+                href,
+                hidden,
+            });
+            return;
+        }
+        _ => {}
+    }
+    if !is_void(name) {
+        open.push(Open {
+            name: name.to_string(),
+            emphasis,
+            href: None,
+            hidden,
+        });
+    }
+}
+
+fn close(writer: &mut Writer, open: &mut Vec<Open>, lists: &mut Vec<(bool, usize)>, name: &str) {
+    // Close the elements left open inside it, as browsers do
+    let Some(position) = open.iter().rposition(|o| o.name == name) else {
+        return;
+    };
+    for element in open.drain(position..).rev() {
+        match element.name.as_str() {
+            "a" => {
+                if let Some(href) = element.href {
+                    writer.raw(&format!("]({})", href));
+                }
+            }
+            "code" if writer.kind != Some(BlockKind::Code) => writer.raw("`"),
+            "ul" | "ol" => {
+                writer.flush();
+                lists.pop();
+            }
+            "blockquote" => {
+                writer.flush();
+                writer.quote_depth = writer.quote_depth.saturating_sub(1);
+            }
+            "p" | "div" | "li" | "pre" | "tr" | "dt" | "dd" | "h1" | "h2" | "h3" | "h4" | "h5"
+            | "h6" => writer.flush(),
+            _ => {}
+        }
+    }
+}
+
+/// Add the text between two tags to the current block.
+fn push_text(writer: &mut Writer, open: &[Open], text: &str) {
+    let outer = open.last();
+    if outer.is_some_and(|o| o.hidden) || text.is_empty() {
+        return;
+    }
+    let text = decode_entities(text);
+    if writer.kind == Some(BlockKind::Code) {
+        writer.raw(&text);
+        return;
+    }
+    // HTML collapses whitespace
+    let mut collapsed = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            if !collapsed.ends_with(' ') {
+                collapsed.push(' ');
+            }
+        } else {
+            collapsed.push(c);
+        }
+    }
+    if collapsed == " " && writer.spans.is_empty() {
+        return;
+    }
+    writer.text(&collapsed, outer.map(|o| o.emphasis).unwrap_or_default());
+}
+
+/// Value of the attribute `name` in the attributes of a tag.
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let lower = attributes.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(found) = lower[from..].find(name) {
+        let start = from + found;
+        from = start + name.len();
+        let preceded = start == 0 || lower.as_bytes()[start - 1].is_ascii_whitespace();
+        let rest = lower[from..].trim_start();
+        if !preceded || !rest.starts_with('=') {
+            continue;
+        }
+        let value_start = attributes.len() - rest.len() + 1;
+        let value = attributes[value_start..].trim_start();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+            _ => value.split(char::is_whitespace).next().unwrap_or_default(),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+/// Value of the property `name` in a CSS declaration list without spaces.
+fn css_value<'a>(style: &'a str, name: &str) -> Option<&'a str> {
+    style.split(';').find_map(|declaration| {
+        let (property, value) = declaration.split_once(':')?;
+        (property == name).then_some(value.trim_end_matches("!important"))
+    })
+}
+
+/// Replace the character references of `text`.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';').filter(|end| *end <= 10) else {
+            decoded.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "laquo" => Some('«'),
+            "raquo" => Some('»'),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|n| n.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_pasted_text() {
+        let pasted = "\u{feff}Chapter\u{ad}One\r\n\r\n\r\n\r\n\tShe said: 'it's \"late\"'.  \r\n";
+        assert_eq!(
+            clean(pasted, QuoteStyle::Curly),
+            "ChapterOne\n\nShe said: ‘it’s “late”’.\n"
+        );
+        assert_eq!(
+            clean("Il dit : \"non\" !", QuoteStyle::Guillemets),
+            "Il dit : «\u{a0}non\u{a0}» !"
+        );
+        // French spacing is kept, code is left alone
+        assert_eq!(
+            clean("Quoi\u{a0}? `\"x\"`", QuoteStyle::Low),
+            "Quoi\u{a0}? `\"x\"`"
+        );
+        assert_eq!(clean("“Yes”", QuoteStyle::Keep), "“Yes”");
+        assert_eq!(clean("“Yes”", QuoteStyle::Straight), "\"Yes\"");
+    }
+
+    #[test]
+    fn test_google_docs_and_word_html() {
+        let docs = r#"<meta charset="utf-8"><b style="font-weight:normal;" id="docs-internal-guid-1"><p dir="ltr"><span style="font-weight:700;">Bold</span><span style="font-weight:400;"> and </span><span style="font-style:italic;">italic</span></p><br><ol><li><p>One</p></li><li><p>Two &amp; <a href="https://example.com">three</a></p></li></ol></b>"#;
+        assert_eq!(
+            html_to_markdown(docs),
+            "**Bold** and *italic*\n\n1. One\n2. Two & [three](https://example.com)"
+        );
+
+        let word = "Version:0.9\r\nStartHTML:0000000105\r\n<html><head><style>p {margin:0}</style></head><body>\
+            <!--StartFragment--><p class=MsoNormal>Some <b>text</b><o:p></o:p></p>\
+            <p class=MsoListParagraphCxSpFirst><span style='mso-list:Ignore'>·<span>&nbsp;&nbsp;</span></span>Bread</p>\
+            <blockquote><p>Quoted</p></blockquote><!--EndFragment--></body></html>";
+        assert_eq!(
+            html_to_markdown(word),
+            "Some **text**\n\n- Bread\n\n> Quoted"
+        );
+    }
+
+    #[test]
+    fn test_prepare() {
+        let html = Some("<p><i>Hi</i></p>");
+        assert_eq!(
+            prepare("Hi\r\n", html, true, QuoteStyle::Keep, false),
+            "*Hi*"
+        );
+        assert_eq!(
+            prepare("\tHi\r\n", html, true, QuoteStyle::Keep, true),
+            "\tHi\n"
+        );
+        assert_eq!(
+            prepare("\tHi\u{200b}", None, false, QuoteStyle::Curly, false),
+            "\tHi\u{200b}"
+        );
+    }
+}
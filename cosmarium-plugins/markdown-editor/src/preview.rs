@@ -3,13 +3,27 @@
 //! This module provides live preview functionality for markdown content,
 //! allowing writers to see how their markdown will be rendered while they write.
 //! It supports HTML rendering, custom CSS styling, and synchronized scrolling.
+//! With the `live-preview` feature, [`LivePreview`] lays the document out with
+//! egui, beside the editor, and [`ScrollSync`] keeps the two in step.
 
 use cosmarium_plugin_api::Result;
 #[cfg(feature = "live-preview")]
+use cosmarium_plugin_api::{direction, verse};
+#[cfg(feature = "live-preview")]
 use pulldown_cmark::{html, Options, Parser};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+#[cfg(feature = "live-preview")]
+mod live;
+#[cfg(feature = "live-preview")]
+mod sync;
+
+#[cfg(feature = "live-preview")]
+pub use live::{parse_blocks, BlockKind, LivePreview, PreviewBlock, Span, SpanStyle};
+#[cfg(feature = "live-preview")]
+pub use sync::{interpolate, EditorView, ScrollSide, ScrollSync};
+
 /// Markdown preview renderer.
 ///
 /// The [`PreviewRenderer`] converts markdown content to HTML and provides
@@ -107,8 +121,10 @@ impl PreviewRenderer {
     pub fn render(&self, markdown: &str) -> Result<String> {
         #[cfg(feature = "live-preview")]
         {
-            // Apply custom replacements
-            let processed_markdown = self.apply_replacements(markdown);
+            // Apply custom replacements, and lay out verse, right-to-left
+            // and aligned paragraphs
+            let processed_markdown =
+                direction::wrap_paragraphs(&verse::wrap_verse(&self.apply_replacements(markdown)));
 
             // Parse markdown
             let parser = Parser::new_ext(&processed_markdown, self.options);
@@ -140,6 +156,12 @@ impl PreviewRenderer {
         }
     }
 
+    /// Markdown parsing options.
+    #[cfg(feature = "live-preview")]
+    pub fn options(&self) -> Options {
+        self.options
+    }
+
     /// Render markdown content to plain HTML without template wrapping.
     ///
     /// # Arguments
@@ -165,7 +187,8 @@ impl PreviewRenderer {
     pub fn render_fragment(&self, markdown: &str) -> Result<String> {
         #[cfg(feature = "live-preview")]
         {
-            let processed_markdown = self.apply_replacements(markdown);
+            let processed_markdown =
+                direction::wrap_paragraphs(&verse::wrap_verse(&self.apply_replacements(markdown)));
             let parser = Parser::new_ext(&processed_markdown, self.options);
 
             let mut html_output = String::new();
@@ -0,0 +1,624 @@
+//! Markdown laid out with egui, for the preview beside the editor.
+//!
+//! The document is parsed into [`PreviewBlock`]s once per change of its
+//! content, and the blocks are laid out with the style of the UI on each
+//! frame. Each stanza of verse is a paragraph keeping its lines and their
+//! indentation.
+
+use super::sync::interpolate;
+use cosmarium_plugin_api::verse::{self, Verse};
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId, Response, Sense, Stroke, TextStyle, Ui};
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Indentation of a level of list or block quote, in points.
+const INDENT: f32 = 18.0;
+
+/// Size of the headings relative to body text, from `#` to `######`.
+const HEADING_SCALE: [f32; 6] = [1.9, 1.55, 1.3, 1.15, 1.05, 1.0];
+
+/// Inline style of a run of text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpanStyle {
+    pub strong: bool,
+    pub emphasis: bool,
+    pub strikethrough: bool,
+    pub code: bool,
+    /// Target of the link the text is part of
+    pub link: Option<String>,
+}
+
+/// A run of text in one style.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+/// What a block of the preview is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockKind {
+    /// Heading of level 1 to 6
+    Heading(u8),
+    Paragraph,
+    /// Fenced or indented code, shown verbatim
+    Code,
+    /// Thematic break
+    Rule,
+    /// Rows of cells, the header first
+    Table(Vec<Vec<Vec<Span>>>),
+}
+
+/// A block of the preview: a heading, a paragraph or list item, a code
+/// block, a rule or a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreviewBlock {
+    pub kind: BlockKind,
+    pub spans: Vec<Span>,
+    /// Bullet, number or checkbox of a list item
+    pub marker: Option<String>,
+    /// Depth of the lists the block is in
+    pub depth: usize,
+    /// Depth of the block quotes the block is in
+    pub quote: usize,
+    /// Byte offset of the block in the Markdown
+    pub offset: usize,
+}
+
+impl PreviewBlock {
+    /// Text of the block, without styles.
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+}
+
+/// Builds the blocks from the events of the parser.
+#[derive(Default)]
+struct BlockBuilder {
+    blocks: Vec<PreviewBlock>,
+    /// Block being filled
+    current: Option<PreviewBlock>,
+    style: SpanStyle,
+    /// Next number of each open list, `None` for bullets
+    lists: Vec<Option<u64>>,
+    quote: usize,
+    /// Marker of the list item whose first block is not started yet
+    marker: Option<String>,
+    table: Option<TableBuilder>,
+}
+
+/// Table being read.
+struct TableBuilder {
+    rows: Vec<Vec<Vec<Span>>>,
+    /// Cells of the current row
+    row: Vec<Vec<Span>>,
+    offset: usize,
+}
+
+impl BlockBuilder {
+    fn start(&mut self, kind: BlockKind, offset: usize) {
+        self.finish();
+        self.current = Some(PreviewBlock {
+            kind,
+            spans: Vec::new(),
+            marker: self.marker.take(),
+            depth: self.lists.len(),
+            quote: self.quote,
+            offset,
+        });
+    }
+
+    fn finish(&mut self) {
+        if let Some(block) = self.current.take() {
+            self.blocks.push(block);
+        }
+    }
+
+    fn push_text(&mut self, text: &str, style: SpanStyle, offset: usize) {
+        if let Some(table) = &mut self.table {
+            if let Some(cell) = table.row.last_mut() {
+                push_span(cell, text, style);
+            }
+            return;
+        }
+        // Text of a tight list item comes without a paragraph
+        if self.current.is_none() {
+            self.start(BlockKind::Paragraph, offset);
+        }
+        if let Some(block) = &mut self.current {
+            push_span(&mut block.spans, text, style);
+        }
+    }
+
+    /// Add the blocks of `markdown`, found at byte `base` of the document.
+    fn parse(&mut self, markdown: &str, base: usize, options: Options) {
+        for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+            self.event(event, base + range.start);
+        }
+        self.finish();
+    }
+
+    /// Add a paragraph for each stanza of `verse`, its lines broken and
+    /// indented with no-break spaces.
+    fn verse(&mut self, verse: &Verse, line_start: &dyn Fn(usize) -> usize, options: Options) {
+        for stanza in &verse.stanzas {
+            let Some(first) = stanza.first() else {
+                continue;
+            };
+            self.start(BlockKind::Paragraph, line_start(first.line));
+            for (i, line) in stanza.iter().enumerate() {
+                if i > 0 {
+                    self.push_text("\n", SpanStyle::default(), 0);
+                }
+                if line.indent > 0 {
+                    self.push_text(&"\u{a0}".repeat(line.indent), SpanStyle::default(), 0);
+                }
+                let text = verse::escape_line(&line.text);
+                let spans = parse_blocks(&text, options)
+                    .into_iter()
+                    .next()
+                    .map(|block| block.spans)
+                    .unwrap_or_default();
+                for span in spans {
+                    self.push_text(&span.text, span.style, 0);
+                }
+            }
+            self.finish();
+        }
+    }
+
+    fn event(&mut self, event: Event, offset: usize) {
+        match event {
+            Event::Start(tag) => self.start_tag(tag, offset),
+            Event::End(tag) => self.end_tag(tag),
+            Event::Text(text) => self.push_text(&text, self.style.clone(), offset),
+            Event::Code(code) => {
+                let style = SpanStyle {
+                    code: true,
+                    ..self.style.clone()
+                };
+                self.push_text(&code, style, offset);
+            }
+            Event::FootnoteReference(label) => {
+                self.push_text(&format!("[{}]", label), self.style.clone(), offset);
+            }
+            Event::SoftBreak => self.push_text(" ", self.style.clone(), offset),
+            Event::HardBreak => self.push_text("\n", self.style.clone(), offset),
+            Event::Rule => {
+                self.start(BlockKind::Rule, offset);
+                self.finish();
+            }
+            Event::TaskListMarker(done) => {
+                let marker = if done { "☑" } else { "☐" };
+                match &mut self.current {
+                    Some(block) => block.marker = Some(marker.to_string()),
+                    None => self.marker = Some(marker.to_string()),
+                }
+            }
+            // Comments and markup are not shown
+            Event::Html(_) => {}
+        }
+    }
+
+    fn start_tag(&mut self, tag: Tag, offset: usize) {
+        match tag {
+            Tag::Paragraph => self.start(BlockKind::Paragraph, offset),
+            Tag::Heading(level, _, _) => self.start(BlockKind::Heading(level as u8), offset),
+            Tag::CodeBlock(_) => self.start(BlockKind::Code, offset),
+            Tag::BlockQuote => {
+                self.finish();
+                self.quote += 1;
+            }
+            Tag::List(start) => {
+                self.finish();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.finish();
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}.", *number - 1)
+                    }
+                    _ => "•".to_string(),
+                };
+                self.marker = Some(marker);
+            }
+            Tag::FootnoteDefinition(label) => {
+                self.finish();
+                self.marker = Some(format!("[{}]", label));
+            }
+            Tag::Table(_) => {
+                self.finish();
+                self.table = Some(TableBuilder {
+                    rows: Vec::new(),
+                    row: Vec::new(),
+                    offset,
+                });
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(table) = &mut self.table {
+                    table.row.clear();
+                }
+            }
+            Tag::TableCell => {
+                if let Some(table) = &mut self.table {
+                    table.row.push(Vec::new());
+                }
+            }
+            Tag::Emphasis => self.style.emphasis = true,
+            Tag::Strong => self.style.strong = true,
+            Tag::Strikethrough => self.style.strikethrough = true,
+            Tag::Link(_, url, _) => self.style.link = Some(url.to_string()),
+            Tag::Image(_, _, _) => {
+                self.style.emphasis = true;
+                self.push_text("🖼 ", self.style.clone(), offset);
+            }
+        }
+    }
+
+    fn end_tag(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph | Tag::Heading(..) | Tag::CodeBlock(_) | Tag::Item => self.finish(),
+            Tag::FootnoteDefinition(_) => {
+                self.finish();
+                self.marker = None;
+            }
+            Tag::BlockQuote => {
+                self.finish();
+                self.quote = self.quote.saturating_sub(1);
+            }
+            Tag::List(_) => {
+                self.finish();
+                self.lists.pop();
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(table) = &mut self.table {
+                    table.rows.push(std::mem::take(&mut table.row));
+                }
+            }
+            Tag::Table(_) => {
+                if let Some(table) = self.table.take() {
+                    self.start(BlockKind::Table(table.rows), table.offset);
+                    self.finish();
+                }
+            }
+            Tag::Emphasis | Tag::Image(..) => self.style.emphasis = false,
+            Tag::Strong => self.style.strong = false,
+            Tag::Strikethrough => self.style.strikethrough = false,
+            Tag::Link(..) => self.style.link = None,
+            Tag::TableCell => {}
+        }
+    }
+}
+
+/// Append `text` to `spans`, in the last span when it has the same style.
+fn push_span(spans: &mut Vec<Span>, text: &str, style: SpanStyle) {
+    match spans.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(text),
+        _ => spans.push(Span {
+            text: text.to_string(),
+            style,
+        }),
+    }
+}
+
+/// Parse `markdown` into the blocks of its preview.
+pub fn parse_blocks(markdown: &str, options: Options) -> Vec<PreviewBlock> {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(markdown.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let line_start = |line: usize| line_starts.get(line).copied().unwrap_or(markdown.len());
+
+    let mut builder = BlockBuilder::default();
+    let mut start = 0;
+    for verse in verse::verses(markdown) {
+        let end = line_start(verse.lines.start);
+        builder.parse(&markdown[start..end], start, options);
+        builder.verse(&verse, &line_start, options);
+        start = line_start(verse.lines.end);
+    }
+    builder.parse(&markdown[start..], start, options);
+    builder.blocks
+}
+
+/// The preview of a document, laid out with egui.
+///
+/// The blocks are parsed again only when the content changes.
+#[derive(Debug, Default)]
+pub struct LivePreview {
+    blocks: Vec<PreviewBlock>,
+    /// Hash of the content `blocks` were parsed from
+    parsed: Option<u64>,
+    /// Top of each block in the scrolled content, as last laid out
+    tops: Vec<f32>,
+}
+
+impl LivePreview {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Blocks of the content last shown.
+    pub fn blocks(&self) -> &[PreviewBlock] {
+        &self.blocks
+    }
+
+    /// Parse `markdown` unless it is the content already parsed. Returns
+    /// whether the blocks changed.
+    pub fn update(&mut self, markdown: &str, options: Options) -> bool {
+        let mut hasher = DefaultHasher::new();
+        markdown.hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.parsed == Some(hash) {
+            return false;
+        }
+        self.blocks = parse_blocks(markdown, options);
+        self.parsed = Some(hash);
+        true
+    }
+
+    /// Lay out the blocks in `ui`, the content of a scroll area. Returns the
+    /// byte offset of the block clicked, if any.
+    pub fn show(&mut self, ui: &mut Ui) -> Option<usize> {
+        self.tops.clear();
+        if self.blocks.is_empty() {
+            ui.weak("Nothing to preview yet.");
+            return None;
+        }
+        let top = ui.max_rect().top();
+        let mut clicked = None;
+        for (index, block) in self.blocks.iter().enumerate() {
+            self.tops.push(ui.cursor().top() - top);
+            if show_block(ui, block, index).clicked() {
+                clicked = Some(block.offset);
+            }
+        }
+        clicked
+    }
+
+    /// Height in the scrolled content of the byte offset `source` of the
+    /// Markdown, interpolated between the blocks around it.
+    pub fn height_of(&self, source: usize) -> Option<f32> {
+        let points = self.blocks.iter().zip(&self.tops);
+        interpolate(
+            points.map(|(block, top)| (block.offset as f32, *top)),
+            source as f32,
+        )
+    }
+
+    /// Byte offset of the Markdown at height `y` of the scrolled content.
+    pub fn source_at(&self, y: f32) -> Option<usize> {
+        let points = self.blocks.iter().zip(&self.tops);
+        interpolate(points.map(|(block, top)| (*top, block.offset as f32)), y)
+            .map(|source| source as usize)
+    }
+}
+
+/// Lay out `block`, returning the response to clicks on it.
+fn show_block(ui: &mut Ui, block: &PreviewBlock, index: usize) -> Response {
+    let indent = INDENT * (block.depth.saturating_sub(1) + block.quote) as f32;
+    let quote_color = ui.visuals().weak_text_color();
+    let response = ui.horizontal_top(|ui| {
+        ui.add_space(indent);
+        if let Some(marker) = &block.marker {
+            ui.label(marker);
+        } else if block.depth > 0 {
+            // Later paragraphs of a list item line up with its first
+            ui.add_space(INDENT);
+        }
+        ui.vertical(|ui| match &block.kind {
+            BlockKind::Rule => {
+                ui.separator();
+            }
+            BlockKind::Code => {
+                egui::Frame::new()
+                    .fill(ui.visuals().code_bg_color)
+                    .inner_margin(6)
+                    .corner_radius(4)
+                    .show(ui, |ui| {
+                        ui.set_width(ui.available_width());
+                        ui.label(egui::RichText::new(block.text().trim_end()).monospace());
+                    });
+            }
+            BlockKind::Table(rows) => {
+                egui::Grid::new(("preview_table", index))
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (row_index, row) in rows.iter().enumerate() {
+                            for cell in row {
+                                let job = layout_spans(ui, cell, 1.0, row_index == 0);
+                                ui.label(job);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            }
+            BlockKind::Heading(level) => {
+                let scale = HEADING_SCALE[usize::from(*level).clamp(1, 6) - 1];
+                ui.add_space(4.0);
+                ui.label(layout_spans(ui, &block.spans, scale, true));
+            }
+            BlockKind::Paragraph => {
+                ui.label(layout_spans(ui, &block.spans, 1.0, false));
+            }
+        });
+    });
+
+    // Bars to the left of quoted blocks
+    if block.quote > 0 {
+        let rect = response.response.rect;
+        for level in 0..block.quote {
+            let x = rect.left() + INDENT * level as f32 + 4.0;
+            ui.painter()
+                .vline(x, rect.y_range(), Stroke::new(2.0, quote_color));
+        }
+    }
+    ui.add_space(ui.spacing().item_spacing.y);
+    ui.interact(
+        response.response.rect,
+        ui.id().with(("preview_block", index)),
+        Sense::click(),
+    )
+}
+
+/// Lay out `spans` in the body font scaled by `scale`, in the strong color
+/// when `strong`.
+fn layout_spans(ui: &Ui, spans: &[Span], scale: f32, strong: bool) -> LayoutJob {
+    let style = ui.style();
+    let body = TextStyle::Body.resolve(style);
+    let monospace = TextStyle::Monospace.resolve(style);
+    let visuals = &style.visuals;
+    let mut job = LayoutJob::default();
+    job.wrap.max_width = ui.available_width();
+    for span in spans {
+        let span_style = &span.style;
+        let font_id = if span_style.code {
+            FontId::new(monospace.size * scale, monospace.family.clone())
+        } else {
+            FontId::new(body.size * scale, body.family.clone())
+        };
+        let color = if span_style.link.is_some() {
+            visuals.hyperlink_color
+        } else if strong || span_style.strong {
+            visuals.strong_text_color()
+        } else {
+            visuals.text_color()
+        };
+        let format = TextFormat {
+            font_id,
+            color,
+            italics: span_style.emphasis,
+            background: if span_style.code {
+                visuals.code_bg_color
+            } else {
+                Color32::TRANSPARENT
+            },
+            underline: if span_style.link.is_some() {
+                Stroke::new(1.0, color)
+            } else {
+                Stroke::NONE
+            },
+            strikethrough: if span_style.strikethrough {
+                Stroke::new(1.0, color)
+            } else {
+                Stroke::NONE
+            },
+            ..Default::default()
+        };
+        job.append(&span.text, 0.0, format);
+    }
+    job
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(markdown: &str) -> Vec<PreviewBlock> {
+        parse_blocks(markdown, Options::all())
+    }
+
+    #[test]
+    fn test_blocks_and_inline_styles() {
+        let blocks = parse("# Title\n\nSome **bold** and `code`.\n\n---\n\n```\nlet x;\n```\n");
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[0].kind, BlockKind::Heading(1));
+        assert_eq!(blocks[0].text(), "Title");
+        assert_eq!(blocks[1].offset, 9);
+        let styles: Vec<_> = blocks[1]
+            .spans
+            .iter()
+            .map(|span| (span.text.as_str(), span.style.strong, span.style.code))
+            .collect();
+        assert_eq!(
+            styles,
+            [
+                ("Some ", false, false),
+                ("bold", true, false),
+                (" and ", false, false),
+                ("code", false, true),
+                (".", false, false)
+            ]
+        );
+        assert_eq!(blocks[2].kind, BlockKind::Rule);
+        assert_eq!(blocks[3].kind, BlockKind::Code);
+        assert_eq!(blocks[3].text(), "let x;\n");
+    }
+
+    #[test]
+    fn test_lists_quotes_and_tasks() {
+        let blocks = parse("3. one\n4. two\n   - [x] done\n\n> quoted\n");
+        let items: Vec<_> = blocks
+            .iter()
+            .map(|b| (b.marker.as_deref(), b.depth, b.quote, b.text()))
+            .collect();
+        assert_eq!(
+            items,
+            [
+                (Some("3."), 1, 0, "one".to_string()),
+                (Some("4."), 1, 0, "two".to_string()),
+                (Some("☑"), 2, 0, "done".to_string()),
+                (None, 0, 1, "quoted".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tables() {
+        let blocks = parse("Intro\n\n| Name | Role |\n|---|---|\n| Mira | *lead* |\n");
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].offset, 7);
+        let BlockKind::Table(rows) = &blocks[1].kind else {
+            panic!("not a table: {:?}", blocks[1]);
+        };
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][1][0].text, "Role");
+        assert!(rows[1][1][0].style.emphasis);
+    }
+
+    #[test]
+    fn test_verse_keeps_its_lines() {
+        let blocks = parse(
+            "Prose.\n\n<!-- verse -->\nOne *line*,\n    - two.\n\nThree.\n<!-- /verse -->\nEnd.",
+        );
+        let texts: Vec<_> = blocks.iter().map(|b| (b.offset, b.text())).collect();
+        assert_eq!(
+            texts,
+            [
+                (0, "Prose.".to_string()),
+                (23, "One line,\n\u{a0}\u{a0}\u{a0}\u{a0}- two.".to_string()),
+                (47, "Three.".to_string()),
+                (70, "End.".to_string()),
+            ]
+        );
+        assert!(blocks[1].spans[1].style.emphasis);
+    }
+
+    #[test]
+    fn test_content_is_parsed_once_per_change() {
+        let mut preview = LivePreview::new();
+        assert!(preview.update("one", Options::empty()));
+        assert_eq!(preview.blocks()[0].text(), "one");
+        assert!(!preview.update("one", Options::empty()));
+        assert!(preview.update("two", Options::empty()));
+        assert_eq!(preview.blocks()[0].text(), "two");
+    }
+
+    #[test]
+    fn test_source_maps_to_block_heights() {
+        let mut preview = LivePreview::new();
+        preview.update("# Title\n\nFirst paragraph.\n\nSecond.\n", Options::empty());
+        // Laid out at these heights
+        preview.tops = vec![0.0, 40.0, 100.0];
+        let offsets: Vec<_> = preview.blocks().iter().map(|b| b.offset).collect();
+        assert_eq!(offsets, [0, 9, 27]);
+
+        assert_eq!(preview.height_of(9), Some(40.0));
+        assert_eq!(preview.height_of(18), Some(70.0));
+        assert_eq!(preview.source_at(70.0), Some(18));
+        assert_eq!(preview.source_at(500.0), Some(27));
+    }
+}
@@ -0,0 +1,188 @@
+//! Scrolling of the editor and the live preview kept in step.
+//!
+//! Both sides map byte offsets of the Markdown to heights in their scrolled
+//! content: the editor through the galley of its text, the preview through
+//! the tops of its blocks, between which offsets are interpolated. Whichever
+//! side is scrolled, the other follows to show the same source at its top;
+//! a click on either side brings the source clicked to the same height on the
+//! other.
+
+use egui::text::CCursor;
+use egui::{Galley, Id};
+use std::sync::Arc;
+
+/// Least change of a scroll offset, in points, taken for a scroll.
+const MIN_SCROLL: f32 = 0.5;
+
+/// Side scrolled since the last frame: the editor view or the preview.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollSide {
+    Editor,
+    Preview,
+}
+
+/// Scroll offsets of the editor and the preview, and where to scroll them.
+#[derive(Debug, Default)]
+pub struct ScrollSync {
+    editor_offset: f32,
+    preview_offset: f32,
+    /// Offsets to scroll to on the next frame
+    editor_target: Option<f32>,
+    preview_target: Option<f32>,
+    /// Whether a side was sent to a target since the last frame, so that
+    /// its move is not taken for a scroll
+    editor_moved: bool,
+    preview_moved: bool,
+}
+
+impl ScrollSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the scroll offsets of this frame. Returns the side scrolled
+    /// since the last frame, other than to follow the other side.
+    pub fn scrolled(&mut self, editor: f32, preview: f32) -> Option<ScrollSide> {
+        let editor_moved = std::mem::take(&mut self.editor_moved);
+        let preview_moved = std::mem::take(&mut self.preview_moved);
+        let editor_scrolled = !editor_moved && (editor - self.editor_offset).abs() > MIN_SCROLL;
+        let preview_scrolled = !preview_moved && (preview - self.preview_offset).abs() > MIN_SCROLL;
+        self.editor_offset = editor;
+        self.preview_offset = preview;
+        if editor_scrolled {
+            Some(ScrollSide::Editor)
+        } else if preview_scrolled {
+            Some(ScrollSide::Preview)
+        } else {
+            None
+        }
+    }
+
+    /// Scroll the editor to `offset` on the next frame.
+    pub fn scroll_editor(&mut self, offset: f32) {
+        self.editor_target = Some(offset.max(0.0));
+        self.editor_moved = true;
+    }
+
+    /// Scroll the preview to `offset` on the next frame.
+    pub fn scroll_preview(&mut self, offset: f32) {
+        self.preview_target = Some(offset.max(0.0));
+        self.preview_moved = true;
+    }
+
+    /// Whether a side is to be scrolled on the next frame.
+    pub fn is_pending(&self) -> bool {
+        self.editor_target.is_some() || self.preview_target.is_some()
+    }
+
+    /// Offset to scroll the editor to, once.
+    pub fn take_editor_target(&mut self) -> Option<f32> {
+        self.editor_target.take()
+    }
+
+    /// Offset to scroll the preview to, once.
+    pub fn take_preview_target(&mut self) -> Option<f32> {
+        self.preview_target.take()
+    }
+}
+
+/// Layout of the text in the editor view the preview follows, as of its
+/// last frame.
+pub struct EditorView {
+    /// Tab of the view
+    pub tab: String,
+    /// Id of the view's text edit
+    pub id: Id,
+    galley: Arc<Galley>,
+    /// Top of the galley in the scrolled content
+    top: f32,
+    /// Scroll offset of the view
+    pub offset: f32,
+    /// Byte offset of the caret, when placed with a click in this frame
+    pub clicked: Option<usize>,
+}
+
+impl EditorView {
+    pub fn new(tab: &str, id: Id, galley: Arc<Galley>, top: f32, offset: f32) -> Self {
+        Self {
+            tab: tab.to_string(),
+            id,
+            galley,
+            top,
+            offset,
+            clicked: None,
+        }
+    }
+
+    /// Height of the byte offset `source` of `text` in the scrolled
+    /// content.
+    pub fn height_of(&self, text: &str, source: usize) -> f32 {
+        let index = text
+            .get(..source)
+            .map_or_else(|| text.chars().count(), |before| before.chars().count());
+        self.galley.pos_from_cursor(CCursor::new(index)).top() + self.top
+    }
+
+    /// Byte offset in `text` of the line at height `y` of the scrolled
+    /// content.
+    pub fn source_at(&self, text: &str, y: f32) -> usize {
+        let cursor = self.galley.cursor_from_pos(egui::vec2(0.0, y - self.top));
+        text.char_indices()
+            .nth(cursor.index)
+            .map_or(text.len(), |(byte, _)| byte)
+    }
+}
+
+/// `x` mapped through `points`, pairs `(x, y)` in increasing order of both,
+/// by linear interpolation between them. Before the first point and after
+/// the last, `y` is that of the point.
+pub fn interpolate(points: impl IntoIterator<Item = (f32, f32)>, x: f32) -> Option<f32> {
+    let mut previous: Option<(f32, f32)> = None;
+    for (point_x, point_y) in points {
+        if point_x > x {
+            return Some(match previous {
+                Some((previous_x, previous_y)) if point_x > previous_x => {
+                    let fraction = (x - previous_x) / (point_x - previous_x);
+                    previous_y + fraction * (point_y - previous_y)
+                }
+                Some((_, previous_y)) => previous_y,
+                None => point_y,
+            });
+        }
+        previous = Some((point_x, point_y));
+    }
+    previous.map(|(_, y)| y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate_between_points() {
+        let points = [(0.0, 0.0), (100.0, 40.0), (100.0, 60.0), (200.0, 160.0)];
+        assert_eq!(interpolate(points, 50.0), Some(20.0));
+        assert_eq!(interpolate(points, 150.0), Some(110.0));
+        assert_eq!(interpolate(points, -10.0), Some(0.0));
+        assert_eq!(interpolate(points, 500.0), Some(160.0));
+        assert_eq!(interpolate([], 10.0), None);
+    }
+
+    #[test]
+    fn test_following_is_not_taken_for_a_scroll() {
+        let mut sync = ScrollSync::new();
+        assert_eq!(sync.scrolled(0.0, 0.0), None);
+
+        assert_eq!(sync.scrolled(120.0, 0.0), Some(ScrollSide::Editor));
+        sync.scroll_preview(80.0);
+        assert_eq!(sync.take_preview_target(), Some(80.0));
+        assert_eq!(sync.take_preview_target(), None);
+        // The preview followed the editor
+        assert_eq!(sync.scrolled(120.0, 80.0), None);
+
+        assert_eq!(sync.scrolled(120.0, 30.0), Some(ScrollSide::Preview));
+        sync.scroll_editor(-5.0);
+        assert_eq!(sync.take_editor_target(), Some(0.0));
+        assert_eq!(sync.scrolled(0.0, 30.0), None);
+    }
+}
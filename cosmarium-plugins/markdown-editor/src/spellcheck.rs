@@ -0,0 +1,403 @@
+//! # Spell checking
+//!
+//! Words missing from both the Hunspell dictionary of the configured
+//! language and the project dictionary get a wavy underline in the editor;
+//! their context menu offers the Hunspell suggestions. Code, URLs, link
+//! destinations and wiki links are not checked, nor are all-caps words
+//! (acronyms) and words mixing letters and digits.
+//!
+//! The application publishes the spell check settings under
+//! [`SPELL_CHECK_KEY`]. Dictionaries (`<language>.aff` and `<language>.dic`)
+//! are looked up in the directories listed there, then in the usual system
+//! locations.
+
+use crate::completion::is_word_char;
+use crate::dictionary::ProjectDictionary;
+use anyhow::{anyhow, Context};
+use egui::{text::CCursor, Color32, Galley, Painter, Pos2, Shape, Stroke};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Shared state key ([`SpellCheckSettings`]) of the spell check settings,
+/// set by the application.
+pub const SPELL_CHECK_KEY: &str = "markdown_editor_spell_check";
+
+/// Suggestions offered in the context menu of a misspelled word.
+pub const MAX_SPELL_SUGGESTIONS: usize = 5;
+
+/// Where Hunspell dictionaries are installed by the usual packages.
+const SYSTEM_DICTIONARY_DIRS: &[&str] = &[
+    "/usr/share/hunspell",
+    "/usr/share/myspell",
+    "/usr/share/myspell/dicts",
+    "/usr/local/share/hunspell",
+    "/Library/Spelling",
+];
+
+/// Height of the waves of the underline.
+const WAVE_HEIGHT: f32 = 1.5;
+
+/// Spell check settings, published by the application.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpellCheckSettings {
+    /// Whether misspelled words are underlined
+    pub enabled: bool,
+    /// Dictionary language, such as `en_US`
+    pub language: String,
+    /// Directories searched for dictionaries before the system ones
+    pub dictionary_dirs: Vec<PathBuf>,
+}
+
+/// A Hunspell dictionary.
+///
+/// Without the `spellcheck` feature no dictionary can be loaded, and words
+/// are never underlined.
+pub struct SpellChecker {
+    language: String,
+    #[cfg(feature = "spellcheck")]
+    dictionary: spellbook::Dictionary,
+}
+
+impl std::fmt::Debug for SpellChecker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpellChecker")
+            .field("language", &self.language)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SpellChecker {
+    /// Create a checker from the content of a dictionary's `.aff` and `.dic`
+    /// files.
+    #[cfg(feature = "spellcheck")]
+    pub fn new(language: &str, aff: &str, dic: &str) -> anyhow::Result<Self> {
+        let dictionary = spellbook::Dictionary::new(aff, dic)
+            .map_err(|e| anyhow!("Invalid {} dictionary: {}", language, e))?;
+        Ok(Self {
+            language: language.to_string(),
+            dictionary,
+        })
+    }
+
+    /// Create a checker from the content of a dictionary's `.aff` and `.dic`
+    /// files.
+    #[cfg(not(feature = "spellcheck"))]
+    pub fn new(language: &str, _aff: &str, _dic: &str) -> anyhow::Result<Self> {
+        Err(anyhow!(
+            "Cannot load the {} dictionary: spell checking was left out of this build",
+            language
+        ))
+    }
+
+    /// Load the dictionary of the configured language.
+    pub fn load(settings: &SpellCheckSettings) -> anyhow::Result<Self> {
+        let (aff_path, dic_path) =
+            find_dictionary(&settings.language, &settings.dictionary_dirs)
+                .ok_or_else(|| anyhow!("No {} dictionary installed", settings.language))?;
+        let aff = std::fs::read_to_string(&aff_path)
+            .with_context(|| format!("Failed to read {}", aff_path.display()))?;
+        let dic = std::fs::read_to_string(&dic_path)
+            .with_context(|| format!("Failed to read {}", dic_path.display()))?;
+        Self::new(&settings.language, &aff, &dic)
+    }
+
+    /// Language of the dictionary.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Whether `word` is spelled correctly.
+    #[cfg(feature = "spellcheck")]
+    pub fn check(&self, word: &str) -> bool {
+        self.dictionary.check(&normalize(word))
+    }
+
+    /// Corrections of `word`, best first.
+    #[cfg(feature = "spellcheck")]
+    pub fn suggest(&self, word: &str, limit: usize) -> Vec<String> {
+        let mut suggestions = Vec::new();
+        self.dictionary.suggest(&normalize(word), &mut suggestions);
+        suggestions.truncate(limit);
+        suggestions
+    }
+
+    /// Whether `word` is spelled correctly.
+    #[cfg(not(feature = "spellcheck"))]
+    pub fn check(&self, _word: &str) -> bool {
+        true
+    }
+
+    /// Corrections of `word`, best first.
+    #[cfg(not(feature = "spellcheck"))]
+    pub fn suggest(&self, _word: &str, _limit: usize) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Typographic apostrophes as dictionaries spell them.
+#[cfg(feature = "spellcheck")]
+fn normalize(word: &str) -> String {
+    word.replace('’', "'")
+}
+
+/// Paths of the `.aff` and `.dic` files of `language`, searched in `dirs`
+/// then in the system locations.
+///
+/// `en_US` also matches dictionaries named `en-US`.
+pub fn find_dictionary(language: &str, dirs: &[PathBuf]) -> Option<(PathBuf, PathBuf)> {
+    let names = [language.to_string(), language.replace('_', "-")];
+    let system = SYSTEM_DICTIONARY_DIRS.iter().map(Path::new);
+    dirs.iter()
+        .map(PathBuf::as_path)
+        .chain(system)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .map(|stem| (stem.with_extension("aff"), stem.with_extension("dic")))
+        .find(|(aff, dic)| aff.is_file() && dic.is_file())
+}
+
+/// Byte ranges of the words of `text` to check, leaving out code, URLs,
+/// link destinations and wiki links.
+pub fn checked_words(text: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut in_fence = false;
+    let mut line_start = 0;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if !in_fence {
+            let skipped = skipped_spans(line);
+            let chars: Vec<(usize, char)> = line.char_indices().collect();
+            let mut i = 0;
+            while i < chars.len() {
+                let is_word = |i: usize| {
+                    is_word_char(
+                        i.checked_sub(1).map(|p| chars[p].1),
+                        chars[i].1,
+                        chars.get(i + 1).map(|&(_, c)| c),
+                    )
+                };
+                if !is_word(i) {
+                    i += 1;
+                    continue;
+                }
+                let start = i;
+                while i < chars.len() && is_word(i) {
+                    i += 1;
+                }
+                let range = chars[start].0..chars.get(i).map_or(line.len(), |&(b, _)| b);
+
+                // Words glued to digits or underscores are identifiers
+                let glued = |c: Option<&(usize, char)>| {
+                    c.is_some_and(|&(_, c)| c.is_ascii_digit() || c == '_')
+                };
+                let word = &line[range.clone()];
+                let acronym = word.chars().filter(|c| c.is_alphabetic()).count() > 1
+                    && !word.chars().any(char::is_lowercase);
+                if skipped
+                    .iter()
+                    .any(|span| span.start < range.end && range.start < span.end)
+                    || glued(start.checked_sub(1).and_then(|p| chars.get(p)))
+                    || glued(chars.get(i))
+                    || acronym
+                    || word.chars().count() < 2
+                {
+                    continue;
+                }
+                words.push(line_start + range.start..line_start + range.end);
+            }
+        }
+        line_start += line.len();
+    }
+    words
+}
+
+/// Byte ranges of a line not to check: inline code, URLs, link
+/// destinations and wiki links.
+fn skipped_spans(line: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut rest = 0;
+    while let Some(offset) = line[rest..].find(['`', '(', '[', 'h', 'w']) {
+        let start = rest + offset;
+        let tail = &line[start..];
+        let end = if let Some(code) = tail.strip_prefix('`') {
+            code.find('`').map(|end| start + end + 2)
+        } else if tail.starts_with("[[") {
+            tail.find("]]").map(|end| start + end + 2)
+        } else if tail.starts_with('(') && line[..start].ends_with(']') {
+            tail.find(')').map(|end| start + end + 1)
+        } else if tail.starts_with("http://")
+            || tail.starts_with("https://")
+            || tail.starts_with("www.")
+        {
+            Some(start + tail.find(char::is_whitespace).unwrap_or(tail.len()))
+        } else {
+            None
+        };
+        match end {
+            Some(end) => {
+                spans.push(start..end);
+                rest = end;
+            }
+            None => rest = start + tail.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    spans
+}
+
+/// Byte ranges of the misspelled words of `text`.
+///
+/// Words of the project dictionary, and their inflected forms, are spelled
+/// correctly whatever the language dictionary says.
+pub fn misspellings(
+    text: &str,
+    checker: &SpellChecker,
+    project: &ProjectDictionary,
+) -> Vec<Range<usize>> {
+    checked_words(text)
+        .into_iter()
+        .filter(|range| {
+            let word = &text[range.clone()];
+            !project.contains(word) && !checker.check(word)
+        })
+        .collect()
+}
+
+/// Draw wavy underlines below the `misspellings` of `content`, laid out in
+/// `galley` at `galley_pos`.
+pub fn paint(
+    painter: &Painter,
+    content: &str,
+    galley: &Galley,
+    galley_pos: Pos2,
+    misspellings: &[Range<usize>],
+    color: Color32,
+) {
+    let stroke = Stroke::new(1.0, color);
+    let clip = painter.clip_rect();
+    for range in misspellings {
+        let (Some(before), Some(word)) = (content.get(..range.start), content.get(range.clone()))
+        else {
+            continue;
+        };
+        let start_char = before.chars().count();
+        let start = galley
+            .pos_from_cursor(CCursor::new(start_char))
+            .translate(galley_pos.to_vec2());
+        let end = galley
+            .pos_from_cursor(CCursor::new(start_char + word.chars().count()))
+            .translate(galley_pos.to_vec2());
+        if !clip.intersects(start.union(end)) {
+            continue;
+        }
+
+        if (start.bottom() - end.bottom()).abs() < 1.0 {
+            wave(painter, start.left(), end.left(), start.bottom(), stroke);
+        } else {
+            // Wrapped over two rows
+            let right = galley_pos.x + galley.rect.right();
+            wave(painter, start.left(), right, start.bottom(), stroke);
+            wave(painter, galley_pos.x, end.left(), end.bottom(), stroke);
+        }
+    }
+}
+
+/// Draw a wavy line from `left` to `right` just above `y`.
+fn wave(painter: &Painter, left: f32, right: f32, y: f32, stroke: Stroke) {
+    let y = y - WAVE_HEIGHT;
+    let mut points = Vec::new();
+    let mut x = left;
+    let mut up = false;
+    while x < right {
+        points.push(Pos2::new(x, if up { y - WAVE_HEIGHT } else { y }));
+        x += WAVE_HEIGHT * 2.0;
+        up = !up;
+    }
+    points.push(Pos2::new(right, if up { y - WAVE_HEIGHT } else { y }));
+    if points.len() > 1 {
+        painter.add(Shape::line(points, stroke));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AFF: &str = "SET UTF-8\n\nSFX S Y 1\nSFX S 0 s .\n";
+    const DIC: &str = "4\nthe\ndragon/S\nsleep/S\ndon't\n";
+
+    #[cfg(feature = "spellcheck")]
+    fn checker() -> SpellChecker {
+        SpellChecker::new("en_TEST", AFF, DIC).unwrap()
+    }
+
+    fn words(text: &str) -> Vec<&str> {
+        checked_words(text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    #[cfg(feature = "spellcheck")]
+    #[test]
+    fn test_check_and_suggest() {
+        let checker = checker();
+        assert!(checker.check("dragons"));
+        assert!(checker.check("don’t"));
+        assert!(!checker.check("dragno"));
+        assert_eq!(
+            checker.suggest("dragno", MAX_SPELL_SUGGESTIONS)[0],
+            "dragon"
+        );
+    }
+
+    #[test]
+    fn test_checked_words_skip_code_and_links() {
+        let text = "The `cdoe` sleeps [here](http://exmple.org) and [[Velmari]] www.site.io\n\
+                    ```\nfenced cdoe\n```\nNASA wrote x42y at 3am";
+        assert_eq!(
+            words(text),
+            vec!["The", "sleeps", "here", "and", "wrote", "at"]
+        );
+    }
+
+    #[cfg(feature = "spellcheck")]
+    #[test]
+    fn test_misspellings_honor_project_dictionary() {
+        let checker = checker();
+        let mut project = ProjectDictionary::default();
+        project.add("Velmari");
+
+        let text = "The dragno sleeps, the Velmaris don’t.";
+        let found: Vec<&str> = misspellings(text, &checker, &project)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+        assert_eq!(found, vec!["dragno"]);
+    }
+
+    #[test]
+    fn test_find_dictionary() {
+        let dir = std::env::temp_dir().join(format!("cosmarium-spell-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("en-TEST.aff"), AFF).unwrap();
+        std::fs::write(dir.join("en-TEST.dic"), DIC).unwrap();
+
+        let found = find_dictionary("en_TEST", std::slice::from_ref(&dir));
+        assert_eq!(found.map(|(aff, _)| aff), Some(dir.join("en-TEST.aff")));
+        assert!(find_dictionary("xx_NONE", std::slice::from_ref(&dir)).is_none());
+
+        let settings = SpellCheckSettings {
+            enabled: true,
+            language: "en_TEST".to_string(),
+            dictionary_dirs: vec![dir.clone()],
+        };
+        let loaded = SpellChecker::load(&settings);
+        #[cfg(feature = "spellcheck")]
+        assert_eq!(loaded.unwrap().language(), "en_TEST");
+        #[cfg(not(feature = "spellcheck"))]
+        assert!(loaded.is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -4,10 +4,221 @@
 //! Markdown editor plugin. It tracks various metrics such as word count,
 //! character count, reading time, and writing patterns to help authors
 //! monitor their progress and improve their writing.
+//!
+//! Words are told apart by the spaces between them, except in Chinese and
+//! Japanese text, written without spaces, which is split along Unicode word
+//! boundaries (UAX #29). A document in Chinese or Japanese, according to the
+//! `lang` of its front matter or to the language set with
+//! [`WritingStats::set_language`], is measured in characters, the way
+//! manuscripts in those languages are.
+//!
+//! Verse (see [`cosmarium_plugin_api::verse`]) is counted in lines, stanzas
+//! and syllables as well.
 
+use cosmarium_plugin_api::verse::{self, VerseStats};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::OnceLock;
 use std::time::{Duration, SystemTime};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Configuration key under which the active project's [`WordCountRules`] are
+/// published to plugins.
+pub const WORD_COUNT_RULES_KEY: &str = "word_count_rules";
+
+/// Shared state key (`Option<Range<usize>>`) of the byte range of the text
+/// that the style panel of the prose plugin is asked to check alone.
+pub const STYLE_CHECK_REQUEST: &str = "style_check_request";
+
+/// Name of the panel checking the style of a passage.
+pub const STYLE_PANEL: &str = "prose";
+
+/// Rules deciding which parts of a document contribute to its word count.
+///
+/// Publishers and agents usually expect a manuscript count that leaves out
+/// headings, metadata and the author's own notes. Every rule is disabled by
+/// default so the count matches the raw buffer.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::stats::{WordCountRules, WritingStats};
+///
+/// let mut stats = WritingStats::new();
+/// stats.set_rules(WordCountRules::manuscript());
+/// stats.update("# Chapter One\n\nShe left. [TODO: fix ending]");
+/// assert_eq!(stats.word_count(), 2);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WordCountRules {
+    /// Skip ATX headings (`# Title`)
+    pub exclude_headings: bool,
+    /// Skip a leading YAML front matter block delimited by `---`
+    pub exclude_front_matter: bool,
+    /// Skip HTML comments (`<!-- -->`) and CriticMarkup annotations (`{>> <<}`)
+    pub exclude_comments: bool,
+    /// Skip bracketed author notes such as `[TODO: fix]` or `[TK: name]`
+    pub exclude_bracketed_notes: bool,
+}
+
+impl WordCountRules {
+    /// Rules matching a typical manuscript submission count.
+    pub fn manuscript() -> Self {
+        Self {
+            exclude_headings: true,
+            exclude_front_matter: true,
+            exclude_comments: true,
+            exclude_bracketed_notes: true,
+        }
+    }
+
+    /// Check whether any rule is enabled.
+    pub fn is_active(&self) -> bool {
+        self.exclude_headings
+            || self.exclude_front_matter
+            || self.exclude_comments
+            || self.exclude_bracketed_notes
+    }
+
+    /// Return the portion of `text` that should be counted.
+    ///
+    /// Excluded regions are replaced by whitespace so that the words on either
+    /// side are never glued together.
+    pub fn countable_text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if !self.is_active() {
+            return Cow::Borrowed(text);
+        }
+
+        let mut text = Cow::Borrowed(text);
+
+        if self.exclude_front_matter {
+            if let Some(end) = front_matter_end(&text) {
+                text = Cow::Owned(text[end..].to_string());
+            }
+        }
+
+        if self.exclude_comments {
+            for pattern in [html_comment_regex(), critic_comment_regex()] {
+                if pattern.is_match(&text) {
+                    text = Cow::Owned(pattern.replace_all(&text, " ").into_owned());
+                }
+            }
+        }
+
+        if self.exclude_bracketed_notes && note_regex().is_match(&text) {
+            text = Cow::Owned(note_regex().replace_all(&text, " ").into_owned());
+        }
+
+        if self.exclude_headings && text.lines().any(is_atx_heading) {
+            text = Cow::Owned(
+                text.lines()
+                    .filter(|line| !is_atx_heading(line))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+        }
+
+        text
+    }
+}
+
+/// Byte offset just past a leading front matter block, if the text has one.
+fn front_matter_end(text: &str) -> Option<usize> {
+    let body = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let offset = text.len() - body.len();
+    let first_line_end = body.find('\n')?;
+    if body[..first_line_end].trim_end() != "---" {
+        return None;
+    }
+
+    let mut pos = first_line_end + 1;
+    while pos < body.len() {
+        let line_end = body[pos..].find('\n').map_or(body.len(), |i| pos + i);
+        let line = body[pos..line_end].trim_end();
+        if line == "---" || line == "..." {
+            return Some(offset + (line_end + 1).min(body.len()));
+        }
+        pos = line_end + 1;
+    }
+
+    None
+}
+
+/// Check whether a line is an ATX heading (up to three spaces of indentation).
+fn is_atx_heading(line: &str) -> bool {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return false;
+    }
+    let rest = &line[indent..];
+    let hashes = rest.len() - rest.trim_start_matches('#').len();
+    (1..=6).contains(&hashes)
+        && rest[hashes..]
+            .chars()
+            .next()
+            .is_none_or(|c| c == ' ' || c == '\t')
+}
+
+/// Language set by the `lang` (or `language`) key of a document's front
+/// matter, if any.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::stats::document_language;
+///
+/// assert_eq!(document_language("---\nlang: zh-TW\n---\n你好"), Some("zh-TW"));
+/// assert_eq!(document_language("lang: fr"), None);
+/// ```
+pub fn document_language(text: &str) -> Option<&str> {
+    let end = front_matter_end(text)?;
+    text[..end].lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+        (matches!(key.trim(), "lang" | "language") && !value.is_empty()).then_some(value)
+    })
+}
+
+/// Check whether the words of a language, as an ISO 639-1 code or a locale,
+/// are counted in characters.
+fn counts_characters(language: &str) -> bool {
+    let primary = language.split(['-', '_']).next().unwrap_or_default();
+    ["zh", "ja", "yue"]
+        .iter()
+        .any(|code| primary.eq_ignore_ascii_case(code))
+}
+
+/// Check whether a character belongs to a script written without spaces
+/// between words: Han ideographs and Japanese kana.
+fn is_unspaced(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'   // Hiragana, Katakana
+        | '\u{31F0}'..='\u{31FF}' // Katakana extensions
+        | '\u{3400}'..='\u{4DBF}' // CJK extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+        | '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+        | '\u{FF66}'..='\u{FF9F}' // Half-width Katakana
+        | '\u{20000}'..='\u{3134F}' // CJK extensions B to G
+    )
+}
+
+fn html_comment_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?s)<!--.*?-->").expect("valid regex"))
+}
+
+fn critic_comment_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?s)\{>>.*?<<\}").expect("valid regex"))
+}
+
+fn note_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\[[A-Z][A-Z0-9_-]*:[^\]\n]*\]").expect("valid regex"))
+}
 
 /// Comprehensive writing statistics for a document.
 ///
@@ -48,6 +259,15 @@ pub struct WritingStats {
     last_updated: SystemTime,
     /// Session statistics
     session_stats: SessionStats,
+    /// Rules applied when counting words
+    #[serde(default)]
+    rules: WordCountRules,
+    /// Language of the documents without a `lang` of their own
+    #[serde(default)]
+    language: Option<String>,
+    /// Lines, stanzas and syllables of the verse
+    #[serde(default)]
+    verse: VerseStats,
 }
 
 /// Session-based writing statistics.
@@ -93,6 +313,9 @@ impl WritingStats {
             word_frequency: HashMap::new(),
             last_updated: now,
             session_stats: SessionStats::new(now),
+            rules: WordCountRules::default(),
+            language: None,
+            verse: VerseStats::default(),
         }
     }
 
@@ -118,11 +341,14 @@ impl WritingStats {
         let old_word_count = self.word_count;
 
         // Update basic counts
-        self.word_count = Self::count_words(content);
+        let language = document_language(content).or(self.language.as_deref());
+        let per_character = language.is_some_and(counts_characters);
+        self.word_count = Self::count_words(&self.rules.countable_text(content), per_character);
         self.char_count = content.chars().count();
         self.char_count_no_spaces = content.chars().filter(|&c| c != ' ').count();
         self.paragraph_count = Self::count_paragraphs(content);
         self.sentence_count = Self::count_sentences(content);
+        self.verse = VerseStats::of(content);
 
         // Calculate averages
         if self.sentence_count > 0 {
@@ -222,6 +448,22 @@ impl WritingStats {
         self.paragraph_count
     }
 
+    /// Lines, stanzas and syllables of the verse of the document.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_markdown_editor::stats::WritingStats;
+    ///
+    /// let mut stats = WritingStats::new();
+    /// stats.update("<!-- verse -->\nThe rain\nfell.\n\nIt stopped.");
+    /// assert_eq!(stats.verse_stats().lines, 3);
+    /// assert_eq!(stats.verse_stats().stanzas, 2);
+    /// ```
+    pub fn verse_stats(&self) -> VerseStats {
+        self.verse
+    }
+
     /// Get the total sentence count.
     ///
     /// # Returns
@@ -373,6 +615,46 @@ impl WritingStats {
         &self.session_stats
     }
 
+    /// Get the rules applied when counting words.
+    pub fn rules(&self) -> WordCountRules {
+        self.rules
+    }
+
+    /// Set the rules applied when counting words.
+    ///
+    /// The new rules take effect on the next call to [`WritingStats::update`].
+    pub fn set_rules(&mut self, rules: WordCountRules) {
+        self.rules = rules;
+    }
+
+    /// Get the language of the documents whose front matter sets none.
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Set the language, as an ISO 639-1 code or a locale (`ja`, `zh_TW`), of
+    /// the documents whose front matter sets none.
+    ///
+    /// The new language takes effect on the next call to
+    /// [`WritingStats::update`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cosmarium_markdown_editor::stats::WritingStats;
+    ///
+    /// let mut stats = WritingStats::new();
+    /// stats.update("コーヒーを飲む");
+    /// assert_eq!(stats.word_count(), 4);
+    ///
+    /// stats.set_language(Some("ja".to_string()));
+    /// stats.update("コーヒーを飲む");
+    /// assert_eq!(stats.word_count(), 7);
+    /// ```
+    pub fn set_language(&mut self, language: Option<String>) {
+        self.language = language;
+    }
+
     /// Reset session statistics.
     ///
     /// This starts a new writing session while preserving document statistics.
@@ -394,11 +676,26 @@ impl WritingStats {
     /// Count words in the given text.
     ///
     /// This method handles Markdown syntax and provides accurate word counts
-    /// by excluding markup elements.
-    fn count_words(text: &str) -> usize {
+    /// by excluding markup elements. Runs of text written without spaces are
+    /// split along Unicode word boundaries, or into characters when
+    /// `per_character` is set.
+    fn count_words(text: &str, per_character: bool) -> usize {
         text.split_whitespace()
             .filter(|word| !word.is_empty())
             .map(|word| {
+                if word.chars().any(is_unspaced) {
+                    return word
+                        .unicode_words()
+                        .map(|segment| {
+                            if per_character && segment.chars().any(is_unspaced) {
+                                segment.chars().count()
+                            } else {
+                                1
+                            }
+                        })
+                        .sum();
+                }
+
                 // Remove common Markdown syntax
                 let cleaned = word
                     .trim_matches(|c: char| ".,!?;:()[]{}\"'`*_~".contains(c))
@@ -433,11 +730,15 @@ impl WritingStats {
     /// Count sentences in the given text.
     fn count_sentences(text: &str) -> usize {
         let sentence_endings = ['.', '!', '?'];
+        // Full-width endings are never followed by a space
+        let full_width_endings = ['。', '！', '？'];
         let mut count = 0;
         let mut chars = text.chars().peekable();
 
         while let Some(ch) = chars.next() {
-            if sentence_endings.contains(&ch) {
+            if full_width_endings.contains(&ch) {
+                count += 1;
+            } else if sentence_endings.contains(&ch) {
                 // Check if it's not an abbreviation or decimal
                 if let Some(&next_ch) = chars.peek() {
                     if next_ch.is_whitespace() || next_ch == '\n' {
@@ -538,10 +839,111 @@ impl SessionStats {
     }
 }
 
+/// Counts and readability of a passage, such as the selection.
+///
+/// The readability is the Flesch reading ease, from about 100 for prose a
+/// child reads easily down to 0 and below for dense academic text. Its
+/// formula was made for English.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::stats::SelectionStats;
+///
+/// let stats = SelectionStats::of("The cat sat. It purred.");
+/// assert_eq!((stats.words, stats.sentences), (5, 2));
+/// assert!(stats.reading_ease().unwrap() > 90.0);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SelectionStats {
+    /// Words, counted as in the document
+    pub words: usize,
+    /// Characters, spaces included
+    pub chars: usize,
+    /// Sentences, a passage without an ending counting as one
+    pub sentences: usize,
+    /// Syllables of the words, estimated from their vowels
+    pub syllables: usize,
+}
+
+impl SelectionStats {
+    /// Counts of `text`.
+    pub fn of(text: &str) -> Self {
+        let words = WritingStats::count_words(text, false);
+        let sentences = WritingStats::count_sentences(text);
+        Self {
+            words,
+            chars: text.chars().count(),
+            sentences: if words > 0 { sentences.max(1) } else { 0 },
+            syllables: text.split_whitespace().map(verse::syllables).sum(),
+        }
+    }
+
+    /// Flesch reading ease, `None` without words.
+    pub fn reading_ease(&self) -> Option<f32> {
+        if self.words == 0 {
+            return None;
+        }
+        let words = self.words as f32;
+        Some(
+            206.835
+                - 1.015 * (words / self.sentences as f32)
+                - 84.6 * (self.syllables as f32 / words),
+        )
+    }
+
+    /// How hard a text of reading ease `score` is to read.
+    pub fn ease_label(score: f32) -> &'static str {
+        match score {
+            s if s >= 90.0 => "very easy",
+            s if s >= 70.0 => "easy",
+            s if s >= 60.0 => "plain",
+            s if s >= 50.0 => "fairly difficult",
+            s if s >= 30.0 => "difficult",
+            _ => "very difficult",
+        }
+    }
+
+    /// The counts on one line, to paste elsewhere.
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} words, {} characters, {} sentences",
+            self.words, self.chars, self.sentences
+        );
+        if let Some(score) = self.reading_ease() {
+            summary.push_str(&format!(
+                ", reading ease {:.0} ({})",
+                score,
+                Self::ease_label(score)
+            ));
+        }
+        summary
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_selection_stats() {
+        assert_eq!(SelectionStats::of("  ").reading_ease(), None);
+        let fragment = SelectionStats::of("a door left open");
+        assert_eq!((fragment.words, fragment.sentences), (4, 1));
+        let plain = SelectionStats::of("She ran. He hid.")
+            .reading_ease()
+            .unwrap();
+        let dense = SelectionStats::of(
+            "Institutional considerations notwithstanding, administrative reorganization necessitates deliberation.",
+        )
+        .reading_ease()
+        .unwrap();
+        assert!(plain > dense);
+        assert!(SelectionStats::of("She ran.")
+            .summary()
+            .starts_with("2 words, 8 characters, 1 sentences, reading ease"));
+    }
+
     #[test]
     fn test_writing_stats_creation() {
         let stats = WritingStats::new();
@@ -664,6 +1066,116 @@ mod tests {
         assert_eq!(stats.sentence_count(), 0);
     }
 
+    #[test]
+    fn test_default_rules_count_everything() {
+        let mut stats = WritingStats::new();
+        stats.update("# Title\n\nBody text. [TODO: fix this]");
+
+        assert_eq!(stats.word_count(), 6);
+    }
+
+    #[test]
+    fn test_rules_exclude_headings() {
+        let mut stats = WritingStats::new();
+        stats.set_rules(WordCountRules {
+            exclude_headings: true,
+            ..Default::default()
+        });
+        stats.update("# Chapter One\n\nIt was late.\n\n## Scene\n#hashtag stays");
+
+        assert_eq!(stats.word_count(), 5);
+    }
+
+    #[test]
+    fn test_rules_exclude_front_matter() {
+        let rules = WordCountRules {
+            exclude_front_matter: true,
+            ..Default::default()
+        };
+        let text = "---\ntitle: My Novel\nauthor: Someone\n---\nThe end.";
+        assert_eq!(rules.countable_text(text).trim(), "The end.");
+
+        // A thematic break later in the document is not front matter
+        let text = "Intro\n---\nMore";
+        assert_eq!(rules.countable_text(text), text);
+
+        // An unterminated block is left alone
+        let text = "---\ntitle: draft";
+        assert_eq!(rules.countable_text(text), text);
+    }
+
+    #[test]
+    fn test_rules_exclude_comments_and_notes() {
+        let mut stats = WritingStats::new();
+        stats.set_rules(WordCountRules {
+            exclude_comments: true,
+            exclude_bracketed_notes: true,
+            ..Default::default()
+        });
+        stats.update(
+            "She<!-- rename\nher --> ran{>>too fast?<<} home. [TODO: fix] [a link](url) [TK: name]",
+        );
+
+        // She, ran, home, a, link
+        assert_eq!(stats.word_count(), 5);
+    }
+
+    #[test]
+    fn test_manuscript_rules() {
+        let mut stats = WritingStats::new();
+        stats.set_rules(WordCountRules::manuscript());
+        stats.update("---\ntags: [draft]\n---\n# One\n\nWords here. <!-- note -->");
+
+        assert_eq!(stats.word_count(), 2);
+        assert_eq!(stats.rules(), WordCountRules::manuscript());
+    }
+
+    #[test]
+    fn test_chinese_words_are_characters() {
+        let mut stats = WritingStats::new();
+        stats.update("我喜欢写小说。你呢？");
+
+        // Every ideograph is a word, with or without a language
+        assert_eq!(stats.word_count(), 8);
+        assert_eq!(stats.sentence_count(), 2);
+
+        stats.update("他用Rust写了一个 editor。");
+        assert_eq!(stats.word_count(), 8);
+    }
+
+    #[test]
+    fn test_japanese_language_hint() {
+        let text = "カタカナの言葉";
+        let mut stats = WritingStats::new();
+        stats.update(text);
+        // The Katakana run is one word along Unicode word boundaries
+        assert_eq!(stats.word_count(), 4);
+
+        stats.set_language(Some("ja_JP".to_string()));
+        stats.update(text);
+        assert_eq!(stats.word_count(), 7);
+
+        // The document's own language comes first
+        stats.set_rules(WordCountRules {
+            exclude_front_matter: true,
+            ..Default::default()
+        });
+        stats.update(&format!("---\nlang: en\n---\n{}", text));
+        assert_eq!(stats.word_count(), 4);
+    }
+
+    #[test]
+    fn test_document_language() {
+        assert_eq!(
+            document_language("---\ntitle: x\nlanguage: 'ja'\n---\n"),
+            Some("ja")
+        );
+        assert_eq!(document_language("---\nlang:\n---\n"), None);
+        assert!(counts_characters("zh-Hant"));
+        assert!(!counts_characters("ko"));
+        assert!(!counts_characters("jv"));
+    }
+
     #[test]
     fn test_whitespace_only() {
         let mut stats = WritingStats::new();
@@ -1,548 +1,416 @@
 //! # Syntax highlighting module for the Markdown Editor plugin
 //!
-//! This module provides syntax highlighting capabilities for markdown content
-//! in the Cosmarium creative writing software. It handles highlighting of
-//! markdown syntax elements, code blocks, and provides customizable themes.
+//! While editing, [`layout_job`] styles the text after its [`markup`]:
+//! headings and strong emphasis stand out, emphasis and block quotes are
+//! slanted, code has its background, links are underlined and the Markdown
+//! syntax itself is dimmed. The text keeps its monospace font, so columns
+//! stay where they were.
 
-use cosmarium_plugin_api::Result;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId, Stroke, Visuals};
+use std::ops::Range;
 
-#[cfg(feature = "syntax-highlighting")]
-use syntect::highlighting::{Color, FontStyle, Style, Theme, ThemeSet};
-#[cfg(feature = "syntax-highlighting")]
-use syntect::parsing::{SyntaxReference, SyntaxSet};
+use crate::wrap;
 
-/// Markdown syntax highlighter.
+/// Markdown markup of a stretch of the text being edited.
 ///
-/// The [`MarkdownHighlighter`] provides syntax highlighting for markdown
-/// content, supporting various themes and customization options.
+/// Flags combine, as for a link in a block quote or emphasis in a heading.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Markup {
+    /// Text of a heading
+    pub heading: bool,
+    /// Strong emphasis
+    pub strong: bool,
+    /// Emphasis
+    pub emphasis: bool,
+    /// Code span or code block
+    pub code: bool,
+    /// Text of a block quote
+    pub quote: bool,
+    /// Text of a link
+    pub link: bool,
+    /// Markdown syntax itself: markers, link targets, comments and front matter
+    pub syntax: bool,
+}
+
+impl Markup {
+    /// Markup of the syntax within this markup.
+    fn syntax(self) -> Self {
+        Self {
+            syntax: true,
+            ..self
+        }
+    }
+
+    /// Format of text with this markup in the editor, written with `font_id`
+    /// in `color`.
+    pub fn format(&self, font_id: &FontId, color: Color32, visuals: &Visuals) -> TextFormat {
+        let color = if self.syntax {
+            visuals.weak_text_color()
+        } else if self.link {
+            visuals.hyperlink_color
+        } else if self.heading || self.strong {
+            visuals.strong_text_color()
+        } else {
+            color
+        };
+        TextFormat {
+            font_id: font_id.clone(),
+            color,
+            background: if self.code {
+                visuals.code_bg_color
+            } else {
+                Color32::TRANSPARENT
+            },
+            italics: self.emphasis || self.quote,
+            underline: if self.link && !self.syntax {
+                Stroke::new(1.0, color)
+            } else {
+                Stroke::NONE
+            },
+            ..TextFormat::default()
+        }
+    }
+}
+
+/// Markup of `content`, as consecutive byte ranges covering all of it.
+///
+/// Front matter, fenced code, comments, rules, block quotes, headings and
+/// list markers are found line by line; emphasis, code spans and links
+/// within a line, when they are closed on it.
 ///
 /// # Example
 ///
 /// ```rust
-/// # #[cfg(feature = "syntax-highlighting")]
-/// # {
-/// use cosmarium_markdown_editor::syntax::MarkdownHighlighter;
+/// use cosmarium_markdown_editor::syntax::markup;
 ///
-/// let highlighter = MarkdownHighlighter::new().unwrap();
-/// let highlighted = highlighter.highlight("# Hello **World**");
-/// # }
+/// let content = "# Title\n\nShe *ran*.";
+/// let emphasis: Vec<&str> = markup(content)
+///     .into_iter()
+///     .filter(|(_, markup)| markup.emphasis && !markup.syntax)
+///     .map(|(range, _)| &content[range])
+///     .collect();
+/// assert_eq!(emphasis, ["ran"]);
 /// ```
-#[derive(Debug)]
-pub struct MarkdownHighlighter {
-    #[cfg(feature = "syntax-highlighting")]
-    syntax_set: SyntaxSet,
-    #[cfg(feature = "syntax-highlighting")]
-    theme_set: ThemeSet,
-    #[cfg(feature = "syntax-highlighting")]
-    current_theme: String,
-    /// Custom highlighting rules
-    custom_rules: HashMap<String, HighlightStyle>,
-    /// Whether highlighting is enabled
-    enabled: bool,
-}
+pub fn markup(content: &str) -> Vec<(Range<usize>, Markup)> {
+    let mut spans = Spans::default();
+    let mut front_matter = content.starts_with("---\n") || content.starts_with("---\r\n");
+    let mut fence: Option<&str> = None;
+    let mut start = 0;
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let text = line.trim_end_matches(['\n', '\r']);
+        let body = text.trim();
+        let end = start + text.len();
+        let code = Markup {
+            code: true,
+            ..Markup::default()
+        };
 
-/// Style information for syntax highlighting.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HighlightStyle {
-    /// Foreground color (hex format)
-    pub foreground: String,
-    /// Background color (hex format, optional)
-    pub background: Option<String>,
-    /// Font style flags
-    pub font_style: FontStyleFlags,
+        if front_matter {
+            front_matter = i == 0 || !matches!(body, "---" | "...");
+            spans.push(start..end, Markup::default().syntax());
+        } else if let Some(marker) = fence {
+            if body.starts_with(marker) {
+                fence = None;
+            }
+            spans.push(start..end, code);
+        } else if body.starts_with("```") || body.starts_with("~~~") {
+            fence = Some(&body[..3]);
+            spans.push(start..end, code);
+        } else if (body.starts_with("<!--") && body.ends_with("-->")) || wrap::is_rule(body) {
+            spans.push(start..end, Markup::default().syntax());
+        } else {
+            block(&mut spans, content, start, end);
+        }
+        spans.push(end..start + line.len(), Markup::default());
+        start += line.len();
+    }
+    spans.0
 }
 
-/// Font style flags for highlighting.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub struct FontStyleFlags {
-    /// Bold text
-    pub bold: bool,
-    /// Italic text
-    pub italic: bool,
-    /// Underlined text
-    pub underline: bool,
+/// Layout of `content` in the editor, styled after its [`markup`], written
+/// with `font_id` in `color` and wrapped at `wrap_width`.
+pub fn layout_job(
+    content: &str,
+    font_id: &FontId,
+    color: Color32,
+    visuals: &Visuals,
+    wrap_width: f32,
+) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    for (range, markup) in markup(content) {
+        job.append(&content[range], 0.0, markup.format(font_id, color, visuals));
+    }
+    job.wrap.max_width = wrap_width;
+    job
 }
 
-/// Highlighted text segment.
-#[derive(Debug, Clone)]
-pub struct HighlightedSegment {
-    /// Text content
-    pub text: String,
-    /// Style for this segment
-    pub style: HighlightStyle,
-    /// Syntax type (heading, emphasis, etc.)
-    pub syntax_type: String,
+/// Last [`layout_job`] of a view, laid out again only when its text or
+/// style changes, so that typing in a long chapter does not find the
+/// markup of all of it every frame.
+#[derive(Default)]
+pub struct LayoutCache {
+    /// Text and style the job was laid out for
+    key: Option<LayoutKey>,
+    job: LayoutJob,
 }
 
-impl MarkdownHighlighter {
-    /// Create a new markdown highlighter.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if the syntax highlighting system cannot be initialized.
-    ///
-    /// # Example
-    ///
-    /// ```rust
-    /// # #[cfg(feature = "syntax-highlighting")]
-    /// # {
-    /// use cosmarium_markdown_editor::syntax::MarkdownHighlighter;
-    ///
-    /// let highlighter = MarkdownHighlighter::new().unwrap();
-    /// # }
-    /// ```
-    pub fn new() -> Result<Self> {
-        #[cfg(feature = "syntax-highlighting")]
-        {
-            let syntax_set = SyntaxSet::load_defaults_newlines();
-            let theme_set = ThemeSet::load_defaults();
-
-            Ok(Self {
-                syntax_set,
-                theme_set,
-                current_theme: "base16-ocean.dark".to_string(),
-                custom_rules: Self::default_markdown_rules(),
-                enabled: true,
-            })
-        }
+#[derive(PartialEq)]
+struct LayoutKey {
+    content: String,
+    font_id: FontId,
+    color: Color32,
+    visuals: Visuals,
+    wrap_width: f32,
+}
 
-        #[cfg(not(feature = "syntax-highlighting"))]
-        {
-            Ok(Self {
-                custom_rules: Self::default_markdown_rules(),
-                enabled: false,
-            })
+impl LayoutCache {
+    /// [`layout_job`] of `content`, from the cache if nothing changed since
+    /// the last call.
+    pub fn layout_job(
+        &mut self,
+        content: &str,
+        font_id: &FontId,
+        color: Color32,
+        visuals: &Visuals,
+        wrap_width: f32,
+    ) -> LayoutJob {
+        let fresh = self.key.as_ref().is_some_and(|key| {
+            key.content == content
+                && key.font_id == *font_id
+                && key.color == color
+                && key.wrap_width == wrap_width
+                && key.visuals == *visuals
+        });
+        if !fresh {
+            self.job = layout_job(content, font_id, color, visuals, wrap_width);
+            self.key = Some(LayoutKey {
+                content: content.to_string(),
+                font_id: font_id.clone(),
+                color,
+                visuals: visuals.clone(),
+                wrap_width,
+            });
         }
+        self.job.clone()
     }
+}
 
-    /// Highlight markdown content.
-    ///
-    /// # Arguments
-    ///
-    /// * `content` - Markdown content to highlight
-    ///
-    /// # Returns
-    ///
-    /// Vector of highlighted segments.
-    ///
-    /// # Example
-    ///
-    /// ```rust
-    /// use cosmarium_markdown_editor::syntax::MarkdownHighlighter;
-    ///
-    /// let highlighter = MarkdownHighlighter::new().unwrap();
-    /// let segments = highlighter.highlight("# Hello **World**");
-    /// assert!(!segments.is_empty());
-    /// ```
-    pub fn highlight(&self, content: &str) -> Vec<HighlightedSegment> {
-        if !self.enabled {
-            return vec![HighlightedSegment {
-                text: content.to_string(),
-                style: HighlightStyle::default(),
-                syntax_type: "plain".to_string(),
-            }];
-        }
-
-        #[cfg(feature = "syntax-highlighting")]
-        {
-            self.highlight_with_syntect(content)
-        }
+/// Spans of markup being found, joined while they follow each other with
+/// the same markup.
+#[derive(Default)]
+struct Spans(Vec<(Range<usize>, Markup)>);
 
-        #[cfg(not(feature = "syntax-highlighting"))]
-        {
-            self.highlight_simple(content)
+impl Spans {
+    fn push(&mut self, range: Range<usize>, markup: Markup) {
+        if range.is_empty() {
+            return;
         }
-    }
-
-    /// Set the current theme.
-    ///
-    /// # Arguments
-    ///
-    /// * `theme_name` - Name of the theme to use
-    ///
-    /// # Example
-    ///
-    /// ```rust
-    /// use cosmarium_markdown_editor::syntax::MarkdownHighlighter;
-    ///
-    /// let mut highlighter = MarkdownHighlighter::new().unwrap();
-    /// highlighter.set_theme("Solarized (dark)");
-    /// ```
-    pub fn set_theme(&mut self, _theme_name: &str) {
-        #[cfg(feature = "syntax-highlighting")]
-        {
-            if self.theme_set.themes.contains_key(theme_name) {
-                self.current_theme = theme_name.to_string();
+        match self.0.last_mut() {
+            Some((last, last_markup)) if last.end == range.start && *last_markup == markup => {
+                last.end = range.end;
             }
-        }
-
-        #[cfg(not(feature = "syntax-highlighting"))]
-        {
-            // Store theme name even without syntect for consistency
-            // self.current_theme = theme_name.to_string();
+            _ => self.0.push((range, markup)),
         }
     }
+}
 
-    /// Get the current theme name.
-    pub fn current_theme(&self) -> &str {
-        #[cfg(feature = "syntax-highlighting")]
-        {
-            &self.current_theme
-        }
-
-        #[cfg(not(feature = "syntax-highlighting"))]
-        {
-            "default"
-        }
-    }
-
-    /// List available themes.
-    ///
-    /// # Returns
-    ///
-    /// Vector of theme names.
-    pub fn available_themes(&self) -> Vec<String> {
-        #[cfg(feature = "syntax-highlighting")]
-        {
-            self.theme_set.themes.keys().cloned().collect()
-        }
-
-        #[cfg(not(feature = "syntax-highlighting"))]
-        {
-            vec!["default".to_string()]
-        }
-    }
-
-    /// Enable or disable syntax highlighting.
-    ///
-    /// # Arguments
-    ///
-    /// * `enabled` - Whether to enable highlighting
-    pub fn set_enabled(&mut self, enabled: bool) {
-        self.enabled = enabled;
-    }
-
-    /// Check if syntax highlighting is enabled.
-    pub fn is_enabled(&self) -> bool {
-        self.enabled
-    }
-
-    /// Add a custom highlighting rule.
-    ///
-    /// # Arguments
-    ///
-    /// * `syntax_type` - Type of syntax element
-    /// * `style` - Style to apply
-    pub fn add_custom_rule(&mut self, syntax_type: &str, style: HighlightStyle) {
-        self.custom_rules.insert(syntax_type.to_string(), style);
-    }
+/// Markup of the line at `start..end` of `content`: block quote, heading
+/// and list markers, then the markup of its text.
+fn block(spans: &mut Spans, content: &str, mut start: usize, end: usize) {
+    let mut markup = Markup::default();
 
-    /// Remove a custom highlighting rule.
-    ///
-    /// # Arguments
-    ///
-    /// * `syntax_type` - Type of syntax element to remove
-    pub fn remove_custom_rule(&mut self, syntax_type: &str) {
-        self.custom_rules.remove(syntax_type);
+    // Block quote markers, nested or not
+    loop {
+        let rest = &content[start..end];
+        let Some(after) = rest.trim_start_matches([' ', '\t']).strip_prefix('>') else {
+            break;
+        };
+        let marker = rest.len() - after.len() + usize::from(after.starts_with(' '));
+        markup.quote = true;
+        spans.push(start..start + marker, markup.syntax());
+        start += marker;
     }
 
-    /// Get default markdown highlighting rules.
-    fn default_markdown_rules() -> HashMap<String, HighlightStyle> {
-        let mut rules = HashMap::new();
-
-        // Headers
-        rules.insert(
-            "heading1".to_string(),
-            HighlightStyle {
-                foreground: "#FF6B6B".to_string(),
-                background: None,
-                font_style: FontStyleFlags {
-                    bold: true,
-                    italic: false,
-                    underline: false,
-                },
-            },
-        );
-
-        rules.insert(
-            "heading2".to_string(),
-            HighlightStyle {
-                foreground: "#4ECDC4".to_string(),
-                background: None,
-                font_style: FontStyleFlags {
-                    bold: true,
-                    italic: false,
-                    underline: false,
-                },
-            },
-        );
-
-        rules.insert(
-            "heading3".to_string(),
-            HighlightStyle {
-                foreground: "#45B7D1".to_string(),
-                background: None,
-                font_style: FontStyleFlags {
-                    bold: true,
-                    italic: false,
-                    underline: false,
-                },
-            },
-        );
-
-        // Text formatting
-        rules.insert(
-            "bold".to_string(),
-            HighlightStyle {
-                foreground: "#F7931E".to_string(),
-                background: None,
-                font_style: FontStyleFlags {
-                    bold: true,
-                    italic: false,
-                    underline: false,
-                },
-            },
-        );
-
-        rules.insert(
-            "italic".to_string(),
-            HighlightStyle {
-                foreground: "#FFD93D".to_string(),
-                background: None,
-                font_style: FontStyleFlags {
-                    bold: false,
-                    italic: true,
-                    underline: false,
-                },
-            },
-        );
-
-        rules.insert(
-            "code".to_string(),
-            HighlightStyle {
-                foreground: "#6BCF7F".to_string(),
-                background: Some("#2D3748".to_string()),
-                font_style: FontStyleFlags {
-                    bold: false,
-                    italic: false,
-                    underline: false,
-                },
-            },
-        );
-
-        // Links
-        rules.insert(
-            "link".to_string(),
-            HighlightStyle {
-                foreground: "#4299E1".to_string(),
-                background: None,
-                font_style: FontStyleFlags {
-                    bold: false,
-                    italic: false,
-                    underline: true,
-                },
-            },
-        );
-
-        // Lists
-        rules.insert(
-            "list_marker".to_string(),
-            HighlightStyle {
-                foreground: "#805AD5".to_string(),
-                background: None,
-                font_style: FontStyleFlags {
-                    bold: true,
-                    italic: false,
-                    underline: false,
-                },
-            },
-        );
-
-        // Blockquotes
-        rules.insert(
-            "blockquote".to_string(),
-            HighlightStyle {
-                foreground: "#A0AEC0".to_string(),
-                background: None,
-                font_style: FontStyleFlags {
-                    bold: false,
-                    italic: true,
-                    underline: false,
-                },
-            },
-        );
+    let rest = &content[start..end];
+    let body = rest.trim_start_matches([' ', '\t']);
+    let indent = rest.len() - body.len();
+    let hashes = body.len() - body.trim_start_matches('#').len();
+    let marker = if (1..=6).contains(&hashes)
+        && (body.len() == hashes || body[hashes..].starts_with([' ', '\t']))
+    {
+        markup.heading = true;
+        hashes + usize::from(body.len() > hashes)
+    } else {
+        list_marker(body)
+    };
+    spans.push(start..start + indent, markup);
+    spans.push(start + indent..start + indent + marker, markup.syntax());
+
+    inline(spans, &content[..end], start + indent + marker, markup);
+}
 
-        rules
+/// Length of the list item marker starting `body` with the space after it,
+/// or 0 if there is none.
+fn list_marker(body: &str) -> usize {
+    let digits = body.len() - body.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let marker = if digits == 0 {
+        usize::from(body.starts_with(['-', '*', '+']))
+    } else if digits <= 9 && body[digits..].starts_with(['.', ')']) {
+        digits + 1
+    } else {
+        0
+    };
+    if marker > 0 && body[marker..].starts_with([' ', '\t']) {
+        marker + 1
+    } else {
+        0
     }
+}
 
-    /// Highlight using syntect library (when available).
-    #[cfg(feature = "syntax-highlighting")]
-    fn highlight_with_syntect(&self, content: &str) -> Vec<HighlightedSegment> {
-        let syntax = self
-            .syntax_set
-            .find_syntax_by_extension("md")
-            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
-
-        let theme = &self.theme_set.themes[&self.current_theme];
-
-        use syntect::highlighting::Highlighter;
-        use syntect::parsing::ParseState;
-
-        let mut highlighter = Highlighter::new(theme);
-        let mut parse_state = ParseState::new(syntax);
-        let mut segments = Vec::new();
-
-        for line in content.lines() {
-            let ops = parse_state.parse_line(line, &self.syntax_set).unwrap();
-            let highlighted = highlighter.highlight_line(line, &ops).unwrap();
-
-            for (style, text) in highlighted {
-                if !text.is_empty() {
-                    segments.push(HighlightedSegment {
-                        text: text.to_string(),
-                        style: HighlightStyle::from_syntect_style(style),
-                        syntax_type: "code".to_string(), // Could be more specific
-                    });
-                }
+/// Markup of the text of a line, from `start` to the end of `content`,
+/// within the `markup` of the line: emphasis, code spans, links, autolinks
+/// and comments.
+fn inline(spans: &mut Spans, content: &str, start: usize, mut markup: Markup) {
+    let text = &content[start..];
+    let bytes = text.as_bytes();
+    // Text not pushed yet, from `plain` to `i`
+    let mut plain = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let before = markup;
+        let run = bytes[i..].iter().take_while(|&&b| b == bytes[i]).count();
+        // Lengths and markup of the pieces of syntax starting at `i`
+        let pieces: Vec<(usize, Markup)> = match bytes[i] {
+            b'\\' => {
+                i += text[i..].chars().take(2).map(char::len_utf8).sum::<usize>();
+                continue;
             }
+            b'`' => code_span(&text[i + run..], run).map_or_else(Vec::new, |len| {
+                let code = Markup {
+                    code: true,
+                    ..markup
+                };
+                vec![(run, code.syntax()), (len, code), (run, code.syntax())]
+            }),
+            b'*' | b'_' => emphasis(text, i, run.min(3), markup).map_or_else(Vec::new, |after| {
+                markup = after;
+                // Markers take the emphasis of the text they enclose
+                let enclosed = if after.strong || after.emphasis {
+                    after
+                } else {
+                    before
+                };
+                vec![(run.min(3), enclosed.syntax())]
+            }),
+            b'[' => link(&text[i..]).map_or_else(Vec::new, |(opening, label, target)| {
+                let link = Markup {
+                    link: true,
+                    ..markup
+                };
+                vec![
+                    (opening, markup.syntax()),
+                    (label, link),
+                    (target, markup.syntax()),
+                ]
+            }),
+            b'<' if text[i..].starts_with("<!--") => text[i..]
+                .find("-->")
+                .map_or_else(Vec::new, |at| vec![(at + 3, markup.syntax())]),
+            b'<' => autolink(&text[i..]).map_or_else(Vec::new, |len| {
+                let link = Markup {
+                    link: true,
+                    ..markup
+                };
+                vec![(1, markup.syntax()), (len, link), (1, markup.syntax())]
+            }),
+            _ => Vec::new(),
+        };
 
-            // Add newline
-            segments.push(HighlightedSegment {
-                text: "\n".to_string(),
-                style: HighlightStyle::default(),
-                syntax_type: "newline".to_string(),
-            });
-        }
-
-        segments
-    }
-
-    /// Simple regex-based highlighting (fallback when syntect is not available).
-    fn highlight_simple(&self, content: &str) -> Vec<HighlightedSegment> {
-        let mut segments = Vec::new();
-        let _current_pos = 0;
-
-        // Simple patterns for common markdown elements
-        let _patterns = [
-            (r"^#{1,6}\s.*$", "heading"),
-            (r"\*\*.*?\*\*", "bold"),
-            (r"\*.*?\*", "italic"),
-            (r"`.*?`", "code"),
-            (r"\[.*?\]\(.*?\)", "link"),
-            (r"^>\s.*$", "blockquote"),
-            (r"^[-*+]\s", "list_marker"),
-        ];
-
-        // For this simple implementation, we'll just return the content as-is
-        // with basic classification
-        let lines: Vec<&str> = content.lines().collect();
-
-        for line in lines {
-            let syntax_type = if line.starts_with('#') {
-                "heading"
-            } else if line.starts_with('>') {
-                "blockquote"
-            } else if line.trim_start().starts_with(&['-', '*', '+']) {
-                "list_marker"
-            } else {
-                "plain"
+        if pieces.is_empty() {
+            i += match bytes[i] {
+                b'`' | b'*' | b'_' => run,
+                _ => text[i..].chars().next().map_or(1, char::len_utf8),
             };
-
-            let style = self
-                .custom_rules
-                .get(syntax_type)
-                .cloned()
-                .unwrap_or_default();
-
-            segments.push(HighlightedSegment {
-                text: format!("{}\n", line),
-                style,
-                syntax_type: syntax_type.to_string(),
-            });
+            continue;
         }
-
-        segments
-    }
-}
-
-impl Default for MarkdownHighlighter {
-    fn default() -> Self {
-        Self::new().unwrap_or_else(|_| Self {
-            #[cfg(feature = "syntax-highlighting")]
-            syntax_set: SyntaxSet::load_defaults_newlines(),
-            #[cfg(feature = "syntax-highlighting")]
-            theme_set: ThemeSet::load_defaults(),
-            #[cfg(feature = "syntax-highlighting")]
-            current_theme: "base16-ocean.dark".to_string(),
-            custom_rules: Self::default_markdown_rules(),
-            enabled: false,
-        })
+        spans.push(start + plain..start + i, before);
+        for (len, markup) in pieces {
+            spans.push(start + i..start + i + len, markup);
+            i += len;
+        }
+        plain = i;
     }
+    spans.push(start + plain..content.len(), markup);
 }
 
-impl HighlightStyle {
-    /// Create a new highlight style.
-    pub fn new(foreground: &str) -> Self {
-        Self {
-            foreground: foreground.to_string(),
-            background: None,
-            font_style: FontStyleFlags::default(),
+/// Length of the code of a code span opened by `run` backticks, up to as
+/// many backticks in `rest`.
+fn code_span(rest: &str, run: usize) -> Option<usize> {
+    let bytes = rest.as_bytes();
+    let mut at = 0;
+    while at < bytes.len() {
+        let len = bytes[at..].iter().take_while(|&&b| b == b'`').count();
+        if len == run {
+            return Some(at);
         }
+        at += len.max(1);
     }
+    None
+}
 
-    /// Set background color.
-    pub fn with_background(mut self, background: &str) -> Self {
-        self.background = Some(background.to_string());
-        self
-    }
-
-    /// Set font style.
-    pub fn with_font_style(mut self, font_style: FontStyleFlags) -> Self {
-        self.font_style = font_style;
-        self
-    }
-
-    /// Convert from syntect style (when available).
-    #[cfg(feature = "syntax-highlighting")]
-    pub fn from_syntect_style(style: Style) -> Self {
-        Self {
-            foreground: format!(
-                "#{:02X}{:02X}{:02X}",
-                style.foreground.r, style.foreground.g, style.foreground.b
-            ),
-            background: Some(format!(
-                "#{:02X}{:02X}{:02X}",
-                style.background.r, style.background.g, style.background.b
-            )),
-            font_style: FontStyleFlags {
-                bold: style.font_style.contains(FontStyle::BOLD),
-                italic: style.font_style.contains(FontStyle::ITALIC),
-                underline: style.font_style.contains(FontStyle::UNDERLINE),
-            },
-        }
-    }
+/// Markup after the `run` of `*` or `_` at byte `at` of `text`, opening or
+/// closing emphasis within `markup`, if it does.
+fn emphasis(text: &str, at: usize, run: usize, markup: Markup) -> Option<Markup> {
+    let delimiter = &text[at..at + run];
+    let before = text[..at].chars().next_back();
+    let after = text[at + run..].chars().next();
+    // Underscores within words are no emphasis
+    let underscore = delimiter.starts_with('_');
+    let closes = before.is_some_and(|c| !c.is_whitespace())
+        && !(underscore && after.is_some_and(char::is_alphanumeric));
+    let opens = after.is_some_and(|c| !c.is_whitespace())
+        && !(underscore && before.is_some_and(char::is_alphanumeric))
+        && text[at + run..].contains(delimiter);
+
+    let (strong, emphasis) = (run >= 2, run != 2);
+    let open = if (!strong || markup.strong) && (!emphasis || markup.emphasis) && closes {
+        false
+    } else if (!strong || !markup.strong) && (!emphasis || !markup.emphasis) && opens {
+        true
+    } else {
+        return None;
+    };
+    Some(Markup {
+        strong: if strong { open } else { markup.strong },
+        emphasis: if emphasis { open } else { markup.emphasis },
+        ..markup
+    })
 }
 
-impl Default for HighlightStyle {
-    fn default() -> Self {
-        Self {
-            foreground: "#000000".to_string(),
-            background: None,
-            font_style: FontStyleFlags::default(),
-        }
+/// Lengths of the opening bracket, label and target of the link starting
+/// `text`: `[label](url)`, `[label][reference]` or a wiki link `[[Name]]`.
+fn link(text: &str) -> Option<(usize, usize, usize)> {
+    if let Some(inner) = text.strip_prefix("[[") {
+        return inner.find("]]").map(|label| (2, label, 2));
     }
+    let label = text[1..].find(']')?;
+    let rest = &text[1 + label..];
+    let target = match rest[1..].chars().next() {
+        Some('(') => rest.find(')')? + 1,
+        Some('[') => rest[1..].find(']')? + 2,
+        _ => return None,
+    };
+    Some((1, label, target))
 }
 
-impl Default for FontStyleFlags {
-    fn default() -> Self {
-        Self {
-            bold: false,
-            italic: false,
-            underline: false,
-        }
-    }
+/// Length of the address of the autolink starting `text`, such as
+/// `<https://example.com>`, without its brackets.
+fn autolink(text: &str) -> Option<usize> {
+    let address = &text[1..text.find('>')?];
+    ((address.contains("://") || address.starts_with("mailto:"))
+        && !address.contains(char::is_whitespace))
+    .then_some(address.len())
 }
 
 #[cfg(test)]
@@ -550,87 +418,91 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_highlighter_creation() {
-        let highlighter = MarkdownHighlighter::new();
-        assert!(highlighter.is_ok());
-    }
-
-    #[test]
-    fn test_basic_highlighting() {
-        let highlighter = MarkdownHighlighter::new().unwrap();
-        let segments = highlighter.highlight("# Hello World");
-        assert!(!segments.is_empty());
-    }
-
-    #[test]
-    fn test_theme_management() {
-        let mut highlighter = MarkdownHighlighter::new().unwrap();
-        let themes = highlighter.available_themes();
-        assert!(!themes.is_empty());
-
-        if !themes.is_empty() {
-            highlighter.set_theme(&themes[0]);
-        }
-    }
-
-    #[test]
-    fn test_enable_disable() {
-        let mut highlighter = MarkdownHighlighter::new().unwrap();
-        #[cfg(feature = "syntax-highlighting")]
-        assert!(highlighter.is_enabled());
-        #[cfg(not(feature = "syntax-highlighting"))]
-        assert!(!highlighter.is_enabled());
-
-        highlighter.set_enabled(false);
-        assert!(!highlighter.is_enabled());
-
-        let segments = highlighter.highlight("# Test");
-        assert_eq!(segments.len(), 1);
-        assert_eq!(segments[0].syntax_type, "plain");
-    }
-
-    #[test]
-    fn test_custom_rules() {
-        let mut highlighter = MarkdownHighlighter::new().unwrap();
-        let custom_style = HighlightStyle::new("#FF0000").with_font_style(FontStyleFlags {
-            bold: true,
-            italic: false,
-            underline: false,
-        });
-
-        highlighter.add_custom_rule("custom", custom_style.clone());
+    fn test_layout_cache_follows_the_text() {
+        let font_id = FontId::monospace(14.0);
+        let visuals = Visuals::dark();
+        let mut cache = LayoutCache::default();
+        let job = |content| layout_job(content, &font_id, Color32::WHITE, &visuals, 300.0);
+        let cached = |cache: &mut LayoutCache, content| {
+            cache.layout_job(content, &font_id, Color32::WHITE, &visuals, 300.0)
+        };
 
-        // Test rule was added (we can't easily test the highlighting without more complex setup)
-        highlighter.remove_custom_rule("custom");
+        assert_eq!(cached(&mut cache, "She *ran*."), job("She *ran*."));
+        assert_eq!(cached(&mut cache, "She *ran*."), job("She *ran*."));
+        assert_eq!(cached(&mut cache, "She **ran**."), job("She **ran**."));
+        let light = cache.layout_job(
+            "She **ran**.",
+            &font_id,
+            Color32::BLACK,
+            &Visuals::light(),
+            300.0,
+        );
+        assert_eq!(
+            light,
+            layout_job(
+                "She **ran**.",
+                &font_id,
+                Color32::BLACK,
+                &Visuals::light(),
+                300.0
+            )
+        );
     }
 
-    #[test]
-    fn test_highlight_style_creation() {
-        let style = HighlightStyle::new("#FF0000")
-            .with_background("#00FF00")
-            .with_font_style(FontStyleFlags {
-                bold: true,
-                italic: true,
-                underline: false,
-            });
-
-        assert_eq!(style.foreground, "#FF0000");
-        assert_eq!(style.background, Some("#00FF00".to_string()));
-        assert!(style.font_style.bold);
-        assert!(style.font_style.italic);
-        assert!(!style.font_style.underline);
+    fn marked(content: &str, filter: impl Fn(&Markup) -> bool) -> Vec<&str> {
+        markup(content)
+            .into_iter()
+            .filter(|(_, markup)| filter(markup))
+            .map(|(range, _)| &content[range])
+            .collect()
     }
 
     #[test]
-    fn test_font_style_flags() {
-        let flags = FontStyleFlags {
-            bold: true,
-            italic: false,
-            underline: true,
-        };
+    fn test_markup() {
+        let content = "---\ntitle: Dawn\n---\n## The *first* day\n\n\
+            > She said **no** to `ls *.md` and\n\
+            - [the inn](inn.md), [[Mira]] or <https://example.com>\n\
+            snake_case_name * alone \\*not emphasis*\n\
+            ```\nlet *x* = 1;\n```\n";
+        let spans = markup(content);
+        assert_eq!(spans.first().map(|(range, _)| range.start), Some(0));
+        assert_eq!(
+            spans.last().map(|(range, _)| range.end),
+            Some(content.len())
+        );
+        assert!(spans.windows(2).all(|w| w[0].0.end == w[1].0.start));
 
-        assert!(flags.bold);
-        assert!(!flags.italic);
-        assert!(flags.underline);
+        assert_eq!(
+            marked(content, |m| m.heading && !m.syntax),
+            ["The ", "first", " day"]
+        );
+        assert_eq!(marked(content, |m| m.emphasis && !m.syntax), ["first"]);
+        assert_eq!(marked(content, |m| m.strong && !m.syntax), ["no"]);
+        assert_eq!(
+            marked(content, |m| m.code && !m.syntax),
+            ["ls *.md", "```", "let *x* = 1;", "```"]
+        );
+        assert_eq!(
+            marked(content, |m| m.quote && !m.syntax && !m.strong && !m.code),
+            ["She said ", " to ", " and"]
+        );
+        assert_eq!(
+            marked(content, |m| m.link && !m.syntax),
+            ["the inn", "Mira", "https://example.com"]
+        );
+        assert_eq!(
+            marked(content, |m| m.syntax && !m.quote && !m.heading),
+            [
+                "---",
+                "title: Dawn",
+                "---",
+                "- [",
+                "](inn.md)",
+                "[[",
+                "]]",
+                "<",
+                ">"
+            ]
+        );
     }
 }
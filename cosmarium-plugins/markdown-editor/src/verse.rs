@@ -0,0 +1,120 @@
+//! # Verse
+//!
+//! Poems are written between verse markers (see
+//! [`cosmarium_plugin_api::verse`]), which keep their lines and indentation
+//! in the preview and the exports. The context menu sets the paragraphs
+//! under the caret as verse, or back to prose; reflowing and hard wrapping
+//! leave verse as it is, and the statistics count its lines, stanzas and
+//! syllables.
+
+use cosmarium_plugin_api::verse::{parse_marker, verses, VERSE_END_MARKER, VERSE_MARKER};
+use std::ops::Range;
+
+use crate::pairs::Edit;
+use crate::wrap;
+
+/// Whether the byte `range` of `content` touches verse.
+pub fn in_verse(content: &str, range: Range<usize>) -> bool {
+    let first = content[..range.start].matches('\n').count();
+    let last = first + content[range].matches('\n').count();
+    verses(content)
+        .iter()
+        .any(|verse| verse.lines.start <= last && first < verse.lines.end)
+}
+
+/// Change of `content` turning the verse at the byte `selection` back to
+/// prose, removing its markers, or else the paragraphs it touches into
+/// verse.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::verse::toggle_verse;
+///
+/// let content = "Prose.\n\nRoses are red,\nviolets are blue.\n\nMore prose.";
+/// let edit = toggle_verse(content, 10..10).unwrap();
+/// assert_eq!(
+///     edit.text,
+///     "<!-- verse -->\nRoses are red,\nviolets are blue.\n<!-- /verse -->"
+/// );
+/// ```
+pub fn toggle_verse(content: &str, selection: Range<usize>) -> Option<Edit> {
+    let line_start = |line: usize| {
+        content
+            .split_inclusive('\n')
+            .take(line)
+            .map(str::len)
+            .sum::<usize>()
+    };
+    let line = content[..selection.start].matches('\n').count();
+
+    if let Some(verse) = verses(content)
+        .into_iter()
+        .find(|verse| verse.lines.contains(&line))
+    {
+        let range = line_start(verse.lines.start)..line_start(verse.lines.end);
+        let marker = line_start(verse.lines.start + 1) - range.start;
+        let text: String = content[range.clone()]
+            .split_inclusive('\n')
+            .filter(|line| parse_marker(line).is_none())
+            .collect();
+        let caret = selection
+            .start
+            .saturating_sub(marker)
+            .clamp(range.start, range.start + text.len());
+        return Some(Edit {
+            range,
+            text,
+            selection: caret..caret,
+        });
+    }
+
+    let range = wrap::paragraphs_around(content, selection.clone());
+    if content[range.clone()].trim().is_empty() {
+        return None;
+    }
+    let text = format!(
+        "{}\n{}\n{}",
+        VERSE_MARKER,
+        &content[range.clone()],
+        VERSE_END_MARKER
+    );
+    let caret = selection.start + VERSE_MARKER.len() + 1;
+    Some(Edit {
+        range,
+        text,
+        selection: caret..caret,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(content: &str, edit: &Edit) -> String {
+        let mut content = content.to_string();
+        content.replace_range(edit.range.clone(), &edit.text);
+        content
+    }
+
+    #[test]
+    fn test_toggle_verse_back_and_forth() {
+        let prose = "Prose.\n\nRoses are red,\n  violets are blue.\n\nMore prose.\n";
+        let caret = prose.find("violets").unwrap();
+        let edit = toggle_verse(prose, caret..caret).unwrap();
+        let verse = apply(prose, &edit);
+        assert_eq!(
+            verse,
+            "Prose.\n\n<!-- verse -->\nRoses are red,\n  violets are blue.\n<!-- /verse -->\n\nMore prose.\n"
+        );
+        assert_eq!(edit.selection.start, verse.find("violets").unwrap());
+        assert!(in_verse(&verse, edit.selection.clone()));
+        assert!(!in_verse(&verse, 0..3));
+
+        let edit = toggle_verse(&verse, edit.selection.clone()).unwrap();
+        assert_eq!(apply(&verse, &edit), prose);
+        assert_eq!(edit.selection.start, caret);
+
+        assert_eq!(toggle_verse("\n\n", 1..1), None);
+    }
+}
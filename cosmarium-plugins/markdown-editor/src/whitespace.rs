@@ -0,0 +1,56 @@
+//! # Whitespace visualization
+//!
+//! Shows the spaces and tabs of the text being edited, as marks drawn over
+//! the text, for writers tracking down double spaces or stray tabs. No-break
+//! spaces, common before punctuation in French, get a mark of their own.
+
+use egui::{Align2, Color32, FontId, Galley, Painter, Pos2};
+
+/// Shared state key (`bool`) of whether whitespace is shown, set by the
+/// application.
+pub const SHOW_WHITESPACE_KEY: &str = "markdown_editor_show_whitespace";
+
+/// Mark shown over a whitespace character, if it has one.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::whitespace::mark;
+///
+/// assert_eq!(mark(' '), Some('·'));
+/// assert_eq!(mark('\t'), Some('→'));
+/// assert_eq!(mark('a'), None);
+/// ```
+pub fn mark(c: char) -> Option<char> {
+    match c {
+        ' ' => Some('·'),
+        '\t' => Some('→'),
+        '\u{a0}' | '\u{202f}' => Some('°'),
+        _ => None,
+    }
+}
+
+/// Draw the marks of the whitespace of `galley`, laid out at `galley_pos`,
+/// on the rows visible through `painter`.
+pub fn paint(
+    painter: &Painter,
+    galley: &Galley,
+    galley_pos: Pos2,
+    font_id: &FontId,
+    color: Color32,
+) {
+    let clip = painter.clip_rect();
+    for row in &galley.rows {
+        let row_pos = galley_pos + row.pos.to_vec2();
+        if !clip.intersects(row.rect().translate(galley_pos.to_vec2())) {
+            continue;
+        }
+        for glyph in &row.glyphs {
+            if let Some(mark) = mark(glyph.chr) {
+                let center =
+                    row_pos + egui::vec2(glyph.pos.x + glyph.advance_width / 2.0, row.size.y / 2.0);
+                painter.text(center, Align2::CENTER_CENTER, mark, font_id.clone(), color);
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! # Wiki links
+//!
+//! `[[Entry]]` links to the worldbuilding entries of the project (see
+//! [`cosmarium_plugin_api::wiki`]) are underlined in the editor: in the
+//! link color when they resolve to an entry, in the warning color when the
+//! entry is missing. Ctrl+click on a link, or its context menu, opens the
+//! entry in the wiki panel.
+
+use cosmarium_plugin_api::wiki::{links, LinkResolver};
+use egui::{text::CCursor, Color32, Galley, Painter, Pos2, Stroke};
+
+/// Draw the underlines of the links of `content`, laid out in `galley` at
+/// `galley_pos`.
+///
+/// Links resolve with `resolver`; without one, as when the wiki plugin is
+/// not loaded, they are all drawn as resolved.
+pub fn paint(
+    painter: &Painter,
+    content: &str,
+    galley: &Galley,
+    galley_pos: Pos2,
+    resolver: Option<&LinkResolver>,
+    colors: (Color32, Color32),
+) {
+    let (resolved, missing) = colors;
+    let clip = painter.clip_rect();
+    for link in links(content) {
+        let color = match resolver {
+            Some(resolver) if resolver.resolve(&link.target).is_none() => missing,
+            _ => resolved,
+        };
+        let stroke = Stroke::new(1.0, color);
+        let char_at = |byte: usize| CCursor::new(content[..byte].chars().count());
+        let start = galley
+            .pos_from_cursor(char_at(link.range.start))
+            .translate(galley_pos.to_vec2());
+        let end = galley
+            .pos_from_cursor(char_at(link.range.end))
+            .translate(galley_pos.to_vec2());
+        if !clip.intersects(start.union(end)) {
+            continue;
+        }
+
+        if (start.bottom() - end.bottom()).abs() < 1.0 {
+            painter.hline(start.left()..=end.left(), start.bottom(), stroke);
+        } else {
+            // Wrapped over two rows
+            let right = galley_pos.x + galley.rect.right();
+            painter.hline(start.left()..=right, start.bottom(), stroke);
+            painter.hline(galley_pos.x..=end.left(), end.bottom(), stroke);
+        }
+    }
+}
@@ -0,0 +1,381 @@
+//! # Hard wrapping of Markdown prose
+//!
+//! Writers exchanging text by e-mail or on newsgroups keep their lines under
+//! a fixed width. [`hard_wrap`] breaks the lines longer than a column, as
+//! done on save; [`reflow`] fills paragraphs up to the column again after
+//! they were edited, joining their lines.
+//!
+//! Both keep the Markdown structure: blockquote markers are repeated on
+//! continuation lines, list items go on under their text, a hard line break
+//! stays one, and front matter, code, verse, headings, tables and HTML are
+//! left as they are. A line is never broken before a word that would start a list
+//! item, a quote or a heading, and words longer than the column (such as
+//! URLs) stay whole.
+
+use cosmarium_plugin_api::verse;
+use std::ops::Range;
+
+/// Shared state key (`usize`) of the column of the line-length guide and of
+/// reflowed paragraphs, set by the application (0 for none).
+pub const WRAP_COLUMN_KEY: &str = "markdown_editor_wrap_column";
+
+/// Column of reflowed paragraphs when no wrap column is set, the usual width
+/// of e-mail.
+pub const DEFAULT_COLUMN: usize = 72;
+
+/// Break the lines of `text` longer than `column` characters.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::wrap::hard_wrap;
+///
+/// let text = "> She closed the door behind her and listened.\n";
+/// assert_eq!(
+///     hard_wrap(text, 24),
+///     "> She closed the door\n> behind her and\n> listened.\n"
+/// );
+/// ```
+pub fn hard_wrap(text: &str, column: usize) -> String {
+    let mut wrapped = Vec::new();
+    for (line, verbatim) in scan(text) {
+        if verbatim || width(line) <= column {
+            wrapped.push(line.to_string());
+            continue;
+        }
+        let prefix = Prefix::of(line);
+        let (body, hard_break) = split_hard_break(&line[prefix.len..]);
+        let mut lines = fill(
+            &line[..prefix.len],
+            &prefix.continuation,
+            body.split_whitespace(),
+            column,
+        );
+        if let (Some(last), Some(hard_break)) = (lines.last_mut(), hard_break) {
+            last.push_str(hard_break);
+        }
+        wrapped.extend(lines);
+    }
+    join_lines(text, wrapped)
+}
+
+/// Fill the paragraphs of `text` up to `column` characters, joining their
+/// lines.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_markdown_editor::wrap::reflow;
+///
+/// let text = "- The rain had\n  stopped.\n- Nobody\nanswered the door.";
+/// assert_eq!(
+///     reflow(text, 30),
+///     "- The rain had stopped.\n- Nobody answered the door."
+/// );
+/// ```
+pub fn reflow(text: &str, column: usize) -> String {
+    let mut reflowed = Vec::new();
+    let mut paragraph: Option<Paragraph> = None;
+    for (line, verbatim) in scan(text) {
+        let prefix = Prefix::of(line);
+        let (body, hard_break) = split_hard_break(&line[prefix.len..]);
+        let ends_paragraph = verbatim || body.trim().is_empty();
+        let starts_item = prefix.list_item
+            || paragraph
+                .as_ref()
+                .is_some_and(|paragraph| paragraph.hard_break.is_some());
+
+        if ends_paragraph || starts_item {
+            if let Some(paragraph) = paragraph.take() {
+                reflowed.extend(paragraph.fill(column));
+            }
+        }
+        if ends_paragraph {
+            reflowed.push(line.to_string());
+            continue;
+        }
+        let paragraph = paragraph.get_or_insert_with(|| Paragraph {
+            first_prefix: line[..prefix.len].to_string(),
+            continuation: prefix.continuation.clone(),
+            words: Vec::new(),
+            hard_break: None,
+        });
+        paragraph.words.extend(body.split_whitespace());
+        paragraph.hard_break = hard_break;
+    }
+    if let Some(paragraph) = paragraph {
+        reflowed.extend(paragraph.fill(column));
+    }
+    join_lines(text, reflowed)
+}
+
+/// Byte range of the paragraphs overlapping `range` in `text`, from the
+/// blank line before the first one to the blank line after the last one.
+pub fn paragraphs_around(text: &str, range: Range<usize>) -> Range<usize> {
+    let mut start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    while start > 0 {
+        let previous = text[..start - 1].rfind('\n').map_or(0, |i| i + 1);
+        if text[previous..start - 1].trim().is_empty() {
+            break;
+        }
+        start = previous;
+    }
+
+    let mut end = text[range.end..]
+        .find('\n')
+        .map_or(text.len(), |i| range.end + i);
+    while end < text.len() {
+        let next = text[end + 1..]
+            .find('\n')
+            .map_or(text.len(), |i| end + 1 + i);
+        if text[end + 1..next].trim().is_empty() {
+            break;
+        }
+        end = next;
+    }
+    start..end
+}
+
+/// A paragraph being reflowed.
+struct Paragraph<'a> {
+    /// Text before the words of its first line
+    first_prefix: String,
+    /// Text before the words of the following lines
+    continuation: String,
+    words: Vec<&'a str>,
+    /// Hard line break ending it, if any
+    hard_break: Option<&'a str>,
+}
+
+impl Paragraph<'_> {
+    fn fill(self, column: usize) -> Vec<String> {
+        let mut lines = fill(
+            &self.first_prefix,
+            &self.continuation,
+            self.words.into_iter(),
+            column,
+        );
+        if let (Some(last), Some(hard_break)) = (lines.last_mut(), self.hard_break) {
+            last.push_str(hard_break);
+        }
+        lines
+    }
+}
+
+/// How a line starts: indentation, blockquote markers and list marker.
+struct Prefix {
+    /// Length of the prefix, in bytes
+    len: usize,
+    /// Prefix of the lines continuing this one
+    continuation: String,
+    /// Whether the line starts a list item
+    list_item: bool,
+}
+
+impl Prefix {
+    fn of(line: &str) -> Self {
+        let mut rest = line.trim_start_matches([' ', '\t']);
+        let mut continuation = line[..line.len() - rest.len()].to_string();
+        while let Some(after) = rest.strip_prefix('>') {
+            let after = after.strip_prefix(' ').unwrap_or(after);
+            continuation.push_str(&rest[..rest.len() - after.len()]);
+            rest = after;
+        }
+        let marker = list_marker(rest);
+        if let Some(marker) = marker {
+            continuation.push_str(&" ".repeat(marker));
+            rest = &rest[marker..];
+        }
+        Self {
+            len: line.len() - rest.len(),
+            continuation,
+            list_item: marker.is_some(),
+        }
+    }
+}
+
+/// Length of the list marker starting `text`, with the spaces after it.
+fn list_marker(text: &str) -> Option<usize> {
+    let digits = text.bytes().take_while(u8::is_ascii_digit).count();
+    let marker = match text.as_bytes().get(digits) {
+        Some(b'.' | b')') if (1..10).contains(&digits) => digits + 1,
+        Some(b'-' | b'*' | b'+') if digits == 0 => 1,
+        _ => return None,
+    };
+    let spaces = text[marker..].len() - text[marker..].trim_start_matches(' ').len();
+    (spaces > 0 && !text[marker..].trim().is_empty()).then_some(marker + spaces)
+}
+
+/// Whether a line starting with `word` would start a list item, a quote or
+/// a heading.
+fn starts_block(word: &str) -> bool {
+    word.starts_with(['>', '#']) || list_marker(&format!("{} x", word)) == Some(word.len() + 1)
+}
+
+/// Lay `words` out in lines of at most `column` characters.
+fn fill<'a>(
+    first_prefix: &str,
+    continuation: &str,
+    words: impl Iterator<Item = &'a str>,
+    column: usize,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = first_prefix.to_string();
+    let mut line_width = width(&line);
+    let mut empty = true;
+    for word in words {
+        let word_width = width(word);
+        if !empty && line_width + 1 + word_width > column && !starts_block(word) {
+            lines.push(std::mem::replace(&mut line, continuation.to_string()));
+            line_width = width(continuation);
+            empty = true;
+        }
+        if !empty {
+            line.push(' ');
+            line_width += 1;
+        }
+        line.push_str(word);
+        line_width += word_width;
+        empty = false;
+    }
+    lines.push(line);
+    lines
+}
+
+/// Split the hard line break (two trailing spaces or a backslash) off the
+/// end of a line.
+fn split_hard_break(body: &str) -> (&str, Option<&str>) {
+    if let Some(body) = body.strip_suffix('\\') {
+        (body, Some("\\"))
+    } else if body.ends_with("  ") && !body.trim().is_empty() {
+        (body.trim_end(), Some("  "))
+    } else {
+        (body, None)
+    }
+}
+
+/// Lines of `text`, with whether they must be kept as they are.
+fn scan(text: &str) -> Vec<(&str, bool)> {
+    let mut lines = Vec::new();
+    let mut front_matter = text.starts_with("---\n") || text.starts_with("---\r\n");
+    let mut fence: Option<&str> = None;
+    let mut in_verse = false;
+    let mut after_blank = true;
+    for (i, line) in text.lines().enumerate() {
+        let prefix = Prefix::of(line);
+        let body = line[prefix.len..].trim_end();
+        let verbatim = if front_matter {
+            front_matter = i == 0 || !matches!(body, "---" | "...");
+            true
+        } else if let Some(marker) = fence {
+            if body.starts_with(marker) {
+                fence = None;
+            }
+            true
+        } else if body.starts_with("```") || body.starts_with("~~~") {
+            fence = Some(&body[..3]);
+            true
+        } else if let Some(start) = verse::parse_marker(body) {
+            in_verse = start;
+            true
+        } else if in_verse {
+            true
+        } else {
+            let indented = line[..line.len() - line.trim_start().len()]
+                .replace('\t', "    ")
+                .len()
+                >= 4;
+            // Indented code cannot interrupt a paragraph
+            (indented && after_blank && !prefix.list_item)
+                || body.starts_with(['#', '|', '<'])
+                || is_rule(line.trim_start_matches(['>', ' ', '\t']))
+        };
+        after_blank = body.is_empty();
+        lines.push((line, verbatim));
+    }
+    lines
+}
+
+/// Whether `line` is a thematic break such as `***` or `- - -`, or the
+/// underline of a heading.
+pub(crate) fn is_rule(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    match marks.first() {
+        Some('=') => marks.iter().all(|c| *c == '='),
+        Some('-' | '*' | '_') => marks.len() >= 3 && marks.iter().all(|c| *c == marks[0]),
+        _ => false,
+    }
+}
+
+/// Join `lines` back, ending with a newline if `text` did.
+fn join_lines(text: &str, lines: Vec<String>) -> String {
+    let mut joined = lines.join("\n");
+    if text.ends_with('\n') {
+        joined.push('\n');
+    }
+    joined
+}
+
+/// Width of `text`, in characters.
+fn width(text: &str) -> usize {
+    text.chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hard_wrap_keeps_markdown_structure() {
+        let text = "---\ntitle: A very long title that must not be wrapped at all\n---\n\
+                    # A heading far longer than the column of the text\n\
+                    \n\
+                    1. The first item of the list is long.  \n\
+                    Then - what a surprise - it went on.\n\
+                    \n\
+                    ```\nlet code = \"longer than the column, as it is\";\n```\n\
+                    <!-- verse -->\nA line of verse longer than the column\n";
+        assert_eq!(
+            hard_wrap(text, 20),
+            "---\ntitle: A very long title that must not be wrapped at all\n---\n\
+             # A heading far longer than the column of the text\n\
+             \n\
+             1. The first item of\n   the list is long.  \n\
+             Then - what a\nsurprise - it went\non.\n\
+             \n\
+             ```\nlet code = \"longer than the column, as it is\";\n```\n\
+             <!-- verse -->\nA line of verse longer than the column\n"
+        );
+    }
+
+    #[test]
+    fn test_reflow_joins_lines() {
+        let text = "> It was\n> late.\n>\n> The inn was\n> closed, and the rain had not stopped since noon.";
+        assert_eq!(
+            reflow(text, 30),
+            "> It was late.\n>\n> The inn was closed, and the\n> rain had not stopped since\n> noon."
+        );
+
+        // Hard line breaks stay
+        assert_eq!(
+            reflow("Roses are red,\\\nviolets\nare blue", 40),
+            "Roses are red,\\\nviolets are blue"
+        );
+        // Overlong words are not broken
+        assert_eq!(
+            reflow("See https://example.com/a/long/path", 10),
+            "See\nhttps://example.com/a/long/path"
+        );
+    }
+
+    #[test]
+    fn test_paragraphs_around() {
+        let text = "One.\n\nTwo\nlines.\nThree.\n\nFour.";
+        let start = text.find("lines").unwrap();
+        let range = paragraphs_around(text, start..start);
+        assert_eq!(&text[range], "Two\nlines.\nThree.");
+
+        let range = paragraphs_around(text, 0..text.len());
+        assert_eq!(&text[range], text);
+    }
+}
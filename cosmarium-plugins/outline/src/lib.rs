@@ -1,28 +1,93 @@
+use cosmarium_plugin_api::metadata::{NodeMetadata, ACTIVE_METADATA_KEY};
+use cosmarium_plugin_api::scene::{
+    parse_scenes, Scene, SceneHeadingFormat, SCENE_HEADING_FORMAT_KEY,
+};
 use cosmarium_plugin_api::{
-    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+    EventType, PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+    Subscription, SESSION_STATE_KEY,
 };
 use egui::Ui;
 use pulldown_cmark::{Event, Options, Parser, Tag};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tree::{Fold, FOLD_REQUEST};
+
+mod sections;
+pub mod tree;
+
+/// Name of the plugin, under which its session state is kept.
+const PLUGIN_NAME: &str = "outline";
+
+/// Shared state key (`Vec<(u32, String, usize)>`) under which the headers
+/// are published, for the editor's heading picker.
+const OUTLINE_HEADINGS_KEY: &str = "outline_headings";
+
+/// Shared state key (`Option<(PathBuf, usize)>`) of the file and line of the
+/// editor's caret, published by the editor when the document is saved.
+const CURSOR_LOCATION_KEY: &str = "editor_cursor_location";
+
+/// Deepest level the outline can be collapsed to from a command.
+const FOLD_COMMAND_LEVELS: u32 = 3;
+
+/// Header dragged in the outline, by index, to move its section.
+struct DraggedHeader(usize);
+
+/// Width of the button collapsing or expanding a header.
+const TOGGLE_WIDTH: f32 = 14.0;
+
+/// Color of the word count of a section that reached its target.
+const TARGET_REACHED_COLOR: egui::Color32 = egui::Color32::from_rgb(90, 170, 100);
 
 pub struct OutlinePlugin {
     /// Cached headers: (level, text, line_number)
     headers: Vec<(u32, String, usize)>,
-    /// Last content hash to detect changes
-    last_content_hash: u64,
-    /// Manually expanded nodes (by header index or similar stable ID)
-    expanded_nodes: HashSet<usize>,
+    /// Content the headers were parsed from
+    content: String,
+    /// Words of the content
+    content_words: usize,
+    /// Counts the words of the sections as the content changes
+    counter: sections::SectionCounter,
+    /// Words of the section of each header
+    words: Vec<sections::SectionWords>,
+    /// Targets of the sections set in the front matter, by header text
+    targets: BTreeMap<String, usize>,
+    /// Set when the editor reports a content change
+    content_dirty: Arc<AtomicBool>,
+    /// Subscription to document change events
+    subscription: Option<Subscription>,
+    /// Keys of the headers whose subsections are hidden
+    collapsed: BTreeSet<String>,
+    /// File of the document outlined, if saved
+    document: Option<PathBuf>,
+    /// Headers collapsed in each saved document
+    session: tree::OutlineSession,
     /// Current active header index (based on cursor)
     active_header_index: Option<usize>,
+    /// Scene heading convention of the project
+    scene_format: Option<SceneHeadingFormat>,
+    /// Scenes whose heading follows the convention
+    scenes: Vec<Scene>,
 }
 
 impl Default for OutlinePlugin {
     fn default() -> Self {
         Self {
             headers: Vec::new(),
-            last_content_hash: 0,
-            expanded_nodes: HashSet::new(),
+            content: String::new(),
+            content_words: 0,
+            counter: sections::SectionCounter::default(),
+            words: Vec::new(),
+            targets: BTreeMap::new(),
+            content_dirty: Arc::new(AtomicBool::new(true)),
+            subscription: None,
+            collapsed: BTreeSet::new(),
+            document: None,
+            session: tree::OutlineSession::default(),
             active_header_index: None,
+            scene_format: None,
+            scenes: Vec::new(),
         }
     }
 }
@@ -34,6 +99,7 @@ impl OutlinePlugin {
 
     fn parse_headers(&mut self, content: &str) {
         self.headers.clear();
+        self.content = content.to_string();
 
         // We need line numbers. pulldown-cmark provides byte offsets.
         // We can build a line index map.
@@ -76,19 +142,63 @@ impl OutlinePlugin {
             }
         }
 
-        // Update hash
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-        let mut hasher = DefaultHasher::new();
-        content.hash(&mut hasher);
-        self.last_content_hash = hasher.finish();
+        self.words = self.counter.count(content, &self.headers);
+        self.targets = sections::front_matter_targets(content);
+        self.content_words = sections::word_count(content);
+
+        self.scenes = self
+            .scene_format
+            .as_ref()
+            .map(|format| parse_scenes(content, format))
+            .unwrap_or_default();
+    }
+
+    /// Collapse the headers of `collapsed` only, remembering them for the
+    /// document.
+    fn set_collapsed(&mut self, ctx: &mut PluginContext, collapsed: BTreeSet<String>) {
+        self.collapsed = collapsed;
+        let Some(path) = &self.document else {
+            return;
+        };
+        if self.session.set_collapsed(path, &self.collapsed) {
+            match serde_json::to_value(&self.session) {
+                Ok(state) => ctx.set_plugin_data(PLUGIN_NAME, SESSION_STATE_KEY, state),
+                Err(e) => tracing::warn!("Cannot keep the outline session state: {}", e),
+            }
+        }
+    }
+
+    /// Level buttons collapsing the outline, and the button expanding it.
+    fn render_fold_bar(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        let deepest = self.headers.iter().map(|(level, _, _)| *level).max();
+        let Some(deepest) = deepest.filter(|deepest| *deepest > 1) else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 2.0;
+            for level in 1..deepest {
+                if ui
+                    .small_button(format!("H{}", level))
+                    .on_hover_text(format!("Show headers down to level {} only", level))
+                    .clicked()
+                {
+                    let collapsed = tree::collapse_to(&self.headers, level);
+                    self.set_collapsed(ctx, collapsed);
+                }
+            }
+            let expand =
+                ui.add_enabled(!self.collapsed.is_empty(), egui::Button::new("All").small());
+            if expand.on_hover_text("Show every header").clicked() {
+                self.set_collapsed(ctx, BTreeSet::new());
+            }
+        });
     }
 }
 
 impl Plugin for OutlinePlugin {
     fn info(&self) -> PluginInfo {
         PluginInfo::new(
-            "outline",
+            PLUGIN_NAME,
             "0.1.0",
             "Document outline view",
             "Cosmarium Team",
@@ -96,7 +206,41 @@ impl Plugin for OutlinePlugin {
         .with_dependency("markdown-editor")
     }
 
-    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+    fn initialize(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        if let Some(state) =
+            ctx.get_plugin_data::<serde_json::Value>(PLUGIN_NAME, SESSION_STATE_KEY)
+        {
+            match serde_json::from_value(state) {
+                Ok(session) => self.session = session,
+                Err(e) => tracing::warn!("Ignoring the outline session state: {}", e),
+            }
+        }
+
+        ctx.register_command(
+            "outline.expand_all",
+            "Expand Outline",
+            None,
+            |ctx: &mut PluginContext| ctx.set_shared_state(FOLD_REQUEST, Some(Fold::ExpandAll)),
+        );
+        for level in 1..=FOLD_COMMAND_LEVELS {
+            ctx.register_command(
+                format!("outline.collapse_to_h{}", level),
+                format!("Collapse Outline to H{}", level),
+                None,
+                move |ctx: &mut PluginContext| {
+                    ctx.set_shared_state(FOLD_REQUEST, Some(Fold::CollapseTo(level)))
+                },
+            );
+        }
+
+        let dirty = Arc::clone(&self.content_dirty);
+        self.subscription = Some(ctx.subscribe(
+            EventType::DocumentChanged,
+            move |_: &cosmarium_plugin_api::Event| {
+                dirty.store(true, Ordering::Relaxed);
+                Ok(())
+            },
+        ));
         Ok(())
     }
 
@@ -123,20 +267,43 @@ impl PanelPlugin for OutlinePlugin {
     }
 
     fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
-        // Check for content updates
-        if let Some(content) = ctx.get_shared_state::<String>("markdown_editor_content") {
-            // tracing::info!("Outline received content update, length: {}", content.len());
-            let mut hasher = std::collections::hash_map::DefaultHasher::new();
-            use std::hash::{Hash, Hasher};
-            content.hash(&mut hasher);
-            let new_hash = hasher.finish();
-
-            if new_hash != self.last_content_hash {
-                tracing::info!("Outline parsing new content");
+        let scene_format = ctx.get_config::<SceneHeadingFormat>(SCENE_HEADING_FORMAT_KEY);
+        if scene_format != self.scene_format {
+            self.scene_format = scene_format;
+            self.content_dirty.store(true, Ordering::Relaxed);
+        }
+
+        // Reparse only after the editor reported a change
+        if self.content_dirty.load(Ordering::Relaxed) {
+            if let Some(content) = ctx.get_shared_state::<String>("markdown_editor_content") {
+                tracing::debug!("Outline parsing new content");
+                self.content_dirty.store(false, Ordering::Relaxed);
                 self.parse_headers(&content);
+                ctx.set_shared_state(OUTLINE_HEADINGS_KEY, self.headers.clone());
+            }
+        }
+
+        // Headers collapsed in the document when it was last outlined
+        if let Some(location) =
+            ctx.get_shared_state::<Option<(PathBuf, usize)>>(CURSOR_LOCATION_KEY)
+        {
+            let document = location.map(|(path, _)| path);
+            if document != self.document {
+                self.collapsed = document
+                    .as_deref()
+                    .map(|path| self.session.collapsed(path))
+                    .unwrap_or_default();
+                self.document = document;
             }
-        } else {
-            // tracing::warn!("Outline: No content in shared state");
+        }
+
+        if let Some(fold) = ctx.get_shared_state::<Option<Fold>>(FOLD_REQUEST).flatten() {
+            ctx.set_shared_state::<Option<Fold>>(FOLD_REQUEST, None);
+            let collapsed = match fold {
+                Fold::ExpandAll => BTreeSet::new(),
+                Fold::CollapseTo(level) => tree::collapse_to(&self.headers, level),
+            };
+            self.set_collapsed(ctx, collapsed);
         }
 
         // Check for cursor updates
@@ -162,15 +329,63 @@ impl PanelPlugin for OutlinePlugin {
             return;
         }
 
+        // Words of the document, against its target if it has one
+        let target = ctx
+            .get_shared_state::<Option<NodeMetadata>>(ACTIVE_METADATA_KEY)
+            .flatten()
+            .and_then(|node| node.metadata.target_words);
+        ui.weak(match target {
+            Some(target) => format!("{} / {} words", self.content_words, target),
+            None => format!("{} words", self.content_words),
+        });
+
+        self.render_fold_bar(ui, ctx);
+
+        let visible = tree::visible(&self.headers, &self.collapsed);
+        let keys = tree::header_keys(&self.headers);
+        // The caret in a collapsed section marks the header of the section
+        let active = self
+            .active_header_index
+            .and_then(|active| (0..=active).rev().find(|i| visible.get(*i) == Some(&true)));
+
+        // Section dragged onto another header, moved before it, or past the
+        // last one, moved to the end
+        let mut moved: Option<(usize, Option<usize>)> = None;
+        // Header collapsed or expanded, by key
+        let mut toggled: Option<String> = None;
+        // Level whose headers are all collapsed (true) or expanded (false)
+        let mut level_fold: Option<(u32, bool)> = None;
+
         egui::ScrollArea::vertical().show(ui, |ui| {
-            // Simple indentation-based rendering for now (placeholder for egui_ltreeview)
             for (i, (level, text, line)) in self.headers.iter().enumerate() {
+                if !visible[i] {
+                    continue;
+                }
                 let indent = (*level as f32 - 1.0) * 10.0;
 
-                let is_active = self.active_header_index == Some(i);
+                let is_active = active == Some(i);
 
-                ui.horizontal(|ui| {
+                let row = ui.horizontal(|ui| {
                     ui.add_space(indent);
+                    if tree::has_children(&self.headers, i) {
+                        let collapsed = self.collapsed.contains(&keys[i]);
+                        let (icon, hint) = if collapsed {
+                            ("▶", "Show the subsections")
+                        } else {
+                            ("▼", "Hide the subsections")
+                        };
+                        let toggle =
+                            egui::Button::new(egui::RichText::new(icon).small()).frame(false);
+                        if ui
+                            .add_sized([TOGGLE_WIDTH, TOGGLE_WIDTH], toggle)
+                            .on_hover_text(hint)
+                            .clicked()
+                        {
+                            toggled = Some(keys[i].clone());
+                        }
+                    } else {
+                        ui.add_space(TOGGLE_WIDTH + ui.spacing().item_spacing.x);
+                    }
                     let label = if is_active {
                         egui::RichText::new(text)
                             .strong()
@@ -179,17 +394,118 @@ impl PanelPlugin for OutlinePlugin {
                         egui::RichText::new(text)
                     };
 
-                    if ui
-                        .add(egui::Label::new(label).sense(egui::Sense::click()))
+                    let response = ui
+                        .add(egui::Label::new(label).sense(egui::Sense::click_and_drag()))
                         .on_hover_cursor(egui::CursorIcon::PointingHand)
-                        .clicked()
-                    {
+                        .on_hover_text("Drag onto another header to move the section before it");
+                    response.dnd_set_drag_payload(DraggedHeader(i));
+                    response.context_menu(|ui| {
+                        if ui.button(format!("Collapse All H{}", level)).clicked() {
+                            level_fold = Some((*level, true));
+                            ui.close();
+                        }
+                        if ui.button(format!("Expand All H{}", level)).clicked() {
+                            level_fold = Some((*level, false));
+                            ui.close();
+                        }
+                    });
+                    if response.clicked() {
                         // Navigate to line
                         ctx.set_shared_state("markdown_editor_goto_line", *line);
                         self.active_header_index = Some(i);
                     }
+
+                    if let Some(words) = self.words.get(i) {
+                        let target = self.targets.get(text);
+                        let count = match target {
+                            Some(target) => format!("{} / {}", words.total, target),
+                            None => words.total.to_string(),
+                        };
+                        let count = if target.is_some_and(|target| words.total >= *target) {
+                            egui::RichText::new(count)
+                                .small()
+                                .color(TARGET_REACHED_COLOR)
+                        } else {
+                            egui::RichText::new(count).small().weak()
+                        };
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.label(count).on_hover_text(format!(
+                                "{} words of its own, {} with its subsections",
+                                words.own, words.total
+                            ));
+                        });
+                    }
                 });
+                if let Some(dragged) = drop_target(ui, &row.response) {
+                    moved = Some((dragged, Some(i)));
+                }
+
+                // Metadata read from a scene heading
+                if let Some(scene) = self.scenes.iter().find(|scene| scene.line == *line) {
+                    let fields: Vec<String> = self
+                        .scene_format
+                        .iter()
+                        .flat_map(SceneHeadingFormat::fields)
+                        .filter_map(|name| {
+                            scene
+                                .field(name)
+                                .map(|value| format!("{}: {}", name, value))
+                        })
+                        .collect();
+                    ui.horizontal(|ui| {
+                        ui.add_space(indent + TOGGLE_WIDTH + ui.spacing().item_spacing.x);
+                        ui.label(egui::RichText::new(fields.join(" · ")).small().weak());
+                    });
+                }
+            }
+
+            if egui::DragAndDrop::has_payload_of_type::<DraggedHeader>(ui.ctx()) {
+                let (_, end) = ui.allocate_exact_size(
+                    egui::vec2(ui.available_width(), 16.0),
+                    egui::Sense::hover(),
+                );
+                if let Some(dragged) = drop_target(ui, &end) {
+                    moved = Some((dragged, None));
+                }
             }
         });
+
+        if let Some(key) = toggled {
+            let mut collapsed = self.collapsed.clone();
+            if !collapsed.remove(&key) {
+                collapsed.insert(key);
+            }
+            self.set_collapsed(ctx, collapsed);
+        }
+        if let Some((level, collapse)) = level_fold {
+            let keys = tree::level_keys(&self.headers, level);
+            let collapsed = if collapse {
+                self.collapsed.union(&keys).cloned().collect()
+            } else {
+                self.collapsed.difference(&keys).cloned().collect()
+            };
+            self.set_collapsed(ctx, collapsed);
+        }
+
+        if let Some((from, before)) = moved {
+            match sections::move_section(&self.content, &self.headers, from, before) {
+                Some(transaction) => {
+                    transaction.commit(ctx);
+                }
+                None => tracing::debug!("Outline section {} left in place", from),
+            }
+        }
     }
 }
+
+/// Mark `target` as the place a dragged section goes before, returning the
+/// index of the header dropped on it.
+fn drop_target(ui: &Ui, target: &egui::Response) -> Option<usize> {
+    target.dnd_hover_payload::<DraggedHeader>()?;
+    let rect = target.rect;
+    ui.painter()
+        .hline(rect.x_range(), rect.top(), ui.visuals().selection.stroke);
+    target
+        .dnd_release_payload::<DraggedHeader>()
+        .map(|dragged| dragged.0)
+}
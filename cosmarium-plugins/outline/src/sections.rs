@@ -0,0 +1,282 @@
+//! Sections of a document, for counting their words and reordering them
+//! from the outline.
+//!
+//! The section of a header runs from the start of its line to the next
+//! header of the same or a higher level, so that its subsections move with
+//! it. Moving a section is a single [`EditTransaction`], undone in one step.
+//!
+//! The text of a section before its first subsection is its own; its total
+//! adds up the words of its subsections, for a chapter to show those of its
+//! scenes. A [`SectionCounter`] only counts again the text that changed.
+//! Targets for sections are set in the front matter, by header:
+//!
+//! ```markdown
+//! ---
+//! targets:
+//!   The Inn: 3000
+//!   Night Falls: 1200
+//! ---
+//! ```
+
+use cosmarium_plugin_api::grammar::text_hash;
+use cosmarium_plugin_api::transaction::EditTransaction;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+/// Front matter key of the targets of the sections.
+const TARGETS_KEY: &str = "targets";
+
+/// Words of a section.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SectionWords {
+    /// Words of its own text, up to its first subsection
+    pub own: usize,
+    /// Words of the whole section, subsections included
+    pub total: usize,
+}
+
+/// Counts the words of the sections of a document, keeping the count of
+/// each text to count only the texts that changed since.
+#[derive(Debug, Default)]
+pub struct SectionCounter {
+    /// Words by hash of the text counted
+    counted: HashMap<u64, usize>,
+}
+
+impl SectionCounter {
+    /// Words of the sections of `content`, one per header of `headers`.
+    pub fn count(&mut self, content: &str, headers: &[(u32, String, usize)]) -> Vec<SectionWords> {
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let start_of = |line: usize| {
+            line_starts
+                .get(line.saturating_sub(1))
+                .copied()
+                .unwrap_or(content.len())
+        };
+
+        let mut counted = HashMap::new();
+        let own: Vec<usize> = headers
+            .iter()
+            .enumerate()
+            .map(|(i, (_, _, line))| {
+                // The text under the header line, up to the next header
+                let start = line_starts.get(*line).copied().unwrap_or(content.len());
+                let end = headers
+                    .get(i + 1)
+                    .map_or(content.len(), |(_, _, next)| start_of(*next));
+                let text = content.get(start..end.max(start)).unwrap_or_default();
+                let hash = text_hash(text);
+                let words = self
+                    .counted
+                    .get(&hash)
+                    .copied()
+                    .unwrap_or_else(|| word_count(text));
+                counted.insert(hash, words);
+                words
+            })
+            .collect();
+        self.counted = counted;
+
+        headers
+            .iter()
+            .enumerate()
+            .map(|(i, (level, _, _))| {
+                let subsections = headers[i + 1..]
+                    .iter()
+                    .take_while(|(next, _, _)| next > level)
+                    .count();
+                SectionWords {
+                    own: own[i],
+                    total: own[i..=i + subsections].iter().sum(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Words of `text`, leaving out the Markdown markers standing alone.
+pub fn word_count(text: &str) -> usize {
+    text.split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count()
+}
+
+/// Targets of the sections set in the front matter opening `content`, by
+/// header text.
+pub fn front_matter_targets(content: &str) -> BTreeMap<String, usize> {
+    let mut targets = BTreeMap::new();
+    let mut lines = content.lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        return targets;
+    }
+    let mut in_targets = false;
+    for line in lines {
+        if matches!(line.trim_end(), "---" | "...") {
+            return targets;
+        }
+        let indented = line.starts_with([' ', '\t']);
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        if !indented {
+            in_targets = key.trim() == TARGETS_KEY && value.trim().is_empty();
+        } else if in_targets {
+            let key = key.trim().trim_matches(['"', '\'']);
+            if let Ok(words) = value.trim().parse() {
+                targets.insert(key.to_string(), words);
+            }
+        }
+    }
+    // Unclosed front matter is text
+    BTreeMap::new()
+}
+
+/// Byte ranges of the sections of `content`, one per header of `headers`
+/// (level, text, 1-based line).
+pub fn section_ranges(content: &str, headers: &[(u32, String, usize)]) -> Vec<Range<usize>> {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let start_of = |line: usize| {
+        line_starts
+            .get(line.saturating_sub(1))
+            .copied()
+            .unwrap_or(content.len())
+    };
+    headers
+        .iter()
+        .enumerate()
+        .map(|(i, (level, _, line))| {
+            let end = headers[i + 1..]
+                .iter()
+                .find(|(next, _, _)| next <= level)
+                .map_or(content.len(), |(_, _, next_line)| start_of(*next_line));
+            start_of(*line)..end
+        })
+        .collect()
+}
+
+/// Transaction moving the section of header `from` before the header
+/// `before`, or to the end of the document if `None`.
+///
+/// Returns `None` if the section would not move, or would move inside
+/// itself.
+pub fn move_section(
+    content: &str,
+    headers: &[(u32, String, usize)],
+    from: usize,
+    before: Option<usize>,
+) -> Option<EditTransaction> {
+    let sections = section_ranges(content, headers);
+    let range = sections.get(from)?.clone();
+    let to = match before {
+        Some(before) => sections.get(before)?.start,
+        None => content.len(),
+    };
+    if range.start <= to && to <= range.end {
+        return None;
+    }
+
+    // A section moved without a line ending would run into the next one
+    let mut transaction = EditTransaction::begin();
+    let unterminated = !content.is_empty() && !content.ends_with('\n');
+    let (range, to) = if unterminated && (range.end == content.len() || to == content.len()) {
+        transaction.replace(content.len()..content.len(), "\n");
+        let grow = |offset: usize| {
+            if offset == content.len() {
+                offset + 1
+            } else {
+                offset
+            }
+        };
+        (range.start..grow(range.end), grow(to))
+    } else {
+        (range, to)
+    };
+    transaction.move_range(range, to);
+    Some(transaction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers() -> Vec<(u32, String, usize)> {
+        vec![
+            (1, "One".to_string(), 1),
+            (2, "One A".to_string(), 3),
+            (1, "Two".to_string(), 5),
+            (1, "Three".to_string(), 7),
+        ]
+    }
+
+    const CONTENT: &str = "# One\nmorning\n## One A\nnoon\n# Two\nevening\n# Three\nnight";
+
+    #[test]
+    fn test_section_words_roll_up() {
+        let content = "# One\nmorning sun\n## One A\nnoon\n## One B\nlate noon\n# Two\n- evening";
+        let headers = vec![
+            (1, "One".to_string(), 1),
+            (2, "One A".to_string(), 3),
+            (2, "One B".to_string(), 5),
+            (1, "Two".to_string(), 7),
+        ];
+        let mut counter = SectionCounter::default();
+        let words = counter.count(content, &headers);
+        let totals: Vec<(usize, usize)> = words.iter().map(|w| (w.own, w.total)).collect();
+        assert_eq!(totals, [(2, 5), (1, 1), (2, 2), (1, 1)]);
+        assert_eq!(counter.counted.len(), 4);
+
+        let content = content.replace("late noon", "noon");
+        assert_eq!(counter.count(&content, &headers)[0].total, 4);
+    }
+
+    #[test]
+    fn test_front_matter_targets() {
+        let content = "---\ntitle: Inn\ntargets:\n  The Inn: 3000\n  \"Night: Falls\": x\n  Dawn: 900\nlang: en\n  Late: 5\n---\n# The Inn";
+        let targets = front_matter_targets(content);
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets["The Inn"], 3000);
+        assert_eq!(targets["Dawn"], 900);
+        assert!(front_matter_targets("targets:\n  The Inn: 3000\n").is_empty());
+    }
+
+    #[test]
+    fn test_section_ranges_include_subsections() {
+        let sections = section_ranges(CONTENT, &headers());
+        assert_eq!(
+            &CONTENT[sections[0].clone()],
+            "# One\nmorning\n## One A\nnoon\n"
+        );
+        assert_eq!(&CONTENT[sections[1].clone()], "## One A\nnoon\n");
+        assert_eq!(&CONTENT[sections[3].clone()], "# Three\nnight");
+    }
+
+    #[test]
+    fn test_move_section() {
+        let moved = |from, before| {
+            move_section(CONTENT, &headers(), from, before)
+                .unwrap()
+                .apply(CONTENT)
+                .unwrap()
+                .content
+        };
+        assert_eq!(
+            moved(2, Some(0)),
+            "# Two\nevening\n# One\nmorning\n## One A\nnoon\n# Three\nnight"
+        );
+        assert_eq!(
+            moved(0, None),
+            "# Two\nevening\n# Three\nnight\n# One\nmorning\n## One A\nnoon\n"
+        );
+        assert_eq!(
+            moved(3, Some(2)),
+            "# One\nmorning\n## One A\nnoon\n# Three\nnight\n# Two\nevening\n"
+        );
+        // Not into itself, nor where it already is
+        assert!(move_section(CONTENT, &headers(), 0, Some(1)).is_none());
+        assert!(move_section(CONTENT, &headers(), 2, Some(3)).is_none());
+    }
+}
@@ -0,0 +1,194 @@
+//! Headers of the outline as a tree whose branches can be collapsed.
+//!
+//! A collapsed header hides its subsections. Headers are known by their text
+//! and those of the headers they are under, so that they stay collapsed as
+//! the text around them changes; the collapsed headers of each saved
+//! document are kept from one session to the next in an [`OutlineSession`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use cosmarium_outline::tree::{collapse_to, visible};
+//!
+//! let headers = vec![
+//!     (1, "Part One".to_string(), 1),
+//!     (2, "The Inn".to_string(), 3),
+//!     (3, "Night Falls".to_string(), 9),
+//!     (2, "The Road".to_string(), 20),
+//! ];
+//! let collapsed = collapse_to(&headers, 2);
+//! assert_eq!(visible(&headers, &collapsed), [true, true, false, true]);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Shared state key (`Option<Fold>`) of a change to the collapsed headers
+/// asked for by a command.
+pub const FOLD_REQUEST: &str = "outline_fold_request";
+
+/// Change to the collapsed headers of the outline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fold {
+    /// Show every header
+    ExpandAll,
+    /// Show the headers of this level and above only
+    CollapseTo(u32),
+}
+
+/// Headers collapsed in each saved document, kept from one session to the
+/// next.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutlineSession {
+    /// Keys of the collapsed headers, by file
+    collapsed: BTreeMap<PathBuf, BTreeSet<String>>,
+}
+
+impl OutlineSession {
+    /// Keys of the headers collapsed in a document.
+    pub fn collapsed(&self, path: &Path) -> BTreeSet<String> {
+        self.collapsed.get(path).cloned().unwrap_or_default()
+    }
+
+    /// Remember the headers collapsed in a document. Returns whether it
+    /// changed.
+    pub fn set_collapsed(&mut self, path: &Path, collapsed: &BTreeSet<String>) -> bool {
+        if collapsed.is_empty() {
+            return self.collapsed.remove(path).is_some();
+        }
+        if self.collapsed.get(path) == Some(collapsed) {
+            return false;
+        }
+        self.collapsed.insert(path.to_path_buf(), collapsed.clone());
+        true
+    }
+}
+
+/// Key of each header: its text under those of the headers it is in, one
+/// per line.
+pub fn header_keys(headers: &[(u32, String, usize)]) -> Vec<String> {
+    let mut path: Vec<(u32, &str)> = Vec::new();
+    headers
+        .iter()
+        .map(|(level, text, _)| {
+            path.retain(|(parent, _)| parent < level);
+            path.push((*level, text));
+            path.iter()
+                .map(|(_, text)| *text)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect()
+}
+
+/// Whether the header at `index` has subsections.
+pub fn has_children(headers: &[(u32, String, usize)], index: usize) -> bool {
+    match (headers.get(index), headers.get(index + 1)) {
+        (Some((level, _, _)), Some((next, _, _))) => next > level,
+        _ => false,
+    }
+}
+
+/// Whether each header is shown, none of the headers it is in being
+/// collapsed.
+pub fn visible(headers: &[(u32, String, usize)], collapsed: &BTreeSet<String>) -> Vec<bool> {
+    // Level of the collapsed header being gone through, if any
+    let mut hidden_under: Option<u32> = None;
+    headers
+        .iter()
+        .zip(header_keys(headers))
+        .map(|((level, _, _), key)| {
+            if hidden_under.is_some_and(|under| *level > under) {
+                return false;
+            }
+            hidden_under = collapsed.contains(&key).then_some(*level);
+            true
+        })
+        .collect()
+}
+
+/// Keys of the headers of `level`, among those with subsections.
+pub fn level_keys(headers: &[(u32, String, usize)], level: u32) -> BTreeSet<String> {
+    header_keys(headers)
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| headers[*i].0 == level && has_children(headers, *i))
+        .map(|(_, key)| key)
+        .collect()
+}
+
+/// Keys of the headers to collapse for only those of `level` and above to
+/// be shown.
+pub fn collapse_to(headers: &[(u32, String, usize)], level: u32) -> BTreeSet<String> {
+    header_keys(headers)
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| headers[*i].0 >= level && has_children(headers, *i))
+        .map(|(_, key)| key)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers() -> Vec<(u32, String, usize)> {
+        vec![
+            (1, "One".to_string(), 1),
+            (2, "Scene".to_string(), 3),
+            (3, "Beat".to_string(), 4),
+            (1, "Two".to_string(), 6),
+            (2, "Scene".to_string(), 8),
+        ]
+    }
+
+    #[test]
+    fn test_header_keys_follow_parents() {
+        assert_eq!(
+            header_keys(&headers()),
+            ["One", "One\nScene", "One\nScene\nBeat", "Two", "Two\nScene"]
+        );
+    }
+
+    #[test]
+    fn test_collapsed_headers_hide_subsections() {
+        let headers = headers();
+        let collapsed = BTreeSet::from(["One".to_string()]);
+        assert_eq!(
+            visible(&headers, &collapsed),
+            [true, false, false, true, true]
+        );
+
+        // The second "Scene" has the same text, but not the same parent
+        let collapsed = BTreeSet::from(["One\nScene".to_string()]);
+        assert_eq!(
+            visible(&headers, &collapsed),
+            [true, true, false, true, true]
+        );
+
+        assert_eq!(collapse_to(&headers, 1).len(), 3);
+        assert_eq!(
+            visible(&headers, &collapse_to(&headers, 2)),
+            [true, true, false, true, true]
+        );
+        assert_eq!(
+            level_keys(&headers, 2),
+            BTreeSet::from(["One\nScene".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_session_keeps_documents_with_collapsed_headers() {
+        let mut session = OutlineSession::default();
+        let path = Path::new("/novel/one.md");
+        let collapsed = BTreeSet::from(["One".to_string()]);
+        assert!(session.set_collapsed(path, &collapsed));
+        assert!(!session.set_collapsed(path, &collapsed));
+        assert_eq!(session.collapsed(path), collapsed);
+
+        assert!(session.set_collapsed(path, &BTreeSet::new()));
+        assert!(session.collapsed.is_empty());
+    }
+}
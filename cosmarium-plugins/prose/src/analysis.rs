@@ -0,0 +1,454 @@
+//! Style analysis of English prose.
+//!
+//! Finds the adverbs, passive constructions, filter words and overly long
+//! sentences of a Markdown text with simple word lists and patterns. Code
+//! blocks are skipped. The findings are hints for revision, not errors:
+//! a word ending in `-ly` may not be an adverb, and a passive may be the
+//! right choice.
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Words ending in `-ly` that are not adverbs.
+const NOT_ADVERBS: &[&str] = &[
+    "ally",
+    "anomaly",
+    "apply",
+    "assembly",
+    "belly",
+    "bully",
+    "burly",
+    "butterfly",
+    "chilly",
+    "comply",
+    "costly",
+    "cuddly",
+    "curly",
+    "daily",
+    "deadly",
+    "dragonfly",
+    "early",
+    "elderly",
+    "family",
+    "firefly",
+    "friendly",
+    "frilly",
+    "ghastly",
+    "ghostly",
+    "gnarly",
+    "grizzly",
+    "holy",
+    "homely",
+    "hourly",
+    "italy",
+    "jelly",
+    "jolly",
+    "july",
+    "kindly",
+    "lily",
+    "likely",
+    "lively",
+    "lonely",
+    "lovely",
+    "lowly",
+    "monopoly",
+    "monthly",
+    "oily",
+    "only",
+    "orderly",
+    "rally",
+    "reply",
+    "sally",
+    "scaly",
+    "silly",
+    "sly",
+    "smelly",
+    "supply",
+    "surly",
+    "ugly",
+    "unlikely",
+    "weekly",
+    "wily",
+    "woolly",
+    "worldly",
+    "yearly",
+];
+
+/// Words telling what a character perceives instead of showing it.
+const FILTER_WORDS: &[&str] = &[
+    "saw", "see", "sees", "seeing", "seen", "heard", "hear", "hears", "hearing", "felt", "feel",
+    "feels", "feeling", "noticed", "notice", "notices", "noticing", "realized", "realised",
+    "realize", "realise", "realizes", "realises", "wondered", "wonder", "wonders", "watched",
+    "watch", "watches", "watching", "seemed", "seem", "seems", "decided", "decide", "decides",
+    "smelled", "smelt", "tasted", "knew", "know", "knows", "thought", "think", "thinks", "looked",
+    "noted",
+];
+
+/// Forms of *to be* starting a passive construction.
+const BE_FORMS: &[&str] = &[
+    "am", "is", "are", "was", "were", "be", "been", "being", "isn't", "aren't", "wasn't", "weren't",
+];
+
+/// Irregular past participles.
+const IRREGULAR_PARTICIPLES: &[&str] = &[
+    "awoken",
+    "beaten",
+    "become",
+    "begun",
+    "bent",
+    "bitten",
+    "blown",
+    "born",
+    "borne",
+    "bought",
+    "bound",
+    "broken",
+    "brought",
+    "built",
+    "burnt",
+    "caught",
+    "chosen",
+    "cut",
+    "dealt",
+    "done",
+    "drawn",
+    "driven",
+    "drunk",
+    "dug",
+    "eaten",
+    "fallen",
+    "fed",
+    "felt",
+    "fought",
+    "found",
+    "flown",
+    "forbidden",
+    "forgiven",
+    "forgotten",
+    "frozen",
+    "given",
+    "gone",
+    "grown",
+    "heard",
+    "held",
+    "hidden",
+    "hit",
+    "hung",
+    "hurt",
+    "kept",
+    "known",
+    "laid",
+    "led",
+    "left",
+    "lent",
+    "lit",
+    "lost",
+    "made",
+    "meant",
+    "met",
+    "paid",
+    "put",
+    "read",
+    "ridden",
+    "rung",
+    "risen",
+    "run",
+    "said",
+    "seen",
+    "sent",
+    "set",
+    "shaken",
+    "shot",
+    "shown",
+    "shut",
+    "sold",
+    "sought",
+    "spent",
+    "spoken",
+    "spun",
+    "stolen",
+    "struck",
+    "stuck",
+    "stung",
+    "sung",
+    "sunk",
+    "swept",
+    "sworn",
+    "swung",
+    "taken",
+    "taught",
+    "thrown",
+    "told",
+    "torn",
+    "understood",
+    "woken",
+    "won",
+    "worn",
+    "woven",
+    "written",
+    "wound",
+];
+
+/// Words ending in `-ed` that are not participles after *to be*.
+const NOT_PARTICIPLES: &[&str] = &[
+    "bed", "red", "shed", "sled", "need", "seed", "feed", "speed",
+];
+
+/// What a finding points out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Category {
+    /// Words ending in `-ly` modifying a verb or an adjective
+    Adverb,
+    /// A form of *to be* followed by a past participle
+    Passive,
+    /// Verbs of perception filtering the scene through a character
+    FilterWord,
+    /// Sentences longer than the limit of the settings
+    LongSentence,
+}
+
+impl Category {
+    /// All categories, in the order they are listed.
+    pub const ALL: [Category; 4] = [
+        Category::Adverb,
+        Category::Passive,
+        Category::FilterWord,
+        Category::LongSentence,
+    ];
+
+    /// Get the display name of the category.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Adverb => "Adverbs",
+            Category::Passive => "Passive voice",
+            Category::FilterWord => "Filter words",
+            Category::LongSentence => "Long sentences",
+        }
+    }
+}
+
+/// A passage of the text pointed out by the analysis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    /// What the passage points out
+    pub category: Category,
+    /// Byte range of the passage
+    pub range: Range<usize>,
+}
+
+/// A word of the text, with its byte range.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Word<'a> {
+    pub(crate) text: &'a str,
+    pub(crate) start: usize,
+}
+
+impl Word<'_> {
+    pub(crate) fn range(&self) -> Range<usize> {
+        self.start..self.start + self.text.len()
+    }
+
+    pub(crate) fn is(&self, list: &[&str]) -> bool {
+        list.iter().any(|word| self.text.eq_ignore_ascii_case(word))
+    }
+}
+
+/// Analyze a text, finding the passages of every category.
+///
+/// Sentences of more than `max_sentence_words` words are long.
+pub fn analyze(text: &str, max_sentence_words: usize) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for (offset, block) in prose_blocks(text) {
+        for sentence in sentences(block) {
+            let words = words(sentence.clone(), block, offset);
+            if words.len() > max_sentence_words {
+                findings.push(Finding {
+                    category: Category::LongSentence,
+                    range: offset + sentence.start..offset + sentence.end,
+                });
+            }
+
+            for (i, word) in words.iter().enumerate() {
+                if is_adverb(word) {
+                    findings.push(Finding {
+                        category: Category::Adverb,
+                        range: word.range(),
+                    });
+                }
+                if word.is(FILTER_WORDS) {
+                    findings.push(Finding {
+                        category: Category::FilterWord,
+                        range: word.range(),
+                    });
+                }
+                if word.is(BE_FORMS) {
+                    // An adverb may come in between: "was quickly taken"
+                    let participle = match words.get(i + 1) {
+                        Some(next) if is_adverb(next) => words.get(i + 2),
+                        next => next,
+                    };
+                    if let Some(participle) = participle.filter(|w| is_participle(w)) {
+                        findings.push(Finding {
+                            category: Category::Passive,
+                            range: word.start..participle.range().end,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    findings.sort_by_key(|finding| (finding.range.start, finding.range.end));
+    findings
+}
+
+fn is_adverb(word: &Word) -> bool {
+    word.text.len() > 4 && word.text.to_ascii_lowercase().ends_with("ly") && !word.is(NOT_ADVERBS)
+}
+
+fn is_participle(word: &Word) -> bool {
+    let lower = word.text.to_ascii_lowercase();
+    (lower.len() > 3 && lower.ends_with("ed") && !word.is(NOT_PARTICIPLES))
+        || word.is(IRREGULAR_PARTICIPLES)
+}
+
+/// Paragraphs of prose with their byte offset, leaving out code blocks.
+pub(crate) fn prose_blocks(text: &str) -> Vec<(usize, &str)> {
+    let mut blocks = Vec::new();
+    let mut offset = 0;
+    let mut start = None;
+    let mut in_fence = false;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+        let prose = !in_fence && !fence && !trimmed.is_empty();
+        match (prose, start) {
+            (true, None) => start = Some(offset),
+            (false, Some(from)) => {
+                blocks.push((from, &text[from..offset]));
+                start = None;
+            }
+            _ => {}
+        }
+        in_fence ^= fence;
+        offset += line.len();
+    }
+    if let Some(from) = start {
+        blocks.push((from, &text[from..]));
+    }
+    blocks
+}
+
+/// Byte ranges of the sentences of a paragraph, without the spaces around
+/// them.
+pub(crate) fn sentences(block: &str) -> Vec<Range<usize>> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = block.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if !matches!(c, '.' | '!' | '?' | '…') {
+            continue;
+        }
+        // Closing quotes and brackets belong to the sentence
+        let mut end = i + c.len_utf8();
+        while let Some(&(j, next)) = chars.peek() {
+            if matches!(
+                next,
+                '.' | '!' | '?' | '"' | '\'' | '”' | '’' | ')' | '*' | '_'
+            ) {
+                end = j + next.len_utf8();
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        if chars.peek().is_none_or(|(_, next)| next.is_whitespace()) {
+            push_trimmed(&mut sentences, block, start..end);
+            start = end;
+        }
+    }
+    push_trimmed(&mut sentences, block, start..block.len());
+    sentences
+}
+
+fn push_trimmed(sentences: &mut Vec<Range<usize>>, block: &str, range: Range<usize>) {
+    let sentence = &block[range.clone()];
+    let trimmed = sentence.trim_start();
+    let start = range.start + sentence.len() - trimmed.len();
+    let end = start + trimmed.trim_end().len();
+    if start < end {
+        sentences.push(start..end);
+    }
+}
+
+/// Words of a sentence of `block`, with their byte ranges in the text.
+pub(crate) fn words(sentence: Range<usize>, block: &str, offset: usize) -> Vec<Word<'_>> {
+    let text = &block[sentence.clone()];
+    let mut words = Vec::new();
+    let mut start = None;
+
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        let in_word = c.is_alphanumeric() || (start.is_some() && matches!(c, '\'' | '’' | '-'));
+        match (in_word, start) {
+            (true, None) => start = Some(i),
+            (false, Some(from)) => {
+                let word = text[from..i].trim_end_matches(['\'', '’', '-']);
+                words.push(Word {
+                    text: word,
+                    start: offset + sentence.start + from,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(text: &str, category: Category) -> Vec<&str> {
+        analyze(text, 30)
+            .into_iter()
+            .filter(|finding| finding.category == category)
+            .map(|finding| &text[finding.range])
+            .collect()
+    }
+
+    #[test]
+    fn test_adverbs_and_filter_words() {
+        let text = "She saw him smile slowly. Only the family felt truly lonely.";
+        assert_eq!(found(text, Category::Adverb), vec!["slowly", "truly"]);
+        assert_eq!(found(text, Category::FilterWord), vec!["saw", "felt"]);
+    }
+
+    #[test]
+    fn test_passive_voice() {
+        let text = "The letter was quickly written. The door is red. They were taken away.";
+        assert_eq!(
+            found(text, Category::Passive),
+            vec!["was quickly written", "were taken"]
+        );
+    }
+
+    #[test]
+    fn test_long_sentences_and_code() {
+        let text = "One two three. \"Four five six seven?\" She nods.\n\n\
+                    ```\nthe code was written slowly\n```\n";
+        let long: Vec<_> = analyze(text, 3)
+            .into_iter()
+            .filter(|finding| finding.category == Category::LongSentence)
+            .map(|finding| &text[finding.range])
+            .collect();
+        assert_eq!(long, vec!["\"Four five six seven?\""]);
+        assert!(found(text, Category::Adverb).is_empty());
+    }
+}
@@ -0,0 +1,551 @@
+//! # Prose analysis plugin for Cosmarium
+//!
+//! Points out the adverbs, passive constructions, filter words ("saw",
+//! "felt") and overly long sentences of the document open in the editor.
+//! The panel counts them by category and lists them to jump to; while it is
+//! shown, the categories turned on are highlighted in the editor.
+//!
+//! The panel also ranks the words and phrases the document repeats, those
+//! coming back within a few words first (see [`repetition`]). Selecting one
+//! highlights all its uses and lists them to jump to.
+//!
+//! The editor can ask for a passage to be checked alone, such as the
+//! selection, by its byte range in [`STYLE_CHECK_REQUEST`].
+//!
+//! The analysis works on English prose, see [`analysis`].
+
+pub mod analysis;
+pub mod repetition;
+
+use analysis::{Category, Finding};
+use cosmarium_plugin_api::grammar::text_hash;
+use cosmarium_plugin_api::highlight::{self, Highlight, TextHighlights};
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+};
+use egui::{Color32, Ui};
+use repetition::Repetition;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Name of the plugin and of its panel.
+pub const PLUGIN_NAME: &str = "prose";
+
+/// Configuration key for [`ProseSettings`], under the plugin's name.
+pub const CONFIG_KEY: &str = PLUGIN_NAME;
+
+/// Shared state key (`Option<Range<usize>>`) of the byte range of the
+/// editor's text to check alone, cleared once taken.
+pub const STYLE_CHECK_REQUEST: &str = "style_check_request";
+
+/// How long the highlights stay after the panel was last shown.
+const SHOWN_GRACE: Duration = Duration::from_millis(500);
+
+/// Longest snippet listed for a finding, in characters.
+const SNIPPET_CHARS: usize = 60;
+
+/// Characters of context shown on each side of a repeated word.
+const CONTEXT_CHARS: usize = 25;
+
+/// Number of repetitions listed.
+const MAX_REPETITIONS: usize = 50;
+
+/// Highlight color of the uses of the selected repetition.
+const REPETITION_COLOR: Color32 = Color32::from_rgba_premultiplied(120, 40, 120, 90);
+
+/// Settings of the analysis.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProseSettings {
+    /// Categories highlighted in the editor
+    pub highlighted: Vec<Category>,
+    /// Number of words past which a sentence is long
+    pub max_sentence_words: usize,
+    /// Number of words within which a repeated word is noticed
+    pub repetition_window: usize,
+}
+
+impl Default for ProseSettings {
+    fn default() -> Self {
+        Self {
+            highlighted: Category::ALL.to_vec(),
+            max_sentence_words: 30,
+            repetition_window: 100,
+        }
+    }
+}
+
+/// Highlight color of a category.
+fn color(category: Category) -> Color32 {
+    match category {
+        Category::Adverb => Color32::from_rgba_unmultiplied(80, 140, 255, 60),
+        Category::Passive => Color32::from_rgba_unmultiplied(60, 200, 120, 60),
+        Category::FilterWord => Color32::from_rgba_unmultiplied(255, 150, 40, 70),
+        Category::LongSentence => Color32::from_rgba_unmultiplied(240, 220, 60, 35),
+    }
+}
+
+#[derive(Default)]
+pub struct ProsePlugin {
+    /// Settings of the analysis
+    settings: ProseSettings,
+    /// Findings in the editor's text
+    findings: Vec<Finding>,
+    /// Words and phrases the editor's text repeats, most noticeable first
+    repetitions: Vec<Repetition>,
+    /// Repetition whose uses are highlighted
+    selected: Option<String>,
+    /// Passage of the editor's text checked alone, the whole text if `None`
+    passage: Option<Range<usize>>,
+    /// Hash of the text analyzed
+    analyzed: Option<u64>,
+    /// When the panel was last shown
+    shown_at: Option<Instant>,
+    /// Hash of the text whose highlights are published
+    published: Option<u64>,
+}
+
+impl ProsePlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Analyze the editor's text again if it changed.
+    ///
+    /// # Returns
+    ///
+    /// The hash of the text.
+    fn refresh(&mut self, content: &str) -> u64 {
+        let hash = text_hash(content);
+        if self.analyzed != Some(hash) {
+            // A passage the text no longer has is dropped
+            let passage = self
+                .passage
+                .take()
+                .filter(|range| content.get(range.clone()).is_some());
+            let start = passage.as_ref().map_or(0, |range| range.start);
+            let text = passage
+                .as_ref()
+                .map_or(content, |range| &content[range.clone()]);
+            let shift = |range: &mut Range<usize>| *range = range.start + start..range.end + start;
+
+            self.findings = analysis::analyze(text, self.settings.max_sentence_words);
+            self.findings
+                .iter_mut()
+                .for_each(|finding| shift(&mut finding.range));
+            self.repetitions = repetition::find(text, self.settings.repetition_window);
+            self.repetitions
+                .iter_mut()
+                .flat_map(|repetition| repetition.occurrences.iter_mut())
+                .for_each(shift);
+            self.passage = passage;
+            self.analyzed = Some(hash);
+        }
+        hash
+    }
+
+    /// Check the passage the editor asked for, if any, instead of the
+    /// whole text.
+    fn take_request(&mut self, ctx: &mut PluginContext) {
+        let Some(range) = ctx
+            .get_shared_state::<Option<Range<usize>>>(STYLE_CHECK_REQUEST)
+            .flatten()
+        else {
+            return;
+        };
+        ctx.set_shared_state::<Option<Range<usize>>>(STYLE_CHECK_REQUEST, None);
+        self.passage = Some(range);
+        self.analyzed = None;
+        self.published = None;
+    }
+
+    /// Publish the highlights of the categories turned on.
+    fn publish(&mut self, ctx: &mut PluginContext, hash: u64) {
+        let mut highlights = TextHighlights::new(hash);
+        highlights.highlights = self
+            .findings
+            .iter()
+            .filter(|finding| self.settings.highlighted.contains(&finding.category))
+            .map(|finding| Highlight::new(finding.range.clone(), color(finding.category)))
+            .collect();
+        if let Some(selected) = self
+            .repetitions
+            .iter()
+            .find(|repetition| Some(&repetition.phrase) == self.selected.as_ref())
+        {
+            highlights.highlights.extend(
+                selected
+                    .occurrences
+                    .iter()
+                    .map(|range| Highlight::new(range.clone(), REPETITION_COLOR)),
+            );
+            highlights.highlights.sort_by_key(|h| h.range.start);
+        }
+        highlight::publish(ctx, PLUGIN_NAME, Some(highlights));
+        self.published = Some(hash);
+    }
+
+    fn withdraw(&mut self, ctx: &mut PluginContext) {
+        if self.published.take().is_some() {
+            highlight::publish(ctx, PLUGIN_NAME, None);
+        }
+    }
+
+    fn render_findings(&self, ui: &mut Ui, content: &str, category: Category) -> Option<usize> {
+        let mut goto = None;
+        for finding in self.findings.iter().filter(|f| f.category == category) {
+            let Some(passage) = content.get(finding.range.clone()) else {
+                continue;
+            };
+            let mut snippet: String = passage.chars().take(SNIPPET_CHARS).collect();
+            if snippet.len() < passage.len() {
+                snippet.push('…');
+            }
+            if ui
+                .add(egui::Label::new(snippet.replace('\n', " ")).sense(egui::Sense::click()))
+                .on_hover_cursor(egui::CursorIcon::PointingHand)
+                .clicked()
+            {
+                goto = Some(line_of(content, finding.range.start));
+            }
+        }
+        goto
+    }
+
+    /// List the repetitions, the uses of the selected one under it.
+    ///
+    /// # Returns
+    ///
+    /// The line of the use clicked, if any.
+    fn render_repetitions(&mut self, ui: &mut Ui, content: &str) -> Option<usize> {
+        let mut goto = None;
+        let mut selected = self.selected.clone();
+        for repetition in self.repetitions.iter().take(MAX_REPETITIONS) {
+            let is_selected = selected.as_ref() == Some(&repetition.phrase);
+            let mut label = format!("{} ×{}", repetition.phrase, repetition.occurrences.len());
+            if repetition.close > 0 {
+                label.push_str(&format!(" ({} close)", repetition.close));
+            }
+            if ui.selectable_label(is_selected, label).clicked() {
+                selected = (!is_selected).then(|| repetition.phrase.clone());
+            }
+            if !is_selected {
+                continue;
+            }
+            ui.indent(("prose_repetition", &repetition.phrase), |ui| {
+                for range in &repetition.occurrences {
+                    if ui
+                        .add(
+                            egui::Label::new(format!(
+                                "{}: {}",
+                                line_of(content, range.start),
+                                context(content, range.clone())
+                            ))
+                            .sense(egui::Sense::click()),
+                        )
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .clicked()
+                    {
+                        goto = Some(line_of(content, range.start));
+                    }
+                }
+            });
+        }
+        if selected != self.selected {
+            self.selected = selected;
+            self.published = None;
+        }
+        goto
+    }
+}
+
+/// 1-based line of a byte offset of `content`.
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset].matches('\n').count() + 1
+}
+
+/// A passage of `content` with a few characters of its line on each side.
+fn context(content: &str, range: std::ops::Range<usize>) -> String {
+    let line_start = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = content[range.end..]
+        .find('\n')
+        .map_or(content.len(), |i| range.end + i);
+    let before = &content[line_start..range.start];
+    let skip = before.chars().count().saturating_sub(CONTEXT_CHARS);
+    let mut before: String = before.chars().skip(skip).collect();
+    if skip > 0 {
+        before.insert(0, '…');
+    }
+    let mut after: String = content[range.end..line_end]
+        .chars()
+        .take(CONTEXT_CHARS)
+        .collect();
+    if after.len() < line_end - range.end {
+        after.push('…');
+    }
+    format!(
+        "{}{}{}",
+        before.trim_start(),
+        &content[range],
+        after.trim_end()
+    )
+}
+
+impl Plugin for ProsePlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            PLUGIN_NAME,
+            "0.1.0",
+            "Adverbs, passive voice, filter words and long sentences",
+            "Cosmarium Team",
+        )
+        .with_dependency("markdown-editor")
+    }
+
+    fn initialize(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        if let Some(settings) = ctx.get_config::<ProseSettings>(CONFIG_KEY) {
+            self.settings = settings;
+        } else {
+            ctx.set_config(CONFIG_KEY, &self.settings);
+        }
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+
+    fn update(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl PanelPlugin for ProsePlugin {
+    fn panel_title(&self) -> &str {
+        "Style"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "🔍"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Right
+    }
+
+    fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        self.take_request(ctx);
+
+        // Highlights only show while the panel does
+        let shown = self
+            .shown_at
+            .is_some_and(|shown| shown.elapsed() < SHOWN_GRACE);
+        if !shown {
+            self.withdraw(ctx);
+            return Ok(());
+        }
+
+        let content = ctx
+            .get_shared_state::<String>("markdown_editor_content")
+            .unwrap_or_default();
+        let hash = self.refresh(&content);
+        if self.published != Some(hash) {
+            self.publish(ctx, hash);
+        }
+        Ok(())
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        self.shown_at = Some(Instant::now());
+        let content = ctx
+            .get_shared_state::<String>("markdown_editor_content")
+            .unwrap_or_default();
+        self.refresh(&content);
+
+        let mut changed = false;
+        let mut goto = None;
+        if let Some(passage) = self.passage.clone() {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "Checking the selection, lines {}–{}",
+                    line_of(&content, passage.start),
+                    line_of(&content, passage.end)
+                ));
+                if ui.small_button("Whole Document").clicked() {
+                    self.passage = None;
+                    changed = true;
+                }
+            });
+            ui.separator();
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for category in Category::ALL {
+                let count = self
+                    .findings
+                    .iter()
+                    .filter(|finding| finding.category == category)
+                    .count();
+                let id = ui.make_persistent_id(("prose_category", category));
+                egui::collapsing_header::CollapsingState::load_with_default_open(
+                    ui.ctx(),
+                    id,
+                    false,
+                )
+                .show_header(ui, |ui| {
+                    let (swatch, _) =
+                        ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+                    ui.painter()
+                        .rect_filled(swatch, 2.0, color(category).to_opaque());
+                    let mut on = self.settings.highlighted.contains(&category);
+                    let label = format!("{} ({})", category.name(), count);
+                    if ui
+                        .checkbox(&mut on, label)
+                        .on_hover_text("Highlight in the editor")
+                        .changed()
+                    {
+                        if on {
+                            self.settings.highlighted.push(category);
+                        } else {
+                            self.settings.highlighted.retain(|c| *c != category);
+                        }
+                        changed = true;
+                    }
+                })
+                .body(|ui| {
+                    goto = goto.or(self.render_findings(ui, &content, category));
+                });
+            }
+
+            let id = ui.make_persistent_id("prose_repetitions");
+            egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, false)
+                .show_header(ui, |ui| {
+                    ui.label(format!("Repetitions ({})", self.repetitions.len()));
+                })
+                .body(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Close within");
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut self.settings.repetition_window)
+                                    .range(10..=1000),
+                            )
+                            .changed();
+                        ui.label("words");
+                    });
+                    goto = goto.or(self.render_repetitions(ui, &content));
+                });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Long sentences over");
+                changed |= ui
+                    .add(egui::DragValue::new(&mut self.settings.max_sentence_words).range(5..=100))
+                    .changed();
+                ui.label("words");
+            });
+        });
+
+        if changed {
+            ctx.set_config(CONFIG_KEY, &self.settings);
+            self.analyzed = None;
+            self.published = None;
+        }
+        if let Some(line) = goto {
+            ctx.set_shared_state("markdown_editor_goto_line", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlights_follow_the_panel() {
+        let mut ctx = PluginContext::new();
+        let mut plugin = ProsePlugin::new();
+        plugin.initialize(&mut ctx).unwrap();
+        ctx.set_shared_state(
+            "markdown_editor_content",
+            "He walked slowly. The door was opened.".to_string(),
+        );
+
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(highlight::published(&ctx).is_empty());
+
+        plugin.shown_at = Some(Instant::now());
+        plugin.settings.highlighted = vec![Category::Passive];
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        let published = highlight::published(&ctx);
+        let highlights = &published[PLUGIN_NAME].highlights;
+        assert_eq!(highlights.len(), 1);
+        assert_eq!(highlights[0].range, 27..37);
+
+        plugin.shown_at = None;
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(highlight::published(&ctx).is_empty());
+    }
+
+    #[test]
+    fn test_selected_repetition_is_highlighted() {
+        let mut ctx = PluginContext::new();
+        let mut plugin = ProsePlugin::new();
+        plugin.initialize(&mut ctx).unwrap();
+        plugin.settings.highlighted.clear();
+        ctx.set_shared_state(
+            "markdown_editor_content",
+            "A grey sky.\nThe grey sea.".to_string(),
+        );
+
+        plugin.shown_at = Some(Instant::now());
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(highlight::published(&ctx)[PLUGIN_NAME]
+            .highlights
+            .is_empty());
+        assert_eq!(plugin.repetitions[0].phrase, "grey");
+
+        plugin.selected = Some("grey".to_string());
+        plugin.published = None;
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        let published = highlight::published(&ctx);
+        let ranges: Vec<_> = published[PLUGIN_NAME]
+            .highlights
+            .iter()
+            .map(|h| h.range.clone())
+            .collect();
+        assert_eq!(ranges, vec![2..6, 16..20]);
+        assert_eq!(
+            context("A grey sky.\nThe grey sea.", 16..20),
+            "The grey sea."
+        );
+    }
+
+    #[test]
+    fn test_passage_checked_alone() {
+        let mut ctx = PluginContext::new();
+        let mut plugin = ProsePlugin::new();
+        plugin.initialize(&mut ctx).unwrap();
+        plugin.settings.highlighted = vec![Category::Adverb];
+        ctx.set_shared_state(
+            "markdown_editor_content",
+            "He walked slowly. She ran quickly.".to_string(),
+        );
+        ctx.set_shared_state(STYLE_CHECK_REQUEST, Some(18..34usize));
+
+        plugin.shown_at = Some(Instant::now());
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        let ranges: Vec<_> = plugin.findings.iter().map(|f| f.range.clone()).collect();
+        assert_eq!(ranges, vec![26..33]);
+        assert_eq!(
+            ctx.get_shared_state::<Option<Range<usize>>>(STYLE_CHECK_REQUEST),
+            Some(None)
+        );
+
+        // Out of a shorter text, the whole text is checked again
+        ctx.set_shared_state("markdown_editor_content", "He walked slowly.".to_string());
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert_eq!(plugin.passage, None);
+        assert_eq!(plugin.findings[0].range, 10..16);
+    }
+}
@@ -0,0 +1,126 @@
+//! Repeated and overused words.
+//!
+//! Finds the noticeable words, and the phrases of two of them, used more
+//! than once in a text, ranked by how often they come back within a few
+//! words of their previous use, then by how often they are used overall.
+//! Common function words ("the", "which") are not noticeable and never
+//! counted.
+
+use crate::analysis::{prose_blocks, sentences, words};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Words too common to be noticed when repeated.
+const COMMON_WORDS: &[&str] = &[
+    "a", "about", "after", "again", "all", "also", "an", "and", "any", "are", "as", "at", "back",
+    "be", "been", "before", "being", "but", "by", "can", "could", "did", "do", "does", "down",
+    "for", "from", "had", "has", "have", "he", "her", "here", "hers", "him", "his", "how", "i",
+    "if", "in", "into", "is", "it", "its", "it's", "just", "me", "more", "my", "no", "not", "now",
+    "of", "off", "on", "one", "only", "or", "our", "out", "over", "said", "she", "so", "some",
+    "than", "that", "the", "their", "them", "then", "there", "these", "they", "this", "those",
+    "through", "to", "too", "up", "us", "very", "was", "we", "were", "what", "when", "where",
+    "which", "while", "who", "will", "with", "would", "you", "your",
+];
+
+/// Words shorter than this, in characters, are never noticeable.
+const MIN_WORD_CHARS: usize = 3;
+
+/// A word or phrase used more than once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repetition {
+    /// The word or phrase, in lowercase
+    pub phrase: String,
+    /// Byte ranges of its uses, in text order
+    pub occurrences: Vec<Range<usize>>,
+    /// Number of uses within the window of the previous one
+    pub close: usize,
+}
+
+/// Find the words and two-word phrases used more than once in `text`,
+/// most noticeable first.
+///
+/// A use is close when it comes at most `window` words after the previous
+/// one.
+pub fn find(text: &str, window: usize) -> Vec<Repetition> {
+    // Position in the text, in words, and range of each use
+    let mut uses: HashMap<String, Vec<(usize, Range<usize>)>> = HashMap::new();
+    let mut position = 0;
+
+    for (offset, block) in prose_blocks(text) {
+        for sentence in sentences(block) {
+            // Phrases stay within a sentence
+            let mut previous: Option<(String, Range<usize>)> = None;
+            for word in words(sentence, block, offset) {
+                position += 1;
+                let lower = word.text.to_lowercase();
+                if word.text.chars().count() < MIN_WORD_CHARS || COMMON_WORDS.contains(&&*lower) {
+                    previous = None;
+                    continue;
+                }
+
+                if let Some((before, range)) = previous.take() {
+                    uses.entry(format!("{} {}", before, lower))
+                        .or_default()
+                        .push((position, range.start..word.range().end));
+                }
+                uses.entry(lower.clone())
+                    .or_default()
+                    .push((position, word.range()));
+                previous = Some((lower, word.range()));
+            }
+        }
+    }
+
+    let mut repetitions: Vec<Repetition> = uses
+        .into_iter()
+        .filter(|(_, uses)| uses.len() > 1)
+        .map(|(phrase, uses)| Repetition {
+            close: uses
+                .windows(2)
+                .filter(|pair| pair[1].0 - pair[0].0 <= window)
+                .count(),
+            occurrences: uses.into_iter().map(|(_, range)| range).collect(),
+            phrase,
+        })
+        .collect();
+    repetitions.sort_by(|a, b| {
+        (b.close, b.occurrences.len())
+            .cmp(&(a.close, a.occurrences.len()))
+            .then_with(|| a.phrase.cmp(&b.phrase))
+    });
+    repetitions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_close_repetitions_rank_first() {
+        let text = "The dark corridor led to a dark room. She smiled.\n\n\
+                    A door opened on the garden, far below the house. She smiled at the door.";
+        let repetitions = find(text, 5);
+        let ranked: Vec<_> = repetitions
+            .iter()
+            .map(|r| (r.phrase.as_str(), r.occurrences.len(), r.close))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![("dark", 2, 1), ("door", 2, 0), ("smiled", 2, 0)]
+        );
+        assert_eq!(&text[repetitions[0].occurrences[1].clone()], "dark");
+    }
+
+    #[test]
+    fn test_phrases_of_noticeable_words() {
+        let text = "Her green eyes shone. His green eyes did not. The green of the eyes.";
+        let repetitions = find(text, 100);
+        let phrase = repetitions
+            .iter()
+            .find(|r| r.phrase == "green eyes")
+            .unwrap();
+        assert_eq!(phrase.close, 1);
+        assert_eq!(&text[phrase.occurrences[0].clone()], "green eyes");
+        assert!(repetitions.iter().all(|r| r.phrase != "the"));
+    }
+}
@@ -0,0 +1,513 @@
+//! # Quote card rendering
+//!
+//! A quote card is a passage laid out on a fixed-size image with a styled
+//! background and an attribution line, ready to be shared. Glyphs come from
+//! the fonts registered with egui, so cards use the same typefaces as the
+//! rest of the interface. The passage is shrunk until it fits the card.
+
+use ab_glyph::{Font, FontArc, FontVec, GlyphId, PxScale, ScaleFont};
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+
+/// Card sizes offered to the author (label, width, height).
+pub const CARD_SIZES: &[(&str, u32, u32)] = &[
+    ("Square (1080 × 1080)", 1080, 1080),
+    ("Portrait (1080 × 1350)", 1080, 1350),
+    ("Story (1080 × 1920)", 1080, 1920),
+    ("Landscape (1200 × 675)", 1200, 675),
+];
+
+/// Width the style's font sizes are given for; other widths scale them.
+const REFERENCE_WIDTH: f32 = 1080.0;
+
+/// Smallest passage font size tried before giving up on fitting the text.
+const MIN_FONT_SIZE: f32 = 14.0;
+
+/// Visual style of a card.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CardStyle {
+    /// Card width in pixels
+    pub width: u32,
+    /// Card height in pixels
+    pub height: u32,
+    /// Background color at the top of the card
+    pub background: [u8; 3],
+    /// Background color at the bottom of the card (vertical gradient)
+    pub background_end: [u8; 3],
+    /// Passage color
+    pub text_color: [u8; 3],
+    /// Color of the rule and the attribution
+    pub accent_color: [u8; 3],
+    /// egui font family name (`Proportional`, `Monospace` or a custom name)
+    pub font_family: String,
+    /// Largest passage font size, for a 1080 pixel wide card
+    pub font_size: f32,
+}
+
+impl Default for CardStyle {
+    fn default() -> Self {
+        Self::presets().remove(0).1
+    }
+}
+
+impl CardStyle {
+    /// Built-in styles (name, style).
+    pub fn presets() -> Vec<(&'static str, CardStyle)> {
+        let base = |background, background_end, text_color, accent_color| CardStyle {
+            width: 1080,
+            height: 1080,
+            background,
+            background_end,
+            text_color,
+            accent_color,
+            font_family: "Proportional".to_string(),
+            font_size: 64.0,
+        };
+        vec![
+            (
+                "Parchment",
+                base(
+                    [246, 239, 224],
+                    [232, 220, 196],
+                    [58, 46, 36],
+                    [150, 96, 52],
+                ),
+            ),
+            (
+                "Midnight",
+                base([24, 28, 48], [8, 10, 20], [236, 236, 244], [212, 175, 95]),
+            ),
+            (
+                "Minimal",
+                base(
+                    [255, 255, 255],
+                    [255, 255, 255],
+                    [20, 20, 20],
+                    [120, 120, 120],
+                ),
+            ),
+            (
+                "Dusk",
+                base(
+                    [92, 52, 112],
+                    [228, 122, 96],
+                    [255, 248, 240],
+                    [255, 222, 170],
+                ),
+            ),
+        ]
+    }
+
+    /// Factor applied to font sizes and spacing for this card's width.
+    fn scale(&self) -> f32 {
+        self.width as f32 / REFERENCE_WIDTH
+    }
+}
+
+/// A passage and its attribution, ready to be rendered.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuoteCard {
+    /// Passage, as selected in the editor
+    pub passage: String,
+    /// Attribution line, such as the author and book title
+    pub attribution: String,
+    /// Card style
+    pub style: CardStyle,
+}
+
+/// Fonts of one egui font family, primary font first.
+///
+/// Characters missing from the primary font are taken from the family's
+/// fallback fonts, as egui does.
+pub struct CardFont {
+    fonts: Vec<FontArc>,
+}
+
+impl CardFont {
+    /// Load the fonts of `family` from egui font definitions.
+    ///
+    /// `family` is `Proportional`, `Monospace` or the name of a custom family.
+    /// Returns `None` if the family has no usable font.
+    pub fn from_definitions(definitions: &egui::FontDefinitions, family: &str) -> Option<Self> {
+        let family = match family {
+            "Proportional" => egui::FontFamily::Proportional,
+            "Monospace" => egui::FontFamily::Monospace,
+            name => egui::FontFamily::Name(name.into()),
+        };
+        let fonts: Vec<FontArc> = definitions
+            .families
+            .get(&family)?
+            .iter()
+            .filter_map(|name| definitions.font_data.get(name))
+            .filter_map(|data| {
+                FontVec::try_from_vec_and_index(data.font.to_vec(), data.index)
+                    .ok()
+                    .map(FontArc::new)
+            })
+            .collect();
+
+        if fonts.is_empty() {
+            None
+        } else {
+            Some(Self { fonts })
+        }
+    }
+
+    /// Names of the font families defined in `definitions`.
+    pub fn family_names(definitions: &egui::FontDefinitions) -> Vec<String> {
+        definitions
+            .families
+            .keys()
+            .map(|family| match family {
+                egui::FontFamily::Proportional => "Proportional".to_string(),
+                egui::FontFamily::Monospace => "Monospace".to_string(),
+                egui::FontFamily::Name(name) => name.to_string(),
+            })
+            .collect()
+    }
+
+    /// Font and glyph used to draw `c`.
+    fn glyph(&self, c: char) -> (&FontArc, GlyphId) {
+        self.fonts
+            .iter()
+            .map(|font| (font, font.glyph_id(c)))
+            .find(|(_, id)| id.0 != 0)
+            .unwrap_or_else(|| (&self.fonts[0], self.fonts[0].glyph_id(c)))
+    }
+
+    /// Width of `text` at `size` pixels.
+    fn text_width(&self, text: &str, size: f32) -> f32 {
+        text.chars()
+            .map(|c| {
+                let (font, id) = self.glyph(c);
+                font.as_scaled(PxScale::from(size)).h_advance(id)
+            })
+            .sum()
+    }
+
+    /// Ascent and line height at `size` pixels, from the primary font.
+    fn metrics(&self, size: f32) -> (f32, f32) {
+        let font = self.fonts[0].as_scaled(PxScale::from(size));
+        (font.ascent(), font.height() + font.line_gap())
+    }
+}
+
+/// An RGBA image with unpremultiplied alpha.
+#[derive(Debug, Clone)]
+pub struct CardImage {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Pixels, row by row, four bytes each
+    pub pixels: Vec<u8>,
+}
+
+impl CardImage {
+    fn filled(style: &CardStyle) -> Self {
+        let (width, height) = (style.width.max(1), style.height.max(1));
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height {
+            let t = y as f32 / (height - 1).max(1) as f32;
+            let color = lerp_color(style.background, style.background_end, t);
+            for _ in 0..width {
+                pixels.extend_from_slice(&[color[0], color[1], color[2], 255]);
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Blend `color` over the pixel at (`x`, `y`) with the given coverage.
+    fn blend(&mut self, x: i32, y: i32, color: [u8; 3], coverage: f32) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let coverage = coverage.clamp(0.0, 1.0);
+        for (channel, value) in color.iter().enumerate() {
+            let dst = self.pixels[i + channel] as f32;
+            self.pixels[i + channel] = (dst + (*value as f32 - dst) * coverage).round() as u8;
+        }
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [u8; 3]) {
+        for py in y.round() as i32..(y + height).round() as i32 {
+            for px in x.round() as i32..(x + width).round() as i32 {
+                self.blend(px, py, color, 1.0);
+            }
+        }
+    }
+
+    /// Draw one line of text with its left end at `x` and its baseline at `baseline`.
+    fn draw_text(
+        &mut self,
+        font: &CardFont,
+        text: &str,
+        size: f32,
+        x: f32,
+        baseline: f32,
+        color: [u8; 3],
+    ) {
+        let mut caret = x;
+        for c in text.chars() {
+            let (face, id) = font.glyph(c);
+            let scaled = face.as_scaled(PxScale::from(size));
+            let glyph = id.with_scale_and_position(size, ab_glyph::point(caret, baseline));
+            caret += scaled.h_advance(id);
+
+            if let Some(outline) = face.outline_glyph(glyph) {
+                let bounds = outline.px_bounds();
+                outline.draw(|gx, gy, coverage| {
+                    self.blend(
+                        bounds.min.x as i32 + gx as i32,
+                        bounds.min.y as i32 + gy as i32,
+                        color,
+                        coverage,
+                    );
+                });
+            }
+        }
+    }
+
+    /// Encode the image as PNG.
+    pub fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut data, self.width, self.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder
+                .write_header()
+                .context("Failed to write PNG header")?;
+            writer
+                .write_image_data(&self.pixels)
+                .context("Failed to write PNG data")?;
+        }
+        Ok(data)
+    }
+}
+
+impl QuoteCard {
+    /// Render the card with `font`.
+    pub fn render(&self, font: &CardFont) -> CardImage {
+        let style = &self.style;
+        let mut image = CardImage::filled(style);
+        let scale = style.scale();
+        let margin = image.width.min(image.height) as f32 * 0.1;
+        let text_width = image.width as f32 - 2.0 * margin;
+        let text_height = image.height as f32 - 2.0 * margin;
+
+        let passage = format!("\u{201C}{}\u{201D}", clean_passage(&self.passage));
+        let attribution = self.attribution.trim();
+
+        // Shrink the passage until the whole block fits the card
+        let mut size = (style.font_size * scale).max(MIN_FONT_SIZE);
+        let (lines, size) = loop {
+            let lines = wrap_text(font, &passage, size, text_width);
+            let block = block_height(font, lines.len(), size, !attribution.is_empty());
+            if block <= text_height || size <= MIN_FONT_SIZE {
+                break (lines, size);
+            }
+            size = (size * 0.92).max(MIN_FONT_SIZE);
+        };
+
+        let (ascent, line_height) = font.metrics(size);
+        let line_height = line_height * 1.25;
+        let block = block_height(font, lines.len(), size, !attribution.is_empty());
+        let mut top = margin + ((text_height - block) / 2.0).max(0.0);
+
+        for line in &lines {
+            let width = font.text_width(line, size);
+            let x = (image.width as f32 - width) / 2.0;
+            image.draw_text(font, line, size, x, top + ascent, style.text_color);
+            top += line_height;
+        }
+
+        if !attribution.is_empty() {
+            let small = attribution_size(size);
+            let rule_width = image.width as f32 * 0.12;
+            let rule_height = (2.0 * scale).max(1.0);
+            top += small;
+            image.fill_rect(
+                (image.width as f32 - rule_width) / 2.0,
+                top,
+                rule_width,
+                rule_height,
+                style.accent_color,
+            );
+            top += small;
+
+            let (small_ascent, _) = font.metrics(small);
+            let line = format!("\u{2014} {}", attribution);
+            let width = font.text_width(&line, small);
+            let x = (image.width as f32 - width) / 2.0;
+            image.draw_text(
+                font,
+                &line,
+                small,
+                x,
+                top + small_ascent,
+                style.accent_color,
+            );
+        }
+
+        image
+    }
+}
+
+/// Attribution font size for a passage at `size`.
+fn attribution_size(size: f32) -> f32 {
+    (size * 0.55).max(MIN_FONT_SIZE)
+}
+
+/// Height of the passage lines plus the attribution block.
+fn block_height(font: &CardFont, lines: usize, size: f32, attribution: bool) -> f32 {
+    let (_, line_height) = font.metrics(size);
+    let mut height = lines as f32 * line_height * 1.25;
+    if attribution {
+        let small = attribution_size(size);
+        height += 2.0 * small + font.metrics(small).1;
+    }
+    height
+}
+
+/// Turn a Markdown passage into the text shown on a card.
+///
+/// Emphasis markers, heading and quote prefixes are dropped; lines of a
+/// paragraph are joined and paragraphs are kept apart.
+pub fn clean_passage(passage: &str) -> String {
+    passage
+        .split("\n\n")
+        .map(|paragraph| {
+            paragraph
+                .lines()
+                .map(|line| line.trim().trim_start_matches(['#', '>']).trim())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+                .replace(['*', '_'], "")
+        })
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Break `text` into lines no wider than `max_width` at `size` pixels.
+///
+/// Newlines start a new line; words wider than a line are split.
+pub fn wrap_text(font: &CardFont, text: &str, size: f32, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if font.text_width(&candidate, size) <= max_width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            for c in word.chars() {
+                line.push(c);
+                if font.text_width(&line, size) > max_width && line.chars().count() > 1 {
+                    line.pop();
+                    lines.push(std::mem::replace(&mut line, c.to_string()));
+                }
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn lerp_color(from: [u8; 3], to: [u8; 3], t: f32) -> [u8; 3] {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    [
+        mix(from[0], to[0]),
+        mix(from[1], to[1]),
+        mix(from[2], to[2]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font() -> CardFont {
+        CardFont::from_definitions(&egui::FontDefinitions::default(), "Proportional").unwrap()
+    }
+
+    #[test]
+    fn test_clean_passage() {
+        let passage = "> She *never* came back,\n> not even once.\n\n## __The end__";
+        assert_eq!(
+            clean_passage(passage),
+            "She never came back, not even once.\nThe end"
+        );
+    }
+
+    #[test]
+    fn test_wrap_text_fits_width() {
+        let font = font();
+        let text = "The night was long and the road was longer still";
+        let lines = wrap_text(&font, text, 32.0, 300.0);
+
+        assert!(lines.len() > 1);
+        assert_eq!(lines.join(" "), text);
+        for line in &lines {
+            assert!(font.text_width(line, 32.0) <= 300.0);
+        }
+    }
+
+    #[test]
+    fn test_wrap_text_splits_long_words() {
+        let font = font();
+        let lines = wrap_text(&font, "Supercalifragilisticexpialidocious", 32.0, 100.0);
+        assert!(lines.len() > 1);
+        assert_eq!(lines.concat(), "Supercalifragilisticexpialidocious");
+    }
+
+    #[test]
+    fn test_render_card() {
+        let card = QuoteCard {
+            passage: "All we have to decide is what to do with the time given to us.".into(),
+            attribution: "Jane Doe, The Road".into(),
+            style: CardStyle {
+                width: 400,
+                height: 300,
+                ..CardStyle::default()
+            },
+        };
+        let image = card.render(&font());
+
+        assert_eq!((image.width, image.height), (400, 300));
+        assert_eq!(image.pixels.len(), 400 * 300 * 4);
+        // Corners keep the background, the text covers some pixels
+        assert_eq!(&image.pixels[..3], &card.style.background);
+        let text_pixels = image
+            .pixels
+            .chunks(4)
+            .filter(|p| p[..3] == card.style.text_color)
+            .count();
+        assert!(text_pixels > 0);
+
+        let png = image.to_png().unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn test_unknown_family() {
+        let definitions = egui::FontDefinitions::default();
+        assert!(CardFont::from_definitions(&definitions, "Nope").is_none());
+        assert!(CardFont::family_names(&definitions).contains(&"Monospace".to_string()));
+    }
+}
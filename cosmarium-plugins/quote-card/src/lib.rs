@@ -0,0 +1,357 @@
+//! # Quote card plugin for Cosmarium
+//!
+//! Turns a passage selected in the editor into an image card for sharing:
+//! the passage is typeset on a styled background with an attribution line
+//! and exported as PNG to the project's `exports/quotes` directory. The
+//! style and attribution are kept in the plugin configuration.
+
+pub mod card;
+
+use card::{CardFont, CardImage, CardStyle, QuoteCard, CARD_SIZES};
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+};
+use egui::Ui;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Configuration key of the quote card settings.
+const CONFIG_KEY: &str = "quote_card";
+
+/// Settings remembered between sessions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct QuoteCardSettings {
+    /// Last attribution line
+    attribution: String,
+    /// Last card style
+    style: CardStyle,
+}
+
+#[derive(Default)]
+pub struct QuoteCardPlugin {
+    /// Card being edited
+    card: QuoteCard,
+    /// Fonts of the card's font family
+    font: Option<(String, CardFont)>,
+    /// Card the preview was rendered from
+    rendered: Option<QuoteCard>,
+    /// Last rendered image
+    image: Option<CardImage>,
+    /// Preview texture
+    preview: Option<egui::TextureHandle>,
+    /// Result of the last export
+    status: Option<String>,
+}
+
+impl QuoteCardPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Directory receiving the cards of `project`.
+    fn export_dir(project: &Path) -> PathBuf {
+        project.join("exports/quotes")
+    }
+
+    /// Make sure the fonts of the card's family are loaded.
+    fn ensure_font(&mut self, ui: &Ui) {
+        let family = &self.card.style.font_family;
+        if self.font.as_ref().is_some_and(|(name, _)| name == family) {
+            return;
+        }
+        let definitions = ui.ctx().fonts(|fonts| fonts.definitions().clone());
+        self.font = CardFont::from_definitions(&definitions, family)
+            .or_else(|| CardFont::from_definitions(&definitions, "Proportional"))
+            .map(|font| (family.clone(), font));
+    }
+
+    /// Render the card again if it changed since the last preview.
+    fn refresh_preview(&mut self, ui: &Ui) {
+        if self.rendered.as_ref() == Some(&self.card) {
+            return;
+        }
+        self.ensure_font(ui);
+        let Some((_, font)) = &self.font else {
+            return;
+        };
+
+        let image = self.card.render(font);
+        let color_image = egui::ColorImage::from_rgba_unmultiplied(
+            [image.width as usize, image.height as usize],
+            &image.pixels,
+        );
+        match &mut self.preview {
+            Some(texture) => texture.set(color_image, egui::TextureOptions::LINEAR),
+            None => {
+                self.preview = Some(ui.ctx().load_texture(
+                    "quote-card-preview",
+                    color_image,
+                    egui::TextureOptions::LINEAR,
+                ))
+            }
+        }
+        self.image = Some(image);
+        self.rendered = Some(self.card.clone());
+    }
+
+    fn save_settings(&self, ctx: &mut PluginContext) {
+        let settings = QuoteCardSettings {
+            attribution: self.card.attribution.clone(),
+            style: self.card.style.clone(),
+        };
+        ctx.set_config(CONFIG_KEY, &settings);
+    }
+
+    /// Write the rendered card to the project's export directory.
+    fn export(&self, project: &Path) -> anyhow::Result<PathBuf> {
+        let image = self
+            .image
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Nothing to export"))?;
+        let dir = Self::export_dir(project);
+        std::fs::create_dir_all(&dir)?;
+
+        let stem = card_file_stem(&self.card.passage);
+        let mut path = dir.join(format!("{}.png", stem));
+        let mut n = 2;
+        while path.exists() {
+            path = dir.join(format!("{}-{}.png", stem, n));
+            n += 1;
+        }
+        std::fs::write(&path, image.to_png()?)?;
+        Ok(path)
+    }
+
+    fn render_style(&mut self, ui: &mut Ui) {
+        let style = &mut self.card.style;
+
+        ui.horizontal(|ui| {
+            ui.label("Preset:");
+            egui::ComboBox::from_id_salt("quote_card_preset")
+                .selected_text("Apply…")
+                .show_ui(ui, |ui| {
+                    for (name, preset) in CardStyle::presets() {
+                        if ui.selectable_label(false, name).clicked() {
+                            // Presets change colors, not the size or typeface
+                            *style = CardStyle {
+                                width: style.width,
+                                height: style.height,
+                                font_family: style.font_family.clone(),
+                                font_size: style.font_size,
+                                ..preset
+                            };
+                        }
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Size:");
+            let current = CARD_SIZES
+                .iter()
+                .find(|(_, w, h)| (*w, *h) == (style.width, style.height))
+                .map_or("Custom", |(label, _, _)| *label);
+            egui::ComboBox::from_id_salt("quote_card_size")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for (label, width, height) in CARD_SIZES {
+                        if ui.selectable_label(current == *label, *label).clicked() {
+                            style.width = *width;
+                            style.height = *height;
+                        }
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Font:");
+            let families = ui
+                .ctx()
+                .fonts(|fonts| CardFont::family_names(fonts.definitions()));
+            egui::ComboBox::from_id_salt("quote_card_font")
+                .selected_text(&style.font_family)
+                .show_ui(ui, |ui| {
+                    for family in families {
+                        let selected = style.font_family == family;
+                        if ui.selectable_label(selected, &family).clicked() {
+                            style.font_family = family;
+                        }
+                    }
+                });
+            ui.add(egui::Slider::new(&mut style.font_size, 24.0..=120.0).text("px"));
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Background:");
+            ui.color_edit_button_srgb(&mut style.background);
+            ui.color_edit_button_srgb(&mut style.background_end);
+            ui.label("Text:");
+            ui.color_edit_button_srgb(&mut style.text_color);
+            ui.label("Accent:");
+            ui.color_edit_button_srgb(&mut style.accent_color);
+        });
+    }
+}
+
+/// File name stem for a card, from the first words of its passage.
+fn card_file_stem(passage: &str) -> String {
+    let words: Vec<String> = card::clean_passage(passage)
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .take(6)
+        .collect();
+
+    if words.is_empty() {
+        "quote".to_string()
+    } else {
+        format!("quote-{}", words.join("-"))
+    }
+}
+
+impl Plugin for QuoteCardPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            "quote-card",
+            "0.1.0",
+            "Quote card images for sharing passages",
+            "Cosmarium Team",
+        )
+        .with_dependency("markdown-editor")
+    }
+
+    fn initialize(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        if let Some(settings) = ctx.get_config::<QuoteCardSettings>(CONFIG_KEY) {
+            self.card.attribution = settings.attribution;
+            self.card.style = settings.style;
+        }
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+
+    fn update(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl PanelPlugin for QuoteCardPlugin {
+    fn panel_title(&self) -> &str {
+        "Quote Card"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "🖼"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Right
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        let before = (self.card.attribution.clone(), self.card.style.clone());
+        let selection = ctx
+            .get_shared_state::<String>("markdown_editor_selection")
+            .unwrap_or_default();
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Passage");
+                if ui
+                    .add_enabled(
+                        !selection.trim().is_empty(),
+                        egui::Button::new("Use selection"),
+                    )
+                    .on_disabled_hover_text("Select a passage in the editor")
+                    .clicked()
+                {
+                    self.card.passage = selection.trim().to_string();
+                }
+            });
+            ui.add(
+                egui::TextEdit::multiline(&mut self.card.passage)
+                    .desired_rows(4)
+                    .desired_width(f32::INFINITY),
+            );
+            ui.horizontal(|ui| {
+                ui.label("Attribution:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.card.attribution)
+                        .hint_text("Author, Title"),
+                );
+            });
+
+            ui.separator();
+            self.render_style(ui);
+            ui.separator();
+
+            if self.card.passage.trim().is_empty() {
+                ui.label("Select a passage in the editor, then click \"Use selection\".");
+                return;
+            }
+
+            self.refresh_preview(ui);
+            if let Some(texture) = &self.preview {
+                ui.add(egui::Image::new(texture).max_width(ui.available_width()));
+            }
+
+            let project = ctx.project_path();
+            let export = ui
+                .add_enabled(project.is_some(), egui::Button::new("Export PNG"))
+                .on_disabled_hover_text("Open a project to export cards");
+            if export.clicked() {
+                if let Some(project) = project {
+                    self.status = Some(match self.export(&project) {
+                        Ok(path) => {
+                            tracing::info!("Quote card exported to {:?}", path);
+                            format!("Saved {}", path.display())
+                        }
+                        Err(e) => format!("Export failed: {}", e),
+                    });
+                }
+            }
+            if let Some(status) = &self.status {
+                ui.label(status);
+            }
+        });
+
+        if before != (self.card.attribution.clone(), self.card.style.clone()) {
+            self.save_settings(ctx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_card_file_stem() {
+        assert_eq!(
+            card_file_stem("*Call* me Ishmael. Some years ago, never mind how long"),
+            "quote-call-me-ishmael-some-years-ago"
+        );
+        assert_eq!(card_file_stem("…"), "quote");
+    }
+
+    #[test]
+    fn test_settings_roundtrip() {
+        let mut ctx = PluginContext::new();
+        let mut plugin = QuoteCardPlugin::new();
+        plugin.card.attribution = "Jane Doe".to_string();
+        plugin.card.style.font_size = 80.0;
+        plugin.save_settings(&mut ctx);
+
+        let mut restored = QuoteCardPlugin::new();
+        restored.initialize(&mut ctx).unwrap();
+        assert_eq!(restored.card.attribution, "Jane Doe");
+        assert_eq!(restored.card.style.font_size, 80.0);
+    }
+}
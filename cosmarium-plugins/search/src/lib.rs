@@ -0,0 +1,649 @@
+//! # Search plugin for Cosmarium
+//!
+//! Searches the whole project from a side panel: the text of every
+//! document, open or not, their annotations, the research notes, the entity
+//! sheets, the synopses and the metadata. Each match is shown with the lines
+//! around it, and clicking it opens its document at the match. The recent
+//! projects can be searched too; a match in one of them opens its project.
+//!
+//! In replace mode, the panel previews every change a replacement would make
+//! to the documents, notes and entity sheets, each of which can be left out,
+//! then replaces the others in one step that can be undone.
+//!
+//! The application owns the index and keeps it up to date as documents
+//! change; the panel only posts the query and shows the results.
+
+use cosmarium_plugin_api::search::{
+    ReplaceMatch, ReplacePreview, ReplaceQuery, ReplaceRequest, ReplaceStatus, SearchHit,
+    SearchQuery, SearchResponse, SearchSource, REPLACE_REQUEST, REPLACE_STATUS_KEY, SEARCH_REQUEST,
+    SEARCH_RESULTS_KEY,
+};
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result, Shortcut,
+    FOCUS_PANEL_REQUEST,
+};
+use egui::text::LayoutJob;
+use egui::{TextFormat, Ui};
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Name of the plugin and of its panel.
+pub const PLUGIN_NAME: &str = "search";
+
+/// Identifier of the command showing the panel, bound to Ctrl+Shift+F.
+pub const SEARCH_COMMAND: &str = "search.show";
+
+#[derive(Default)]
+pub struct SearchPlugin {
+    /// Query being edited
+    query: SearchQuery,
+    /// Whether a search was posted and its results are awaited
+    pending: bool,
+    /// Results of the last search
+    response: Option<SearchResponse>,
+    /// Whether the panel replaces the text rather than searching for it
+    replacing: bool,
+    /// Text replacing the query text
+    replacement: String,
+    /// Whether only whole words are replaced
+    whole_word: bool,
+    /// Whether a replacement step was posted and its outcome is awaited
+    replace_pending: bool,
+    /// Matches of the replacement being previewed
+    preview: Option<ReplacePreview>,
+    /// Outcome of the last replacement applied or undone
+    replace_outcome: Option<ReplaceStatus>,
+}
+
+impl SearchPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Post the query to the application.
+    fn search(&mut self, ctx: &mut PluginContext) {
+        if self.query.text.trim().is_empty() {
+            return;
+        }
+        ctx.set_shared_state(SEARCH_REQUEST, Some(self.query.clone()));
+        // Results still published are those of a previous search
+        ctx.set_shared_state::<Option<SearchResponse>>(SEARCH_RESULTS_KEY, None);
+        self.pending = true;
+    }
+
+    /// Post a replacement step to the application.
+    fn post_replace(&mut self, ctx: &mut PluginContext, request: ReplaceRequest) {
+        ctx.set_shared_state(REPLACE_REQUEST, Some(request));
+        ctx.set_shared_state::<Option<ReplaceStatus>>(REPLACE_STATUS_KEY, None);
+        self.replace_pending = true;
+    }
+
+    /// The replacement of the query text.
+    fn replace_query(&self) -> ReplaceQuery {
+        ReplaceQuery {
+            find: self.query.text.clone(),
+            replace: self.replacement.clone(),
+            case_sensitive: self.query.case_sensitive,
+            whole_word: self.whole_word,
+            sources: self.query.sources.clone(),
+            folder: self.query.folder.clone(),
+        }
+    }
+
+    fn render_query(&mut self, ui: &mut Ui) -> bool {
+        let mut search = false;
+        ui.horizontal(|ui| {
+            ui.toggle_value(&mut self.replacing, "⇄")
+                .on_hover_text("Replace in the project");
+            let hint = if self.replacing {
+                "Replace..."
+            } else {
+                "Search for..."
+            };
+            let response = ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.query.text)
+                        .hint_text(hint)
+                        .desired_width(ui.available_width() - 30.0),
+                )
+                .on_hover_text(if self.replacing {
+                    "Text replaced exactly as typed"
+                } else {
+                    "Quote phrases (\"the old inn\"), combine words with AND, OR and NOT, \
+                     or exclude them with -word"
+                });
+            search |= response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if self.pending || self.replace_pending {
+                ui.spinner();
+            } else if !self.replacing {
+                search |= ui.button("🔍").on_hover_text("Search").clicked();
+            }
+        });
+        if self.replacing {
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.replacement)
+                        .hint_text("With...")
+                        .desired_width(ui.available_width() - 30.0),
+                );
+                search |= response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if !self.replace_pending {
+                    search |= ui
+                        .button("👁")
+                        .on_hover_text("Preview the replacement")
+                        .clicked();
+                }
+            });
+        }
+
+        egui::CollapsingHeader::new("Options")
+            .id_salt("search_options")
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for source in SearchSource::ALL {
+                        let mut checked = self.query.sources.contains(&source);
+                        if ui.checkbox(&mut checked, source.label()).changed() {
+                            if checked {
+                                self.query.sources.insert(source);
+                            } else {
+                                self.query.sources.remove(&source);
+                            }
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Leave every source unchecked to search them all");
+                ui.horizontal(|ui| {
+                    ui.label("Within:");
+                    let mut folder = self.query.folder.clone().unwrap_or_default();
+                    if ui
+                        .add(
+                            egui::TextEdit::singleline(&mut folder)
+                                .hint_text("entities/characters")
+                                .desired_width(ui.available_width()),
+                        )
+                        .changed()
+                    {
+                        self.query.folder = Some(folder).filter(|f| !f.trim().is_empty());
+                    }
+                });
+                ui.checkbox(&mut self.query.case_sensitive, "Match case");
+                if self.replacing {
+                    ui.checkbox(&mut self.whole_word, "Whole words only");
+                } else {
+                    ui.checkbox(&mut self.query.all_projects, "Search recent projects too")
+                        .on_hover_text("Clicking a match in another project opens that project");
+                }
+            });
+        search
+    }
+
+    /// Show the matches of the replacement being previewed, each as the
+    /// change it makes to its line, and the outcome of the last one.
+    /// Returns the step to post and the file and line of the match clicked.
+    fn render_preview(
+        &mut self,
+        ui: &mut Ui,
+    ) -> (Option<ReplaceRequest>, Option<(PathBuf, usize)>) {
+        let (mut request, mut open) = (None, None);
+        if let Some(outcome) = &self.replace_outcome {
+            ui.separator();
+            match outcome {
+                ReplaceStatus::Applied { matches, documents } => {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(format!(
+                            "Replaced {} matches in {} documents.",
+                            matches, documents
+                        ));
+                        if ui
+                            .add_enabled(!self.replace_pending, egui::Button::new("Undo"))
+                            .clicked()
+                        {
+                            request = Some(ReplaceRequest::Undo);
+                        }
+                    });
+                }
+                ReplaceStatus::Undone { documents } => {
+                    ui.label(format!("Restored {} documents.", documents));
+                }
+                ReplaceStatus::Failed(error) => {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                ReplaceStatus::Preview(_) => {}
+            }
+        }
+
+        let Some(preview) = &mut self.preview else {
+            return (request, open);
+        };
+        ui.separator();
+        if preview.is_empty() {
+            ui.label("Nothing found.");
+            return (request, open);
+        }
+        ui.horizontal_wrapped(|ui| {
+            ui.label(format!(
+                "{} of {} matches in {} files",
+                preview.selected(),
+                preview.len(),
+                preview.files.len()
+            ));
+            let label = format!("Replace {}", preview.selected());
+            if ui
+                .add_enabled(
+                    preview.selected() > 0 && !self.replace_pending,
+                    egui::Button::new(label),
+                )
+                .clicked()
+            {
+                request = Some(ReplaceRequest::Apply(preview.clone()));
+            }
+        });
+
+        let replacement = preview.query.replace.clone();
+        let root = preview.root.clone();
+        egui::ScrollArea::vertical()
+            .id_salt("replace_preview")
+            .show(ui, |ui| {
+                for file in &mut preview.files {
+                    let title = format!(
+                        "{} ({}/{})",
+                        file.title,
+                        file.selected(),
+                        file.matches.len()
+                    );
+                    egui::CollapsingHeader::new(title)
+                        .id_salt(("replace_file", &file.path))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.weak(&file.path);
+                                for (label, selected) in [("All", true), ("None", false)] {
+                                    if ui.small_button(label).clicked() {
+                                        for found in &mut file.matches {
+                                            found.selected = selected;
+                                        }
+                                    }
+                                }
+                            });
+                            for found in &mut file.matches {
+                                ui.horizontal(|ui| {
+                                    ui.checkbox(&mut found.selected, "");
+                                    if ui.link(found.line.to_string()).clicked() {
+                                        let path = file
+                                            .path
+                                            .split('/')
+                                            .fold(root.clone(), |path, part| path.join(part));
+                                        open = Some((path, found.line));
+                                    }
+                                    ui.add(
+                                        egui::Label::new(change_job(ui, found, &replacement))
+                                            .truncate(),
+                                    );
+                                });
+                            }
+                        });
+                }
+            });
+        (request, open)
+    }
+
+    /// Show the results of the last search, returning the file and line of
+    /// the match clicked.
+    fn render_results(&self, ui: &mut Ui) -> Option<(PathBuf, usize)> {
+        let response = self.response.as_ref()?;
+        ui.separator();
+        if let Some(error) = &response.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+            return None;
+        }
+        if response.hits.is_empty() {
+            ui.label("Nothing found.");
+            return None;
+        }
+        ui.label(format!("{} matches", response.hits.len()));
+
+        let words = response.query.words();
+        let case_sensitive = response.query.case_sensitive;
+        let mut open = None;
+        egui::ScrollArea::vertical()
+            .id_salt("search_results")
+            .show(ui, |ui| {
+                for (source, hits) in response.groups() {
+                    egui::CollapsingHeader::new(format!("{} ({})", source.label(), hits.len()))
+                        .id_salt(("search_group", source))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            for hit in hits {
+                                let file = response.file(hit);
+                                if Self::render_hit(ui, hit, file.is_some(), &words, case_sensitive)
+                                {
+                                    open = file.map(|file| (file, hit.line.unwrap_or(0)));
+                                }
+                                ui.add_space(4.0);
+                            }
+                        });
+                }
+            });
+        open
+    }
+
+    /// Show a match with its context, returning whether it was clicked.
+    fn render_hit(
+        ui: &mut Ui,
+        hit: &SearchHit,
+        in_file: bool,
+        words: &[&str],
+        case_sensitive: bool,
+    ) -> bool {
+        let mut location = hit.title.clone();
+        if let Some(project) = hit.project.as_deref().and_then(|p| p.file_name()) {
+            location = format!("{} › {}", project.to_string_lossy(), location);
+        }
+        if let Some(field) = &hit.field {
+            location = format!("{} · {}", location, field);
+        }
+        if let Some(line) = hit.line {
+            location = format!("{}:{}", location, line);
+        }
+        let mut clicked = false;
+        if in_file {
+            clicked |= ui
+                .link(location)
+                .on_hover_text(hit.path.clone().unwrap_or_default())
+                .clicked();
+        } else {
+            ui.strong(location);
+        }
+
+        let context = |ui: &mut Ui, line: &Option<String>| {
+            if let Some(line) = line {
+                ui.add(egui::Label::new(egui::RichText::new(line).weak().small()).truncate());
+            }
+        };
+        context(ui, &hit.before);
+        let style = ui.style().clone();
+        let mut job = LayoutJob::default();
+        let mut end = 0;
+        for range in match_ranges(&hit.snippet, words, case_sensitive) {
+            job.append(
+                &hit.snippet[end..range.start],
+                0.0,
+                text_format(&style, false),
+            );
+            job.append(&hit.snippet[range.clone()], 0.0, text_format(&style, true));
+            end = range.end;
+        }
+        job.append(&hit.snippet[end..], 0.0, text_format(&style, false));
+        let snippet = ui.add(egui::Label::new(job).sense(egui::Sense::click()));
+        if in_file {
+            clicked |= snippet
+                .on_hover_cursor(egui::CursorIcon::PointingHand)
+                .clicked();
+        }
+        context(ui, &hit.after);
+        clicked
+    }
+}
+
+fn text_format(style: &egui::Style, highlighted: bool) -> TextFormat {
+    let mut format = TextFormat {
+        font_id: egui::TextStyle::Body.resolve(style),
+        color: style.visuals.text_color(),
+        ..Default::default()
+    };
+    if highlighted {
+        format.color = style.visuals.strong_text_color();
+        format.background = style.visuals.selection.bg_fill;
+    }
+    format
+}
+
+/// `found` shown as the change it makes to its line: the text matched struck
+/// out, followed by `replacement`.
+fn change_job(ui: &Ui, found: &ReplaceMatch, replacement: &str) -> LayoutJob {
+    let style = ui.style();
+    let mut job = LayoutJob::default();
+    job.append(&found.prefix, 0.0, text_format(style, false));
+    let mut removed = text_format(style, false);
+    removed.strikethrough = egui::Stroke::new(1.0, style.visuals.error_fg_color);
+    removed.color = style.visuals.error_fg_color;
+    job.append(&found.found, 0.0, removed);
+    job.append(replacement, 0.0, text_format(style, true));
+    job.append(&found.suffix, 0.0, text_format(style, false));
+    job
+}
+
+/// Byte ranges of the occurrences of `words` in `text`, in order and
+/// without overlaps.
+fn match_ranges(text: &str, words: &[&str], case_sensitive: bool) -> Vec<Range<usize>> {
+    let fold = |s: &str| {
+        if case_sensitive {
+            s.to_string()
+        } else {
+            s.to_lowercase()
+        }
+    };
+    // Folding case can change lengths, so matches are looked for char by char
+    let starts: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+    let words: Vec<String> = words.iter().map(|word| fold(word)).collect();
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < starts.len() {
+        let rest = &text[starts[i]..];
+        let found = words.iter().find_map(|word| {
+            let len = rest
+                .char_indices()
+                .map(|(j, c)| j + c.len_utf8())
+                .find(|&len| fold(&rest[..len]).len() >= word.len())?;
+            (fold(&rest[..len]) == *word).then_some(len)
+        });
+        match found {
+            Some(len) => {
+                ranges.push(starts[i]..starts[i] + len);
+                i = starts.partition_point(|&start| start < starts[i] + len);
+            }
+            None => i += 1,
+        }
+    }
+    ranges
+}
+
+impl Plugin for SearchPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            PLUGIN_NAME,
+            "0.1.0",
+            "Full-text search across the project",
+            "Cosmarium Team",
+        )
+    }
+
+    fn initialize(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        ctx.register_command(
+            SEARCH_COMMAND,
+            "Search Project",
+            Some(Shortcut::ctrl("F").with_shift()),
+            |ctx: &mut PluginContext| {
+                ctx.set_shared_state(FOCUS_PANEL_REQUEST, Some(PLUGIN_NAME.to_string()))
+            },
+        );
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+
+    fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        if self.replace_pending {
+            if let Some(status) = ctx
+                .get_shared_state::<Option<ReplaceStatus>>(REPLACE_STATUS_KEY)
+                .flatten()
+            {
+                // Applied or not, the offsets of the previewed matches are stale
+                match status {
+                    ReplaceStatus::Preview(preview) => {
+                        self.preview = Some(preview);
+                        self.replace_outcome = None;
+                    }
+                    outcome => {
+                        self.preview = None;
+                        self.replace_outcome = Some(outcome);
+                    }
+                }
+                self.replace_pending = false;
+            }
+        }
+        if self.pending {
+            if let Some(response) = ctx
+                .get_shared_state::<Option<SearchResponse>>(SEARCH_RESULTS_KEY)
+                .flatten()
+            {
+                self.response = Some(response);
+                self.pending = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PanelPlugin for SearchPlugin {
+    fn panel_title(&self) -> &str {
+        "Search"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "🔍"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Left
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        if self.render_query(ui) {
+            if !self.replacing {
+                self.search(ctx);
+            } else if !self.query.text.is_empty() {
+                self.post_replace(ctx, ReplaceRequest::Preview(self.replace_query()));
+            }
+        }
+        let open = if self.replacing {
+            let (request, open) = self.render_preview(ui);
+            if let Some(request) = request {
+                self.post_replace(ctx, request);
+            }
+            open
+        } else {
+            self.render_results(ui)
+        };
+        if let Some((path, line)) = open {
+            tracing::debug!("Opening search match in {:?} at line {}", path, line);
+            ctx.set_shared_state("open_document_request", Some((path, line)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_results_of_the_posted_search() {
+        let mut ctx = PluginContext::new();
+        let mut plugin = SearchPlugin::new();
+        let old = SearchResponse {
+            query: SearchQuery::new("fog"),
+            root: PathBuf::from("/novel"),
+            hits: Vec::new(),
+            error: None,
+        };
+        ctx.set_shared_state(SEARCH_RESULTS_KEY, Some(old));
+
+        // Nothing is searched for blank queries
+        plugin.search(&mut ctx);
+        assert!(!plugin.pending);
+
+        plugin.query.text = "inn".to_string();
+        plugin.search(&mut ctx);
+        assert_eq!(
+            ctx.get_shared_state::<Option<SearchQuery>>(SEARCH_REQUEST),
+            Some(Some(SearchQuery::new("inn")))
+        );
+        Plugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(plugin.pending && plugin.response.is_none());
+
+        let response = SearchResponse {
+            query: SearchQuery::new("inn"),
+            root: PathBuf::from("/novel"),
+            hits: Vec::new(),
+            error: Some("Open a project before searching it".to_string()),
+        };
+        ctx.set_shared_state(SEARCH_RESULTS_KEY, Some(response.clone()));
+        Plugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(!plugin.pending);
+        assert_eq!(plugin.response, Some(response));
+    }
+
+    #[test]
+    fn test_replace_steps() {
+        let mut ctx = PluginContext::new();
+        let mut plugin = SearchPlugin::new();
+        plugin.replacing = true;
+        plugin.query.text = "inn".to_string();
+        plugin.replacement = "tavern".to_string();
+        plugin.post_replace(&mut ctx, ReplaceRequest::Preview(plugin.replace_query()));
+        assert_eq!(
+            ctx.get_shared_state::<Option<ReplaceRequest>>(REPLACE_REQUEST),
+            Some(Some(ReplaceRequest::Preview(ReplaceQuery::new(
+                "inn", "tavern"
+            ))))
+        );
+
+        let preview = ReplacePreview {
+            query: ReplaceQuery::new("inn", "tavern"),
+            root: PathBuf::from("/novel"),
+            files: Vec::new(),
+        };
+        ctx.set_shared_state(
+            REPLACE_STATUS_KEY,
+            Some(ReplaceStatus::Preview(preview.clone())),
+        );
+        Plugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(!plugin.replace_pending);
+        assert_eq!(plugin.preview, Some(preview.clone()));
+
+        // The preview is dropped once applied
+        plugin.post_replace(&mut ctx, ReplaceRequest::Apply(preview));
+        Plugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(plugin.replace_pending && plugin.preview.is_some());
+        let applied = ReplaceStatus::Applied {
+            matches: 3,
+            documents: 2,
+        };
+        ctx.set_shared_state(REPLACE_STATUS_KEY, Some(applied.clone()));
+        Plugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(plugin.preview.is_none());
+        assert_eq!(plugin.replace_outcome, Some(applied));
+    }
+
+    #[test]
+    fn test_match_ranges() {
+        let text = "The Inn, the old inn: innkeepers";
+        assert_eq!(
+            match_ranges(text, &["inn"], false),
+            vec![4..7, 17..20, 22..25]
+        );
+        assert_eq!(match_ranges(text, &["inn"], true), vec![17..20, 22..25]);
+        assert_eq!(
+            match_ranges(text, &["the old", "the"], false),
+            vec![0..3, 9..16]
+        );
+        assert_eq!(
+            match_ranges("Élan, élan", &["élan"], false),
+            vec![0..5, 7..12]
+        );
+        assert!(match_ranges(text, &[], false).is_empty());
+    }
+}
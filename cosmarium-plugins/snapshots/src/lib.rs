@@ -0,0 +1,315 @@
+//! # Document snapshots plugin for Cosmarium
+//!
+//! Keeps the text of the active document under a label, "Before rewrite of
+//! ch. 3" for instance, and lists the snapshots kept of it, newest first.
+//! A snapshot can be read as it was, compared with the text now, line by
+//! line, or restored.
+//!
+//! The snapshots are kept by the application (see
+//! [`document_snapshot`](cosmarium_plugin_api::document_snapshot)). Restoring
+//! one replaces the text in the editor as a single undo step, and first
+//! keeps the text it replaces as a snapshot of its own.
+
+use cosmarium_plugin_api::diff::Comparison;
+use cosmarium_plugin_api::document_snapshot::{
+    DocumentSnapshot, DocumentSnapshotRequest, DocumentSnapshots, DOCUMENT_SNAPSHOTS_KEY,
+    DOCUMENT_SNAPSHOT_REQUEST,
+};
+use cosmarium_plugin_api::grammar::text_hash;
+use cosmarium_plugin_api::locale::{DateStyle, Locale, LOCALE_KEY};
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+};
+use egui::{RichText, Ui};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Name of the plugin and of its panel.
+pub const PLUGIN_NAME: &str = "snapshots";
+
+/// Unchanged lines shown around the lines that changed.
+const DIFF_CONTEXT: usize = 3;
+
+/// How a snapshot is shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum View {
+    /// Its text, as it was
+    #[default]
+    Text,
+    /// The lines changed since
+    Changes,
+}
+
+#[derive(Default)]
+pub struct SnapshotsPlugin {
+    /// Snapshots of the active document, as published
+    snapshots: Option<Arc<DocumentSnapshots>>,
+    /// Label of the next snapshot
+    label: String,
+    selected: Option<Uuid>,
+    view: View,
+    /// Snapshot whose deletion awaits confirmation
+    deleting: Option<Uuid>,
+    /// Changes from a snapshot to the text now, with the snapshot and the
+    /// hash of the text
+    comparison: Option<(Uuid, u64, Comparison)>,
+}
+
+impl SnapshotsPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send `request` to the application.
+    fn request(ctx: &mut PluginContext, request: DocumentSnapshotRequest) {
+        ctx.set_shared_state(DOCUMENT_SNAPSHOT_REQUEST, Some(request));
+    }
+
+    /// The changes from `snapshot` to `current`, compared again only when
+    /// either changed.
+    fn compare(&mut self, snapshot: &DocumentSnapshot, current: &str) -> &Comparison {
+        let hash = text_hash(current);
+        if self
+            .comparison
+            .as_ref()
+            .is_some_and(|(id, compared, _)| *id != snapshot.id || *compared != hash)
+        {
+            self.comparison = None;
+        }
+        let (_, _, comparison) = self.comparison.get_or_insert_with(|| {
+            let comparison = Comparison::new(&snapshot.content, current, DIFF_CONTEXT);
+            (snapshot.id, hash, comparison)
+        });
+        comparison
+    }
+
+    /// Label to keep the text now under, and the button keeping it.
+    fn render_take(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.label)
+                    .hint_text("Label, e.g. Before rewrite of ch. 3")
+                    .desired_width(200.0),
+            );
+            let entered =
+                response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            let take = ui
+                .add_enabled(
+                    !self.label.trim().is_empty(),
+                    egui::Button::new("Take Snapshot"),
+                )
+                .on_hover_text("Keep the text of the document now under this label");
+            if (take.clicked() || entered) && !self.label.trim().is_empty() {
+                let label = std::mem::take(&mut self.label).trim().to_string();
+                Self::request(ctx, DocumentSnapshotRequest::Take(label));
+            }
+        });
+    }
+
+    /// Snapshots of the document, newest first.
+    fn render_list(&mut self, ui: &mut Ui, snapshots: &DocumentSnapshots, locale: &Locale) {
+        egui::ScrollArea::vertical()
+            .id_salt("snapshots_list")
+            .max_height(160.0)
+            .show(ui, |ui| {
+                for snapshot in &snapshots.snapshots {
+                    let date = locale.format_date_time(
+                        snapshot
+                            .taken_at
+                            .with_timezone(&chrono::Local)
+                            .naive_local(),
+                        DateStyle::Short,
+                    );
+                    let selected = self.selected == Some(snapshot.id);
+                    let button = egui::Button::selectable(selected, snapshot.label.as_str())
+                        .shortcut_text(
+                            RichText::new(format!(
+                                "{} · {} words",
+                                date,
+                                locale.format_count(snapshot.words())
+                            ))
+                            .weak(),
+                        )
+                        .min_size(egui::vec2(ui.available_width(), 0.0));
+                    if ui.add(button).clicked() {
+                        self.selected = (!selected).then_some(snapshot.id);
+                        self.deleting = None;
+                    }
+                }
+            });
+    }
+
+    /// Actions on the selected snapshot.
+    fn render_actions(
+        &mut self,
+        ui: &mut Ui,
+        ctx: &mut PluginContext,
+        snapshot: &DocumentSnapshot,
+    ) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.view, View::Text, "Text");
+            ui.selectable_value(&mut self.view, View::Changes, "Changes");
+            ui.separator();
+            if ui
+                .button("Restore")
+                .on_hover_text(
+                    "Replace the text of the document with this snapshot, keeping the text now as a snapshot",
+                )
+                .clicked()
+            {
+                Self::request(ctx, DocumentSnapshotRequest::Restore(snapshot.id));
+            }
+            if self.deleting == Some(snapshot.id) {
+                ui.label("Delete it?");
+                if ui.button("Delete").clicked() {
+                    Self::request(ctx, DocumentSnapshotRequest::Delete(snapshot.id));
+                    self.deleting = None;
+                    self.selected = None;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.deleting = None;
+                }
+            } else if ui.button("Delete…").clicked() {
+                self.deleting = Some(snapshot.id);
+            }
+        });
+    }
+
+    /// Lines changed since `snapshot`.
+    fn render_changes(&mut self, ui: &mut Ui, snapshot: &DocumentSnapshot, current: &str) {
+        let comparison = self.compare(snapshot, current);
+        if comparison.is_unchanged() {
+            ui.weak("The text has not changed since this snapshot.");
+            return;
+        }
+        ui.label(format!(
+            "{} lines removed, {} added since",
+            comparison.removed, comparison.added
+        ));
+        comparison.show(ui, "snapshots_changes");
+    }
+}
+
+impl Plugin for SnapshotsPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            PLUGIN_NAME,
+            "0.1.0",
+            "Labeled snapshots of a document, to compare with and restore",
+            "Cosmarium Team",
+        )
+    }
+
+    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+}
+
+impl PanelPlugin for SnapshotsPlugin {
+    fn panel_title(&self) -> &str {
+        "Snapshots"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "📷"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Right
+    }
+
+    fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        self.snapshots = ctx
+            .get_shared_state::<Option<Arc<DocumentSnapshots>>>(DOCUMENT_SNAPSHOTS_KEY)
+            .flatten();
+        let kept = |id: Uuid| {
+            self.snapshots
+                .as_ref()
+                .is_some_and(|snapshots| snapshots.get(id).is_some())
+        };
+        if !self.selected.is_some_and(kept) {
+            self.selected = None;
+        }
+        if !self.deleting.is_some_and(kept) {
+            self.deleting = None;
+        }
+        if self.selected.is_none() {
+            self.comparison = None;
+        }
+        Ok(())
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        let Some(snapshots) = self.snapshots.clone() else {
+            ui.label("Open a document of the project to keep snapshots of it.");
+            return;
+        };
+        let locale = ctx
+            .get_shared_state::<Locale>(LOCALE_KEY)
+            .unwrap_or_default();
+
+        self.render_take(ui, ctx);
+        ui.separator();
+        if snapshots.snapshots.is_empty() {
+            ui.weak("No snapshot of this document yet. Take one before a revision, to compare with or go back to.");
+            return;
+        }
+        self.render_list(ui, &snapshots, &locale);
+
+        let Some(snapshot) = self.selected.and_then(|id| snapshots.get(id)) else {
+            return;
+        };
+        ui.separator();
+        self.render_actions(ui, ctx, snapshot);
+        match self.view {
+            View::Text => {
+                egui::ScrollArea::vertical()
+                    .id_salt("snapshots_text")
+                    .show(ui, |ui| {
+                        ui.label(snapshot.content.as_str());
+                    });
+            }
+            View::Changes => {
+                let current = ctx
+                    .get_shared_state::<String>("markdown_editor_content")
+                    .unwrap_or_default();
+                self.render_changes(ui, snapshot, &current);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_follows_published_snapshots() {
+        let mut ctx = PluginContext::new();
+        let mut plugin = SnapshotsPlugin::new();
+        let snapshot = DocumentSnapshot::new("Draft", "It rained.", chrono::Utc::now());
+        let snapshots = DocumentSnapshots {
+            path: "/novel/content/inn.md".into(),
+            snapshots: vec![snapshot.clone()],
+        };
+        ctx.set_shared_state(DOCUMENT_SNAPSHOTS_KEY, Some(Arc::new(snapshots)));
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        plugin.selected = Some(snapshot.id);
+
+        let comparison = plugin.compare(&snapshot, "It rained.\nThe inn was full.");
+        assert_eq!((comparison.removed, comparison.added), (0, 1));
+
+        // Deleted elsewhere
+        ctx.set_shared_state(
+            DOCUMENT_SNAPSHOTS_KEY,
+            Some(Arc::new(DocumentSnapshots::default())),
+        );
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(plugin.selected.is_none());
+        assert!(plugin.comparison.is_none());
+    }
+}
@@ -0,0 +1,348 @@
+//! # Writing sprint plugin for Cosmarium
+//!
+//! A timer for sprints of 15 or 25 minutes of writing. The words written
+//! during a sprint are the change of the session's words the application
+//! publishes with the writing history. While a sprint runs in focus, the
+//! application hides the other panels and holds its announcements back; when
+//! it ends, the sprint goes to the writing history.
+
+use chrono::{DateTime, Utc};
+use cosmarium_plugin_api::history::{WritingHistory, WRITING_HISTORY_KEY};
+use cosmarium_plugin_api::locale::{DateStyle, Locale, LOCALE_KEY};
+use cosmarium_plugin_api::sprint::{
+    Sprint, SprintRecord, SPRINT_KEY, SPRINT_RECORD_REQUEST, SPRINT_STOP_REQUEST,
+};
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+};
+use egui::Ui;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Name of the plugin and of its panel.
+pub const PLUGIN_NAME: &str = "sprint";
+
+/// Configuration key for [`SprintSettings`], under the plugin's name.
+pub const CONFIG_KEY: &str = PLUGIN_NAME;
+
+/// Lengths of sprint offered, in minutes.
+const LENGTHS: [u32; 2] = [15, 25];
+
+/// Past sprints listed.
+const MAX_SPRINTS: usize = 10;
+
+/// Settings of the next sprints.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SprintSettings {
+    /// Length of a sprint, in minutes
+    pub minutes: u32,
+    /// Whether to hide the other panels during a sprint
+    pub focus: bool,
+}
+
+impl Default for SprintSettings {
+    fn default() -> Self {
+        Self {
+            minutes: 25,
+            focus: true,
+        }
+    }
+}
+
+/// A sprint under way, with the session's words when it started.
+struct Running {
+    sprint: Sprint,
+    start_words: i64,
+}
+
+#[derive(Default)]
+pub struct SprintPlugin {
+    settings: SprintSettings,
+    running: Option<Running>,
+    /// Last sprint that ended, shown until the next starts
+    last: Option<SprintRecord>,
+}
+
+impl SprintPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a sprint now, counting from the words written so far.
+    fn start(&mut self, ctx: &mut PluginContext) {
+        let sprint = Sprint::new(
+            ctx.clock().now(),
+            self.settings.minutes,
+            self.settings.focus,
+        );
+        ctx.set_shared_state(SPRINT_KEY, Some(sprint.clone()));
+        self.running = Some(Running {
+            sprint,
+            start_words: session_words(ctx),
+        });
+        self.last = None;
+    }
+
+    /// End the sprint under way and send it to the writing history.
+    fn finish(&mut self, ctx: &mut PluginContext) {
+        let Some(running) = self.running.take() else {
+            return;
+        };
+        let record = running.sprint.record(ctx.clock().now());
+        ctx.set_shared_state(SPRINT_KEY, None::<Sprint>);
+        ctx.set_shared_state(SPRINT_RECORD_REQUEST, Some(record.clone()));
+        self.last = Some(record);
+    }
+
+    fn render_running(&mut self, ui: &mut Ui, ctx: &mut PluginContext, locale: &Locale) {
+        let Some(running) = &self.running else {
+            return;
+        };
+        let sprint = &running.sprint;
+        let now = ctx.clock().now();
+        let remaining = sprint.remaining(now);
+        let length = f64::from(sprint.minutes) * 60.0;
+
+        ui.heading(format_remaining(remaining));
+        ui.add(
+            egui::ProgressBar::new((1.0 - remaining.as_secs_f64() / length) as f32)
+                .text(format!("{} words", locale.format_number(sprint.words))),
+        );
+        if sprint.focus {
+            ui.weak("Other panels are hidden until the sprint ends.");
+        }
+        if ui.button("⏹ Stop").clicked() {
+            self.finish(ctx);
+        }
+        ui.ctx().request_repaint_after(Duration::from_secs(1));
+    }
+
+    fn render_settings(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        let before = self.settings.clone();
+        ui.horizontal(|ui| {
+            for minutes in LENGTHS {
+                ui.radio_value(
+                    &mut self.settings.minutes,
+                    minutes,
+                    format!("{} min", minutes),
+                );
+            }
+        });
+        ui.checkbox(
+            &mut self.settings.focus,
+            "Hide other panels while sprinting",
+        );
+        if self.settings != before {
+            ctx.set_config(CONFIG_KEY, &self.settings);
+        }
+        if ui.button("▶ Start sprint").clicked() {
+            self.start(ctx);
+        }
+    }
+}
+
+/// Words written in the session, as the application last published them.
+fn session_words(ctx: &PluginContext) -> i64 {
+    ctx.get_shared_state::<WritingHistory>(WRITING_HISTORY_KEY)
+        .map_or(0, |history| history.session_words)
+}
+
+/// Time left on the clock, e.g. `24:05`.
+fn format_remaining(remaining: Duration) -> String {
+    let seconds = remaining.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// One line of the sprint list, e.g. `25 min · 412 words · 16/min`.
+fn describe(sprint: &SprintRecord, locale: &Locale) -> String {
+    let mut line = format!(
+        "{} min · {} words · {}/min",
+        sprint.minutes,
+        locale.format_number(sprint.words),
+        locale.format_number(sprint.words_per_minute().round() as i64)
+    );
+    if !sprint.is_complete() {
+        line.push_str(" · stopped");
+    }
+    line
+}
+
+fn local_time(time: DateTime<Utc>) -> chrono::NaiveDateTime {
+    time.with_timezone(&chrono::Local).naive_local()
+}
+
+impl Plugin for SprintPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            PLUGIN_NAME,
+            "0.1.0",
+            "Timed writing sprints, logged in the writing history",
+            "Cosmarium Team",
+        )
+    }
+
+    fn initialize(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        if let Some(settings) = ctx.get_config::<SprintSettings>(CONFIG_KEY) {
+            self.settings = settings;
+        } else {
+            ctx.set_config(CONFIG_KEY, &self.settings);
+        }
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+}
+
+impl PanelPlugin for SprintPlugin {
+    fn panel_title(&self) -> &str {
+        "Writing Sprint"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "⏱"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Right
+    }
+
+    fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        let stop = ctx
+            .get_shared_state::<bool>(SPRINT_STOP_REQUEST)
+            .unwrap_or(false);
+        if stop {
+            ctx.set_shared_state(SPRINT_STOP_REQUEST, false);
+            self.finish(ctx);
+        }
+
+        let words = session_words(ctx);
+        let now = ctx.clock().now();
+        let Some(running) = &mut self.running else {
+            return Ok(());
+        };
+        if running.sprint.words != words - running.start_words {
+            running.sprint.words = words - running.start_words;
+            ctx.set_shared_state(SPRINT_KEY, Some(running.sprint.clone()));
+        }
+        if running.sprint.is_over(now) {
+            self.finish(ctx);
+        }
+        Ok(())
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        let locale = ctx
+            .get_shared_state::<Locale>(LOCALE_KEY)
+            .unwrap_or_default();
+
+        if self.running.is_some() {
+            self.render_running(ui, ctx, &locale);
+            return;
+        }
+
+        if let Some(last) = &self.last {
+            ui.label(format!("Sprint over: {}", describe(last, &locale)));
+            ui.separator();
+        }
+        self.render_settings(ui, ctx);
+
+        let history = ctx
+            .get_shared_state::<WritingHistory>(WRITING_HISTORY_KEY)
+            .unwrap_or_default();
+        let mut sprints = history
+            .log
+            .sprints(history.project.as_deref())
+            .take(MAX_SPRINTS)
+            .peekable();
+        if sprints.peek().is_none() {
+            return;
+        }
+        ui.separator();
+        ui.label("Recent sprints");
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for sprint in sprints {
+                ui.horizontal(|ui| {
+                    ui.weak(
+                        locale.format_date_time(local_time(sprint.started_at), DateStyle::Short),
+                    );
+                    ui.label(describe(sprint, &locale));
+                });
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmarium_plugin_api::clock::Clock;
+
+    fn write(ctx: &mut PluginContext, session_words: i64) {
+        let history = WritingHistory {
+            session_words,
+            ..WritingHistory::default()
+        };
+        ctx.set_shared_state(WRITING_HISTORY_KEY, history);
+    }
+
+    fn record(ctx: &PluginContext) -> Option<SprintRecord> {
+        ctx.get_shared_state::<Option<SprintRecord>>(SPRINT_RECORD_REQUEST)
+            .flatten()
+    }
+
+    #[test]
+    fn test_sprint_counts_the_words_of_its_window() {
+        let clock = Clock::manual(Clock::EPOCH);
+        let mut ctx = PluginContext::new();
+        ctx.set_clock(clock.clone());
+        let mut plugin = SprintPlugin::new();
+        plugin.settings.minutes = 15;
+
+        write(&mut ctx, 200);
+        plugin.start(&mut ctx);
+        clock.advance(Duration::from_secs(10 * 60));
+        write(&mut ctx, 650);
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        let sprint = ctx.get_shared_state::<Option<Sprint>>(SPRINT_KEY).flatten();
+        assert_eq!(sprint.map(|s| s.words), Some(450));
+        assert_eq!(record(&ctx), None);
+
+        clock.advance(Duration::from_secs(5 * 60));
+        write(&mut ctx, 700);
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        let record = record(&ctx).expect("the sprint is over");
+        assert_eq!(record.words, 500);
+        assert!(record.is_complete());
+        assert_eq!(
+            ctx.get_shared_state::<Option<Sprint>>(SPRINT_KEY),
+            Some(None)
+        );
+    }
+
+    #[test]
+    fn test_stop_request_ends_the_sprint_early() {
+        let clock = Clock::manual(Clock::EPOCH);
+        let mut ctx = PluginContext::new();
+        ctx.set_clock(clock.clone());
+        let mut plugin = SprintPlugin::new();
+
+        plugin.start(&mut ctx);
+        clock.advance(Duration::from_secs(60));
+        ctx.set_shared_state(SPRINT_STOP_REQUEST, true);
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+
+        let record = record(&ctx).expect("the sprint was stopped");
+        assert_eq!(record.seconds, 60);
+        assert!(!record.is_complete());
+        assert!(plugin.running.is_none());
+    }
+
+    #[test]
+    fn test_remaining_time_format() {
+        assert_eq!(format_remaining(Duration::from_secs(25 * 60)), "25:00");
+        assert_eq!(format_remaining(Duration::from_secs(65)), "01:05");
+    }
+}
@@ -0,0 +1,584 @@
+//! # Inline task tracking plugin for Cosmarium
+//!
+//! Collects the revision markers (`TODO:`, `FIXME:`, `[check]`, ...) written
+//! across the project's documents and lists them in a panel grouped by
+//! document. Clicking a task jumps to its line, opening the document first
+//! when needed. Tasks can also be pushed to an external tracker, and show as
+//! resolved once closed there (see [`tracker`]).
+
+pub mod markers;
+pub mod tracker;
+
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result, TaskHandle,
+};
+use egui::Ui;
+use markers::{InlineTask, TaskMarkerConfig};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracker::{Connector, LinkedTask, TrackerLinks, TrackerSettings, TRACKER_CONFIG_KEY};
+
+/// Configuration key for [`TaskMarkerConfig`].
+pub const CONFIG_KEY: &str = "tasks";
+
+/// Shared state key holding per-document task counts (`HashMap<PathBuf, usize>`).
+pub const TASK_COUNTS_KEY: &str = "task_counts";
+
+/// How often documents that are not open in the editor are rescanned.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the tracker is asked which pushed tasks were closed.
+const SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+/// File extensions scanned for tasks.
+const SCANNED_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// Tasks found in a single document.
+#[derive(Debug, Clone)]
+struct DocumentTasks {
+    /// Document file, `None` for an unsaved editor buffer
+    path: Option<PathBuf>,
+    /// Display name
+    name: String,
+    /// Tasks in line order
+    tasks: Vec<InlineTask>,
+}
+
+#[derive(Default)]
+pub struct TasksPlugin {
+    /// Marker configuration
+    config: TaskMarkerConfig,
+    /// Tasks found on disk, sorted by path
+    documents: Vec<DocumentTasks>,
+    /// Tasks of the document currently in the editor
+    active: Option<DocumentTasks>,
+    /// Project the disk scan belongs to
+    scanned_project: Option<PathBuf>,
+    /// Time of the last disk scan
+    last_scan: Option<Instant>,
+    /// Hash of the last editor content scanned
+    last_content_hash: u64,
+    /// Settings of the external tracker
+    tracker: TrackerSettings,
+    /// Tasks of the project pushed to the tracker
+    links: TrackerLinks,
+    /// Push in progress
+    pushing: Option<TaskHandle<(Vec<LinkedTask>, Option<anyhow::Error>)>>,
+    /// Sync in progress, asking which pushed tasks were closed
+    syncing: Option<TaskHandle<anyhow::Result<Vec<String>>>>,
+    /// When the tracker was last asked which tasks were closed
+    last_sync: Option<Instant>,
+    /// Why the last push or sync failed
+    tracker_error: Option<String>,
+}
+
+impl TasksPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rescan every document in the project's content directory.
+    fn scan_project(&mut self, project: &Path) {
+        let mut files = Vec::new();
+        collect_documents(&project.join("content"), &mut files);
+        files.sort();
+
+        self.documents = files
+            .into_iter()
+            .filter_map(|path| {
+                let content = std::fs::read_to_string(&path).ok()?;
+                Some(DocumentTasks {
+                    name: display_name(&path),
+                    tasks: markers::scan(&content, &self.config.markers),
+                    path: Some(path),
+                })
+            })
+            .collect();
+    }
+
+    /// All documents with their tasks, the live editor buffer taking
+    /// precedence over the copy on disk.
+    fn grouped(&self) -> Vec<&DocumentTasks> {
+        let active_path = self.active.as_ref().and_then(|a| a.path.as_ref());
+        let mut groups: Vec<&DocumentTasks> = self
+            .documents
+            .iter()
+            .filter(|doc| active_path.is_none() || doc.path.as_ref() != active_path)
+            .collect();
+
+        if let Some(active) = &self.active {
+            let pos = groups
+                .iter()
+                .position(|doc| doc.path > active.path)
+                .unwrap_or(groups.len());
+            groups.insert(pos, active);
+        }
+
+        groups
+    }
+
+    /// Take the links of a finished push or sync, or keep its error.
+    fn poll_tracker(&mut self, ctx: &PluginContext) {
+        let mut changed = false;
+        if let Some(result) = self.pushing.as_mut().and_then(|task| task.try_take()) {
+            self.pushing = None;
+            let (linked, error) = result.unwrap_or_else(|e| (Vec::new(), Some(e)));
+            changed |= !linked.is_empty();
+            self.links.tasks.extend(linked);
+            self.tracker_error = error.map(|e| {
+                tracing::warn!("Pushing tasks failed: {:#}", e);
+                format!("{:#}", e)
+            });
+        }
+        if let Some(result) = self.syncing.as_mut().and_then(|task| task.try_take()) {
+            self.syncing = None;
+            match result.and_then(|closed| closed) {
+                Ok(closed) => {
+                    changed |= self.links.resolve(&closed);
+                    self.tracker_error = None;
+                }
+                Err(e) => {
+                    tracing::warn!("Syncing tasks failed: {:#}", e);
+                    self.tracker_error = Some(format!("{:#}", e));
+                }
+            }
+        }
+        if changed {
+            if let Some(project) = ctx.project_path() {
+                self.links.save(&project);
+            }
+        }
+    }
+
+    /// Ask a REST tracker, now and then, which pushed tasks were closed.
+    fn schedule_sync(&mut self, ctx: &PluginContext) {
+        let clock = ctx.clock();
+        let due = self
+            .last_sync
+            .is_none_or(|synced| clock.since(synced) >= SYNC_INTERVAL);
+        if !self.tracker.is_ready()
+            || self.tracker.connector != Connector::Rest
+            || self.syncing.is_some()
+            || !due
+        {
+            return;
+        }
+        self.last_sync = Some(clock.instant());
+        let ids = self.links.open_ids();
+        if ids.is_empty() {
+            return;
+        }
+        let settings = self.tracker.clone();
+        self.syncing = Some(ctx.spawn_blocking(move || tracker::closed_items(&settings, &ids)));
+    }
+
+    /// Push `tasks`, each with its document, to the tracker.
+    fn push(&mut self, ctx: &PluginContext, tasks: Vec<(String, InlineTask)>) {
+        if tasks.is_empty() || self.pushing.is_some() {
+            return;
+        }
+        let settings = self.tracker.clone();
+        self.pushing = Some(ctx.spawn_blocking(move || tracker::push(&settings, &tasks)));
+    }
+
+    fn render_tracker_settings(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = ui
+            .checkbox(&mut self.tracker.enabled, "Push tasks to a tracker")
+            .changed();
+        egui::Grid::new("tasks_tracker_settings")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Connector");
+                egui::ComboBox::from_id_salt("tasks_tracker_connector")
+                    .selected_text(self.tracker.connector.label())
+                    .show_ui(ui, |ui| {
+                        for connector in [Connector::Webhook, Connector::Rest] {
+                            changed |= ui
+                                .selectable_value(
+                                    &mut self.tracker.connector,
+                                    connector,
+                                    connector.label(),
+                                )
+                                .changed();
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("URL");
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut self.tracker.url)
+                            .hint_text("https://api.todoist.com/rest/v2/tasks"),
+                    )
+                    .on_hover_text("Webhook, or collection of tasks of a REST tracker")
+                    .lost_focus();
+                ui.end_row();
+
+                ui.label("Token");
+                changed |= ui
+                    .add(egui::TextEdit::singleline(&mut self.tracker.token).password(true))
+                    .on_hover_text("Sent as a bearer token, if any")
+                    .lost_focus();
+                ui.end_row();
+
+                if self.tracker.connector == Connector::Rest {
+                    ui.label("Title field");
+                    changed |= ui
+                        .add(
+                            egui::TextEdit::singleline(&mut self.tracker.title_field)
+                                .desired_width(80.0),
+                        )
+                        .on_hover_text("Field holding the title of a task, such as content")
+                        .lost_focus();
+                    ui.end_row();
+                }
+            });
+        changed
+    }
+
+    /// Publish task counts per document for other panels.
+    fn publish_counts(&self, ctx: &mut PluginContext) {
+        let counts: HashMap<PathBuf, usize> = self
+            .grouped()
+            .into_iter()
+            .filter_map(|doc| Some((doc.path.clone()?, doc.tasks.len())))
+            .collect();
+        ctx.set_shared_state(TASK_COUNTS_KEY, counts);
+    }
+}
+
+/// Recursively collect scannable documents under `dir`.
+fn collect_documents(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_documents(&path, files);
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| SCANNED_EXTENSIONS.contains(&e))
+        {
+            files.push(path);
+        }
+    }
+}
+
+/// Path of `path` within `project`, as stored in the tracker links.
+fn relative_path(project: Option<&Path>, path: &Path) -> String {
+    let relative = project
+        .and_then(|project| path.strip_prefix(project).ok())
+        .unwrap_or(path);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Untitled")
+        .to_string()
+}
+
+impl Plugin for TasksPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            "tasks",
+            "0.1.0",
+            "Inline TODO/FIXME tracking",
+            "Cosmarium Team",
+        )
+        .with_dependency("markdown-editor")
+    }
+
+    fn initialize(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        if let Some(config) = ctx.get_config::<TaskMarkerConfig>(CONFIG_KEY) {
+            self.config = config;
+        } else {
+            ctx.set_config(CONFIG_KEY, &self.config);
+        }
+        if let Some(settings) = ctx.get_config::<TrackerSettings>(TRACKER_CONFIG_KEY) {
+            self.tracker = settings;
+        } else {
+            ctx.set_config(TRACKER_CONFIG_KEY, &self.tracker);
+        }
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+
+    fn update(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl PanelPlugin for TasksPlugin {
+    fn panel_title(&self) -> &str {
+        "Tasks"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "☑"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Left
+    }
+
+    fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        let mut changed = false;
+
+        // Rescan the project on a timer, or immediately when it changes
+        let project = ctx.project_path();
+        let stale = self
+            .last_scan
+            .is_none_or(|t| t.elapsed() >= RESCAN_INTERVAL);
+        if project != self.scanned_project {
+            self.links = project
+                .as_deref()
+                .map(TrackerLinks::load)
+                .unwrap_or_default();
+            self.pushing = None;
+            self.syncing = None;
+            self.last_sync = None;
+        }
+        if project != self.scanned_project || stale {
+            match &project {
+                Some(path) => self.scan_project(path),
+                None => self.documents.clear(),
+            }
+            self.scanned_project = project;
+            self.last_scan = Some(Instant::now());
+            changed = true;
+        }
+
+        // The editor buffer is scanned live
+        if let Some(content) = ctx.get_shared_state::<String>("markdown_editor_content") {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            content.hash(&mut hasher);
+            let active_path = ctx
+                .get_shared_state::<Option<PathBuf>>("active_document_path")
+                .flatten();
+            active_path.hash(&mut hasher);
+            let new_hash = hasher.finish();
+
+            if new_hash != self.last_content_hash {
+                self.active = Some(DocumentTasks {
+                    name: active_path
+                        .as_deref()
+                        .map(display_name)
+                        .unwrap_or_else(|| "Current document".to_string()),
+                    tasks: markers::scan(&content, &self.config.markers),
+                    path: active_path,
+                });
+                self.last_content_hash = new_hash;
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.publish_counts(ctx);
+        }
+
+        self.poll_tracker(ctx);
+        self.schedule_sync(ctx);
+
+        Ok(())
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        let mut changed = false;
+        egui::CollapsingHeader::new("Tracker")
+            .id_salt("tasks_tracker_header")
+            .show(ui, |ui| {
+                changed |= self.render_tracker_settings(ui);
+            });
+        if changed {
+            ctx.set_config(TRACKER_CONFIG_KEY, &self.tracker);
+            self.tracker_error = None;
+            self.last_sync = None;
+        }
+        if let Some(error) = &self.tracker_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        let groups = self.grouped();
+        let total: usize = groups.iter().map(|doc| doc.tasks.len()).sum();
+        if total == 0 {
+            ui.label("No tasks found");
+            return;
+        }
+
+        let project = ctx.project_path();
+        let active_path = self.active.as_ref().and_then(|a| a.path.clone());
+        let mut jump: Option<(Option<PathBuf>, usize)> = None;
+        let mut push: Vec<(String, InlineTask)> = Vec::new();
+
+        // Tasks of saved documents not pushed yet
+        let can_push = self.tracker.is_ready() && self.pushing.is_none();
+        let unpushed: Vec<(String, InlineTask)> = groups
+            .iter()
+            .filter_map(|doc| Some((relative_path(project.as_deref(), doc.path.as_ref()?), doc)))
+            .flat_map(|(document, doc)| {
+                doc.tasks
+                    .iter()
+                    .filter(|task| self.links.find(&document, task).is_none())
+                    .map(|task| (document.clone(), task.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        if self.tracker.is_ready() {
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(
+                        can_push && !unpushed.is_empty(),
+                        egui::Button::new(format!("Push all ({})", unpushed.len())),
+                    )
+                    .on_hover_text("Push the tasks of saved documents not pushed yet")
+                    .clicked()
+                {
+                    push = unpushed.clone();
+                }
+                if self.pushing.is_some() || self.syncing.is_some() {
+                    ui.spinner();
+                }
+            });
+        }
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for doc in groups.iter().filter(|doc| !doc.tasks.is_empty()) {
+                let id = ui.make_persistent_id(("tasks_group", &doc.path));
+                egui::CollapsingHeader::new(format!("{} ({})", doc.name, doc.tasks.len()))
+                    .id_salt(id)
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        let document = doc
+                            .path
+                            .as_deref()
+                            .map(|path| relative_path(project.as_deref(), path));
+                        for task in &doc.tasks {
+                            let linked = document
+                                .as_deref()
+                                .and_then(|document| self.links.find(document, task));
+                            let resolved = linked.is_some_and(|linked| linked.resolved);
+                            let mut label = egui::RichText::new(format!(
+                                "{}  {} {}",
+                                task.line, task.marker, task.text
+                            ));
+                            if resolved {
+                                label = label.strikethrough().weak();
+                            }
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .add(egui::Label::new(label).sense(egui::Sense::click()))
+                                    .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                    .clicked()
+                                {
+                                    jump = Some((doc.path.clone(), task.line));
+                                }
+                                match (linked, &document) {
+                                    (Some(_), _) if resolved => {
+                                        ui.weak("✔").on_hover_text("Closed in the tracker");
+                                    }
+                                    (Some(_), _) => {
+                                        ui.weak("↗").on_hover_text("Pushed to the tracker");
+                                    }
+                                    (None, Some(document)) if self.tracker.is_ready() => {
+                                        let clicked = ui
+                                            .add_enabled(can_push, egui::Button::new("↗").small())
+                                            .on_hover_text("Push to the tracker")
+                                            .clicked();
+                                        if clicked {
+                                            push = vec![(document.clone(), task.clone())];
+                                        }
+                                    }
+                                    _ => {}
+                                }
+                            });
+                        }
+                    });
+            }
+        });
+
+        self.push(ctx, push);
+
+        match jump {
+            Some((path, line)) if path.is_none() || path == active_path => {
+                ctx.set_shared_state("markdown_editor_goto_line", line);
+            }
+            Some((Some(path), line)) => {
+                ctx.set_shared_state("open_document_request", Some((path, line)));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_buffer_replaces_disk_copy() {
+        let mut plugin = TasksPlugin::new();
+        let chapter = PathBuf::from("/p/content/chapter.md");
+        plugin.documents = vec![
+            DocumentTasks {
+                path: Some(PathBuf::from("/p/content/a.md")),
+                name: "a".to_string(),
+                tasks: Vec::new(),
+            },
+            DocumentTasks {
+                path: Some(chapter.clone()),
+                name: "chapter".to_string(),
+                tasks: Vec::new(),
+            },
+        ];
+
+        let mut ctx = PluginContext::new();
+        ctx.set_shared_state("active_document_path", Some(chapter.clone()));
+        ctx.set_shared_state(
+            "markdown_editor_content",
+            "TODO: one\nFIXME: two".to_string(),
+        );
+        plugin.last_scan = Some(Instant::now());
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+
+        let groups = plugin.grouped();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1].path.as_ref(), Some(&chapter));
+        assert_eq!(groups[1].tasks.len(), 2);
+
+        let counts: HashMap<PathBuf, usize> = ctx.get_shared_state(TASK_COUNTS_KEY).unwrap();
+        assert_eq!(counts.get(&chapter), Some(&2));
+    }
+
+    #[test]
+    fn test_scan_project_content_directory() {
+        let root =
+            std::env::temp_dir().join(format!("cosmarium_tasks_test_{}", std::process::id()));
+        let content = root.join("content").join("part1");
+        std::fs::create_dir_all(&content).unwrap();
+        std::fs::write(content.join("one.md"), "TODO: a\n[check] b").unwrap();
+        std::fs::write(content.join("notes.bin"), "TODO: ignored").unwrap();
+
+        let mut plugin = TasksPlugin::new();
+        plugin.scan_project(&root);
+
+        assert_eq!(plugin.documents.len(), 1);
+        assert_eq!(plugin.documents[0].name, "one");
+        assert_eq!(plugin.documents[0].tasks.len(), 2);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
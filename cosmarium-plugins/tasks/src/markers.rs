@@ -0,0 +1,141 @@
+//! # Inline marker scanning
+//!
+//! Finds lightweight revision tasks written directly in the manuscript, such
+//! as `TODO: tighten this scene` or `[check] dates in chapter 3`.
+
+use serde::{Deserialize, Serialize};
+
+/// Configuration of the markers recognized as inline tasks.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_tasks::markers::TaskMarkerConfig;
+///
+/// let config = TaskMarkerConfig::default();
+/// assert!(config.markers.contains(&"TODO:".to_string()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskMarkerConfig {
+    /// Markers to look for, matched case-sensitively
+    pub markers: Vec<String>,
+}
+
+impl Default for TaskMarkerConfig {
+    fn default() -> Self {
+        Self {
+            markers: vec![
+                "TODO:".to_string(),
+                "FIXME:".to_string(),
+                "[check]".to_string(),
+            ],
+        }
+    }
+}
+
+/// A task found in a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineTask {
+    /// Marker that introduced the task
+    pub marker: String,
+    /// Text following the marker on the same line
+    pub text: String,
+    /// Line number (1-based)
+    pub line: usize,
+}
+
+/// Scan `content` for inline tasks.
+///
+/// Each line yields at most one task: the marker occurring first on the line
+/// wins. Closing brackets and comment terminators left after the task text
+/// are trimmed.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_tasks::markers::{scan, TaskMarkerConfig};
+///
+/// let tasks = scan("Intro\nShe ran. TODO: check pacing", &TaskMarkerConfig::default().markers);
+/// assert_eq!(tasks.len(), 1);
+/// assert_eq!(tasks[0].line, 2);
+/// assert_eq!(tasks[0].text, "check pacing");
+/// ```
+pub fn scan(content: &str, markers: &[String]) -> Vec<InlineTask> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let (start, marker) = markers
+                .iter()
+                .filter(|m| !m.is_empty())
+                .filter_map(|m| line.find(m.as_str()).map(|pos| (pos, m)))
+                .min_by_key(|(pos, _)| *pos)?;
+
+            let text = line[start + marker.len()..]
+                .trim()
+                .trim_end_matches("-->")
+                .trim_end_matches(']')
+                .trim()
+                .to_string();
+
+            Some(InlineTask {
+                marker: marker.clone(),
+                text,
+                line: index + 1,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_markers() -> Vec<String> {
+        TaskMarkerConfig::default().markers
+    }
+
+    #[test]
+    fn test_scan_default_markers() {
+        let content =
+            "# Chapter\n\nTODO: rewrite opening\nPlain line\n[check] the date\nFIXME: typo";
+        let tasks = scan(content, &default_markers());
+
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[0].marker, "TODO:");
+        assert_eq!(tasks[0].line, 3);
+        assert_eq!(tasks[1].marker, "[check]");
+        assert_eq!(tasks[1].text, "the date");
+        assert_eq!(tasks[2].line, 6);
+    }
+
+    #[test]
+    fn test_scan_trims_wrappers() {
+        let tasks = scan(
+            "Text <!-- TODO: name the inn -->\nMore [TODO: fix this]",
+            &default_markers(),
+        );
+
+        assert_eq!(tasks[0].text, "name the inn");
+        assert_eq!(tasks[1].text, "fix this");
+    }
+
+    #[test]
+    fn test_scan_first_marker_wins() {
+        let tasks = scan("FIXME: broken TODO: later", &default_markers());
+
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].marker, "FIXME:");
+        assert_eq!(tasks[0].text, "broken TODO: later");
+    }
+
+    #[test]
+    fn test_scan_custom_markers() {
+        let markers = vec!["XXX".to_string(), String::new()];
+        let tasks = scan("todo: lowercase\nXXX weird", &markers);
+
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].text, "weird");
+    }
+}
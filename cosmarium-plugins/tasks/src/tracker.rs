@@ -0,0 +1,455 @@
+//! # External trackers
+//!
+//! Inline tasks can be pushed to an external task system, for authors who
+//! manage their revisions in Todoist, Notion or a tracker of their own. Two
+//! connectors are offered:
+//!
+//! - webhook: each task is posted as JSON to a URL, such as an automation
+//!   forwarding it to Notion; nothing comes back
+//! - REST: each task is created by a POST to a collection URL answering
+//!   with its `id`, then read at `<url>/<id>` to learn when it is closed, as
+//!   with Todoist (`https://api.todoist.com/rest/v2/tasks`, title field
+//!   `content`)
+//!
+//! A token, when set, is sent as a bearer token. Tasks closed remotely, or
+//! deleted, are marked resolved in the panel; the text keeps its markers.
+//! Which task went to which item is kept with the project, in
+//! `meta/plugins/tasks/tracker.toon`.
+
+use crate::markers::InlineTask;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Configuration key for [`TrackerSettings`].
+pub const TRACKER_CONFIG_KEY: &str = "tasks_tracker";
+
+/// How long to wait for the tracker's answer.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Fields of a remote item telling it is closed when true.
+const CLOSED_FLAGS: &[&str] = &[
+    "completed",
+    "is_completed",
+    "done",
+    "closed",
+    "resolved",
+    "checked",
+    "archived",
+];
+
+/// Fields of a remote item holding its status.
+const STATUS_FIELDS: &[&str] = &["status", "state"];
+
+/// Statuses of a closed remote item.
+const CLOSED_STATUSES: &[&str] = &["done", "closed", "completed", "complete", "resolved"];
+
+/// How tasks reach the tracker.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Connector {
+    /// Tasks are posted to a webhook, one way
+    #[default]
+    Webhook,
+    /// Tasks are created in a REST collection and read back
+    Rest,
+}
+
+impl Connector {
+    /// Name of the connector in the settings.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Webhook => "Webhook",
+            Self::Rest => "REST",
+        }
+    }
+}
+
+/// Settings of the external tracker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrackerSettings {
+    /// Whether tasks can be pushed
+    pub enabled: bool,
+    /// How tasks reach the tracker
+    pub connector: Connector,
+    /// Webhook URL, or URL of the collection of tasks
+    pub url: String,
+    /// Bearer token, none when empty
+    pub token: String,
+    /// Field of the title in the items of a REST tracker
+    pub title_field: String,
+}
+
+impl Default for TrackerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            connector: Connector::Webhook,
+            url: String::new(),
+            token: String::new(),
+            title_field: "title".to_string(),
+        }
+    }
+}
+
+impl TrackerSettings {
+    /// Whether tasks can be pushed with these settings.
+    pub fn is_ready(&self) -> bool {
+        self.enabled && !self.url.trim().is_empty()
+    }
+}
+
+/// A task pushed to the tracker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkedTask {
+    /// Document of the task, relative to the project
+    pub document: String,
+    /// Marker of the task
+    pub marker: String,
+    /// Text of the task
+    pub text: String,
+    /// Identifier of the remote item, empty through a webhook
+    #[serde(default)]
+    pub id: String,
+    /// Whether the remote item was closed
+    #[serde(default)]
+    pub resolved: bool,
+}
+
+impl LinkedTask {
+    /// Whether this is `task` of `document`, found by its marker and text
+    /// wherever its line moved.
+    pub fn is(&self, document: &str, task: &InlineTask) -> bool {
+        self.document == document && self.marker == task.marker && self.text == task.text
+    }
+}
+
+/// Tasks of a project pushed to the tracker.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackerLinks {
+    /// Pushed tasks, in the order they were pushed
+    pub tasks: Vec<LinkedTask>,
+}
+
+impl TrackerLinks {
+    fn file(project: &Path) -> PathBuf {
+        project.join("meta/plugins/tasks/tracker.toon")
+    }
+
+    /// Links of `project`, or none yet.
+    pub fn load(project: &Path) -> Self {
+        let file = Self::file(project);
+        match std::fs::read_to_string(&file) {
+            Ok(content) => serde_toon2::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Failed to parse tracker links {:?}: {}", file, e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Save the links with `project`.
+    pub fn save(&self, project: &Path) {
+        let file = Self::file(project);
+        if let Some(dir) = file.parent() {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                tracing::error!("Failed to create tasks plugin directory: {}", e);
+                return;
+            }
+        }
+        match serde_toon2::to_string(self) {
+            Ok(content) => {
+                if let Err(e) = std::fs::write(&file, content) {
+                    tracing::error!("Failed to write tracker links: {}", e);
+                }
+            }
+            Err(e) => tracing::error!("Failed to serialize tracker links: {}", e),
+        }
+    }
+
+    /// The link of `task` of `document`, if it was pushed.
+    pub fn find(&self, document: &str, task: &InlineTask) -> Option<&LinkedTask> {
+        self.tasks.iter().find(|linked| linked.is(document, task))
+    }
+
+    /// Identifiers of the remote items not known to be closed.
+    pub fn open_ids(&self) -> Vec<String> {
+        self.tasks
+            .iter()
+            .filter(|linked| !linked.resolved && !linked.id.is_empty())
+            .map(|linked| linked.id.clone())
+            .collect()
+    }
+
+    /// Mark the tasks of the remote items `closed` as resolved, telling
+    /// whether any was not already.
+    pub fn resolve(&mut self, closed: &[String]) -> bool {
+        let mut changed = false;
+        for linked in &mut self.tasks {
+            if !linked.resolved && closed.contains(&linked.id) {
+                linked.resolved = true;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// JSON body sent for `task` of `document`.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_tasks::markers::InlineTask;
+/// use cosmarium_tasks::tracker::{payload, Connector, TrackerSettings};
+///
+/// let task = InlineTask {
+///     marker: "TODO:".to_string(),
+///     text: "name the inn".to_string(),
+///     line: 12,
+/// };
+/// let settings = TrackerSettings {
+///     connector: Connector::Rest,
+///     title_field: "content".to_string(),
+///     ..TrackerSettings::default()
+/// };
+/// let body = payload(&settings, "content/chapter-1.md", &task);
+/// assert_eq!(body["content"], "name the inn");
+/// assert_eq!(body["description"], "TODO: in content/chapter-1.md, line 12");
+/// ```
+pub fn payload(settings: &TrackerSettings, document: &str, task: &InlineTask) -> Value {
+    match settings.connector {
+        Connector::Webhook => serde_json::json!({
+            "event": "task",
+            "title": task.text,
+            "marker": task.marker,
+            "document": document,
+            "line": task.line,
+        }),
+        Connector::Rest => {
+            let mut body = serde_json::Map::new();
+            body.insert(
+                settings.title_field.clone(),
+                Value::String(task.text.clone()),
+            );
+            body.insert(
+                "description".to_string(),
+                Value::String(format!(
+                    "{} in {}, line {}",
+                    task.marker, document, task.line
+                )),
+            );
+            Value::Object(body)
+        }
+    }
+}
+
+/// Identifier of a created item, out of the tracker's answer.
+pub fn item_id(item: &Value) -> Option<String> {
+    match item.get("id")? {
+        Value::String(id) => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether a remote `item` is closed.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_tasks::tracker::is_closed;
+///
+/// assert!(is_closed(&serde_json::json!({ "id": "1", "is_completed": true })));
+/// assert!(is_closed(&serde_json::json!({ "status": "Done" })));
+/// assert!(!is_closed(&serde_json::json!({ "state": "open", "done": false })));
+/// ```
+pub fn is_closed(item: &Value) -> bool {
+    CLOSED_FLAGS
+        .iter()
+        .any(|flag| item.get(flag).and_then(Value::as_bool) == Some(true))
+        || STATUS_FIELDS.iter().any(|field| {
+            item.get(field)
+                .and_then(Value::as_str)
+                .is_some_and(|status| CLOSED_STATUSES.contains(&status.to_lowercase().as_str()))
+        })
+}
+
+fn client() -> Result<reqwest::blocking::Client> {
+    Ok(reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?)
+}
+
+fn authorized(
+    request: reqwest::blocking::RequestBuilder,
+    settings: &TrackerSettings,
+) -> reqwest::blocking::RequestBuilder {
+    if settings.token.trim().is_empty() {
+        request
+    } else {
+        request.bearer_auth(settings.token.trim())
+    }
+}
+
+/// Push `tasks`, each with its document, to the tracker, returning their
+/// links, then the error that stopped the push, if one did.
+pub fn push(
+    settings: &TrackerSettings,
+    tasks: &[(String, InlineTask)],
+) -> (Vec<LinkedTask>, Option<anyhow::Error>) {
+    let client = match client() {
+        Ok(client) => client,
+        Err(e) => return (Vec::new(), Some(e)),
+    };
+    let mut linked = Vec::new();
+    for (document, task) in tasks {
+        match push_one(&client, settings, document, task) {
+            Ok(id) => linked.push(LinkedTask {
+                document: document.clone(),
+                marker: task.marker.clone(),
+                text: task.text.clone(),
+                id: id.unwrap_or_default(),
+                resolved: false,
+            }),
+            Err(e) => return (linked, Some(e)),
+        }
+    }
+    (linked, None)
+}
+
+fn push_one(
+    client: &reqwest::blocking::Client,
+    settings: &TrackerSettings,
+    document: &str,
+    task: &InlineTask,
+) -> Result<Option<String>> {
+    let url = settings.url.trim();
+    let response = authorized(client.post(url), settings)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(payload(settings, document, task).to_string())
+        .send()
+        .with_context(|| format!("Could not reach the tracker at {}", url))?;
+    if !response.status().is_success() {
+        anyhow::bail!("The tracker answered with HTTP {}", response.status());
+    }
+    match settings.connector {
+        Connector::Webhook => Ok(None),
+        Connector::Rest => {
+            let item: Value = serde_json::from_str(&response.text()?)
+                .context("Unexpected answer from the tracker")?;
+            item_id(&item)
+                .map(Some)
+                .context("The tracker did not answer with the id of the task")
+        }
+    }
+}
+
+/// Identifiers among `ids` of the remote items closed or deleted.
+///
+/// # Errors
+///
+/// Returns an error if the tracker cannot be reached or answers otherwise.
+pub fn closed_items(settings: &TrackerSettings, ids: &[String]) -> Result<Vec<String>> {
+    let client = client()?;
+    let base = settings.url.trim().trim_end_matches('/');
+    let mut closed = Vec::new();
+    for id in ids {
+        let url = format!("{}/{}", base, id);
+        let response = authorized(client.get(&url), settings)
+            .send()
+            .with_context(|| format!("Could not reach the tracker at {}", base))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            closed.push(id.clone());
+            continue;
+        }
+        if !response.status().is_success() {
+            anyhow::bail!("The tracker answered with HTTP {}", response.status());
+        }
+        let item: Value = serde_json::from_str(&response.text()?)
+            .context("Unexpected answer from the tracker")?;
+        if is_closed(&item) {
+            closed.push(id.clone());
+        }
+    }
+    Ok(closed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(text: &str) -> InlineTask {
+        InlineTask {
+            marker: "TODO:".to_string(),
+            text: text.to_string(),
+            line: 3,
+        }
+    }
+
+    #[test]
+    fn test_webhook_payload() {
+        let body = payload(&TrackerSettings::default(), "content/one.md", &task("fix"));
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "event": "task",
+                "title": "fix",
+                "marker": "TODO:",
+                "document": "content/one.md",
+                "line": 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_item_id() {
+        assert_eq!(
+            item_id(&serde_json::json!({ "id": "2995104339" })).as_deref(),
+            Some("2995104339")
+        );
+        assert_eq!(
+            item_id(&serde_json::json!({ "id": 42 })).as_deref(),
+            Some("42")
+        );
+        assert_eq!(item_id(&serde_json::json!({ "ok": true })), None);
+    }
+
+    #[test]
+    fn test_links_resolve_and_roundtrip() {
+        let mut links = TrackerLinks {
+            tasks: vec![
+                LinkedTask {
+                    document: "content/one.md".to_string(),
+                    marker: "TODO:".to_string(),
+                    text: "fix".to_string(),
+                    id: "7".to_string(),
+                    resolved: false,
+                },
+                LinkedTask {
+                    document: "content/one.md".to_string(),
+                    marker: "TODO:".to_string(),
+                    text: "hooked".to_string(),
+                    id: String::new(),
+                    resolved: false,
+                },
+            ],
+        };
+        assert_eq!(links.open_ids(), ["7"]);
+        assert!(links.find("content/one.md", &task("fix")).is_some());
+        assert!(links.find("content/two.md", &task("fix")).is_none());
+
+        assert!(links.resolve(&["7".to_string()]));
+        assert!(!links.resolve(&["7".to_string()]));
+        assert!(links.open_ids().is_empty());
+
+        let root =
+            std::env::temp_dir().join(format!("cosmarium_tracker_test_{}", std::process::id()));
+        links.save(&root);
+        assert_eq!(TrackerLinks::load(&root), links);
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,106 @@
+//! Backlinks of worldbuilding entries: the lines of the project's documents
+//! that link to an entry, or mention it by name without a link.
+
+use cosmarium_plugin_api::wiki::{links, LinkResolver, WikiEntry};
+
+/// Longest excerpt of a line shown in the backlinks.
+const EXCERPT_CHARS: usize = 80;
+
+/// A line mentioning an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mention {
+    /// Line, starting at 1
+    pub line: usize,
+    /// Whether the line links to the entry, rather than only naming it
+    pub linked: bool,
+    /// Start of the line
+    pub excerpt: String,
+}
+
+/// Lines of `content` linking to `entry`, as resolved by `resolver`, or
+/// naming it.
+///
+/// # Example
+///
+/// ```rust
+/// use cosmarium_plugin_api::wiki::{LinkResolver, WikiEntry};
+/// use cosmarium_wiki::backlinks::mentions;
+///
+/// let inn = WikiEntry::new("Crow Inn", "places", "/novel/entities/places/inn.md");
+/// let resolver = LinkResolver::new(vec![inn.clone()]);
+///
+/// let mentions = mentions("At the [[crow inn]].\n\nThe Crow Inn burned.", &inn, &resolver);
+/// assert_eq!(mentions.len(), 2);
+/// assert!(mentions[0].linked);
+/// assert_eq!((mentions[1].line, mentions[1].linked), (3, false));
+/// ```
+pub fn mentions(content: &str, entry: &WikiEntry, resolver: &LinkResolver) -> Vec<Mention> {
+    let links = links(content);
+    let mut mentions = Vec::new();
+    let mut offset = 0;
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let range = offset..offset + line.len();
+        offset += line.len();
+
+        let line_links: Vec<_> = links
+            .iter()
+            .filter(|link| range.contains(&link.range.start))
+            .collect();
+        let linked = line_links
+            .iter()
+            .any(|link| resolver.resolve(&link.target) == Some(entry));
+        let named = || {
+            line.match_indices(entry.name.as_str()).any(|(at, name)| {
+                let start = range.start + at;
+                let inside_link = line_links.iter().any(|link| link.range.contains(&start));
+                let before = line[..at].chars().next_back();
+                let after = line[at + name.len()..].chars().next();
+                !inside_link
+                    && !before.is_some_and(char::is_alphanumeric)
+                    && !after.is_some_and(char::is_alphanumeric)
+            })
+        };
+        if !entry.name.is_empty() && (linked || named()) {
+            mentions.push(Mention {
+                line: i + 1,
+                linked,
+                excerpt: excerpt(line),
+            });
+        }
+    }
+    mentions
+}
+
+/// `line` trimmed, and shortened to [`EXCERPT_CHARS`].
+fn excerpt(line: &str) -> String {
+    let line = line.trim();
+    if line.chars().count() <= EXCERPT_CHARS {
+        return line.to_string();
+    }
+    let mut excerpt: String = line.chars().take(EXCERPT_CHARS - 1).collect();
+    excerpt.push('…');
+    excerpt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mentions_are_whole_words_outside_links() {
+        let ann = WikiEntry::new("Ann", "characters", "/p/entities/characters/ann.md");
+        let resolver = LinkResolver::new(vec![
+            ann.clone(),
+            WikiEntry::new("Anna", "characters", "/p/entities/characters/anna.md"),
+        ]);
+        let content = "Anna sang.\n[[Anna|Ann's sister]] left.\n[[characters/ann]] and Ann.\n";
+        let found = mentions(content, &ann, &resolver);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].line, 3);
+        assert!(found[0].linked);
+        assert_eq!(found[0].excerpt, "[[characters/ann]] and Ann.");
+
+        let long = format!("Ann {}", "walked ".repeat(20));
+        assert!(mentions(&long, &ann, &resolver)[0].excerpt.ends_with('…'));
+    }
+}
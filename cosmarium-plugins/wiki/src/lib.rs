@@ -0,0 +1,550 @@
+//! # Worldbuilding wiki plugin for Cosmarium
+//!
+//! Lists the worldbuilding entries of the project's `entities/` folder by
+//! kind, and shows an entry with its backlinks: every document linking to
+//! the entry with a `[[link]]`, or naming it. Links clicked with Ctrl in the
+//! editor open their entry here; links to missing entries offer to create
+//! them.
+//!
+//! Links are resolved by the [`LinkResolver`] of `cosmarium_plugin_api::wiki`,
+//! which the plugin publishes for the editor and the other plugins.
+
+pub mod backlinks;
+
+use backlinks::Mention;
+use cosmarium_plugin_api::wiki::{
+    LinkResolver, WikiEntry, ENTITIES_DIR, LINK_RESOLVER_KEY, OPEN_ENTRY_REQUEST,
+};
+use cosmarium_plugin_api::{
+    PanelPlugin, PanelPosition, Plugin, PluginContext, PluginInfo, PluginType, Result,
+    FOCUS_PANEL_REQUEST,
+};
+use egui::Ui;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Name of the plugin, and of its panel.
+pub const PLUGIN_NAME: &str = "wiki";
+
+/// How often the project's entries and documents are rescanned.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Folders of the project whose documents can link to entries.
+const SCANNED_DIRS: &[&str] = &["content", "notes", ENTITIES_DIR];
+
+/// Kinds offered for new entries, besides those of the project.
+const DEFAULT_KINDS: &[&str] = &["characters", "places", "factions", "items"];
+
+/// A Markdown document of the project.
+#[derive(Debug, Clone)]
+struct Document {
+    path: PathBuf,
+    /// Display name
+    name: String,
+    content: String,
+}
+
+#[derive(Default)]
+pub struct WikiPlugin {
+    /// Entries of the project
+    resolver: LinkResolver,
+    /// Documents of the project, as saved
+    documents: Vec<Document>,
+    /// Document in the editor, with its unsaved edits
+    active: Option<Document>,
+    /// Project the scan belongs to
+    scanned_project: Option<PathBuf>,
+    /// Time of the last scan
+    last_scan: Option<Instant>,
+    /// File of the entry shown
+    selected: Option<PathBuf>,
+    /// Target of a link opened to a missing entry
+    missing: Option<String>,
+    /// Text filtering the list of entries
+    filter: String,
+}
+
+impl WikiPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rescan the entries and documents of the project at `project`.
+    fn scan_project(&mut self, project: &Path) {
+        self.resolver = LinkResolver::scan(project);
+        let mut files = Vec::new();
+        for dir in SCANNED_DIRS {
+            collect_documents(&project.join(dir), &mut files);
+        }
+        files.sort();
+        self.documents = files
+            .into_iter()
+            .filter_map(|path| {
+                let content = std::fs::read_to_string(&path).ok()?;
+                Some(Document {
+                    name: display_name(&path),
+                    path,
+                    content,
+                })
+            })
+            .collect();
+    }
+
+    /// Documents of the project, the editor buffer taking precedence over
+    /// the copy on disk.
+    fn documents(&self) -> impl Iterator<Item = &Document> {
+        let active = self.active.as_ref();
+        self.documents.iter().map(move |doc| match active {
+            Some(active) if active.path == doc.path => active,
+            _ => doc,
+        })
+    }
+
+    /// Show the entry a link to `target` points to, or offer to create it.
+    fn open_target(&mut self, target: &str) {
+        match self.resolver.resolve(target) {
+            Some(entry) => {
+                self.selected = Some(entry.path.clone());
+                self.missing = None;
+            }
+            None => self.missing = Some(target.to_string()),
+        }
+    }
+
+    /// Documents linking to or naming `entry`, with the lines doing so.
+    fn backlinks(&self, entry: &WikiEntry) -> Vec<(&Document, Vec<Mention>)> {
+        self.documents()
+            .filter(|doc| doc.path != entry.path)
+            .filter_map(|doc| {
+                let mentions = backlinks::mentions(&doc.content, entry, &self.resolver);
+                (!mentions.is_empty()).then_some((doc, mentions))
+            })
+            .collect()
+    }
+
+    /// Write a new entry named `name`, of `kind`, in the project at
+    /// `project`, and show it.
+    fn create_entry(&mut self, project: &Path, kind: &str, name: &str) -> Result<PathBuf> {
+        let name = name.rsplit('/').next().unwrap_or(name).trim();
+        let dir = project.join(ENTITIES_DIR).join(kind);
+        let path = dir.join(format!("{}.md", file_name(name)));
+        if path.exists() {
+            anyhow::bail!("An entry already exists at {}", path.display());
+        }
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(&path, format!("# {}\n\n", name))?;
+
+        self.scan_project(project);
+        self.last_scan = Some(Instant::now());
+        self.selected = Some(path.clone());
+        self.missing = None;
+        Ok(path)
+    }
+
+    /// Kinds offered for a new entry.
+    fn kinds(&self) -> Vec<&str> {
+        let mut kinds = self.resolver.kinds();
+        for kind in DEFAULT_KINDS {
+            if !kinds.contains(kind) {
+                kinds.push(kind);
+            }
+        }
+        kinds.retain(|kind| !kind.is_empty());
+        kinds
+    }
+
+    /// Show the missing entry of the last link opened, with the kinds it
+    /// can be created as.
+    fn render_missing(&mut self, ui: &mut Ui, ctx: &mut PluginContext, target: String) {
+        ui.label(format!("No entry is named “{}”.", target));
+        ui.label(egui::RichText::new("Create it as:").weak());
+        let mut created = None;
+        ui.horizontal_wrapped(|ui| {
+            for kind in self.kinds() {
+                if ui.button(kind).clicked() {
+                    created = Some(kind.to_string());
+                }
+            }
+        });
+        if ui.small_button("Dismiss").clicked() {
+            self.missing = None;
+        }
+
+        let (Some(kind), Some(project)) = (created, ctx.project_path()) else {
+            return;
+        };
+        match self.create_entry(&project, &kind, &target) {
+            Ok(path) => {
+                ctx.set_shared_state(LINK_RESOLVER_KEY, self.resolver.clone());
+                ctx.set_shared_state("open_document_request", Some((path, 1usize)));
+            }
+            Err(e) => tracing::error!("Failed to create the entry '{}': {}", target, e),
+        }
+    }
+
+    /// Show the selected entry, its text and its backlinks.
+    fn render_entry(&mut self, ui: &mut Ui, entry: &WikiEntry) -> Option<(PathBuf, usize)> {
+        let mut jump = None;
+        ui.horizontal(|ui| {
+            ui.heading(&entry.name);
+            if !entry.kind.is_empty() {
+                ui.label(egui::RichText::new(&entry.kind).weak());
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Edit Entry").clicked() {
+                jump = Some((entry.path.clone(), 1));
+            }
+            if ui.button("Close").clicked() {
+                self.selected = None;
+            }
+        });
+
+        let text = self
+            .documents()
+            .find(|doc| doc.path == entry.path)
+            .map(|doc| entry_text(&doc.content).to_string())
+            .unwrap_or_default();
+        if !text.is_empty() {
+            egui::ScrollArea::vertical()
+                .id_salt("wiki_entry_text")
+                .max_height(160.0)
+                .show(ui, |ui| {
+                    ui.label(text);
+                });
+        }
+
+        ui.separator();
+        let backlinks = self.backlinks(entry);
+        if backlinks.is_empty() {
+            ui.label(egui::RichText::new("No document mentions this entry.").weak());
+        }
+        for (doc, mentions) in backlinks {
+            egui::CollapsingHeader::new(format!("{} ({})", doc.name, mentions.len()))
+                .id_salt(("wiki_backlinks", &doc.path))
+                .default_open(true)
+                .show(ui, |ui| {
+                    for mention in mentions {
+                        let mut label =
+                            egui::RichText::new(format!("{}  {}", mention.line, mention.excerpt));
+                        if !mention.linked {
+                            label = label.italics();
+                        }
+                        if ui
+                            .add(egui::Label::new(label).sense(egui::Sense::click()))
+                            .on_hover_cursor(egui::CursorIcon::PointingHand)
+                            .on_hover_text(if mention.linked {
+                                "Links to the entry"
+                            } else {
+                                "Names the entry without a link"
+                            })
+                            .clicked()
+                        {
+                            jump = Some((doc.path.clone(), mention.line));
+                        }
+                    }
+                });
+        }
+        jump
+    }
+
+    /// Show the entries matching the filter, by kind.
+    fn render_entries(&mut self, ui: &mut Ui) {
+        ui.add(egui::TextEdit::singleline(&mut self.filter).hint_text("Filter entries"));
+        if self.resolver.is_empty() {
+            ui.label(
+                egui::RichText::new(format!(
+                    "No entries yet: add Markdown files in a folder per kind under {}/, \
+                     or create them from [[links]].",
+                    ENTITIES_DIR
+                ))
+                .weak(),
+            );
+            return;
+        }
+
+        let filter = self.filter.to_lowercase();
+        let mut selected = None;
+        for kind in self.resolver.kinds() {
+            let entries: Vec<&WikiEntry> = self
+                .resolver
+                .entries()
+                .iter()
+                .filter(|entry| entry.kind == kind)
+                .filter(|entry| entry.name.to_lowercase().contains(&filter))
+                .collect();
+            if entries.is_empty() {
+                continue;
+            }
+            let title = if kind.is_empty() { "Other" } else { kind };
+            egui::CollapsingHeader::new(format!("{} ({})", title, entries.len()))
+                .id_salt(("wiki_kind", kind))
+                .default_open(true)
+                .show(ui, |ui| {
+                    for entry in entries {
+                        let is_selected = self.selected.as_ref() == Some(&entry.path);
+                        if ui.selectable_label(is_selected, &entry.name).clicked() {
+                            selected = Some(entry.path.clone());
+                        }
+                    }
+                });
+        }
+        if selected.is_some() {
+            self.selected = selected;
+            self.missing = None;
+        }
+    }
+}
+
+/// Recursively collect the Markdown documents under `dir`.
+fn collect_documents(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_documents(&path, files);
+        } else if path.extension().is_some_and(|e| e == "md") {
+            files.push(path);
+        }
+    }
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Untitled")
+        .to_string()
+}
+
+/// File name, without extension, of a new entry named `name`.
+fn file_name(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Text of an entry, without its front matter and its title.
+fn entry_text(content: &str) -> &str {
+    let mut text = content;
+    if let Some(rest) = text.strip_prefix("---\n") {
+        if let Some(end) = rest.find("\n---\n") {
+            text = &rest[end + 5..];
+        }
+    }
+    let text = text.trim_start();
+    let text = match text.strip_prefix("# ") {
+        Some(rest) => rest.split_once('\n').map_or("", |(_, body)| body),
+        None => text,
+    };
+    text.trim()
+}
+
+impl Plugin for WikiPlugin {
+    fn info(&self) -> PluginInfo {
+        PluginInfo::new(
+            PLUGIN_NAME,
+            "0.1.0",
+            "Worldbuilding wiki with wiki-links",
+            "Cosmarium Team",
+        )
+        .with_dependency("markdown-editor")
+    }
+
+    fn initialize(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn plugin_type(&self) -> PluginType {
+        PluginType::Panel
+    }
+
+    fn update(&mut self, _ctx: &mut PluginContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl PanelPlugin for WikiPlugin {
+    fn panel_title(&self) -> &str {
+        "Wiki"
+    }
+
+    fn panel_icon(&self) -> &str {
+        "🌐"
+    }
+
+    fn default_position(&self) -> PanelPosition {
+        PanelPosition::Left
+    }
+
+    fn update(&mut self, ctx: &mut PluginContext) -> Result<()> {
+        // Rescan the project on a timer, or immediately when it changes
+        let project = ctx.project_path();
+        let stale = self
+            .last_scan
+            .is_none_or(|t| t.elapsed() >= RESCAN_INTERVAL);
+        if project != self.scanned_project || stale {
+            match &project {
+                Some(path) => self.scan_project(path),
+                None => {
+                    self.resolver = LinkResolver::default();
+                    self.documents.clear();
+                }
+            }
+            if project != self.scanned_project {
+                self.selected = None;
+                self.missing = None;
+            }
+            ctx.set_shared_state(LINK_RESOLVER_KEY, self.resolver.clone());
+            self.scanned_project = project;
+            self.last_scan = Some(Instant::now());
+        }
+
+        // Backlinks follow the editor buffer as it is typed
+        let active_path = ctx
+            .get_shared_state::<Option<PathBuf>>("active_document_path")
+            .flatten();
+        self.active = active_path.and_then(|path| {
+            let content = ctx.get_shared_state::<String>("markdown_editor_content")?;
+            Some(Document {
+                name: display_name(&path),
+                path,
+                content,
+            })
+        });
+
+        // Links opened from the editor
+        if let Some(target) = ctx
+            .get_shared_state::<Option<String>>(OPEN_ENTRY_REQUEST)
+            .flatten()
+        {
+            ctx.set_shared_state::<Option<String>>(OPEN_ENTRY_REQUEST, None);
+            self.open_target(&target);
+            ctx.set_shared_state(FOCUS_PANEL_REQUEST, Some(PLUGIN_NAME.to_string()));
+        }
+
+        Ok(())
+    }
+
+    fn render_panel(&mut self, ui: &mut Ui, ctx: &mut PluginContext) {
+        if ctx.project_path().is_none() {
+            ui.label("Open a project to see its worldbuilding entries");
+            return;
+        }
+
+        let mut jump = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            if let Some(target) = self.missing.clone() {
+                self.render_missing(ui, ctx, target);
+                ui.separator();
+            }
+            let entry = self
+                .selected
+                .as_deref()
+                .and_then(|path| self.resolver.entry_at(path))
+                .cloned();
+            if let Some(entry) = entry {
+                jump = self.render_entry(ui, &entry);
+                ui.separator();
+            }
+            self.render_entries(ui);
+        });
+
+        let active_path = self.active.as_ref().map(|doc| doc.path.clone());
+        match jump {
+            Some((path, line)) if Some(&path) == active_path.as_ref() => {
+                ctx.set_shared_state("markdown_editor_goto_line", line);
+            }
+            Some((path, line)) => {
+                ctx.set_shared_state("open_document_request", Some((path, line)));
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("cosmarium_wiki_{}_{}", name, std::process::id()));
+        let places = root.join(ENTITIES_DIR).join("places");
+        std::fs::create_dir_all(&places).unwrap();
+        std::fs::create_dir_all(root.join("content")).unwrap();
+        std::fs::write(
+            places.join("harbor.md"),
+            "# Silver Harbor\n\nA cold port.\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("content").join("one.md"),
+            "They sailed to [[Silver Harbor]].\n",
+        )
+        .unwrap();
+        root
+    }
+
+    #[test]
+    fn test_links_open_entries_with_backlinks() {
+        let root = project("open");
+        let mut plugin = WikiPlugin::new();
+        let mut ctx = PluginContext::new();
+        ctx.set_project_path(Some(root.clone()));
+        ctx.set_shared_state(OPEN_ENTRY_REQUEST, Some("silver-harbor".to_string()));
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+
+        let resolver: LinkResolver = ctx.get_shared_state(LINK_RESOLVER_KEY).unwrap();
+        let entry = resolver.resolve("Silver Harbor").unwrap().clone();
+        assert_eq!(plugin.selected.as_ref(), Some(&entry.path));
+        assert_eq!(
+            ctx.get_shared_state::<Option<String>>(FOCUS_PANEL_REQUEST),
+            Some(Some(PLUGIN_NAME.to_string()))
+        );
+        let backlinks = plugin.backlinks(&entry);
+        assert_eq!(backlinks.len(), 1);
+        assert_eq!(backlinks[0].0.name, "one");
+
+        // Unsaved edits of the editor count
+        ctx.set_shared_state("active_document_path", Some(root.join("content/one.md")));
+        ctx.set_shared_state("markdown_editor_content", "Nowhere.".to_string());
+        PanelPlugin::update(&mut plugin, &mut ctx).unwrap();
+        assert!(plugin.backlinks(&entry).is_empty());
+        assert_eq!(
+            entry_text("# Silver Harbor\n\nA cold port.\n"),
+            "A cold port."
+        );
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_missing_entries_are_created() {
+        let root = project("create");
+        let mut plugin = WikiPlugin::new();
+        plugin.scan_project(&root);
+        plugin.open_target("factions/The Black Crows");
+        assert_eq!(plugin.missing.as_deref(), Some("factions/The Black Crows"));
+        assert!(plugin.kinds().starts_with(&["places", "characters"]));
+
+        let path = plugin
+            .create_entry(&root, "factions", "factions/The Black Crows")
+            .unwrap();
+        assert_eq!(path, root.join("entities/factions/the-black-crows.md"));
+        assert_eq!(plugin.missing, None);
+        assert_eq!(
+            plugin.resolver.resolve("The Black Crows").map(|e| &e.path),
+            Some(&path)
+        );
+        assert!(plugin
+            .create_entry(&root, "factions", "The Black Crows")
+            .is_err());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}